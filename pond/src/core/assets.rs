@@ -35,6 +35,10 @@ pub struct Cosmetic {
     pub metadata: CosmeticMetadata,
     pub approved: bool,
     pub enabled: bool,
+    /// Sold on the central marketplace under this id; only worn once the
+    /// player's ownership has been verified there.
+    #[serde(default)]
+    pub premium: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +64,7 @@ pub struct AssetRegistry {
     ownership: DashMap<Uuid, Vec<CosmeticOwnership>>,
     approval_rules: DashMap<String, ApprovalRule>,
     allowed_types: DashMap<CosmeticType, bool>,
+    defaults: DashMap<CosmeticType, Uuid>,
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +82,7 @@ impl AssetRegistry {
             ownership: DashMap::new(),
             approval_rules: DashMap::new(),
             allowed_types: DashMap::new(),
+            defaults: DashMap::new(),
         };
         
         for cosmetic_type in [
@@ -214,6 +220,51 @@ impl AssetRegistry {
         Ok(())
     }
     
+    /// Worn in place of a premium cosmetic of the same type whose ownership
+    /// isn't verified.
+    pub fn set_default_cosmetic(&self, cosmetic_type: CosmeticType, id: Uuid) -> Result<(), String> {
+        let cosmetic = self.cosmetics.get(&id).ok_or("Cosmetic not found")?;
+        if cosmetic.cosmetic_type != cosmetic_type {
+            return Err(format!("Cosmetic {} is not a {:?}", id, cosmetic_type));
+        }
+        if cosmetic.premium {
+            return Err("A premium cosmetic cannot be a default".to_string());
+        }
+        self.defaults.insert(cosmetic_type, id);
+        Ok(())
+    }
+    
+    /// The cosmetics a player gets for what they asked to wear. Unknown or
+    /// unapproved ids are rejected; premium ones not in `verified` fall back
+    /// to the default of their type, if there is one.
+    pub fn apply_cosmetics(&self, requested: &[Uuid], verified: &HashSet<Uuid>) -> AppliedCosmetics {
+        let mut applied = AppliedCosmetics::default();
+        
+        for id in requested {
+            let Some(cosmetic) = self.get_cosmetic(*id).filter(|c| c.approved && c.enabled) else {
+                applied.rejected.push(*id);
+                continue;
+            };
+            if !cosmetic.premium || verified.contains(id) {
+                applied.cosmetics.push(cosmetic);
+                continue;
+            }
+            
+            warn!("Premium cosmetic {} is not verified, using the default", id);
+            applied.unverified.push(*id);
+            let fallback = self.defaults.get(&cosmetic.cosmetic_type)
+                .and_then(|default| self.get_cosmetic(*default))
+                .filter(|c| c.approved && c.enabled);
+            if let Some(fallback) = fallback {
+                if !applied.cosmetics.iter().any(|c| c.id == fallback.id) {
+                    applied.cosmetics.push(fallback);
+                }
+            }
+        }
+        
+        applied
+    }
+    
     pub fn validate_asset_manifest(&self, manifest: &AssetManifest) -> ValidationResult {
         let mut valid = Vec::new();
         let mut invalid = Vec::new();
@@ -245,3 +296,12 @@ pub struct ValidationResult {
     pub valid: Vec<Uuid>,
     pub invalid: Vec<Uuid>,
 }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppliedCosmetics {
+    pub cosmetics: Vec<Cosmetic>,
+    /// Premium cosmetics that were replaced by a default
+    pub unverified: Vec<Uuid>,
+    /// Unknown, unapproved or disabled cosmetics
+    pub rejected: Vec<Uuid>,
+}
@@ -1,8 +1,14 @@
+use crate::core::sandbox::SandboxPolicy;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long cached cosmetic ownership answers outlive their expiry while
+/// the central server is unreachable.
+pub const DEFAULT_OWNERSHIP_GRACE_SECS: u32 = 600;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -28,6 +34,8 @@ pub struct PluginSettings {
     pub auto_load: bool,
     pub hot_reload: bool,
     pub sandbox_enabled: bool,
+    #[serde(default)]
+    pub sandbox: SandboxPolicy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +61,41 @@ pub struct IntegrationSettings {
     pub launcher_api_port: u16,
     pub advertise_capabilities: bool,
     pub accept_asset_manifests: bool,
+    /// Id this server was registered under in the server browser.
+    #[serde(default)]
+    pub server_id: Option<Uuid>,
+    /// Scoped `srv_` token from `POST /api/v1/servers/:id/token`, sent with
+    /// browser heartbeats instead of the owner's login token. Tokens are
+    /// revoked when the server changes owner.
+    #[serde(default)]
+    pub server_token: Option<String>,
+    /// Seconds cached cosmetic ownership stays valid past its expiry while
+    /// the central server can't be reached.
+    #[serde(default = "default_ownership_grace_secs")]
+    pub ownership_grace_secs: u32,
+}
+
+fn default_ownership_grace_secs() -> u32 {
+    DEFAULT_OWNERSHIP_GRACE_SECS
+}
+
+impl IntegrationSettings {
+    /// Body for `POST /api/v1/servers/heartbeat`, if this server is listed.
+    /// A token without the `srv_` prefix is a user session token and is
+    /// refused rather than left sitting in the config.
+    pub fn heartbeat_request(&self, current_players: u32) -> Result<Option<serde_json::Value>, String> {
+        let (Some(server_id), Some(token)) = (self.server_id, &self.server_token) else {
+            return Ok(None);
+        };
+        if !token.starts_with("srv_") {
+            return Err("integration.server_token must be a scoped server token (srv_...)".to_string());
+        }
+        Ok(Some(serde_json::json!({
+            "token": token,
+            "server_id": server_id,
+            "current_players": current_players,
+        })))
+    }
 }
 
 impl Default for ServerConfig {
@@ -70,6 +113,7 @@ impl Default for ServerConfig {
                 auto_load: true,
                 hot_reload: true,
                 sandbox_enabled: true,
+                sandbox: SandboxPolicy::default(),
             },
             performance: PerformanceSettings {
                 tick_budget_ms: 50.0,
@@ -93,6 +137,9 @@ impl Default for ServerConfig {
                 launcher_api_port: 25566,
                 advertise_capabilities: true,
                 accept_asset_manifests: true,
+                server_id: None,
+                server_token: None,
+                ownership_grace_secs: DEFAULT_OWNERSHIP_GRACE_SECS,
             },
         }
     }
@@ -164,6 +211,7 @@ impl ConfigManager {
             "server.description" => Some(config.server.description.clone()),
             "plugins.directory" => Some(config.plugins.directory.clone()),
             "assets.cache_directory" => Some(config.assets.cache_directory.clone()),
+            "integration.server_token" => config.integration.server_token.clone(),
             _ => None,
         }
     }
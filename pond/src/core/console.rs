@@ -0,0 +1,153 @@
+//! Operator commands typed into the server console.
+
+use crate::core::plugins::PluginManager;
+use crate::core::scheduler::Scheduler;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+const HELP: &str = "\
+plugins               List plugins, their state and sandbox standing
+plugin violations [id] Show recent sandbox violations
+plugin reload <id>    Swap a running plugin for the version on disk
+plugin enable <id>    Enable a plugin
+plugin disable <id>   Disable a plugin
+tasks                 List scheduled tasks and their next run
+tasks cancel <id>     Cancel a scheduled task
+help                  Show this list";
+
+pub struct AdminConsole {
+    plugins: Arc<PluginManager>,
+    scheduler: Option<Arc<Scheduler>>,
+}
+
+impl AdminConsole {
+    pub fn new(plugins: Arc<PluginManager>) -> Self {
+        Self { plugins, scheduler: None }
+    }
+    
+    pub fn with_scheduler(mut self, scheduler: Arc<Scheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+    
+    /// Runs one command line and returns what to print.
+    pub async fn execute(&self, line: &str) -> String {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args.as_slice() {
+            [] => String::new(),
+            ["help"] => HELP.to_string(),
+            ["plugins"] | ["plugin", "list"] => self.list_plugins(),
+            ["plugin", "violations"] => self.list_violations(None),
+            ["plugin", "violations", id] => self.list_violations(Some(id)),
+            ["plugin", "reload", id] => match self.plugins.reload(id).await {
+                Ok(metadata) => format!("Reloaded {} v{}", id, metadata.version),
+                Err(e) => e,
+            },
+            ["plugin", "enable", id] => match self.plugins.enable_plugin(id).await {
+                Ok(()) => format!("Enabled {}", id),
+                Err(e) => e,
+            },
+            ["plugin", "disable", id] => match self.plugins.disable_plugin(id).await {
+                Ok(()) => format!("Disabled {}", id),
+                Err(e) => e,
+            },
+            ["tasks"] => self.list_tasks(),
+            ["tasks", "cancel", id] => match (&self.scheduler, id.parse()) {
+                (None, _) => "Scheduler not available".to_string(),
+                (Some(_), Err(_)) => format!("Invalid task id: {}", id),
+                (Some(scheduler), Ok(id)) if scheduler.cancel(id) => format!("Cancelled {}", id),
+                (Some(_), Ok(id)) => format!("No scheduled task {}", id),
+            },
+            _ => format!("Unknown command: {}. Type 'help' for a list.", line.trim()),
+        }
+    }
+    
+    /// Reads commands line by line until `input` closes.
+    pub async fn run<R: AsyncBufRead + Unpin>(self, input: R) {
+        let mut lines = input.lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let output = self.execute(&line).await;
+            if !output.is_empty() {
+                println!("{}", output);
+            }
+        }
+    }
+    
+    fn list_plugins(&self) -> String {
+        let mut plugins = self.plugins.list_plugins();
+        if plugins.is_empty() {
+            return "No plugins loaded".to_string();
+        }
+        plugins.sort_by(|a, b| a.id.cmp(&b.id));
+        plugins
+            .iter()
+            .map(|p| {
+                let state = self.plugins.get_plugin_state(&p.id)
+                    .map(|s| format!("{:?}", s))
+                    .unwrap_or_default();
+                let mut line = format!("{} v{} [{}]", p.id, p.version, state);
+                let report = self.plugins.sandbox_report(&p.id);
+                if report.strikes > 0 {
+                    line.push_str(&format!(" strikes={}", report.strikes));
+                }
+                if report.throttles > 0 {
+                    line.push_str(&format!(" throttled={}x", report.throttles));
+                }
+                if report.throttled {
+                    line.push_str(" (sitting out)");
+                }
+                if let Some(error) = self.plugins.get_plugin_error(&p.id) {
+                    line.push_str(&format!(" - {}", error));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
+    fn list_tasks(&self) -> String {
+        let Some(scheduler) = &self.scheduler else {
+            return "Scheduler not available".to_string();
+        };
+        let tasks = scheduler.scheduled_tasks();
+        if tasks.is_empty() {
+            return "No scheduled tasks.".to_string();
+        }
+        let now = chrono::Utc::now();
+        let mut output = format!("Scheduled tasks ({}):", tasks.len());
+        for task in tasks {
+            let until = (task.next_run - now).num_seconds().max(0);
+            output.push_str(&format!(
+                "\n  {} {} ({}, {:?}) next {} (in {}m {}s), {} runs",
+                task.id,
+                task.name,
+                task.schedule,
+                task.priority,
+                task.next_run.format("%Y-%m-%d %H:%M UTC"),
+                until / 60,
+                until % 60,
+                task.runs
+            ));
+        }
+        output
+    }
+    
+    fn list_violations(&self, id: Option<&str>) -> String {
+        let violations = self.plugins.violations(id);
+        if violations.is_empty() {
+            return "No sandbox violations.".to_string();
+        }
+        let mut output = format!("Sandbox violations ({}):", violations.len());
+        for v in violations {
+            output.push_str(&format!(
+                "\n  {} {} #{} {} '{}'",
+                v.at.format("%H:%M:%S"),
+                v.plugin_id,
+                v.strike,
+                v.capability.as_str(),
+                v.target
+            ));
+        }
+        output
+    }
+}
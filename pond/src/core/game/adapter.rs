@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
 
+use super::world_dir::{DirectoryWorldProvider, HytaleWorldStorage};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerAdapterConfig {
     pub server_path: PathBuf,
@@ -116,6 +118,11 @@ impl HytaleServerAdapter {
             motd: "A Pond Server".to_string(),
         })
     }
+    
+    /// Saved worlds under the configured world directory.
+    pub fn world_provider(&self) -> DirectoryWorldProvider<HytaleWorldStorage> {
+        DirectoryWorldProvider::from_path(&self.config.world_path)
+    }
 }
 
 #[async_trait]
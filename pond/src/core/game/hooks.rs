@@ -68,69 +68,157 @@ pub trait GameHook: Send + Sync {
     async fn execute(&self, event: &GameHookEvent) -> HookResult;
 }
 
+/// Hooks by event type. Hooks registered for a plugin carry its id so a
+/// reload can swap the whole set under one lock; dispatch works on a
+/// snapshot, so an event sees either the old set or the new one. Time spent
+/// in a plugin's hooks is tallied for its tick budget.
 pub struct HookRegistry {
-    hooks: dashmap::DashMap<String, Vec<HookEntry>>,
+    hooks: parking_lot::RwLock<std::collections::HashMap<String, Vec<HookEntry>>>,
+    suspended: parking_lot::RwLock<std::collections::HashSet<String>>,
+    usage: parking_lot::Mutex<std::collections::HashMap<String, std::time::Duration>>,
 }
 
+#[derive(Clone)]
 struct HookEntry {
     priority: HookPriority,
+    owner: Option<String>,
     hook: std::sync::Arc<dyn GameHook>,
 }
 
 impl HookRegistry {
     pub fn new() -> Self {
         Self {
-            hooks: dashmap::DashMap::new(),
+            hooks: parking_lot::RwLock::new(std::collections::HashMap::new()),
+            suspended: parking_lot::RwLock::new(std::collections::HashSet::new()),
+            usage: parking_lot::Mutex::new(std::collections::HashMap::new()),
         }
     }
     
     pub fn register(&self, event_type: &str, hook: std::sync::Arc<dyn GameHook>) {
-        let entry = HookEntry {
-            priority: hook.priority(),
-            hook,
-        };
-        
-        let mut hooks = self.hooks.entry(event_type.to_string()).or_insert_with(Vec::new);
-        hooks.push(entry);
-        hooks.sort_by_key(|e| e.priority);
+        let mut hooks = self.hooks.write();
+        insert_entry(&mut hooks, event_type, None, hook);
+    }
+    
+    /// Registers a hook on behalf of a plugin.
+    pub fn register_owned(&self, owner: &str, event_type: &str, hook: std::sync::Arc<dyn GameHook>) {
+        let mut hooks = self.hooks.write();
+        insert_entry(&mut hooks, event_type, Some(owner), hook);
+    }
+    
+    /// Replaces every hook owned by `owner` with `hooks` in one step.
+    pub fn replace_owned(&self, owner: &str, hooks: Vec<(String, std::sync::Arc<dyn GameHook>)>) {
+        let mut registered = self.hooks.write();
+        for entries in registered.values_mut() {
+            entries.retain(|e| e.owner.as_deref() != Some(owner));
+        }
+        for (event_type, hook) in hooks {
+            insert_entry(&mut registered, &event_type, Some(owner), hook);
+        }
+        registered.retain(|_, entries| !entries.is_empty());
     }
     
     pub fn unregister(&self, event_type: &str, hook_name: &str) {
-        if let Some(mut hooks) = self.hooks.get_mut(event_type) {
+        if let Some(hooks) = self.hooks.write().get_mut(event_type) {
             hooks.retain(|e| e.hook.name() != hook_name);
         }
     }
     
+    /// Removes every hook owned by `owner`.
+    pub fn unregister_owned(&self, owner: &str) {
+        self.replace_owned(owner, Vec::new());
+    }
+    
+    /// Names of the hooks `owner` has registered, in no particular order.
+    pub fn owned_hooks(&self, owner: &str) -> Vec<String> {
+        self.hooks
+            .read()
+            .values()
+            .flatten()
+            .filter(|e| e.owner.as_deref() == Some(owner))
+            .map(|e| e.hook.name().to_string())
+            .collect()
+    }
+    
+    /// Skips `owner`'s hooks while it is suspended.
+    pub fn set_suspended(&self, owner: &str, suspended: bool) {
+        let mut set = self.suspended.write();
+        if suspended {
+            set.insert(owner.to_string());
+        } else {
+            set.remove(owner);
+        }
+    }
+    
+    /// Time spent in each owner's hooks since the last call.
+    pub fn take_usage(&self) -> std::collections::HashMap<String, std::time::Duration> {
+        std::mem::take(&mut *self.usage.lock())
+    }
+    
     pub async fn dispatch(&self, event: &GameHookEvent) -> HookResult {
         let event_type = event_type_name(event);
+        let (typed, any) = {
+            let hooks = self.hooks.read();
+            let suspended = self.suspended.read();
+            let active = |entries: Option<&Vec<HookEntry>>| -> Vec<HookEntry> {
+                entries.into_iter()
+                    .flatten()
+                    .filter(|e| e.owner.as_ref().is_none_or(|o| !suspended.contains(o)))
+                    .cloned()
+                    .collect()
+            };
+            (active(hooks.get(event_type)), active(hooks.get("*")))
+        };
         
-        if let Some(hooks) = self.hooks.get(event_type) {
-            for entry in hooks.iter() {
-                if entry.hook.handles(event) {
-                    let result = entry.hook.execute(event).await;
-                    if matches!(result, HookResult::Cancel) {
-                        return result;
-                    }
-                    if let HookResult::Modify(_) = &result {
-                        return result;
-                    }
+        for entry in typed.iter() {
+            if entry.hook.handles(event) {
+                let result = self.execute(entry, event).await;
+                if matches!(result, HookResult::Cancel) {
+                    return result;
+                }
+                if let HookResult::Modify(_) = &result {
+                    return result;
                 }
             }
         }
         
-        if let Some(hooks) = self.hooks.get("*") {
-            for entry in hooks.iter() {
-                if entry.hook.handles(event) {
-                    let result = entry.hook.execute(event).await;
-                    if matches!(result, HookResult::Cancel) {
-                        return result;
-                    }
+        for entry in any.iter() {
+            if entry.hook.handles(event) {
+                let result = self.execute(entry, event).await;
+                if matches!(result, HookResult::Cancel) {
+                    return result;
                 }
             }
         }
         
         HookResult::Continue
     }
+    
+    async fn execute(&self, entry: &HookEntry, event: &GameHookEvent) -> HookResult {
+        let Some(owner) = &entry.owner else {
+            return entry.hook.execute(event).await;
+        };
+        let started = std::time::Instant::now();
+        let result = entry.hook.execute(event).await;
+        *self.usage.lock().entry(owner.clone()).or_default() += started.elapsed();
+        result
+    }
+}
+
+fn insert_entry(
+    hooks: &mut std::collections::HashMap<String, Vec<HookEntry>>,
+    event_type: &str,
+    owner: Option<&str>,
+    hook: std::sync::Arc<dyn GameHook>,
+) {
+    let entry = HookEntry {
+        priority: hook.priority(),
+        owner: owner.map(str::to_string),
+        hook,
+    };
+    
+    let entries = hooks.entry(event_type.to_string()).or_default();
+    entries.push(entry);
+    entries.sort_by_key(|e| e.priority);
 }
 
 impl Default for HookRegistry {
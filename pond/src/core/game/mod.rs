@@ -1,7 +1,9 @@
 pub mod adapter;
 pub mod hooks;
 pub mod world;
+pub mod world_dir;
 
 pub use adapter::{ServerAdapter, ServerAdapterConfig, ServerCapabilities as GameServerCapabilities};
-pub use hooks::{GameHook, HookPriority, HookResult};
-pub use world::{WorldProvider, ChunkData, EntityData};
+pub use hooks::{GameHook, GameHookEvent, HookPriority, HookRegistry, HookResult};
+pub use world::{WorldProvider, ChunkData, EntityData, WorldSummary, RegionManifest, RegionEntry, RegionPosition};
+pub use world_dir::{DirectoryWorldProvider, WorldStorage, HytaleWorldStorage};
@@ -35,12 +35,91 @@ pub struct BlockData {
     pub nbt: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpawnPoint {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Region coordinates; each region covers 32x32 chunks.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RegionPosition {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl RegionPosition {
+    pub const CHUNKS_PER_SIDE: i32 = 32;
+
+    pub fn containing(chunk: ChunkPosition) -> Self {
+        Self {
+            x: chunk.x.div_euclid(Self::CHUNKS_PER_SIDE),
+            z: chunk.z.div_euclid(Self::CHUNKS_PER_SIDE),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSummary {
+    pub world_id: String,
+    pub spawn: SpawnPoint,
+    pub generator: String,
+    pub seed: Option<i64>,
+    pub region_count: usize,
+    /// Bytes on disk across all region files
+    pub size_bytes: u64,
+    /// Smallest and largest region coordinates present
+    pub bounds: Option<(RegionPosition, RegionPosition)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionEntry {
+    pub position: RegionPosition,
+    pub size_bytes: u64,
+    /// `content_hash` of the region file
+    pub hash: String,
+}
+
+/// Every region of a world with a content hash, so a client can fetch only
+/// the regions it doesn't already have.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionManifest {
+    pub world_id: String,
+    pub hash_algorithm: String,
+    pub regions: Vec<RegionEntry>,
+}
+
+impl RegionManifest {
+    /// Regions whose hash differs from, or are absent in, `known`.
+    pub fn missing_from(&self, known: &std::collections::HashMap<RegionPosition, String>) -> Vec<RegionEntry> {
+        self.regions.iter()
+            .filter(|r| known.get(&r.position) != Some(&r.hash))
+            .cloned()
+            .collect()
+    }
+}
+
+pub const REGION_HASH_ALGORITHM: &str = "fnv1a64";
+
+/// 64-bit FNV-1a as 16 hex digits. Cheap and stable across builds, which is
+/// all change detection needs; it is not a security boundary.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorldError {
     ChunkNotLoaded,
     ChunkLoadFailed(String),
     SaveFailed(String),
     InvalidPosition,
+    WorldNotFound(String),
     Unknown(String),
 }
 
@@ -51,6 +130,7 @@ impl std::fmt::Display for WorldError {
             Self::ChunkLoadFailed(e) => write!(f, "Chunk load failed: {}", e),
             Self::SaveFailed(e) => write!(f, "Save failed: {}", e),
             Self::InvalidPosition => write!(f, "Invalid position"),
+            Self::WorldNotFound(id) => write!(f, "World not found: {}", id),
             Self::Unknown(e) => write!(f, "Unknown error: {}", e),
         }
     }
@@ -77,6 +157,19 @@ pub trait WorldProvider: Send + Sync {
     async fn get_entities_in_radius(&self, center: (f64, f64, f64), radius: f64) -> Vec<EntityData>;
     
     async fn save_all(&self) -> Result<(), WorldError>;
+    
+    /// Worlds this provider can describe before a player connects.
+    async fn list_worlds(&self) -> Result<Vec<String>, WorldError> {
+        Ok(vec![])
+    }
+    
+    async fn world_summary(&self, world_id: &str) -> Result<WorldSummary, WorldError> {
+        Err(WorldError::WorldNotFound(world_id.to_string()))
+    }
+    
+    async fn region_manifest(&self, world_id: &str) -> Result<RegionManifest, WorldError> {
+        Err(WorldError::WorldNotFound(world_id.to_string()))
+    }
 }
 
 pub struct StubWorldProvider {
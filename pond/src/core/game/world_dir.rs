@@ -0,0 +1,373 @@
+use super::world::{
+    content_hash, BlockData, ChunkData, ChunkPosition, EntityData, RegionEntry, RegionManifest,
+    RegionPosition, SpawnPoint, StubWorldProvider, WorldError, WorldProvider, WorldSummary,
+    REGION_HASH_ALGORITHM,
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Read access to saved worlds on disk.
+pub trait WorldStorage: Send + Sync {
+    fn world_ids(&self) -> io::Result<Vec<String>>;
+    /// Raw world config, or `None` if the world has none.
+    fn read_config(&self, world_id: &str) -> io::Result<Option<Vec<u8>>>;
+    /// Region files of the world with their size in bytes.
+    fn regions(&self, world_id: &str) -> io::Result<Vec<(RegionPosition, u64)>>;
+    fn read_region(&self, world_id: &str, pos: RegionPosition) -> io::Result<Vec<u8>>;
+}
+
+/// The Hytale server's world layout:
+///
+/// ```text
+/// <root>/<world_id>/config.json
+/// <root>/<world_id>/chunks/<x>.<z>.region.bin
+/// ```
+pub struct HytaleWorldStorage {
+    root: PathBuf,
+}
+
+impl HytaleWorldStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn world_dir(&self, world_id: &str) -> io::Result<PathBuf> {
+        if world_id.is_empty() || world_id.contains(['/', '\\']) || world_id.starts_with('.') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid world id"));
+        }
+        Ok(self.root.join(world_id))
+    }
+
+    fn region_path(&self, world_id: &str, pos: RegionPosition) -> io::Result<PathBuf> {
+        Ok(self.world_dir(world_id)?.join("chunks").join(format!("{}.{}.region.bin", pos.x, pos.z)))
+    }
+}
+
+fn parse_region_file_name(name: &str) -> Option<RegionPosition> {
+    let coords = name.strip_suffix(".region.bin")?;
+    let (x, z) = coords.split_once('.')?;
+    Some(RegionPosition { x: x.parse().ok()?, z: z.parse().ok()? })
+}
+
+impl WorldStorage for HytaleWorldStorage {
+    fn world_ids(&self) -> io::Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let is_world = path.join("config.json").is_file() || path.join("chunks").is_dir();
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if is_world && !name.starts_with('.') {
+                    ids.push(name.to_string());
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn read_config(&self, world_id: &str) -> io::Result<Option<Vec<u8>>> {
+        match std::fs::read(self.world_dir(world_id)?.join("config.json")) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn regions(&self, world_id: &str) -> io::Result<Vec<(RegionPosition, u64)>> {
+        let entries = match std::fs::read_dir(self.world_dir(world_id)?.join("chunks")) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let mut regions = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Some(pos) = entry.file_name().to_str().and_then(parse_region_file_name) else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                regions.push((pos, metadata.len()));
+            }
+        }
+        regions.sort();
+        Ok(regions)
+    }
+
+    fn read_region(&self, world_id: &str, pos: RegionPosition) -> io::Result<Vec<u8>> {
+        std::fs::read(self.region_path(world_id, pos)?)
+    }
+}
+
+/// World metadata pulled out of a world config. Keys are matched
+/// case-insensitively since server versions disagree on casing.
+struct WorldConfig {
+    spawn: SpawnPoint,
+    generator: String,
+    seed: Option<i64>,
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value.as_object()?
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+impl WorldConfig {
+    fn parse(bytes: Option<&[u8]>) -> Self {
+        let value = bytes
+            .and_then(|b| serde_json::from_slice::<Value>(b).ok())
+            .unwrap_or(Value::Null);
+
+        let spawn = field(&value, "SpawnPoint")
+            .or_else(|| field(&value, "Spawn"))
+            .map(|spawn| {
+                let axis = |key| field(spawn, key).and_then(Value::as_f64).unwrap_or(0.0);
+                SpawnPoint { x: axis("x"), y: axis("y"), z: axis("z") }
+            })
+            .unwrap_or_default();
+
+        let generator = field(&value, "WorldGen")
+            .and_then(|gen| field(gen, "Type"))
+            .or_else(|| field(&value, "Generator"))
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+
+        let seed = field(&value, "Seed").and_then(Value::as_i64);
+
+        Self { spawn, generator, seed }
+    }
+}
+
+/// A world provider that answers world metadata queries from saved worlds
+/// on disk and delegates live chunk and entity operations to `live`.
+pub struct DirectoryWorldProvider<S: WorldStorage> {
+    storage: S,
+    live: Arc<dyn WorldProvider>,
+}
+
+impl<S: WorldStorage> DirectoryWorldProvider<S> {
+    pub fn new(storage: S) -> Self {
+        Self::with_live(storage, Arc::new(StubWorldProvider::new()))
+    }
+
+    pub fn with_live(storage: S, live: Arc<dyn WorldProvider>) -> Self {
+        Self { storage, live }
+    }
+
+    fn ensure_world(&self, world_id: &str) -> Result<(), WorldError> {
+        let ids = self.storage.world_ids().map_err(|e| WorldError::Unknown(e.to_string()))?;
+        if ids.iter().any(|id| id == world_id) {
+            Ok(())
+        } else {
+            Err(WorldError::WorldNotFound(world_id.to_string()))
+        }
+    }
+}
+
+impl DirectoryWorldProvider<HytaleWorldStorage> {
+    pub fn from_path(root: impl AsRef<Path>) -> Self {
+        Self::new(HytaleWorldStorage::new(root.as_ref()))
+    }
+}
+
+#[async_trait]
+impl<S: WorldStorage + 'static> WorldProvider for DirectoryWorldProvider<S> {
+    async fn load_chunk(&self, pos: ChunkPosition) -> Result<ChunkData, WorldError> {
+        self.live.load_chunk(pos).await
+    }
+
+    async fn unload_chunk(&self, pos: ChunkPosition) -> Result<(), WorldError> {
+        self.live.unload_chunk(pos).await
+    }
+
+    async fn save_chunk(&self, chunk: &ChunkData) -> Result<(), WorldError> {
+        self.live.save_chunk(chunk).await
+    }
+
+    async fn is_chunk_loaded(&self, pos: ChunkPosition) -> bool {
+        self.live.is_chunk_loaded(pos).await
+    }
+
+    async fn get_block(&self, x: i32, y: i32, z: i32) -> Result<BlockData, WorldError> {
+        self.live.get_block(x, y, z).await
+    }
+
+    async fn set_block(&self, x: i32, y: i32, z: i32, block: BlockData) -> Result<(), WorldError> {
+        self.live.set_block(x, y, z, block).await
+    }
+
+    async fn spawn_entity(&self, entity: EntityData) -> Result<Uuid, WorldError> {
+        self.live.spawn_entity(entity).await
+    }
+
+    async fn remove_entity(&self, id: Uuid) -> Result<(), WorldError> {
+        self.live.remove_entity(id).await
+    }
+
+    async fn get_entity(&self, id: Uuid) -> Result<EntityData, WorldError> {
+        self.live.get_entity(id).await
+    }
+
+    async fn update_entity(&self, entity: &EntityData) -> Result<(), WorldError> {
+        self.live.update_entity(entity).await
+    }
+
+    async fn get_entities_in_chunk(&self, pos: ChunkPosition) -> Vec<EntityData> {
+        self.live.get_entities_in_chunk(pos).await
+    }
+
+    async fn get_entities_in_radius(&self, center: (f64, f64, f64), radius: f64) -> Vec<EntityData> {
+        self.live.get_entities_in_radius(center, radius).await
+    }
+
+    async fn save_all(&self) -> Result<(), WorldError> {
+        self.live.save_all().await
+    }
+
+    async fn list_worlds(&self) -> Result<Vec<String>, WorldError> {
+        self.storage.world_ids().map_err(|e| WorldError::Unknown(e.to_string()))
+    }
+
+    async fn world_summary(&self, world_id: &str) -> Result<WorldSummary, WorldError> {
+        self.ensure_world(world_id)?;
+        let io_err = |e: io::Error| WorldError::Unknown(format!("{}: {}", world_id, e));
+
+        let config = self.storage.read_config(world_id).map_err(io_err)?;
+        let config = WorldConfig::parse(config.as_deref());
+        let regions = self.storage.regions(world_id).map_err(io_err)?;
+
+        let bounds = regions.iter().map(|(pos, _)| *pos).fold(None, |bounds, pos| match bounds {
+            None => Some((pos, pos)),
+            Some((min, max)) => Some((
+                RegionPosition { x: min.x.min(pos.x), z: min.z.min(pos.z) },
+                RegionPosition { x: max.x.max(pos.x), z: max.z.max(pos.z) },
+            )),
+        });
+
+        Ok(WorldSummary {
+            world_id: world_id.to_string(),
+            spawn: config.spawn,
+            generator: config.generator,
+            seed: config.seed,
+            region_count: regions.len(),
+            size_bytes: regions.iter().map(|(_, size)| size).sum(),
+            bounds,
+        })
+    }
+
+    async fn region_manifest(&self, world_id: &str) -> Result<RegionManifest, WorldError> {
+        self.ensure_world(world_id)?;
+        let io_err = |e: io::Error| WorldError::Unknown(format!("{}: {}", world_id, e));
+
+        let mut regions = Vec::new();
+        for (position, _) in self.storage.regions(world_id).map_err(io_err)? {
+            let bytes = self.storage.read_region(world_id, position).map_err(io_err)?;
+            regions.push(RegionEntry {
+                position,
+                size_bytes: bytes.len() as u64,
+                hash: content_hash(&bytes),
+            });
+        }
+
+        Ok(RegionManifest {
+            world_id: world_id.to_string(),
+            hash_algorithm: REGION_HASH_ALGORITHM.to_string(),
+            regions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct FakeWorldDir(PathBuf);
+
+    impl FakeWorldDir {
+        fn new() -> Self {
+            let root = std::env::temp_dir().join(format!("pond-worlds-{}", Uuid::new_v4()));
+            let chunks = root.join("default").join("chunks");
+            std::fs::create_dir_all(&chunks).unwrap();
+            std::fs::write(
+                root.join("default").join("config.json"),
+                r#"{"Seed": 1337, "WorldGen": {"Type": "Hytale"}, "SpawnPoint": {"X": 12.5, "Y": 80, "Z": -4}}"#,
+            ).unwrap();
+            std::fs::write(chunks.join("0.0.region.bin"), vec![1u8; 64]).unwrap();
+            std::fs::write(chunks.join("-1.2.region.bin"), vec![2u8; 32]).unwrap();
+            std::fs::write(chunks.join("notes.txt"), "not a region").unwrap();
+            std::fs::create_dir_all(root.join(".trash")).unwrap();
+            Self(root)
+        }
+
+        fn region(&self, name: &str) -> PathBuf {
+            self.0.join("default").join("chunks").join(name)
+        }
+    }
+
+    impl Drop for FakeWorldDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_world_summary_reads_config_and_regions() {
+        let dir = FakeWorldDir::new();
+        let provider = DirectoryWorldProvider::from_path(&dir.0);
+
+        assert_eq!(provider.list_worlds().await.unwrap(), vec!["default".to_string()]);
+
+        let summary = provider.world_summary("default").await.unwrap();
+        assert_eq!(summary.spawn, SpawnPoint { x: 12.5, y: 80.0, z: -4.0 });
+        assert_eq!(summary.generator, "Hytale");
+        assert_eq!(summary.seed, Some(1337));
+        assert_eq!(summary.region_count, 2);
+        assert_eq!(summary.size_bytes, 96);
+        assert_eq!(summary.bounds, Some((RegionPosition { x: -1, z: 0 }, RegionPosition { x: 0, z: 2 })));
+
+        assert!(matches!(provider.world_summary("missing").await, Err(WorldError::WorldNotFound(_))));
+        assert!(matches!(provider.region_manifest("../default").await, Err(WorldError::WorldNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_hash_changes_with_region_contents() {
+        let dir = FakeWorldDir::new();
+        let provider = DirectoryWorldProvider::from_path(&dir.0);
+
+        let before = provider.region_manifest("default").await.unwrap();
+        assert_eq!(before.hash_algorithm, "fnv1a64");
+        assert_eq!(before.regions.len(), 2);
+        assert_eq!(provider.region_manifest("default").await.unwrap(), before);
+
+        std::fs::write(dir.region("0.0.region.bin"), vec![9u8; 64]).unwrap();
+        let after = provider.region_manifest("default").await.unwrap();
+
+        let hash_of = |manifest: &RegionManifest, x, z| {
+            manifest.regions.iter().find(|r| r.position == RegionPosition { x, z }).unwrap().hash.clone()
+        };
+        assert_ne!(hash_of(&before, 0, 0), hash_of(&after, 0, 0));
+        assert_eq!(hash_of(&before, -1, 2), hash_of(&after, -1, 2));
+
+        let known: HashMap<RegionPosition, String> = before.regions.iter()
+            .map(|r| (r.position, r.hash.clone()))
+            .collect();
+        let stale = after.missing_from(&known);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].position, RegionPosition { x: 0, z: 0 });
+    }
+}
@@ -1,12 +1,20 @@
-use crate::core::assets::{AssetRegistry, AssetManifest, ValidationResult};
+use crate::core::assets::{AppliedCosmetics, AssetRegistry, AssetManifest, ValidationResult};
+use crate::core::game::world::{RegionManifest, WorldProvider, WorldSummary};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use tokio::sync::RwLock;
-use tracing::{info, debug};
+use tracing::{info, debug, warn};
 use uuid::Uuid;
 
+pub mod discovery;
+pub mod ownership;
+pub mod queue;
+
+use ownership::OwnershipVerifier;
+use queue::{JoinQueue, PriorityVerifier};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerCapabilities {
     pub cosmetics: CosmeticCapabilities,
@@ -15,6 +23,8 @@ pub struct ServerCapabilities {
     pub sync: SyncCapabilities,
     pub features: Vec<String>,
     pub api_version: String,
+    #[serde(default)]
+    pub worlds: Vec<WorldSummary>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,9 +88,14 @@ pub struct LauncherBridge {
     running: AtomicBool,
     capabilities: ServerCapabilities,
     connected_launchers: DashMap<Uuid, LauncherSession>,
-    queue: RwLock<Vec<QueueEntry>>,
+    queue: JoinQueue,
+    priority: Option<Arc<PriorityVerifier>>,
     player_count: AtomicU32,
     max_players: AtomicU32,
+    world_provider: Option<Arc<dyn WorldProvider>>,
+    worlds: parking_lot::RwLock<Vec<(WorldSummary, RegionManifest)>>,
+    preload_assets: parking_lot::RwLock<Vec<PreloadAsset>>,
+    ownership: Option<Arc<OwnershipVerifier>>,
 }
 
 #[derive(Debug, Clone)]
@@ -174,17 +189,93 @@ impl LauncherBridge {
                     "queue_priority".to_string(),
                     "asset_streaming".to_string(),
                     "ping_optimization".to_string(),
+                    "world_manifest".to_string(),
                 ],
-                api_version: "1.1.0".to_string(),
+                api_version: "1.2.0".to_string(),
+                worlds: vec![],
             },
             connected_launchers: DashMap::new(),
-            queue: RwLock::new(Vec::new()),
+            queue: JoinQueue::default(),
+            priority: None,
             player_count: AtomicU32::new(0),
             max_players: AtomicU32::new(100),
+            world_provider: None,
+            worlds: parking_lot::RwLock::new(Vec::new()),
+            preload_assets: parking_lot::RwLock::new(Vec::new()),
+            ownership: None,
         }
     }
     
+    pub fn with_world_provider(mut self, provider: Arc<dyn WorldProvider>) -> Self {
+        self.world_provider = Some(provider);
+        self
+    }
+    
+    /// Check premium cosmetics against the central marketplace. Without a
+    /// verifier no premium cosmetic is ever applied.
+    pub fn with_ownership_verifier(mut self, verifier: Arc<OwnershipVerifier>) -> Self {
+        self.ownership = Some(verifier);
+        self.capabilities.features.push("ownership_validation".to_string());
+        self
+    }
+    
+    /// Check priority claims in the join queue against the central
+    /// server's subscriptions. Without a verifier every player queues in
+    /// the regular tier.
+    pub fn with_priority_verifier(mut self, verifier: Arc<PriorityVerifier>) -> Self {
+        self.priority = Some(verifier);
+        self
+    }
+    
+    pub fn with_queue(mut self, queue: JoinQueue) -> Self {
+        self.queue = queue;
+        self
+    }
+    
+    pub fn set_max_players(&self, max_players: u32) {
+        self.max_players.store(max_players, Ordering::Relaxed);
+    }
+    
+    /// Players on the server; slots this frees are given to the queue.
+    pub fn set_player_count(&self, player_count: u32) {
+        self.player_count.store(player_count, Ordering::Relaxed);
+    }
+    
+    pub fn player_count(&self) -> u32 {
+        self.player_count.load(Ordering::Relaxed)
+    }
+    
+    pub fn max_players(&self) -> u32 {
+        self.max_players.load(Ordering::Relaxed)
+    }
+    
+    /// Re-reads world summaries and region manifests from the world provider.
+    /// Worlds that fail to load are skipped rather than failing the refresh.
+    pub async fn refresh_worlds(&self) -> Result<usize, String> {
+        let Some(provider) = &self.world_provider else {
+            return Ok(0);
+        };
+        
+        let mut worlds = Vec::new();
+        for world_id in provider.list_worlds().await.map_err(|e| e.to_string())? {
+            let summary = provider.world_summary(&world_id).await;
+            let manifest = provider.region_manifest(&world_id).await;
+            match (summary, manifest) {
+                (Ok(summary), Ok(manifest)) => worlds.push((summary, manifest)),
+                (Err(e), _) | (_, Err(e)) => warn!("Skipping world {} in preload manifest: {}", world_id, e),
+            }
+        }
+        
+        let count = worlds.len();
+        *self.worlds.write() = worlds;
+        debug!("Indexed {} worlds for launcher preload", count);
+        Ok(count)
+    }
+    
     pub async fn start(&self) {
+        if let Err(e) = self.refresh_worlds().await {
+            warn!("Failed to index worlds: {}", e);
+        }
         self.running.store(true, Ordering::SeqCst);
         info!("Launcher bridge started");
     }
@@ -198,7 +289,7 @@ impl LauncherBridge {
         debug!("Processing launcher handshake from user {}", handshake.user_id);
         
         let capabilities = if handshake.capabilities_requested {
-            Some(self.capabilities.clone())
+            Some(self.get_capabilities())
         } else {
             None
         };
@@ -216,7 +307,9 @@ impl LauncherBridge {
     }
     
     pub fn get_capabilities(&self) -> ServerCapabilities {
-        self.capabilities.clone()
+        let mut capabilities = self.capabilities.clone();
+        capabilities.worlds = self.worlds.read().iter().map(|(summary, _)| summary.clone()).collect();
+        capabilities
     }
     
     pub fn validate_cosmetic_ownership(&self, user_id: Uuid, cosmetic_ids: &[Uuid]) -> Vec<bool> {
@@ -225,15 +318,33 @@ impl LauncherBridge {
             .collect()
     }
     
+    /// The cosmetics a player may wear from their manifest, verifying the
+    /// premium ones in one batch
+    pub async fn apply_cosmetics(&self, manifest: &AssetManifest) -> AppliedCosmetics {
+        let claims: Vec<(Uuid, Uuid)> = manifest.cosmetic_ids.iter()
+            .filter(|id| self.assets.get_cosmetic(**id).is_some_and(|c| c.premium))
+            .map(|id| (manifest.user_id, *id))
+            .collect();
+        
+        let verified: HashSet<Uuid> = match (&self.ownership, claims.is_empty()) {
+            (Some(verifier), false) => verifier.verify(&claims).await
+                .into_iter()
+                .map(|(_, item_id)| item_id)
+                .collect(),
+            _ => HashSet::new(),
+        };
+        self.assets.apply_cosmetics(&manifest.cosmetic_ids, &verified)
+    }
+    
     pub fn export_server_info(&self) -> ServerInfo {
         ServerInfo {
             name: "Pond Server".to_string(),
             description: "A modular Hytale server".to_string(),
-            capabilities: self.capabilities.clone(),
+            capabilities: self.get_capabilities(),
             online: self.running.load(Ordering::Relaxed),
-            player_count: self.player_count.load(Ordering::Relaxed),
-            max_players: self.max_players.load(Ordering::Relaxed),
-            queue_length: 0,
+            player_count: self.player_count(),
+            max_players: self.max_players(),
+            queue_length: self.queue.len() as u32,
         }
     }
     
@@ -262,59 +373,18 @@ impl LauncherBridge {
     
     pub fn disconnect_launcher(&self, user_id: Uuid) {
         self.connected_launchers.remove(&user_id);
+        if let Some(verifier) = &self.ownership {
+            verifier.forget_user(user_id);
+        }
         debug!("Disconnected launcher for user {}", user_id);
     }
     
-    pub async fn get_queue_position(&self, user_id: Uuid) -> Option<QueueEntry> {
-        let queue = self.queue.read().await;
-        queue.iter().find(|e| e.user_id == user_id).cloned()
+    pub fn get_queue_position(&self, user_id: Uuid) -> Option<QueueEntry> {
+        self.queue.entry(user_id)
     }
     
-    pub async fn join_queue(&self, user_id: Uuid, priority: bool) -> QueueEntry {
-        let mut queue = self.queue.write().await;
-        
-        if let Some(existing) = queue.iter().position(|e| e.user_id == user_id) {
-            return queue[existing].clone();
-        }
-        
-        let position = if priority {
-            let priority_end = queue.iter().take_while(|e| e.priority).count();
-            priority_end as u32
-        } else {
-            queue.len() as u32
-        };
-        
-        let entry = QueueEntry {
-            user_id,
-            position,
-            joined_at: chrono::Utc::now(),
-            estimated_wait_secs: position * 30,
-            priority,
-        };
-        
-        if priority {
-            let insert_pos = queue.iter().take_while(|e| e.priority).count();
-            queue.insert(insert_pos, entry.clone());
-            for (i, e) in queue.iter_mut().enumerate() {
-                e.position = i as u32;
-                e.estimated_wait_secs = e.position * 30;
-            }
-        } else {
-            queue.push(entry.clone());
-        }
-        
-        entry
-    }
-    
-    pub async fn leave_queue(&self, user_id: Uuid) {
-        let mut queue = self.queue.write().await;
-        if let Some(pos) = queue.iter().position(|e| e.user_id == user_id) {
-            queue.remove(pos);
-            for (i, e) in queue.iter_mut().enumerate() {
-                e.position = i as u32;
-                e.estimated_wait_secs = e.position * 30;
-            }
-        }
+    pub fn leave_queue(&self, user_id: Uuid) {
+        self.queue.leave_user(user_id);
     }
     
     pub fn get_friends_on_server(&self, friend_ids: &[Uuid]) -> Vec<PlayerActivity> {
@@ -337,14 +407,29 @@ impl LauncherBridge {
         self.connected_launchers.len() as u32
     }
     
+    /// Advertise an asset launchers should cache before or after joining.
+    /// Registering the same path again replaces the earlier entry.
+    pub fn register_preload_asset(&self, asset: PreloadAsset) {
+        let mut assets = self.preload_assets.write();
+        assets.retain(|a| a.path != asset.path);
+        assets.push(asset);
+    }
+    
     pub fn get_asset_preload_manifest(&self) -> AssetPreloadManifest {
+        let assets = self.preload_assets.read().clone();
+        let total_bytes: u64 = assets.iter().map(|a| a.size_bytes).sum();
         AssetPreloadManifest {
             textures: vec![],
             models: vec![],
             sounds: vec![],
-            priority_assets: vec![],
-            total_size_mb: 0,
+            priority_assets: assets.iter()
+                .filter(|a| a.priority == PreloadPriority::Required)
+                .map(|a| a.path.clone())
+                .collect(),
+            total_size_mb: total_bytes.div_ceil(1024 * 1024) as u32,
             cache_duration_hours: 24,
+            regions: self.worlds.read().iter().map(|(_, manifest)| manifest.clone()).collect(),
+            assets,
         }
     }
     
@@ -388,6 +473,32 @@ pub struct AssetPreloadManifest {
     pub priority_assets: Vec<String>,
     pub total_size_mb: u32,
     pub cache_duration_hours: u32,
+    /// Per-world region hashes; launchers fetch only regions whose hash they lack.
+    #[serde(default)]
+    pub regions: Vec<RegionManifest>,
+    /// Content-addressed assets; launchers download only hashes they lack.
+    #[serde(default)]
+    pub assets: Vec<PreloadAsset>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreloadPriority {
+    /// Must be cached before the player can join
+    Required,
+    /// Fetched in the background once the player is in
+    Streamable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreloadAsset {
+    pub path: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub priority: PreloadPriority,
+    /// Where to download it; defaults to the server's asset endpoint
+    #[serde(default)]
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,239 @@
+//! Capability discovery for launchers.
+//!
+//! A launcher POSTs its `ClientCapabilities` as JSON to `/pond/discover`
+//! and gets back the server's capabilities, asset preload manifest and
+//! network hints in one answer. `GET /pond/discover` answers the same for a
+//! launcher that only pings. Both sides ignore fields they don't know, so
+//! either can add fields without a protocol bump; the answer carries the
+//! lower of the two protocol versions.
+//!
+//! The same listener serves the join queue; see [`super::queue`].
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+use super::queue::{self, QUEUE_JOIN_PATH, QUEUE_LEAVE_PATH, QUEUE_STATUS_PATH};
+use super::{AssetPreloadManifest, LauncherBridge, NetworkOptimizationHints, ServerCapabilities};
+
+pub const DISCOVERY_PATH: &str = "/pond/discover";
+
+/// Bumped only when a field changes meaning or is removed.
+pub const DISCOVERY_PROTOCOL_VERSION: u32 = 1;
+
+/// The join queue, served on the same port.
+const QUEUE_PATHS: &[&str] = &[QUEUE_JOIN_PATH, QUEUE_STATUS_PATH, QUEUE_LEAVE_PATH];
+
+/// Requests bigger than this are refused unread.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// What the launcher says about itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientCapabilities {
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub launcher_version: String,
+    /// Launcher features the server may rely on, such as `asset_preload`.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Measured round trip to this server, used for the network hints.
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
+}
+
+impl Default for ClientCapabilities {
+    fn default() -> Self {
+        Self {
+            protocol_version: DISCOVERY_PROTOCOL_VERSION,
+            launcher_version: String::new(),
+            features: Vec::new(),
+            latency_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryResponse {
+    pub protocol_version: u32,
+    pub capabilities: ServerCapabilities,
+    pub preload: AssetPreloadManifest,
+    pub network_hints: NetworkOptimizationHints,
+}
+
+impl LauncherBridge {
+    /// Answer a launcher's discovery request.
+    pub fn discover(&self, client: &ClientCapabilities) -> Result<DiscoveryResponse, String> {
+        if client.protocol_version == 0 {
+            return Err("protocol_version must be at least 1".to_string());
+        }
+        debug!("Capability discovery from launcher {} (protocol {})", client.launcher_version, client.protocol_version);
+        Ok(DiscoveryResponse {
+            protocol_version: client.protocol_version.min(DISCOVERY_PROTOCOL_VERSION),
+            capabilities: self.get_capabilities(),
+            preload: self.get_asset_preload_manifest(),
+            network_hints: self.get_network_optimization_hints(client.latency_ms.unwrap_or(0)),
+        })
+    }
+}
+
+/// Serves `/pond/discover` and the join queue over plain HTTP/1.1, one
+/// request per connection.
+pub struct DiscoveryEndpoint {
+    listener: TcpListener,
+    bridge: Arc<LauncherBridge>,
+}
+
+impl DiscoveryEndpoint {
+    pub async fn bind(addr: SocketAddr, bridge: Arc<LauncherBridge>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(Self { listener, bridge })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections until the task is dropped.
+    pub async fn serve(self) {
+        if let Ok(addr) = self.listener.local_addr() {
+            info!("Capability discovery listening on {}", addr);
+        }
+        loop {
+            let Ok((stream, peer)) = self.listener.accept().await else {
+                continue;
+            };
+            let bridge = self.bridge.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &bridge).await {
+                    debug!("Discovery request from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, bridge: &LauncherBridge) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let route = path.split('?').next().unwrap_or("");
+    let (status, body) = if route != DISCOVERY_PATH && !QUEUE_PATHS.contains(&route) {
+        ("404 Not Found", error_body("Not found"))
+    } else if content_length > MAX_REQUEST_BYTES {
+        ("413 Payload Too Large", error_body("Request too large"))
+    } else if route != DISCOVERY_PATH {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+        queue::answer(bridge, method, route, &body).await
+    } else {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+        let client = match method {
+            "GET" => Ok(ClientCapabilities::default()),
+            "POST" => serde_json::from_slice(&body).map_err(|e| e.to_string()),
+            _ => Err(format!("Method {} not allowed", method)),
+        };
+        match client.and_then(|client| bridge.discover(&client)) {
+            Ok(response) => ("200 OK", serde_json::to_string(&response).unwrap_or_default()),
+            Err(e) => ("400 Bad Request", error_body(&e)),
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::assets::AssetRegistry;
+    use crate::core::integration::{PreloadAsset, PreloadPriority};
+
+    async fn endpoint() -> SocketAddr {
+        let bridge = LauncherBridge::new(Arc::new(AssetRegistry::new()));
+        bridge.register_preload_asset(PreloadAsset {
+            path: "textures/sky.png".to_string(),
+            sha256: "ab".repeat(32),
+            size_bytes: 2048,
+            priority: PreloadPriority::Required,
+            url: None,
+        });
+        let endpoint = DiscoveryEndpoint::bind("127.0.0.1:0".parse().unwrap(), Arc::new(bridge)).await.unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(endpoint.serve());
+        addr
+    }
+
+    async fn send(addr: SocketAddr, request: String) -> (String, serde_json::Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), serde_json::from_str(body).unwrap())
+    }
+
+    fn post(body: &str) -> String {
+        format!("POST {} HTTP/1.1\r\nHost: pond\r\nContent-Length: {}\r\n\r\n{}", DISCOVERY_PATH, body.len(), body)
+    }
+
+    #[tokio::test]
+    async fn test_tolerates_unknown_fields_and_newer_launchers() {
+        let addr = endpoint().await;
+        let (status, body) = send(addr, post(r#"{
+            "protocol_version": 7, "launcher_version": "9.0.0", "latency_ms": 120,
+            "features": ["asset_preload"], "hologram_support": true
+        }"#)).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body["protocol_version"], DISCOVERY_PROTOCOL_VERSION);
+        assert!(body["capabilities"]["features"].as_array().unwrap().contains(&"cosmetics".into()));
+        assert_eq!(body["preload"]["priority_assets"], serde_json::json!(["textures/sky.png"]));
+        assert_eq!(body["network_hints"]["recommended_update_rate"], 10);
+    }
+
+    #[tokio::test]
+    async fn test_ping_and_bad_requests() {
+        let addr = endpoint().await;
+        let (status, body) = send(addr, format!("GET {} HTTP/1.1\r\n\r\n", DISCOVERY_PATH)).await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body["network_hints"]["recommended_update_rate"], 20);
+
+        let (status, _) = send(addr, post(r#"{ "protocol_version": 0 }"#)).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        let (status, _) = send(addr, post("not json")).await;
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        let (status, _) = send(addr, "GET /elsewhere HTTP/1.1\r\n\r\n".to_string()).await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+    }
+}
@@ -0,0 +1,342 @@
+//! Cosmetic ownership verification against the central marketplace.
+//!
+//! Premium cosmetics are sold on the central server, so before wearing one
+//! a player's claim is sent to `POST /api/v1/cosmetics/verify`. Answers
+//! come back signed with the hash of this server's token and are cached
+//! until they expire. While the central server can't be reached, cached
+//! answers keep counting for a further grace window.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::core::config::IntegrationSettings;
+
+pub const VERIFY_PATH: &str = "/api/v1/cosmetics/verify";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CentralApiError {
+    /// No answer; cached verifications stay usable for the grace window.
+    Unreachable(String),
+    /// The central server answered with an error.
+    Rejected(String),
+}
+
+impl fmt::Display for CentralApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable(e) => write!(f, "Central server unreachable: {}", e),
+            Self::Rejected(e) => write!(f, "Central server rejected the request: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CentralApiError {}
+
+/// HTTP access to the central server, supplied by whatever embeds Pond.
+#[async_trait]
+pub trait CentralApi: Send + Sync {
+    /// POST a JSON body to `path` and return the JSON response body.
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value, CentralApiError>;
+}
+
+/// One signed answer from the central server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifiedOwnership {
+    pub user_id: Uuid,
+    pub item_id: Uuid,
+    pub owned: bool,
+    pub expires_at: DateTime<Utc>,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyResponse {
+    success: bool,
+    data: Option<VerifyData>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyData {
+    results: Vec<VerifiedOwnership>,
+}
+
+pub struct OwnershipVerifier {
+    api: Arc<dyn CentralApi>,
+    server_id: Uuid,
+    server_token: String,
+    /// Hex SHA-256 of the server token, which the central server keys
+    /// its signatures with.
+    signing_key: String,
+    offline_grace: Duration,
+    cache: DashMap<(Uuid, Uuid), VerifiedOwnership>,
+}
+
+impl OwnershipVerifier {
+    pub fn new(api: Arc<dyn CentralApi>, server_id: Uuid, server_token: impl Into<String>) -> Self {
+        let server_token = server_token.into();
+        Self {
+            api,
+            server_id,
+            signing_key: hex(&Sha256::digest(server_token.as_bytes())),
+            server_token,
+            offline_grace: Duration::seconds(i64::from(crate::core::config::DEFAULT_OWNERSHIP_GRACE_SECS)),
+            cache: DashMap::new(),
+        }
+    }
+
+    /// A verifier for the listed server in `settings`, if it has a scoped
+    /// token. Like heartbeats, a user session token is refused.
+    pub fn from_settings(api: Arc<dyn CentralApi>, settings: &IntegrationSettings) -> Result<Option<Self>, String> {
+        let (Some(server_id), Some(token)) = (settings.server_id, &settings.server_token) else {
+            return Ok(None);
+        };
+        if !token.starts_with("srv_") {
+            return Err("integration.server_token must be a scoped server token (srv_...)".to_string());
+        }
+        Ok(Some(
+            Self::new(api, server_id, token.clone())
+                .with_offline_grace(Duration::seconds(i64::from(settings.ownership_grace_secs))),
+        ))
+    }
+
+    pub fn with_offline_grace(mut self, grace: Duration) -> Self {
+        self.offline_grace = grace;
+        self
+    }
+
+    /// The `(user_id, item_id)` claims that are verified as owned.
+    pub async fn verify(&self, claims: &[(Uuid, Uuid)]) -> HashSet<(Uuid, Uuid)> {
+        self.verify_at(claims, Utc::now()).await
+    }
+
+    async fn verify_at(&self, claims: &[(Uuid, Uuid)], now: DateTime<Utc>) -> HashSet<(Uuid, Uuid)> {
+        let mut missing: Vec<(Uuid, Uuid)> = claims.iter()
+            .filter(|claim| self.cache.get(claim).is_none_or(|v| v.expires_at <= now))
+            .copied()
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        let mut offline = false;
+        if !missing.is_empty() {
+            match self.fetch(&missing).await {
+                Ok(results) => self.store(&missing, results),
+                Err(CentralApiError::Unreachable(e)) => {
+                    warn!("Using cached cosmetic ownership, central server unreachable: {}", e);
+                    offline = true;
+                }
+                Err(e) => warn!("Cosmetic ownership not verified: {}", e),
+            }
+        }
+
+        let grace = if offline { self.offline_grace } else { Duration::zero() };
+        claims.iter()
+            .filter(|claim| {
+                self.cache.get(claim).is_some_and(|v| v.owned && v.expires_at + grace > now)
+            })
+            .copied()
+            .collect()
+    }
+
+    async fn fetch(&self, claims: &[(Uuid, Uuid)]) -> Result<Vec<VerifiedOwnership>, CentralApiError> {
+        let body = serde_json::json!({
+            "token": self.server_token,
+            "server_id": self.server_id,
+            "items": claims.iter()
+                .map(|(user_id, item_id)| serde_json::json!({ "user_id": user_id, "item_id": item_id }))
+                .collect::<Vec<_>>(),
+        });
+        let response: VerifyResponse = serde_json::from_value(self.api.post(VERIFY_PATH, body).await?)
+            .map_err(|e| CentralApiError::Rejected(format!("Malformed response: {}", e)))?;
+        match response {
+            VerifyResponse { success: true, data: Some(data), .. } => Ok(data.results),
+            VerifyResponse { error, .. } => Err(CentralApiError::Rejected(
+                error.unwrap_or_else(|| "Unknown error".to_string()),
+            )),
+        }
+    }
+
+    /// Cache answers to `asked`. Answers to anything else, or with a bad
+    /// signature, are dropped; claims left unanswered stay unverified and
+    /// are asked again next time.
+    fn store(&self, asked: &[(Uuid, Uuid)], results: Vec<VerifiedOwnership>) {
+        for result in results {
+            let claim = (result.user_id, result.item_id);
+            if !asked.contains(&claim) {
+                warn!("Ignoring ownership answer for {} / {} that was not asked for", claim.0, claim.1);
+                continue;
+            }
+            if !self.signature_valid(&result) {
+                warn!("Ignoring ownership answer for {} / {} with a bad signature", claim.0, claim.1);
+                continue;
+            }
+            self.cache.insert(claim, result);
+        }
+        debug!("Cached {} cosmetic ownership answers", self.cache.len());
+    }
+
+    fn signature_valid(&self, result: &VerifiedOwnership) -> bool {
+        let payload = signing_payload(self.server_id, result);
+        hex(&hmac_sha256(self.signing_key.as_bytes(), payload.as_bytes())) == result.signature.to_ascii_lowercase()
+    }
+
+    /// Drop a player's cached answers when their session ends.
+    pub fn forget_user(&self, user_id: Uuid) {
+        self.cache.retain(|(user, _), _| *user != user_id);
+    }
+}
+
+/// What the central server signs; see `server/src/ownership.rs`.
+fn signing_payload(server_id: Uuid, result: &VerifiedOwnership) -> String {
+    format!(
+        "{}|{}|{}|{}|{}",
+        server_id, result.user_id, result.item_id, result.owned, result.expires_at.timestamp()
+    )
+}
+
+const HMAC_BLOCK: usize = 64;
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; HMAC_BLOCK];
+    if key.len() > HMAC_BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    const TOKEN: &str = "srv_test";
+
+    /// Stands in for the central server's endpoint.
+    struct MockCentral {
+        server_id: Uuid,
+        owned: HashSet<(Uuid, Uuid)>,
+        online: AtomicBool,
+        calls: AtomicUsize,
+        /// Rewrites answers after signing, as a tampering proxy would.
+        tamper: Option<fn(&mut VerifiedOwnership)>,
+    }
+
+    impl MockCentral {
+        fn new(server_id: Uuid, owned: &[(Uuid, Uuid)]) -> Self {
+            Self {
+                server_id,
+                owned: owned.iter().copied().collect(),
+                online: AtomicBool::new(true),
+                calls: AtomicUsize::new(0),
+                tamper: None,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl CentralApi for MockCentral {
+        async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value, CentralApiError> {
+            assert_eq!(path, VERIFY_PATH);
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if !self.online.load(Ordering::SeqCst) {
+                return Err(CentralApiError::Unreachable("connection refused".to_string()));
+            }
+            assert_eq!(body["token"], TOKEN);
+
+            let key = hex(&Sha256::digest(TOKEN.as_bytes()));
+            let expires_at = Utc::now() + Duration::seconds(300);
+            let results: Vec<VerifiedOwnership> = body["items"].as_array().unwrap().iter()
+                .map(|item| {
+                    let claim = (
+                        serde_json::from_value(item["user_id"].clone()).unwrap(),
+                        serde_json::from_value(item["item_id"].clone()).unwrap(),
+                    );
+                    let mut result = VerifiedOwnership {
+                        user_id: claim.0,
+                        item_id: claim.1,
+                        owned: self.owned.contains(&claim),
+                        expires_at,
+                        signature: String::new(),
+                    };
+                    let payload = signing_payload(self.server_id, &result);
+                    result.signature = hex(&hmac_sha256(key.as_bytes(), payload.as_bytes()));
+                    if let Some(tamper) = self.tamper {
+                        tamper(&mut result);
+                    }
+                    result
+                })
+                .collect();
+            Ok(serde_json::json!({ "success": true, "data": { "results": results }, "error": null }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_expiry_and_offline_grace() {
+        let (server_id, player, cape) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let central = Arc::new(MockCentral::new(server_id, &[(player, cape)]));
+        let verifier = OwnershipVerifier::new(central.clone(), server_id, TOKEN)
+            .with_offline_grace(Duration::seconds(600));
+        let now = Utc::now();
+
+        assert!(verifier.verify_at(&[(player, cape)], now).await.contains(&(player, cape)));
+        // Served from the cache until the answer expires
+        assert!(verifier.verify_at(&[(player, cape)], now + Duration::seconds(60)).await.contains(&(player, cape)));
+        assert_eq!(central.calls.load(Ordering::SeqCst), 1);
+
+        // Expired and unreachable: the grace window still covers it...
+        central.online.store(false, Ordering::SeqCst);
+        let later = now + Duration::seconds(600);
+        assert!(verifier.verify_at(&[(player, cape)], later).await.contains(&(player, cape)));
+        assert_eq!(central.calls.load(Ordering::SeqCst), 2);
+        // ...until it runs out
+        assert!(verifier.verify_at(&[(player, cape)], now + Duration::seconds(1000)).await.is_empty());
+
+        // Without a cached answer there is nothing to fall back on
+        verifier.forget_user(player);
+        assert!(verifier.verify_at(&[(player, cape)], now).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_forged_item_ids_are_not_verified() {
+        let (server_id, player, cape, forged) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let central = Arc::new(MockCentral::new(server_id, &[(player, cape)]));
+        let verifier = OwnershipVerifier::new(central.clone(), server_id, TOKEN);
+
+        // An item the player doesn't own is answered, but not owned
+        let verified = verifier.verify(&[(player, cape), (player, forged)]).await;
+        assert_eq!(verified, HashSet::from([(player, cape)]));
+
+        // An answer flipped to owned after signing is dropped
+        let mut tampered = MockCentral::new(server_id, &[(player, cape)]);
+        tampered.tamper = Some(|result| result.owned = true);
+        let verifier = OwnershipVerifier::new(Arc::new(tampered), server_id, TOKEN);
+        assert!(verifier.verify(&[(player, forged)]).await.is_empty());
+
+        // As is one signed for a different server
+        let other_server = MockCentral::new(Uuid::new_v4(), &[(player, cape)]);
+        let verifier = OwnershipVerifier::new(Arc::new(other_server), server_id, TOKEN);
+        assert!(verifier.verify(&[(player, cape)]).await.is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+//! Join queue for launchers.
+//!
+//! When the server is full, launchers wait in line over the launcher API.
+//! `POST /pond/queue/join` hands back a ticket, `POST /pond/queue/status`
+//! reports where that ticket stands and `POST /pond/queue/leave` gives the
+//! place up. A launcher that sends its central-server session token claims
+//! priority; the claim is checked against the subscription API and, if the
+//! account is premium, the player queues ahead of everyone without one.
+//! Within a tier it's first come, first served.
+//!
+//! When a slot frees up the head of the queue is admitted and sees
+//! `admitted` on its next poll. Launchers that stop polling lose their
+//! place, and an admitted ticket holds its slot only for a while.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::ownership::{CentralApi, CentralApiError};
+use super::{LauncherBridge, QueueEntry};
+
+pub const QUEUE_JOIN_PATH: &str = "/pond/queue/join";
+pub const QUEUE_STATUS_PATH: &str = "/pond/queue/status";
+pub const QUEUE_LEAVE_PATH: &str = "/pond/queue/leave";
+
+/// The central server's subscription lookup, which takes a session token.
+pub const SUBSCRIPTION_PATH: &str = "/api/v1/subscription";
+
+/// Rough time each player ahead adds to the wait.
+pub const SECS_PER_POSITION: u32 = 30;
+
+/// Waiting tickets not polled for this long give up their place.
+pub const DEFAULT_TICKET_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long an admitted player's slot is held for them.
+pub const DEFAULT_ADMIT_WINDOW: Duration = Duration::from_secs(120);
+
+/// Subscription statuses that still count as paid.
+const PAID_STATUSES: &[&str] = &["active", "trialing"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinQueueRequest {
+    pub user_id: Uuid,
+    /// Central-server session token, sent to claim priority.
+    #[serde(default)]
+    pub priority_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketRequest {
+    pub ticket: Uuid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueState {
+    Waiting,
+    /// A slot is held; the player should connect now.
+    Admitted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub ticket: Uuid,
+    pub state: QueueState,
+    /// Players ahead of this one; 0 once admitted.
+    pub position: u32,
+    pub queue_length: u32,
+    pub estimated_wait_secs: u32,
+    /// Whether the ticket was placed in the priority tier.
+    pub priority: bool,
+}
+
+/// Checks priority claims against the central server's subscription API.
+pub struct PriorityVerifier {
+    api: Arc<dyn CentralApi>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionResponse {
+    success: bool,
+    data: Option<SubscriptionData>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionData {
+    user_id: Uuid,
+    tier: String,
+    status: String,
+}
+
+impl PriorityVerifier {
+    pub fn new(api: Arc<dyn CentralApi>) -> Self {
+        Self { api }
+    }
+
+    /// Whether `token` is a session of `user_id` on a paid subscription.
+    pub async fn verify(&self, user_id: Uuid, token: &str) -> bool {
+        match self.fetch(token).await {
+            Ok(sub) if sub.user_id != user_id => {
+                warn!("Priority claim for {} used a token of another user", user_id);
+                false
+            }
+            Ok(sub) => sub.tier != "free" && PAID_STATUSES.contains(&sub.status.as_str()),
+            Err(e) => {
+                warn!("Priority claim for {} not verified: {}", user_id, e);
+                false
+            }
+        }
+    }
+
+    async fn fetch(&self, token: &str) -> Result<SubscriptionData, CentralApiError> {
+        let body = serde_json::json!({ "token": token });
+        let response: SubscriptionResponse = serde_json::from_value(self.api.post(SUBSCRIPTION_PATH, body).await?)
+            .map_err(|e| CentralApiError::Rejected(format!("Malformed response: {}", e)))?;
+        match response {
+            SubscriptionResponse { success: true, data: Some(data), .. } => Ok(data),
+            SubscriptionResponse { error, .. } => Err(CentralApiError::Rejected(
+                error.unwrap_or_else(|| "Unknown error".to_string()),
+            )),
+        }
+    }
+}
+
+struct Ticket {
+    user_id: Uuid,
+    priority: bool,
+    last_seen: Instant,
+    admitted_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    /// Waiting players, the priority tier first, each tier in arrival order.
+    entries: Vec<QueueEntry>,
+    tickets: HashMap<Uuid, Ticket>,
+}
+
+impl QueueInner {
+    fn ticket_of(&self, user_id: Uuid) -> Option<Uuid> {
+        self.tickets.iter().find(|(_, t)| t.user_id == user_id).map(|(id, _)| *id)
+    }
+
+    fn renumber(&mut self) {
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            entry.position = i as u32;
+            entry.estimated_wait_secs = entry.position * SECS_PER_POSITION;
+        }
+    }
+
+    fn status(&self, ticket: Uuid) -> Option<QueueStatus> {
+        let t = self.tickets.get(&ticket)?;
+        let queue_length = self.entries.len() as u32;
+        if t.admitted_at.is_some() {
+            return Some(QueueStatus {
+                ticket,
+                state: QueueState::Admitted,
+                position: 0,
+                queue_length,
+                estimated_wait_secs: 0,
+                priority: t.priority,
+            });
+        }
+        let entry = self.entries.iter().find(|e| e.user_id == t.user_id)?;
+        Some(QueueStatus {
+            ticket,
+            state: QueueState::Waiting,
+            position: entry.position,
+            queue_length,
+            estimated_wait_secs: entry.estimated_wait_secs,
+            priority: entry.priority,
+        })
+    }
+}
+
+/// The line of players waiting for a slot.
+pub struct JoinQueue {
+    inner: parking_lot::Mutex<QueueInner>,
+    ticket_timeout: Duration,
+    admit_window: Duration,
+}
+
+impl Default for JoinQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICKET_TIMEOUT, DEFAULT_ADMIT_WINDOW)
+    }
+}
+
+impl JoinQueue {
+    pub fn new(ticket_timeout: Duration, admit_window: Duration) -> Self {
+        Self {
+            inner: parking_lot::Mutex::new(QueueInner::default()),
+            ticket_timeout,
+            admit_window,
+        }
+    }
+
+    /// Queue `user_id` and return their ticket, or the ticket they already
+    /// hold if they're queued or admitted.
+    pub fn join(&self, user_id: Uuid, priority: bool) -> Uuid {
+        let mut inner = self.inner.lock();
+        if let Some(ticket) = inner.ticket_of(user_id) {
+            if let Some(t) = inner.tickets.get_mut(&ticket) {
+                t.last_seen = Instant::now();
+            }
+            return ticket;
+        }
+
+        let entry = QueueEntry {
+            user_id,
+            position: 0,
+            joined_at: chrono::Utc::now(),
+            estimated_wait_secs: 0,
+            priority,
+        };
+        let insert_at = if priority {
+            inner.entries.iter().take_while(|e| e.priority).count()
+        } else {
+            inner.entries.len()
+        };
+        inner.entries.insert(insert_at, entry);
+        inner.renumber();
+
+        let ticket = Uuid::new_v4();
+        inner.tickets.insert(ticket, Ticket { user_id, priority, last_seen: Instant::now(), admitted_at: None });
+        debug!("Queued {} at position {} (priority: {})", user_id, insert_at, priority);
+        ticket
+    }
+
+    /// Where `ticket` stands, counting the poll as a sign of life.
+    pub fn poll(&self, ticket: Uuid) -> Option<QueueStatus> {
+        let mut inner = self.inner.lock();
+        inner.tickets.get_mut(&ticket)?.last_seen = Instant::now();
+        inner.status(ticket)
+    }
+
+    pub fn status(&self, ticket: Uuid) -> Option<QueueStatus> {
+        self.inner.lock().status(ticket)
+    }
+
+    pub fn entry(&self, user_id: Uuid) -> Option<QueueEntry> {
+        self.inner.lock().entries.iter().find(|e| e.user_id == user_id).cloned()
+    }
+
+    /// Give up a ticket, waiting or admitted. Returns whether it existed.
+    pub fn leave(&self, ticket: Uuid) -> bool {
+        let mut inner = self.inner.lock();
+        let Some(t) = inner.tickets.remove(&ticket) else {
+            return false;
+        };
+        inner.entries.retain(|e| e.user_id != t.user_id);
+        inner.renumber();
+        true
+    }
+
+    pub fn leave_user(&self, user_id: Uuid) -> bool {
+        let ticket = self.inner.lock().ticket_of(user_id);
+        ticket.is_some_and(|ticket| self.leave(ticket))
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop silent and lapsed tickets, then admit from the head of the
+    /// queue into `open_slots` less the slots already held. Returns the
+    /// users admitted.
+    pub fn admit(&self, open_slots: u32) -> Vec<Uuid> {
+        let mut inner = self.inner.lock();
+        let now = Instant::now();
+        let (ticket_timeout, admit_window) = (self.ticket_timeout, self.admit_window);
+        let mut dropped = Vec::new();
+        inner.tickets.retain(|_, t| {
+            let live = match t.admitted_at {
+                Some(admitted_at) => now.duration_since(admitted_at) < admit_window,
+                None => now.duration_since(t.last_seen) < ticket_timeout,
+            };
+            if !live && t.admitted_at.is_none() {
+                dropped.push(t.user_id);
+            }
+            live
+        });
+        if !dropped.is_empty() {
+            debug!("Dropped {} queue tickets that stopped polling", dropped.len());
+            inner.entries.retain(|e| !dropped.contains(&e.user_id));
+        }
+
+        let held = inner.tickets.values().filter(|t| t.admitted_at.is_some()).count() as u32;
+        let admitting = (open_slots.saturating_sub(held) as usize).min(inner.entries.len());
+        let admitted: Vec<Uuid> = inner.entries.drain(..admitting).map(|e| e.user_id).collect();
+        for t in inner.tickets.values_mut() {
+            if admitted.contains(&t.user_id) {
+                t.admitted_at = Some(now);
+            }
+        }
+        inner.renumber();
+        for user_id in &admitted {
+            info!("Admitted {} from the join queue", user_id);
+        }
+        admitted
+    }
+}
+
+impl LauncherBridge {
+    /// Queue a launcher, verifying its priority claim if it made one.
+    pub async fn enter_queue(&self, request: &JoinQueueRequest) -> QueueStatus {
+        let priority = match (&request.priority_token, &self.priority) {
+            (Some(token), Some(verifier)) => verifier.verify(request.user_id, token).await,
+            _ => false,
+        };
+        let ticket = self.queue.join(request.user_id, priority);
+        self.admit_from_queue();
+        self.queue.status(ticket).expect("ticket was just issued")
+    }
+
+    /// Where a ticket stands, or `None` if it's unknown or lapsed.
+    pub fn queue_status(&self, ticket: Uuid) -> Option<QueueStatus> {
+        self.admit_from_queue();
+        self.queue.poll(ticket)
+    }
+
+    pub fn leave_queue_ticket(&self, ticket: Uuid) -> bool {
+        self.queue.leave(ticket)
+    }
+
+    /// Admit waiting players into whatever slots are free.
+    pub fn admit_from_queue(&self) -> Vec<Uuid> {
+        let open = self.max_players().saturating_sub(self.player_count());
+        self.queue.admit(open)
+    }
+}
+
+/// Answer one of the queue routes with an HTTP status and JSON body.
+pub(super) async fn answer(bridge: &LauncherBridge, method: &str, path: &str, body: &[u8]) -> (&'static str, String) {
+    if method != "POST" {
+        return ("400 Bad Request", error_body(&format!("Method {} not allowed", method)));
+    }
+    let result = match path {
+        QUEUE_JOIN_PATH => match serde_json::from_slice::<JoinQueueRequest>(body) {
+            Ok(request) => Ok(serde_json::to_value(bridge.enter_queue(&request).await).unwrap_or_default()),
+            Err(e) => Err(("400 Bad Request", e.to_string())),
+        },
+        QUEUE_STATUS_PATH => match serde_json::from_slice::<TicketRequest>(body) {
+            Ok(request) => bridge.queue_status(request.ticket)
+                .map(|status| serde_json::to_value(status).unwrap_or_default())
+                .ok_or(("404 Not Found", "Unknown ticket".to_string())),
+            Err(e) => Err(("400 Bad Request", e.to_string())),
+        },
+        QUEUE_LEAVE_PATH => match serde_json::from_slice::<TicketRequest>(body) {
+            Ok(request) => Ok(serde_json::json!({ "left": bridge.leave_queue_ticket(request.ticket) })),
+            Err(e) => Err(("400 Bad Request", e.to_string())),
+        },
+        _ => Err(("404 Not Found", "Not found".to_string())),
+    };
+    match result {
+        Ok(value) => ("200 OK", value.to_string()),
+        Err((status, message)) => (status, error_body(&message)),
+    }
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::assets::AssetRegistry;
+    use async_trait::async_trait;
+
+    /// Stands in for the central server's subscription lookup.
+    struct MockSubscriptions {
+        /// Token to (user, tier)
+        sessions: HashMap<String, (Uuid, &'static str)>,
+    }
+
+    #[async_trait]
+    impl CentralApi for MockSubscriptions {
+        async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value, CentralApiError> {
+            assert_eq!(path, SUBSCRIPTION_PATH);
+            let token = body["token"].as_str().unwrap_or_default();
+            Ok(match self.sessions.get(token) {
+                Some((user_id, tier)) => serde_json::json!({
+                    "success": true,
+                    "data": { "user_id": user_id, "tier": tier, "status": "active", "features": {} },
+                    "error": null,
+                }),
+                None => serde_json::json!({ "success": false, "data": null, "error": "Invalid token" }),
+            })
+        }
+    }
+
+    fn full_bridge(sessions: HashMap<String, (Uuid, &'static str)>) -> LauncherBridge {
+        let bridge = LauncherBridge::new(Arc::new(AssetRegistry::new()))
+            .with_priority_verifier(Arc::new(PriorityVerifier::new(Arc::new(MockSubscriptions { sessions }))));
+        bridge.set_max_players(2);
+        bridge.set_player_count(2);
+        bridge
+    }
+
+    fn join(user_id: Uuid, token: Option<&str>) -> JoinQueueRequest {
+        JoinQueueRequest { user_id, priority_token: token.map(String::from) }
+    }
+
+    #[tokio::test]
+    async fn test_priority_client_jumps_the_regular_tier() {
+        let regulars: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let (premium, free_user) = (Uuid::new_v4(), regulars[3]);
+        let bridge = full_bridge(HashMap::from([
+            ("premium-token".to_string(), (premium, "premium")),
+            ("free-token".to_string(), (free_user, "free")),
+        ]));
+
+        let mut tickets = Vec::new();
+        for user in &regulars[..3] {
+            tickets.push(bridge.enter_queue(&join(*user, None)).await.ticket);
+        }
+        // A free account's claim doesn't hold, so it queues normally
+        let free = bridge.enter_queue(&join(free_user, Some("free-token"))).await;
+        assert!(!free.priority);
+        assert_eq!(free.position, 3);
+        tickets.push(free.ticket);
+
+        let jumped = bridge.enter_queue(&join(premium, Some("premium-token"))).await;
+        assert!(jumped.priority);
+        assert_eq!((jumped.position, jumped.queue_length), (0, 5));
+        for (i, ticket) in tickets.iter().enumerate() {
+            let status = bridge.queue_status(*ticket).unwrap();
+            assert_eq!(status.position, i as u32 + 1);
+            assert_eq!(status.estimated_wait_secs, (i as u32 + 1) * SECS_PER_POSITION);
+        }
+
+        // A stolen token doesn't carry priority to someone else
+        let thief = bridge.enter_queue(&join(Uuid::new_v4(), Some("premium-token"))).await;
+        assert!(!thief.priority);
+        assert_eq!(thief.position, 5);
+
+        // One slot frees up: the priority player is let in first
+        bridge.set_player_count(1);
+        assert_eq!(bridge.admit_from_queue(), vec![premium]);
+        assert_eq!(bridge.queue_status(jumped.ticket).unwrap().state, QueueState::Admitted);
+        assert_eq!(bridge.queue_status(tickets[0]).unwrap().position, 0);
+        // The held slot isn't given away twice
+        assert!(bridge.admit_from_queue().is_empty());
+
+        assert!(bridge.leave_queue_ticket(tickets[0]));
+        assert!(bridge.queue_status(tickets[0]).is_none());
+        assert_eq!(bridge.queue_status(tickets[1]).unwrap().position, 0);
+        assert_eq!(bridge.export_server_info().queue_length, 4);
+    }
+
+    #[tokio::test]
+    async fn test_silent_tickets_and_lapsed_admissions_are_dropped() {
+        let bridge = full_bridge(HashMap::new())
+            .with_queue(JoinQueue::new(Duration::from_millis(100), Duration::from_millis(100)));
+        let (quiet, polling) = (Uuid::new_v4(), Uuid::new_v4());
+        let quiet = bridge.enter_queue(&join(quiet, None)).await.ticket;
+        let polling = bridge.enter_queue(&join(polling, None)).await.ticket;
+
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(bridge.queue_status(polling).is_some());
+        }
+        assert!(bridge.queue_status(quiet).is_none());
+        assert_eq!(bridge.queue_status(polling).unwrap().position, 0);
+
+        bridge.set_player_count(1);
+        assert_eq!(bridge.queue_status(polling).unwrap().state, QueueState::Admitted);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(bridge.queue_status(polling).is_none());
+        assert!(bridge.admit_from_queue().is_empty());
+    }
+}
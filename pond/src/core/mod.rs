@@ -1,9 +1,12 @@
 pub mod game;
 pub mod server;
 pub mod plugins;
+pub mod sandbox;
 pub mod scheduler;
+pub mod cron;
 pub mod performance;
 pub mod assets;
 pub mod config;
 pub mod telemetry;
 pub mod integration;
+pub mod console;
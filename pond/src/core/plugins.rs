@@ -1,10 +1,15 @@
 use crate::core::config::ConfigManager;
+use crate::core::game::{GameHook, HookRegistry};
+use crate::core::sandbox::{Capability, PluginCapabilities, PluginSandbox, PluginViolation, Sandbox, SandboxReport};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{broadcast, Mutex};
 use tracing::{info, warn, error};
-use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginMetadata {
@@ -15,6 +20,8 @@ pub struct PluginMetadata {
     pub description: String,
     pub dependencies: Vec<PluginDependency>,
     pub api_version: String,
+    #[serde(default)]
+    pub capabilities: PluginCapabilities,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +48,37 @@ pub trait Plugin: Send + Sync {
     async fn on_disable(&mut self) -> Result<(), String>;
     async fn on_tick(&mut self);
     async fn on_reload(&mut self) -> Result<(), String>;
+    
+    /// Hooks to register while the plugin is enabled, keyed by event type.
+    fn hooks(&self) -> Vec<(String, Arc<dyn GameHook>)> {
+        Vec::new()
+    }
+    
+    /// State to hand to the next instance on a hot reload.
+    fn serialize_state(&self) -> Result<Option<serde_json::Value>, String> {
+        Ok(None)
+    }
+    
+    /// Takes over the state the previous instance serialized.
+    fn restore_state(&mut self, _state: serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Builds a plugin instance from its directory on disk. Pond doesn't fix an
+/// artifact format, so the embedder supplies the loader. The plugin should
+/// reach files, the network and the scheduler through `sandbox`.
+pub trait PluginLoader: Send + Sync {
+    fn load(&self, dir: &Path, metadata: &PluginMetadata, sandbox: PluginSandbox) -> Result<Box<dyn Plugin>, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PluginEvent {
+    Reloaded { plugin_id: String, from_version: String, to_version: String },
+    ReloadFailed { plugin_id: String, version: String, error: String },
+    SandboxViolation { plugin_id: String, capability: Capability, target: String, strikes: u32 },
+    Throttled { plugin_id: String, tick_ms: f64, budget_ms: f64, ticks: u64, throttles: u32 },
 }
 
 pub struct PluginInstance {
@@ -48,10 +86,19 @@ pub struct PluginInstance {
     pub state: PluginState,
     pub load_order: i32,
     pub error: Option<String>,
+    pub dir: PathBuf,
 }
 
+type PluginSlot = Arc<Mutex<Box<dyn Plugin>>>;
+
 pub struct PluginManager {
     plugins: DashMap<String, PluginInstance>,
+    running: DashMap<String, PluginSlot>,
+    loader: Option<Arc<dyn PluginLoader>>,
+    hooks: Arc<HookRegistry>,
+    events: broadcast::Sender<PluginEvent>,
+    sandbox: Arc<Sandbox>,
+    ticks: AtomicU64,
     config: Arc<ConfigManager>,
     plugins_dir: String,
 }
@@ -60,13 +107,50 @@ impl PluginManager {
     pub fn new(config: Arc<ConfigManager>) -> Self {
         let plugins_dir = config.get_string("plugins.directory").unwrap_or_else(|| "plugins".to_string());
         
+        let events = broadcast::channel(64).0;
+        let sandbox_root = std::env::current_dir().unwrap_or_default();
+        
         Self {
             plugins: DashMap::new(),
+            running: DashMap::new(),
+            loader: None,
+            hooks: Arc::new(HookRegistry::new()),
+            sandbox: Arc::new(Sandbox::new(config.clone(), sandbox_root, events.clone())),
+            events,
+            ticks: AtomicU64::new(0),
             config,
             plugins_dir,
         }
     }
     
+    /// Directory relative sandbox paths resolve against; the working
+    /// directory by default.
+    pub fn with_sandbox_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.sandbox = Arc::new(Sandbox::new(self.config.clone(), root.into(), self.events.clone()));
+        self
+    }
+    
+    /// Instantiates enabled plugins through `loader`. Without one plugins
+    /// are tracked by metadata only.
+    pub fn with_loader(mut self, loader: Arc<dyn PluginLoader>) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+    
+    pub fn with_hooks(mut self, hooks: Arc<HookRegistry>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+    
+    pub fn hooks(&self) -> &Arc<HookRegistry> {
+        &self.hooks
+    }
+    
+    /// Reload and sandbox announcements.
+    pub fn subscribe(&self) -> broadcast::Receiver<PluginEvent> {
+        self.events.subscribe()
+    }
+    
     pub async fn load_all(&self) -> Result<(), String> {
         info!("Discovering plugins in: {}", self.plugins_dir);
         
@@ -83,6 +167,7 @@ impl PluginManager {
         };
         
         let mut discovered = Vec::new();
+        let mut dirs = HashMap::new();
         
         for entry in plugin_dirs.flatten() {
             let path = entry.path();
@@ -92,6 +177,7 @@ impl PluginManager {
                     match self.load_plugin_metadata(&config_path) {
                         Ok(metadata) => {
                             info!("Discovered plugin: {} v{}", metadata.name, metadata.version);
+                            dirs.insert(metadata.id.clone(), path.clone());
                             discovered.push(metadata);
                         }
                         Err(e) => {
@@ -110,13 +196,17 @@ impl PluginManager {
                 state: PluginState::Discovered,
                 load_order: order as i32,
                 error: None,
+                dir: dirs.remove(&metadata.id).unwrap_or_default(),
             };
             self.plugins.insert(metadata.id.clone(), instance);
         }
         
-        for entry in self.plugins.iter() {
-            let id = entry.key().clone();
-            drop(entry);
+        let mut ids: Vec<(i32, String)> = self.plugins.iter()
+            .map(|e| (e.load_order, e.key().clone()))
+            .collect();
+        ids.sort();
+        
+        for (_, id) in ids {
             if let Err(e) = self.enable_plugin(&id).await {
                 error!("Failed to enable plugin {}: {}", id, e);
             }
@@ -151,39 +241,67 @@ impl PluginManager {
     }
     
     pub async fn enable_plugin(&self, id: &str) -> Result<(), String> {
-        let mut instance = self.plugins.get_mut(id).ok_or("Plugin not found")?;
-        
-        if instance.state == PluginState::Enabled {
-            return Ok(());
-        }
+        let (metadata, dir) = {
+            let instance = self.plugins.get(id).ok_or("Plugin not found")?;
+            if instance.state == PluginState::Enabled {
+                return Ok(());
+            }
+            (instance.metadata.clone(), instance.dir.clone())
+        };
         
-        for dep in &instance.metadata.dependencies {
+        for dep in &metadata.dependencies {
             if !dep.optional {
-                if !self.plugins.contains_key(&dep.id) {
-                    return Err(format!("Missing required dependency: {}", dep.id));
-                }
-                let dep_instance = self.plugins.get(&dep.id).unwrap();
-                if dep_instance.state != PluginState::Enabled {
+                let dep_state = self.plugins.get(&dep.id).map(|p| p.state)
+                    .ok_or_else(|| format!("Missing required dependency: {}", dep.id))?;
+                if dep_state != PluginState::Enabled {
                     return Err(format!("Dependency {} is not enabled", dep.id));
                 }
             }
         }
         
-        instance.state = PluginState::Loading;
-        info!("Enabling plugin: {}", instance.metadata.name);
+        if let Some(policy) = self.sandbox.policy() {
+            if let Err(e) = policy.admit(&metadata, self.sandbox.root()) {
+                error!("Refusing to enable plugin {}: {}", id, e);
+                self.set_state(id, PluginState::Failed, Some(e.to_string()));
+                return Err(e.to_string());
+            }
+        }
+        
+        self.set_state(id, PluginState::Loading, None);
+        info!("Enabling plugin: {}", metadata.name);
+        self.sandbox.reset(id);
+        
+        if let Err(e) = self.start_instance(id, &dir, &metadata).await {
+            self.set_state(id, PluginState::Failed, Some(e.clone()));
+            return Err(e);
+        }
         
-        instance.state = PluginState::Enabled;
-        info!("Plugin {} enabled successfully", instance.metadata.name);
+        self.set_state(id, PluginState::Enabled, None);
+        info!("Plugin {} enabled successfully", metadata.name);
         
         Ok(())
     }
     
-    pub async fn disable_plugin(&self, id: &str) -> Result<(), String> {
-        let mut instance = self.plugins.get_mut(id).ok_or("Plugin not found")?;
-        
-        if instance.state != PluginState::Enabled {
+    async fn start_instance(&self, id: &str, dir: &Path, metadata: &PluginMetadata) -> Result<(), String> {
+        let Some(loader) = &self.loader else {
             return Ok(());
-        }
+        };
+        
+        let mut plugin = loader.load(dir, metadata, self.handle(id, metadata))?;
+        plugin.on_enable().await?;
+        self.hooks.replace_owned(id, plugin.hooks());
+        self.running.insert(id.to_string(), Arc::new(Mutex::new(plugin)));
+        Ok(())
+    }
+    
+    pub async fn disable_plugin(&self, id: &str) -> Result<(), String> {
+        let name = {
+            let instance = self.plugins.get(id).ok_or("Plugin not found")?;
+            if instance.state != PluginState::Enabled {
+                return Ok(());
+            }
+            instance.metadata.name.clone()
+        };
         
         for other in self.plugins.iter() {
             if other.state == PluginState::Enabled {
@@ -195,15 +313,184 @@ impl PluginManager {
             }
         }
         
-        instance.state = PluginState::Unloading;
-        info!("Disabling plugin: {}", instance.metadata.name);
+        self.set_state(id, PluginState::Unloading, None);
+        info!("Disabling plugin: {}", name);
+        
+        if let Some((_, slot)) = self.running.remove(id) {
+            self.hooks.unregister_owned(id);
+            if let Err(e) = slot.lock().await.on_disable().await {
+                warn!("Plugin {} failed to shut down cleanly: {}", name, e);
+            }
+        }
         
-        instance.state = PluginState::Disabled;
-        info!("Plugin {} disabled", instance.metadata.name);
+        self.set_state(id, PluginState::Disabled, None);
+        info!("Plugin {} disabled", name);
         
         Ok(())
     }
     
+    /// Swaps a running plugin for the artifact now on disk, carrying its
+    /// state over. The plugin's ticks wait for the swap and its hooks are
+    /// replaced in one step; if anything fails the previous instance is
+    /// enabled again and keeps running.
+    pub async fn reload(&self, id: &str) -> Result<PluginMetadata, String> {
+        if !self.config.get().plugins.hot_reload {
+            return Err("Hot reload is disabled (plugins.hot_reload)".to_string());
+        }
+        let loader = self.loader.clone().ok_or("No plugin loader configured")?;
+        let dir = self.plugins.get(id).map(|p| p.dir.clone()).ok_or("Plugin not found")?;
+        let slot = self.running.get(id).map(|e| e.value().clone())
+            .ok_or_else(|| format!("Plugin {} is not running", id))?;
+        
+        let mut current = slot.lock().await;
+        let from_version = current.metadata().version.clone();
+        info!("Reloading plugin {} from {:?}", id, dir);
+        
+        match self.swap(loader.as_ref(), id, &dir, &mut current).await {
+            Ok(metadata) => {
+                if let Some(mut instance) = self.plugins.get_mut(id) {
+                    instance.metadata = metadata.clone();
+                }
+                info!("Plugin {} reloaded: v{} -> v{}", id, from_version, metadata.version);
+                let _ = self.events.send(PluginEvent::Reloaded {
+                    plugin_id: id.to_string(),
+                    from_version,
+                    to_version: metadata.version.clone(),
+                });
+                Ok(metadata)
+            }
+            Err(e) => {
+                warn!("Reload of plugin {} failed, keeping v{}: {}", id, from_version, e);
+                let _ = self.events.send(PluginEvent::ReloadFailed {
+                    plugin_id: id.to_string(),
+                    version: from_version.clone(),
+                    error: e.clone(),
+                });
+                Err(format!("Reload of {} failed, kept v{}: {}", id, from_version, e))
+            }
+        }
+    }
+    
+    async fn swap(
+        &self,
+        loader: &dyn PluginLoader,
+        id: &str,
+        dir: &Path,
+        current: &mut Box<dyn Plugin>,
+    ) -> Result<PluginMetadata, String> {
+        let metadata = self.load_plugin_metadata(&dir.join("plugin.toml"))?;
+        if metadata.id != id {
+            return Err(format!("{:?} now declares plugin {}", dir, metadata.id));
+        }
+        if let Some(policy) = self.sandbox.policy() {
+            policy.admit(&metadata, self.sandbox.root()).map_err(|e| e.to_string())?;
+        }
+        
+        let state = current.serialize_state()?;
+        current.on_disable().await?;
+        
+        let next = async {
+            let mut next = loader.load(dir, &metadata, self.handle(id, &metadata))?;
+            if let Some(state) = state {
+                next.restore_state(state)?;
+            }
+            next.on_enable().await?;
+            Ok::<_, String>(next)
+        }.await;
+        
+        match next {
+            Ok(next) => {
+                self.hooks.replace_owned(id, next.hooks());
+                *current = next;
+                Ok(metadata)
+            }
+            Err(e) => {
+                if let Err(rollback) = current.on_enable().await {
+                    error!("Plugin {} could not be restored after a failed reload: {}", id, rollback);
+                    self.running.remove(id);
+                    self.hooks.unregister_owned(id);
+                    self.set_state(id, PluginState::Failed, Some(rollback.clone()));
+                    return Err(format!("{}; rollback failed: {}", e, rollback));
+                }
+                Err(e)
+            }
+        }
+    }
+    
+    /// Runs `on_tick` for every running plugin and charges it, with the time
+    /// its hooks took since the last tick, against its tick budget. Throttled
+    /// plugins and their hooks sit the tick out; plugins out of strikes are
+    /// disabled.
+    pub async fn tick(&self) {
+        let tick = self.ticks.fetch_add(1, Ordering::SeqCst) + 1;
+        let hook_usage = self.hooks.take_usage();
+        let running: Vec<(String, PluginSlot)> = self.running.iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        
+        for (id, plugin) in running {
+            if self.sandbox.is_revoked(&id) {
+                self.revoke(&id).await;
+                continue;
+            }
+            let throttled = self.sandbox.is_throttled(&id, tick);
+            self.hooks.set_suspended(&id, throttled);
+            if throttled {
+                continue;
+            }
+            
+            let mut plugin = plugin.lock().await;
+            let started = std::time::Instant::now();
+            plugin.on_tick().await;
+            let elapsed = started.elapsed() + hook_usage.get(&id).copied().unwrap_or_default();
+            self.sandbox.record_tick(&id, tick, elapsed);
+            self.hooks.set_suspended(&id, self.sandbox.is_throttled(&id, tick + 1));
+        }
+    }
+    
+    async fn revoke(&self, id: &str) {
+        let strikes = self.sandbox.report(id, 0).strikes;
+        if let Err(e) = self.disable_plugin(id).await {
+            warn!("Could not disable plugin {} after sandbox violations: {}", id, e);
+            return;
+        }
+        if let Some(mut instance) = self.plugins.get_mut(id) {
+            instance.error = Some(format!("Disabled after {} sandbox violations", strikes));
+        }
+        error!("Plugin {} disabled after {} sandbox violations", id, strikes);
+    }
+    
+    fn handle(&self, id: &str, metadata: &PluginMetadata) -> PluginSandbox {
+        PluginSandbox::new(id.to_string(), metadata.capabilities.clone(), self.sandbox.clone())
+    }
+    
+    /// Handle through which a plugin reaches files, the network and the
+    /// scheduler, limited to what its manifest declares.
+    pub fn sandbox(&self, id: &str) -> Result<PluginSandbox, String> {
+        let metadata = self.plugins.get(id).map(|p| p.metadata.clone()).ok_or("Plugin not found")?;
+        Ok(self.handle(id, &metadata))
+    }
+    
+    pub fn sandbox_report(&self, id: &str) -> SandboxReport {
+        self.sandbox.report(id, self.ticks.load(Ordering::SeqCst) + 1)
+    }
+    
+    /// Recent sandbox violations, oldest first, for one plugin or all of them.
+    pub fn violations(&self, id: Option<&str>) -> Vec<PluginViolation> {
+        self.sandbox.violations(id)
+    }
+    
+    pub fn get_plugin_error(&self, id: &str) -> Option<String> {
+        self.plugins.get(id).and_then(|p| p.error.clone())
+    }
+    
+    fn set_state(&self, id: &str, state: PluginState, error: Option<String>) {
+        if let Some(mut instance) = self.plugins.get_mut(id) {
+            instance.state = state;
+            instance.error = error;
+        }
+    }
+    
     pub async fn unload_all(&self) {
         let mut ids: Vec<String> = self.plugins.iter()
             .map(|e| (e.load_order, e.key().clone()))
@@ -242,3 +529,201 @@ impl PluginManager {
         self.plugins.get(id).map(|p| p.state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::ServerConfig;
+    use crate::core::game::{GameHookEvent, HookPriority, HookResult};
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    /// Counts its own ticks and carries the count across reloads.
+    struct CounterPlugin {
+        metadata: PluginMetadata,
+        ticks: u64,
+        seen: Arc<AtomicU64>,
+    }
+
+    #[async_trait]
+    impl Plugin for CounterPlugin {
+        fn metadata(&self) -> &PluginMetadata {
+            &self.metadata
+        }
+
+        async fn on_enable(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        async fn on_disable(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        async fn on_tick(&mut self) {
+            self.ticks += 1;
+            self.seen.store(self.ticks, Ordering::SeqCst);
+        }
+
+        async fn on_reload(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn hooks(&self) -> Vec<(String, Arc<dyn GameHook>)> {
+            let hook = VersionHook(format!("counter-v{}", self.metadata.version));
+            vec![("server_tick".to_string(), Arc::new(hook) as Arc<dyn GameHook>)]
+        }
+
+        fn serialize_state(&self) -> Result<Option<serde_json::Value>, String> {
+            Ok(Some(serde_json::json!({ "ticks": self.ticks })))
+        }
+
+        fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+            self.ticks = state["ticks"].as_u64().ok_or("state has no tick count")?;
+            Ok(())
+        }
+    }
+
+    struct VersionHook(String);
+
+    #[async_trait]
+    impl GameHook for VersionHook {
+        fn name(&self) -> &str {
+            &self.0
+        }
+
+        fn priority(&self) -> HookPriority {
+            HookPriority::Normal
+        }
+
+        fn handles(&self, _event: &GameHookEvent) -> bool {
+            true
+        }
+
+        async fn execute(&self, _event: &GameHookEvent) -> HookResult {
+            HookResult::Continue
+        }
+    }
+
+    #[derive(Default)]
+    struct CounterLoader {
+        seen: Arc<AtomicU64>,
+        broken: AtomicBool,
+    }
+
+    impl PluginLoader for CounterLoader {
+        fn load(&self, _dir: &Path, metadata: &PluginMetadata, _sandbox: PluginSandbox) -> Result<Box<dyn Plugin>, String> {
+            if self.broken.load(Ordering::SeqCst) {
+                return Err("artifact is corrupt".to_string());
+            }
+            Ok(Box::new(CounterPlugin {
+                metadata: metadata.clone(),
+                ticks: 0,
+                seen: self.seen.clone(),
+            }))
+        }
+    }
+
+    fn write_plugin(root: &Path, version: &str) {
+        let dir = root.join("plugins").join("counter");
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = format!(
+            "id = \"counter\"\nname = \"Counter\"\nversion = \"{}\"\nauthor = \"test\"\n\
+             description = \"Counts ticks\"\napi_version = \"1.0.0\"\ndependencies = []\n",
+            version
+        );
+        std::fs::write(dir.join("plugin.toml"), manifest).unwrap();
+    }
+
+    async fn manager(loader: Arc<CounterLoader>) -> (PluginManager, PathBuf) {
+        let root = std::env::temp_dir().join(format!("pond-plugins-{}", uuid::Uuid::new_v4()));
+        write_plugin(&root, "1.0.0");
+        let mut config = ServerConfig::default();
+        config.plugins.directory = root.join("plugins").to_string_lossy().into_owned();
+        let config_path = root.join("pond.toml");
+        std::fs::write(&config_path, toml::to_string(&config).unwrap()).unwrap();
+
+        let config = Arc::new(ConfigManager::new(config_path.to_str().unwrap()).unwrap());
+        let manager = PluginManager::new(config).with_loader(loader);
+        manager.load_all().await.unwrap();
+        assert_eq!(manager.get_plugin_state("counter"), Some(PluginState::Enabled));
+        (manager, root)
+    }
+
+    #[tokio::test]
+    async fn reload_carries_ticks_over_to_the_new_version() {
+        let loader = Arc::new(CounterLoader::default());
+        let (manager, root) = manager(loader.clone()).await;
+        let mut events = manager.subscribe();
+
+        for _ in 0..3 {
+            manager.tick().await;
+        }
+        write_plugin(&root, "1.1.0");
+        let reloaded = manager.reload("counter").await.unwrap();
+        assert_eq!(reloaded.version, "1.1.0");
+        for _ in 0..2 {
+            manager.tick().await;
+        }
+
+        assert_eq!(loader.seen.load(Ordering::SeqCst), 5);
+        assert_eq!(manager.hooks().owned_hooks("counter"), vec!["counter-v1.1.0"]);
+        assert_eq!(manager.list_plugins()[0].version, "1.1.0");
+        assert_eq!(
+            events.try_recv().unwrap(),
+            PluginEvent::Reloaded {
+                plugin_id: "counter".to_string(),
+                from_version: "1.0.0".to_string(),
+                to_version: "1.1.0".to_string(),
+            }
+        );
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[tokio::test]
+    async fn failed_reload_rolls_back_to_the_running_version() {
+        let loader = Arc::new(CounterLoader::default());
+        let (manager, root) = manager(loader.clone()).await;
+        let mut events = manager.subscribe();
+
+        manager.tick().await;
+        write_plugin(&root, "2.0.0");
+        loader.broken.store(true, Ordering::SeqCst);
+        assert!(manager.reload("counter").await.is_err());
+        manager.tick().await;
+
+        assert_eq!(loader.seen.load(Ordering::SeqCst), 2);
+        assert_eq!(manager.get_plugin_state("counter"), Some(PluginState::Enabled));
+        assert_eq!(manager.list_plugins()[0].version, "1.0.0");
+        assert_eq!(manager.hooks().owned_hooks("counter"), vec!["counter-v1.0.0"]);
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            PluginEvent::ReloadFailed { version, .. } if version == "1.0.0"
+        ));
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn ticks_during_reloads_see_one_whole_plugin() {
+        let loader = Arc::new(CounterLoader::default());
+        let (manager, root) = manager(loader.clone()).await;
+        let manager = Arc::new(manager);
+
+        let reloads = {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                for _ in 0..20 {
+                    manager.reload("counter").await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        for _ in 0..100 {
+            manager.tick().await;
+            assert_eq!(manager.hooks().owned_hooks("counter").len(), 1);
+            tokio::task::yield_now().await;
+        }
+        reloads.await.unwrap();
+
+        assert_eq!(loader.seen.load(Ordering::SeqCst), 100);
+        std::fs::remove_dir_all(root).ok();
+    }
+}
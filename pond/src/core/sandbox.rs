@@ -0,0 +1,732 @@
+//! Plugin sandboxing: capabilities a plugin declares in its manifest, the
+//! operator's limits on them, and per-tick execution budgets.
+
+use crate::core::config::ConfigManager;
+use crate::core::plugins::{PluginEvent, PluginMetadata};
+use crate::core::scheduler::{Scheduler, Task, TaskPriority};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Violations remembered per plugin.
+pub const VIOLATION_HISTORY: usize = 50;
+
+/// What a plugin declares it needs in the `[capabilities]` table of
+/// plugin.toml. Anything not listed is refused at runtime.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginCapabilities {
+    /// Files and directories the plugin may touch through its VFS handle.
+    pub filesystem: Vec<String>,
+    /// Hosts the plugin may connect to, `*.example.com` style wildcards allowed.
+    pub network: Vec<String>,
+    /// Highest priority the plugin may schedule tasks at; `Normal` if unset.
+    pub scheduler_priority: Option<TaskPriority>,
+}
+
+impl PluginCapabilities {
+    pub fn scheduler_priority(&self) -> TaskPriority {
+        self.scheduler_priority.unwrap_or(TaskPriority::Normal)
+    }
+}
+
+/// The operator's limits on plugin manifests and plugin tick time,
+/// `[plugins.sandbox]` in pond.toml.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    /// Violations before a plugin is disabled; 0 never disables.
+    pub max_strikes: u32,
+    /// Roots that declared filesystem paths must sit under.
+    pub allowed_paths: Vec<String>,
+    pub allowed_hosts: Vec<String>,
+    /// Highest task priority a manifest may ask for.
+    pub max_scheduler_priority: TaskPriority,
+    /// Time one plugin may spend per tick, its `on_tick` and hooks together.
+    pub tick_budget_ms: f64,
+    /// Consecutive over-budget ticks before the plugin is throttled.
+    pub overruns_before_throttle: u32,
+    /// Ticks a throttled plugin sits out.
+    pub throttle_ticks: u64,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            max_strikes: 3,
+            allowed_paths: vec!["plugins".to_string(), "data".to_string()],
+            allowed_hosts: vec!["*".to_string()],
+            max_scheduler_priority: TaskPriority::High,
+            tick_budget_ms: 5.0,
+            overruns_before_throttle: 3,
+            throttle_ticks: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Filesystem,
+    Network,
+    Scheduler,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Filesystem => "filesystem",
+            Capability::Network => "network",
+            Capability::Scheduler => "scheduler",
+        }
+    }
+
+    fn policy_key(&self) -> &'static str {
+        match self {
+            Capability::Filesystem => "allowed_paths",
+            Capability::Network => "allowed_hosts",
+            Capability::Scheduler => "max_scheduler_priority",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SandboxError {
+    /// The manifest asks for something the server config doesn't allow.
+    NotPermitted { plugin: String, capability: Capability, requested: String, allowed: String },
+    /// The plugin tried something its manifest doesn't declare.
+    Denied { plugin: String, capability: Capability, target: String },
+    PluginDisabled(String),
+    Io(String),
+}
+
+impl std::fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SandboxError::NotPermitted { plugin, capability, requested, allowed } => write!(
+                f,
+                "plugin {} requests {} capability '{}' which plugins.sandbox.{} does not permit (allowed: {})",
+                plugin,
+                capability.as_str(),
+                requested,
+                capability.policy_key(),
+                allowed
+            ),
+            SandboxError::Denied { plugin, capability, target } => write!(
+                f,
+                "plugin {} did not declare {} access to '{}'",
+                plugin,
+                capability.as_str(),
+                target
+            ),
+            SandboxError::PluginDisabled(plugin) => write!(f, "plugin {} is not enabled", plugin),
+            SandboxError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SandboxError {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginViolation {
+    pub plugin_id: String,
+    pub capability: Capability,
+    pub target: String,
+    pub strike: u32,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A plugin's standing with the sandbox, for listings.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SandboxReport {
+    pub strikes: u32,
+    pub throttles: u32,
+    pub throttled: bool,
+    pub last_tick_ms: f64,
+}
+
+/// `*` matches anything, `*.suffix` matches the suffix and its subdomains,
+/// `prefix*` matches by prefix, anything else must match exactly.
+pub fn matches_pattern(pattern: &str, value: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    if let Some(suffix) = pattern.strip_prefix("*.") {
+        return value == suffix || value.ends_with(&format!(".{}", suffix));
+    }
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        return value.starts_with(prefix);
+    }
+    pattern == value
+}
+
+/// Makes `path` absolute against `root` and folds `.`/`..` without touching
+/// the filesystem, so paths that don't exist yet can still be checked.
+pub fn normalize_path(root: &Path, path: &Path) -> PathBuf {
+    let joined = if path.is_absolute() { path.to_path_buf() } else { root.join(path) };
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Resolves symlinks for the longest existing ancestor of a normalized path,
+/// so a link inside an allowed directory can't point out of it.
+fn resolve_links(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest.iter().rev().fold(canonical, |acc, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+fn within_any(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| path.starts_with(root))
+}
+
+impl SandboxPolicy {
+    /// Checks a manifest against the policy before the plugin is enabled.
+    pub fn admit(&self, metadata: &PluginMetadata, root: &Path) -> Result<(), SandboxError> {
+        let caps = &metadata.capabilities;
+        let not_permitted = |capability, requested: String, allowed: String| SandboxError::NotPermitted {
+            plugin: metadata.id.clone(),
+            capability,
+            requested,
+            allowed,
+        };
+
+        let allowed_roots: Vec<PathBuf> = self.allowed_paths.iter()
+            .map(|p| resolve_links(&normalize_path(root, Path::new(p))))
+            .collect();
+        for path in &caps.filesystem {
+            let resolved = resolve_links(&normalize_path(root, Path::new(path)));
+            if !within_any(&resolved, &allowed_roots) {
+                return Err(not_permitted(Capability::Filesystem, path.clone(), format!("[{}]", self.allowed_paths.join(", "))));
+            }
+        }
+
+        for host in &caps.network {
+            // A wildcard request is only admitted by an equally broad allowance.
+            let ok = self.allowed_hosts.iter()
+                .any(|a| a == "*" || a == host || (!host.contains('*') && matches_pattern(a, host)));
+            if !ok {
+                return Err(not_permitted(Capability::Network, host.clone(), format!("[{}]", self.allowed_hosts.join(", "))));
+            }
+        }
+
+        if caps.scheduler_priority() < self.max_scheduler_priority {
+            return Err(not_permitted(
+                Capability::Scheduler,
+                format!("{:?}", caps.scheduler_priority()),
+                format!("{:?} and below", self.max_scheduler_priority),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct SandboxRecord {
+    strikes: u32,
+    revoked: bool,
+    overruns: u32,
+    throttles: u32,
+    throttled_until: u64,
+    last_tick: Duration,
+    recent: VecDeque<PluginViolation>,
+}
+
+/// Strikes and tick budgets for every plugin, shared by the plugin manager
+/// and the handles it gives out.
+pub struct Sandbox {
+    config: Arc<ConfigManager>,
+    root: PathBuf,
+    events: broadcast::Sender<PluginEvent>,
+    records: DashMap<String, SandboxRecord>,
+}
+
+impl Sandbox {
+    pub(crate) fn new(config: Arc<ConfigManager>, root: PathBuf, events: broadcast::Sender<PluginEvent>) -> Self {
+        Self {
+            config,
+            root,
+            events,
+            records: DashMap::new(),
+        }
+    }
+
+    /// Directory relative sandbox paths resolve against.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The policy in force, or `None` when `plugins.sandbox_enabled` is off.
+    pub fn policy(&self) -> Option<SandboxPolicy> {
+        let plugins = self.config.get().plugins;
+        plugins.sandbox_enabled.then_some(plugins.sandbox)
+    }
+
+    /// Clears strikes and throttling for a plugin that is being enabled.
+    pub(crate) fn reset(&self, id: &str) {
+        if let Some(mut record) = self.records.get_mut(id) {
+            record.strikes = 0;
+            record.revoked = false;
+            record.overruns = 0;
+            record.throttled_until = 0;
+        }
+    }
+
+    /// Whether the plugin has used up its strikes.
+    pub fn is_revoked(&self, id: &str) -> bool {
+        self.records.get(id).is_some_and(|r| r.revoked)
+    }
+
+    pub fn is_throttled(&self, id: &str, tick: u64) -> bool {
+        self.records.get(id).is_some_and(|r| r.throttled_until > tick)
+    }
+
+    /// Counts a strike, announces it and revokes the plugin once it reaches
+    /// `plugins.sandbox.max_strikes`.
+    pub(crate) fn record_violation(&self, id: &str, capability: Capability, target: &str) -> u32 {
+        let max_strikes = self.config.get().plugins.sandbox.max_strikes;
+        let strike = {
+            let mut record = self.records.entry(id.to_string()).or_default();
+            record.strikes += 1;
+            let strike = record.strikes;
+            record.recent.push_back(PluginViolation {
+                plugin_id: id.to_string(),
+                capability,
+                target: target.to_string(),
+                strike,
+                at: chrono::Utc::now(),
+            });
+            if record.recent.len() > VIOLATION_HISTORY {
+                record.recent.pop_front();
+            }
+            if max_strikes > 0 && record.strikes >= max_strikes && !record.revoked {
+                record.revoked = true;
+                error!("Plugin {} revoked after {} sandbox violations", id, record.strikes);
+            }
+            record.strikes
+        };
+        warn!("Plugin {} sandbox violation #{}: {} access to '{}'", id, strike, capability.as_str(), target);
+
+        let _ = self.events.send(PluginEvent::SandboxViolation {
+            plugin_id: id.to_string(),
+            capability,
+            target: target.to_string(),
+            strikes: strike,
+        });
+        strike
+    }
+
+    /// Charges a plugin for one tick. Running over budget
+    /// `overruns_before_throttle` ticks in a row benches it for
+    /// `throttle_ticks`.
+    pub(crate) fn record_tick(&self, id: &str, tick: u64, elapsed: Duration) {
+        let Some(policy) = self.policy() else {
+            return;
+        };
+        let budget = Duration::from_secs_f64(policy.tick_budget_ms.max(0.0) / 1000.0);
+
+        let throttles = {
+            let mut record = self.records.entry(id.to_string()).or_default();
+            record.last_tick = elapsed;
+            if elapsed <= budget {
+                record.overruns = 0;
+                return;
+            }
+            record.overruns += 1;
+            if record.overruns < policy.overruns_before_throttle.max(1) {
+                return;
+            }
+            record.overruns = 0;
+            record.throttles += 1;
+            record.throttled_until = tick + policy.throttle_ticks;
+            record.throttles
+        };
+
+        let tick_ms = elapsed.as_secs_f64() * 1000.0;
+        warn!(
+            "Plugin {} over its {:.1}ms tick budget ({:.1}ms), throttled for {} ticks",
+            id, policy.tick_budget_ms, tick_ms, policy.throttle_ticks
+        );
+        let _ = self.events.send(PluginEvent::Throttled {
+            plugin_id: id.to_string(),
+            tick_ms,
+            budget_ms: policy.tick_budget_ms,
+            ticks: policy.throttle_ticks,
+            throttles,
+        });
+    }
+
+    pub fn report(&self, id: &str, tick: u64) -> SandboxReport {
+        self.records.get(id)
+            .map(|r| SandboxReport {
+                strikes: r.strikes,
+                throttles: r.throttles,
+                throttled: r.throttled_until > tick,
+                last_tick_ms: r.last_tick.as_secs_f64() * 1000.0,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Recent violations, oldest first, for one plugin or all of them.
+    pub fn violations(&self, id: Option<&str>) -> Vec<PluginViolation> {
+        let mut violations: Vec<PluginViolation> = self.records.iter()
+            .filter(|e| id.is_none_or(|id| e.key() == id))
+            .flat_map(|e| e.recent.iter().cloned().collect::<Vec<_>>())
+            .collect();
+        violations.sort_by_key(|v| v.at);
+        violations
+    }
+}
+
+/// A plugin's handle on files, the network and the scheduler, checked
+/// against its manifest. Every undeclared access is refused and counts as
+/// a strike.
+#[derive(Clone)]
+pub struct PluginSandbox {
+    plugin_id: String,
+    capabilities: PluginCapabilities,
+    sandbox: Arc<Sandbox>,
+}
+
+impl PluginSandbox {
+    pub(crate) fn new(plugin_id: String, capabilities: PluginCapabilities, sandbox: Arc<Sandbox>) -> Self {
+        Self { plugin_id, capabilities, sandbox }
+    }
+
+    pub fn plugin_id(&self) -> &str {
+        &self.plugin_id
+    }
+
+    pub fn vfs(&self) -> PluginVfs {
+        let root = self.sandbox.root().to_path_buf();
+        let allowed = self.capabilities.filesystem.iter()
+            .map(|p| resolve_links(&normalize_path(&root, Path::new(p))))
+            .collect();
+        PluginVfs { sandbox: self.clone(), root, allowed }
+    }
+
+    fn enforced(&self) -> bool {
+        self.sandbox.policy().is_some()
+    }
+
+    fn ensure_enabled(&self) -> Result<(), SandboxError> {
+        if self.sandbox.is_revoked(&self.plugin_id) {
+            Err(SandboxError::PluginDisabled(self.plugin_id.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn deny(&self, capability: Capability, target: &str) -> SandboxError {
+        self.sandbox.record_violation(&self.plugin_id, capability, target);
+        SandboxError::Denied {
+            plugin: self.plugin_id.clone(),
+            capability,
+            target: target.to_string(),
+        }
+    }
+
+    pub fn check_host(&self, host: &str) -> Result<(), SandboxError> {
+        self.ensure_enabled()?;
+        if !self.enforced() || self.capabilities.network.iter().any(|p| matches_pattern(p, host)) {
+            return Ok(());
+        }
+        Err(self.deny(Capability::Network, host))
+    }
+
+    /// Opens a TCP connection to a declared host.
+    pub async fn connect(&self, host: &str, port: u16) -> Result<tokio::net::TcpStream, SandboxError> {
+        self.check_host(host)?;
+        tokio::net::TcpStream::connect((host, port)).await
+            .map_err(|e| SandboxError::Io(format!("{}:{}: {}", host, port, e)))
+    }
+
+    /// Registers a task, refusing priorities above the declared one.
+    pub fn register_task(&self, scheduler: &Scheduler, task: Task) -> Result<Uuid, SandboxError> {
+        self.ensure_enabled()?;
+        if self.enforced() && task.priority < self.capabilities.scheduler_priority() {
+            return Err(self.deny(Capability::Scheduler, &format!("{} at {:?}", task.name, task.priority)));
+        }
+        Ok(scheduler.register_task(task))
+    }
+}
+
+/// File access for one plugin, limited to the paths in its manifest.
+/// Relative paths resolve against the sandbox root.
+pub struct PluginVfs {
+    sandbox: PluginSandbox,
+    root: PathBuf,
+    allowed: Vec<PathBuf>,
+}
+
+impl PluginVfs {
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf, SandboxError> {
+        self.sandbox.ensure_enabled()?;
+        let resolved = resolve_links(&normalize_path(&self.root, path.as_ref()));
+        if !self.sandbox.enforced() || within_any(&resolved, &self.allowed) {
+            return Ok(resolved);
+        }
+        Err(self.sandbox.deny(Capability::Filesystem, &resolved.to_string_lossy()))
+    }
+
+    pub async fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, SandboxError> {
+        let path = self.resolve(path)?;
+        tokio::fs::read(&path).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub async fn read_to_string(&self, path: impl AsRef<Path>) -> Result<String, SandboxError> {
+        let path = self.resolve(path)?;
+        tokio::fs::read_to_string(&path).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub async fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), SandboxError> {
+        let path = self.resolve(path)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await
+                .map_err(|e| SandboxError::Io(format!("{}: {}", parent.display(), e)))?;
+        }
+        tokio::fs::write(&path, contents).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub async fn remove(&self, path: impl AsRef<Path>) -> Result<(), SandboxError> {
+        let path = self.resolve(path)?;
+        tokio::fs::remove_file(&path).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::ServerConfig;
+    use crate::core::game::{GameHook, GameHookEvent, HookPriority, HookResult};
+    use crate::core::plugins::{Plugin, PluginLoader, PluginManager, PluginState};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Sleeps through every tick and every hook call.
+    struct SlowPlugin {
+        metadata: PluginMetadata,
+        ticks: Arc<AtomicU64>,
+        hook_calls: Arc<AtomicU64>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl Plugin for SlowPlugin {
+        fn metadata(&self) -> &PluginMetadata {
+            &self.metadata
+        }
+
+        async fn on_enable(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        async fn on_disable(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        async fn on_tick(&mut self) {
+            self.ticks.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+        }
+
+        async fn on_reload(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn hooks(&self) -> Vec<(String, Arc<dyn GameHook>)> {
+            vec![("world_save".to_string(), Arc::new(CountingHook(self.hook_calls.clone())) as Arc<dyn GameHook>)]
+        }
+    }
+
+    struct CountingHook(Arc<AtomicU64>);
+
+    #[async_trait]
+    impl GameHook for CountingHook {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn priority(&self) -> HookPriority {
+            HookPriority::Normal
+        }
+
+        fn handles(&self, _event: &GameHookEvent) -> bool {
+            true
+        }
+
+        async fn execute(&self, _event: &GameHookEvent) -> HookResult {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            HookResult::Continue
+        }
+    }
+
+    #[derive(Default)]
+    struct Loader {
+        ticks: Arc<AtomicU64>,
+        hook_calls: Arc<AtomicU64>,
+        delay: Duration,
+        handles: parking_lot::Mutex<Vec<PluginSandbox>>,
+    }
+
+    impl PluginLoader for Loader {
+        fn load(&self, _dir: &Path, metadata: &PluginMetadata, sandbox: PluginSandbox) -> Result<Box<dyn Plugin>, String> {
+            self.handles.lock().push(sandbox);
+            Ok(Box::new(SlowPlugin {
+                metadata: metadata.clone(),
+                ticks: self.ticks.clone(),
+                hook_calls: self.hook_calls.clone(),
+                delay: self.delay,
+            }))
+        }
+    }
+
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("pond-sandbox-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    fn write_plugin(root: &Path, id: &str, capabilities: &str) {
+        let dir = root.join("plugins").join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("plugin.toml"), format!(
+            "id = \"{id}\"\nname = \"{id}\"\nversion = \"1.0.0\"\nauthor = \"test\"\ndescription = \"\"\n\
+             dependencies = []\napi_version = \"1\"\n\n[capabilities]\n{capabilities}\n"
+        )).unwrap();
+    }
+
+    async fn manager(root: &Path, policy: SandboxPolicy, loader: Arc<Loader>) -> PluginManager {
+        let mut config = ServerConfig::default();
+        config.plugins.directory = root.join("plugins").to_string_lossy().into_owned();
+        config.plugins.sandbox = policy;
+        let path = root.join("pond.toml");
+        std::fs::write(&path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        let config = Arc::new(ConfigManager::new(path.to_str().unwrap()).unwrap());
+        let manager = PluginManager::new(config).with_sandbox_root(root).with_loader(loader);
+        manager.load_all().await.unwrap();
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_plugin_over_its_tick_budget_is_throttled() {
+        let root = temp_root();
+        write_plugin(&root, "slow", "");
+        let policy = SandboxPolicy {
+            tick_budget_ms: 1.0,
+            overruns_before_throttle: 2,
+            throttle_ticks: 5,
+            ..SandboxPolicy::default()
+        };
+        let loader = Arc::new(Loader { delay: Duration::from_millis(5), ..Loader::default() });
+        let manager = manager(&root, policy, loader.clone()).await;
+        let mut events = manager.subscribe();
+
+        manager.tick().await;
+        assert!(events.try_recv().is_err());
+        manager.tick().await;
+        match events.try_recv().unwrap() {
+            PluginEvent::Throttled { plugin_id, ticks, throttles, tick_ms, .. } => {
+                assert_eq!(plugin_id, "slow");
+                assert_eq!(ticks, 5);
+                assert_eq!(throttles, 1);
+                assert!(tick_ms >= 5.0, "{}", tick_ms);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(manager.sandbox_report("slow").throttled);
+
+        // Benched: neither its tick nor its hooks run.
+        manager.hooks().dispatch(&GameHookEvent::WorldSave).await;
+        for _ in 0..4 {
+            manager.tick().await;
+        }
+        assert_eq!(loader.ticks.load(Ordering::SeqCst), 2);
+        assert_eq!(loader.hook_calls.load(Ordering::SeqCst), 0);
+
+        manager.tick().await;
+        assert_eq!(loader.ticks.load(Ordering::SeqCst), 3);
+        manager.hooks().dispatch(&GameHookEvent::WorldSave).await;
+        assert_eq!(loader.hook_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(manager.sandbox_report("slow").throttles, 1);
+        assert_eq!(manager.get_plugin_state("slow"), Some(PluginState::Enabled));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_unapproved_filesystem_access_is_denied() {
+        let root = temp_root();
+        let data_dir = root.join("plugins/toy/data");
+        write_plugin(&root, "toy", &format!("filesystem = [\"{}\"]", data_dir.display()));
+        write_plugin(&root, "greedy", "filesystem = [\"/etc\"]");
+        std::fs::write(root.join("secret.txt"), "hunter2").unwrap();
+
+        let loader = Arc::new(Loader::default());
+        let manager = manager(&root, SandboxPolicy::default(), loader.clone()).await;
+        let mut events = manager.subscribe();
+
+        assert_eq!(manager.get_plugin_state("greedy"), Some(PluginState::Failed));
+        let error = manager.get_plugin_error("greedy").unwrap();
+        assert!(error.contains("filesystem capability '/etc'"), "{}", error);
+        assert_eq!(manager.get_plugin_state("toy"), Some(PluginState::Enabled));
+
+        let sandbox = loader.handles.lock().pop().unwrap();
+        assert_eq!(sandbox.plugin_id(), "toy");
+        let vfs = sandbox.vfs();
+        vfs.write(data_dir.join("state.json"), "{}").await.unwrap();
+        assert_eq!(vfs.read_to_string("plugins/toy/data/state.json").await.unwrap(), "{}");
+
+        let err = vfs.read(root.join("secret.txt")).await.unwrap_err();
+        assert!(matches!(err, SandboxError::Denied { capability: Capability::Filesystem, .. }));
+        assert!(vfs.read("plugins/toy/data/../../../secret.txt").await.is_err());
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            PluginEvent::SandboxViolation { capability: Capability::Filesystem, strikes: 1, .. }
+        ));
+
+        let task = Task::new("urgent", TaskPriority::Critical);
+        let scheduler = Scheduler::new(Arc::new(crate::core::performance::PerformanceMonitor::new(
+            Arc::new(crate::core::telemetry::TelemetryCollector::new()),
+        )));
+        let err = sandbox.register_task(&scheduler, task).unwrap_err();
+        assert!(matches!(err, SandboxError::Denied { capability: Capability::Scheduler, .. }));
+        assert_eq!(scheduler.task_count(), 0);
+
+        // Out of strikes: refused at once, disabled on the next tick.
+        assert_eq!(vfs.read(data_dir.join("state.json")).await.unwrap_err(), SandboxError::PluginDisabled("toy".into()));
+        manager.tick().await;
+        assert_eq!(manager.get_plugin_state("toy"), Some(PluginState::Disabled));
+        assert!(manager.get_plugin_error("toy").unwrap().contains("3 sandbox violations"));
+        assert_eq!(manager.violations(Some("toy")).len(), 3);
+        assert_eq!(loader.ticks.load(Ordering::SeqCst), 0);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,19 +1,15 @@
 use crate::core::performance::PerformanceMonitor;
 use dashmap::DashMap;
+use std::future::Future;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::{debug, warn};
 use uuid::Uuid;
+use yellow_tale_core::schedule::JobQueue;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub enum TaskPriority {
-    Critical = 0,
-    High = 1,
-    Normal = 2,
-    Low = 3,
-    Background = 4,
-}
+pub use yellow_tale_core::schedule::{Clock, Schedule, ScheduledTaskInfo, SystemClock, TaskHandle, TaskPriority};
 
 #[derive(Debug, Clone)]
 pub struct Task {
@@ -52,6 +48,7 @@ impl Task {
 
 pub struct Scheduler {
     tasks: DashMap<Uuid, Task>,
+    jobs: JobQueue,
     current_tick: AtomicU64,
     running: AtomicBool,
     performance: Arc<PerformanceMonitor>,
@@ -63,6 +60,7 @@ impl Scheduler {
     pub fn new(performance: Arc<PerformanceMonitor>) -> Self {
         Self {
             tasks: DashMap::new(),
+            jobs: JobQueue::new(),
             current_tick: AtomicU64::new(0),
             running: AtomicBool::new(false),
             performance,
@@ -71,6 +69,11 @@ impl Scheduler {
         }
     }
     
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.jobs = self.jobs.with_clock(clock);
+        self
+    }
+    
     pub async fn start(&self) {
         self.running.store(true, Ordering::SeqCst);
         debug!("Scheduler started");
@@ -117,10 +120,63 @@ impl Scheduler {
             self.performance.record_task_duration(&task.name, task_duration).await;
         }
         
+        self.run_due().await;
+        
         let total_ms = start.elapsed().as_secs_f64() * 1000.0;
         self.performance.record_tick_duration(total_ms).await;
     }
     
+    /// Runs `job` every `interval`, the first time one interval from now.
+    pub fn schedule_repeating<F, Fut>(&self, name: impl Into<String>, interval: Duration, priority: TaskPriority, job: F) -> TaskHandle
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.jobs.schedule_repeating(name, interval, priority, job)
+    }
+    
+    /// Runs `job` whenever the cron expression `expr` matches; see
+    /// `CronSchedule` for the supported subset.
+    pub fn schedule_cron<F, Fut>(&self, name: impl Into<String>, expr: &str, priority: TaskPriority, job: F) -> Result<TaskHandle, String>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.jobs.schedule_cron(name, expr, priority, job)
+    }
+    
+    /// Runs every scheduled job that is due; see `JobQueue::run_due`.
+    /// Called from `tick`; servers that don't tick the scheduler use
+    /// `spawn_timer`.
+    pub async fn run_due(&self) -> usize {
+        self.jobs.run_due().await
+    }
+    
+    /// Calls `run_due` once a second while the scheduler is running.
+    pub fn spawn_timer(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let scheduler = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                if !scheduler.running.load(Ordering::SeqCst) {
+                    break;
+                }
+                scheduler.run_due().await;
+            }
+        })
+    }
+    
+    /// Scheduled jobs by next run time.
+    pub fn scheduled_tasks(&self) -> Vec<ScheduledTaskInfo> {
+        self.jobs.scheduled_tasks()
+    }
+    
+    /// Cancels a scheduled job by id.
+    pub fn cancel(&self, id: Uuid) -> bool {
+        self.jobs.cancel(id)
+    }
+    
     pub fn register_task(&self, task: Task) -> Uuid {
         let id = task.id;
         self.tasks.insert(id, task);
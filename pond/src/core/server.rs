@@ -5,7 +5,7 @@ use crate::core::{
     assets::AssetRegistry,
     config::ConfigManager,
     telemetry::TelemetryCollector,
-    integration::LauncherBridge,
+    integration::{discovery::DiscoveryEndpoint, LauncherBridge},
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -29,6 +29,7 @@ pub struct Server {
     assets: Arc<AssetRegistry>,
     telemetry: Arc<TelemetryCollector>,
     launcher_bridge: Arc<LauncherBridge>,
+    discovery: parking_lot::Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 impl Server {
@@ -42,6 +43,7 @@ impl Server {
         let assets = Arc::new(AssetRegistry::new());
         let plugins = Arc::new(PluginManager::new(config.clone()));
         let launcher_bridge = Arc::new(LauncherBridge::new(assets.clone()));
+        launcher_bridge.set_max_players(config.get().server.max_players);
         
         Ok(Self {
             state: Arc::new(RwLock::new(ServerState::Stopped)),
@@ -52,6 +54,7 @@ impl Server {
             assets,
             telemetry,
             launcher_bridge,
+            discovery: parking_lot::Mutex::new(None),
         })
     }
     
@@ -76,6 +79,15 @@ impl Server {
         self.performance.start_monitoring().await;
         self.launcher_bridge.start().await;
         
+        let integration = self.config.get().integration;
+        if integration.enabled {
+            let addr = std::net::SocketAddr::from(([0, 0, 0, 0], integration.launcher_api_port));
+            match DiscoveryEndpoint::bind(addr, self.launcher_bridge.clone()).await {
+                Ok(endpoint) => *self.discovery.lock() = Some(tokio::spawn(endpoint.serve())),
+                Err(e) => warn!("Capability discovery unavailable on {}: {}", addr, e),
+            }
+        }
+        
         *self.state.write().await = ServerState::Running;
         info!("Pond server is now running");
         
@@ -94,6 +106,7 @@ impl Server {
             }
             
             self.scheduler.tick().await;
+            self.plugins.tick().await;
             self.telemetry.record_tick().await;
         }
         
@@ -104,6 +117,9 @@ impl Server {
         info!("Shutting down Pond server...");
         *self.state.write().await = ServerState::Stopping;
         
+        if let Some(discovery) = self.discovery.lock().take() {
+            discovery.abort();
+        }
         self.launcher_bridge.stop().await;
         self.performance.stop_monitoring().await;
         self.scheduler.stop().await;
@@ -1,16 +1,22 @@
 pub mod core;
 
-pub use core::game::{ServerAdapter, GameHook, HookPriority, WorldProvider};
+pub use core::game::{ServerAdapter, GameHook, HookPriority, HookRegistry, WorldProvider};
 pub use core::game::adapter::HytaleServerAdapter;
 pub use core::server::Server;
-pub use core::plugins::{Plugin, PluginManager, PluginMetadata};
-pub use core::scheduler::{Scheduler, Task, TaskPriority};
+pub use core::plugins::{Plugin, PluginEvent, PluginLoader, PluginManager, PluginMetadata};
+pub use core::console::AdminConsole;
+pub use core::sandbox::{Capability, PluginCapabilities, PluginSandbox, PluginVfs, SandboxError, SandboxPolicy};
+pub use core::scheduler::{Clock, Schedule, ScheduledTaskInfo, Scheduler, Task, TaskHandle, TaskPriority};
+pub use core::cron::CronSchedule;
 pub use core::performance::PerformanceMonitor;
-pub use core::assets::{AssetRegistry, Cosmetic, CosmeticScope};
+pub use core::assets::{AppliedCosmetics, AssetRegistry, Cosmetic, CosmeticScope};
 pub use core::config::ConfigManager;
 pub use core::telemetry::TelemetryCollector;
 pub use core::integration::{
     LauncherBridge, ServerCapabilities, ConnectivityFeatures, 
     SyncCapabilities, PlayerActivity, PlayerStatus, QueueEntry,
-    AssetPreloadManifest, NetworkOptimizationHints,
+    AssetPreloadManifest, PreloadAsset, PreloadPriority, NetworkOptimizationHints,
 };
+pub use core::integration::discovery::{ClientCapabilities, DiscoveryEndpoint, DiscoveryResponse};
+pub use core::integration::ownership::{CentralApi, CentralApiError, OwnershipVerifier};
+pub use core::integration::queue::{JoinQueue, JoinQueueRequest, PriorityVerifier, QueueState, QueueStatus};
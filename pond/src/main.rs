@@ -1,4 +1,4 @@
-use pond::Server;
+use pond::{AdminConsole, Server};
 use tracing::info;
 
 #[tokio::main]
@@ -9,6 +9,10 @@ async fn main() {
     
     let mut server = Server::new("pond.toml").await.expect("Failed to initialize server");
     
+    let console = AdminConsole::new(server.plugins().clone())
+        .with_scheduler(server.scheduler().clone());
+    tokio::spawn(console.run(tokio::io::BufReader::new(tokio::io::stdin())));
+    
     if let Err(e) = server.start().await {
         tracing::error!("Server error: {}", e);
         std::process::exit(1);
@@ -1,8 +1,22 @@
-use crate::bridge::GameServerBridge;
+use crate::bridge::{GameServerBridge, ServerStatus};
 use crate::anticheat::AnticheatService;
+use crate::admin::health::{ComponentHealth, HealthChecker};
+use crate::admin::commands::{self, CommandSpec};
+use crate::admin::repl::{self, ScriptErrorPolicy, ScriptReport, ScriptStep};
+use crate::core::config::ConfigManager;
+use crate::core::histogram::TickWindowSummary;
+use crate::core::performance::PerformanceMonitor;
+use crate::core::profiler::{TickBreakdownSummary, TickProfile, TickProfiler};
+use crate::core::plugins::PluginManager;
+use crate::core::scheduler::{ScheduledTaskInfo, Scheduler};
+use crate::core::task_graph::{GraphStatus, TaskNode};
+use crate::core::throttle::{ThrottlePolicy, ThrottleState};
 use crate::events::EventBus;
 use crate::features::SessionManager;
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
 pub struct AdminCli {
@@ -10,6 +24,11 @@ pub struct AdminCli {
     anticheat: Arc<AnticheatService>,
     event_bus: Arc<EventBus>,
     session_manager: Arc<SessionManager>,
+    performance: Arc<PerformanceMonitor>,
+    plugins: Option<Arc<PluginManager>>,
+    task_graph: Option<Arc<GraphStatus>>,
+    scheduler: Option<Arc<Scheduler>>,
+    config: Option<Arc<ConfigManager>>,
 }
 
 impl AdminCli {
@@ -18,62 +37,144 @@ impl AdminCli {
         anticheat: Arc<AnticheatService>,
         event_bus: Arc<EventBus>,
         session_manager: Arc<SessionManager>,
+        performance: Arc<PerformanceMonitor>,
     ) -> Self {
         Self {
             game_server,
             anticheat,
             event_bus,
             session_manager,
+            performance,
+            plugins: None,
+            task_graph: None,
+            scheduler: None,
+            config: None,
         }
     }
 
+    pub fn with_plugins(mut self, plugins: Arc<PluginManager>) -> Self {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    pub fn with_task_graph(mut self, task_graph: Arc<GraphStatus>) -> Self {
+        self.task_graph = Some(task_graph);
+        self
+    }
+
+    pub fn with_scheduler(mut self, scheduler: Arc<Scheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Lets runtime tuning such as `throttle set` write through to the config.
+    pub fn with_config(mut self, config: Arc<ConfigManager>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn game_server(&self) -> &Arc<GameServerBridge> {
+        &self.game_server
+    }
+
+    /// The built-in commands with their aliases, arguments and help
+    pub fn commands(&self) -> &'static [CommandSpec] {
+        commands::COMMANDS
+    }
+
+    /// Command names, built-in aliases and configured aliases, for completion
+    pub fn list_commands(&self) -> Vec<String> {
+        commands::names()
+            .map(str::to_string)
+            .chain(self.aliases().into_keys())
+            .collect()
+    }
+
+    /// Subcommands accepted as the first argument of `command`
+    pub fn subcommands(command: &str) -> &'static [&'static str] {
+        commands::lookup(command).map(CommandSpec::subcommands).unwrap_or(&[])
+    }
+
+    /// Usernames of everyone with an active session
+    pub fn player_names(&self) -> Vec<String> {
+        self.session_manager.get_all_sessions()
+            .into_iter()
+            .map(|session| session.username)
+            .collect()
+    }
+
     pub async fn execute(&self, command: &str) -> Result<String, String> {
+        let command = commands::expand_alias(command, &self.aliases());
         let parts: Vec<&str> = command.trim().split_whitespace().collect();
-        if parts.is_empty() {
-            return Ok(self.help());
+        let Some(spec) = parts.first().and_then(|name| commands::lookup(name)) else {
+            return match parts.is_empty() {
+                true => self.help(&[]),
+                false => self.passthrough(&command).await,
+            };
+        };
+        if parts.get(1) == Some(&"?") {
+            return Ok(spec.help_text());
         }
 
-        match parts[0] {
-            "help" | "?" => Ok(self.help()),
+        match spec.name {
+            "help" => self.help(&parts[1..]),
             "status" => Ok(self.status().await),
             "players" => Ok(self.players().await),
             "anticheat" => self.anticheat_cmd(&parts[1..]).await,
             "tps" => Ok(self.tps().await),
+            "perf" => self.perf(&parts[1..]).await,
+            "health" => Ok(self.health()),
             "uptime" => Ok(self.uptime().await),
-            "events" => Ok(self.events().await),
+            "events" => self.events(&parts[1..]).await,
             "sessions" => Ok(self.sessions().await),
+            "plugins" => self.plugins_cmd(&parts[1..]),
+            "tasks" => self.tasks_cmd(&parts[1..]),
+            "throttle" => self.throttle_cmd(&parts[1..]),
             "findings" => self.findings(&parts[1..]).await,
             "kick" => self.kick(&parts[1..]).await,
             "say" => self.say(&parts[1..]).await,
             "stop" => self.stop().await,
             "reload" => self.reload().await,
-            _ => self.passthrough(command).await,
+            _ => self.passthrough(&command).await,
+        }
+    }
+
+    /// Runs a file of commands, one per line, as `--init-script` does at
+    /// startup. Blank lines and `#` comments are skipped; a failed command
+    /// stops the script under `ScriptErrorPolicy::Abort` unless its line
+    /// starts with `-`.
+    pub async fn execute_script(&self, path: &Path, policy: ScriptErrorPolicy) -> Result<ScriptReport, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read script {:?}: {}", path, e))?;
+
+        let mut report = ScriptReport::default();
+        for (line, command) in repl::script_lines(&contents) {
+            let (command, ignore_errors) = match command.strip_prefix('-') {
+                Some(rest) => (rest.trim().to_string(), true),
+                None => (command, false),
+            };
+            let result = self.execute(&command).await;
+            let failed = result.is_err();
+            report.steps.push(ScriptStep { line, command, result });
+            if failed && !ignore_errors && policy == ScriptErrorPolicy::Abort {
+                report.aborted_at = Some(line);
+                break;
+            }
         }
+        Ok(report)
     }
 
-    fn help(&self) -> String {
-        r#"
-Rubidium Admin Commands:
-  status          - Show server status overview
-  players         - List online players
-  tps             - Show current TPS
-  uptime          - Show server uptime
-  events          - Show event statistics
-  sessions        - Show session statistics
-  
-  anticheat status    - Show anticheat status
-  anticheat toggle    - Enable/disable anticheat
-  anticheat findings  - Show recent findings
-  
-  findings [player]   - Show anticheat findings
-  kick <player> [reason] - Kick a player
-  say <message>       - Broadcast a message
-  
-  reload          - Reload configuration
-  stop            - Stop the server
-  
-  Any other command is passed to the game server.
-"#.to_string()
+    fn aliases(&self) -> BTreeMap<String, String> {
+        self.config.as_ref().map(|config| config.get().aliases).unwrap_or_default()
+    }
+
+    fn help(&self, args: &[&str]) -> Result<String, String> {
+        match args.first() {
+            None => Ok(commands::help_overview(&self.aliases())),
+            Some(name) => commands::lookup(name)
+                .map(CommandSpec::help_text)
+                .ok_or_else(|| format!("Unknown command: {}", name)),
+        }
     }
 
     async fn status(&self) -> String {
@@ -135,6 +236,82 @@ Rubidium Admin Commands:
         format!("TPS: {:.1} (tick: {})", tps, tick)
     }
 
+    async fn perf(&self, args: &[&str]) -> Result<String, String> {
+        match args.first().copied().unwrap_or("summary") {
+            "summary" => {
+                let summaries = self.performance.tick_summaries().await;
+                let slow_tick = self.performance.slow_tick_config();
+                let mut output = format!(
+                    "Tick times (slow threshold {:.1}ms, {} alerts):\n",
+                    slow_tick.threshold_ms,
+                    self.performance.slow_tick_alert_count()
+                );
+                output.push_str("  window   ticks      p50      p95      p99      max\n");
+                for summary in &summaries {
+                    output.push_str(&format_window(summary));
+                }
+                Ok(output)
+            }
+            "watch" => {
+                let interval = parse_arg(args.get(1), 2)?;
+                let count = parse_arg(args.get(2), 10)?;
+                let mut output = String::from("  window   ticks      p50      p95      p99      max\n");
+                for i in 0..count {
+                    if i > 0 {
+                        tokio::time::sleep(Duration::from_secs(interval)).await;
+                    }
+                    let summaries = self.performance.tick_summaries().await;
+                    if let Some(summary) = summaries.first() {
+                        output.push_str(&format_window(summary));
+                    }
+                }
+                Ok(output)
+            }
+            "breakdown" | "slowticks" if !TickProfiler::compiled_in() => {
+                Err("Tick profiling is not compiled in (build with the `profiling` feature)".to_string())
+            }
+            "breakdown" | "slowticks" if !self.performance.profiler().is_enabled() => {
+                Err("Tick profiling is off (set performance.profiler.enabled)".to_string())
+            }
+            "breakdown" => Ok(format_breakdown(&self.performance.profiler().breakdown())),
+            "slowticks" => {
+                let limit = parse_arg(args.get(1), 5)? as usize;
+                let slowest = self.performance.profiler().slowest_ticks();
+                Ok(format_slow_ticks(&slowest[..slowest.len().min(limit)]))
+            }
+            other => Err(format!("Unknown perf command: {}", other)),
+        }
+    }
+
+    fn health(&self) -> String {
+        let mut checker = HealthChecker::new();
+        let game_server = self.game_server.clone();
+        checker.add_check(move || match game_server.status() {
+            ServerStatus::Running => ComponentHealth::healthy("game_server"),
+            ServerStatus::Offline | ServerStatus::Crashed => {
+                ComponentHealth::unhealthy("game_server", format!("{:?}", game_server.status()))
+            }
+            status => ComponentHealth::degraded("game_server", format!("{:?}", status)),
+        });
+        let performance = self.performance.clone();
+        checker.add_check(move || performance.tick_health());
+        let performance = self.performance.clone();
+        checker.add_check(move || performance.event_health());
+        let performance = self.performance.clone();
+        checker.add_check(move || performance.throttler().health());
+
+        let health = checker.run(crate::VERSION);
+        let mut output = format!("Health: {:?}\n", health.status);
+        for check in &health.checks {
+            output.push_str(&format!("  {:12} {:?}", check.name, check.status));
+            if let Some(message) = &check.message {
+                output.push_str(&format!(" - {}", message));
+            }
+            output.push('\n');
+        }
+        output
+    }
+
     async fn uptime(&self) -> String {
         match self.game_server.uptime() {
             Some(d) => {
@@ -148,10 +325,51 @@ Rubidium Admin Commands:
         }
     }
 
-    async fn events(&self) -> String {
-        let count = self.event_bus.event_count();
-        let handlers = self.event_bus.handler_count();
-        format!("Events processed: {}\nActive handlers: {}", count, handlers)
+    async fn events(&self, args: &[&str]) -> Result<String, String> {
+        match args.first() {
+            None => {
+                let count = self.event_bus.event_count();
+                let handlers = self.event_bus.handler_count();
+                Ok(format!("Events processed: {}\nActive handlers: {}", count, handlers))
+            }
+            Some(&"tail") => {
+                let count = match args.get(1) {
+                    Some(n) => n.parse::<usize>()
+                        .map_err(|_| format!("Invalid event count: {}", n))?,
+                    None => 20,
+                };
+                let events = self.game_server.recent_events(count);
+                if events.is_empty() {
+                    return Ok("No parsed events yet.".to_string());
+                }
+                let mut output = format!("Last {} parsed events:\n", events.len());
+                for event in events {
+                    output.push_str(&format!("  [{}] {:?}\n", event.event_name(), event));
+                }
+                Ok(output)
+            }
+            Some(&"stats") => {
+                let stats = self.event_bus.stats();
+                let log = self.game_server.log_pipeline_stats();
+                let log_line = format!("Server log lines parsed: {}, dropped: {}\n", log.parsed, log.dropped);
+                if stats.subscribers.is_empty() {
+                    return Ok(format!("{}Events published: {}\nNo subscribers.", log_line, stats.published));
+                }
+                let mut output = format!(
+                    "{}Events published: {}\n  {:<20} {:<16} {:>8} {:>10} {:>8}  {}\n",
+                    log_line, stats.published, "SUBSCRIBER", "PATTERN", "QUEUED", "DELIVERED", "DROPPED", "POLICY"
+                );
+                for s in &stats.subscribers {
+                    let state = if s.disconnected { " (disconnected)" } else { "" };
+                    output.push_str(&format!(
+                        "  {:<20} {:<16} {:>8} {:>10} {:>8}  {}{}\n",
+                        s.name, s.pattern, format!("{}/{}", s.queued, s.capacity), s.delivered, s.dropped, s.overflow.as_str(), state
+                    ));
+                }
+                Ok(output)
+            }
+            Some(other) => Err(format!("Unknown events command: {}", other)),
+        }
     }
 
     async fn sessions(&self) -> String {
@@ -165,6 +383,88 @@ Rubidium Admin Commands:
         )
     }
 
+    fn plugins_cmd(&self, args: &[&str]) -> Result<String, String> {
+        let plugins = self.plugins.as_ref().ok_or("Plugin manager not available")?;
+        match args.first().copied().unwrap_or("list") {
+            "list" => {
+                let mut list = plugins.list_plugins();
+                if list.is_empty() {
+                    return Ok("No plugins loaded.".to_string());
+                }
+                list.sort_by(|a, b| a.id.cmp(&b.id));
+                let mut output = format!("Plugins ({}):\n", list.len());
+                for metadata in list {
+                    let state = plugins.get_plugin_state(&metadata.id)
+                        .map(|s| format!("{:?}", s))
+                        .unwrap_or_default();
+                    output.push_str(&format!("  {} v{} [{}] strikes: {}\n",
+                                              metadata.id, metadata.version, state, plugins.strikes(&metadata.id)));
+                    if let Some(error) = plugins.get_plugin_error(&metadata.id) {
+                        output.push_str(&format!("    {}\n", error));
+                    }
+                }
+                Ok(output)
+            }
+            "violations" => {
+                let violations = plugins.violations(args.get(1).copied());
+                if violations.is_empty() {
+                    return Ok("No sandbox violations.".to_string());
+                }
+                let mut output = format!("Sandbox violations ({}):\n", violations.len());
+                for v in violations {
+                    output.push_str(&format!("  {} {} #{} {} -> {}\n",
+                                              v.at.format("%H:%M:%S"), v.plugin_id, v.strike,
+                                              v.capability.as_str(), v.target));
+                }
+                Ok(output)
+            }
+            other => Err(format!("Unknown plugins command: {}", other)),
+        }
+    }
+
+    fn tasks_cmd(&self, args: &[&str]) -> Result<String, String> {
+        match args.first().copied().unwrap_or("list") {
+            "list" => {
+                let scheduler = self.scheduler.as_ref().ok_or("Scheduler not available")?;
+                Ok(format_scheduled_tasks(&scheduler.scheduled_tasks(), chrono::Utc::now()))
+            }
+            "graph" => {
+                let task_graph = self.task_graph.as_ref().ok_or("Task graph not available")?;
+                Ok(format_task_graph(&task_graph.snapshot()))
+            }
+            other => Err(format!("Unknown tasks command: {}", other)),
+        }
+    }
+
+    fn throttle_cmd(&self, args: &[&str]) -> Result<String, String> {
+        let throttler = self.performance.throttler();
+        match args.first().copied().unwrap_or("status") {
+            "status" => Ok(format_throttle(&throttler.state(), &throttler.policy(), throttler.tick_budget_ms())),
+            "set" => {
+                let (Some(key), Some(value)) = (args.get(1), args.get(2)) else {
+                    return Err("Usage: throttle set <key> <value>".to_string());
+                };
+                let mut policy = throttler.policy();
+                policy.set(key, value)?;
+                throttler.set_policy(policy.clone())?;
+                if let Some(config) = &self.config {
+                    config.update(|config| config.performance.throttling = policy);
+                }
+                info!("Throttle {} set to {} via admin CLI", key, value);
+                Ok(format!("Throttle {} = {}", key, value))
+            }
+            toggle @ ("enable" | "disable") => {
+                let enabled = toggle == "enable";
+                throttler.set_enabled(enabled);
+                if let Some(config) = &self.config {
+                    config.update(|config| config.performance.adaptive_throttling = enabled);
+                }
+                Ok(format!("Adaptive entity throttling {}d", toggle))
+            }
+            other => Err(format!("Unknown throttle command: {}", other)),
+        }
+    }
+
     async fn anticheat_cmd(&self, args: &[&str]) -> Result<String, String> {
         if args.is_empty() {
             return Ok(format!("Anticheat: {}", if self.anticheat.is_enabled() { "enabled" } else { "disabled" }));
@@ -261,3 +561,215 @@ Rubidium Admin Commands:
         Ok(format!("Command sent: {}", command))
     }
 }
+
+fn format_window(summary: &TickWindowSummary) -> String {
+    format!(
+        "  {:6} {:7} {:6.1}ms {:6.1}ms {:6.1}ms {:6.1}ms\n",
+        summary.window, summary.count, summary.p50_ms, summary.p95_ms, summary.p99_ms, summary.max_ms
+    )
+}
+
+fn format_breakdown(breakdown: &TickBreakdownSummary) -> String {
+    if breakdown.ticks == 0 {
+        return "No profiled ticks yet.".to_string();
+    }
+    let mut output = format!(
+        "Tick breakdown over {} ticks (avg {:.2}ms):\n  scope                        avg      max   share\n",
+        breakdown.ticks, breakdown.avg_tick_ms
+    );
+    for scope in &breakdown.scopes {
+        output.push_str(&format!(
+            "  {:<24} {:>6.2}ms {:>6.2}ms {:>6.1}%\n",
+            scope.name, scope.avg_ms, scope.max_ms, scope.share * 100.0
+        ));
+    }
+    output
+}
+
+fn format_slow_ticks(profiles: &[TickProfile]) -> String {
+    if profiles.is_empty() {
+        return "No profiled ticks yet.".to_string();
+    }
+    let mut output = format!("Slowest {} ticks:\n", profiles.len());
+    for profile in profiles {
+        output.push_str(&format!(
+            "  tick {} at {}: {:.2}ms\n",
+            profile.tick,
+            profile.at.format("%H:%M:%S UTC"),
+            profile.total_ms
+        ));
+        for scope in &profile.scopes {
+            output.push_str(&format!("    {:<22} {:>6.2}ms x{}\n", scope.name, scope.duration_ms, scope.calls));
+        }
+        output.push_str(&format!("    {:<22} {:>6.2}ms\n", "(untracked)", profile.untracked_ms));
+    }
+    output
+}
+
+fn format_throttle(state: &ThrottleState, policy: &ThrottlePolicy, budget_ms: f64) -> String {
+    let mut output = format!(
+        "Entity throttling: {}, level {}/{} ({} changes)\n",
+        if state.enabled { "enabled" } else { "disabled" },
+        state.level,
+        state.max_level,
+        state.changes
+    );
+    output.push_str(&format!(
+        "  engage after {} ticks over {:.1}ms, release after {} ticks under {:.1}ms\n",
+        policy.engage_after_ticks,
+        budget_ms,
+        policy.release_after_ticks,
+        budget_ms * policy.release_below
+    ));
+    output.push_str(&format!(
+        "  streaks: {} over budget, {} with headroom\n",
+        state.overrun_streak, state.headroom_streak
+    ));
+    for tier in &policy.tiers {
+        output.push_str(&format!(
+            "  >= {:>5.0} blocks: every {} ticks now (at most {})\n",
+            tier.distance,
+            policy.interval(state.level, tier.distance),
+            tier.max_interval
+        ));
+    }
+    output
+}
+
+fn format_task_graph(nodes: &[TaskNode]) -> String {
+    if nodes.is_empty() {
+        return "No task graph has run.".to_string();
+    }
+    let lanes = nodes.iter().map(|n| n.lane).max().map_or(0, |l| l + 1);
+    let mut output = format!("Task graph ({} tasks, {} lanes):\n", nodes.len(), lanes);
+    for node in nodes {
+        let time = node.duration
+            .map(|d| format!("{}ms", d.as_millis()))
+            .unwrap_or_default();
+        output.push_str(&format!("  [{}] {:<20} {:>8}  {}\n", node.lane, node.name, time, node.status));
+        if !node.depends_on.is_empty() {
+            output.push_str(&format!("        <- {}\n", node.depends_on.join(", ")));
+        }
+    }
+    output
+}
+
+fn format_scheduled_tasks(tasks: &[ScheduledTaskInfo], now: chrono::DateTime<chrono::Utc>) -> String {
+    if tasks.is_empty() {
+        return "No scheduled tasks.".to_string();
+    }
+    let mut output = format!("Scheduled tasks ({}):\n", tasks.len());
+    for task in tasks {
+        let until = (task.next_run - now).num_seconds().max(0);
+        output.push_str(&format!(
+            "  {:<24} {:<18} {:<10} next {} (in {}m {}s), {} runs\n",
+            task.name,
+            task.schedule,
+            format!("{:?}", task.priority),
+            task.next_run.format("%Y-%m-%d %H:%M UTC"),
+            until / 60,
+            until % 60,
+            task.runs
+        ));
+    }
+    output
+}
+
+fn parse_arg(arg: Option<&&str>, default: u64) -> Result<u64, String> {
+    match arg {
+        Some(value) => value.parse::<u64>().map_err(|_| format!("Invalid number: {}", value)),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anticheat::AnticheatConfig;
+    use crate::bridge::GameServerConfig;
+    use crate::core::telemetry::TelemetryCollector;
+    use std::path::PathBuf;
+
+    struct Fixture {
+        cli: AdminCli,
+        dir: PathBuf,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn fixture() -> Fixture {
+        let dir = std::env::temp_dir().join(format!("rubidium-cli-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Arc::new(ConfigManager::new(dir.join("rubidium.toml").to_str().unwrap()).unwrap());
+        config.update(|config| {
+            config.aliases.insert("fps".into(), "tps".into());
+            config.aliases.insert("k".into(), "kick".into());
+            config.aliases.insert("stop".into(), "say not stopping".into());
+        });
+
+        let cli = AdminCli::new(
+            Arc::new(GameServerBridge::new(GameServerConfig::default())),
+            Arc::new(AnticheatService::new(AnticheatConfig::default())),
+            Arc::new(EventBus::new()),
+            Arc::new(SessionManager::new(Duration::from_secs(60))),
+            Arc::new(PerformanceMonitor::new(Arc::new(TelemetryCollector::new()))),
+        ).with_config(config);
+        Fixture { cli, dir }
+    }
+
+    #[tokio::test]
+    async fn test_aliases_resolve_to_commands() {
+        let f = fixture();
+        assert!(f.cli.execute("fps").await.unwrap().starts_with("TPS: "));
+        assert!(f.cli.execute("who").await.is_ok());
+        // `k` expands to `kick`, which still checks its arguments.
+        assert_eq!(f.cli.execute("k").await.unwrap_err(), "Usage: kick <player> [reason]");
+        // Built-in names can't be shadowed, and unknown words go to the game server.
+        assert_eq!(f.cli.execute("stop ?").await.unwrap(), commands::lookup("stop").unwrap().help_text());
+        assert_eq!(f.cli.execute("time set day").await.unwrap_err(), "Not connected to game server");
+
+        let names = f.cli.list_commands();
+        assert!(names.iter().any(|n| n == "fps") && names.iter().any(|n| n == "broadcast"));
+        assert_eq!(AdminCli::subcommands("ac"), &["status", "toggle", "findings"]);
+    }
+
+    #[tokio::test]
+    async fn test_help_lists_every_command() {
+        let f = fixture();
+        let help = f.cli.execute("help").await.unwrap();
+        for spec in f.cli.commands() {
+            for (usage, _) in spec.help {
+                assert!(help.contains(usage), "help is missing {:?}", usage);
+            }
+        }
+        assert!(help.contains("fps") && help.contains("= tps"));
+        assert!(f.cli.execute("help kick").await.unwrap().starts_with("Usage: kick <player> [reason...]"));
+        assert!(f.cli.execute("help teleport").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_script_error_policies() {
+        let f = fixture();
+        let script = f.dir.join("init.txt");
+        std::fs::write(&script, "# startup\ntps\n-weather clear\n\nsummon boat\nsay ready\n").unwrap();
+
+        let report = f.cli.execute_script(&script, ScriptErrorPolicy::Continue).await.unwrap();
+        let lines: Vec<usize> = report.steps.iter().map(|s| s.line).collect();
+        assert_eq!(lines, [2, 3, 5, 6]);
+        assert_eq!(report.steps[1].command, "weather clear");
+        assert_eq!(report.failures().map(|s| s.line).collect::<Vec<_>>(), [3, 5]);
+        assert_eq!(report.aborted_at, None);
+        assert!(!report.succeeded());
+
+        // The `-` line is allowed to fail; the next failure stops the script.
+        let report = f.cli.execute_script(&script, ScriptErrorPolicy::Abort).await.unwrap();
+        assert_eq!(report.steps.len(), 3);
+        assert_eq!(report.aborted_at, Some(5));
+
+        assert!(f.cli.execute_script(&f.dir.join("missing.txt"), ScriptErrorPolicy::Abort).await.is_err());
+    }
+}
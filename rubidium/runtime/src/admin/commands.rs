@@ -0,0 +1,343 @@
+//! Registry of the admin CLI's built-in commands: names, aliases, arguments
+//! and help text. `AdminCli` dispatches on it and the REPL reads it for
+//! completion.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// One of a fixed set of words
+    Subcommand(&'static [&'static str]),
+    Player,
+    Number,
+    /// Everything to the end of the line
+    Text,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+impl ArgSpec {
+    const fn required(name: &'static str, kind: ArgKind) -> Self {
+        Self { name, kind, required: true }
+    }
+
+    const fn optional(name: &'static str, kind: ArgKind) -> Self {
+        Self { name, kind, required: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [ArgSpec],
+    /// Usage lines and what each does
+    pub help: &'static [(&'static str, &'static str)],
+}
+
+impl CommandSpec {
+    /// Words accepted as the first argument, if it is a subcommand
+    pub fn subcommands(&self) -> &'static [&'static str] {
+        match self.args.first() {
+            Some(ArgSpec { kind: ArgKind::Subcommand(words), .. }) => words,
+            _ => &[],
+        }
+    }
+
+    /// `kick <player> [reason]` style synopsis built from the arg specs
+    pub fn synopsis(&self) -> String {
+        let mut synopsis = self.name.to_string();
+        for arg in self.args {
+            let name = match arg.kind {
+                ArgKind::Subcommand(words) => words.join("|"),
+                ArgKind::Text => format!("{}...", arg.name),
+                _ => arg.name.to_string(),
+            };
+            if arg.required {
+                synopsis.push_str(&format!(" <{}>", name));
+            } else {
+                synopsis.push_str(&format!(" [{}]", name));
+            }
+        }
+        synopsis
+    }
+
+    /// Full help for `help <command>` and `<command> ?`
+    pub fn help_text(&self) -> String {
+        let mut output = format!("Usage: {}\n", self.synopsis());
+        if !self.aliases.is_empty() {
+            output.push_str(&format!("Aliases: {}\n", self.aliases.join(", ")));
+        }
+        for (usage, description) in self.help {
+            output.push_str(&format!("  {:<28} {}\n", usage, description));
+        }
+        output
+    }
+}
+
+const PERF: &[&str] = &["summary", "watch", "breakdown", "slowticks"];
+const EVENTS: &[&str] = &["tail", "stats"];
+const PLUGINS: &[&str] = &["list", "violations"];
+const TASKS: &[&str] = &["list", "graph"];
+const THROTTLE: &[&str] = &["status", "set", "enable", "disable"];
+const ANTICHEAT: &[&str] = &["status", "toggle", "findings"];
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        aliases: &["?"],
+        args: &[ArgSpec::optional("command", ArgKind::Word)],
+        help: &[
+            ("help", "List commands"),
+            ("help <command>", "Show usage for one command (or: <command> ?)"),
+        ],
+    },
+    CommandSpec {
+        name: "status",
+        aliases: &[],
+        args: &[],
+        help: &[("status", "Show server status overview")],
+    },
+    CommandSpec {
+        name: "players",
+        aliases: &["who"],
+        args: &[],
+        help: &[("players", "List online players")],
+    },
+    CommandSpec {
+        name: "tps",
+        aliases: &[],
+        args: &[],
+        help: &[("tps", "Show current TPS")],
+    },
+    CommandSpec {
+        name: "perf",
+        aliases: &[],
+        args: &[
+            ArgSpec::optional("command", ArgKind::Subcommand(PERF)),
+            ArgSpec::optional("n", ArgKind::Number),
+        ],
+        help: &[
+            ("perf summary", "Show tick time percentiles (1m/5m/15m)"),
+            ("perf watch [secs] [count]", "Sample the 1m tick window repeatedly"),
+            ("perf breakdown", "Show average tick time per scope"),
+            ("perf slowticks [n]", "Show the slowest profiled ticks by scope"),
+        ],
+    },
+    CommandSpec {
+        name: "health",
+        aliases: &[],
+        args: &[],
+        help: &[("health", "Show component health")],
+    },
+    CommandSpec {
+        name: "uptime",
+        aliases: &[],
+        args: &[],
+        help: &[("uptime", "Show server uptime")],
+    },
+    CommandSpec {
+        name: "events",
+        aliases: &[],
+        args: &[
+            ArgSpec::optional("command", ArgKind::Subcommand(EVENTS)),
+            ArgSpec::optional("n", ArgKind::Number),
+        ],
+        help: &[
+            ("events", "Show event statistics"),
+            ("events tail [n]", "Show the last n parsed server events"),
+            ("events stats", "Show queue depth and drops per subscriber"),
+        ],
+    },
+    CommandSpec {
+        name: "sessions",
+        aliases: &[],
+        args: &[],
+        help: &[("sessions", "Show session statistics")],
+    },
+    CommandSpec {
+        name: "plugins",
+        aliases: &[],
+        args: &[
+            ArgSpec::optional("command", ArgKind::Subcommand(PLUGINS)),
+            ArgSpec::optional("plugin", ArgKind::Word),
+        ],
+        help: &[
+            ("plugins", "List plugins and their state"),
+            ("plugins violations [plugin]", "Show recent sandbox violations"),
+        ],
+    },
+    CommandSpec {
+        name: "tasks",
+        aliases: &[],
+        args: &[ArgSpec::optional("command", ArgKind::Subcommand(TASKS))],
+        help: &[
+            ("tasks", "List scheduled tasks and their next run"),
+            ("tasks graph", "Show startup tasks, their dependencies and status"),
+        ],
+    },
+    CommandSpec {
+        name: "throttle",
+        aliases: &[],
+        args: &[
+            ArgSpec::optional("command", ArgKind::Subcommand(THROTTLE)),
+            ArgSpec::optional("key", ArgKind::Word),
+            ArgSpec::optional("value", ArgKind::Word),
+        ],
+        help: &[
+            ("throttle", "Show entity throttling level and policy"),
+            ("throttle set <key> <value>", "Tune tiers, max_level, engage_after_ticks, release_after_ticks or release_below"),
+            ("throttle enable|disable", "Switch adaptive entity throttling"),
+        ],
+    },
+    CommandSpec {
+        name: "anticheat",
+        aliases: &["ac"],
+        args: &[ArgSpec::optional("command", ArgKind::Subcommand(ANTICHEAT))],
+        help: &[
+            ("anticheat status", "Show anticheat status"),
+            ("anticheat toggle", "Enable/disable anticheat"),
+            ("anticheat findings", "Show recent findings"),
+        ],
+    },
+    CommandSpec {
+        name: "findings",
+        aliases: &[],
+        args: &[ArgSpec::optional("player", ArgKind::Player)],
+        help: &[("findings [player]", "Show anticheat findings")],
+    },
+    CommandSpec {
+        name: "kick",
+        aliases: &[],
+        args: &[
+            ArgSpec::required("player", ArgKind::Player),
+            ArgSpec::optional("reason", ArgKind::Text),
+        ],
+        help: &[("kick <player> [reason]", "Kick a player")],
+    },
+    CommandSpec {
+        name: "say",
+        aliases: &["broadcast"],
+        args: &[ArgSpec::required("message", ArgKind::Text)],
+        help: &[("say <message>", "Broadcast a message")],
+    },
+    CommandSpec {
+        name: "reload",
+        aliases: &[],
+        args: &[],
+        help: &[("reload", "Reload configuration")],
+    },
+    CommandSpec {
+        name: "stop",
+        aliases: &["shutdown"],
+        args: &[],
+        help: &[("stop", "Stop the server")],
+    },
+];
+
+/// The command `name` refers to, by name or built-in alias
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name == name || spec.aliases.contains(&name))
+}
+
+/// Command names followed by their aliases
+pub fn names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().map(|spec| spec.name)
+        .chain(COMMANDS.iter().flat_map(|spec| spec.aliases.iter().copied()))
+}
+
+/// Expands an operator alias from the `[aliases]` config section. Only the
+/// first word is replaced and the expansion isn't expanded again, so aliases
+/// can't loop; built-in command names can't be shadowed.
+pub fn expand_alias(line: &str, aliases: &BTreeMap<String, String>) -> String {
+    let line = line.trim();
+    let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if lookup(first).is_some() {
+        return line.to_string();
+    }
+    match aliases.get(first) {
+        Some(expansion) if rest.trim().is_empty() => expansion.trim().to_string(),
+        Some(expansion) => format!("{} {}", expansion.trim(), rest.trim()),
+        None => line.to_string(),
+    }
+}
+
+/// Overview of every command, plus operator aliases when there are any
+pub fn help_overview(aliases: &BTreeMap<String, String>) -> String {
+    let mut output = String::from("\nRubidium Admin Commands:\n");
+    for spec in COMMANDS {
+        for (usage, description) in spec.help {
+            output.push_str(&format!("  {:<28} - {}\n", usage, description));
+        }
+    }
+    if !aliases.is_empty() {
+        output.push_str("\nAliases:\n");
+        for (alias, expansion) in aliases {
+            output.push_str(&format!("  {:<28} = {}\n", alias, expansion));
+        }
+    }
+    output.push_str("\n  Any other command is passed to the game server.\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn test_lookup_and_names() {
+        assert_eq!(lookup("who").unwrap().name, "players");
+        assert_eq!(lookup("?").unwrap().name, "help");
+        assert!(lookup("tp").is_none());
+
+        let names: Vec<&str> = names().collect();
+        assert!(names.contains(&"kick") && names.contains(&"shutdown"));
+        let mut unique = names.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len(), "names and aliases must not collide");
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = aliases(&[
+            ("tp", "teleport"),
+            ("k", "kick"),
+            ("warn-restart", "say Restarting in 5 minutes"),
+            ("kick", "stop"),
+            ("a", "b"),
+            ("b", "stop"),
+        ]);
+        assert_eq!(expand_alias("tp Steve Alex", &aliases), "teleport Steve Alex");
+        assert_eq!(expand_alias("k Steve griefing", &aliases), "kick Steve griefing");
+        assert_eq!(expand_alias("warn-restart", &aliases), "say Restarting in 5 minutes");
+        assert_eq!(expand_alias("kick Steve", &aliases), "kick Steve");
+        assert_eq!(expand_alias("a", &aliases), "b");
+        assert_eq!(expand_alias("  time set day ", &aliases), "time set day");
+    }
+
+    #[test]
+    fn test_help_covers_every_command() {
+        let help = help_overview(&aliases(&[("tp", "teleport")]));
+        for spec in COMMANDS {
+            assert!(help.contains(spec.name), "help is missing {}", spec.name);
+            assert!(!spec.help.is_empty(), "{} has no help text", spec.name);
+        }
+        assert!(help.contains("tp") && help.contains("teleport"));
+
+        let kick = lookup("kick").unwrap();
+        assert_eq!(kick.synopsis(), "kick <player> [reason...]");
+        assert_eq!(lookup("tasks").unwrap().synopsis(), "tasks [list|graph]");
+        assert!(lookup("say").unwrap().help_text().contains("Aliases: broadcast"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Liveness and readiness probes over plain HTTP/1.1 for process supervisors
+//! (systemd, Docker healthchecks, Kubernetes). `/healthz` answers whether the
+//! process should be restarted, `/readyz` whether it should receive players.
+
+use crate::admin::health::{ComponentHealth, HealthCheck, HealthChecker};
+use crate::bootstrap::BootstrapPhase;
+use crate::bridge::{GameServerBridge, ServerStatus};
+use crate::events::EventBus;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+pub const LIVENESS_PATH: &str = "/healthz";
+pub const READINESS_PATH: &str = "/readyz";
+
+/// The event bus counts as hung if it can't be read within this long.
+const EVENT_BUS_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthEndpointConfig {
+    pub enabled: bool,
+    pub bind: String,
+    /// Readiness fails while the game server reports fewer ticks per second
+    pub min_tps: f64,
+}
+
+impl Default for HealthEndpointConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8087".to_string(),
+            min_tps: 15.0,
+        }
+    }
+}
+
+/// What the probes report on. The bootstrap fills it in as phases complete,
+/// so probes answer sensibly from the moment the listener is bound.
+pub struct Readiness {
+    completed: RwLock<Vec<BootstrapPhase>>,
+    failure: RwLock<Option<String>>,
+    game_server: RwLock<Option<Arc<GameServerBridge>>>,
+    event_bus: RwLock<Option<Arc<EventBus>>>,
+    min_tps: RwLock<f64>,
+    endpoint: RwLock<Option<SocketAddr>>,
+}
+
+impl Readiness {
+    pub fn new(min_tps: f64) -> Self {
+        Self {
+            completed: RwLock::new(Vec::new()),
+            failure: RwLock::new(None),
+            game_server: RwLock::new(None),
+            event_bus: RwLock::new(None),
+            min_tps: RwLock::new(min_tps),
+            endpoint: RwLock::new(None),
+        }
+    }
+
+    pub fn phase_completed(&self, phase: BootstrapPhase) {
+        let mut completed = self.completed.write();
+        if !completed.contains(&phase) {
+            completed.push(phase);
+        }
+    }
+
+    /// Readiness stays failed for the life of the process
+    pub fn phase_failed(&self, phase: BootstrapPhase, error: &str) {
+        self.failure.write().get_or_insert_with(|| format!("{} failed: {}", phase.name(), error));
+    }
+
+    pub fn completed_phases(&self) -> Vec<BootstrapPhase> {
+        self.completed.read().clone()
+    }
+
+    /// Every bootstrap phase, up to and including Ready, has completed
+    pub fn is_bootstrapped(&self) -> bool {
+        self.completed.read().contains(&BootstrapPhase::Ready)
+    }
+
+    pub fn set_game_server(&self, game_server: Arc<GameServerBridge>) {
+        *self.game_server.write() = Some(game_server);
+    }
+
+    pub fn set_event_bus(&self, event_bus: Arc<EventBus>) {
+        *self.event_bus.write() = Some(event_bus);
+    }
+
+    pub fn set_min_tps(&self, min_tps: f64) {
+        *self.min_tps.write() = min_tps;
+    }
+
+    pub fn min_tps(&self) -> f64 {
+        *self.min_tps.read()
+    }
+
+    /// Address the HTTP endpoint listens on, once it is bound
+    pub fn endpoint_addr(&self) -> Option<SocketAddr> {
+        *self.endpoint.read()
+    }
+
+    fn set_endpoint_addr(&self, addr: SocketAddr) {
+        *self.endpoint.write() = Some(addr);
+    }
+
+    /// The process answers and the event bus isn't wedged. An event bus that
+    /// hasn't started yet is only degraded, so slow startups aren't restarted.
+    pub async fn liveness(&self) -> HealthCheck {
+        let mut health = HealthCheck::new(crate::VERSION);
+        health.add_check(ComponentHealth::healthy("process")
+            .with_detail("pid", std::process::id().to_string()));
+
+        let event_bus = self.event_bus.read().clone();
+        let start = Instant::now();
+        let check = match event_bus {
+            None => ComponentHealth::degraded("event_bus", "Not started yet"),
+            Some(event_bus) => {
+                let probe = tokio::task::spawn_blocking(move || (event_bus.handler_count(), event_bus.event_count()));
+                match tokio::time::timeout(EVENT_BUS_TIMEOUT, probe).await {
+                    Ok(Ok((subscribers, published))) => ComponentHealth::healthy("event_bus")
+                        .with_detail("subscribers", subscribers.to_string())
+                        .with_detail("published", published.to_string()),
+                    Ok(Err(e)) => ComponentHealth::unhealthy("event_bus", format!("Probe failed: {}", e)),
+                    Err(_) => ComponentHealth::unhealthy(
+                        "event_bus",
+                        format!("No response within {}ms", EVENT_BUS_TIMEOUT.as_millis()),
+                    ),
+                }
+            }
+        };
+        health.add_check(check.with_latency(start.elapsed().as_secs_f64() * 1000.0));
+        health
+    }
+
+    /// Bootstrap finished, the game server is Running and its TPS is at
+    /// least `min_tps`.
+    pub fn readiness(&self) -> HealthCheck {
+        let mut checker = HealthChecker::new();
+
+        let completed = self.completed_phases();
+        let failure = self.failure.read().clone();
+        checker.add_check(move || {
+            let progress = format!("{}/{}", completed.len(), BootstrapPhase::GRAPH.len());
+            let check = if let Some(failure) = &failure {
+                ComponentHealth::unhealthy("bootstrap", failure.clone())
+            } else if completed.contains(&BootstrapPhase::Ready) {
+                ComponentHealth::healthy("bootstrap")
+            } else {
+                let pending: Vec<String> = BootstrapPhase::GRAPH.iter()
+                    .filter(|phase| !completed.contains(phase))
+                    .map(|phase| phase.name())
+                    .collect();
+                ComponentHealth::unhealthy("bootstrap", format!("Waiting for {}", pending.join(", ")))
+            };
+            check.with_detail("phases", progress)
+        });
+
+        let game_server = self.game_server.read().clone();
+        let status_server = game_server.clone();
+        checker.add_check(move || match &status_server {
+            None => ComponentHealth::unhealthy("game_server", "Not started yet"),
+            Some(game_server) => {
+                let status = game_server.status();
+                let check = if status == ServerStatus::Running {
+                    ComponentHealth::healthy("game_server")
+                } else {
+                    ComponentHealth::unhealthy("game_server", format!("{:?}", status))
+                };
+                check.with_detail("status", format!("{:?}", status))
+                    .with_detail("players", game_server.player_count().to_string())
+            }
+        });
+
+        let min_tps = self.min_tps();
+        checker.add_check(move || match &game_server {
+            None => ComponentHealth::unhealthy("tps", "No game server"),
+            Some(game_server) => {
+                let tps = game_server.tps();
+                let check = if tps >= min_tps {
+                    ComponentHealth::healthy("tps")
+                } else {
+                    ComponentHealth::unhealthy("tps", format!("{:.1} TPS is below {:.1}", tps, min_tps))
+                };
+                check.with_detail("tps", format!("{:.1}", tps))
+                    .with_detail("min_tps", format!("{:.1}", min_tps))
+            }
+        });
+
+        checker.run(crate::VERSION)
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new(HealthEndpointConfig::default().min_tps)
+    }
+}
+
+/// Serves `/healthz` and `/readyz`, one request per connection. Both answer
+/// 200 or 503 with the `HealthCheck` as the JSON body.
+pub struct HealthEndpoint {
+    listener: TcpListener,
+    readiness: Arc<Readiness>,
+}
+
+impl HealthEndpoint {
+    pub async fn bind(addr: SocketAddr, readiness: Arc<Readiness>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        readiness.set_endpoint_addr(listener.local_addr()?);
+        Ok(Self { listener, readiness })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections until the task is dropped.
+    pub async fn serve(self) {
+        if let Ok(addr) = self.listener.local_addr() {
+            info!("Health endpoint listening on {}", addr);
+        }
+        loop {
+            let Ok((stream, peer)) = self.listener.accept().await else {
+                continue;
+            };
+            let readiness = self.readiness.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &readiness).await {
+                    debug!("Health probe from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, readiness: &Readiness) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let route = path.split('?').next().unwrap_or("");
+    let (status, body) = if route != LIVENESS_PATH && route != READINESS_PATH {
+        ("404 Not Found", error_body("Not found"))
+    } else if method != "GET" {
+        ("405 Method Not Allowed", error_body(&format!("Method {} not allowed", method)))
+    } else {
+        let (health, ok) = if route == LIVENESS_PATH {
+            let health = readiness.liveness().await;
+            let live = health.is_live();
+            (health, live)
+        } else {
+            let health = readiness.readiness();
+            let ready = health.is_ready();
+            (health, ready)
+        };
+        let status = if ok { "200 OK" } else { "503 Service Unavailable" };
+        (status, serde_json::to_string(&health).unwrap_or_default())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::GameServerConfig;
+
+    async fn endpoint(readiness: Arc<Readiness>) -> SocketAddr {
+        let endpoint = HealthEndpoint::bind("127.0.0.1:0".parse().unwrap(), readiness).await.unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(endpoint.serve());
+        addr
+    }
+
+    async fn send(addr: SocketAddr, request: &str) -> (String, serde_json::Value) {
+        use tokio::io::AsyncReadExt;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.lines().next().unwrap().to_string();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_probes_before_bootstrap() {
+        let readiness = Arc::new(Readiness::default());
+        let addr = endpoint(readiness.clone()).await;
+        assert_eq!(readiness.endpoint_addr(), Some(addr));
+
+        let (status, body) = send(addr, "GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body["status"], "Degraded");
+
+        let (status, body) = send(addr, "GET /readyz HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        assert_eq!(body["checks"][0]["name"], "bootstrap");
+        assert_eq!(body["checks"][0]["details"]["phases"], "0/8");
+
+        let (status, _) = send(addr, "POST /readyz HTTP/1.1\r\nContent-Length: 0\r\n\r\n").await;
+        assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+        let (status, body) = send(addr, "GET /metrics HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        assert_eq!(body["error"], "Not found");
+    }
+
+    #[tokio::test]
+    async fn test_readiness_components() {
+        let readiness = Readiness::new(18.0);
+        readiness.set_event_bus(Arc::new(EventBus::new()));
+        for phase in BootstrapPhase::GRAPH {
+            readiness.phase_completed(phase);
+        }
+        assert!(readiness.is_bootstrapped());
+        assert!(readiness.liveness().await.is_live());
+
+        // A registered but unstarted server is Offline
+        readiness.set_game_server(Arc::new(GameServerBridge::new(GameServerConfig::default())));
+        let health = readiness.readiness();
+        assert!(!health.is_ready());
+        let failing: Vec<&str> = health.checks.iter()
+            .filter(|c| c.status != crate::admin::HealthStatus::Healthy)
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(failing, ["game_server"]);
+
+        readiness.set_min_tps(25.0);
+        let health = readiness.readiness();
+        let tps = health.checks.iter().find(|c| c.name == "tps").unwrap();
+        assert_eq!(tps.message.as_deref(), Some("20.0 TPS is below 25.0"));
+
+        readiness.phase_failed(BootstrapPhase::Plugins, "boom");
+        readiness.phase_failed(BootstrapPhase::Anticheat, "later");
+        let health = readiness.readiness();
+        assert_eq!(health.checks[0].message.as_deref(), Some("Plugins failed: boom"));
+    }
+}
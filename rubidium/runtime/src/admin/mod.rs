@@ -1,7 +1,12 @@
 pub mod cli;
+pub mod commands;
 pub mod status;
 pub mod health;
+pub mod http;
+pub mod repl;
 
 pub use cli::AdminCli;
+pub use commands::{ArgKind, ArgSpec, CommandSpec};
 pub use status::{ServerStats, StatusReport};
 pub use health::{HealthCheck, HealthStatus};
+pub use http::{HealthEndpoint, HealthEndpointConfig, Readiness};
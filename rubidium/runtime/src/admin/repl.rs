@@ -0,0 +1,434 @@
+//! Console REPL support for the admin CLI: persistent history, command and
+//! player-name completion, startup scripts and Ctrl-C handling.
+//!
+//! Line editing is done by rustyline; `ConsoleHelper` plugs completion into
+//! it so Tab completes as it does in a shell.
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+pub const HISTORY_FILE: &str = "admin_history";
+pub const MAX_HISTORY: usize = 1000;
+/// A second Ctrl-C inside this window shuts the server down.
+pub const SHUTDOWN_WINDOW: Duration = Duration::from_secs(2);
+
+/// Commands the REPL handles itself rather than passing to `AdminCli`
+pub const REPL_COMMANDS: &[&str] = &["exit", "quit", "history"];
+
+/// What a startup script does after a command fails. A line starting with
+/// `-` never stops the script, whatever the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScriptErrorPolicy {
+    Abort,
+    #[default]
+    Continue,
+}
+
+impl std::str::FromStr for ScriptErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(Self::Abort),
+            "continue" => Ok(Self::Continue),
+            other => Err(format!("Unknown script error policy: {} (expected abort or continue)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplOptions {
+    /// Script of newline-separated commands to run before going interactive
+    pub exec: Option<PathBuf>,
+    pub on_error: ScriptErrorPolicy,
+    /// Exit after the script instead of reading from the console
+    pub non_interactive: bool,
+}
+
+impl ReplOptions {
+    /// Reads `--init-script <file>` (or its older spelling `--exec`),
+    /// `--on-error abort|continue` and `--non-interactive`, falling back to
+    /// `RUBIDIUM_EXEC` for the script.
+    pub fn parse(args: impl IntoIterator<Item = String>, env_exec: Option<String>) -> Result<Self, String> {
+        let mut options = Self {
+            exec: env_exec.filter(|v| !v.is_empty()).map(PathBuf::from),
+            ..Self::default()
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            let mut value = |what: &str| inline.clone().or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires {}", flag, what));
+            match flag {
+                "--init-script" | "--exec" => options.exec = Some(PathBuf::from(value("a file path")?)),
+                "--on-error" => options.on_error = value("abort or continue")?.parse()?,
+                "--non-interactive" if inline.is_none() => options.non_interactive = true,
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1), std::env::var("RUBIDIUM_EXEC").ok())
+    }
+}
+
+/// Commands from a script, skipping blank lines and `#` comments
+pub fn parse_script(contents: &str) -> Vec<String> {
+    script_lines(contents).into_iter().map(|(_, line)| line).collect()
+}
+
+/// Like `parse_script`, with the 1-based line number of each command
+pub fn script_lines(contents: &str) -> Vec<(usize, String)> {
+    contents.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| (number, line.to_string()))
+        .collect()
+}
+
+/// One command run from a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptStep {
+    pub line: usize,
+    pub command: String,
+    pub result: Result<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptReport {
+    pub steps: Vec<ScriptStep>,
+    /// Set when a failure stopped the script before its last command
+    pub aborted_at: Option<usize>,
+}
+
+impl ScriptReport {
+    pub fn failures(&self) -> impl Iterator<Item = &ScriptStep> {
+        self.steps.iter().filter(|step| step.result.is_err())
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+pub fn load_script(path: &Path) -> Result<Vec<String>, String> {
+    fs::read_to_string(path)
+        .map(|contents| parse_script(&contents))
+        .map_err(|e| format!("Failed to read script {:?}: {}", path, e))
+}
+
+/// Command history, appended to a file in the data dir as lines are entered
+pub struct CommandHistory {
+    path: Option<PathBuf>,
+    entries: VecDeque<String>,
+}
+
+impl CommandHistory {
+    pub fn in_memory() -> Self {
+        Self { path: None, entries: VecDeque::new() }
+    }
+
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut history = Self { path: None, entries: VecDeque::new() };
+        if let Ok(contents) = fs::read_to_string(&path) {
+            for line in contents.lines() {
+                history.remember(line);
+            }
+        }
+
+        // Rewrite the file so it doesn't grow without bound
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let mut contents = history.entries.iter().cloned().collect::<Vec<_>>().join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        let _ = fs::write(&path, contents);
+
+        history.path = Some(path);
+        history
+    }
+
+    fn remember(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        if line.is_empty() || self.entries.back().is_some_and(|last| last == line) {
+            return false;
+        }
+        self.entries.push_back(line.to_string());
+        while self.entries.len() > MAX_HISTORY {
+            self.entries.pop_front();
+        }
+        true
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if !self.remember(line) {
+            return;
+        }
+        if let Some(path) = &self.path {
+            let appended = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line.trim()));
+            if let Err(e) = appended {
+                tracing::warn!("Failed to save command history: {}", e);
+            }
+        }
+    }
+
+    /// Expands `!!` (last command) and `!<n>` (entry n from `history`)
+    pub fn expand(&self, line: &str) -> Result<String, String> {
+        let Some(reference) = line.trim().strip_prefix('!') else {
+            return Ok(line.to_string());
+        };
+
+        let entry = if reference == "!" {
+            self.entries.back()
+        } else {
+            let index = reference.parse::<usize>()
+                .map_err(|_| format!("Invalid history reference: !{}", reference))?;
+            index.checked_sub(1).and_then(|i| self.entries.get(i))
+        };
+        entry.cloned().ok_or_else(|| format!("No such history entry: !{}", reference))
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter()
+    }
+
+    pub fn format(&self, count: usize) -> String {
+        let skip = self.entries.len().saturating_sub(count);
+        self.entries.iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, entry)| format!("{:5}  {}\n", i + 1, entry))
+            .collect()
+    }
+}
+
+/// Candidates for the last word of `line`. The first word completes to
+/// command names, the second to a command's subcommands where it has any,
+/// and everything else to online player names.
+pub fn complete(line: &str, commands: &[&str], subcommands: impl Fn(&str) -> &'static [&'static str], players: &[String]) -> Vec<String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let completing_new_word = line.is_empty() || line.ends_with(char::is_whitespace);
+    let (prefix, previous) = if completing_new_word {
+        ("", words.as_slice())
+    } else {
+        (words[words.len() - 1], &words[..words.len() - 1])
+    };
+
+    let mut candidates: Vec<String> = match previous {
+        [] => commands.iter().map(|c| c.to_string()).collect(),
+        [command] if !subcommands(command).is_empty() => {
+            subcommands(command).iter().map(|s| s.to_string()).collect()
+        }
+        _ => players.to_vec(),
+    };
+
+    candidates.retain(|c| c.to_lowercase().starts_with(&prefix.to_lowercase()));
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Names the console completes. The line editor runs on its own thread, so
+/// the REPL refreshes these before each prompt rather than the helper
+/// reaching into the server.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionSource {
+    pub commands: Vec<String>,
+    pub players: Vec<String>,
+}
+
+/// rustyline helper completing command names, subcommands and player names
+pub struct ConsoleHelper {
+    source: Arc<RwLock<CompletionSource>>,
+    subcommands: fn(&str) -> &'static [&'static str],
+}
+
+impl ConsoleHelper {
+    pub fn new(subcommands: fn(&str) -> &'static [&'static str]) -> Self {
+        Self { source: Arc::new(RwLock::new(CompletionSource::default())), subcommands }
+    }
+
+    /// Handle for updating the names after the helper moves to the editor
+    pub fn source(&self) -> Arc<RwLock<CompletionSource>> {
+        self.source.clone()
+    }
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.trim_end_matches(|c: char| !c.is_whitespace()).len();
+        let source = self.source.read();
+        let commands: Vec<&str> = source.commands.iter().map(String::as_str).collect();
+        Ok((start, complete(line, &commands, self.subcommands, &source.players)))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ConsoleHelper {}
+
+impl Validator for ConsoleHelper {}
+
+impl Helper for ConsoleHelper {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptAction {
+    CancelLine,
+    Shutdown,
+}
+
+/// Tracks Ctrl-C presses: the first cancels the current line, a second one
+/// within `SHUTDOWN_WINDOW` asks for shutdown.
+#[derive(Debug, Default)]
+pub struct InterruptState {
+    last: Option<Instant>,
+}
+
+impl InterruptState {
+    pub fn interrupt(&mut self, now: Instant) -> InterruptAction {
+        match self.last {
+            Some(last) if now.duration_since(last) <= SHUTDOWN_WINDOW => {
+                self.last = None;
+                InterruptAction::Shutdown
+            }
+            _ => {
+                self.last = Some(now);
+                InterruptAction::CancelLine
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subcommands(command: &str) -> &'static [&'static str] {
+        match command {
+            "perf" => &["summary", "watch"],
+            _ => &[],
+        }
+    }
+
+    #[test]
+    fn test_options_from_args_and_env() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let options = ReplOptions::parse(args(&["--exec", "boot.txt", "--non-interactive"]), None).unwrap();
+        assert_eq!(options.exec, Some(PathBuf::from("boot.txt")));
+        assert!(options.non_interactive);
+
+        let options = ReplOptions::parse(args(&[]), Some("env.txt".into())).unwrap();
+        assert_eq!(options.exec, Some(PathBuf::from("env.txt")));
+        assert!(!options.non_interactive);
+
+        let options = ReplOptions::parse(args(&["--exec=cli.txt"]), Some("env.txt".into())).unwrap();
+        assert_eq!(options.exec, Some(PathBuf::from("cli.txt")));
+
+        let options = ReplOptions::parse(args(&["--init-script", "init.txt", "--on-error=abort"]), None).unwrap();
+        assert_eq!(options.exec, Some(PathBuf::from("init.txt")));
+        assert_eq!(options.on_error, ScriptErrorPolicy::Abort);
+        assert_eq!(ReplOptions::parse(args(&[]), None).unwrap().on_error, ScriptErrorPolicy::Continue);
+
+        assert!(ReplOptions::parse(args(&["--exec"]), None).is_err());
+        assert!(ReplOptions::parse(args(&["--on-error", "retry"]), None).is_err());
+        assert!(ReplOptions::parse(args(&["--non-interactive=yes"]), None).is_err());
+        assert!(ReplOptions::parse(args(&["--verbose"]), None).is_err());
+    }
+
+    #[test]
+    fn test_script_skips_comments_and_blanks() {
+        let script = "# warm up\nsay hello\n\n  tps  \n#done\n";
+        assert_eq!(parse_script(script), vec!["say hello", "tps"]);
+        assert_eq!(script_lines(script), vec![(2, "say hello".to_string()), (4, "tps".to_string())]);
+    }
+
+    #[test]
+    fn test_history_persists_and_expands() {
+        let dir = std::env::temp_dir().join(format!("rubidium-history-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(HISTORY_FILE);
+
+        let mut history = CommandHistory::load(&path);
+        history.push("status");
+        history.push("status");
+        history.push("kick Steve griefing");
+
+        let reloaded = CommandHistory::load(&path);
+        assert_eq!(reloaded.entries().collect::<Vec<_>>(), vec!["status", "kick Steve griefing"]);
+        assert_eq!(reloaded.expand("!!").unwrap(), "kick Steve griefing");
+        assert_eq!(reloaded.expand("!1").unwrap(), "status");
+        assert!(reloaded.expand("!9").is_err());
+        assert_eq!(reloaded.expand("tps").unwrap(), "tps");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_completion() {
+        let commands = ["perf", "players", "kick", "say"];
+        let players = vec!["Steve".to_string(), "Stella".to_string(), "Alex".to_string()];
+
+        assert_eq!(complete("p", &commands, subcommands, &players), vec!["perf", "players"]);
+        assert_eq!(complete("perf ", &commands, subcommands, &players), vec!["summary", "watch"]);
+        assert_eq!(complete("kick st", &commands, subcommands, &players), vec!["Stella", "Steve"]);
+        assert_eq!(complete("say hi a", &commands, subcommands, &players), vec!["Alex"]);
+    }
+
+    #[test]
+    fn test_helper_completes_the_word_before_the_cursor() {
+        let helper = ConsoleHelper::new(subcommands);
+        *helper.source().write() = CompletionSource {
+            commands: vec!["perf".into(), "players".into(), "kick".into()],
+            players: vec!["Steve".into(), "Stella".into()],
+        };
+        let history = rustyline::history::MemHistory::new();
+        let ctx = Context::new(&history);
+
+        assert_eq!(helper.complete("kick st", 7, &ctx).unwrap(), (5, vec!["Stella".to_string(), "Steve".to_string()]));
+        assert_eq!(helper.complete("perf w", 6, &ctx).unwrap(), (5, vec!["watch".to_string()]));
+        assert_eq!(helper.complete("pe Steve", 2, &ctx).unwrap(), (0, vec!["perf".to_string()]));
+        assert_eq!(helper.complete("kick ", 5, &ctx).unwrap().0, 5);
+    }
+
+    #[test]
+    fn test_second_interrupt_within_window_shuts_down() {
+        let mut state = InterruptState::default();
+        let start = Instant::now();
+
+        assert_eq!(state.interrupt(start), InterruptAction::CancelLine);
+        assert_eq!(state.interrupt(start + Duration::from_secs(3)), InterruptAction::CancelLine);
+        assert_eq!(state.interrupt(start + Duration::from_secs(4)), InterruptAction::Shutdown);
+        assert_eq!(state.interrupt(start + Duration::from_secs(5)), InterruptAction::CancelLine);
+    }
+}
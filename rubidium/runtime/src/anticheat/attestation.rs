@@ -0,0 +1,421 @@
+//! Launcher attestation
+//!
+//! Yellow Tale sends a manifest of file hashes when a player connects: its
+//! own version and binaries plus every enabled mod. Nothing here looks at
+//! the client's memory or process; the server only compares the hashes the
+//! launcher reports against its `AttestationPolicy`.
+//!
+//! Manifests and heartbeats are signed with an Ed25519 key generated once
+//! per install; only the public key is sent. The first hello from a player
+//! pins that public key to the player's account for as long as the server
+//! runs, so later hellos and heartbeats for the account must come from the
+//! same install, and a token seen on the wire can't be renewed by anyone
+//! else. `unpin` lets an admin accept a reinstalled launcher.
+
+use super::config::AttestationPolicy;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+use ahash::RandomState;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub name: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModDigest {
+    pub id: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityManifest {
+    pub install_id: Uuid,
+    pub launcher_version: String,
+    pub binaries: Vec<FileDigest>,
+    pub mods: Vec<ModDigest>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What the launcher sends. The manifest travels as the exact JSON string
+/// that was signed, so neither side has to agree on a canonical encoding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AttestationMessage {
+    Hello {
+        manifest: String,
+        signature: String,
+        /// Hex Ed25519 public key of the install
+        public_key: String,
+    },
+    Heartbeat {
+        token: String,
+        sequence: u64,
+        signature: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationGrant {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub heartbeat_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AttestationViolation {
+    #[error("malformed attestation: {0}")]
+    Malformed(String),
+    #[error("signature does not match the player's install key")]
+    BadSignature,
+    #[error("launcher {found} is older than the required {required}")]
+    OutdatedLauncher { found: String, required: String },
+    #[error("launcher file {name} does not match release {version}")]
+    TamperedLauncher { name: String, version: String },
+    #[error("mod {0} does not match any allowed build")]
+    TamperedMod(String),
+    #[error("mod {0} is not allowed on this server")]
+    DisallowedMod(String),
+    #[error("no attestation received")]
+    Missing,
+    #[error("attestation expired without a heartbeat")]
+    Expired,
+    #[error("unknown or superseded attestation token")]
+    UnknownToken,
+    #[error("heartbeat sequence {0} was already used")]
+    Replayed(u64),
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_public_key(text: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = from_hex(text)?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+/// Whether `signature` is the hex Ed25519 signature of `message` under `key`
+fn verify(key: &VerifyingKey, message: &[u8], signature: &str) -> bool {
+    let Some(bytes) = from_hex(signature).and_then(|b| <[u8; 64]>::try_from(b).ok()) else {
+        return false;
+    };
+    key.verify_strict(message, &Signature::from_bytes(&bytes)).is_ok()
+}
+
+/// The bytes a heartbeat signature covers
+pub fn heartbeat_payload(token: &str, sequence: u64) -> String {
+    format!("{}:{}", token, sequence)
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version.split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// Compare a manifest against the policy, reporting the first problem found
+pub fn check_manifest(policy: &AttestationPolicy, manifest: &IntegrityManifest) -> Result<(), AttestationViolation> {
+    if let Some(required) = &policy.min_launcher_version {
+        if version_parts(&manifest.launcher_version) < version_parts(required) {
+            return Err(AttestationViolation::OutdatedLauncher {
+                found: manifest.launcher_version.clone(),
+                required: required.clone(),
+            });
+        }
+    }
+
+    if let Some(release) = policy.launcher_builds.get(&manifest.launcher_version) {
+        for (name, sha256) in release {
+            let reported = manifest.binaries.iter().find(|b| &b.name == name);
+            if !reported.is_some_and(|b| b.sha256.eq_ignore_ascii_case(sha256)) {
+                return Err(AttestationViolation::TamperedLauncher {
+                    name: name.clone(),
+                    version: manifest.launcher_version.clone(),
+                });
+            }
+        }
+    }
+
+    for module in &manifest.mods {
+        match policy.allowed_mods.get(&module.id) {
+            Some(hashes) if !hashes.iter().any(|h| h.eq_ignore_ascii_case(&module.sha256)) => {
+                return Err(AttestationViolation::TamperedMod(module.id.clone()));
+            }
+            None if !policy.allow_unlisted_mods => {
+                return Err(AttestationViolation::DisallowedMod(module.id.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+struct Session {
+    token: Option<String>,
+    last_sequence: Option<u64>,
+    expires_at: DateTime<Utc>,
+}
+
+/// Per-player attestation state and the install key pinned to each player
+pub struct AttestationRegistry {
+    keys: DashMap<Uuid, VerifyingKey, RandomState>,
+    sessions: DashMap<Uuid, Session, RandomState>,
+}
+
+impl AttestationRegistry {
+    pub fn new() -> Self {
+        Self {
+            keys: DashMap::with_hasher(RandomState::new()),
+            sessions: DashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    /// Start the clock for a player who just joined; they are expired if no
+    /// hello arrives within the heartbeat timeout.
+    pub fn expect(&self, policy: &AttestationPolicy, player_id: Uuid, now: DateTime<Utc>) {
+        self.sessions.entry(player_id).or_insert(Session {
+            token: None,
+            last_sequence: None,
+            expires_at: now + Duration::seconds(policy.heartbeat_timeout_secs as i64),
+        });
+    }
+
+    pub fn handle(&self, policy: &AttestationPolicy, player_id: Uuid, message: &AttestationMessage, now: DateTime<Utc>) -> Result<AttestationGrant, AttestationViolation> {
+        let result = match message {
+            AttestationMessage::Hello { manifest, signature, public_key } => self.hello(policy, player_id, manifest, signature, public_key, now),
+            AttestationMessage::Heartbeat { token, sequence, signature } => self.heartbeat(policy, player_id, token, *sequence, signature, now),
+        };
+        if result.is_err() {
+            self.sessions.remove(&player_id);
+        }
+        result
+    }
+
+    fn hello(&self, policy: &AttestationPolicy, player_id: Uuid, manifest: &str, signature: &str, public_key: &str, now: DateTime<Utc>) -> Result<AttestationGrant, AttestationViolation> {
+        let parsed: IntegrityManifest = serde_json::from_str(manifest)
+            .map_err(|e| AttestationViolation::Malformed(e.to_string()))?;
+        let offered = parse_public_key(public_key)
+            .ok_or_else(|| AttestationViolation::Malformed("public key is not a hex Ed25519 key".to_string()))?;
+
+        // The player id is the authenticated account, so only its owner can
+        // pin a key to it; the install id is the client's claim and isn't used
+        let pinned = self.keys.get(&player_id).map(|k| *k);
+        let key = pinned.unwrap_or(offered);
+        if !verify(&key, manifest.as_bytes(), signature) {
+            return Err(AttestationViolation::BadSignature);
+        }
+        self.keys.insert(player_id, key);
+
+        check_manifest(policy, &parsed)?;
+
+        let grant = AttestationGrant {
+            token: Uuid::new_v4().simple().to_string(),
+            expires_at: now + Duration::seconds(policy.heartbeat_timeout_secs as i64),
+            heartbeat_interval_secs: policy.heartbeat_interval_secs,
+        };
+        self.sessions.insert(player_id, Session {
+            token: Some(grant.token.clone()),
+            last_sequence: None,
+            expires_at: grant.expires_at,
+        });
+        Ok(grant)
+    }
+
+    /// Forget the key pinned to a player, so their next hello pins a new one
+    pub fn unpin(&self, player_id: Uuid) -> bool {
+        self.keys.remove(&player_id).is_some()
+    }
+
+    fn heartbeat(&self, policy: &AttestationPolicy, player_id: Uuid, token: &str, sequence: u64, signature: &str, now: DateTime<Utc>) -> Result<AttestationGrant, AttestationViolation> {
+        let mut session = self.sessions.get_mut(&player_id).ok_or(AttestationViolation::UnknownToken)?;
+        if session.token.as_deref() != Some(token) {
+            return Err(AttestationViolation::UnknownToken);
+        }
+        if session.expires_at <= now {
+            return Err(AttestationViolation::Expired);
+        }
+        if session.last_sequence.is_some_and(|last| sequence <= last) {
+            return Err(AttestationViolation::Replayed(sequence));
+        }
+
+        let key = self.keys.get(&player_id).map(|k| *k).ok_or(AttestationViolation::UnknownToken)?;
+        if !verify(&key, heartbeat_payload(token, sequence).as_bytes(), signature) {
+            return Err(AttestationViolation::BadSignature);
+        }
+
+        session.last_sequence = Some(sequence);
+        session.expires_at = now + Duration::seconds(policy.heartbeat_timeout_secs as i64);
+        Ok(AttestationGrant {
+            token: token.to_string(),
+            expires_at: session.expires_at,
+            heartbeat_interval_secs: policy.heartbeat_interval_secs,
+        })
+    }
+
+    /// Drop and return every player whose attestation has lapsed, with
+    /// whether they had ever attested
+    pub fn expire(&self, now: DateTime<Utc>) -> Vec<(Uuid, AttestationViolation)> {
+        let mut expired = Vec::new();
+        self.sessions.retain(|player_id, session| {
+            if session.expires_at > now {
+                return true;
+            }
+            let violation = if session.token.is_some() { AttestationViolation::Expired } else { AttestationViolation::Missing };
+            expired.push((*player_id, violation));
+            false
+        });
+        expired
+    }
+
+    pub fn is_attested(&self, player_id: Uuid, now: DateTime<Utc>) -> bool {
+        self.sessions.get(&player_id)
+            .is_some_and(|s| s.token.is_some() && s.expires_at > now)
+    }
+
+    pub fn remove(&self, player_id: Uuid) {
+        self.sessions.remove(&player_id);
+    }
+}
+
+impl Default for AttestationRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::collections::HashMap;
+
+    /// Shared with the launcher's integrity module so the two can't drift.
+    const HELLO_FIXTURE: &str = include_str!("../../tests/fixtures/attestation_hello.json");
+
+    fn fixture() -> (SigningKey, AttestationMessage) {
+        let value: serde_json::Value = serde_json::from_str(HELLO_FIXTURE).unwrap();
+        let seed = from_hex(value["seed"].as_str().unwrap()).unwrap();
+        (signing_key(&seed), serde_json::from_value(value["message"].clone()).unwrap())
+    }
+
+    fn signing_key(seed: &[u8]) -> SigningKey {
+        SigningKey::from_bytes(&seed.try_into().unwrap())
+    }
+
+    fn sign(key: &SigningKey, message: &[u8]) -> String {
+        key.sign(message).to_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn policy() -> AttestationPolicy {
+        AttestationPolicy {
+            enabled: true,
+            min_launcher_version: Some("0.1.0".to_string()),
+            allowed_mods: HashMap::from([(
+                "minimap".to_string(),
+                vec!["9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string()],
+            )]),
+            ..AttestationPolicy::default()
+        }
+    }
+
+    fn signed_hello(key: &SigningKey, manifest: &IntegrityManifest) -> AttestationMessage {
+        let manifest = serde_json::to_string(manifest).unwrap();
+        AttestationMessage::Hello {
+            signature: sign(key, manifest.as_bytes()),
+            manifest,
+            public_key: key.verifying_key().to_bytes().iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_launcher_fixture_is_accepted() {
+        let (key, hello) = fixture();
+        let AttestationMessage::Hello { manifest, signature, public_key } = &hello else {
+            panic!("fixture is not a hello");
+        };
+        // Ed25519 signatures are deterministic, so the fixture is reproducible
+        assert_eq!(&sign(&key, manifest.as_bytes()), signature);
+        assert_eq!(parse_public_key(public_key), Some(key.verifying_key()));
+
+        let registry = AttestationRegistry::new();
+        let now = Utc::now();
+        let grant = registry.handle(&policy(), Uuid::new_v4(), &hello, now).unwrap();
+        assert_eq!(grant.expires_at, now + Duration::seconds(90));
+    }
+
+    #[test]
+    fn test_tampered_mod_hash_is_rejected() {
+        let (key, hello) = fixture();
+        let AttestationMessage::Hello { manifest, .. } = hello else {
+            panic!("fixture is not a hello");
+        };
+        let mut manifest: IntegrityManifest = serde_json::from_str(&manifest).unwrap();
+        manifest.mods[0].sha256 = "0".repeat(64);
+
+        let registry = AttestationRegistry::new();
+        let player = Uuid::new_v4();
+        let result = registry.handle(&policy(), player, &signed_hello(&key, &manifest), Utc::now());
+        assert_eq!(result, Err(AttestationViolation::TamperedMod("minimap".to_string())));
+        assert!(!registry.is_attested(player, Utc::now()));
+
+        // Re-signing under a different key doesn't get past the pinned one
+        manifest.mods[0].sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string();
+        let forged = signed_hello(&signing_key(&[7; 32]), &manifest);
+        assert_eq!(registry.handle(&policy(), player, &forged, Utc::now()), Err(AttestationViolation::BadSignature));
+
+        // The same install can attest as another player, but only after an
+        // admin unpins does the forged key get accepted for this one
+        assert!(registry.handle(&policy(), Uuid::new_v4(), &forged, Utc::now()).is_ok());
+        assert!(registry.unpin(player));
+        assert!(registry.handle(&policy(), player, &forged, Utc::now()).is_ok());
+
+        let strict = AttestationPolicy { allow_unlisted_mods: false, allowed_mods: HashMap::new(), ..policy() };
+        assert_eq!(check_manifest(&strict, &manifest), Err(AttestationViolation::DisallowedMod("minimap".to_string())));
+        let newer = AttestationPolicy { min_launcher_version: Some("0.10.0".to_string()), ..policy() };
+        assert!(matches!(check_manifest(&newer, &manifest), Err(AttestationViolation::OutdatedLauncher { .. })));
+    }
+
+    #[test]
+    fn test_stale_heartbeat_expires_attestation() {
+        let (key, hello) = fixture();
+        let registry = AttestationRegistry::new();
+        let player = Uuid::new_v4();
+        let start = Utc::now();
+        let grant = registry.handle(&policy(), player, &hello, start).unwrap();
+
+        let beat = |sequence: u64| AttestationMessage::Heartbeat {
+            token: grant.token.clone(),
+            sequence,
+            signature: sign(&key, heartbeat_payload(&grant.token, sequence).as_bytes()),
+        };
+
+        let renewed = registry.handle(&policy(), player, &beat(1), start + Duration::seconds(30)).unwrap();
+        assert_eq!(renewed.expires_at, start + Duration::seconds(120));
+        assert_eq!(registry.handle(&policy(), player, &beat(1), start + Duration::seconds(31)), Err(AttestationViolation::Replayed(1)));
+
+        // The replay dropped the session; attest again and then go quiet
+        registry.handle(&policy(), player, &hello, start).unwrap();
+        assert!(registry.expire(start + Duration::seconds(89)).is_empty());
+        assert!(registry.is_attested(player, start + Duration::seconds(89)));
+        assert_eq!(registry.expire(start + Duration::seconds(90)), vec![(player, AttestationViolation::Expired)]);
+        assert!(!registry.is_attested(player, start + Duration::seconds(90)));
+
+        // A player who never says hello is reported as missing
+        let silent = Uuid::new_v4();
+        registry.expect(&policy(), silent, start);
+        assert_eq!(registry.expire(start + Duration::seconds(90)), vec![(silent, AttestationViolation::Missing)]);
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnticheatConfig {
@@ -10,6 +11,8 @@ pub struct AnticheatConfig {
     pub sample_rate: f64,
     pub log_violations: bool,
     pub auto_kick_threshold: u32,
+    #[serde(default)]
+    pub attestation: AttestationPolicy,
 }
 
 impl Default for AnticheatConfig {
@@ -23,6 +26,7 @@ impl Default for AnticheatConfig {
             sample_rate: 0.25,
             log_violations: true,
             auto_kick_threshold: 10,
+            attestation: AttestationPolicy::default(),
         }
     }
 }
@@ -98,6 +102,38 @@ impl Default for PacketCheckConfig {
     }
 }
 
+/// What a launcher attestation must satisfy. Servers opt in with `enabled`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttestationPolicy {
+    pub enabled: bool,
+    pub min_launcher_version: Option<String>,
+    /// Release file hashes by launcher version; unlisted versions aren't checked
+    pub launcher_builds: HashMap<String, HashMap<String, String>>,
+    /// Accepted SHA-256 hashes by mod id
+    pub allowed_mods: HashMap<String, Vec<String>>,
+    pub allow_unlisted_mods: bool,
+    pub heartbeat_interval_secs: u64,
+    /// How long an attestation lasts without a heartbeat
+    pub heartbeat_timeout_secs: u64,
+    pub kick_on_violation: bool,
+}
+
+impl Default for AttestationPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_launcher_version: None,
+            launcher_builds: HashMap::new(),
+            allowed_mods: HashMap::new(),
+            allow_unlisted_mods: true,
+            heartbeat_interval_secs: 30,
+            heartbeat_timeout_secs: 90,
+            kick_on_violation: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum MalformedPacketAction {
     Ignore,
@@ -28,6 +28,7 @@ pub enum FindingType {
     InvalidPacket,
     KeepAliveManipulation,
     TimerHack,
+    AttestationFailure,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -2,7 +2,9 @@ pub mod service;
 pub mod detectors;
 pub mod findings;
 pub mod config;
+pub mod attestation;
 
 pub use service::AnticheatService;
 pub use findings::{Finding, FindingLevel, FindingRing, FindingType};
-pub use config::{AnticheatConfig, MovementCheckConfig, CombatCheckConfig, PacketCheckConfig, MalformedPacketAction};
+pub use config::{AnticheatConfig, MovementCheckConfig, CombatCheckConfig, PacketCheckConfig, MalformedPacketAction, AttestationPolicy};
+pub use attestation::{AttestationMessage, AttestationGrant, AttestationViolation, IntegrityManifest};
@@ -1,4 +1,5 @@
-use super::config::AnticheatConfig;
+use super::attestation::{AttestationGrant, AttestationMessage, AttestationRegistry, AttestationViolation};
+use super::config::{AnticheatConfig, AttestationPolicy};
 use super::detectors::*;
 use super::detectors::movement::*;
 use super::detectors::combat::*;
@@ -12,6 +13,7 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use tracing::info;
 use uuid::Uuid;
 use ahash::RandomState;
+use chrono::{DateTime, Utc};
 
 const MAX_HISTORY_PER_PLAYER: usize = 20;
 
@@ -24,6 +26,7 @@ pub struct AnticheatService {
     packet_stats: DashMap<Uuid, PlayerPacketStats, RandomState>,
     
     findings: Arc<FindingRing>,
+    attestations: AttestationRegistry,
     
     movement_detectors: RwLock<Vec<Box<dyn MovementDetector>>>,
     combat_detectors: RwLock<Vec<Box<dyn CombatDetector>>>,
@@ -62,6 +65,7 @@ impl AnticheatService {
             combat_history: DashMap::with_hasher(RandomState::new()),
             packet_stats: DashMap::with_hasher(RandomState::new()),
             findings,
+            attestations: AttestationRegistry::new(),
             movement_detectors: RwLock::new(movement_detectors),
             combat_detectors: RwLock::new(combat_detectors),
             packet_detectors: RwLock::new(packet_detectors),
@@ -229,6 +233,63 @@ impl AnticheatService {
         self.movement_history.remove(&player_id);
         self.combat_history.remove(&player_id);
         self.packet_stats.remove(&player_id);
+        self.attestations.remove(player_id);
+    }
+
+    pub fn attestation_policy(&self) -> AttestationPolicy {
+        self.config.read().attestation.clone()
+    }
+
+    /// Give a newly joined player until the heartbeat timeout to attest
+    pub fn expect_attestation(&self, player_id: Uuid, now: DateTime<Utc>) {
+        let policy = self.attestation_policy();
+        if policy.enabled {
+            self.attestations.expect(&policy, player_id, now);
+        }
+    }
+
+    /// Verify a JSON hello or heartbeat from the player's launcher. Failures
+    /// are recorded as findings and end the player's attestation.
+    pub fn handle_attestation(&self, player_id: Uuid, message: &str, now: DateTime<Utc>) -> Result<AttestationGrant, AttestationViolation> {
+        let policy = self.attestation_policy();
+        let result = serde_json::from_str::<AttestationMessage>(message)
+            .map_err(|e| AttestationViolation::Malformed(e.to_string()))
+            .and_then(|message| self.attestations.handle(&policy, player_id, &message, now));
+        if let Err(violation) = &result {
+            self.attestations.remove(player_id);
+            self.record_attestation_failure(player_id, violation);
+        }
+        result
+    }
+
+    /// Online players whose attestation lapsed or never arrived; lapsed
+    /// entries for players who already left are dropped quietly
+    pub fn expire_attestations(&self, now: DateTime<Utc>, is_online: impl Fn(Uuid) -> bool) -> Vec<(Uuid, AttestationViolation)> {
+        let mut expired = self.attestations.expire(now);
+        expired.retain(|(player_id, _)| is_online(*player_id));
+        for (player_id, violation) in &expired {
+            self.record_attestation_failure(*player_id, violation);
+        }
+        expired
+    }
+
+    pub fn is_attested(&self, player_id: Uuid, now: DateTime<Utc>) -> bool {
+        self.attestations.is_attested(player_id, now)
+    }
+
+    /// Let a player attest from a new install, e.g. after reinstalling
+    pub fn unpin_attestation_key(&self, player_id: Uuid) -> bool {
+        self.attestations.unpin(player_id)
+    }
+
+    fn record_attestation_failure(&self, player_id: Uuid, violation: &AttestationViolation) {
+        let tick = self.current_tick.load(Ordering::Relaxed);
+        self.findings.push(Finding::new(
+            player_id,
+            crate::anticheat::FindingType::AttestationFailure,
+            FindingLevel::Definite,
+            violation.to_string(),
+        ).with_tick(tick));
     }
 
     pub fn reload_config(&self, config: AnticheatConfig) {
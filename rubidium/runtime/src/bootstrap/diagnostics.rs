@@ -88,6 +88,18 @@ impl DiagnosticResult {
         }
     }
 
+    /// A phase that never ran because one it depends on failed
+    pub fn skipped(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            success: false,
+            message: Some(message.into()),
+            duration: Duration::ZERO,
+            level: DiagnosticLevel::Warning,
+            details: Vec::new(),
+        }
+    }
+
     pub fn warning(name: impl Into<String>, message: impl Into<String>, duration: Duration) -> Self {
         Self {
             name: name.into(),
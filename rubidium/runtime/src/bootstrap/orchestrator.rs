@@ -1,23 +1,46 @@
 use super::phases::BootstrapPhase;
-use super::diagnostics::{StartupReport, DiagnosticResult};
-use crate::bridge::{GameServerBridge, GameServerConfig};
+use super::diagnostics::{StartupReport, DiagnosticResult, DiagnosticLevel};
+use crate::admin::http::{HealthEndpoint, Readiness};
+use crate::bridge::{GameServerBridge, GameServerConfig, ServerStatus};
 use crate::anticheat::AnticheatService;
 use crate::core::config::ConfigManager;
 use crate::core::plugins::PluginManager;
 use crate::core::scheduler::Scheduler;
 use crate::core::performance::PerformanceMonitor;
+use crate::core::task_graph::{GraphStatus, TaskGraph, TaskOutput, TaskOutputs, TaskStatus};
 use crate::core::telemetry::TelemetryCollector;
 use crate::events::EventBus;
 use crate::features::{AdaptiveScheduler, WorldHeatmap, SessionManager};
 use parking_lot::RwLock;
-use std::future::Future;
+use std::any::Any;
 use std::path::PathBuf;
-use std::pin::Pin;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{info, warn, error, debug};
+use chrono::Utc;
 
-type PhaseFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + 'a>>;
+type SharedReport = Arc<RwLock<StartupReport>>;
+
+/// What the bootstrap phases share besides each other's outputs
+#[derive(Clone)]
+struct PhaseContext {
+    config_path: PathBuf,
+    server_jar: PathBuf,
+    report: SharedReport,
+    readiness: Arc<Readiness>,
+}
+
+/// Output of the CoreServices phase
+#[derive(Clone)]
+struct CoreServices {
+    telemetry: Arc<TelemetryCollector>,
+    performance: Arc<PerformanceMonitor>,
+    scheduler: Arc<Scheduler>,
+    event_bus: Arc<EventBus>,
+    adaptive_scheduler: Arc<AdaptiveScheduler>,
+    world_heatmap: Arc<WorldHeatmap>,
+    session_manager: Arc<SessionManager>,
+}
 
 pub struct BootstrapOrchestrator {
     config_path: PathBuf,
@@ -35,9 +58,10 @@ pub struct BootstrapOrchestrator {
     world_heatmap: Option<Arc<WorldHeatmap>>,
     session_manager: Option<Arc<SessionManager>>,
     
-    current_phase: RwLock<BootstrapPhase>,
+    task_graph: Arc<GraphStatus>,
+    readiness: Arc<Readiness>,
     start_time: Option<Instant>,
-    report: RwLock<StartupReport>,
+    report: SharedReport,
 }
 
 impl BootstrapOrchestrator {
@@ -56,241 +80,71 @@ impl BootstrapOrchestrator {
             adaptive_scheduler: None,
             world_heatmap: None,
             session_manager: None,
-            current_phase: RwLock::new(BootstrapPhase::Initializing),
+            task_graph: Arc::new(GraphStatus::default()),
+            readiness: Arc::new(Readiness::default()),
             start_time: None,
-            report: RwLock::new(StartupReport::new()),
+            report: Arc::new(RwLock::new(StartupReport::new())),
         }
     }
 
+    /// Runs the phases as a task graph: independent phases start together and
+    /// a failed phase skips the phases that depend on it.
     pub async fn bootstrap(&mut self) -> Result<(), String> {
         self.start_time = Some(Instant::now());
         info!("=== Rubidium Server Bootstrap ===");
-        
-        self.run_phase(BootstrapPhase::Configuration, |this| Box::pin(this.phase_configuration())).await?;
-        self.run_phase(BootstrapPhase::Verification, |this| Box::pin(this.phase_verification())).await?;
-        self.run_phase(BootstrapPhase::CoreServices, |this| Box::pin(this.phase_core_services())).await?;
-        self.run_phase(BootstrapPhase::GameServer, |this| Box::pin(this.phase_game_server())).await?;
-        self.run_phase(BootstrapPhase::EventSubscriptions, |this| Box::pin(this.phase_event_subscriptions())).await?;
-        self.run_phase(BootstrapPhase::Plugins, |this| Box::pin(this.phase_plugins())).await?;
-        self.run_phase(BootstrapPhase::Anticheat, |this| Box::pin(this.phase_anticheat())).await?;
-        self.run_phase(BootstrapPhase::Ready, |this| Box::pin(this.phase_ready())).await?;
-        
-        let elapsed = self.start_time.unwrap().elapsed();
-        self.report.write().total_time = elapsed;
-        
-        info!("=== Bootstrap Complete in {:.2}s ===", elapsed.as_secs_f64());
-        self.print_report();
-        
-        Ok(())
-    }
 
-    async fn run_phase<F>(&mut self, phase: BootstrapPhase, f: F) -> Result<(), String>
-    where
-        F: for<'a> FnOnce(&'a mut Self) -> PhaseFuture<'a>,
-    {
-        let phase_start = Instant::now();
-        *self.current_phase.write() = phase;
-        info!("[{:?}] Starting...", phase);
-        
-        let result = f(self).await;
-        let elapsed = phase_start.elapsed();
-        
-        let diagnostic = match &result {
-            Ok(_) => {
-                info!("[{:?}] Complete ({:.2}ms)", phase, elapsed.as_secs_f64() * 1000.0);
-                DiagnosticResult::success(format!("{:?}", phase), elapsed)
-            }
-            Err(e) => {
-                error!("[{:?}] Failed: {}", phase, e);
-                DiagnosticResult::failure(format!("{:?}", phase), e.clone(), elapsed)
-            }
-        };
-        
-        self.report.write().phases.push(diagnostic);
-        result
-    }
-
-    async fn phase_configuration(&mut self) -> Result<(), String> {
-        debug!("Loading configuration from {:?}", self.config_path);
-        
-        if !self.config_path.exists() {
-            return Err(format!("Config file not found: {:?}", self.config_path));
+        let mut graph = TaskGraph::new();
+        for phase in BootstrapPhase::GRAPH {
+            let depends_on: Vec<String> = phase.depends_on().iter().map(|p| p.name()).collect();
+            let depends_on: Vec<&str> = depends_on.iter().map(String::as_str).collect();
+            let context = PhaseContext {
+                config_path: self.config_path.clone(),
+                server_jar: self.server_jar.clone(),
+                report: self.report.clone(),
+                readiness: self.readiness.clone(),
+            };
+            graph.add(phase.name(), &depends_on, move |inputs| run_phase(phase, context, inputs))
+                .map_err(|e| e.to_string())?;
         }
-        
-        let config = Arc::new(ConfigManager::new(
-            self.config_path.to_string_lossy().as_ref()
-        )?);
-        
-        self.report.write().add_info("Configuration loaded successfully");
-        self.config = Some(config);
-        Ok(())
-    }
+        self.task_graph = graph.status();
 
-    async fn phase_verification(&mut self) -> Result<(), String> {
-        debug!("Verifying server JAR: {:?}", self.server_jar);
-        
-        if !self.server_jar.exists() {
-            return Err(format!("Server JAR not found: {:?}", self.server_jar));
+        let result = graph.run().await.map_err(|e| e.to_string())?;
+        let outputs = &result.outputs;
+        self.config = output(outputs, BootstrapPhase::Configuration);
+        self.game_server = output(outputs, BootstrapPhase::GameServer);
+        self.plugins = output(outputs, BootstrapPhase::Plugins);
+        self.anticheat = output(outputs, BootstrapPhase::Anticheat);
+        if let Some(core) = output::<CoreServices>(outputs, BootstrapPhase::CoreServices) {
+            self.telemetry = Some(core.telemetry);
+            self.performance = Some(core.performance);
+            self.scheduler = Some(core.scheduler);
+            self.event_bus = Some(core.event_bus);
+            self.adaptive_scheduler = Some(core.adaptive_scheduler);
+            self.world_heatmap = Some(core.world_heatmap);
+            self.session_manager = Some(core.session_manager);
         }
-        
-        let metadata = std::fs::metadata(&self.server_jar)
-            .map_err(|e| format!("Cannot read JAR file: {}", e))?;
-        
-        let size_mb = metadata.len() as f64 / (1024.0 * 1024.0);
-        self.report.write().add_info(format!("Server JAR: {:.2} MB", size_mb));
-        
-        if let Ok(output) = std::process::Command::new("java")
-            .arg("-version")
-            .output()
+
+        let elapsed = self.start_time.unwrap().elapsed();
         {
-            let version = String::from_utf8_lossy(&output.stderr);
-            if let Some(line) = version.lines().next() {
-                self.report.write().add_info(format!("Java: {}", line));
+            let mut report = self.report.write();
+            for task in &result.tasks {
+                let duration = task.duration.unwrap_or_default();
+                report.phases.push(match &task.status {
+                    TaskStatus::Completed => DiagnosticResult::success(&task.name, duration),
+                    TaskStatus::Failed(e) => DiagnosticResult::failure(&task.name, e.clone(), duration),
+                    status => DiagnosticResult::skipped(&task.name, status.to_string()),
+                });
             }
-        } else {
-            self.report.write().add_warning("Java version check failed");
+            report.total_time = elapsed;
         }
-        
-        Ok(())
-    }
-
-    async fn phase_core_services(&mut self) -> Result<(), String> {
-        debug!("Initializing core services");
-        
-        let telemetry = Arc::new(TelemetryCollector::new());
-        let performance = Arc::new(PerformanceMonitor::new(telemetry.clone()));
-        let scheduler = Arc::new(Scheduler::new(performance.clone()));
-        let event_bus = Arc::new(EventBus::new());
-        
-        let adaptive_scheduler = Arc::new(AdaptiveScheduler::new(50.0));
-        let world_heatmap = Arc::new(WorldHeatmap::new(256));
-        let session_manager = Arc::new(SessionManager::new(Duration::from_secs(3600)));
-        
-        self.telemetry = Some(telemetry);
-        self.performance = Some(performance);
-        self.scheduler = Some(scheduler);
-        self.event_bus = Some(event_bus);
-        self.adaptive_scheduler = Some(adaptive_scheduler);
-        self.world_heatmap = Some(world_heatmap);
-        self.session_manager = Some(session_manager);
-        
-        self.report.write().add_info("Core services initialized");
-        Ok(())
-    }
 
-    async fn phase_game_server(&mut self) -> Result<(), String> {
-        debug!("Starting game server");
-        
-        let game_config = GameServerConfig {
-            jar_path: self.server_jar.clone(),
-            working_dir: self.server_jar.parent()
-                .unwrap_or(&PathBuf::from("."))
-                .to_path_buf(),
-            ..Default::default()
-        };
-        
-        let game_server = Arc::new(GameServerBridge::new(game_config));
-        game_server.start().await?;
-        
-        self.game_server = Some(game_server);
-        self.report.write().add_info("Game server started");
-        Ok(())
-    }
-
-    async fn phase_event_subscriptions(&mut self) -> Result<(), String> {
-        debug!("Setting up event subscriptions");
-        
-        let event_bus = self.event_bus.as_ref().unwrap();
-        let game_server = self.game_server.as_ref().unwrap();
-        
-        let mut receiver = game_server.subscribe_events();
-        let event_bus_clone = event_bus.clone();
-        let session_manager = self.session_manager.as_ref().unwrap().clone();
-        let world_heatmap = self.world_heatmap.as_ref().unwrap().clone();
-        
-        tokio::spawn(async move {
-            while let Ok(event) = receiver.recv().await {
-                event_bus_clone.emit(event.clone()).await;
-                
-                match &event {
-                    crate::bridge::GameEvent::PlayerJoin(info) => {
-                        session_manager.create_session(info.id, info.name.clone());
-                    }
-                    crate::bridge::GameEvent::PlayerQuit { id, .. } => {
-                        session_manager.remove_session(*id);
-                    }
-                    crate::bridge::GameEvent::PlayerMove { x, z, .. } => {
-                        world_heatmap.record_player_position(*x, *z, "world");
-                    }
-                    crate::bridge::GameEvent::BlockChange { x, z, world, .. } |
-                    crate::bridge::GameEvent::BlockBreak { x, z, world, .. } |
-                    crate::bridge::GameEvent::BlockPlace { x, z, world, .. } => {
-                        world_heatmap.record_block_change(*x as f64, *z as f64, world);
-                    }
-                    _ => {}
-                }
-            }
-        });
-        
-        self.report.write().add_info("Event subscriptions configured");
-        Ok(())
-    }
-
-    async fn phase_plugins(&mut self) -> Result<(), String> {
-        debug!("Loading plugins");
-        
-        let config = self.config.as_ref().unwrap();
-        let plugins = Arc::new(PluginManager::new(config.clone()));
-        
-        if let Err(e) = plugins.load_all().await {
-            self.report.write().add_warning(format!("Plugin loading: {}", e));
+        if let Some((phase, e)) = result.first_failure() {
+            self.print_report();
+            return Err(format!("{} failed: {}", phase, e));
         }
         
-        let count = plugins.count();
-        self.plugins = Some(plugins);
-        self.report.write().add_info(format!("{} plugins loaded", count));
-        Ok(())
-    }
-
-    async fn phase_anticheat(&mut self) -> Result<(), String> {
-        debug!("Initializing anticheat");
-        
-        let anticheat_config = crate::anticheat::AnticheatConfig::default();
-        let anticheat = Arc::new(AnticheatService::new(anticheat_config));
-        
-        let event_bus = self.event_bus.as_ref().unwrap();
-        let anticheat_clone = anticheat.clone();
-        let mut receiver = event_bus.subscribe();
-        
-        tokio::spawn(async move {
-            while let Ok(event) = receiver.recv().await {
-                match event {
-                    crate::bridge::GameEvent::PlayerMove { id, x, y, z, yaw, pitch, on_ground } => {
-                        let snapshot = crate::abstraction::MovementSnapshot::new(x, y, z, yaw, pitch);
-                        anticheat_clone.process_movement(id, snapshot);
-                    }
-                    crate::bridge::GameEvent::PlayerAttack { attacker_id, target_id, damage, distance } => {
-                        let snapshot = crate::abstraction::CombatSnapshot::attack(target_id, distance, 0.0);
-                        anticheat_clone.process_combat(attacker_id, snapshot);
-                    }
-                    _ => {}
-                }
-            }
-        });
-        
-        self.anticheat = Some(anticheat);
-        self.report.write().add_info("Anticheat initialized with 25% sampling");
-        Ok(())
-    }
-
-    async fn phase_ready(&mut self) -> Result<(), String> {
-        debug!("Finalizing startup");
-        
-        self.scheduler.as_ref().unwrap().start().await;
-        self.performance.as_ref().unwrap().start_monitoring().await;
-        
-        let player_count = self.game_server.as_ref().unwrap().player_count();
-        self.report.write().add_info(format!("Server ready with {} players", player_count));
+        info!("=== Bootstrap Complete in {:.2}s ===", elapsed.as_secs_f64());
+        self.print_report();
         
         Ok(())
     }
@@ -304,7 +158,11 @@ impl BootstrapOrchestrator {
         info!("├─────────────────────────────────────────┤");
         
         for phase in &report.phases {
-            let status = if phase.success { "✓" } else { "✗" };
+            let status = match (phase.success, phase.level) {
+                (true, _) => "✓",
+                (false, DiagnosticLevel::Warning) => "-",
+                _ => "✗",
+            };
             let time = phase.duration.as_millis();
             info!("│ {} {:25} {:8}ms │", status, phase.name, time);
         }
@@ -327,6 +185,28 @@ impl BootstrapOrchestrator {
         }
     }
 
+    /// Stops services in reverse start order: plugins, scheduler, then the game server
+    pub async fn shutdown(&self) -> Result<(), String> {
+        info!("=== Rubidium Server Shutdown ===");
+
+        if let Some(plugins) = &self.plugins {
+            plugins.unload_all().await;
+        }
+
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.stop().await;
+        }
+
+        if let Some(game_server) = &self.game_server {
+            if !matches!(game_server.status(), ServerStatus::Offline | ServerStatus::Stopping | ServerStatus::Crashed) {
+                game_server.stop().await?;
+            }
+        }
+
+        info!("Shutdown complete");
+        Ok(())
+    }
+
     pub fn game_server(&self) -> Option<&Arc<GameServerBridge>> {
         self.game_server.as_ref()
     }
@@ -342,4 +222,358 @@ impl BootstrapOrchestrator {
     pub fn session_manager(&self) -> Option<&Arc<SessionManager>> {
         self.session_manager.as_ref()
     }
+
+    pub fn config(&self) -> Option<&Arc<ConfigManager>> {
+        self.config.as_ref()
+    }
+
+    pub fn performance(&self) -> Option<&Arc<PerformanceMonitor>> {
+        self.performance.as_ref()
+    }
+
+    pub fn scheduler(&self) -> Option<&Arc<Scheduler>> {
+        self.scheduler.as_ref()
+    }
+
+    pub fn plugins(&self) -> Option<&Arc<PluginManager>> {
+        self.plugins.as_ref()
+    }
+
+    /// Status of each bootstrap phase in the task graph
+    pub fn task_graph(&self) -> Arc<GraphStatus> {
+        self.task_graph.clone()
+    }
+
+    /// What `/healthz` and `/readyz` report; updated as each phase completes
+    pub fn readiness(&self) -> Arc<Readiness> {
+        self.readiness.clone()
+    }
+}
+
+fn output<T: Any + Send + Sync + Clone>(outputs: &TaskOutputs, phase: BootstrapPhase) -> Option<T> {
+    outputs.get::<T>(&phase.name()).cloned()
+}
+
+fn input<T: Any + Send + Sync + Clone>(outputs: &TaskOutputs, phase: BootstrapPhase) -> Result<T, String> {
+    outputs.require::<T>(&phase.name()).cloned()
+}
+
+async fn run_phase(phase: BootstrapPhase, context: PhaseContext, inputs: TaskOutputs) -> Result<TaskOutput, String> {
+    info!("[{:?}] Starting...", phase);
+    let phase_start = Instant::now();
+
+    let result = match phase {
+        BootstrapPhase::Configuration => phase_configuration(&context).await,
+        BootstrapPhase::Verification => phase_verification(&context).await,
+        BootstrapPhase::CoreServices => phase_core_services(&context, &inputs).await,
+        BootstrapPhase::GameServer => phase_game_server(&context, &inputs).await,
+        BootstrapPhase::EventSubscriptions => phase_event_subscriptions(&context, &inputs).await,
+        BootstrapPhase::Plugins => phase_plugins(&context, &inputs).await,
+        BootstrapPhase::Anticheat => phase_anticheat(&context, &inputs).await,
+        BootstrapPhase::Ready => phase_ready(&context, &inputs).await,
+        BootstrapPhase::Initializing | BootstrapPhase::Failed => Ok(TaskOutput::empty()),
+    };
+
+    match &result {
+        Ok(_) => {
+            info!("[{:?}] Complete ({:.2}ms)", phase, phase_start.elapsed().as_secs_f64() * 1000.0);
+            context.readiness.phase_completed(phase);
+        }
+        Err(e) => {
+            error!("[{:?}] Failed: {}", phase, e);
+            context.readiness.phase_failed(phase, e);
+        }
+    }
+    result
+}
+
+async fn phase_configuration(context: &PhaseContext) -> Result<TaskOutput, String> {
+    debug!("Loading configuration from {:?}", context.config_path);
+    
+    if !context.config_path.exists() {
+        return Err(format!("Config file not found: {:?}", context.config_path));
+    }
+    
+    let config = Arc::new(ConfigManager::new(
+        context.config_path.to_string_lossy().as_ref()
+    )?);
+    
+    context.report.write().add_info("Configuration loaded successfully");
+    start_health_endpoint(context, &config).await;
+    Ok(TaskOutput::new(config))
+}
+
+/// Binds the probes as soon as the config is known, so supervisors see the
+/// remaining phases flip readiness. A bind failure doesn't stop the server.
+async fn start_health_endpoint(context: &PhaseContext, config: &ConfigManager) {
+    let settings = config.get().health_http;
+    context.readiness.set_min_tps(settings.min_tps);
+    if !settings.enabled {
+        return;
+    }
+
+    let endpoint = match settings.bind.parse() {
+        Ok(addr) => HealthEndpoint::bind(addr, context.readiness.clone()).await
+            .map_err(|e| format!("cannot bind {}: {}", settings.bind, e)),
+        Err(e) => Err(format!("invalid bind address {:?}: {}", settings.bind, e)),
+    };
+    match endpoint {
+        Ok(endpoint) => {
+            if let Ok(addr) = endpoint.local_addr() {
+                context.report.write().add_info(format!("Health endpoint on http://{}", addr));
+            }
+            tokio::spawn(endpoint.serve());
+        }
+        Err(e) => context.report.write().add_warning(format!("Health endpoint disabled: {}", e)),
+    }
+}
+
+async fn phase_verification(context: &PhaseContext) -> Result<TaskOutput, String> {
+    debug!("Verifying server JAR: {:?}", context.server_jar);
+    
+    if !context.server_jar.exists() {
+        return Err(format!("Server JAR not found: {:?}", context.server_jar));
+    }
+    
+    let metadata = std::fs::metadata(&context.server_jar)
+        .map_err(|e| format!("Cannot read JAR file: {}", e))?;
+    
+    let size_mb = metadata.len() as f64 / (1024.0 * 1024.0);
+    context.report.write().add_info(format!("Server JAR: {:.2} MB", size_mb));
+    
+    if let Ok(output) = std::process::Command::new("java")
+        .arg("-version")
+        .output()
+    {
+        let version = String::from_utf8_lossy(&output.stderr);
+        if let Some(line) = version.lines().next() {
+            context.report.write().add_info(format!("Java: {}", line));
+        }
+    } else {
+        context.report.write().add_warning("Java version check failed");
+    }
+    
+    Ok(TaskOutput::empty())
+}
+
+async fn phase_core_services(context: &PhaseContext, inputs: &TaskOutputs) -> Result<TaskOutput, String> {
+    debug!("Initializing core services");
+    
+    let config: Arc<ConfigManager> = input(inputs, BootstrapPhase::Configuration)?;
+    let telemetry = Arc::new(TelemetryCollector::new());
+    let performance = Arc::new(PerformanceMonitor::new(telemetry.clone()));
+    let scheduler = Arc::new(Scheduler::new(performance.clone()));
+    let event_bus = Arc::new(EventBus::new());
+    performance.set_event_bus(event_bus.clone());
+    let settings = config.get();
+    performance.set_slow_tick_config(settings.performance.slow_tick);
+    performance.profiler().configure(settings.performance.profiler);
+    performance.throttler().set_tick_budget_ms(settings.performance.tick_budget_ms);
+    performance.throttler().set_enabled(settings.performance.adaptive_throttling);
+    performance.throttler().set_policy(settings.performance.throttling)?;
+    event_bus.set_config(settings.events);
+    context.readiness.set_event_bus(event_bus.clone());
+    
+    let core = CoreServices {
+        telemetry,
+        performance,
+        scheduler,
+        event_bus,
+        adaptive_scheduler: Arc::new(AdaptiveScheduler::new(50.0)),
+        world_heatmap: Arc::new(WorldHeatmap::new(256)),
+        session_manager: Arc::new(SessionManager::new(Duration::from_secs(3600))),
+    };
+    
+    context.report.write().add_info("Core services initialized");
+    Ok(TaskOutput::new(core))
+}
+
+async fn phase_game_server(context: &PhaseContext, inputs: &TaskOutputs) -> Result<TaskOutput, String> {
+    debug!("Starting game server");
+    
+    let config: Arc<ConfigManager> = input(inputs, BootstrapPhase::Configuration)?;
+    let settings = config.get();
+    let game_config = GameServerConfig {
+        jar_path: context.server_jar.clone(),
+        working_dir: context.server_jar.parent()
+            .unwrap_or(&PathBuf::from("."))
+            .to_path_buf(),
+        java_path: settings.server.java_path,
+        log_parser: settings.log_parser,
+        ..Default::default()
+    };
+    
+    let game_server = Arc::new(GameServerBridge::new(game_config));
+    context.readiness.set_game_server(game_server.clone());
+    game_server.start_log_pipeline();
+    game_server.start().await?;
+    
+    context.report.write().add_info("Game server started");
+    Ok(TaskOutput::new(game_server))
+}
+
+async fn phase_event_subscriptions(context: &PhaseContext, inputs: &TaskOutputs) -> Result<TaskOutput, String> {
+    debug!("Setting up event subscriptions");
+    
+    let core: CoreServices = input(inputs, BootstrapPhase::CoreServices)?;
+    let game_server: Arc<GameServerBridge> = input(inputs, BootstrapPhase::GameServer)?;
+    
+    let mut receiver = game_server.subscribe_events();
+    let event_bus = core.event_bus;
+    let session_manager = core.session_manager;
+    let world_heatmap = core.world_heatmap;
+    
+    tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Event forwarding lagged, {} game events skipped", skipped);
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            event_bus.emit(event.clone()).await;
+            
+            match &event {
+                crate::bridge::GameEvent::PlayerJoin(info) => {
+                    session_manager.create_session(info.id, info.name.clone());
+                }
+                crate::bridge::GameEvent::PlayerJoined { name, uuid: Some(id) } => {
+                    session_manager.create_session(*id, name.clone());
+                }
+                crate::bridge::GameEvent::PlayerLeft { name, .. } => {
+                    if let Some(session) = session_manager.get_session_by_username(name) {
+                        session_manager.remove_session(session.player_id);
+                    }
+                }
+                crate::bridge::GameEvent::PlayerQuit { id, .. } => {
+                    session_manager.remove_session(*id);
+                }
+                crate::bridge::GameEvent::PlayerMove { x, z, .. } => {
+                    world_heatmap.record_player_position(*x, *z, "world");
+                }
+                crate::bridge::GameEvent::BlockChange { x, z, world, .. } |
+                crate::bridge::GameEvent::BlockBreak { x, z, world, .. } |
+                crate::bridge::GameEvent::BlockPlace { x, z, world, .. } => {
+                    world_heatmap.record_block_change(*x as f64, *z as f64, world);
+                }
+                _ => {}
+            }
+        }
+    });
+    
+    context.report.write().add_info("Event subscriptions configured");
+    Ok(TaskOutput::empty())
+}
+
+async fn phase_plugins(context: &PhaseContext, inputs: &TaskOutputs) -> Result<TaskOutput, String> {
+    debug!("Loading plugins");
+    
+    let config: Arc<ConfigManager> = input(inputs, BootstrapPhase::Configuration)?;
+    let core: CoreServices = input(inputs, BootstrapPhase::CoreServices)?;
+    let plugins = Arc::new(PluginManager::new(config));
+    plugins.set_event_bus(core.event_bus);
+    
+    if let Err(e) = plugins.load_all().await {
+        context.report.write().add_warning(format!("Plugin loading: {}", e));
+    }
+    
+    context.report.write().add_info(format!("{} plugins loaded", plugins.count()));
+    Ok(TaskOutput::new(plugins))
+}
+
+async fn phase_anticheat(context: &PhaseContext, inputs: &TaskOutputs) -> Result<TaskOutput, String> {
+    debug!("Initializing anticheat");
+    
+    let config: Arc<ConfigManager> = input(inputs, BootstrapPhase::Configuration)?;
+    let core: CoreServices = input(inputs, BootstrapPhase::CoreServices)?;
+    let game_server: Arc<GameServerBridge> = input(inputs, BootstrapPhase::GameServer)?;
+    let anticheat_config = config.get().anticheat;
+    let attestation = anticheat_config.attestation.clone();
+    let anticheat = Arc::new(AnticheatService::new(anticheat_config));
+    
+    let anticheat_clone = anticheat.clone();
+    let mut receiver = core.event_bus.subscribe();
+    
+    let attestation_server = game_server.clone();
+    let kick = attestation.kick_on_violation;
+    tokio::spawn(async move {
+        while let Ok(event) = receiver.recv().await {
+            match event {
+                crate::bridge::GameEvent::PlayerJoin(info) if attestation.enabled => {
+                    anticheat_clone.expect_attestation(info.id, Utc::now());
+                }
+                crate::bridge::GameEvent::PlayerJoined { uuid: Some(id), .. } if attestation.enabled => {
+                    anticheat_clone.expect_attestation(id, Utc::now());
+                }
+                crate::bridge::GameEvent::PlayerQuit { id, .. } => {
+                    anticheat_clone.remove_player(id);
+                }
+                crate::bridge::GameEvent::PlayerAttestation { id, message } if attestation.enabled => {
+                    match anticheat_clone.handle_attestation(id, &message, Utc::now()) {
+                        Ok(grant) => attestation_server.emit_event(crate::bridge::GameEvent::AttestationGranted {
+                            id,
+                            token: grant.token,
+                            heartbeat_interval_secs: grant.heartbeat_interval_secs,
+                        }),
+                        Err(violation) => enforce_attestation(&attestation_server, id, &violation, kick).await,
+                    }
+                }
+                crate::bridge::GameEvent::PlayerMove { id, x, y, z, yaw, pitch, on_ground } => {
+                    let snapshot = crate::abstraction::MovementSnapshot::new(x, y, z, yaw, pitch);
+                    anticheat_clone.process_movement(id, snapshot);
+                }
+                crate::bridge::GameEvent::PlayerAttack { attacker_id, target_id, damage, distance } => {
+                    let snapshot = crate::abstraction::CombatSnapshot::attack(target_id, distance, 0.0);
+                    anticheat_clone.process_combat(attacker_id, snapshot);
+                }
+                _ => {}
+            }
+        }
+    });
+    
+    if attestation.enabled {
+        let anticheat_clone = anticheat.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                let expired = anticheat_clone.expire_attestations(Utc::now(), |id| game_server.has_player(id));
+                for (id, violation) in expired {
+                    enforce_attestation(&game_server, id, &violation, kick).await;
+                }
+            }
+        });
+        context.report.write().add_info("Launcher attestation required");
+    }
+    
+    context.report.write().add_info("Anticheat initialized with 25% sampling");
+    Ok(TaskOutput::new(anticheat))
+}
+
+async fn phase_ready(context: &PhaseContext, inputs: &TaskOutputs) -> Result<TaskOutput, String> {
+    debug!("Finalizing startup");
+    
+    let core: CoreServices = input(inputs, BootstrapPhase::CoreServices)?;
+    let game_server: Arc<GameServerBridge> = input(inputs, BootstrapPhase::GameServer)?;
+    core.scheduler.start().await;
+    core.scheduler.spawn_timer();
+    core.performance.start_monitoring().await;
+    
+    let player_count = game_server.player_count();
+    context.report.write().add_info(format!("Server ready with {} players", player_count));
+    
+    Ok(TaskOutput::empty())
+}
+
+/// Report a failed attestation and, if the policy says so, remove the player
+async fn enforce_attestation(game_server: &GameServerBridge, id: uuid::Uuid, violation: &crate::anticheat::AttestationViolation, kick: bool) {
+    warn!("Launcher attestation failed for {}: {}", id, violation);
+    game_server.emit_event(crate::bridge::GameEvent::AttestationFailed { id, reason: violation.to_string() });
+    if kick {
+        if let Err(e) = game_server.kick_player(id, "Launcher attestation failed").await {
+            warn!("Could not kick {}: {}", id, e);
+        }
+    }
 }
@@ -15,6 +15,18 @@ pub enum BootstrapPhase {
 }
 
 impl BootstrapPhase {
+    /// Phases run by the bootstrap graph, each after the phases it depends on
+    pub const GRAPH: [BootstrapPhase; 8] = [
+        BootstrapPhase::Configuration,
+        BootstrapPhase::Verification,
+        BootstrapPhase::CoreServices,
+        BootstrapPhase::GameServer,
+        BootstrapPhase::EventSubscriptions,
+        BootstrapPhase::Plugins,
+        BootstrapPhase::Anticheat,
+        BootstrapPhase::Ready,
+    ];
+
     pub fn description(&self) -> &'static str {
         match self {
             BootstrapPhase::Initializing => "Preparing bootstrap sequence",
@@ -30,6 +42,24 @@ impl BootstrapPhase {
         }
     }
 
+    pub fn depends_on(&self) -> &'static [BootstrapPhase] {
+        use BootstrapPhase::*;
+        match self {
+            CoreServices => &[Configuration],
+            GameServer => &[Configuration, Verification],
+            EventSubscriptions => &[CoreServices, GameServer],
+            Plugins => &[Configuration, CoreServices],
+            Anticheat => &[Configuration, CoreServices, GameServer],
+            Ready => &[CoreServices, GameServer, EventSubscriptions, Plugins, Anticheat],
+            Initializing | Configuration | Verification | Failed => &[],
+        }
+    }
+
+    /// Task name in the bootstrap graph and the startup report
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
     pub fn is_critical(&self) -> bool {
         matches!(self, 
             BootstrapPhase::Configuration |
@@ -1,5 +1,6 @@
 use parking_lot::RwLock;
 use std::collections::VecDeque;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
 use tokio::sync::broadcast;
 use tracing::{info, warn, error};
 use chrono::{DateTime, Utc};
@@ -27,6 +28,9 @@ pub enum ConsoleSource {
     Rubidium,
 }
 
+/// Longest console line kept in one piece; the rest follows as further lines.
+pub const MAX_LINE_BYTES: usize = 16 * 1024;
+
 pub struct ConsoleHandler {
     history: RwLock<VecDeque<ConsoleLine>>,
     max_history: usize,
@@ -58,6 +62,35 @@ impl ConsoleHandler {
         self.append_line(content, ConsoleLevel::Error, ConsoleSource::Server).await;
     }
 
+    /// Appends every line read from `reader` until it closes. Lines longer
+    /// than `MAX_LINE_BYTES` are split, invalid UTF-8 is replaced rather than
+    /// ending the stream, and a final line without a newline is still kept.
+    /// `level` forces the level, as for stderr; otherwise it is detected.
+    pub async fn pipe<R: AsyncRead + Unpin>(&self, reader: R, level: Option<ConsoleLevel>) {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match (&mut reader).take(MAX_LINE_BYTES as u64).read_until(b'\n', &mut buf).await {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Server output stream failed: {}", e);
+                    break;
+                }
+            }
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+            }
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let level = level.unwrap_or_else(|| self.detect_level(&line));
+            self.append_line(&line, level, ConsoleSource::Server).await;
+        }
+    }
+
     pub async fn append_rubidium(&self, content: &str, level: ConsoleLevel) {
         self.append_line(content, level, ConsoleSource::Rubidium).await;
     }
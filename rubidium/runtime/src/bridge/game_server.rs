@@ -1,15 +1,16 @@
 use super::process_manager::ProcessManager;
-use super::console::ConsoleHandler;
+use super::console::{ConsoleHandler, ConsoleLine, ConsoleSource};
+use super::log_parser::{LogParser, LogParserConfig, RuleLogParser};
 use super::protocol::{GameEvent, GameCommand, PlayerInfo, WorldInfo};
 use crate::abstraction::GameAdapter;
 use crate::abstraction::entities::{EntityHandle, PlayerHandle, GameMode, BoundingBox};
 use crate::abstraction::world::{WorldHandle, Dimension, Weather, BlockData};
 use async_trait::async_trait;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use tokio::sync::{broadcast, mpsc};
 use tracing::{info, warn, error};
 use uuid::Uuid;
@@ -25,6 +26,7 @@ pub struct GameServerConfig {
     pub min_memory_mb: u32,
     pub auto_restart: bool,
     pub restart_delay_secs: u32,
+    pub log_parser: LogParserConfig,
 }
 
 impl Default for GameServerConfig {
@@ -45,6 +47,7 @@ impl Default for GameServerConfig {
             min_memory_mb: 1024,
             auto_restart: true,
             restart_delay_secs: 10,
+            log_parser: LogParserConfig::default(),
         }
     }
 }
@@ -75,10 +78,24 @@ pub struct GameServerBridge {
     event_tx: broadcast::Sender<GameEvent>,
     command_tx: mpsc::Sender<GameCommand>,
     
+    log_parser: RwLock<Arc<dyn LogParser>>,
+    recent_events: RwLock<VecDeque<GameEvent>>,
+    log_pipeline_started: AtomicBool,
+    lines_parsed: AtomicU64,
+    lines_dropped: Arc<AtomicU64>,
+    
     start_time: RwLock<Option<std::time::Instant>>,
     version: RwLock<Option<String>>,
 }
 
+const RECENT_EVENTS_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogPipelineStats {
+    pub parsed: u64,
+    pub dropped: u64,
+}
+
 impl GameServerBridge {
     pub fn new(config: GameServerConfig) -> Self {
         let (event_tx, _) = broadcast::channel(10000);
@@ -86,6 +103,7 @@ impl GameServerBridge {
         
         let console = Arc::new(ConsoleHandler::new());
         let process = Arc::new(ProcessManager::new(console.clone()));
+        let log_parser: Arc<dyn LogParser> = Arc::new(RuleLogParser::from_config(&config.log_parser));
         
         Self {
             config: RwLock::new(config),
@@ -99,6 +117,11 @@ impl GameServerBridge {
             worlds: RwLock::new(HashMap::new()),
             event_tx,
             command_tx,
+            log_parser: RwLock::new(log_parser),
+            recent_events: RwLock::new(VecDeque::with_capacity(RECENT_EVENTS_CAPACITY)),
+            log_pipeline_started: AtomicBool::new(false),
+            lines_parsed: AtomicU64::new(0),
+            lines_dropped: Arc::new(AtomicU64::new(0)),
             start_time: RwLock::new(None),
             version: RwLock::new(None),
         }
@@ -196,7 +219,121 @@ impl GameServerBridge {
             return Err("Not connected to game server".to_string());
         }
         
-        self.console.send_input(command).await
+        self.console.send_input(command).await?;
+        self.process.send_input(command).await
+    }
+
+    pub fn set_log_parser(&self, parser: Arc<dyn LogParser>) {
+        *self.log_parser.write() = parser;
+    }
+
+    pub fn start_log_pipeline(self: &Arc<Self>) {
+        if self.log_pipeline_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        
+        let capacity = self.config.read().log_parser.queue_capacity.max(1);
+        let (queue_tx, mut queue_rx) = mpsc::channel::<ConsoleLine>(capacity);
+        let mut lines = self.console.subscribe();
+        let dropped = self.lines_dropped.clone();
+        
+        // Reading the console never waits on parsing: when the queue is full
+        // the line is dropped and counted instead of stalling the server's output.
+        tokio::spawn(async move {
+            loop {
+                match lines.recv().await {
+                    Ok(line) if line.source != ConsoleSource::Server => {}
+                    Ok(line) => match queue_tx.try_send(line) {
+                        Ok(()) => {}
+                        Err(mpsc::error::TrySendError::Full(_)) => {
+                            if dropped.fetch_add(1, Ordering::Relaxed).is_multiple_of(1000) {
+                                warn!("Log parser queue full, dropping console lines");
+                            }
+                        }
+                        Err(mpsc::error::TrySendError::Closed(_)) => break,
+                    },
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        dropped.fetch_add(skipped, Ordering::Relaxed);
+                        warn!("Log parser lagged, {} console lines skipped", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        let bridge: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while let Some(line) = queue_rx.recv().await {
+                let Some(bridge) = bridge.upgrade() else { break };
+                bridge.handle_console_line(&line);
+            }
+        });
+    }
+    
+    /// Lines parsed into events and lines dropped because the parser fell behind.
+    pub fn log_pipeline_stats(&self) -> LogPipelineStats {
+        LogPipelineStats {
+            parsed: self.lines_parsed.load(Ordering::Relaxed),
+            dropped: self.lines_dropped.load(Ordering::Relaxed),
+        }
+    }
+    
+    pub fn console(&self) -> &Arc<ConsoleHandler> {
+        &self.console
+    }
+
+    fn handle_console_line(&self, line: &ConsoleLine) {
+        if line.source != ConsoleSource::Server {
+            return;
+        }
+        
+        let event = self.log_parser.read().parse(&line.content);
+        self.lines_parsed.fetch_add(1, Ordering::Relaxed);
+        
+        match &event {
+            GameEvent::PlayerJoined { name, uuid: Some(id) } => {
+                self.add_player(PlayerInfo {
+                    id: *id,
+                    name: name.clone(),
+                    display_name: None,
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                    world: "world".to_string(),
+                    ip_address: None,
+                    client_brand: None,
+                    protocol_version: None,
+                });
+            }
+            GameEvent::PlayerLeft { name, .. } => {
+                let id = self.players.read().values()
+                    .find(|p| p.name.read().eq_ignore_ascii_case(name))
+                    .map(|p| p.id);
+                if let Some(player) = id.and_then(|id| self.remove_player(id)) {
+                    player.online.store(false, Ordering::Relaxed);
+                }
+            }
+            GameEvent::TpsReport { tps } => {
+                *self.tps.write() = *tps;
+            }
+            _ => {}
+        }
+        
+        if !matches!(event, GameEvent::RawLine { .. }) {
+            let mut recent = self.recent_events.write();
+            if recent.len() >= RECENT_EVENTS_CAPACITY {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        
+        self.emit_event(event);
+    }
+
+    pub fn recent_events(&self, count: usize) -> Vec<GameEvent> {
+        let recent = self.recent_events.read();
+        let skip = recent.len().saturating_sub(count);
+        recent.iter().skip(skip).cloned().collect()
     }
 
     pub fn subscribe_events(&self) -> broadcast::Receiver<GameEvent> {
@@ -229,6 +366,17 @@ impl GameServerBridge {
         self.players.read().len()
     }
 
+    pub fn has_player(&self, id: Uuid) -> bool {
+        self.players.read().contains_key(&id)
+    }
+
+    pub async fn kick_player(&self, id: Uuid, reason: &str) -> Result<(), String> {
+        let name = self.players.read().get(&id)
+            .map(|p| p.name.read().clone())
+            .ok_or("Player not online")?;
+        self.send_command(&format!("kick {} {}", name, reason)).await
+    }
+
     pub fn tps(&self) -> f64 {
         *self.tps.read()
     }
@@ -476,3 +624,147 @@ impl WorldHandle for LoadedWorld {
     fn weather(&self) -> Weather { *self.weather.read() }
     async fn set_weather(&self, weather: Weather, _duration: i32) { *self.weather.write() = weather; }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::console::MAX_LINE_BYTES;
+    use tokio::io::AsyncWriteExt;
+
+    const SAMPLE_LOG: &[u8] = b"\
+[09:14:02 INFO]: Starting Hytale server version 0.9.3\r
+[09:14:05 INFO]: Preparing spawn area: 87%\r
+[09:15:11 INFO]: Steve (6f1c9a2e-3b4d-4e5f-8a9b-0c1d2e3f4a5b) joined the game\r
+[09:15:40 INFO]: <Steve> hi \xff\xfe all\r
+[09:16:02 ERROR]: Failed to load chunk (12, -4): region file truncated\r
+[09:20:00 INFO]: Saved world 'orbis'\r
+[09:21:13 INFO]: Steve lost connection: Disconnected";
+
+    async fn next_events(receiver: &mut broadcast::Receiver<GameEvent>, count: usize) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        while events.len() < count {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(2), receiver.recv())
+                .await
+                .expect("timed out waiting for events")
+                .unwrap();
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_sample_log_emits_event_sequence() {
+        let bridge = Arc::new(GameServerBridge::new(GameServerConfig::default()));
+        let mut receiver = bridge.subscribe_events();
+        bridge.start_log_pipeline();
+
+        // Deliver the log in uneven chunks so lines arrive split across reads.
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let console = bridge.console().clone();
+        let piping = tokio::spawn(async move { console.pipe(reader, None).await });
+        for chunk in SAMPLE_LOG.chunks(13) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        drop(writer);
+        piping.await.unwrap();
+
+        let events = next_events(&mut receiver, 7).await;
+        let names: Vec<&str> = events.iter().map(|e| e.event_name()).collect();
+        assert_eq!(names, [
+            "raw_line", "raw_line", "player_joined", "chat_message", "error_line", "world_saved", "player_left",
+        ]);
+        match &events[3] {
+            GameEvent::ChatMessage { sender, message } => {
+                assert_eq!(sender, "Steve");
+                assert_eq!(message, "hi \u{fffd}\u{fffd} all");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match &events[6] {
+            GameEvent::PlayerLeft { name, reason } => {
+                assert_eq!(name, "Steve");
+                assert_eq!(reason.as_deref(), Some("Disconnected"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(bridge.player_count(), 0);
+        assert_eq!(bridge.log_pipeline_stats(), LogPipelineStats { parsed: 7, dropped: 0 });
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_commands_reach_server_stdin() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("rubidium-stdin-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let java = dir.join("fake-java.sh");
+        std::fs::write(&java, "#!/bin/sh\necho 'Done'\nwhile read -r line; do echo \"got $line\"; [ \"$line\" = stop ] && exit 0; done\n").unwrap();
+        std::fs::set_permissions(&java, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(dir.join("server.jar"), b"").unwrap();
+
+        let bridge = GameServerBridge::new(GameServerConfig {
+            jar_path: dir.join("server.jar"),
+            working_dir: dir.clone(),
+            java_path: Some(java),
+            ..Default::default()
+        });
+        bridge.start().await.unwrap();
+        assert_eq!(bridge.status(), ServerStatus::Running);
+
+        bridge.send_command("say hello").await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !bridge.console.contains_pattern("got say hello").await {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }).await.expect("command never reached the server");
+
+        bridge.stop().await.unwrap();
+        assert_eq!(bridge.status(), ServerStatus::Offline);
+        assert!(bridge.send_command("say gone").await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_overlong_lines_are_split() {
+        let console = ConsoleHandler::new();
+        let line = "x".repeat(MAX_LINE_BYTES + 10);
+        console.pipe(format!("{}\nshort", line).as_bytes(), None).await;
+
+        let lines: Vec<usize> = console.get_history(10).iter().rev().map(|l| l.content.len()).collect();
+        assert_eq!(lines, [MAX_LINE_BYTES, 10, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_full_parse_queue_drops_and_counts_lines() {
+        let config = GameServerConfig {
+            log_parser: LogParserConfig { queue_capacity: 8, ..Default::default() },
+            ..Default::default()
+        };
+        let bridge = Arc::new(GameServerBridge::new(config));
+        let mut receiver = bridge.subscribe_events();
+        bridge.start_log_pipeline();
+        tokio::task::yield_now().await;
+
+        // Nothing else runs while the lines are appended, so the queue
+        // overflows before the parser gets a turn.
+        let total = 500u64;
+        let log: String = (0..total).map(|i| format!("[10:00:00 INFO]: TPS: {}.0\n", i % 20)).collect();
+        bridge.console().pipe(log.as_bytes(), None).await;
+
+        let stats = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            loop {
+                let stats = bridge.log_pipeline_stats();
+                if stats.parsed + stats.dropped == total {
+                    break stats;
+                }
+                tokio::task::yield_now().await;
+            }
+        }).await.expect("pipeline did not account for every line");
+        assert!(stats.dropped > 0);
+        assert!(stats.parsed >= 8);
+
+        let events = next_events(&mut receiver, stats.parsed as usize).await;
+        assert!(events.iter().all(|e| e.event_name() == "tps_report"));
+    }
+}
@@ -0,0 +1,475 @@
+use super::protocol::GameEvent;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+use uuid::Uuid;
+
+pub trait LogParser: Send + Sync {
+    fn parse(&self, line: &str) -> GameEvent;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogEventKind {
+    PlayerJoined,
+    PlayerLeft,
+    ChatMessage,
+    WorldSaved,
+    TpsReport,
+    ErrorLine,
+}
+
+/// Matches the line body after the prefix, either with a `{name}` template
+/// in `pattern` or with a regular expression whose named groups become the
+/// captures. A rule with `regex` set ignores `pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRule {
+    pub kind: LogEventKind,
+    #[serde(default)]
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+impl LogRule {
+    pub fn new(kind: LogEventKind, pattern: impl Into<String>) -> Self {
+        Self {
+            kind,
+            pattern: pattern.into(),
+            regex: None,
+            level: None,
+        }
+    }
+
+    pub fn regex(kind: LogEventKind, regex: impl Into<String>) -> Self {
+        Self {
+            kind,
+            pattern: String::new(),
+            regex: Some(regex.into()),
+            level: None,
+        }
+    }
+
+    pub fn with_level(mut self, level: impl Into<String>) -> Self {
+        self.level = Some(level.into());
+        self
+    }
+}
+
+/// The `[log_parser]` section. Leaving out `rules` keeps the shipped
+/// defaults; `overrides` are tried before them, so operators can add or
+/// shadow rules without copying the whole list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogParserConfig {
+    pub line_prefix: Option<String>,
+    pub overrides: Vec<LogRule>,
+    pub rules: Vec<LogRule>,
+    /// Console lines waiting to be parsed before new ones are dropped.
+    pub queue_capacity: usize,
+}
+
+impl Default for LogParserConfig {
+    fn default() -> Self {
+        Self {
+            line_prefix: Some("[{time} {level}]: ".to_string()),
+            overrides: Vec::new(),
+            queue_capacity: 4096,
+            rules: vec![
+                LogRule::new(LogEventKind::PlayerJoined, "{name} ({uuid}) joined the game"),
+                LogRule::new(LogEventKind::PlayerJoined, "{name} joined the game"),
+                LogRule::new(LogEventKind::PlayerLeft, "{name} lost connection: {reason}"),
+                LogRule::new(LogEventKind::PlayerLeft, "{name} left the game"),
+                LogRule::new(LogEventKind::ChatMessage, "<{sender}> {message}"),
+                LogRule::new(LogEventKind::WorldSaved, "Saved world '{world}'"),
+                LogRule::new(LogEventKind::WorldSaved, "Saved the game"),
+                LogRule::new(LogEventKind::TpsReport, "TPS: {tps}"),
+                LogRule::new(LogEventKind::ErrorLine, "{message}").with_level("ERROR"),
+            ],
+        }
+    }
+}
+
+/// Compiles a `{name}` template to a regex. Each placeholder captures as
+/// few characters as it can, at least one, `{*}` matches without capturing,
+/// and everything else is literal. With `whole_line` the template has to
+/// match all of the input; otherwise only its start.
+fn compile_template(pattern: &str, whole_line: bool) -> Result<Regex, String> {
+    let mut regex = String::from("^");
+    let mut literal = String::new();
+    let mut names = Vec::new();
+    let mut after_placeholder = false;
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            literal.push(c);
+            continue;
+        }
+
+        let mut name = String::new();
+        let mut closed = false;
+        for c in chars.by_ref() {
+            if c == '}' {
+                closed = true;
+                break;
+            }
+            name.push(c);
+        }
+        if !closed {
+            return Err(format!("Unclosed placeholder in pattern: {}", pattern));
+        }
+        if name.is_empty() {
+            return Err(format!("Empty placeholder in pattern: {}", pattern));
+        }
+        if after_placeholder && literal.is_empty() {
+            return Err(format!("Adjacent placeholders in pattern: {}", pattern));
+        }
+
+        regex.push_str(&regex::escape(&std::mem::take(&mut literal)));
+        if name == "*" {
+            regex.push_str(".+?");
+        } else {
+            if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("Invalid placeholder {{{}}} in pattern: {}", name, pattern));
+            }
+            if names.contains(&name) {
+                return Err(format!("Duplicate placeholder {{{}}} in pattern: {}", name, pattern));
+            }
+            regex.push_str(&format!("(?P<{}>.+?)", name));
+            names.push(name);
+        }
+        after_placeholder = true;
+    }
+
+    regex.push_str(&regex::escape(&literal));
+    if whole_line {
+        regex.push('$');
+    }
+    Regex::new(&regex).map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))
+}
+
+fn compile_rule(rule: &LogRule) -> Result<Regex, String> {
+    match &rule.regex {
+        Some(regex) => Regex::new(regex).map_err(|e| format!("Invalid regex {:?}: {}", regex, e)),
+        None if rule.pattern.is_empty() => Err("Rule needs a pattern or a regex".to_string()),
+        None => compile_template(&rule.pattern, true),
+    }
+}
+
+/// Named groups that took part in the match
+fn named_captures(regex: &Regex, found: &regex::Captures<'_>) -> HashMap<String, String> {
+    regex.capture_names()
+        .flatten()
+        .filter_map(|name| Some((name.to_string(), found.name(name)?.as_str().to_string())))
+        .collect()
+}
+
+struct CompiledRule {
+    kind: LogEventKind,
+    regex: Regex,
+    level: Option<String>,
+}
+
+pub struct RuleLogParser {
+    prefix: Option<Regex>,
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleLogParser {
+    pub fn new(config: &LogParserConfig) -> Result<Self, String> {
+        let prefix = config.line_prefix.as_deref()
+            .map(|prefix| compile_template(prefix, false))
+            .transpose()?;
+
+        let rules = config.overrides.iter().chain(&config.rules)
+            .map(|rule| {
+                Ok(CompiledRule {
+                    kind: rule.kind,
+                    regex: compile_rule(rule)?,
+                    level: rule.level.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self { prefix, rules })
+    }
+
+    pub fn from_config(config: &LogParserConfig) -> Self {
+        let prefix = match config.line_prefix.as_deref().map(|prefix| compile_template(prefix, false)) {
+            Some(Ok(regex)) => Some(regex),
+            Some(Err(e)) => {
+                warn!("Ignoring log prefix: {}", e);
+                None
+            }
+            None => None,
+        };
+
+        let rules = config.overrides.iter().chain(&config.rules)
+            .filter_map(|rule| match compile_rule(rule) {
+                Ok(regex) => Some(CompiledRule {
+                    kind: rule.kind,
+                    regex,
+                    level: rule.level.clone(),
+                }),
+                Err(e) => {
+                    warn!("Ignoring log rule {:?}: {}", rule.kind, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self { prefix, rules }
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    fn build_event(kind: LogEventKind, captures: &HashMap<String, String>) -> Option<GameEvent> {
+        let get = |key: &str| captures.get(key).map(|v| v.trim().to_string());
+
+        match kind {
+            LogEventKind::PlayerJoined => {
+                let uuid = match captures.get("uuid") {
+                    Some(raw) => Some(Uuid::parse_str(raw.trim()).ok()?),
+                    None => None,
+                };
+                Some(GameEvent::PlayerJoined { name: get("name")?, uuid })
+            }
+            LogEventKind::PlayerLeft => Some(GameEvent::PlayerLeft {
+                name: get("name")?,
+                reason: get("reason"),
+            }),
+            LogEventKind::ChatMessage => Some(GameEvent::ChatMessage {
+                sender: get("sender")?,
+                message: get("message")?,
+            }),
+            LogEventKind::WorldSaved => Some(GameEvent::WorldSaved { world: get("world") }),
+            LogEventKind::TpsReport => {
+                let tps = get("tps")?.parse::<f64>().ok()?;
+                Some(GameEvent::TpsReport { tps })
+            }
+            LogEventKind::ErrorLine => Some(GameEvent::ErrorLine { message: get("message")? }),
+        }
+    }
+}
+
+impl Default for RuleLogParser {
+    fn default() -> Self {
+        Self::from_config(&LogParserConfig::default())
+    }
+}
+
+impl LogParser for RuleLogParser {
+    fn parse(&self, line: &str) -> GameEvent {
+        let (prefix_captures, body) = self.prefix.as_ref()
+            .and_then(|prefix| {
+                let found = prefix.captures(line)?;
+                Some((named_captures(prefix, &found), &line[found.get(0)?.end()..]))
+            })
+            .unwrap_or_else(|| (HashMap::new(), line));
+        let level = prefix_captures.get("level").map(|l| l.trim());
+
+        for rule in &self.rules {
+            if let Some(required) = &rule.level {
+                if !level.is_some_and(|l| l.eq_ignore_ascii_case(required)) {
+                    continue;
+                }
+            }
+
+            let Some(found) = rule.regex.captures(body) else {
+                continue;
+            };
+            let mut captures = named_captures(&rule.regex, &found);
+            for (key, value) in &prefix_captures {
+                captures.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+
+            if let Some(event) = Self::build_event(rule.kind, &captures) {
+                return event;
+            }
+        }
+
+        GameEvent::RawLine { line: line.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> GameEvent {
+        RuleLogParser::default().parse(line)
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let uuid = "6f1c9a2e-3b4d-4e5f-8a9b-0c1d2e3f4a5b";
+        let cases: Vec<(String, &str)> = vec![
+            (format!("[12:00:01 INFO]: Steve ({}) joined the game", uuid), "player_joined"),
+            ("[12:00:01 INFO]: Steve joined the game".to_string(), "player_joined"),
+            ("[12:00:02 INFO]: Steve left the game".to_string(), "player_left"),
+            ("[12:00:02 INFO]: Alex lost connection: Timed out".to_string(), "player_left"),
+            ("[12:00:03 INFO]: <Steve> hello there".to_string(), "chat_message"),
+            ("[12:00:04 INFO]: Saved world 'overworld'".to_string(), "world_saved"),
+            ("[12:00:04 INFO]: Saved the game".to_string(), "world_saved"),
+            ("[12:00:05 INFO]: TPS: 19.8".to_string(), "tps_report"),
+            ("[12:00:06 ERROR]: Could not load chunk".to_string(), "error_line"),
+            ("[12:00:07 INFO]: Preparing spawn area".to_string(), "raw_line"),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(parse(&line).event_name(), expected, "line: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_captured_fields() {
+        let uuid = Uuid::new_v4();
+        match parse(&format!("[12:00:01 INFO]: Steve ({}) joined the game", uuid)) {
+            GameEvent::PlayerJoined { name, uuid: parsed } => {
+                assert_eq!(name, "Steve");
+                assert_eq!(parsed, Some(uuid));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        match parse("[12:00:02 INFO]: Alex lost connection: Timed out") {
+            GameEvent::PlayerLeft { name, reason } => {
+                assert_eq!(name, "Alex");
+                assert_eq!(reason.as_deref(), Some("Timed out"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        match parse("[12:00:03 INFO]: <Steve> <3 you all") {
+            GameEvent::ChatMessage { sender, message } => {
+                assert_eq!(sender, "Steve");
+                assert_eq!(message, "<3 you all");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        match parse("[12:00:05 INFO]: TPS: 17.5") {
+            GameEvent::TpsReport { tps } => assert!((tps - 17.5).abs() < f64::EPSILON),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ambiguous_lines_fall_back() {
+        let cases = [
+            "[12:00:05 INFO]: TPS: fast",
+            "[12:00:01 INFO]: Steve (not-a-uuid) joined the game",
+            "[12:00:03 INFO]: <Steve",
+            "Steve joined the game but the prefix is missing",
+            "",
+        ];
+
+        for line in cases {
+            let event = parse(line);
+            match (line, &event) {
+                ("[12:00:01 INFO]: Steve (not-a-uuid) joined the game", GameEvent::PlayerJoined { name, uuid }) => {
+                    assert_eq!(name, "Steve (not-a-uuid)");
+                    assert!(uuid.is_none());
+                }
+                (_, GameEvent::RawLine { line: raw }) => assert_eq!(raw, line),
+                _ => panic!("unexpected event for {:?}: {:?}", line, event),
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_rules_from_config() {
+        let config: LogParserConfig = toml::from_str(r#"
+            line_prefix = "{*} | "
+
+            [[rules]]
+            kind = "player_joined"
+            pattern = "+ {name}"
+
+            [[rules]]
+            kind = "error_line"
+            pattern = "!! {message}"
+        "#).unwrap();
+        let parser = RuleLogParser::new(&config).unwrap();
+
+        assert_eq!(parser.parse("2024-01-01 | + Steve").event_name(), "player_joined");
+        assert_eq!(parser.parse("2024-01-01 | !! disk full").event_name(), "error_line");
+        assert_eq!(parser.parse("Steve joined the game").event_name(), "raw_line");
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(compile_template("{name", true).is_err());
+        assert!(compile_template("{} joined", true).is_err());
+        assert!(compile_template("{a}{b}", true).is_err());
+        assert!(compile_template("{name} met {name}", true).unwrap_err().contains("Duplicate"));
+        assert!(compile_template("{player name} joined", true).is_err());
+
+        let config = LogParserConfig {
+            line_prefix: None,
+            overrides: Vec::new(),
+            queue_capacity: 16,
+            rules: vec![
+                LogRule::new(LogEventKind::ChatMessage, "<{sender"),
+                LogRule::new(LogEventKind::TpsReport, "TPS: {tps}"),
+            ],
+        };
+        assert!(RuleLogParser::new(&config).is_err());
+        assert_eq!(RuleLogParser::from_config(&config).rule_count(), 1);
+
+        let config: LogParserConfig = toml::from_str(r#"
+            [[overrides]]
+            kind = "chat_message"
+            regex = "(?P<sender>[a-z]+"
+        "#).unwrap();
+        let error = RuleLogParser::new(&config).err().unwrap();
+        assert!(error.contains("Invalid regex"), "{}", error);
+    }
+
+    #[test]
+    fn test_templates_treat_regex_syntax_as_literal() {
+        let template = compile_template("[{level}] {message} (x*)", true).unwrap();
+        let found = template.captures("[WARN] low memory (x*)").unwrap();
+        assert_eq!(&found["level"], "WARN");
+        assert_eq!(&found["message"], "low memory");
+        assert!(!template.is_match("[WARN] low memory (xx)"));
+
+        let prefix = compile_template("{*} | ", false).unwrap();
+        assert_eq!(prefix.find("12:00 | a | b").unwrap().end(), "12:00 | ".len());
+    }
+
+    #[test]
+    fn test_regex_overrides_take_precedence_over_defaults() {
+        let config: LogParserConfig = toml::from_str(r#"
+            [[overrides]]
+            kind = "chat_message"
+            regex = '^\[(?P<channel>\w+)\] (?P<sender>\w+): (?P<message>.+)$'
+
+            [[overrides]]
+            kind = "error_line"
+            regex = 'Exception in thread "(?P<thread>[^"]+)" (?P<message>.+)'
+        "#).unwrap();
+        let parser = RuleLogParser::new(&config).unwrap();
+        assert_eq!(parser.rule_count(), LogParserConfig::default().rules.len() + 2);
+
+        match parser.parse("[12:00:01 INFO]: [global] Steve: anyone selling iron?") {
+            GameEvent::ChatMessage { sender, message } => {
+                assert_eq!(sender, "Steve");
+                assert_eq!(message, "anyone selling iron?");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match parser.parse(r#"[12:00:02 WARN]: Exception in thread "Chunk IO" java.io.IOException: disk full"#) {
+            GameEvent::ErrorLine { message } => assert_eq!(message, "java.io.IOException: disk full"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        // Defaults still apply underneath the overrides.
+        assert_eq!(parser.parse("[12:00:03 INFO]: Steve joined the game").event_name(), "player_joined");
+    }
+}
@@ -2,8 +2,10 @@ pub mod game_server;
 pub mod process_manager;
 pub mod console;
 pub mod protocol;
+pub mod log_parser;
 
-pub use game_server::{GameServerBridge, GameServerConfig, ServerStatus};
+pub use game_server::{GameServerBridge, GameServerConfig, LogPipelineStats, ServerStatus};
 pub use process_manager::ProcessManager;
 pub use console::ConsoleHandler;
-pub use protocol::{GameEvent, GameCommand};
+pub use protocol::{GameEvent, GameCommand, ScopeTiming, TickBreakdown, TaskTiming};
+pub use log_parser::{LogParser, LogParserConfig, LogRule, LogEventKind, RuleLogParser};
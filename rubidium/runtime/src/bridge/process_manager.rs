@@ -1,9 +1,9 @@
-use super::console::ConsoleHandler;
+use super::console::{ConsoleHandler, ConsoleLevel};
 use parking_lot::RwLock;
 use std::path::Path;
 use std::process::Stdio;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::AsyncWriteExt;
 use tokio::process::{Child, Command};
 use tokio::sync::mpsc;
 use tracing::info;
@@ -64,20 +64,12 @@ impl ProcessManager {
 
         let console_clone = self.console.clone();
         tokio::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                console_clone.append_output(&line).await;
-            }
+            console_clone.pipe(stdout, None).await;
         });
 
         let console_clone = self.console.clone();
         tokio::spawn(async move {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                console_clone.append_error(&line).await;
-            }
+            console_clone.pipe(stderr, Some(ConsoleLevel::Error)).await;
         });
 
         tokio::spawn(async move {
@@ -123,18 +115,19 @@ impl ProcessManager {
     }
 
     pub async fn kill(&self) -> Result<(), String> {
-        if let Some(ref mut child) = *self.child.write() {
+        let child = self.child.write().take();
+        if let Some(mut child) = child {
             child.kill().await
                 .map_err(|e| format!("Failed to kill process: {}", e))?;
         }
-        *self.child.write() = None;
         *self.stdin_tx.write() = None;
         *self.pid.write() = None;
         Ok(())
     }
 
     pub async fn send_input(&self, input: &str) -> Result<(), String> {
-        if let Some(ref tx) = *self.stdin_tx.read() {
+        let tx = self.stdin_tx.read().clone();
+        if let Some(tx) = tx {
             tx.send(input.to_string()).await
                 .map_err(|e| format!("Failed to send input: {}", e))
         } else {
@@ -37,8 +37,24 @@ pub enum GameEvent {
     
     TickComplete { tick: u64, duration_ms: f64 },
     TpsUpdate { tps: f64 },
+    PerformanceAlert { tick: u64, duration_ms: f64, threshold_ms: f64, breakdown: TickBreakdown },
+    SlowTick { tick: u64, duration_ms: f64, threshold_ms: f64, scopes: Vec<ScopeTiming>, untracked_ms: f64 },
+    ThrottleChanged { previous: u8, level: u8, tick_ms: f64 },
     
     PluginMessage { channel: String, data: Vec<u8> },
+    PluginViolation { plugin_id: String, capability: String, target: String, strikes: u32 },
+    
+    PlayerAttestation { id: Uuid, message: String },
+    AttestationGranted { id: Uuid, token: String, heartbeat_interval_secs: u64 },
+    AttestationFailed { id: Uuid, reason: String },
+    
+    PlayerJoined { name: String, uuid: Option<Uuid> },
+    PlayerLeft { name: String, reason: Option<String> },
+    ChatMessage { sender: String, message: String },
+    WorldSaved { world: Option<String> },
+    TpsReport { tps: f64 },
+    ErrorLine { message: String },
+    RawLine { line: String },
     
     Custom { event_type: String, data: String },
 }
@@ -63,6 +79,9 @@ pub enum GameCommand {
     SaveWorld { world: String },
     LoadChunk { world: String, x: i32, z: i32 },
     UnloadChunk { world: String, x: i32, z: i32 },
+
+    NearestWaypoints { world: String, x: f64, y: f64, z: f64, limit: usize, max_distance: f64, viewer: Option<Uuid> },
+    SetWaypointGroupVisibility { player: Uuid, group: String, visible: bool },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +109,26 @@ pub struct WorldInfo {
     pub difficulty: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TickBreakdown {
+    pub tasks: Vec<TaskTiming>,
+    pub entities_processed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskTiming {
+    pub name: String,
+    pub duration_ms: f64,
+}
+
+/// Time one `TickProfiler` scope took during a tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScopeTiming {
+    pub name: String,
+    pub duration_ms: f64,
+    pub calls: u32,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum DamageSource {
     Player,
@@ -136,8 +175,97 @@ impl GameEvent {
             GameEvent::BlockPlace { .. } => "block_place",
             GameEvent::TickComplete { .. } => "tick_complete",
             GameEvent::TpsUpdate { .. } => "tps_update",
+            GameEvent::PerformanceAlert { .. } => "performance_alert",
+            GameEvent::SlowTick { .. } => "slow_tick",
+            GameEvent::ThrottleChanged { .. } => "throttle_changed",
             GameEvent::PluginMessage { .. } => "plugin_message",
+            GameEvent::PluginViolation { .. } => "plugin_violation",
+            GameEvent::PlayerAttestation { .. } => "player_attestation",
+            GameEvent::AttestationGranted { .. } => "attestation_granted",
+            GameEvent::AttestationFailed { .. } => "attestation_failed",
+            GameEvent::PlayerJoined { .. } => "player_joined",
+            GameEvent::PlayerLeft { .. } => "player_left",
+            GameEvent::ChatMessage { .. } => "chat_message",
+            GameEvent::WorldSaved { .. } => "world_saved",
+            GameEvent::TpsReport { .. } => "tps_report",
+            GameEvent::ErrorLine { .. } => "error_line",
+            GameEvent::RawLine { .. } => "raw_line",
+            GameEvent::Custom { .. } => "custom",
+        }
+    }
+
+    /// Dot-separated topic for `EventBus` wildcard subscriptions, e.g.
+    /// `player.*` or `world.chunk.*`.
+    pub fn topic(&self) -> &'static str {
+        match self {
+            GameEvent::ServerStarting => "server.starting",
+            GameEvent::ServerStarted { .. } => "server.started",
+            GameEvent::ServerStopping => "server.stopping",
+            GameEvent::ServerStopped => "server.stopped",
+            GameEvent::PlayerJoin(_) => "player.join",
+            GameEvent::PlayerQuit { .. } => "player.quit",
+            GameEvent::PlayerMove { .. } => "player.move",
+            GameEvent::PlayerChat { .. } => "player.chat",
+            GameEvent::PlayerCommand { .. } => "player.command",
+            GameEvent::PlayerAttack { .. } => "player.attack",
+            GameEvent::PlayerDamage { .. } => "player.damage",
+            GameEvent::PlayerDeath { .. } => "player.death",
+            GameEvent::PlayerRespawn { .. } => "player.respawn",
+            GameEvent::WorldLoad(_) => "world.load",
+            GameEvent::WorldUnload { .. } => "world.unload",
+            GameEvent::WorldTimeChange { .. } => "world.time_change",
+            GameEvent::WorldWeatherChange { .. } => "world.weather_change",
+            GameEvent::EntitySpawn { .. } => "entity.spawn",
+            GameEvent::EntityRemove { .. } => "entity.remove",
+            GameEvent::EntityMove { .. } => "entity.move",
+            GameEvent::ChunkLoad { .. } => "world.chunk.load",
+            GameEvent::ChunkUnload { .. } => "world.chunk.unload",
+            GameEvent::BlockChange { .. } => "world.block.change",
+            GameEvent::BlockBreak { .. } => "world.block.break",
+            GameEvent::BlockPlace { .. } => "world.block.place",
+            GameEvent::TickComplete { .. } => "performance.tick_complete",
+            GameEvent::TpsUpdate { .. } => "performance.tps_update",
+            GameEvent::PerformanceAlert { .. } => "performance.alert",
+            GameEvent::SlowTick { .. } => "performance.slow_tick",
+            GameEvent::ThrottleChanged { .. } => "performance.throttle",
+            GameEvent::PluginMessage { .. } => "plugin.message",
+            GameEvent::PluginViolation { .. } => "plugin.violation",
+            GameEvent::PlayerAttestation { .. } => "player.attestation",
+            GameEvent::AttestationGranted { .. } => "anticheat.attestation.granted",
+            GameEvent::AttestationFailed { .. } => "anticheat.attestation.failed",
+            GameEvent::PlayerJoined { .. } => "player.joined",
+            GameEvent::PlayerLeft { .. } => "player.left",
+            GameEvent::ChatMessage { .. } => "player.chat_message",
+            GameEvent::WorldSaved { .. } => "world.saved",
+            GameEvent::TpsReport { .. } => "performance.tps_report",
+            GameEvent::ErrorLine { .. } => "server.error_line",
+            GameEvent::RawLine { .. } => "server.raw_line",
             GameEvent::Custom { .. } => "custom",
         }
     }
 }
+
+impl GameCommand {
+    /// Name used for this command in plugin capability manifests.
+    pub fn command_name(&self) -> &'static str {
+        match self {
+            GameCommand::Say(_) => "say",
+            GameCommand::Kick { .. } => "kick",
+            GameCommand::Ban { .. } => "ban",
+            GameCommand::Teleport { .. } => "teleport",
+            GameCommand::SetTime { .. } => "set_time",
+            GameCommand::SetWeather { .. } => "set_weather",
+            GameCommand::Raw(_) => "raw",
+            GameCommand::SendTitle { .. } => "send_title",
+            GameCommand::SendActionBar { .. } => "send_action_bar",
+            GameCommand::PlaySound { .. } => "play_sound",
+            GameCommand::SetGameMode { .. } => "set_game_mode",
+            GameCommand::GiveItem { .. } => "give_item",
+            GameCommand::SaveWorld { .. } => "save_world",
+            GameCommand::LoadChunk { .. } => "load_chunk",
+            GameCommand::UnloadChunk { .. } => "unload_chunk",
+            GameCommand::NearestWaypoints { .. } => "nearest_waypoints",
+            GameCommand::SetWaypointGroupVisibility { .. } => "set_waypoint_group_visibility",
+        }
+    }
+}
@@ -1,5 +1,14 @@
+use crate::admin::HealthEndpointConfig;
+use crate::anticheat::AnticheatConfig;
+use crate::bridge::LogParserConfig;
+use crate::core::performance::SlowTickConfig;
+use crate::core::profiler::ProfilerConfig;
+use crate::core::sandbox::SandboxPolicy;
+use crate::core::throttle::ThrottlePolicy;
+use crate::events::EventBusConfig;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use tracing::info;
 
@@ -10,6 +19,18 @@ pub struct ServerConfig {
     pub performance: PerformanceSettings,
     pub assets: AssetSettings,
     pub integration: IntegrationSettings,
+    #[serde(default)]
+    pub log_parser: LogParserConfig,
+    #[serde(default)]
+    pub events: EventBusConfig,
+    #[serde(default)]
+    pub anticheat: AnticheatConfig,
+    /// HTTP `/healthz` and `/readyz` probes; off unless `enabled`.
+    #[serde(default)]
+    pub health_http: HealthEndpointConfig,
+    /// Admin console aliases, e.g. `tp = "teleport"`; see `admin::commands::expand_alias`.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +40,9 @@ pub struct ServerSettings {
     pub max_players: u32,
     pub tick_rate: u32,
     pub description: String,
+    /// Java binary the game server is launched with; `java` on the PATH if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +51,8 @@ pub struct PluginSettings {
     pub auto_load: bool,
     pub hot_reload: bool,
     pub sandbox_enabled: bool,
+    #[serde(default)]
+    pub sandbox: SandboxPolicy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +62,13 @@ pub struct PerformanceSettings {
     pub max_entities_per_tick: u32,
     pub max_chunk_updates_per_tick: u32,
     pub memory_pool_size_mb: u32,
+    #[serde(default)]
+    pub slow_tick: SlowTickConfig,
+    #[serde(default)]
+    pub profiler: ProfilerConfig,
+    /// Distance tiers and hysteresis for adaptive entity throttling; `adaptive_throttling` switches it on.
+    #[serde(default)]
+    pub throttling: ThrottlePolicy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,12 +96,14 @@ impl Default for ServerConfig {
                 max_players: 100,
                 tick_rate: 20,
                 description: "A Pond-powered server".to_string(),
+                java_path: None,
             },
             plugins: PluginSettings {
                 directory: "plugins".to_string(),
                 auto_load: true,
                 hot_reload: true,
                 sandbox_enabled: true,
+                sandbox: SandboxPolicy::default(),
             },
             performance: PerformanceSettings {
                 tick_budget_ms: 50.0,
@@ -76,6 +111,9 @@ impl Default for ServerConfig {
                 max_entities_per_tick: 100,
                 max_chunk_updates_per_tick: 50,
                 memory_pool_size_mb: 256,
+                slow_tick: SlowTickConfig::default(),
+                profiler: ProfilerConfig::default(),
+                throttling: ThrottlePolicy::default(),
             },
             assets: AssetSettings {
                 max_cosmetic_size_mb: 5,
@@ -93,6 +131,11 @@ impl Default for ServerConfig {
                 advertise_capabilities: true,
                 accept_asset_manifests: true,
             },
+            log_parser: LogParserConfig::default(),
+            events: EventBusConfig::default(),
+            anticheat: AnticheatConfig::default(),
+            health_http: HealthEndpointConfig::default(),
+            aliases: BTreeMap::new(),
         }
     }
 }
@@ -156,6 +199,12 @@ impl ConfigManager {
         self.config.read().clone()
     }
     
+    /// Changes the in-memory config at runtime; `save` writes it back to disk.
+    pub fn update(&self, change: impl FnOnce(&mut ServerConfig)) {
+        change(&mut self.config.write());
+        *self.version.write() += 1;
+    }
+    
     pub fn get_string(&self, key: &str) -> Option<String> {
         let config = self.config.read();
         match key {
@@ -163,6 +212,7 @@ impl ConfigManager {
             "server.description" => Some(config.server.description.clone()),
             "plugins.directory" => Some(config.plugins.directory.clone()),
             "assets.cache_directory" => Some(config.assets.cache_directory.clone()),
+            "health_http.bind" => Some(config.health_http.bind.clone()),
             _ => None,
         }
     }
@@ -183,6 +233,9 @@ impl ConfigManager {
         let config = self.config.read();
         match key {
             "performance.tick_budget_ms" => Some(config.performance.tick_budget_ms),
+            "performance.slow_tick.threshold_ms" => Some(config.performance.slow_tick.threshold_ms),
+            "performance.throttling.release_below" => Some(config.performance.throttling.release_below),
+            "health_http.min_tps" => Some(config.health_http.min_tps),
             _ => None,
         }
     }
@@ -194,10 +247,12 @@ impl ConfigManager {
             "plugins.hot_reload" => Some(config.plugins.hot_reload),
             "plugins.sandbox_enabled" => Some(config.plugins.sandbox_enabled),
             "performance.adaptive_throttling" => Some(config.performance.adaptive_throttling),
+            "performance.profiler.enabled" => Some(config.performance.profiler.enabled),
             "assets.require_approval" => Some(config.assets.require_approval),
             "integration.enabled" => Some(config.integration.enabled),
             "integration.advertise_capabilities" => Some(config.integration.advertise_capabilities),
             "integration.accept_asset_manifests" => Some(config.integration.accept_asset_manifests),
+            "health_http.enabled" => Some(config.health_http.enabled),
             _ => None,
         }
     }
@@ -206,3 +261,21 @@ impl ConfigManager {
         *self.version.read()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_java_path_is_optional() {
+        let config = ServerConfig::default();
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(!content.contains("java_path"));
+        let parsed: ServerConfig = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.server.java_path, None);
+
+        let content = content.replacen("[server]\n", "[server]\njava_path = \"/opt/jdk/bin/java\"\n", 1);
+        let parsed: ServerConfig = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.server.java_path, Some(PathBuf::from("/opt/jdk/bin/java")));
+    }
+}
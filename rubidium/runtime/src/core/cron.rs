@@ -0,0 +1 @@
+pub use yellow_tale_core::cron::CronSchedule;
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Values below this (in microseconds) get one bucket each.
+const LINEAR_LIMIT: u64 = 64;
+/// Sub-buckets per power of two above `LINEAR_LIMIT`, giving ~3% relative error.
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Largest trackable value is just under 2^26 us (~67s); anything above is clamped.
+const MAX_EXPONENT: u32 = 26;
+const BUCKET_COUNT: usize =
+    LINEAR_LIMIT as usize + (MAX_EXPONENT - LINEAR_LIMIT.trailing_zeros()) as usize * SUB_BUCKETS as usize;
+
+/// Width of each time slice kept by [`SlidingTickHistogram`].
+pub const SLICE_SECS: u64 = 5;
+
+pub const TICK_WINDOWS: [(&str, u64); 3] = [("1m", 60), ("5m", 300), ("15m", 900)];
+
+/// Log-linear (HDR-style) histogram of tick durations, bucketed in microseconds.
+#[derive(Debug, Clone)]
+pub struct TickHistogram {
+    counts: Vec<u32>,
+    total: u64,
+    max_us: u64,
+}
+
+impl TickHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; BUCKET_COUNT],
+            total: 0,
+            max_us: 0,
+        }
+    }
+
+    pub fn record(&mut self, duration_ms: f64) {
+        let us = (duration_ms.max(0.0) * 1000.0).round() as u64;
+        self.counts[bucket_index(us)] += 1;
+        self.total += 1;
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn merge(&mut self, other: &TickHistogram) {
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.total += other.total;
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    pub fn max_ms(&self) -> f64 {
+        self.max_us as f64 / 1000.0
+    }
+
+    /// Value at the given percentile (0-100), in milliseconds.
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut seen = 0u64;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count as u64;
+            if seen >= rank {
+                return bucket_value(index).min(self.max_us) as f64 / 1000.0;
+            }
+        }
+        self.max_ms()
+    }
+
+    pub fn summary(&self, window: &str) -> TickWindowSummary {
+        TickWindowSummary {
+            window: window.to_string(),
+            count: self.total,
+            p50_ms: self.percentile(50.0),
+            p95_ms: self.percentile(95.0),
+            p99_ms: self.percentile(99.0),
+            max_ms: self.max_ms(),
+        }
+    }
+}
+
+impl Default for TickHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn bucket_index(us: u64) -> usize {
+    if us < LINEAR_LIMIT {
+        return us as usize;
+    }
+    let exponent = (63 - us.leading_zeros()).min(MAX_EXPONENT - 1);
+    let us = us.min((1 << MAX_EXPONENT) - 1);
+    let sub = (us >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    LINEAR_LIMIT as usize
+        + (exponent - LINEAR_LIMIT.trailing_zeros()) as usize * SUB_BUCKETS as usize
+        + sub as usize
+}
+
+/// Highest value that maps to the bucket, in microseconds.
+fn bucket_value(index: usize) -> u64 {
+    if index < LINEAR_LIMIT as usize {
+        return index as u64;
+    }
+    let offset = index - LINEAR_LIMIT as usize;
+    let exponent = (offset / SUB_BUCKETS as usize) as u32 + LINEAR_LIMIT.trailing_zeros();
+    let sub = (offset % SUB_BUCKETS as usize) as u64;
+    let width = 1u64 << (exponent - SUB_BUCKET_BITS);
+    let lower = (SUB_BUCKETS + sub) * width;
+    lower + width - 1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickWindowSummary {
+    pub window: String,
+    pub count: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Tick histograms split into fixed time slices so 1m/5m/15m windows can slide.
+pub struct SlidingTickHistogram {
+    slices: VecDeque<(u64, TickHistogram)>,
+    retention_secs: u64,
+}
+
+impl SlidingTickHistogram {
+    pub fn new(retention_secs: u64) -> Self {
+        Self {
+            slices: VecDeque::new(),
+            retention_secs,
+        }
+    }
+
+    /// Records a tick that finished `now_secs` seconds after the monitor started.
+    pub fn record(&mut self, duration_ms: f64, now_secs: u64) {
+        let slice_start = now_secs - now_secs % SLICE_SECS;
+        match self.slices.back_mut() {
+            Some((start, histogram)) if *start == slice_start => histogram.record(duration_ms),
+            _ => {
+                let mut histogram = TickHistogram::new();
+                histogram.record(duration_ms);
+                self.slices.push_back((slice_start, histogram));
+            }
+        }
+        self.expire(now_secs);
+    }
+
+    fn expire(&mut self, now_secs: u64) {
+        while let Some((start, _)) = self.slices.front() {
+            if start + SLICE_SECS + self.retention_secs <= now_secs {
+                self.slices.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Merged histogram of every slice overlapping the last `window_secs` seconds.
+    pub fn window(&self, window_secs: u64, now_secs: u64) -> TickHistogram {
+        let mut merged = TickHistogram::new();
+        for (start, histogram) in &self.slices {
+            if start + SLICE_SECS + window_secs > now_secs {
+                merged.merge(histogram);
+            }
+        }
+        merged
+    }
+
+    pub fn summaries(&self, now_secs: u64) -> Vec<TickWindowSummary> {
+        TICK_WINDOWS.iter()
+            .map(|(name, secs)| self.window(*secs, now_secs).summary(name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        let tolerance = expected * 0.035 + 0.001;
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected ~{} got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_uniform_distribution_percentiles() {
+        let mut histogram = TickHistogram::new();
+        for ms in 1..=1000 {
+            histogram.record(ms as f64);
+        }
+
+        assert_eq!(histogram.count(), 1000);
+        assert_close(histogram.percentile(50.0), 500.0);
+        assert_close(histogram.percentile(95.0), 950.0);
+        assert_close(histogram.percentile(99.0), 990.0);
+        assert_eq!(histogram.max_ms(), 1000.0);
+    }
+
+    #[test]
+    fn test_constant_distribution_is_exact_at_max() {
+        let mut histogram = TickHistogram::new();
+        for _ in 0..500 {
+            histogram.record(50.0);
+        }
+
+        assert_close(histogram.percentile(50.0), 50.0);
+        assert_eq!(histogram.percentile(99.0), 50.0);
+        assert_eq!(histogram.max_ms(), 50.0);
+    }
+
+    #[test]
+    fn test_bimodal_distribution_tail() {
+        let mut histogram = TickHistogram::new();
+        for _ in 0..90 {
+            histogram.record(20.0);
+        }
+        for _ in 0..10 {
+            histogram.record(200.0);
+        }
+
+        assert_close(histogram.percentile(50.0), 20.0);
+        assert_close(histogram.percentile(90.0), 20.0);
+        assert_close(histogram.percentile(95.0), 200.0);
+        assert_close(histogram.percentile(99.0), 200.0);
+    }
+
+    #[test]
+    fn test_sub_millisecond_and_clamped_values() {
+        let mut histogram = TickHistogram::new();
+        histogram.record(0.032);
+        histogram.record(120_000.0);
+
+        assert_eq!(histogram.percentile(50.0), 0.032);
+        assert!(histogram.percentile(100.0) > 60_000.0);
+        assert_eq!(histogram.max_ms(), 120_000.0);
+    }
+
+    #[test]
+    fn test_bucket_boundaries_round_trip() {
+        for us in [0u64, 63, 64, 65, 127, 128, 1_000, 50_000, 1_234_567] {
+            let value = bucket_value(bucket_index(us));
+            let error = value.abs_diff(us) as f64 / us.max(1) as f64;
+            assert!(error <= 1.0 / SUB_BUCKETS as f64, "{} -> {}", us, value);
+        }
+    }
+
+    #[test]
+    fn test_sliding_windows_expire_old_slices() {
+        let mut sliding = SlidingTickHistogram::new(900);
+        for _ in 0..100 {
+            sliding.record(100.0, 0);
+        }
+        for _ in 0..100 {
+            sliding.record(10.0, 400);
+        }
+
+        let summaries = sliding.summaries(400);
+        assert_eq!(summaries[0].window, "1m");
+        assert_eq!(summaries[0].count, 100);
+        assert_close(summaries[0].p99_ms, 10.0);
+        assert_eq!(summaries[1].count, 100);
+        assert_eq!(summaries[2].count, 200);
+        assert_eq!(summaries[2].max_ms, 100.0);
+
+        sliding.record(10.0, 1000);
+        assert_eq!(sliding.window(900, 1000).count(), 101);
+        assert_eq!(sliding.window(900, 1000).max_ms(), 10.0);
+    }
+}
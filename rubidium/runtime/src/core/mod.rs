@@ -1,7 +1,13 @@
 pub mod server;
 pub mod plugins;
+pub mod sandbox;
 pub mod scheduler;
+pub mod cron;
+pub mod task_graph;
 pub mod performance;
+pub mod histogram;
+pub mod profiler;
+pub mod throttle;
 pub mod assets;
 pub mod config;
 pub mod telemetry;
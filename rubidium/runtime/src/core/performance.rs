@@ -1,9 +1,19 @@
+use crate::admin::health::ComponentHealth;
+use crate::bridge::{GameEvent, TickBreakdown, TaskTiming};
+use crate::core::profiler::{TickProfile, TickProfiler};
+use crate::core::throttle::{EntityThrottler, ThrottleChange};
+use crate::core::histogram::{SlidingTickHistogram, TickWindowSummary};
 use crate::core::telemetry::TelemetryCollector;
+use crate::events::{EventBus, EventBusStats};
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
+
+const HISTOGRAM_RETENTION_SECS: u64 = 900;
 
 #[derive(Debug, Clone)]
 pub struct PerformanceMetrics {
@@ -25,9 +35,29 @@ pub struct TaskMetrics {
     pub budget_exceeded_count: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowTickConfig {
+    /// Ticks longer than this publish a PerformanceAlert (and a SlowTick while profiling), and a 1m p95
+    /// above it marks health Degraded.
+    pub threshold_ms: f64,
+    /// Minimum gap between two alerts so a struggling server doesn't flood the bus.
+    pub alert_cooldown_ms: u64,
+}
+
+impl Default for SlowTickConfig {
+    fn default() -> Self {
+        Self {
+            threshold_ms: 50.0,
+            alert_cooldown_ms: 5000,
+        }
+    }
+}
+
 struct TickStats {
     durations: Vec<f64>,
     last_reset: std::time::Instant,
+    histogram: SlidingTickHistogram,
+    last_alert: Option<Instant>,
 }
 
 impl TickStats {
@@ -35,6 +65,8 @@ impl TickStats {
         Self {
             durations: Vec::with_capacity(1000),
             last_reset: std::time::Instant::now(),
+            histogram: SlidingTickHistogram::new(HISTOGRAM_RETENTION_SECS),
+            last_alert: None,
         }
     }
 }
@@ -46,6 +78,15 @@ pub struct PerformanceMonitor {
     running: AtomicBool,
     tick_count: AtomicU64,
     entity_budget: RwLock<EntityBudget>,
+    started_at: Instant,
+    slow_tick: parking_lot::RwLock<SlowTickConfig>,
+    event_bus: parking_lot::RwLock<Option<Arc<EventBus>>>,
+    entities_processed: AtomicU64,
+    entities_reported: AtomicBool,
+    p95_1m_bits: AtomicU64,
+    alert_count: AtomicU64,
+    profiler: Arc<TickProfiler>,
+    throttler: Arc<EntityThrottler>,
 }
 
 #[derive(Debug, Clone)]
@@ -76,9 +117,46 @@ impl PerformanceMonitor {
             running: AtomicBool::new(false),
             tick_count: AtomicU64::new(0),
             entity_budget: RwLock::new(EntityBudget::default()),
+            started_at: Instant::now(),
+            slow_tick: parking_lot::RwLock::new(SlowTickConfig::default()),
+            event_bus: parking_lot::RwLock::new(None),
+            entities_processed: AtomicU64::new(0),
+            entities_reported: AtomicBool::new(false),
+            p95_1m_bits: AtomicU64::new(0f64.to_bits()),
+            alert_count: AtomicU64::new(0),
+            profiler: Arc::new(TickProfiler::default()),
+            throttler: Arc::new(EntityThrottler::default()),
         }
     }
     
+    pub fn set_event_bus(&self, event_bus: Arc<EventBus>) {
+        *self.event_bus.write() = Some(event_bus);
+    }
+    
+    pub fn set_slow_tick_config(&self, config: SlowTickConfig) {
+        *self.slow_tick.write() = config;
+    }
+    
+    pub fn slow_tick_config(&self) -> SlowTickConfig {
+        self.slow_tick.read().clone()
+    }
+    
+    /// Distance-tiered entity update rates, adjusted by `record_tick`.
+    pub fn throttler(&self) -> &Arc<EntityThrottler> {
+        &self.throttler
+    }
+    
+    /// Per-scope tick timing. Closed out by `record_tick`.
+    pub fn profiler(&self) -> &Arc<TickProfiler> {
+        &self.profiler
+    }
+    
+    /// Called by the game adapter when it knows how many entities the current tick processed.
+    pub fn report_entities_processed(&self, count: u64) {
+        self.entities_processed.fetch_add(count, Ordering::Relaxed);
+        self.entities_reported.store(true, Ordering::Relaxed);
+    }
+    
     pub async fn start_monitoring(&self) {
         self.running.store(true, Ordering::SeqCst);
         info!("Performance monitoring started");
@@ -90,23 +168,70 @@ impl PerformanceMonitor {
     }
     
     pub async fn record_tick_duration(&self, duration_ms: f64) {
+        self.record_tick(duration_ms, Vec::new()).await;
+    }
+    
+    /// Records a finished tick along with the scheduler tasks it ran.
+    pub async fn record_tick(&self, duration_ms: f64, tasks: Vec<TaskTiming>) {
+        let entities = self.entities_processed.swap(0, Ordering::Relaxed);
+        let entities_reported = self.entities_reported.swap(false, Ordering::Relaxed);
+        // Always close the profiler frame so scopes can't leak into the next tick.
+        let tick = self.tick_count.load(Ordering::Relaxed) + 1;
+        let profile = self.profiler.finish_tick(tick, duration_ms);
+        
         if !self.running.load(Ordering::Relaxed) {
             return;
         }
         
-        self.tick_count.fetch_add(1, Ordering::Relaxed);
+        let tick = self.tick_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let now_secs = self.started_at.elapsed().as_secs();
+        let slow_tick = self.slow_tick_config();
         
-        let mut stats = self.tick_stats.write().await;
-        stats.durations.push(duration_ms);
-        
-        if stats.durations.len() > 1200 {
-            stats.durations.drain(0..200);
+        if let Some(change) = self.throttler.observe_tick(duration_ms) {
+            self.publish_throttle_change(change).await;
         }
         
-        if stats.last_reset.elapsed().as_secs() >= 60 {
-            let metrics = self.calculate_metrics_internal(&stats).await;
+        let (alert, metrics) = {
+            let mut stats = self.tick_stats.write().await;
+            stats.durations.push(duration_ms);
+            stats.histogram.record(duration_ms, now_secs);
+            let p95 = stats.histogram.window(60, now_secs).percentile(95.0);
+            self.p95_1m_bits.store(p95.to_bits(), Ordering::Relaxed);
+            
+            let cooled_down = stats.last_alert
+                .map(|t| t.elapsed().as_millis() as u64 >= slow_tick.alert_cooldown_ms)
+                .unwrap_or(true);
+            let alert = duration_ms > slow_tick.threshold_ms && cooled_down;
+            if alert {
+                stats.last_alert = Some(Instant::now());
+            }
+            
+            if stats.durations.len() > 1200 {
+                stats.durations.drain(0..200);
+            }
+            
+            let mut metrics = None;
+            if stats.last_reset.elapsed().as_secs() >= 60 {
+                metrics = Some(self.calculate_metrics_internal(&stats).await);
+                stats.last_reset = std::time::Instant::now();
+            }
+            (alert, metrics)
+        };
+        
+        // Published with tick_stats released, since handlers may read it.
+        if alert {
+            self.publish_slow_tick(tick, duration_ms, slow_tick.threshold_ms, TickBreakdown {
+                tasks,
+                entities_processed: entities_reported.then_some(entities),
+            }).await;
+        }
+        if duration_ms > slow_tick.threshold_ms {
+            if let Some(profile) = profile {
+                self.publish_tick_profile(profile, slow_tick.threshold_ms).await;
+            }
+        }
+        if let Some(metrics) = metrics {
             self.telemetry.record_performance_snapshot(metrics).await;
-            stats.last_reset = std::time::Instant::now();
         }
         
         if duration_ms > 45.0 {
@@ -124,6 +249,104 @@ impl PerformanceMonitor {
         }
     }
     
+    async fn publish_slow_tick(&self, tick: u64, duration_ms: f64, threshold_ms: f64, breakdown: TickBreakdown) {
+        self.alert_count.fetch_add(1, Ordering::Relaxed);
+        warn!("Slow tick {}: {:.1}ms (threshold {:.1}ms)", tick, duration_ms, threshold_ms);
+        
+        let event_bus = self.event_bus.read().clone();
+        if let Some(event_bus) = event_bus {
+            event_bus.emit(GameEvent::PerformanceAlert {
+                tick,
+                duration_ms,
+                threshold_ms,
+                breakdown,
+            }).await;
+        }
+    }
+    
+    async fn publish_throttle_change(&self, change: ThrottleChange) {
+        if change.level > change.previous {
+            warn!("Throttling distant entities harder: level {} -> {} ({:.1}ms tick)", change.previous, change.level, change.tick_ms);
+        } else {
+            info!("Easing entity throttling: level {} -> {}", change.previous, change.level);
+        }
+        
+        let event_bus = self.event_bus.read().clone();
+        if let Some(event_bus) = event_bus {
+            event_bus.emit(GameEvent::ThrottleChanged {
+                previous: change.previous,
+                level: change.level,
+                tick_ms: change.tick_ms,
+            }).await;
+        }
+    }
+    
+    /// Unlike PerformanceAlert this isn't rate limited: every slow tick is traced while profiling is on.
+    async fn publish_tick_profile(&self, profile: TickProfile, threshold_ms: f64) {
+        let event_bus = self.event_bus.read().clone();
+        if let Some(event_bus) = event_bus {
+            event_bus.emit(GameEvent::SlowTick {
+                tick: profile.tick,
+                duration_ms: profile.total_ms,
+                threshold_ms,
+                scopes: profile.scopes,
+                untracked_ms: profile.untracked_ms,
+            }).await;
+        }
+    }
+    
+    /// p50/p95/p99/max over the 1m, 5m and 15m windows.
+    pub async fn tick_summaries(&self) -> Vec<TickWindowSummary> {
+        let now_secs = self.started_at.elapsed().as_secs();
+        self.tick_stats.read().await.histogram.summaries(now_secs)
+    }
+    
+    pub fn p95_tick_ms(&self) -> f64 {
+        f64::from_bits(self.p95_1m_bits.load(Ordering::Relaxed))
+    }
+    
+    pub fn slow_tick_alert_count(&self) -> u64 {
+        self.alert_count.load(Ordering::Relaxed)
+    }
+    
+    /// Tick health for the admin HealthCheck, based on the last minute's p95.
+    pub fn tick_health(&self) -> ComponentHealth {
+        let p95 = self.p95_tick_ms();
+        let threshold = self.slow_tick.read().threshold_ms;
+        let health = if p95 > threshold {
+            ComponentHealth::degraded("ticks", format!("p95 tick time {:.1}ms exceeds {:.1}ms", p95, threshold))
+        } else {
+            ComponentHealth::healthy("ticks")
+        };
+        health
+            .with_detail("p95_ms", format!("{:.2}", p95))
+            .with_detail("threshold_ms", format!("{:.2}", threshold))
+    }
+    
+    /// Published and per-subscriber dropped counts from the event bus.
+    pub fn event_bus_stats(&self) -> Option<EventBusStats> {
+        self.event_bus.read().as_ref().map(|bus| bus.stats())
+    }
+    
+    /// Degraded while any subscriber is dropping events or has been disconnected.
+    pub fn event_health(&self) -> ComponentHealth {
+        let Some(stats) = self.event_bus_stats() else {
+            return ComponentHealth::healthy("events");
+        };
+        let disconnected: Vec<&str> = stats.disconnected().map(|s| s.name.as_str()).collect();
+        let dropped = stats.total_dropped();
+        let health = if !disconnected.is_empty() {
+            ComponentHealth::degraded("events", format!("disconnected slow subscribers: {}", disconnected.join(", ")))
+        } else if dropped > 0 {
+            ComponentHealth::degraded("events", format!("{} events dropped by slow subscribers", dropped))
+        } else {
+            ComponentHealth::healthy("events")
+        };
+        health
+            .with_detail("published", stats.published.to_string())
+            .with_detail("dropped", dropped.to_string())
+    }
+    
     async fn calculate_metrics_internal(&self, stats: &TickStats) -> PerformanceMetrics {
         let durations = &stats.durations;
         
@@ -197,3 +420,85 @@ impl PerformanceMonitor {
         *self.entity_budget.write().await = budget;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_tick_publishes_alert_and_degrades_health() {
+        let monitor = PerformanceMonitor::new(Arc::new(TelemetryCollector::new()));
+        let event_bus = Arc::new(EventBus::new());
+        let mut receiver = event_bus.subscribe();
+        monitor.set_event_bus(event_bus);
+        monitor.start_monitoring().await;
+
+        for _ in 0..19 {
+            monitor.record_tick_duration(10.0).await;
+        }
+        assert_eq!(monitor.tick_health().status, crate::admin::HealthStatus::Healthy);
+
+        monitor.report_entities_processed(42);
+        monitor.record_tick(120.0, vec![TaskTiming { name: "ai".to_string(), duration_ms: 110.0 }]).await;
+
+        match receiver.try_recv().unwrap() {
+            GameEvent::PerformanceAlert { tick, duration_ms, breakdown, .. } => {
+                assert_eq!(tick, 20);
+                assert_eq!(duration_ms, 120.0);
+                assert_eq!(breakdown.tasks.len(), 1);
+                assert_eq!(breakdown.entities_processed, Some(42));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(monitor.tick_health().status, crate::admin::HealthStatus::Healthy);
+
+        for _ in 0..5 {
+            monitor.record_tick_duration(120.0).await;
+        }
+        assert!(receiver.try_recv().is_err(), "alerts should respect the cooldown");
+        assert_eq!(monitor.slow_tick_alert_count(), 1);
+        assert_eq!(monitor.tick_health().status, crate::admin::HealthStatus::Degraded);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[tokio::test]
+    async fn test_profiled_slow_ticks_publish_scope_breakdown() {
+        use crate::core::profiler::ProfilerConfig;
+        use crate::core::scheduler::{Scheduler, TaskPriority};
+        use std::time::Duration;
+
+        let monitor = Arc::new(PerformanceMonitor::new(Arc::new(TelemetryCollector::new())));
+        let event_bus = Arc::new(EventBus::new());
+        let mut receiver = event_bus.subscribe();
+        monitor.set_event_bus(event_bus);
+        monitor.set_slow_tick_config(SlowTickConfig { threshold_ms: 5.0, alert_cooldown_ms: 60_000 });
+        monitor.profiler().configure(ProfilerConfig { enabled: true, worst_ticks: 2 });
+        monitor.start_monitoring().await;
+
+        let scheduler = Scheduler::new(monitor.clone());
+        scheduler.start().await;
+        scheduler.schedule_repeating("autosave", Duration::from_millis(1), TaskPriority::Normal, || async {
+            std::thread::sleep(Duration::from_millis(8));
+        });
+
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            scheduler.tick().await;
+        }
+
+        let mut slow_ticks = 0;
+        while let Ok(event) = receiver.try_recv() {
+            if let GameEvent::SlowTick { duration_ms, scopes, untracked_ms, .. } = event {
+                slow_ticks += 1;
+                assert_eq!(scopes[0].name, "scheduler.jobs");
+                assert!(scopes[0].duration_ms >= 8.0);
+                let tracked: f64 = scopes.iter().map(|s| s.duration_ms).sum();
+                assert!((tracked + untracked_ms - duration_ms).abs() < 0.01);
+                assert!(untracked_ms < 2.0, "untracked {}ms of {}ms", untracked_ms, duration_ms);
+            }
+        }
+        assert_eq!(slow_ticks, 3, "every slow tick is traced, unlike the rate-limited alert");
+        assert_eq!(monitor.slow_tick_alert_count(), 1);
+        assert_eq!(monitor.profiler().slowest_ticks().len(), 2);
+    }
+}
@@ -1,7 +1,13 @@
+use crate::bridge::GameEvent;
 use crate::core::config::ConfigManager;
+use crate::core::sandbox::{Capability, PluginCapabilities, PluginSandbox, PluginViolation, SandboxPolicy, VIOLATION_HISTORY};
+use crate::events::EventBus;
 use async_trait::async_trait;
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{info, warn, error};
 
@@ -14,6 +20,8 @@ pub struct PluginMetadata {
     pub description: String,
     pub dependencies: Vec<PluginDependency>,
     pub api_version: String,
+    #[serde(default)]
+    pub capabilities: PluginCapabilities,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,10 +57,19 @@ pub struct PluginInstance {
     pub error: Option<String>,
 }
 
+#[derive(Default)]
+struct ViolationLog {
+    strikes: u32,
+    recent: VecDeque<PluginViolation>,
+}
+
 pub struct PluginManager {
     plugins: DashMap<String, PluginInstance>,
     config: Arc<ConfigManager>,
     plugins_dir: String,
+    sandbox_root: PathBuf,
+    event_bus: RwLock<Option<Arc<EventBus>>>,
+    violations: DashMap<String, ViolationLog>,
 }
 
 impl PluginManager {
@@ -63,8 +80,31 @@ impl PluginManager {
             plugins: DashMap::new(),
             config,
             plugins_dir,
+            sandbox_root: std::env::current_dir().unwrap_or_default(),
+            event_bus: RwLock::new(None),
+            violations: DashMap::new(),
         }
     }
+
+    /// Directory relative sandbox paths resolve against; the working directory by default.
+    pub fn with_sandbox_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.sandbox_root = root.into();
+        self
+    }
+
+    pub fn sandbox_root(&self) -> &Path {
+        &self.sandbox_root
+    }
+
+    /// Bus that receives `PluginViolation` events.
+    pub fn set_event_bus(&self, event_bus: Arc<EventBus>) {
+        *self.event_bus.write() = Some(event_bus);
+    }
+
+    fn sandbox_policy(&self) -> Option<SandboxPolicy> {
+        let plugins = self.config.get().plugins;
+        plugins.sandbox_enabled.then_some(plugins.sandbox)
+    }
     
     pub async fn load_all(&self) -> Result<(), String> {
         info!("Discovering plugins in: {}", self.plugins_dir);
@@ -103,19 +143,33 @@ impl PluginManager {
         
         let ordered = self.resolve_load_order(discovered)?;
         
+        let policy = self.sandbox_policy();
         for (order, metadata) in ordered.into_iter().enumerate() {
+            let admitted = match &policy {
+                Some(policy) => policy.admit(&metadata, &self.sandbox_root),
+                None => Ok(()),
+            };
+            let (state, error) = match admitted {
+                Ok(()) => (PluginState::Discovered, None),
+                Err(e) => {
+                    error!("Refusing to load plugin {}: {}", metadata.id, e);
+                    (PluginState::Failed, Some(e.to_string()))
+                }
+            };
             let instance = PluginInstance {
                 metadata: metadata.clone(),
-                state: PluginState::Discovered,
+                state,
                 load_order: order as i32,
-                error: None,
+                error,
             };
             self.plugins.insert(metadata.id.clone(), instance);
         }
         
-        for entry in self.plugins.iter() {
-            let id = entry.key().clone();
-            drop(entry);
+        let ids: Vec<String> = self.plugins.iter()
+            .filter(|e| e.state != PluginState::Failed)
+            .map(|e| e.key().clone())
+            .collect();
+        for id in ids {
             if let Err(e) = self.enable_plugin(&id).await {
                 error!("Failed to enable plugin {}: {}", id, e);
             }
@@ -159,6 +213,9 @@ impl PluginManager {
         if instance.state == PluginState::Enabled {
             return Ok(());
         }
+        if instance.state == PluginState::Failed {
+            return Err(instance.error.clone().unwrap_or_else(|| "Plugin failed to load".to_string()));
+        }
         
         for dep in &instance.metadata.dependencies {
             if !dep.optional {
@@ -244,4 +301,80 @@ impl PluginManager {
     pub fn get_plugin_state(&self, id: &str) -> Option<PluginState> {
         self.plugins.get(id).map(|p| p.state)
     }
+
+    pub fn get_plugin_error(&self, id: &str) -> Option<String> {
+        self.plugins.get(id).and_then(|p| p.error.clone())
+    }
+
+    pub fn is_enabled(&self, id: &str) -> bool {
+        self.get_plugin_state(id) == Some(PluginState::Enabled)
+    }
+
+    /// Handle through which a plugin reaches files, events and commands,
+    /// limited to what its manifest declares.
+    pub fn sandbox(self: &Arc<Self>, id: &str) -> Result<PluginSandbox, String> {
+        let capabilities = self.plugins.get(id)
+            .map(|p| p.metadata.capabilities.clone())
+            .ok_or("Plugin not found")?;
+        let enforced = self.sandbox_policy().is_some();
+        Ok(PluginSandbox::new(id.to_string(), capabilities, enforced, self.clone()))
+    }
+
+    /// Counts a strike against the plugin, publishes a `PluginViolation` and
+    /// disables the plugin once it reaches `plugins.sandbox.max_strikes`.
+    pub async fn record_violation(&self, id: &str, capability: Capability, target: &str) -> u32 {
+        let violation = {
+            let mut log = self.violations.entry(id.to_string()).or_default();
+            log.strikes += 1;
+            let violation = PluginViolation {
+                plugin_id: id.to_string(),
+                capability,
+                target: target.to_string(),
+                strike: log.strikes,
+                at: chrono::Utc::now(),
+            };
+            log.recent.push_back(violation.clone());
+            if log.recent.len() > VIOLATION_HISTORY {
+                log.recent.pop_front();
+            }
+            violation
+        };
+        warn!("Plugin {} sandbox violation #{}: {} access to '{}'", id, violation.strike, capability.as_str(), target);
+
+        let event_bus = self.event_bus.read().clone();
+        if let Some(event_bus) = event_bus {
+            event_bus.emit(GameEvent::PluginViolation {
+                plugin_id: id.to_string(),
+                capability: capability.as_str().to_string(),
+                target: target.to_string(),
+                strikes: violation.strike,
+            }).await;
+        }
+
+        let max_strikes = self.config.get().plugins.sandbox.max_strikes;
+        if max_strikes > 0 && violation.strike >= max_strikes {
+            if let Some(mut instance) = self.plugins.get_mut(id) {
+                if instance.state == PluginState::Enabled {
+                    instance.state = PluginState::Disabled;
+                    instance.error = Some(format!("Disabled after {} sandbox violations", violation.strike));
+                    error!("Plugin {} disabled after {} sandbox violations", id, violation.strike);
+                }
+            }
+        }
+        violation.strike
+    }
+
+    pub fn strikes(&self, id: &str) -> u32 {
+        self.violations.get(id).map(|log| log.strikes).unwrap_or(0)
+    }
+
+    /// Recent violations, oldest first, for one plugin or all of them.
+    pub fn violations(&self, id: Option<&str>) -> Vec<PluginViolation> {
+        let mut violations: Vec<PluginViolation> = self.violations.iter()
+            .filter(|e| id.is_none_or(|id| e.key() == id))
+            .flat_map(|e| e.recent.iter().cloned().collect::<Vec<_>>())
+            .collect();
+        violations.sort_by_key(|v| v.at);
+        violations
+    }
 }
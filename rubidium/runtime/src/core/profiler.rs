@@ -0,0 +1,323 @@
+use crate::bridge::ScopeTiming;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::marker::PhantomData;
+#[cfg(feature = "profiling")]
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "profiling")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+
+/// Ticks `perf breakdown` averages over.
+pub const BREAKDOWN_TICKS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfilerConfig {
+    /// Time scopes each tick. Without the `profiling` feature scopes are
+    /// compiled out and this does nothing.
+    pub enabled: bool,
+    /// Slowest ticks kept with their full breakdown for `perf slowticks`.
+    pub worst_ticks: usize,
+}
+
+impl Default for ProfilerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            worst_ticks: 10,
+        }
+    }
+}
+
+/// One tick split by scope. Time outside every scope is `untracked_ms`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TickProfile {
+    pub tick: u64,
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub total_ms: f64,
+    /// Slowest first.
+    pub scopes: Vec<ScopeTiming>,
+    pub untracked_ms: f64,
+}
+
+/// A scope averaged over recent ticks.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopeSummary {
+    pub name: String,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    /// Fraction of all tick time spent in the scope.
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TickBreakdownSummary {
+    pub ticks: usize,
+    pub avg_tick_ms: f64,
+    /// Slowest on average first; untracked time is listed as `(untracked)`.
+    pub scopes: Vec<ScopeSummary>,
+}
+
+#[cfg(feature = "profiling")]
+#[derive(Default)]
+struct ProfilerState {
+    frame: HashMap<Cow<'static, str>, (f64, u32)>,
+    recent: VecDeque<TickProfile>,
+    worst: Vec<TickProfile>,
+    worst_capacity: usize,
+}
+
+/// Splits tick time by what the tick spent it on. Systems and plugin hooks
+/// wrap their work in `scope("entities")`; the time is charged to the tick
+/// being profiled when its guard drops. Scopes shouldn't nest, or the inner
+/// time is counted twice.
+pub struct TickProfiler {
+    #[cfg(feature = "profiling")]
+    enabled: AtomicBool,
+    #[cfg(feature = "profiling")]
+    state: parking_lot::Mutex<ProfilerState>,
+}
+
+/// Charges the time until it drops to its scope.
+#[must_use = "the scope ends when this guard is dropped"]
+pub struct ProfileScope<'a> {
+    #[cfg(feature = "profiling")]
+    active: Option<(&'a TickProfiler, Cow<'static, str>, Instant)>,
+    _profiler: PhantomData<&'a TickProfiler>,
+}
+
+impl Drop for ProfileScope<'_> {
+    fn drop(&mut self) {
+        #[cfg(feature = "profiling")]
+        if let Some((profiler, name, started)) = self.active.take() {
+            let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+            let mut state = profiler.state.lock();
+            let entry = state.frame.entry(name).or_default();
+            entry.0 += elapsed_ms;
+            entry.1 += 1;
+        }
+    }
+}
+
+impl TickProfiler {
+    pub fn new(config: ProfilerConfig) -> Self {
+        let profiler = Self {
+            #[cfg(feature = "profiling")]
+            enabled: AtomicBool::new(false),
+            #[cfg(feature = "profiling")]
+            state: parking_lot::Mutex::new(ProfilerState::default()),
+        };
+        profiler.configure(config);
+        profiler
+    }
+
+    pub fn configure(&self, config: ProfilerConfig) {
+        #[cfg(feature = "profiling")]
+        {
+            self.enabled.store(config.enabled, Ordering::Relaxed);
+            let mut state = self.state.lock();
+            state.worst_capacity = config.worst_ticks;
+            state.worst.truncate(config.worst_ticks);
+        }
+        #[cfg(not(feature = "profiling"))]
+        let _ = config;
+    }
+
+    /// Whether the `profiling` feature is compiled in.
+    pub const fn compiled_in() -> bool {
+        cfg!(feature = "profiling")
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "profiling")]
+        return self.enabled.load(Ordering::Relaxed);
+        #[cfg(not(feature = "profiling"))]
+        false
+    }
+
+    /// Times the work until the returned guard drops. Costs one atomic load
+    /// while profiling is off.
+    #[inline]
+    pub fn scope(&self, name: impl Into<Cow<'static, str>>) -> ProfileScope<'_> {
+        #[cfg(feature = "profiling")]
+        {
+            let active = self.is_enabled().then(|| (self, name.into(), Instant::now()));
+            ProfileScope { active, _profiler: PhantomData }
+        }
+        #[cfg(not(feature = "profiling"))]
+        {
+            let _ = name;
+            ProfileScope { _profiler: PhantomData }
+        }
+    }
+
+    /// Closes the current tick. Scopes that ended since the previous call
+    /// are charged to it; anything left of `total_ms` is untracked.
+    pub fn finish_tick(&self, tick: u64, total_ms: f64) -> Option<TickProfile> {
+        #[cfg(feature = "profiling")]
+        {
+            let mut state = self.state.lock();
+            let frame = std::mem::take(&mut state.frame);
+            if !self.is_enabled() {
+                return None;
+            }
+
+            let mut scopes: Vec<ScopeTiming> = frame.into_iter()
+                .map(|(name, (duration_ms, calls))| ScopeTiming { name: name.into_owned(), duration_ms, calls })
+                .collect();
+            scopes.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+            let tracked: f64 = scopes.iter().map(|s| s.duration_ms).sum();
+            let profile = TickProfile {
+                tick,
+                at: chrono::Utc::now(),
+                total_ms,
+                scopes,
+                untracked_ms: (total_ms - tracked).max(0.0),
+            };
+
+            state.recent.push_back(profile.clone());
+            if state.recent.len() > BREAKDOWN_TICKS {
+                state.recent.pop_front();
+            }
+            let capacity = state.worst_capacity;
+            let position = state.worst.partition_point(|p| p.total_ms >= total_ms);
+            if position < capacity {
+                state.worst.insert(position, profile.clone());
+                state.worst.truncate(capacity);
+            }
+            Some(profile)
+        }
+        #[cfg(not(feature = "profiling"))]
+        {
+            let _ = (tick, total_ms);
+            None
+        }
+    }
+
+    /// Per-scope averages over the last `BREAKDOWN_TICKS` profiled ticks.
+    pub fn breakdown(&self) -> TickBreakdownSummary {
+        #[cfg(feature = "profiling")]
+        {
+            let state = self.state.lock();
+            let ticks = state.recent.len();
+            if ticks == 0 {
+                return TickBreakdownSummary::default();
+            }
+            let total: f64 = state.recent.iter().map(|p| p.total_ms).sum();
+
+            let mut by_scope: HashMap<&str, (f64, f64)> = HashMap::new();
+            for profile in &state.recent {
+                let timings = profile.scopes.iter()
+                    .map(|s| (s.name.as_str(), s.duration_ms))
+                    .chain(std::iter::once(("(untracked)", profile.untracked_ms)));
+                for (name, ms) in timings {
+                    let entry = by_scope.entry(name).or_default();
+                    entry.0 += ms;
+                    entry.1 = entry.1.max(ms);
+                }
+            }
+
+            let mut scopes: Vec<ScopeSummary> = by_scope.into_iter()
+                .map(|(name, (sum, max))| ScopeSummary {
+                    name: name.to_string(),
+                    avg_ms: sum / ticks as f64,
+                    max_ms: max,
+                    share: if total > 0.0 { sum / total } else { 0.0 },
+                })
+                .collect();
+            scopes.sort_by(|a, b| b.avg_ms.total_cmp(&a.avg_ms));
+            TickBreakdownSummary {
+                ticks,
+                avg_tick_ms: total / ticks as f64,
+                scopes,
+            }
+        }
+        #[cfg(not(feature = "profiling"))]
+        TickBreakdownSummary::default()
+    }
+
+    /// The slowest profiled ticks, slowest first.
+    pub fn slowest_ticks(&self) -> Vec<TickProfile> {
+        #[cfg(feature = "profiling")]
+        return self.state.lock().worst.clone();
+        #[cfg(not(feature = "profiling"))]
+        Vec::new()
+    }
+}
+
+impl Default for TickProfiler {
+    fn default() -> Self {
+        Self::new(ProfilerConfig::default())
+    }
+}
+
+#[cfg(all(test, feature = "profiling"))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn enabled(worst_ticks: usize) -> TickProfiler {
+        TickProfiler::new(ProfilerConfig { enabled: true, worst_ticks })
+    }
+
+    #[test]
+    fn test_scopes_and_untracked_sum_to_tick_time() {
+        let profiler = enabled(3);
+        let started = Instant::now();
+        {
+            let _entities = profiler.scope("entities");
+            std::thread::sleep(Duration::from_millis(6));
+        }
+        for _ in 0..2 {
+            let _chunks = profiler.scope("chunk_io");
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        std::thread::sleep(Duration::from_millis(3));
+        let total_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let profile = profiler.finish_tick(1, total_ms).unwrap();
+        assert_eq!(profile.scopes[0].name, "entities");
+        assert_eq!(profile.scopes[1].name, "chunk_io");
+        assert_eq!(profile.scopes[1].calls, 2);
+        assert!(profile.scopes[0].duration_ms >= 6.0);
+        assert!(profile.scopes[1].duration_ms >= 4.0);
+        assert!(profile.untracked_ms >= 3.0, "{:?}", profile);
+        let tracked: f64 = profile.scopes.iter().map(|s| s.duration_ms).sum();
+        assert!(tracked <= total_ms);
+        assert!((tracked + profile.untracked_ms - total_ms).abs() < 0.01);
+
+        // The next tick starts from an empty frame.
+        let next = profiler.finish_tick(2, 1.0).unwrap();
+        assert!(next.scopes.is_empty());
+        assert_eq!(next.untracked_ms, 1.0);
+    }
+
+    #[test]
+    fn test_worst_ticks_keep_their_breakdown() {
+        let profiler = enabled(2);
+        for (tick, ms) in [(1, 10.0), (2, 80.0), (3, 20.0), (4, 60.0)] {
+            profiler.finish_tick(tick, ms);
+        }
+        let worst: Vec<u64> = profiler.slowest_ticks().iter().map(|p| p.tick).collect();
+        assert_eq!(worst, vec![2, 4]);
+
+        let breakdown = profiler.breakdown();
+        assert_eq!(breakdown.ticks, 4);
+        assert_eq!(breakdown.avg_tick_ms, 42.5);
+        assert_eq!(breakdown.scopes[0].name, "(untracked)");
+        assert!((breakdown.scopes[0].share - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_disabled_profiler_records_nothing() {
+        let profiler = TickProfiler::default();
+        {
+            let _scope = profiler.scope("entities");
+        }
+        assert!(profiler.finish_tick(1, 5.0).is_none());
+        assert!(profiler.slowest_ticks().is_empty());
+        assert_eq!(profiler.breakdown().ticks, 0);
+    }
+}
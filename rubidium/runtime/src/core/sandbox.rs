@@ -0,0 +1,467 @@
+use crate::bridge::{GameCommand, GameEvent};
+use crate::core::plugins::{PluginManager, PluginMetadata};
+use crate::events::{EventBus, SubscribeOptions};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Violations remembered per plugin for `plugins violations`.
+pub const VIOLATION_HISTORY: usize = 50;
+
+/// What a plugin declares it needs in the `[capabilities]` table of plugin.toml.
+/// Anything not listed here is refused at runtime.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginCapabilities {
+    /// Files and directories the plugin may touch through its VFS handle
+    pub filesystem: Vec<String>,
+    /// Hosts the plugin may connect to, `*.example.com` style wildcards allowed
+    pub network: Vec<String>,
+    /// Event names the plugin may subscribe to, as in `GameEvent::event_name`
+    pub events: Vec<String>,
+    /// Commands the plugin may dispatch, as in `GameCommand::command_name`
+    pub commands: Vec<String>,
+}
+
+/// Server-side limits on what a plugin manifest may ask for, `[plugins.sandbox]`
+/// in the server config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    /// Violations before a plugin is disabled; 0 never disables
+    pub max_strikes: u32,
+    /// Roots that declared filesystem paths must sit under
+    pub allowed_paths: Vec<String>,
+    pub allowed_hosts: Vec<String>,
+    pub allowed_events: Vec<String>,
+    pub allowed_commands: Vec<String>,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            max_strikes: 3,
+            allowed_paths: vec!["plugins".to_string(), "data".to_string()],
+            allowed_hosts: vec!["*".to_string()],
+            allowed_events: vec!["*".to_string()],
+            allowed_commands: vec!["*".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Capability {
+    Filesystem,
+    Network,
+    Events,
+    Commands,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Filesystem => "filesystem",
+            Capability::Network => "network",
+            Capability::Events => "events",
+            Capability::Commands => "commands",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SandboxError {
+    /// The manifest asks for something the server config doesn't allow
+    NotPermitted { plugin: String, capability: Capability, requested: String, allowed: Vec<String> },
+    /// The plugin tried something its manifest doesn't declare
+    Denied { plugin: String, capability: Capability, target: String },
+    PluginDisabled(String),
+    Io(String),
+}
+
+impl std::fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SandboxError::NotPermitted { plugin, capability, requested, allowed } => write!(
+                f,
+                "plugin {} requests {} capability '{}' which plugins.sandbox.allowed_{} does not permit (allowed: [{}])",
+                plugin,
+                capability.as_str(),
+                requested,
+                policy_key(*capability),
+                allowed.join(", ")
+            ),
+            SandboxError::Denied { plugin, capability, target } => write!(
+                f,
+                "plugin {} did not declare {} access to '{}'",
+                plugin,
+                capability.as_str(),
+                target
+            ),
+            SandboxError::PluginDisabled(plugin) => write!(f, "plugin {} is not enabled", plugin),
+            SandboxError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SandboxError {}
+
+fn policy_key(capability: Capability) -> &'static str {
+    match capability {
+        Capability::Filesystem => "paths",
+        Capability::Network => "hosts",
+        Capability::Events => "events",
+        Capability::Commands => "commands",
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginViolation {
+    pub plugin_id: String,
+    pub capability: Capability,
+    pub target: String,
+    pub strike: u32,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+/// `*` matches anything, `*.suffix` matches the suffix and its subdomains,
+/// `prefix*` matches by prefix, anything else must match exactly.
+pub fn matches_pattern(pattern: &str, value: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    if let Some(suffix) = pattern.strip_prefix("*.") {
+        return value == suffix || value.ends_with(&format!(".{}", suffix));
+    }
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        return value.starts_with(prefix);
+    }
+    pattern == value
+}
+
+/// Makes `path` absolute against `root` and folds `.`/`..` without touching the
+/// filesystem, so paths that don't exist yet can still be checked.
+pub fn normalize_path(root: &Path, path: &Path) -> PathBuf {
+    let joined = if path.is_absolute() { path.to_path_buf() } else { root.join(path) };
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Resolves symlinks for the longest existing ancestor of an already
+/// normalized path, so a link inside an allowed directory can't point out of it.
+fn resolve_links(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest.iter().rev().fold(canonical, |acc, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+fn within_any(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| path.starts_with(root))
+}
+
+impl SandboxPolicy {
+    /// Checks a manifest against the policy before the plugin is loaded.
+    pub fn admit(&self, metadata: &PluginMetadata, root: &Path) -> Result<(), SandboxError> {
+        let caps = &metadata.capabilities;
+        let not_permitted = |capability, requested: &str, allowed: &[String]| SandboxError::NotPermitted {
+            plugin: metadata.id.clone(),
+            capability,
+            requested: requested.to_string(),
+            allowed: allowed.to_vec(),
+        };
+
+        let allowed_roots: Vec<PathBuf> = self.allowed_paths.iter()
+            .map(|p| resolve_links(&normalize_path(root, Path::new(p))))
+            .collect();
+        for path in &caps.filesystem {
+            let resolved = resolve_links(&normalize_path(root, Path::new(path)));
+            if !within_any(&resolved, &allowed_roots) {
+                return Err(not_permitted(Capability::Filesystem, path, &self.allowed_paths));
+            }
+        }
+
+        let lists = [
+            (Capability::Network, &caps.network, &self.allowed_hosts),
+            (Capability::Events, &caps.events, &self.allowed_events),
+            (Capability::Commands, &caps.commands, &self.allowed_commands),
+        ];
+        for (capability, requested, allowed) in lists {
+            for value in requested {
+                // A wildcard request is only admitted by an equally broad allowance.
+                let ok = allowed.iter().any(|a| a == "*" || a == value || (!value.contains('*') && matches_pattern(a, value)));
+                if !ok {
+                    return Err(not_permitted(capability, value, allowed));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A plugin's handle on the server, checked against its manifest. Every
+/// undeclared access is refused and counts as a strike.
+#[derive(Clone)]
+pub struct PluginSandbox {
+    plugin_id: String,
+    capabilities: PluginCapabilities,
+    enforced: bool,
+    manager: Arc<PluginManager>,
+}
+
+impl PluginSandbox {
+    pub(crate) fn new(plugin_id: String, capabilities: PluginCapabilities, enforced: bool, manager: Arc<PluginManager>) -> Self {
+        Self { plugin_id, capabilities, enforced, manager }
+    }
+
+    pub fn plugin_id(&self) -> &str {
+        &self.plugin_id
+    }
+
+    pub fn vfs(&self) -> PluginVfs {
+        let root = self.manager.sandbox_root().to_path_buf();
+        let allowed = self.capabilities.filesystem.iter()
+            .map(|p| resolve_links(&normalize_path(&root, Path::new(p))))
+            .collect();
+        PluginVfs { sandbox: self.clone(), root, allowed }
+    }
+
+    fn ensure_enabled(&self) -> Result<(), SandboxError> {
+        if self.manager.is_enabled(&self.plugin_id) {
+            Ok(())
+        } else {
+            Err(SandboxError::PluginDisabled(self.plugin_id.clone()))
+        }
+    }
+
+    async fn deny(&self, capability: Capability, target: &str) -> SandboxError {
+        self.manager.record_violation(&self.plugin_id, capability, target).await;
+        SandboxError::Denied {
+            plugin: self.plugin_id.clone(),
+            capability,
+            target: target.to_string(),
+        }
+    }
+
+    async fn check(&self, capability: Capability, declared: &[String], target: &str) -> Result<(), SandboxError> {
+        self.ensure_enabled()?;
+        if !self.enforced || declared.iter().any(|p| matches_pattern(p, target)) {
+            return Ok(());
+        }
+        Err(self.deny(capability, target).await)
+    }
+
+    pub async fn check_host(&self, host: &str) -> Result<(), SandboxError> {
+        self.check(Capability::Network, &self.capabilities.network, host).await
+    }
+
+    /// Gate for a command the plugin wants dispatched to the game server.
+    pub async fn check_command(&self, command: &GameCommand) -> Result<(), SandboxError> {
+        self.check(Capability::Commands, &self.capabilities.commands, command.command_name()).await
+    }
+
+    /// Subscribes to `event_name` on the bus if the manifest declares it. The
+    /// handler stops receiving events once the plugin is disabled.
+    pub async fn subscribe<F>(&self, event_bus: &EventBus, event_name: &str, handler: F) -> Result<u64, SandboxError>
+    where
+        F: Fn(GameEvent) + Send + Sync + 'static,
+    {
+        self.check(Capability::Events, &self.capabilities.events, event_name).await?;
+        let manager = self.manager.clone();
+        let plugin_id = self.plugin_id.clone();
+        Ok(event_bus.on_with(event_name, SubscribeOptions::named(&self.plugin_id), move |event| {
+            if manager.is_enabled(&plugin_id) {
+                handler(event);
+            }
+        }))
+    }
+}
+
+/// File access for one plugin, limited to the paths in its manifest.
+/// Relative paths resolve against the sandbox root.
+pub struct PluginVfs {
+    sandbox: PluginSandbox,
+    root: PathBuf,
+    allowed: Vec<PathBuf>,
+}
+
+impl PluginVfs {
+    pub async fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf, SandboxError> {
+        self.sandbox.ensure_enabled()?;
+        let resolved = resolve_links(&normalize_path(&self.root, path.as_ref()));
+        if !self.sandbox.enforced || within_any(&resolved, &self.allowed) {
+            return Ok(resolved);
+        }
+        Err(self.sandbox.deny(Capability::Filesystem, &resolved.to_string_lossy()).await)
+    }
+
+    pub async fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, SandboxError> {
+        let path = self.resolve(path).await?;
+        tokio::fs::read(&path).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub async fn read_to_string(&self, path: impl AsRef<Path>) -> Result<String, SandboxError> {
+        let path = self.resolve(path).await?;
+        tokio::fs::read_to_string(&path).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub async fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), SandboxError> {
+        let path = self.resolve(path).await?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await
+                .map_err(|e| SandboxError::Io(format!("{}: {}", parent.display(), e)))?;
+        }
+        tokio::fs::write(&path, contents).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    pub async fn remove(&self, path: impl AsRef<Path>) -> Result<(), SandboxError> {
+        let path = self.resolve(path).await?;
+        tokio::fs::remove_file(&path).await.map_err(|e| SandboxError::Io(format!("{}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{ConfigManager, ServerConfig};
+    use crate::core::plugins::PluginState;
+
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rubidium-sandbox-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    fn write_plugin(root: &Path, id: &str, capabilities: &str) {
+        let dir = root.join("plugins").join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("plugin.toml"), format!(
+            "id = \"{id}\"\nname = \"{id}\"\nversion = \"1.0.0\"\nauthor = \"test\"\ndescription = \"\"\n\
+             dependencies = []\napi_version = \"1\"\n\n[capabilities]\n{capabilities}\n"
+        )).unwrap();
+    }
+
+    fn manager(root: &Path, policy: SandboxPolicy) -> Arc<PluginManager> {
+        let mut config = ServerConfig::default();
+        config.plugins.directory = root.join("plugins").to_string_lossy().into_owned();
+        config.plugins.sandbox = policy;
+        let path = root.join("rubidium.toml");
+        std::fs::write(&path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        let config = Arc::new(ConfigManager::new(path.to_str().unwrap()).unwrap());
+        Arc::new(PluginManager::new(config).with_sandbox_root(root))
+    }
+
+    #[test]
+    fn test_patterns_and_paths() {
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("*.example.com", "api.example.com"));
+        assert!(matches_pattern("*.example.com", "example.com"));
+        assert!(!matches_pattern("*.example.com", "badexample.com"));
+        assert!(matches_pattern("player_*", "player_join"));
+        assert!(!matches_pattern("player_join", "player_quit"));
+
+        let root = Path::new("/srv/rubidium");
+        assert_eq!(normalize_path(root, Path::new("plugins/./toy")), PathBuf::from("/srv/rubidium/plugins/toy"));
+        assert_eq!(normalize_path(root, Path::new("plugins/toy/../../etc")), PathBuf::from("/srv/rubidium/etc"));
+        assert_eq!(normalize_path(root, Path::new("/etc/passwd")), PathBuf::from("/etc/passwd"));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_outside_policy_fails_to_load() {
+        let root = temp_root();
+        write_plugin(&root, "greedy", "filesystem = [\"/etc\"]");
+        write_plugin(&root, "chatty", "commands = [\"raw\"]");
+        let policy = SandboxPolicy {
+            allowed_paths: vec![root.join("plugins").to_string_lossy().into_owned()],
+            allowed_commands: vec!["say".to_string(), "send_title".to_string()],
+            ..SandboxPolicy::default()
+        };
+        let manager = manager(&root, policy);
+        manager.load_all().await.unwrap();
+
+        assert_eq!(manager.get_plugin_state("greedy"), Some(PluginState::Failed));
+        let error = manager.get_plugin_error("greedy").unwrap();
+        assert!(error.contains("filesystem capability '/etc'"), "{}", error);
+        assert!(error.contains("allowed_paths"), "{}", error);
+
+        assert_eq!(manager.get_plugin_state("chatty"), Some(PluginState::Failed));
+        let error = manager.get_plugin_error("chatty").unwrap();
+        assert!(error.contains("commands capability 'raw'"), "{}", error);
+        assert!(manager.enable_plugin("chatty").await.is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_toy_plugin_reading_outside_its_path_is_disabled() {
+        let root = temp_root();
+        let data_dir = root.join("plugins/toy/data");
+        write_plugin(&root, "toy", &format!(
+            "filesystem = [\"{}\"]\nevents = [\"player_join\"]\ncommands = [\"say\"]",
+            data_dir.display()
+        ));
+        std::fs::write(root.join("secret.txt"), "hunter2").unwrap();
+
+        let manager = manager(&root, SandboxPolicy::default());
+        let event_bus = Arc::new(EventBus::new());
+        manager.set_event_bus(event_bus.clone());
+        let mut events = event_bus.subscribe();
+        manager.load_all().await.unwrap();
+        assert_eq!(manager.get_plugin_state("toy"), Some(PluginState::Enabled));
+
+        let sandbox = manager.sandbox("toy").unwrap();
+        let vfs = sandbox.vfs();
+        vfs.write(data_dir.join("state.json"), "{}").await.unwrap();
+        assert_eq!(vfs.read_to_string("plugins/toy/data/state.json").await.unwrap(), "{}");
+        assert!(sandbox.check_command(&GameCommand::Say("hi".into())).await.is_ok());
+        assert!(sandbox.subscribe(&event_bus, "player_join", |_| {}).await.is_ok());
+
+        let err = vfs.read(root.join("secret.txt")).await.unwrap_err();
+        assert!(matches!(err, SandboxError::Denied { capability: Capability::Filesystem, .. }));
+        assert!(vfs.read("plugins/toy/data/../../../secret.txt").await.is_err());
+        assert_eq!(manager.strikes("toy"), 2);
+        assert_eq!(manager.get_plugin_state("toy"), Some(PluginState::Enabled));
+
+        let err = sandbox.check_command(&GameCommand::Raw("op toy".into())).await.unwrap_err();
+        assert!(matches!(err, SandboxError::Denied { capability: Capability::Commands, .. }));
+        assert_eq!(manager.get_plugin_state("toy"), Some(PluginState::Disabled));
+        assert!(manager.get_plugin_error("toy").unwrap().contains("3 sandbox violations"));
+
+        // Once disabled even declared access is refused, without further strikes.
+        assert_eq!(vfs.read(data_dir.join("state.json")).await.unwrap_err(), SandboxError::PluginDisabled("toy".into()));
+        assert_eq!(manager.strikes("toy"), 3);
+
+        let violations = manager.violations(Some("toy"));
+        assert_eq!(violations.len(), 3);
+        assert_eq!(violations[2].capability, Capability::Commands);
+
+        let mut published = 0;
+        while let Ok(event) = events.try_recv() {
+            if let GameEvent::PluginViolation { plugin_id, .. } = event {
+                assert_eq!(plugin_id, "toy");
+                published += 1;
+            }
+        }
+        assert_eq!(published, 3);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
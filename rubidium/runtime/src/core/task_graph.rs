@@ -0,0 +1,452 @@
+use parking_lot::RwLock;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::JoinSet;
+
+type TaskFuture = Pin<Box<dyn Future<Output = Result<TaskOutput, String>> + Send>>;
+type TaskFn = Box<dyn FnOnce(TaskOutputs) -> TaskFuture + Send>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GraphError {
+    #[error("task {0} is already registered")]
+    DuplicateTask(String),
+    #[error("dependency cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("task {task} depends on unknown task {dependency}")]
+    UnknownDependency { task: String, dependency: String },
+}
+
+/// Value a task hands to the tasks that depend on it
+#[derive(Clone, Default)]
+pub struct TaskOutput(Option<Arc<dyn Any + Send + Sync>>);
+
+impl TaskOutput {
+    pub fn empty() -> Self {
+        Self(None)
+    }
+
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        Self(Some(Arc::new(value)))
+    }
+}
+
+/// Outputs of a task's parents, looked up by task name and type
+#[derive(Clone, Default)]
+pub struct TaskOutputs {
+    outputs: HashMap<String, TaskOutput>,
+}
+
+impl TaskOutputs {
+    pub fn get<T: Any + Send + Sync>(&self, task: &str) -> Option<&T> {
+        self.outputs.get(task)?.0.as_ref()?.downcast_ref()
+    }
+
+    /// Like `get`, with an error naming the missing output
+    pub fn require<T: Any + Send + Sync>(&self, task: &str) -> Result<&T, String> {
+        self.get(task).ok_or_else(|| format!("missing output of {}", task))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed(String),
+    /// Not run because an ancestor failed. `cause` runs from the task that
+    /// failed down to the parent that was skipped before this one.
+    Skipped { cause: Vec<String> },
+}
+
+impl TaskStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, TaskStatus::Pending | TaskStatus::Running)
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskStatus::Pending => write!(f, "pending"),
+            TaskStatus::Running => write!(f, "running"),
+            TaskStatus::Completed => write!(f, "completed"),
+            TaskStatus::Failed(e) => write!(f, "failed: {}", e),
+            TaskStatus::Skipped { cause } => write!(f, "skipped: {} failed", cause.join(" -> ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskNode {
+    pub name: String,
+    pub depends_on: Vec<String>,
+    /// Tasks in different lanes share no dependencies, directly or not
+    pub lane: usize,
+    pub status: TaskStatus,
+    pub duration: Option<Duration>,
+}
+
+/// Live view of a graph's tasks, updated while it runs
+#[derive(Default)]
+pub struct GraphStatus {
+    nodes: RwLock<Vec<TaskNode>>,
+}
+
+impl GraphStatus {
+    /// Tasks in registration order
+    pub fn snapshot(&self) -> Vec<TaskNode> {
+        self.nodes.read().clone()
+    }
+
+    fn set(&self, index: usize, status: TaskStatus, duration: Option<Duration>) {
+        if let Some(node) = self.nodes.write().get_mut(index) {
+            node.status = status;
+            node.duration = duration;
+        }
+    }
+}
+
+pub struct GraphReport {
+    pub tasks: Vec<TaskNode>,
+    pub outputs: TaskOutputs,
+    pub total_time: Duration,
+}
+
+impl GraphReport {
+    pub fn is_success(&self) -> bool {
+        self.tasks.iter().all(|t| t.status == TaskStatus::Completed)
+    }
+
+    pub fn first_failure(&self) -> Option<(&str, &str)> {
+        self.tasks.iter().find_map(|t| match &t.status {
+            TaskStatus::Failed(e) => Some((t.name.as_str(), e.as_str())),
+            _ => None,
+        })
+    }
+}
+
+struct Entry {
+    name: String,
+    depends_on: Vec<String>,
+    run: Option<TaskFn>,
+}
+
+/// Tasks that run once each, after every task they depend on has completed.
+/// Tasks whose dependencies are satisfied run concurrently on the tokio
+/// runtime; a failure skips everything downstream of it.
+#[derive(Default)]
+pub struct TaskGraph {
+    entries: Vec<Entry>,
+    index: HashMap<String, usize>,
+    status: Arc<GraphStatus>,
+}
+
+impl TaskGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `name` to run after `depends_on`. Dependencies may be added
+    /// later, but one that would close a cycle is rejected here.
+    pub fn add<F, Fut>(&mut self, name: impl Into<String>, depends_on: &[&str], run: F) -> Result<(), GraphError>
+    where
+        F: FnOnce(TaskOutputs) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<TaskOutput, String>> + Send + 'static,
+    {
+        let name = name.into();
+        if self.index.contains_key(&name) {
+            return Err(GraphError::DuplicateTask(name));
+        }
+        let depends_on: Vec<String> = depends_on.iter().map(|d| d.to_string()).collect();
+        if let Some(cycle) = self.find_cycle(&name, &depends_on) {
+            return Err(GraphError::Cycle(cycle));
+        }
+
+        self.index.insert(name.clone(), self.entries.len());
+        self.entries.push(Entry {
+            name,
+            depends_on,
+            run: Some(Box::new(move |inputs| Box::pin(run(inputs)))),
+        });
+        Ok(())
+    }
+
+    /// Path from `name` back to itself through already registered tasks
+    fn find_cycle(&self, name: &str, depends_on: &[String]) -> Option<Vec<String>> {
+        let mut stack: Vec<Vec<String>> = depends_on.iter()
+            .map(|d| vec![name.to_string(), d.clone()])
+            .collect();
+        let mut visited = std::collections::HashSet::new();
+        while let Some(path) = stack.pop() {
+            let last = path.last().unwrap();
+            if last == name {
+                return Some(path);
+            }
+            if !visited.insert(last.clone()) {
+                continue;
+            }
+            if let Some(&i) = self.index.get(last) {
+                for dep in &self.entries[i].depends_on {
+                    let mut next = path.clone();
+                    next.push(dep.clone());
+                    stack.push(next);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn status(&self) -> Arc<GraphStatus> {
+        self.status.clone()
+    }
+
+    /// Groups of tasks with no dependency between groups
+    pub fn lanes(&self) -> Vec<Vec<String>> {
+        let lane_of = self.lane_assignments();
+        let mut lanes: Vec<Vec<String>> = Vec::new();
+        for (entry, lane) in self.entries.iter().zip(lane_of) {
+            if lanes.len() <= lane {
+                lanes.resize(lane + 1, Vec::new());
+            }
+            lanes[lane].push(entry.name.clone());
+        }
+        lanes
+    }
+
+    fn lane_assignments(&self) -> Vec<usize> {
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut parent: Vec<usize> = (0..self.entries.len()).collect();
+        for (i, entry) in self.entries.iter().enumerate() {
+            for dep in &entry.depends_on {
+                if let Some(&j) = self.index.get(dep) {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut numbering = HashMap::new();
+        (0..self.entries.len())
+            .map(|i| {
+                let r = root(&mut parent, i);
+                let next = numbering.len();
+                *numbering.entry(r).or_insert(next)
+            })
+            .collect()
+    }
+
+    /// Run every task to completion, failure or skip
+    pub async fn run(mut self) -> Result<GraphReport, GraphError> {
+        for entry in &self.entries {
+            if let Some(dep) = entry.depends_on.iter().find(|d| !self.index.contains_key(*d)) {
+                return Err(GraphError::UnknownDependency { task: entry.name.clone(), dependency: dep.clone() });
+            }
+        }
+
+        let start = Instant::now();
+        let count = self.entries.len();
+        let lanes = self.lane_assignments();
+        *self.status.nodes.write() = self.entries.iter().zip(&lanes)
+            .map(|(entry, &lane)| TaskNode {
+                name: entry.name.clone(),
+                depends_on: entry.depends_on.clone(),
+                lane,
+                status: TaskStatus::Pending,
+                duration: None,
+            })
+            .collect();
+
+        let mut children = vec![Vec::new(); count];
+        let mut waiting = vec![0usize; count];
+        for (i, entry) in self.entries.iter().enumerate() {
+            for dep in &entry.depends_on {
+                children[self.index[dep]].push(i);
+                waiting[i] += 1;
+            }
+        }
+
+        let mut outputs: HashMap<String, TaskOutput> = HashMap::new();
+        // First failure upstream of each task, as the chain that reached it
+        let mut blocked: Vec<Option<Vec<String>>> = vec![None; count];
+        let mut ready: VecDeque<usize> = (0..count).filter(|&i| waiting[i] == 0).collect();
+        let mut running = JoinSet::new();
+        let mut task_index = HashMap::new();
+
+        loop {
+            while let Some(i) = ready.pop_front() {
+                if let Some(cause) = blocked[i].clone() {
+                    self.status.set(i, TaskStatus::Skipped { cause: cause.clone() }, None);
+                    let mut chain = cause;
+                    chain.push(self.entries[i].name.clone());
+                    self.release(i, &children, &mut waiting, &mut blocked, &mut ready, Some(chain));
+                    continue;
+                }
+
+                let inputs = TaskOutputs {
+                    outputs: self.entries[i].depends_on.iter()
+                        .filter_map(|d| Some((d.clone(), outputs.get(d)?.clone())))
+                        .collect(),
+                };
+                let run = self.entries[i].run.take().expect("task runs once");
+                self.status.set(i, TaskStatus::Running, None);
+                let handle = running.spawn(async move {
+                    let started = Instant::now();
+                    let result = run(inputs).await;
+                    (result, started.elapsed())
+                });
+                task_index.insert(handle.id(), (i, Instant::now()));
+            }
+
+            let Some(joined) = running.join_next_with_id().await else {
+                break;
+            };
+            let (i, result, duration) = match joined {
+                Ok((id, (result, duration))) => (task_index[&id].0, result, duration),
+                Err(e) => {
+                    let (i, started) = task_index[&e.id()];
+                    (i, Err(format!("task panicked: {}", e)), started.elapsed())
+                }
+            };
+
+            match result {
+                Ok(output) => {
+                    outputs.insert(self.entries[i].name.clone(), output);
+                    self.status.set(i, TaskStatus::Completed, Some(duration));
+                    self.release(i, &children, &mut waiting, &mut blocked, &mut ready, None);
+                }
+                Err(e) => {
+                    self.status.set(i, TaskStatus::Failed(e), Some(duration));
+                    let chain = vec![self.entries[i].name.clone()];
+                    self.release(i, &children, &mut waiting, &mut blocked, &mut ready, Some(chain));
+                }
+            }
+        }
+
+        Ok(GraphReport {
+            tasks: self.status.snapshot(),
+            outputs: TaskOutputs { outputs },
+            total_time: start.elapsed(),
+        })
+    }
+
+    /// Mark `i` finished for its children, passing on `failure` if it didn't complete
+    fn release(
+        &self,
+        i: usize,
+        children: &[Vec<usize>],
+        waiting: &mut [usize],
+        blocked: &mut [Option<Vec<String>>],
+        ready: &mut VecDeque<usize>,
+        failure: Option<Vec<String>>,
+    ) {
+        for &child in &children[i] {
+            if blocked[child].is_none() {
+                blocked[child] = failure.clone();
+            }
+            waiting[child] -= 1;
+            if waiting[child] == 0 {
+                ready.push_back(child);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn record(log: &Arc<Mutex<Vec<String>>>, name: &'static str) -> impl FnOnce(TaskOutputs) -> std::future::Ready<Result<TaskOutput, String>> {
+        let log = log.clone();
+        move |_| {
+            log.lock().unwrap().push(name.to_string());
+            std::future::ready(Ok(TaskOutput::empty()))
+        }
+    }
+
+    fn position(log: &[String], name: &str) -> usize {
+        log.iter().position(|n| n == name).unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_diamond_runs_parents_first_and_passes_outputs() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut graph = TaskGraph::new();
+        // Registered out of order on purpose
+        graph.add("join", &["left", "right"], |inputs: TaskOutputs| async move {
+            Ok(TaskOutput::new(inputs.require::<u32>("left")? + inputs.require::<u32>("right")?))
+        }).unwrap();
+        graph.add("left", &["root"], |inputs: TaskOutputs| async move {
+            Ok(TaskOutput::new(inputs.require::<u32>("root")? * 2))
+        }).unwrap();
+        graph.add("right", &["root"], |inputs: TaskOutputs| async move {
+            Ok(TaskOutput::new(inputs.require::<u32>("root")? * 3))
+        }).unwrap();
+        graph.add("root", &[], |_| async { Ok(TaskOutput::new(5u32)) }).unwrap();
+        graph.add("other", &[], record(&log, "other")).unwrap();
+        graph.add("after_other", &["other"], record(&log, "after_other")).unwrap();
+
+        let lanes = graph.lanes();
+        assert_eq!(lanes.len(), 2);
+        assert_eq!(lanes[0], vec!["join", "left", "right", "root"]);
+
+        let report = graph.run().await.unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.outputs.get::<u32>("join"), Some(&25));
+        assert_eq!(report.outputs.get::<String>("join"), None);
+        let log = log.lock().unwrap();
+        assert!(position(&log, "other") < position(&log, "after_other"));
+    }
+
+    #[test]
+    fn test_cycles_are_rejected_at_registration() {
+        let noop = |_| async { Ok(TaskOutput::empty()) };
+        let mut graph = TaskGraph::new();
+        graph.add("a", &["c"], noop).unwrap();
+        graph.add("b", &["a"], noop).unwrap();
+        let err = graph.add("c", &["b"], noop).unwrap_err();
+        assert_eq!(err, GraphError::Cycle(vec!["c".into(), "b".into(), "a".into(), "c".into()]));
+        assert_eq!(err.to_string(), "dependency cycle: c -> b -> a -> c");
+        assert_eq!(graph.add("d", &["d"], noop), Err(GraphError::Cycle(vec!["d".into(), "d".into()])));
+        assert_eq!(graph.add("a", &[], noop), Err(GraphError::DuplicateTask("a".into())));
+    }
+
+    #[tokio::test]
+    async fn test_failure_skips_descendants_with_cause() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut graph = TaskGraph::new();
+        graph.add("config", &[], |_| async { Err("config.yaml missing".to_string()) }).unwrap();
+        graph.add("jar", &[], record(&log, "jar")).unwrap();
+        graph.add("services", &["config"], record(&log, "services")).unwrap();
+        graph.add("server", &["services", "jar"], record(&log, "server")).unwrap();
+        let status = graph.status();
+
+        let report = graph.run().await.unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.first_failure(), Some(("config", "config.yaml missing")));
+        assert_eq!(*log.lock().unwrap(), vec!["jar"]);
+
+        let nodes = status.snapshot();
+        assert_eq!(nodes[2].status, TaskStatus::Skipped { cause: vec!["config".into()] });
+        assert_eq!(nodes[3].status, TaskStatus::Skipped { cause: vec!["config".into(), "services".into()] });
+        assert_eq!(nodes[3].status.to_string(), "skipped: config -> services failed");
+        assert!(nodes.iter().all(|n| n.status.is_finished()));
+
+        let mut graph = TaskGraph::new();
+        graph.add("a", &["missing"], |_| async { Ok(TaskOutput::empty()) }).unwrap();
+        assert!(matches!(graph.run().await, Err(GraphError::UnknownDependency { .. })));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::admin::health::ComponentHealth;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+
+/// Entities at least `distance` blocks from the nearest player. While
+/// throttled they update every 2^level ticks, but never less often than
+/// every `max_interval` ticks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistanceTier {
+    pub distance: f32,
+    pub max_interval: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThrottlePolicy {
+    /// Nearest first. Entities closer than the first tier always update every tick.
+    pub tiers: Vec<DistanceTier>,
+    pub max_level: u8,
+    /// Consecutive ticks over budget before throttling one level harder.
+    pub engage_after_ticks: u32,
+    /// Consecutive ticks under `release_below` of the budget before easing off one level.
+    pub release_after_ticks: u32,
+    pub release_below: f64,
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            tiers: vec![
+                DistanceTier { distance: 32.0, max_interval: 2 },
+                DistanceTier { distance: 64.0, max_interval: 4 },
+                DistanceTier { distance: 128.0, max_interval: 10 },
+            ],
+            max_level: 4,
+            engage_after_ticks: 20,
+            release_after_ticks: 100,
+            release_below: 0.7,
+        }
+    }
+}
+
+impl ThrottlePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tiers.windows(2).any(|pair| pair[0].distance >= pair[1].distance) {
+            return Err("Throttle tiers must be ordered by increasing distance".to_string());
+        }
+        if self.tiers.iter().any(|tier| tier.max_interval == 0) {
+            return Err("Throttle tier max_interval must be at least 1".to_string());
+        }
+        if self.engage_after_ticks == 0 || self.release_after_ticks == 0 {
+            return Err("Throttle engage/release tick counts must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.release_below) {
+            return Err("Throttle release_below must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Sets one field from its admin CLI spelling. Tiers are written as
+    /// `distance:max_interval` pairs, e.g. `32:2,64:4,128:10`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mut updated = self.clone();
+        match key {
+            "tiers" => updated.tiers = parse_tiers(value)?,
+            "max_level" => updated.max_level = parse(key, value)?,
+            "engage_after_ticks" => updated.engage_after_ticks = parse(key, value)?,
+            "release_after_ticks" => updated.release_after_ticks = parse(key, value)?,
+            "release_below" => updated.release_below = parse(key, value)?,
+            other => return Err(format!("Unknown throttle setting: {}", other)),
+        }
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Update interval in ticks for an entity `distance` blocks from the nearest player.
+    pub fn interval(&self, level: u8, distance: f32) -> u32 {
+        if level == 0 {
+            return 1;
+        }
+        match self.tiers.iter().rev().find(|tier| distance >= tier.distance) {
+            Some(tier) => (1u32 << level.min(31)).min(tier.max_interval),
+            None => 1,
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", key, value))
+}
+
+fn parse_tiers(value: &str) -> Result<Vec<DistanceTier>, String> {
+    value.split(',')
+        .map(|tier| {
+            let (distance, max_interval) = tier.split_once(':')
+                .ok_or_else(|| format!("Invalid tier '{}', expected distance:max_interval", tier))?;
+            Ok(DistanceTier {
+                distance: parse("tier distance", distance.trim())?,
+                max_interval: parse("tier max_interval", max_interval.trim())?,
+            })
+        })
+        .collect()
+}
+
+/// A throttle level change, published as `GameEvent::ThrottleChanged`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThrottleChange {
+    pub previous: u8,
+    pub level: u8,
+    pub tick_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThrottleState {
+    pub enabled: bool,
+    pub level: u8,
+    pub max_level: u8,
+    pub overrun_streak: u32,
+    pub headroom_streak: u32,
+    pub changes: u64,
+}
+
+/// Per-tick update rates for the game adapter, taken once per tick so the
+/// entity loop doesn't touch the throttler's locks.
+#[derive(Debug, Clone)]
+pub struct UpdateRates {
+    level: u8,
+    policy: ThrottlePolicy,
+}
+
+impl UpdateRates {
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn interval(&self, distance: f32) -> u32 {
+        self.policy.interval(self.level, distance)
+    }
+
+    /// Whether entity `entity_id` updates on `tick`. Entities sharing an
+    /// interval are spread across ticks by id instead of all updating together.
+    pub fn should_update(&self, tick: u64, entity_id: u64, distance: f32) -> bool {
+        let interval = self.interval(distance) as u64;
+        tick.wrapping_add(entity_id).is_multiple_of(interval)
+    }
+}
+
+#[derive(Default)]
+struct Streaks {
+    overrun: u32,
+    headroom: u32,
+}
+
+/// Slows down updates for entities far from every player while ticks keep
+/// running over budget, and restores full rates once there is headroom again.
+pub struct EntityThrottler {
+    enabled: AtomicBool,
+    level: AtomicU8,
+    changes: AtomicU64,
+    tick_budget_bits: AtomicU64,
+    policy: parking_lot::RwLock<ThrottlePolicy>,
+    streaks: parking_lot::Mutex<Streaks>,
+}
+
+impl EntityThrottler {
+    pub fn new(policy: ThrottlePolicy, tick_budget_ms: f64) -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            level: AtomicU8::new(0),
+            changes: AtomicU64::new(0),
+            tick_budget_bits: AtomicU64::new(tick_budget_ms.to_bits()),
+            policy: parking_lot::RwLock::new(policy),
+            streaks: parking_lot::Mutex::new(Streaks::default()),
+        }
+    }
+
+    pub fn policy(&self) -> ThrottlePolicy {
+        self.policy.read().clone()
+    }
+
+    /// Replaces the policy, clamping the current level to the new maximum.
+    pub fn set_policy(&self, policy: ThrottlePolicy) -> Result<(), String> {
+        policy.validate()?;
+        self.level.fetch_min(policy.max_level, Ordering::Relaxed);
+        *self.policy.write() = policy;
+        Ok(())
+    }
+
+    pub fn tick_budget_ms(&self) -> f64 {
+        f64::from_bits(self.tick_budget_bits.load(Ordering::Relaxed))
+    }
+
+    pub fn set_tick_budget_ms(&self, budget_ms: f64) {
+        self.tick_budget_bits.store(budget_ms.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Disabling drops straight back to full update rates.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.level.store(0, Ordering::Relaxed);
+            *self.streaks.lock() = Streaks::default();
+        }
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level.load(Ordering::Relaxed)
+    }
+
+    pub fn rates(&self) -> UpdateRates {
+        UpdateRates {
+            level: self.level(),
+            policy: self.policy(),
+        }
+    }
+
+    /// Feeds one finished tick into the hysteresis and returns the level
+    /// change it caused, if any.
+    pub fn observe_tick(&self, tick_ms: f64) -> Option<ThrottleChange> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let policy = self.policy.read();
+        let budget = self.tick_budget_ms();
+        let previous = self.level();
+        let mut streaks = self.streaks.lock();
+
+        let level = if tick_ms > budget {
+            streaks.headroom = 0;
+            streaks.overrun += 1;
+            if streaks.overrun < policy.engage_after_ticks || previous >= policy.max_level {
+                return None;
+            }
+            previous + 1
+        } else if tick_ms < budget * policy.release_below {
+            streaks.overrun = 0;
+            streaks.headroom += 1;
+            if streaks.headroom < policy.release_after_ticks || previous == 0 {
+                return None;
+            }
+            previous - 1
+        } else {
+            *streaks = Streaks::default();
+            return None;
+        };
+
+        *streaks = Streaks::default();
+        self.level.store(level, Ordering::Relaxed);
+        self.changes.fetch_add(1, Ordering::Relaxed);
+        Some(ThrottleChange { previous, level, tick_ms })
+    }
+
+    pub fn state(&self) -> ThrottleState {
+        let streaks = self.streaks.lock();
+        ThrottleState {
+            enabled: self.is_enabled(),
+            level: self.level(),
+            max_level: self.policy.read().max_level,
+            overrun_streak: streaks.overrun,
+            headroom_streak: streaks.headroom,
+            changes: self.changes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Degraded once throttling has run out of levels to shed load with.
+    pub fn health(&self) -> ComponentHealth {
+        let state = self.state();
+        let health = if state.enabled && state.max_level > 0 && state.level >= state.max_level {
+            ComponentHealth::degraded("throttle", format!("entity throttling at maximum level {}", state.level))
+        } else {
+            ComponentHealth::healthy("throttle")
+        };
+        health
+            .with_detail("enabled", state.enabled.to_string())
+            .with_detail("level", format!("{}/{}", state.level, state.max_level))
+    }
+}
+
+impl Default for EntityThrottler {
+    fn default() -> Self {
+        Self::new(ThrottlePolicy::default(), 50.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::HealthStatus;
+
+    const COST_PER_ENTITY_MS: f64 = 0.05;
+
+    /// Distances spread evenly from 0 to 200 blocks.
+    fn simulate_tick(throttler: &EntityThrottler, tick: u64, entities: u64) -> f64 {
+        let rates = throttler.rates();
+        let updated = (0..entities)
+            .filter(|&id| rates.should_update(tick, id, (id % 200) as f32))
+            .count();
+        updated as f64 * COST_PER_ENTITY_MS
+    }
+
+    #[test]
+    fn test_interval_by_tier_and_level() {
+        let policy = ThrottlePolicy::default();
+        assert_eq!(policy.interval(0, 500.0), 1);
+        assert_eq!(policy.interval(1, 10.0), 1);
+        assert_eq!(policy.interval(1, 40.0), 2);
+        assert_eq!(policy.interval(3, 40.0), 2);
+        assert_eq!(policy.interval(3, 64.0), 4);
+        assert_eq!(policy.interval(3, 150.0), 8);
+        assert_eq!(policy.interval(4, 150.0), 10);
+    }
+
+    #[test]
+    fn test_policy_set_validates() {
+        let mut policy = ThrottlePolicy::default();
+        policy.set("tiers", "16:2, 48:6").unwrap();
+        assert_eq!(policy.tiers, vec![
+            DistanceTier { distance: 16.0, max_interval: 2 },
+            DistanceTier { distance: 48.0, max_interval: 6 },
+        ]);
+        assert!(policy.set("tiers", "48:2,16:6").is_err());
+        assert!(policy.set("release_below", "1.5").is_err());
+        assert!(policy.set("engage_after_ticks", "x").is_err());
+        assert!(policy.set("nope", "1").is_err());
+        assert_eq!(policy.tiers.len(), 2);
+        assert_eq!(policy.release_below, 0.7);
+    }
+
+    #[test]
+    fn test_simulated_overload_recovers_and_restores_full_rates() {
+        // 2000 entities at 0.05ms each is a 100ms tick against a 50ms budget.
+        let throttler = EntityThrottler::default();
+        let mut tick = 0;
+        let mut changes = Vec::new();
+        let mut run = |throttler: &EntityThrottler, entities: u64, ticks: u64| {
+            let mut last = 0.0;
+            for _ in 0..ticks {
+                tick += 1;
+                last = simulate_tick(throttler, tick, entities);
+                changes.extend(throttler.observe_tick(last));
+            }
+            last
+        };
+
+        assert!(run(&throttler, 2000, 1) > 50.0);
+        let settled = run(&throttler, 2000, 200);
+        assert!(throttler.level() > 0);
+        assert!(settled <= 50.0, "tick still {:.1}ms at level {}", settled, throttler.level());
+        assert_eq!(throttler.health().status, HealthStatus::Healthy);
+
+        // Load drops off: full rates come back one level at a time.
+        let recovered = run(&throttler, 300, 1000);
+        assert_eq!(throttler.level(), 0);
+        assert_eq!(recovered, 300.0 * COST_PER_ENTITY_MS);
+
+        let levels: Vec<u8> = changes.iter().map(|c| c.level).collect();
+        let peak = *levels.iter().max().unwrap();
+        assert!(levels.windows(2).all(|w| w[0].abs_diff(w[1]) == 1));
+        assert_eq!(levels.last(), Some(&0));
+        assert!(peak <= throttler.policy().max_level);
+        assert_eq!(throttler.state().changes, changes.len() as u64);
+    }
+
+    #[test]
+    fn test_hysteresis_and_max_level_health() {
+        let throttler = EntityThrottler::default();
+        for _ in 0..19 {
+            assert!(throttler.observe_tick(80.0).is_none());
+        }
+        // A tick inside the band between release and budget resets the streak.
+        assert!(throttler.observe_tick(45.0).is_none());
+        for _ in 0..19 {
+            assert!(throttler.observe_tick(80.0).is_none());
+        }
+        assert_eq!(throttler.observe_tick(80.0), Some(ThrottleChange { previous: 0, level: 1, tick_ms: 80.0 }));
+
+        for _ in 0..100 {
+            throttler.observe_tick(80.0);
+        }
+        assert_eq!(throttler.level(), 4);
+        assert_eq!(throttler.health().status, HealthStatus::Degraded);
+
+        throttler.set_enabled(false);
+        assert_eq!(throttler.level(), 0);
+        assert!(throttler.observe_tick(80.0).is_none());
+    }
+}
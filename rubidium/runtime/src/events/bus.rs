@@ -1,16 +1,250 @@
 use crate::bridge::GameEvent;
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::sync::broadcast;
-use tracing::debug;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::sync::{broadcast, Notify};
+use tracing::{debug, warn};
 
 pub type EventHandler = Arc<dyn Fn(GameEvent) + Send + Sync>;
 
+/// What happens when a subscriber's queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued event to make room.
+    #[default]
+    DropOldest,
+    /// Discard the event being published.
+    DropNewest,
+    /// Stop delivering to the subscriber altogether.
+    DisconnectSubscriber,
+}
+
+impl OverflowPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverflowPolicy::DropOldest => "drop_oldest",
+            OverflowPolicy::DropNewest => "drop_newest",
+            OverflowPolicy::DisconnectSubscriber => "disconnect_subscriber",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventBusConfig {
+    /// Events buffered per subscriber before the overflow policy applies.
+    pub queue_capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for EventBusConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1024,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// Per-subscription overrides of `EventBusConfig`.
+#[derive(Debug, Clone, Default)]
+pub struct SubscribeOptions {
+    /// Shown in stats, e.g. the plugin id. Defaults to the pattern.
+    pub name: Option<String>,
+    pub queue_capacity: Option<usize>,
+    pub overflow: Option<OverflowPolicy>,
+}
+
+impl SubscribeOptions {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self { name: Some(name.into()), ..Self::default() }
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = Some(overflow);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriberStats {
+    pub id: u64,
+    pub name: String,
+    pub pattern: String,
+    pub overflow: OverflowPolicy,
+    pub capacity: usize,
+    pub queued: usize,
+    pub delivered: u64,
+    pub dropped: u64,
+    pub disconnected: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventBusStats {
+    pub published: u64,
+    pub subscribers: Vec<SubscriberStats>,
+}
+
+impl EventBusStats {
+    pub fn total_dropped(&self) -> u64 {
+        self.subscribers.iter().map(|s| s.dropped).sum()
+    }
+
+    pub fn disconnected(&self) -> impl Iterator<Item = &SubscriberStats> {
+        self.subscribers.iter().filter(|s| s.disconnected)
+    }
+}
+
+/// Whether `pattern` selects `topic`. Topics are dot-separated; `*` matches
+/// one segment, or any number of remaining segments when it comes last, so
+/// `player.*` covers `player.join` and `world.*` covers `world.chunk.load`.
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    let mut pattern_segments = pattern.split('.').peekable();
+    let mut topic_segments = topic.split('.');
+    while let Some(segment) = pattern_segments.next() {
+        let is_last = pattern_segments.peek().is_none();
+        match topic_segments.next() {
+            None => return false,
+            Some(_) if segment == "*" && is_last => return true,
+            Some(part) if segment == "*" || segment == part => {}
+            Some(_) => return false,
+        }
+    }
+    topic_segments.next().is_none()
+}
+
+/// Exact event names (`player_join`) keep working next to topic patterns.
+fn subscription_matches(pattern: &str, event: &GameEvent) -> bool {
+    pattern == event.event_name() || topic_matches(pattern, event.topic())
+}
+
+struct Subscriber {
+    id: u64,
+    name: String,
+    pattern: String,
+    handler: EventHandler,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    queue: Mutex<VecDeque<GameEvent>>,
+    notify: Notify,
+    worker_started: AtomicBool,
+    closed: AtomicBool,
+    disconnected: AtomicBool,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Subscriber {
+    /// Queues without waiting; a full queue is resolved by the overflow policy.
+    fn offer(self: &Arc<Self>, event: GameEvent) {
+        if self.closed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        {
+            let mut queue = self.queue.lock();
+            if queue.len() >= self.capacity {
+                match self.overflow {
+                    OverflowPolicy::DropOldest => {
+                        queue.pop_front();
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    OverflowPolicy::DropNewest => {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    OverflowPolicy::DisconnectSubscriber => {
+                        self.dropped.fetch_add(queue.len() as u64 + 1, Ordering::Relaxed);
+                        queue.clear();
+                        self.disconnected.store(true, Ordering::Relaxed);
+                        self.closed.store(true, Ordering::Relaxed);
+                        drop(queue);
+                        self.notify.notify_one();
+                        warn!("Event subscriber '{}' fell {} events behind and was disconnected", self.name, self.capacity);
+                        return;
+                    }
+                }
+            }
+            queue.push_back(event);
+        }
+
+        self.ensure_worker();
+        self.notify.notify_one();
+    }
+
+    /// Starts the delivery task on first use. Outside a runtime the events
+    /// stay queued until something publishes from inside one.
+    fn ensure_worker(self: &Arc<Self>) {
+        if self.worker_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(self.clone().deliver());
+            }
+            Err(_) => self.worker_started.store(false, Ordering::Release),
+        }
+    }
+
+    async fn deliver(self: Arc<Self>) {
+        let mut since_yield = 0u32;
+        loop {
+            let next = self.queue.lock().pop_front();
+            match next {
+                Some(event) => {
+                    if std::panic::catch_unwind(AssertUnwindSafe(|| (self.handler)(event))).is_err() {
+                        warn!("Event subscriber '{}' panicked while handling an event", self.name);
+                    }
+                    self.delivered.fetch_add(1, Ordering::Relaxed);
+                    since_yield += 1;
+                    if since_yield >= 64 {
+                        since_yield = 0;
+                        tokio::task::yield_now().await;
+                    }
+                }
+                None if self.closed.load(Ordering::Relaxed) => break,
+                None => self.notify.notified().await,
+            }
+        }
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.queue.lock().clear();
+        self.notify.notify_one();
+    }
+
+    fn stats(&self) -> SubscriberStats {
+        SubscriberStats {
+            id: self.id,
+            name: self.name.clone(),
+            pattern: self.pattern.clone(),
+            overflow: self.overflow,
+            capacity: self.capacity,
+            queued: self.queue.lock().len(),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            disconnected: self.disconnected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Each subscriber gets its own bounded queue and delivery task, so
+/// publishing never waits on a handler and a slow handler only loses its
+/// own events.
 pub struct EventBus {
     sender: broadcast::Sender<GameEvent>,
-    handlers: RwLock<HashMap<String, Vec<(u64, EventHandler)>>>,
+    subscribers: RwLock<Vec<Arc<Subscriber>>>,
+    config: RwLock<EventBusConfig>,
     handler_counter: AtomicU64,
     event_count: AtomicU64,
 }
@@ -21,42 +255,47 @@ impl EventBus {
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_config(capacity, EventBusConfig::default())
+    }
+
+    /// `capacity` sizes the broadcast channel behind `subscribe`; `config`
+    /// applies to handlers registered with `on`.
+    pub fn with_config(capacity: usize, config: EventBusConfig) -> Self {
         let (sender, _) = broadcast::channel(capacity);
         Self {
             sender,
-            handlers: RwLock::new(HashMap::new()),
+            subscribers: RwLock::new(Vec::new()),
+            config: RwLock::new(config),
             handler_counter: AtomicU64::new(0),
             event_count: AtomicU64::new(0),
         }
     }
 
+    /// Applies to subscriptions made after the call.
+    pub fn set_config(&self, config: EventBusConfig) {
+        *self.config.write() = config;
+    }
+
+    pub fn config(&self) -> EventBusConfig {
+        self.config.read().clone()
+    }
+
     pub async fn emit(&self, event: GameEvent) {
+        self.publish(event);
+    }
+
+    /// Hands the event to every matching subscriber's queue and returns
+    /// without running any handler.
+    pub fn publish(&self, event: GameEvent) {
         self.event_count.fetch_add(1, Ordering::Relaxed);
-        
-        let event_name = event.event_name();
-        debug!("Event emitted: {}", event_name);
-        
-        let handlers = self.handlers.read();
-        if let Some(handler_list) = handlers.get(event_name) {
-            for (_, handler) in handler_list {
-                let event_clone = event.clone();
-                let handler_clone = handler.clone();
-                tokio::spawn(async move {
-                    handler_clone(event_clone);
-                });
-            }
-        }
-        
-        if let Some(wildcard_handlers) = handlers.get("*") {
-            for (_, handler) in wildcard_handlers {
-                let event_clone = event.clone();
-                let handler_clone = handler.clone();
-                tokio::spawn(async move {
-                    handler_clone(event_clone);
-                });
+        debug!("Event emitted: {}", event.event_name());
+
+        for subscriber in self.subscribers.read().iter() {
+            if subscription_matches(&subscriber.pattern, &event) {
+                subscriber.offer(event.clone());
             }
         }
-        
+
         let _ = self.sender.send(event);
     }
 
@@ -64,18 +303,39 @@ impl EventBus {
         self.sender.subscribe()
     }
 
-    pub fn on<F>(&self, event_name: &str, handler: F) -> u64
+    /// Runs `handler` for events matching `pattern`: an event name like
+    /// `player_join`, a topic like `player.join`, or a wildcard like
+    /// `world.chunk.*` or `*`.
+    pub fn on<F>(&self, pattern: &str, handler: F) -> u64
+    where
+        F: Fn(GameEvent) + Send + Sync + 'static,
+    {
+        self.on_with(pattern, SubscribeOptions::default(), handler)
+    }
+
+    pub fn on_with<F>(&self, pattern: &str, options: SubscribeOptions, handler: F) -> u64
     where
         F: Fn(GameEvent) + Send + Sync + 'static,
     {
         let id = self.handler_counter.fetch_add(1, Ordering::Relaxed);
-        let handler_arc: EventHandler = Arc::new(handler);
-        
-        let mut handlers = self.handlers.write();
-        handlers.entry(event_name.to_string())
-            .or_insert_with(Vec::new)
-            .push((id, handler_arc));
-        
+        let defaults = self.config();
+        let subscriber = Arc::new(Subscriber {
+            id,
+            name: options.name.unwrap_or_else(|| pattern.to_string()),
+            pattern: pattern.to_string(),
+            handler: Arc::new(handler),
+            capacity: options.queue_capacity.unwrap_or(defaults.queue_capacity).max(1),
+            overflow: options.overflow.unwrap_or(defaults.overflow),
+            queue: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            worker_started: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            disconnected: AtomicBool::new(false),
+            delivered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+
+        self.subscribers.write().push(subscriber);
         id
     }
 
@@ -87,33 +347,48 @@ impl EventBus {
     }
 
     pub fn off(&self, handler_id: u64) -> bool {
-        let mut handlers = self.handlers.write();
-        for (_, handler_list) in handlers.iter_mut() {
-            if let Some(pos) = handler_list.iter().position(|(id, _)| *id == handler_id) {
-                handler_list.remove(pos);
-                return true;
+        let mut subscribers = self.subscribers.write();
+        match subscribers.iter().position(|s| s.id == handler_id) {
+            Some(pos) => {
+                subscribers.remove(pos).close();
+                true
             }
+            None => false,
         }
-        false
     }
 
-    pub fn off_all(&self, event_name: &str) {
-        let mut handlers = self.handlers.write();
-        handlers.remove(event_name);
+    pub fn off_all(&self, pattern: &str) {
+        self.subscribers.write().retain(|s| {
+            let keep = s.pattern != pattern;
+            if !keep {
+                s.close();
+            }
+            keep
+        });
     }
 
     pub fn clear(&self) {
-        self.handlers.write().clear();
+        for subscriber in self.subscribers.write().drain(..) {
+            subscriber.close();
+        }
     }
 
     pub fn event_count(&self) -> u64 {
         self.event_count.load(Ordering::Relaxed)
     }
 
+    /// Subscribers still receiving events.
     pub fn handler_count(&self) -> usize {
-        self.handlers.read().values()
-            .map(|v| v.len())
-            .sum()
+        self.subscribers.read().iter()
+            .filter(|s| !s.disconnected.load(Ordering::Relaxed))
+            .count()
+    }
+
+    pub fn stats(&self) -> EventBusStats {
+        EventBusStats {
+            published: self.event_count(),
+            subscribers: self.subscribers.read().iter().map(|s| s.stats()).collect(),
+        }
     }
 }
 
@@ -122,3 +397,125 @@ impl Default for EventBus {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn chunk_load(x: i32) -> GameEvent {
+        GameEvent::ChunkLoad { world: "overworld".to_string(), x, z: 0 }
+    }
+
+    /// Polls on the paused clock, so every sleep just lets the delivery
+    /// tasks run until they're idle.
+    async fn wait_for(mut done: impl FnMut() -> bool) {
+        for _ in 0..1000 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("timed out waiting for delivery");
+    }
+
+    #[test]
+    fn test_topic_patterns() {
+        assert!(topic_matches("player.join", "player.join"));
+        assert!(topic_matches("player.*", "player.join"));
+        assert!(topic_matches("world.*", "world.chunk.load"));
+        assert!(topic_matches("world.chunk.*", "world.chunk.unload"));
+        assert!(topic_matches("world.*.load", "world.chunk.load"));
+        assert!(topic_matches("*", "tick.complete"));
+        assert!(!topic_matches("player.*", "player"));
+        assert!(!topic_matches("player.*", "world.load"));
+        assert!(!topic_matches("world.*.load", "world.chunk.unload"));
+        assert!(!topic_matches("player.join", "player.join.late"));
+
+        assert!(subscription_matches("chunk_load", &chunk_load(0)));
+        assert!(subscription_matches("world.chunk.*", &chunk_load(0)));
+        assert!(!subscription_matches("player.*", &chunk_load(0)));
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        let bus = EventBus::new();
+        bus.on_with("chunk_load", SubscribeOptions::named("oldest").with_capacity(2), |_| {});
+        bus.on_with("chunk_load", SubscribeOptions::named("newest").with_capacity(2).with_overflow(OverflowPolicy::DropNewest), |_| {});
+        bus.on_with(
+            "chunk_load",
+            SubscribeOptions::named("disconnect").with_capacity(2).with_overflow(OverflowPolicy::DisconnectSubscriber),
+            |_| {},
+        );
+
+        // Publishing outside the runtime queues without starting delivery, so every queue fills
+        for x in 0..5 {
+            bus.publish(chunk_load(x));
+        }
+
+        let stats = bus.stats();
+        let by_name = |name: &str| stats.subscribers.iter().find(|s| s.name == name).unwrap().clone();
+        assert_eq!((by_name("oldest").queued, by_name("oldest").dropped), (2, 3));
+        assert_eq!((by_name("newest").queued, by_name("newest").dropped), (2, 3));
+        assert!(by_name("disconnect").disconnected);
+        assert_eq!(by_name("disconnect").dropped, 3);
+        assert_eq!(bus.handler_count(), 2);
+        assert_eq!(stats.published, 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_subscriber_does_not_hold_back_publisher() {
+        const EVENTS: u64 = 100_000;
+        const SLOW_CAPACITY: u64 = 256;
+        let bus = Arc::new(EventBus::with_config(16, EventBusConfig { queue_capacity: SLOW_CAPACITY as usize, overflow: OverflowPolicy::DropOldest }));
+
+        let fast_seen = Arc::new(AtomicU64::new(0));
+        let counter = fast_seen.clone();
+        bus.on_with("world.chunk.*", SubscribeOptions::named("fast").with_capacity(EVENTS as usize), move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let slow_seen = Arc::new(Mutex::new(Vec::new()));
+        let seen = slow_seen.clone();
+        bus.on_with("chunk_load", SubscribeOptions::named("slow"), move |event| {
+            if let GameEvent::ChunkLoad { x, .. } = event {
+                seen.lock().push(x);
+            }
+        });
+
+        // Nothing yields to the delivery tasks until publishing is done, so
+        // a publisher that waited on a handler would never get there.
+        for x in 0..EVENTS as i32 {
+            bus.publish(chunk_load(x));
+        }
+        assert_eq!(fast_seen.load(Ordering::Relaxed), 0);
+        let stats = bus.stats();
+        let slow = stats.subscribers.iter().find(|s| s.name == "slow").unwrap();
+        assert_eq!((slow.queued as u64, slow.dropped), (SLOW_CAPACITY, EVENTS - SLOW_CAPACITY));
+
+        wait_for(|| fast_seen.load(Ordering::Relaxed) == EVENTS && slow_seen.lock().len() as u64 == SLOW_CAPACITY).await;
+        let stats = bus.stats();
+        let fast = stats.subscribers.iter().find(|s| s.name == "fast").unwrap();
+        assert_eq!(fast.dropped, 0);
+        // DropOldest keeps the newest events, in order
+        let expected: Vec<i32> = ((EVENTS - SLOW_CAPACITY) as i32..EVENTS as i32).collect();
+        assert_eq!(*slow_seen.lock(), expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_off_stops_delivery() {
+        let bus = EventBus::new();
+        let seen = Arc::new(AtomicU64::new(0));
+        let counter = seen.clone();
+        let id = bus.on_all(move |_| { counter.fetch_add(1, Ordering::Relaxed); });
+
+        bus.emit(chunk_load(0)).await;
+        wait_for(|| seen.load(Ordering::Relaxed) == 1).await;
+        assert!(bus.off(id));
+        bus.emit(chunk_load(1)).await;
+        // With the clock paused this returns once every task is idle
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(seen.load(Ordering::Relaxed), 1);
+        assert_eq!(bus.handler_count(), 0);
+        assert!(bus.stats().subscribers.is_empty());
+    }
+}
@@ -41,6 +41,19 @@ pub fn log_performance_events(event: GameEvent) {
                 debug!("[Performance] Tick {} took {:.1}ms (lag)", tick, duration_ms);
             }
         }
+        GameEvent::PerformanceAlert { tick, duration_ms, threshold_ms, breakdown } => {
+            tracing::warn!("[Performance] Slow tick {} took {:.1}ms (threshold {:.1}ms, {} tasks)",
+                           tick, duration_ms, threshold_ms, breakdown.tasks.len());
+        }
+        GameEvent::ThrottleChanged { previous, level, .. } => {
+            debug!("[Performance] Entity throttle level {} -> {}", previous, level);
+        }
+        GameEvent::SlowTick { tick, duration_ms, scopes, .. } => {
+            if let Some(slowest) = scopes.first() {
+                debug!("[Performance] Tick {} took {:.1}ms, {:.1}ms in {}",
+                       tick, duration_ms, slowest.duration_ms, slowest.name);
+            }
+        }
         _ => {}
     }
 }
@@ -1,4 +1,4 @@
 pub mod bus;
 pub mod handlers;
 
-pub use bus::EventBus;
+pub use bus::{EventBus, EventBusConfig, EventBusStats, OverflowPolicy, SubscribeOptions, SubscriberStats};
@@ -137,3 +137,27 @@ impl CameraPath {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared with the API server's camera path export so the two can't drift.
+    const EXPORT_FIXTURE: &str = include_str!("../../../tests/fixtures/camera_path_export.json");
+
+    #[test]
+    fn test_server_export_deserializes() {
+        let path: CameraPath = serde_json::from_str(EXPORT_FIXTURE).unwrap();
+
+        assert_eq!(path.name, "Base Tour");
+        assert_eq!(path.keyframes.len(), 2);
+        assert_eq!(path.keyframes[1].interpolation, InterpolationType::Linear);
+        assert_eq!(path.duration_ms, 2500);
+
+        let midpoint = path.get_position_at(1250).unwrap();
+        assert!(midpoint.x > 10.0 && midpoint.x < 20.0);
+
+        let reserialized: serde_json::Value = serde_json::to_value(&path).unwrap();
+        assert_eq!(reserialized, serde_json::from_str::<serde_json::Value>(EXPORT_FIXTURE).unwrap());
+    }
+}
@@ -14,7 +14,7 @@ pub use adaptive_scheduler::AdaptiveScheduler;
 pub use world_heatmap::WorldHeatmap;
 pub use session_manager::SessionManager;
 
-pub use replay::{ReplayCapture, ReplayStorage, ReplayPlayer, ReplayCamera, ReplayConfig, CaptureFrame, PlaybackState, PlaybackSpeed, CameraMode};
+pub use replay::{ReplayCapture, ReplayStorage, ReplayPlayer, ReplayCamera, ReplayConfig, CaptureFrame, PlaybackState, PlaybackSpeed, CameraMode, CameraInterpolation};
 pub use mapping::{MappingConfig, MapMode, MinimapService, WorldMapService, MapMarker, MarkerType, MarkerRegistry, MappingCoordinator, MapData};
 pub use waypoints::{WaypointConfig, WaypointService, Waypoint, WaypointVisibility, WaypointIcon};
 pub use toggles::{FeatureToggleRegistry, FeatureToggle, FeatureStatus, ToggleConfig};
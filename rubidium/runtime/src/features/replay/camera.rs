@@ -141,6 +141,7 @@ pub struct ReplayCamera {
     orbit_pitch: f32,
     spline: Option<CameraSpline>,
     smooth_factor: f64,
+    driven: Option<CameraPosition>,
 }
 
 impl ReplayCamera {
@@ -154,6 +155,7 @@ impl ReplayCamera {
             orbit_pitch: 30.0,
             spline: None,
             smooth_factor: 0.1,
+            driven: None,
         }
     }
 
@@ -165,8 +167,18 @@ impl ReplayCamera {
         self.mode = mode;
     }
 
+    /// The transform to render: an attached replay camera path while it is
+    /// driving the camera, otherwise whatever the current mode produced.
     pub fn position(&self) -> &CameraPosition {
-        &self.position
+        self.driven.as_ref().unwrap_or(&self.position)
+    }
+
+    pub fn drive(&mut self, position: Option<CameraPosition>) {
+        self.driven = position;
+    }
+
+    pub fn is_driven(&self) -> bool {
+        self.driven.is_some()
     }
 
     pub fn follow_entity(&mut self, entity_id: Uuid) {
@@ -0,0 +1,246 @@
+use super::camera::CameraPosition;
+use crate::features::cinema::{CameraPath, PathKeyframe};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Replays are captured at 20 ticks per second.
+pub const TICK_MS: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraInterpolation {
+    Linear,
+    CatmullRom,
+}
+
+/// A cinematic path bound to a replay's timeline. Path time 0 lines up with
+/// the first captured frame, scaled by the path's `time_scale`.
+#[derive(Debug, Clone)]
+pub struct CameraBinding {
+    pub path: CameraPath,
+    pub interpolation: CameraInterpolation,
+}
+
+impl CameraBinding {
+    pub fn new(path: CameraPath, interpolation: CameraInterpolation) -> Self {
+        Self { path, interpolation }
+    }
+
+    /// Camera transform at `replay_ms`, or `None` once a non-looping path has
+    /// run out so the caller can fall back to its own camera mode. This is a
+    /// pure function of its input, so seeking to the same time always yields
+    /// the same shot.
+    pub fn evaluate(&self, replay_ms: f64) -> Option<CameraPosition> {
+        let keyframes = &self.path.keyframes;
+        if keyframes.is_empty() {
+            return None;
+        }
+
+        let duration = self.path.duration_ms as f64;
+        let mut time = replay_ms.max(0.0) * self.path.time_scale as f64;
+        if self.path.loop_enabled && duration > 0.0 {
+            time %= duration;
+        } else if time > duration {
+            return None;
+        }
+
+        let next = keyframes.iter()
+            .position(|k| k.time_ms as f64 > time)
+            .unwrap_or(keyframes.len());
+        if next == 0 {
+            return Some(position_of(&keyframes[0]));
+        }
+        if next == keyframes.len() {
+            return Some(position_of(&keyframes[next - 1]));
+        }
+
+        let from = &keyframes[next - 1];
+        let to = &keyframes[next];
+        let t = (time - from.time_ms as f64) / (to.time_ms - from.time_ms) as f64;
+
+        Some(match self.interpolation {
+            CameraInterpolation::Linear => blend(from, to, |a, b| a + (b - a) * t),
+            CameraInterpolation::CatmullRom => {
+                // Endpoints are repeated so the curve still passes through the
+                // first and last keyframes.
+                let before = &keyframes[next.saturating_sub(2)];
+                let after = keyframes.get(next + 1).unwrap_or(to);
+                CameraPosition {
+                    x: catmull_rom(before.x, from.x, to.x, after.x, t),
+                    y: catmull_rom(before.y, from.y, to.y, after.y, t),
+                    z: catmull_rom(before.z, from.z, to.z, after.z, t),
+                    yaw: catmull_rom(before.yaw as f64, from.yaw as f64, to.yaw as f64, after.yaw as f64, t) as f32,
+                    pitch: catmull_rom(before.pitch as f64, from.pitch as f64, to.pitch as f64, after.pitch as f64, t) as f32,
+                    roll: catmull_rom(before.roll as f64, from.roll as f64, to.roll as f64, after.roll as f64, t) as f32,
+                    fov: catmull_rom(before.fov as f64, from.fov as f64, to.fov as f64, after.fov as f64, t) as f32,
+                }
+            }
+        })
+    }
+}
+
+fn position_of(keyframe: &PathKeyframe) -> CameraPosition {
+    CameraPosition {
+        x: keyframe.x,
+        y: keyframe.y,
+        z: keyframe.z,
+        yaw: keyframe.yaw,
+        pitch: keyframe.pitch,
+        roll: keyframe.roll,
+        fov: keyframe.fov,
+    }
+}
+
+fn blend(from: &PathKeyframe, to: &PathKeyframe, f: impl Fn(f64, f64) -> f64) -> CameraPosition {
+    CameraPosition {
+        x: f(from.x, to.x),
+        y: f(from.y, to.y),
+        z: f(from.z, to.z),
+        yaw: f(from.yaw as f64, to.yaw as f64) as f32,
+        pitch: f(from.pitch as f64, to.pitch as f64) as f32,
+        roll: f(from.roll as f64, to.roll as f64) as f32,
+        fov: f(from.fov as f64, to.fov as f64) as f32,
+    }
+}
+
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedCameraFrame {
+    pub frame: usize,
+    pub tick: u64,
+    pub position: CameraPosition,
+}
+
+/// Camera transforms evaluated once per captured frame and stored with the
+/// replay, so viewers that don't have the original path see the same shot.
+/// Frames past the end of the path are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedCameraTrack {
+    pub path_id: Uuid,
+    pub path_name: String,
+    pub interpolation: CameraInterpolation,
+    pub frames: Vec<BakedCameraFrame>,
+}
+
+impl BakedCameraTrack {
+    pub fn bake(binding: &CameraBinding, ticks: &[u64]) -> Self {
+        let origin = ticks.first().copied().unwrap_or(0);
+        let frames = ticks.iter()
+            .enumerate()
+            .filter_map(|(frame, &tick)| {
+                let replay_ms = (tick - origin) as f64 * TICK_MS;
+                binding.evaluate(replay_ms).map(|position| BakedCameraFrame { frame, tick, position })
+            })
+            .collect();
+
+        Self {
+            path_id: binding.path.id,
+            path_name: binding.path.name.clone(),
+            interpolation: binding.interpolation,
+            frames,
+        }
+    }
+
+    /// Transform at a fractional frame cursor; between two baked frames the
+    /// transform is blended linearly.
+    pub fn position_at(&self, cursor: f64) -> Option<CameraPosition> {
+        let frame = cursor.floor() as usize;
+        let idx = self.frames.binary_search_by_key(&frame, |f| f.frame).ok()?;
+        let current = &self.frames[idx].position;
+        let fraction = cursor - frame as f64;
+
+        match self.frames.get(idx + 1) {
+            Some(next) if fraction > 0.0 && next.frame == frame + 1 => {
+                let next = &next.position;
+                let lerp = |a: f64, b: f64| a + (b - a) * fraction;
+                Some(CameraPosition {
+                    x: lerp(current.x, next.x),
+                    y: lerp(current.y, next.y),
+                    z: lerp(current.z, next.z),
+                    yaw: lerp(current.yaw as f64, next.yaw as f64) as f32,
+                    pitch: lerp(current.pitch as f64, next.pitch as f64) as f32,
+                    roll: lerp(current.roll as f64, next.roll as f64) as f32,
+                    fov: lerp(current.fov as f64, next.fov as f64) as f32,
+                })
+            }
+            _ => Some(current.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(points: &[(u64, f64)]) -> CameraPath {
+        let mut path = CameraPath::new(Uuid::nil(), "test".to_string());
+        for &(time_ms, x) in points {
+            path.add_keyframe(PathKeyframe::new(time_ms, x, 64.0, 0.0, x as f32, 0.0));
+        }
+        path
+    }
+
+    fn x_at(binding: &CameraBinding, ms: f64) -> f64 {
+        binding.evaluate(ms).unwrap().x
+    }
+
+    #[test]
+    fn test_keyframe_boundaries_are_exact() {
+        let points = [(0, 0.0), (1000, 10.0), (2000, 30.0), (3000, 20.0)];
+        for interpolation in [CameraInterpolation::Linear, CameraInterpolation::CatmullRom] {
+            let binding = CameraBinding::new(path(&points), interpolation);
+            for &(time_ms, x) in &points {
+                assert_eq!(x_at(&binding, time_ms as f64), x, "{:?} at {}ms", interpolation, time_ms);
+                assert_eq!(binding.evaluate(time_ms as f64).unwrap().yaw, x as f32);
+            }
+            // Approaching a boundary from below converges on the keyframe
+            assert!((x_at(&binding, 999.999) - 10.0).abs() < 1e-3);
+            assert!((x_at(&binding, 2999.999) - 20.0).abs() < 1e-3);
+            assert!(binding.evaluate(3000.001).is_none());
+        }
+    }
+
+    #[test]
+    fn test_interpolated_values() {
+        let points = [(0, 0.0), (1000, 10.0), (2000, 30.0), (3000, 20.0)];
+        let linear = CameraBinding::new(path(&points), CameraInterpolation::Linear);
+        assert_eq!(x_at(&linear, 500.0), 5.0);
+        assert_eq!(x_at(&linear, 1250.0), 15.0);
+        assert_eq!(x_at(&linear, 2500.0), 25.0);
+
+        // Segment 1..2 with neighbours 0 and 20: 0.5 * (20 + 30*0.5 + 50*0.25 - 40*0.125)
+        let catmull = CameraBinding::new(path(&points), CameraInterpolation::CatmullRom);
+        assert!((x_at(&catmull, 1500.0) - 21.25).abs() < 1e-9);
+        // First segment repeats the first keyframe: 0.5 * (0 + 10*0.5 + 10*0.25 + 0*0.125)
+        assert!((x_at(&catmull, 500.0) - 3.75).abs() < 1e-9);
+
+        // Evenly spaced collinear keyframes make catmull-rom a straight line
+        let straight = CameraBinding::new(path(&[(0, 0.0), (100, 1.0), (200, 2.0), (300, 3.0)]), CameraInterpolation::CatmullRom);
+        assert!((x_at(&straight, 150.0) - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_time_scale_loop_and_bake() {
+        let mut looping = path(&[(0, 0.0), (1000, 10.0)]);
+        looping.loop_enabled = true;
+        looping.time_scale = 2.0;
+        let binding = CameraBinding::new(looping, CameraInterpolation::Linear);
+        assert_eq!(x_at(&binding, 250.0), 5.0);
+        assert_eq!(x_at(&binding, 750.0), 5.0);
+
+        let binding = CameraBinding::new(path(&[(0, 0.0), (100, 10.0)]), CameraInterpolation::Linear);
+        let track = BakedCameraTrack::bake(&binding, &[40, 41, 42, 43]);
+        assert_eq!(track.frames.len(), 3);
+        assert_eq!(track.frames[1].position.x, 5.0);
+        assert_eq!(track.position_at(0.5).unwrap().x, 2.5);
+        assert_eq!(track.position_at(2.0).unwrap().x, 10.0);
+        assert!(track.position_at(3.0).is_none());
+    }
+}
@@ -2,10 +2,12 @@ pub mod capture;
 pub mod storage;
 pub mod playback;
 pub mod camera;
+pub mod camera_track;
 pub mod config;
 
 pub use capture::{ReplayCapture, CaptureFrame, CaptureConfig};
 pub use storage::{ReplayStorage, ReplaySegment, ReplayManifest};
 pub use playback::{ReplayPlayer, PlaybackState, PlaybackSpeed};
-pub use camera::{ReplayCamera, CameraMode, CameraSpline};
+pub use camera::{ReplayCamera, CameraMode, CameraSpline, CameraPosition};
+pub use camera_track::{CameraBinding, CameraInterpolation, BakedCameraTrack};
 pub use config::ReplayConfig;
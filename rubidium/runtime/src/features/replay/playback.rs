@@ -1,6 +1,8 @@
 use super::capture::CaptureFrame;
 use super::storage::{ReplayStorage, ReplayManifest};
-use super::camera::{ReplayCamera, CameraMode};
+use super::camera::{ReplayCamera, CameraMode, CameraPosition};
+use super::camera_track::{BakedCameraTrack, CameraBinding, CameraInterpolation, TICK_MS};
+use crate::features::cinema::CameraPath;
 use parking_lot::RwLock;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
@@ -42,12 +44,16 @@ pub struct ReplayPlayer {
     storage: Arc<ReplayStorage>,
     current_replay: RwLock<Option<ActivePlayback>>,
     camera: RwLock<ReplayCamera>,
+    binding: RwLock<Option<CameraBinding>>,
 }
 
 struct ActivePlayback {
     manifest: ReplayManifest,
     frames: Vec<CaptureFrame>,
     current_frame: usize,
+    /// Fractional frame position, so slow speeds still make progress.
+    cursor: f64,
+    reverse: bool,
     state: PlaybackState,
     speed: PlaybackSpeed,
     loop_enabled: bool,
@@ -61,6 +67,7 @@ impl ReplayPlayer {
             storage,
             current_replay: RwLock::new(None),
             camera: RwLock::new(ReplayCamera::new()),
+            binding: RwLock::new(None),
         }
     }
 
@@ -75,6 +82,8 @@ impl ReplayPlayer {
             manifest: manifest.clone(),
             frames,
             current_frame: 0,
+            cursor: 0.0,
+            reverse: false,
             state: PlaybackState::Stopped,
             speed: PlaybackSpeed::Normal,
             loop_enabled: false,
@@ -82,12 +91,14 @@ impl ReplayPlayer {
             end_frame: frame_count.saturating_sub(1),
         };
 
+        self.sync_camera(&playback);
         *self.current_replay.write() = Some(playback);
         Ok(manifest)
     }
 
     pub fn unload(&self) {
         *self.current_replay.write() = None;
+        self.camera.write().drive(None);
     }
 
     pub fn play(&self) -> Result<(), String> {
@@ -108,7 +119,8 @@ impl ReplayPlayer {
         let mut replay = self.current_replay.write();
         let playback = replay.as_mut().ok_or("No replay loaded")?;
         playback.state = PlaybackState::Stopped;
-        playback.current_frame = playback.start_frame;
+        playback.set_frame(playback.start_frame);
+        self.sync_camera(playback);
         Ok(())
     }
 
@@ -120,7 +132,8 @@ impl ReplayPlayer {
             return Err("Frame out of range".to_string());
         }
         
-        playback.current_frame = frame;
+        playback.set_frame(frame);
+        self.sync_camera(playback);
         Ok(())
     }
 
@@ -154,6 +167,14 @@ impl ReplayPlayer {
         Ok(())
     }
 
+    /// Plays backwards from the current frame towards the trim start.
+    pub fn set_reverse(&self, reverse: bool) -> Result<(), String> {
+        let mut replay = self.current_replay.write();
+        let playback = replay.as_mut().ok_or("No replay loaded")?;
+        playback.reverse = reverse;
+        Ok(())
+    }
+
     pub fn set_loop(&self, enabled: bool) -> Result<(), String> {
         let mut replay = self.current_replay.write();
         let playback = replay.as_mut().ok_or("No replay loaded")?;
@@ -175,11 +196,12 @@ impl ReplayPlayer {
         playback.start_frame = start_frame;
         playback.end_frame = end_frame;
         if playback.current_frame < start_frame {
-            playback.current_frame = start_frame;
+            playback.set_frame(start_frame);
         }
         if playback.current_frame > end_frame {
-            playback.current_frame = end_frame;
+            playback.set_frame(end_frame);
         }
+        self.sync_camera(playback);
         Ok(())
     }
 
@@ -187,6 +209,7 @@ impl ReplayPlayer {
         let mut replay = self.current_replay.write();
         let playback = replay.as_mut()?;
 
+        self.sync_camera(playback);
         if playback.state != PlaybackState::Playing {
             return playback.frames.get(playback.current_frame).cloned();
         }
@@ -194,12 +217,19 @@ impl ReplayPlayer {
         let frame = playback.frames.get(playback.current_frame)?.clone();
         
         let advance = playback.speed.multiplier();
-        playback.current_frame = ((playback.current_frame as f64 + advance) as usize)
-            .min(playback.end_frame);
+        let (start, end) = (playback.start_frame as f64, playback.end_frame as f64);
+        if playback.reverse {
+            playback.cursor = (playback.cursor - advance).max(start);
+        } else {
+            playback.cursor = (playback.cursor + advance).min(end);
+        }
+        playback.current_frame = playback.cursor as usize;
 
-        if playback.current_frame >= playback.end_frame {
+        let boundary = if playback.reverse { playback.start_frame } else { playback.end_frame };
+        if playback.current_frame == boundary {
             if playback.loop_enabled {
-                playback.current_frame = playback.start_frame;
+                let restart = if playback.reverse { playback.end_frame } else { playback.start_frame };
+                playback.set_frame(restart);
             } else {
                 playback.state = PlaybackState::Finished;
             }
@@ -246,4 +276,180 @@ impl ReplayPlayer {
     pub fn set_camera_mode(&self, mode: CameraMode) {
         self.camera.write().set_mode(mode);
     }
+
+    /// Drives the camera along `path` during playback. Once the path runs out
+    /// the camera falls back to its current `CameraMode`.
+    pub fn attach_camera(&self, path: CameraPath) {
+        *self.binding.write() = Some(CameraBinding::new(path, CameraInterpolation::CatmullRom));
+        self.resync_camera();
+    }
+
+    pub fn set_camera_interpolation(&self, interpolation: CameraInterpolation) -> Result<(), String> {
+        self.binding.write().as_mut()
+            .ok_or("No camera path attached")?
+            .interpolation = interpolation;
+        self.resync_camera();
+        Ok(())
+    }
+
+    pub fn detach_camera(&self) -> Option<CameraPath> {
+        let path = self.binding.write().take().map(|b| b.path);
+        self.resync_camera();
+        path
+    }
+
+    /// Evaluates the attached path at every frame and stores the result in the
+    /// replay's manifest.
+    pub fn bake_camera(&self) -> Result<BakedCameraTrack, String> {
+        let mut replay = self.current_replay.write();
+        let playback = replay.as_mut().ok_or("No replay loaded")?;
+        let track = {
+            let binding = self.binding.read();
+            let binding = binding.as_ref().ok_or("No camera path attached")?;
+            let ticks: Vec<u64> = playback.frames.iter().map(|f| f.tick).collect();
+            BakedCameraTrack::bake(binding, &ticks)
+        };
+
+        playback.manifest = self.storage.set_camera_track(playback.manifest.id, Some(track.clone()))?;
+        Ok(track)
+    }
+
+    /// Camera transform at the current playback position, if a path or baked
+    /// track covers it.
+    pub fn camera_override(&self) -> Option<CameraPosition> {
+        let replay = self.current_replay.read();
+        self.evaluate_camera(replay.as_ref()?)
+    }
+
+    fn resync_camera(&self) {
+        match self.current_replay.read().as_ref() {
+            Some(playback) => self.sync_camera(playback),
+            None => self.camera.write().drive(None),
+        }
+    }
+
+    fn sync_camera(&self, playback: &ActivePlayback) {
+        let position = self.evaluate_camera(playback);
+        self.camera.write().drive(position);
+    }
+
+    fn evaluate_camera(&self, playback: &ActivePlayback) -> Option<CameraPosition> {
+        if let Some(binding) = self.binding.read().as_ref() {
+            return binding.evaluate(playback.timeline_ms());
+        }
+        playback.manifest.camera_track.as_ref()?.position_at(playback.cursor)
+    }
+}
+
+impl ActivePlayback {
+    fn set_frame(&mut self, frame: usize) {
+        self.current_frame = frame;
+        self.cursor = frame as f64;
+    }
+
+    /// Milliseconds since the first captured frame, interpolating between
+    /// frame ticks at fractional positions.
+    fn timeline_ms(&self) -> f64 {
+        let Some(origin) = self.frames.first().map(|f| f.tick) else {
+            return 0.0;
+        };
+        let frame = self.cursor.floor() as usize;
+        let tick = self.frames.get(frame).map(|f| f.tick).unwrap_or(origin);
+        let next = self.frames.get(frame + 1).map(|f| f.tick).unwrap_or(tick);
+        let tick = tick as f64 + (next - tick) as f64 * (self.cursor - frame as f64);
+        (tick - origin as f64) * TICK_MS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::cinema::PathKeyframe;
+    use chrono::Utc;
+
+    fn frame(tick: u64) -> CaptureFrame {
+        CaptureFrame {
+            tick,
+            timestamp: Utc::now(),
+            player_states: Vec::new(),
+            entity_states: Vec::new(),
+            block_changes: Vec::new(),
+            particles: Vec::new(),
+            sounds: Vec::new(),
+            chat_messages: Vec::new(),
+            world_events: Vec::new(),
+        }
+    }
+
+    fn camera_x(player: &ReplayPlayer) -> Option<f64> {
+        let camera = player.camera().read();
+        camera.is_driven().then(|| camera.position().x)
+    }
+
+    #[test]
+    fn test_attached_path_follows_timeline() {
+        let dir = std::env::temp_dir().join(format!("rubidium-replay-{}", Uuid::new_v4()));
+        let storage = Arc::new(ReplayStorage::new(dir.clone(), 1.0));
+        let frames: Vec<CaptureFrame> = (100..110).map(frame).collect();
+        let replay_id = storage.save_replay(Uuid::new_v4(), Utc::now(), Utc::now(), 100, 109, frames).unwrap();
+
+        // 200ms path over a 450ms replay: frames 0..=4 are covered
+        let mut path = CameraPath::new(Uuid::nil(), "flyby".to_string());
+        path.add_keyframe(PathKeyframe::new(0, 0.0, 64.0, 0.0, 0.0, 0.0));
+        path.add_keyframe(PathKeyframe::new(200, 40.0, 64.0, 0.0, 0.0, 0.0));
+
+        let player = ReplayPlayer::new(storage.clone());
+        player.load(replay_id).unwrap();
+        player.attach_camera(path);
+        player.set_camera_interpolation(CameraInterpolation::Linear).unwrap();
+        player.play().unwrap();
+
+        let mut seen = Vec::new();
+        for _ in 0..6 {
+            player.tick();
+            seen.push(camera_x(&player));
+        }
+        assert_eq!(seen, vec![Some(0.0), Some(10.0), Some(20.0), Some(30.0), Some(40.0), None]);
+
+        // Half speed lands between frames; pausing holds the shot
+        player.seek(1).unwrap();
+        player.set_speed(PlaybackSpeed::Slow050).unwrap();
+        player.tick();
+        player.tick();
+        assert_eq!(camera_x(&player), Some(15.0));
+        player.pause().unwrap();
+        player.tick();
+        player.tick();
+        assert_eq!(player.tick().map(|f| f.tick), Some(102));
+        assert_eq!(camera_x(&player), Some(20.0));
+
+        // Reverse walks the same transforms back
+        player.seek(4).unwrap();
+        player.set_speed(PlaybackSpeed::Normal).unwrap();
+        player.set_reverse(true).unwrap();
+        player.play().unwrap();
+        player.tick();
+        player.tick();
+        assert_eq!(camera_x(&player), Some(30.0));
+
+        // Seeking is deterministic
+        player.seek(2).unwrap();
+        let first = player.camera_override().unwrap();
+        player.seek(7).unwrap();
+        assert!(player.camera_override().is_none());
+        player.seek(2).unwrap();
+        assert_eq!(player.camera_override().unwrap().x, first.x);
+
+        // A viewer without the path gets the baked shot from the manifest
+        let track = player.bake_camera().unwrap();
+        assert_eq!(track.frames.len(), 5);
+        let viewer = ReplayPlayer::new(Arc::new(ReplayStorage::new(dir.clone(), 1.0)));
+        viewer.load(replay_id).unwrap();
+        viewer.seek(3).unwrap();
+        assert_eq!(camera_x(&viewer), Some(30.0));
+        viewer.seek(8).unwrap();
+        assert_eq!(camera_x(&viewer), None);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
 }
@@ -1,3 +1,4 @@
+use super::camera_track::BakedCameraTrack;
 use super::capture::CaptureFrame;
 use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
@@ -28,6 +29,8 @@ pub struct ReplayManifest {
     pub capture_radius: f64,
     pub tags: Vec<String>,
     pub shared_with: Vec<Uuid>,
+    #[serde(default)]
+    pub camera_track: Option<BakedCameraTrack>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -141,6 +144,7 @@ impl ReplayStorage {
             capture_radius: 64.0,
             tags: Vec::new(),
             shared_with: Vec::new(),
+            camera_track: None,
         };
 
         let manifest_path = replay_dir.join("manifest.json");
@@ -241,6 +245,22 @@ impl ReplayStorage {
         Ok(())
     }
 
+    /// Embeds a baked camera track in the replay's manifest, replacing any
+    /// earlier one.
+    pub fn set_camera_track(&self, replay_id: Uuid, track: Option<BakedCameraTrack>) -> Result<ReplayManifest, String> {
+        let mut index = self.index.write();
+        let manifest = index.get_mut(&replay_id).ok_or("Replay not found")?;
+        manifest.camera_track = track;
+        let manifest = manifest.clone();
+        drop(index);
+
+        let manifest_path = self.storage_path.join(replay_id.to_string()).join("manifest.json");
+        let manifest_data = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        fs::write(&manifest_path, &manifest_data).map_err(|e| e.to_string())?;
+        self.save_index();
+        Ok(manifest)
+    }
+
     pub fn get_total_size(&self) -> u64 {
         self.index.read().values().map(|m| m.total_size_bytes).sum()
     }
@@ -8,6 +8,10 @@ pub struct WaypointConfig {
     pub features: WaypointFeatures,
     pub display: WaypointDisplay,
     pub permissions: WaypointPermissions,
+    #[serde(default)]
+    pub groups: WaypointGroupSettings,
+    #[serde(default)]
+    pub spatial_index: WaypointSpatialIndex,
 }
 
 impl Default for WaypointConfig {
@@ -19,6 +23,8 @@ impl Default for WaypointConfig {
             features: WaypointFeatures::default(),
             display: WaypointDisplay::default(),
             permissions: WaypointPermissions::default(),
+            groups: WaypointGroupSettings::default(),
+            spatial_index: WaypointSpatialIndex::default(),
         }
     }
 }
@@ -147,3 +153,38 @@ impl Default for WaypointPermissions {
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaypointGroupSettings {
+    pub max_groups_per_player: u32,
+    pub max_group_name_length: usize,
+    /// Groups hidden by a player also drop out of their proximity alerts.
+    pub hidden_groups_skip_proximity: bool,
+}
+
+impl Default for WaypointGroupSettings {
+    fn default() -> Self {
+        Self {
+            max_groups_per_player: 20,
+            max_group_name_length: 24,
+            hidden_groups_skip_proximity: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaypointSpatialIndex {
+    /// Width of a grid cell in blocks. Roughly the radius most nearest
+    /// queries use works well; the index is rebuilt when this changes.
+    pub cell_size: f64,
+    pub max_query_results: usize,
+}
+
+impl Default for WaypointSpatialIndex {
+    fn default() -> Self {
+        Self {
+            cell_size: 64.0,
+            max_query_results: 256,
+        }
+    }
+}
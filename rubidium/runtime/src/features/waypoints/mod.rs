@@ -1,7 +1,9 @@
 pub mod config;
 pub mod service;
+pub mod spatial;
 pub mod types;
 
 pub use config::WaypointConfig;
 pub use service::WaypointService;
-pub use types::{Waypoint, WaypointVisibility, WaypointIcon};
+pub use spatial::SpatialGrid;
+pub use types::{Waypoint, WaypointVisibility, WaypointIcon, WaypointGroup, NearbyWaypoint, WaypointCommandResult};
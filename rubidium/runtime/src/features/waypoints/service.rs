@@ -1,8 +1,11 @@
 use super::config::WaypointConfig;
-use super::types::{Waypoint, WaypointGroup, WaypointHudInfo, WaypointType, WaypointVisibility};
+use super::spatial::SpatialGrid;
+use super::types::{NearbyWaypoint, Waypoint, WaypointCommandResult, WaypointGroup, WaypointHudInfo, WaypointType, WaypointVisibility};
+use crate::bridge::GameCommand;
 use chrono::Utc;
 use dashmap::DashMap;
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -13,10 +16,12 @@ pub struct WaypointService {
     dimension_index: DashMap<String, Vec<Uuid>>,
     groups: DashMap<Uuid, Vec<WaypointGroup>>,
     proximity_tracking: DashMap<Uuid, Vec<Uuid>>,
+    spatial: RwLock<SpatialGrid>,
 }
 
 impl WaypointService {
     pub fn new(config: WaypointConfig) -> Self {
+        let spatial = SpatialGrid::new(config.spatial_index.cell_size);
         Self {
             config: Arc::new(RwLock::new(config)),
             waypoints: DashMap::new(),
@@ -24,6 +29,7 @@ impl WaypointService {
             dimension_index: DashMap::new(),
             groups: DashMap::new(),
             proximity_tracking: DashMap::new(),
+            spatial: RwLock::new(spatial),
         }
     }
 
@@ -96,12 +102,20 @@ impl WaypointService {
             return Err("Temporary waypoints are not allowed".to_string());
         }
 
+        if let Some(group) = &waypoint.group {
+            self.check_group_name(&config, waypoint.owner_id, group)?;
+        }
+
         drop(config);
 
         let id = waypoint.id;
         let owner = waypoint.owner_id;
         let dimension = waypoint.dimension.clone();
 
+        if let Some(group) = &waypoint.group {
+            self.ensure_group(owner, group);
+        }
+        self.spatial.write().insert(&dimension, id, waypoint.x, waypoint.y, waypoint.z);
         self.waypoints.insert(id, waypoint);
         
         self.owner_index.entry(owner)
@@ -131,9 +145,11 @@ impl WaypointService {
 
         let owner = waypoint.owner_id;
         let dimension = waypoint.dimension.clone();
+        let (x, z) = (waypoint.x, waypoint.z);
         drop(waypoint);
 
         self.waypoints.remove(&waypoint_id);
+        self.spatial.write().remove(&dimension, waypoint_id, x, z);
 
         if let Some(mut ids) = self.owner_index.get_mut(&owner) {
             ids.retain(|id| *id != waypoint_id);
@@ -163,8 +179,26 @@ impl WaypointService {
             return Err("You can only edit your own waypoints".to_string());
         }
 
+        let before = (waypoint.dimension.clone(), waypoint.x, waypoint.y, waypoint.z);
         updater(&mut waypoint);
         waypoint.updated_at = Utc::now();
+        let after = (waypoint.dimension.clone(), waypoint.x, waypoint.y, waypoint.z);
+        // Release the entry before taking the index lock; queries hold the
+        // index lock while reading waypoints.
+        drop(waypoint);
+
+        if before != after {
+            let mut spatial = self.spatial.write();
+            spatial.remove(&before.0, waypoint_id, before.1, before.3);
+            spatial.insert(&after.0, waypoint_id, after.1, after.2, after.3);
+        }
+
+        if before.0 != after.0 {
+            if let Some(mut ids) = self.dimension_index.get_mut(&before.0) {
+                ids.retain(|id| *id != waypoint_id);
+            }
+            self.dimension_index.entry(after.0).or_default().push(waypoint_id);
+        }
 
         Ok(())
     }
@@ -182,15 +216,75 @@ impl WaypointService {
     }
 
     pub fn get_visible_waypoints(&self, player_id: Uuid, dimension: &str) -> Vec<Waypoint> {
+        self.visible_waypoints(player_id, dimension, true)
+    }
+
+    fn visible_waypoints(&self, player_id: Uuid, dimension: &str, skip_hidden_groups: bool) -> Vec<Waypoint> {
+        let hidden = if skip_hidden_groups { self.hidden_groups(player_id) } else { HashSet::new() };
         self.dimension_index.get(dimension)
             .map(|ids| ids.iter()
                 .filter_map(|id| self.waypoints.get(id))
-                .filter(|w| w.is_visible_to(player_id))
+                .filter(|w| w.is_visible_to(player_id) && !in_hidden_group(w, &hidden))
                 .map(|w| w.clone())
                 .collect())
             .unwrap_or_default()
     }
 
+    /// Nearest waypoints in `world` regardless of who owns them, nearest first.
+    pub fn nearest_waypoints(&self, world_id: &str, x: f64, y: f64, z: f64, limit: usize, max_distance: f64) -> Vec<NearbyWaypoint> {
+        self.nearest_matching(world_id, x, y, z, limit, max_distance, |_| true)
+    }
+
+    /// Like `nearest_waypoints`, limited to what `player_id` can see with
+    /// their hidden groups left out.
+    #[allow(clippy::too_many_arguments)]
+    pub fn nearest_visible_waypoints(&self, player_id: Uuid, world_id: &str, x: f64, y: f64, z: f64, limit: usize, max_distance: f64) -> Vec<NearbyWaypoint> {
+        let hidden = self.hidden_groups(player_id);
+        self.nearest_matching(world_id, x, y, z, limit, max_distance, |wp| {
+            wp.is_visible_to(player_id) && !in_hidden_group(wp, &hidden)
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn nearest_matching<F>(&self, world_id: &str, x: f64, y: f64, z: f64, limit: usize, max_distance: f64, filter: F) -> Vec<NearbyWaypoint>
+    where
+        F: Fn(&Waypoint) -> bool,
+    {
+        let config = self.config.read();
+        let limit = limit.min(config.spatial_index.max_query_results);
+        let cell_size = config.spatial_index.cell_size;
+        drop(config);
+
+        if self.spatial.read().cell_size() != cell_size {
+            self.rebuild_spatial_index();
+        }
+
+        let nearest = self.spatial.read().nearest(world_id, x, y, z, limit, max_distance, |id| {
+            self.waypoints.get(&id).is_some_and(|wp| filter(&wp))
+        });
+
+        nearest.into_iter()
+            .filter_map(|(id, distance)| self.waypoints.get(&id).map(|wp| NearbyWaypoint {
+                waypoint: wp.clone(),
+                distance,
+            }))
+            .collect()
+    }
+
+    /// Rebuilds the spatial index from scratch using the configured cell size.
+    pub fn rebuild_spatial_index(&self) {
+        let cell_size = self.config.read().spatial_index.cell_size;
+        let entries: Vec<_> = self.waypoints.iter()
+            .map(|wp| (wp.dimension.clone(), wp.id, wp.x, wp.y, wp.z))
+            .collect();
+
+        let mut grid = SpatialGrid::new(cell_size);
+        for (dimension, id, x, y, z) in entries {
+            grid.insert(&dimension, id, x, y, z);
+        }
+        *self.spatial.write() = grid;
+    }
+
     pub fn get_hud_waypoints(&self, player_id: Uuid, player_x: f64, player_y: f64, player_z: f64, player_yaw: f32, dimension: &str) -> Vec<WaypointHudInfo> {
         let config = self.config.read();
         if !config.display.hud_enabled {
@@ -314,11 +408,12 @@ impl WaypointService {
         }
         drop(config);
 
+        let skip_hidden_groups = self.config.read().groups.hidden_groups_skip_proximity;
         let mut triggered = Vec::new();
         let mut already_triggered = self.proximity_tracking.entry(player_id)
             .or_insert_with(Vec::new);
 
-        for wp in self.get_visible_waypoints(player_id, dimension) {
+        for wp in self.visible_waypoints(player_id, dimension, skip_hidden_groups) {
             if let Some(radius) = wp.proximity_radius {
                 let distance = wp.horizontal_distance_to(player_x, player_z);
                 
@@ -342,10 +437,109 @@ impl WaypointService {
         }
     }
 
-    pub fn create_group(&self, player_id: Uuid, group: WaypointGroup) {
-        self.groups.entry(player_id)
-            .or_insert_with(Vec::new)
-            .push(group);
+    pub fn create_group(&self, player_id: Uuid, group: WaypointGroup) -> Result<(), String> {
+        let config = self.config.read();
+        self.check_group_name(&config, player_id, &group.name)?;
+        drop(config);
+
+        let mut groups = self.groups.entry(player_id).or_default();
+        if groups.iter().any(|g| g.name == group.name) {
+            return Err(format!("Group '{}' already exists", group.name));
+        }
+        groups.push(group);
+        Ok(())
+    }
+
+    fn check_group_name(&self, config: &WaypointConfig, player_id: Uuid, name: &str) -> Result<(), String> {
+        if !config.features.waypoint_groups {
+            return Err("Waypoint groups are disabled".to_string());
+        }
+
+        if name.trim().is_empty() || name.len() > config.groups.max_group_name_length {
+            return Err(format!("Group name must be 1-{} characters", config.groups.max_group_name_length));
+        }
+
+        let groups = self.groups.get(&player_id);
+        let exists = groups.as_ref().is_some_and(|g| g.iter().any(|g| g.name == name));
+        let count = groups.map(|g| g.len()).unwrap_or(0);
+        if !exists && count >= config.groups.max_groups_per_player as usize {
+            return Err(format!("Maximum groups ({}) reached", config.groups.max_groups_per_player));
+        }
+
+        Ok(())
+    }
+
+    fn ensure_group(&self, player_id: Uuid, name: &str) {
+        let mut groups = self.groups.entry(player_id).or_default();
+        if !groups.iter().any(|g| g.name == name) {
+            groups.push(WaypointGroup::new(name.to_string(), 0xFFFFFF));
+        }
+    }
+
+    /// Moves a waypoint into `group`, creating the group if needed, or out of
+    /// any group with `None`.
+    pub fn set_waypoint_group(&self, waypoint_id: Uuid, requester_id: Uuid, group: Option<String>) -> Result<(), String> {
+        if let Some(name) = &group {
+            let config = self.config.read();
+            self.check_group_name(&config, requester_id, name)?;
+        }
+
+        self.update_waypoint(waypoint_id, requester_id, |wp| {
+            wp.group = group.clone();
+        })?;
+
+        if let Some(name) = &group {
+            self.ensure_group(requester_id, name);
+        }
+        Ok(())
+    }
+
+    /// Shows or hides every waypoint in one of the player's groups at once.
+    pub fn set_group_visible(&self, player_id: Uuid, group: &str, visible: bool) -> Result<(), String> {
+        if !self.config.read().features.waypoint_groups {
+            return Err("Waypoint groups are disabled".to_string());
+        }
+
+        let mut groups = self.groups.get_mut(&player_id).ok_or("Group not found")?;
+        let group = groups.iter_mut()
+            .find(|g| g.name == group)
+            .ok_or("Group not found")?;
+        group.visible = visible;
+        Ok(())
+    }
+
+    pub fn get_group_waypoints(&self, player_id: Uuid, group: &str) -> Vec<Waypoint> {
+        self.get_player_waypoints(player_id)
+            .into_iter()
+            .filter(|wp| wp.group.as_deref() == Some(group))
+            .collect()
+    }
+
+    fn hidden_groups(&self, player_id: Uuid) -> HashSet<String> {
+        self.groups.get(&player_id)
+            .map(|groups| groups.iter()
+                .filter(|g| !g.visible)
+                .map(|g| g.name.clone())
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Answers the waypoint commands; `None` for anything else.
+    pub fn handle_command(&self, command: &GameCommand) -> Option<Result<WaypointCommandResult, String>> {
+        match command {
+            GameCommand::NearestWaypoints { world, x, y, z, limit, max_distance, viewer } => {
+                let nearest = match viewer {
+                    Some(player) => self.nearest_visible_waypoints(*player, world, *x, *y, *z, *limit, *max_distance),
+                    None => self.nearest_waypoints(world, *x, *y, *z, *limit, *max_distance),
+                };
+                Some(Ok(WaypointCommandResult::Nearest(nearest)))
+            }
+            GameCommand::SetWaypointGroupVisibility { player, group, visible } => Some(
+                self.set_group_visible(*player, group, *visible)
+                    .map(|_| WaypointCommandResult::GroupVisibility { group: group.clone(), visible: *visible })
+            ),
+            _ => None,
+        }
     }
 
     pub fn get_groups(&self, player_id: Uuid) -> Vec<WaypointGroup> {
@@ -358,14 +552,18 @@ impl WaypointService {
         let now = Utc::now();
         let expired: Vec<_> = self.waypoints.iter()
             .filter(|w| w.expires_at.map(|e| e < now).unwrap_or(false))
-            .map(|w| (w.id, w.owner_id))
+            .map(|w| (w.id, w.owner_id, w.dimension.clone(), w.x, w.z))
             .collect();
 
-        for (id, owner) in expired {
+        for (id, owner, dimension, x, z) in expired {
             self.waypoints.remove(&id);
+            self.spatial.write().remove(&dimension, id, x, z);
             if let Some(mut ids) = self.owner_index.get_mut(&owner) {
                 ids.retain(|i| *i != id);
             }
+            if let Some(mut ids) = self.dimension_index.get_mut(&dimension) {
+                ids.retain(|i| *i != id);
+            }
         }
     }
 
@@ -381,3 +579,113 @@ impl WaypointService {
         self.config.write().enabled = enabled;
     }
 }
+
+fn in_hidden_group(waypoint: &Waypoint, hidden: &HashSet<String>) -> bool {
+    waypoint.group.as_ref().is_some_and(|g| hidden.contains(g))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> WaypointService {
+        WaypointService::new(WaypointConfig {
+            max_waypoints_per_player: 100_000,
+            ..Default::default()
+        })
+    }
+
+    /// Deterministic coordinates without pulling in a RNG crate
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, range: f64) -> f64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * range
+        }
+    }
+
+    #[test]
+    fn test_nearest_with_50k_waypoints_matches_brute_force() {
+        let service = service();
+        let owner = Uuid::new_v4();
+        let mut rng = Lcg(42);
+        for i in 0..50_000 {
+            let dimension = if i % 10 == 0 { "nether" } else { "overworld" };
+            let wp = Waypoint::new(owner, format!("wp{}", i), rng.next(20_000.0), rng.next(256.0), rng.next(20_000.0), dimension.to_string());
+            service.create_waypoint(wp).unwrap();
+        }
+
+        let all: Vec<Waypoint> = service.get_player_waypoints(owner)
+            .into_iter()
+            .filter(|wp| wp.dimension == "overworld")
+            .collect();
+
+        let queries: Vec<(f64, f64, f64)> = (0..100).map(|_| (rng.next(20_000.0), 64.0, rng.next(20_000.0))).collect();
+        let results: Vec<_> = queries.iter()
+            .map(|&(x, y, z)| service.nearest_waypoints("overworld", x, y, z, 10, 2_000.0))
+            .collect();
+
+        for (&(x, y, z), nearest) in queries.iter().zip(&results) {
+            let mut expected: Vec<(f64, Uuid)> = all.iter()
+                .map(|wp| (wp.distance_to(x, y, z), wp.id))
+                .filter(|(d, _)| *d <= 2_000.0)
+                .collect();
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            expected.truncate(10);
+
+            let got: Vec<(f64, Uuid)> = nearest.iter().map(|n| (n.distance, n.waypoint.id)).collect();
+            assert_eq!(got, expected);
+            assert!(nearest.iter().all(|n| n.waypoint.dimension == "overworld"));
+        }
+    }
+
+    #[test]
+    fn test_index_follows_moves_and_deletes() {
+        let service = service();
+        let owner = Uuid::new_v4();
+        let id = service.create_waypoint(Waypoint::new(owner, "mine".into(), 0.0, 64.0, 0.0, "overworld".into())).unwrap();
+
+        service.update_waypoint(id, owner, |wp| {
+            wp.x = 5_000.0;
+            wp.dimension = "nether".into();
+        }).unwrap();
+        assert!(service.nearest_waypoints("overworld", 0.0, 64.0, 0.0, 5, 100.0).is_empty());
+        assert_eq!(service.nearest_waypoints("nether", 5_000.0, 64.0, 0.0, 5, 100.0)[0].waypoint.id, id);
+        assert_eq!(service.get_visible_waypoints(owner, "nether").len(), 1);
+
+        service.delete_waypoint(id, owner).unwrap();
+        assert!(service.nearest_waypoints("nether", 5_000.0, 64.0, 0.0, 5, 100.0).is_empty());
+    }
+
+    #[test]
+    fn test_hiding_a_group_hides_its_waypoints() {
+        let service = service();
+        let owner = Uuid::new_v4();
+        let mut ore = Waypoint::new(owner, "iron".into(), 1.0, 64.0, 0.0, "overworld".into());
+        ore.group = Some("ores".into());
+        service.create_waypoint(ore).unwrap();
+        service.create_waypoint(Waypoint::new(owner, "base".into(), 2.0, 64.0, 0.0, "overworld".into())).unwrap();
+
+        let command = GameCommand::SetWaypointGroupVisibility { player: owner, group: "ores".into(), visible: false };
+        assert!(matches!(service.handle_command(&command), Some(Ok(_))));
+        assert_eq!(service.get_visible_waypoints(owner, "overworld").len(), 1);
+
+        let query = GameCommand::NearestWaypoints {
+            world: "overworld".into(), x: 0.0, y: 64.0, z: 0.0, limit: 5, max_distance: 10.0, viewer: Some(owner),
+        };
+        match service.handle_command(&query) {
+            Some(Ok(WaypointCommandResult::Nearest(nearest))) => {
+                assert_eq!(nearest.len(), 1);
+                assert_eq!(nearest[0].waypoint.name, "base");
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+        assert_eq!(service.nearest_waypoints("overworld", 0.0, 64.0, 0.0, 5, 10.0).len(), 2);
+
+        service.set_group_visible(owner, "ores", true).unwrap();
+        assert_eq!(service.get_visible_waypoints(owner, "overworld").len(), 2);
+        assert!(service.set_group_visible(owner, "missing", false).is_err());
+        assert!(service.handle_command(&GameCommand::Say("hi".into())).is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use uuid::Uuid;
+
+/// Uniform grid over the horizontal plane, one per world. Queries scan rings
+/// of cells outward from the query point, so their cost depends on how many
+/// cells are visited rather than on how many waypoints exist.
+pub struct SpatialGrid {
+    cell_size: f64,
+    worlds: HashMap<String, WorldGrid>,
+}
+
+#[derive(Default)]
+struct WorldGrid {
+    cells: HashMap<(i64, i64), Vec<GridEntry>>,
+    min: (i64, i64),
+    max: (i64, i64),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GridEntry {
+    id: Uuid,
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f64,
+    id: Uuid,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size: if cell_size.is_finite() && cell_size > 0.0 { cell_size } else { 64.0 },
+            worlds: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    fn cell_of(&self, x: f64, z: f64) -> (i64, i64) {
+        ((x / self.cell_size).floor() as i64, (z / self.cell_size).floor() as i64)
+    }
+
+    pub fn insert(&mut self, world: &str, id: Uuid, x: f64, y: f64, z: f64) {
+        let cell = self.cell_of(x, z);
+        let grid = match self.worlds.get_mut(world) {
+            Some(grid) => grid,
+            None => self.worlds.entry(world.to_string()).or_insert_with(|| WorldGrid {
+                min: cell,
+                max: cell,
+                ..Default::default()
+            }),
+        };
+
+        grid.min = (grid.min.0.min(cell.0), grid.min.1.min(cell.1));
+        grid.max = (grid.max.0.max(cell.0), grid.max.1.max(cell.1));
+        grid.cells.entry(cell).or_default().push(GridEntry { id, x, y, z });
+    }
+
+    pub fn remove(&mut self, world: &str, id: Uuid, x: f64, z: f64) -> bool {
+        let cell = self.cell_of(x, z);
+        let Some(grid) = self.worlds.get_mut(world) else {
+            return false;
+        };
+        let Some(entries) = grid.cells.get_mut(&cell) else {
+            return false;
+        };
+        let Some(index) = entries.iter().position(|e| e.id == id) else {
+            return false;
+        };
+
+        entries.swap_remove(index);
+        if entries.is_empty() {
+            grid.cells.remove(&cell);
+        }
+        if grid.cells.is_empty() {
+            self.worlds.remove(world);
+        }
+        true
+    }
+
+    /// Up to `limit` entries within `max_distance` of the point, nearest
+    /// first. `filter` is only consulted for entries already in range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn nearest<F>(&self, world: &str, x: f64, y: f64, z: f64, limit: usize, max_distance: f64, filter: F) -> Vec<(Uuid, f64)>
+    where
+        F: FnMut(Uuid) -> bool,
+    {
+        self.search(world, x, y, z, limit, max_distance, filter).0
+    }
+
+    /// `nearest`, along with how many entries it had to look at
+    #[allow(clippy::too_many_arguments)]
+    fn search<F>(&self, world: &str, x: f64, y: f64, z: f64, limit: usize, max_distance: f64, mut filter: F) -> (Vec<(Uuid, f64)>, usize)
+    where
+        F: FnMut(Uuid) -> bool,
+    {
+        let Some(grid) = self.worlds.get(world) else {
+            return (Vec::new(), 0);
+        };
+        if limit == 0 || max_distance.is_nan() || max_distance < 0.0 {
+            return (Vec::new(), 0);
+        }
+
+        let (cx, cz) = self.cell_of(x, z);
+        let max_ring = [cx - grid.min.0, grid.max.0 - cx, cz - grid.min.1, grid.max.1 - cz]
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+            .max(0);
+
+        let mut best: BinaryHeap<Candidate> = BinaryHeap::with_capacity(limit + 1);
+        let mut examined = 0;
+        let mut visit = |cell: (i64, i64), best: &mut BinaryHeap<Candidate>| {
+            let Some(entries) = grid.cells.get(&cell) else {
+                return;
+            };
+            examined += entries.len();
+            for entry in entries {
+                let (dx, dy, dz) = (entry.x - x, entry.y - y, entry.z - z);
+                let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+                if distance > max_distance {
+                    continue;
+                }
+                let candidate = Candidate { distance, id: entry.id };
+                if best.len() == limit && best.peek().is_some_and(|worst| candidate >= *worst) {
+                    continue;
+                }
+                if !filter(entry.id) {
+                    continue;
+                }
+                best.push(candidate);
+                if best.len() > limit {
+                    best.pop();
+                }
+            }
+        };
+
+        for ring in 0..=max_ring {
+            if ring == 0 {
+                visit((cx, cz), &mut best);
+            } else {
+                for dx in -ring..=ring {
+                    visit((cx + dx, cz - ring), &mut best);
+                    visit((cx + dx, cz + ring), &mut best);
+                }
+                for dz in (1 - ring)..ring {
+                    visit((cx - ring, cz + dz), &mut best);
+                    visit((cx + ring, cz + dz), &mut best);
+                }
+            }
+
+            // Anything in the next ring is at least `ring` whole cells away
+            let next_ring_bound = ring as f64 * self.cell_size;
+            if next_ring_bound > max_distance {
+                break;
+            }
+            if best.len() == limit && best.peek().is_some_and(|worst| worst.distance <= next_ring_bound) {
+                break;
+            }
+        }
+
+        let mut results: Vec<(Uuid, f64)> = best.into_sorted_vec()
+            .into_iter()
+            .map(|c| (c.id, c.distance))
+            .collect();
+        results.truncate(limit);
+        (results, examined)
+    }
+
+    pub fn clear(&mut self) {
+        self.worlds.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.worlds.values()
+            .map(|grid| grid.cells.values().map(Vec::len).sum::<usize>())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.worlds.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_scans_past_cell_boundaries() {
+        let mut grid = SpatialGrid::new(16.0);
+        let near = Uuid::new_v4();
+        let far = Uuid::new_v4();
+        let other_world = Uuid::new_v4();
+        grid.insert("overworld", far, 15.0, 64.0, 0.0);
+        grid.insert("overworld", near, -1.0, 64.0, 0.0);
+        grid.insert("nether", other_world, 0.0, 64.0, 0.0);
+
+        let results = grid.nearest("overworld", 1.0, 64.0, 0.0, 10, 100.0, |_| true);
+        assert_eq!(results.iter().map(|r| r.0).collect::<Vec<_>>(), vec![near, far]);
+        assert_eq!(results[0].1, 2.0);
+
+        assert!(grid.nearest("overworld", 1.0, 64.0, 0.0, 10, 1.0, |_| true).is_empty());
+        assert_eq!(grid.nearest("overworld", 1.0, 64.0, 0.0, 10, 100.0, |id| id != near)[0].0, far);
+    }
+
+    #[test]
+    fn test_nearest_only_examines_nearby_cells() {
+        // 50,176 waypoints, one every 90 blocks on a 224x224 lattice
+        let mut grid = SpatialGrid::new(64.0);
+        let ids: Vec<Uuid> = (0..224 * 224).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            grid.insert("overworld", *id, (i % 224) as f64 * 90.0, 64.0, (i / 224) as f64 * 90.0);
+        }
+
+        let (results, examined) = grid.search("overworld", 10_000.0, 64.0, 10_000.0, 10, 2_000.0, |_| true);
+        assert_eq!(results.len(), 10);
+        assert!(results.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert!(examined < 100, "examined {} of {} waypoints", examined, grid.len());
+
+        // A far-away query walks rings out to the radius, but still only those
+        let (results, examined) = grid.search("overworld", -5_000.0, 64.0, -5_000.0, 10, 2_000.0, |_| true);
+        assert!(results.is_empty());
+        assert_eq!(examined, 0);
+    }
+
+    #[test]
+    fn test_remove_drops_empty_cells_and_worlds() {
+        let mut grid = SpatialGrid::new(16.0);
+        let id = Uuid::new_v4();
+        grid.insert("overworld", id, 100.0, 0.0, -100.0);
+        assert_eq!(grid.len(), 1);
+
+        assert!(!grid.remove("overworld", id, 0.0, 0.0));
+        assert!(grid.remove("overworld", id, 100.0, -100.0));
+        assert!(grid.is_empty());
+    }
+}
@@ -226,3 +226,16 @@ pub struct WaypointHudInfo {
     pub on_screen: bool,
     pub alpha: f32,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearbyWaypoint {
+    pub waypoint: Waypoint,
+    pub distance: f64,
+}
+
+/// Reply to a waypoint `GameCommand`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WaypointCommandResult {
+    Nearest(Vec<NearbyWaypoint>),
+    GroupVisibility { group: String, visible: bool },
+}
@@ -10,8 +10,12 @@ pub mod logging;
 
 pub use core::server::Server;
 pub use core::config::ConfigManager;
-pub use core::scheduler::{Scheduler, Task, TaskPriority};
+pub use core::scheduler::{Clock, Schedule, ScheduledTaskInfo, Scheduler, Task, TaskHandle, TaskPriority};
+pub use core::cron::CronSchedule;
+pub use core::task_graph::{TaskGraph, TaskOutput, TaskOutputs, TaskStatus};
 pub use core::performance::PerformanceMonitor;
+pub use core::profiler::{ProfilerConfig, TickProfile, TickProfiler};
+pub use core::throttle::{DistanceTier, EntityThrottler, ThrottlePolicy, UpdateRates};
 pub use core::plugins::PluginManager;
 
 pub use anticheat::AnticheatService;
@@ -20,7 +24,7 @@ pub use abstraction::GameAdapter;
 pub use bridge::{GameServerBridge, GameServerConfig, ServerStatus, GameEvent, GameCommand};
 pub use bootstrap::{BootstrapOrchestrator, BootstrapPhase, StartupReport};
 pub use events::EventBus;
-pub use admin::{AdminCli, HealthCheck, HealthStatus};
+pub use admin::{AdminCli, HealthCheck, HealthEndpoint, HealthStatus, Readiness};
 pub use logging::{LoggingConfig, init_logging};
 
 pub use features::{
@@ -1,8 +1,16 @@
 use rubidium::{BootstrapOrchestrator, LoggingConfig, init_logging, AdminCli};
+use rubidium::admin::repl::{CommandHistory, CompletionSource, ConsoleHelper, InterruptAction, InterruptState, ReplOptions, ScriptErrorPolicy, HISTORY_FILE, MAX_HISTORY, REPL_COMMANDS, SHUTDOWN_WINDOW};
 use rubidium::logging::config::development_config;
+use parking_lot::RwLock;
+use rustyline::config::{CompletionType, Config};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
 use std::path::PathBuf;
-use std::io::{self, Write, BufRead};
-use tracing::{info, error};
+use std::sync::{mpsc as std_mpsc, Arc};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::{info, warn, error};
 
 #[tokio::main]
 async fn main() {
@@ -13,6 +21,15 @@ async fn main() {
     };
     init_logging(&logging_config);
     
+    let options = match ReplOptions::from_env() {
+        Ok(options) => options,
+        Err(e) => {
+            error!("{}", e);
+            error!("Usage: rubidium-server [--init-script <file>] [--on-error abort|continue] [--non-interactive]");
+            std::process::exit(2);
+        }
+    };
+    
     println!();
     println!("  ██████╗ ██╗   ██╗██████╗ ██╗██████╗ ██╗██╗   ██╗███╗   ███╗");
     println!("  ██╔══██╗██║   ██║██╔══██╗██║██╔══██╗██║██║   ██║████╗ ████║");
@@ -57,55 +74,54 @@ async fn main() {
             let anticheat = orchestrator.anticheat().unwrap().clone();
             let event_bus = orchestrator.event_bus().unwrap().clone();
             let session_manager = orchestrator.session_manager().unwrap().clone();
+            let performance = orchestrator.performance().unwrap().clone();
             
-            let admin_cli = AdminCli::new(
+            let mut admin_cli = AdminCli::new(
                 game_server.clone(),
                 anticheat,
                 event_bus,
                 session_manager,
+                performance,
             );
+            if let Some(plugins) = orchestrator.plugins() {
+                admin_cli = admin_cli.with_plugins(plugins.clone());
+            }
+            if let Some(scheduler) = orchestrator.scheduler() {
+                admin_cli = admin_cli.with_scheduler(scheduler.clone());
+            }
+            if let Some(config) = orchestrator.config() {
+                admin_cli = admin_cli.with_config(config.clone());
+            }
+            admin_cli = admin_cli.with_task_graph(orchestrator.task_graph());
+            
+            if let Some(script) = &options.exec {
+                if !run_script(&admin_cli, script, options.on_error).await && options.non_interactive {
+                    shutdown(&orchestrator).await;
+                    std::process::exit(1);
+                }
+            }
+            
+            if options.non_interactive {
+                shutdown(&orchestrator).await;
+                return;
+            }
+            
+            let data_dir = std::env::var("RUBIDIUM_DATA_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("data"));
+            let mut history = CommandHistory::load(data_dir.join(HISTORY_FILE));
             
             println!();
             println!("Type 'help' for available commands, or enter server commands directly.");
+            println!("Press Tab to complete commands and player names; Ctrl-C twice to stop the server.");
             println!();
             
-            let stdin = io::stdin();
-            let mut stdout = io::stdout();
+            run_console(&admin_cli, &mut history).await;
             
-            loop {
-                print!("rubidium> ");
-                stdout.flush().unwrap();
-                
-                let mut input = String::new();
-                if stdin.lock().read_line(&mut input).is_err() {
-                    break;
-                }
-                
-                let input = input.trim();
-                if input.is_empty() {
-                    continue;
-                }
-                
-                if input == "exit" || input == "quit" {
-                    info!("Shutdown requested...");
-                    break;
-                }
-                
-                match admin_cli.execute(input).await {
-                    Ok(output) => {
-                        if !output.is_empty() {
-                            println!("{}", output);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Error: {}", e);
-                    }
-                }
-                
-                if game_server.status() == rubidium::ServerStatus::Offline {
-                    info!("Server stopped.");
-                    break;
-                }
+            if game_server.status() == rubidium::ServerStatus::Offline {
+                info!("Server stopped.");
+            } else {
+                shutdown(&orchestrator).await;
             }
         }
         Err(e) => {
@@ -116,3 +132,196 @@ async fn main() {
         }
     }
 }
+
+/// Runs the startup script; false if it couldn't be read or a command failed.
+async fn run_script(admin_cli: &AdminCli, script: &std::path::Path, on_error: ScriptErrorPolicy) -> bool {
+    info!("Running init script {:?}", script);
+    let report = match admin_cli.execute_script(script, on_error).await {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{}", e);
+            return false;
+        }
+    };
+    
+    for step in &report.steps {
+        println!("rubidium> {}", step.command);
+        match &step.result {
+            Ok(output) if !output.is_empty() => println!("{}", output),
+            Ok(_) => {}
+            Err(e) => error!("Error on line {}: {}", step.line, e),
+        }
+    }
+    if let Some(line) = report.aborted_at {
+        error!("Init script stopped at line {}", line);
+    }
+    report.succeeded()
+}
+
+async fn shutdown(orchestrator: &BootstrapOrchestrator) {
+    if let Err(e) = orchestrator.shutdown().await {
+        error!("Shutdown failed: {}", e);
+    }
+}
+
+/// Interactive loop; returns when the user exits, Ctrl-C is pressed twice,
+/// or the game server goes offline.
+async fn run_console(admin_cli: &AdminCli, history: &mut CommandHistory) {
+    let helper = ConsoleHelper::new(AdminCli::subcommands);
+    let completions = helper.source();
+    let (prompts, mut inputs) = spawn_line_editor(helper, history.entries().cloned().collect());
+    let mut console_open = true;
+    let mut prompting = false;
+    let mut entered = None;
+    let mut interrupts = InterruptState::default();
+    let game_server = admin_cli.game_server();
+    
+    loop {
+        if console_open && !prompting {
+            refresh_completions(admin_cli, &completions);
+            prompting = prompts.send(entered.take()).is_ok();
+        }
+        
+        let input = tokio::select! {
+            input = inputs.recv(), if console_open => {
+                prompting = false;
+                input.unwrap_or(ConsoleInput::Closed)
+            }
+            signal = tokio::signal::ctrl_c() => {
+                if let Err(e) = signal {
+                    warn!("Failed to listen for Ctrl-C: {}", e);
+                    return;
+                }
+                ConsoleInput::Interrupted
+            }
+        };
+        
+        let line = match input {
+            ConsoleInput::Line(line) => line,
+            ConsoleInput::Closed => {
+                info!("Console closed; press Ctrl-C twice to stop the server");
+                console_open = false;
+                continue;
+            }
+            ConsoleInput::Interrupted => match interrupts.interrupt(Instant::now()) {
+                InterruptAction::Shutdown => {
+                    println!();
+                    info!("Shutdown requested...");
+                    return;
+                }
+                InterruptAction::CancelLine => {
+                    println!("^C (press Ctrl-C again within {}s to stop the server)", SHUTDOWN_WINDOW.as_secs());
+                    continue;
+                }
+            },
+        };
+        
+        let input = match history.expand(line.trim()) {
+            Ok(input) => input,
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+        if input.is_empty() {
+            continue;
+        }
+        if input != line.trim() {
+            println!("{}", input);
+        }
+        history.push(&input);
+        entered = Some(input.clone());
+        
+        let mut words = input.split_whitespace();
+        match words.next() {
+            Some("exit") | Some("quit") => {
+                info!("Shutdown requested...");
+                return;
+            }
+            Some("history") => {
+                let count = words.next().and_then(|n| n.parse().ok()).unwrap_or(20);
+                print!("{}", history.format(count));
+            }
+            _ => match admin_cli.execute(&input).await {
+                Ok(output) => {
+                    if !output.is_empty() {
+                        println!("{}", output);
+                    }
+                }
+                Err(e) => {
+                    error!("Error: {}", e);
+                }
+            },
+        }
+        
+        if game_server.status() == rubidium::ServerStatus::Offline {
+            return;
+        }
+    }
+}
+
+enum ConsoleInput {
+    Line(String),
+    Interrupted,
+    Closed,
+}
+
+/// Runs the line editor on a blocking thread. It reads one line per prompt
+/// request, so command output never lands on top of a half-drawn prompt;
+/// each request carries the previous command for the editor's history.
+fn spawn_line_editor(helper: ConsoleHelper, past: Vec<String>) -> (std_mpsc::Sender<Option<String>>, mpsc::UnboundedReceiver<ConsoleInput>) {
+    let (prompt_tx, prompt_rx) = std_mpsc::channel::<Option<String>>();
+    let (input_tx, input_rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut editor = match new_editor(helper) {
+            Ok(editor) => editor,
+            Err(e) => {
+                error!("Failed to start the console: {}", e);
+                let _ = input_tx.send(ConsoleInput::Closed);
+                return;
+            }
+        };
+        for line in &past {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        
+        while let Ok(entered) = prompt_rx.recv() {
+            if let Some(line) = entered {
+                let _ = editor.add_history_entry(line);
+            }
+            let input = match editor.readline("rubidium> ") {
+                Ok(line) => ConsoleInput::Line(line),
+                Err(ReadlineError::Interrupted) => ConsoleInput::Interrupted,
+                Err(ReadlineError::Eof) => ConsoleInput::Closed,
+                Err(e) => {
+                    warn!("Console read failed: {}", e);
+                    ConsoleInput::Closed
+                }
+            };
+            let closed = matches!(input, ConsoleInput::Closed);
+            if input_tx.send(input).is_err() || closed {
+                break;
+            }
+        }
+    });
+    (prompt_tx, input_rx)
+}
+
+fn new_editor(helper: ConsoleHelper) -> rustyline::Result<Editor<ConsoleHelper, DefaultHistory>> {
+    let config = Config::builder()
+        .max_history_size(MAX_HISTORY)?
+        .completion_type(CompletionType::List)
+        .auto_add_history(false)
+        .build();
+    let mut editor = Editor::with_config(config)?;
+    editor.set_helper(Some(helper));
+    Ok(editor)
+}
+
+fn refresh_completions(admin_cli: &AdminCli, completions: &Arc<RwLock<CompletionSource>>) {
+    let commands = admin_cli.list_commands()
+        .into_iter()
+        .chain(REPL_COMMANDS.iter().map(|c| c.to_string()))
+        .collect();
+    *completions.write() = CompletionSource { commands, players: admin_cli.player_names() };
+}
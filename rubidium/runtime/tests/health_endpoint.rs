@@ -0,0 +1,143 @@
+//! Boots the orchestrator against a shell script standing in for the game
+//! server and follows `/healthz` and `/readyz` through startup, a TPS drop
+//! and shutdown.
+#![cfg(unix)]
+
+use rubidium::core::config::ServerConfig;
+use rubidium::BootstrapOrchestrator;
+use std::net::SocketAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Logs like the real server once `release` exists, then answers `lag` and
+/// `recover` with TPS reports until it is told to `stop`.
+const FAKE_SERVER: &str = r#"#!/bin/sh
+echo "[12:00:00 INFO]: Starting fake server"
+while [ ! -f release ]; do sleep 0.05; done
+echo "[12:00:01 INFO]: Done (0.1s)! For help, type \"help\""
+while read -r line; do
+    case "$line" in
+        lag) echo "[12:00:02 INFO]: TPS: 5.0" ;;
+        recover) echo "[12:00:03 INFO]: TPS: 19.9" ;;
+        stop) exit 0 ;;
+    esac
+done
+"#;
+
+fn server_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rubidium-health-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let java = dir.join("fake-java.sh");
+    std::fs::write(&java, FAKE_SERVER).unwrap();
+    std::fs::set_permissions(&java, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(dir.join("server.jar"), b"not really a jar").unwrap();
+
+    let mut config = ServerConfig::default();
+    config.server.java_path = Some(java);
+    config.plugins.directory = dir.join("plugins").to_string_lossy().to_string();
+    config.health_http.enabled = true;
+    config.health_http.bind = "127.0.0.1:0".to_string();
+    config.health_http.min_tps = 15.0;
+    std::fs::write(dir.join("rubidium.toml"), toml::to_string(&config).unwrap()).unwrap();
+    dir
+}
+
+async fn get(addr: SocketAddr, path: &str) -> (u16, serde_json::Value) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let code = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (code, serde_json::from_str(body).unwrap())
+}
+
+/// Polls `path` until it answers `code`, returning the body
+async fn wait_for(addr: SocketAddr, path: &str, code: u16) -> serde_json::Value {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let (status, body) = get(addr, path).await;
+        if status == code {
+            return body;
+        }
+        assert!(tokio::time::Instant::now() < deadline, "{} stuck at {}: {}", path, status, body);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+fn check<'a>(body: &'a serde_json::Value, name: &str) -> &'a serde_json::Value {
+    body["checks"].as_array().unwrap().iter()
+        .find(|check| check["name"] == name)
+        .unwrap_or_else(|| panic!("no {} check in {}", name, body))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_readiness_follows_bootstrap_and_server_status() {
+    let dir = server_dir();
+    let mut orchestrator = BootstrapOrchestrator::new(dir.join("rubidium.toml"), dir.join("server.jar"));
+    let readiness = orchestrator.readiness();
+    let bootstrap = tokio::spawn(async move {
+        let result = orchestrator.bootstrap().await;
+        (orchestrator, result)
+    });
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    let addr = loop {
+        if let Some(addr) = readiness.endpoint_addr() {
+            break addr;
+        }
+        assert!(tokio::time::Instant::now() < deadline, "health endpoint never bound");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+
+    // The game server is held before "Done": alive, but not ready
+    let body = wait_for(addr, "/readyz", 503).await;
+    assert_eq!(check(&body, "bootstrap")["status"], "Unhealthy");
+    let (status, body) = get(addr, "/healthz").await;
+    assert_eq!(status, 200, "{}", body);
+    let body = loop {
+        let (_, body) = get(addr, "/readyz").await;
+        if check(&body, "game_server")["details"]["status"] == "Loading" {
+            break body;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert_eq!(check(&body, "game_server")["status"], "Unhealthy");
+    assert!(!readiness.is_bootstrapped());
+
+    release(&dir);
+    let (orchestrator, result) = bootstrap.await.unwrap();
+    result.unwrap();
+    assert!(readiness.is_bootstrapped());
+
+    let body = wait_for(addr, "/readyz", 200).await;
+    assert_eq!(body["status"], "Healthy");
+    assert_eq!(check(&body, "bootstrap")["details"]["phases"], "8/8");
+    assert_eq!(check(&body, "game_server")["details"]["status"], "Running");
+    let (status, body) = get(addr, "/healthz").await;
+    assert_eq!(status, 200);
+    assert_eq!(check(&body, "event_bus")["status"], "Healthy");
+
+    let game_server = orchestrator.game_server().unwrap().clone();
+    game_server.send_command("lag").await.unwrap();
+    let body = wait_for(addr, "/readyz", 503).await;
+    assert_eq!(check(&body, "tps")["message"], "5.0 TPS is below 15.0");
+    game_server.send_command("recover").await.unwrap();
+    wait_for(addr, "/readyz", 200).await;
+
+    orchestrator.shutdown().await.unwrap();
+    let body = wait_for(addr, "/readyz", 503).await;
+    assert_eq!(check(&body, "game_server")["details"]["status"], "Offline");
+    let (status, _) = get(addr, "/healthz").await;
+    assert_eq!(status, 200);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn release(dir: &Path) {
+    std::fs::write(dir.join("release"), b"").unwrap();
+}
@@ -0,0 +1,346 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Owner of marketplace items whose author's account has been purged, so
+/// listings, purchases and escrows keep a valid author.
+pub const ARCHIVED_AUTHOR_ID: Uuid = Uuid::from_u128(0x0000_0000_0000_4000_8000_00a2_c41e_d000);
+
+#[derive(Debug, Clone)]
+pub struct DeletionConfig {
+    /// How long a deleted account can still be reactivated by logging in.
+    pub grace_period: Duration,
+    pub sweep_interval: Duration,
+}
+
+impl Default for DeletionConfig {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(14 * 24 * 60 * 60),
+            sweep_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl DeletionConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            grace_period: std::env::var("ACCOUNT_DELETION_GRACE_DAYS").ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+                .unwrap_or(defaults.grace_period),
+            sweep_interval: defaults.sweep_interval,
+        }
+    }
+
+    pub fn purge_after(&self, requested_at: DateTime<Utc>) -> DateTime<Utc> {
+        requested_at + ChronoDuration::from_std(self.grace_period).unwrap_or(ChronoDuration::days(14))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletionState {
+    /// Deleted but still inside the grace period; logging in reactivates it.
+    Pending { purge_after: DateTime<Utc> },
+    /// Past the grace period; the account is anonymized for good.
+    Purged,
+}
+
+impl DeletionState {
+    pub fn at(purge_after: DateTime<Utc>, finalized: bool, now: DateTime<Utc>) -> Self {
+        if finalized || now >= purge_after {
+            DeletionState::Purged
+        } else {
+            DeletionState::Pending { purge_after }
+        }
+    }
+
+    pub fn can_reactivate(&self) -> bool {
+        matches!(self, DeletionState::Pending { .. })
+    }
+}
+
+/// Placeholder identity for a deleted account. Derived from the id so it is
+/// unique and never contains anything the user chose.
+pub fn anonymized_identity(user_id: Uuid) -> (String, String) {
+    let id = user_id.simple().to_string();
+    (format!("deleted-{}", id), format!("{}@deleted.invalid", id))
+}
+
+/// Rows removed as soon as deletion is requested. Reactivating does not
+/// bring these back.
+const DELETE_ON_REQUEST: &[&str] = &[
+    "DELETE FROM user_sessions WHERE user_id = $1",
+    "DELETE FROM friendships WHERE user_id = $1 OR friend_id = $1",
+    "DELETE FROM user_equipped_cosmetics WHERE user_id = $1",
+    "DELETE FROM notifications WHERE user_id = $1",
+];
+
+/// Personal rows removed once the grace period is over. Purchases and escrow
+/// transactions are kept for accounting and point at the anonymized user.
+const DELETE_ON_PURGE: &[&str] = &[
+    "DELETE FROM blocks WHERE blocker_id = $1 OR blocked_id = $1",
+    "DELETE FROM game_stats WHERE user_id = $1",
+    "DELETE FROM play_sessions WHERE user_id = $1",
+    "DELETE FROM mod_profiles WHERE user_id = $1",
+    "DELETE FROM performance_settings WHERE user_id = $1",
+    "DELETE FROM settings_sync WHERE user_id = $1",
+    "DELETE FROM cosmetic_loadouts WHERE user_id = $1",
+    "DELETE FROM camera_paths WHERE user_id = $1",
+    "DELETE FROM user_achievements WHERE user_id = $1",
+    "DELETE FROM user_verifications WHERE user_id = $1",
+    "DELETE FROM marketplace_likes WHERE user_id = $1",
+    "DELETE FROM party_chat_messages WHERE sender_id = $1",
+];
+
+/// Soft-deletes the account: the username and email are swapped for
+/// placeholders (the originals are held back for reactivation), sessions and
+/// social links are dropped. Returns when the account will be purged.
+pub async fn request_deletion(db: &PgPool, config: &DeletionConfig, user_id: Uuid, now: DateTime<Utc>) -> Result<DateTime<Utc>, sqlx::Error> {
+    let purge_after = config.purge_after(now);
+    let (username, email) = anonymized_identity(user_id);
+
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        "INSERT INTO account_deletions (user_id, original_username, original_email, requested_at, purge_after)
+         SELECT id, username, email, $2, $3 FROM users WHERE id = $1
+         ON CONFLICT (user_id) DO UPDATE SET
+            original_username = EXCLUDED.original_username,
+            original_email = EXCLUDED.original_email,
+            requested_at = EXCLUDED.requested_at,
+            purge_after = EXCLUDED.purge_after,
+            finalized_at = NULL"
+    )
+        .bind(user_id)
+        .bind(now)
+        .bind(purge_after)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE users SET username = $2, email = $3, deleted_at = $4, updated_at = $4 WHERE id = $1")
+        .bind(user_id)
+        .bind(&username)
+        .bind(&email)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    for sql in DELETE_ON_REQUEST {
+        sqlx::query(sql).bind(user_id).execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(purge_after)
+}
+
+#[derive(Debug)]
+pub enum ReactivateError {
+    /// Someone else has taken the username or email in the meantime.
+    IdentityTaken,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ReactivateError {
+    fn from(e: sqlx::Error) -> Self {
+        ReactivateError::Database(e)
+    }
+}
+
+/// Restores the original username and email of an account still inside its
+/// grace period.
+pub async fn reactivate(db: &PgPool, user_id: Uuid, now: DateTime<Utc>) -> Result<bool, ReactivateError> {
+    let mut tx = db.begin().await?;
+    let pending = sqlx::query_as::<_, (Option<String>, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>)>(
+        "SELECT original_username, original_email, purge_after, finalized_at
+         FROM account_deletions WHERE user_id = $1 FOR UPDATE"
+    )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let Some((Some(username), Some(email), purge_after, finalized_at)) = pending else {
+        return Ok(false);
+    };
+    if !DeletionState::at(purge_after, finalized_at.is_some(), now).can_reactivate() {
+        return Ok(false);
+    }
+
+    let restored = sqlx::query("UPDATE users SET username = $2, email = $3, deleted_at = NULL, updated_at = $4 WHERE id = $1")
+        .bind(user_id)
+        .bind(&username)
+        .bind(&email)
+        .bind(now)
+        .execute(&mut *tx)
+        .await;
+    match restored {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(ReactivateError::IdentityTaken),
+        Err(e) => return Err(e.into()),
+    }
+    sqlx::query("DELETE FROM account_deletions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Whether a username or email is held by an account that can still be
+/// reactivated, so signups can't take it.
+pub async fn is_identity_reserved(db: &PgPool, username: &str, email: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM account_deletions
+         WHERE finalized_at IS NULL AND (original_username = $1 OR original_email = $2))"
+    )
+        .bind(username)
+        .bind(email)
+        .fetch_one(db)
+        .await
+}
+
+/// Purges every account whose grace period is over: personal rows are
+/// deleted, the held-back identity is dropped, the password is invalidated
+/// and authored marketplace items move to the archived author.
+pub async fn finalize_expired(db: &PgPool, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let expired = sqlx::query_scalar::<_, Uuid>(
+        "SELECT user_id FROM account_deletions WHERE finalized_at IS NULL AND purge_after <= $1"
+    )
+        .bind(now)
+        .fetch_all(db)
+        .await?;
+
+    for user_id in &expired {
+        let mut tx = db.begin().await?;
+        for sql in DELETE_ON_PURGE {
+            sqlx::query(sql).bind(user_id).execute(&mut *tx).await?;
+        }
+        sqlx::query("UPDATE marketplace_items SET author_id = $2 WHERE author_id = $1")
+            .bind(user_id)
+            .bind(ARCHIVED_AUTHOR_ID)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE users SET password_hash = '!', display_name = NULL, avatar_url = NULL,
+                verification_status = 'unverified', verified_at = NULL, verification_method = NULL,
+                last_seen = NULL, updated_at = $2
+             WHERE id = $1"
+        )
+            .bind(user_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE account_deletions SET original_username = NULL, original_email = NULL, finalized_at = $2
+             WHERE user_id = $1"
+        )
+            .bind(user_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    Ok(expired.len() as u64)
+}
+
+/// Runs `finalize_expired` every `sweep_interval` for the life of the process.
+pub fn spawn_finalizer(db: PgPool, config: DeletionConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.sweep_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match finalize_expired(&db, Utc::now()).await {
+                Ok(purged) if purged > 0 => info!("Purged {} deleted accounts", purged),
+                Ok(_) => {}
+                Err(e) => warn!("Account deletion sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Every row the user owns, as JSON. The users row leaves out the password
+/// hash.
+pub async fn export(db: &PgPool, user_id: Uuid) -> Result<serde_json::Value, sqlx::Error> {
+    let sections: &[(&str, &str)] = &[
+        ("user", "SELECT id, username, email, display_name, avatar_url, verification_status, verified_at, created_at, updated_at, last_seen FROM users WHERE id = $1"),
+        ("friendships", "SELECT * FROM friendships WHERE user_id = $1 OR friend_id = $1"),
+        ("blocks", "SELECT * FROM blocks WHERE blocker_id = $1"),
+        ("game_stats", "SELECT * FROM game_stats WHERE user_id = $1"),
+        ("play_sessions", "SELECT * FROM play_sessions WHERE user_id = $1"),
+        ("notifications", "SELECT * FROM notifications WHERE user_id = $1"),
+        ("achievements", "SELECT * FROM user_achievements WHERE user_id = $1"),
+        ("mod_profiles", "SELECT * FROM mod_profiles WHERE user_id = $1"),
+        ("performance_settings", "SELECT * FROM performance_settings WHERE user_id = $1"),
+        ("settings_sync", "SELECT * FROM settings_sync WHERE user_id = $1"),
+        ("cosmetic_loadouts", "SELECT * FROM cosmetic_loadouts WHERE user_id = $1"),
+        ("camera_paths", "SELECT * FROM camera_paths WHERE user_id = $1"),
+        ("purchases", "SELECT * FROM marketplace_purchases WHERE user_id = $1"),
+        ("escrow_transactions", "SELECT * FROM escrow_transactions WHERE buyer_id = $1 OR seller_id = $1"),
+        ("seller_ledger", "SELECT * FROM seller_ledger WHERE seller_id = $1"),
+        ("seller_payouts", "SELECT * FROM seller_payouts WHERE seller_id = $1"),
+        ("seller_payout_batches", "SELECT * FROM seller_payout_batches WHERE seller_id = $1"),
+        ("marketplace_items", "SELECT * FROM marketplace_items WHERE author_id = $1"),
+    ];
+
+    let mut bundle = serde_json::Map::new();
+    bundle.insert("exported_at".to_string(), serde_json::json!(Utc::now()));
+    for (name, sql) in sections {
+        let rows = sqlx::query_scalar::<_, serde_json::Value>(&format!(
+            "SELECT COALESCE(json_agg(row_to_json(t)), '[]'::json) FROM ({}) t", sql
+        ))
+            .bind(user_id)
+            .fetch_one(db)
+            .await?;
+        bundle.insert(name.to_string(), rows);
+    }
+    // Waypoints are only kept client-side for now, so there is nothing to export.
+    bundle.insert("waypoints".to_string(), serde_json::json!([]));
+
+    Ok(serde_json::Value::Object(bundle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2024-03-{:02}T12:00:00Z", day)).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_login_reactivates_within_grace_period() {
+        let config = DeletionConfig::default();
+        let purge_after = config.purge_after(at(1));
+        assert_eq!(purge_after, at(15));
+
+        assert!(DeletionState::at(purge_after, false, at(1)).can_reactivate());
+        assert!(DeletionState::at(purge_after, false, at(14)).can_reactivate());
+        assert_eq!(DeletionState::at(purge_after, false, at(15)), DeletionState::Purged);
+        assert!(!DeletionState::at(purge_after, false, at(20)).can_reactivate());
+        assert!(!DeletionState::at(purge_after, true, at(2)).can_reactivate());
+    }
+
+    #[test]
+    fn test_anonymized_identity_hides_original() {
+        let user_id = Uuid::new_v4();
+        let (username, email) = anonymized_identity(user_id);
+        assert!(username.starts_with("deleted-") && username.len() <= 64);
+        assert!(email.ends_with("@deleted.invalid"));
+        assert_eq!(anonymized_identity(user_id), (username.clone(), email));
+        assert_ne!(anonymized_identity(Uuid::new_v4()).0, username);
+    }
+
+    #[test]
+    fn test_purge_keeps_accounting_rows() {
+        let purged: Vec<&str> = DELETE_ON_REQUEST.iter().chain(DELETE_ON_PURGE).copied().collect();
+        for table in ["marketplace_purchases", "escrow_transactions", "marketplace_items", "FROM users"] {
+            assert!(purged.iter().all(|sql| !sql.contains(table)), "{} must survive a purge", table);
+        }
+        for table in ["user_sessions", "friendships", "user_equipped_cosmetics", "game_stats", "mod_profiles"] {
+            assert!(purged.iter().any(|sql| sql.contains(table)), "{} must be purged", table);
+        }
+    }
+}
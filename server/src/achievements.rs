@@ -0,0 +1,341 @@
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Stat values criteria can refer to, as of the session just recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StatSnapshot {
+    pub total_playtime_minutes: i64,
+    pub total_sessions: i64,
+    pub session_minutes: i64,
+    pub achievements_count: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    TotalPlaytimeMinutes,
+    TotalSessions,
+    SessionMinutes,
+    AchievementsCount,
+}
+
+impl Stat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "total_playtime_minutes" => Some(Self::TotalPlaytimeMinutes),
+            "sessions" | "total_sessions" => Some(Self::TotalSessions),
+            "session_minutes" => Some(Self::SessionMinutes),
+            "achievements_count" => Some(Self::AchievementsCount),
+            _ => None,
+        }
+    }
+
+    fn value(self, stats: &StatSnapshot) -> i64 {
+        match self {
+            Self::TotalPlaytimeMinutes => stats.total_playtime_minutes,
+            Self::TotalSessions => stats.total_sessions,
+            Self::SessionMinutes => stats.session_minutes,
+            Self::AchievementsCount => stats.achievements_count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Gte,
+    Gt,
+    Lte,
+    Lt,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            ">=" => Some(Self::Gte),
+            ">" => Some(Self::Gt),
+            "<=" => Some(Self::Lte),
+            "<" => Some(Self::Lt),
+            "==" => Some(Self::Eq),
+            "!=" => Some(Self::Ne),
+            _ => None,
+        }
+    }
+
+    fn apply(self, left: i64, right: i64) -> bool {
+        match self {
+            Self::Gte => left >= right,
+            Self::Gt => left > right,
+            Self::Lte => left <= right,
+            Self::Lt => left < right,
+            Self::Eq => left == right,
+            Self::Ne => left != right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub stat: Stat,
+    pub op: CompareOp,
+    pub value: i64,
+}
+
+/// Conditions that must all hold. Parsed from JSON such as
+/// `{"total_playtime_minutes": 6000}` (a bare number means `>=`),
+/// `{"sessions": {">=": 100, "<": 200}}`, or `{"all": [{...}, {...}]}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Criteria {
+    pub conditions: Vec<Condition>,
+}
+
+impl Criteria {
+    pub fn parse(value: &serde_json::Value) -> Result<Self, String> {
+        let mut conditions = Vec::new();
+        collect_conditions(value, &mut conditions)?;
+        if conditions.is_empty() {
+            return Err("Criteria must contain at least one condition".to_string());
+        }
+        Ok(Self { conditions })
+    }
+
+    pub fn is_satisfied(&self, stats: &StatSnapshot) -> bool {
+        self.conditions.iter().all(|c| c.op.apply(c.stat.value(stats), c.value))
+    }
+}
+
+fn collect_conditions(value: &serde_json::Value, conditions: &mut Vec<Condition>) -> Result<(), String> {
+    let object = value.as_object().ok_or("Criteria must be a JSON object")?;
+
+    for (key, value) in object {
+        if key == "all" {
+            let parts = value.as_array().ok_or("'all' must be an array of criteria")?;
+            for part in parts {
+                collect_conditions(part, conditions)?;
+            }
+            continue;
+        }
+
+        let stat = Stat::parse(key).ok_or_else(|| format!("Unknown stat '{}'", key))?;
+        match value {
+            serde_json::Value::Number(_) => conditions.push(Condition {
+                stat,
+                op: CompareOp::Gte,
+                value: as_integer(key, value)?,
+            }),
+            serde_json::Value::Object(comparisons) if !comparisons.is_empty() => {
+                for (op, value) in comparisons {
+                    let op = CompareOp::parse(op)
+                        .ok_or_else(|| format!("Unknown comparison '{}' for '{}'", op, key))?;
+                    conditions.push(Condition { stat, op, value: as_integer(key, value)? });
+                }
+            }
+            _ => return Err(format!("'{}' must be a number or an object of comparisons", key)),
+        }
+    }
+
+    Ok(())
+}
+
+fn as_integer(key: &str, value: &serde_json::Value) -> Result<i64, String> {
+    value.as_i64().ok_or_else(|| format!("'{}' must be compared against an integer", key))
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub criteria: Criteria,
+}
+
+/// Definitions newly satisfied by `stats`. Each unlock counts toward
+/// `achievements_count`, so achievements for unlocking others chain in the
+/// same pass.
+pub fn newly_unlocked<'a>(definitions: &'a [Definition], already: &HashSet<String>, stats: StatSnapshot) -> Vec<&'a Definition> {
+    let mut stats = stats;
+    let mut unlocked: Vec<&Definition> = Vec::new();
+
+    loop {
+        let before = unlocked.len();
+        for definition in definitions {
+            let done = already.contains(&definition.id) || unlocked.iter().any(|d| d.id == definition.id);
+            if !done && definition.criteria.is_satisfied(&stats) {
+                unlocked.push(definition);
+                stats.achievements_count += 1;
+            }
+        }
+        if unlocked.len() == before {
+            return unlocked;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionUnlocks {
+    pub unlocked: Vec<UnlockedAchievement>,
+    pub achievements_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnlockedAchievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub unlocked_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Unlocks every active achievement `stats` now satisfies and refreshes the
+/// user's `achievements_count`. Runs inside the caller's transaction.
+pub async fn unlock_satisfied(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    mut stats: StatSnapshot,
+) -> Result<SessionUnlocks, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String, String, serde_json::Value)>(
+        "SELECT id, name, description, criteria FROM achievements WHERE retired_at IS NULL"
+    )
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let definitions: Vec<Definition> = rows.into_iter()
+        .filter_map(|(id, name, description, criteria)| match Criteria::parse(&criteria) {
+            Ok(criteria) => Some(Definition { id, name, description, criteria }),
+            Err(e) => {
+                tracing::warn!("Skipping achievement {} with invalid criteria: {}", id, e);
+                None
+            }
+        })
+        .collect();
+
+    let already: HashSet<String> = sqlx::query_scalar::<_, String>(
+        "SELECT achievement_id FROM user_achievements WHERE user_id = $1"
+    )
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .collect();
+    stats.achievements_count = already.len() as i64;
+
+    let now = chrono::Utc::now();
+    let mut unlocked = Vec::new();
+    for definition in newly_unlocked(&definitions, &already, stats) {
+        let inserted = sqlx::query(
+            "INSERT INTO user_achievements (user_id, achievement_id, unlocked_at) VALUES ($1, $2, $3)
+             ON CONFLICT (user_id, achievement_id) DO NOTHING"
+        )
+            .bind(user_id)
+            .bind(&definition.id)
+            .bind(now)
+            .execute(&mut *conn)
+            .await?;
+
+        if inserted.rows_affected() > 0 {
+            unlocked.push(UnlockedAchievement {
+                id: definition.id.clone(),
+                name: definition.name.clone(),
+                description: definition.description.clone(),
+                unlocked_at: now,
+            });
+        }
+    }
+
+    let achievements_count = sqlx::query_scalar::<_, i32>(
+        "UPDATE game_stats SET achievements_count = (SELECT COUNT(*) FROM user_achievements WHERE user_id = $1)
+         WHERE user_id = $1
+         RETURNING achievements_count"
+    )
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(SessionUnlocks { unlocked, achievements_count })
+}
+
+/// Achievement ids are stable slugs like `first_session`.
+pub fn validate_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err("Achievement id must be 1-64 characters of a-z, 0-9 and _".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn definition(id: &str, criteria: serde_json::Value) -> Definition {
+        Definition {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            criteria: Criteria::parse(&criteria).unwrap(),
+        }
+    }
+
+    fn stats(playtime: i64, sessions: i64) -> StatSnapshot {
+        StatSnapshot { total_playtime_minutes: playtime, total_sessions: sessions, ..Default::default() }
+    }
+
+    #[test]
+    fn test_bare_numbers_mean_at_least() {
+        let criteria = Criteria::parse(&json!({"total_playtime_minutes": 6000})).unwrap();
+        assert!(!criteria.is_satisfied(&stats(5999, 1)));
+        assert!(criteria.is_satisfied(&stats(6000, 1)));
+    }
+
+    #[test]
+    fn test_conditions_are_anded() {
+        let criteria = Criteria::parse(&json!({
+            "sessions": {">=": 10, "<": 20},
+            "all": [{"total_playtime_minutes": {">": 60}}]
+        })).unwrap();
+        assert_eq!(criteria.conditions.len(), 3);
+        assert!(criteria.is_satisfied(&stats(61, 10)));
+        assert!(!criteria.is_satisfied(&stats(60, 10)));
+        assert!(!criteria.is_satisfied(&stats(61, 20)));
+    }
+
+    #[test]
+    fn test_invalid_criteria_are_rejected() {
+        assert!(Criteria::parse(&json!({})).is_err());
+        assert!(Criteria::parse(&json!([1, 2])).is_err());
+        assert!(Criteria::parse(&json!({"deaths": 5})).is_err());
+        assert!(Criteria::parse(&json!({"sessions": {"~=": 5}})).is_err());
+        assert!(Criteria::parse(&json!({"sessions": 1.5})).is_err());
+        assert!(Criteria::parse(&json!({"sessions": "100"})).is_err());
+    }
+
+    #[test]
+    fn test_ids_are_slugs() {
+        assert!(validate_id("first_session").is_ok());
+        assert!(validate_id("").is_err());
+        assert!(validate_id("First Session").is_err());
+    }
+
+    #[test]
+    fn test_unlocks_skip_owned_and_chain_on_count() {
+        let definitions = vec![
+            definition("first_session", json!({"sessions": 1})),
+            definition("marathon", json!({"session_minutes": 240})),
+            definition("veteran", json!({"sessions": 100})),
+            definition("collector", json!({"achievements_count": 2})),
+        ];
+        let already: HashSet<String> = ["first_session".to_string()].into();
+
+        let snapshot = StatSnapshot { total_sessions: 2, session_minutes: 300, achievements_count: 1, ..Default::default() };
+        let ids: Vec<&str> = newly_unlocked(&definitions, &already, snapshot).iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["marathon", "collector"]);
+
+        let snapshot = StatSnapshot { total_sessions: 2, session_minutes: 10, achievements_count: 1, ..Default::default() };
+        assert!(newly_unlocked(&definitions, &already, snapshot).is_empty());
+    }
+}
@@ -0,0 +1,466 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notifications::{self, NewNotification, NotificationHub};
+
+/// Signed timestamps further than this from now are rejected, so a captured
+/// request can't be replayed later.
+pub const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+/// Signing secret of the Stripe webhook endpoint; the endpoint refuses every
+/// event while it is unset.
+pub fn webhook_secret() -> Option<String> {
+    std::env::var("STRIPE_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookError {
+    MissingSignature,
+    BadSignature,
+    Expired,
+    Malformed(String),
+}
+
+impl std::fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingSignature => write!(f, "Missing Stripe-Signature header"),
+            Self::BadSignature => write!(f, "Signature does not match"),
+            Self::Expired => write!(f, "Signature timestamp outside tolerance"),
+            Self::Malformed(e) => write!(f, "Malformed event: {}", e),
+        }
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// What Stripe's `v1` signatures cover: `<timestamp>.<payload>`
+fn signed_payload_mac(payload: &[u8], timestamp: i64, secret: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(payload);
+    mac
+}
+
+/// Check a `Stripe-Signature` header (`t=...,v1=...[,v1=...]`). Any `v1`
+/// entry may match, which is how Stripe signs while a secret is being rolled.
+pub fn verify_signature(payload: &[u8], header: &str, secret: &str, now: i64) -> Result<(), WebhookError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+            Some(("v1", v)) => signatures.push(v),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(WebhookError::MissingSignature)?;
+    if signatures.is_empty() {
+        return Err(WebhookError::MissingSignature);
+    }
+
+    let mac = signed_payload_mac(payload, timestamp, secret);
+    let matches = |signature: &&str| {
+        hex::decode(signature).is_ok_and(|signature| mac.clone().verify_slice(&signature).is_ok())
+    };
+    if !signatures.iter().any(matches) {
+        return Err(WebhookError::BadSignature);
+    }
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return Err(WebhookError::Expired);
+    }
+    Ok(())
+}
+
+/// What an event asks us to change, with everything we don't act on folded
+/// into `Ignored`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventAction {
+    CheckoutCompleted {
+        session_id: String,
+        mode: String,
+        paid: bool,
+        user_id: Option<Uuid>,
+        customer: Option<String>,
+        subscription: Option<String>,
+    },
+    SubscriptionChanged {
+        customer: String,
+        subscription_id: String,
+        status: String,
+        current_period_end: Option<i64>,
+    },
+    PaymentFailed {
+        customer: String,
+        subscription: Option<String>,
+    },
+    Ignored,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookEvent {
+    pub id: String,
+    pub kind: String,
+    pub action: EventAction,
+}
+
+#[derive(Deserialize)]
+struct RawEvent {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    data: RawData,
+}
+
+#[derive(Deserialize)]
+struct RawData {
+    object: serde_json::Value,
+}
+
+fn field(object: &serde_json::Value, name: &str) -> Option<String> {
+    object.get(name).and_then(|v| v.as_str()).map(String::from)
+}
+
+fn required(object: &serde_json::Value, name: &str) -> Result<String, WebhookError> {
+    field(object, name).ok_or_else(|| WebhookError::Malformed(format!("missing {}", name)))
+}
+
+pub fn parse_event(payload: &[u8]) -> Result<WebhookEvent, WebhookError> {
+    let raw: RawEvent = serde_json::from_slice(payload)
+        .map_err(|e| WebhookError::Malformed(e.to_string()))?;
+    let object = &raw.data.object;
+
+    let action = match raw.kind.as_str() {
+        "checkout.session.completed" => EventAction::CheckoutCompleted {
+            session_id: required(object, "id")?,
+            mode: required(object, "mode")?,
+            paid: matches!(field(object, "payment_status").as_deref(), Some("paid" | "no_payment_required")),
+            user_id: field(object, "client_reference_id").and_then(|id| id.parse().ok()),
+            customer: field(object, "customer"),
+            subscription: field(object, "subscription"),
+        },
+        "customer.subscription.updated" | "customer.subscription.deleted" => {
+            // Newer API versions moved the billing period onto the items
+            let current_period_end = object.get("current_period_end")
+                .or_else(|| object.pointer("/items/data/0/current_period_end"))
+                .and_then(|v| v.as_i64());
+            let status = if raw.kind == "customer.subscription.deleted" {
+                "canceled".to_string()
+            } else {
+                required(object, "status")?
+            };
+            EventAction::SubscriptionChanged {
+                customer: required(object, "customer")?,
+                subscription_id: required(object, "id")?,
+                status,
+                current_period_end,
+            }
+        }
+        "invoice.payment_failed" => EventAction::PaymentFailed {
+            customer: required(object, "customer")?,
+            subscription: field(object, "subscription")
+                .or_else(|| object.pointer("/parent/subscription_details/subscription")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)),
+        },
+        _ => EventAction::Ignored,
+    };
+
+    Ok(WebhookEvent { id: raw.id, kind: raw.kind, action })
+}
+
+/// Verify `payload` against its signature header and parse it
+pub fn construct_event(payload: &[u8], header: &str, secret: &str, now: i64) -> Result<WebhookEvent, WebhookError> {
+    verify_signature(payload, header, secret, now)?;
+    parse_event(payload)
+}
+
+/// Tier granted for a Stripe subscription status
+pub fn tier_for_status(status: &str) -> &'static str {
+    match status {
+        "active" | "trialing" => "premium",
+        _ => "free",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Processed,
+    /// The event id was recorded before; nothing was changed
+    Duplicate,
+}
+
+/// A marketplace escrow moved from pending to completed
+#[derive(Debug, Clone, Copy)]
+pub struct CompletedEscrow {
+    pub escrow_id: Uuid,
+    pub seller_id: Uuid,
+    pub item_id: Uuid,
+}
+
+/// Complete a pending escrow and record the purchase. Returns `None` when it
+/// is not pending anymore, so the webhook and a client confirmation racing
+/// each other only record the purchase once.
+pub async fn complete_escrow(conn: &mut PgConnection, escrow_id: Uuid) -> Result<Option<CompletedEscrow>, sqlx::Error> {
+    let completed = sqlx::query_as::<_, (Uuid, Uuid, Uuid, f64)>(
+        "UPDATE escrow_transactions SET status = 'completed', completed_at = NOW()
+         WHERE id = $1 AND status = 'pending'
+         RETURNING buyer_id, seller_id, item_id, amount"
+    )
+        .bind(escrow_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let Some((buyer_id, seller_id, item_id, amount)) = completed else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        "INSERT INTO marketplace_purchases (user_id, item_id, amount, escrow_id, status, created_at) VALUES ($1, $2, $3, $4, 'completed', NOW())"
+    )
+        .bind(buyer_id)
+        .bind(item_id)
+        .bind(amount)
+        .bind(escrow_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("UPDATE marketplace_items SET downloads = downloads + 1 WHERE id = $1")
+        .bind(item_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(Some(CompletedEscrow { escrow_id, seller_id, item_id }))
+}
+
+/// Apply a verified event exactly once. The event id is recorded in the same
+/// transaction as its effects, so a failed attempt leaves nothing behind and
+/// Stripe's retry is processed normally.
+pub async fn process(db: &PgPool, hub: &NotificationHub, event: &WebhookEvent) -> Result<Outcome, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let recorded = sqlx::query(
+        "INSERT INTO stripe_webhook_events (event_id, event_type, processed_at) VALUES ($1, $2, NOW())
+         ON CONFLICT (event_id) DO NOTHING"
+    )
+        .bind(&event.id)
+        .bind(&event.kind)
+        .execute(&mut *tx)
+        .await?;
+    if recorded.rows_affected() == 0 {
+        return Ok(Outcome::Duplicate);
+    }
+
+    let notifications = apply(&mut tx, &event.action).await?;
+    tx.commit().await?;
+
+    for notification in notifications {
+        notifications::send_logged(db, hub, notification).await;
+    }
+    info!("Processed Stripe event {} ({})", event.id, event.kind);
+    Ok(Outcome::Processed)
+}
+
+async fn apply(conn: &mut PgConnection, action: &EventAction) -> Result<Vec<NewNotification>, sqlx::Error> {
+    match action {
+        EventAction::CheckoutCompleted { paid: false, session_id, .. } => {
+            info!("Checkout session {} completed without payment yet", session_id);
+            Ok(Vec::new())
+        }
+        EventAction::CheckoutCompleted { session_id, mode, user_id, customer, subscription, .. } if mode == "subscription" => {
+            let Some(user_id) = user_id else {
+                warn!("Subscription checkout {} has no client_reference_id", session_id);
+                return Ok(Vec::new());
+            };
+            sqlx::query(
+                "INSERT INTO subscriptions (user_id, tier, status, stripe_customer_id, stripe_subscription_id)
+                 VALUES ($1, 'premium', 'active', $2, $3)
+                 ON CONFLICT (user_id) DO UPDATE SET
+                    tier = 'premium',
+                    status = 'active',
+                    stripe_customer_id = COALESCE(EXCLUDED.stripe_customer_id, subscriptions.stripe_customer_id),
+                    stripe_subscription_id = COALESCE(EXCLUDED.stripe_subscription_id, subscriptions.stripe_subscription_id),
+                    updated_at = NOW()"
+            )
+                .bind(user_id)
+                .bind(customer)
+                .bind(subscription)
+                .execute(&mut *conn)
+                .await?;
+            Ok(Vec::new())
+        }
+        EventAction::CheckoutCompleted { session_id, .. } => {
+            let escrow_id = sqlx::query_scalar::<_, Uuid>(
+                "SELECT id FROM escrow_transactions WHERE stripe_session_id = $1"
+            )
+                .bind(session_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+            let Some(escrow_id) = escrow_id else {
+                warn!("No escrow for checkout session {}", session_id);
+                return Ok(Vec::new());
+            };
+            Ok(complete_escrow(conn, escrow_id).await?
+                .map(|done| NewNotification::escrow_update(done.seller_id, done.escrow_id, done.item_id, "completed"))
+                .into_iter()
+                .collect())
+        }
+        EventAction::SubscriptionChanged { customer, subscription_id, status, current_period_end } => {
+            let period_end = current_period_end.and_then(|t| DateTime::<Utc>::from_timestamp(t, 0));
+            sqlx::query(
+                "UPDATE subscriptions SET
+                    tier = $1,
+                    status = $2,
+                    stripe_subscription_id = $3,
+                    current_period_end = COALESCE($4, current_period_end),
+                    updated_at = NOW()
+                 WHERE stripe_customer_id = $5"
+            )
+                .bind(tier_for_status(status))
+                .bind(status)
+                .bind(subscription_id)
+                .bind(period_end)
+                .bind(customer)
+                .execute(&mut *conn)
+                .await?;
+            Ok(Vec::new())
+        }
+        EventAction::PaymentFailed { customer, subscription } => {
+            let users = sqlx::query_scalar::<_, Uuid>(
+                "UPDATE subscriptions SET status = 'past_due', updated_at = NOW()
+                 WHERE stripe_customer_id = $1 RETURNING user_id"
+            )
+                .bind(customer)
+                .fetch_all(&mut *conn)
+                .await?;
+            Ok(users.into_iter()
+                .map(|user| NewNotification::payment_failed(user, subscription.as_deref()))
+                .collect())
+        }
+        EventAction::Ignored => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test_4eC39HqLyjWDarjtT1zdp7dc";
+    const NOW: i64 = 1_738_371_600;
+
+    const SUBSCRIPTION_CHECKOUT: &str = include_str!("../tests/fixtures/stripe/checkout_session_completed_subscription.json");
+    const PAYMENT_CHECKOUT: &str = include_str!("../tests/fixtures/stripe/checkout_session_completed_payment.json");
+    const SUBSCRIPTION_UPDATED: &str = include_str!("../tests/fixtures/stripe/customer_subscription_updated.json");
+    const SUBSCRIPTION_DELETED: &str = include_str!("../tests/fixtures/stripe/customer_subscription_deleted.json");
+    const PAYMENT_FAILED: &str = include_str!("../tests/fixtures/stripe/invoice_payment_failed.json");
+
+    /// The `v1` signature Stripe sends for `payload` signed at `timestamp`
+    fn sign(payload: &[u8], timestamp: i64, secret: &str) -> String {
+        hex::encode(signed_payload_mac(payload, timestamp, secret).finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        // Test cases 1, 2 and 6: short key, short ASCII key, key longer than a block
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            (b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+            (&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First", "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+        ];
+        for (key, message, expected) in cases {
+            assert_eq!(hex::encode(hmac_sha256(key, message)), expected);
+        }
+    }
+
+    #[test]
+    fn test_sign_covers_timestamp_and_payload() {
+        let payload = br#"{"id":"evt_test"}"#;
+        let mut signed = b"1738371600.".to_vec();
+        signed.extend_from_slice(payload);
+        assert_eq!(sign(payload, NOW, SECRET), hex::encode(hmac_sha256(SECRET.as_bytes(), &signed)));
+        let header = format!("t={},v1=zz,v1={}", NOW, sign(payload, NOW, SECRET));
+        assert_eq!(verify_signature(payload, &header, SECRET, NOW), Ok(()));
+    }
+
+    fn header(payload: &str, timestamp: i64) -> String {
+        format!("t={},v1={}", timestamp, sign(payload.as_bytes(), timestamp, SECRET))
+    }
+
+    fn receive(payload: &str) -> WebhookEvent {
+        construct_event(payload.as_bytes(), &header(payload, NOW), SECRET, NOW).unwrap()
+    }
+
+    #[test]
+    fn test_recorded_events_map_to_actions() {
+        let checkout = receive(SUBSCRIPTION_CHECKOUT);
+        assert_eq!(checkout.id, "evt_1QdXr2KpV3sJ8mRt0aB1cD2e");
+        assert_eq!(checkout.action, EventAction::CheckoutCompleted {
+            session_id: "cs_test_a1B2c3D4e5F6g7H8i9J0kLmN".to_string(),
+            mode: "subscription".to_string(),
+            paid: true,
+            user_id: Some("7f3c2a1e-9b8d-4c6f-a5e4-3d2c1b0a9f8e".parse().unwrap()),
+            customer: Some("cus_RkT9xYz12AbCdE".to_string()),
+            subscription: Some("sub_1QdXqzKpV3sJ8mRtF6gH7iJ8".to_string()),
+        });
+
+        match receive(PAYMENT_CHECKOUT).action {
+            EventAction::CheckoutCompleted { mode, paid: true, user_id: None, customer: None, .. } => assert_eq!(mode, "payment"),
+            other => panic!("unexpected action {:?}", other),
+        }
+
+        assert_eq!(receive(SUBSCRIPTION_UPDATED).action, EventAction::SubscriptionChanged {
+            customer: "cus_RkT9xYz12AbCdE".to_string(),
+            subscription_id: "sub_1QdXqzKpV3sJ8mRtF6gH7iJ8".to_string(),
+            status: "active".to_string(),
+            current_period_end: Some(1_740_787_200),
+        });
+
+        match receive(SUBSCRIPTION_DELETED).action {
+            EventAction::SubscriptionChanged { status, current_period_end, .. } => {
+                assert_eq!(tier_for_status(&status), "free");
+                assert_eq!(current_period_end, Some(1_740_787_200));
+            }
+            other => panic!("unexpected action {:?}", other),
+        }
+
+        assert_eq!(receive(PAYMENT_FAILED).action, EventAction::PaymentFailed {
+            customer: "cus_RkT9xYz12AbCdE".to_string(),
+            subscription: Some("sub_1QdXqzKpV3sJ8mRtF6gH7iJ8".to_string()),
+        });
+    }
+
+    #[test]
+    fn test_rejects_forged_stale_and_unsigned_events() {
+        let payload = SUBSCRIPTION_UPDATED.as_bytes();
+        let signed = header(SUBSCRIPTION_UPDATED, NOW);
+
+        let forged = SUBSCRIPTION_UPDATED.replace("\"active\"", "\"trialing\"");
+        assert_eq!(construct_event(forged.as_bytes(), &signed, SECRET, NOW), Err(WebhookError::BadSignature));
+        assert_eq!(construct_event(payload, &signed, "whsec_other", NOW), Err(WebhookError::BadSignature));
+        assert_eq!(
+            construct_event(payload, &signed, SECRET, NOW + SIGNATURE_TOLERANCE_SECS + 1),
+            Err(WebhookError::Expired),
+        );
+        assert_eq!(construct_event(payload, "", SECRET, NOW), Err(WebhookError::MissingSignature));
+
+        // A rolled secret signs with both; either matching is enough
+        let rolled = format!("t={},v1={},{}", NOW, "0".repeat(64), &signed[signed.find("v1=").unwrap()..]);
+        assert!(construct_event(payload, &rolled, SECRET, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_unhandled_event_types_are_ignored() {
+        let payload = r#"{"id":"evt_1","type":"customer.created","data":{"object":{"id":"cus_1"}}}"#;
+        assert_eq!(receive(payload).action, EventAction::Ignored);
+    }
+}
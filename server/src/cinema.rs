@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const MIN_KEYFRAMES: usize = 2;
+pub const MAX_KEYFRAMES: usize = 1000;
+pub const MAX_DURATION_SECONDS: f64 = 3600.0;
+
+/// Mirrors rubidium's `InterpolationType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Interpolation {
+    Linear,
+    #[default]
+    Smooth,
+    Bezier,
+    Catmull,
+}
+
+/// Mirrors rubidium's `PathKeyframe`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    pub time_ms: u64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    #[serde(default)]
+    pub roll: f32,
+    #[serde(default = "default_fov")]
+    pub fov: f32,
+    #[serde(default)]
+    pub focus_entity: Option<Uuid>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+fn default_fov() -> f32 {
+    70.0
+}
+
+/// Export format that rubidium deserializes directly as a `CameraPath`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraPathExport {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub keyframes: Vec<CameraKeyframe>,
+    pub duration_ms: u64,
+    pub loop_enabled: bool,
+    pub time_scale: f32,
+}
+
+impl CameraPathExport {
+    pub fn new(id: Uuid, name: String, owner_id: Uuid, keyframes: Vec<CameraKeyframe>, duration_seconds: f64) -> Self {
+        Self {
+            id,
+            name,
+            owner_id,
+            keyframes,
+            duration_ms: (duration_seconds * 1000.0).round() as u64,
+            loop_enabled: false,
+            time_scale: 1.0,
+        }
+    }
+}
+
+pub fn validate_path(name: &str, keyframes: &[CameraKeyframe], duration_seconds: f64) -> Result<(), String> {
+    if name.trim().is_empty() || name.len() > 64 {
+        return Err("Path name must be 1-64 characters".to_string());
+    }
+
+    if !duration_seconds.is_finite() || duration_seconds <= 0.0 || duration_seconds > MAX_DURATION_SECONDS {
+        return Err(format!("Duration must be between 0 and {} seconds", MAX_DURATION_SECONDS));
+    }
+
+    if keyframes.len() < MIN_KEYFRAMES || keyframes.len() > MAX_KEYFRAMES {
+        return Err(format!("A path needs {}-{} keyframes", MIN_KEYFRAMES, MAX_KEYFRAMES));
+    }
+
+    let duration_ms = (duration_seconds * 1000.0).round() as u64;
+    let mut previous: Option<u64> = None;
+
+    for (index, keyframe) in keyframes.iter().enumerate() {
+        let position_ok = [keyframe.x, keyframe.y, keyframe.z].iter().all(|v| v.is_finite());
+        let rotation_ok = [keyframe.yaw, keyframe.pitch, keyframe.roll].iter().all(|v| v.is_finite());
+        if !position_ok || !rotation_ok {
+            return Err(format!("Keyframe {} has an invalid position or rotation", index));
+        }
+
+        if !(-90.0..=90.0).contains(&keyframe.pitch) {
+            return Err(format!("Keyframe {} pitch must be between -90 and 90", index));
+        }
+
+        if !(keyframe.fov > 0.0 && keyframe.fov < 180.0) {
+            return Err(format!("Keyframe {} fov must be between 0 and 180", index));
+        }
+
+        if keyframe.time_ms > duration_ms {
+            return Err(format!("Keyframe {} is past the end of the path", index));
+        }
+
+        if previous.is_some_and(|prev| keyframe.time_ms <= prev) {
+            return Err(format!("Keyframe {} timestamp must be after the previous keyframe", index));
+        }
+        previous = Some(keyframe.time_ms);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same fixture rubidium's `CameraPath` is tested against.
+    const EXPORT_FIXTURE: &str = include_str!("../../rubidium/runtime/tests/fixtures/camera_path_export.json");
+
+    fn keyframe(time_ms: u64) -> CameraKeyframe {
+        serde_json::from_value(serde_json::json!({
+            "time_ms": time_ms, "x": 0.0, "y": 64.0, "z": 0.0, "yaw": 0.0, "pitch": 0.0
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_export_matches_rubidium_camera_path() {
+        let fixture: CameraPathExport = serde_json::from_str(EXPORT_FIXTURE).unwrap();
+        let export = CameraPathExport::new(
+            fixture.id,
+            fixture.name.clone(),
+            fixture.owner_id,
+            fixture.keyframes.clone(),
+            2.5,
+        );
+
+        assert!(validate_path(&export.name, &export.keyframes, 2.5).is_ok());
+        assert_eq!(
+            serde_json::to_value(&export).unwrap(),
+            serde_json::from_str::<serde_json::Value>(EXPORT_FIXTURE).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_keyframe_defaults() {
+        let keyframe = keyframe(0);
+        assert_eq!(keyframe.fov, 70.0);
+        assert_eq!(keyframe.interpolation, Interpolation::Smooth);
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_paths() {
+        assert!(validate_path("tour", &[keyframe(0)], 10.0).is_err());
+        assert!(validate_path("tour", &[keyframe(0), keyframe(11_000)], 10.0).is_err());
+        assert!(validate_path("tour", &[keyframe(500), keyframe(500)], 10.0).is_err());
+        assert!(validate_path("tour", &[keyframe(900), keyframe(100)], 10.0).is_err());
+        assert!(validate_path("", &[keyframe(0), keyframe(100)], 10.0).is_err());
+        assert!(validate_path("tour", &[keyframe(0), keyframe(100)], f64::NAN).is_err());
+
+        let mut tilted = keyframe(100);
+        tilted.pitch = 120.0;
+        assert!(validate_path("tour", &[keyframe(0), tilted], 10.0).is_err());
+
+        assert!(validate_path("tour", &[keyframe(0), keyframe(10_000)], 10.0).is_ok());
+    }
+}
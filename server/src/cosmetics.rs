@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+pub const VALID_SLOTS: &[&str] = &["skin", "emote_1", "emote_2", "emote_3", "emote_4", "cape", "wings", "aura"];
+
+/// Pairs of slots that cannot be equipped at the same time.
+pub const SLOT_CONFLICTS: &[(&str, &str)] = &[
+    ("wings", "cape"),
+];
+
+pub const FREE_LOADOUT_LIMIT: i64 = 3;
+pub const PREMIUM_LOADOUT_LIMIT: i64 = 20;
+
+pub fn is_valid_slot(slot: &str) -> bool {
+    VALID_SLOTS.contains(&slot)
+}
+
+pub fn conflicting_slots(slot: &str) -> Vec<&'static str> {
+    SLOT_CONFLICTS.iter()
+        .filter_map(|(a, b)| {
+            if *a == slot {
+                Some(*b)
+            } else if *b == slot {
+                Some(*a)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn loadout_limit(premium: bool) -> i64 {
+    if premium { PREMIUM_LOADOUT_LIMIT } else { FREE_LOADOUT_LIMIT }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadoutSlot {
+    pub slot: String,
+    pub item_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedSlot {
+    pub slot: String,
+    pub item_id: String,
+    pub reason: String,
+}
@@ -0,0 +1,435 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use futures_util::future::BoxFuture;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Longest an SMTP exchange may take from connecting to `QUIT`.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    /// `host:port` of the SMTP relay codes are sent through.
+    pub smtp_server: Option<String>,
+    pub from_address: String,
+    /// Marketplace listings need a verified email address.
+    pub require_verified_for_listings: bool,
+    pub code_ttl: Duration,
+    /// Wrong codes allowed before the code stops working.
+    pub max_attempts: i32,
+    pub resend_cooldown: Duration,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            smtp_server: None,
+            from_address: "noreply@yellowtale.local".to_string(),
+            require_verified_for_listings: false,
+            code_ttl: Duration::from_secs(15 * 60),
+            max_attempts: 5,
+            resend_cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+impl EmailConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            smtp_server: std::env::var("SMTP_SERVER").ok().filter(|v| !v.is_empty()),
+            from_address: std::env::var("MAIL_FROM").unwrap_or(defaults.from_address),
+            require_verified_for_listings: std::env::var("REQUIRE_VERIFIED_EMAIL_FOR_LISTINGS")
+                .is_ok_and(|v| v == "true" || v == "1"),
+            ..defaults
+        }
+    }
+
+    pub fn mailer(&self) -> Option<SmtpMailer> {
+        self.smtp_server.as_ref().map(|server| SmtpMailer::new(server.clone(), self.from_address.clone()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers outgoing mail. SMTP is the only transport for now.
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), String>>;
+}
+
+/// Hands messages to an SMTP relay, such as the host's MTA, in plain SMTP
+/// without authentication; encryption onward is the relay's job.
+pub struct SmtpMailer {
+    server: String,
+    from: String,
+}
+
+impl SmtpMailer {
+    pub fn new(server: String, from: String) -> Self {
+        Self { server, from }
+    }
+
+    async fn deliver(&self, email: &Email) -> Result<(), String> {
+        let stream = TcpStream::connect(&self.server).await.map_err(|e| e.to_string())?;
+        let mut stream = BufReader::new(stream);
+
+        expect_reply(&mut stream, "connect", 220).await?;
+        command(&mut stream, "EHLO localhost", 250).await?;
+        command(&mut stream, &format!("MAIL FROM:<{}>", self.from), 250).await?;
+        command(&mut stream, &format!("RCPT TO:<{}>", email.to), 250).await?;
+        command(&mut stream, "DATA", 354).await?;
+        stream.get_mut().write_all(message(&self.from, email).as_bytes()).await.map_err(|e| e.to_string())?;
+        expect_reply(&mut stream, "message", 250).await?;
+        let _ = command(&mut stream, "QUIT", 221).await;
+        Ok(())
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            check_address(&self.from)?;
+            check_address(&email.to)?;
+            tokio::time::timeout(SMTP_TIMEOUT, self.deliver(email))
+                .await
+                .map_err(|_| "Mail server timed out".to_string())?
+        })
+    }
+}
+
+/// Addresses go into SMTP commands and headers as they are.
+fn check_address(address: &str) -> Result<(), String> {
+    let valid = address.split_once('@').is_some_and(|(local, domain)| !local.is_empty() && !domain.is_empty())
+        && !address.chars().any(|c| c.is_control() || c.is_whitespace() || c == '<' || c == '>');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid address: {}", address.escape_debug()))
+    }
+}
+
+fn message(from: &str, email: &Email) -> String {
+    let subject: String = email.subject.chars().filter(|c| !c.is_control()).collect();
+    let mut message = format!(
+        "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        from,
+        email.to,
+        subject,
+        Utc::now().to_rfc2822(),
+    );
+    for line in email.body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    message
+}
+
+async fn command(stream: &mut BufReader<TcpStream>, line: &str, expected: u16) -> Result<(), String> {
+    stream.get_mut().write_all(format!("{}\r\n", line).as_bytes()).await.map_err(|e| e.to_string())?;
+    let name = line.split([' ', ':']).next().unwrap_or(line);
+    expect_reply(stream, name, expected).await
+}
+
+async fn expect_reply(stream: &mut BufReader<TcpStream>, command: &str, expected: u16) -> Result<(), String> {
+    let mut reply = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+            return Err("Mail server closed the connection".to_string());
+        }
+        reply.push_str(&line);
+        if line.as_bytes().get(3) != Some(&b'-') {
+            break;
+        }
+    }
+
+    let code: u16 = reply.get(..3).and_then(|code| code.parse().ok()).unwrap_or(0);
+    if code == expected || (expected == 250 && code == 251) {
+        Ok(())
+    } else {
+        Err(format!("Mail server rejected {}: {}", command, reply.trim_end()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    AlreadyVerified,
+    /// Seconds until another code may be sent.
+    Cooldown(u64),
+    InvalidCode,
+    Expired,
+    Exhausted,
+    NotConfigured,
+    Delivery(String),
+    Database(String),
+}
+
+impl VerificationError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::AlreadyVerified => "Email already verified".to_string(),
+            Self::Cooldown(secs) => format!("A code was sent recently; try again in {}s", secs),
+            Self::InvalidCode => "Invalid verification code".to_string(),
+            Self::Expired => "Verification code expired; request a new one".to_string(),
+            Self::Exhausted => "Too many wrong codes; request a new one".to_string(),
+            Self::NotConfigured => "Email delivery not configured".to_string(),
+            Self::Delivery(_) => "Could not send the verification email".to_string(),
+            Self::Database(_) => "Database error".to_string(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for VerificationError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CodeCheck {
+    Confirmed,
+    Wrong,
+    Expired,
+    Exhausted,
+}
+
+fn check_code(
+    config: &EmailConfig,
+    user_id: Uuid,
+    stored_hash: &str,
+    expires_at: DateTime<Utc>,
+    attempts: i32,
+    code: &str,
+    now: DateTime<Utc>,
+) -> CodeCheck {
+    if attempts >= config.max_attempts {
+        return CodeCheck::Exhausted;
+    }
+    if expires_at <= now {
+        return CodeCheck::Expired;
+    }
+    if hash_code(user_id, code.trim()) == stored_hash {
+        CodeCheck::Confirmed
+    } else {
+        CodeCheck::Wrong
+    }
+}
+
+/// Seconds, rounded up, until a code sent at `last_sent_at` can be replaced.
+fn resend_wait(config: &EmailConfig, last_sent_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<u64> {
+    let cooldown = ChronoDuration::from_std(config.resend_cooldown).ok()?;
+    let wait = (last_sent_at? + cooldown - now).num_milliseconds();
+    (wait > 0).then(|| (wait as u64).div_ceil(1000))
+}
+
+fn generate_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+fn hash_code(user_id: Uuid, code: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(user_id.as_bytes());
+    hasher.update(code.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Mails a fresh six-digit code to the user, replacing any earlier one, and
+/// returns when it expires.
+pub async fn request_verification(
+    db: &PgPool,
+    config: &EmailConfig,
+    mailer: Option<&dyn Mailer>,
+    user_id: Uuid,
+) -> Result<DateTime<Utc>, VerificationError> {
+    let (email, verified) = sqlx::query_as::<_, (String, bool)>("SELECT email, email_verified FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(db)
+        .await?;
+    if verified {
+        return Err(VerificationError::AlreadyVerified);
+    }
+    let mailer = mailer.ok_or(VerificationError::NotConfigured)?;
+
+    let now = Utc::now();
+    let last_sent_at = sqlx::query_scalar::<_, DateTime<Utc>>("SELECT sent_at FROM email_verifications WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+    if let Some(wait) = resend_wait(config, last_sent_at, now) {
+        return Err(VerificationError::Cooldown(wait));
+    }
+
+    let code = generate_code();
+    let expires_at = now + ChronoDuration::from_std(config.code_ttl).unwrap_or(ChronoDuration::minutes(15));
+    let cooldown = ChronoDuration::from_std(config.resend_cooldown).unwrap_or(ChronoDuration::zero());
+    // The WHERE repeats the cooldown so two requests can't both send
+    let stored = sqlx::query(
+        "INSERT INTO email_verifications (user_id, code_hash, sent_at, expires_at, attempts)
+         VALUES ($1, $2, $3, $4, 0)
+         ON CONFLICT (user_id) DO UPDATE
+            SET code_hash = EXCLUDED.code_hash, sent_at = EXCLUDED.sent_at, expires_at = EXCLUDED.expires_at, attempts = 0
+            WHERE email_verifications.sent_at <= $5"
+    )
+        .bind(user_id)
+        .bind(hash_code(user_id, &code))
+        .bind(now)
+        .bind(expires_at)
+        .bind(now - cooldown)
+        .execute(db)
+        .await?
+        .rows_affected();
+    if stored == 0 {
+        return Err(VerificationError::Cooldown(config.resend_cooldown.as_secs().max(1)));
+    }
+
+    let message = Email {
+        to: email,
+        subject: "Your Yellow Tale verification code".to_string(),
+        body: format!(
+            "Your verification code is {}.\n\nIt expires in {} minutes. If you didn't ask for it, you can ignore this email.",
+            code,
+            config.code_ttl.as_secs() / 60,
+        ),
+    };
+    if let Err(e) = mailer.send(&message).await {
+        // Nothing arrived, so don't hold the user to the cooldown
+        let _ = sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+            .bind(user_id)
+            .execute(db)
+            .await;
+        warn!("Verification email to user {} failed: {}", user_id, e);
+        return Err(VerificationError::Delivery(e));
+    }
+
+    info!("Sent email verification code to user {}", user_id);
+    Ok(expires_at)
+}
+
+/// Marks the user's email verified if `code` is the one last sent.
+pub async fn confirm(db: &PgPool, config: &EmailConfig, user_id: Uuid, code: &str) -> Result<(), VerificationError> {
+    let mut tx = db.begin().await?;
+    let (code_hash, expires_at, attempts) = sqlx::query_as::<_, (String, DateTime<Utc>, i32)>(
+        "SELECT code_hash, expires_at, attempts FROM email_verifications WHERE user_id = $1 FOR UPDATE"
+    )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(VerificationError::InvalidCode)?;
+
+    match check_code(config, user_id, &code_hash, expires_at, attempts, code, Utc::now()) {
+        CodeCheck::Confirmed => {
+            sqlx::query("UPDATE users SET email_verified = TRUE WHERE id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            info!("Email verified for user {}", user_id);
+            Ok(())
+        }
+        CodeCheck::Wrong => {
+            sqlx::query("UPDATE email_verifications SET attempts = attempts + 1 WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            Err(VerificationError::InvalidCode)
+        }
+        CodeCheck::Expired => Err(VerificationError::Expired),
+        CodeCheck::Exhausted => Err(VerificationError::Exhausted),
+    }
+}
+
+pub async fn is_verified(db: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT email_verified FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_expire_and_wrong_guesses_use_them_up() {
+        let config = EmailConfig::default();
+        let user = Uuid::new_v4();
+        let now = Utc::now();
+        let hash = hash_code(user, "042917");
+        let expires_at = now + ChronoDuration::minutes(15);
+
+        assert_eq!(check_code(&config, user, &hash, expires_at, 0, "042917", now), CodeCheck::Confirmed);
+        assert_eq!(check_code(&config, user, &hash, expires_at, 0, "000000", now), CodeCheck::Wrong);
+        assert_eq!(check_code(&config, Uuid::new_v4(), &hash, expires_at, 0, "042917", now), CodeCheck::Wrong);
+        assert_eq!(check_code(&config, user, &hash, now, 0, "042917", now), CodeCheck::Expired);
+        assert_eq!(check_code(&config, user, &hash, expires_at, config.max_attempts, "042917", now), CodeCheck::Exhausted);
+    }
+
+    #[test]
+    fn resends_wait_out_the_cooldown() {
+        let config = EmailConfig::default();
+        let now = Utc::now();
+
+        assert_eq!(resend_wait(&config, None, now), None);
+        assert_eq!(resend_wait(&config, Some(now - ChronoDuration::milliseconds(20_500)), now), Some(40));
+        assert_eq!(resend_wait(&config, Some(now - ChronoDuration::seconds(60)), now), None);
+        assert_eq!(generate_code().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn smtp_mailer_stuffs_dots_and_refuses_injected_addresses() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mailer = SmtpMailer::new(listener.local_addr().unwrap().to_string(), "noreply@example.com".to_string());
+
+        let relay = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            stream.get_mut().write_all(b"220 ready\r\n").await.unwrap();
+            let mut transcript = String::new();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                transcript.push_str(&line);
+                let reply: &[u8] = match line.as_str() {
+                    ".\r\n" if in_data => { in_data = false; b"250 queued\r\n" }
+                    _ if in_data => continue,
+                    "DATA\r\n" => { in_data = true; b"354 go ahead\r\n" }
+                    "QUIT\r\n" => b"221 bye\r\n",
+                    _ => b"250 ok\r\n",
+                };
+                stream.get_mut().write_all(reply).await.unwrap();
+            }
+            transcript
+        });
+
+        let injected = Email { to: "a@example.com>\r\nRCPT TO:<b@example.com".to_string(), subject: String::new(), body: String::new() };
+        assert!(mailer.send(&injected).await.is_err());
+
+        let email = Email { to: "anna@example.com".to_string(), subject: "Code".to_string(), body: "123456\n.".to_string() };
+        mailer.send(&email).await.unwrap();
+        let transcript = relay.await.unwrap();
+        assert!(transcript.contains("RCPT TO:<anna@example.com>\r\n"));
+        assert!(transcript.contains("\r\n123456\r\n..\r\n.\r\n"));
+    }
+}
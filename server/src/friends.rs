@@ -1,18 +1,183 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
 use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{info, warn};
 use uuid::Uuid;
 
-#[allow(dead_code)]
+/// Longest message a friend request may carry, in characters.
+pub const MAX_REQUEST_MESSAGE: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct RequestConfig {
+    /// How long a request waits for an answer before it expires.
+    pub expiry: Duration,
+    pub sweep_interval: Duration,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self {
+            expiry: Duration::from_secs(30 * 24 * 60 * 60),
+            sweep_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl RequestConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            expiry: std::env::var("FRIEND_REQUEST_EXPIRY_DAYS").ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|days| *days > 0)
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+                .unwrap_or(defaults.expiry),
+            sweep_interval: defaults.sweep_interval,
+        }
+    }
+
+    pub fn expires_at(&self, sent_at: DateTime<Utc>) -> DateTime<Utc> {
+        sent_at + ChronoDuration::from_std(self.expiry).unwrap_or(ChronoDuration::days(30))
+    }
+}
+
+/// Trims a request message; blank means none. `None` if it is too long.
+pub fn request_message(message: Option<&str>) -> Option<Option<String>> {
+    let Some(message) = message.map(str::trim).filter(|m| !m.is_empty()) else {
+        return Some(None);
+    };
+    (message.chars().count() <= MAX_REQUEST_MESSAGE).then(|| Some(message.to_string()))
+}
+
+/// Requests from before expiry was added have no `expires_at` and stay
+/// until answered.
+pub fn is_expired(expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= now)
+}
+
+/// Deletes pending requests nobody answered in time.
+pub async fn purge_expired_requests(db: &PgPool, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM friendships WHERE status = 'pending' AND expires_at <= $1")
+        .bind(now)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub fn spawn_request_expiry(db: PgPool, config: RequestConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.sweep_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match purge_expired_requests(&db, Utc::now()).await {
+                Ok(purged) if purged > 0 => info!("Purged {} expired friend requests", purged),
+                Ok(_) => {}
+                Err(e) => warn!("Friend request expiry sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub blocked_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
 pub struct FriendsService {
     db: PgPool,
 }
 
-#[allow(dead_code)]
 impl FriendsService {
     pub fn new(db: PgPool) -> Self {
         Self { db }
     }
-    
+
+    #[allow(dead_code)]
     pub async fn get_friends(&self, _user_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
         Ok(vec![])
     }
+
+    /// Ends any friendship or pending request between the two and records
+    /// the block. False if `blocker` had already blocked `blocked`.
+    pub async fn block_user(&self, blocker: Uuid, blocked: Uuid, reason: Option<&str>) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query(
+            "DELETE FROM friendships WHERE (user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1)"
+        )
+            .bind(blocker)
+            .bind(blocked)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query(
+            "INSERT INTO blocks (id, blocker_id, blocked_id, reason, created_at) VALUES ($1, $2, $3, $4, NOW())
+             ON CONFLICT (blocker_id, blocked_id) DO NOTHING"
+        )
+            .bind(Uuid::new_v4())
+            .bind(blocker)
+            .bind(blocked)
+            .bind(reason)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// False if `blocker` had not blocked `blocked`
+    pub async fn unblock_user(&self, blocker: Uuid, blocked: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM blocks WHERE blocker_id = $1 AND blocked_id = $2")
+            .bind(blocker)
+            .bind(blocked)
+            .execute(&self.db)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Users `user_id` has blocked, newest first. Blocks placed on them by
+    /// others are never listed.
+    pub async fn blocked_users(&self, user_id: Uuid) -> Result<Vec<BlockedUser>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (Uuid, String, DateTime<Utc>, Option<String>)>(
+            "SELECT u.id, u.username, b.created_at, b.reason FROM blocks b
+             JOIN users u ON u.id = b.blocked_id
+             WHERE b.blocker_id = $1
+             ORDER BY b.created_at DESC"
+        )
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await?;
+        Ok(rows.into_iter()
+            .map(|(user_id, username, blocked_at, reason)| BlockedUser { user_id, username, blocked_at, reason })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_messages_are_trimmed_and_capped() {
+        assert_eq!(request_message(None), Some(None));
+        assert_eq!(request_message(Some("  ")), Some(None));
+        assert_eq!(request_message(Some(" hey it's Alex from the Orbis server ")), Some(Some("hey it's Alex from the Orbis server".to_string())));
+        assert!(request_message(Some(&"é".repeat(MAX_REQUEST_MESSAGE))).is_some());
+        assert_eq!(request_message(Some(&"a".repeat(MAX_REQUEST_MESSAGE + 1))), None);
+    }
+
+    #[test]
+    fn requests_expire_after_the_configured_days() {
+        let config = RequestConfig::default();
+        let sent_at = Utc::now();
+        let expires_at = config.expires_at(sent_at);
+
+        assert_eq!(expires_at - sent_at, ChronoDuration::days(30));
+        assert!(!is_expired(Some(expires_at), expires_at - ChronoDuration::seconds(1)));
+        assert!(is_expired(Some(expires_at), expires_at));
+        assert!(!is_expired(None, expires_at + ChronoDuration::days(365)));
+    }
 }
@@ -1,7 +1,9 @@
 use axum::{
+    body::Bytes,
     extract::{Path, State, WebSocketUpgrade, ws::{Message, WebSocket}},
-    http::{StatusCode, Method, header},
-    response::{IntoResponse, Json},
+    http::{HeaderMap, StatusCode, Method, header},
+    middleware,
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
@@ -10,28 +12,62 @@ use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::{net::SocketAddr, sync::Arc};
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
-use tracing::{info, error};
+use tracing::{info, warn, error};
 use uuid::Uuid;
 use sha2::Digest;
 
+mod account;
+mod achievements;
 mod admin;
 mod auth;
+mod billing;
+mod cinema;
+mod cosmetics;
+mod email;
 mod escrow;
 mod features;
 mod friends;
+mod moderation;
+mod notifications;
+mod ownership;
+mod pagination;
+mod party;
+mod payouts;
+mod play_stats;
+mod rate_limit;
 mod relay;
+mod releases;
+mod server_directory;
+mod server_metrics;
+mod server_owners;
+mod sessions;
+mod social_guard;
 mod stripe;
+mod user_search;
 mod verification;
 
 use auth::{hash_password, verify_password, generate_token, hash_token};
-use relay::RelayHub;
+use rate_limit::{AuthRateLimiter, ClientIp, RateLimitConfig};
+use notifications::{NewNotification, NotificationHub};
+use party::PartyHub;
+use relay::{RelayHub, RelayLimits};
 use verification::{VerificationService, VerificationMethod};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
     pub relay: Arc<RwLock<RelayHub>>,
+    pub parties: Arc<PartyHub>,
+    pub notifications: Arc<NotificationHub>,
     pub verification: Arc<VerificationService>,
+    pub auth_limiter: Arc<AuthRateLimiter>,
+    pub account_deletion: account::DeletionConfig,
+    pub payouts: payouts::PayoutConfig,
+    pub payout_transfer: Arc<dyn payouts::PayoutTransfer>,
+    pub presence: Arc<ownership::PresenceHints>,
+    pub email: email::EmailConfig,
+    pub mailer: Option<Arc<dyn email::Mailer>>,
+    pub friend_requests: friends::RequestConfig,
 }
 
 #[derive(Debug, Serialize)]
@@ -49,6 +85,10 @@ impl<T: Serialize> ApiResponse<T> {
     fn error(msg: impl Into<String>) -> Json<Self> {
         Json(Self { success: false, data: None, error: Some(msg.into()) })
     }
+    
+    fn error_with_data(msg: impl Into<String>, data: T) -> Json<Self> {
+        Json(Self { success: false, data: Some(data), error: Some(msg.into()) })
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -85,10 +125,46 @@ struct TokenRequest {
     token: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct ChangePasswordRequest {
+    token: String,
+    old_password: String,
+    new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevokeSessionRequest {
+    token: String,
+    /// Without one, every session but the caller's is signed out
+    session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfirmEmailRequest {
+    token: String,
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteAccountRequest {
+    token: String,
+    password: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct FriendRequest {
     token: String,
     target_user_id: Uuid,
+    /// Note shown to the recipient; only read when sending
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockRequest {
+    token: String,
+    user_id: Uuid,
+    reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -96,6 +172,8 @@ struct ProfileUpdateRequest {
     token: String,
     display_name: Option<String>,
     avatar_url: Option<String>,
+    /// Leaves the user out of public leaderboards; personal stats are kept
+    hide_from_leaderboards: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -129,11 +207,44 @@ struct RegisterServerRequest {
 
 #[derive(Debug, Deserialize)]
 struct ServerHeartbeatRequest {
+    /// A scoped `srv_` server token; the owner's session token is refused.
     token: String,
     server_id: Uuid,
     current_players: i32,
 }
 
+#[derive(Debug, Deserialize)]
+struct DeregisterServerRequest {
+    /// A scoped `srv_` server token for the listing
+    token: String,
+    server_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateServerRequest {
+    token: String,
+    #[serde(flatten)]
+    changes: server_owners::ServerUpdate,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferServerRequest {
+    token: String,
+    to_user_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueServerTokenRequest {
+    token: String,
+    label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevokeServerTokenRequest {
+    token: String,
+    token_id: Uuid,
+}
+
 #[derive(Debug, Serialize)]
 struct GameStats {
     user_id: Uuid,
@@ -149,6 +260,15 @@ struct RecordSessionRequest {
     token: String,
     duration_minutes: i32,
     server_name: Option<String>,
+    server_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    metric: Option<String>,
+    period: Option<String>,
+    page: Option<i64>,
+    limit: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -160,6 +280,7 @@ struct ModProfile {
     mods: serde_json::Value,
     is_active: bool,
     created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -170,6 +291,18 @@ struct CreateModProfileRequest {
     mods: serde_json::Value,
 }
 
+/// A profile written by a launcher's profile sync, keeping the launcher's id
+/// and edit time so other devices can merge it
+#[derive(Debug, Deserialize)]
+struct UpsertModProfileRequest {
+    token: String,
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    mods: serde_json::Value,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ActivateModProfileRequest {
     token: String,
@@ -239,6 +372,7 @@ struct MarketplaceItem {
     thumbnail_url: Option<String>,
     file_url: Option<String>,
     is_featured: bool,
+    status: String,
     created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -257,6 +391,8 @@ struct CreateMarketplaceItemRequest {
     category: String,
     price: f64,
     tags: Vec<String>,
+    #[serde(default)]
+    file_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -265,10 +401,27 @@ struct MarketplaceQueryParams {
     price: Option<String>,
     sort: Option<String>,
     q: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+    /// Continues a `downloads` sort after the last page's `next_cursor`
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerListParams {
+    game_mode: Option<String>,
+    /// Comma separated; servers must carry all of them
+    tags: Option<String>,
+    has_slots: Option<bool>,
+    q: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
 }
 
 async fn signup(
     State(state): State<AppState>,
+    client_ip: Option<axum::Extension<ClientIp>>,
+    headers: HeaderMap,
     Json(req): Json<SignupRequest>,
 ) -> impl IntoResponse {
     if req.username.len() < 3 || req.username.len() > 32 {
@@ -291,6 +444,10 @@ async fn signup(
         }
     }
     
+    if let Ok(true) = account::is_identity_reserved(&state.db, &req.username, &req.email).await {
+        return (StatusCode::CONFLICT, ApiResponse::error("Username or email already exists"));
+    }
+    
     let password_hash = hash_password(&req.password);
     let user_id = Uuid::new_v4();
     let now = chrono::Utc::now();
@@ -314,17 +471,9 @@ async fn signup(
     let token = generate_token();
     let token_hash = hash_token(&token);
     let expires = now + chrono::Duration::days(30);
-    
-    let _ = sqlx::query(
-        "INSERT INTO user_sessions (id, user_id, token_hash, expires_at, created_at) VALUES ($1, $2, $3, $4, $5)"
-    )
-        .bind(Uuid::new_v4())
-        .bind(user_id)
-        .bind(&token_hash)
-        .bind(expires)
-        .bind(now)
-        .execute(&state.db)
-        .await;
+    let device = sessions::device_info(headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok()));
+    let ip = client_ip.map(|axum::Extension(ClientIp(ip))| ip);
+    let _ = sessions::create(&state.db, user_id, &token_hash, expires, device.as_deref(), ip.as_deref()).await;
     
     let user = User {
         id: user_id,
@@ -340,39 +489,83 @@ async fn signup(
 
 async fn login(
     State(state): State<AppState>,
+    client_ip: Option<axum::Extension<ClientIp>>,
+    headers: HeaderMap,
     Json(req): Json<LoginRequest>,
-) -> impl IntoResponse {
-    let row = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Option<String>, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, username, password_hash, display_name, avatar_url, created_at FROM users WHERE username = $1"
+) -> Response {
+    let limits = &state.auth_limiter.config;
+    match rate_limit::check_account_lockout(&state.db, limits, &req.username).await {
+        Ok(Some(retry_after)) => {
+            return rate_limit::too_many_requests(retry_after, "Too many failed login attempts, account temporarily locked");
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Could not check account lockout for {}, refusing the attempt: {}", req.username, e);
+            return (StatusCode::SERVICE_UNAVAILABLE, ApiResponse::<AuthResponse>::error("Login is temporarily unavailable")).into_response();
+        }
+    }
+    let ip = client_ip.map(|axum::Extension(ClientIp(ip))| ip);
+    if let Some(ip) = ip.as_deref().filter(|ip| *ip != "unknown") {
+        match rate_limit::check_source_lockout(&state.db, limits, ip).await {
+            Ok(Some(retry_after)) => {
+                return rate_limit::too_many_requests(retry_after, "Too many failed login attempts from this address, try again later");
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Could not check login lockout for {}, refusing the attempt: {}", ip, e);
+                return (StatusCode::SERVICE_UNAVAILABLE, ApiResponse::<AuthResponse>::error("Login is temporarily unavailable")).into_response();
+            }
+        }
+    }
+    
+    // Accounts pending deletion are found by the username they had before it
+    let row = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Option<String>, chrono::DateTime<chrono::Utc>, bool)>(
+        "SELECT u.id, COALESCE(d.original_username, u.username), u.password_hash, u.display_name, u.avatar_url, u.created_at, u.deleted_at IS NOT NULL
+         FROM users u
+         LEFT JOIN account_deletions d ON d.user_id = u.id AND d.finalized_at IS NULL
+         WHERE (u.username = $1 AND u.deleted_at IS NULL) OR d.original_username = $1"
     )
         .bind(&req.username)
         .fetch_optional(&state.db)
         .await;
     
-    let (user_id, username, password_hash, display_name, avatar_url, created_at) = match row {
+    let (user_id, username, password_hash, display_name, avatar_url, created_at, pending_deletion) = match row {
         Ok(Some(r)) => r,
-        _ => return (StatusCode::UNAUTHORIZED, ApiResponse::<AuthResponse>::error("Invalid credentials")),
+        Ok(None) => {
+            // Still counts toward the address's limit, for guesses across usernames
+            rate_limit::record_failed_login(&state.db, limits, &req.username, ip.as_deref().unwrap_or_default()).await;
+            return (StatusCode::UNAUTHORIZED, ApiResponse::<AuthResponse>::error("Invalid credentials")).into_response();
+        }
+        Err(_) => return (StatusCode::UNAUTHORIZED, ApiResponse::<AuthResponse>::error("Invalid credentials")).into_response(),
     };
     
     if !verify_password(&req.password, &password_hash) {
-        return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid credentials"));
+        rate_limit::record_failed_login(&state.db, limits, &username, ip.as_deref().unwrap_or_default()).await;
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<AuthResponse>::error("Invalid credentials")).into_response();
+    }
+    
+    rate_limit::clear_failed_logins(&state.db, &username).await;
+    
+    if pending_deletion {
+        match account::reactivate(&state.db, user_id, chrono::Utc::now()).await {
+            Ok(true) => info!("Reactivated account {} pending deletion", user_id),
+            Ok(false) => return (StatusCode::UNAUTHORIZED, ApiResponse::<AuthResponse>::error("Invalid credentials")).into_response(),
+            Err(account::ReactivateError::IdentityTaken) => {
+                return (StatusCode::CONFLICT, ApiResponse::<AuthResponse>::error("Username or email is now used by another account")).into_response();
+            }
+            Err(account::ReactivateError::Database(e)) => {
+                error!("Failed to reactivate account {}: {}", user_id, e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<AuthResponse>::error("Failed to reactivate account")).into_response();
+            }
+        }
     }
     
     let token = generate_token();
     let token_hash = hash_token(&token);
     let now = chrono::Utc::now();
     let expires = now + chrono::Duration::days(30);
-    
-    let _ = sqlx::query(
-        "INSERT INTO user_sessions (id, user_id, token_hash, expires_at, created_at) VALUES ($1, $2, $3, $4, $5)"
-    )
-        .bind(Uuid::new_v4())
-        .bind(user_id)
-        .bind(&token_hash)
-        .bind(expires)
-        .bind(now)
-        .execute(&state.db)
-        .await;
+    let device = sessions::device_info(headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok()));
+    let _ = sessions::create(&state.db, user_id, &token_hash, expires, device.as_deref(), ip.as_deref()).await;
     
     let _ = sqlx::query("UPDATE users SET last_seen = $1 WHERE id = $2")
         .bind(now)
@@ -381,8 +574,10 @@ async fn login(
         .await;
     
     let user = User { id: user_id, username, display_name, avatar_url, premium: false, created_at };
+    // Friends' launchers learn of it without polling
+    let _ = broadcast_presence(&state, user_id, "online", None, None, now).await;
     
-    (StatusCode::OK, ApiResponse::success(AuthResponse { user, token }))
+    (StatusCode::OK, ApiResponse::success(AuthResponse { user, token })).into_response()
 }
 
 async fn logout(
@@ -390,14 +585,231 @@ async fn logout(
     Json(req): Json<TokenRequest>,
 ) -> impl IntoResponse {
     let token_hash = hash_token(&req.token);
-    let _ = sqlx::query("DELETE FROM user_sessions WHERE token_hash = $1")
+    let signed_out = sqlx::query_scalar::<_, Uuid>("DELETE FROM user_sessions WHERE token_hash = $1 RETURNING user_id")
         .bind(&token_hash)
-        .execute(&state.db)
+        .fetch_optional(&state.db)
         .await;
+    if let Ok(Some(user_id)) = signed_out {
+        let _ = broadcast_presence(&state, user_id, "offline", None, None, chrono::Utc::now()).await;
+    }
     
     (StatusCode::OK, ApiResponse::success(serde_json::json!({"logged_out": true})))
 }
 
+/// Wrong old passwords count toward the same per-account lockout as failed
+/// logins. The hash is always verified, so only the answer tells them apart.
+async fn change_password(
+    State(state): State<AppState>,
+    client_ip: Option<axum::Extension<ClientIp>>,
+    Json(req): Json<ChangePasswordRequest>,
+) -> Response {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")).into_response(),
+    };
+    
+    if req.new_password.len() < 8 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error("Password must be at least 8 characters")).into_response();
+    }
+    
+    let limits = &state.auth_limiter.config;
+    match rate_limit::check_account_lockout(&state.db, limits, &user.username).await {
+        Ok(Some(retry_after)) => {
+            return rate_limit::too_many_requests(retry_after, "Too many failed password attempts, account temporarily locked");
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Could not check account lockout for {}, refusing the password change: {}", user.username, e);
+            return (StatusCode::SERVICE_UNAVAILABLE, ApiResponse::<serde_json::Value>::error("Password change is temporarily unavailable")).into_response();
+        }
+    }
+    
+    let password_hash = sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if !verify_password(&req.old_password, &password_hash) {
+        let ip = client_ip.map(|axum::Extension(ClientIp(ip))| ip).unwrap_or_default();
+        rate_limit::record_failed_login(&state.db, limits, &user.username, &ip).await;
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Password is incorrect")).into_response();
+    }
+    rate_limit::clear_failed_logins(&state.db, &user.username).await;
+    
+    let new_hash = hash_password(&req.new_password);
+    let result: Result<u64, sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(&new_hash)
+            .bind(user.id)
+            .execute(&mut *tx)
+            .await?;
+        let revoked = sessions::revoke_others(&mut *tx, user.id, &hash_token(&req.token)).await?;
+        tx.commit().await?;
+        Ok(revoked)
+    }.await;
+    
+    match result {
+        Ok(revoked) => {
+            info!("Password changed for {}; {} other sessions signed out", user.id, revoked);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({"changed": true, "sessions_revoked": revoked}))).into_response()
+        }
+        Err(e) => {
+            error!("Failed to change password for {}: {}", user.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::<serde_json::Value>::error("Failed to change password")).into_response()
+        }
+    }
+}
+
+async fn list_sessions(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<sessions::SessionInfo>>::error("Invalid token")),
+    };
+    
+    match sessions::list(&state.db, user.id, &hash_token(&req.token)).await {
+        Ok(list) => (StatusCode::OK, ApiResponse::success(list)),
+        Err(e) => {
+            error!("Failed to list sessions for {}: {}", user.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to list sessions"))
+        }
+    }
+}
+
+async fn revoke_session(
+    State(state): State<AppState>,
+    Json(req): Json<RevokeSessionRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    let revoked = match req.session_id {
+        Some(session_id) => match sessions::revoke(&state.db, user.id, session_id).await {
+            Ok(true) => Ok(1),
+            Ok(false) => return (StatusCode::NOT_FOUND, ApiResponse::error("Session not found")),
+            Err(e) => Err(e),
+        },
+        None => sessions::revoke_others(&state.db, user.id, &hash_token(&req.token)).await,
+    };
+    
+    match revoked {
+        Ok(revoked) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"sessions_revoked": revoked}))),
+        Err(e) => {
+            error!("Failed to revoke sessions for {}: {}", user.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to revoke sessions"))
+        }
+    }
+}
+
+async fn request_email_verification(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> Response {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")).into_response(),
+    };
+    
+    match email::request_verification(&state.db, &state.email, state.mailer.as_deref(), user.id).await {
+        Ok(expires_at) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"sent": true, "expires_at": expires_at}))).into_response(),
+        Err(e) => email_error(user.id, e),
+    }
+}
+
+async fn confirm_email(
+    State(state): State<AppState>,
+    Json(req): Json<ConfirmEmailRequest>,
+) -> Response {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")).into_response(),
+    };
+    
+    match email::confirm(&state.db, &state.email, user.id, &req.code).await {
+        Ok(()) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"email_verified": true}))).into_response(),
+        Err(e) => email_error(user.id, e),
+    }
+}
+
+fn email_error(user_id: Uuid, e: email::VerificationError) -> Response {
+    use email::VerificationError;
+    let status = match &e {
+        VerificationError::Cooldown(secs) => {
+            return rate_limit::too_many_requests(std::time::Duration::from_secs(*secs), &e.message());
+        }
+        VerificationError::AlreadyVerified => StatusCode::CONFLICT,
+        VerificationError::InvalidCode | VerificationError::Expired | VerificationError::Exhausted => StatusCode::BAD_REQUEST,
+        VerificationError::NotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+        VerificationError::Delivery(_) => StatusCode::BAD_GATEWAY,
+        VerificationError::Database(reason) => {
+            error!("Email verification failed for {}: {}", user_id, reason);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (status, ApiResponse::<serde_json::Value>::error(e.message())).into_response()
+}
+
+async fn delete_account(
+    State(state): State<AppState>,
+    Json(req): Json<DeleteAccountRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    let password_hash = sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten();
+    if !password_hash.is_some_and(|hash| verify_password(&req.password, &hash)) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("Password is incorrect"));
+    }
+    
+    let purge_after = match account::request_deletion(&state.db, &state.account_deletion, user.id, chrono::Utc::now()).await {
+        Ok(purge_after) => purge_after,
+        Err(e) => {
+            error!("Failed to delete account {}: {}", user.id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to delete account"));
+        }
+    };
+    state.parties.leave(user.id);
+    info!("Account {} scheduled for deletion after {}", user.id, purge_after);
+    
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({
+        "deleted": true,
+        "purge_after": purge_after,
+        "message": "Log in before the purge date to reactivate your account"
+    })))
+}
+
+async fn export_data(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    match account::export(&state.db, user.id).await {
+        Ok(bundle) => (StatusCode::OK, ApiResponse::success(bundle)),
+        Err(e) => {
+            error!("Failed to export data for {}: {}", user.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to export data"))
+        }
+    }
+}
+
 async fn get_me(
     State(state): State<AppState>,
     Json(req): Json<TokenRequest>,
@@ -420,12 +832,14 @@ async fn update_profile(
     };
     
     let result = sqlx::query(
-        "UPDATE users SET display_name = COALESCE($1, display_name), avatar_url = COALESCE($2, avatar_url), updated_at = $3 WHERE id = $4"
+        "UPDATE users SET display_name = COALESCE($1, display_name), avatar_url = COALESCE($2, avatar_url),
+            hide_from_leaderboards = COALESCE($5, hide_from_leaderboards), updated_at = $3 WHERE id = $4"
     )
         .bind(&req.display_name)
         .bind(&req.avatar_url)
         .bind(chrono::Utc::now())
         .bind(user.id)
+        .bind(req.hide_from_leaderboards)
         .execute(&state.db)
         .await;
     
@@ -444,20 +858,19 @@ async fn update_profile(
 
 async fn validate_token(db: &PgPool, token: &str) -> Option<User> {
     let token_hash = hash_token(token);
-    let row = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<String>, chrono::DateTime<chrono::Utc>)>(
+    let (id, username, display_name, avatar_url, created_at) = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<String>, chrono::DateTime<chrono::Utc>)>(
         "SELECT u.id, u.username, u.display_name, u.avatar_url, u.created_at 
          FROM users u 
          JOIN user_sessions s ON u.id = s.user_id 
-         WHERE s.token_hash = $1 AND s.expires_at > NOW()"
+         WHERE s.token_hash = $1 AND s.expires_at > NOW() AND u.deleted_at IS NULL"
     )
         .bind(&token_hash)
         .fetch_optional(db)
         .await
-        .ok()?;
+        .ok()??;
     
-    row.map(|(id, username, display_name, avatar_url, created_at)| {
-        User { id, username, display_name, avatar_url, premium: false, created_at }
-    })
+    sessions::touch(db, &token_hash).await;
+    Some(User { id, username, display_name, avatar_url, premium: false, created_at })
 }
 
 async fn send_friend_request(
@@ -473,9 +886,24 @@ async fn send_friend_request(
     if user.id == req.target_user_id {
         return (StatusCode::BAD_REQUEST, ApiResponse::error("Cannot friend yourself"));
     }
+    let Some(message) = friends::request_message(req.message.as_deref()) else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(format!("Message must be at most {} characters", friends::MAX_REQUEST_MESSAGE)));
+    };
+    
+    // A block answers exactly like a missing user so neither side learns of it
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(req.target_user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(false);
+    if !exists || !social_guard::allows(&state.db, user.id, req.target_user_id).await {
+        return (StatusCode::NOT_FOUND, ApiResponse::error(social_guard::HIDDEN));
+    }
     
     let existing = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM friendships WHERE (user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1)"
+        "SELECT COUNT(*) FROM friendships
+         WHERE ((user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1))
+         AND NOT (status = 'pending' AND expires_at <= NOW())"
     )
         .bind(user.id)
         .bind(req.target_user_id)
@@ -487,18 +915,40 @@ async fn send_friend_request(
         return (StatusCode::CONFLICT, ApiResponse::error("Friendship already exists or pending"));
     }
     
-    let result = sqlx::query(
-        "INSERT INTO friendships (id, user_id, friend_id, status, created_at) VALUES ($1, $2, $3, 'pending', $4)"
-    )
-        .bind(Uuid::new_v4())
-        .bind(user.id)
-        .bind(req.target_user_id)
-        .bind(chrono::Utc::now())
-        .execute(&state.db)
-        .await;
+    // An expired request between the two is replaced by this one
+    let now = chrono::Utc::now();
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = state.db.begin().await?;
+        sqlx::query(
+            "DELETE FROM friendships
+             WHERE ((user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1))
+             AND status = 'pending' AND expires_at <= NOW()"
+        )
+            .bind(user.id)
+            .bind(req.target_user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO friendships (id, user_id, friend_id, status, created_at, message, expires_at)
+             VALUES ($1, $2, $3, 'pending', $4, $5, $6)"
+        )
+            .bind(Uuid::new_v4())
+            .bind(user.id)
+            .bind(req.target_user_id)
+            .bind(now)
+            .bind(&message)
+            .bind(state.friend_requests.expires_at(now))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }.await;
     
     match result {
-        Ok(_) => (StatusCode::CREATED, ApiResponse::success(serde_json::json!({"sent": true}))),
+        Ok(_) => {
+            let notification = NewNotification::friend_request(user.id, &user.username, req.target_user_id);
+            notifications::send_logged(&state.db, &state.notifications, notification).await;
+            (StatusCode::CREATED, ApiResponse::success(serde_json::json!({"sent": true})))
+        }
         Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to send request")),
     }
 }
@@ -514,7 +964,8 @@ async fn accept_friend_request(
     };
     
     let result = sqlx::query(
-        "UPDATE friendships SET status = 'accepted', accepted_at = $1 WHERE user_id = $2 AND friend_id = $3 AND status = 'pending'"
+        "UPDATE friendships SET status = 'accepted', accepted_at = $1, expires_at = NULL
+         WHERE user_id = $2 AND friend_id = $3 AND status = 'pending' AND (expires_at IS NULL OR expires_at > $1)"
     )
         .bind(chrono::Utc::now())
         .bind(req.target_user_id)
@@ -585,6 +1036,9 @@ async fn get_friends(
     (StatusCode::OK, ApiResponse::success(serde_json::json!({"friends": friends})))
 }
 
+/// id, username, display_name, message, expires_at of the other side of a request
+type FriendRequestRow = (Uuid, String, Option<String>, Option<String>, Option<chrono::DateTime<chrono::Utc>>);
+
 async fn get_pending_requests(
     State(state): State<AppState>,
     Json(req): Json<TokenRequest>,
@@ -595,8 +1049,8 @@ async fn get_pending_requests(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
     
-    let incoming = sqlx::query_as::<_, (Uuid, String, Option<String>)>(
-        "SELECT u.id, u.username, u.display_name FROM users u
+    let incoming = sqlx::query_as::<_, FriendRequestRow>(
+        "SELECT u.id, u.username, u.display_name, f.message, f.expires_at FROM users u
          JOIN friendships f ON f.user_id = u.id
          WHERE f.friend_id = $1 AND f.status = 'pending'"
     )
@@ -605,8 +1059,8 @@ async fn get_pending_requests(
         .await
         .unwrap_or_default();
     
-    let outgoing = sqlx::query_as::<_, (Uuid, String, Option<String>)>(
-        "SELECT u.id, u.username, u.display_name FROM users u
+    let outgoing = sqlx::query_as::<_, FriendRequestRow>(
+        "SELECT u.id, u.username, u.display_name, f.message, f.expires_at FROM users u
          JOIN friendships f ON f.friend_id = u.id
          WHERE f.user_id = $1 AND f.status = 'pending'"
     )
@@ -615,38 +1069,100 @@ async fn get_pending_requests(
         .await
         .unwrap_or_default();
     
+    let now = chrono::Utc::now();
+    let live = |requests: Vec<FriendRequestRow>| {
+        requests.into_iter()
+            .filter(|(.., expires_at)| !friends::is_expired(*expires_at, now))
+            .map(|(id, username, display_name, message, expires_at)| serde_json::json!({
+                "id": id, "username": username, "display_name": display_name, "message": message, "expires_at": expires_at
+            }))
+            .collect::<Vec<_>>()
+    };
+    
     (StatusCode::OK, ApiResponse::success(serde_json::json!({
-        "incoming": incoming.iter().map(|(id, username, display_name)| serde_json::json!({
-            "id": id, "username": username, "display_name": display_name
-        })).collect::<Vec<_>>(),
-        "outgoing": outgoing.iter().map(|(id, username, display_name)| serde_json::json!({
-            "id": id, "username": username, "display_name": display_name
-        })).collect::<Vec<_>>()
+        "incoming": live(incoming),
+        "outgoing": live(outgoing)
     })))
 }
 
+#[derive(Debug, Deserialize)]
+struct UserSearchParams {
+    token: Option<String>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
 async fn search_users(
     State(state): State<AppState>,
     Path(query): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<UserSearchParams>,
 ) -> impl IntoResponse {
-    let users = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<String>)>(
-        "SELECT id, username, display_name, avatar_url FROM users WHERE username ILIKE $1 LIMIT 20"
+    let requester = match &params.token {
+        Some(token) => match validate_token(&state.db, token).await {
+            Some(user) => Some(user.id),
+            None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+        },
+        None => None,
+    };
+    let friends_of_friends = user_search::friends_of_friends_only();
+    if friends_of_friends && requester.is_none() {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error("Sign in to search for players"));
+    }
+    let after = match params.cursor.as_deref().map(user_search::SearchCursor::decode) {
+        Some(None) => return (StatusCode::BAD_REQUEST, ApiResponse::error("Invalid cursor")),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let limit = params.limit.unwrap_or(user_search::DEFAULT_LIMIT).clamp(1, user_search::MAX_LIMIT);
+    
+    let blocked = match requester {
+        Some(user_id) => match social_guard::block_set(&state.db, user_id).await {
+            Ok(blocks) => blocks.ids().clone(),
+            Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to check blocks")),
+        },
+        None => std::collections::HashSet::new(),
+    };
+    
+    // Candidates come back in rank order so the cap never drops a better
+    // match; `rank_page` applies the exact ordering and the cursor.
+    let candidates = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<String>, Option<chrono::DateTime<chrono::Utc>>)>(
+        "WITH friends AS (
+             SELECT CASE WHEN user_id = $3 THEN friend_id ELSE user_id END AS id FROM friendships
+             WHERE status = 'accepted' AND (user_id = $3 OR friend_id = $3)
+         ), circle AS (
+             SELECT id FROM friends
+             UNION
+             SELECT CASE WHEN f.user_id = fr.id THEN f.friend_id ELSE f.user_id END FROM friendships f
+             JOIN friends fr ON f.user_id = fr.id OR f.friend_id = fr.id
+             WHERE f.status = 'accepted'
+         )
+         SELECT id, username, display_name, avatar_url, last_seen FROM users
+         WHERE (username ILIKE $1 OR display_name ILIKE $1) AND deleted_at IS NULL AND id <> $2
+           AND (NOT $4 OR id IN (SELECT id FROM circle WHERE id <> $3))
+         ORDER BY CASE
+                 WHEN LOWER(username) = LOWER($5) OR LOWER(display_name) = LOWER($5) THEN 0
+                 WHEN username ILIKE $6 OR display_name ILIKE $6 THEN 1
+                 ELSE 2
+             END, last_seen DESC NULLS LAST, id
+         LIMIT $7"
     )
         .bind(format!("%{}%", query))
+        .bind(account::ARCHIVED_AUTHOR_ID)
+        .bind(requester)
+        .bind(friends_of_friends)
+        .bind(&query)
+        .bind(format!("{}%", query))
+        .bind(user_search::MAX_CANDIDATES)
         .fetch_all(&state.db)
         .await
         .unwrap_or_default();
     
-    let users: Vec<serde_json::Value> = users.iter().map(|(id, username, display_name, avatar_url)| {
-        serde_json::json!({
-            "id": id,
-            "username": username,
-            "display_name": display_name,
-            "avatar_url": avatar_url
-        })
+    let candidates = candidates.into_iter().map(|(id, username, display_name, avatar_url, last_seen)| {
+        user_search::UserResult { id, username, display_name, avatar_url, last_seen }
     }).collect();
+    let page = user_search::rank_page(&query, candidates, &blocked, after.as_ref(), limit);
     
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({"users": users})))
+    (StatusCode::OK, ApiResponse::success(serde_json::to_value(page).unwrap_or_default()))
 }
 
 async fn health() -> impl IntoResponse {
@@ -659,33 +1175,23 @@ async fn health() -> impl IntoResponse {
 
 async fn list_servers(
     State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<ServerListParams>,
 ) -> impl IntoResponse {
-    let servers = sqlx::query_as::<_, (Uuid, String, Option<String>, String, i32, i32, i32, String, Uuid, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, name, description, address, port, max_players, current_players, game_mode, owner_id, is_online, last_ping, created_at 
-         FROM game_servers WHERE is_online = true AND last_ping > NOW() - INTERVAL '5 minutes' ORDER BY current_players DESC LIMIT 100"
-    )
-        .fetch_all(&state.db)
-        .await
-        .unwrap_or_default();
-    
-    let servers: Vec<serde_json::Value> = servers.iter().map(|(id, name, desc, addr, port, max, curr, mode, owner, online, ping, created)| {
-        serde_json::json!({
-            "id": id,
-            "name": name,
-            "description": desc,
-            "address": addr,
-            "port": port,
-            "max_players": max,
-            "current_players": curr,
-            "game_mode": mode,
-            "owner_id": owner,
-            "is_online": online,
-            "last_ping": ping,
-            "created_at": created
-        })
-    }).collect();
+    let page = pagination::PageRequest::new(params.page, params.per_page);
+    let filter = server_directory::ServerFilter::parse(
+        params.game_mode.as_deref(),
+        params.tags.as_deref(),
+        params.has_slots,
+        params.q.as_deref(),
+    );
     
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({"servers": servers})))
+    match server_directory::list(&state.db, &filter, page).await {
+        Ok(listing) => (StatusCode::OK, ApiResponse::success(listing)),
+        Err(e) => {
+            error!("Failed to list servers: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to list servers"))
+        }
+    }
 }
 
 async fn register_server(
@@ -695,7 +1201,7 @@ async fn register_server(
     let user = validate_token(&state.db, &req.token).await;
     let user = match user {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<GameServer>::error("Invalid token")),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
     
     let server_id = Uuid::new_v4();
@@ -736,35 +1242,388 @@ async fn register_server(
                 last_ping: now,
                 created_at: now,
             };
-            (StatusCode::CREATED, ApiResponse::success(server))
+            // The host heartbeats with this token, so it never needs the
+            // owner's login. It is shown only in this response.
+            let server_api_token = match server_owners::issue_token(&state.db, server_id, user.id, Some("registration")).await {
+                Ok((_, token)) => token,
+                Err(e) => {
+                    error!("Failed to issue token for server {}: {}", server_id, e);
+                    return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to register server"));
+                }
+            };
+            let mut data = serde_json::to_value(server).unwrap_or_default();
+            data["server_api_token"] = serde_json::json!(server_api_token);
+            (StatusCode::CREATED, ApiResponse::success(data))
         }
         Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to register server")),
     }
 }
 
+/// Resolves a host's server token for `server_id`. User session tokens are
+/// refused outright.
+async fn require_server_token(db: &PgPool, token: &str, server_id: Uuid) -> Result<server_owners::TokenRecord, (StatusCode, String)> {
+    if let Err(e) = server_owners::require_server_token(token) {
+        return Err((StatusCode::UNAUTHORIZED, e.to_string()));
+    }
+    let record = match server_owners::find_token(db, token).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return Err((StatusCode::UNAUTHORIZED, "Invalid token".to_string())),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to check token".to_string())),
+    };
+    server_owners::authorize_server_token(server_id, &record)
+        .map(|()| record)
+        .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))
+}
+
 async fn server_heartbeat(
     State(state): State<AppState>,
     Json(req): Json<ServerHeartbeatRequest>,
 ) -> impl IntoResponse {
-    let user = validate_token(&state.db, &req.token).await;
-    let user = match user {
-        Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    let token = match require_server_token(&state.db, &req.token, req.server_id).await {
+        Ok(token) => token,
+        Err((status, e)) => return (status, ApiResponse::<serde_json::Value>::error(e)),
     };
     
+    let max_players = sqlx::query_scalar::<_, i32>("SELECT max_players FROM game_servers WHERE id = $1")
+        .bind(req.server_id)
+        .fetch_optional(&state.db)
+        .await;
+    let max_players = match max_players {
+        Ok(Some(max_players)) => max_players,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("Server not found")),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update server")),
+    };
+    if let Err(e) = server_metrics::validate_player_count(req.current_players, max_players) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+    
+    let now = chrono::Utc::now();
     let result = sqlx::query(
-        "UPDATE game_servers SET current_players = $1, last_ping = $2, is_online = true WHERE id = $3 AND owner_id = $4"
+        "UPDATE game_servers SET current_players = $1, last_ping = $2, is_online = true WHERE id = $3"
     )
         .bind(req.current_players)
-        .bind(chrono::Utc::now())
+        .bind(now)
         .bind(req.server_id)
-        .bind(user.id)
         .execute(&state.db)
         .await;
     
     match result {
-        Ok(r) if r.rows_affected() > 0 => (StatusCode::OK, ApiResponse::success(serde_json::json!({"updated": true}))),
-        _ => (StatusCode::NOT_FOUND, ApiResponse::error("Server not found or not owned by you")),
+        Ok(r) if r.rows_affected() > 0 => {
+            if let Err(e) = server_owners::touch_token(&state.db, token.id, now).await {
+                error!("Failed to record server token use: {}", e);
+            }
+            if let Err(e) = server_metrics::record_sample(&state.db, req.server_id, req.current_players, now).await {
+                error!("Failed to record server metrics: {}", e);
+            }
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({"updated": true})))
+        }
+        Ok(_) => (StatusCode::NOT_FOUND, ApiResponse::error("Server not found")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update server")),
+    }
+}
+
+/// A host taking its listing down for good; the owner can do the same with
+/// `DELETE /api/v1/servers/:id`.
+async fn deregister_server(
+    State(state): State<AppState>,
+    Json(req): Json<DeregisterServerRequest>,
+) -> impl IntoResponse {
+    if let Err((status, e)) = require_server_token(&state.db, &req.token, req.server_id).await {
+        return (status, ApiResponse::<serde_json::Value>::error(e));
+    }
+    let owner_id = match server_owners::owner_of(&state.db, req.server_id).await {
+        Ok(Some(owner_id)) => owner_id,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("Server not found")),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to deregister server")),
+    };
+    
+    match server_owners::delete(&state.db, req.server_id, owner_id).await {
+        Ok(true) => {
+            info!("Server {} deregistered itself", req.server_id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({"deregistered": true})))
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("Server not found")),
+        Err(e) => {
+            error!("Failed to deregister server {}: {}", req.server_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to deregister server"))
+        }
+    }
+}
+
+async fn get_server(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let server = sqlx::query_as::<_, (Uuid, String, Option<String>, String, i32, i32, i32, String, serde_json::Value, Uuid, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, name, description, address, port, max_players, current_players, game_mode, COALESCE(tags, '[]'), owner_id, is_online, last_ping, created_at 
+         FROM game_servers WHERE id = $1"
+    )
+        .bind(server_id)
+        .fetch_optional(&state.db)
+        .await;
+    
+    let (id, name, description, address, port, max_players, current_players, game_mode, tags, owner_id, is_online, last_ping, created_at) = match server {
+        Ok(Some(server)) => server,
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<serde_json::Value>::error("Server not found")),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load server")),
+    };
+    let peak_players_24h = server_metrics::peak_players_24h(&state.db, server_id, chrono::Utc::now())
+        .await
+        .unwrap_or(current_players);
+    
+    let server = GameServer {
+        id,
+        name,
+        description,
+        address,
+        port,
+        max_players,
+        current_players,
+        game_mode,
+        tags: serde_json::from_value(tags).unwrap_or_default(),
+        owner_id,
+        is_online,
+        last_ping,
+        created_at,
+    };
+    let mut detail = serde_json::to_value(server).unwrap_or_default();
+    detail["peak_players_24h"] = serde_json::json!(peak_players_24h);
+    
+    (StatusCode::OK, ApiResponse::success(detail))
+}
+
+async fn get_server_metrics(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let exists = sqlx::query_scalar::<_, Uuid>("SELECT id FROM game_servers WHERE id = $1")
+        .bind(server_id)
+        .fetch_optional(&state.db)
+        .await;
+    match exists {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::<server_metrics::MetricsSeries>::error("Server not found")),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load metrics")),
+    }
+    
+    match server_metrics::series(&state.db, server_id, chrono::Utc::now()).await {
+        Ok(series) => (StatusCode::OK, ApiResponse::success(series)),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load metrics")),
+    }
+}
+
+/// The user behind `token`, provided they own `server_id`.
+async fn require_server_owner(db: &PgPool, token: &str, server_id: Uuid) -> Result<User, (StatusCode, String)> {
+    let Some(user) = validate_token(db, token).await else {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid token".to_string()));
+    };
+    let owner_id = server_owners::owner_of(db, server_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load server".to_string()))?;
+    match server_owners::check_owner(owner_id, user.id) {
+        Ok(()) => Ok(user),
+        Err(e @ server_owners::AccessError::NotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::FORBIDDEN, e.to_string())),
+    }
+}
+
+async fn update_server(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<UpdateServerRequest>,
+) -> impl IntoResponse {
+    if let Err((status, e)) = require_server_owner(&state.db, &req.token, server_id).await {
+        return (status, ApiResponse::<serde_json::Value>::error(e));
+    }
+    if let Err(e) = req.changes.validate() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+    
+    match server_owners::update(&state.db, server_id, &req.changes).await {
+        Ok(true) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"updated": true, "id": server_id}))),
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("Server not found")),
+        Err(e) => {
+            error!("Failed to update server {}: {}", server_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update server"))
+        }
+    }
+}
+
+async fn delete_server(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match require_server_owner(&state.db, &req.token, server_id).await {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<serde_json::Value>::error(e)),
+    };
+    
+    match server_owners::delete(&state.db, server_id, user.id).await {
+        Ok(true) => {
+            info!("User {} deleted server {}", user.id, server_id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({"deleted": true})))
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("Server not found")),
+        Err(e) => {
+            error!("Failed to delete server {}: {}", server_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to delete server"))
+        }
+    }
+}
+
+async fn transfer_server(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<TransferServerRequest>,
+) -> impl IntoResponse {
+    let user = match require_server_owner(&state.db, &req.token, server_id).await {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<serde_json::Value>::error(e)),
+    };
+    if req.to_user_id == user.id {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("You already own this server"));
+    }
+    
+    let recipient = sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE id = $1 AND deleted_at IS NULL")
+        .bind(req.to_user_id)
+        .fetch_optional(&state.db)
+        .await;
+    match recipient {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, ApiResponse::error("User not found")),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to offer transfer")),
+    }
+    let server_name = sqlx::query_scalar::<_, String>("SELECT name FROM game_servers WHERE id = $1")
+        .bind(server_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or_default();
+    
+    let transfer = server_owners::PendingTransfer::new(server_id, user.id, req.to_user_id, chrono::Utc::now());
+    match server_owners::offer_transfer(&state.db, &transfer).await {
+        Ok(()) => {
+            let notification = NewNotification::server_transfer(user.id, &user.username, req.to_user_id, server_id, &server_name);
+            notifications::send_logged(&state.db, &state.notifications, notification).await;
+            (StatusCode::CREATED, ApiResponse::success(serde_json::to_value(transfer).unwrap_or_default()))
+        }
+        Err(e) => {
+            error!("Failed to offer transfer of server {}: {}", server_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to offer transfer"))
+        }
+    }
+}
+
+async fn accept_server_transfer(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let Some(user) = validate_token(&state.db, &req.token).await else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token"));
+    };
+    let transfer = match server_owners::pending_transfer(&state.db, server_id).await {
+        Ok(transfer) => transfer,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to accept transfer")),
+    };
+    let now = chrono::Utc::now();
+    match server_owners::check_acceptance(transfer.as_ref(), user.id, now) {
+        Ok(()) => {}
+        Err(e @ server_owners::TransferError::NotRecipient) => return (StatusCode::FORBIDDEN, ApiResponse::error(e.to_string())),
+        Err(e @ server_owners::TransferError::Expired) => return (StatusCode::GONE, ApiResponse::error(e.to_string())),
+        Err(e) => return (StatusCode::NOT_FOUND, ApiResponse::error(e.to_string())),
+    }
+    let Some(transfer) = transfer else {
+        return (StatusCode::NOT_FOUND, ApiResponse::error(server_owners::TransferError::NoPendingTransfer.to_string()));
+    };
+    
+    match server_owners::complete_transfer(&state.db, &transfer, now).await {
+        Ok(Some(tokens_revoked)) => {
+            info!("Server {} transferred from {} to {}", server_id, transfer.from_user_id, user.id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({
+                "transferred": true,
+                "owner_id": user.id,
+                "tokens_revoked": tokens_revoked,
+            })))
+        }
+        Ok(None) => (StatusCode::CONFLICT, ApiResponse::error(server_owners::TransferError::OwnerChanged.to_string())),
+        Err(e) => {
+            error!("Failed to transfer server {}: {}", server_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to accept transfer"))
+        }
+    }
+}
+
+async fn cancel_server_transfer(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let Some(user) = validate_token(&state.db, &req.token).await else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token"));
+    };
+    
+    match server_owners::cancel_transfer(&state.db, server_id, user.id).await {
+        Ok(true) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"cancelled": true}))),
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error(server_owners::TransferError::NoPendingTransfer.to_string())),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to cancel transfer")),
+    }
+}
+
+async fn issue_server_token(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<IssueServerTokenRequest>,
+) -> impl IntoResponse {
+    let user = match require_server_owner(&state.db, &req.token, server_id).await {
+        Ok(user) => user,
+        Err((status, e)) => return (status, ApiResponse::<serde_json::Value>::error(e)),
+    };
+    if req.label.as_ref().is_some_and(|label| label.len() > 64) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("label must be at most 64 characters"));
+    }
+    
+    match server_owners::issue_token(&state.db, server_id, user.id, req.label.as_deref()).await {
+        Ok((summary, server_api_token)) => {
+            let mut data = serde_json::to_value(summary).unwrap_or_default();
+            data["server_api_token"] = serde_json::json!(server_api_token);
+            (StatusCode::CREATED, ApiResponse::success(data))
+        }
+        Err(e) => {
+            error!("Failed to issue token for server {}: {}", server_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to issue token"))
+        }
+    }
+}
+
+async fn list_server_tokens(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    if let Err((status, e)) = require_server_owner(&state.db, &req.token, server_id).await {
+        return (status, ApiResponse::<serde_json::Value>::error(e));
+    }
+    
+    match server_owners::list_tokens(&state.db, server_id).await {
+        Ok(tokens) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"tokens": tokens}))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load tokens")),
+    }
+}
+
+async fn revoke_server_token(
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<RevokeServerTokenRequest>,
+) -> impl IntoResponse {
+    if let Err((status, e)) = require_server_owner(&state.db, &req.token, server_id).await {
+        return (status, ApiResponse::<serde_json::Value>::error(e));
+    }
+    
+    match server_owners::revoke_token(&state.db, server_id, req.token_id).await {
+        Ok(true) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"revoked": true}))),
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("Token not found or already revoked")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to revoke token")),
     }
 }
 
@@ -820,26 +1679,315 @@ async fn record_session(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
     
+    if req.duration_minutes < 0 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Duration cannot be negative"));
+    }
+    
     let now = chrono::Utc::now();
-    let result = sqlx::query(
-        "INSERT INTO game_stats (user_id, total_playtime_minutes, total_sessions, last_played, favorite_server, achievements_count)
-         VALUES ($1, $2, 1, $3, $4, 0)
-         ON CONFLICT (user_id) DO UPDATE SET 
-           total_playtime_minutes = game_stats.total_playtime_minutes + $2,
-           total_sessions = game_stats.total_sessions + 1,
-           last_played = $3,
-           favorite_server = COALESCE($4, game_stats.favorite_server)"
+    let result = async {
+        let mut tx = state.db.begin().await?;
+        let (playtime, sessions, achievements_before) = sqlx::query_as::<_, (i64, i64, i32)>(
+            "INSERT INTO game_stats (user_id, total_playtime_minutes, total_sessions, last_played, achievements_count)
+             VALUES ($1, $2, 1, $3, 0)
+             ON CONFLICT (user_id) DO UPDATE SET 
+               total_playtime_minutes = game_stats.total_playtime_minutes + $2,
+               total_sessions = game_stats.total_sessions + 1,
+               last_played = $3
+             RETURNING total_playtime_minutes, total_sessions, achievements_count"
+        )
+            .bind(user.id)
+            .bind(req.duration_minutes)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?;
+        let favorite_server = play_stats::record(&mut tx, user.id, req.server_id, req.server_name.as_deref(), req.duration_minutes, now).await?;
+        
+        let stats = achievements::StatSnapshot {
+            total_playtime_minutes: playtime,
+            total_sessions: sessions,
+            session_minutes: req.duration_minutes as i64,
+            achievements_count: achievements_before as i64,
+        };
+        let unlocks = achievements::unlock_satisfied(&mut tx, user.id, stats).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>((stats, favorite_server, achievements_before, unlocks))
+    }.await;
+    
+    // `unlocked` is what this session earned, so the launcher can show it without diffing
+    match result {
+        Ok((stats, favorite_server, achievements_before, unlocks)) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "recorded": true,
+            "stats": {
+                "total_playtime_minutes": stats.total_playtime_minutes,
+                "total_sessions": stats.total_sessions,
+                "favorite_server": favorite_server,
+                "achievements_count": unlocks.achievements_count,
+            },
+            "achievements_before": achievements_before,
+            "unlocked": unlocks.unlocked,
+        }))),
+        Err(e) => {
+            error!("Failed to record session: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to record session"))
+        }
+    }
+}
+
+async fn get_leaderboard(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<LeaderboardQuery>,
+) -> impl IntoResponse {
+    let Some(metric) = play_stats::Metric::parse(query.metric.as_deref().unwrap_or("playtime")) else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::<play_stats::Leaderboard>::error("metric must be playtime or sessions"));
+    };
+    let Some(period) = play_stats::Period::parse(query.period.as_deref().unwrap_or("all")) else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("period must be week, month or all"));
+    };
+    
+    match play_stats::leaderboard(&state.db, metric, period, query.page, query.limit, chrono::Utc::now()).await {
+        Ok(leaderboard) => (StatusCode::OK, ApiResponse::success(leaderboard)),
+        Err(e) => {
+            error!("Failed to load leaderboard: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load leaderboard"))
+        }
+    }
+}
+
+async fn get_stats_breakdown(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<play_stats::Breakdown>::error("Invalid token")),
+    };
+    
+    match play_stats::breakdown(&state.db, user.id, chrono::Utc::now()).await {
+        Ok(breakdown) => (StatusCode::OK, ApiResponse::success(breakdown)),
+        Err(e) => {
+            error!("Failed to load stats breakdown: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load stats breakdown"))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListNotificationsRequest {
+    token: String,
+    #[serde(default)]
+    unread_only: bool,
+    limit: Option<i64>,
+}
+
+async fn list_notifications(
+    State(state): State<AppState>,
+    Json(req): Json<ListNotificationsRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<notifications::NotificationPage>::error("Invalid token")),
+    };
+    
+    match notifications::list(&state.db, user.id, req.unread_only, req.limit).await {
+        Ok(page) => (StatusCode::OK, ApiResponse::success(page)),
+        Err(e) => {
+            error!("Failed to load notifications: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load notifications"))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationActionRequest {
+    token: String,
+    notification_id: Uuid,
+}
+
+async fn mark_notification_read(
+    State(state): State<AppState>,
+    Json(req): Json<NotificationActionRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    match notifications::mark_read(&state.db, user.id, Some(req.notification_id)).await {
+        Ok(updated) => (StatusCode::OK, ApiResponse::success(serde_json::json!({ "updated": updated }))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update notification")),
+    }
+}
+
+async fn mark_all_notifications_read(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    match notifications::mark_read(&state.db, user.id, None).await {
+        Ok(updated) => (StatusCode::OK, ApiResponse::success(serde_json::json!({ "updated": updated }))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update notifications")),
+    }
+}
+
+async fn delete_notification(
+    State(state): State<AppState>,
+    Json(req): Json<NotificationActionRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    match notifications::delete(&state.db, user.id, req.notification_id).await {
+        Ok(true) => (StatusCode::OK, ApiResponse::success(serde_json::json!({ "deleted": true }))),
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("Notification not found")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to delete notification")),
+    }
+}
+
+async fn list_achievements(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let rows = sqlx::query_as::<_, (String, String, String, serde_json::Value, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, name, description, criteria, created_at FROM achievements
+         WHERE retired_at IS NULL ORDER BY created_at"
+    )
+        .fetch_all(&state.db)
+        .await;
+    
+    match rows {
+        Ok(rows) => {
+            let definitions: Vec<_> = rows.into_iter().map(|(id, name, description, criteria, created_at)| serde_json::json!({
+                "id": id,
+                "name": name,
+                "description": description,
+                "criteria": criteria,
+                "created_at": created_at,
+            })).collect();
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({ "achievements": definitions })))
+        }
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load achievements")),
+    }
+}
+
+async fn list_my_achievements(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    // Unlocks of since-retired achievements are kept
+    let rows = sqlx::query_as::<_, (String, String, String, chrono::DateTime<chrono::Utc>, bool)>(
+        "SELECT a.id, a.name, a.description, ua.unlocked_at, a.retired_at IS NOT NULL
+         FROM user_achievements ua JOIN achievements a ON a.id = ua.achievement_id
+         WHERE ua.user_id = $1 ORDER BY ua.unlocked_at DESC"
     )
         .bind(user.id)
-        .bind(req.duration_minutes)
-        .bind(now)
-        .bind(&req.server_name)
+        .fetch_all(&state.db)
+        .await;
+    
+    match rows {
+        Ok(rows) => {
+            let unlocked: Vec<_> = rows.into_iter().map(|(id, name, description, unlocked_at, retired)| serde_json::json!({
+                "id": id,
+                "name": name,
+                "description": description,
+                "unlocked_at": unlocked_at,
+                "retired": retired,
+            })).collect();
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({ "unlocked": unlocked })))
+        }
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load achievements")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminCreateAchievementRequest {
+    admin_token: String,
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    criteria: serde_json::Value,
+}
+
+async fn admin_create_achievement(
+    State(state): State<AppState>,
+    Json(req): Json<AdminCreateAchievementRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+    
+    if let Err(e) = achievements::validate_id(&req.id) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(&e));
+    }
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 128 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Name must be 1-128 characters"));
+    }
+    if let Err(e) = achievements::Criteria::parse(&req.criteria) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(format!("Invalid criteria: {}", e)));
+    }
+    
+    let result = sqlx::query(
+        "INSERT INTO achievements (id, name, description, criteria, created_at) VALUES ($1, $2, $3, $4, NOW())
+         ON CONFLICT (id) DO NOTHING"
+    )
+        .bind(&req.id)
+        .bind(name)
+        .bind(req.description.trim())
+        .bind(&req.criteria)
         .execute(&state.db)
         .await;
     
     match result {
-        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"recorded": true}))),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to record session")),
+        Ok(r) if r.rows_affected() > 0 => {
+            info!("Admin created achievement {}", req.id);
+            (StatusCode::CREATED, ApiResponse::success(serde_json::json!({
+                "id": req.id,
+                "name": name,
+                "description": req.description.trim(),
+                "criteria": req.criteria,
+            })))
+        }
+        Ok(_) => (StatusCode::CONFLICT, ApiResponse::error("An achievement with that id already exists")),
+        Err(e) => {
+            error!("Failed to create achievement: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to create achievement"))
+        }
+    }
+}
+
+/// Retired achievements stop unlocking; existing unlocks are kept.
+async fn admin_retire_achievement(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<AdminTokenRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+    
+    let result = sqlx::query("UPDATE achievements SET retired_at = NOW() WHERE id = $1 AND retired_at IS NULL")
+        .bind(&id)
+        .execute(&state.db)
+        .await;
+    
+    match result {
+        Ok(r) if r.rows_affected() > 0 => {
+            info!("Admin retired achievement {}", id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({ "id": id, "retired": true })))
+        }
+        Ok(_) => (StatusCode::NOT_FOUND, ApiResponse::error("No active achievement with that id")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to retire achievement")),
     }
 }
 
@@ -853,15 +2001,16 @@ async fn get_mod_profiles(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
     
-    let profiles = sqlx::query_as::<_, (Uuid, String, Option<String>, serde_json::Value, bool, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, name, description, mods, is_active, created_at FROM mod_profiles WHERE user_id = $1 ORDER BY created_at DESC"
+    type Row = (Uuid, String, Option<String>, serde_json::Value, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>);
+    let profiles = sqlx::query_as::<_, Row>(
+        "SELECT id, name, description, mods, is_active, created_at, updated_at FROM mod_profiles WHERE user_id = $1 ORDER BY created_at DESC"
     )
         .bind(user.id)
         .fetch_all(&state.db)
         .await
         .unwrap_or_default();
     
-    let profiles: Vec<ModProfile> = profiles.iter().map(|(id, name, desc, mods, active, created)| {
+    let profiles: Vec<ModProfile> = profiles.iter().map(|(id, name, desc, mods, active, created, updated)| {
         ModProfile {
             id: *id,
             user_id: user.id,
@@ -870,6 +2019,7 @@ async fn get_mod_profiles(
             mods: mods.clone(),
             is_active: *active,
             created_at: *created,
+            updated_at: *updated,
         }
     }).collect();
     
@@ -890,7 +2040,7 @@ async fn create_mod_profile(
     let now = chrono::Utc::now();
     
     let result = sqlx::query(
-        "INSERT INTO mod_profiles (id, user_id, name, description, mods, is_active, created_at) VALUES ($1, $2, $3, $4, $5, false, $6)"
+        "INSERT INTO mod_profiles (id, user_id, name, description, mods, is_active, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, false, $6, $6)"
     )
         .bind(profile_id)
         .bind(user.id)
@@ -911,6 +2061,7 @@ async fn create_mod_profile(
                 mods: req.mods,
                 is_active: false,
                 created_at: now,
+                updated_at: now,
             };
             (StatusCode::CREATED, ApiResponse::success(profile))
         }
@@ -918,6 +2069,56 @@ async fn create_mod_profile(
     }
 }
 
+/// Create or replace a profile under the id the launcher gave it
+async fn upsert_mod_profile(
+    State(state): State<AppState>,
+    Json(req): Json<UpsertModProfileRequest>,
+) -> impl IntoResponse {
+    let Some(user) = validate_token(&state.db, &req.token).await else {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<ModProfile>::error("Invalid token"));
+    };
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > 128 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Profile name must be 1 to 128 characters"));
+    }
+    
+    // Another user's id is treated as missing rather than overwritten
+    let row = sqlx::query_as::<_, (bool, chrono::DateTime<chrono::Utc>)>(
+        "INSERT INTO mod_profiles (id, user_id, name, description, mods, is_active, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, false, NOW(), $6)
+         ON CONFLICT (id) DO UPDATE SET
+            name = EXCLUDED.name,
+            description = EXCLUDED.description,
+            mods = EXCLUDED.mods,
+            updated_at = EXCLUDED.updated_at
+         WHERE mod_profiles.user_id = EXCLUDED.user_id
+         RETURNING is_active, created_at"
+    )
+        .bind(req.id)
+        .bind(user.id)
+        .bind(name)
+        .bind(&req.description)
+        .bind(&req.mods)
+        .bind(req.updated_at)
+        .fetch_optional(&state.db)
+        .await;
+    
+    match row {
+        Ok(Some((is_active, created_at))) => (StatusCode::OK, ApiResponse::success(ModProfile {
+            id: req.id,
+            user_id: user.id,
+            name: name.to_string(),
+            description: req.description,
+            mods: req.mods,
+            is_active,
+            created_at,
+            updated_at: req.updated_at,
+        })),
+        Ok(None) => (StatusCode::NOT_FOUND, ApiResponse::error("Profile not found")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to save profile")),
+    }
+}
+
 async fn activate_mod_profile(
     State(state): State<AppState>,
     Json(req): Json<ActivateModProfileRequest>,
@@ -928,20 +2129,37 @@ async fn activate_mod_profile(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
     
-    let _ = sqlx::query("UPDATE mod_profiles SET is_active = false WHERE user_id = $1")
-        .bind(user.id)
-        .execute(&state.db)
-        .await;
-    
-    let result = sqlx::query("UPDATE mod_profiles SET is_active = true WHERE id = $1 AND user_id = $2")
+    let profile = sqlx::query_as::<_, (Uuid, String, serde_json::Value)>(
+        "SELECT id, name, mods FROM mod_profiles WHERE id = $1 AND user_id = $2"
+    )
         .bind(req.profile_id)
         .bind(user.id)
-        .execute(&state.db)
-        .await;
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten();
+    
+    let Some((id, name, mods)) = profile else {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("Profile not found"));
+    };
     
+    let result = async {
+        let mut tx = state.db.begin().await?;
+        sqlx::query("UPDATE mod_profiles SET is_active = (id = $1) WHERE user_id = $2")
+            .bind(id)
+            .bind(user.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }.await;
+    
+    // The client materializes the returned mod set on disk
     match result {
-        Ok(r) if r.rows_affected() > 0 => (StatusCode::OK, ApiResponse::success(serde_json::json!({"activated": true}))),
-        _ => (StatusCode::NOT_FOUND, ApiResponse::error("Profile not found")),
+        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "activated": true,
+            "profile": { "id": id, "name": name, "mods": mods },
+        }))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to activate profile")),
     }
 }
 
@@ -1016,8 +2234,161 @@ async fn update_performance_settings(
         .await;
     
     match result {
-        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"updated": true}))),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update settings")),
+        Ok(_) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"updated": true}))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update settings")),
+    }
+}
+
+fn subscription_features(tier: &str) -> SubscriptionFeatures {
+    match tier {
+        "premium" => SubscriptionFeatures {
+            max_friends: 500,
+            cloud_storage_mb: 5120,
+            priority_relay: true,
+            custom_themes: true,
+            early_access: true,
+        },
+        _ => SubscriptionFeatures {
+            max_friends: 100,
+            cloud_storage_mb: 0,
+            priority_relay: false,
+            custom_themes: false,
+            early_access: false,
+        },
+    }
+}
+
+/// Free accounts get enough room for plain settings, but no mod profile archives.
+const FREE_SYNC_LIMIT_BYTES: usize = 256 * 1024;
+const SYNC_ARCHIVE_SECTIONS: &[&str] = &["mod_profiles"];
+
+#[derive(Debug, Deserialize)]
+struct SyncPushRequest {
+    token: String,
+    document: serde_json::Value,
+    base_etag: Option<String>,
+}
+
+async fn sync_pull(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
+    };
+    
+    let row = sqlx::query_as::<_, (serde_json::Value, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT document, etag, updated_at FROM settings_sync WHERE user_id = $1"
+    )
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten();
+    
+    match row {
+        Some((document, etag, updated_at)) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "document": document,
+            "etag": etag,
+            "updated_at": updated_at,
+        }))),
+        None => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "document": null,
+            "etag": null,
+            "updated_at": null,
+        }))),
+    }
+}
+
+async fn sync_push(
+    State(state): State<AppState>,
+    Json(req): Json<SyncPushRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
+    };
+    
+    let mut document = req.document;
+    if !document.get("sections").map(|s| s.is_object()).unwrap_or(false) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Sync document must contain a sections object"));
+    }
+    
+    let premium = is_premium_user(&state.db, user.id).await;
+    let features = subscription_features(if premium { "premium" } else { "free" });
+    
+    let mut dropped_sections = Vec::new();
+    if !premium {
+        if let Some(sections) = document.get_mut("sections").and_then(|s| s.as_object_mut()) {
+            for name in SYNC_ARCHIVE_SECTIONS {
+                if sections.remove(*name).is_some() {
+                    dropped_sections.push(name.to_string());
+                }
+            }
+        }
+    }
+    
+    let size_bytes = serde_json::to_vec(&document).map(|b| b.len()).unwrap_or(0);
+    let limit_bytes = if features.cloud_storage_mb > 0 {
+        features.cloud_storage_mb as usize * 1024 * 1024
+    } else {
+        FREE_SYNC_LIMIT_BYTES
+    };
+    if size_bytes > limit_bytes {
+        return (StatusCode::PAYLOAD_TOO_LARGE, ApiResponse::error(format!(
+            "Sync document is {} bytes, quota is {} bytes", size_bytes, limit_bytes
+        )));
+    }
+    
+    let current = sqlx::query_as::<_, (serde_json::Value, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT document, etag, updated_at FROM settings_sync WHERE user_id = $1"
+    )
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten();
+    
+    if let Some((current_document, current_etag, updated_at)) = current {
+        if req.base_etag.as_deref() != Some(current_etag.as_str()) {
+            return (StatusCode::CONFLICT, ApiResponse::error_with_data(
+                "Sync document changed on another device; pull and merge first",
+                serde_json::json!({
+                    "document": current_document,
+                    "etag": current_etag,
+                    "updated_at": updated_at,
+                }),
+            ));
+        }
+    }
+    
+    let etag = Uuid::new_v4().simple().to_string();
+    let result = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+        "INSERT INTO settings_sync (user_id, document, etag, size_bytes, updated_at)
+         VALUES ($1, $2, $3, $4, NOW())
+         ON CONFLICT (user_id) DO UPDATE SET document = $2, etag = $3, size_bytes = $4, updated_at = NOW()
+         RETURNING updated_at"
+    )
+        .bind(user.id)
+        .bind(&document)
+        .bind(&etag)
+        .bind(size_bytes as i64)
+        .fetch_one(&state.db)
+        .await;
+    
+    match result {
+        Ok(updated_at) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "etag": etag,
+            "updated_at": updated_at,
+            "size_bytes": size_bytes,
+            "quota_bytes": limit_bytes,
+            "dropped_sections": dropped_sections,
+        }))),
+        Err(e) => {
+            error!("Failed to store sync document: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to store sync document"))
+        }
     }
 }
 
@@ -1042,22 +2413,7 @@ async fn get_subscription(
     
     let (tier, status, period_end) = sub.unwrap_or(("free".to_string(), "active".to_string(), None));
     
-    let features = match tier.as_str() {
-        "premium" => SubscriptionFeatures {
-            max_friends: 500,
-            cloud_storage_mb: 5120,
-            priority_relay: true,
-            custom_themes: true,
-            early_access: true,
-        },
-        _ => SubscriptionFeatures {
-            max_friends: 100,
-            cloud_storage_mb: 0,
-            priority_relay: false,
-            custom_themes: false,
-            early_access: false,
-        },
-    };
+    let features = subscription_features(&tier);
     
     (StatusCode::OK, ApiResponse::success(Subscription {
         user_id: user.id,
@@ -1276,12 +2632,41 @@ async fn create_checkout(
     let success_url = format!("{}/dashboard?checkout=success", base_url);
     let cancel_url = format!("{}/premium?checkout=cancelled", base_url);
     
-    match stripe::create_checkout_session(&email, &req.price_id, &success_url, &cancel_url).await {
+    match stripe::create_checkout_session(&email, &user.id.to_string(), &req.price_id, &success_url, &cancel_url).await {
         Ok(url) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"url": url}))),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error(format!("Checkout failed: {}", e))),
     }
 }
 
+/// Stripe's record of payments and subscriptions, signed with
+/// STRIPE_WEBHOOK_SECRET. Anything but a 2xx makes Stripe retry the event.
+async fn stripe_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(secret) = billing::webhook_secret() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, ApiResponse::<serde_json::Value>::error("Webhook not configured"));
+    };
+    let signature = headers.get("Stripe-Signature").and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+    let event = match billing::construct_event(&body, signature, &secret, chrono::Utc::now().timestamp()) {
+        Ok(event) => event,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
+    };
+
+    match billing::process(&state.db, &state.notifications, &event).await {
+        Ok(outcome) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "received": true,
+            "duplicate": outcome == billing::Outcome::Duplicate,
+        }))),
+        Err(e) => {
+            error!("Failed to process Stripe event {}: {}", event.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to process event"))
+        }
+    }
+}
+
 async fn manage_subscription(
     State(state): State<AppState>,
     Json(req): Json<ManageSubscriptionRequest>,
@@ -1320,19 +2705,177 @@ async fn manage_subscription(
     }
 }
 
-async fn get_releases() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "latest": {
-            "version": "0.1.0",
-            "date": "2026-01-10",
-            "downloads": {
-                "windows": "/releases/yellow-tale-0.1.0-windows.exe",
-                "macos": "/releases/yellow-tale-0.1.0-macos.dmg",
-                "linux": "/releases/yellow-tale-0.1.0-linux.tar.gz"
-            },
-            "changelog": "Initial release with user accounts, friends system, and P2P relay."
+/// Latest stable release per platform, in the shape launchers before the
+/// update channel expect.
+async fn get_releases(State(state): State<AppState>) -> impl IntoResponse {
+    let mut latest: Option<releases::Release> = None;
+    let mut downloads = serde_json::Map::new();
+    for platform in releases::PLATFORMS {
+        let list = match releases::list(&state.db, releases::Channel::Stable, platform).await {
+            Ok(list) => list,
+            Err(e) => {
+                error!("Failed to load releases: {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "Failed to load releases" })));
+            }
+        };
+        if let Some(release) = list.into_iter().next() {
+            downloads.insert(platform.to_string(), serde_json::Value::String(release.url.clone()));
+            let newer = latest.as_ref().is_none_or(|current| {
+                matches!(
+                    (releases::Version::parse(&release.version), releases::Version::parse(&current.version)),
+                    (Ok(a), Ok(b)) if a > b
+                )
+            });
+            if newer {
+                latest = Some(release);
+            }
         }
-    }))
+    }
+
+    match latest {
+        Some(release) => (StatusCode::OK, Json(serde_json::json!({
+            "latest": {
+                "version": release.version,
+                "date": release.published_at.format("%Y-%m-%d").to_string(),
+                "downloads": downloads,
+                "changelog": release.changelog,
+            }
+        }))),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "No releases published" }))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseQuery {
+    channel: Option<String>,
+    platform: String,
+    /// The caller's version; `update_available` compares against it.
+    current: Option<String>,
+}
+
+fn parse_release_query(query: &ReleaseQuery) -> Result<releases::Channel, String> {
+    let channel = query.channel.as_deref().unwrap_or("stable");
+    let channel = releases::Channel::parse(channel)
+        .ok_or_else(|| format!("Unknown channel '{}', expected stable or beta", channel))?;
+    releases::validate_platform(&query.platform)?;
+    Ok(channel)
+}
+
+async fn get_latest_release(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<ReleaseQuery>,
+) -> impl IntoResponse {
+    let channel = match parse_release_query(&query) {
+        Ok(channel) => channel,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(e)),
+    };
+    let current = match query.current.as_deref().map(releases::Version::parse).transpose() {
+        Ok(current) => current,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::error(e)),
+    };
+
+    match releases::list(&state.db, channel, &query.platform).await {
+        Ok(list) => {
+            let latest = list.into_iter().next();
+            let update_available = match (&latest, &current) {
+                (Some(latest), Some(current)) => releases::Version::parse(&latest.version).is_ok_and(|v| v > *current),
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({
+                "channel": channel.as_str(),
+                "release": latest,
+                "update_available": update_available,
+            })))
+        }
+        Err(e) => {
+            error!("Failed to load releases: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load releases"))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangelogQuery {
+    channel: Option<String>,
+    platform: String,
+    since: String,
+}
+
+async fn get_release_changelog(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<ChangelogQuery>,
+) -> impl IntoResponse {
+    let release_query = ReleaseQuery { channel: query.channel, platform: query.platform, current: None };
+    let channel = match parse_release_query(&release_query) {
+        Ok(channel) => channel,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::<serde_json::Value>::error(e)),
+    };
+    let since = match releases::Version::parse(&query.since) {
+        Ok(since) => since,
+        Err(e) => return (StatusCode::BAD_REQUEST, ApiResponse::error(e)),
+    };
+
+    match releases::list(&state.db, channel, &release_query.platform).await {
+        Ok(list) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "since": query.since,
+            "entries": releases::changelog_since(&list, &since),
+        }))),
+        Err(e) => {
+            error!("Failed to load releases: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load releases"))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminPublishReleaseRequest {
+    admin_token: String,
+    #[serde(flatten)]
+    release: releases::PublishRelease,
+}
+
+async fn admin_publish_release(
+    State(state): State<AppState>,
+    Json(req): Json<AdminPublishReleaseRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+    if let Err(e) = req.release.validate() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    match releases::publish(&state.db, &req.release).await {
+        Ok(Some(release)) => {
+            info!("Admin published release {} ({}, {})", release.version, release.channel, release.platform);
+            (StatusCode::CREATED, ApiResponse::success(serde_json::to_value(release).unwrap_or_default()))
+        }
+        Ok(None) => (StatusCode::CONFLICT, ApiResponse::error("That version is already published for this platform")),
+        Err(e) => {
+            error!("Failed to publish release: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to publish release"))
+        }
+    }
+}
+
+async fn admin_yank_release(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<AdminTokenRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+
+    match releases::yank(&state.db, id).await {
+        Ok(true) => {
+            info!("Admin yanked release {}", id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({ "id": id, "yanked": true })))
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("No live release with that id")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to yank release")),
+    }
 }
 
 async fn ws_relay(
@@ -1342,21 +2885,207 @@ async fn ws_relay(
     ws.on_upgrade(move |socket| handle_relay_connection(socket, state))
 }
 
-async fn handle_relay_connection(socket: WebSocket, _state: AppState) {
+async fn handle_relay_connection(socket: WebSocket, state: AppState) {
     use futures_util::{SinkExt, StreamExt};
+    use relay::{Outbound, RelayJoin, RelayMessage};
     
     let (mut sender, mut receiver) = socket.split();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Outbound>();
+    
+    let send_task = tokio::spawn(async move {
+        while let Some(outbound) = rx.recv().await {
+            let message = match outbound {
+                Outbound::Text(text) => Message::Text(text),
+                Outbound::Binary(data) => Message::Binary(data),
+                Outbound::Close => {
+                    let _ = sender.send(Message::Close(None)).await;
+                    break;
+                }
+            };
+            if sender.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
     
-    while let Some(msg) = receiver.next().await {
-        if let Ok(Message::Text(text)) = msg {
-            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&text) {
-                let response = serde_json::json!({
-                    "type": "ack",
-                    "received": parsed
-                });
-                let _ = sender.send(Message::Text(response.to_string().into())).await;
+    let reply = |message: RelayMessage| {
+        let _ = tx.send(Outbound::Text(message.to_text()));
+    };
+    let error = |message: &str| reply(RelayMessage::Error { message: message.to_string() });
+    
+    // (user id, connection id) once joined
+    let mut member: Option<(Uuid, u64)> = None;
+    // (user id, subscription id) once subscribed to party chat
+    let mut party_chat: Option<(Uuid, u64)> = None;
+    // (user id, subscription id) once subscribed to notifications
+    let mut notification_feed: Option<(Uuid, u64)> = None;
+    
+    while let Some(Ok(msg)) = receiver.next().await {
+        match msg {
+            Message::Text(text) => match serde_json::from_str::<RelayMessage>(&text) {
+                Ok(RelayMessage::Join { session_id, user_id, token, password, public_key, .. }) => {
+                    if member.is_some() {
+                        error("Already joined a session");
+                        continue;
+                    }
+                    let Some(token) = token else {
+                        error("Join requires a session token");
+                        continue;
+                    };
+                    let Some(user) = validate_token(&state.db, &token).await else {
+                        error("Invalid session token");
+                        continue;
+                    };
+                    if user_id.is_some_and(|id| id != user.id) {
+                        error("user_id does not match the session token");
+                        continue;
+                    }
+                    let premium = is_premium_user(&state.db, user.id).await;
+                    
+                    let join = RelayJoin {
+                        session_id,
+                        user_id: user.id,
+                        username: user.display_name.unwrap_or(user.username),
+                        premium,
+                        password,
+                        public_key,
+                    };
+                    match state.relay.read().await.join_live(join, tx.clone()) {
+                        Ok(joined) => {
+                            info!("User {} joined relay session {}", user.id, joined.session_id);
+                            member = Some((user.id, joined.connection_id));
+                            reply(RelayMessage::PeerList { peers: joined.peers, host: Some(joined.host_id) });
+                        }
+                        Err(e) => error(&e.to_string()),
+                    }
+                }
+                Ok(RelayMessage::Data { to, payload, key_id, .. }) => match member {
+                    Some((user_id, _)) => {
+                        state.relay.read().await.relay_data(user_id, to, payload, key_id);
+                    }
+                    None => error("Join a session before sending data"),
+                },
+                Ok(RelayMessage::TransferHost { new_host }) => match member {
+                    Some((user_id, _)) => {
+                        if let Err(e) = state.relay.read().await.transfer_host(user_id, new_host) {
+                            error(&e.to_string());
+                        }
+                    }
+                    None => error("Join a session before handing off hosting"),
+                },
+                Ok(RelayMessage::SessionKey { to, key_id, ephemeral_key, sealed_key, .. }) => match member {
+                    Some((user_id, _)) => {
+                        state.relay.read().await.relay_session_key(user_id, to, key_id, ephemeral_key, sealed_key);
+                    }
+                    None => error("Join a session before sending keys"),
+                },
+                Ok(RelayMessage::PartySubscribe { token }) => {
+                    let user_id = match (token, member) {
+                        (Some(token), _) => validate_token(&state.db, &token).await.map(|u| u.id),
+                        (None, Some((user_id, _))) => Some(user_id),
+                        (None, None) => None,
+                    };
+                    let Some(user_id) = user_id else {
+                        error("Party chat requires a valid session token");
+                        continue;
+                    };
+                    if let Some((previous, subscription)) = party_chat.take() {
+                        state.parties.unsubscribe(previous, subscription);
+                    }
+                    party_chat = Some((user_id, state.parties.subscribe(user_id, tx.clone())));
+                }
+                Ok(RelayMessage::NotificationsSubscribe { token }) => {
+                    let user_id = match (token, member) {
+                        (Some(token), _) => validate_token(&state.db, &token).await.map(|u| u.id),
+                        (None, Some((user_id, _))) => Some(user_id),
+                        (None, None) => None,
+                    };
+                    let Some(user_id) = user_id else {
+                        error("Notifications require a valid session token");
+                        continue;
+                    };
+                    if let Some((previous, subscription)) = notification_feed.take() {
+                        state.notifications.unsubscribe(previous, subscription);
+                    }
+                    notification_feed = Some((user_id, state.notifications.subscribe(user_id, tx.clone())));
+                }
+                Ok(RelayMessage::PartyChat { party_id, text, .. }) => match party_chat {
+                    Some((user_id, _)) => {
+                        if let Err((_, e)) = send_party_chat(&state, user_id, party_id, &text).await {
+                            error(&e);
+                        }
+                    }
+                    None => error("Subscribe to party chat before sending"),
+                },
+                Ok(RelayMessage::Ping) => reply(RelayMessage::Pong),
+                Ok(RelayMessage::Leave { .. }) => break,
+                Ok(_) => {}
+                Err(_) => error("Invalid message"),
+            },
+            Message::Binary(data) => {
+                if let Some((user_id, _)) = member {
+                    state.relay.read().await.relay_binary(user_id, data);
+                }
             }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    
+    if let Some((user_id, connection_id)) = member {
+        state.relay.read().await.leave_live(user_id, connection_id);
+    }
+    if let Some((user_id, subscription)) = party_chat {
+        state.parties.unsubscribe(user_id, subscription);
+    }
+    if let Some((user_id, subscription)) = notification_feed {
+        state.notifications.unsubscribe(user_id, subscription);
+    }
+    send_task.abort();
+}
+
+#[derive(Debug, Deserialize)]
+struct RegisterRelaySessionRequest {
+    token: String,
+    invite_code: String,
+    max_peers: Option<u32>,
+    password: Option<String>,
+}
+
+/// Lets a launcher-hosted session use the central relay as a fallback.
+/// Guests join over `/api/v1/relay` with the invite code as the session id.
+async fn register_relay_session(
+    State(state): State<AppState>,
+    Json(req): Json<RegisterRelaySessionRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    
+    if !relay::is_invite_code(req.invite_code.trim()) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Invalid invite code"));
+    }
+    
+    let tier_cap = if is_premium_user(&state.db, user.id).await {
+        relay::PREMIUM_SESSION_PEERS
+    } else {
+        relay::FREE_SESSION_PEERS
+    };
+    let max_peers = req.max_peers.unwrap_or(tier_cap).clamp(2, tier_cap);
+    let password_protected = req.password.is_some();
+    
+    match state.relay.read().await.register_session(&req.invite_code, user.id, max_peers, req.password) {
+        Ok(session) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "session_id": session.id,
+            "max_peers": session.max_peers,
+            "password_protected": password_protected,
+            "relay_path": "/api/v1/relay",
+        }))),
+        Err(relay::RelayError::Unauthorized) => {
+            (StatusCode::CONFLICT, ApiResponse::error("Invite code is registered to another host"))
         }
+        Err(e) => (StatusCode::BAD_REQUEST, ApiResponse::error(e.to_string())),
     }
 }
 
@@ -1379,10 +3108,35 @@ async fn main() {
     info!("Running migrations...");
     run_migrations(&db).await;
     
+    server_metrics::spawn_sweeper(db.clone(), server_metrics::HeartbeatConfig::from_env());
+    let account_deletion = account::DeletionConfig::from_env();
+    account::spawn_finalizer(db.clone(), account_deletion.clone());
+    notifications::spawn_retention(db.clone());
+    let notification_hub = Arc::new(NotificationHub::new());
+    let payout_config = payouts::PayoutConfig::from_env();
+    payouts::spawn_auto_release(db.clone(), notification_hub.clone(), payout_config.clone());
+    let friend_requests = friends::RequestConfig::from_env();
+    friends::spawn_request_expiry(db.clone(), friend_requests.clone());
+    let email_config = email::EmailConfig::from_env();
+    let mailer = email_config.mailer().map(|m| Arc::new(m) as Arc<dyn email::Mailer>);
+    if mailer.is_none() {
+        warn!("SMTP_SERVER not set; email verification is unavailable");
+    }
+    
     let state = AppState {
         db,
-        relay: Arc::new(RwLock::new(RelayHub::new())),
+        relay: Arc::new(RwLock::new(RelayHub::new().with_limits(RelayLimits::from_env()))),
+        parties: Arc::new(PartyHub::new(Box::new(party::WordMaskFilter::from_env()))),
+        notifications: notification_hub,
         verification: Arc::new(VerificationService::new()),
+        auth_limiter: Arc::new(AuthRateLimiter::new(RateLimitConfig::from_env())),
+        account_deletion,
+        payouts: payout_config,
+        payout_transfer: Arc::new(payouts::ManualTransfer),
+        presence: Arc::new(ownership::PresenceHints::new()),
+        email: email_config,
+        mailer,
+        friend_requests,
     };
     
     let cors = CorsLayer::new()
@@ -1390,16 +3144,33 @@ async fn main() {
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
         .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]);
     
+    // Brute-force protection for the credential endpoints
+    let auth_routes = Router::new()
+        .route("/api/v1/auth/signup", post(signup))
+        .route("/api/v1/auth/login", post(login))
+        .route("/api/v1/admin/login", post(admin_login))
+        .route("/api/v1/auth/delete-account", post(delete_account))
+        .route("/api/v1/auth/change-password", post(change_password))
+        .route_layer(middleware::from_fn_with_state(
+            state.auth_limiter.clone(),
+            rate_limit::limit_auth_requests,
+        ));
+    
     let app = Router::new()
         .route("/health", get(health))
         .route("/api/v1/releases", get(get_releases))
+        .route("/api/v1/releases/latest", get(get_latest_release))
+        .route("/api/v1/releases/changelog", get(get_release_changelog))
         .route("/api/v1/pricing", get(get_pricing))
         .route("/api/v1/features", post(get_feature_gates))
         // Auth
-        .route("/api/v1/auth/signup", post(signup))
-        .route("/api/v1/auth/login", post(login))
         .route("/api/v1/auth/logout", post(logout))
         .route("/api/v1/auth/me", post(get_me))
+        .route("/api/v1/auth/export-data", post(export_data))
+        .route("/api/v1/auth/sessions", post(list_sessions))
+        .route("/api/v1/auth/sessions/revoke", post(revoke_session))
+        .route("/api/v1/auth/email/request-verification", post(request_email_verification))
+        .route("/api/v1/auth/email/confirm", post(confirm_email))
         .route("/api/v1/profile", post(update_profile))
         // Friends
         .route("/api/v1/friends", post(get_friends))
@@ -1407,48 +3178,94 @@ async fn main() {
         .route("/api/v1/friends/accept", post(accept_friend_request))
         .route("/api/v1/friends/decline", post(decline_friend_request))
         .route("/api/v1/friends/pending", post(get_pending_requests))
+        // Notifications
+        .route("/api/v1/notifications", post(list_notifications))
+        .route("/api/v1/notifications/read", post(mark_notification_read))
+        .route("/api/v1/notifications/read-all", post(mark_all_notifications_read))
+        .route("/api/v1/notifications/delete", post(delete_notification))
         .route("/api/v1/users/search/:query", get(search_users))
+        .route("/api/v1/users/block", post(block_user))
+        .route("/api/v1/users/unblock", post(unblock_user))
+        .route("/api/v1/users/blocked", post(list_blocked_users))
         // Server Browser
         .route("/api/v1/servers", get(list_servers))
         .route("/api/v1/servers/register", post(register_server))
         .route("/api/v1/servers/heartbeat", post(server_heartbeat))
+        .route("/api/v1/servers/deregister", post(deregister_server))
+        .route("/api/v1/servers/:id", get(get_server))
+        .route("/api/v1/servers/:id", axum::routing::patch(update_server))
+        .route("/api/v1/servers/:id", axum::routing::delete(delete_server))
+        .route("/api/v1/servers/:id/metrics", get(get_server_metrics))
+        .route("/api/v1/servers/:id/transfer", post(transfer_server))
+        .route("/api/v1/servers/:id/transfer/accept", post(accept_server_transfer))
+        .route("/api/v1/servers/:id/transfer/cancel", post(cancel_server_transfer))
+        .route("/api/v1/servers/:id/token", post(issue_server_token))
+        .route("/api/v1/servers/:id/tokens", post(list_server_tokens))
+        .route("/api/v1/servers/:id/token/revoke", post(revoke_server_token))
         // Game Stats
         .route("/api/v1/stats", post(get_game_stats))
         .route("/api/v1/stats/session", post(record_session))
+        .route("/api/v1/stats/leaderboard", get(get_leaderboard))
+        .route("/api/v1/stats/breakdown", post(get_stats_breakdown))
+        .route("/api/v1/achievements", get(list_achievements))
+        .route("/api/v1/achievements/mine", post(list_my_achievements))
         // Mod Profiles
         .route("/api/v1/mods/profiles", post(get_mod_profiles))
         .route("/api/v1/mods/profiles/create", post(create_mod_profile))
+        .route("/api/v1/mods/profiles/upsert", post(upsert_mod_profile))
         .route("/api/v1/mods/profiles/activate", post(activate_mod_profile))
         // Performance Settings
         .route("/api/v1/performance", post(get_performance_settings))
         .route("/api/v1/performance/update", post(update_performance_settings))
+        // Settings Sync
+        .route("/api/v1/sync/pull", post(sync_pull))
+        .route("/api/v1/sync/push", post(sync_push))
         // Subscription
         .route("/api/v1/subscription", post(get_subscription))
         .route("/api/v1/subscription/checkout", post(create_checkout))
         .route("/api/v1/subscription/manage", post(manage_subscription))
+        .route("/api/v1/stripe/webhook", post(stripe_webhook))
         // Marketplace
         .route("/api/v1/marketplace/items", get(list_marketplace_items))
         .route("/api/v1/marketplace/items", post(create_marketplace_item))
+        .route("/api/v1/marketplace/items/mine", post(list_my_marketplace_items))
         .route("/api/v1/marketplace/items/:id", get(get_marketplace_item))
+        .route("/api/v1/marketplace/items/:id/resubmit", post(resubmit_marketplace_item))
         .route("/api/v1/marketplace/items/:id/like", post(like_marketplace_item))
         .route("/api/v1/marketplace/items/:id/download", post(download_marketplace_item))
         .route("/api/v1/marketplace/items/:id/purchase", post(purchase_marketplace_item))
         .route("/api/v1/marketplace/purchase/:escrow_id/confirm", post(confirm_purchase))
         .route("/api/v1/marketplace/purchases", post(get_user_purchases))
+        .route("/api/v1/marketplace/earnings", get(get_seller_earnings))
+        .route("/api/v1/marketplace/payouts", get(get_seller_payouts))
         // Admin Marketplace
-        .route("/api/v1/admin/login", post(admin_login))
         .route("/api/v1/admin/marketplace/items", post(admin_create_marketplace_item))
         .route("/api/v1/admin/marketplace/items", get(admin_list_all_items))
         .route("/api/v1/admin/marketplace/items/:id", axum::routing::put(admin_update_marketplace_item))
         .route("/api/v1/admin/marketplace/items/:id", axum::routing::delete(admin_delete_marketplace_item))
+        .route("/api/v1/admin/marketplace/moderation", post(admin_moderation_queue))
+        .route("/api/v1/admin/marketplace/items/:id/approve", post(admin_approve_marketplace_item))
+        .route("/api/v1/admin/marketplace/items/:id/reject", post(admin_reject_marketplace_item))
+        .route("/api/v1/admin/achievements", post(admin_create_achievement))
+        .route("/api/v1/admin/achievements/:id/retire", post(admin_retire_achievement))
+        .route("/api/v1/admin/releases", post(admin_publish_release))
+        .route("/api/v1/admin/releases/:id/yank", post(admin_yank_release))
         .route("/api/v1/admin/escrow", post(admin_list_escrow_transactions))
         .route("/api/v1/admin/escrow/release", post(admin_release_escrow))
+        .route("/api/v1/admin/payouts", post(admin_list_owed_sellers))
+        .route("/api/v1/admin/payouts/create", post(admin_create_payout))
+        .route("/api/v1/admin/payouts/mark-paid", post(admin_mark_payout_paid))
         // Cosmetics
         .route("/api/v1/cosmetics", post(get_user_cosmetics))
         .route("/api/v1/cosmetics/equip", post(equip_cosmetic))
         .route("/api/v1/cosmetics/unequip", post(unequip_cosmetic))
         .route("/api/v1/cosmetics/equipped", post(get_equipped_cosmetics))
         .route("/api/v1/cosmetics/user", post(get_public_user_cosmetics))
+        .route("/api/v1/cosmetics/verify", post(verify_cosmetics))
+        .route("/api/v1/cosmetics/loadouts", post(list_cosmetic_loadouts))
+        .route("/api/v1/cosmetics/loadouts/save", post(save_cosmetic_loadout))
+        .route("/api/v1/cosmetics/loadouts/apply", post(apply_cosmetic_loadout))
+        .route("/api/v1/cosmetics/loadouts/delete", post(delete_cosmetic_loadout))
         // Verification
         .route("/api/v1/verification/methods", get(get_verification_methods))
         .route("/api/v1/verification/start", post(start_verification))
@@ -1457,6 +3274,7 @@ async fn main() {
         .route("/api/v1/verification/admin/resolve", post(admin_resolve_verification))
         // Relay
         .route("/api/v1/relay", get(ws_relay))
+        .route("/api/v1/relay/sessions", post(register_relay_session))
         // Rubidium API - Feature Toggles
         .route("/api/v1/rubidium/features", post(get_rubidium_features))
         .route("/api/v1/rubidium/features/toggle", post(toggle_rubidium_feature))
@@ -1478,17 +3296,23 @@ async fn main() {
         .route("/api/v1/rubidium/social/party/join", post(join_party))
         .route("/api/v1/rubidium/social/party/leave", post(leave_party))
         .route("/api/v1/rubidium/social/party/invite", post(invite_to_party))
+        .route("/api/v1/rubidium/social/party/chat/send", post(party_chat_send))
+        .route("/api/v1/rubidium/social/party/chat/history", get(party_chat_history))
         .route("/api/v1/rubidium/social/presence", post(update_presence))
         // Rubidium API - Cinema Camera
         .route("/api/v1/rubidium/cinema/paths", post(list_camera_paths))
         .route("/api/v1/rubidium/cinema/paths/create", post(create_camera_path))
+        .route("/api/v1/rubidium/cinema/paths/get", post(get_camera_path))
+        .route("/api/v1/rubidium/cinema/paths/update", post(update_camera_path))
         .route("/api/v1/rubidium/cinema/paths/delete", post(delete_camera_path))
+        .route("/api/v1/rubidium/cinema/paths/export", post(export_camera_path))
         // Rubidium API - Anticheat
         .route("/api/v1/rubidium/anticheat/status", post(get_anticheat_status))
         .route("/api/v1/rubidium/anticheat/report", post(report_violation))
         // Rubidium API - Plugins
         .route("/api/v1/rubidium/plugins", post(list_server_plugins))
         .route("/api/v1/rubidium/plugins/config", post(get_plugin_config))
+        .merge(auth_routes)
         .layer(cors)
         .with_state(state);
     
@@ -1496,7 +3320,7 @@ async fn main() {
     info!("Yellow Tale API Server starting on {}", addr);
     
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
 async fn list_marketplace_items(
@@ -1513,34 +3337,66 @@ async fn list_marketplace_items(
     }).unwrap_or("all");
     
     let search_pattern = params.q.as_ref().map(|q| format!("%{}%", q));
+    let page = pagination::PageRequest::new(params.page, params.per_page);
     
+    // Every order ends on the id so pages never shuffle ties between them
+    let by_downloads = params.sort.as_deref() == Some("downloads");
     let order_clause = match params.sort.as_deref() {
-        Some("downloads") => "m.downloads DESC",
-        Some("newest") => "m.created_at DESC",
-        Some("price_low") => "m.price ASC",
-        Some("price_high") => "m.price DESC",
-        _ => "m.downloads DESC, m.likes DESC",
+        Some("downloads") => "m.downloads DESC, m.id DESC",
+        Some("newest") => "m.created_at DESC, m.id",
+        Some("price_low") => "m.price ASC, m.id",
+        Some("price_high") => "m.price DESC, m.id",
+        _ => "m.downloads DESC, m.likes DESC, m.id",
     };
     
-    let query = format!(
-        "SELECT m.id, m.name, m.description, m.category, m.price, m.downloads, m.likes, 
-                m.tags, m.thumbnail_url, m.file_url, m.is_featured, m.created_at,
-                u.id as author_id, u.username, u.display_name
-         FROM marketplace_items m
+    // The downloads sort pages by keyset so deep pages don't scan past every
+    // earlier row; the cursor replaces the page's offset
+    let after = match params.cursor.as_deref().filter(|_| by_downloads).map(pagination::DownloadsCursor::decode) {
+        Some(None) => return (StatusCode::BAD_REQUEST, ApiResponse::error("Invalid cursor")),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let offset = if after.is_some() { 0 } else { page.offset() };
+    
+    let filters = "FROM marketplace_items m
          JOIN users u ON m.author_id = u.id
          WHERE ($1::text IS NULL OR m.category = $1)
            AND (($2 = 'all') OR ($2 = 'free' AND m.price = 0) OR ($2 = 'paid' AND m.price > 0))
            AND ($3::text IS NULL OR m.name ILIKE $3 OR m.description ILIKE $3)
-         ORDER BY {} LIMIT 100", order_clause
+           AND m.status = 'active'";
+    let query = format!(
+        "SELECT m.id, m.name, m.description, m.category, m.price, m.downloads, m.likes, 
+                m.tags, m.thumbnail_url, m.file_url, m.is_featured, m.created_at,
+                u.id as author_id, u.username, u.display_name
+         {}
+           AND ($4::bigint IS NULL OR (m.downloads, m.id) < ($4, $5))
+         ORDER BY {} LIMIT $6 OFFSET $7", filters, order_clause
     );
     
-    let rows = sqlx::query_as::<_, (Uuid, String, String, String, f64, i64, i64, serde_json::Value, Option<String>, Option<String>, bool, chrono::DateTime<chrono::Utc>, Uuid, String, Option<String>)>(&query)
+    let mut rows = sqlx::query_as::<_, (Uuid, String, String, String, f64, i64, i64, serde_json::Value, Option<String>, Option<String>, bool, chrono::DateTime<chrono::Utc>, Uuid, String, Option<String>)>(&query)
         .bind(category_filter)
         .bind(price_filter)
         .bind(&search_pattern)
+        .bind(after.map(|a| a.downloads))
+        .bind(after.map(|a| a.id))
+        .bind(page.limit() + 1)
+        .bind(offset)
         .fetch_all(&state.db)
         .await
         .unwrap_or_default();
+    let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) {}", filters))
+        .bind(category_filter)
+        .bind(price_filter)
+        .bind(&search_pattern)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+    
+    let more = rows.len() > page.per_page as usize;
+    rows.truncate(page.per_page as usize);
+    let next_cursor = rows.last()
+        .filter(|_| by_downloads && more)
+        .map(|row| pagination::DownloadsCursor { downloads: row.5, id: row.0 }.encode());
     
     let items: Vec<MarketplaceItem> = rows.into_iter().map(|(id, name, description, category, price, downloads, likes, tags_json, thumbnail_url, file_url, is_featured, created_at, author_id, username, display_name)| {
         let tags: Vec<String> = serde_json::from_value(tags_json).unwrap_or_default();
@@ -1557,11 +3413,18 @@ async fn list_marketplace_items(
             thumbnail_url,
             file_url,
             is_featured,
+            status: moderation::STATUS_ACTIVE.to_string(),
             created_at,
         }
     }).collect();
     
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({"items": items})))
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({
+        "items": items,
+        "total": total,
+        "page": page.page,
+        "per_page": page.per_page,
+        "next_cursor": next_cursor
+    })))
 }
 
 async fn create_marketplace_item(
@@ -1591,13 +3454,17 @@ async fn create_marketplace_item(
         return (StatusCode::BAD_REQUEST, ApiResponse::error("Price must be 0-99.99"));
     }
     
+    if state.email.require_verified_for_listings && !email::is_verified(&state.db, user.id).await.unwrap_or(false) {
+        return (StatusCode::FORBIDDEN, ApiResponse::error("Verify your email address before listing items"));
+    }
+    
     let item_id = Uuid::new_v4();
     let now = chrono::Utc::now();
     let tags_json = serde_json::to_value(&req.tags).unwrap_or(serde_json::json!([]));
     
     let result = sqlx::query(
-        "INSERT INTO marketplace_items (id, name, description, category, author_id, price, downloads, likes, tags, is_featured, created_at)
-         VALUES ($1, $2, $3, $4, $5, $6, 0, 0, $7, false, $8)"
+        "INSERT INTO marketplace_items (id, name, description, category, author_id, price, downloads, likes, tags, file_url, is_featured, status, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, 0, 0, $7, $8, false, $9, $10)"
     )
         .bind(item_id)
         .bind(&req.name)
@@ -1606,12 +3473,16 @@ async fn create_marketplace_item(
         .bind(user.id)
         .bind(req.price)
         .bind(&tags_json)
+        .bind(&req.file_url)
+        .bind(moderation::STATUS_PENDING)
         .bind(now)
         .execute(&state.db)
         .await;
     
     match result {
         Ok(_) => {
+            tokio::spawn(moderation::review_item(state.db.clone(), item_id, moderation::auto_approve_enabled()));
+            
             let item = MarketplaceItem {
                 id: item_id,
                 name: req.name,
@@ -1623,8 +3494,9 @@ async fn create_marketplace_item(
                 likes: 0,
                 tags: req.tags,
                 thumbnail_url: None,
-                file_url: None,
+                file_url: req.file_url,
                 is_featured: false,
+                status: moderation::STATUS_PENDING.to_string(),
                 created_at: now,
             };
             (StatusCode::CREATED, ApiResponse::success(item))
@@ -1636,6 +3508,153 @@ async fn create_marketplace_item(
     }
 }
 
+async fn moderation_checks_for(db: &PgPool, item_id: Uuid) -> Vec<serde_json::Value> {
+    sqlx::query_as::<_, (String, bool, Option<String>, chrono::DateTime<chrono::Utc>)>(
+        "SELECT check_name, passed, detail, created_at FROM moderation_checks WHERE item_id = $1 ORDER BY created_at, check_name"
+    )
+        .bind(item_id)
+        .fetch_all(db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(check, passed, detail, checked_at)| serde_json::json!({
+            "check": check,
+            "passed": passed,
+            "detail": detail,
+            "checked_at": checked_at
+        }))
+        .collect()
+}
+
+/// The caller's own items in every moderation state, with check results.
+async fn list_my_marketplace_items(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    let rows = sqlx::query_as::<_, (Uuid, String, String, String, f64, Option<String>, String, Option<String>, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, name, description, category, price, file_url, status, moderation_reason, created_at
+         FROM marketplace_items WHERE author_id = $1 ORDER BY created_at DESC"
+    )
+        .bind(user.id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let mut items = Vec::with_capacity(rows.len());
+    for (id, name, description, category, price, file_url, status, moderation_reason, created_at) in rows {
+        items.push(serde_json::json!({
+            "id": id,
+            "name": name,
+            "description": description,
+            "category": category,
+            "price": price,
+            "file_url": file_url,
+            "status": status,
+            "moderation_reason": moderation_reason,
+            "checks": moderation_checks_for(&state.db, id).await,
+            "created_at": created_at
+        }));
+    }
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "items": items })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ResubmitMarketplaceItemRequest {
+    token: String,
+    name: Option<String>,
+    description: Option<String>,
+    price: Option<f64>,
+    tags: Option<Vec<String>>,
+    file_url: Option<String>,
+}
+
+/// Lets the author edit a rejected item and send it back through review.
+async fn resubmit_marketplace_item(
+    State(state): State<AppState>,
+    Path(item_id): Path<Uuid>,
+    Json(req): Json<ResubmitMarketplaceItemRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    let existing = sqlx::query_as::<_, (String, String, f64, serde_json::Value, Option<String>, String)>(
+        "SELECT name, description, price, tags, file_url, status FROM marketplace_items WHERE id = $1 AND author_id = $2"
+    )
+        .bind(item_id)
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten();
+
+    let Some((name, description, price, tags, file_url, status)) = existing else {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("Item not found"));
+    };
+
+    if status != moderation::STATUS_REJECTED {
+        return (StatusCode::CONFLICT, ApiResponse::error("Only rejected items can be resubmitted"));
+    }
+
+    let name = req.name.unwrap_or(name);
+    let description = req.description.unwrap_or(description);
+    let price = req.price.unwrap_or(price);
+    let tags = req.tags.map(|t| serde_json::to_value(t).unwrap_or_default()).unwrap_or(tags);
+    let file_url = req.file_url.or(file_url);
+
+    if name.len() < 3 || name.len() > 100 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Name must be 3-100 characters"));
+    }
+    if description.len() > 2000 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Description too long"));
+    }
+    if !(0.0..=99.99).contains(&price) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Price must be 0-99.99"));
+    }
+
+    let result = async {
+        let mut tx = state.db.begin().await?;
+        sqlx::query("DELETE FROM moderation_checks WHERE item_id = $1")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE marketplace_items SET name = $1, description = $2, price = $3, tags = $4, file_url = $5,
+                    file_hash = NULL, status = $6, moderation_reason = NULL
+             WHERE id = $7"
+        )
+            .bind(&name)
+            .bind(&description)
+            .bind(price)
+            .bind(&tags)
+            .bind(&file_url)
+            .bind(moderation::STATUS_PENDING)
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }.await;
+
+    if let Err(e) = result {
+        error!("Failed to resubmit marketplace item: {}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to resubmit item"));
+    }
+
+    tokio::spawn(moderation::review_item(state.db.clone(), item_id, moderation::auto_approve_enabled()));
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({
+        "id": item_id,
+        "status": moderation::STATUS_PENDING
+    })))
+}
+
 async fn get_marketplace_item(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
@@ -1646,7 +3665,7 @@ async fn get_marketplace_item(
                 u.id as author_id, u.username, u.display_name
          FROM marketplace_items m
          JOIN users u ON m.author_id = u.id
-         WHERE m.id = $1"
+         WHERE m.id = $1 AND m.status = 'active'"
     )
         .bind(id)
         .fetch_optional(&state.db)
@@ -1668,6 +3687,7 @@ async fn get_marketplace_item(
                 thumbnail_url,
                 file_url,
                 is_featured,
+                status: moderation::STATUS_ACTIVE.to_string(),
                 created_at,
             };
             (StatusCode::OK, ApiResponse::success(item))
@@ -1687,6 +3707,18 @@ async fn like_marketplace_item(
     }
     let user = user.unwrap();
     
+    let listed = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM marketplace_items WHERE id = $1 AND status = 'active'"
+    )
+        .bind(id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+    
+    if listed == 0 {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("Item not found"));
+    }
+    
     let existing = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM marketplace_likes WHERE user_id = $1 AND item_id = $2"
     )
@@ -1727,7 +3759,7 @@ async fn download_marketplace_item(
     let user = user.unwrap();
     
     let item = sqlx::query_as::<_, (f64, Option<String>)>(
-        "SELECT price, file_url FROM marketplace_items WHERE id = $1"
+        "SELECT price, file_url FROM marketplace_items WHERE id = $1 AND status = 'active'"
     )
         .bind(id)
         .fetch_optional(&state.db)
@@ -1774,6 +3806,8 @@ struct EquipCosmeticRequest {
     token: String,
     item_id: String,
     slot: String,
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1851,8 +3885,7 @@ async fn equip_cosmetic(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
     };
 
-    let valid_slots = ["skin", "emote_1", "emote_2", "emote_3", "emote_4", "cape", "wings", "aura"];
-    if !valid_slots.contains(&req.slot.as_str()) {
+    if !cosmetics::is_valid_slot(&req.slot) {
         return (StatusCode::BAD_REQUEST, ApiResponse::error("Invalid slot"));
     }
 
@@ -1861,30 +3894,50 @@ async fn equip_cosmetic(
         Err(_) => return (StatusCode::BAD_REQUEST, ApiResponse::error("Invalid item ID")),
     };
 
-    let owned = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM marketplace_purchases WHERE user_id = $1 AND item_id = $2"
+    if !can_equip_cosmetic(&state.db, user.id, item_uuid).await {
+        return (StatusCode::FORBIDDEN, ApiResponse::error("You don't own this item"));
+    }
+
+    let conflict_slots: Vec<String> = cosmetics::conflicting_slots(&req.slot)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let conflicts = sqlx::query_as::<_, (String, String)>(
+        "SELECT slot, item_id FROM user_equipped_cosmetics WHERE user_id = $1 AND slot = ANY($2)"
     )
         .bind(user.id)
-        .bind(item_uuid)
-        .fetch_one(&state.db)
+        .bind(&conflict_slots)
+        .fetch_all(&state.db)
         .await
-        .unwrap_or(0);
+        .unwrap_or_default();
 
-    let is_free = sqlx::query_scalar::<_, f64>(
-        "SELECT COALESCE(price, 0) FROM marketplace_items WHERE id = $1"
-    )
-        .bind(item_uuid)
-        .fetch_optional(&state.db)
-        .await
-        .ok()
-        .flatten()
-        .unwrap_or(1.0);
+    if !conflicts.is_empty() && !req.force {
+        let conflicts_with: Vec<_> = conflicts.iter()
+            .map(|(slot, item_id)| serde_json::json!({ "slot": slot, "item_id": item_id }))
+            .collect();
+        return (StatusCode::CONFLICT, ApiResponse::error_with_data(
+            "Item conflicts with an equipped cosmetic",
+            serde_json::json!({ "conflicts_with": conflicts_with }),
+        ));
+    }
 
-    if owned == 0 && is_free > 0.0 {
-        return (StatusCode::FORBIDDEN, ApiResponse::error("You don't own this item"));
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to equip item")),
+    };
+
+    if !conflicts.is_empty() {
+        let removed = sqlx::query("DELETE FROM user_equipped_cosmetics WHERE user_id = $1 AND slot = ANY($2)")
+            .bind(user.id)
+            .bind(&conflict_slots)
+            .execute(&mut *tx)
+            .await;
+        if removed.is_err() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to equip item"));
+        }
     }
 
-    let _ = sqlx::query(
+    let inserted = sqlx::query(
         "INSERT INTO user_equipped_cosmetics (user_id, slot, item_id)
          VALUES ($1, $2, $3)
          ON CONFLICT (user_id, slot) DO UPDATE SET item_id = $3"
@@ -1892,10 +3945,130 @@ async fn equip_cosmetic(
         .bind(user.id)
         .bind(&req.slot)
         .bind(&req.item_id)
-        .execute(&state.db)
+        .execute(&mut *tx)
+        .await;
+
+    if inserted.is_err() || tx.commit().await.is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to equip item"));
+    }
+
+    let unequipped: Vec<String> = conflicts.into_iter().map(|(slot, _)| slot).collect();
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({
+        "equipped": true,
+        "unequipped": unequipped,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyCosmeticsRequest {
+    /// A scoped `srv_` token for `server_id`
+    token: String,
+    server_id: Uuid,
+    items: Vec<ownership::OwnershipClaim>,
+}
+
+/// Lets a registered server check that its players own the cosmetics they
+/// wear. Players not on that server are left out of the results.
+async fn verify_cosmetics(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyCosmeticsRequest>,
+) -> impl IntoResponse {
+    if !server_owners::is_server_token(&req.token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("A scoped server token is required"));
+    }
+    let token = match server_owners::find_token(&state.db, &req.token).await {
+        Ok(Some(token)) => token,
+        Ok(None) => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid token")),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to verify cosmetics")),
+    };
+    if let Err(e) = server_owners::authorize_server_token(req.server_id, &token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::error(e.to_string()));
+    }
+    if req.items.len() > ownership::MAX_VERIFY_ITEMS {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(format!(
+            "At most {} items can be verified at once", ownership::MAX_VERIFY_ITEMS
+        )));
+    }
+    
+    let now = chrono::Utc::now();
+    let claims = ownership::answerable(&req.items, req.server_id, &state.presence, now);
+    let (user_ids, item_ids): (Vec<Uuid>, Vec<Uuid>) = claims.iter().map(|c| (c.user_id, c.item_id)).unzip();
+    
+    let purchased = sqlx::query_as::<_, (Uuid, Uuid)>(
+        "SELECT p.user_id, p.item_id FROM marketplace_purchases p
+         JOIN UNNEST($1::uuid[], $2::uuid[]) AS c(user_id, item_id)
+           ON p.user_id = c.user_id AND p.item_id = c.item_id"
+    )
+        .bind(&user_ids)
+        .bind(&item_ids)
+        .fetch_all(&state.db)
+        .await;
+    let free = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM marketplace_items WHERE id = ANY($1) AND status = 'active' AND COALESCE(price, 0) <= 0"
+    )
+        .bind(&item_ids)
+        .fetch_all(&state.db)
         .await;
+    let (purchased, free) = match (purchased, free) {
+        (Ok(purchased), Ok(free)) => (
+            purchased.into_iter().collect::<std::collections::HashSet<_>>(),
+            free.into_iter().collect::<std::collections::HashSet<_>>(),
+        ),
+        _ => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to verify cosmetics")),
+    };
+    
+    let token_hash = hash_token(&req.token);
+    let expires_at = ownership::expiry(now);
+    let results: Vec<_> = claims.into_iter()
+        .map(|claim| {
+            let owned = purchased.contains(&(claim.user_id, claim.item_id)) || free.contains(&claim.item_id);
+            ownership::OwnershipResult::signed(req.server_id, claim, owned, expires_at, &token_hash)
+        })
+        .collect();
+    
+    if let Err(e) = server_owners::touch_token(&state.db, token.id, now).await {
+        error!("Failed to record server token use: {}", e);
+    }
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({
+        "results": results,
+        "expires_at": expires_at,
+    })))
+}
+
+async fn can_equip_cosmetic(db: &PgPool, user_id: Uuid, item_id: Uuid) -> bool {
+    let owned = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM marketplace_purchases WHERE user_id = $1 AND item_id = $2"
+    )
+        .bind(user_id)
+        .bind(item_id)
+        .fetch_one(db)
+        .await
+        .unwrap_or(0);
+
+    let is_free = sqlx::query_scalar::<_, f64>(
+        "SELECT COALESCE(price, 0) FROM marketplace_items WHERE id = $1 AND status = 'active'"
+    )
+        .bind(item_id)
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(1.0);
+
+    owned > 0 || is_free <= 0.0
+}
 
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "equipped": true })))
+async fn is_premium_user(db: &PgPool, user_id: Uuid) -> bool {
+    sqlx::query_scalar::<_, String>(
+        "SELECT tier FROM subscriptions WHERE user_id = $1 AND status = 'active'"
+    )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .map(|tier| tier == "premium")
+        .unwrap_or(false)
 }
 
 async fn unequip_cosmetic(
@@ -1944,58 +4117,294 @@ async fn get_equipped_cosmetics(
         "aura": null
     });
 
-    for (slot, item_id) in equipped {
-        cosmetics[slot] = serde_json::Value::String(item_id);
+    for (slot, item_id) in equipped {
+        cosmetics[slot] = serde_json::Value::String(item_id);
+    }
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "equipped": cosmetics })))
+}
+
+#[derive(Debug, Deserialize)]
+struct GetUserCosmeticsRequest {
+    user_id: Uuid,
+    /// The viewer's session. Blocked viewers get the same 404 as for a
+    /// missing user.
+    token: Option<String>,
+}
+
+async fn get_public_user_cosmetics(
+    State(state): State<AppState>,
+    Json(req): Json<GetUserCosmeticsRequest>,
+) -> impl IntoResponse {
+    let user_exists = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM users WHERE id = $1"
+    )
+        .bind(req.user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+
+    if user_exists == 0 {
+        return (StatusCode::NOT_FOUND, ApiResponse::error(social_guard::HIDDEN));
+    }
+    if let Some(token) = &req.token {
+        let Some(viewer) = validate_token(&state.db, token).await else {
+            return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid token"));
+        };
+        if !social_guard::allows(&state.db, viewer.id, req.user_id).await {
+            return (StatusCode::NOT_FOUND, ApiResponse::error(social_guard::HIDDEN));
+        }
+    }
+
+    let equipped = sqlx::query_as::<_, (String, String)>(
+        "SELECT slot, item_id FROM user_equipped_cosmetics WHERE user_id = $1"
+    )
+        .bind(req.user_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let mut cosmetics = serde_json::json!({
+        "skin": null,
+        "emote_1": null,
+        "emote_2": null,
+        "emote_3": null,
+        "emote_4": null,
+        "cape": null,
+        "wings": null,
+        "aura": null
+    });
+
+    for (slot, item_id) in equipped {
+        cosmetics[slot.clone()] = serde_json::Value::String(item_id);
+    }
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "equipped": cosmetics, "user_id": req.user_id })))
+}
+
+#[derive(Debug, Deserialize)]
+struct SaveLoadoutRequest {
+    token: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoadoutActionRequest {
+    token: String,
+    loadout_id: Uuid,
+}
+
+async fn list_cosmetic_loadouts(
+    State(state): State<AppState>,
+    Json(req): Json<CosmeticsRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
+    };
+
+    let rows = sqlx::query_as::<_, (Uuid, String, serde_json::Value, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, name, slots, updated_at FROM cosmetic_loadouts WHERE user_id = $1 ORDER BY name"
+    )
+        .bind(user.id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let loadouts: Vec<_> = rows.into_iter().map(|(id, name, slots, updated_at)| {
+        serde_json::json!({
+            "id": id,
+            "name": name,
+            "slots": slots,
+            "updated_at": updated_at,
+        })
+    }).collect();
+
+    let limit = cosmetics::loadout_limit(is_premium_user(&state.db, user.id).await);
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "loadouts": loadouts, "limit": limit })))
+}
+
+async fn save_cosmetic_loadout(
+    State(state): State<AppState>,
+    Json(req): Json<SaveLoadoutRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
+    };
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 64 {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Loadout name must be 1-64 characters"));
+    }
+
+    let exists = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM cosmetic_loadouts WHERE user_id = $1 AND name = $2"
+    )
+        .bind(user.id)
+        .bind(name)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0) > 0;
+
+    if !exists {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM cosmetic_loadouts WHERE user_id = $1")
+            .bind(user.id)
+            .fetch_one(&state.db)
+            .await
+            .unwrap_or(0);
+        let limit = cosmetics::loadout_limit(is_premium_user(&state.db, user.id).await);
+        if count >= limit {
+            return (StatusCode::FORBIDDEN, ApiResponse::error(format!("Loadout limit reached ({})", limit)));
+        }
+    }
+
+    let equipped = sqlx::query_as::<_, (String, String)>(
+        "SELECT slot, item_id FROM user_equipped_cosmetics WHERE user_id = $1"
+    )
+        .bind(user.id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let slots: Vec<cosmetics::LoadoutSlot> = equipped.into_iter()
+        .map(|(slot, item_id)| cosmetics::LoadoutSlot { slot, item_id })
+        .collect();
+    let slots_json = serde_json::to_value(&slots).unwrap_or_default();
+
+    let result = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO cosmetic_loadouts (id, user_id, name, slots, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, NOW(), NOW())
+         ON CONFLICT (user_id, name) DO UPDATE SET slots = $4, updated_at = NOW()
+         RETURNING id"
+    )
+        .bind(Uuid::new_v4())
+        .bind(user.id)
+        .bind(name)
+        .bind(&slots_json)
+        .fetch_one(&state.db)
+        .await;
+
+    match result {
+        Ok(id) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "id": id,
+            "name": name,
+            "slots": slots_json,
+        }))),
+        Err(e) => {
+            error!("Failed to save loadout: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to save loadout"))
+        }
+    }
+}
+
+async fn apply_cosmetic_loadout(
+    State(state): State<AppState>,
+    Json(req): Json<LoadoutActionRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
+    };
+
+    let slots_json = sqlx::query_scalar::<_, serde_json::Value>(
+        "SELECT slots FROM cosmetic_loadouts WHERE id = $1 AND user_id = $2"
+    )
+        .bind(req.loadout_id)
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten();
+
+    let slots: Vec<cosmetics::LoadoutSlot> = match slots_json {
+        Some(value) => serde_json::from_value(value).unwrap_or_default(),
+        None => return (StatusCode::NOT_FOUND, ApiResponse::error("Loadout not found")),
+    };
+
+    let mut applied: Vec<cosmetics::LoadoutSlot> = Vec::new();
+    let mut skipped: Vec<cosmetics::SkippedSlot> = Vec::new();
+
+    for entry in slots {
+        if !cosmetics::is_valid_slot(&entry.slot) {
+            skipped.push(cosmetics::SkippedSlot { slot: entry.slot, item_id: entry.item_id, reason: "invalid_slot".to_string() });
+            continue;
+        }
+        let owned = match Uuid::parse_str(&entry.item_id) {
+            Ok(item_uuid) => can_equip_cosmetic(&state.db, user.id, item_uuid).await,
+            Err(_) => false,
+        };
+        if !owned {
+            skipped.push(cosmetics::SkippedSlot { slot: entry.slot, item_id: entry.item_id, reason: "not_owned".to_string() });
+            continue;
+        }
+        let conflicts = cosmetics::conflicting_slots(&entry.slot);
+        if applied.iter().any(|a| conflicts.contains(&a.slot.as_str())) {
+            skipped.push(cosmetics::SkippedSlot { slot: entry.slot, item_id: entry.item_id, reason: "slot_conflict".to_string() });
+            continue;
+        }
+        applied.push(entry);
+    }
+
+    let mut tx = match state.db.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to apply loadout")),
+    };
+
+    if sqlx::query("DELETE FROM user_equipped_cosmetics WHERE user_id = $1")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .is_err()
+    {
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to apply loadout"));
     }
 
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "equipped": cosmetics })))
-}
+    for entry in &applied {
+        let inserted = sqlx::query(
+            "INSERT INTO user_equipped_cosmetics (user_id, slot, item_id) VALUES ($1, $2, $3)"
+        )
+            .bind(user.id)
+            .bind(&entry.slot)
+            .bind(&entry.item_id)
+            .execute(&mut *tx)
+            .await;
+        if inserted.is_err() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to apply loadout"));
+        }
+    }
 
-#[derive(Debug, Deserialize)]
-struct GetUserCosmeticsRequest {
-    user_id: Uuid,
+    if tx.commit().await.is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to apply loadout"));
+    }
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({
+        "loadout_id": req.loadout_id,
+        "applied": applied,
+        "skipped": skipped,
+    })))
 }
 
-async fn get_public_user_cosmetics(
+async fn delete_cosmetic_loadout(
     State(state): State<AppState>,
-    Json(req): Json<GetUserCosmeticsRequest>,
+    Json(req): Json<LoadoutActionRequest>,
 ) -> impl IntoResponse {
-    let user_exists = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM users WHERE id = $1"
-    )
-        .bind(req.user_id)
-        .fetch_one(&state.db)
-        .await
-        .unwrap_or(0);
-
-    if user_exists == 0 {
-        return (StatusCode::NOT_FOUND, ApiResponse::error("User not found"));
-    }
-
-    let equipped = sqlx::query_as::<_, (String, String)>(
-        "SELECT slot, item_id FROM user_equipped_cosmetics WHERE user_id = $1"
-    )
-        .bind(req.user_id)
-        .fetch_all(&state.db)
-        .await
-        .unwrap_or_default();
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::error("Invalid session")),
+    };
 
-    let mut cosmetics = serde_json::json!({
-        "skin": null,
-        "emote_1": null,
-        "emote_2": null,
-        "emote_3": null,
-        "emote_4": null,
-        "cape": null,
-        "wings": null,
-        "aura": null
-    });
+    let result = sqlx::query("DELETE FROM cosmetic_loadouts WHERE id = $1 AND user_id = $2")
+        .bind(req.loadout_id)
+        .bind(user.id)
+        .execute(&state.db)
+        .await;
 
-    for (slot, item_id) in equipped {
-        cosmetics[slot.clone()] = serde_json::Value::String(item_id);
+    match result {
+        Ok(r) if r.rows_affected() > 0 => (StatusCode::OK, ApiResponse::success(serde_json::json!({ "deleted": true }))),
+        Ok(_) => (StatusCode::NOT_FOUND, ApiResponse::error("Loadout not found")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to delete loadout")),
     }
-
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "equipped": cosmetics, "user_id": req.user_id })))
 }
 
 async fn get_verification_methods(
@@ -2211,6 +4620,25 @@ struct AdminReleaseEscrowRequest {
     escrow_id: Uuid,
 }
 
+#[derive(Debug, Deserialize)]
+struct AdminCreatePayoutRequest {
+    admin_token: String,
+    seller_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminMarkPayoutPaidRequest {
+    admin_token: String,
+    payout_id: Uuid,
+    /// Bank or transfer reference for the manual payment
+    reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SellerTokenQuery {
+    token: String,
+}
+
 const ADMIN_USERNAME: &str = "DeQuackDealer";
 const ADMIN_TOKEN_VALIDITY_HOURS: i64 = 24;
 
@@ -2365,6 +4793,7 @@ async fn admin_create_marketplace_item(
                 thumbnail_url: req.thumbnail_url,
                 file_url: req.file_url,
                 is_featured: req.is_featured,
+                status: moderation::STATUS_ACTIVE.to_string(),
                 created_at: now,
             };
             (StatusCode::CREATED, ApiResponse::success(item))
@@ -2462,15 +4891,15 @@ async fn admin_list_all_items(
         return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
     }
 
-    let items = sqlx::query_as::<_, (Uuid, String, String, String, f64, i64, i64, Option<String>, Option<String>, bool, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, name, description, category, price, downloads, likes, thumbnail_url, file_url, is_featured, created_at 
+    let items = sqlx::query_as::<_, (Uuid, String, String, String, f64, i64, i64, Option<String>, Option<String>, bool, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, name, description, category, price, downloads, likes, thumbnail_url, file_url, is_featured, status, created_at 
          FROM marketplace_items ORDER BY created_at DESC"
     )
         .fetch_all(&state.db)
         .await
         .unwrap_or_default();
 
-    let items: Vec<serde_json::Value> = items.into_iter().map(|(id, name, desc, cat, price, downloads, likes, thumb, file, featured, created)| {
+    let items: Vec<serde_json::Value> = items.into_iter().map(|(id, name, desc, cat, price, downloads, likes, thumb, file, featured, status, created)| {
         serde_json::json!({
             "id": id,
             "name": name,
@@ -2482,6 +4911,7 @@ async fn admin_list_all_items(
             "thumbnail_url": thumb,
             "file_url": file,
             "is_featured": featured,
+            "status": status,
             "created_at": created
         })
     }).collect();
@@ -2489,6 +4919,122 @@ async fn admin_list_all_items(
     (StatusCode::OK, ApiResponse::success(serde_json::json!({"items": items, "count": items.len()})))
 }
 
+/// Items waiting on an admin decision, with their automated check results.
+async fn admin_moderation_queue(
+    State(state): State<AppState>,
+    Json(req): Json<AdminTokenRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+
+    let rows = sqlx::query_as::<_, (Uuid, String, String, String, f64, Option<String>, Uuid, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT m.id, m.name, m.description, m.category, m.price, m.file_url, u.id, u.username, m.created_at
+         FROM marketplace_items m
+         JOIN users u ON m.author_id = u.id
+         WHERE m.status = $1
+         ORDER BY m.created_at ASC"
+    )
+        .bind(moderation::STATUS_PENDING)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let mut items = Vec::with_capacity(rows.len());
+    for (id, name, description, category, price, file_url, author_id, author_username, created_at) in rows {
+        let checks = moderation_checks_for(&state.db, id).await;
+        let checks_passed = !checks.is_empty() && checks.iter().all(|c| c["passed"] == true);
+        items.push(serde_json::json!({
+            "id": id,
+            "name": name,
+            "description": description,
+            "category": category,
+            "price": price,
+            "file_url": file_url,
+            "author": { "id": author_id, "username": author_username },
+            "checks": checks,
+            "checks_passed": checks_passed,
+            "created_at": created_at
+        }));
+    }
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({"items": items, "count": items.len()})))
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminModerationDecisionRequest {
+    admin_token: String,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+async fn admin_approve_marketplace_item(
+    State(state): State<AppState>,
+    Path(item_id): Path<Uuid>,
+    Json(req): Json<AdminModerationDecisionRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+
+    set_moderation_decision(&state, item_id, moderation::STATUS_ACTIVE, req.reason).await
+}
+
+async fn admin_reject_marketplace_item(
+    State(state): State<AppState>,
+    Path(item_id): Path<Uuid>,
+    Json(req): Json<AdminModerationDecisionRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+
+    let reason = req.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if reason.is_none() {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("A rejection reason is required"));
+    }
+
+    set_moderation_decision(&state, item_id, moderation::STATUS_REJECTED, reason).await
+}
+
+/// Records an admin decision on a pending item; the reason is shown to the author.
+async fn set_moderation_decision(
+    state: &AppState,
+    item_id: Uuid,
+    status: &str,
+    reason: Option<String>,
+) -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+    let result = sqlx::query_as::<_, (Uuid, String)>(
+        "UPDATE marketplace_items SET status = $1, moderation_reason = $2
+         WHERE id = $3 AND status = $4
+         RETURNING author_id, name"
+    )
+        .bind(status)
+        .bind(&reason)
+        .bind(item_id)
+        .bind(moderation::STATUS_PENDING)
+        .fetch_optional(&state.db)
+        .await;
+
+    match result {
+        Ok(Some((author_id, item_name))) => {
+            info!("Admin set marketplace item {} to {} (author {})", item_id, status, author_id);
+            let notification = NewNotification::moderation_decision(author_id, item_id, &item_name, status, reason.as_deref());
+            notifications::send_logged(&state.db, &state.notifications, notification).await;
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({
+                "id": item_id,
+                "status": status,
+                "reason": reason
+            })))
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, ApiResponse::error("No pending item with that id")),
+        Err(e) => {
+            error!("Failed to record moderation decision: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update item"))
+        }
+    }
+}
+
 async fn purchase_marketplace_item(
     State(state): State<AppState>,
     Json(req): Json<PurchaseItemRequest>,
@@ -2499,7 +5045,7 @@ async fn purchase_marketplace_item(
     };
 
     let item = sqlx::query_as::<_, (Uuid, String, f64, Uuid)>(
-        "SELECT id, name, price, author_id FROM marketplace_items WHERE id = $1"
+        "SELECT id, name, price, author_id FROM marketplace_items WHERE id = $1 AND status = 'active'"
     )
         .bind(req.item_id)
         .fetch_optional(&state.db)
@@ -2611,7 +5157,7 @@ async fn confirm_purchase(
         .ok()
         .flatten();
 
-    let (buyer_id, _seller_id, item_id, amount, status, stripe_session_id) = match escrow {
+    let (buyer_id, seller_id, item_id, amount, status, stripe_session_id) = match escrow {
         Some(e) => e,
         None => return (StatusCode::NOT_FOUND, ApiResponse::error("Escrow not found")),
     };
@@ -2620,6 +5166,14 @@ async fn confirm_purchase(
         return (StatusCode::FORBIDDEN, ApiResponse::error("Not your transaction"));
     }
 
+    // The webhook usually records the payment first; polling Stripe below is
+    // only the fallback for when it hasn't arrived yet.
+    if status == "completed" {
+        return (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "confirmed": true,
+            "item_id": item_id
+        })));
+    }
     if status != "pending" {
         return (StatusCode::BAD_REQUEST, ApiResponse::error("Transaction already processed"));
     }
@@ -2639,25 +5193,24 @@ async fn confirm_purchase(
         }
     }
 
-    let _ = sqlx::query("UPDATE escrow_transactions SET status = 'completed', completed_at = NOW() WHERE id = $1")
-        .bind(escrow_id)
-        .execute(&state.db)
-        .await;
-
-    let _ = sqlx::query(
-        "INSERT INTO marketplace_purchases (user_id, item_id, amount, escrow_id, status, created_at) VALUES ($1, $2, $3, $4, 'completed', NOW())"
-    )
-        .bind(user.id)
-        .bind(item_id)
-        .bind(amount)
-        .bind(escrow_id)
-        .execute(&state.db)
-        .await;
-
-    let _ = sqlx::query("UPDATE marketplace_items SET downloads = downloads + 1 WHERE id = $1")
-        .bind(item_id)
-        .execute(&state.db)
-        .await;
+    let completed = async {
+        let mut tx = state.db.begin().await?;
+        let completed = billing::complete_escrow(&mut tx, escrow_id).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(completed)
+    }.await;
+    match completed {
+        Ok(Some(_)) => {
+            let notification = NewNotification::escrow_update(seller_id, escrow_id, item_id, "completed");
+            notifications::send_logged(&state.db, &state.notifications, notification).await;
+        }
+        // The webhook completed it while we were asking Stripe
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to complete escrow {}: {}", escrow_id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to record purchase"));
+        }
+    }
 
     (StatusCode::OK, ApiResponse::success(serde_json::json!({
         "confirmed": true,
@@ -2696,8 +5249,8 @@ async fn admin_release_escrow(
         return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
     }
 
-    let escrow = sqlx::query_as::<_, (String, f64, Uuid)>(
-        "SELECT status, amount, seller_id FROM escrow_transactions WHERE id = $1"
+    let escrow = sqlx::query_as::<_, (String, f64, Uuid, Uuid)>(
+        "SELECT status, amount, seller_id, item_id FROM escrow_transactions WHERE id = $1"
     )
         .bind(req.escrow_id)
         .fetch_optional(&state.db)
@@ -2705,7 +5258,7 @@ async fn admin_release_escrow(
         .ok()
         .flatten();
 
-    let (status, _amount, _seller_id) = match escrow {
+    let (status, _amount, seller_id, item_id) = match escrow {
         Some(e) => e,
         None => return (StatusCode::NOT_FOUND, ApiResponse::error("Escrow not found")),
     };
@@ -2714,16 +5267,105 @@ async fn admin_release_escrow(
         return (StatusCode::BAD_REQUEST, ApiResponse::error("Can only release completed escrows"));
     }
 
-    let _ = sqlx::query("UPDATE escrow_transactions SET status = 'released', released_at = NOW() WHERE id = $1")
-        .bind(req.escrow_id)
-        .execute(&state.db)
-        .await;
+    match payouts::release_escrow(&state.db, req.escrow_id, state.payouts.fee_basis_points).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::CONFLICT, ApiResponse::error("Escrow was already released")),
+        Err(e) => {
+            error!("Failed to release escrow {}: {}", req.escrow_id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to release escrow"));
+        }
+    }
 
     info!("Admin released escrow: {}", req.escrow_id);
+    let notification = NewNotification::escrow_update(seller_id, req.escrow_id, item_id, "released");
+    notifications::send_logged(&state.db, &state.notifications, notification).await;
 
     (StatusCode::OK, ApiResponse::success(serde_json::json!({"released": true, "escrow_id": req.escrow_id})))
 }
 
+async fn admin_list_owed_sellers(
+    State(state): State<AppState>,
+    Json(req): Json<AdminTokenRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+
+    match payouts::owed_sellers(&state.db).await {
+        Ok(sellers) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "sellers": sellers,
+            "fee_basis_points": state.payouts.fee_basis_points,
+        }))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load balances")),
+    }
+}
+
+async fn admin_create_payout(
+    State(state): State<AppState>,
+    Json(req): Json<AdminCreatePayoutRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<payouts::Payout>::error("Invalid admin token"));
+    }
+
+    match payouts::create_payout(&state.db, req.seller_id, state.payout_transfer.as_ref()).await {
+        Ok(Some(payout)) => (StatusCode::CREATED, ApiResponse::success(payout)),
+        Ok(None) => (StatusCode::BAD_REQUEST, ApiResponse::error("Seller has no unpaid earnings")),
+        Err(e) => {
+            error!("Failed to create payout for {}: {}", req.seller_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to create payout"))
+        }
+    }
+}
+
+async fn admin_mark_payout_paid(
+    State(state): State<AppState>,
+    Json(req): Json<AdminMarkPayoutPaidRequest>,
+) -> impl IntoResponse {
+    if !validate_admin_token(&req.admin_token) {
+        return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid admin token"));
+    }
+
+    match payouts::mark_paid(&state.db, req.payout_id, req.reference.as_deref()).await {
+        Ok(true) => {
+            info!("Admin marked payout {} as paid", req.payout_id);
+            (StatusCode::OK, ApiResponse::success(serde_json::json!({"paid": true, "payout_id": req.payout_id})))
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("Payout not found or already paid")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update payout")),
+    }
+}
+
+async fn get_seller_earnings(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<SellerTokenQuery>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &query.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<payouts::Earnings>::error("Invalid token")),
+    };
+
+    match payouts::earnings(&state.db, user.id).await {
+        Ok(earnings) => (StatusCode::OK, ApiResponse::success(earnings)),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load earnings")),
+    }
+}
+
+async fn get_seller_payouts(
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<SellerTokenQuery>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &query.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    match payouts::payouts(&state.db, user.id).await {
+        Ok(payouts) => (StatusCode::OK, ApiResponse::success(serde_json::json!({ "payouts": payouts }))),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load payouts")),
+    }
+}
+
 async fn get_user_purchases(
     State(state): State<AppState>,
     Json(req): Json<TokenRequest>,
@@ -3242,65 +5884,166 @@ struct PartyRequest {
     token: String,
     name: Option<String>,
     max_members: Option<i32>,
+    persist_chat: Option<bool>,
+}
+
+fn party_error_status(error: &party::PartyError) -> StatusCode {
+    match error {
+        party::PartyError::NotFound => StatusCode::NOT_FOUND,
+        party::PartyError::Full | party::PartyError::AlreadyInParty => StatusCode::CONFLICT,
+        party::PartyError::NotMember => StatusCode::FORBIDDEN,
+        party::PartyError::InvalidMessage(_) => StatusCode::BAD_REQUEST,
+    }
 }
 
 async fn create_party(
     State(state): State<AppState>,
     Json(req): Json<PartyRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<party::Party>::error("Invalid token")),
+    };
+
+    let max_members = req.max_members.map(|m| m.max(0) as usize);
+    match state.parties.create(user.id, &user.username, req.name, max_members, req.persist_chat.unwrap_or(false)) {
+        Ok(party) => (StatusCode::CREATED, ApiResponse::success(party)),
+        Err(e) => (party_error_status(&e), ApiResponse::error(e.to_string())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinPartyRequest {
+    token: String,
+    party_id: Option<Uuid>,
+    invite_code: Option<String>,
+}
+
+async fn join_party(
+    State(state): State<AppState>,
+    Json(req): Json<JoinPartyRequest>,
 ) -> impl IntoResponse {
     let user = match validate_token(&state.db, &req.token).await {
         Some(u) => u,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
 
-    let party_id = Uuid::new_v4();
-    (StatusCode::CREATED, ApiResponse::success(serde_json::json!({
-        "party_id": party_id,
-        "name": req.name.unwrap_or_else(|| format!("{}'s Party", user.username)),
-        "leader_id": user.id,
-        "max_members": req.max_members.unwrap_or(8),
-        "members": [{ "id": user.id, "username": user.username, "role": "leader" }],
-        "invite_code": format!("PARTY-{}", &party_id.to_string()[..8].to_uppercase())
-    })))
+    match state.parties.join(req.party_id, req.invite_code.as_deref(), user.id, &user.username) {
+        Ok(party) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "joined": true,
+            "party_id": party.party_id,
+            "user_id": user.id,
+            "role": if party.leader_id == user.id { "leader" } else { "member" },
+            "party": party
+        }))),
+        Err(e) => (party_error_status(&e), ApiResponse::error(e.to_string())),
+    }
+}
+
+async fn leave_party(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    match state.parties.leave(user.id) {
+        Some(party_id) => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "left": true,
+            "party_id": party_id,
+            "message": "You have left the party"
+        }))),
+        None => (StatusCode::NOT_FOUND, ApiResponse::error("You are not in a party")),
+    }
+}
+
+/// Validate, store and fan out a chat message from `user_id`. Members with
+/// a block either way between them and the sender don't receive it.
+async fn send_party_chat(
+    state: &AppState,
+    user_id: Uuid,
+    party_id: Uuid,
+    text: &str,
+) -> Result<party::PartyChatMessage, (StatusCode, String)> {
+    let (party, text) = state.parties.prepare(party_id, user_id, text)
+        .map_err(|e| (party_error_status(&e), e.to_string()))?;
+    let muted = party::blocked_members(&state.db, user_id, &party).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to check blocks".to_string()))?;
+
+    let (message, _) = state.parties.post(party_id, user_id, text, &muted)
+        .map_err(|e| (party_error_status(&e), e.to_string()))?;
+
+    if party.persist_chat {
+        let stored = sqlx::query(
+            "INSERT INTO party_chat_messages (id, party_id, sender_id, text, sent_at) VALUES ($1, $2, $3, $4, $5)"
+        )
+            .bind(message.id)
+            .bind(message.party_id)
+            .bind(message.from)
+            .bind(&message.text)
+            .bind(message.ts)
+            .execute(&state.db)
+            .await;
+        if let Err(e) = stored {
+            error!("Failed to persist party chat message: {}", e);
+        }
+    }
+    Ok(message)
 }
 
 #[derive(Debug, Deserialize)]
-struct JoinPartyRequest {
+struct PartyChatSendRequest {
     token: String,
-    party_id: Option<Uuid>,
-    invite_code: Option<String>,
+    party_id: Uuid,
+    text: String,
 }
 
-async fn join_party(
+async fn party_chat_send(
     State(state): State<AppState>,
-    Json(req): Json<JoinPartyRequest>,
+    Json(req): Json<PartyChatSendRequest>,
 ) -> impl IntoResponse {
     let user = match validate_token(&state.db, &req.token).await {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<party::PartyChatMessage>::error("Invalid token")),
     };
 
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({
-        "joined": true,
-        "party_id": req.party_id.unwrap_or_else(Uuid::new_v4),
-        "user_id": user.id,
-        "role": "member"
-    })))
+    match send_party_chat(&state, user.id, req.party_id, &req.text).await {
+        Ok(message) => (StatusCode::OK, ApiResponse::success(message)),
+        Err((status, e)) => (status, ApiResponse::error(e)),
+    }
 }
 
-async fn leave_party(
+#[derive(Debug, Deserialize)]
+struct PartyChatHistoryQuery {
+    token: String,
+    party_id: Uuid,
+    limit: Option<usize>,
+}
+
+async fn party_chat_history(
     State(state): State<AppState>,
-    Json(req): Json<TokenRequest>,
+    axum::extract::Query(query): axum::extract::Query<PartyChatHistoryQuery>,
 ) -> impl IntoResponse {
-    let _user = match validate_token(&state.db, &req.token).await {
+    let user = match validate_token(&state.db, &query.token).await {
         Some(u) => u,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
 
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({
-        "left": true,
-        "message": "You have left the party"
-    })))
+    let Some(party) = state.parties.get(query.party_id) else {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("Party not found"));
+    };
+    let muted = match party::blocked_members(&state.db, user.id, &party).await {
+        Ok(muted) => muted,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to check blocks")),
+    };
+
+    let limit = query.limit.unwrap_or(50).min(party::CHAT_HISTORY_LIMIT);
+    match state.parties.history(query.party_id, user.id, &muted, limit) {
+        Ok(messages) => (StatusCode::OK, ApiResponse::success(serde_json::json!({ "messages": messages }))),
+        Err(e) => (party_error_status(&e), ApiResponse::error(e.to_string())),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -3309,19 +6052,43 @@ struct InviteToPartyRequest {
     user_id: Uuid,
 }
 
+/// Sends the invitee a notification with the party's invite code. A block
+/// between the two is refused as if the user did not exist.
 async fn invite_to_party(
     State(state): State<AppState>,
     Json(req): Json<InviteToPartyRequest>,
 ) -> impl IntoResponse {
-    let _user = match validate_token(&state.db, &req.token).await {
+    let user = match validate_token(&state.db, &req.token).await {
         Some(u) => u,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
 
+    let Some(party) = state.parties.party_of(user.id) else {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("You are not in a party"));
+    };
+    if req.user_id == user.id || party.is_member(req.user_id) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("User is already in your party"));
+    }
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(req.user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(false);
+    if !exists || !social_guard::allows(&state.db, user.id, req.user_id).await {
+        return (StatusCode::NOT_FOUND, ApiResponse::error(social_guard::HIDDEN));
+    }
+
+    let notification = NewNotification::party_invite(user.id, &user.username, req.user_id, &party);
+    if let Err(e) = notifications::send(&state.db, &state.notifications, notification).await {
+        error!("Failed to send party invite: {}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to send invite"));
+    }
+
     (StatusCode::OK, ApiResponse::success(serde_json::json!({
         "invited": true,
         "invited_user_id": req.user_id,
-        "expires_in_seconds": 300
+        "party_id": party.party_id,
+        "invite_code": party.invite_code,
     })))
 }
 
@@ -3333,6 +6100,40 @@ struct UpdatePresenceRequest {
     server_id: Option<String>,
 }
 
+/// Pushes `user_id`'s presence over the notification feed of every connected
+/// friend they have no block with, in either direction. Returns how many
+/// received it.
+async fn broadcast_presence(
+    state: &AppState,
+    user_id: Uuid,
+    status: &str,
+    activity: Option<String>,
+    server_id: Option<String>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+) -> Result<usize, sqlx::Error> {
+    let friends = sqlx::query_scalar::<_, Uuid>(
+        "SELECT CASE WHEN user_id = $1 THEN friend_id ELSE user_id END FROM friendships
+         WHERE status = 'accepted' AND (user_id = $1 OR friend_id = $1)"
+    )
+        .bind(user_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+    let blocks = social_guard::block_set(&state.db, user_id).await?;
+
+    state.presence.record(user_id, status, server_id.as_deref(), updated_at);
+    let message = relay::RelayMessage::Presence {
+        user_id,
+        status: status.to_string(),
+        activity,
+        server_id,
+        updated_at,
+    };
+    Ok(state.notifications.push_to_all(blocks.visible(friends, |id| *id), &message))
+}
+
+/// Pushes the new presence to every connected friend the user has no block
+/// with, in either direction.
 async fn update_presence(
     State(state): State<AppState>,
     Json(req): Json<UpdatePresenceRequest>,
@@ -3342,15 +6143,118 @@ async fn update_presence(
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
 
+    let updated_at = chrono::Utc::now();
+    let delivered = match broadcast_presence(&state, user.id, &req.status, req.activity.clone(), req.server_id.clone(), updated_at).await {
+        Ok(delivered) => delivered,
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to check blocks")),
+    };
+
     (StatusCode::OK, ApiResponse::success(serde_json::json!({
         "user_id": user.id,
         "status": req.status,
         "activity": req.activity,
         "server_id": req.server_id,
-        "updated_at": chrono::Utc::now()
+        "updated_at": updated_at,
+        "delivered_to": delivered
     })))
 }
 
+async fn block_user(
+    State(state): State<AppState>,
+    Json(req): Json<BlockRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+    if user.id == req.user_id {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Cannot block yourself"));
+    }
+    if req.reason.as_ref().is_some_and(|r| r.len() > 500) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error("Reason must be at most 500 characters"));
+    }
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(req.user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(false);
+    if !exists {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("User not found"));
+    }
+
+    match friends::FriendsService::new(state.db.clone()).block_user(user.id, req.user_id, req.reason.as_deref()).await {
+        Ok(true) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"blocked": true, "user_id": req.user_id}))),
+        Ok(false) => (StatusCode::CONFLICT, ApiResponse::error("User already blocked")),
+        Err(e) => {
+            error!("Failed to block user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to block user"))
+        }
+    }
+}
+
+async fn unblock_user(
+    State(state): State<AppState>,
+    Json(req): Json<BlockRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    match friends::FriendsService::new(state.db.clone()).unblock_user(user.id, req.user_id).await {
+        Ok(true) => (StatusCode::OK, ApiResponse::success(serde_json::json!({"unblocked": true, "user_id": req.user_id}))),
+        Ok(false) => (StatusCode::NOT_FOUND, ApiResponse::error("User is not blocked")),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to unblock user")),
+    }
+}
+
+async fn list_blocked_users(
+    State(state): State<AppState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<Vec<friends::BlockedUser>>::error("Invalid token")),
+    };
+
+    match friends::FriendsService::new(state.db.clone()).blocked_users(user.id).await {
+        Ok(blocked) => (StatusCode::OK, ApiResponse::success(blocked)),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to load blocked users")),
+    }
+}
+
+type CameraPathRow = (Uuid, Uuid, String, f64, serde_json::Value, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>);
+
+const CAMERA_PATH_COLUMNS: &str = "id, user_id, name, duration_seconds, keyframes, shared, created_at, updated_at";
+
+fn camera_path_json(row: &CameraPathRow) -> serde_json::Value {
+    let (id, owner_id, name, duration_seconds, keyframes, shared, created_at, updated_at) = row;
+    serde_json::json!({
+        "id": id,
+        "owner_id": owner_id,
+        "name": name,
+        "duration_seconds": duration_seconds,
+        "keyframes": keyframes,
+        "shared": shared,
+        "created_at": created_at,
+        "updated_at": updated_at
+    })
+}
+
+/// Fetches a path the user owns, or one its owner has shared.
+async fn fetch_visible_camera_path(db: &PgPool, user_id: Uuid, path_id: Uuid) -> Option<CameraPathRow> {
+    sqlx::query_as::<_, CameraPathRow>(&format!(
+        "SELECT {} FROM camera_paths WHERE id = $1 AND (user_id = $2 OR shared = true)",
+        CAMERA_PATH_COLUMNS
+    ))
+        .bind(path_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+}
+
 async fn list_camera_paths(
     State(state): State<AppState>,
     Json(req): Json<TokenRequest>,
@@ -3364,32 +6268,34 @@ async fn list_camera_paths(
         return (StatusCode::FORBIDDEN, ApiResponse::error("Cinema camera requires premium"));
     }
 
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({
-        "paths": [
-            {
-                "id": Uuid::new_v4(),
-                "name": "Cinematic Flyover",
-                "duration_seconds": 30,
-                "keyframes": 12,
-                "created_at": chrono::Utc::now() - chrono::Duration::days(3)
-            },
-            {
-                "id": Uuid::new_v4(),
-                "name": "Base Tour",
-                "duration_seconds": 60,
-                "keyframes": 24,
-                "created_at": chrono::Utc::now() - chrono::Duration::days(1)
-            }
-        ]
-    })))
+    let rows = sqlx::query_as::<_, CameraPathRow>(&format!(
+        "SELECT {} FROM camera_paths WHERE user_id = $1 ORDER BY created_at DESC",
+        CAMERA_PATH_COLUMNS
+    ))
+        .bind(user.id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let paths: Vec<serde_json::Value> = rows.iter()
+        .map(|row| {
+            let mut path = camera_path_json(row);
+            path["keyframes"] = serde_json::json!(row.4.as_array().map(|k| k.len()).unwrap_or(0));
+            path
+        })
+        .collect();
+
+    (StatusCode::OK, ApiResponse::success(serde_json::json!({ "paths": paths })))
 }
 
 #[derive(Debug, Deserialize)]
 struct CreateCameraPathRequest {
     token: String,
     name: String,
-    keyframes: Vec<serde_json::Value>,
+    keyframes: Vec<cinema::CameraKeyframe>,
     duration_seconds: f64,
+    #[serde(default)]
+    shared: bool,
 }
 
 async fn create_camera_path(
@@ -3405,35 +6311,167 @@ async fn create_camera_path(
         return (StatusCode::FORBIDDEN, ApiResponse::error("Cinema camera requires premium"));
     }
 
-    let path_id = Uuid::new_v4();
-    (StatusCode::CREATED, ApiResponse::success(serde_json::json!({
-        "id": path_id,
-        "name": req.name,
-        "keyframes": req.keyframes.len(),
-        "duration_seconds": req.duration_seconds,
-        "created_at": chrono::Utc::now()
-    })))
+    if let Err(e) = cinema::validate_path(&req.name, &req.keyframes, req.duration_seconds) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let row = sqlx::query_as::<_, CameraPathRow>(&format!(
+        "INSERT INTO camera_paths (id, user_id, name, duration_seconds, keyframes, shared, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW()) RETURNING {}",
+        CAMERA_PATH_COLUMNS
+    ))
+        .bind(Uuid::new_v4())
+        .bind(user.id)
+        .bind(req.name.trim())
+        .bind(req.duration_seconds)
+        .bind(serde_json::to_value(&req.keyframes).unwrap_or_default())
+        .bind(req.shared)
+        .fetch_one(&state.db)
+        .await;
+
+    match row {
+        Ok(row) => (StatusCode::CREATED, ApiResponse::success(camera_path_json(&row))),
+        Err(e) => {
+            error!("Failed to create camera path: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to create camera path"))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CameraPathRequest {
+    token: String,
+    path_id: Uuid,
+}
+
+async fn get_camera_path(
+    State(state): State<AppState>,
+    Json(req): Json<CameraPathRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    match fetch_visible_camera_path(&state.db, user.id, req.path_id).await {
+        Some(row) => (StatusCode::OK, ApiResponse::success(camera_path_json(&row))),
+        None => (StatusCode::NOT_FOUND, ApiResponse::error("Camera path not found")),
+    }
 }
 
 #[derive(Debug, Deserialize)]
-struct DeleteCameraPathRequest {
+struct UpdateCameraPathRequest {
     token: String,
     path_id: Uuid,
+    name: Option<String>,
+    keyframes: Option<Vec<cinema::CameraKeyframe>>,
+    duration_seconds: Option<f64>,
+    shared: Option<bool>,
+}
+
+async fn update_camera_path(
+    State(state): State<AppState>,
+    Json(req): Json<UpdateCameraPathRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
+    };
+
+    if !user.premium {
+        return (StatusCode::FORBIDDEN, ApiResponse::error("Cinema camera requires premium"));
+    }
+
+    let existing = match fetch_visible_camera_path(&state.db, user.id, req.path_id).await {
+        Some(row) if row.1 == user.id => row,
+        _ => return (StatusCode::NOT_FOUND, ApiResponse::error("Camera path not found")),
+    };
+
+    let name = req.name.unwrap_or(existing.2);
+    let duration_seconds = req.duration_seconds.unwrap_or(existing.3);
+    let keyframes = match req.keyframes {
+        Some(keyframes) => keyframes,
+        None => serde_json::from_value(existing.4).unwrap_or_default(),
+    };
+    let shared = req.shared.unwrap_or(existing.5);
+
+    if let Err(e) = cinema::validate_path(&name, &keyframes, duration_seconds) {
+        return (StatusCode::BAD_REQUEST, ApiResponse::error(e));
+    }
+
+    let row = sqlx::query_as::<_, CameraPathRow>(&format!(
+        "UPDATE camera_paths SET name = $1, duration_seconds = $2, keyframes = $3, shared = $4, updated_at = NOW()
+         WHERE id = $5 AND user_id = $6 RETURNING {}",
+        CAMERA_PATH_COLUMNS
+    ))
+        .bind(name.trim())
+        .bind(duration_seconds)
+        .bind(serde_json::to_value(&keyframes).unwrap_or_default())
+        .bind(shared)
+        .bind(req.path_id)
+        .bind(user.id)
+        .fetch_optional(&state.db)
+        .await;
+
+    match row {
+        Ok(Some(row)) => (StatusCode::OK, ApiResponse::success(camera_path_json(&row))),
+        Ok(None) => (StatusCode::NOT_FOUND, ApiResponse::error("Camera path not found")),
+        Err(e) => {
+            error!("Failed to update camera path: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Failed to update camera path"))
+        }
+    }
 }
 
 async fn delete_camera_path(
     State(state): State<AppState>,
-    Json(req): Json<DeleteCameraPathRequest>,
+    Json(req): Json<CameraPathRequest>,
 ) -> impl IntoResponse {
-    let _user = match validate_token(&state.db, &req.token).await {
+    let user = match validate_token(&state.db, &req.token).await {
         Some(u) => u,
         None => return (StatusCode::UNAUTHORIZED, ApiResponse::<serde_json::Value>::error("Invalid token")),
     };
 
-    (StatusCode::OK, ApiResponse::success(serde_json::json!({
-        "deleted": true,
-        "path_id": req.path_id
-    })))
+    let result = sqlx::query("DELETE FROM camera_paths WHERE id = $1 AND user_id = $2")
+        .bind(req.path_id)
+        .bind(user.id)
+        .execute(&state.db)
+        .await;
+
+    match result {
+        Ok(r) if r.rows_affected() > 0 => (StatusCode::OK, ApiResponse::success(serde_json::json!({
+            "deleted": true,
+            "path_id": req.path_id
+        }))),
+        _ => (StatusCode::NOT_FOUND, ApiResponse::error("Camera path not found")),
+    }
+}
+
+/// Returns the path in the shape rubidium's `CameraPath` deserializes from.
+async fn export_camera_path(
+    State(state): State<AppState>,
+    Json(req): Json<CameraPathRequest>,
+) -> impl IntoResponse {
+    let user = match validate_token(&state.db, &req.token).await {
+        Some(u) => u,
+        None => return (StatusCode::UNAUTHORIZED, ApiResponse::<cinema::CameraPathExport>::error("Invalid token")),
+    };
+
+    let Some((id, owner_id, name, duration_seconds, keyframes, ..)) =
+        fetch_visible_camera_path(&state.db, user.id, req.path_id).await
+    else {
+        return (StatusCode::NOT_FOUND, ApiResponse::error("Camera path not found"));
+    };
+
+    match serde_json::from_value::<Vec<cinema::CameraKeyframe>>(keyframes) {
+        Ok(keyframes) => (StatusCode::OK, ApiResponse::success(
+            cinema::CameraPathExport::new(id, name, owner_id, keyframes, duration_seconds)
+        )),
+        Err(e) => {
+            error!("Stored camera path {} has malformed keyframes: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, ApiResponse::error("Camera path is corrupt"))
+        }
+    }
 }
 
 async fn get_anticheat_status(
@@ -3587,6 +6625,17 @@ async fn run_migrations(db: &PgPool) {
             expires_at TIMESTAMPTZ NOT NULL,
             created_at TIMESTAMPTZ NOT NULL
         )",
+        "ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS device_info TEXT",
+        "ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS ip_address TEXT",
+        "ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ",
+        "ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE",
+        "CREATE TABLE IF NOT EXISTS email_verifications (
+            user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+            code_hash TEXT NOT NULL,
+            sent_at TIMESTAMPTZ NOT NULL,
+            expires_at TIMESTAMPTZ NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0
+        )",
         "CREATE TABLE IF NOT EXISTS friendships (
             id UUID PRIMARY KEY,
             user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
@@ -3595,6 +6644,8 @@ async fn run_migrations(db: &PgPool) {
             created_at TIMESTAMPTZ NOT NULL,
             accepted_at TIMESTAMPTZ
         )",
+        "ALTER TABLE friendships ADD COLUMN IF NOT EXISTS message VARCHAR(200)",
+        "ALTER TABLE friendships ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ",
         "CREATE TABLE IF NOT EXISTS blocks (
             id UUID PRIMARY KEY,
             blocker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
@@ -3602,6 +6653,11 @@ async fn run_migrations(db: &PgPool) {
             reason TEXT,
             created_at TIMESTAMPTZ NOT NULL
         )",
+        "DELETE FROM blocks a USING blocks b
+         WHERE a.blocker_id = b.blocker_id AND a.blocked_id = b.blocked_id
+           AND (a.created_at, a.id) > (b.created_at, b.id)",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_blocks_pair ON blocks(blocker_id, blocked_id)",
+        "CREATE INDEX IF NOT EXISTS idx_blocks_blocked ON blocks(blocked_id)",
         "CREATE TABLE IF NOT EXISTS game_servers (
             id UUID PRIMARY KEY,
             name VARCHAR(128) NOT NULL,
@@ -3658,7 +6714,11 @@ async fn run_migrations(db: &PgPool) {
         "CREATE INDEX IF NOT EXISTS idx_friendships_user ON friendships(user_id)",
         "CREATE INDEX IF NOT EXISTS idx_friendships_friend ON friendships(friend_id)",
         "CREATE INDEX IF NOT EXISTS idx_servers_online ON game_servers(is_online, last_ping)",
+        "CREATE INDEX IF NOT EXISTS idx_servers_tags ON game_servers USING GIN (tags)",
         "CREATE INDEX IF NOT EXISTS idx_mod_profiles_user ON mod_profiles(user_id)",
+        "ALTER TABLE mod_profiles ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ",
+        "UPDATE mod_profiles SET updated_at = created_at WHERE updated_at IS NULL",
+        "ALTER TABLE mod_profiles ALTER COLUMN updated_at SET NOT NULL",
         "CREATE TABLE IF NOT EXISTS marketplace_items (
             id UUID PRIMARY KEY,
             name VARCHAR(100) NOT NULL,
@@ -3699,6 +6759,41 @@ async fn run_migrations(db: &PgPool) {
             PRIMARY KEY (user_id, slot)
         )",
         "CREATE INDEX IF NOT EXISTS idx_equipped_cosmetics_user ON user_equipped_cosmetics(user_id)",
+        "CREATE TABLE IF NOT EXISTS settings_sync (
+            user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+            document JSONB NOT NULL,
+            etag VARCHAR(64) NOT NULL,
+            size_bytes BIGINT NOT NULL DEFAULT 0,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )",
+        "CREATE TABLE IF NOT EXISTS cosmetic_loadouts (
+            id UUID PRIMARY KEY,
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            name VARCHAR(64) NOT NULL,
+            slots JSONB NOT NULL DEFAULT '[]',
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE (user_id, name)
+        )",
+        "CREATE TABLE IF NOT EXISTS camera_paths (
+            id UUID PRIMARY KEY,
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            name VARCHAR(64) NOT NULL,
+            duration_seconds DOUBLE PRECISION NOT NULL,
+            keyframes JSONB NOT NULL DEFAULT '[]',
+            shared BOOLEAN NOT NULL DEFAULT false,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_camera_paths_user ON camera_paths(user_id)",
+        "CREATE TABLE IF NOT EXISTS login_attempts (
+            id UUID PRIMARY KEY,
+            username VARCHAR(64) NOT NULL,
+            ip_address VARCHAR(64) NOT NULL DEFAULT '',
+            attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_login_attempts_username ON login_attempts(LOWER(username), attempted_at)",
+        "CREATE INDEX IF NOT EXISTS idx_login_attempts_ip ON login_attempts(ip_address, attempted_at)",
         "CREATE TABLE IF NOT EXISTS user_verifications (
             id UUID PRIMARY KEY,
             user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
@@ -3728,10 +6823,168 @@ async fn run_migrations(db: &PgPool) {
         "CREATE INDEX IF NOT EXISTS idx_escrow_buyer ON escrow_transactions(buyer_id)",
         "CREATE INDEX IF NOT EXISTS idx_escrow_seller ON escrow_transactions(seller_id)",
         "CREATE INDEX IF NOT EXISTS idx_escrow_status ON escrow_transactions(status)",
+        "CREATE INDEX IF NOT EXISTS idx_escrow_stripe_session ON escrow_transactions(stripe_session_id)",
+        "CREATE TABLE IF NOT EXISTS seller_payout_batches (
+            id UUID PRIMARY KEY,
+            seller_id UUID NOT NULL REFERENCES users(id),
+            amount_cents BIGINT NOT NULL,
+            entries BIGINT NOT NULL,
+            status VARCHAR(16) NOT NULL DEFAULT 'pending',
+            method VARCHAR(32) NOT NULL,
+            reference VARCHAR(255),
+            created_at TIMESTAMPTZ NOT NULL,
+            paid_at TIMESTAMPTZ
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_seller_payout_batches_seller ON seller_payout_batches(seller_id, created_at DESC)",
+        "CREATE TABLE IF NOT EXISTS seller_ledger (
+            id UUID PRIMARY KEY,
+            seller_id UUID NOT NULL REFERENCES users(id),
+            escrow_id UUID NOT NULL UNIQUE,
+            item_id UUID NOT NULL,
+            item_name VARCHAR(100),
+            gross_cents BIGINT NOT NULL,
+            fee_basis_points INTEGER NOT NULL,
+            fee_cents BIGINT NOT NULL,
+            net_cents BIGINT NOT NULL,
+            payout_id UUID REFERENCES seller_payout_batches(id),
+            created_at TIMESTAMPTZ NOT NULL
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_seller_ledger_seller ON seller_ledger(seller_id)",
+        "CREATE INDEX IF NOT EXISTS idx_seller_ledger_unpaid ON seller_ledger(seller_id) WHERE payout_id IS NULL",
+        "CREATE TABLE IF NOT EXISTS stripe_webhook_events (
+            event_id VARCHAR(255) PRIMARY KEY,
+            event_type VARCHAR(64) NOT NULL,
+            processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )",
         "ALTER TABLE marketplace_items ADD COLUMN IF NOT EXISTS status VARCHAR(32) NOT NULL DEFAULT 'active'",
         "ALTER TABLE marketplace_items ADD COLUMN IF NOT EXISTS admin_notes TEXT",
+        "ALTER TABLE marketplace_items ADD COLUMN IF NOT EXISTS moderation_reason TEXT",
+        "ALTER TABLE marketplace_items ADD COLUMN IF NOT EXISTS file_hash VARCHAR(64)",
+        "CREATE INDEX IF NOT EXISTS idx_marketplace_status ON marketplace_items(status)",
+        "CREATE INDEX IF NOT EXISTS idx_marketplace_file_hash ON marketplace_items(file_hash)",
+        "CREATE TABLE IF NOT EXISTS moderation_checks (
+            id UUID PRIMARY KEY,
+            item_id UUID NOT NULL REFERENCES marketplace_items(id) ON DELETE CASCADE,
+            check_name VARCHAR(32) NOT NULL,
+            passed BOOLEAN NOT NULL,
+            detail TEXT,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_moderation_checks_item ON moderation_checks(item_id)",
         "ALTER TABLE marketplace_purchases ADD COLUMN IF NOT EXISTS escrow_id UUID REFERENCES escrow_transactions(id)",
         "ALTER TABLE marketplace_purchases ADD COLUMN IF NOT EXISTS status VARCHAR(32) NOT NULL DEFAULT 'completed'",
+        "CREATE TABLE IF NOT EXISTS achievements (
+            id VARCHAR(64) PRIMARY KEY,
+            name VARCHAR(128) NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            criteria JSONB NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            retired_at TIMESTAMPTZ
+        )",
+        "CREATE TABLE IF NOT EXISTS user_achievements (
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            achievement_id VARCHAR(64) NOT NULL REFERENCES achievements(id) ON DELETE CASCADE,
+            unlocked_at TIMESTAMPTZ NOT NULL,
+            PRIMARY KEY (user_id, achievement_id)
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_user_achievements_user ON user_achievements(user_id)",
+        "CREATE TABLE IF NOT EXISTS server_metrics (
+            server_id UUID NOT NULL REFERENCES game_servers(id) ON DELETE CASCADE,
+            ts TIMESTAMPTZ NOT NULL,
+            current_players INTEGER NOT NULL
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_server_metrics_server_ts ON server_metrics(server_id, ts)",
+        "CREATE INDEX IF NOT EXISTS idx_server_metrics_ts ON server_metrics(ts)",
+        "CREATE TABLE IF NOT EXISTS server_metrics_hourly (
+            server_id UUID NOT NULL REFERENCES game_servers(id) ON DELETE CASCADE,
+            hour TIMESTAMPTZ NOT NULL,
+            samples BIGINT NOT NULL,
+            total_players BIGINT NOT NULL,
+            peak_players INTEGER NOT NULL,
+            PRIMARY KEY (server_id, hour)
+        )",
+        "CREATE TABLE IF NOT EXISTS party_chat_messages (
+            id UUID PRIMARY KEY,
+            party_id UUID NOT NULL,
+            sender_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            text TEXT NOT NULL,
+            sent_at TIMESTAMPTZ NOT NULL
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_party_chat_messages_party ON party_chat_messages(party_id, sent_at)",
+        "CREATE TABLE IF NOT EXISTS releases (
+            id UUID PRIMARY KEY,
+            version VARCHAR(64) NOT NULL,
+            channel VARCHAR(16) NOT NULL,
+            platform VARCHAR(16) NOT NULL,
+            url TEXT NOT NULL,
+            sha256 VARCHAR(64) NOT NULL,
+            size BIGINT NOT NULL,
+            changelog TEXT NOT NULL DEFAULT '',
+            published_at TIMESTAMPTZ NOT NULL,
+            yanked_at TIMESTAMPTZ,
+            UNIQUE (version, platform)
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_releases_platform_channel ON releases(platform, channel)",
+        "ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ",
+        "CREATE TABLE IF NOT EXISTS account_deletions (
+            user_id UUID PRIMARY KEY REFERENCES users(id),
+            original_username VARCHAR(64),
+            original_email VARCHAR(255),
+            requested_at TIMESTAMPTZ NOT NULL,
+            purge_after TIMESTAMPTZ NOT NULL,
+            finalized_at TIMESTAMPTZ
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_account_deletions_pending ON account_deletions(purge_after) WHERE finalized_at IS NULL",
+        "INSERT INTO users (id, username, email, password_hash, created_at, updated_at)
+         VALUES ('00000000-0000-4000-8000-00a2c41ed000', 'archived', 'archived@deleted.invalid', '!', NOW(), NOW())
+         ON CONFLICT DO NOTHING",
+        "ALTER TABLE users ADD COLUMN IF NOT EXISTS hide_from_leaderboards BOOLEAN NOT NULL DEFAULT FALSE",
+        "CREATE TABLE IF NOT EXISTS play_sessions (
+            id UUID PRIMARY KEY,
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            server_id UUID REFERENCES game_servers(id) ON DELETE SET NULL,
+            server_name VARCHAR(128),
+            started_at TIMESTAMPTZ NOT NULL,
+            duration_minutes INTEGER NOT NULL,
+            session_count INTEGER NOT NULL DEFAULT 1
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_play_sessions_user ON play_sessions(user_id, started_at)",
+        "CREATE INDEX IF NOT EXISTS idx_play_sessions_started ON play_sessions(started_at)",
+        // Users from before play_sessions get one row standing in for their whole history
+        "INSERT INTO play_sessions (id, user_id, server_name, started_at, duration_minutes, session_count)
+         SELECT gen_random_uuid(), s.user_id, s.favorite_server, COALESCE(s.last_played, NOW()),
+                LEAST(s.total_playtime_minutes, 2147483647)::INTEGER, LEAST(s.total_sessions, 2147483647)::INTEGER
+         FROM game_stats s
+         WHERE s.total_sessions > 0
+           AND NOT EXISTS (SELECT 1 FROM play_sessions p WHERE p.user_id = s.user_id)",
+        "CREATE TABLE IF NOT EXISTS notifications (
+            id UUID PRIMARY KEY,
+            user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            kind VARCHAR(32) NOT NULL,
+            payload JSONB NOT NULL DEFAULT '{}',
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            read_at TIMESTAMPTZ
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications(user_id, created_at DESC)",
+        "CREATE INDEX IF NOT EXISTS idx_notifications_read ON notifications(read_at) WHERE read_at IS NOT NULL",
+        "CREATE TABLE IF NOT EXISTS server_tokens (
+            id UUID PRIMARY KEY,
+            server_id UUID NOT NULL REFERENCES game_servers(id) ON DELETE CASCADE,
+            token_hash VARCHAR(64) NOT NULL UNIQUE,
+            label VARCHAR(64),
+            created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+            created_at TIMESTAMPTZ NOT NULL,
+            last_used_at TIMESTAMPTZ,
+            revoked_at TIMESTAMPTZ
+        )",
+        "CREATE INDEX IF NOT EXISTS idx_server_tokens_server ON server_tokens(server_id)",
+        "CREATE TABLE IF NOT EXISTS server_transfers (
+            server_id UUID PRIMARY KEY REFERENCES game_servers(id) ON DELETE CASCADE,
+            from_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            to_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            created_at TIMESTAMPTZ NOT NULL,
+            expires_at TIMESTAMPTZ NOT NULL
+        )",
     ];
     
     for sql in migrations {
@@ -0,0 +1,377 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub const STATUS_PENDING: &str = "pending_review";
+pub const STATUS_ACTIVE: &str = "active";
+pub const STATUS_REJECTED: &str = "rejected";
+
+pub const ALLOWED_FILE_EXTENSIONS: &[&str] = &["jar", "zip", "ytmod", "png", "json"];
+pub const MAX_FILE_SIZE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Matched as whole words, case-insensitively.
+pub const BANNED_WORDS: &[&str] = &["crack", "cracked", "keygen", "nulled", "malware", "stealer", "ratware"];
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckResult {
+    pub check: &'static str,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(check: &'static str) -> Self {
+        Self { check, passed: true, detail: None }
+    }
+
+    fn fail(check: &'static str, detail: impl Into<String>) -> Self {
+        Self { check, passed: false, detail: Some(detail.into()) }
+    }
+}
+
+/// Whether items that pass every check go live without an admin.
+pub fn auto_approve_enabled() -> bool {
+    std::env::var("MARKETPLACE_AUTO_APPROVE")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+pub fn check_file_extension(file_url: Option<&str>) -> CheckResult {
+    let Some(url) = file_url else {
+        return CheckResult::pass("file_extension");
+    };
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = path.rsplit('/').next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_lowercase());
+
+    match extension {
+        Some(ext) if ALLOWED_FILE_EXTENSIONS.contains(&ext.as_str()) => CheckResult::pass("file_extension"),
+        Some(ext) => CheckResult::fail("file_extension", format!(".{} files are not allowed", ext)),
+        None => CheckResult::fail("file_extension", "File has no extension"),
+    }
+}
+
+pub fn check_file_size(size: Option<u64>) -> CheckResult {
+    match size {
+        Some(bytes) if bytes > MAX_FILE_SIZE_BYTES => CheckResult::fail(
+            "file_size",
+            format!("File is {} MB, limit is {} MB", bytes / (1024 * 1024), MAX_FILE_SIZE_BYTES / (1024 * 1024)),
+        ),
+        Some(_) => CheckResult::pass("file_size"),
+        None => CheckResult::fail("file_size", "Server did not report a file size"),
+    }
+}
+
+pub fn check_banned_words(name: &str, description: &str) -> CheckResult {
+    let text = format!("{} {}", name, description).to_lowercase();
+    let found: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| BANNED_WORDS.contains(word))
+        .collect();
+
+    if found.is_empty() {
+        CheckResult::pass("banned_words")
+    } else {
+        CheckResult::fail("banned_words", format!("Contains banned words: {}", found.join(", ")))
+    }
+}
+
+/// Runs every automated check against a marketplace item, records the results
+/// and the file hash, and auto-approves the item if configured to.
+pub async fn review_item(db: PgPool, item_id: Uuid, auto_approve: bool) {
+    let item = sqlx::query_as::<_, (String, String, Option<String>)>(
+        "SELECT name, description, file_url FROM marketplace_items WHERE id = $1 AND status = $2"
+    )
+        .bind(item_id)
+        .bind(STATUS_PENDING)
+        .fetch_optional(&db)
+        .await
+        .ok()
+        .flatten();
+
+    let Some((name, description, file_url)) = item else {
+        return;
+    };
+
+    let mut results = vec![
+        check_file_extension(file_url.as_deref()),
+        check_banned_words(&name, &description),
+    ];
+
+    let mut file_hash = None;
+    match file_url.as_deref() {
+        Some(url) if results[0].passed => {
+            let (size_result, hash) = fetch_and_hash(url).await;
+            results.push(size_result);
+            file_hash = hash;
+        }
+        Some(_) => results.push(CheckResult::fail("file_size", "Skipped: file type not allowed")),
+        None => results.push(CheckResult::pass("file_size")),
+    }
+
+    results.push(match &file_hash {
+        Some(hash) => {
+            let duplicate = sqlx::query_scalar::<_, Uuid>(
+                "SELECT id FROM marketplace_items WHERE file_hash = $1 AND id <> $2 LIMIT 1"
+            )
+                .bind(hash)
+                .bind(item_id)
+                .fetch_optional(&db)
+                .await
+                .ok()
+                .flatten();
+            match duplicate {
+                Some(other) => CheckResult::fail("duplicate_hash", format!("Same file as item {}", other)),
+                None => CheckResult::pass("duplicate_hash"),
+            }
+        }
+        None if file_url.is_none() => CheckResult::pass("duplicate_hash"),
+        None => CheckResult::fail("duplicate_hash", "Skipped: file could not be fetched"),
+    });
+
+    for result in &results {
+        let _ = sqlx::query(
+            "INSERT INTO moderation_checks (id, item_id, check_name, passed, detail, created_at) VALUES ($1, $2, $3, $4, $5, NOW())"
+        )
+            .bind(Uuid::new_v4())
+            .bind(item_id)
+            .bind(result.check)
+            .bind(result.passed)
+            .bind(&result.detail)
+            .execute(&db)
+            .await;
+    }
+
+    let all_passed = results.iter().all(|r| r.passed);
+    let approve = all_passed && auto_approve;
+    let _ = sqlx::query(
+        "UPDATE marketplace_items SET file_hash = $1, status = CASE WHEN $2 THEN $3 ELSE status END WHERE id = $4 AND status = $5"
+    )
+        .bind(&file_hash)
+        .bind(approve)
+        .bind(STATUS_ACTIVE)
+        .bind(item_id)
+        .bind(STATUS_PENDING)
+        .execute(&db)
+        .await;
+
+    if approve {
+        info!("Marketplace item {} passed all checks and was auto-approved", item_id);
+    } else if !all_passed {
+        info!("Marketplace item {} failed automated checks, awaiting admin review", item_id);
+    }
+}
+
+/// A checked download location: the URL, and the addresses its host resolved
+/// to, which the client is pinned to so a second lookup can't swap them.
+struct FetchTarget {
+    url: reqwest::Url,
+    host: String,
+    addrs: Vec<SocketAddr>,
+}
+
+/// Item URLs come from sellers, so they may only point at public https
+/// hosts: anything resolving to loopback, private, link-local (including
+/// cloud metadata endpoints) or otherwise reserved space is refused.
+async fn resolve_fetch_target(url: &str) -> Result<FetchTarget, String> {
+    let url = reqwest::Url::parse(url).map_err(|_| "File URL is not valid".to_string())?;
+    if url.scheme() != "https" {
+        return Err("File URL must use https".to_string());
+    }
+    let host = url.host_str().ok_or("File URL has no host")?.trim_matches(['[', ']']).to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port)).await
+        .map_err(|_| "File host could not be resolved".to_string())?
+        .collect();
+    if addrs.is_empty() {
+        return Err("File host could not be resolved".to_string());
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        warn!("Refusing to fetch {}: {} resolves to {}", url, host, addr.ip());
+        return Err("File host is not a public address".to_string());
+    }
+    Ok(FetchTarget { url, host, addrs })
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || a >= 240
+                // Shared address space (carrier-grade NAT)
+                || (a == 100 && (64..128).contains(&b))
+                // IETF protocol assignments
+                || (a == 192 && b == 0 && c == 0)
+                // Benchmarking
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let segments = v6.segments();
+            // NAT64 embeds the IPv4 address in the low 32 bits
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., hi, lo] = segments;
+                return is_public_ip(IpAddr::V4(Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo))));
+            }
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // Unique local, e.g. fd00:ec2::254
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link-local
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+/// Checks the size with a HEAD request, then downloads the file to hash it.
+/// Redirects aren't followed, since their target wasn't checked, and the
+/// download stops once it passes the size the HEAD reported.
+async fn fetch_and_hash(url: &str) -> (CheckResult, Option<String>) {
+    let target = match resolve_fetch_target(url).await {
+        Ok(target) => target,
+        Err(reason) => return (CheckResult::fail("file_size", reason), None),
+    };
+    let client = match reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&target.host, &target.addrs)
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => return (CheckResult::fail("file_size", e.to_string()), None),
+    };
+
+    let size = match client.head(target.url.clone()).send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) if response.status().is_redirection() => {
+            return (CheckResult::fail("file_size", "File URL redirects elsewhere"), None);
+        }
+        Ok(response) => response.content_length(),
+        Err(e) => {
+            warn!("HEAD {} failed: {}", url, e);
+            return (CheckResult::fail("file_size", "File could not be reached"), None);
+        }
+    };
+
+    let size_result = check_file_size(size);
+    let Some(size) = size.filter(|_| size_result.passed) else {
+        return (size_result, None);
+    };
+
+    let mut response = match client.get(target.url).send().await.and_then(|r| r.error_for_status()) {
+        Ok(r) if r.status().is_redirection() => {
+            return (CheckResult::fail("file_size", "File URL redirects elsewhere"), None);
+        }
+        Ok(r) => r,
+        Err(e) => {
+            warn!("GET {} failed: {}", url, e);
+            return (size_result, None);
+        }
+    };
+
+    let mut hasher = Sha256::new();
+    let mut downloaded = 0u64;
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                downloaded += chunk.len() as u64;
+                if downloaded > size {
+                    return (CheckResult::fail("file_size", "File is larger than its reported size"), None);
+                }
+                hasher.update(&chunk);
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Download of {} failed: {}", url, e);
+                return (size_result, None);
+            }
+        }
+    }
+
+    (size_result, Some(hex::encode(hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_extension_allowlist() {
+        assert!(check_file_extension(Some("https://cdn.example.com/mods/Better.JAR")).passed);
+        assert!(check_file_extension(Some("https://cdn.example.com/pack.zip?sig=abc.exe")).passed);
+        assert!(check_file_extension(None).passed);
+        assert!(!check_file_extension(Some("https://cdn.example.com/setup.exe")).passed);
+        assert!(!check_file_extension(Some("https://cdn.example.com/download")).passed);
+    }
+
+    #[test]
+    fn test_file_size_limit() {
+        assert!(check_file_size(Some(1024)).passed);
+        assert!(check_file_size(Some(MAX_FILE_SIZE_BYTES)).passed);
+        assert!(!check_file_size(Some(MAX_FILE_SIZE_BYTES + 1)).passed);
+        assert!(!check_file_size(None).passed);
+    }
+
+    #[test]
+    fn test_banned_words_match_whole_words() {
+        assert!(check_banned_words("Crackling Fire", "A cozy campfire").passed);
+        let result = check_banned_words("Free Skins", "Totally not a CRACKED client");
+        assert!(!result.passed);
+        assert_eq!(result.detail.as_deref(), Some("Contains banned words: cracked"));
+    }
+
+    #[test]
+    fn test_only_public_addresses_are_fetchable() {
+        for ip in ["93.184.216.34", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1", "10.0.0.5", "172.16.3.4", "192.168.1.1", "169.254.169.254", "0.0.0.0",
+            "100.64.0.1", "255.255.255.255", "::1", "fe80::1", "fd00:ec2::254", "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_targets_must_be_public_https() {
+        let reason = |url: &'static str| async move { resolve_fetch_target(url).await.err() };
+        assert_eq!(reason("http://93.184.216.34/mod.jar").await.as_deref(), Some("File URL must use https"));
+        assert_eq!(reason("file:///etc/passwd").await.as_deref(), Some("File URL must use https"));
+        for url in ["https://127.0.0.1/mod.jar", "https://169.254.169.254/latest/meta-data.json", "https://[::1]:8443/mod.jar", "https://localhost/mod.jar"] {
+            assert_eq!(reason(url).await.as_deref(), Some("File host is not a public address"), "{}", url);
+        }
+
+        let target = resolve_fetch_target("https://93.184.216.34:8443/mod.jar").await.unwrap();
+        assert_eq!(target.host, "93.184.216.34");
+        assert_eq!(target.addrs, vec!["93.184.216.34:8443".parse().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_private_hosts_before_connecting() {
+        let (result, hash) = fetch_and_hash("https://10.0.0.1/mod.jar").await;
+        assert_eq!(result, CheckResult::fail("file_size", "File host is not a public address"));
+        assert_eq!(hash, None);
+    }
+}
@@ -0,0 +1,454 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::party::Party;
+use crate::relay::{Outbound, RelayMessage};
+use crate::social_guard;
+
+/// Read notifications older than this are deleted by the retention sweep.
+pub const READ_RETENTION_DAYS: i64 = 30;
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+const RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    FriendRequest,
+    PartyInvite,
+    ModerationDecision,
+    EscrowUpdate,
+    PaymentFailed,
+    ServerTransfer,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::FriendRequest => "friend_request",
+            Kind::PartyInvite => "party_invite",
+            Kind::ModerationDecision => "moderation_decision",
+            Kind::EscrowUpdate => "escrow_update",
+            Kind::PaymentFailed => "payment_failed",
+            Kind::ServerTransfer => "server_transfer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl Notification {
+    fn to_relay(&self) -> RelayMessage {
+        RelayMessage::Notification {
+            id: self.id,
+            kind: self.kind.clone(),
+            payload: self.payload.clone(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// A notification that has not been stored yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewNotification {
+    pub recipient: Uuid,
+    /// User whose action caused it. A block between them and the recipient,
+    /// in either direction, drops the notification. `None` for system events.
+    pub actor: Option<Uuid>,
+    pub kind: Kind,
+    pub payload: serde_json::Value,
+}
+
+impl NewNotification {
+    pub fn friend_request(from: Uuid, from_username: &str, to: Uuid) -> Self {
+        Self {
+            recipient: to,
+            actor: Some(from),
+            kind: Kind::FriendRequest,
+            payload: serde_json::json!({ "from_user_id": from, "from_username": from_username }),
+        }
+    }
+
+    pub fn party_invite(from: Uuid, from_username: &str, to: Uuid, party: &Party) -> Self {
+        Self {
+            recipient: to,
+            actor: Some(from),
+            kind: Kind::PartyInvite,
+            payload: serde_json::json!({
+                "from_user_id": from,
+                "from_username": from_username,
+                "party_id": party.party_id,
+                "party_name": party.name,
+                "invite_code": party.invite_code,
+            }),
+        }
+    }
+
+    pub fn moderation_decision(author: Uuid, item_id: Uuid, item_name: &str, status: &str, reason: Option<&str>) -> Self {
+        Self {
+            recipient: author,
+            actor: None,
+            kind: Kind::ModerationDecision,
+            payload: serde_json::json!({
+                "item_id": item_id,
+                "item_name": item_name,
+                "status": status,
+                "reason": reason,
+            }),
+        }
+    }
+
+    pub fn escrow_update(seller: Uuid, escrow_id: Uuid, item_id: Uuid, status: &str) -> Self {
+        Self {
+            recipient: seller,
+            actor: None,
+            kind: Kind::EscrowUpdate,
+            payload: serde_json::json!({ "escrow_id": escrow_id, "item_id": item_id, "status": status }),
+        }
+    }
+
+    pub fn payment_failed(user: Uuid, subscription_id: Option<&str>) -> Self {
+        Self {
+            recipient: user,
+            actor: None,
+            kind: Kind::PaymentFailed,
+            payload: serde_json::json!({ "subscription_id": subscription_id, "status": "past_due" }),
+        }
+    }
+
+    pub fn server_transfer(from: Uuid, from_username: &str, to: Uuid, server_id: Uuid, server_name: &str) -> Self {
+        Self {
+            recipient: to,
+            actor: Some(from),
+            kind: Kind::ServerTransfer,
+            payload: serde_json::json!({
+                "from_user_id": from,
+                "from_username": from_username,
+                "server_id": server_id,
+                "server_name": server_name,
+            }),
+        }
+    }
+
+    /// Whether it may be delivered given the blocks between the actor and the
+    /// recipient, as (blocker, blocked) pairs.
+    pub fn deliverable(&self, blocks: &[(Uuid, Uuid)]) -> bool {
+        let Some(actor) = self.actor else {
+            return true;
+        };
+        actor != self.recipient
+            && !blocks.iter().any(|&(blocker, blocked)| {
+                (blocker, blocked) == (actor, self.recipient) || (blocker, blocked) == (self.recipient, actor)
+            })
+    }
+}
+
+struct Subscriber {
+    connection_id: u64,
+    sender: mpsc::UnboundedSender<Outbound>,
+}
+
+/// Websocket connections that get new notifications pushed as they are stored
+pub struct NotificationHub {
+    subscribers: Mutex<HashMap<Uuid, Subscriber>>,
+    next_connection: AtomicU64,
+}
+
+impl NotificationHub {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Mutex::new(HashMap::new()),
+            next_connection: AtomicU64::new(1),
+        }
+    }
+
+    /// Push the user's notifications to this connection, replacing any earlier
+    /// one. Returns an id for `unsubscribe`.
+    pub fn subscribe(&self, user_id: Uuid, sender: mpsc::UnboundedSender<Outbound>) -> u64 {
+        let connection_id = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        self.subscribers.lock().unwrap().insert(user_id, Subscriber { connection_id, sender });
+        connection_id
+    }
+
+    pub fn unsubscribe(&self, user_id: Uuid, connection_id: u64) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.get(&user_id).is_some_and(|s| s.connection_id == connection_id) {
+            subscribers.remove(&user_id);
+        }
+    }
+
+    /// Returns whether the user had a live connection to push to
+    pub fn push(&self, user_id: Uuid, notification: &Notification) -> bool {
+        self.push_message(user_id, &notification.to_relay())
+    }
+
+    /// Pushes any server event over the user's notification feed
+    pub fn push_message(&self, user_id: Uuid, message: &RelayMessage) -> bool {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers.get(&user_id)
+            .is_some_and(|s| s.sender.send(Outbound::Text(message.to_text())).is_ok())
+    }
+
+    /// Pushes one event to each of `users` that is connected; returns how
+    /// many received it
+    pub fn push_to_all(&self, users: impl IntoIterator<Item = Uuid>, message: &RelayMessage) -> usize {
+        users.into_iter().filter(|user_id| self.push_message(*user_id, message)).count()
+    }
+}
+
+impl Default for NotificationHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stores the notification and pushes it to the recipient if they are
+/// connected. Returns `None` when a block suppressed it.
+pub async fn send(db: &PgPool, hub: &NotificationHub, notification: NewNotification) -> Result<Option<Notification>, sqlx::Error> {
+    if let Some(actor) = notification.actor {
+        let blocks = social_guard::pairs_between(db, actor, notification.recipient).await?;
+        if !notification.deliverable(&blocks) {
+            return Ok(None);
+        }
+    }
+
+    let (id, created_at) = sqlx::query_as::<_, (Uuid, DateTime<Utc>)>(
+        "INSERT INTO notifications (id, user_id, kind, payload, created_at)
+         VALUES ($1, $2, $3, $4, NOW()) RETURNING id, created_at"
+    )
+        .bind(Uuid::new_v4())
+        .bind(notification.recipient)
+        .bind(notification.kind.as_str())
+        .bind(&notification.payload)
+        .fetch_one(db)
+        .await?;
+
+    let stored = Notification {
+        id,
+        kind: notification.kind.as_str().to_string(),
+        payload: notification.payload,
+        created_at,
+        read_at: None,
+    };
+    hub.push(notification.recipient, &stored);
+    Ok(Some(stored))
+}
+
+/// Logs rather than fails: a notification going missing shouldn't undo the
+/// action that caused it.
+pub async fn send_logged(db: &PgPool, hub: &NotificationHub, notification: NewNotification) {
+    let kind = notification.kind;
+    if let Err(e) = send(db, hub, notification).await {
+        warn!("Failed to send {} notification: {}", kind.as_str(), e);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationPage {
+    pub notifications: Vec<Notification>,
+    pub unread_count: i64,
+}
+
+/// Newest first
+pub async fn list(db: &PgPool, user_id: Uuid, unread_only: bool, limit: Option<i64>) -> Result<NotificationPage, sqlx::Error> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let rows = sqlx::query_as::<_, (Uuid, String, serde_json::Value, DateTime<Utc>, Option<DateTime<Utc>>)>(
+        "SELECT id, kind, payload, created_at, read_at FROM notifications
+         WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+         ORDER BY created_at DESC LIMIT $3"
+    )
+        .bind(user_id)
+        .bind(unread_only)
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+    let unread_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL"
+    )
+        .bind(user_id)
+        .fetch_one(db)
+        .await?;
+
+    Ok(NotificationPage {
+        notifications: rows.into_iter()
+            .map(|(id, kind, payload, created_at, read_at)| Notification { id, kind, payload, created_at, read_at })
+            .collect(),
+        unread_count,
+    })
+}
+
+/// Marks one notification read, or all of them when `id` is `None`. Returns
+/// how many changed.
+pub async fn mark_read(db: &PgPool, user_id: Uuid, id: Option<Uuid>) -> Result<u64, sqlx::Error> {
+    sqlx::query(
+        "UPDATE notifications SET read_at = NOW()
+         WHERE user_id = $1 AND read_at IS NULL AND ($2::UUID IS NULL OR id = $2)"
+    )
+        .bind(user_id)
+        .bind(id)
+        .execute(db)
+        .await
+        .map(|r| r.rows_affected())
+}
+
+pub async fn delete(db: &PgPool, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM notifications WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(db)
+        .await
+        .map(|r| r.rows_affected() > 0)
+}
+
+pub async fn purge_read(db: &PgPool, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    sqlx::query("DELETE FROM notifications WHERE read_at < $1")
+        .bind(now - ChronoDuration::days(READ_RETENTION_DAYS))
+        .execute(db)
+        .await
+        .map(|r| r.rows_affected())
+}
+
+pub fn spawn_retention(db: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RETENTION_SWEEP_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match purge_read(&db, Utc::now()).await {
+                Ok(purged) if purged > 0 => info!("Deleted {} old read notifications", purged),
+                Ok(_) => {}
+                Err(e) => warn!("Notification retention sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect(hub: &NotificationHub, user_id: Uuid) -> mpsc::UnboundedReceiver<Outbound> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        hub.subscribe(user_id, tx);
+        rx
+    }
+
+    fn stored(notification: &NewNotification) -> Notification {
+        Notification {
+            id: Uuid::new_v4(),
+            kind: notification.kind.as_str().to_string(),
+            payload: notification.payload.clone(),
+            created_at: Utc::now(),
+            read_at: None,
+        }
+    }
+
+    fn party(leader_id: Uuid) -> Party {
+        Party {
+            party_id: Uuid::new_v4(),
+            name: "alex's Party".to_string(),
+            leader_id,
+            max_members: 8,
+            invite_code: "PARTY-1A2B3C4D".to_string(),
+            persist_chat: false,
+            members: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_each_event_notifies_only_its_recipient_once() {
+        let (alex, sam, item) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let events = [
+            (NewNotification::friend_request(alex, "alex", sam), Kind::FriendRequest),
+            (NewNotification::party_invite(alex, "alex", sam, &party(alex)), Kind::PartyInvite),
+            (NewNotification::moderation_decision(sam, item, "Shaders", "rejected", Some("Broken link")), Kind::ModerationDecision),
+            (NewNotification::escrow_update(sam, Uuid::new_v4(), item, "released"), Kind::EscrowUpdate),
+            (NewNotification::payment_failed(sam, Some("sub_123")), Kind::PaymentFailed),
+            (NewNotification::server_transfer(alex, "alex", sam, Uuid::new_v4(), "Skyblock"), Kind::ServerTransfer),
+        ];
+
+        let hub = NotificationHub::new();
+        let mut alex_rx = connect(&hub, alex);
+        let mut sam_rx = connect(&hub, sam);
+        for (notification, kind) in events {
+            assert_eq!(notification.kind, kind);
+            assert_eq!(notification.recipient, sam);
+            assert!(notification.deliverable(&[]));
+            assert!(hub.push(notification.recipient, &stored(&notification)));
+
+            match sam_rx.try_recv().unwrap() {
+                Outbound::Text(text) => match serde_json::from_str::<RelayMessage>(&text).unwrap() {
+                    RelayMessage::Notification { kind: received, .. } => assert_eq!(received, kind.as_str()),
+                    other => panic!("unexpected message {:?}", other),
+                },
+                other => panic!("unexpected frame {:?}", other),
+            }
+            assert!(sam_rx.try_recv().is_err());
+            assert!(alex_rx.try_recv().is_err());
+        }
+
+        // Offline users still get the stored notification, just no push
+        assert!(!hub.push(Uuid::new_v4(), &stored(&NewNotification::friend_request(alex, "alex", sam))));
+    }
+
+    #[test]
+    fn test_login_presence_reaches_connected_friends() {
+        let (alex, sam, stranger) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let hub = NotificationHub::new();
+        let mut alex_rx = connect(&hub, alex);
+        let mut stranger_rx = connect(&hub, stranger);
+
+        // What logging in pushes to sam's friends
+        let online = RelayMessage::Presence {
+            user_id: sam,
+            status: "online".to_string(),
+            activity: None,
+            server_id: None,
+            updated_at: Utc::now(),
+        };
+        assert_eq!(hub.push_to_all([alex, Uuid::new_v4()], &online), 1);
+
+        match alex_rx.try_recv().unwrap() {
+            Outbound::Text(text) => match serde_json::from_str::<RelayMessage>(&text).unwrap() {
+                RelayMessage::Presence { user_id, status, .. } => assert_eq!((user_id, status.as_str()), (sam, "online")),
+                other => panic!("unexpected message {:?}", other),
+            },
+            other => panic!("unexpected frame {:?}", other),
+        }
+        assert!(stranger_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_blocks_suppress_notifications_both_ways() {
+        let (alex, sam, item) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let invite = NewNotification::party_invite(alex, "alex", sam, &party(alex));
+        let request = NewNotification::friend_request(sam, "sam", alex);
+
+        for blocks in [[(alex, sam)], [(sam, alex)]] {
+            assert!(!invite.deliverable(&blocks));
+            assert!(!request.deliverable(&blocks));
+        }
+        assert!(invite.deliverable(&[(alex, Uuid::new_v4())]));
+        assert!(!NewNotification::friend_request(alex, "alex", alex).deliverable(&[]));
+
+        // System events aren't anyone's action to block
+        let decision = NewNotification::moderation_decision(sam, item, "Shaders", "active", None);
+        assert!(decision.deliverable(&[(sam, alex)]));
+    }
+}
@@ -0,0 +1,150 @@
+//! Cosmetic ownership answers for registered game servers
+//!
+//! A server with a scoped token asks whether its players own the cosmetics
+//! they claim. Only players whose latest presence puts them on that server
+//! get an answer, so a server token can't be used to read anyone else's
+//! inventory. Each answer is signed with the hash of the server's token,
+//! which the server can compute too, and expires after
+//! `VERIFICATION_TTL_SECS`.
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::billing::hmac_sha256;
+
+/// Claims accepted in one request
+pub const MAX_VERIFY_ITEMS: usize = 200;
+/// How long a server may rely on an answer before asking again
+pub const VERIFICATION_TTL_SECS: i64 = 300;
+/// A presence update older than this no longer places a user on a server
+pub const PRESENCE_HINT_TTL_SECS: i64 = 15 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub struct OwnershipClaim {
+    pub user_id: Uuid,
+    pub item_id: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnershipResult {
+    pub user_id: Uuid,
+    pub item_id: Uuid,
+    pub owned: bool,
+    pub expires_at: DateTime<Utc>,
+    /// Hex HMAC-SHA256 of `signing_payload`, keyed with the token hash
+    pub signature: String,
+}
+
+impl OwnershipResult {
+    pub fn signed(server_id: Uuid, claim: OwnershipClaim, owned: bool, expires_at: DateTime<Utc>, token_hash: &str) -> Self {
+        let payload = signing_payload(server_id, claim.user_id, claim.item_id, owned, expires_at);
+        Self {
+            user_id: claim.user_id,
+            item_id: claim.item_id,
+            owned,
+            expires_at,
+            signature: hex::encode(hmac_sha256(token_hash.as_bytes(), payload.as_bytes())),
+        }
+    }
+}
+
+/// What an answer's signature covers. Servers rebuild this to check it.
+pub fn signing_payload(server_id: Uuid, user_id: Uuid, item_id: Uuid, owned: bool, expires_at: DateTime<Utc>) -> String {
+    format!("{}|{}|{}|{}|{}", server_id, user_id, item_id, owned, expires_at.timestamp())
+}
+
+pub fn expiry(now: DateTime<Utc>) -> DateTime<Utc> {
+    now + Duration::seconds(VERIFICATION_TTL_SECS)
+}
+
+/// Which server each user last said they were playing on, from presence
+/// updates. Kept in memory; a restart only means users are unanswered
+/// until their next update.
+#[derive(Debug, Default)]
+pub struct PresenceHints {
+    hints: DashMap<Uuid, (String, DateTime<Utc>)>,
+}
+
+impl PresenceHints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, user_id: Uuid, status: &str, server_id: Option<&str>, now: DateTime<Utc>) {
+        match server_id {
+            Some(server_id) if status != "offline" => {
+                self.hints.insert(user_id, (server_id.to_string(), now));
+            }
+            _ => {
+                self.hints.remove(&user_id);
+            }
+        }
+    }
+
+    pub fn is_on_server(&self, user_id: Uuid, server_id: Uuid, now: DateTime<Utc>) -> bool {
+        self.hints.get(&user_id).is_some_and(|hint| {
+            let (on, updated_at) = hint.value();
+            on.eq_ignore_ascii_case(&server_id.to_string())
+                && now - *updated_at <= Duration::seconds(PRESENCE_HINT_TTL_SECS)
+        })
+    }
+}
+
+/// The claims that get an answer: each pair once, and only for users whose
+/// presence puts them on `server_id`
+pub fn answerable(claims: &[OwnershipClaim], server_id: Uuid, presence: &PresenceHints, now: DateTime<Utc>) -> Vec<OwnershipClaim> {
+    let mut seen = HashSet::new();
+    claims.iter()
+        .filter(|claim| seen.insert(**claim))
+        .filter(|claim| presence.is_on_server(claim.user_id, server_id, now))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(user_id: Uuid) -> OwnershipClaim {
+        OwnershipClaim { user_id, item_id: Uuid::new_v4() }
+    }
+
+    #[test]
+    fn test_only_players_on_the_server_are_answered() {
+        let presence = PresenceHints::new();
+        let (server, elsewhere) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        let (here, away, stale, left) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        presence.record(here, "in_game", Some(&server.to_string().to_uppercase()), now);
+        presence.record(away, "in_game", Some(&elsewhere.to_string()), now);
+        presence.record(stale, "in_game", Some(&server.to_string()), now - Duration::seconds(PRESENCE_HINT_TTL_SECS + 1));
+        presence.record(left, "in_game", Some(&server.to_string()), now);
+        presence.record(left, "offline", Some(&server.to_string()), now);
+
+        let here_claim = claim(here);
+        let claims = [here_claim, here_claim, claim(away), claim(stale), claim(left), claim(Uuid::new_v4())];
+        assert_eq!(answerable(&claims, server, &presence, now), vec![here_claim]);
+    }
+
+    #[test]
+    fn test_signature_covers_every_field() {
+        let (server, now) = (Uuid::new_v4(), Utc::now());
+        let claim = claim(Uuid::new_v4());
+        let result = OwnershipResult::signed(server, claim, true, expiry(now), "hash");
+        assert_eq!(result, OwnershipResult::signed(server, claim, true, expiry(now), "hash"));
+
+        let variants = [
+            OwnershipResult::signed(Uuid::new_v4(), claim, true, expiry(now), "hash"),
+            OwnershipResult::signed(server, OwnershipClaim { item_id: Uuid::new_v4(), ..claim }, true, expiry(now), "hash"),
+            OwnershipResult::signed(server, claim, false, expiry(now), "hash"),
+            OwnershipResult::signed(server, claim, true, expiry(now) + Duration::seconds(1), "hash"),
+            OwnershipResult::signed(server, claim, true, expiry(now), "other"),
+        ];
+        for variant in variants {
+            assert_ne!(variant.signature, result.signature);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+pub const DEFAULT_PER_PAGE: u32 = 50;
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// A numbered page of a listing. Pages count from 1; a missing or zero page
+/// is the first, and `per_page` is capped at `MAX_PER_PAGE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PageRequest {
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl PageRequest {
+    pub fn new(page: Option<u32>, per_page: Option<u32>) -> Self {
+        Self {
+            page: page.unwrap_or(1).max(1),
+            per_page: per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE),
+        }
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page as i64
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page as i64 - 1) * self.per_page as i64
+    }
+}
+
+/// Position after the last item of a downloads-sorted page. Items are ordered
+/// by downloads, then id, both descending, so the key is unique and the next
+/// page is every item whose key is lower; `Ord` compares the same way as the
+/// `(downloads, id)` row comparison in SQL, uuids included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DownloadsCursor {
+    pub downloads: i64,
+    pub id: Uuid,
+}
+
+impl DownloadsCursor {
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.downloads, self.id)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let (downloads, id) = cursor.split_once('.')?;
+        Some(Self {
+            downloads: downloads.parse().ok()?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 160 items with plenty of tied download counts, in listing order
+    fn seeded() -> Vec<DownloadsCursor> {
+        let mut items: Vec<DownloadsCursor> = (0..160)
+            .map(|i| DownloadsCursor { downloads: (i % 7) * 100, id: Uuid::new_v4() })
+            .collect();
+        items.sort_by(|a, b| b.cmp(a));
+        items
+    }
+
+    #[test]
+    fn test_page_request_defaults_and_caps() {
+        assert_eq!(PageRequest::new(None, None), PageRequest { page: 1, per_page: DEFAULT_PER_PAGE });
+        assert_eq!(PageRequest::new(Some(0), Some(0)), PageRequest { page: 1, per_page: 1 });
+        assert_eq!(PageRequest::new(Some(3), Some(10_000)).per_page, MAX_PER_PAGE);
+        assert_eq!(PageRequest::new(Some(3), Some(40)).offset(), 80);
+    }
+
+    #[test]
+    fn test_numbered_pages_cover_every_item_once() {
+        let items = seeded();
+        let mut seen = Vec::new();
+        for page in 1.. {
+            let request = PageRequest::new(Some(page), Some(40));
+            let start = (request.offset() as usize).min(items.len());
+            let end = (start + request.limit() as usize).min(items.len());
+            if start == end {
+                break;
+            }
+            seen.extend_from_slice(&items[start..end]);
+        }
+        assert_eq!(seen, items);
+    }
+
+    #[test]
+    fn test_cursor_pages_without_gaps_or_repeats() {
+        let items = seeded();
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let after = cursor.as_deref().map(|c| DownloadsCursor::decode(c).unwrap());
+            let page: Vec<DownloadsCursor> = items.iter()
+                .filter(|item| after.is_none_or(|after| **item < after))
+                .take(MAX_PER_PAGE as usize)
+                .copied()
+                .collect();
+            seen.extend_from_slice(&page);
+            if page.len() < MAX_PER_PAGE as usize {
+                break;
+            }
+            cursor = page.last().map(DownloadsCursor::encode);
+        }
+        assert_eq!(seen, items);
+        assert!(DownloadsCursor::decode("12").is_none());
+        assert!(DownloadsCursor::decode("many.nope").is_none());
+    }
+}
@@ -0,0 +1,461 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::relay::{Outbound, RelayMessage};
+use crate::social_guard;
+
+pub const DEFAULT_MAX_MEMBERS: usize = 8;
+pub const MAX_MEMBERS: usize = 32;
+/// Messages kept per party for `chat/history`
+pub const CHAT_HISTORY_LIMIT: usize = 200;
+pub const MAX_CHAT_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartyError {
+    NotFound,
+    Full,
+    NotMember,
+    AlreadyInParty,
+    InvalidMessage(String),
+}
+
+impl std::fmt::Display for PartyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Party not found"),
+            Self::Full => write!(f, "Party is full"),
+            Self::NotMember => write!(f, "You are not a member of this party"),
+            Self::AlreadyInParty => write!(f, "Leave your current party first"),
+            Self::InvalidMessage(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PartyError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartyMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Party {
+    pub party_id: Uuid,
+    pub name: String,
+    pub leader_id: Uuid,
+    pub max_members: usize,
+    pub invite_code: String,
+    /// Also write chat messages to `party_chat_messages`
+    pub persist_chat: bool,
+    pub members: Vec<PartyMember>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Party {
+    pub fn is_member(&self, user_id: Uuid) -> bool {
+        self.members.iter().any(|m| m.user_id == user_id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartyChatMessage {
+    pub id: Uuid,
+    pub party_id: Uuid,
+    pub from: Uuid,
+    pub username: String,
+    pub text: String,
+    pub ts: DateTime<Utc>,
+}
+
+impl PartyChatMessage {
+    fn to_relay(&self) -> RelayMessage {
+        RelayMessage::PartyChat {
+            party_id: self.party_id,
+            from: self.from,
+            text: self.text.clone(),
+            ts: self.ts,
+        }
+    }
+}
+
+/// What a chat filter decided about a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterVerdict {
+    /// Deliver, possibly with words masked
+    Allow(String),
+    Reject(String),
+}
+
+/// Hook for screening party chat before it is stored or delivered
+pub trait ChatFilter: Send + Sync {
+    fn check(&self, text: &str) -> FilterVerdict;
+}
+
+/// Masks configured words, matched as whole words case-insensitively
+pub struct WordMaskFilter {
+    words: Vec<String>,
+}
+
+impl WordMaskFilter {
+    pub fn new(words: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            words: words.into_iter().map(|w| w.into().to_lowercase()).filter(|w| !w.is_empty()).collect(),
+        }
+    }
+
+    /// Words from the comma-separated `PARTY_CHAT_MASKED_WORDS`
+    pub fn from_env() -> Self {
+        let words = std::env::var("PARTY_CHAT_MASKED_WORDS").unwrap_or_default();
+        Self::new(words.split(',').map(str::trim))
+    }
+}
+
+impl ChatFilter for WordMaskFilter {
+    fn check(&self, text: &str) -> FilterVerdict {
+        let mut masked = String::with_capacity(text.len());
+        let mut word = String::new();
+        let flush = |word: &mut String, masked: &mut String| {
+            if self.words.contains(&word.to_lowercase()) {
+                masked.extend(std::iter::repeat_n('*', word.chars().count()));
+            } else {
+                masked.push_str(word);
+            }
+            word.clear();
+        };
+        for c in text.chars() {
+            if c.is_alphanumeric() {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut masked);
+                masked.push(c);
+            }
+        }
+        flush(&mut word, &mut masked);
+        FilterVerdict::Allow(masked)
+    }
+}
+
+struct Subscriber {
+    connection_id: u64,
+    sender: mpsc::UnboundedSender<Outbound>,
+}
+
+#[derive(Default)]
+struct PartyState {
+    parties: HashMap<Uuid, Party>,
+    /// Which party each user is in
+    member_of: HashMap<Uuid, Uuid>,
+    history: HashMap<Uuid, VecDeque<PartyChatMessage>>,
+}
+
+/// Parties and their chat, held in memory
+pub struct PartyHub {
+    state: Mutex<PartyState>,
+    /// Websocket connections receiving chat, one per user
+    subscribers: Mutex<HashMap<Uuid, Subscriber>>,
+    next_connection: AtomicU64,
+    filter: Box<dyn ChatFilter>,
+}
+
+impl PartyHub {
+    pub fn new(filter: Box<dyn ChatFilter>) -> Self {
+        Self {
+            state: Mutex::new(PartyState::default()),
+            subscribers: Mutex::new(HashMap::new()),
+            next_connection: AtomicU64::new(1),
+            filter,
+        }
+    }
+
+    pub fn create(&self, leader_id: Uuid, username: &str, name: Option<String>, max_members: Option<usize>, persist_chat: bool) -> Result<Party, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        if state.member_of.contains_key(&leader_id) {
+            return Err(PartyError::AlreadyInParty);
+        }
+
+        let party_id = Uuid::new_v4();
+        let party = Party {
+            party_id,
+            name: name.unwrap_or_else(|| format!("{}'s Party", username)),
+            leader_id,
+            max_members: max_members.unwrap_or(DEFAULT_MAX_MEMBERS).clamp(2, MAX_MEMBERS),
+            invite_code: format!("PARTY-{}", &party_id.simple().to_string()[..8].to_uppercase()),
+            persist_chat,
+            members: vec![PartyMember { user_id: leader_id, username: username.to_string(), role: "leader" }],
+            created_at: Utc::now(),
+        };
+        state.member_of.insert(leader_id, party_id);
+        state.parties.insert(party_id, party.clone());
+        Ok(party)
+    }
+
+    /// Join by id or by invite code
+    pub fn join(&self, party_id: Option<Uuid>, invite_code: Option<&str>, user_id: Uuid, username: &str) -> Result<Party, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        let party_id = match (party_id, invite_code) {
+            (Some(id), _) => id,
+            (None, Some(code)) => state.parties.values()
+                .find(|p| p.invite_code.eq_ignore_ascii_case(code.trim()))
+                .map(|p| p.party_id)
+                .ok_or(PartyError::NotFound)?,
+            (None, None) => return Err(PartyError::NotFound),
+        };
+
+        match state.member_of.get(&user_id) {
+            Some(current) if *current == party_id => {
+                return state.parties.get(&party_id).cloned().ok_or(PartyError::NotFound);
+            }
+            Some(_) => return Err(PartyError::AlreadyInParty),
+            None => {}
+        }
+
+        let party = state.parties.get_mut(&party_id).ok_or(PartyError::NotFound)?;
+        if party.members.len() >= party.max_members {
+            return Err(PartyError::Full);
+        }
+        party.members.push(PartyMember { user_id, username: username.to_string(), role: "member" });
+        let party = party.clone();
+        state.member_of.insert(user_id, party_id);
+        Ok(party)
+    }
+
+    /// Leave the user's party. The oldest remaining member becomes leader;
+    /// the party and its history go away with its last member.
+    pub fn leave(&self, user_id: Uuid) -> Option<Uuid> {
+        let mut state = self.state.lock().unwrap();
+        let party_id = state.member_of.remove(&user_id)?;
+        let Some(party) = state.parties.get_mut(&party_id) else {
+            return Some(party_id);
+        };
+
+        party.members.retain(|m| m.user_id != user_id);
+        if party.members.is_empty() {
+            state.parties.remove(&party_id);
+            state.history.remove(&party_id);
+        } else if party.leader_id == user_id {
+            party.leader_id = party.members[0].user_id;
+            party.members[0].role = "leader";
+        }
+        Some(party_id)
+    }
+
+    /// The party the user is in
+    pub fn party_of(&self, user_id: Uuid) -> Option<Party> {
+        let state = self.state.lock().unwrap();
+        state.member_of.get(&user_id).and_then(|id| state.parties.get(id)).cloned()
+    }
+
+    pub fn get(&self, party_id: Uuid) -> Option<Party> {
+        self.state.lock().unwrap().parties.get(&party_id).cloned()
+    }
+
+    /// Screen and validate a message from `from` before it is posted
+    pub fn prepare(&self, party_id: Uuid, from: Uuid, text: &str) -> Result<(Party, String), PartyError> {
+        let party = self.get(party_id).ok_or(PartyError::NotFound)?;
+        if !party.is_member(from) {
+            return Err(PartyError::NotMember);
+        }
+
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(PartyError::InvalidMessage("Message is empty".to_string()));
+        }
+        if text.chars().count() > MAX_CHAT_LENGTH {
+            return Err(PartyError::InvalidMessage(format!("Messages are limited to {} characters", MAX_CHAT_LENGTH)));
+        }
+        match self.filter.check(text) {
+            FilterVerdict::Allow(text) => Ok((party, text)),
+            FilterVerdict::Reject(reason) => Err(PartyError::InvalidMessage(reason)),
+        }
+    }
+
+    /// Record a prepared message and deliver it to connected members other
+    /// than the sender, skipping anyone in `muted` (users with a block
+    /// between them and the sender). Returns the message and how many
+    /// connections it was sent to.
+    pub fn post(&self, party_id: Uuid, from: Uuid, text: String, muted: &HashSet<Uuid>) -> Result<(PartyChatMessage, usize), PartyError> {
+        let (message, recipients) = {
+            let mut state = self.state.lock().unwrap();
+            let party = state.parties.get(&party_id).ok_or(PartyError::NotFound)?;
+            let sender = party.members.iter().find(|m| m.user_id == from).ok_or(PartyError::NotMember)?;
+
+            let message = PartyChatMessage {
+                id: Uuid::new_v4(),
+                party_id,
+                from,
+                username: sender.username.clone(),
+                text,
+                ts: Utc::now(),
+            };
+            let recipients: Vec<Uuid> = party.members.iter()
+                .map(|m| m.user_id)
+                .filter(|id| *id != from && !muted.contains(id))
+                .collect();
+
+            let history = state.history.entry(party_id).or_default();
+            history.push_back(message.clone());
+            while history.len() > CHAT_HISTORY_LIMIT {
+                history.pop_front();
+            }
+            (message, recipients)
+        };
+
+        let outbound = Outbound::Text(message.to_relay().to_text());
+        let subscribers = self.subscribers.lock().unwrap();
+        let delivered = recipients.iter()
+            .filter_map(|id| subscribers.get(id))
+            .filter(|s| s.sender.send(outbound.clone()).is_ok())
+            .count();
+        Ok((message, delivered))
+    }
+
+    /// Recent messages for a member, oldest first, leaving out senders in
+    /// `muted`
+    pub fn history(&self, party_id: Uuid, user_id: Uuid, muted: &HashSet<Uuid>, limit: usize) -> Result<Vec<PartyChatMessage>, PartyError> {
+        let state = self.state.lock().unwrap();
+        let party = state.parties.get(&party_id).ok_or(PartyError::NotFound)?;
+        if !party.is_member(user_id) {
+            return Err(PartyError::NotMember);
+        }
+
+        let visible: Vec<PartyChatMessage> = state.history.get(&party_id)
+            .map(|h| h.iter().filter(|m| !muted.contains(&m.from)).cloned().collect())
+            .unwrap_or_default();
+        let skip = visible.len().saturating_sub(limit);
+        Ok(visible.into_iter().skip(skip).collect())
+    }
+
+    /// Deliver the user's party chat to this connection, replacing any
+    /// earlier one. Returns an id for `unsubscribe`.
+    pub fn subscribe(&self, user_id: Uuid, sender: mpsc::UnboundedSender<Outbound>) -> u64 {
+        let connection_id = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        self.subscribers.lock().unwrap().insert(user_id, Subscriber { connection_id, sender });
+        connection_id
+    }
+
+    pub fn unsubscribe(&self, user_id: Uuid, connection_id: u64) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.get(&user_id).is_some_and(|s| s.connection_id == connection_id) {
+            subscribers.remove(&user_id);
+        }
+    }
+}
+
+/// Members of `party` with a block in either direction between them and `user_id`
+pub async fn blocked_members(db: &sqlx::PgPool, user_id: Uuid, party: &Party) -> Result<HashSet<Uuid>, sqlx::Error> {
+    let members: Vec<Uuid> = party.members.iter().map(|m| m.user_id).filter(|id| *id != user_id).collect();
+    social_guard::blocked_among(db, user_id, &members).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hub() -> PartyHub {
+        PartyHub::new(Box::new(WordMaskFilter::new(["darn"])))
+    }
+
+    fn connect(hub: &PartyHub, user_id: Uuid) -> mpsc::UnboundedReceiver<Outbound> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        hub.subscribe(user_id, tx);
+        rx
+    }
+
+    fn received(rx: &mut mpsc::UnboundedReceiver<Outbound>) -> Vec<String> {
+        let mut texts = Vec::new();
+        while let Ok(Outbound::Text(text)) = rx.try_recv() {
+            match serde_json::from_str::<RelayMessage>(&text).unwrap() {
+                RelayMessage::PartyChat { text, .. } => texts.push(text),
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+        texts
+    }
+
+    #[test]
+    fn test_membership_lifecycle() {
+        let hub = hub();
+        let (alex, sam) = (Uuid::new_v4(), Uuid::new_v4());
+        let party = hub.create(alex, "alex", None, Some(2), false).unwrap();
+        assert_eq!(party.name, "alex's Party");
+
+        let joined = hub.join(None, Some(&party.invite_code.to_lowercase()), sam, "sam").unwrap();
+        assert_eq!(joined.members.len(), 2);
+        assert_eq!(hub.join(Some(party.party_id), None, Uuid::new_v4(), "late").unwrap_err(), PartyError::Full);
+        assert!(matches!(hub.create(sam, "sam", None, None, false), Err(PartyError::AlreadyInParty)));
+
+        assert_eq!(hub.leave(alex), Some(party.party_id));
+        assert_eq!(hub.get(party.party_id).unwrap().leader_id, sam);
+        hub.leave(sam);
+        assert!(hub.get(party.party_id).is_none());
+    }
+
+    #[test]
+    fn test_non_members_cannot_send_or_read() {
+        let hub = hub();
+        let (alex, outsider) = (Uuid::new_v4(), Uuid::new_v4());
+        let party = hub.create(alex, "alex", None, None, false).unwrap();
+        let mut alex_rx = connect(&hub, alex);
+        connect(&hub, outsider);
+
+        assert_eq!(hub.prepare(party.party_id, outsider, "hi").unwrap_err(), PartyError::NotMember);
+        assert_eq!(hub.post(party.party_id, outsider, "hi".to_string(), &HashSet::new()).unwrap_err(), PartyError::NotMember);
+        assert_eq!(hub.history(party.party_id, outsider, &HashSet::new(), 50).unwrap_err(), PartyError::NotMember);
+        assert!(received(&mut alex_rx).is_empty());
+    }
+
+    #[test]
+    fn test_blocked_users_never_receive() {
+        let hub = hub();
+        let (alex, sam, kim) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let party = hub.create(alex, "alex", None, None, false).unwrap();
+        hub.join(Some(party.party_id), None, sam, "sam").unwrap();
+        hub.join(Some(party.party_id), None, kim, "kim").unwrap();
+        let (mut alex_rx, mut sam_rx, mut kim_rx) = (connect(&hub, alex), connect(&hub, sam), connect(&hub, kim));
+
+        // Kim has blocked Alex
+        let muted: HashSet<Uuid> = [kim].into();
+        let (_, text) = hub.prepare(party.party_id, alex, "  well darn  ").unwrap();
+        let (_, delivered) = hub.post(party.party_id, alex, text, &muted).unwrap();
+        assert_eq!(delivered, 1);
+        assert_eq!(received(&mut sam_rx), vec!["well ****"]);
+        assert!(received(&mut kim_rx).is_empty());
+        assert!(received(&mut alex_rx).is_empty());
+
+        hub.post(party.party_id, sam, "gg".to_string(), &HashSet::new()).unwrap();
+        let history = hub.history(party.party_id, kim, &[alex].into(), 50).unwrap();
+        assert_eq!(history.iter().map(|m| m.text.as_str()).collect::<Vec<_>>(), vec!["gg"]);
+    }
+
+    #[test]
+    fn test_history_is_a_ring() {
+        let hub = hub();
+        let alex = Uuid::new_v4();
+        let party = hub.create(alex, "alex", None, None, false).unwrap();
+        for i in 0..CHAT_HISTORY_LIMIT + 5 {
+            hub.post(party.party_id, alex, i.to_string(), &HashSet::new()).unwrap();
+        }
+
+        let history = hub.history(party.party_id, alex, &HashSet::new(), usize::MAX).unwrap();
+        assert_eq!(history.len(), CHAT_HISTORY_LIMIT);
+        assert_eq!(history[0].text, "5");
+        assert_eq!(hub.history(party.party_id, alex, &HashSet::new(), 2).unwrap()[1].text, (CHAT_HISTORY_LIMIT + 4).to_string());
+    }
+
+    #[test]
+    fn test_messages_are_validated() {
+        let hub = hub();
+        let alex = Uuid::new_v4();
+        let party = hub.create(alex, "alex", None, None, false).unwrap();
+        assert!(matches!(hub.prepare(party.party_id, alex, "   "), Err(PartyError::InvalidMessage(_))));
+        assert!(matches!(hub.prepare(party.party_id, alex, &"a".repeat(MAX_CHAT_LENGTH + 1)), Err(PartyError::InvalidMessage(_))));
+        assert_eq!(hub.prepare(Uuid::new_v4(), alex, "hi").unwrap_err(), PartyError::NotFound);
+    }
+}
@@ -0,0 +1,459 @@
+//! What marketplace sellers are owed and have been paid. Every released
+//! escrow writes one credit to `seller_ledger`, net of the platform fee in
+//! force at that moment; admins group unpaid credits into payouts, stored
+//! in `seller_payout_batches`. The older per-escrow `seller_payouts` table
+//! from `004_admin_features_escrow.sql` is left to the escrow module.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notifications::{self, NewNotification, NotificationHub};
+
+/// 10%, in hundredths of a percent
+pub const DEFAULT_FEE_BASIS_POINTS: i32 = 1000;
+
+const BASIS_POINTS: i64 = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct PayoutConfig {
+    /// Platform fee taken from each sale, in hundredths of a percent
+    pub fee_basis_points: i32,
+    /// Completed escrows are released on their own after this long
+    pub auto_release_after: Duration,
+    pub sweep_interval: Duration,
+}
+
+impl Default for PayoutConfig {
+    fn default() -> Self {
+        Self {
+            fee_basis_points: DEFAULT_FEE_BASIS_POINTS,
+            auto_release_after: Duration::from_secs(7 * 24 * 60 * 60),
+            sweep_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl PayoutConfig {
+    /// `MARKETPLACE_FEE_PERCENT` (e.g. `12.5`) and `ESCROW_AUTO_RELEASE_DAYS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            fee_basis_points: std::env::var("MARKETPLACE_FEE_PERCENT").ok()
+                .and_then(|v| fee_basis_points(&v))
+                .unwrap_or(defaults.fee_basis_points),
+            auto_release_after: std::env::var("ESCROW_AUTO_RELEASE_DAYS").ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+                .unwrap_or(defaults.auto_release_after),
+            sweep_interval: defaults.sweep_interval,
+        }
+    }
+}
+
+/// Parses a fee percentage between 0 and 100
+pub fn fee_basis_points(percent: &str) -> Option<i32> {
+    let percent = percent.trim().parse::<f64>().ok()?;
+    (0.0..=100.0).contains(&percent).then(|| (percent * 100.0).round() as i32)
+}
+
+/// Escrow amounts are stored in dollars
+pub fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeeSplit {
+    pub gross_cents: i64,
+    pub fee_cents: i64,
+    pub net_cents: i64,
+}
+
+/// The fee is rounded half up to the cent and the seller gets the rest, so
+/// the two always add up to the sale.
+pub fn split(gross_cents: i64, fee_basis_points: i32) -> FeeSplit {
+    let fee_cents = (gross_cents * fee_basis_points as i64 + BASIS_POINTS / 2) / BASIS_POINTS;
+    FeeSplit { gross_cents, fee_cents, net_cents: gross_cents - fee_cents }
+}
+
+/// An escrow moved from completed to released
+#[derive(Debug, Clone, Copy)]
+pub struct ReleasedEscrow {
+    pub escrow_id: Uuid,
+    pub seller_id: Uuid,
+    pub item_id: Uuid,
+}
+
+/// Writes the seller's credit for a released escrow at the given fee.
+/// `escrow_id` is unique in the ledger, so a second call writes nothing and
+/// returns false.
+pub async fn credit_release(conn: &mut PgConnection, escrow_id: Uuid, fee_basis_points: i32) -> Result<bool, sqlx::Error> {
+    let escrow = sqlx::query_as::<_, (Uuid, Uuid, Option<String>, f64)>(
+        "SELECT e.seller_id, e.item_id, i.name, e.amount FROM escrow_transactions e
+         LEFT JOIN marketplace_items i ON i.id = e.item_id
+         WHERE e.id = $1 AND e.status = 'released'"
+    )
+        .bind(escrow_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let Some((seller_id, item_id, item_name, amount)) = escrow else {
+        return Ok(false);
+    };
+
+    let split = split(to_cents(amount), fee_basis_points);
+    let written = sqlx::query(
+        "INSERT INTO seller_ledger
+            (id, seller_id, escrow_id, item_id, item_name, gross_cents, fee_basis_points, fee_cents, net_cents, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+         ON CONFLICT (escrow_id) DO NOTHING"
+    )
+        .bind(Uuid::new_v4())
+        .bind(seller_id)
+        .bind(escrow_id)
+        .bind(item_id)
+        .bind(item_name)
+        .bind(split.gross_cents)
+        .bind(fee_basis_points)
+        .bind(split.fee_cents)
+        .bind(split.net_cents)
+        .execute(&mut *conn)
+        .await?;
+    Ok(written.rows_affected() > 0)
+}
+
+/// Releases a completed escrow and credits the seller in one transaction.
+/// `None` when it was not completed, e.g. already released.
+pub async fn release_escrow(db: &PgPool, escrow_id: Uuid, fee_basis_points: i32) -> Result<Option<ReleasedEscrow>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let released = sqlx::query_as::<_, (Uuid, Uuid)>(
+        "UPDATE escrow_transactions SET status = 'released', released_at = NOW()
+         WHERE id = $1 AND status = 'completed'
+         RETURNING seller_id, item_id"
+    )
+        .bind(escrow_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some((seller_id, item_id)) = released else {
+        return Ok(None);
+    };
+    credit_release(&mut tx, escrow_id, fee_basis_points).await?;
+    tx.commit().await?;
+    Ok(Some(ReleasedEscrow { escrow_id, seller_id, item_id }))
+}
+
+/// Releases every escrow completed before `now - auto_release_after`
+pub async fn auto_release(db: &PgPool, now: DateTime<Utc>, config: &PayoutConfig) -> Result<Vec<ReleasedEscrow>, sqlx::Error> {
+    let cutoff = now - ChronoDuration::from_std(config.auto_release_after).unwrap_or(ChronoDuration::days(7));
+    let due = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM escrow_transactions WHERE status = 'completed' AND completed_at < $1"
+    )
+        .bind(cutoff)
+        .fetch_all(db)
+        .await?;
+
+    let mut released = Vec::new();
+    for escrow_id in due {
+        // An admin may have released it since the select
+        if let Some(escrow) = release_escrow(db, escrow_id, config.fee_basis_points).await? {
+            released.push(escrow);
+        }
+    }
+    Ok(released)
+}
+
+/// Runs `auto_release` every `sweep_interval` for the life of the process
+pub fn spawn_auto_release(db: PgPool, hub: Arc<NotificationHub>, config: PayoutConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.sweep_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match auto_release(&db, Utc::now(), &config).await {
+                Ok(released) => {
+                    if !released.is_empty() {
+                        info!("Auto-released {} escrows", released.len());
+                    }
+                    for escrow in released {
+                        let notification = NewNotification::escrow_update(escrow.seller_id, escrow.escrow_id, escrow.item_id, "released");
+                        notifications::send_logged(&db, &hub, notification).await;
+                    }
+                }
+                Err(e) => warn!("Escrow auto-release sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemEarnings {
+    pub item_id: Uuid,
+    pub item_name: Option<String>,
+    pub sales: i64,
+    pub gross_cents: i64,
+    pub fee_cents: i64,
+    pub net_cents: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Earnings {
+    /// Credited but not yet in a payout
+    pub balance_cents: i64,
+    /// Sales paid for but still held in escrow, before fees
+    pub pending_escrow_cents: i64,
+    /// Every credit ever written
+    pub lifetime_cents: i64,
+    pub paid_out_cents: i64,
+    pub items: Vec<ItemEarnings>,
+}
+
+pub async fn earnings(db: &PgPool, seller_id: Uuid) -> Result<Earnings, sqlx::Error> {
+    let (balance_cents, lifetime_cents, paid_out_cents) = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT COALESCE(SUM(l.net_cents) FILTER (WHERE l.payout_id IS NULL), 0)::BIGINT,
+                COALESCE(SUM(l.net_cents), 0)::BIGINT,
+                COALESCE(SUM(l.net_cents) FILTER (WHERE p.status = 'paid'), 0)::BIGINT
+         FROM seller_ledger l LEFT JOIN seller_payout_batches p ON p.id = l.payout_id
+         WHERE l.seller_id = $1"
+    )
+        .bind(seller_id)
+        .fetch_one(db)
+        .await?;
+
+    let pending_escrow = sqlx::query_scalar::<_, f64>(
+        "SELECT COALESCE(SUM(amount), 0) FROM escrow_transactions WHERE seller_id = $1 AND status = 'completed'"
+    )
+        .bind(seller_id)
+        .fetch_one(db)
+        .await?;
+
+    let items = sqlx::query_as::<_, (Uuid, Option<String>, i64, i64, i64, i64)>(
+        "SELECT item_id, MAX(item_name), COUNT(*), SUM(gross_cents)::BIGINT, SUM(fee_cents)::BIGINT, SUM(net_cents)::BIGINT
+         FROM seller_ledger WHERE seller_id = $1
+         GROUP BY item_id ORDER BY SUM(net_cents) DESC"
+    )
+        .bind(seller_id)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|(item_id, item_name, sales, gross_cents, fee_cents, net_cents)| ItemEarnings {
+            item_id, item_name, sales, gross_cents, fee_cents, net_cents,
+        })
+        .collect();
+
+    Ok(Earnings {
+        balance_cents,
+        pending_escrow_cents: to_cents(pending_escrow),
+        lifetime_cents,
+        paid_out_cents,
+        items,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Payout {
+    pub id: Uuid,
+    pub seller_id: Uuid,
+    pub amount_cents: i64,
+    /// Ledger credits grouped into it
+    pub entries: i64,
+    /// `pending` until sent or marked paid, then `paid`
+    pub status: String,
+    pub method: String,
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub paid_at: Option<DateTime<Utc>>,
+}
+
+type PayoutRow = (Uuid, Uuid, i64, i64, String, String, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>);
+
+const PAYOUT_COLUMNS: &str = "id, seller_id, amount_cents, entries, status, method, reference, created_at, paid_at";
+
+impl From<PayoutRow> for Payout {
+    fn from((id, seller_id, amount_cents, entries, status, method, reference, created_at, paid_at): PayoutRow) -> Self {
+        Self { id, seller_id, amount_cents, entries, status, method, reference, created_at, paid_at }
+    }
+}
+
+/// Payout history for a seller, newest first
+pub async fn payouts(db: &PgPool, seller_id: Uuid) -> Result<Vec<Payout>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, PayoutRow>(&format!(
+        "SELECT {} FROM seller_payout_batches WHERE seller_id = $1 ORDER BY created_at DESC", PAYOUT_COLUMNS
+    ))
+        .bind(seller_id)
+        .fetch_all(db)
+        .await?;
+    Ok(rows.into_iter().map(Payout::from).collect())
+}
+
+/// Sellers with unpaid credits
+#[derive(Debug, Clone, Serialize)]
+pub struct OwedSeller {
+    pub seller_id: Uuid,
+    pub username: String,
+    pub balance_cents: i64,
+    pub entries: i64,
+}
+
+pub async fn owed_sellers(db: &PgPool) -> Result<Vec<OwedSeller>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid, String, i64, i64)>(
+        "SELECT l.seller_id, u.username, SUM(l.net_cents)::BIGINT, COUNT(*)
+         FROM seller_ledger l JOIN users u ON u.id = l.seller_id
+         WHERE l.payout_id IS NULL
+         GROUP BY l.seller_id, u.username ORDER BY SUM(l.net_cents) DESC"
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(rows.into_iter()
+        .map(|(seller_id, username, balance_cents, entries)| OwedSeller { seller_id, username, balance_cents, entries })
+        .collect())
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferRequest {
+    pub payout_id: Uuid,
+    pub seller_id: Uuid,
+    pub amount_cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferOutcome {
+    /// Money was sent; the payout is paid with this reference
+    Sent { reference: String },
+    /// Left for an admin to pay by hand and mark as paid
+    Manual,
+}
+
+/// Moves a payout's money to the seller. Stripe Connect transfers plug in
+/// here; until then payouts are made by hand.
+pub trait PayoutTransfer: Send + Sync {
+    fn method(&self) -> &'static str;
+    fn transfer<'a>(&'a self, request: &'a TransferRequest) -> BoxFuture<'a, Result<TransferOutcome, String>>;
+}
+
+pub struct ManualTransfer;
+
+impl PayoutTransfer for ManualTransfer {
+    fn method(&self) -> &'static str {
+        "manual"
+    }
+
+    fn transfer<'a>(&'a self, _request: &'a TransferRequest) -> BoxFuture<'a, Result<TransferOutcome, String>> {
+        Box::pin(async { Ok(TransferOutcome::Manual) })
+    }
+}
+
+/// Groups every unpaid credit of the seller into a new payout and hands it
+/// to `transfer`. `None` when nothing is owed. A failed transfer leaves the
+/// payout pending so it can be paid by hand.
+pub async fn create_payout(db: &PgPool, seller_id: Uuid, transfer: &dyn PayoutTransfer) -> Result<Option<Payout>, sqlx::Error> {
+    let payout_id = Uuid::new_v4();
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        "INSERT INTO seller_payout_batches (id, seller_id, amount_cents, entries, status, method, created_at)
+         VALUES ($1, $2, 0, 0, 'pending', $3, NOW())"
+    )
+        .bind(payout_id)
+        .bind(seller_id)
+        .bind(transfer.method())
+        .execute(&mut *tx)
+        .await?;
+
+    let credits = sqlx::query_scalar::<_, i64>(
+        "UPDATE seller_ledger SET payout_id = $1 WHERE seller_id = $2 AND payout_id IS NULL RETURNING net_cents"
+    )
+        .bind(payout_id)
+        .bind(seller_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    if credits.is_empty() {
+        return Ok(None);
+    }
+
+    let row = sqlx::query_as::<_, PayoutRow>(&format!(
+        "UPDATE seller_payout_batches SET amount_cents = $2, entries = $3 WHERE id = $1 RETURNING {}", PAYOUT_COLUMNS
+    ))
+        .bind(payout_id)
+        .bind(credits.iter().sum::<i64>())
+        .bind(credits.len() as i64)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    let mut payout = Payout::from(row);
+    info!("Created payout {} of {} cents for seller {}", payout.id, payout.amount_cents, seller_id);
+
+    let request = TransferRequest { payout_id, seller_id, amount_cents: payout.amount_cents };
+    match transfer.transfer(&request).await {
+        Ok(TransferOutcome::Sent { reference }) => {
+            mark_paid(db, payout_id, Some(&reference)).await?;
+            payout.status = "paid".to_string();
+            payout.reference = Some(reference);
+            payout.paid_at = Some(Utc::now());
+            Ok(Some(payout))
+        }
+        Ok(TransferOutcome::Manual) => Ok(Some(payout)),
+        Err(e) => {
+            warn!("Transfer for payout {} failed, leaving it pending: {}", payout_id, e);
+            Ok(Some(payout))
+        }
+    }
+}
+
+/// Marks a pending payout as paid. False if it doesn't exist or was paid
+/// already.
+pub async fn mark_paid(db: &PgPool, payout_id: Uuid, reference: Option<&str>) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE seller_payout_batches SET status = 'paid', paid_at = NOW(), reference = COALESCE($2, reference)
+         WHERE id = $1 AND status = 'pending'"
+    )
+        .bind(payout_id)
+        .bind(reference)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_is_rounded_half_up_to_the_cent() {
+        assert_eq!(split(1000, 1000), FeeSplit { gross_cents: 1000, fee_cents: 100, net_cents: 900 });
+        // 10% of $4.99 is 49.9 cents
+        assert_eq!(split(499, 1000), FeeSplit { gross_cents: 499, fee_cents: 50, net_cents: 449 });
+        // 10% of $0.05 is exactly half a cent
+        assert_eq!(split(5, 1000).fee_cents, 1);
+        assert_eq!(split(4, 1000).fee_cents, 0);
+        // 12.5% of $3.33 is 41.625 cents
+        assert_eq!(split(333, 1250), FeeSplit { gross_cents: 333, fee_cents: 42, net_cents: 291 });
+    }
+
+    #[test]
+    fn test_fee_and_net_always_add_up() {
+        for gross in [0, 1, 99, 1999, 123_457] {
+            for bps in [0, 1, 250, 1000, 3333, 10_000] {
+                let split = split(gross, bps);
+                assert_eq!(split.fee_cents + split.net_cents, gross);
+                assert!(split.net_cents >= 0);
+            }
+        }
+        assert_eq!(split(2500, 0).net_cents, 2500);
+        assert_eq!(split(2500, 10_000).net_cents, 0);
+    }
+
+    #[test]
+    fn test_dollar_amounts_and_percentages_convert_exactly() {
+        // 19.99 * 100 is 1998.9999999999998 in floating point
+        assert_eq!(to_cents(19.99), 1999);
+        assert_eq!(to_cents(0.1 + 0.2), 30);
+        assert_eq!(fee_basis_points("12.5"), Some(1250));
+        assert_eq!(fee_basis_points(" 10 "), Some(1000));
+        assert_eq!(fee_basis_points("101"), None);
+        assert_eq!(fee_basis_points("-1"), None);
+        assert_eq!(fee_basis_points("ten"), None);
+    }
+}
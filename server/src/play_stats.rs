@@ -0,0 +1,297 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 25;
+pub const MAX_PAGE_SIZE: i64 = 100;
+/// Weeks covered by the activity histogram in a breakdown.
+pub const HISTOGRAM_WEEKS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Playtime,
+    Sessions,
+}
+
+impl Metric {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "playtime" => Some(Metric::Playtime),
+            "sessions" => Some(Metric::Sessions),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Metric::Playtime => "duration_minutes",
+            Metric::Sessions => "session_count",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Week,
+    Month,
+    All,
+}
+
+impl Period {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "week" => Some(Period::Week),
+            "month" => Some(Period::Month),
+            "all" => Some(Period::All),
+            _ => None,
+        }
+    }
+
+    /// Start of the rolling window, or `None` for all time.
+    pub fn since(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Period::Week => Some(now - ChronoDuration::days(7)),
+            Period::Month => Some(now - ChronoDuration::days(30)),
+            Period::All => None,
+        }
+    }
+}
+
+/// 1-based page and a page size clamped to `MAX_PAGE_SIZE`, as (limit, offset).
+pub fn page_bounds(page: Option<i64>, limit: Option<i64>) -> (i64, i64) {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = page.unwrap_or(1).max(1);
+    (limit, (page - 1) * limit)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: i64,
+    pub user_id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Leaderboard {
+    pub metric: Metric,
+    pub period: Period,
+    pub page: i64,
+    pub limit: i64,
+    pub total: i64,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+/// Public ranking; users who opted out or deleted their account are left out.
+/// Ties share a rank.
+pub async fn leaderboard(db: &PgPool, metric: Metric, period: Period, page: Option<i64>, limit: Option<i64>, now: DateTime<Utc>) -> Result<Leaderboard, sqlx::Error> {
+    let (limit, offset) = page_bounds(page, limit);
+    let totals = format!(
+        "SELECT u.id, u.username, u.display_name, SUM(p.{})::BIGINT AS value
+         FROM play_sessions p JOIN users u ON u.id = p.user_id
+         WHERE u.deleted_at IS NULL AND NOT u.hide_from_leaderboards
+           AND ($1::TIMESTAMPTZ IS NULL OR p.started_at >= $1)
+         GROUP BY u.id, u.username, u.display_name
+         HAVING SUM(p.{}) > 0",
+        metric.column(), metric.column()
+    );
+    let since = period.since(now);
+
+    let rows = sqlx::query_as::<_, (i64, Uuid, String, Option<String>, i64)>(&format!(
+        "SELECT RANK() OVER (ORDER BY value DESC), id, username, display_name, value
+         FROM ({}) totals ORDER BY value DESC, username ASC LIMIT $2 OFFSET $3",
+        totals
+    ))
+        .bind(since)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(db)
+        .await?;
+    let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM ({}) totals", totals))
+        .bind(since)
+        .fetch_one(db)
+        .await?;
+
+    Ok(Leaderboard {
+        metric,
+        period,
+        page: offset / limit + 1,
+        limit,
+        total,
+        entries: rows.into_iter()
+            .map(|(rank, user_id, username, display_name, value)| LeaderboardEntry { rank, user_id, username, display_name, value })
+            .collect(),
+    })
+}
+
+/// Records one play session and recomputes the user's favorite server from
+/// the sessions on record.
+pub async fn record(conn: &mut PgConnection, user_id: Uuid, server_id: Option<Uuid>, server_name: Option<&str>, duration_minutes: i32, now: DateTime<Utc>) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO play_sessions (id, user_id, server_id, server_name, started_at, duration_minutes, session_count)
+         VALUES ($1, $2, $3, COALESCE($4, (SELECT name FROM game_servers WHERE id = $3)), $5, $6, 1)"
+    )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(server_id)
+        .bind(server_name)
+        .bind(now - ChronoDuration::minutes(duration_minutes as i64))
+        .bind(duration_minutes)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query_scalar::<_, Option<String>>(
+        "UPDATE game_stats SET favorite_server = (
+            SELECT server_name FROM play_sessions
+            WHERE user_id = $1 AND server_name IS NOT NULL
+            GROUP BY server_name
+            ORDER BY SUM(duration_minutes) DESC, MAX(started_at) DESC
+            LIMIT 1
+         ) WHERE user_id = $1 RETURNING favorite_server"
+    )
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map(Option::flatten)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerTotal {
+    pub server_id: Option<Uuid>,
+    pub server_name: Option<String>,
+    pub playtime_minutes: i64,
+    pub sessions: i64,
+    pub last_played: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WeekBucket {
+    /// Monday 00:00 UTC
+    pub week_start: DateTime<Utc>,
+    pub playtime_minutes: i64,
+    pub sessions: i64,
+}
+
+fn week_start(ts: DateTime<Utc>) -> DateTime<Utc> {
+    let monday = ts.date_naive() - ChronoDuration::days(ts.weekday().num_days_from_monday() as i64);
+    monday.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Per-week totals for the `weeks` weeks up to and including the current
+/// one, oldest first. Weeks without play are present with zeros.
+pub fn weekly_histogram(sessions: &[(DateTime<Utc>, i64, i64)], now: DateTime<Utc>, weeks: usize) -> Vec<WeekBucket> {
+    let current = week_start(now);
+    let mut buckets: Vec<WeekBucket> = (0..weeks)
+        .rev()
+        .map(|ago| WeekBucket {
+            week_start: current - ChronoDuration::weeks(ago as i64),
+            playtime_minutes: 0,
+            sessions: 0,
+        })
+        .collect();
+
+    for (started_at, minutes, count) in sessions {
+        let week = week_start(*started_at);
+        if let Some(bucket) = buckets.iter_mut().find(|b| b.week_start == week) {
+            bucket.playtime_minutes += minutes;
+            bucket.sessions += count;
+        }
+    }
+    buckets
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Breakdown {
+    pub servers: Vec<ServerTotal>,
+    pub weekly: Vec<WeekBucket>,
+    pub hidden_from_leaderboards: bool,
+}
+
+pub async fn breakdown(db: &PgPool, user_id: Uuid, now: DateTime<Utc>) -> Result<Breakdown, sqlx::Error> {
+    let servers = sqlx::query_as::<_, (Option<Uuid>, Option<String>, i64, i64, DateTime<Utc>)>(
+        "SELECT server_id, server_name, SUM(duration_minutes)::BIGINT, SUM(session_count)::BIGINT, MAX(started_at)
+         FROM play_sessions WHERE user_id = $1
+         GROUP BY server_id, server_name
+         ORDER BY SUM(duration_minutes) DESC"
+    )
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+
+    let since = week_start(now) - ChronoDuration::weeks(HISTOGRAM_WEEKS as i64 - 1);
+    let recent = sqlx::query_as::<_, (DateTime<Utc>, i64, i64)>(
+        "SELECT started_at, duration_minutes::BIGINT, session_count::BIGINT
+         FROM play_sessions WHERE user_id = $1 AND started_at >= $2"
+    )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(db)
+        .await?;
+
+    let hidden_from_leaderboards = sqlx::query_scalar::<_, bool>("SELECT hide_from_leaderboards FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?
+        .unwrap_or(false);
+
+    Ok(Breakdown {
+        servers: servers.into_iter()
+            .map(|(server_id, server_name, playtime_minutes, sessions, last_played)| ServerTotal {
+                server_id,
+                server_name,
+                playtime_minutes,
+                sessions,
+                last_played,
+            })
+            .collect(),
+        weekly: weekly_histogram(&recent, now, HISTOGRAM_WEEKS),
+        hidden_from_leaderboards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2024-05-{:02}T{:02}:30:00Z", day, hour)).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_query_params() {
+        assert_eq!(Metric::parse("playtime"), Some(Metric::Playtime));
+        assert_eq!(Metric::parse("kills"), None);
+        assert_eq!(Period::parse("month"), Some(Period::Month));
+        assert_eq!(Period::Week.since(at(15, 0)), Some(at(8, 0)));
+        assert_eq!(Period::All.since(at(15, 0)), None);
+
+        assert_eq!(page_bounds(None, None), (DEFAULT_PAGE_SIZE, 0));
+        assert_eq!(page_bounds(Some(3), Some(10)), (10, 20));
+        assert_eq!(page_bounds(Some(0), Some(1000)), (MAX_PAGE_SIZE, 0));
+    }
+
+    #[test]
+    fn test_weekly_histogram_buckets_by_monday() {
+        // 2024-05-15 is a Wednesday; its week starts Monday the 13th
+        let now = at(15, 12);
+        let sessions = [
+            (at(13, 0), 30, 1),
+            (at(15, 9), 45, 1),
+            (at(12, 23), 60, 1),
+            (at(1, 10), 500, 7),
+        ];
+        let weekly = weekly_histogram(&sessions, now, 2);
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[1].week_start, at(13, 0) - ChronoDuration::minutes(30));
+        assert_eq!((weekly[1].playtime_minutes, weekly[1].sessions), (75, 2));
+        assert_eq!((weekly[0].playtime_minutes, weekly[0].sessions), (60, 1));
+
+        let quiet = weekly_histogram(&[], now, 4);
+        assert_eq!(quiet.len(), 4);
+        assert!(quiet.iter().all(|b| b.playtime_minutes == 0 && b.sessions == 0));
+    }
+}
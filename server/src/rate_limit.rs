@@ -0,0 +1,505 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use sqlx::PgPool;
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+use crate::ApiResponse;
+
+/// Auth request bodies are tiny; anything bigger is not worth buffering.
+const MAX_AUTH_BODY_BYTES: usize = 16 * 1024;
+/// Sweep idle keys once the limiter tracks this many.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub per_ip: usize,
+    pub per_username: usize,
+    pub window: Duration,
+    pub lockout_threshold: i64,
+    /// Failed logins from one address, across accounts, before it is locked out.
+    pub source_lockout_threshold: i64,
+    pub lockout_window: Duration,
+    /// Reverse proxies whose `X-Forwarded-For` is believed. Empty means the
+    /// header is ignored and the peer address is the client.
+    pub trusted_proxies: Vec<TrustedProxy>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: 30,
+            per_username: 10,
+            window: Duration::from_secs(60),
+            lockout_threshold: 10,
+            source_lockout_threshold: 30,
+            lockout_window: Duration::from_secs(15 * 60),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            per_ip: env_or("AUTH_RATE_LIMIT_PER_IP", defaults.per_ip),
+            per_username: env_or("AUTH_RATE_LIMIT_PER_USERNAME", defaults.per_username),
+            window: Duration::from_secs(env_or("AUTH_RATE_LIMIT_WINDOW_SECS", defaults.window.as_secs())),
+            lockout_threshold: env_or("AUTH_LOCKOUT_THRESHOLD", defaults.lockout_threshold),
+            source_lockout_threshold: env_or("AUTH_SOURCE_LOCKOUT_THRESHOLD", defaults.source_lockout_threshold),
+            lockout_window: Duration::from_secs(env_or("AUTH_LOCKOUT_WINDOW_SECS", defaults.lockout_window.as_secs())),
+            trusted_proxies: std::env::var("AUTH_TRUSTED_PROXIES")
+                .map(|v| parse_trusted_proxies(&v))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Comma-separated addresses or CIDR ranges; bad entries are logged and skipped.
+fn parse_trusted_proxies(value: &str) -> Vec<TrustedProxy> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.parse() {
+            Ok(proxy) => Some(proxy),
+            Err(e) => {
+                warn!("Ignoring trusted proxy {:?}: {}", entry, e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Allows `limit` hits per key in any `window`-long span.
+pub struct SlidingWindowLimiter {
+    hits: DashMap<String, VecDeque<Instant>>,
+    limit: usize,
+    window: Duration,
+}
+
+impl SlidingWindowLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self { hits: DashMap::new(), limit, window }
+    }
+
+    /// Records a hit for `key`, or returns how long until one would be allowed.
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        if self.hits.len() > SWEEP_THRESHOLD {
+            self.sweep(now);
+        }
+
+        let mut hits = self.hits.entry(key.to_string()).or_default();
+        while hits.front().is_some_and(|t| now.duration_since(*t) >= self.window) {
+            hits.pop_front();
+        }
+
+        if hits.len() >= self.limit {
+            let oldest = hits.front().copied().unwrap_or(now);
+            return Err(self.window.saturating_sub(now.duration_since(oldest)));
+        }
+
+        hits.push_back(now);
+        Ok(())
+    }
+
+    fn sweep(&self, now: Instant) {
+        self.hits.retain(|_, hits| hits.back().is_some_and(|t| now.duration_since(*t) < self.window));
+    }
+}
+
+/// Per-IP and per-username limits for the auth endpoints.
+pub struct AuthRateLimiter {
+    pub config: RateLimitConfig,
+    clock: Box<dyn Clock>,
+    by_ip: SlidingWindowLimiter,
+    by_username: SlidingWindowLimiter,
+}
+
+impl AuthRateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self::with_clock(config, Box::new(SystemClock))
+    }
+
+    pub fn with_clock(config: RateLimitConfig, clock: Box<dyn Clock>) -> Self {
+        Self {
+            by_ip: SlidingWindowLimiter::new(config.per_ip, config.window),
+            by_username: SlidingWindowLimiter::new(config.per_username, config.window),
+            config,
+            clock,
+        }
+    }
+
+    pub fn check(&self, ip: &str, username: Option<&str>) -> Result<(), Duration> {
+        let now = self.clock.now();
+        self.by_ip.check(ip, now)?;
+        if let Some(username) = username {
+            self.by_username.check(&username.to_lowercase(), now)?;
+        }
+        Ok(())
+    }
+}
+
+/// A proxy address, or a range of them, e.g. `10.0.0.0/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl TrustedProxy {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for TrustedProxy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = s.split_once('/').map_or((s, None), |(a, p)| (a, Some(p)));
+        let network: IpAddr = address.parse().map_err(|_| format!("{:?} is not an IP address", address))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max)
+                .ok_or_else(|| format!("{:?} is not a prefix length up to {}", prefix, max))?,
+            None => max,
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+/// Client address as seen by the rate limiter, for handlers that log attempts.
+#[derive(Debug, Clone)]
+pub struct ClientIp(pub String);
+
+/// The peer address, unless the peer is a trusted proxy: then the nearest
+/// `X-Forwarded-For` hop that isn't one. Hops are read right to left, as
+/// each proxy appends the address it got the request from; anything left of
+/// the first untrusted hop was written by the client and is ignored.
+fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted_proxies: &[TrustedProxy]) -> String {
+    let Some(peer) = peer else {
+        return "unknown".to_string();
+    };
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(ip));
+    if !is_trusted(peer) {
+        return peer.to_string();
+    }
+
+    let forwarded: Vec<&str> = headers.get_all("x-forwarded-for").iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+    let mut client = peer;
+    for hop in forwarded.into_iter().rev() {
+        // A malformed hop can't be attributed; stop at the proxy that sent it
+        let Ok(ip) = hop.parse::<IpAddr>() else { break };
+        client = ip;
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    client.to_string()
+}
+
+pub fn too_many_requests(retry_after: Duration, message: &str) -> Response {
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.max(1).to_string())],
+        ApiResponse::<serde_json::Value>::error(message),
+    ).into_response()
+}
+
+/// Middleware for signup/login/admin_login: limits by client IP and by the
+/// `username` field of the JSON body.
+pub async fn limit_auth_requests(
+    State(limiter): State<Arc<AuthRateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+    let ip = client_ip(request.headers(), peer, &limiter.config.trusted_proxies);
+
+    let (mut parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_AUTH_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, ApiResponse::<serde_json::Value>::error("Request too large")).into_response(),
+    };
+    let username = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|v| v.get("username").and_then(|u| u.as_str()).map(str::to_string));
+
+    if let Err(retry_after) = limiter.check(&ip, username.as_deref()) {
+        return too_many_requests(retry_after, "Too many requests, try again later");
+    }
+
+    parts.extensions.insert(ClientIp(ip));
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+/// Returns how long the account stays locked after too many failed passwords.
+/// Kept apart from credential checks so the password reset flow can skip it.
+/// Like `check_source_lockout`, a database error is returned, not read as
+/// "not locked".
+pub async fn check_account_lockout(db: &PgPool, config: &RateLimitConfig, username: &str) -> Result<Option<Duration>, sqlx::Error> {
+    let Ok(window) = chrono::Duration::from_std(config.lockout_window) else {
+        return Ok(None);
+    };
+    let since = chrono::Utc::now() - window;
+
+    let (failures, oldest) = sqlx::query_as::<_, (i64, Option<chrono::DateTime<chrono::Utc>>)>(
+        "SELECT COUNT(*), MIN(attempted_at) FROM login_attempts WHERE LOWER(username) = LOWER($1) AND attempted_at > $2"
+    )
+        .bind(username)
+        .bind(since)
+        .fetch_one(db)
+        .await?;
+
+    Ok(remaining_lockout(failures, oldest, config.lockout_threshold, window, chrono::Utc::now()))
+}
+
+/// Like `check_account_lockout`, for failures from one client address. These
+/// survive restarts, unlike the per-IP request limit. `ip` must be the
+/// address from `ClientIp`, which only takes `X-Forwarded-For` from trusted
+/// proxies, so a client can't escape its lockout by naming another address.
+///
+/// A database error is returned rather than treated as "not locked", so the
+/// caller can refuse the attempt instead of letting guesses through unseen.
+pub async fn check_source_lockout(db: &PgPool, config: &RateLimitConfig, ip: &str) -> Result<Option<Duration>, sqlx::Error> {
+    let Ok(window) = chrono::Duration::from_std(config.lockout_window) else {
+        return Ok(None);
+    };
+    let since = chrono::Utc::now() - window;
+
+    let (failures, oldest) = sqlx::query_as::<_, (i64, Option<chrono::DateTime<chrono::Utc>>)>(
+        "SELECT COUNT(*), MIN(attempted_at) FROM login_attempts WHERE ip_address = $1 AND attempted_at > $2"
+    )
+        .bind(ip)
+        .bind(since)
+        .fetch_one(db)
+        .await?;
+
+    Ok(remaining_lockout(failures, oldest, config.source_lockout_threshold, window, chrono::Utc::now()))
+}
+
+/// How long a key with `failures` in the window, the first at `oldest`, stays locked.
+fn remaining_lockout(
+    failures: i64,
+    oldest: Option<chrono::DateTime<chrono::Utc>>,
+    threshold: i64,
+    window: chrono::Duration,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<Duration> {
+    if failures < threshold {
+        return None;
+    }
+    (oldest? + window - now).to_std().ok()
+}
+
+pub async fn record_failed_login(db: &PgPool, config: &RateLimitConfig, username: &str, ip: &str) {
+    let _ = sqlx::query("INSERT INTO login_attempts (id, username, ip_address, attempted_at) VALUES ($1, $2, $3, NOW())")
+        .bind(uuid::Uuid::new_v4())
+        .bind(username)
+        .bind(ip)
+        .execute(db)
+        .await;
+
+    if let Ok(window) = chrono::Duration::from_std(config.lockout_window) {
+        let _ = sqlx::query("DELETE FROM login_attempts WHERE attempted_at < $1")
+            .bind(chrono::Utc::now() - window)
+            .execute(db)
+            .await;
+    }
+}
+
+pub async fn clear_failed_logins(db: &PgPool, username: &str) {
+    let _ = sqlx::query("DELETE FROM login_attempts WHERE LOWER(username) = LOWER($1)")
+        .bind(username)
+        .execute(db)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct FakeClock {
+        now: Arc<Mutex<Instant>>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    fn limiter(config: RateLimitConfig) -> (AuthRateLimiter, Arc<Mutex<Instant>>) {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let limiter = AuthRateLimiter::with_clock(config, Box::new(FakeClock { now: now.clone() }));
+        (limiter, now)
+    }
+
+    fn advance(now: &Mutex<Instant>, secs: u64) {
+        *now.lock().unwrap() += Duration::from_secs(secs);
+    }
+
+    #[test]
+    fn test_username_limit_blocks_until_window_slides() {
+        let (limiter, now) = limiter(RateLimitConfig { per_username: 3, ..Default::default() });
+
+        for _ in 0..3 {
+            assert!(limiter.check("1.1.1.1", Some("alice")).is_ok());
+        }
+        assert_eq!(limiter.check("2.2.2.2", Some("Alice")), Err(Duration::from_secs(60)));
+
+        advance(&now, 45);
+        assert_eq!(limiter.check("3.3.3.3", Some("alice")), Err(Duration::from_secs(15)));
+
+        advance(&now, 15);
+        assert!(limiter.check("3.3.3.3", Some("alice")).is_ok());
+        assert!(limiter.check("3.3.3.3", Some("bob")).is_ok());
+    }
+
+    #[test]
+    fn test_ip_limit_applies_across_usernames() {
+        let (limiter, now) = limiter(RateLimitConfig { per_ip: 2, ..Default::default() });
+
+        assert!(limiter.check("1.1.1.1", Some("a")).is_ok());
+        advance(&now, 30);
+        assert!(limiter.check("1.1.1.1", Some("b")).is_ok());
+        assert_eq!(limiter.check("1.1.1.1", Some("c")), Err(Duration::from_secs(30)));
+        assert!(limiter.check("9.9.9.9", Some("c")).is_ok());
+
+        advance(&now, 30);
+        assert!(limiter.check("1.1.1.1", None).is_ok());
+        assert!(limiter.check("1.1.1.1", None).is_err());
+    }
+
+    #[test]
+    fn test_lockout_lasts_until_the_oldest_failure_leaves_the_window() {
+        let now = chrono::Utc::now();
+        let window = chrono::Duration::minutes(15);
+        let oldest = Some(now - chrono::Duration::minutes(5));
+
+        assert_eq!(remaining_lockout(9, oldest, 10, window, now), None);
+        assert_eq!(remaining_lockout(10, oldest, 10, window, now), Some(Duration::from_secs(10 * 60)));
+        assert_eq!(remaining_lockout(10, Some(now - window), 10, window, now), Some(Duration::ZERO));
+        assert_eq!(remaining_lockout(10, Some(now - window - chrono::Duration::seconds(1)), 10, window, now), None);
+    }
+
+    #[test]
+    fn test_idle_keys_are_swept() {
+        let limiter = SlidingWindowLimiter::new(1, Duration::from_secs(60));
+        let start = Instant::now();
+        for i in 0..=SWEEP_THRESHOLD {
+            limiter.check(&i.to_string(), start).unwrap();
+        }
+
+        limiter.check("late", start + Duration::from_secs(61)).unwrap();
+        assert_eq!(limiter.hits.len(), 1);
+    }
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_forwarded_for_is_only_believed_from_trusted_proxies() {
+        let proxies = parse_trusted_proxies("10.0.0.0/8, 192.168.1.5, not-an-ip, ::1");
+        assert_eq!(proxies.len(), 3);
+        let peer = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+
+        // No proxies configured: the header is ignored
+        assert_eq!(client_ip(&forwarded("6.6.6.6"), peer("203.0.113.9"), &[]), "203.0.113.9");
+        // An untrusted peer can't claim another address
+        assert_eq!(client_ip(&forwarded("6.6.6.6"), peer("203.0.113.9"), &proxies), "203.0.113.9");
+        // Through a trusted proxy, the hop it appended is the client
+        assert_eq!(client_ip(&forwarded("198.51.100.7"), peer("10.1.2.3"), &proxies), "198.51.100.7");
+        // Spoofed hops left of the real client are skipped, trusted chains are walked
+        assert_eq!(client_ip(&forwarded("6.6.6.6, 198.51.100.7, 10.9.9.9"), peer("192.168.1.5"), &proxies), "198.51.100.7");
+        assert_eq!(client_ip(&forwarded("garbage, 10.9.9.9"), peer("::1"), &proxies), "10.9.9.9");
+        assert_eq!(client_ip(&HeaderMap::new(), peer("10.1.2.3"), &proxies), "10.1.2.3");
+        assert_eq!(client_ip(&forwarded("6.6.6.6"), None, &proxies), "unknown");
+    }
+
+    #[test]
+    fn test_trusted_proxy_ranges() {
+        let range: TrustedProxy = "172.16.0.0/12".parse().unwrap();
+        assert!(range.contains("172.31.255.255".parse().unwrap()));
+        assert!(!range.contains("172.32.0.0".parse().unwrap()));
+        assert!(range.contains("::ffff:172.16.0.1".parse().unwrap()));
+        let everything: TrustedProxy = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains("8.8.8.8".parse().unwrap()));
+        let v6: TrustedProxy = "fd00::/8".parse().unwrap();
+        assert!(v6.contains("fd12::1".parse().unwrap()) && !v6.contains("fe80::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<TrustedProxy>().is_err());
+        assert!("proxy.local".parse::<TrustedProxy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_source_lockout_reports_database_errors() {
+        let db = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+            .unwrap();
+        let result = check_source_lockout(&db, &RateLimitConfig::default(), "198.51.100.7").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_account_lockout_reports_database_errors() {
+        let db = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+            .unwrap();
+        let result = check_account_lockout(&db, &RateLimitConfig::default(), "alice").await;
+        assert!(result.is_err());
+    }
+}
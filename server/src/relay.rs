@@ -1,10 +1,245 @@
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::sync::RwLock;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, RwLock};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+/// Connected peers allowed in a session hosted by a free-tier user
+pub const FREE_SESSION_PEERS: u32 = 8;
+/// Connected peers allowed in a session hosted by a premium user
+pub const PREMIUM_SESSION_PEERS: u32 = 32;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RelayLimits {
+    /// Live relay connections across all sessions
+    pub max_connections: usize,
+    /// The last `premium_reserve` connections are kept for premium users
+    pub premium_reserve: usize,
+}
+
+impl Default for RelayLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: 2000,
+            premium_reserve: 200,
+        }
+    }
+}
+
+impl RelayLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_connections: env_or("RELAY_MAX_CONNECTIONS", defaults.max_connections),
+            premium_reserve: env_or("RELAY_PREMIUM_RESERVE", defaults.premium_reserve),
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Messages on the relay websocket. This is the launcher's relay protocol;
+/// `Join` also carries the user's session token, and the identity in it
+/// wins over any `user_id`/`username` the client claims.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RelayMessage {
+    Join {
+        session_id: String,
+        #[serde(default)]
+        user_id: Option<Uuid>,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        token: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        /// Hex X25519 public key of a peer that encrypts payloads end to end
+        #[serde(default)]
+        public_key: Option<String>,
+    },
+    Leave {
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        user_id: Option<Uuid>,
+    },
+    Data {
+        #[serde(default)]
+        from: Uuid,
+        to: Option<Uuid>,
+        payload: Vec<u8>,
+        /// Session key an end-to-end encrypted payload is sealed under
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_id: Option<u32>,
+    },
+    PeerList {
+        peers: Vec<RelayPeer>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        host: Option<Uuid>,
+    },
+    PeerJoined {
+        peer: RelayPeer,
+    },
+    PeerLeft {
+        user_id: Uuid,
+    },
+    Ping,
+    Pong,
+    Error {
+        message: String,
+    },
+    Ack {
+        message_id: String,
+    },
+    HostMigration {
+        new_host: Uuid,
+    },
+    /// Hand hosting to another peer in the session. Host only; everyone
+    /// is told with a `HostMigration`.
+    TransferHost {
+        new_host: Uuid,
+    },
+    SessionClosed {
+        reason: String,
+    },
+    /// A session key the host sealed to one peer. Forwarded as is; the
+    /// relay can't open it.
+    SessionKey {
+        #[serde(default)]
+        from: Uuid,
+        to: Uuid,
+        key_id: u32,
+        ephemeral_key: String,
+        sealed_key: Vec<u8>,
+    },
+    /// Receive party chat on this socket. The token may be left out once
+    /// the socket has joined a session.
+    PartySubscribe {
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// Chat within a party. `from` and `ts` are set by the server.
+    PartyChat {
+        party_id: Uuid,
+        #[serde(default)]
+        from: Uuid,
+        text: String,
+        #[serde(default = "Utc::now")]
+        ts: DateTime<Utc>,
+    },
+    /// Receive new notifications on this socket, with the same token rules
+    /// as `PartySubscribe`.
+    NotificationsSubscribe {
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// A notification stored for the subscribed user
+    Notification {
+        id: Uuid,
+        kind: String,
+        payload: serde_json::Value,
+        created_at: DateTime<Utc>,
+    },
+    /// A friend's presence changed; sent on the notification feed
+    Presence {
+        user_id: Uuid,
+        status: String,
+        activity: Option<String>,
+        server_id: Option<String>,
+        updated_at: DateTime<Utc>,
+    },
+}
+
+impl RelayMessage {
+    pub fn to_text(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// A peer as seen by the other members of its relay session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayPeer {
+    pub user_id: Uuid,
+    pub username: String,
+    pub is_host: bool,
+    pub joined_at: DateTime<Utc>,
+    pub latency_ms: Option<u32>,
+    /// Whether the peer encrypts `Data` payloads end to end
+    #[serde(default)]
+    pub encryption: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// A frame queued for a connected peer's socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outbound {
+    Text(String),
+    Binary(Vec<u8>),
+    Close,
+}
+
+impl Outbound {
+    fn message(message: &RelayMessage) -> Self {
+        Self::Text(message.to_text())
+    }
+}
+
+#[derive(Debug)]
+struct PeerLink {
+    connection_id: u64,
+    session_id: String,
+    username: String,
+    premium: bool,
+    public_key: Option<String>,
+    joined_at: DateTime<Utc>,
+    sender: mpsc::UnboundedSender<Outbound>,
+}
+
+/// An authenticated request to join a relay session
+#[derive(Debug, Clone)]
+pub struct RelayJoin {
+    pub session_id: String,
+    pub user_id: Uuid,
+    pub username: String,
+    pub premium: bool,
+    pub password: Option<String>,
+    pub public_key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinedSession {
+    pub connection_id: u64,
+    pub session_id: String,
+    pub host_id: Uuid,
+    pub is_host: bool,
+    /// Peers that were already connected
+    pub peers: Vec<RelayPeer>,
+}
+
+/// Launcher invite codes look like `ABCD-EFGH-JKLM`
+pub fn is_invite_code(id: &str) -> bool {
+    id.len() == 14 && id.chars().enumerate().all(|(i, c)| {
+        if i % 5 == 4 { c == '-' } else { c.is_ascii_alphanumeric() }
+    })
+}
+
+/// Invite codes are matched case-insensitively; other ids are used as given
+pub fn normalize_session_id(id: &str) -> String {
+    let id = id.trim();
+    if is_invite_code(id) {
+        id.to_ascii_uppercase()
+    } else {
+        id.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerInfo {
     pub user_id: Uuid,
@@ -102,6 +337,11 @@ pub struct RelayHub {
     stun_servers: Vec<String>,
     turn_servers: Vec<TurnServer>,
     stats: RelayStats,
+    /// Live websocket connections, one per user. Locked before `sessions`
+    /// whenever both are needed.
+    links: Mutex<HashMap<Uuid, PeerLink>>,
+    next_connection: AtomicU64,
+    limits: RelayLimits,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,9 +380,17 @@ impl RelayHub {
                 failed_connections: AtomicU64::new(0),
                 bytes_relayed: AtomicU64::new(0),
             },
+            links: Mutex::new(HashMap::new()),
+            next_connection: AtomicU64::new(1),
+            limits: RelayLimits::default(),
         }
     }
 
+    pub fn with_limits(mut self, limits: RelayLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn register_peer(&self, info: PeerInfo) -> Result<(), RelayError> {
         let user_id = info.user_id;
         self.peers.insert(user_id, info);
@@ -310,6 +558,242 @@ impl RelayHub {
         }
     }
 
+    /// Register a launcher-hosted session under its invite code so guests
+    /// can fall back to this relay. Re-registering by the same host updates
+    /// the cap and password.
+    pub fn register_session(&self, session_id: &str, host_id: Uuid, max_peers: u32, password: Option<String>) -> Result<RelaySession, RelayError> {
+        let session_id = normalize_session_id(session_id);
+        let password_hash = password.map(|p| hex::encode(sha256(&p)));
+
+        let mut session = self.sessions.entry(session_id.clone()).or_insert_with(|| {
+            self.stats.total_sessions.fetch_add(1, Ordering::Relaxed);
+            RelaySession {
+                id: session_id,
+                host_id,
+                created_at: Utc::now(),
+                peers: Vec::new(),
+                max_peers,
+                password_hash: password_hash.clone(),
+                region: "auto".to_string(),
+                relay_mode: RelayMode::Relay,
+            }
+        });
+        if session.host_id != host_id {
+            return Err(RelayError::Unauthorized);
+        }
+        session.max_peers = max_peers;
+        session.password_hash = password_hash;
+        Ok(session.clone())
+    }
+
+    /// Connect a peer to a relay session, creating the session with the peer
+    /// as host if nobody registered it. Free-tier joins are refused once the
+    /// hub reaches its premium reserve; a premium join into a full hub evicts
+    /// the newest free-tier peer.
+    pub fn join_live(&self, join: RelayJoin, sender: mpsc::UnboundedSender<Outbound>) -> Result<JoinedSession, RelayError> {
+        let session_id = normalize_session_id(&join.session_id);
+        if session_id.is_empty() {
+            return Err(RelayError::SessionNotFound);
+        }
+
+        let mut links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+
+        // A reconnecting user replaces their previous connection
+        if links.contains_key(&join.user_id) {
+            self.drop_link(&mut links, join.user_id, Some("Connected from another client"));
+        }
+
+        if let Some(session) = self.sessions.get(&session_id) {
+            if session.peers.len() >= session.max_peers as usize {
+                return Err(RelayError::SessionFull);
+            }
+            if let Some(ref hash) = session.password_hash {
+                let provided = join.password.as_deref().map(|p| hex::encode(sha256(p)));
+                if provided.as_ref() != Some(hash) {
+                    return Err(RelayError::InvalidPassword);
+                }
+            }
+        }
+
+        let free_limit = self.limits.max_connections.saturating_sub(self.limits.premium_reserve);
+        if !join.premium && links.len() >= free_limit {
+            return Err(RelayError::RelayFull);
+        }
+        if join.premium && links.len() >= self.limits.max_connections {
+            let newest_free = links.iter()
+                .filter(|(_, link)| !link.premium)
+                .max_by_key(|(_, link)| (link.joined_at, link.connection_id))
+                .map(|(id, _)| *id)
+                .ok_or(RelayError::RelayFull)?;
+            self.drop_link(&mut links, newest_free, Some("Relay capacity is reserved for priority traffic"));
+        }
+
+        let now = Utc::now();
+        let mut session = self.sessions.entry(session_id.clone()).or_insert_with(|| {
+            self.stats.total_sessions.fetch_add(1, Ordering::Relaxed);
+            RelaySession {
+                id: session_id.clone(),
+                host_id: join.user_id,
+                created_at: now,
+                peers: Vec::new(),
+                max_peers: if join.premium { PREMIUM_SESSION_PEERS } else { FREE_SESSION_PEERS },
+                password_hash: join.password.as_deref().map(|p| hex::encode(sha256(p))),
+                region: "auto".to_string(),
+                relay_mode: RelayMode::Relay,
+            }
+        });
+
+        let host_id = session.host_id;
+        let is_host = host_id == join.user_id;
+        let peers: Vec<RelayPeer> = session.peers.iter()
+            .filter_map(|id| links.get(id).map(|link| peer_view(*id, link, session.host_id)))
+            .collect();
+
+        let joined = RelayMessage::PeerJoined {
+            peer: RelayPeer {
+                user_id: join.user_id,
+                username: join.username.clone(),
+                is_host,
+                joined_at: now,
+                latency_ms: None,
+                encryption: join.public_key.is_some(),
+                public_key: join.public_key.clone(),
+            },
+        };
+        let joined = Outbound::message(&joined);
+        for link in session.peers.iter().filter_map(|id| links.get(id)) {
+            let _ = link.sender.send(joined.clone());
+        }
+        session.peers.push(join.user_id);
+        drop(session);
+
+        let connection_id = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        links.insert(join.user_id, PeerLink {
+            connection_id,
+            session_id: session_id.clone(),
+            username: join.username,
+            premium: join.premium,
+            public_key: join.public_key,
+            joined_at: now,
+            sender,
+        });
+
+        Ok(JoinedSession { connection_id, session_id, host_id, is_host, peers })
+    }
+
+    /// Disconnect a peer, unless `connection_id` belongs to a connection
+    /// that has since been replaced or evicted
+    pub fn leave_live(&self, user_id: Uuid, connection_id: u64) {
+        let mut links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+        if links.get(&user_id).is_some_and(|link| link.connection_id == connection_id) {
+            self.drop_link(&mut links, user_id, None);
+        }
+    }
+
+    fn drop_link(&self, links: &mut HashMap<Uuid, PeerLink>, user_id: Uuid, reason: Option<&str>) {
+        let Some(link) = links.remove(&user_id) else {
+            return;
+        };
+        if let Some(reason) = reason {
+            let _ = link.sender.send(Outbound::message(&RelayMessage::SessionClosed { reason: reason.to_string() }));
+            let _ = link.sender.send(Outbound::Close);
+        }
+
+        let Some(mut session) = self.sessions.get_mut(&link.session_id) else {
+            return;
+        };
+        session.peers.retain(|&id| id != user_id);
+
+        let left = Outbound::message(&RelayMessage::PeerLeft { user_id });
+        for peer in session.peers.iter().filter_map(|id| links.get(id)) {
+            let _ = peer.sender.send(left.clone());
+        }
+
+        if session.peers.is_empty() {
+            drop(session);
+            self.sessions.remove(&link.session_id);
+            return;
+        }
+
+        if session.host_id == user_id {
+            let new_host = session.peers.iter()
+                .filter_map(|id| links.get(id).map(|link| (*id, link.joined_at)))
+                .min_by_key(|(_, joined_at)| *joined_at)
+                .map(|(id, _)| id);
+            if let Some(new_host) = new_host {
+                session.host_id = new_host;
+                let migration = Outbound::message(&RelayMessage::HostMigration { new_host });
+                for peer in session.peers.iter().filter_map(|id| links.get(id)) {
+                    let _ = peer.sender.send(migration.clone());
+                }
+            }
+        }
+    }
+
+    /// Make `new_host` the host of `from`'s session, if `from` is its host
+    pub fn transfer_host(&self, from: Uuid, new_host: Uuid) -> Result<(), RelayError> {
+        let links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+        let link = links.get(&from).ok_or(RelayError::SessionNotFound)?;
+        let mut session = self.sessions.get_mut(&link.session_id).ok_or(RelayError::SessionNotFound)?;
+        if session.host_id != from {
+            return Err(RelayError::Unauthorized);
+        }
+        if !session.peers.contains(&new_host) || !links.contains_key(&new_host) {
+            return Err(RelayError::PeerNotFound);
+        }
+
+        session.host_id = new_host;
+        let migration = Outbound::message(&RelayMessage::HostMigration { new_host });
+        for peer in session.peers.iter().filter_map(|id| links.get(id)) {
+            let _ = peer.sender.send(migration.clone());
+        }
+        Ok(())
+    }
+
+    /// Forward a `Data` message to one peer or, with no target, to every
+    /// other peer in the sender's session. Returns how many peers got it.
+    pub fn relay_data(&self, from: Uuid, to: Option<Uuid>, payload: Vec<u8>, key_id: Option<u32>) -> usize {
+        let bytes = payload.len() as u64;
+        let message = Outbound::message(&RelayMessage::Data { from, to, payload, key_id });
+        self.forward(from, to, message, bytes)
+    }
+
+    /// Forward a sealed session key to one peer in the sender's session
+    pub fn relay_session_key(&self, from: Uuid, to: Uuid, key_id: u32, ephemeral_key: String, sealed_key: Vec<u8>) -> usize {
+        let bytes = sealed_key.len() as u64;
+        let message = Outbound::message(&RelayMessage::SessionKey { from, to, key_id, ephemeral_key, sealed_key });
+        self.forward(from, Some(to), message, bytes)
+    }
+
+    /// Forward a binary frame to every other peer in the sender's session
+    pub fn relay_binary(&self, from: Uuid, data: Vec<u8>) -> usize {
+        let bytes = data.len() as u64;
+        self.forward(from, None, Outbound::Binary(data), bytes)
+    }
+
+    fn forward(&self, from: Uuid, to: Option<Uuid>, message: Outbound, bytes: u64) -> usize {
+        let links = self.links.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(link) = links.get(&from) else {
+            return 0;
+        };
+        let Some(session) = self.sessions.get(&link.session_id) else {
+            return 0;
+        };
+
+        let mut delivered = 0;
+        for id in session.peers.iter().filter(|&&id| id != from && to.is_none_or(|target| target == id)) {
+            if links.get(id).is_some_and(|peer| peer.sender.send(message.clone()).is_ok()) {
+                delivered += 1;
+            }
+        }
+        self.stats.bytes_relayed.fetch_add(bytes * delivered as u64, Ordering::Relaxed);
+        delivered
+    }
+
+    pub fn live_connections(&self) -> usize {
+        self.links.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
     pub fn cleanup_stale_peers(&self, timeout_secs: i64) {
         let cutoff = Utc::now() - chrono::Duration::seconds(timeout_secs);
         
@@ -324,6 +808,18 @@ impl RelayHub {
     }
 }
 
+fn peer_view(user_id: Uuid, link: &PeerLink, host_id: Uuid) -> RelayPeer {
+    RelayPeer {
+        user_id,
+        username: link.username.clone(),
+        is_host: user_id == host_id,
+        joined_at: link.joined_at,
+        latency_ms: None,
+        encryption: link.public_key.is_some(),
+        public_key: link.public_key.clone(),
+    }
+}
+
 impl Default for RelayHub {
     fn default() -> Self {
         Self::new()
@@ -348,6 +844,7 @@ pub enum RelayError {
     PeerNotFound,
     ConnectionFailed(String),
     Unauthorized,
+    RelayFull,
 }
 
 impl std::fmt::Display for RelayError {
@@ -359,6 +856,7 @@ impl std::fmt::Display for RelayError {
             Self::PeerNotFound => write!(f, "Peer not found"),
             Self::ConnectionFailed(e) => write!(f, "Connection failed: {}", e),
             Self::Unauthorized => write!(f, "Unauthorized"),
+            Self::RelayFull => write!(f, "Relay is at capacity"),
         }
     }
 }
@@ -371,3 +869,217 @@ fn sha256(input: &str) -> [u8; 32] {
     hasher.update(input.as_bytes());
     hasher.finalize().into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const SESSION: &str = "ABCD-EFGH-JKLM";
+
+    fn join(user_id: Uuid, premium: bool) -> RelayJoin {
+        RelayJoin {
+            session_id: SESSION.to_string(),
+            user_id,
+            username: user_id.to_string(),
+            premium,
+            password: None,
+            public_key: None,
+        }
+    }
+
+    fn connect(hub: &RelayHub, request: RelayJoin) -> Result<(JoinedSession, mpsc::UnboundedReceiver<Outbound>), RelayError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        hub.join_live(request, tx).map(|joined| (joined, rx))
+    }
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<Outbound>) -> Vec<Outbound> {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    fn parse(outbound: &Outbound) -> Option<RelayMessage> {
+        match outbound {
+            Outbound::Text(text) => serde_json::from_str(text).ok(),
+            _ => None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_broadcast_and_targeted_delivery_across_concurrent_clients() {
+        const CLIENTS: usize = 32;
+        let hub = Arc::new(RelayHub::new());
+        let ids: Arc<Vec<Uuid>> = Arc::new((0..CLIENTS).map(|_| Uuid::new_v4()).collect());
+        hub.register_session(SESSION, ids[0], CLIENTS as u32, None).unwrap();
+
+        let joined = Arc::new(tokio::sync::Barrier::new(CLIENTS));
+        let sent = Arc::new(tokio::sync::Barrier::new(CLIENTS));
+        let mut clients = Vec::new();
+        for i in 0..CLIENTS {
+            let (hub, ids, joined, sent) = (hub.clone(), ids.clone(), joined.clone(), sent.clone());
+            clients.push(tokio::spawn(async move {
+                let me = ids[i];
+                let mut request = join(me, false);
+                request.session_id = SESSION.to_lowercase();
+                let (_, mut rx) = connect(&hub, request).unwrap();
+                joined.wait().await;
+
+                assert_eq!(hub.relay_data(me, None, vec![i as u8], None), CLIENTS - 1);
+                let next = ids[(i + 1) % CLIENTS];
+                assert_eq!(hub.relay_data(me, Some(next), vec![i as u8, 0xff], None), 1);
+                sent.wait().await;
+
+                let mut broadcasts = HashSet::new();
+                let mut targeted = Vec::new();
+                while broadcasts.len() < CLIENTS - 1 || targeted.is_empty() {
+                    let outbound = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+                    if let Some(RelayMessage::Data { from, to, payload, .. }) = parse(&outbound) {
+                        assert_ne!(from, me);
+                        match to {
+                            None => assert!(broadcasts.insert(from)),
+                            Some(target) => {
+                                assert_eq!(target, me);
+                                targeted.push((from, payload));
+                            }
+                        }
+                    }
+                }
+
+                let previous = (i + CLIENTS - 1) % CLIENTS;
+                assert_eq!(targeted, vec![(ids[previous], vec![previous as u8, 0xff])]);
+                assert!(drain(&mut rx).iter().all(|o| !matches!(parse(o), Some(RelayMessage::Data { .. }))));
+            }));
+        }
+        for client in clients {
+            client.await.unwrap();
+        }
+
+        assert_eq!(hub.live_connections(), CLIENTS);
+        assert_eq!(hub.get_session(SESSION).unwrap().peers.len(), CLIENTS);
+        assert_eq!(hub.get_stats().bytes_relayed, (CLIENTS * (CLIENTS - 1) + CLIENTS * 2) as u64);
+    }
+
+    #[test]
+    fn test_session_cap_and_password_are_enforced() {
+        let hub = RelayHub::new();
+        let host = Uuid::new_v4();
+        hub.register_session(SESSION, host, 2, Some("hunter2".to_string())).unwrap();
+        assert!(matches!(hub.register_session(SESSION, Uuid::new_v4(), 8, None), Err(RelayError::Unauthorized)));
+
+        assert!(matches!(connect(&hub, join(host, false)), Err(RelayError::InvalidPassword)));
+        let with_password = |user_id| RelayJoin { password: Some("hunter2".to_string()), ..join(user_id, false) };
+        let (joined, _host_rx) = connect(&hub, with_password(host)).unwrap();
+        assert!(joined.is_host);
+        assert!(joined.peers.is_empty());
+
+        let guest = Uuid::new_v4();
+        let (joined, _guest_rx) = connect(&hub, with_password(guest)).unwrap();
+        assert!(!joined.is_host);
+        assert_eq!(joined.peers.iter().map(|p| p.user_id).collect::<Vec<_>>(), vec![host]);
+        assert!(matches!(connect(&hub, with_password(Uuid::new_v4())), Err(RelayError::SessionFull)));
+    }
+
+    #[test]
+    fn test_premium_joins_take_priority_near_capacity() {
+        let hub = RelayHub::new().with_limits(RelayLimits { max_connections: 4, premium_reserve: 1 });
+        let free: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut free_rx = Vec::new();
+        for &id in &free {
+            free_rx.push(connect(&hub, join(id, false)).unwrap().1);
+        }
+
+        // The last slot is reserved for premium users
+        assert!(matches!(connect(&hub, join(Uuid::new_v4(), false)), Err(RelayError::RelayFull)));
+        let (_, mut premium_rx) = connect(&hub, join(Uuid::new_v4(), true)).unwrap();
+        drain(&mut premium_rx);
+
+        // A full hub makes room by evicting the newest free-tier peer
+        let (_, _second_premium_rx) = connect(&hub, join(Uuid::new_v4(), true)).unwrap();
+        assert_eq!(hub.live_connections(), 4);
+        let evicted = drain(&mut free_rx[2]);
+        assert!(matches!(parse(&evicted[evicted.len() - 2]), Some(RelayMessage::SessionClosed { .. })));
+        assert_eq!(evicted.last(), Some(&Outbound::Close));
+        assert!(drain(&mut premium_rx).iter().any(|o| {
+            matches!(parse(o), Some(RelayMessage::PeerLeft { user_id }) if user_id == free[2])
+        }));
+        assert_eq!(hub.relay_data(free[2], None, vec![1], None), 0);
+    }
+
+    #[test]
+    fn test_host_migration_and_stale_leaves() {
+        let hub = RelayHub::new();
+        let (host, guest) = (Uuid::new_v4(), Uuid::new_v4());
+        let (first, _old_rx) = connect(&hub, join(host, true)).unwrap();
+        assert_eq!(hub.get_session(SESSION).unwrap().max_peers, PREMIUM_SESSION_PEERS);
+
+        // Reconnecting replaces the old connection, so its late leave is ignored
+        let (second, _host_rx) = connect(&hub, join(host, true)).unwrap();
+        let (_, mut guest_rx) = connect(&hub, join(guest, false)).unwrap();
+        hub.leave_live(host, first.connection_id);
+        assert_eq!(hub.live_connections(), 2);
+
+        hub.leave_live(host, second.connection_id);
+        let messages: Vec<_> = drain(&mut guest_rx).iter().filter_map(parse).collect();
+        assert!(messages.iter().any(|m| matches!(m, RelayMessage::HostMigration { new_host } if *new_host == guest)));
+        assert_eq!(hub.get_session(SESSION).unwrap().host_id, guest);
+
+        hub.leave_live(guest, 0);
+        assert!(hub.get_session(SESSION).is_some());
+        let connection_id = connect(&hub, join(guest, false)).unwrap().0.connection_id;
+        hub.leave_live(guest, connection_id);
+        assert!(hub.get_session(SESSION).is_none());
+    }
+
+    #[test]
+    fn test_host_hands_off_hosting() {
+        let hub = RelayHub::new();
+        let (host, guest) = (Uuid::new_v4(), Uuid::new_v4());
+        let (_, mut host_rx) = connect(&hub, join(host, false)).unwrap();
+        let (_, mut guest_rx) = connect(&hub, join(guest, false)).unwrap();
+
+        assert!(matches!(hub.transfer_host(guest, guest), Err(RelayError::Unauthorized)));
+        assert!(matches!(hub.transfer_host(host, Uuid::new_v4()), Err(RelayError::PeerNotFound)));
+        hub.transfer_host(host, guest).unwrap();
+        assert_eq!(hub.get_session(SESSION).unwrap().host_id, guest);
+        for rx in [&mut host_rx, &mut guest_rx] {
+            let messages: Vec<_> = drain(rx).iter().filter_map(parse).collect();
+            assert!(messages.iter().any(|m| matches!(m, RelayMessage::HostMigration { new_host } if *new_host == guest)));
+        }
+    }
+
+    #[test]
+    fn test_session_keys_reach_only_their_target() {
+        let hub = RelayHub::new();
+        let (host, guest, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let with_key = |user_id| RelayJoin { public_key: Some("ab".repeat(32)), ..join(user_id, false) };
+        let (_, mut host_rx) = connect(&hub, with_key(host)).unwrap();
+        let (joined, mut guest_rx) = connect(&hub, with_key(guest)).unwrap();
+        assert_eq!(joined.host_id, host);
+        assert!(joined.peers[0].encryption);
+
+        let announced: Vec<_> = drain(&mut host_rx).iter().filter_map(parse).collect();
+        assert!(announced.iter().any(|m| matches!(m, RelayMessage::PeerJoined { peer } if peer.encryption)));
+
+        let other_session = RelayJoin { session_id: "other".to_string(), ..join(outsider, false) };
+        let (_, mut outsider_rx) = connect(&hub, other_session).unwrap();
+        assert_eq!(hub.relay_session_key(host, outsider, 1, "cd".repeat(32), vec![7; 56]), 0);
+        assert_eq!(hub.relay_session_key(host, guest, 1, "cd".repeat(32), vec![7; 56]), 1);
+
+        let received: Vec<_> = drain(&mut guest_rx).iter().filter_map(parse).collect();
+        assert!(received.iter().any(|m| matches!(
+            m,
+            RelayMessage::SessionKey { from, to, key_id: 1, .. } if *from == host && *to == guest
+        )));
+        assert!(drain(&mut outsider_rx).iter().all(|o| !matches!(parse(o), Some(RelayMessage::SessionKey { .. }))));
+    }
+
+    #[test]
+    fn test_invite_codes_are_normalized() {
+        assert!(is_invite_code("abcd-2345-WXYZ"));
+        assert!(!is_invite_code("abcd2345wxyz"));
+        assert_eq!(normalize_session_id(" abcd-2345-wxyz "), "ABCD-2345-WXYZ");
+        let id = Uuid::new_v4().to_string();
+        assert_eq!(normalize_session_id(&id), id);
+    }
+}
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::cmp::Ordering;
+use uuid::Uuid;
+
+pub const PLATFORMS: &[&str] = &["windows", "macos", "linux"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Stable,
+    Beta,
+}
+
+impl Channel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stable" => Some(Channel::Stable),
+            "beta" => Some(Channel::Beta),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+        }
+    }
+
+    /// Channels whose releases a client on this channel is offered. Beta
+    /// clients also get stable releases when those are newer.
+    pub fn includes(&self) -> &'static [&'static str] {
+        match self {
+            Channel::Stable => &["stable"],
+            Channel::Beta => &["stable", "beta"],
+        }
+    }
+}
+
+pub fn validate_platform(platform: &str) -> Result<(), String> {
+    if PLATFORMS.contains(&platform) {
+        Ok(())
+    } else {
+        Err(format!("Unknown platform '{}', expected one of: {}", platform, PLATFORMS.join(", ")))
+    }
+}
+
+/// A semver version (`MAJOR.MINOR.PATCH[-PRERELEASE][+BUILD]`), ordered by
+/// semver precedence so `1.2.0-beta.2 < 1.2.0-beta.10 < 1.2.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<String>,
+}
+
+impl Version {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid version '{}', expected MAJOR.MINOR.PATCH", value);
+        let value = value.trim().trim_start_matches('v');
+        let without_build = value.split('+').next().unwrap_or(value);
+        let (core, pre) = match without_build.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (without_build, None),
+        };
+
+        let numbers: Vec<u64> = core.split('.')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let [major, minor, patch] = numbers[..] else {
+            return Err(invalid());
+        };
+
+        let pre = match pre {
+            Some(pre) => {
+                let identifiers: Vec<String> = pre.split('.').map(str::to_string).collect();
+                if identifiers.iter().any(|id| id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')) {
+                    return Err(invalid());
+                }
+                identifiers
+            }
+            None => Vec::new(),
+        };
+        Ok(Self { major, minor, patch, pre })
+    }
+}
+
+fn compare_identifiers(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.iter().zip(&other.pre)
+                    .map(|(a, b)| compare_identifiers(a, b))
+                    .find(|o| o.is_ne())
+                    .unwrap_or_else(|| self.pre.len().cmp(&other.pre.len())),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Release {
+    pub id: Uuid,
+    pub version: String,
+    pub channel: String,
+    pub platform: String,
+    pub url: String,
+    pub sha256: String,
+    pub size: i64,
+    pub changelog: String,
+    pub published_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogEntry {
+    pub version: String,
+    pub channel: String,
+    pub changelog: String,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Sorts newest first; releases with unparseable versions sort last.
+pub fn sort_newest_first(releases: &mut [Release]) {
+    releases.sort_by(|a, b| match (Version::parse(&a.version), Version::parse(&b.version)) {
+        (Ok(a), Ok(b)) => b.cmp(&a),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => b.published_at.cmp(&a.published_at),
+    });
+}
+
+/// Releases strictly newer than `since`, newest first.
+pub fn changelog_since(releases: &[Release], since: &Version) -> Vec<ChangelogEntry> {
+    let mut newer: Vec<Release> = releases.iter()
+        .filter(|r| Version::parse(&r.version).is_ok_and(|v| v > *since))
+        .cloned()
+        .collect();
+    sort_newest_first(&mut newer);
+    newer.into_iter()
+        .map(|r| ChangelogEntry { version: r.version, channel: r.channel, changelog: r.changelog, published_at: r.published_at })
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublishRelease {
+    pub version: String,
+    pub channel: String,
+    pub platform: String,
+    pub url: String,
+    pub sha256: String,
+    pub size: i64,
+    #[serde(default)]
+    pub changelog: String,
+}
+
+impl PublishRelease {
+    pub fn validate(&self) -> Result<(), String> {
+        let version = Version::parse(&self.version)?;
+        let channel = Channel::parse(&self.channel)
+            .ok_or_else(|| format!("Unknown channel '{}', expected stable or beta", self.channel))?;
+        if channel == Channel::Stable && !version.pre.is_empty() {
+            return Err("Prerelease versions can only be published to the beta channel".to_string());
+        }
+        validate_platform(&self.platform)?;
+        if !(self.url.starts_with("https://") || self.url.starts_with('/')) {
+            return Err("url must be an https:// URL or a server-relative path".to_string());
+        }
+        if self.sha256.len() != 64 || !self.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("sha256 must be 64 hex characters".to_string());
+        }
+        if self.size <= 0 {
+            return Err("size must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+type ReleaseRow = (Uuid, String, String, String, String, String, i64, String, DateTime<Utc>);
+
+fn from_row(row: ReleaseRow) -> Release {
+    let (id, version, channel, platform, url, sha256, size, changelog, published_at) = row;
+    Release { id, version, channel, platform, url, sha256, size, changelog, published_at }
+}
+
+/// Every live release for the platform on the channel, newest first.
+pub async fn list(db: &PgPool, channel: Channel, platform: &str) -> Result<Vec<Release>, sqlx::Error> {
+    let channels: Vec<String> = channel.includes().iter().map(|c| c.to_string()).collect();
+    let rows = sqlx::query_as::<_, ReleaseRow>(
+        "SELECT id, version, channel, platform, url, sha256, size, changelog, published_at
+         FROM releases WHERE platform = $1 AND channel = ANY($2) AND yanked_at IS NULL"
+    )
+        .bind(platform)
+        .bind(&channels)
+        .fetch_all(db)
+        .await?;
+    let mut releases: Vec<Release> = rows.into_iter().map(from_row).collect();
+    sort_newest_first(&mut releases);
+    Ok(releases)
+}
+
+/// Inserts the release, or returns `None` if that version is already published for the platform.
+pub async fn publish(db: &PgPool, release: &PublishRelease) -> Result<Option<Release>, sqlx::Error> {
+    let row = sqlx::query_as::<_, ReleaseRow>(
+        "INSERT INTO releases (id, version, channel, platform, url, sha256, size, changelog, published_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+         ON CONFLICT (version, platform) DO NOTHING
+         RETURNING id, version, channel, platform, url, sha256, size, changelog, published_at"
+    )
+        .bind(Uuid::new_v4())
+        .bind(release.version.trim())
+        .bind(&release.channel)
+        .bind(&release.platform)
+        .bind(&release.url)
+        .bind(release.sha256.to_ascii_lowercase())
+        .bind(release.size)
+        .bind(release.changelog.trim())
+        .fetch_optional(db)
+        .await?;
+    Ok(row.map(from_row))
+}
+
+/// Hides a release from clients without deleting it.
+pub async fn yank(db: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE releases SET yanked_at = NOW() WHERE id = $1 AND yanked_at IS NULL")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(value: &str) -> Version {
+        Version::parse(value).unwrap()
+    }
+
+    fn release(version: &str, channel: &str) -> Release {
+        Release {
+            id: Uuid::new_v4(),
+            version: version.to_string(),
+            channel: channel.to_string(),
+            platform: "linux".to_string(),
+            url: format!("/releases/yellow-tale-{}-linux.tar.gz", version),
+            sha256: "0".repeat(64),
+            size: 1,
+            changelog: format!("Changes in {}", version),
+            published_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_version_precedence() {
+        assert!(v("1.0.0") < v("1.0.1"));
+        assert!(v("1.9.0") < v("1.10.0"));
+        assert!(v("1.0.0-beta") < v("1.0.0"));
+        assert!(v("1.0.0-alpha") < v("1.0.0-alpha.1"));
+        assert!(v("1.0.0-beta.2") < v("1.0.0-beta.10"));
+        assert!(v("1.0.0-1") < v("1.0.0-alpha"));
+        assert_eq!(v("v1.2.3+build.5"), v("1.2.3"));
+        assert!(Version::parse("1.2").is_err());
+        assert!(Version::parse("1.2.x").is_err());
+        assert!(Version::parse("1.2.3-").is_err());
+    }
+
+    #[test]
+    fn test_changelog_since_is_newest_first() {
+        let releases = vec![release("0.1.0", "stable"), release("0.3.0-beta.1", "beta"), release("0.2.0", "stable")];
+        let versions: Vec<String> = changelog_since(&releases, &v("0.1.0")).into_iter().map(|e| e.version).collect();
+        assert_eq!(versions, vec!["0.3.0-beta.1", "0.2.0"]);
+        assert!(changelog_since(&releases, &v("0.3.0")).is_empty());
+    }
+
+    #[test]
+    fn test_publish_validation() {
+        let valid = PublishRelease {
+            version: "1.0.0".into(),
+            channel: "stable".into(),
+            platform: "linux".into(),
+            url: "https://cdn.yellowtale.com/yellow-tale-1.0.0.tar.gz".into(),
+            sha256: "a".repeat(64),
+            size: 1024,
+            changelog: String::new(),
+        };
+        assert!(valid.validate().is_ok());
+        assert!(PublishRelease { version: "1.1.0-rc.1".into(), ..valid.clone() }.validate().is_err());
+        assert!(PublishRelease { version: "1.1.0-rc.1".into(), channel: "beta".into(), ..valid.clone() }.validate().is_ok());
+        assert!(PublishRelease { platform: "amiga".into(), ..valid.clone() }.validate().is_err());
+        assert!(PublishRelease { url: "http://insecure".into(), ..valid.clone() }.validate().is_err());
+        assert!(PublishRelease { sha256: "abc".into(), ..valid }.validate().is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::pagination::PageRequest;
+
+/// Tags a search may require at once; any more are ignored.
+pub const MAX_FILTER_TAGS: usize = 10;
+
+/// Narrows the directory to servers matching every criterion given, on top
+/// of its online-and-recently-pinged rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerFilter {
+    /// Compared without regard to case
+    pub game_mode: Option<String>,
+    /// Every tag must be on the server
+    pub tags: Vec<String>,
+    /// Only servers below their player limit
+    pub has_slots: bool,
+    /// Matched anywhere in the name or description, ignoring case
+    pub q: Option<String>,
+}
+
+impl ServerFilter {
+    /// From the listing's query string: blank values are ignored and `tags`
+    /// is a comma-separated list.
+    pub fn parse(game_mode: Option<&str>, tags: Option<&str>, has_slots: Option<bool>, q: Option<&str>) -> Self {
+        let present = |value: Option<&str>| value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        let mut wanted: Vec<String> = Vec::new();
+        for tag in tags.unwrap_or_default().split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if wanted.len() < MAX_FILTER_TAGS && !wanted.iter().any(|w| w == tag) {
+                wanted.push(tag.to_string());
+            }
+        }
+        Self {
+            game_mode: present(game_mode),
+            tags: wanted,
+            has_slots: has_slots.unwrap_or(false),
+            q: present(q),
+        }
+    }
+
+    /// `q` as an ILIKE pattern, with its own wildcards taken literally
+    fn search_pattern(&self) -> Option<String> {
+        self.q.as_ref().map(|q| {
+            let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+    }
+
+    /// The tags as a JSON array for containment against the tags column
+    fn tags_json(&self) -> Option<serde_json::Value> {
+        (!self.tags.is_empty()).then(|| serde_json::json!(self.tags))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListedServer {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub address: String,
+    pub port: i32,
+    pub max_players: i32,
+    pub current_players: i32,
+    pub game_mode: String,
+    pub tags: Vec<String>,
+    pub owner_id: Uuid,
+    pub is_online: bool,
+    pub last_ping: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerListing {
+    pub servers: Vec<ListedServer>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+/// Busiest first. The tags filter is a JSONB containment test, which the GIN
+/// index on `tags` serves.
+pub async fn list(db: &PgPool, filter: &ServerFilter, page: PageRequest) -> Result<ServerListing, sqlx::Error> {
+    let listed = "FROM game_servers
+         WHERE is_online = true AND last_ping > NOW() - INTERVAL '5 minutes'
+           AND ($1::TEXT IS NULL OR LOWER(game_mode) = LOWER($1))
+           AND ($2::JSONB IS NULL OR tags @> $2)
+           AND (NOT $3 OR current_players < max_players)
+           AND ($4::TEXT IS NULL OR name ILIKE $4 OR description ILIKE $4)";
+    let (game_mode, tags, pattern) = (filter.game_mode.as_deref(), filter.tags_json(), filter.search_pattern());
+
+    let rows = sqlx::query_as::<_, (Uuid, String, Option<String>, String, i32, i32, i32, String, Option<serde_json::Value>, Uuid, bool, DateTime<Utc>, DateTime<Utc>)>(&format!(
+        "SELECT id, name, description, address, port, max_players, current_players, game_mode, tags, owner_id, is_online, last_ping, created_at
+         {} ORDER BY current_players DESC, id LIMIT $5 OFFSET $6",
+        listed
+    ))
+        .bind(game_mode)
+        .bind(&tags)
+        .bind(filter.has_slots)
+        .bind(&pattern)
+        .bind(page.limit())
+        .bind(page.offset())
+        .fetch_all(db)
+        .await?;
+    let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) {}", listed))
+        .bind(game_mode)
+        .bind(&tags)
+        .bind(filter.has_slots)
+        .bind(&pattern)
+        .fetch_one(db)
+        .await?;
+
+    Ok(ServerListing {
+        servers: rows.into_iter()
+            .map(|(id, name, description, address, port, max_players, current_players, game_mode, tags, owner_id, is_online, last_ping, created_at)| ListedServer {
+                id,
+                name,
+                description,
+                address,
+                port,
+                max_players,
+                current_players,
+                game_mode,
+                tags: tags.and_then(|t| serde_json::from_value(t).ok()).unwrap_or_default(),
+                owner_id,
+                is_online,
+                last_ping,
+                created_at,
+            })
+            .collect(),
+        total,
+        page: page.page,
+        per_page: page.per_page,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The listing's WHERE clause, applied in memory
+    fn matches(filter: &ServerFilter, server: &ListedServer) -> bool {
+        let text = filter.q.as_deref().map(str::to_lowercase);
+        filter.game_mode.as_deref().is_none_or(|mode| mode.eq_ignore_ascii_case(&server.game_mode))
+            && filter.tags.iter().all(|tag| server.tags.contains(tag))
+            && (!filter.has_slots || server.current_players < server.max_players)
+            && text.is_none_or(|text| {
+                server.name.to_lowercase().contains(&text)
+                    || server.description.as_deref().is_some_and(|d| d.to_lowercase().contains(&text))
+            })
+    }
+
+    fn server(name: &str, description: Option<&str>, game_mode: &str, tags: &[&str], players: (i32, i32)) -> ListedServer {
+        ListedServer {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            address: "play.example.com".to_string(),
+            port: 25565,
+            max_players: players.1,
+            current_players: players.0,
+            game_mode: game_mode.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            owner_id: Uuid::new_v4(),
+            is_online: true,
+            last_ping: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn seeded() -> Vec<ListedServer> {
+        vec![
+            server("Castle Builders", Some("Creative plots"), "creative", &["europe", "plots"], (12, 50)),
+            server("Full Castle", None, "Creative", &["europe"], (20, 20)),
+            server("Arena", Some("Castle sieges every hour"), "pvp", &["europe", "pvp"], (3, 40)),
+            server("Prairie", Some("Relaxed survival"), "survival", &["na"], (0, 30)),
+            server("Sky Plots", None, "creative", &["na", "plots"], (5, 10)),
+        ]
+    }
+
+    fn names(filter: &ServerFilter) -> Vec<String> {
+        seeded().into_iter().filter(|s| matches(filter, s)).map(|s| s.name).collect()
+    }
+
+    #[test]
+    fn test_each_filter_against_seeded_servers() {
+        assert_eq!(names(&ServerFilter::default()).len(), 5);
+        assert_eq!(names(&ServerFilter::parse(Some("CREATIVE"), None, None, None)), ["Castle Builders", "Full Castle", "Sky Plots"]);
+        assert_eq!(names(&ServerFilter::parse(None, Some("europe"), None, None)), ["Castle Builders", "Full Castle", "Arena"]);
+        assert_eq!(names(&ServerFilter::parse(None, Some("plots,na"), None, None)), ["Sky Plots"]);
+        assert_eq!(names(&ServerFilter::parse(None, None, Some(true), None)), ["Castle Builders", "Arena", "Prairie", "Sky Plots"]);
+        assert_eq!(names(&ServerFilter::parse(None, None, Some(false), None)).len(), 5);
+        assert_eq!(names(&ServerFilter::parse(None, None, None, Some("castle"))), ["Castle Builders", "Full Castle", "Arena"]);
+    }
+
+    #[test]
+    fn test_filters_combine_on_seeded_servers() {
+        let filter = ServerFilter::parse(Some("creative"), Some("europe"), Some(true), Some("castle"));
+        assert_eq!(names(&filter), ["Castle Builders"]);
+        let filter = ServerFilter::parse(Some("pvp"), Some("na"), None, None);
+        assert!(names(&filter).is_empty());
+    }
+
+    #[test]
+    fn test_blank_params_filter_nothing() {
+        let filter = ServerFilter::parse(Some("  "), Some(" , ,"), None, Some(""));
+        assert_eq!(filter, ServerFilter::default());
+        assert!(filter.tags_json().is_none());
+        assert!(filter.search_pattern().is_none());
+    }
+
+    #[test]
+    fn test_params_combine() {
+        let filter = ServerFilter::parse(Some(" Creative "), Some("europe, pvp,europe,,"), Some(true), Some(" castle "));
+        assert_eq!(filter.game_mode.as_deref(), Some("Creative"));
+        assert_eq!(filter.tags, vec!["europe", "pvp"]);
+        assert!(filter.has_slots);
+        assert_eq!(filter.tags_json(), Some(serde_json::json!(["europe", "pvp"])));
+        assert_eq!(filter.search_pattern().as_deref(), Some("%castle%"));
+    }
+
+    #[test]
+    fn test_search_text_is_literal() {
+        let filter = ServerFilter::parse(None, None, None, Some("100%_fun\\"));
+        assert_eq!(filter.search_pattern().as_deref(), Some("%100\\%\\_fun\\\\%"));
+    }
+
+    #[test]
+    fn test_tag_count_is_capped() {
+        let tags: Vec<String> = (0..25).map(|i| format!("t{}", i)).collect();
+        let filter = ServerFilter::parse(None, Some(&tags.join(",")), None, None);
+        assert_eq!(filter.tags, tags[..MAX_FILTER_TAGS]);
+    }
+}
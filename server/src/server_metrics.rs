@@ -0,0 +1,313 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Raw heartbeat samples are kept this long before being rolled into hours.
+pub const RAW_RETENTION_HOURS: i64 = 24;
+/// Hourly rollups are kept this long.
+pub const HOURLY_RETENTION_DAYS: i64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    /// How often hosts are expected to heartbeat.
+    pub heartbeat_interval: Duration,
+    /// Servers that miss this many heartbeats in a row are marked offline.
+    pub missed_heartbeats: u32,
+    /// Offline listings with no heartbeat for this long are deleted.
+    pub dead_after: Duration,
+    pub sweep_interval: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(60),
+            missed_heartbeats: 5,
+            dead_after: Duration::from_secs(30 * 24 * 60 * 60),
+            sweep_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            heartbeat_interval: Duration::from_secs(env_or("SERVER_HEARTBEAT_INTERVAL_SECS", defaults.heartbeat_interval.as_secs()).max(1)),
+            missed_heartbeats: env_or("SERVER_MISSED_HEARTBEATS", defaults.missed_heartbeats).max(1),
+            dead_after: Duration::from_secs(env_or("SERVER_DEAD_AFTER_DAYS", defaults.dead_after.as_secs() / 86_400) * 86_400),
+            sweep_interval: Duration::from_secs(env_or("SERVER_SWEEP_INTERVAL_SECS", defaults.sweep_interval.as_secs()).max(1)),
+        }
+    }
+
+    /// Servers whose last heartbeat is older than this are offline.
+    pub fn stale_before(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let stale_after = self.heartbeat_interval * self.missed_heartbeats;
+        now - ChronoDuration::from_std(stale_after).unwrap_or(ChronoDuration::minutes(5))
+    }
+
+    /// Listings whose last heartbeat is older than this are deleted.
+    pub fn dead_before(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - ChronoDuration::from_std(self.dead_after).unwrap_or(ChronoDuration::days(30))
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+pub fn validate_player_count(current_players: i32, max_players: i32) -> Result<(), String> {
+    if current_players < 0 {
+        return Err("current_players cannot be negative".to_string());
+    }
+    if current_players > max_players {
+        return Err(format!("current_players ({}) exceeds max_players ({})", current_players, max_players));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sample {
+    pub ts: DateTime<Utc>,
+    pub current_players: i32,
+}
+
+/// Samples from one hour, stored as sums so later sweeps can add to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HourlyBucket {
+    pub hour: DateTime<Utc>,
+    pub samples: i64,
+    pub total_players: i64,
+    pub peak_players: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct HourlyPoint {
+    pub hour: DateTime<Utc>,
+    pub avg_players: f64,
+    pub peak_players: i32,
+}
+
+pub fn truncate_to_hour(ts: DateTime<Utc>) -> DateTime<Utc> {
+    let seconds = ts.timestamp();
+    DateTime::from_timestamp(seconds - seconds.rem_euclid(3600), 0).unwrap_or(ts)
+}
+
+pub fn downsample(samples: &[Sample]) -> Vec<HourlyBucket> {
+    let mut buckets: BTreeMap<DateTime<Utc>, HourlyBucket> = BTreeMap::new();
+    for sample in samples {
+        let hour = truncate_to_hour(sample.ts);
+        let bucket = buckets.entry(hour).or_insert(HourlyBucket { hour, samples: 0, total_players: 0, peak_players: 0 });
+        bucket.samples += 1;
+        bucket.total_players += i64::from(sample.current_players);
+        bucket.peak_players = bucket.peak_players.max(sample.current_players);
+    }
+    buckets.into_values().collect()
+}
+
+/// Stores one heartbeat's player count.
+pub async fn record_sample(db: &PgPool, server_id: Uuid, current_players: i32, ts: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO server_metrics (server_id, ts, current_players) VALUES ($1, $2, $3)")
+        .bind(server_id)
+        .bind(ts)
+        .bind(current_players)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SweepStats {
+    pub marked_offline: u64,
+    pub listings_deleted: u64,
+    pub samples_rolled_up: u64,
+    pub hours_expired: u64,
+}
+
+/// Marks stale servers offline, deletes dead listings, rolls raw samples
+/// past retention into hourly buckets and drops buckets past theirs.
+pub async fn sweep(db: &PgPool, config: &HeartbeatConfig, now: DateTime<Utc>) -> Result<SweepStats, sqlx::Error> {
+    let marked_offline = sqlx::query("UPDATE game_servers SET is_online = false WHERE is_online = true AND last_ping < $1")
+        .bind(config.stale_before(now))
+        .execute(db)
+        .await?
+        .rows_affected();
+    let listings_deleted = sqlx::query("DELETE FROM game_servers WHERE last_ping < $1")
+        .bind(config.dead_before(now))
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    let raw_before = now - ChronoDuration::hours(RAW_RETENTION_HOURS);
+    let mut tx = db.begin().await?;
+    let rows = sqlx::query_as::<_, (Uuid, DateTime<Utc>, i32)>(
+        "DELETE FROM server_metrics WHERE ts < $1 RETURNING server_id, ts, current_players"
+    )
+        .bind(raw_before)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut by_server: BTreeMap<Uuid, Vec<Sample>> = BTreeMap::new();
+    for (server_id, ts, current_players) in &rows {
+        by_server.entry(*server_id).or_default().push(Sample { ts: *ts, current_players: *current_players });
+    }
+    for (server_id, samples) in &by_server {
+        for bucket in downsample(samples) {
+            sqlx::query(
+                "INSERT INTO server_metrics_hourly (server_id, hour, samples, total_players, peak_players)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (server_id, hour) DO UPDATE SET
+                    samples = server_metrics_hourly.samples + EXCLUDED.samples,
+                    total_players = server_metrics_hourly.total_players + EXCLUDED.total_players,
+                    peak_players = GREATEST(server_metrics_hourly.peak_players, EXCLUDED.peak_players)"
+            )
+                .bind(server_id)
+                .bind(bucket.hour)
+                .bind(bucket.samples)
+                .bind(bucket.total_players)
+                .bind(bucket.peak_players)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    let hours_expired = sqlx::query("DELETE FROM server_metrics_hourly WHERE hour < $1")
+        .bind(now - ChronoDuration::days(HOURLY_RETENTION_DAYS))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+
+    Ok(SweepStats { marked_offline, listings_deleted, samples_rolled_up: rows.len() as u64, hours_expired })
+}
+
+/// Runs `sweep` every `sweep_interval` for the life of the process.
+pub fn spawn_sweeper(db: PgPool, config: HeartbeatConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.sweep_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match sweep(&db, &config, Utc::now()).await {
+                Ok(stats) if stats.marked_offline > 0 || stats.listings_deleted > 0 => {
+                    info!("Marked {} stale servers offline, deleted {} dead listings", stats.marked_offline, stats.listings_deleted);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Server heartbeat sweep failed: {}", e),
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSeries {
+    /// Every heartbeat from the last 24 hours
+    pub raw: Vec<Sample>,
+    /// Hourly averages for the last 30 days, older than the raw series
+    pub hourly: Vec<HourlyPoint>,
+    pub peak_players_24h: i32,
+}
+
+pub async fn series(db: &PgPool, server_id: Uuid, now: DateTime<Utc>) -> Result<MetricsSeries, sqlx::Error> {
+    let raw = sqlx::query_as::<_, (DateTime<Utc>, i32)>(
+        "SELECT ts, current_players FROM server_metrics WHERE server_id = $1 AND ts >= $2 ORDER BY ts"
+    )
+        .bind(server_id)
+        .bind(now - ChronoDuration::hours(RAW_RETENTION_HOURS))
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|(ts, current_players)| Sample { ts, current_players })
+        .collect::<Vec<_>>();
+
+    let hourly = sqlx::query_as::<_, (DateTime<Utc>, i64, i64, i32)>(
+        "SELECT hour, samples, total_players, peak_players FROM server_metrics_hourly
+         WHERE server_id = $1 AND hour >= $2 ORDER BY hour"
+    )
+        .bind(server_id)
+        .bind(now - ChronoDuration::days(HOURLY_RETENTION_DAYS))
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|(hour, samples, total_players, peak_players)| HourlyPoint {
+            hour,
+            avg_players: total_players as f64 / samples.max(1) as f64,
+            peak_players,
+        })
+        .collect();
+
+    let peak_players_24h = raw.iter().map(|s| s.current_players).max().unwrap_or(0);
+    Ok(MetricsSeries { raw, hourly, peak_players_24h })
+}
+
+pub async fn peak_players_24h(db: &PgPool, server_id: Uuid, now: DateTime<Utc>) -> Result<i32, sqlx::Error> {
+    let peak = sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT MAX(current_players) FROM server_metrics WHERE server_id = $1 AND ts >= $2"
+    )
+        .bind(server_id)
+        .bind(now - ChronoDuration::hours(RAW_RETENTION_HOURS))
+        .fetch_one(db)
+        .await?;
+    Ok(peak.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        format!("2026-03-01T{:02}:{:02}:00Z", hour, minute).parse().unwrap()
+    }
+
+    #[test]
+    fn test_player_count_must_fit_server() {
+        assert!(validate_player_count(0, 20).is_ok());
+        assert!(validate_player_count(20, 20).is_ok());
+        assert!(validate_player_count(21, 20).is_err());
+        assert!(validate_player_count(-1, 20).is_err());
+    }
+
+    #[test]
+    fn test_missed_heartbeats_take_servers_offline() {
+        let config = HeartbeatConfig {
+            heartbeat_interval: Duration::from_secs(30),
+            missed_heartbeats: 4,
+            ..HeartbeatConfig::default()
+        };
+        let now = at(12, 0);
+        assert_eq!(config.stale_before(now), at(11, 58));
+        assert_eq!(HeartbeatConfig::default().stale_before(now), at(11, 55));
+    }
+
+    #[test]
+    fn test_listings_silent_for_a_month_are_dead() {
+        let config = HeartbeatConfig::default();
+        let now = at(12, 0);
+        assert_eq!(config.dead_before(now), now - ChronoDuration::days(30));
+        assert!(config.dead_before(now) < config.stale_before(now));
+    }
+
+    #[test]
+    fn test_truncate_to_hour() {
+        assert_eq!(truncate_to_hour(at(13, 59)), at(13, 0));
+        assert_eq!(truncate_to_hour(at(14, 0)), at(14, 0));
+    }
+
+    #[test]
+    fn test_downsample_groups_by_hour() {
+        let samples = [
+            Sample { ts: at(10, 5), current_players: 4 },
+            Sample { ts: at(10, 35), current_players: 8 },
+            Sample { ts: at(11, 0), current_players: 3 },
+        ];
+        assert_eq!(downsample(&samples), vec![
+            HourlyBucket { hour: at(10, 0), samples: 2, total_players: 12, peak_players: 8 },
+            HourlyBucket { hour: at(11, 0), samples: 1, total_players: 3, peak_players: 3 },
+        ]);
+        assert!(downsample(&[]).is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::auth::{generate_token, hash_token};
+
+/// Scoped server tokens carry this prefix so heartbeats can tell them apart
+/// from user session tokens without a second lookup.
+pub const SERVER_TOKEN_PREFIX: &str = "srv_";
+/// How long a transfer offer waits for the recipient to accept it.
+pub const TRANSFER_TTL_DAYS: i64 = 7;
+const MAX_NAME_LEN: usize = 128;
+const MAX_TAGS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessError {
+    NotFound,
+    NotOwner,
+    /// A server token that was revoked, or cleared by a transfer.
+    Revoked,
+    /// A server token issued for a different server.
+    WrongServer,
+    /// A user session token where only a server token is accepted.
+    UserToken,
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::NotFound => write!(f, "Server not found"),
+            AccessError::NotOwner => write!(f, "You do not own this server"),
+            AccessError::Revoked => write!(f, "Server token has been revoked"),
+            AccessError::WrongServer => write!(f, "Server token is not valid for this server"),
+            AccessError::UserToken => write!(f, "A server token is required; issue one for this server"),
+        }
+    }
+}
+
+/// `owner_id` is `None` when the server doesn't exist.
+pub fn check_owner(owner_id: Option<Uuid>, user_id: Uuid) -> Result<(), AccessError> {
+    match owner_id {
+        None => Err(AccessError::NotFound),
+        Some(owner_id) if owner_id != user_id => Err(AccessError::NotOwner),
+        Some(_) => Ok(()),
+    }
+}
+
+pub async fn owner_of(db: &PgPool, server_id: Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar::<_, Uuid>("SELECT owner_id FROM game_servers WHERE id = $1")
+        .bind(server_id)
+        .fetch_optional(db)
+        .await
+}
+
+/// Mutable listing fields; anything left out keeps its current value.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ServerUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub address: Option<String>,
+    pub port: Option<i32>,
+    pub max_players: Option<i32>,
+    pub game_mode: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl ServerUpdate {
+    pub fn validate(&self) -> Result<(), String> {
+        let changes = [
+            self.name.is_some(),
+            self.description.is_some(),
+            self.address.is_some(),
+            self.port.is_some(),
+            self.max_players.is_some(),
+            self.game_mode.is_some(),
+            self.tags.is_some(),
+        ];
+        if !changes.contains(&true) {
+            return Err("No fields to update".to_string());
+        }
+        if let Some(name) = &self.name {
+            if name.trim().is_empty() || name.len() > MAX_NAME_LEN {
+                return Err(format!("name must be 1-{} characters", MAX_NAME_LEN));
+            }
+        }
+        if matches!(&self.address, Some(address) if address.trim().is_empty()) {
+            return Err("address cannot be empty".to_string());
+        }
+        if matches!(self.port, Some(port) if !(1..=65535).contains(&port)) {
+            return Err("port must be between 1 and 65535".to_string());
+        }
+        if matches!(self.max_players, Some(max) if max < 1) {
+            return Err("max_players must be at least 1".to_string());
+        }
+        if matches!(&self.tags, Some(tags) if tags.len() > MAX_TAGS) {
+            return Err(format!("At most {} tags are allowed", MAX_TAGS));
+        }
+        Ok(())
+    }
+}
+
+/// Applies a validated update; false if the server doesn't exist.
+pub async fn update(db: &PgPool, server_id: Uuid, update: &ServerUpdate) -> Result<bool, sqlx::Error> {
+    let tags = update.tags.as_ref().map(|tags| serde_json::to_value(tags).unwrap_or_default());
+    let result = sqlx::query(
+        "UPDATE game_servers SET
+            name = COALESCE($2, name),
+            description = COALESCE($3, description),
+            address = COALESCE($4, address),
+            port = COALESCE($5, port),
+            max_players = COALESCE($6, max_players),
+            game_mode = COALESCE($7, game_mode),
+            tags = COALESCE($8, tags),
+            current_players = LEAST(current_players, COALESCE($6, max_players))
+         WHERE id = $1"
+    )
+        .bind(server_id)
+        .bind(&update.name)
+        .bind(&update.description)
+        .bind(&update.address)
+        .bind(update.port)
+        .bind(update.max_players)
+        .bind(&update.game_mode)
+        .bind(tags)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Deletes the listing; metrics, tokens and pending transfers go with it.
+pub async fn delete(db: &PgPool, server_id: Uuid, owner_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM game_servers WHERE id = $1 AND owner_id = $2")
+        .bind(server_id)
+        .bind(owner_id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub fn is_server_token(token: &str) -> bool {
+    token.starts_with(SERVER_TOKEN_PREFIX)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRecord {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Heartbeats and deregistration only accept the listing's own server
+/// tokens, so the host never needs its owner's login.
+pub fn require_server_token(token: &str) -> Result<(), AccessError> {
+    if is_server_token(token) {
+        Ok(())
+    } else {
+        Err(AccessError::UserToken)
+    }
+}
+
+pub fn authorize_server_token(server_id: Uuid, token: &TokenRecord) -> Result<(), AccessError> {
+    if token.server_id != server_id {
+        Err(AccessError::WrongServer)
+    } else if token.revoked_at.is_some() {
+        Err(AccessError::Revoked)
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenSummary {
+    pub id: Uuid,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Creates a token for `server_id`. The raw token is returned only here;
+/// just its hash is stored.
+pub async fn issue_token(db: &PgPool, server_id: Uuid, created_by: Uuid, label: Option<&str>) -> Result<(TokenSummary, String), sqlx::Error> {
+    let token = format!("{}{}", SERVER_TOKEN_PREFIX, generate_token());
+    let summary = TokenSummary {
+        id: Uuid::new_v4(),
+        label: label.map(str::to_string),
+        created_at: Utc::now(),
+        last_used_at: None,
+        revoked_at: None,
+    };
+    sqlx::query(
+        "INSERT INTO server_tokens (id, server_id, token_hash, label, created_by, created_at) VALUES ($1, $2, $3, $4, $5, $6)"
+    )
+        .bind(summary.id)
+        .bind(server_id)
+        .bind(hash_token(&token))
+        .bind(&summary.label)
+        .bind(created_by)
+        .bind(summary.created_at)
+        .execute(db)
+        .await?;
+    Ok((summary, token))
+}
+
+pub async fn list_tokens(db: &PgPool, server_id: Uuid) -> Result<Vec<TokenSummary>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>, Option<DateTime<Utc>>)>(
+        "SELECT id, label, created_at, last_used_at, revoked_at FROM server_tokens WHERE server_id = $1 ORDER BY created_at DESC"
+    )
+        .bind(server_id)
+        .fetch_all(db)
+        .await?;
+    Ok(rows.into_iter()
+        .map(|(id, label, created_at, last_used_at, revoked_at)| TokenSummary { id, label, created_at, last_used_at, revoked_at })
+        .collect())
+}
+
+/// False if the token doesn't belong to the server or was already revoked.
+pub async fn revoke_token(db: &PgPool, server_id: Uuid, token_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE server_tokens SET revoked_at = NOW() WHERE id = $1 AND server_id = $2 AND revoked_at IS NULL")
+        .bind(token_id)
+        .bind(server_id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Looks a raw token up by hash, revoked ones included so callers can say why
+/// it was refused.
+pub async fn find_token(db: &PgPool, token: &str) -> Result<Option<TokenRecord>, sqlx::Error> {
+    let row = sqlx::query_as::<_, (Uuid, Uuid, Option<DateTime<Utc>>)>(
+        "SELECT id, server_id, revoked_at FROM server_tokens WHERE token_hash = $1"
+    )
+        .bind(hash_token(token))
+        .fetch_optional(db)
+        .await?;
+    Ok(row.map(|(id, server_id, revoked_at)| TokenRecord { id, server_id, revoked_at }))
+}
+
+pub async fn touch_token(db: &PgPool, token_id: Uuid, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE server_tokens SET last_used_at = $2 WHERE id = $1")
+        .bind(token_id)
+        .bind(now)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingTransfer {
+    pub server_id: Uuid,
+    pub from_user_id: Uuid,
+    pub to_user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PendingTransfer {
+    pub fn new(server_id: Uuid, from_user_id: Uuid, to_user_id: Uuid, now: DateTime<Utc>) -> Self {
+        Self {
+            server_id,
+            from_user_id,
+            to_user_id,
+            created_at: now,
+            expires_at: now + ChronoDuration::days(TRANSFER_TTL_DAYS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferError {
+    NoPendingTransfer,
+    NotRecipient,
+    Expired,
+    /// The server changed hands or was deleted after the offer was made.
+    OwnerChanged,
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferError::NoPendingTransfer => write!(f, "No pending transfer for this server"),
+            TransferError::NotRecipient => write!(f, "This transfer was offered to someone else"),
+            TransferError::Expired => write!(f, "Transfer offer has expired"),
+            TransferError::OwnerChanged => write!(f, "Server owner changed since the offer was made"),
+        }
+    }
+}
+
+pub fn check_acceptance(transfer: Option<&PendingTransfer>, user_id: Uuid, now: DateTime<Utc>) -> Result<(), TransferError> {
+    let transfer = transfer.ok_or(TransferError::NoPendingTransfer)?;
+    if transfer.to_user_id != user_id {
+        return Err(TransferError::NotRecipient);
+    }
+    if now >= transfer.expires_at {
+        return Err(TransferError::Expired);
+    }
+    Ok(())
+}
+
+/// Records an offer, replacing any earlier one for the same server.
+pub async fn offer_transfer(db: &PgPool, transfer: &PendingTransfer) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO server_transfers (server_id, from_user_id, to_user_id, created_at, expires_at) VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (server_id) DO UPDATE SET from_user_id = $2, to_user_id = $3, created_at = $4, expires_at = $5"
+    )
+        .bind(transfer.server_id)
+        .bind(transfer.from_user_id)
+        .bind(transfer.to_user_id)
+        .bind(transfer.created_at)
+        .bind(transfer.expires_at)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn pending_transfer(db: &PgPool, server_id: Uuid) -> Result<Option<PendingTransfer>, sqlx::Error> {
+    let row = sqlx::query_as::<_, (Uuid, Uuid, Uuid, DateTime<Utc>, DateTime<Utc>)>(
+        "SELECT server_id, from_user_id, to_user_id, created_at, expires_at FROM server_transfers WHERE server_id = $1"
+    )
+        .bind(server_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(row.map(|(server_id, from_user_id, to_user_id, created_at, expires_at)| PendingTransfer {
+        server_id,
+        from_user_id,
+        to_user_id,
+        created_at,
+        expires_at,
+    }))
+}
+
+/// Withdrawn by the owner or declined by the recipient.
+pub async fn cancel_transfer(db: &PgPool, server_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM server_transfers WHERE server_id = $1 AND (from_user_id = $2 OR to_user_id = $2)")
+        .bind(server_id)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Hands the server to the recipient and revokes every outstanding token, so
+/// the previous owner's hosts can no longer heartbeat for it. Returns how
+/// many tokens were revoked, or `None` if the owner changed since the offer.
+pub async fn complete_transfer(db: &PgPool, transfer: &PendingTransfer, now: DateTime<Utc>) -> Result<Option<u64>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let moved = sqlx::query("UPDATE game_servers SET owner_id = $3 WHERE id = $1 AND owner_id = $2")
+        .bind(transfer.server_id)
+        .bind(transfer.from_user_id)
+        .bind(transfer.to_user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    sqlx::query("DELETE FROM server_transfers WHERE server_id = $1")
+        .bind(transfer.server_id)
+        .execute(&mut *tx)
+        .await?;
+    if moved == 0 {
+        tx.commit().await?;
+        return Ok(None);
+    }
+    let revoked = sqlx::query("UPDATE server_tokens SET revoked_at = $2 WHERE server_id = $1 AND revoked_at IS NULL")
+        .bind(transfer.server_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    Ok(Some(revoked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2024-05-{:02}T12:00:00Z", day)).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_non_owners_are_rejected() {
+        let (owner, stranger) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(check_owner(Some(owner), owner), Ok(()));
+        assert_eq!(check_owner(Some(owner), stranger), Err(AccessError::NotOwner));
+        assert_eq!(check_owner(None, owner), Err(AccessError::NotFound));
+    }
+
+    #[test]
+    fn test_heartbeat_with_revoked_token_fails() {
+        let server = Uuid::new_v4();
+        let token = TokenRecord { id: Uuid::new_v4(), server_id: server, revoked_at: None };
+        assert_eq!(authorize_server_token(server, &token), Ok(()));
+
+        let revoked = TokenRecord { revoked_at: Some(at(2)), ..token.clone() };
+        assert_eq!(authorize_server_token(server, &revoked), Err(AccessError::Revoked));
+
+        assert_eq!(authorize_server_token(Uuid::new_v4(), &token), Err(AccessError::WrongServer));
+    }
+
+    #[test]
+    fn test_heartbeat_needs_a_server_token() {
+        let raw = format!("{}{}", SERVER_TOKEN_PREFIX, generate_token());
+        assert_eq!(require_server_token(&raw), Ok(()));
+        assert_eq!(require_server_token(&generate_token()), Err(AccessError::UserToken));
+        assert_eq!(require_server_token(""), Err(AccessError::UserToken));
+    }
+
+    #[test]
+    fn test_only_the_recipient_accepts_before_expiry() {
+        let (owner, recipient, server) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let transfer = PendingTransfer::new(server, owner, recipient, at(1));
+        assert_eq!(transfer.expires_at, at(8));
+
+        assert_eq!(check_acceptance(Some(&transfer), recipient, at(7)), Ok(()));
+        assert_eq!(check_acceptance(Some(&transfer), owner, at(2)), Err(TransferError::NotRecipient));
+        assert_eq!(check_acceptance(Some(&transfer), recipient, at(8)), Err(TransferError::Expired));
+        assert_eq!(check_acceptance(None, recipient, at(2)), Err(TransferError::NoPendingTransfer));
+    }
+
+    #[test]
+    fn test_update_validation() {
+        assert!(ServerUpdate::default().validate().is_err());
+        assert!(ServerUpdate { name: Some("Skyblock".into()), ..Default::default() }.validate().is_ok());
+        assert!(ServerUpdate { name: Some("  ".into()), ..Default::default() }.validate().is_err());
+        assert!(ServerUpdate { port: Some(70000), ..Default::default() }.validate().is_err());
+        assert!(ServerUpdate { max_players: Some(0), ..Default::default() }.validate().is_err());
+        assert!(ServerUpdate { tags: Some(vec![]), ..Default::default() }.validate().is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Longest `device_info` kept from a client's User-Agent.
+const MAX_DEVICE_INFO: usize = 255;
+
+/// A signed-in device, as listed to the account owner.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    /// Whether this is the session asking.
+    pub current: bool,
+}
+
+/// What a session records about the client from its User-Agent header.
+pub fn device_info(user_agent: Option<&str>) -> Option<String> {
+    let user_agent = user_agent?.trim();
+    if user_agent.is_empty() {
+        return None;
+    }
+    Some(user_agent.chars().take(MAX_DEVICE_INFO).collect())
+}
+
+/// Stores a new session for `user_id` under `token_hash`.
+pub async fn create(
+    db: &PgPool,
+    user_id: Uuid,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+    device_info: Option<&str>,
+    ip_address: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO user_sessions (id, user_id, token_hash, expires_at, created_at, device_info, ip_address, last_used_at)
+         VALUES ($1, $2, $3, $4, NOW(), $5, $6, NOW())"
+    )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .bind(device_info)
+        .bind(ip_address)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Marks the session as used, at most once a minute.
+pub async fn touch(db: &PgPool, token_hash: &str) {
+    let _ = sqlx::query(
+        "UPDATE user_sessions SET last_used_at = NOW()
+         WHERE token_hash = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')"
+    )
+        .bind(token_hash)
+        .execute(db)
+        .await;
+}
+
+/// The user's unexpired sessions, most recently used first.
+pub async fn list(db: &PgPool, user_id: Uuid, current_token_hash: &str) -> Result<Vec<SessionInfo>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid, Option<String>, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>, DateTime<Utc>, bool)>(
+        "SELECT id, device_info, ip_address, created_at, last_used_at, expires_at, token_hash = $2
+         FROM user_sessions
+         WHERE user_id = $1 AND expires_at > NOW()
+         ORDER BY last_used_at DESC NULLS LAST, created_at DESC"
+    )
+        .bind(user_id)
+        .bind(current_token_hash)
+        .fetch_all(db)
+        .await?;
+
+    Ok(rows.into_iter()
+        .map(|(id, device_info, ip_address, created_at, last_used_at, expires_at, current)| SessionInfo {
+            id, device_info, ip_address, created_at, last_used_at, expires_at, current,
+        })
+        .collect())
+}
+
+/// Signs out one of the user's sessions; false when they have no such session.
+pub async fn revoke(db: &PgPool, user_id: Uuid, session_id: Uuid) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query("DELETE FROM user_sessions WHERE id = $1 AND user_id = $2")
+        .bind(session_id)
+        .bind(user_id)
+        .execute(db)
+        .await?
+        .rows_affected();
+    Ok(deleted > 0)
+}
+
+/// Signs out every session of the user but the one under `keep_token_hash`.
+pub async fn revoke_others<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    user_id: Uuid,
+    keep_token_hash: &str,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query("DELETE FROM user_sessions WHERE user_id = $1 AND token_hash <> $2")
+        .bind(user_id)
+        .bind(keep_token_hash)
+        .execute(executor)
+        .await?
+        .rows_affected();
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_info_is_trimmed_and_capped() {
+        assert_eq!(device_info(None), None);
+        assert_eq!(device_info(Some("  ")), None);
+        assert_eq!(device_info(Some(" YellowTale/1.4 (Windows) ")).as_deref(), Some("YellowTale/1.4 (Windows)"));
+
+        let long = "é".repeat(MAX_DEVICE_INFO + 10);
+        assert_eq!(device_info(Some(&long)).unwrap().chars().count(), MAX_DEVICE_INFO);
+    }
+}
@@ -0,0 +1,147 @@
+//! The one place social surfaces ask whether two users may reach each
+//! other. A block in either direction hides both users from one another:
+//! friend requests, search, party invites, public profiles, presence and
+//! notifications all go through here.
+
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Returned wherever a block must not be revealed. It matches the message
+/// for a user that does not exist.
+pub const HIDDEN: &str = "User not found";
+
+/// Everyone with a block in either direction against one user
+#[derive(Debug, Clone, Default)]
+pub struct BlockSet {
+    others: HashSet<Uuid>,
+}
+
+impl BlockSet {
+    /// Builds the set for `user_id` from `(blocker_id, blocked_id)` rows
+    pub fn from_pairs(user_id: Uuid, pairs: &[(Uuid, Uuid)]) -> Self {
+        let others = pairs.iter()
+            .filter_map(|&(blocker, blocked)| {
+                if blocker == user_id {
+                    Some(blocked)
+                } else if blocked == user_id {
+                    Some(blocker)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Self { others }
+    }
+
+    pub fn allows(&self, other: Uuid) -> bool {
+        !self.others.contains(&other)
+    }
+
+    pub fn ids(&self) -> &HashSet<Uuid> {
+        &self.others
+    }
+
+    /// Keeps only the items whose user is not blocked
+    pub fn visible<T>(&self, items: Vec<T>, user_of: impl Fn(&T) -> Uuid) -> Vec<T> {
+        items.into_iter().filter(|item| self.allows(user_of(item))).collect()
+    }
+}
+
+/// Loads every block touching `user_id`
+pub async fn block_set(db: &PgPool, user_id: Uuid) -> Result<BlockSet, sqlx::Error> {
+    let pairs = sqlx::query_as::<_, (Uuid, Uuid)>(
+        "SELECT blocker_id, blocked_id FROM blocks WHERE blocker_id = $1 OR blocked_id = $1"
+    )
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+    Ok(BlockSet::from_pairs(user_id, &pairs))
+}
+
+/// Block rows between `a` and `b`, in either direction
+pub async fn pairs_between(db: &PgPool, a: Uuid, b: Uuid) -> Result<Vec<(Uuid, Uuid)>, sqlx::Error> {
+    sqlx::query_as::<_, (Uuid, Uuid)>(
+        "SELECT blocker_id, blocked_id FROM blocks
+         WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1)"
+    )
+        .bind(a)
+        .bind(b)
+        .fetch_all(db)
+        .await
+}
+
+/// Whether `a` and `b` may interact. A lookup failure counts as blocked so
+/// an outage never leaks someone who asked to be hidden.
+pub async fn allows(db: &PgPool, a: Uuid, b: Uuid) -> bool {
+    match pairs_between(db, a, b).await {
+        Ok(pairs) => BlockSet::from_pairs(a, &pairs).allows(b),
+        Err(e) => {
+            tracing::error!("Failed to check blocks between {} and {}: {}", a, b, e);
+            false
+        }
+    }
+}
+
+/// Those of `candidates` with a block in either direction against `user_id`
+pub async fn blocked_among(db: &PgPool, user_id: Uuid, candidates: &[Uuid]) -> Result<HashSet<Uuid>, sqlx::Error> {
+    if candidates.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let pairs = sqlx::query_as::<_, (Uuid, Uuid)>(
+        "SELECT blocker_id, blocked_id FROM blocks
+         WHERE (blocker_id = $1 AND blocked_id = ANY($2)) OR (blocked_id = $1 AND blocker_id = ANY($2))"
+    )
+        .bind(user_id)
+        .bind(candidates)
+        .fetch_all(db)
+        .await?;
+    Ok(BlockSet::from_pairs(user_id, &pairs).others)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::NewNotification;
+    use crate::user_search::{self, UserResult};
+
+    fn user(id: Uuid, username: &str) -> UserResult {
+        UserResult { id, username: username.to_string(), display_name: None, avatar_url: None, last_seen: None }
+    }
+
+    #[test]
+    fn test_block_in_either_direction_hides_both_users_on_every_surface() {
+        let (me, them, bystander) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        for pairs in [vec![(me, them)], vec![(them, me)]] {
+            for (viewer, other) in [(me, them), (them, me)] {
+                let blocks = BlockSet::from_pairs(viewer, &pairs);
+
+                // Friend requests, party invites and public cosmetics refuse
+                assert!(!blocks.allows(other));
+                assert!(blocks.allows(bystander));
+
+                // Search drops them
+                let candidates = vec![user(other, "anna"), user(bystander, "annabel")];
+                let page = user_search::rank_page("anna", candidates, blocks.ids(), None, 10);
+                assert_eq!(page.users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![bystander]);
+
+                // Presence fans out to everyone else
+                assert_eq!(blocks.visible(vec![other, bystander], |id| *id), vec![bystander]);
+
+                // Notifications are dropped
+                let notification = NewNotification::friend_request(other, "them", viewer);
+                assert!(!notification.deliverable(&pairs));
+            }
+        }
+    }
+
+    #[test]
+    fn test_unrelated_pairs_are_ignored() {
+        let (me, a, b) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let blocks = BlockSet::from_pairs(me, &[(a, b), (me, a)]);
+        assert!(!blocks.allows(a));
+        assert!(blocks.allows(b));
+        assert_eq!(blocks.ids().len(), 1);
+    }
+}
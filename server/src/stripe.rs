@@ -61,6 +61,7 @@ pub async fn get_stripe_credentials() -> Result<StripeCredentials, String> {
 
 pub async fn create_checkout_session(
     customer_email: &str,
+    client_reference_id: &str,
     price_id: &str,
     success_url: &str,
     cancel_url: &str,
@@ -70,6 +71,7 @@ pub async fn create_checkout_session(
     let client = reqwest::Client::new();
     let params = [
         ("customer_email", customer_email),
+        ("client_reference_id", client_reference_id),
         ("line_items[0][price]", price_id),
         ("line_items[0][quantity]", "1"),
         ("mode", "subscription"),
@@ -272,41 +274,3 @@ pub async fn create_marketplace_checkout(
     
     Ok(CheckoutResult { url, session_id })
 }
-
-#[allow(dead_code)]
-pub async fn update_subscription_from_webhook(
-    db: &PgPool,
-    customer_id: &str,
-    subscription_id: &str,
-    status: &str,
-    current_period_end: i64,
-) -> Result<(), String> {
-    let tier = if status == "active" || status == "trialing" {
-        "premium"
-    } else {
-        "free"
-    };
-    
-    let period_end = chrono::DateTime::from_timestamp(current_period_end, 0)
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    
-    sqlx::query(
-        "UPDATE subscriptions SET 
-            tier = $1, 
-            status = $2, 
-            stripe_subscription_id = $3, 
-            current_period_end = $4,
-            updated_at = NOW()
-         WHERE stripe_customer_id = $5"
-    )
-        .bind(tier)
-        .bind(status)
-        .bind(subscription_id)
-        .bind(period_end)
-        .bind(customer_id)
-        .execute(db)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?;
-    
-    Ok(())
-}
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Matches considered per search; pages past this many results are not
+/// reachable, which keeps a one-letter query from scanning every user.
+pub const MAX_CANDIDATES: i64 = 500;
+pub const DEFAULT_LIMIT: usize = 20;
+pub const MAX_LIMIT: usize = 50;
+
+/// Whether search only returns the requester's friends and their friends.
+pub fn friends_of_friends_only() -> bool {
+    std::env::var("USER_SEARCH_FRIENDS_OF_FRIENDS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchRank {
+    Exact,
+    Prefix,
+    Substring,
+}
+
+impl MatchRank {
+    /// Best match of `query` against any of `names`, ignoring case
+    pub fn of(query: &str, names: &[Option<&str>]) -> Option<Self> {
+        let query = query.to_lowercase();
+        names.iter()
+            .flatten()
+            .filter_map(|name| {
+                let name = name.to_lowercase();
+                if name == query {
+                    Some(Self::Exact)
+                } else if name.starts_with(&query) {
+                    Some(Self::Prefix)
+                } else if name.contains(&query) {
+                    Some(Self::Substring)
+                } else {
+                    None
+                }
+            })
+            .min()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserResult {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Position after the last result of a page. Results are ordered by match
+/// rank, then most recently seen (never-seen last), then id, so the key is
+/// unique and a cursor stays valid while other users come and go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SearchCursor {
+    rank: MatchRank,
+    last_seen: Reverse<Option<i64>>,
+    id: Uuid,
+}
+
+impl SearchCursor {
+    fn of(rank: MatchRank, user: &UserResult) -> Self {
+        Self {
+            rank,
+            last_seen: Reverse(user.last_seen.map(|t| t.timestamp_micros())),
+            id: user.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let last_seen = self.last_seen.0.map(|t| t.to_string()).unwrap_or_default();
+        format!("{}.{}.{}", self.rank as u8, last_seen, self.id)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.splitn(3, '.');
+        let rank = match parts.next()? {
+            "0" => MatchRank::Exact,
+            "1" => MatchRank::Prefix,
+            "2" => MatchRank::Substring,
+            _ => return None,
+        };
+        let last_seen = match parts.next()? {
+            "" => None,
+            micros => Some(micros.parse().ok()?),
+        };
+        let id = parts.next()?.parse().ok()?;
+        Some(Self { rank, last_seen: Reverse(last_seen), id })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    pub users: Vec<UserResult>,
+    pub next_cursor: Option<String>,
+}
+
+/// Rank the candidates for `query`, drop anyone in `blocked` and return the
+/// page that follows `after`
+pub fn rank_page(query: &str, candidates: Vec<UserResult>, blocked: &HashSet<Uuid>, after: Option<&SearchCursor>, limit: usize) -> SearchPage {
+    let mut ranked: Vec<(SearchCursor, UserResult)> = candidates.into_iter()
+        .filter(|user| !blocked.contains(&user.id))
+        .filter_map(|user| {
+            let rank = MatchRank::of(query, &[Some(&user.username), user.display_name.as_deref()])?;
+            Some((SearchCursor::of(rank, &user), user))
+        })
+        .filter(|(key, _)| after.is_none_or(|after| key > after))
+        .collect();
+    ranked.sort_by_key(|(key, _)| *key);
+
+    let next_cursor = (ranked.len() > limit).then(|| ranked[limit - 1].0.encode());
+    ranked.truncate(limit);
+    SearchPage {
+        users: ranked.into_iter().map(|(_, user)| user).collect(),
+        next_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn user(username: &str, seen_hours_ago: Option<i64>) -> UserResult {
+        UserResult {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            display_name: None,
+            avatar_url: None,
+            last_seen: seen_hours_ago.map(|h| Utc::now() - Duration::hours(h)),
+        }
+    }
+
+    fn names(page: &SearchPage) -> Vec<&str> {
+        page.users.iter().map(|u| u.username.as_str()).collect()
+    }
+
+    #[test]
+    fn test_exact_then_prefix_then_substring_then_recency() {
+        let candidates = vec![
+            user("xXanna", Some(1)),
+            user("annabel", Some(48)),
+            user("Anna", None),
+            user("annika_not", None),
+            user("anna_b", Some(2)),
+            user("joanna", None),
+        ];
+        let page = rank_page("anna", candidates, &HashSet::new(), None, 10);
+        assert_eq!(names(&page), vec!["Anna", "anna_b", "annabel", "xXanna", "joanna"]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_blocked_users_are_excluded() {
+        let candidates = vec![user("anna", Some(1)), user("annabel", Some(2)), user("hanna", None)];
+        let blocked: HashSet<Uuid> = [candidates[0].id, candidates[2].id].into();
+        let page = rank_page("anna", candidates, &blocked, None, 10);
+        assert_eq!(names(&page), vec!["annabel"]);
+    }
+
+    #[test]
+    fn test_cursor_pages_without_gaps_or_repeats() {
+        let candidates: Vec<UserResult> = (0..5)
+            .map(|i| user(&format!("anna{}", i), if i % 2 == 0 { Some(i) } else { None }))
+            .collect();
+        let all = names(&rank_page("anna", candidates.clone(), &HashSet::new(), None, 10))
+            .into_iter().map(String::from).collect::<Vec<_>>();
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let after = cursor.as_deref().map(|c| SearchCursor::decode(c).unwrap());
+            let page = rank_page("anna", candidates.clone(), &HashSet::new(), after.as_ref(), 2);
+            seen.extend(names(&page).into_iter().map(String::from));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, all);
+        assert!(SearchCursor::decode("9..nope").is_none());
+    }
+}
@@ -882,6 +882,8 @@ interface MarketplaceItem {
   is_featured?: boolean;
 }
 
+const MARKETPLACE_PER_PAGE = 48;
+
 function Marketplace() {
   const [items, setItems] = useState<MarketplaceItem[]>([]);
   const [loading, setLoading] = useState(true);
@@ -890,26 +892,36 @@ function Marketplace() {
   const [priceFilter, setPriceFilter] = useState<string>('all');
   const [sortBy, setSortBy] = useState<string>('popular');
   const [viewMode, setViewMode] = useState<'grid' | 'list'>('grid');
+  const [total, setTotal] = useState(0);
+  const [page, setPage] = useState(1);
+  const [nextCursor, setNextCursor] = useState<string | null>(null);
   const { user } = useAuth();
 
   useEffect(() => {
     fetchItems();
   }, [category, priceFilter, sortBy]);
 
-  const fetchItems = async () => {
-    setLoading(true);
+  const fetchItems = async (more = false) => {
+    if (!more) setLoading(true);
     try {
+      const nextPage = more ? page + 1 : 1;
       const params = new URLSearchParams();
       if (category !== 'all') params.append('category', category);
       if (priceFilter === 'free') params.append('price', 'free');
       if (priceFilter === 'paid') params.append('price', 'paid');
       params.append('sort', sortBy);
       if (searchQuery) params.append('q', searchQuery);
+      params.append('page', String(nextPage));
+      params.append('per_page', String(MARKETPLACE_PER_PAGE));
+      if (more && nextCursor) params.append('cursor', nextCursor);
 
       const res = await fetch(`${API_URL}/api/v1/marketplace/items?${params}`);
       const data = await res.json();
       if (data.success) {
-        setItems(data.data.items);
+        setItems(prev => more ? [...prev, ...data.data.items] : data.data.items);
+        setTotal(data.data.total);
+        setPage(nextPage);
+        setNextCursor(data.data.next_cursor ?? null);
       }
     } catch (e) {
       setItems(getMockItems());
@@ -1038,6 +1050,11 @@ function Marketplace() {
                 <MarketplaceCard key={item.id} item={item} listView={viewMode === 'list'} />
               ))}
             </div>
+            {items.length < total && (
+              <button className="btn btn-secondary load-more" onClick={() => fetchItems(true)}>
+                Load more ({items.length}/{total})
+              </button>
+            )}
           </section>
 
           {filteredItems.length === 0 && (
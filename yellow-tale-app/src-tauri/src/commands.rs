@@ -8,7 +8,7 @@ use crate::optimizer::{
     asset_cache::CacheStats,
     world_hosting::{WorldHostConfig, NatInfo, HostingStatus},
     save_snapshot::{Snapshot, SnapshotConfig},
-    mod_resolver::{ModInfo, ContentProfile, ResolutionResult},
+    mod_resolver::{ModInfo, ContentProfile, ResolutionResult, ProfileActivation},
 };
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
@@ -16,9 +16,12 @@ use std::sync::Arc;
 use tauri::State;
 use tokio::sync::RwLock;
 use uuid::Uuid;
+use yellow_tale::core::{install::RejectedCandidate, java::java_executable};
+use yellow_tale_ipc_client::{GetPingHistory, IpcClient, IpcClientExt, LaunchConfig, ListJavaRuntimes, SetGamePath, SetJavaPath};
 
 type AppStateHandle = Arc<RwLock<AppState>>;
 type OptimizerHandle = Arc<OptimizationService>;
+type IpcClientHandle = Arc<dyn IpcClient>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemInfo {
@@ -37,10 +40,25 @@ pub struct ServerInfo {
     pub name: String,
     pub address: String,
     pub port: u16,
+    #[serde(alias = "current_players")]
     pub player_count: u32,
     pub max_players: u32,
+    /// Average of the latest ping the core measured, 0 if never pinged
+    #[serde(default)]
     pub ping_ms: u32,
+    #[serde(default)]
     pub version: String,
+    #[serde(default)]
+    pub favorited: bool,
+}
+
+/// One page of the server directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerPage {
+    pub servers: Vec<ServerInfo>,
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,222 +112,122 @@ pub async fn get_system_info() -> Result<SystemInfo, String> {
 #[tauri::command]
 pub async fn detect_hytale_installation(
     state: State<'_, AppStateHandle>,
+    ipc: State<'_, IpcClientHandle>,
     custom_path: Option<String>,
 ) -> Result<HytaleInstallation, String> {
-    let base_path = if let Some(path) = custom_path {
-        PathBuf::from(path)
-    } else {
-        #[cfg(target_os = "windows")]
-        {
-            if let Some(appdata) = dirs::data_dir() {
-                appdata.join("Hytale")
-            } else {
-                return Err("Could not find AppData directory".to_string());
-            }
-        }
-        #[cfg(target_os = "macos")]
-        {
-            if let Some(home) = dirs::home_dir() {
-                home.join("Library").join("Application Support").join("Hytale")
-            } else {
-                return Err("Could not find home directory".to_string());
-            }
-        }
-        #[cfg(target_os = "linux")]
-        {
-            if let Some(data) = dirs::data_dir() {
-                data.join("Hytale")
-            } else {
-                return Err("Could not find data directory".to_string());
+    let install = match custom_path {
+        Some(path) => ipc.set_game_path(SetGamePath { path: PathBuf::from(path) }).await.map_err(|e| e.to_string())?,
+        None => {
+            let report = ipc.detect_installations().await.map_err(|e| e.to_string())?;
+            match report.installs.into_iter().next() {
+                Some(install) => install,
+                None => return Err(not_found_message(&report.rejected)),
             }
         }
     };
 
-    let install_path = base_path.join("install").join("release").join("package").join("game").join("latest");
-    let client_path = install_path.join("Client");
-    let server_path = install_path.join("Server");
-    let assets_path = install_path.join("Assets.zip");
+    let game_dir = install.path.clone();
+    let base_path = install.root.clone().unwrap_or_else(|| game_dir.clone());
     let user_data_path = base_path.join("UserData");
-    let packs_path = user_data_path.join("Packs");
-
-    let valid = client_path.exists() || assets_path.exists();
-
     let installation = HytaleInstallation {
         path: base_path.to_string_lossy().to_string(),
-        client_path: client_path.to_string_lossy().to_string(),
-        server_path: server_path.to_string_lossy().to_string(),
-        assets_path: assets_path.to_string_lossy().to_string(),
+        client_path: game_dir.join("Client").to_string_lossy().to_string(),
+        server_path: game_dir.join("Server").to_string_lossy().to_string(),
+        assets_path: game_dir.join("Assets.zip").to_string_lossy().to_string(),
         user_data_path: user_data_path.to_string_lossy().to_string(),
-        packs_path: packs_path.to_string_lossy().to_string(),
-        version: None,
-        valid,
+        packs_path: user_data_path.join("Packs").to_string_lossy().to_string(),
+        version: install.version,
+        valid: true,
     };
 
-    if valid {
-        let mut s = state.write().await;
-        s.game_path = Some(base_path.to_string_lossy().to_string());
-    }
+    let mut s = state.write().await;
+    s.game_path = Some(game_dir.to_string_lossy().to_string());
 
     Ok(installation)
 }
 
+/// Say why nothing was found, naming folders that looked like the game
+fn not_found_message(rejected: &[RejectedCandidate]) -> String {
+    let mut message = "Hytale installation not found. Please set the game path in Settings.".to_string();
+    for candidate in rejected {
+        message.push_str(&format!(
+            "\n{} is missing {}",
+            candidate.path.display(),
+            candidate.missing.join(", "),
+        ));
+    }
+    message
+}
+
+/// Hytale ships against Java 25
+const HYTALE_JAVA_REQUIREMENT: &str = ">=25";
+
 #[tauri::command]
 pub async fn detect_java_installation(
     state: State<'_, AppStateHandle>,
+    ipc: State<'_, IpcClientHandle>,
     custom_path: Option<String>,
 ) -> Result<JavaInstallation, String> {
-    use std::process::Command;
-
-    let java_exe = if let Some(path) = custom_path {
-        PathBuf::from(path)
-    } else {
-        let possible_paths = get_java_search_paths();
-        
-        let mut found_path = None;
-        for path in possible_paths {
-            if path.exists() {
-                found_path = Some(path);
-                break;
-            }
+    let runtime = match custom_path {
+        Some(path) => {
+            let set = SetJavaPath {
+                path: PathBuf::from(path),
+                requirement: Some(HYTALE_JAVA_REQUIREMENT.to_string()),
+                profile_id: None,
+            };
+            ipc.set_java_path(set).await.map_err(|e| e.to_string())?.runtime
         }
-        
-        if let Some(path) = found_path {
-            path
-        } else {
-            if let Ok(output) = Command::new("java").arg("-version").output() {
-                if output.status.success() {
-                    PathBuf::from("java")
-                } else {
-                    return Ok(JavaInstallation {
-                        path: String::new(),
-                        version: String::new(),
-                        vendor: String::new(),
-                        is_temurin: false,
-                        is_java_25: false,
-                        valid: false,
-                    });
-                }
-            } else {
-                return Ok(JavaInstallation {
+        None => {
+            let list = ListJavaRuntimes { requirement: Some(HYTALE_JAVA_REQUIREMENT.to_string()) };
+            let found = ipc.list_java_runtimes(list).await.map_err(|e| e.to_string())?;
+            let suitable = found.suitable.unwrap_or_default();
+            match found.runtimes.into_iter().find(|runtime| suitable.contains(&runtime.home)) {
+                Some(runtime) => runtime,
+                None => return Ok(JavaInstallation {
                     path: String::new(),
                     version: String::new(),
                     vendor: String::new(),
                     is_temurin: false,
                     is_java_25: false,
                     valid: false,
-                });
+                }),
             }
         }
     };
 
-    let output = Command::new(&java_exe)
-        .arg("-version")
-        .output()
-        .map_err(|e| format!("Failed to run Java: {}", e))?;
-
-    let version_output = String::from_utf8_lossy(&output.stderr).to_string();
-    
-    let version = extract_java_version(&version_output);
-    let vendor = extract_java_vendor(&version_output);
-    let is_temurin = vendor.to_lowercase().contains("temurin") || 
-                     vendor.to_lowercase().contains("adoptium") ||
-                     version_output.to_lowercase().contains("temurin");
-    let is_java_25 = version.starts_with("25");
-    let valid = output.status.success();
-
+    let java_exe = java_executable(&runtime.home).to_string_lossy().to_string();
+    let vendor = runtime.vendor.to_lowercase();
     let installation = JavaInstallation {
-        path: java_exe.to_string_lossy().to_string(),
-        version,
-        vendor,
-        is_temurin,
-        is_java_25,
-        valid,
+        path: java_exe.clone(),
+        is_temurin: vendor.contains("temurin") || vendor.contains("adoptium"),
+        is_java_25: runtime.major_version == 25,
+        version: runtime.version,
+        vendor: runtime.vendor,
+        valid: true,
     };
 
-    if valid {
-        let mut s = state.write().await;
-        s.java_path = Some(java_exe.to_string_lossy().to_string());
-    }
+    let mut s = state.write().await;
+    s.java_path = Some(java_exe);
 
     Ok(installation)
 }
 
-fn get_java_search_paths() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    
-    #[cfg(target_os = "windows")]
-    {
-        if let Ok(program_files) = std::env::var("ProgramFiles") {
-            paths.push(PathBuf::from(&program_files).join("Eclipse Adoptium").join("jdk-25").join("bin").join("java.exe"));
-            paths.push(PathBuf::from(&program_files).join("Eclipse Adoptium").join("jdk-25-lts").join("bin").join("java.exe"));
-            paths.push(PathBuf::from(&program_files).join("Java").join("jdk-25").join("bin").join("java.exe"));
-            paths.push(PathBuf::from(&program_files).join("Temurin").join("jdk-25").join("bin").join("java.exe"));
-        }
-        if let Ok(java_home) = std::env::var("JAVA_HOME") {
-            paths.push(PathBuf::from(&java_home).join("bin").join("java.exe"));
-        }
-        if let Some(local) = dirs::data_local_dir() {
-            paths.push(local.join("Programs").join("Eclipse Adoptium").join("jdk-25").join("bin").join("java.exe"));
-        }
-    }
-    
-    #[cfg(target_os = "macos")]
-    {
-        paths.push(PathBuf::from("/Library/Java/JavaVirtualMachines/temurin-25/Contents/Home/bin/java"));
-        paths.push(PathBuf::from("/opt/homebrew/opt/openjdk@25/bin/java"));
-        if let Ok(java_home) = std::env::var("JAVA_HOME") {
-            paths.push(PathBuf::from(&java_home).join("bin").join("java"));
-        }
-    }
-    
-    #[cfg(target_os = "linux")]
-    {
-        paths.push(PathBuf::from("/usr/lib/jvm/temurin-25/bin/java"));
-        paths.push(PathBuf::from("/usr/lib/jvm/java-25-openjdk/bin/java"));
-        if let Ok(java_home) = std::env::var("JAVA_HOME") {
-            paths.push(PathBuf::from(&java_home).join("bin").join("java"));
-        }
-    }
-    
-    paths
-}
-
-fn extract_java_version(output: &str) -> String {
-    for line in output.lines() {
-        if line.contains("version") {
-            if let Some(start) = line.find('"') {
-                if let Some(end) = line[start + 1..].find('"') {
-                    return line[start + 1..start + 1 + end].to_string();
-                }
-            }
-        }
-    }
-    String::new()
-}
-
-fn extract_java_vendor(output: &str) -> String {
-    for line in output.lines() {
-        if line.contains("Runtime") || line.contains("OpenJDK") || line.contains("Java(TM)") {
-            return line.trim().to_string();
-        }
-    }
-    output.lines().next().unwrap_or("Unknown").to_string()
-}
-
 #[tauri::command]
 pub async fn set_game_path(
     state: State<'_, AppStateHandle>,
+    ipc: State<'_, IpcClientHandle>,
     path: String,
 ) -> Result<HytaleInstallation, String> {
-    detect_hytale_installation(state, Some(path)).await
+    detect_hytale_installation(state, ipc, Some(path)).await
 }
 
 #[tauri::command]
 pub async fn set_java_path(
     state: State<'_, AppStateHandle>,
+    ipc: State<'_, IpcClientHandle>,
     path: String,
 ) -> Result<JavaInstallation, String> {
-    detect_java_installation(state, Some(path)).await
+    detect_java_installation(state, ipc, Some(path)).await
 }
 
 #[tauri::command]
@@ -417,7 +335,12 @@ pub async fn get_user(state: State<'_, AppStateHandle>) -> Result<Option<User>,
 }
 
 #[tauri::command]
-pub async fn get_servers(state: State<'_, AppStateHandle>) -> Result<Vec<ServerInfo>, String> {
+pub async fn get_servers(
+    state: State<'_, AppStateHandle>,
+    ipc: State<'_, IpcClientHandle>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+) -> Result<ServerPage, String> {
     let api_url = {
         let s = state.read().await;
         s.api_url.clone()
@@ -426,6 +349,7 @@ pub async fn get_servers(state: State<'_, AppStateHandle>) -> Result<Vec<ServerI
     let client = reqwest::Client::new();
     let res = client
         .get(format!("{}/api/v1/servers", api_url))
+        .query(&[("page", page.unwrap_or(1)), ("per_page", per_page.unwrap_or(50))])
         .send()
         .await
         .map_err(|e| e.to_string())?;
@@ -433,22 +357,46 @@ pub async fn get_servers(state: State<'_, AppStateHandle>) -> Result<Vec<ServerI
     let data: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
     
     if !data["success"].as_bool().unwrap_or(false) {
-        return Ok(vec![]);
+        return Ok(ServerPage::default());
     }
     
-    let servers: Vec<ServerInfo> = serde_json::from_value(data["data"].clone())
+    let mut servers: ServerPage = serde_json::from_value(data["data"].clone())
         .unwrap_or_default();
+    merge_ping_data(ipc.inner().as_ref(), &mut servers).await;
     
     Ok(servers)
 }
 
+/// Marks favorites and fills in the latest measured ping for each server.
+/// The listing is still useful without them, so core errors are ignored.
+async fn merge_ping_data(ipc: &dyn IpcClient, page: &mut ServerPage) {
+    let favorites = match ipc.list_favorites().await {
+        Ok(list) => list.favorites,
+        Err(e) => {
+            tracing::debug!("Favorites unavailable: {}", e);
+            return;
+        }
+    };
+    
+    for server in &mut page.servers {
+        let server_id = server.id.to_string();
+        server.favorited = favorites.iter().any(|favorite| favorite.target.server_id == server_id);
+        let latest = match ipc.get_ping_history(GetPingHistory { server_id }).await {
+            Ok(history) => history.points.last().and_then(|point| point.avg_ms),
+            Err(_) => None,
+        };
+        if let Some(avg_ms) = latest {
+            server.ping_ms = avg_ms.round() as u32;
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn launch_game(
     state: State<'_, AppStateHandle>,
+    ipc: State<'_, IpcClientHandle>,
     server_address: Option<String>,
 ) -> Result<(), String> {
-    use std::process::Command;
-    
     let (game_path, java_path, performance) = {
         let s = state.read().await;
         
@@ -462,13 +410,7 @@ pub async fn launch_game(
     let game_path = game_path.ok_or("Hytale installation not found. Please set the game path in Settings.")?;
     let java_path = java_path.ok_or("Java installation not found. Please install Temurin Java 25 LTS.")?;
     
-    let client_path = PathBuf::from(&game_path)
-        .join("install")
-        .join("release")
-        .join("package")
-        .join("game")
-        .join("latest")
-        .join("Client");
+    let client_path = PathBuf::from(&game_path).join("Client");
     
     if !client_path.exists() {
         return Err(format!("Hytale client not found at: {:?}", client_path));
@@ -504,29 +446,20 @@ pub async fn launch_game(
         jvm_args.push(format!("-Dhytale.fpsLimit={}", fps));
     }
     
-    let mut cmd = Command::new(&java_path);
-    cmd.current_dir(&client_path);
-    
-    for arg in &jvm_args {
-        cmd.arg(arg);
-    }
-    
-    cmd.arg("-jar");
-    cmd.arg("HytaleClient.jar");
+    let mut config = LaunchConfig::new(&java_path)
+        .with_working_dir(&client_path)
+        .with_args(jvm_args)
+        .with_args(["-jar", "HytaleClient.jar"]);
     
     if let Some(server) = server_address {
-        cmd.arg("--server");
-        cmd.arg(server);
+        config = config.with_args(["--server".to_string(), server]);
     }
     
-    match cmd.spawn() {
-        Ok(_child) => {
-            let mut s = state.write().await;
-            s.game_running = true;
-            Ok(())
-        }
-        Err(e) => Err(format!("Failed to launch game: {}", e)),
-    }
+    ipc.launch_game(config).await.map_err(|e| e.to_string())?;
+    
+    let mut s = state.write().await;
+    s.game_running = true;
+    Ok(())
 }
 
 #[tauri::command]
@@ -596,18 +529,27 @@ pub async fn check_for_updates(state: State<'_, AppStateHandle>) -> Result<Updat
     
     let client = reqwest::Client::new();
     let res = client
-        .get(format!("{}/api/v1/releases", api_url))
+        .get(format!("{}/api/v1/releases/latest", api_url))
+        .query(&[
+            ("channel", "stable"),
+            ("platform", std::env::consts::OS),
+            ("current", env!("CARGO_PKG_VERSION")),
+        ])
         .send()
         .await
         .map_err(|e| e.to_string())?;
     
     let data: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    if !data["success"].as_bool().unwrap_or(false) {
+        return Err(data["error"].as_str().unwrap_or("Update check failed").to_string());
+    }
     
+    let release = &data["data"]["release"];
     Ok(UpdateInfo {
-        available: data["update_available"].as_bool().unwrap_or(false),
-        version: data["latest_version"].as_str().map(|s| s.to_string()),
-        changelog: data["changelog"].as_str().map(|s| s.to_string()),
-        download_url: data["download_url"].as_str().map(|s| s.to_string()),
+        available: data["data"]["update_available"].as_bool().unwrap_or(false),
+        version: release["version"].as_str().map(|s| s.to_string()),
+        changelog: release["changelog"].as_str().map(|s| s.to_string()),
+        download_url: release["url"].as_str().map(|s| s.to_string()),
     })
 }
 
@@ -1166,8 +1108,9 @@ pub async fn get_local_mod_profiles(
 pub async fn activate_mod_profile(
     optimizer: State<'_, OptimizerHandle>,
     profile_id: String,
-) -> Result<ResolutionResult, String> {
-    optimizer.mod_resolver().activate_profile(&profile_id)
+    dry_run: Option<bool>,
+) -> Result<ProfileActivation, String> {
+    optimizer.mod_resolver().activate_profile(&profile_id, dry_run.unwrap_or(false)).await
 }
 
 #[tauri::command]
@@ -1,7 +1,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use yellow_tale::core::{
+    config::AppConfig, netdiag::PingMonitor, CacheManager, DiagnosticsCollector, LauncherService, ProfileManager,
+    SessionOrchestrator,
+};
+use yellow_tale_ipc_client::{InProcessClient, IpcClient, IpcServer};
 
 mod commands;
 mod state;
@@ -13,12 +19,37 @@ use optimizer::OptimizationService;
 
 pub type AppStateHandle = Arc<RwLock<AppState>>;
 pub type OptimizerHandle = Arc<OptimizationService>;
+pub type IpcClientHandle = Arc<dyn IpcClient>;
+
+/// The core's IPC server, run in this process behind the typed client
+fn core_client() -> IpcClientHandle {
+    let data_dir = directories::ProjectDirs::from("com", "yellowtale", "YellowTale")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let config = AppConfig::default();
+    // The ping monitor forwards results and refreshes favorites on tasks of
+    // its own, so it's built inside the runtime
+    let server = tauri::async_runtime::block_on(async {
+        let ping_monitor = Arc::new(PingMonitor::load(&data_dir, &config.netdiag).await);
+        ping_monitor.clone().spawn_refresh();
+        IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(data_dir.join("profiles")),
+            CacheManager::new(data_dir.join("cache"), config.cache.max_size_bytes),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        )
+        .with_ping_monitor(ping_monitor)
+    });
+    Arc::new(InProcessClient::new(server))
+}
 
 fn main() {
     tracing_subscriber::fmt::init();
     
     let state = Arc::new(RwLock::new(AppState::new()));
     let optimizer = Arc::new(OptimizationService::new());
+    let ipc = core_client();
     
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -27,6 +58,7 @@ fn main() {
         .plugin(tauri_plugin_process::init())
         .manage(state)
         .manage(optimizer)
+        .manage(ipc)
         .invoke_handler(tauri::generate_handler![
             commands::get_system_info,
             commands::login,
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use parking_lot::RwLock;
 use tokio::fs;
+use yellow_tale::core::mods::activator::{ActivationReport, HttpModDownloader, ModProfileSpec, ProfileActivator, ProfileMod};
+use yellow_tale::core::mods::analyzer::{ConflictReport, ModAnalyzer, Severity};
+use yellow_tale::core::mods::resolver::{ModManifest, ModResolver};
+use yellow_tale::core::mods::scanner::{self, ModScanner};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModInfo {
@@ -13,10 +17,73 @@ pub struct ModInfo {
     pub description: Option<String>,
     pub dependencies: Vec<ModDependency>,
     pub conflicts: Vec<String>,
+    #[serde(default)]
+    pub provides: Vec<String>,
+    #[serde(default)]
+    pub overrides: Vec<String>,
+    #[serde(default)]
+    pub load_before: Vec<String>,
+    #[serde(default)]
+    pub load_after: Vec<String>,
     pub file_path: PathBuf,
     pub enabled: bool,
 }
 
+impl From<scanner::ModInfo> for ModInfo {
+    fn from(info: scanner::ModInfo) -> Self {
+        Self {
+            id: info.id,
+            name: info.name,
+            version: info.version.unwrap_or_else(|| "1.0.0".to_string()),
+            author: (!info.authors.is_empty()).then(|| info.authors.join(", ")),
+            description: info.description,
+            dependencies: info.dependencies.into_iter()
+                .map(|d| ModDependency {
+                    mod_id: d.mod_id,
+                    version_requirement: d.version_requirement,
+                    optional: d.optional,
+                })
+                .collect(),
+            conflicts: info.incompatibilities,
+            provides: info.provides,
+            overrides: info.overrides,
+            load_before: info.load_before,
+            load_after: info.load_after,
+            file_path: info.file_path,
+            enabled: info.enabled,
+        }
+    }
+}
+
+impl ModInfo {
+    /// This mod as the core analyzer sees it
+    fn to_scanned(&self) -> scanner::ModInfo {
+        scanner::ModInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            version: Some(self.version.clone()),
+            description: self.description.clone(),
+            authors: self.author.iter().cloned().collect(),
+            dependencies: self.dependencies.iter()
+                .map(|d| scanner::ModDependency {
+                    mod_id: d.mod_id.clone(),
+                    version_requirement: d.version_requirement.clone(),
+                    optional: d.optional,
+                })
+                .collect(),
+            incompatibilities: self.conflicts.clone(),
+            provides: self.provides.clone(),
+            overrides: self.overrides.clone(),
+            load_before: self.load_before.clone(),
+            load_after: self.load_after.clone(),
+            file_path: self.file_path.clone(),
+            hash: String::new(),
+            enabled: self.enabled,
+            source: scanner::MetadataSource::Manifest,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModDependency {
     pub mod_id: String,
@@ -38,6 +105,14 @@ pub struct ResolutionResult {
     pub missing_dependencies: Vec<MissingDependency>,
     pub conflicts: Vec<ModConflict>,
     pub warnings: Vec<String>,
+    /// Everything the analyzer found, with suggested resolutions
+    pub conflict_report: ConflictReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileActivation {
+    pub resolution: ResolutionResult,
+    pub report: ActivationReport,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,22 +136,32 @@ pub struct ContentProfile {
 
 pub struct ModDependencyResolver {
     mods: RwLock<HashMap<String, ModInfo>>,
+    /// Every mod from the last scan, duplicates of one id included
+    scanned: RwLock<Vec<ModInfo>>,
     profiles: RwLock<HashMap<String, ContentProfile>>,
     active_profile: RwLock<Option<String>>,
     mod_directory: RwLock<PathBuf>,
+    /// Where downloaded mods are kept, by hash
+    download_cache: PathBuf,
 }
 
 impl ModDependencyResolver {
     pub fn new() -> Self {
-        let mod_dir = directories::ProjectDirs::from("com", "yellowtale", "YellowTale")
+        let dirs = directories::ProjectDirs::from("com", "yellowtale", "YellowTale");
+        let mod_dir = dirs.as_ref()
             .map(|dirs| dirs.data_dir().join("mods"))
             .unwrap_or_else(|| PathBuf::from("mods"));
+        let download_cache = dirs.as_ref()
+            .map(|dirs| dirs.cache_dir().join("mods"))
+            .unwrap_or_else(|| PathBuf::from("cache/mods"));
         
         Self {
             mods: RwLock::new(HashMap::new()),
+            scanned: RwLock::new(Vec::new()),
             profiles: RwLock::new(HashMap::new()),
             active_profile: RwLock::new(None),
             mod_directory: RwLock::new(mod_dir),
+            download_cache,
         }
     }
     
@@ -93,22 +178,20 @@ impl ModDependencyResolver {
             return Ok(Vec::new());
         }
         
-        let mut found_mods = Vec::new();
+        let scanned = ModScanner::new(mod_dir.clone()).scan().await
+            .map_err(|e| format!("Failed to read mod directory: {}", e))?;
+        for unreadable in &scanned.unreadable {
+            tracing::warn!("Skipping unreadable mod {}: {}", unreadable.file_path.display(), unreadable.error);
+        }
+        let mut found_mods: Vec<ModInfo> = scanned.mods.into_iter().map(ModInfo::from).collect();
+        
         let mut entries = fs::read_dir(&mod_dir).await
             .map_err(|e| format!("Failed to read mod directory: {}", e))?;
         
         while let Ok(Some(entry)) = entries.next_entry().await {
             let path = entry.path();
             
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if ext == "jar" || ext == "zip" || ext == "ytmod" {
-                        if let Some(mod_info) = self.parse_mod_file(&path).await {
-                            found_mods.push(mod_info);
-                        }
-                    }
-                }
-            } else if path.is_dir() {
+            if path.is_dir() {
                 let manifest_path = path.join("mod.json");
                 if manifest_path.exists() {
                     if let Some(mod_info) = self.parse_mod_manifest(&manifest_path).await {
@@ -124,28 +207,13 @@ impl ModDependencyResolver {
                 mods.insert(mod_info.id.clone(), mod_info.clone());
             }
         }
+        *self.scanned.write() = found_mods.clone();
         
         tracing::info!("Scanned {} mods", found_mods.len());
         
         Ok(found_mods)
     }
     
-    async fn parse_mod_file(&self, path: &Path) -> Option<ModInfo> {
-        let file_name = path.file_stem()?.to_str()?;
-        
-        Some(ModInfo {
-            id: file_name.to_lowercase().replace(' ', "_"),
-            name: file_name.to_string(),
-            version: "1.0.0".to_string(),
-            author: None,
-            description: None,
-            dependencies: Vec::new(),
-            conflicts: Vec::new(),
-            file_path: path.to_path_buf(),
-            enabled: true,
-        })
-    }
-    
     async fn parse_mod_manifest(&self, manifest_path: &Path) -> Option<ModInfo> {
         let content = fs::read_to_string(manifest_path).await.ok()?;
         let json: serde_json::Value = serde_json::from_str(&content).ok()?;
@@ -186,6 +254,10 @@ impl ModDependencyResolver {
             description: json["description"].as_str().map(|s| s.to_string()),
             dependencies,
             conflicts,
+            provides: string_list(&json["provides"]),
+            overrides: string_list(&json["overrides"]),
+            load_before: string_list(&json["load_before"]),
+            load_after: string_list(&json["load_after"]),
             file_path: manifest_path.parent()?.to_path_buf(),
             enabled: true,
         })
@@ -195,7 +267,6 @@ impl ModDependencyResolver {
         let mods = self.mods.read();
         
         let mut missing = Vec::new();
-        let mut conflicts = Vec::new();
         let mut warnings = Vec::new();
         
         let enabled_set: HashSet<&String> = enabled_mod_ids.iter().collect();
@@ -219,22 +290,41 @@ impl ModDependencyResolver {
                         }
                     }
                 }
-                
-                for conflict_id in &mod_info.conflicts {
-                    if enabled_set.contains(conflict_id) {
-                        conflicts.push(ModConflict {
-                            mod_a: mod_id.clone(),
-                            mod_b: conflict_id.clone(),
-                            reason: format!("Mod '{}' conflicts with '{}'", mod_id, conflict_id),
-                        });
-                    }
-                }
             }
         }
         
-        let load_order = self.topological_sort(enabled_mod_ids, &mods);
+        let selected: Vec<scanner::ModInfo> = self.scanned.read().iter()
+            .filter(|m| enabled_set.contains(&m.id))
+            .map(|m| scanner::ModInfo { enabled: true, ..m.to_scanned() })
+            .collect();
+        let conflict_report = ModAnalyzer::new(&selected).report();
+        let mut conflicts = Vec::new();
+        for conflict in &conflict_report.conflicts {
+            if conflict.severity == Severity::Warning {
+                warnings.push(conflict.message.clone());
+                continue;
+            }
+            conflicts.push(ModConflict {
+                mod_a: conflict.mods.first().cloned().unwrap_or_default(),
+                mod_b: conflict.mods.get(1).or(conflict.subject.as_ref()).cloned().unwrap_or_default(),
+                reason: conflict.message.clone(),
+            });
+        }
+        
+        let manifests: Vec<ModManifest> = enabled_mod_ids.iter()
+            .map(|id| mods.get(id)
+                .map(|m| ModManifest::from(&m.to_scanned()))
+                .unwrap_or_else(|| ModManifest::bare(id)))
+            .collect();
+        let (load_order, sorted) = match ModResolver::resolve(&manifests) {
+            Ok(resolved) => (resolved.order, true),
+            Err(e) => {
+                warnings.push(e.to_string());
+                (enabled_mod_ids.to_vec(), false)
+            }
+        };
         
-        let success = missing.iter().all(|m| m.optional) && conflicts.is_empty();
+        let success = sorted && missing.iter().all(|m| m.optional) && conflicts.is_empty();
         
         ResolutionResult {
             success,
@@ -242,56 +332,10 @@ impl ModDependencyResolver {
             missing_dependencies: missing,
             conflicts,
             warnings,
+            conflict_report,
         }
     }
     
-    fn topological_sort(&self, mod_ids: &[String], mods: &HashMap<String, ModInfo>) -> Vec<String> {
-        let mut in_degree: HashMap<String, usize> = HashMap::new();
-        let mut graph: HashMap<String, Vec<String>> = HashMap::new();
-        
-        for id in mod_ids {
-            in_degree.entry(id.clone()).or_insert(0);
-            graph.entry(id.clone()).or_insert_with(Vec::new);
-        }
-        
-        for id in mod_ids {
-            if let Some(mod_info) = mods.get(id) {
-                for dep in &mod_info.dependencies {
-                    if mod_ids.contains(&dep.mod_id) {
-                        graph.entry(dep.mod_id.clone())
-                            .or_insert_with(Vec::new)
-                            .push(id.clone());
-                        *in_degree.entry(id.clone()).or_insert(0) += 1;
-                    }
-                }
-            }
-        }
-        
-        let mut queue: VecDeque<String> = in_degree.iter()
-            .filter(|(_, &deg)| deg == 0)
-            .map(|(id, _)| id.clone())
-            .collect();
-        
-        let mut result = Vec::new();
-        
-        while let Some(id) = queue.pop_front() {
-            result.push(id.clone());
-            
-            if let Some(dependents) = graph.get(&id) {
-                for dep in dependents {
-                    if let Some(deg) = in_degree.get_mut(dep) {
-                        *deg -= 1;
-                        if *deg == 0 {
-                            queue.push_back(dep.clone());
-                        }
-                    }
-                }
-            }
-        }
-        
-        result
-    }
-    
     pub fn get_mods(&self) -> Vec<ModInfo> {
         self.mods.read().values().cloned().collect()
     }
@@ -333,23 +377,53 @@ impl ModDependencyResolver {
         self.profiles.read().get(id).cloned()
     }
     
-    pub fn activate_profile(&self, profile_id: &str) -> Result<ResolutionResult, String> {
+    /// Activate a profile with the core `ProfileActivator`: mods outside it
+    /// are disabled, disabled ones it wants are re-enabled and missing ones
+    /// downloaded. Every change is journaled, so a failure rolls the mods
+    /// directory back. Nothing is changed while dependencies don't resolve.
+    pub async fn activate_profile(&self, profile_id: &str, dry_run: bool) -> Result<ProfileActivation, String> {
         let profile = self.profiles.read()
             .get(profile_id)
             .cloned()
             .ok_or_else(|| "Profile not found".to_string())?;
         
-        let result = self.resolve_dependencies(&profile.enabled_mods);
+        let resolution = self.resolve_dependencies(&profile.enabled_mods);
+        let spec = ModProfileSpec {
+            id: profile.id.clone(),
+            name: profile.name.clone(),
+            mods: profile.enabled_mods.iter()
+                .map(|id| ProfileMod { id: id.clone(), file_name: None, download_url: None, sha256: None })
+                .collect(),
+        };
+        let activator = ProfileActivator::new(
+            self.mod_directory.read().clone(),
+            Box::new(HttpModDownloader::new(self.download_cache.clone())),
+        );
+        let report = activator.activate(&spec, dry_run || !resolution.success).await
+            .map_err(|e| e.to_string())?;
         
-        if result.success {
-            *self.active_profile.write() = Some(profile_id.to_string());
-            
-            if let Some(p) = self.profiles.write().get_mut(profile_id) {
-                p.last_used = Some(chrono::Utc::now());
+        if report.dry_run || !report.success() {
+            return Ok(ProfileActivation { resolution, report });
+        }
+        
+        self.scan_mods().await?;
+        let on_disk = activator.scan().await.map_err(|e| e.to_string())?;
+        {
+            let mut mods = self.mods.write();
+            for (id, scanned) in &on_disk {
+                if let Some(mod_info) = mods.get_mut(id) {
+                    mod_info.enabled = scanned.enabled;
+                    mod_info.file_path = scanned.path.clone();
+                }
             }
         }
         
-        Ok(result)
+        *self.active_profile.write() = Some(profile_id.to_string());
+        if let Some(p) = self.profiles.write().get_mut(profile_id) {
+            p.last_used = Some(chrono::Utc::now());
+        }
+        
+        Ok(ProfileActivation { resolution, report })
     }
     
     pub fn delete_profile(&self, id: &str) -> Result<(), String> {
@@ -363,3 +437,9 @@ impl ModDependencyResolver {
         Ok(())
     }
 }
+
+fn string_list(value: &serde_json::Value) -> Vec<String> {
+    value.as_array()
+        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default()
+}
@@ -29,6 +29,15 @@ interface ServerInfo {
   favorited?: boolean;
 }
 
+interface ServerPage {
+  servers: ServerInfo[];
+  total: number;
+  page: number;
+  per_page: number;
+}
+
+const SERVERS_PER_PAGE = 50;
+
 interface Friend {
   id: string;
   username: string;
@@ -636,17 +645,21 @@ function PlayView() {
 
 function ServersView() {
   const [servers, setServers] = useState<ServerInfo[]>([]);
+  const [total, setTotal] = useState(0);
+  const [page, setPage] = useState(1);
   const [loading, setLoading] = useState(true);
   const [filter, setFilter] = useState('all');
 
   useEffect(() => {
-    loadServers();
+    loadServers(1);
   }, []);
 
-  async function loadServers() {
+  async function loadServers(nextPage: number) {
     try {
-      const s = await invoke<ServerInfo[]>('get_servers');
-      setServers(s);
+      const s = await invoke<ServerPage>('get_servers', { page: nextPage, perPage: SERVERS_PER_PAGE });
+      setServers(prev => nextPage === 1 ? s.servers : [...prev, ...s.servers]);
+      setTotal(s.total);
+      setPage(nextPage);
     } catch (e) {
       console.error(e);
       setServers([
@@ -726,6 +739,11 @@ function ServersView() {
               </div>
             </div>
           ))}
+          {servers.length < total && (
+            <button className="btn-load-more" onClick={() => loadServers(page + 1)}>
+              Load more ({servers.length}/{total})
+            </button>
+          )}
         </div>
       )}
     </div>
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::filesystem::{atomic_write_with_backup, load_with_backup};
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Failed to read config file: {0}")]
@@ -106,16 +108,15 @@ impl Default for Config {
 }
 
 impl Config {
+    /// Falls back to the previous save when the file doesn't parse
     pub fn load(path: &std::path::Path) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path)?;
-        
         let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
         
-        match ext {
-            "toml" => Ok(toml::from_str(&content)?),
-            "json" => Ok(serde_json::from_str(&content)?),
+        load_with_backup(path, |content| match ext {
+            "toml" => Ok(toml::from_str(&String::from_utf8_lossy(content))?),
+            "json" => Ok(serde_json::from_slice(content)?),
             _ => Err(ConfigError::UnsupportedFormat),
-        }
+        })
     }
     
     pub fn save(&self, path: &std::path::Path) -> Result<(), ConfigError> {
@@ -127,11 +128,7 @@ impl Config {
             _ => return Err(ConfigError::UnsupportedFormat),
         };
         
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        
-        std::fs::write(path, content)?;
+        atomic_write_with_backup(path, content.as_bytes())?;
         Ok(())
     }
     
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// A cron expression limited to minute, hour and day of week, evaluated in
+/// UTC. Takes the usual five fields with day-of-month and month left as `*`,
+/// or one of `@hourly`, `@daily`, `@midnight` and `@weekly`. Each field
+/// accepts `*`, `n`, `a-b`, `*/step`, `a-b/step` and comma-separated lists;
+/// day of week runs 0-7 with both 0 and 7 meaning Sunday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expr: String,
+    minutes: u64,
+    hours: u32,
+    days: u8,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let expanded = match expr {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!("Cron expression '{}' needs 5 fields, got {}", expr, fields.len()));
+        };
+        if day_of_month != "*" || month != "*" {
+            return Err(format!("Cron expression '{}': day-of-month and month must be '*'", expr));
+        }
+
+        let minutes = parse_field(minute, 0, 59).map_err(|e| format!("Cron minute field: {}", e))?;
+        let hours = parse_field(hour, 0, 23).map_err(|e| format!("Cron hour field: {}", e))?;
+        let mut days = parse_field(day_of_week, 0, 7).map_err(|e| format!("Cron day-of-week field: {}", e))?;
+        if days & (1 << 7) != 0 {
+            days = (days | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expr: expr.to_string(),
+            minutes,
+            hours: hours as u32,
+            days: days as u8,
+        })
+    }
+
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    /// Whether the schedule fires in the minute `at` falls in.
+    pub fn matches(&self, at: DateTime<Utc>) -> bool {
+        self.days & (1 << at.weekday().num_days_from_sunday()) != 0
+            && self.hours & (1 << at.hour()) != 0
+            && self.minutes & (1 << at.minute()) != 0
+    }
+
+    /// The first matching minute strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let mut day = after.date_naive();
+        let (mut from_hour, mut from_minute) = (after.hour(), after.minute() + 1);
+        // Every field matches at least one value, so a week always has a match.
+        loop {
+            if self.days & (1 << day.weekday().num_days_from_sunday()) != 0 {
+                for hour in from_hour..24 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    let start = if hour == from_hour { from_minute } else { 0 };
+                    if let Some(minute) = (start..60).find(|m| self.minutes & (1 << m) != 0) {
+                        return day.and_hms_opt(hour, minute, 0).expect("valid time").and_utc();
+                    }
+                }
+            }
+            day = day.succ_opt().expect("date in range");
+            (from_hour, from_minute) = (0, 0);
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err(format!("step in '{}' must be at least 1", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_value(a, min, max)?, parse_value(b, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // `n/step` runs from n to the end of the range.
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            return Err(format!("range '{}' runs backwards", range));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    let parsed: u32 = value.parse().map_err(|_| format!("'{}' is not a number", value))?;
+    if parsed < min || parsed > max {
+        return Err(format!("{} is outside {}-{}", parsed, min, max));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let cron = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        assert_eq!(cron.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(cron.hours, (9..=17).map(|h| 1u32 << h).sum::<u32>());
+        assert_eq!(cron.days, 0b0111110);
+
+        assert_eq!(CronSchedule::parse("0 0 * * 7").unwrap().days, 1);
+        assert_eq!(CronSchedule::parse("0 0 * * 0,6").unwrap().days, 0b1000001);
+        assert_eq!(CronSchedule::parse("5/20 * * * *").unwrap().minutes, 1 << 5 | 1 << 25 | 1 << 45);
+        assert_eq!(CronSchedule::parse("@daily").unwrap(), CronSchedule { expr: "@daily".into(), ..CronSchedule::parse("0 0 * * *").unwrap() });
+        assert_eq!(CronSchedule::parse("@weekly").unwrap().to_string(), "@weekly");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(CronSchedule::parse("0 0 * *").unwrap_err().contains("5 fields"));
+        assert!(CronSchedule::parse("0 0 1 * *").unwrap_err().contains("day-of-month"));
+        assert!(CronSchedule::parse("60 * * * *").unwrap_err().contains("minute"));
+        assert!(CronSchedule::parse("0 24 * * *").unwrap_err().contains("hour"));
+        assert!(CronSchedule::parse("0 0 * * 8").unwrap_err().contains("day-of-week"));
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("30-10 * * * *").unwrap_err().contains("backwards"));
+        assert!(CronSchedule::parse("a * * * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2026-03-04 is a Wednesday.
+        let hourly = CronSchedule::parse("@hourly").unwrap();
+        assert_eq!(hourly.next_after(at(2026, 3, 4, 10, 0)), at(2026, 3, 4, 11, 0));
+        assert_eq!(hourly.next_after(at(2026, 3, 4, 23, 59)), at(2026, 3, 5, 0, 0));
+
+        let nightly = CronSchedule::parse("30 3 * * *").unwrap();
+        assert_eq!(nightly.next_after(at(2026, 3, 4, 3, 29)), at(2026, 3, 4, 3, 30));
+        assert_eq!(nightly.next_after(at(2026, 3, 4, 3, 30)), at(2026, 3, 5, 3, 30));
+
+        let weekend = CronSchedule::parse("0 12 * * 6,0").unwrap();
+        assert_eq!(weekend.next_after(at(2026, 3, 4, 8, 0)), at(2026, 3, 7, 12, 0));
+        assert_eq!(weekend.next_after(at(2026, 3, 7, 12, 0)), at(2026, 3, 8, 12, 0));
+        assert_eq!(weekend.next_after(at(2026, 3, 8, 12, 0)), at(2026, 3, 14, 12, 0));
+
+        let next = nightly.next_after(at(2026, 3, 4, 12, 0) + chrono::Duration::seconds(42));
+        assert!(nightly.matches(next));
+        assert!(!nightly.matches(next + chrono::Duration::minutes(1)));
+    }
+}
@@ -1,5 +1,13 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::warn;
+
+/// How long a fetched set of gates is trusted before refreshing.
+pub const DEFAULT_GATES_TTL_SECS: i64 = 60 * 60;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeatureGate {
@@ -30,11 +38,265 @@ pub enum FeatureCategory {
     Diagnostics,
 }
 
+#[derive(Debug, Error)]
+pub enum FeatureSyncError {
+    #[error("Failed to fetch feature gates: {0}")]
+    Fetch(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    SerializeError(String),
+}
+
+/// Flags in one section of the gates response, e.g. `diagnostics`
+pub type FeatureSection = BTreeMap<String, bool>;
+
+/// Launcher sections of the `/api/v1/features` response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct YellowTaleGates {
+    #[serde(default)]
+    pub performance: FeatureSection,
+    #[serde(default)]
+    pub diagnostics: FeatureSection,
+    #[serde(default)]
+    pub mod_tooling: FeatureSection,
+    #[serde(default)]
+    pub visual_processing: FeatureSection,
+    #[serde(default)]
+    pub session_networking: FeatureSection,
+    #[serde(default)]
+    pub pond_integration: FeatureSection,
+    #[serde(default)]
+    pub connectivity: FeatureSection,
+}
+
+/// Server sections of the `/api/v1/features` response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PondGates {
+    #[serde(default)]
+    pub server_performance: FeatureSection,
+    #[serde(default)]
+    pub live_tooling: FeatureSection,
+    #[serde(default)]
+    pub cosmetics: FeatureSection,
+    #[serde(default)]
+    pub launcher_integration: FeatureSection,
+    #[serde(default)]
+    pub networking: FeatureSection,
+}
+
+impl YellowTaleGates {
+    pub fn section(&self, name: &str) -> Option<&FeatureSection> {
+        match name {
+            "performance" => Some(&self.performance),
+            "diagnostics" => Some(&self.diagnostics),
+            "mod_tooling" => Some(&self.mod_tooling),
+            "visual_processing" => Some(&self.visual_processing),
+            "session_networking" => Some(&self.session_networking),
+            "pond_integration" => Some(&self.pond_integration),
+            "connectivity" => Some(&self.connectivity),
+            _ => None,
+        }
+    }
+
+    fn section_mut(&mut self, name: &str) -> Option<&mut FeatureSection> {
+        match name {
+            "performance" => Some(&mut self.performance),
+            "diagnostics" => Some(&mut self.diagnostics),
+            "mod_tooling" => Some(&mut self.mod_tooling),
+            "visual_processing" => Some(&mut self.visual_processing),
+            "session_networking" => Some(&mut self.session_networking),
+            "pond_integration" => Some(&mut self.pond_integration),
+            "connectivity" => Some(&mut self.connectivity),
+            _ => None,
+        }
+    }
+}
+
+impl PondGates {
+    pub fn section(&self, name: &str) -> Option<&FeatureSection> {
+        match name {
+            "server_performance" => Some(&self.server_performance),
+            "live_tooling" => Some(&self.live_tooling),
+            "cosmetics" => Some(&self.cosmetics),
+            "launcher_integration" => Some(&self.launcher_integration),
+            "networking" => Some(&self.networking),
+            _ => None,
+        }
+    }
+
+    fn section_mut(&mut self, name: &str) -> Option<&mut FeatureSection> {
+        match name {
+            "server_performance" => Some(&mut self.server_performance),
+            "live_tooling" => Some(&mut self.live_tooling),
+            "cosmetics" => Some(&mut self.cosmetics),
+            "launcher_integration" => Some(&mut self.launcher_integration),
+            "networking" => Some(&mut self.networking),
+            _ => None,
+        }
+    }
+}
+
+/// The `/api/v1/features` response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureGates {
+    pub tier: String,
+    #[serde(default)]
+    pub yellow_tale: YellowTaleGates,
+    #[serde(default)]
+    pub pond: PondGates,
+}
+
+/// Product, section and flags
+type SectionDefaults = (&'static str, &'static str, &'static [(&'static str, bool)]);
+
+/// Flags the free tier gets, mirroring the server's response for a user
+/// without a subscription. Used until the server has answered once.
+const FREE_TIER_DEFAULTS: &[SectionDefaults] = &[
+    ("yellow_tale", "performance", &[
+        ("cpu_core_allocation", false), ("adaptive_ram_reservation", false),
+        ("background_process_suppression", false), ("disk_io_prewarming", false),
+        ("profile_based_presets", false), ("memory_defragmentation", false),
+        ("shader_precompilation", false), ("asset_streaming", true), ("texture_compression", true),
+    ]),
+    ("yellow_tale", "diagnostics", &[
+        ("historical_tracking", false), ("frame_pacing_analysis", false),
+        ("bottleneck_detection", false), ("exportable_reports", false),
+        ("crash_correlation", false), ("gpu_profiling", false),
+        ("network_diagnostics", true), ("memory_leak_detection", false),
+    ]),
+    ("yellow_tale", "mod_tooling", &[
+        ("conflict_detection", false), ("dependency_auto_resolution", false),
+        ("load_order_optimization", false), ("per_server_profiles", false),
+        ("mod_health_checks", false), ("version_compatibility", true), ("safe_mode_fallback", true),
+    ]),
+    ("yellow_tale", "visual_processing", &[
+        ("frame_upscaling", false), ("scaling_algorithms", false), ("profile_presets", false),
+        ("fsr_support", false), ("dynamic_resolution", false),
+    ]),
+    ("yellow_tale", "session_networking", &[
+        ("smart_orchestration", false), ("nat_traversal", true), ("relay_routing", false),
+        ("session_persistence", false), ("connection_pooling", true), ("latency_optimization", false),
+        ("route_selection", false), ("reconnect_grace_period", true),
+    ]),
+    ("yellow_tale", "pond_integration", &[
+        ("capability_discovery", true), ("performance_hints", false), ("asset_availability", true),
+        ("cosmetic_verification", false), ("queue_priority", false), ("friend_activity_sync", true),
+        ("session_transfer", false), ("network_optimization_hints", false),
+    ]),
+    ("yellow_tale", "connectivity", &[
+        ("auto_server_discovery", true), ("ping_history", true), ("server_favorites", true),
+        ("connection_quality_indicator", true), ("bandwidth_estimation", false),
+        ("packet_loss_compensation", false), ("jitter_buffer_optimization", false),
+    ]),
+    ("pond", "server_performance", &[
+        ("advanced_tick_scheduling", false), ("adaptive_entity_throttling", false),
+        ("dynamic_chunk_activation", false), ("memory_pooling", false), ("load_prediction", false),
+        ("gc_optimization", false), ("entity_culling", true), ("view_distance_scaling", true),
+    ]),
+    ("pond", "live_tooling", &[
+        ("plugin_hot_loading", false), ("live_config_reloads", false), ("realtime_dashboards", false),
+        ("plugin_sandboxing", false), ("remote_console", false), ("crash_recovery", true),
+    ]),
+    ("pond", "cosmetics", &[
+        ("cosmetic_registry", true), ("temporary_overrides", false), ("creator_cosmetics", false),
+        ("asset_validation", true), ("cosmetic_caching", true), ("event_cosmetics", false),
+    ]),
+    ("pond", "launcher_integration", &[
+        ("feature_advertising", true), ("asset_manifests", true), ("ownership_validation", false),
+        ("profile_sync", false), ("player_presence", true), ("queue_management", true),
+        ("priority_queue", false), ("session_handoff", false), ("friend_notifications", true),
+    ]),
+    ("pond", "networking", &[
+        ("connection_keep_alive", true), ("ping_optimization", true), ("interpolation_hints", true),
+        ("prediction_config", false), ("compression_level", false), ("batch_updates", true),
+    ]),
+];
+
+impl FeatureGates {
+    pub fn free_tier() -> Self {
+        let mut gates = Self {
+            tier: "free".to_string(),
+            yellow_tale: YellowTaleGates::default(),
+            pond: PondGates::default(),
+        };
+        for (product, section, flags) in FREE_TIER_DEFAULTS {
+            let section = match *product {
+                "pond" => gates.pond.section_mut(section),
+                _ => gates.yellow_tale.section_mut(section),
+            };
+            if let Some(section) = section {
+                section.extend(flags.iter().map(|(name, enabled)| (name.to_string(), *enabled)));
+            }
+        }
+        gates
+    }
+
+    pub fn is_premium(&self) -> bool {
+        self.tier != "free"
+    }
+
+    /// Looks up a dotted path such as `diagnostics.gpu_profiling` or
+    /// `pond.live_tooling.remote_console`. Paths without a `yellow_tale.` or
+    /// `pond.` prefix refer to the launcher sections.
+    pub fn resolve(&self, path: &str) -> Option<bool> {
+        let parts: Vec<&str> = path.split('.').collect();
+        let section = match parts.as_slice() {
+            ["pond", section, _] => self.pond.section(section),
+            ["yellow_tale", section, _] | [section, _] => self.yellow_tale.section(section),
+            _ => None,
+        }?;
+        section.get(*parts.last()?).copied()
+    }
+}
+
+/// Where fresh copies of the gates come from
+#[async_trait]
+pub trait FeatureGateSource: Send + Sync {
+    async fn fetch(&self) -> Result<FeatureGates, FeatureSyncError>;
+}
+
+/// The last successful response, as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFeatureGates {
+    pub tier: String,
+    pub fetched_at: DateTime<Utc>,
+    pub gates: FeatureGates,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GateOrigin {
+    /// Compiled-in free tier defaults
+    Defaults,
+    /// Read from the on-disk cache
+    Cache,
+    /// Fetched from the server this run
+    Server,
+}
+
+/// Snapshot of the gates in use, for the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureGateState {
+    pub tier: String,
+    pub origin: GateOrigin,
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub stale: bool,
+    pub last_error: Option<String>,
+    pub gates: FeatureGates,
+}
+
 pub struct FeatureManager {
     features: HashMap<String, FeatureGate>,
     premium_launcher: bool,
     premium_pond: bool,
     game_api_available: bool,
+    gates: FeatureGates,
+    origin: GateOrigin,
+    fetched_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    cache_path: Option<PathBuf>,
+    ttl: Duration,
 }
 
 impl FeatureManager {
@@ -44,6 +306,12 @@ impl FeatureManager {
             premium_launcher: false,
             premium_pond: false,
             game_api_available: false,
+            gates: FeatureGates::free_tier(),
+            origin: GateOrigin::Defaults,
+            fetched_at: None,
+            last_error: None,
+            cache_path: None,
+            ttl: Duration::seconds(DEFAULT_GATES_TTL_SECS),
         };
         
         manager.register_default_features();
@@ -260,8 +528,86 @@ impl FeatureManager {
         }
     }
     
+    /// Whether a local feature id (`launcher.sync`) or a server gate path
+    /// (`diagnostics.gpu_profiling`) is enabled
     pub fn is_enabled(&self, feature_id: &str) -> bool {
-        self.features.get(feature_id).map(|f| f.enabled).unwrap_or(false)
+        match self.features.get(feature_id) {
+            Some(feature) => feature.enabled,
+            None => self.gates.resolve(feature_id).unwrap_or(false),
+        }
+    }
+    
+    /// Load gates cached at `path`, if any, and save future fetches there
+    pub fn with_cache(mut self, path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        let path = path.into();
+        self.ttl = ttl;
+        match load_cache(&path) {
+            Ok(Some(cached)) => {
+                self.fetched_at = Some(cached.fetched_at);
+                self.apply_gates(cached.gates, GateOrigin::Cache);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Ignoring unreadable feature gate cache: {}", e),
+        }
+        self.cache_path = Some(path);
+        self
+    }
+    
+    /// Whether the gates in use are older than the TTL (or were never fetched)
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.fetched_at.is_none_or(|fetched_at| now - fetched_at >= self.ttl)
+    }
+    
+    /// Fetch fresh gates if the current ones are stale (or `force` is set).
+    /// When the fetch fails the cached or default gates stay in use and the
+    /// error is reported in the returned state.
+    pub async fn refresh(&mut self, source: &dyn FeatureGateSource, force: bool) -> FeatureGateState {
+        let now = Utc::now();
+        if !force && !self.needs_refresh(now) {
+            return self.gate_state(now);
+        }
+        
+        match source.fetch().await {
+            Ok(gates) => {
+                self.fetched_at = Some(now);
+                self.last_error = None;
+                if let Some(path) = &self.cache_path {
+                    let cached = CachedFeatureGates { tier: gates.tier.clone(), fetched_at: now, gates: gates.clone() };
+                    if let Err(e) = save_cache(path, &cached) {
+                        warn!("Failed to cache feature gates: {}", e);
+                    }
+                }
+                self.apply_gates(gates, GateOrigin::Server);
+            }
+            Err(e) => {
+                warn!("Using {:?} feature gates: {}", self.origin, e);
+                self.last_error = Some(e.to_string());
+            }
+        }
+        self.gate_state(now)
+    }
+    
+    fn apply_gates(&mut self, gates: FeatureGates, origin: GateOrigin) {
+        self.premium_launcher = gates.is_premium();
+        self.premium_pond = gates.is_premium();
+        self.gates = gates;
+        self.origin = origin;
+        self.recalculate();
+    }
+    
+    pub fn gates(&self) -> &FeatureGates {
+        &self.gates
+    }
+    
+    pub fn gate_state(&self, now: DateTime<Utc>) -> FeatureGateState {
+        FeatureGateState {
+            tier: self.gates.tier.clone(),
+            origin: self.origin,
+            fetched_at: self.fetched_at,
+            stale: self.needs_refresh(now),
+            last_error: self.last_error.clone(),
+            gates: self.gates.clone(),
+        }
     }
     
     pub fn get(&self, feature_id: &str) -> Option<&FeatureGate> {
@@ -295,6 +641,28 @@ impl FeatureManager {
     }
 }
 
+fn load_cache(path: &Path) -> Result<Option<CachedFeatureGates>, FeatureSyncError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| FeatureSyncError::SerializeError(e.to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_cache(path: &Path, cached: &CachedFeatureGates) -> Result<(), FeatureSyncError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(cached)
+        .map_err(|e| FeatureSyncError::SerializeError(e.to_string()))?;
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
 impl Default for FeatureManager {
     fn default() -> Self {
         Self::new()
@@ -316,3 +684,88 @@ macro_rules! when_feature {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubSource(Option<FeatureGates>);
+
+    #[async_trait]
+    impl FeatureGateSource for StubSource {
+        async fn fetch(&self) -> Result<FeatureGates, FeatureSyncError> {
+            self.0.clone().ok_or_else(|| FeatureSyncError::Fetch("connection refused".to_string()))
+        }
+    }
+
+    fn premium_gates() -> FeatureGates {
+        let mut gates = FeatureGates::free_tier();
+        gates.tier = "premium".to_string();
+        gates.yellow_tale.diagnostics.insert("gpu_profiling".to_string(), true);
+        gates.pond.live_tooling.insert("remote_console".to_string(), true);
+        gates
+    }
+
+    fn temp_cache() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("yt-core-gates-{}", uuid::Uuid::new_v4()))
+            .join("feature_gates.json")
+    }
+
+    #[test]
+    fn test_dotted_paths_resolve() {
+        let gates = premium_gates();
+        assert_eq!(gates.resolve("diagnostics.gpu_profiling"), Some(true));
+        assert_eq!(gates.resolve("yellow_tale.diagnostics.gpu_profiling"), Some(true));
+        assert_eq!(gates.resolve("pond.live_tooling.remote_console"), Some(true));
+        assert_eq!(gates.resolve("diagnostics.network_diagnostics"), Some(true));
+        assert_eq!(gates.resolve("diagnostics.unknown"), None);
+        assert_eq!(gates.resolve("gpu_profiling"), None);
+    }
+
+    #[tokio::test]
+    async fn test_offline_without_cache_uses_free_defaults() {
+        let path = temp_cache();
+        let mut manager = FeatureManager::new().with_cache(&path, Duration::hours(1));
+        let state = manager.refresh(&StubSource(None), false).await;
+
+        assert_eq!(state.origin, GateOrigin::Defaults);
+        assert!(state.last_error.is_some());
+        assert!(manager.is_enabled("connectivity.ping_history"));
+        assert!(!manager.is_enabled("diagnostics.gpu_profiling"));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_expired_premium_cache_downgrades_to_free() {
+        let path = temp_cache();
+        let cached = CachedFeatureGates {
+            tier: "premium".to_string(),
+            fetched_at: Utc::now() - Duration::hours(2),
+            gates: premium_gates(),
+        };
+        save_cache(&path, &cached).unwrap();
+
+        // Offline, the expired cache is still better than the defaults
+        let mut manager = FeatureManager::new().with_cache(&path, Duration::hours(1));
+        let state = manager.refresh(&StubSource(None), false).await;
+        assert_eq!(state.origin, GateOrigin::Cache);
+        assert!(state.stale);
+        assert!(manager.is_enabled("diagnostics.gpu_profiling"));
+        assert!(manager.is_enabled("launcher.sync"));
+
+        let state = manager.refresh(&StubSource(Some(FeatureGates::free_tier())), false).await;
+        assert_eq!(state.origin, GateOrigin::Server);
+        assert_eq!(state.tier, "free");
+        assert!(!state.stale);
+        assert!(!manager.is_enabled("diagnostics.gpu_profiling"));
+        assert!(!manager.is_enabled("pond.live_tooling.remote_console"));
+        assert!(!manager.is_enabled("launcher.sync"));
+
+        let reloaded = FeatureManager::new().with_cache(&path, Duration::hours(1));
+        assert_eq!(reloaded.gates().tier, "free");
+        assert!(!reloaded.needs_refresh(Utc::now()));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use sha2::{Sha256, Digest};
+use tracing::warn;
+
+/// Times a Windows rename is retried while something else has the target open
+#[cfg(windows)]
+const REPLACE_RETRIES: u32 = 5;
+#[cfg(windows)]
+const REPLACE_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
 
 #[derive(Debug, Error)]
 pub enum FsError {
@@ -120,16 +129,7 @@ impl FileSystem {
     
     pub fn atomic_write(&self, path: &Path, content: &[u8]) -> Result<(), FsError> {
         self.check_quota(content.len() as u64)?;
-        
-        let temp_path = path.with_extension("tmp");
-        
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        
-        std::fs::write(&temp_path, content)?;
-        std::fs::rename(&temp_path, path)?;
-        
+        atomic_write(path, content)?;
         Ok(())
     }
     
@@ -202,7 +202,7 @@ impl FileSystem {
             }
         }
         
-        points.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        points.sort_by_key(|point| std::cmp::Reverse(point.created_at));
         
         Ok(points)
     }
@@ -287,6 +287,115 @@ impl FileSystem {
     }
 }
 
+/// `<path>.bak`, the previous version kept by [`atomic_write_with_backup`]
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Replace `path` with `content` so that a crash at any point leaves either
+/// the old file or the new one, never a truncated mix. The content goes to
+/// a temporary file in the same directory, is flushed to disk and is then
+/// renamed over `path`.
+pub fn atomic_write(path: &Path, content: &[u8]) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir)?;
+    
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let temp = dir.join(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4().simple()));
+    let written = (|| {
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()
+    })()
+    .and_then(|_| replace(&temp, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    
+    sync_dir(dir)
+}
+
+/// [`atomic_write`], first keeping the current file as [`backup_path`] for
+/// [`load_with_backup`] to fall back to
+pub fn atomic_write_with_backup(path: &Path, content: &[u8]) -> io::Result<()> {
+    match std::fs::read(path) {
+        Ok(previous) => atomic_write(&backup_path(path), &previous)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    atomic_write(path, content)
+}
+
+/// Read and parse `path`. When it exists but doesn't parse, the backup kept by
+/// [`atomic_write_with_backup`] is used instead and a warning logged; if that
+/// fails too, the primary file's error is returned. A missing or unreadable
+/// primary file is an error as it is, so a deleted file stays deleted.
+pub fn load_with_backup<T, E>(path: &Path, mut parse: impl FnMut(&[u8]) -> Result<T, E>) -> Result<T, E>
+where
+    E: From<io::Error> + fmt::Display,
+{
+    let primary_error = match parse(&std::fs::read(path)?) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    
+    let backup = backup_path(path);
+    match std::fs::read(&backup).map_err(E::from).and_then(|content| parse(&content)) {
+        Ok(value) => {
+            warn!("{} is unreadable ({}), using the backup {}", path.display(), primary_error, backup.display());
+            Ok(value)
+        }
+        Err(_) => Err(primary_error),
+    }
+}
+
+#[cfg(not(windows))]
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::rename(from, to)
+}
+
+/// std's rename is `MoveFileExW` with `MOVEFILE_REPLACE_EXISTING`, which swaps
+/// the file in as `ReplaceFile` would, but is refused while a virus scanner or
+/// indexer holds the target open, or when the target is read-only
+#[cfg(windows)]
+#[allow(clippy::permissions_set_readonly_false)]
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match std::fs::rename(from, to) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied && attempt < REPLACE_RETRIES => {
+                attempt += 1;
+                if let Ok(metadata) = std::fs::metadata(to) {
+                    let mut permissions = metadata.permissions();
+                    if permissions.readonly() {
+                        permissions.set_readonly(false);
+                        let _ = std::fs::set_permissions(to, permissions);
+                    }
+                }
+                std::thread::sleep(REPLACE_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Make the rename itself durable; directories can't be synced on Windows
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    std::fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
 fn calculate_dir_size(path: &Path) -> Result<u64, FsError> {
     let mut size = 0u64;
     
@@ -309,3 +418,54 @@ fn calculate_dir_size(path: &Path) -> Result<u64, FsError> {
     
     Ok(size)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yt-core-fs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn parse_json(content: &[u8]) -> Result<serde_json::Value, FsError> {
+        serde_json::from_slice(content).map_err(|e| FsError::AtomicFailed(e.to_string()))
+    }
+
+    #[test]
+    fn test_atomic_write_replaces_and_leaves_no_temp_files() {
+        let dir = temp_dir();
+        let path = dir.join("settings.json");
+        atomic_write(&path, b"{\"volume\": 1}").unwrap();
+        atomic_write(&path, b"{\"volume\": 2}").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"volume\": 2}");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_truncated_primary_recovers_from_backup() {
+        let dir = temp_dir();
+        let path = dir.join("profile.json");
+        atomic_write_with_backup(&path, b"{\"name\": \"Vanilla\"}").unwrap();
+        assert!(!backup_path(&path).exists());
+        atomic_write_with_backup(&path, b"{\"name\": \"Modded\"}").unwrap();
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), b"{\"name\": \"Vanilla\"}");
+        assert_eq!(load_with_backup(&path, parse_json).unwrap()["name"], "Modded");
+
+        // A save cut off half way, as a plain write killed mid-flight leaves it
+        std::fs::write(&path, b"{\"name\": \"Mod").unwrap();
+        assert_eq!(load_with_backup(&path, parse_json).unwrap()["name"], "Vanilla");
+
+        // Without a usable backup the primary's own error comes back
+        std::fs::write(backup_path(&path), b"").unwrap();
+        assert!(matches!(load_with_backup(&path, parse_json), Err(FsError::AtomicFailed(_))));
+
+        // A missing primary isn't replaced by its backup
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(load_with_backup(&path, parse_json), Err(FsError::IoError(_))));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -4,9 +4,13 @@ pub mod filesystem;
 pub mod protocol;
 pub mod features;
 pub mod assets;
+pub mod cron;
+pub mod schedule;
 
 pub use config::Config;
 pub use profile::{Profile, ProfileManager};
 pub use filesystem::FileSystem;
 pub use protocol::{ControlMessage, ControlResponse};
-pub use features::{FeatureGate, FeatureManager};
+pub use features::{FeatureGate, FeatureGateSource, FeatureGates, FeatureManager};
+pub use cron::CronSchedule;
+pub use schedule::{Clock, JobQueue, Schedule, ScheduledTaskInfo, SystemClock, TaskHandle, TaskPriority};
@@ -5,6 +5,8 @@ use thiserror::Error;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::filesystem::{atomic_write, atomic_write_with_backup, backup_path, load_with_backup};
+
 #[derive(Debug, Error)]
 pub enum ProfileError {
     #[error("Profile not found: {0}")]
@@ -126,10 +128,8 @@ impl ProfileManager {
             let path = entry.path();
             
             if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if let Ok(content) = std::fs::read_to_string(&path) {
-                    if let Ok(profile) = serde_json::from_str::<Profile>(&content) {
-                        self.profiles.insert(profile.id, profile);
-                    }
+                if let Ok(profile) = load_with_backup(&path, parse_profile) {
+                    self.profiles.insert(profile.id, profile);
                 }
             }
         }
@@ -166,8 +166,10 @@ impl ProfileManager {
     pub fn delete(&mut self, id: Uuid) -> Result<(), ProfileError> {
         if let Some(profile) = self.profiles.remove(&id) {
             let path = self.profile_path(&profile);
-            if path.exists() {
-                std::fs::remove_file(path)?;
+            for file in [backup_path(&path), path] {
+                if file.exists() {
+                    std::fs::remove_file(file)?;
+                }
             }
             
             if self.active_profile == Some(id) {
@@ -233,7 +235,7 @@ impl ProfileManager {
         let content = serde_json::to_string_pretty(profile)
             .map_err(|e| ProfileError::SerializeError(e.to_string()))?;
         
-        std::fs::write(path, content)?;
+        atomic_write(path, content.as_bytes())?;
         Ok(())
     }
     
@@ -260,13 +262,15 @@ impl ProfileManager {
     }
     
     fn save_profile(&self, profile: &Profile) -> Result<(), ProfileError> {
-        std::fs::create_dir_all(&self.profiles_dir)?;
-        
         let path = self.profile_path(profile);
         let content = serde_json::to_string_pretty(profile)
             .map_err(|e| ProfileError::SerializeError(e.to_string()))?;
         
-        std::fs::write(path, content)?;
+        atomic_write_with_backup(&path, content.as_bytes())?;
         Ok(())
     }
 }
+
+fn parse_profile(content: &[u8]) -> Result<Profile, ProfileError> {
+    serde_json::from_slice(content).map_err(|e| ProfileError::SerializeError(e.to_string()))
+}
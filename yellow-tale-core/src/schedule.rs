@@ -0,0 +1,370 @@
+//! Wall-clock job scheduling shared by the Pond and Rubidium schedulers.
+//!
+//! Each server's `Scheduler` owns a `JobQueue` and decides when to call
+//! `run_due`, from its tick loop or a timer; the queue only tracks what is
+//! due and runs it.
+
+use crate::cron::CronSchedule;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TaskPriority {
+    Critical = 0,
+    High = 1,
+    Normal = 2,
+    Low = 3,
+    Background = 4,
+}
+
+/// Wall-clock time for scheduled jobs, replaceable in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// When a scheduled job runs.
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    fn first_run(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Schedule::Every(interval) => now + chrono_duration(*interval),
+            Schedule::Cron(cron) => cron.next_after(now),
+        }
+    }
+
+    /// The next run after a run that was due at `due` and ran at `now`.
+    /// Runs missed in between are skipped, so a job that fell behind runs
+    /// once and keeps its phase.
+    fn following(&self, due: DateTime<Utc>, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Schedule::Every(interval) => {
+                let interval = chrono_duration(*interval);
+                let missed = (now - due).num_milliseconds() / interval.num_milliseconds().max(1);
+                due + interval * (missed as i32 + 1)
+            }
+            Schedule::Cron(cron) => cron.next_after(now),
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Every(interval) => {
+                let secs = interval.as_secs();
+                if secs >= 3600 && secs % 3600 == 0 {
+                    write!(f, "every {}h", secs / 3600)
+                } else if secs >= 60 && secs % 60 == 0 {
+                    write!(f, "every {}m", secs / 60)
+                } else {
+                    write!(f, "every {}s", interval.as_secs_f64())
+                }
+            }
+            Schedule::Cron(cron) => write!(f, "cron {}", cron),
+        }
+    }
+}
+
+fn chrono_duration(interval: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(interval.max(Duration::from_millis(1))).unwrap_or(chrono::Duration::MAX)
+}
+
+type Job = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Returned when a job is scheduled. Cancelling stops future runs; a run
+/// already under way finishes.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    id: Uuid,
+    active: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn cancel(&self) {
+        self.active.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+}
+
+struct ScheduledJob {
+    name: String,
+    priority: TaskPriority,
+    schedule: Schedule,
+    next_run: DateTime<Utc>,
+    last_run: Option<DateTime<Utc>>,
+    runs: u64,
+    handle: TaskHandle,
+    job: Job,
+}
+
+/// A scheduled job as listed by `JobQueue::scheduled_tasks`.
+#[derive(Debug, Clone)]
+pub struct ScheduledTaskInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub priority: TaskPriority,
+    pub schedule: String,
+    pub next_run: DateTime<Utc>,
+    pub last_run: Option<DateTime<Utc>>,
+    pub runs: u64,
+}
+
+pub struct JobQueue {
+    jobs: Mutex<HashMap<Uuid, ScheduledJob>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Runs `job` every `interval`, the first time one interval from now.
+    pub fn schedule_repeating<F, Fut>(&self, name: impl Into<String>, interval: Duration, priority: TaskPriority, job: F) -> TaskHandle
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.schedule(name.into(), Schedule::Every(interval), priority, job)
+    }
+
+    /// Runs `job` whenever the cron expression `expr` matches; see
+    /// `CronSchedule` for the supported subset.
+    pub fn schedule_cron<F, Fut>(&self, name: impl Into<String>, expr: &str, priority: TaskPriority, job: F) -> Result<TaskHandle, String>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let cron = CronSchedule::parse(expr)?;
+        Ok(self.schedule(name.into(), Schedule::Cron(cron), priority, job))
+    }
+
+    fn schedule<F, Fut>(&self, name: String, schedule: Schedule, priority: TaskPriority, job: F) -> TaskHandle
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handle = TaskHandle {
+            id: Uuid::new_v4(),
+            active: Arc::new(AtomicBool::new(true)),
+        };
+        let next_run = schedule.first_run(self.clock.now());
+        debug!("Scheduled {} ({}), first run at {}", name, schedule, next_run);
+        self.jobs.lock().unwrap().insert(handle.id, ScheduledJob {
+            name,
+            priority,
+            schedule,
+            next_run,
+            last_run: None,
+            runs: 0,
+            handle: handle.clone(),
+            job: Arc::new(move || Box::pin(job())),
+        });
+        handle
+    }
+
+    /// Runs every job that is due, highest priority first, and drops
+    /// cancelled ones. A job that missed several runs, say while the server
+    /// was suspended, runs once.
+    pub async fn run_due(&self) -> usize {
+        let now = self.clock.now();
+        let mut due = Vec::new();
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.retain(|_, job| job.handle.is_active());
+            for job in jobs.values_mut() {
+                if job.next_run > now {
+                    continue;
+                }
+                let next_run = job.schedule.following(job.next_run, now);
+                if job.schedule.following(job.next_run, job.next_run) < now {
+                    debug!("Scheduled task {} fell behind; skipping missed runs until {}", job.name, next_run);
+                }
+                job.next_run = next_run;
+                job.last_run = Some(now);
+                job.runs += 1;
+                due.push((job.priority, job.name.clone(), job.handle.clone(), job.job.clone()));
+            }
+        }
+        due.sort_by_key(|(priority, ..)| *priority);
+
+        let count = due.len();
+        for (_, name, handle, job) in due {
+            if handle.is_active() {
+                debug!("Running scheduled task {}", name);
+                job().await;
+            }
+        }
+        count
+    }
+
+    /// Scheduled jobs by next run time.
+    pub fn scheduled_tasks(&self) -> Vec<ScheduledTaskInfo> {
+        let mut tasks: Vec<ScheduledTaskInfo> = self.jobs.lock().unwrap().values()
+            .filter(|job| job.handle.is_active())
+            .map(|job| ScheduledTaskInfo {
+                id: job.handle.id,
+                name: job.name.clone(),
+                priority: job.priority,
+                schedule: job.schedule.to_string(),
+                next_run: job.next_run,
+                last_run: job.last_run,
+                runs: job.runs,
+            })
+            .collect();
+        tasks.sort_by_key(|t| t.next_run);
+        tasks
+    }
+
+    /// Cancels a scheduled job by id.
+    pub fn cancel(&self, id: Uuid) -> bool {
+        match self.jobs.lock().unwrap().remove(&id) {
+            Some(job) => {
+                job.handle.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::atomic::AtomicU64;
+
+    struct ManualClock(Mutex<DateTime<Utc>>);
+
+    impl ManualClock {
+        fn advance(&self, by: chrono::Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn queue() -> (JobQueue, Arc<ManualClock>) {
+        // 2026-03-04 is a Wednesday.
+        let clock = Arc::new(ManualClock(Mutex::new(Utc.with_ymd_and_hms(2026, 3, 4, 10, 0, 0).unwrap())));
+        (JobQueue::new().with_clock(clock.clone()), clock)
+    }
+
+    fn counter() -> (Arc<AtomicU64>, impl Fn() -> std::future::Ready<()> + Send + Sync + 'static) {
+        let count = Arc::new(AtomicU64::new(0));
+        let runs = count.clone();
+        (count, move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_repeating_task_coalesces_missed_runs() {
+        let (queue, clock) = queue();
+        let (runs, job) = counter();
+        let handle = queue.schedule_repeating("stats flush", Duration::from_secs(3600), TaskPriority::Low, job);
+
+        assert_eq!(queue.run_due().await, 0);
+        clock.advance(chrono::Duration::hours(1));
+        assert_eq!(queue.run_due().await, 1);
+        assert_eq!(queue.run_due().await, 0);
+
+        // Suspended for five and a half hours: one run, and the schedule keeps its phase.
+        clock.advance(chrono::Duration::minutes(330));
+        assert_eq!(queue.run_due().await, 1);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let info = &queue.scheduled_tasks()[0];
+        assert_eq!(info.next_run, Utc.with_ymd_and_hms(2026, 3, 4, 17, 0, 0).unwrap());
+        assert_eq!(info.schedule, "every 1h");
+        assert_eq!(info.runs, 2);
+
+        handle.cancel();
+        assert!(!handle.is_active());
+        clock.advance(chrono::Duration::hours(2));
+        assert_eq!(queue.run_due().await, 0);
+        assert!(queue.scheduled_tasks().is_empty());
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cron_task_runs_once_after_suspension() {
+        let (queue, clock) = queue();
+        let (runs, job) = counter();
+        let handle = queue.schedule_cron("nightly snapshot", "0 3 * * *", TaskPriority::Background, job).unwrap();
+        assert!(queue.schedule_cron("bad", "0 3 1 * *", TaskPriority::Low, || async {}).is_err());
+        assert_eq!(queue.scheduled_tasks()[0].next_run, Utc.with_ymd_and_hms(2026, 3, 5, 3, 0, 0).unwrap());
+
+        // Three nights pass while suspended.
+        clock.advance(chrono::Duration::days(3));
+        assert_eq!(queue.run_due().await, 1);
+        assert_eq!(queue.run_due().await, 0);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.scheduled_tasks()[0].next_run, Utc.with_ymd_and_hms(2026, 3, 8, 3, 0, 0).unwrap());
+
+        assert!(queue.cancel(handle.id()));
+        assert!(!handle.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_due_jobs_run_by_priority() {
+        let (queue, clock) = queue();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for (name, priority) in [("low", TaskPriority::Low), ("critical", TaskPriority::Critical), ("normal", TaskPriority::Normal)] {
+            let order = order.clone();
+            queue.schedule_repeating(name, Duration::from_secs(60), priority, move || {
+                order.lock().unwrap().push(name);
+                std::future::ready(())
+            });
+        }
+        clock.advance(chrono::Duration::minutes(1));
+        queue.run_due().await;
+        assert_eq!(*order.lock().unwrap(), vec!["critical", "normal", "low"]);
+    }
+}
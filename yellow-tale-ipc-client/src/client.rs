@@ -0,0 +1,227 @@
+//! Transports that carry request envelopes to the core
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+
+use yellow_tale::core::ipc::transport::{self, LocalStream};
+use yellow_tale::core::ipc::{IpcRequest, IpcResponse, IpcServer};
+
+use crate::error::IpcClientError;
+
+/// Sends request envelopes to the core
+///
+/// Implementations only move envelopes; `IpcClientExt` builds them from
+/// typed params and decodes the responses.
+#[async_trait]
+pub trait IpcClient: Send + Sync {
+    async fn send(&self, request: IpcRequest) -> Result<IpcResponse, IpcClientError>;
+}
+
+/// Turn a response to request `id` into the command's typed result
+pub fn decode<T: DeserializeOwned>(command: &str, id: Uuid, response: IpcResponse) -> Result<T, IpcClientError> {
+    if response.id != id {
+        return Err(IpcClientError::Transport(format!(
+            "Response {} does not answer {} request {}", response.id, command, id
+        )));
+    }
+    if let Some(error) = IpcClientError::from_response(&response) {
+        return Err(error);
+    }
+    if response.deprecated {
+        warn!(
+            "IPC command {} is deprecated, use {}",
+            command,
+            response.replacement.as_deref().unwrap_or("its replacement"),
+        );
+    }
+    serde_json::from_value(response.data.unwrap_or_default())
+        .map_err(|e| IpcClientError::Serialization(format!("Invalid {} response: {}", command, e)))
+}
+
+/// Calls `IpcServer::handle` directly, for a UI running in the same process
+pub struct InProcessClient {
+    server: Mutex<IpcServer>,
+}
+
+impl InProcessClient {
+    pub fn new(server: IpcServer) -> Self {
+        Self { server: Mutex::new(server) }
+    }
+}
+
+#[async_trait]
+impl IpcClient for InProcessClient {
+    async fn send(&self, request: IpcRequest) -> Result<IpcResponse, IpcClientError> {
+        Ok(self.server.lock().await.handle(request).await)
+    }
+}
+
+/// Newline-delimited JSON envelopes over a byte stream
+///
+/// Lines that are not responses, such as events, are skipped.
+pub struct StreamClient<S> {
+    stream: Mutex<BufReader<S>>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> StreamClient<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream: Mutex::new(BufReader::new(stream)) }
+    }
+}
+
+impl StreamClient<TcpStream> {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, IpcClientError> {
+        let stream = TcpStream::connect(addr).await
+            .map_err(|e| IpcClientError::Transport(e.to_string()))?;
+        Ok(Self::new(stream))
+    }
+}
+
+impl StreamClient<LocalStream> {
+    /// Connect to the core's socket or named pipe and authenticate with the
+    /// token in `token_path`
+    pub async fn connect_local(endpoint: &Path, token_path: &Path) -> Result<Self, IpcClientError> {
+        let transport_error = |e: std::io::Error| IpcClientError::Transport(e.to_string());
+        let token = tokio::fs::read_to_string(token_path).await.map_err(transport_error)?;
+        let mut stream = BufReader::new(transport::connect_stream(endpoint).await.map_err(transport_error)?);
+        transport::handshake(&mut stream, token.trim()).await.map_err(transport_error)?;
+        Ok(Self { stream: Mutex::new(stream) })
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> IpcClient for StreamClient<S> {
+    async fn send(&self, request: IpcRequest) -> Result<IpcResponse, IpcClientError> {
+        let transport = |e: std::io::Error| IpcClientError::Transport(e.to_string());
+
+        let mut line = serde_json::to_string(&request)
+            .map_err(|e| IpcClientError::Serialization(e.to_string()))?;
+        line.push('\n');
+
+        // Held until the response arrives so answers can't be interleaved
+        let mut stream = self.stream.lock().await;
+        stream.get_mut().write_all(line.as_bytes()).await.map_err(transport)?;
+        stream.get_mut().flush().await.map_err(transport)?;
+
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.map_err(transport)? == 0 {
+                return Err(IpcClientError::Transport("Connection closed".to_string()));
+            }
+            match serde_json::from_str::<IpcResponse>(&line) {
+                Ok(response) if response.id == request.id => return Ok(response),
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::IpcClientExt;
+    use crate::messages::GetInviteCode;
+    use yellow_tale::core::{
+        cache::CacheManager, diagnostics::DiagnosticsCollector, ipc::IPC_VERSION,
+        launcher::LauncherService, profiles::ProfileManager, sessions::SessionOrchestrator,
+    };
+
+    fn in_process() -> InProcessClient {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-client-{}", Uuid::new_v4()));
+        InProcessClient::new(IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(dir.join("profiles")),
+            CacheManager::new(dir.join("cache"), 1024 * 1024 * 1024),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_in_process_client_decodes_results_and_errors() {
+        let client = in_process();
+
+        let version = client.get_version().await.unwrap();
+        assert_eq!(version.ipc_version, IPC_VERSION);
+
+        assert_eq!(
+            client.send_command(GetInviteCode {}).await.unwrap_err(),
+            IpcClientError::CommandFailed("Not in a session".to_string()),
+        );
+
+        let response = client.send(IpcRequest {
+            id: Uuid::new_v4(),
+            version: IPC_VERSION.to_string(),
+            command: "fly".to_string(),
+            params: serde_json::json!({}),
+        }).await.unwrap();
+        assert_eq!(IpcClientError::from_response(&response), Some(IpcClientError::UnknownCommand("fly".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_stream_client_skips_events_and_matches_ids() {
+        let (near, far) = tokio::io::duplex(4096);
+        let client = StreamClient::new(near);
+
+        // Answers each request after pushing an event and a stale response
+        tokio::spawn(async move {
+            let mut far = BufReader::new(far);
+            let mut line = String::new();
+            while far.read_line(&mut line).await.unwrap_or(0) > 0 {
+                let request: IpcRequest = serde_json::from_str(&line).unwrap();
+                let event = serde_json::json!({ "version": IPC_VERSION, "event": "ping_updated", "data": {} });
+                let stale = IpcResponse::success(Uuid::new_v4(), serde_json::json!({}));
+                let answer = IpcResponse::success(request.id, serde_json::json!({ "version": "0.1.0", "ipc_version": IPC_VERSION }));
+                for message in [event, serde_json::to_value(stale).unwrap(), serde_json::to_value(answer).unwrap()] {
+                    far.get_mut().write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+                }
+                line.clear();
+            }
+        });
+
+        let version = client.get_version().await.unwrap();
+        assert_eq!(version.version, "0.1.0");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_local_client_talks_to_a_listening_core() {
+        use std::sync::Arc;
+        use yellow_tale::core::ipc::transport::{default_endpoint, default_token_path, IpcListener};
+
+        // Kept short: socket paths are limited to about 100 bytes
+        let dir = std::path::PathBuf::from("/tmp").join(format!("yt-ipc-{}", &Uuid::new_v4().simple().to_string()[..8]));
+        let server = IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(dir.join("profiles")),
+            CacheManager::new(dir.join("cache"), 1024 * 1024 * 1024),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        );
+        let listener = IpcListener::bind(default_endpoint(&dir), default_token_path(&dir), Arc::new(Mutex::new(server)))
+            .await
+            .unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let serving = tokio::spawn(listener.serve(async {
+            let _ = stopped.await;
+        }));
+
+        let client = StreamClient::connect_local(&default_endpoint(&dir), &default_token_path(&dir)).await.unwrap();
+        assert_eq!(client.get_version().await.unwrap().ipc_version, IPC_VERSION);
+        assert_eq!(
+            client.send_command(GetInviteCode {}).await.unwrap_err(),
+            IpcClientError::CommandFailed("Not in a session".to_string()),
+        );
+
+        let _ = stop.send(());
+        serving.await.unwrap().unwrap();
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
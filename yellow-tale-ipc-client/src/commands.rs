@@ -0,0 +1,955 @@
+//! The command table: which params go with which command and what comes back
+//!
+//! Each row names a command exactly as the core's registry does. The tests
+//! check the table against `registry::COMMANDS`, so a command added to the
+//! core without a row here, or a param renamed on one side only, fails them.
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+use yellow_tale::core::{
+    client::{NotificationPage, ServerPage},
+    diagnostics::{frame_pacing::FramePacingReport, DiagnosticsReport, MetricsSample},
+    ipc::{IpcRequest, IPC_VERSION},
+    install::{DetectedInstall, DetectionReport},
+    java::JavaRuntime,
+    launcher::{
+        safe_mode::{CrashRecoverySuggestion, LaunchRecommendation},
+        LastExit, LaunchConfig, ProcessState,
+    },
+    mods::{
+        activator::ActivationReport,
+        analyzer::ConflictReport,
+        profile_sync::{ProfileSyncReport, SyncedModProfile},
+        scanner::ScanResult,
+    },
+    netdiag::{PingHistory, PingResult},
+    performance::{readiness::LaunchReadiness, SystemSnapshot},
+    pond::{QueueStatus, ServerDiscovery},
+    preload::{PrefetchStatus, PreloadStatus},
+    sessions::NatReport,
+    settings_sync::{SyncReport, SyncStatus},
+    snapshots::{ScheduleStatus, SnapshotReport},
+    updates::UpdateCheck,
+    util::lru::Evicted,
+    users::{search::UserSearchPage, LoginRequest, SignupRequest, User},
+};
+use yellow_tale_core::features::FeatureGateState;
+
+use crate::client::{decode, IpcClient};
+use crate::error::IpcClientError;
+use crate::messages::*;
+
+/// Params of one IPC command
+pub trait IpcCommand: Serialize + DeserializeOwned + Send + 'static {
+    /// Command name as the core's registry lists it
+    const NAME: &'static str;
+
+    /// The response's `data`
+    type Output: Serialize + DeserializeOwned + Send;
+}
+
+/// Rows are `name(arg: Params) -> Output;`, or `name() -> Output = Params;`
+/// for commands without params
+macro_rules! ipc_commands {
+    (@params $arg:ident) => { $arg };
+    (@params ; $params:ty) => { <$params>::default() };
+    ($(
+        $(#[$meta:meta])*
+        $name:ident($($arg:ident: $params:ty)?) -> $output:ty $(= $unit:ty)?;
+    )*) => {
+        $(
+            $(impl IpcCommand for $params {
+                const NAME: &'static str = stringify!($name);
+                type Output = $output;
+            })?
+            $(impl IpcCommand for $unit {
+                const NAME: &'static str = stringify!($name);
+                type Output = $output;
+            })?
+        )*
+
+        /// Every command the typed helpers send
+        pub const COMMAND_NAMES: &[&str] = &[$(stringify!($name)),*];
+
+        /// A typed method per command, for any [`IpcClient`]
+        #[async_trait]
+        pub trait IpcClientExt: IpcClient {
+            /// Send `command` and decode its response
+            async fn send_command<C: IpcCommand>(&self, command: C) -> Result<C::Output, IpcClientError> {
+                let id = Uuid::new_v4();
+                let params = serde_json::to_value(&command)
+                    .map_err(|e| IpcClientError::Serialization(format!("Invalid {} params: {}", C::NAME, e)))?;
+                let response = self.send(IpcRequest {
+                    id,
+                    version: IPC_VERSION.to_string(),
+                    command: C::NAME.to_string(),
+                    params,
+                }).await?;
+                decode(C::NAME, id, response)
+            }
+
+            $(
+                $(#[$meta])*
+                async fn $name(&self $(, $arg: $params)?) -> Result<$output, IpcClientError> {
+                    self.send_command(ipc_commands!(@params $($arg)? $(; $unit)?)).await
+                }
+            )*
+        }
+
+        impl<T: IpcClient + ?Sized> IpcClientExt for T {}
+    };
+}
+
+ipc_commands! {
+    // System
+    get_version() -> VersionInfo = GetVersion;
+    get_capabilities() -> Capabilities = GetCapabilities;
+    negotiate_version() -> VersionNegotiation = NegotiateVersion;
+    /// Game state and the current session
+    get_status() -> Status = GetStatus;
+    get_database_status() -> DatabaseState = GetDatabaseStatus;
+    batch(params: Batch) -> BatchResults;
+
+    // Launcher
+    launch_game(config: LaunchConfig) -> LaunchResult;
+    /// Deprecated; use `get_status`
+    get_game_state() -> ProcessState = GetGameState;
+    terminate_game() -> TerminateResult = TerminateGame;
+    get_launch_recommendation(params: GetLaunchRecommendation) -> LaunchRecommendation;
+    get_crash_recovery_suggestion(params: GetCrashRecoverySuggestion) -> CrashRecoverySuggestion;
+    get_last_exit() -> LastExit = GetLastExit;
+    validate_launch(params: ValidateLaunch) -> LaunchValidation;
+
+    // Profiles
+    list_profiles() -> ProfileList = ListProfiles;
+    get_profile(params: GetProfile) -> ProfileSummary;
+    create_profile(params: CreateProfile) -> ProfileSummary;
+    update_profile(params: UpdateProfile) -> ProfileSummary;
+    delete_profile(params: DeleteProfile) -> ProfileDeletion;
+
+    // Mods
+    list_mods() -> ModList = ListMods;
+    install_mod(params: InstallMod) -> ModChange;
+    enable_mod(params: EnableMod) -> ModChange;
+    disable_mod(params: DisableMod) -> ModChange;
+    /// Only deletes with `confirm`; otherwise reports what would go
+    remove_mod(params: RemoveMod) -> ModRemoval;
+
+    // Cache
+    get_cache_stats() -> CacheStats = GetCacheStats;
+    clear_cache() -> ClearCacheResult = ClearCache;
+    cache_prune() -> Evicted = CachePrune;
+    verify_cache() -> VerifyCacheStarted = VerifyCache;
+
+    // Performance
+    get_system_snapshot(params: GetSystemSnapshot) -> SystemSnapshot;
+    /// Only observes; nothing is done to other processes
+    prepare_for_launch(params: PrepareForLaunch) -> LaunchReadiness;
+
+    // Diagnostics
+    collect_metrics() -> MetricsSample = CollectMetrics;
+    get_diagnostics_report() -> DiagnosticsReport = GetDiagnosticsReport;
+    export_diagnostics(params: ExportDiagnostics) -> DiagnosticsExport;
+    analyze_performance(params: AnalyzePerformance) -> PerformanceAnalysis;
+    analyze_frame_log(params: AnalyzeFrameLog) -> FramePacingReport;
+
+    // Sessions
+    create_session(params: CreateSession) -> SessionInfo;
+    join_session(params: JoinSession) -> JoinSessionResult;
+    get_session_info(params: GetSessionInfo) -> SessionDetails;
+    get_invite_code() -> InviteCode = GetInviteCode;
+    leave_session() -> LeaveSessionResult = LeaveSession;
+    detect_nat() -> NatReport = DetectNat;
+    transfer_host(params: TransferHost) -> TransferHostResult;
+
+    // Users
+    signup(request: SignupRequest) -> AuthResult;
+    login(request: LoginRequest) -> AuthResult;
+    logout(params: Logout) -> LogoutResult;
+    /// Fails with `IpcClientError::AccessTokenExpired` when `refresh_session` can renew it
+    validate_session(params: ValidateSession) -> User;
+    refresh_session(params: RefreshSession) -> AuthResult;
+    change_password(params: ChangePassword) -> PasswordChanged;
+    list_sessions(params: ListSessions) -> AccountSessions;
+    revoke_session(params: RevokeSession) -> SessionsRevoked;
+    request_email_verification(params: RequestEmailVerification) -> VerificationCodeSent;
+    confirm_email(params: ConfirmEmail) -> EmailConfirmed;
+    search_users(params: SearchUsers) -> UserSearchPage;
+    get_current_user(params: GetCurrentUser) -> User;
+    update_user_profile(params: UpdateUserProfile) -> User;
+
+    // Friends
+    send_friend_request(params: SendFriendRequest) -> FriendRequestSent;
+    accept_friend_request(params: AcceptFriendRequest) -> AcceptFriendRequestResult;
+    decline_friend_request(params: DeclineFriendRequest) -> DeclineFriendRequestResult;
+    remove_friend(params: RemoveFriend) -> RemoveFriendResult;
+    get_friends(params: GetFriends) -> FriendList;
+    get_pending_requests(params: GetPendingRequests) -> PendingRequests;
+    get_online_friends(params: GetOnlineFriends) -> FriendList;
+    subscribe_presence(params: SubscribePresence) -> PresenceSubscribed;
+    poll_events(params: PollEvents) -> PolledEvents;
+    block_user(params: BlockUser) -> BlockUserResult;
+    unblock_user(params: UnblockUser) -> UnblockUserResult;
+    get_blocked_users(params: GetBlockedUsers) -> BlockedUsers;
+
+    // Relay
+    start_relay_server(params: StartRelayServer) -> RelayAddress;
+    stop_relay_server() -> StopResult = StopRelayServer;
+    get_relay_status() -> RelayStatus = GetRelayStatus;
+    reset_relay_metrics() -> RelayMetricsReset = ResetRelayMetrics;
+    connect_to_relay() -> RelayConnection = ConnectToRelay;
+    disconnect_from_relay() -> RelayDisconnection = DisconnectFromRelay;
+
+    // Settings sync
+    sync_now(params: SyncNow) -> SyncReport;
+    get_sync_status() -> SyncStatus = GetSyncStatus;
+    update_sync_section(params: UpdateSyncSection) -> UpdateSyncSectionResult;
+
+    // Mod profiles
+    activate_mod_profile(params: ActivateModProfile) -> ActivationReport;
+    scan_mods(params: ScanMods) -> ScanResult;
+    detect_mod_conflicts() -> ConflictReport = DetectModConflicts;
+    optimize_load_order() -> LoadOrderResult = OptimizeLoadOrder;
+    list_mod_profiles() -> ModProfiles = ListModProfiles;
+    save_mod_profile(params: SaveModProfile) -> SyncedModProfile;
+    sync_profiles(params: SyncProfiles) -> ProfileSyncReport;
+    get_profile_sync_status() -> ProfileSyncReport = GetProfileSyncStatus;
+
+    // Game installations
+    detect_installations() -> DetectionReport = DetectInstallations;
+    set_game_path(params: SetGamePath) -> DetectedInstall;
+
+    // Java runtimes
+    list_java_runtimes(params: ListJavaRuntimes) -> JavaRuntimes;
+    provision_java_runtime(params: ProvisionJavaRuntime) -> JavaRuntime;
+    set_profile_java(params: SetProfileJava) -> ProfileJava;
+    set_java_path(params: SetJavaPath) -> JavaPath;
+    install_managed_java(params: InstallManagedJava) -> ManagedJava;
+
+    // Snapshots
+    get_snapshot_schedule() -> ScheduleStatus = GetSnapshotSchedule;
+    set_snapshot_schedule(params: SetSnapshotSchedule) -> ScheduleStatus;
+    prune_snapshots() -> PrunedSnapshots = PruneSnapshots;
+    get_snapshots() -> SnapshotReport = GetSnapshots;
+    create_snapshot() -> CreatedSnapshot = CreateSnapshot;
+    restore_snapshot(params: RestoreSnapshot) -> RestoredSnapshot;
+    delete_snapshot(params: DeleteSnapshot) -> DeletedSnapshot;
+
+    // Feature gates
+    refresh_feature_gates(params: RefreshFeatureGates) -> FeatureGateState;
+    get_feature_state() -> FeatureState = GetFeatureState;
+
+    // Launcher updates
+    check_for_updates() -> UpdateCheck = CheckForUpdates;
+    download_update() -> UpdateDownload = DownloadUpdate;
+    get_update_progress() -> UpdateStatus = GetUpdateProgress;
+
+    // World hosting
+    start_local_server(params: StartLocalServer) -> HostedWorld;
+    stop_local_server() -> StopResult = StopLocalServer;
+    get_hosting_status() -> HostingState = GetHostingStatus;
+
+    // Notifications
+    get_notifications(params: GetNotifications) -> NotificationPage;
+
+    // Config
+    validate_config(params: ValidateConfig) -> ConfigValidation;
+
+    // Attestation
+    get_attestation() -> Attestation = GetAttestation;
+    attestation_heartbeat(params: AttestationHeartbeat) -> AttestationHeartbeatResult;
+
+    // Asset preload
+    preload_server_assets(params: PreloadServerAssets) -> PreloadStarted;
+    get_preload_status() -> PreloadStatus = GetPreloadStatus;
+    discover_server_capabilities(params: DiscoverServerCapabilities) -> ServerDiscovery;
+    prefetch_server_assets(params: PrefetchServerAssets) -> PrefetchStarted;
+    get_prefetch_status() -> PrefetchStatus = GetPrefetchStatus;
+    join_queue(params: JoinQueue) -> QueueStatus;
+    get_queue_status() -> QueueStatus = GetQueueStatus;
+    leave_queue() -> LeaveQueueResult = LeaveQueue;
+
+    // Ping measurement
+    ping_server(params: PingServer) -> PingResult;
+    get_ping_history(params: GetPingHistory) -> PingHistory;
+    set_server_favorite(params: SetServerFavorite) -> FavoriteResult;
+    favorite_server(params: FavoriteServer) -> FavoriteResult;
+    unfavorite_server(params: UnfavoriteServer) -> UnfavoriteResult;
+    list_favorites() -> FavoriteList = ListFavorites;
+
+    // Server directory
+    search_servers(params: SearchServers) -> ServerPage;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+    use yellow_tale::core::{
+        db::supervisor::DatabaseStatus,
+        ipc::registry::{self, ParamKind},
+        launcher::safe_mode::SafeModeReport,
+        netdiag::PingStats,
+        updates::UpdateProgress,
+    };
+    use yellow_tale_core::features::{PondGates, YellowTaleGates};
+
+    const AT: &str = "2026-10-17T12:00:00Z";
+    const ID: &str = "6f1c2a7e-93b4-4d1a-8f3e-2b5c9d0e7a14";
+    const OTHER_ID: &str = "0d4e8b2a-5c6f-47a9-b1d3-e9f0a2c4b6d8";
+
+    fn matches_kind(kind: ParamKind, value: &Value) -> bool {
+        match kind {
+            ParamKind::String => value.is_string(),
+            ParamKind::Uuid => value.as_str().is_some_and(|s| Uuid::parse_str(s).is_ok()),
+            ParamKind::Integer => value.is_u64() || value.is_i64(),
+            ParamKind::Boolean => value.is_boolean(),
+            ParamKind::Array => value.is_array(),
+            ParamKind::Object => value.is_object(),
+            ParamKind::Any => true,
+        }
+    }
+
+    /// Both halves survive a round trip unchanged and the params are the
+    /// ones the registry lists for the command
+    fn check<C: IpcCommand>(params: Value, data: Value) -> &'static str {
+        let spec = registry::find(C::NAME).unwrap_or_else(|| panic!("{} is not in the registry", C::NAME));
+
+        let typed: C = serde_json::from_value(params.clone())
+            .unwrap_or_else(|e| panic!("{} params don't decode: {}", C::NAME, e));
+        let sent = serde_json::to_value(&typed).unwrap();
+        assert_eq!(sent, params, "{} params changed in a round trip", C::NAME);
+
+        let sent = sent.as_object().unwrap_or_else(|| panic!("{} params are not an object", C::NAME));
+        for (name, value) in sent {
+            let param = spec.params.iter()
+                .find(|p| p.name == name)
+                .unwrap_or_else(|| panic!("{} sends '{}', which the registry doesn't list", C::NAME, name));
+            assert!(
+                matches_kind(param.kind, value) || (!param.required && value.is_null()),
+                "{} sends '{}' as {}, the registry expects {:?}", C::NAME, name, value, param.kind,
+            );
+        }
+        for param in spec.params.iter().filter(|p| p.required) {
+            assert!(sent.contains_key(param.name), "{} doesn't send required '{}'", C::NAME, param.name);
+        }
+
+        let output: C::Output = serde_json::from_value(data.clone())
+            .unwrap_or_else(|e| panic!("{} response doesn't decode: {}", C::NAME, e));
+        assert_eq!(serde_json::to_value(&output).unwrap(), data, "{} response changed in a round trip", C::NAME);
+
+        C::NAME
+    }
+
+    fn user() -> Value {
+        json!({
+            "id": ID, "username": "anna", "display_name": "Anna", "email": "anna@example.com",
+            "avatar_url": null, "status": "online", "created_at": AT, "last_seen_at": null,
+            "email_verified": true,
+        })
+    }
+
+    fn metrics() -> Value {
+        json!({
+            "timestamp": AT, "cpu_usage": 12.5, "cpu_per_core": [25.0, 0.0],
+            "ram_used_mb": 4096, "ram_total_mb": 16384, "disk_read_bytes": 0, "disk_write_bytes": 512,
+            "swap_used_mb": 0, "gpu_usage": null, "frame_time_ms": 16.5, "asset_streaming": false,
+            "game_cpu_usage": 8.0, "game_memory_mb": 3072,
+        })
+    }
+
+    fn stats(value: f64) -> Value {
+        json!({ "min": value, "avg": value, "max": value, "p95": value })
+    }
+
+    fn finding() -> Value {
+        json!({
+            "category": "cpu_saturation",
+            "confidence": 0.75,
+            "evidence_window": { "start": AT, "end": AT, "samples": 30 },
+            "summary": "Cores 2 and 3 pinned",
+            "suggested_action": { "setting": "cpu_affinity", "cores": [2, 3] },
+        })
+    }
+
+    fn recommendation() -> Value {
+        json!({
+            "profile_id": "default", "level": "normal", "consecutive_crashes": 0,
+            "safe_mode_suggested": false, "suspected_mod": null, "last_exit": null, "reason": null,
+        })
+    }
+
+    fn hosting_status() -> Value {
+        json!({
+            "state": "running", "world_name": "default", "port": 25565, "pid": 77, "uptime_seconds": 60,
+            "players_online": 1, "players": ["anna"], "max_players": 8, "restarts": 0,
+        })
+    }
+
+    fn merged(mut base: Value, extra: Value) -> Value {
+        base.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        base
+    }
+
+    fn fixtures() -> Vec<&'static str> {
+        let snapshot = json!({
+            "total_ram_mb": 16384, "available_ram_mb": 9000, "cpu_cores": 2, "cpu_usage": [35.0, 10.0],
+            "process_count": 280,
+            "game_drive": { "mount_point": "/games", "total_mb": 500000, "available_mb": 100 },
+            "heavy_processes": [{ "pid": 4321, "name": "encoder", "cpu_usage": 180.0, "memory_mb": 900 }],
+        });
+        let frame_pacing = json!({
+            "source": "/tmp/frames.csv", "analyzed_at": AT, "frames": 1000, "skipped_rows": 2,
+            "duration_ms": 16800.0, "avg_fps": 59.5, "avg_frame_time_ms": 16.8, "median_frame_time_ms": 16.5,
+            "low_1_percent_fps": 58.8, "low_0_1_percent_fps": 20.0,
+            "frame_time_variance": 2.25, "frame_time_std_dev_ms": 1.5,
+            "stutter_count": 1, "stutters": [{ "frame": 100, "frame_time_ms": 50.0, "median_ms": 16.5 }],
+        });
+        let synced_profile = json!({
+            "id": ID, "name": "PvP", "description": null, "updated_at": AT, "base_updated_at": null,
+            "mods": [{ "id": "minimap", "file_name": null, "download_url": null, "sha256": null }],
+        });
+        let profile_sync = json!({
+            "last_synced_at": AT, "last_error": null, "next_retry_at": null,
+            "profiles": [
+                { "id": ID, "name": "PvP", "status": "conflict", "copy_id": ID, "copy_name": "PvP (local copy)" },
+                { "id": ID, "name": "PvP (local copy)", "status": "pushed" },
+            ],
+        });
+        let install = json!({
+            "path": "/games/Hytale/install/release/package/game/latest", "root": "/games/Hytale",
+            "executable": "/games/Hytale/install/release/package/game/latest/Client/HytaleClient.jar",
+            "version": "2026.1.4", "confidence": "high", "source": "manual",
+        });
+        let java = json!({ "home": "/opt/java", "version": "21.0.2", "major_version": 21, "vendor": "Eclipse Adoptium", "arch": "x64", "managed": true });
+        let friend = json!({
+            "user_id": ID, "username": "anna", "display_name": "Anna", "avatar_url": null,
+            "status": "online", "last_seen_at": null, "friendship_since": AT,
+        });
+        let gates = json!({
+            "tier": "free", "origin": "defaults", "fetched_at": null, "stale": false, "last_error": null,
+            "gates": {
+                "tier": "free",
+                "yellow_tale": serde_json::to_value(YellowTaleGates::default()).unwrap(),
+                "pond": serde_json::to_value(PondGates::default()).unwrap(),
+            },
+        });
+        let profile = json!({ "id": ID, "name": "Modded", "created_at": AT });
+        let installed_mod = json!({
+            "id": "Hytale:Minimap", "name": "Minimap", "version": "2.1.0", "enabled": true,
+            "file_name": "minimap-2.1.0.jar", "readable": true,
+        });
+        let session = json!({ "session_id": ID, "invite_code": "ABCD-1234" });
+        let traffic = json!({
+            "messages_relayed": 1000, "bytes_relayed": 131072, "broadcast_messages": 700,
+            "directed_messages": 300, "messages_per_sec": 12.5, "bytes_per_sec": 1600.0,
+        });
+        let limits = json!({
+            "max_frame_bytes": 65536, "messages_per_sec": 50, "message_burst": 100,
+            "bytes_per_sec": 524288, "byte_burst": 1048576, "max_violations": 10,
+        });
+        let auth = json!({ "user": user(), "session": {
+            "token": "t0k3n", "expires_at": AT, "refresh_token": "r3fr3sh", "refresh_expires_at": AT,
+        } });
+        let hello = json!({ "type": "hello", "manifest": "eyJ9", "signature": "c2ln", "public_key": "a2V5" });
+        let empty = json!({});
+        let schedule = json!({
+            "schedule": {
+                "scheduled": true, "interval_minutes": 60, "keep_last": 3, "keep_daily_days": 7,
+                "only_when_game_stopped": false, "max_total_mb": 2048,
+            },
+            "next_run_at": AT,
+            "last_run_at": AT,
+            "last_run": { "outcome": "deferred", "reason": "The game is running" },
+        });
+
+        vec![
+            check::<GetVersion>(empty.clone(), json!({ "version": "0.1.0", "ipc_version": IPC_VERSION })),
+            check::<GetCapabilities>(empty.clone(), registry::capabilities()),
+            check::<NegotiateVersion>(empty.clone(), registry::version_negotiation("1.1.0".parse().unwrap())),
+            check::<GetStatus>(empty.clone(), json!({
+                "game_state": { "Running": { "pid": 4242 } }, "in_session": true, "session_id": ID, "overall": "degraded",
+                "components": [{ "name": "relay", "status": "degraded", "detail": "timeout", "last_checked": AT }],
+            })),
+            check::<GetDatabaseStatus>(empty.clone(), json!({ "status": DatabaseStatus::Degraded })),
+            check::<Batch>(
+                json!({ "requests": [{ "id": ID, "version": IPC_VERSION, "command": "get_invite_code", "params": {} }] }),
+                json!({ "responses": [{
+                    "id": ID, "version": IPC_VERSION, "success": false, "error": "Not in a session", "data": null,
+                }] }),
+            ),
+
+            check::<LaunchConfig>(
+                json!({
+                    "executable_path": "/usr/bin/java", "working_dir": "/games/hytale/Client",
+                    "args": ["-Xmx4096m", "-jar", "HytaleClient.jar"], "env_vars": { "HYTALE_FPS": "144" },
+                    "inherit_env": true, "profile_id": "default", "safe_mode": true,
+                    "shader_cache_dir": null, "java_runtime": null, "mods_dir": null, "mods": [],
+                }),
+                json!({
+                    "pid": 4242,
+                    "recommendation": recommendation(),
+                    "safe_mode": serde_json::to_value(SafeModeReport::default()).unwrap(),
+                }),
+            ),
+            check::<GetGameState>(empty.clone(), json!("Idle")),
+            check::<TerminateGame>(empty.clone(), json!({ "terminated": true })),
+            check::<GetLaunchRecommendation>(json!({ "profile_id": "default" }), recommendation()),
+            check::<GetCrashRecoverySuggestion>(json!({ "profile_id": "modded" }), json!({
+                "profile_id": "modded",
+                "safe_mode_recommended": true,
+                "mods_at_crash": ["minimap", "shaders_plus"],
+                "crashed_at": "2026-01-05T18:30:00Z",
+                "consecutive_crashes": 1,
+                "reason": "The last launch crashed after 12s with 2 mods enabled",
+            })),
+            check::<GetLastExit>(empty.clone(), json!({ "exit_code": null, "crashed": false, "runtime_seconds": 95, "terminated_by_user": true })),
+            check::<ValidateLaunch>(
+                json!({
+                    "executable_path": "/games/hytale/HytaleClient", "working_dir": null, "args": [], "env_vars": {},
+                    "inherit_env": true, "profile_id": "pvp", "safe_mode": false, "shader_cache_dir": null,
+                    "java_runtime": null, "mods_dir": "/games/hytale/mods", "mods": ["minimap"],
+                }),
+                json!({ "valid": false, "problems": [{ "kind": "mod_missing", "message": "Mod minimap is no longer in /games/hytale/mods" }] }),
+            ),
+
+            check::<ListProfiles>(empty.clone(), json!({ "profiles": [profile] })),
+            check::<GetProfile>(json!({ "id": ID }), profile.clone()),
+            check::<CreateProfile>(json!({ "name": "Modded" }), profile.clone()),
+            check::<UpdateProfile>(json!({ "id": ID, "name": "Survival", "settings": { "max_heap_mb": 4096 } }), profile.clone()),
+            check::<DeleteProfile>(json!({ "id": ID }), json!({ "id": ID, "deleted": true })),
+
+            check::<ListMods>(empty.clone(), json!({ "mods": [installed_mod.clone()] })),
+            check::<InstallMod>(json!({ "path": "/downloads/minimap-2.1.0.jar" }), json!({ "mod": installed_mod.clone() })),
+            check::<EnableMod>(json!({ "mod_id": "Hytale:Minimap" }), json!({ "mod": installed_mod.clone() })),
+            check::<DisableMod>(json!({ "mod_id": "Hytale:Minimap" }), json!({ "mod": installed_mod.clone() })),
+            check::<RemoveMod>(json!({ "mod_id": "Hytale:Minimap", "confirm": true }), json!({
+                "mod": installed_mod, "removed": true, "confirm_required": false,
+            })),
+
+            check::<GetCacheStats>(empty.clone(), json!({
+                "entry_count": 3, "total_size": 1024, "evicted_bytes": 4096,
+                "verification": { "running": true, "total": 3, "checked": 1, "corrupted": 0 },
+            })),
+            check::<ClearCache>(empty.clone(), json!({ "cleared": true })),
+            check::<CachePrune>(empty.clone(), json!({ "keys": ["9f86d081884c7d65"], "bytes": 2048 })),
+            check::<VerifyCache>(empty.clone(), json!({ "started": true, "total": 3 })),
+
+            check::<GetSystemSnapshot>(json!({ "game_path": "/games/hytale/HytaleClient" }), snapshot.clone()),
+            check::<PrepareForLaunch>(json!({ "profile_id": ID, "ram_allocation_mb": 8192 }), json!({
+                "ready": false,
+                "blockers": [{ "kind": "low_disk_space", "message": "Only 100 MB is free on /games" }],
+                "warnings": [{ "kind": "competing_process", "message": "encoder (pid 4321) is using 180% CPU" }],
+                "snapshot": snapshot,
+                "cache": { "pruned_bytes": 2048, "warmed_files": 3, "warmed_bytes": 65536 },
+            })),
+
+            check::<CollectMetrics>(empty.clone(), metrics()),
+            check::<GetDiagnosticsReport>(empty.clone(), json!({
+                "generated_at": AT, "launcher_version": "0.1.0",
+                "system_info": {
+                    "os_name": "Linux", "os_version": "6.1", "cpu_model": "Ryzen 7",
+                    "cpu_cores": 8, "total_ram_mb": 16384, "disks": [],
+                },
+                "metrics_history": [metrics()], "summary": {
+                    "window_start": AT, "window_end": AT, "samples": 1,
+                    "cpu_usage": stats(12.5), "ram_used_mb": stats(4096.0),
+                    "disk_read_bytes": stats(0.0), "disk_write_bytes": stats(512.0),
+                    "game_cpu_usage": stats(8.0), "game_memory_mb": null,
+                },
+                "game_metrics": null, "recent_logs": [], "findings": [finding()],
+                "game_exits": [{
+                    "profile_id": "default", "launched_at": AT, "exited_at": AT, "uptime_secs": 95,
+                    "state": { "Crashed": { "reason": "Exit code: 1" } }, "safe_mode": false, "exit_code": 1,
+                    "mods": ["minimap"],
+                }],
+                "crash_rates": {
+                    "with_mods": { "runs": 1, "crashes": 1 },
+                    "without_mods": { "runs": 0, "crashes": 0 },
+                },
+                "frame_pacing": frame_pacing.clone(),
+                "config": { "launcher": { "shutdown_timeout_secs": 10 } },
+            })),
+            check::<ExportDiagnostics>(
+                json!({ "path": "/tmp/report.json.gz", "compress": true }),
+                json!({ "path": "/tmp/report.json.gz", "bytes": 2048, "compressed": true }),
+            ),
+            check::<AnalyzePerformance>(json!({ "cpu_affinity": [2, 3], "max_heap_mb": 4096 }), json!({ "findings": [finding()] })),
+            check::<AnalyzeFrameLog>(json!({ "path": "/tmp/frames.csv" }), frame_pacing),
+
+            check::<CreateSession>(json!({ "name": "Anna", "max_participants": 4, "password": "hunter2" }), session.clone()),
+            check::<JoinSession>(
+                json!({ "invite_code": "ABCD-1234", "name": "Anna", "server": "play.example.com", "password": "hunter2" }),
+                merged(session.clone(), json!({ "warnings": ["ambient.ogg is still downloading"] })),
+            ),
+            check::<GetSessionInfo>(empty.clone(), json!({
+                "id": ID, "invite_code": "ABCD-1234",
+                "host": {
+                    "id": ID, "name": "Anna", "connection": "Hybrid", "p2p_state": "Idle",
+                    "joined_at": AT, "latency_ms": null,
+                },
+                "participants": [{
+                    "id": ID, "name": "Ben", "connection": "Relay", "p2p_state": { "Failed": { "reason": "symmetric NAT" } },
+                    "joined_at": AT, "latency_ms": 38,
+                }],
+                "max_participants": 8, "state": "Open", "created_at": AT, "metadata": {},
+            })),
+            check::<GetInviteCode>(empty.clone(), json!({ "invite_code": "ABCD-1234" })),
+            check::<LeaveSession>(empty.clone(), json!({ "left": true })),
+            check::<TransferHost>(json!({ "participant_id": ID }), json!({ "requested": true })),
+            check::<DetectNat>(empty.clone(), json!({
+                "nat_type": "restricted", "external_ip": "203.0.113.7", "external_port": 40000,
+                "internal_ip": "192.168.1.20", "stun_server": "stun.l.google.com:19302", "detected_at": AT,
+            })),
+
+            check::<SignupRequest>(
+                json!({ "username": "anna", "display_name": "Anna", "email": "anna@example.com", "password": "hunter22" }),
+                auth.clone(),
+            ),
+            check::<LoginRequest>(json!({ "username_or_email": "anna", "password": "hunter22", "device_info": "desktop" }), auth.clone()),
+            check::<Logout>(json!({ "token": "t0k3n" }), json!({ "logged_out": true })),
+            check::<ValidateSession>(json!({ "token": "t0k3n" }), user()),
+            check::<RefreshSession>(json!({ "refresh_token": "r3fr3sh" }), auth.clone()),
+            check::<ChangePassword>(
+                json!({ "token": "t0k3n", "old_password": "hunter22", "new_password": "Hunter222" }),
+                json!({ "changed": true, "sessions_revoked": 2 }),
+            ),
+            check::<ListSessions>(json!({ "token": "t0k3n" }), json!({
+                "sessions": [{
+                    "id": ID, "device_info": "desktop", "ip_address": null,
+                    "created_at": AT, "last_used_at": AT, "expires_at": AT,
+                }],
+                "current_session_id": ID,
+            })),
+            check::<RevokeSession>(json!({ "token": "t0k3n", "session_id": OTHER_ID }), json!({ "sessions_revoked": 1 })),
+            check::<RequestEmailVerification>(json!({ "token": "t0k3n" }), json!({ "sent": true, "expires_at": AT })),
+            check::<ConfirmEmail>(json!({ "token": "t0k3n", "code": "042917" }), json!({ "email_verified": true })),
+            check::<SearchUsers>(
+                json!({ "query": "ann", "limit": 20, "cursor": "1:0:abc", "token": "t0k3n" }),
+                json!({ "users": [user()], "next_cursor": null }),
+            ),
+            check::<GetCurrentUser>(json!({ "token": "t0k3n" }), user()),
+            check::<UpdateUserProfile>(json!({ "user_id": ID, "display_name": "Anna B", "avatar_url": "https://example.com/a.png" }), user()),
+
+            check::<SendFriendRequest>(json!({ "from_user_id": ID, "to_user_id": OTHER_ID, "message": "hey it's Anna" }), json!({ "request_id": OTHER_ID })),
+            check::<AcceptFriendRequest>(json!({ "user_id": ID, "from_user_id": OTHER_ID }), json!({ "accepted": true })),
+            check::<DeclineFriendRequest>(json!({ "user_id": ID, "from_user_id": OTHER_ID }), json!({ "declined": true })),
+            check::<RemoveFriend>(json!({ "user_id": ID, "friend_id": OTHER_ID }), json!({ "removed": true })),
+            check::<GetFriends>(json!({ "user_id": ID }), json!({ "friends": [friend.clone()] })),
+            check::<GetPendingRequests>(json!({ "user_id": ID }), json!({ "requests": [{
+                "id": OTHER_ID, "from_user_id": ID, "from_username": "anna", "from_display_name": "Anna",
+                "from_avatar_url": null, "message": "hey it's Anna", "created_at": AT, "expires_at": AT,
+            }] })),
+            check::<GetOnlineFriends>(json!({ "user_id": ID }), json!({ "friends": [friend] })),
+            check::<SubscribePresence>(json!({ "user_id": ID }), json!({ "subscribed": true })),
+            check::<PollEvents>(json!({}), json!({ "events": [{
+                "version": "1.33.0", "event": "friend_presence_changed",
+                "data": { "user_id": OTHER_ID, "status": "online", "changed_at": AT },
+            }] })),
+            check::<BlockUser>(json!({ "blocker_id": ID, "blocked_id": OTHER_ID, "reason": "spam" }), json!({ "blocked": true })),
+            check::<UnblockUser>(json!({ "blocker_id": ID, "blocked_id": OTHER_ID }), json!({ "unblocked": true })),
+            check::<GetBlockedUsers>(json!({ "user_id": ID }), json!({ "blocked": [{
+                "user_id": OTHER_ID, "username": "spammer", "blocked_at": AT, "reason": null,
+            }] })),
+
+            check::<StartRelayServer>(
+                json!({ "address": "0.0.0.0:9000", "auth_required": true, "limits": limits.clone() }),
+                json!({ "address": "0.0.0.0:9000", "auth_required": true }),
+            ),
+            check::<StopRelayServer>(empty.clone(), json!({ "stopped": true })),
+            check::<GetRelayStatus>(empty.clone(), json!({
+                "running": true, "address": "0.0.0.0:9000", "session_count": 1, "peer_count": 1, "auth_required": false,
+                "limits": limits,
+                "traffic": traffic.clone(),
+                "sessions": [{
+                    "id": "s1", "host_id": ID, "peer_count": 1, "max_peers": 8, "password_required": true, "created_at": AT, "traffic": traffic,
+                    "peers": [{ "user_id": ID, "username": "host", "is_host": true, "joined_at": AT, "latency_ms": 42, "encryption": false }],
+                }],
+            })),
+            check::<ResetRelayMetrics>(empty.clone(), json!({ "reset": true })),
+            check::<ConnectToRelay>(empty.clone(), json!({ "relay_address": "0.0.0.0:9000", "note": "Use WebSocket client" })),
+            check::<DisconnectFromRelay>(empty.clone(), json!({ "disconnected": true, "note": "Close the WebSocket" })),
+
+            check::<SyncNow>(json!({ "token": "t0k3n" }), json!({
+                "etag": "e1", "synced_at": AT, "pulled": ["waypoints"], "pulled_data": { "waypoints": { "home": [0, 64, 0] } }, "conflicts": [],
+                "skipped_sections": [], "size_bytes": 10, "quota_bytes": 100,
+            })),
+            check::<GetSyncStatus>(empty.clone(), json!({
+                "device_id": "desk", "etag": null, "last_synced_at": null, "sections": ["performance_settings"],
+                "pending_sections": [], "last_conflicts": [], "last_error": null,
+            })),
+            check::<UpdateSyncSection>(json!({ "section": "waypoints", "data": { "home": [0, 64, 0] } }), json!({ "updated": true })),
+
+            check::<ActivateModProfile>(
+                json!({
+                    "profile": {
+                        "id": "modded", "name": "Modded",
+                        "mods": [{ "id": "minimap", "file_name": null, "download_url": null, "sha256": null }],
+                    },
+                    "dry_run": true,
+                }),
+                serde_json::to_value(ActivationReport::default()).unwrap(),
+            ),
+            check::<ScanMods>(json!({ "full": true }), serde_json::to_value(ScanResult::default()).unwrap()),
+            check::<DetectModConflicts>(empty.clone(), json!({
+                "profile_id": "pvp",
+                "checked": 2,
+                "conflicts": [{
+                    "kind": "incompatible",
+                    "severity": "error",
+                    "mods": ["hud_plus", "old_hud"],
+                    "subject": null,
+                    "message": "hud_plus is incompatible with old_hud",
+                    "resolutions": [{ "action": "disable", "mod_id": "old_hud", "file_name": "old_hud-1.0.jar" }],
+                }],
+                "errors": 1,
+                "warnings": 0,
+            })),
+            check::<OptimizeLoadOrder>(empty.clone(), json!({
+                "profile_id": "pvp",
+                "changed": true,
+                "before": ["hud_plus", "chat_api"],
+                "after": ["chat_api", "hud_plus"],
+                "unresolved": [{
+                    "mod_id": "hud_plus",
+                    "requires": "chat_api",
+                    "version_requirement": "^2.0",
+                    "installed": "1.4.0",
+                }],
+            })),
+            check::<ListModProfiles>(empty.clone(), json!({ "profiles": [synced_profile.clone()] })),
+            check::<SaveModProfile>(
+                json!({ "id": ID, "name": "PvP", "mods": [{ "id": "minimap", "file_name": null, "download_url": null, "sha256": null }] }),
+                synced_profile,
+            ),
+            check::<SyncProfiles>(json!({ "token": "t0k3n" }), profile_sync.clone()),
+            check::<GetProfileSyncStatus>(empty.clone(), profile_sync),
+
+            check::<DetectInstallations>(empty.clone(), json!({
+                "installs": [install.clone()],
+                "rejected": [{ "path": "/games/old", "source": "config_hint", "missing": ["Assets.zip"] }],
+            })),
+            check::<SetGamePath>(json!({ "path": "/games/Hytale" }), install),
+
+            check::<ListJavaRuntimes>(json!({ "requirement": ">=17" }), json!({ "runtimes": [java.clone()], "suitable": ["/opt/java"] })),
+            check::<ProvisionJavaRuntime>(json!({ "major_version": 21 }), java.clone()),
+            check::<SetProfileJava>(json!({ "profile_id": "default", "java_home": "/opt/java" }), json!({ "runtime": java.clone() })),
+            check::<SetJavaPath>(
+                json!({ "path": "/opt/java/bin/java", "requirement": ">=17", "profile_id": "default" }),
+                json!({ "runtime": java.clone() }),
+            ),
+            check::<InstallManagedJava>(json!({ "requirement": ">=17" }), json!({ "runtime": java, "installed": true })),
+            check::<GetSnapshotSchedule>(empty.clone(), schedule.clone()),
+            check::<SetSnapshotSchedule>(json!({ "keep_last": 3, "max_total_mb": 2048, "only_when_game_stopped": false }), schedule),
+            check::<PruneSnapshots>(empty.clone(), json!({ "removed": [ID] })),
+            check::<GetSnapshots>(empty.clone(), json!({
+                "snapshots": [{
+                    "id": ID, "created_at": AT, "trigger": "scheduled",
+                    "targets": [{ "name": "worlds", "path": "/data/worlds" }],
+                    "file_count": 12, "size_bytes": 5120, "unique_bytes": 1024,
+                }],
+                "logical_bytes": 5120,
+                "stored_bytes": 5120,
+            })),
+            check::<CreateSnapshot>(empty.clone(), json!({ "id": ID, "created_at": AT, "size_bytes": 5120 })),
+            check::<RestoreSnapshot>(json!({ "id": ID, "force": true }), json!({ "safety_snapshot": ID })),
+            check::<DeleteSnapshot>(json!({ "id": ID }), json!({ "freed_bytes": 1024 })),
+
+            check::<RefreshFeatureGates>(json!({ "token": "t0k3n", "force": true }), gates.clone()),
+            check::<GetFeatureState>(empty.clone(), merged(gates, json!({ "features": [{
+                "id": "ping", "name": "Ping", "description": "Server ping", "tier": "Free",
+                "requires_game_api": false, "enabled": true, "category": "Launcher",
+            }] }))),
+
+            check::<CheckForUpdates>(empty.clone(), json!({
+                "current_version": "0.1.0", "channel": "stable", "update_available": true,
+                "latest": {
+                    "version": "0.2.0", "channel": "stable", "platform": "linux-x86_64",
+                    "url": "https://example.com/0.2.0", "sha256": "ab12", "size": 1024,
+                    "changelog": "Fixes", "published_at": AT,
+                },
+                "changelog": [{ "version": "0.2.0", "channel": "stable", "changelog": "Fixes", "published_at": AT }],
+            })),
+            check::<DownloadUpdate>(empty.clone(), json!({ "version": "0.2.0", "total_bytes": 1024 })),
+            check::<GetUpdateProgress>(empty.clone(), merged(
+                serde_json::to_value(UpdateProgress::default()).unwrap(),
+                json!({
+                    "staged": { "version": "0.2.0", "path": "/tmp/update", "sha256": "ab12", "staged_at": AT },
+                    "rollback": null,
+                }),
+            )),
+
+            check::<StartLocalServer>(
+                json!({ "world_name": "default", "port": 25565, "max_players": 8, "motd": "Hello", "host_name": "Anna" }),
+                merged(hosting_status(), session),
+            ),
+            check::<StopLocalServer>(empty.clone(), json!({ "stopped": true })),
+            check::<GetHostingStatus>(empty.clone(), merged(hosting_status(), json!({ "invite_code": null }))),
+
+            check::<GetNotifications>(
+                json!({ "token": "t0k3n", "unread_only": true, "limit": 20 }),
+                json!({ "notifications": [], "unread_count": 0 }),
+            ),
+
+            check::<ValidateConfig>(json!({ "content": "config_version = 3" }), json!({
+                "migrated_from": null,
+                "errors": [{ "field": "cache.max_size_bytes", "message": "Must be at least 256 MiB", "line": 4 }],
+                "warnings": [],
+                "valid": false,
+            })),
+
+            check::<GetAttestation>(empty.clone(), json!({
+                "install_id": ID,
+                "manifest": {
+                    "install_id": ID, "launcher_version": "0.1.0",
+                    "binaries": [{ "name": "yellow-tale", "sha256": "ab12" }],
+                    "mods": [{ "id": "minimap", "sha256": "cd34" }],
+                    "created_at": AT,
+                },
+                "message": hello,
+            })),
+            check::<AttestationHeartbeat>(json!({ "token": "t0k3n" }), json!({
+                "message": { "type": "heartbeat", "token": "t0k3n", "sequence": 3, "signature": "c2ln" },
+            })),
+
+            check::<PreloadServerAssets>(json!({ "server": "play.example.com" }), json!({ "started": true })),
+            check::<GetPreloadStatus>(empty.clone(), serde_json::to_value(PreloadStatus::default()).unwrap()),
+            check::<PrefetchServerAssets>(json!({ "server": "play.example.com" }), json!({ "started": true, "assets": 12 })),
+            check::<GetPrefetchStatus>(empty.clone(), json!({
+                "server": "play.example.com", "phase": "paused", "paused_reason": "The game is running",
+                "items_total": 12, "items_done": 9, "bytes_total": 48000000, "bytes_done": 36000000,
+                "speed_bytes_per_sec": 0,
+                "failed": [{ "path": "textures/sky.png", "sha256": "ab12", "attempts": 3, "error": "Download failed for textures/sky.png: 404" }],
+            })),
+            check::<JoinQueue>(
+                json!({ "server": "play.example.com", "user_id": Uuid::nil(), "token": "t" }),
+                json!({
+                    "server": "play.example.com", "phase": "waiting", "position": 3, "queue_length": 12,
+                    "estimated_wait_secs": 90, "priority": false, "error": null,
+                }),
+            ),
+            check::<GetQueueStatus>(empty.clone(), serde_json::to_value(QueueStatus::default()).unwrap()),
+            check::<LeaveQueue>(empty.clone(), json!({ "left": true })),
+            check::<DiscoverServerCapabilities>(json!({ "address": "play.example.com:25566", "latency_ms": 40 }), json!({
+                "protocol_version": 1,
+                "capabilities": {
+                    "features": ["cosmetics", "queue_priority"], "api_version": "1.2.0",
+                    "cosmetics": { "supported_types": ["skin"], "max_file_size_mb": 5, "allows_animated": true, "allows_custom": false },
+                    "performance": {
+                        "recommended_render_distance": 12, "recommended_entity_distance": 64, "server_tick_rate": 20,
+                        "adaptive_throttling": true, "texture_streaming": true, "chunk_preload_radius": 4,
+                    },
+                    "connectivity": {
+                        "connection_pooling": true, "keep_alive_interval_ms": 15000, "ping_optimization": true,
+                        "route_selection": false, "session_handoff": false, "reconnect_grace_period_secs": 30,
+                        "queue_position_tracking": true,
+                    },
+                    "sync": {
+                        "friend_activity": true, "player_presence": true, "session_transfer": false,
+                        "profile_sync": false, "settings_sync": false, "achievement_sync": false,
+                    },
+                },
+                "preload": { "assets": [] },
+                "network_hints": {
+                    "recommended_update_rate": 20, "interpolation_delay_ms": 90, "prediction_enabled": false,
+                    "compression_level": 1, "batch_updates": false,
+                },
+            })),
+
+            check::<PingServer>(json!({ "address": "play.example.com", "port": 25565, "server_id": "srv-1" }), json!({
+                "server_id": "srv-1", "address": "play.example.com", "port": 25565, "measured_at": AT,
+                "tcp": serde_json::to_value(PingStats::default()).unwrap(), "udp_rtt_ms": null, "quality": "unreachable",
+            })),
+            check::<GetPingHistory>(json!({ "server_id": "srv-1" }), serde_json::to_value(PingHistory::default()).unwrap()),
+            check::<SetServerFavorite>(
+                json!({ "server_id": "srv-1", "address": "play.example.com", "port": 25565, "favorite": true }),
+                json!({ "favorite": true }),
+            ),
+            check::<FavoriteServer>(
+                json!({ "server_id": "srv-1", "address": "play.example.com", "port": 25565 }),
+                json!({ "favorite": true }),
+            ),
+            check::<UnfavoriteServer>(json!({ "server_id": "srv-1" }), json!({ "favorite": false, "removed": true })),
+            check::<ListFavorites>(empty.clone(), json!({
+                "favorites": [{
+                    "server_id": "srv-1", "address": "play.example.com", "port": 25565,
+                    "last_ping": { "at": AT, "avg_ms": 41.5, "jitter_ms": 2.0, "loss_percent": 0.0, "quality": "good" },
+                }],
+            })),
+
+            check::<SearchServers>(
+                json!({ "game_mode": "creative", "tags": ["europe"], "has_slots": true, "q": "castle", "page": 1, "per_page": 20 }),
+                json!({
+                    "servers": [{
+                        "id": ID,
+                        "name": "Castle Builders",
+                        "description": "Creative plots",
+                        "address": "play.example.com",
+                        "port": 25565,
+                        "max_players": 50,
+                        "current_players": 12,
+                        "game_mode": "creative",
+                        "tags": ["europe", "plots"],
+                        "owner_id": ID,
+                        "is_online": true,
+                        "last_ping": AT,
+                        "created_at": AT,
+                    }],
+                    "total": 1,
+                    "page": 1,
+                    "per_page": 20,
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_table_matches_registry() {
+        let registered: HashSet<&str> = registry::COMMANDS.iter().map(|c| c.name).collect();
+        let typed: HashSet<&str> = COMMAND_NAMES.iter().copied().collect();
+        assert_eq!(typed.len(), COMMAND_NAMES.len(), "a command has two rows");
+        assert_eq!(typed, registered);
+    }
+
+    #[test]
+    fn test_every_command_round_trips() {
+        let checked = fixtures();
+        let unique: HashSet<&str> = checked.iter().copied().collect();
+        assert_eq!(unique.len(), checked.len(), "a command was checked twice");
+        assert_eq!(unique, COMMAND_NAMES.iter().copied().collect());
+    }
+
+    #[test]
+    fn test_optional_params_left_out_are_not_sent() {
+        assert_eq!(serde_json::to_value(AnalyzePerformance::default()).unwrap(), json!({}));
+        assert_eq!(
+            serde_json::to_value(SearchUsers::new("ann").with_limit(5)).unwrap(),
+            json!({ "query": "ann", "limit": 5 }),
+        );
+        assert_eq!(
+            serde_json::to_value(PingServer::new("play.example.com").with_server_id("srv-1")).unwrap(),
+            json!({ "address": "play.example.com", "server_id": "srv-1" }),
+        );
+    }
+
+    #[test]
+    fn test_launch_config_builder() {
+        let config = LaunchConfig::new("/usr/bin/java")
+            .with_working_dir("/games/hytale/Client")
+            .with_args(["-Xmx4096m"])
+            .with_args(["-jar", "HytaleClient.jar"])
+            .with_env("HYTALE_FPS", "144")
+            .with_profile("default")
+            .with_safe_mode(true);
+        assert_eq!(serde_json::to_value(config).unwrap(), json!({
+            "executable_path": "/usr/bin/java", "working_dir": "/games/hytale/Client",
+            "args": ["-Xmx4096m", "-jar", "HytaleClient.jar"], "env_vars": { "HYTALE_FPS": "144" },
+            "inherit_env": true, "profile_id": "default", "safe_mode": true,
+            "shader_cache_dir": null, "java_runtime": null, "mods_dir": null, "mods": [],
+        }));
+    }
+}
@@ -0,0 +1,144 @@
+use thiserror::Error;
+use yellow_tale::core::ipc::IpcResponse;
+use yellow_tale::core::users::AuthError;
+
+/// Why a command did not produce its typed result
+///
+/// Errors from the core keep the core's message, so `to_string()` shows
+/// exactly what the core said.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IpcClientError {
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+
+    /// The core rejected the params
+    #[error("{0}")]
+    InvalidParameters(String),
+
+    /// The service behind the command is not set up or not reachable
+    #[error("{0}")]
+    Unavailable(String),
+
+    #[error("{0}")]
+    CommandFailed(String),
+
+    #[error("Version mismatch: expected {expected}, got {actual}")]
+    VersionMismatch { expected: String, actual: String },
+
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
+
+    /// The access token ran out; `refresh_session` can renew the session
+    #[error("{0}")]
+    AccessTokenExpired(String),
+
+    /// Too many wrong passwords; `retry_after` is in seconds
+    #[error("Too many failed attempts; try again in {retry_after}s")]
+    TooManyAttempts { retry_after: u64 },
+
+    /// Params could not be encoded or the response did not match its type
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    /// The request never got an answer
+    #[error("Transport error: {0}")]
+    Transport(String),
+}
+
+impl IpcClientError {
+    /// Classifies the error message of a failed response
+    pub fn from_message(message: &str) -> Self {
+        if let Some(command) = message.strip_prefix("Unknown command: ") {
+            return Self::UnknownCommand(command.to_string());
+        }
+        if let Some((expected, actual)) = message.strip_prefix("Version mismatch: expected ")
+            .and_then(|rest| rest.split_once(", got "))
+        {
+            return Self::VersionMismatch { expected: expected.to_string(), actual: actual.to_string() };
+        }
+        if let Some(version) = message.strip_prefix("Invalid version: ") {
+            return Self::InvalidVersion(version.to_string());
+        }
+        if message == AuthError::AccessTokenExpired.to_string() {
+            return Self::AccessTokenExpired(message.to_string());
+        }
+        if let Some(retry_after) = message.strip_prefix("Too many failed attempts; try again in ")
+            .and_then(|rest| rest.strip_suffix('s'))
+            .and_then(|secs| secs.parse().ok())
+        {
+            return Self::TooManyAttempts { retry_after };
+        }
+
+        let message = message.to_string();
+        // "Missing 'token' parameter", "Invalid user IDs", "'port' must be ..."
+        if message.starts_with("Missing '") || message.starts_with("Invalid ") || message.starts_with('\'') {
+            Self::InvalidParameters(message)
+        } else if message.ends_with(" not available")
+            || message.ends_with(" not configured")
+            || message == "Database temporarily unavailable"
+        {
+            Self::Unavailable(message)
+        } else {
+            Self::CommandFailed(message)
+        }
+    }
+
+    /// The error a response carries, if it failed
+    pub fn from_response(response: &IpcResponse) -> Option<Self> {
+        if response.success {
+            return None;
+        }
+        Some(Self::from_message(response.error.as_deref().unwrap_or("Command failed")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    use yellow_tale::core::ipc::IpcError;
+
+    #[test]
+    fn test_core_errors_map_to_variants() {
+        let cases = [
+            (IpcError::UnknownCommand("fly".into()).to_string(), IpcClientError::UnknownCommand("fly".into())),
+            (
+                IpcError::VersionMismatch { expected: "1.x".into(), actual: "2.0.0".into() }.to_string(),
+                IpcClientError::VersionMismatch { expected: "1.x".into(), actual: "2.0.0".into() },
+            ),
+            (IpcError::InvalidVersion("one".into()).to_string(), IpcClientError::InvalidVersion("one".into())),
+            (
+                AuthError::AccessTokenExpired.to_string(),
+                IpcClientError::AccessTokenExpired(AuthError::AccessTokenExpired.to_string()),
+            ),
+            (
+                AuthError::TooManyAttempts { retry_after: 30 }.to_string(),
+                IpcClientError::TooManyAttempts { retry_after: 30 },
+            ),
+            ("Missing 'token' parameter".into(), IpcClientError::InvalidParameters("Missing 'token' parameter".into())),
+            ("Invalid user IDs".into(), IpcClientError::InvalidParameters("Invalid user IDs".into())),
+            (
+                "'port' must be between 1 and 65535".into(),
+                IpcClientError::InvalidParameters("'port' must be between 1 and 65535".into()),
+            ),
+            ("World hosting not available".into(), IpcClientError::Unavailable("World hosting not available".into())),
+            ("Server not configured".into(), IpcClientError::Unavailable("Server not configured".into())),
+            ("Not in a session".into(), IpcClientError::CommandFailed("Not in a session".into())),
+        ];
+        for (message, expected) in cases {
+            let error = IpcClientError::from_message(&message);
+            assert_eq!(error, expected);
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_successful_response_has_no_error() {
+        let id = Uuid::new_v4();
+        assert_eq!(IpcClientError::from_response(&IpcResponse::success(id, serde_json::json!({}))), None);
+        assert_eq!(
+            IpcClientError::from_response(&IpcResponse::error(id, "Game process not running")),
+            Some(IpcClientError::CommandFailed("Game process not running".into())),
+        );
+    }
+}
@@ -0,0 +1,34 @@
+//! Typed client for the Yellow Tale core IPC API
+//!
+//! Every command in the core's registry has a params type, a result type
+//! and a method on [`IpcClientExt`]:
+//!
+//! ```no_run
+//! # async fn run(client: &dyn yellow_tale_ipc_client::IpcClient) -> Result<(), yellow_tale_ipc_client::IpcClientError> {
+//! use yellow_tale_ipc_client::{IpcClientExt, LaunchConfig};
+//!
+//! let config = LaunchConfig::new("/usr/bin/java")
+//!     .with_working_dir("/games/hytale/Client")
+//!     .with_args(["-jar", "HytaleClient.jar"]);
+//! let launched = client.launch_game(config).await?;
+//! println!("pid {}", launched.pid);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`InProcessClient`] calls an `IpcServer` in the same process;
+//! [`StreamClient`] speaks newline-delimited JSON over a stream, such as the
+//! core's local socket via [`StreamClient::connect_local`].
+
+pub mod client;
+pub mod commands;
+pub mod error;
+pub mod messages;
+
+pub use client::{InProcessClient, IpcClient, StreamClient};
+pub use commands::{IpcClientExt, IpcCommand, COMMAND_NAMES};
+pub use error::IpcClientError;
+pub use messages::*;
+
+pub use yellow_tale::core::ipc::{IpcEvent, IpcRequest, IpcResponse, IpcServer, IPC_VERSION};
+pub use yellow_tale::core::launcher::LaunchConfig;
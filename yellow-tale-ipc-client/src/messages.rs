@@ -0,0 +1,1416 @@
+//! Request params and response data for every IPC command
+//!
+//! Where the core answers with one of its own types, that type is used
+//! as-is. The structs here cover params and the responses the core builds
+//! by hand, field for field.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use yellow_tale::core::{
+    config::ConfigReport,
+    db::supervisor::DatabaseStatus,
+    diagnostics::analysis::Finding,
+    friends::{BlockedUser, FriendInfo, FriendRequest},
+    health::{ComponentHealth, HealthStatus},
+    hosting::{HostingStatus, WorldHostConfig},
+    integrity::{AttestationMessage, IntegrityManifest},
+    ipc::{IpcEvent, IpcRequest, IpcResponse},
+    java::JavaRuntime,
+    launcher::{
+        safe_mode::{LaunchRecommendation, SafeModeReport},
+        validation::LaunchProblem,
+        LaunchConfig, ProcessState,
+    },
+    mods::{
+        activator::{ModProfileSpec, ProfileMod},
+        manager::InstalledMod,
+        profile_sync::SyncedModProfile,
+        resolver::Unresolved,
+    },
+    netdiag::FavoriteStatus,
+    relay::{RelayConfig, SessionInfo as RelaySessionInfo, TrafficStats},
+    sessions::Session,
+    settings_sync::SyncSection,
+    updates::{RollbackInfo, StagedUpdate, UpdateProgress},
+    users::User,
+    util::verify::VerifyStatus,
+};
+use yellow_tale_core::features::{FeatureGate, FeatureGateState};
+
+// System
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetVersion {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub ipc_version: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetCapabilities {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub ipc_version: String,
+    pub commands: Vec<CommandCapability>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandCapability {
+    pub name: String,
+    pub min_version: String,
+    /// JSON Schema for the params object
+    pub params: serde_json::Value,
+    pub deprecated: bool,
+    pub replacement: Option<String>,
+}
+
+/// Answered for any well-formed client version, even one the core rejects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NegotiateVersion {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionNegotiation {
+    pub server_version: String,
+    /// Oldest client version accepted
+    pub min_version: String,
+    /// Newest client version accepted
+    pub max_version: String,
+    pub compatible: bool,
+    /// Version responses are answered at; absent when not compatible
+    pub negotiated_version: Option<String>,
+    pub minors: Vec<MinorCommands>,
+}
+
+/// Commands a client at `version` (`major.minor`) can use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinorCommands {
+    pub version: String,
+    pub commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetStatus {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub game_state: ProcessState,
+    pub in_session: bool,
+    pub session_id: Option<String>,
+    /// The worst component's status; absent before IPC 1.14.0
+    #[serde(default)]
+    pub overall: HealthStatus,
+    #[serde(default)]
+    pub components: Vec<ComponentHealth>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetDatabaseStatus {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseState {
+    pub status: DatabaseStatus,
+}
+
+/// Requests run one after another in a single round trip; a failing one
+/// doesn't stop the rest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Batch {
+    pub requests: Vec<IpcRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResults {
+    /// One per request, in the same order
+    pub responses: Vec<IpcResponse>,
+}
+
+// Launcher
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchResult {
+    pub pid: u32,
+    /// Based on how earlier runs of the profile ended, before this launch
+    pub recommendation: LaunchRecommendation,
+    /// Present when the launch was in safe mode
+    pub safe_mode: Option<SafeModeReport>,
+}
+
+/// Deprecated in favour of [`GetStatus`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetGameState {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TerminateGame {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminateResult {
+    pub terminated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetLaunchRecommendation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetCrashRecoverySuggestion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetLastExit {}
+
+/// Checks `config` the way `launch_game` would, without launching
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidateLaunch {
+    #[serde(flatten)]
+    pub config: LaunchConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchValidation {
+    pub valid: bool,
+    pub problems: Vec<LaunchProblem>,
+}
+
+// Profiles
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListProfiles {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileList {
+    pub profiles: Vec<ProfileSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetProfile {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProfile {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateProfile {
+    pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteProfile {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileDeletion {
+    pub id: Uuid,
+    pub deleted: bool,
+}
+
+// Mods
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListMods {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModList {
+    pub mods: Vec<InstalledMod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallMod {
+    /// Package to copy into the mods directory
+    pub path: PathBuf,
+}
+
+/// A mod by id, or by file name when two files share an id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnableMod {
+    pub mod_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisableMod {
+    pub mod_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModChange {
+    #[serde(rename = "mod")]
+    pub module: InstalledMod,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveMod {
+    pub mod_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModRemoval {
+    #[serde(rename = "mod")]
+    pub module: InstalledMod,
+    pub removed: bool,
+    pub confirm_required: bool,
+}
+
+// Cache
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetCacheStats {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entry_count: usize,
+    pub total_size: u64,
+    /// Absent before IPC 1.21.0
+    #[serde(default)]
+    pub evicted_bytes: u64,
+    /// Absent before IPC 1.22.0
+    #[serde(default)]
+    pub verification: VerifyStatus,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClearCache {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearCacheResult {
+    pub cleared: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachePrune {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyCache {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyCacheStarted {
+    pub started: bool,
+    pub total: usize,
+}
+
+// Performance
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetSystemSnapshot {
+    /// Defaults to the config's `default_game_path`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrepareForLaunch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<Uuid>,
+    /// Overrides the profile's heap size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ram_allocation_mb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_path: Option<PathBuf>,
+}
+
+// Diagnostics
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollectMetrics {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetDiagnosticsReport {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportDiagnostics {
+    /// File the report is written to
+    pub path: PathBuf,
+    /// Gzip the report
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsExport {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub compressed: bool,
+}
+
+/// Setting either field records the game's launch settings before analysing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalyzePerformance {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_affinity: Option<Vec<usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_heap_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalyzeFrameLog {
+    /// PresentMon CSV or one frame time per line
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceAnalysis {
+    pub findings: Vec<Finding>,
+}
+
+// Sessions
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSession {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_participants: Option<u64>,
+    /// Joins must give this; needs a relay server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub invite_code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinSession {
+    pub invite_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Waits for the server's required assets before joining
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// For sessions created with one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl JoinSession {
+    pub fn new(invite_code: impl Into<String>) -> Self {
+        Self { invite_code: invite_code.into(), name: None, server: None, password: None }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_server(mut self, server: impl Into<String>) -> Self {
+        self.server = Some(server.into());
+        self
+    }
+
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinSessionResult {
+    pub session_id: String,
+    pub invite_code: String,
+    /// Streamable assets that were still missing
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetSessionInfo {
+    /// Ask the relay about this session instead of the current one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// The current session, or the relay's view of one when `session_id` was given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SessionDetails {
+    Current(Session),
+    Relay(RelaySessionInfo),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetInviteCode {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteCode {
+    pub invite_code: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LeaveSession {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaveSessionResult {
+    pub left: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DetectNat {}
+
+/// Hand hosting to another participant; the session changes once the relay
+/// confirms with a `session_host_changed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferHost {
+    pub participant_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferHostResult {
+    pub requested: bool,
+}
+
+// Users
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthResult {
+    pub user: User,
+    pub session: SessionToken,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionToken {
+    /// Access token, short-lived since IPC 1.29.0
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    /// Exchanged for a new pair with `refresh_session`; absent before IPC 1.29.0
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub refresh_expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Logout {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogoutResult {
+    pub logged_out: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidateSession {
+    pub token: String,
+}
+
+/// Trades a refresh token for a new token pair; the old pair stops working
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshSession {
+    pub refresh_token: String,
+}
+
+/// Signs out every other session once `old_password` checks out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangePassword {
+    pub token: String,
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordChanged {
+    pub changed: bool,
+    pub sessions_revoked: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSessions {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSessions {
+    pub sessions: Vec<AccountSession>,
+    /// The session `token` belongs to
+    pub current_session_id: Uuid,
+}
+
+/// A signed-in device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSession {
+    pub id: Uuid,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeSession {
+    pub token: String,
+    /// Leave out to sign out every session but this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionsRevoked {
+    pub sessions_revoked: u64,
+}
+
+/// Mails a six-digit code to the account's address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestEmailVerification {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationCodeSent {
+    pub sent: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmEmail {
+    pub token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfirmed {
+    pub email_verified: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchUsers {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// `next_cursor` from the previous page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Hides anyone with a block against the signed-in user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl SearchUsers {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self { query: query.into(), limit: None, cursor: None, token: None }
+    }
+
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetCurrentUser {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateUserProfile {
+    pub user_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+// Friends
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendFriendRequest {
+    pub from_user_id: Uuid,
+    pub to_user_id: Uuid,
+    /// Shown to the recipient with the request, up to 200 characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendRequestSent {
+    pub request_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptFriendRequest {
+    pub user_id: Uuid,
+    pub from_user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptFriendRequestResult {
+    pub accepted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclineFriendRequest {
+    pub user_id: Uuid,
+    pub from_user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclineFriendRequestResult {
+    pub declined: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveFriend {
+    pub user_id: Uuid,
+    pub friend_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveFriendResult {
+    pub removed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetFriends {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendList {
+    pub friends: Vec<FriendInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPendingRequests {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRequests {
+    pub requests: Vec<FriendRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetOnlineFriends {
+    pub user_id: Uuid,
+}
+
+/// Keep the user's friends' status changes for `poll_events`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscribePresence {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceSubscribed {
+    pub subscribed: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PollEvents {}
+
+/// `friend_presence_changed` events since the last poll, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolledEvents {
+    pub events: Vec<IpcEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockUser {
+    pub blocker_id: Uuid,
+    pub blocked_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockUserResult {
+    pub blocked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnblockUser {
+    pub blocker_id: Uuid,
+    pub blocked_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnblockUserResult {
+    pub unblocked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetBlockedUsers {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedUsers {
+    pub blocked: Vec<BlockedUser>,
+}
+
+// Relay
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StartRelayServer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Admit only joins carrying a valid account token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_required: Option<bool>,
+    /// Frame size and rate limits per connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<RelayConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayAddress {
+    pub address: String,
+    /// Absent before IPC 1.15.0
+    #[serde(default)]
+    pub auth_required: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StopRelayServer {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopResult {
+    pub stopped: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetRelayStatus {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayStatus {
+    pub running: bool,
+    pub address: Option<String>,
+    pub session_count: usize,
+    pub peer_count: usize,
+    /// Absent before IPC 1.15.0
+    #[serde(default)]
+    pub auth_required: bool,
+    /// Absent before IPC 1.37.0
+    #[serde(default)]
+    pub limits: RelayConfig,
+    /// Across all sessions; absent before IPC 1.38.0
+    #[serde(default)]
+    pub traffic: TrafficStats,
+    /// Absent before IPC 1.16.0
+    #[serde(default)]
+    pub sessions: Vec<RelaySessionInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResetRelayMetrics {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayMetricsReset {
+    pub reset: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConnectToRelay {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayConnection {
+    pub relay_address: Option<String>,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisconnectFromRelay {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayDisconnection {
+    pub disconnected: bool,
+    pub note: String,
+}
+
+// Settings sync
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncNow {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetSyncStatus {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSyncSection {
+    pub section: SyncSection,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSyncSectionResult {
+    pub updated: bool,
+}
+
+// Mod profiles
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivateModProfile {
+    pub profile: ModProfileSpec,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanMods {
+    /// Ignore the scan cache
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DetectModConflicts {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptimizeLoadOrder {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadOrderResult {
+    pub profile_id: String,
+    pub changed: bool,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    pub unresolved: Vec<Unresolved>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListModProfiles {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModProfiles {
+    pub profiles: Vec<SyncedModProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveModProfile {
+    /// Profile to edit; a new one is created without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub mods: Vec<ProfileMod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncProfiles {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetProfileSyncStatus {}
+
+// Game installations
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DetectInstallations {}
+
+/// Fails listing what's missing when `path` isn't a playable install
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetGamePath {
+    pub path: PathBuf,
+}
+
+// Java runtimes
+
+/// With a `requirement` like `">=17"`, the response lists the `suitable` homes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListJavaRuntimes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaRuntimes {
+    pub runtimes: Vec<JavaRuntime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suitable: Option<Vec<PathBuf>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvisionJavaRuntime {
+    pub major_version: u32,
+}
+
+/// Without `java_home` the profile goes back to the default runtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetProfileJava {
+    pub profile_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_home: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileJava {
+    pub runtime: Option<JavaRuntime>,
+}
+
+/// `path` may be a Java home or its `java` binary; `profile_id` defaults to
+/// `default`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetJavaPath {
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaPath {
+    pub runtime: JavaRuntime,
+}
+
+/// Progress arrives as `java_install_progress` events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallManagedJava {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub major_version: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedJava {
+    pub runtime: JavaRuntime,
+    /// False when an installed runtime already met the requirement
+    pub installed: bool,
+}
+
+// Snapshots
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetSnapshotSchedule {}
+
+/// Only the fields given change; the result is saved to `config.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetSnapshotSchedule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_minutes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_daily_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_when_game_stopped: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PruneSnapshots {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrunedSnapshots {
+    pub removed: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetSnapshots {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSnapshot {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedSnapshot {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// Files changed since the snapshot are only replaced with `force`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreSnapshot {
+    pub id: Uuid,
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoredSnapshot {
+    /// The data the restore replaced; None if there was none
+    pub safety_snapshot: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteSnapshot {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedSnapshot {
+    /// Bytes of blobs no other snapshot used
+    pub freed_bytes: u64,
+}
+
+// Feature gates
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RefreshFeatureGates {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Fetch even if the cached gates are fresh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetFeatureState {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureState {
+    #[serde(flatten)]
+    pub state: FeatureGateState,
+    pub features: Vec<FeatureGate>,
+}
+
+// Launcher updates
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckForUpdates {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DownloadUpdate {}
+
+/// The download continues in the background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateDownload {
+    pub version: String,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetUpdateProgress {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatus {
+    #[serde(flatten)]
+    pub progress: UpdateProgress,
+    pub staged: Option<StagedUpdate>,
+    pub rollback: Option<RollbackInfo>,
+}
+
+// World hosting
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StartLocalServer {
+    #[serde(flatten)]
+    pub config: WorldHostConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_name: Option<String>,
+}
+
+impl StartLocalServer {
+    pub fn new(config: WorldHostConfig) -> Self {
+        Self { config, host_name: None }
+    }
+
+    pub fn with_host_name(mut self, host_name: impl Into<String>) -> Self {
+        self.host_name = Some(host_name.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedWorld {
+    #[serde(flatten)]
+    pub status: HostingStatus,
+    pub session_id: String,
+    pub invite_code: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StopLocalServer {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetHostingStatus {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostingState {
+    #[serde(flatten)]
+    pub status: HostingStatus,
+    /// Set while the hosted world has a session
+    pub invite_code: Option<String>,
+}
+
+// Notifications
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetNotifications {
+    pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unread_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+// Config
+
+/// Without `content` the launcher's own config file is checked
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidateConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigValidation {
+    #[serde(flatten)]
+    pub report: ConfigReport,
+    pub valid: bool,
+}
+
+// Attestation
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetAttestation {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attestation {
+    pub install_id: Uuid,
+    pub manifest: IntegrityManifest,
+    pub message: AttestationMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationHeartbeat {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationHeartbeatResult {
+    pub message: AttestationMessage,
+}
+
+// Asset preload
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreloadServerAssets {
+    pub server: String,
+}
+
+/// Progress arrives as `preload_progress` events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreloadStarted {
+    pub started: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetPreloadStatus {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchServerAssets {
+    pub server: String,
+}
+
+/// Progress is read with `get_prefetch_status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchStarted {
+    pub started: bool,
+    /// Assets in the server's manifest, cached or not
+    pub assets: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetPrefetchStatus {}
+
+/// Position changes arrive as `queue_updated` events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinQueue {
+    pub server: String,
+    pub user_id: Uuid,
+    /// Session token, sent to claim priority
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetQueueStatus {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LeaveQueue {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaveQueueResult {
+    pub left: bool,
+}
+
+/// `address` may be a host, `host:port` or URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoverServerCapabilities {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u32>,
+}
+
+// Ping measurement
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingServer {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Keeps the result in this server's history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_id: Option<String>,
+}
+
+impl PingServer {
+    pub fn new(address: impl Into<String>) -> Self {
+        Self { address: address.into(), port: None, server_id: None }
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn with_server_id(mut self, server_id: impl Into<String>) -> Self {
+        self.server_id = Some(server_id.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPingHistory {
+    pub server_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetServerFavorite {
+    pub server_id: String,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FavoriteResult {
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FavoriteServer {
+    pub server_id: String,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl FavoriteServer {
+    pub fn new(server_id: impl Into<String>, address: impl Into<String>) -> Self {
+        Self { server_id: server_id.into(), address: address.into(), port: None }
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnfavoriteServer {
+    pub server_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnfavoriteResult {
+    pub favorite: bool,
+    /// False when the server wasn't a favorite
+    pub removed: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListFavorites {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FavoriteList {
+    pub favorites: Vec<FavoriteStatus>,
+}
+
+// Server directory
+
+/// Unset fields don't filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchServers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_mode: Option<String>,
+    /// Servers must carry every one of these
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Only servers below their player limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_slots: Option<bool>,
+    /// Text to find in the name or description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+}
+
+impl SearchServers {
+    pub fn with_game_mode(mut self, game_mode: impl Into<String>) -> Self {
+        self.game_mode = Some(game_mode.into());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_free_slots(mut self) -> Self {
+        self.has_slots = Some(true);
+        self
+    }
+
+    pub fn matching(mut self, q: impl Into<String>) -> Self {
+        self.q = Some(q.into());
+        self
+    }
+
+    pub fn page(mut self, page: u32, per_page: u32) -> Self {
+        self.page = Some(page);
+        self.per_page = Some(per_page);
+        self
+    }
+}
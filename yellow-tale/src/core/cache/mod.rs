@@ -0,0 +1,414 @@
+//! Content-addressed cache
+//!
+//! Files are stored under the SHA-256 of their content, so the same asset is
+//! kept once however many profiles or servers use it:
+//! - `put` stores new content and only refreshes content it already has
+//! - The cache stays under its size limit by evicting expired entries first,
+//!   then the least recently used ([`LruIndex`])
+//! - `prune` drops only expired entries
+//! - With integrity checks on, `get` re-hashes what it reads and treats a
+//!   mismatch as a miss; `verify_targets` feeds the background walk
+//!
+//! Sizes, access times and expiries are kept in `index.json` next to the
+//! files, replaced atomically with the previous copy kept as
+//! `index.json.bak`. Reads update access times in memory; they're saved
+//! with the next change.
+
+use std::path::PathBuf;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::core::util::lru::{Evicted, LruIndex};
+use crate::core::util::{read_with_backup, sha256_hash, write_atomic};
+use crate::core::util::verify::VerifyTarget;
+
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entry_count: usize,
+    pub total_size: u64,
+    /// Bytes evicted or pruned since startup
+    pub evicted_bytes: u64,
+}
+
+/// What `put` stored, and what it evicted to make room
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stored {
+    pub key: String,
+    pub evicted: Evicted,
+}
+
+pub struct CacheManager {
+    dir: PathBuf,
+    index: LruIndex,
+    verify_integrity: bool,
+}
+
+impl CacheManager {
+    pub fn new(dir: PathBuf, max_size_bytes: u64) -> Self {
+        Self { dir, index: LruIndex::new(max_size_bytes), verify_integrity: true }
+    }
+
+    /// Whether `get` re-hashes entries before returning them
+    pub fn with_verify_integrity(mut self, verify_integrity: bool) -> Self {
+        self.verify_integrity = verify_integrity;
+        self
+    }
+
+    /// Create the cache directory and load the index. Entries whose file is
+    /// gone are dropped, files the index doesn't know are adopted, and the
+    /// limit given to `new` applies from here on.
+    pub async fn init(&mut self) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let max_size_bytes = self.index.max_bytes();
+        if self.index_path().exists() {
+            match read_with_backup(&self.index_path(), |content| Ok(serde_json::from_slice::<LruIndex>(content)?)).await {
+                Ok(index) => self.index = index,
+                Err(e) => warn!("Cache index is unreadable ({}), rebuilding it", e),
+            }
+        }
+
+        let missing: Vec<String> = self.index.keys()
+            .filter(|key| !self.entry_path(key).exists())
+            .cloned()
+            .collect();
+        for key in &missing {
+            self.index.remove(key);
+        }
+
+        let now = Utc::now();
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !is_key(&name) || self.index.get(&name).is_some() {
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                self.index.insert(&name, metadata.len(), None, now);
+            }
+        }
+
+        let evicted = self.index.set_max_bytes(max_size_bytes, now);
+        self.delete_files(&evicted.keys).await;
+        self.save_index().await
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entry_count: self.index.len(),
+            total_size: self.index.total_bytes(),
+            evicted_bytes: self.index.evicted_bytes(),
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.index.get(key).is_some()
+    }
+
+    /// Store `data` under its SHA-256, evicting other entries if the cache
+    /// would overflow. Content that's already cached isn't written again;
+    /// its access time and `ttl` are renewed.
+    pub async fn put(&mut self, data: &[u8], ttl: Option<Duration>) -> anyhow::Result<Stored> {
+        let key = sha256_hash(data);
+        let path = self.entry_path(&key);
+        if !self.contains(&key) || !path.exists() {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            // Renamed into place, so a crash never leaves a partial entry
+            // under a digest it doesn't match
+            let temp = self.dir.join(format!(".{}.tmp", key));
+            tokio::fs::write(&temp, data).await?;
+            tokio::fs::rename(&temp, &path).await?;
+        }
+
+        let evicted = self.index.insert(&key, data.len() as u64, ttl, Utc::now());
+        self.delete_files(&evicted.keys).await;
+        self.save_index().await?;
+        Ok(Stored { key, evicted })
+    }
+
+    /// Read an entry and mark it used. `None` if it isn't cached, has
+    /// expired, or no longer matches its digest; a corrupted entry is
+    /// dropped so it gets fetched again.
+    pub async fn get(&mut self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        if !self.index.touch(key, Utc::now()) {
+            return Ok(None);
+        }
+        match tokio::fs::read(self.entry_path(key)).await {
+            Ok(data) if self.verify_integrity && sha256_hash(&data) != key => {
+                warn!("Cache entry {} is corrupted, dropping it", key);
+                self.forget(key);
+                self.save_index().await?;
+                Ok(None)
+            }
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.index.remove(key);
+                self.save_index().await?;
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Drop every expired entry
+    pub async fn prune(&mut self) -> anyhow::Result<Evicted> {
+        let evicted = self.index.prune(Utc::now());
+        if !evicted.keys.is_empty() {
+            self.delete_files(&evicted.keys).await;
+            self.save_index().await?;
+        }
+        Ok(evicted)
+    }
+
+    pub async fn clear(&mut self) -> anyhow::Result<()> {
+        let keys: Vec<String> = self.index.keys().cloned().collect();
+        self.delete_files(&keys).await;
+        self.index.clear();
+        self.save_index().await
+    }
+
+    /// Change the size limit, evicting entries if it went down
+    pub async fn set_max_size(&mut self, max_size_bytes: u64) -> anyhow::Result<Evicted> {
+        let evicted = self.index.set_max_bytes(max_size_bytes, Utc::now());
+        self.delete_files(&evicted.keys).await;
+        self.save_index().await?;
+        Ok(evicted)
+    }
+
+    /// Every entry with the digest its file should hash to, for
+    /// `verify_files`
+    pub fn verify_targets(&self) -> Vec<VerifyTarget> {
+        let mut targets: Vec<VerifyTarget> = self.index.keys()
+            .map(|key| VerifyTarget {
+                key: key.clone(),
+                path: self.entry_path(key),
+                sha256: key.clone(),
+            })
+            .collect();
+        targets.sort_by(|a, b| a.key.cmp(&b.key));
+        targets
+    }
+
+    /// Drop an entry found missing or corrupted. Its file is deleted now;
+    /// the index is saved with the next change.
+    pub fn forget(&mut self, key: &str) {
+        if self.index.remove(key).is_some() {
+            let path = self.entry_path(key);
+            if let Err(e) = std::fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Could not delete cache entry {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    async fn save_index(&self) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        write_atomic(&self.index_path(), serde_json::to_vec(&self.index)?).await?;
+        Ok(())
+    }
+
+    async fn delete_files(&self, keys: &[String]) {
+        for key in keys {
+            let path = self.entry_path(key);
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Could not delete cache entry {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a file name is a SHA-256 digest, as entries are named
+fn is_key(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::util::verify;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-cache-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_put_deduplicates_and_evicts_least_recently_used() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 100);
+        cache.init().await.unwrap();
+
+        let a = cache.put(&[b'a'; 40], None).await.unwrap();
+        let b = cache.put(&[b'b'; 40], None).await.unwrap();
+        assert_eq!(a.key, sha256_hash(&[b'a'; 40]));
+        assert!(a.evicted.keys.is_empty() && b.evicted.keys.is_empty());
+
+        // Same content again: one entry, and "a" is now the most recent
+        let again = cache.put(&[b'a'; 40], None).await.unwrap();
+        assert_eq!(again.key, a.key);
+        assert_eq!(cache.stats().entry_count, 2);
+
+        let c = cache.put(&[b'c'; 40], None).await.unwrap();
+        assert_eq!(c.evicted, Evicted { keys: vec![b.key.clone()], bytes: 40 });
+        assert!(!dir.join(&b.key).exists());
+        assert_eq!(cache.get(&b.key).await.unwrap(), None);
+        assert_eq!(cache.get(&a.key).await.unwrap(), Some(vec![b'a'; 40]));
+
+        let stats = cache.stats();
+        assert_eq!((stats.entry_count, stats.total_size, stats.evicted_bytes), (2, 80, 40));
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_prune_drops_expired_entries() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 1024);
+        let kept = cache.put(b"texture", Some(Duration::hours(1))).await.unwrap();
+        let forever = cache.put(b"model", None).await.unwrap();
+        // Stored last: any later put would evict it before `prune` could
+        let expired = cache.put(b"session token", Some(Duration::milliseconds(20))).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(40)).await;
+
+        let pruned = cache.prune().await.unwrap();
+        assert_eq!(pruned, Evicted { keys: vec![expired.key.clone()], bytes: 13 });
+        assert!(!dir.join(&expired.key).exists());
+        assert!(cache.contains(&kept.key) && cache.contains(&forever.key));
+        assert!(cache.prune().await.unwrap().keys.is_empty());
+
+        cache.clear().await.unwrap();
+        assert_eq!(cache.stats().entry_count, 0);
+        assert!(!dir.join(&kept.key).exists());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_init_reloads_the_index_and_applies_the_limit() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 1024);
+        let first = cache.put(&[1; 30], None).await.unwrap();
+        let second = cache.put(&[2; 30], None).await.unwrap();
+        let gone = cache.put(&[3; 30], None).await.unwrap();
+        tokio::fs::remove_file(dir.join(&gone.key)).await.unwrap();
+        // Written by something that didn't update the index
+        let stray = sha256_hash(&[4; 30]);
+        tokio::fs::write(dir.join(&stray), [4; 30]).await.unwrap();
+        tokio::fs::write(dir.join("notes.txt"), b"not an entry").await.unwrap();
+
+        let mut reloaded = CacheManager::new(dir.clone(), 60);
+        reloaded.init().await.unwrap();
+        let stats = reloaded.stats();
+        assert_eq!((stats.entry_count, stats.total_size), (2, 60));
+        assert!(!reloaded.contains(&gone.key));
+        // The oldest entry made room under the lower limit
+        assert!(!reloaded.contains(&first.key) && !dir.join(&first.key).exists());
+        assert!(reloaded.contains(&second.key) && reloaded.contains(&stray));
+        assert!(dir.join("notes.txt").exists());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_entries_are_detected() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 1024);
+        let intact = cache.put(b"intact asset", None).await.unwrap();
+        let damaged = cache.put(b"damaged asset", None).await.unwrap();
+        let lost = cache.put(b"lost asset", None).await.unwrap();
+        tokio::fs::write(dir.join(&damaged.key), b"flipped bits").await.unwrap();
+        tokio::fs::remove_file(dir.join(&lost.key)).await.unwrap();
+
+        let targets = cache.verify_targets();
+        assert_eq!(targets.len(), 3);
+        assert!(targets.iter().all(|target| target.sha256 == target.key && target.path == dir.join(&target.key)));
+        let progress = verify::VerifyProgress::new();
+        assert!(progress.start(targets.len()));
+        let report = verify::verify_files(targets, &progress).await;
+        assert_eq!(report.corrupted, vec![damaged.key.clone()]);
+        assert_eq!(report.missing, vec![lost.key.clone()]);
+
+        for key in report.invalid() {
+            cache.forget(key);
+        }
+        assert!(cache.contains(&intact.key));
+        assert!(!cache.contains(&damaged.key) && !cache.contains(&lost.key));
+        assert_eq!(cache.stats().total_size, 12);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_reads_rehash_when_verifying_integrity() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 1024);
+        let stored = cache.put(b"shader pack", None).await.unwrap();
+        tokio::fs::write(dir.join(&stored.key), b"shader pac").await.unwrap();
+
+        let mut trusting = CacheManager::new(dir.clone(), 1024).with_verify_integrity(false);
+        trusting.init().await.unwrap();
+        assert_eq!(trusting.get(&stored.key).await.unwrap(), Some(b"shader pac".to_vec()));
+
+        assert_eq!(cache.get(&stored.key).await.unwrap(), None);
+        assert!(!cache.contains(&stored.key));
+        assert!(!dir.join(&stored.key).exists());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_set_max_size_evicts_down_to_the_new_limit() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 1024);
+        let oldest = cache.put(&[1; 40], None).await.unwrap();
+        let middle = cache.put(&[2; 40], None).await.unwrap();
+        let newest = cache.put(&[3; 40], None).await.unwrap();
+
+        assert!(cache.set_max_size(2048).await.unwrap().keys.is_empty());
+        let evicted = cache.set_max_size(90).await.unwrap();
+        assert_eq!(evicted, Evicted { keys: vec![oldest.key.clone()], bytes: 40 });
+        assert!(!dir.join(&oldest.key).exists());
+        assert!(cache.contains(&middle.key) && cache.contains(&newest.key));
+
+        // The new limit sticks for later puts and across a reload
+        let stored = cache.put(&[4; 40], None).await.unwrap();
+        assert_eq!(stored.evicted.keys, vec![middle.key.clone()]);
+        let mut reloaded = CacheManager::new(dir.clone(), 90);
+        reloaded.init().await.unwrap();
+        assert_eq!(reloaded.stats().total_size, 80);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_a_damaged_index_falls_back_to_its_backup() {
+        let dir = temp_dir();
+        let mut cache = CacheManager::new(dir.clone(), 1024);
+        let short_lived = cache.put(b"news feed", Some(Duration::hours(1))).await.unwrap();
+        cache.put(b"skin", None).await.unwrap();
+        tokio::fs::write(dir.join(INDEX_FILE), b"{\"entries\": ").await.unwrap();
+
+        let mut reloaded = CacheManager::new(dir.clone(), 1024);
+        reloaded.init().await.unwrap();
+        // The backup predates "skin", which is adopted without a TTL; the
+        // TTL of the older entry survives
+        assert_eq!(reloaded.stats().entry_count, 2);
+        assert!(reloaded.index.get(&short_lived.key).unwrap().expires_at.is_some());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -2,6 +2,10 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
+use yellow_tale_core::features::{FeatureGateSource, FeatureGates, FeatureSyncError};
+
+use crate::core::mods::activator::ProfileMod;
+use crate::core::updates::{ChangelogEntry, ReleaseArtifact, UpdateChannel, UpdateError, UpdateSource};
 
 #[derive(Debug, Error)]
 pub enum ClientError {
@@ -11,6 +15,8 @@ pub enum ClientError {
     Api(String),
     #[error("Not authenticated")]
     NotAuthenticated,
+    #[error("Conflict: {0}")]
+    Conflict(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +82,152 @@ struct PendingResponse {
     outgoing: Vec<User>,
 }
 
+#[derive(Debug, Serialize)]
+struct SyncPushRequest {
+    token: String,
+    document: serde_json::Value,
+    base_etag: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncPullResponse {
+    document: Option<serde_json::Value>,
+    etag: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncPushResponse {
+    etag: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UpsertModProfileRequest<'a> {
+    token: String,
+    #[serde(flatten)]
+    profile: &'a RemoteModProfile,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionResponse {
+    features: SubscriptionFeatures,
+}
+
+/// Limits attached to the user's subscription tier
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionFeatures {
+    pub max_friends: i32,
+    pub cloud_storage_mb: i32,
+    pub priority_relay: bool,
+    pub custom_themes: bool,
+    pub early_access: bool,
+}
+
+/// A notification stored for the signed-in user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: Uuid,
+    /// friend_request, party_invite, moderation_decision or escrow_update
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub read_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Newest notifications first, with the total unread count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPage {
+    pub notifications: Vec<Notification>,
+    pub unread_count: i64,
+}
+
+/// Criteria for the public server directory; unset fields don't filter
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSearch {
+    /// Compared without regard to case
+    pub game_mode: Option<String>,
+    /// Servers must carry every one of these
+    pub tags: Vec<String>,
+    /// Only servers below their player limit
+    pub has_slots: bool,
+    /// Text to find in the name or description
+    pub q: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl ServerSearch {
+    /// The directory's query string parameters
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if let Some(game_mode) = &self.game_mode {
+            query.push(("game_mode", game_mode.clone()));
+        }
+        if !self.tags.is_empty() {
+            query.push(("tags", self.tags.join(",")));
+        }
+        if self.has_slots {
+            query.push(("has_slots", "true".to_string()));
+        }
+        if let Some(q) = &self.q {
+            query.push(("q", q.clone()));
+        }
+        if let Some(page) = self.page {
+            query.push(("page", page.to_string()));
+        }
+        if let Some(per_page) = self.per_page {
+            query.push(("per_page", per_page.to_string()));
+        }
+        query
+    }
+}
+
+/// A server listed in the public directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryServer {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub address: String,
+    pub port: i32,
+    pub max_players: i32,
+    pub current_players: i32,
+    pub game_mode: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub owner_id: Uuid,
+    pub is_online: bool,
+    pub last_ping: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// One page of directory results, busiest servers first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerPage {
+    pub servers: Vec<DirectoryServer>,
+    /// Servers matching the search across every page
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+/// A mod profile as the server stores it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteModProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub mods: Vec<ProfileMod>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The server's copy of the settings sync document
+#[derive(Debug, Clone)]
+pub struct RemoteSyncDocument {
+    pub document: serde_json::Value,
+    pub etag: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ReleaseInfo {
     pub version: String,
@@ -317,6 +469,94 @@ impl ApiClient {
         }
     }
     
+    pub async fn get_subscription_features(&self) -> Result<SubscriptionFeatures, ClientError> {
+        let token = self.token.clone().ok_or(ClientError::NotAuthenticated)?;
+        
+        let resp: ApiResponse<SubscriptionResponse> = self.client
+            .post(format!("{}/api/v1/subscription", self.base_url))
+            .json(&TokenRequest { token })
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        if let Some(data) = resp.data {
+            Ok(data.features)
+        } else {
+            Err(ClientError::Api(resp.error.unwrap_or_default()))
+        }
+    }
+    
+    pub async fn sync_pull(&self) -> Result<Option<RemoteSyncDocument>, ClientError> {
+        let token = self.token.clone().ok_or(ClientError::NotAuthenticated)?;
+        
+        let resp: ApiResponse<SyncPullResponse> = self.client
+            .post(format!("{}/api/v1/sync/pull", self.base_url))
+            .json(&TokenRequest { token })
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        match resp.data {
+            Some(SyncPullResponse { document: Some(document), etag: Some(etag) }) => {
+                Ok(Some(RemoteSyncDocument { document, etag }))
+            }
+            Some(_) => Ok(None),
+            None => Err(ClientError::Api(resp.error.unwrap_or_default())),
+        }
+    }
+    
+    /// Push a sync document; returns the new etag, or `Conflict` if `base_etag` is stale
+    pub async fn sync_push(&self, document: serde_json::Value, base_etag: Option<String>) -> Result<String, ClientError> {
+        let token = self.token.clone().ok_or(ClientError::NotAuthenticated)?;
+        
+        let response = self.client
+            .post(format!("{}/api/v1/sync/push", self.base_url))
+            .json(&SyncPushRequest { token, document, base_etag })
+            .send()
+            .await?;
+        let conflict = response.status() == reqwest::StatusCode::CONFLICT;
+        let resp: ApiResponse<serde_json::Value> = response.json().await?;
+        
+        if conflict {
+            return Err(ClientError::Conflict(resp.error.unwrap_or_default()));
+        }
+        match resp.data.map(serde_json::from_value::<SyncPushResponse>) {
+            Some(Ok(data)) => Ok(data.etag),
+            _ => Err(ClientError::Api(resp.error.unwrap_or_default())),
+        }
+    }
+    
+    pub async fn list_mod_profiles(&self) -> Result<Vec<RemoteModProfile>, ClientError> {
+        let token = self.token.clone().ok_or(ClientError::NotAuthenticated)?;
+        
+        let resp: ApiResponse<Vec<RemoteModProfile>> = self.client
+            .post(format!("{}/api/v1/mods/profiles", self.base_url))
+            .json(&TokenRequest { token })
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        resp.data.ok_or_else(|| ClientError::Api(resp.error.unwrap_or_default()))
+    }
+    
+    /// Create or replace a profile under its own id, keeping its `updated_at`
+    pub async fn upsert_mod_profile(&self, profile: &RemoteModProfile) -> Result<RemoteModProfile, ClientError> {
+        let token = self.token.clone().ok_or(ClientError::NotAuthenticated)?;
+        
+        let resp: ApiResponse<RemoteModProfile> = self.client
+            .post(format!("{}/api/v1/mods/profiles/upsert", self.base_url))
+            .json(&UpsertModProfileRequest { token, profile })
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        resp.data.ok_or_else(|| ClientError::Api(resp.error.unwrap_or_default()))
+    }
+    
     pub async fn get_releases(&self) -> Result<ReleaseInfo, ClientError> {
         #[derive(Deserialize)]
         struct ReleasesResponse {
@@ -341,6 +581,146 @@ impl ApiClient {
         
         Ok(resp.status().is_success())
     }
+    
+    pub async fn get_notifications(&self, unread_only: bool, limit: Option<i64>) -> Result<NotificationPage, ClientError> {
+        #[derive(Serialize)]
+        struct ListNotificationsRequest {
+            token: String,
+            unread_only: bool,
+            limit: Option<i64>,
+        }
+        
+        let token = self.token.clone().ok_or(ClientError::NotAuthenticated)?;
+        
+        let resp: ApiResponse<NotificationPage> = self.client
+            .post(format!("{}/api/v1/notifications", self.base_url))
+            .json(&ListNotificationsRequest { token, unread_only, limit })
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        match resp.data {
+            Some(page) if resp.success => Ok(page),
+            _ => Err(ClientError::Api(resp.error.unwrap_or_default())),
+        }
+    }
+    
+    /// Online servers matching `search`
+    pub async fn search_servers(&self, search: &ServerSearch) -> Result<ServerPage, ClientError> {
+        let resp: ApiResponse<ServerPage> = self.client
+            .get(format!("{}/api/v1/servers", self.base_url))
+            .query(&search.query())
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        match resp.data {
+            Some(page) if resp.success => Ok(page),
+            _ => Err(ClientError::Api(resp.error.unwrap_or_default())),
+        }
+    }
+    
+    /// Newest live release for the channel and platform
+    pub async fn get_latest_release(&self, channel: UpdateChannel, platform: &str) -> Result<Option<ReleaseArtifact>, ClientError> {
+        #[derive(Deserialize)]
+        struct LatestResponse {
+            release: Option<ReleaseArtifact>,
+        }
+        
+        let resp: ApiResponse<LatestResponse> = self.client
+            .get(format!("{}/api/v1/releases/latest", self.base_url))
+            .query(&[("channel", channel.as_str()), ("platform", platform)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        match resp.data {
+            Some(data) if resp.success => Ok(data.release),
+            _ => Err(ClientError::Api(resp.error.unwrap_or_default())),
+        }
+    }
+    
+    /// Releases for the channel and platform newer than `since`, newest first
+    pub async fn get_release_changelog(&self, channel: UpdateChannel, platform: &str, since: &str) -> Result<Vec<ChangelogEntry>, ClientError> {
+        #[derive(Deserialize)]
+        struct ChangelogResponse {
+            entries: Vec<ChangelogEntry>,
+        }
+        
+        let resp: ApiResponse<ChangelogResponse> = self.client
+            .get(format!("{}/api/v1/releases/changelog", self.base_url))
+            .query(&[("channel", channel.as_str()), ("platform", platform), ("since", since)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        match resp.data {
+            Some(data) if resp.success => Ok(data.entries),
+            _ => Err(ClientError::Api(resp.error.unwrap_or_default())),
+        }
+    }
+    
+    /// Feature gates for the signed-in user's tier, or the free tier when signed out
+    pub async fn get_feature_gates(&self) -> Result<FeatureGates, ClientError> {
+        let token = self.token.clone().unwrap_or_default();
+        
+        let gates = self.client
+            .post(format!("{}/api/v1/features", self.base_url))
+            .json(&TokenRequest { token })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        
+        Ok(gates)
+    }
+}
+
+#[async_trait::async_trait]
+impl FeatureGateSource for ApiClient {
+    async fn fetch(&self) -> Result<FeatureGates, FeatureSyncError> {
+        self.get_feature_gates().await.map_err(|e| FeatureSyncError::Fetch(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl UpdateSource for ApiClient {
+    async fn latest(&self, channel: UpdateChannel, platform: &str) -> Result<Option<ReleaseArtifact>, UpdateError> {
+        self.get_latest_release(channel, platform).await.map_err(|e| UpdateError::Fetch(e.to_string()))
+    }
+    
+    async fn changelog_since(&self, channel: UpdateChannel, platform: &str, since: &str) -> Result<Vec<ChangelogEntry>, UpdateError> {
+        self.get_release_changelog(channel, platform, since).await.map_err(|e| UpdateError::Fetch(e.to_string()))
+    }
+    
+    async fn download(&self, artifact: &ReleaseArtifact, dest: &std::path::Path, progress: &(dyn Fn(u64) + Send + Sync)) -> Result<(), UpdateError> {
+        use tokio::io::AsyncWriteExt;
+        
+        // Artifacts may be hosted on the API server itself
+        let url = if artifact.url.starts_with('/') {
+            format!("{}{}", self.base_url, artifact.url)
+        } else {
+            artifact.url.clone()
+        };
+        let mut response = self.client.get(&url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| UpdateError::DownloadFailed(e.to_string()))?;
+        
+        let mut file = tokio::fs::File::create(dest).await?;
+        let mut written = 0u64;
+        while let Some(chunk) = response.chunk().await.map_err(|e| UpdateError::DownloadFailed(e.to_string()))? {
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+            progress(written);
+        }
+        file.flush().await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -360,4 +740,26 @@ mod tests {
         assert!(client.is_authenticated());
         assert_eq!(client.token(), Some("test_token"));
     }
+    
+    #[test]
+    fn test_server_search_query() {
+        assert!(ServerSearch::default().query().is_empty());
+        
+        let search = ServerSearch {
+            game_mode: Some("creative".to_string()),
+            tags: vec!["europe".to_string(), "pvp".to_string()],
+            has_slots: true,
+            q: Some("castle".to_string()),
+            page: Some(2),
+            per_page: Some(25),
+        };
+        assert_eq!(search.query(), vec![
+            ("game_mode", "creative".to_string()),
+            ("tags", "europe,pvp".to_string()),
+            ("has_slots", "true".to_string()),
+            ("q", "castle".to_string()),
+            ("page", "2".to_string()),
+            ("per_page", "25".to_string()),
+        ]);
+    }
 }
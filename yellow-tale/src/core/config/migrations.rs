@@ -0,0 +1,56 @@
+//! Stepwise upgrades of raw config files
+//!
+//! Each step works on the parsed TOML table so values the current schema
+//! would reject survive the upgrade and are reported by validation instead.
+
+use toml::{Table, Value};
+
+use super::{AppConfig, ConfigError, CONFIG_VERSION};
+
+type Migration = fn(&mut Table);
+
+/// `MIGRATIONS[n]` upgrades a version `n` config to version `n + 1`
+const MIGRATIONS: &[Migration] = &[v0_to_v1];
+
+/// Configs written before `config_version` existed carried a semver
+/// `schema_version` and may predate sections added since.
+fn v0_to_v1(table: &mut Table) {
+    table.remove("schema_version");
+    if let Ok(Value::Table(defaults)) = Value::try_from(AppConfig::default()) {
+        for (key, value) in defaults {
+            if value.is_table() {
+                table.entry(key).or_insert(value);
+            }
+        }
+    }
+}
+
+/// Version of a raw config; files without a version are version 0
+pub fn version_of(table: &Table) -> Result<u32, ConfigError> {
+    match table.get("config_version") {
+        None => Ok(0),
+        Some(Value::Integer(version)) => u32::try_from(*version)
+            .map_err(|_| ConfigError::MigrationFailed(format!("invalid config_version {}", version))),
+        Some(other) => Err(ConfigError::MigrationFailed(format!(
+            "config_version must be an integer, found {}", other.type_str()
+        ))),
+    }
+}
+
+/// Upgrade `table` to `CONFIG_VERSION`, returning the version it started at
+/// if anything changed
+pub fn migrate(table: &mut Table) -> Result<Option<u32>, ConfigError> {
+    let from = version_of(table)?;
+    if from > CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion(from));
+    }
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    for step in &MIGRATIONS[from as usize..] {
+        step(table);
+    }
+    table.insert("config_version".to_string(), Value::Integer(CONFIG_VERSION as i64));
+    Ok(Some(from))
+}
@@ -3,15 +3,27 @@
 //! Handles application configuration:
 //! - TOML-based config files
 //! - Versioned schemas with migration
+//! - Field-level validation
 //! - Default configuration
+//! - Reloading on change ([`watcher`])
+
+pub mod migrations;
+pub mod validation;
+pub mod watcher;
 
 use std::path::Path;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use semver::Version;
+use yellow_tale_core::filesystem::load_with_backup;
+
+use crate::core::netdiag::QualityThresholds;
+use crate::core::updates::UpdateChannel;
+use crate::core::util::write_atomic;
+
+pub use validation::{ConfigIssue, ConfigReport};
 
-/// Current config schema version
-pub const CONFIG_SCHEMA_VERSION: &str = "1.0.0";
+/// Current config file version
+pub const CONFIG_VERSION: u32 = 1;
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -24,6 +36,15 @@ pub enum ConfigError {
     #[error("Migration failed: {0}")]
     MigrationFailed(String),
     
+    #[error("Config version {0} is newer than this launcher supports")]
+    UnsupportedVersion(u32),
+    
+    #[error("Invalid TOML at line {line}, column {column}: {message}")]
+    Syntax { line: usize, column: usize, message: String },
+    
+    #[error("Failed to watch config: {0}")]
+    WatchFailed(String),
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     
@@ -94,6 +115,18 @@ pub struct SessionConfig {
     
     /// Relay server addresses
     pub relay_servers: Vec<String>,
+    
+    /// STUN servers for NAT detection; empty skips detection
+    #[serde(default = "default_stun_servers")]
+    pub stun_servers: Vec<String>,
+    
+    /// Encrypt relayed game traffic end to end between peers
+    #[serde(default)]
+    pub encrypt_payloads: bool,
+}
+
+fn default_stun_servers() -> Vec<String> {
+    crate::core::sessions::nat::DEFAULT_STUN_SERVERS.iter().map(|s| s.to_string()).collect()
 }
 
 impl Default for SessionConfig {
@@ -103,6 +136,8 @@ impl Default for SessionConfig {
             max_relay_hops: 3,
             p2p_timeout_secs: 10,
             relay_servers: Vec::new(),
+            stun_servers: default_stun_servers(),
+            encrypt_payloads: false,
         }
     }
 }
@@ -134,11 +169,230 @@ impl Default for TelemetryConfig {
     }
 }
 
+/// Settings sync configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Whether settings sync is enabled
+    pub enabled: bool,
+    
+    /// Central server base URL
+    pub server_url: String,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            server_url: "https://yellowtale.com".to_string(),
+        }
+    }
+}
+
+/// Launcher self-update configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    /// Release channel to follow: stable or beta
+    #[serde(default)]
+    pub channel: UpdateChannel,
+}
+
+/// Ping and connection quality measurement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetDiagConfig {
+    /// Samples taken per measurement
+    pub samples: u32,
+    
+    /// How long a sample may take before it counts as lost
+    pub timeout_ms: u64,
+    
+    /// How often favorited servers are re-measured
+    pub refresh_interval_secs: u64,
+    
+    /// Servers measured at once, and so sockets open at once
+    pub max_concurrent_probes: usize,
+    
+    /// Limits for the good and ok quality classes
+    #[serde(default)]
+    pub thresholds: QualityThresholds,
+}
+
+impl Default for NetDiagConfig {
+    fn default() -> Self {
+        Self {
+            samples: 5,
+            timeout_ms: 2000,
+            refresh_interval_secs: 300,
+            max_concurrent_probes: 16,
+            thresholds: QualityThresholds::default(),
+        }
+    }
+}
+
+/// Scheduled snapshots of worlds and profiles, and how many are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotConfig {
+    /// Whether snapshots are taken on a schedule as well as on request
+    pub scheduled: bool,
+    
+    /// Minutes between scheduled snapshots
+    pub interval_minutes: u64,
+    
+    /// Newest snapshots kept, as far as `max_total_mb` allows
+    pub keep_last: usize,
+    
+    /// Days, counting today, that keep their newest snapshot as well
+    pub keep_daily_days: u32,
+    
+    /// Hold scheduled snapshots while the game is running, so worlds
+    /// aren't archived halfway through a save
+    #[serde(default = "default_only_when_game_stopped")]
+    pub only_when_game_stopped: bool,
+    
+    /// Megabytes all snapshots may take before the oldest are pruned; 0
+    /// for no limit. Each day's snapshot is kept regardless.
+    #[serde(default)]
+    pub max_total_mb: u64,
+}
+
+fn default_only_when_game_stopped() -> bool {
+    true
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            scheduled: true,
+            interval_minutes: 60,
+            keep_last: 5,
+            keep_daily_days: 7,
+            only_when_game_stopped: true,
+            max_total_mb: 0,
+        }
+    }
+}
+
+/// Background downloads of servers' assets ahead of joining
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchConfig {
+    /// Assets downloaded at once
+    pub max_concurrent: usize,
+    
+    /// KiB per second all prefetch downloads may use together; 0 for no
+    /// limit
+    pub bandwidth_limit_kbps: u64,
+    
+    /// Tries per asset before it's reported as failed
+    pub max_attempts: u32,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self { max_concurrent: 4, bandwidth_limit_kbps: 0, max_attempts: 3 }
+    }
+}
+
+/// Game process handling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LauncherConfig {
+    /// How long the game gets to close when asked before it's killed
+    pub shutdown_timeout_secs: u64,
+    
+    /// Extra places to look for the game, checked before the usual ones
+    #[serde(default)]
+    pub install_hints: Vec<String>,
+}
+
+impl Default for LauncherConfig {
+    fn default() -> Self {
+        Self { shutdown_timeout_secs: 10, install_hints: Vec::new() }
+    }
+}
+
+/// IPC request handling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpcConfig {
+    /// How long a command may run before it's answered with a timeout error
+    pub command_timeout_secs: u64,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self { command_timeout_secs: 10 }
+    }
+}
+
+/// Failed password attempts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Failures allowed within the window before attempts have to wait
+    pub max_failed_attempts: u32,
+    
+    /// Seconds a failure counts toward the limit
+    pub lockout_window_secs: u64,
+    
+    /// Longest wait between attempts; waits double from 30s up to this
+    pub max_lockout_secs: u64,
+    
+    /// Friend requests need a confirmed email address
+    #[serde(default)]
+    pub require_verified_email: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { max_failed_attempts: 5, lockout_window_secs: 15 * 60, max_lockout_secs: 15 * 60, require_verified_email: false }
+    }
+}
+
+/// Outgoing email
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// `host:port` of an SMTP relay; verification codes can't be sent without one
+    pub smtp_server: Option<String>,
+    
+    pub from_address: String,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self { smtp_server: None, from_address: "noreply@yellowtale.local".to_string() }
+    }
+}
+
+/// Friend requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendsConfig {
+    /// Days a request waits for an answer before it expires
+    pub request_expiry_days: u64,
+}
+
+impl Default for FriendsConfig {
+    fn default() -> Self {
+        Self { request_expiry_days: 30 }
+    }
+}
+
+/// Background metrics sampling for diagnostics reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsConfig {
+    /// Seconds between samples
+    pub sample_interval_secs: u64,
+    
+    /// Minutes of samples kept for reports
+    pub history_minutes: u64,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self { sample_interval_secs: 5, history_minutes: 60 }
+    }
+}
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
-    /// Schema version for migration
-    pub schema_version: String,
+    /// File version for migration
+    pub config_version: u32,
     
     /// Cache settings
     pub cache: CacheConfig,
@@ -154,33 +408,131 @@ pub struct AppConfig {
     
     /// Path to game executable (global default)
     pub default_game_path: Option<String>,
+    
+    /// Cross-device settings sync
+    #[serde(default)]
+    pub sync: SyncConfig,
+    
+    /// Launcher updates
+    #[serde(default)]
+    pub updates: UpdateConfig,
+    
+    /// Ping measurement
+    #[serde(default)]
+    pub netdiag: NetDiagConfig,
+    
+    /// World and profile snapshots
+    #[serde(default)]
+    pub snapshots: SnapshotConfig,
+    
+    /// Background asset downloads
+    #[serde(default)]
+    pub prefetch: PrefetchConfig,
+    
+    /// Game process handling
+    #[serde(default)]
+    pub launcher: LauncherConfig,
+    
+    /// Metrics sampling
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    
+    /// IPC request handling
+    #[serde(default)]
+    pub ipc: IpcConfig,
+    
+    /// Account sign-in limits
+    #[serde(default)]
+    pub auth: AuthConfig,
+    
+    /// Outgoing email
+    #[serde(default)]
+    pub email: EmailConfig,
+    
+    /// Friend requests
+    #[serde(default)]
+    pub friends: FriendsConfig,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
-            schema_version: CONFIG_SCHEMA_VERSION.to_string(),
+            config_version: CONFIG_VERSION,
             cache: CacheConfig::default(),
             performance: PerformanceConfig::default(),
             session: SessionConfig::default(),
             telemetry: TelemetryConfig::default(),
             default_game_path: None,
+            sync: SyncConfig::default(),
+            updates: UpdateConfig::default(),
+            netdiag: NetDiagConfig::default(),
+            snapshots: SnapshotConfig::default(),
+            prefetch: PrefetchConfig::default(),
+            launcher: LauncherConfig::default(),
+            diagnostics: DiagnosticsConfig::default(),
+            ipc: IpcConfig::default(),
+            auth: AuthConfig::default(),
+            email: EmailConfig::default(),
+            friends: FriendsConfig::default(),
         }
     }
 }
 
 impl AppConfig {
-    /// Load configuration from a file
-    pub async fn load(path: &Path) -> Result<Self, ConfigError> {
-        let content = tokio::fs::read_to_string(path).await?;
-        let mut config: AppConfig = toml::from_str(&content)?;
-        
-        // Check if migration is needed
-        if config.needs_migration() {
-            config = Self::migrate(config)?;
+    /// Parse, migrate and validate config file content without touching disk.
+    /// Invalid values are replaced by their defaults and listed in the report.
+    pub fn parse(content: &str) -> Result<(Self, ConfigReport), ConfigError> {
+        let (_, config, report) = Self::parse_table(content)?;
+        Ok((config, report))
+    }
+    
+    fn parse_table(content: &str) -> Result<(toml::Table, Self, ConfigReport), ConfigError> {
+        let mut table: toml::Table = toml::from_str(content).map_err(|e| syntax_error(content, &e))?;
+        let mut report = ConfigReport {
+            migrated_from: migrations::migrate(&mut table)?,
+            ..ConfigReport::default()
+        };
+        let config = validation::resolve(&table, &mut report);
+        Ok((table, config, report))
+    }
+    
+    /// Report every problem with `content` as it would be loaded. Files that
+    /// can't be read at all come back as a single whole-file error.
+    pub fn validate(content: &str) -> ConfigReport {
+        match Self::parse(content) {
+            Ok((_, report)) => report,
+            Err(e) => {
+                let mut issue = ConfigIssue::new("", e.to_string());
+                if let ConfigError::Syntax { line, column, message } = e {
+                    issue.line = Some(line);
+                    issue.column = Some(column);
+                    issue.message = message;
+                }
+                ConfigReport { errors: vec![issue], ..ConfigReport::default() }
+            }
+        }
+    }
+    
+    /// Load configuration from a file, falling back to the previous save
+    /// (`<name>.bak`) when it doesn't parse. Older files are upgraded in
+    /// place, keeping the original next to it as `<name>.v<version>.bak`.
+    pub async fn load(path: &Path) -> Result<(Self, ConfigReport), ConfigError> {
+        let primary = path.to_path_buf();
+        let (table, config, report) = tokio::task::spawn_blocking(move || {
+            load_with_backup(&primary, |content| Self::parse_table(&String::from_utf8_lossy(content)))
+        })
+        .await
+        .map_err(|e| ConfigError::LoadFailed(e.to_string()))??;
+        
+        if let Some(from) = report.migrated_from {
+            tracing::info!("Migrating config from v{} to v{}", from, CONFIG_VERSION);
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(format!(".v{}.bak", from));
+            tokio::fs::copy(path, &backup).await?;
+            write_atomic(path, toml::to_string_pretty(&table)?.into_bytes()).await?;
         }
         
-        Ok(config)
+        Ok((config, report))
     }
     
     /// Save configuration to a file
@@ -191,35 +543,11 @@ impl AppConfig {
         }
         
         let content = toml::to_string_pretty(self)?;
-        tokio::fs::write(path, content).await?;
+        write_atomic(path, content.into_bytes()).await?;
         
         Ok(())
     }
     
-    /// Check if this config needs migration
-    pub fn needs_migration(&self) -> bool {
-        match (Version::parse(&self.schema_version), Version::parse(CONFIG_SCHEMA_VERSION)) {
-            (Ok(current), Ok(target)) => current < target,
-            _ => true,
-        }
-    }
-    
-    /// Migrate config to current schema version
-    fn migrate(mut config: AppConfig) -> Result<AppConfig, ConfigError> {
-        let from = Version::parse(&config.schema_version)
-            .map_err(|e| ConfigError::MigrationFailed(e.to_string()))?;
-        let to = Version::parse(CONFIG_SCHEMA_VERSION)
-            .map_err(|e| ConfigError::MigrationFailed(e.to_string()))?;
-        
-        tracing::info!("Migrating config from v{} to v{}", from, to);
-        
-        // Add migration logic here as schema evolves
-        // For now, just update version
-        config.schema_version = CONFIG_SCHEMA_VERSION.to_string();
-        
-        Ok(config)
-    }
-    
     /// Generate default config file content
     pub fn default_toml() -> String {
         let config = AppConfig::default();
@@ -227,22 +555,131 @@ impl AppConfig {
     }
 }
 
+fn syntax_error(content: &str, error: &toml::de::Error) -> ConfigError {
+    let offset = error.span().map(|span| span.start).unwrap_or(0).min(content.len());
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    ConfigError::Syntax { line, column, message: error.message().trim().to_string() }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    const V0_FIXTURE: &str = include_str!("../../../tests/fixtures/config/v0_missing_sections.toml");
+    const OUT_OF_RANGE_FIXTURE: &str = include_str!("../../../tests/fixtures/config/cache_out_of_range.toml");
+    const CORRUPT_FIXTURE: &str = include_str!("../../../tests/fixtures/config/corrupt.toml");
+    
     #[test]
     fn test_default_config() {
         let config = AppConfig::default();
-        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
-        assert!(!config.needs_migration());
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        
+        let (parsed, report) = AppConfig::parse(&AppConfig::default_toml()).unwrap();
+        assert_eq!(parsed.config_version, CONFIG_VERSION);
+        assert!(report.is_valid() && report.warnings.is_empty());
+        assert_eq!(report.migrated_from, None);
     }
     
     #[test]
     fn test_default_toml() {
         let toml = AppConfig::default_toml();
-        assert!(toml.contains("schema_version"));
+        assert!(toml.contains("config_version"));
         assert!(toml.contains("[cache]"));
         assert!(toml.contains("[performance]"));
     }
+    
+    #[test]
+    fn test_v0_config_is_migrated() {
+        let (config, report) = AppConfig::parse(V0_FIXTURE).unwrap();
+        assert_eq!(report.migrated_from, Some(0));
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.cache.max_size_bytes, 5 * 1024 * 1024 * 1024);
+        assert!(!config.cache.enable_warming);
+        assert_eq!(config.performance.default_priority, "high");
+        assert_eq!(config.session.preferred_method, "hybrid");
+        assert_eq!(config.telemetry.log_level, "info");
+    }
+    
+    #[test]
+    fn test_invalid_values_fall_back_individually() {
+        let (config, report) = AppConfig::parse(OUT_OF_RANGE_FIXTURE).unwrap();
+        let fields: Vec<&str> = report.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields.len(), 2, "{:?}", report.errors);
+        assert!(fields.contains(&"cache.max_size_bytes"));
+        assert!(fields.contains(&"session.max_relay_hops"));
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].field, "telemetry.log_colour");
+        
+        // Only the bad values are replaced
+        assert_eq!(config.cache.max_size_bytes, CacheConfig::default().max_size_bytes);
+        assert_eq!(config.session.max_relay_hops, 3);
+        assert_eq!(config.session.preferred_method, "relay");
+        
+        let (_, report) = AppConfig::parse("config_version = 1\ndefault_game_path = \"/no/such/game.exe\"").unwrap();
+        assert_eq!(report.errors[0].field, "default_game_path");
+    }
+    
+    #[test]
+    fn test_corrupt_toml_reports_location() {
+        match AppConfig::parse(CORRUPT_FIXTURE) {
+            Err(ConfigError::Syntax { line, .. }) => assert_eq!(line, 7),
+            other => panic!("expected a syntax error, got {:?}", other.map(|(_, r)| r)),
+        }
+        
+        let report = AppConfig::validate(CORRUPT_FIXTURE);
+        assert!(!report.is_valid());
+        assert_eq!(report.errors[0].line, Some(7));
+        
+        let report = AppConfig::validate("config_version = 99");
+        assert!(report.errors[0].message.contains("newer"));
+    }
+    
+    #[tokio::test]
+    async fn test_load_upgrades_in_place_with_backup() {
+        let dir = std::env::temp_dir().join(format!("yt-config-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("config.toml");
+        tokio::fs::write(&path, V0_FIXTURE).await.unwrap();
+        
+        let (config, report) = AppConfig::load(&path).await.unwrap();
+        assert_eq!(report.migrated_from, Some(0));
+        assert_eq!(tokio::fs::read_to_string(dir.join("config.toml.v0.bak")).await.unwrap(), V0_FIXTURE);
+        
+        let (reloaded, report) = AppConfig::load(&path).await.unwrap();
+        assert_eq!(report.migrated_from, None);
+        assert_eq!(reloaded.performance.default_priority, config.performance.default_priority);
+        assert!(!dir.join("config.toml.tmp").exists());
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_truncated_config_recovers_from_backup() {
+        let dir = std::env::temp_dir().join(format!("yt-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("config.toml");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let [stable, beta] = ["HytaleClient", "HytaleClient-beta"].map(|name| dir.join(name).display().to_string());
+        for exe in [&stable, &beta] {
+            tokio::fs::write(exe, b"").await.unwrap();
+        }
+        
+        let config = AppConfig { default_game_path: Some(stable.clone()), ..AppConfig::default() };
+        config.save(&path).await.unwrap();
+        AppConfig { default_game_path: Some(beta), ..config }.save(&path).await.unwrap();
+        
+        // Killed half way through a plain write, inside the game path
+        let content = tokio::fs::read_to_string(&path).await.unwrap();
+        let cut = content.find("HytaleClient-beta").unwrap();
+        tokio::fs::write(&path, &content[..cut]).await.unwrap();
+        
+        let (recovered, report) = AppConfig::load(&path).await.unwrap();
+        assert!(report.errors.is_empty());
+        assert_eq!(recovered.default_game_path, Some(stable));
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
 }
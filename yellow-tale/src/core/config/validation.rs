@@ -0,0 +1,210 @@
+//! Field-level config validation
+//!
+//! A config is resolved one value at a time on top of the defaults, so a
+//! single bad value falls back to its default instead of discarding the
+//! whole file. Every rejected value and unknown key ends up in the report.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use super::AppConfig;
+
+const MIN_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+const MAX_CACHE_BYTES: u64 = 1024 * 1024 * 1024 * 1024;
+
+/// Keys that are valid but absent from the serialized defaults
+const OPTIONAL_KEYS: &[&str] = &["default_game_path"];
+
+/// A single problem with a config value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigIssue {
+    /// Dotted key path, e.g. `cache.max_size_bytes`; empty for the whole file
+    pub field: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl ConfigIssue {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into(), line: None, column: None }
+    }
+}
+
+/// Everything found while loading a config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigReport {
+    /// Version the file was upgraded from, if it needed migrating
+    pub migrated_from: Option<u32>,
+    /// Values that were rejected and replaced by their defaults
+    pub errors: Vec<ConfigIssue>,
+    /// Values that were ignored, such as unknown keys
+    pub warnings: Vec<ConfigIssue>,
+}
+
+impl ConfigReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+fn defaults() -> Table {
+    match Value::try_from(AppConfig::default()) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
+fn lookup<'a>(table: &'a Table, path: &[&str]) -> Option<&'a Value> {
+    let (last, parents) = path.split_last()?;
+    let mut current = table;
+    for key in parents {
+        current = current.get(*key)?.as_table()?;
+    }
+    current.get(*last)
+}
+
+/// Set or, with `None`, remove the value at `path`
+fn assign(table: &mut Table, path: &[&str], value: Option<Value>) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut current = table;
+    for key in parents {
+        let entry = current.entry(key.to_string()).or_insert_with(|| Value::Table(Table::new()));
+        let Value::Table(next) = entry else {
+            return;
+        };
+        current = next;
+    }
+    match value {
+        Some(value) => current.insert(last.to_string(), value),
+        None => current.remove(*last),
+    };
+}
+
+/// Copy each value from `user` onto `merged` if the result still deserializes
+fn merge(merged: &mut Table, defaults: &Table, user: &Table, prefix: &mut Vec<String>, report: &mut ConfigReport) {
+    for (key, value) in user {
+        if prefix.is_empty() && key == "config_version" {
+            continue;
+        }
+        prefix.push(key.clone());
+        let path: Vec<&str> = prefix.iter().map(String::as_str).collect();
+        let field = prefix.join(".");
+
+        match (lookup(defaults, &path), value) {
+            (Some(Value::Table(_)), Value::Table(section)) => {
+                merge(merged, defaults, section, prefix, report);
+            }
+            (None, _) if !OPTIONAL_KEYS.contains(&field.as_str()) => {
+                report.warnings.push(ConfigIssue::new(&field, "Unknown key, ignored"));
+            }
+            _ => {
+                let mut candidate = merged.clone();
+                assign(&mut candidate, &path, Some(value.clone()));
+                match Value::Table(candidate.clone()).try_into::<AppConfig>() {
+                    Ok(_) => *merged = candidate,
+                    Err(e) => report.errors.push(ConfigIssue::new(&field, e.message().trim())),
+                }
+            }
+        }
+        prefix.pop();
+    }
+}
+
+fn check_range(field: &str, value: u64, min: u64, max: u64, issues: &mut Vec<ConfigIssue>) {
+    if value < min || value > max {
+        issues.push(ConfigIssue::new(field, format!("{} is out of range ({}..={})", value, min, max)));
+    }
+}
+
+fn check_choice(field: &str, value: &str, choices: &[&str], issues: &mut Vec<ConfigIssue>) {
+    if !choices.contains(&value.to_lowercase().as_str()) {
+        issues.push(ConfigIssue::new(field, format!("'{}' is not one of: {}", value, choices.join(", "))));
+    }
+}
+
+/// Semantic checks on values that deserialized fine
+pub fn check_values(config: &AppConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    check_range("cache.max_size_bytes", config.cache.max_size_bytes, MIN_CACHE_BYTES, MAX_CACHE_BYTES, &mut issues);
+    check_choice("performance.default_priority", &config.performance.default_priority, &["low", "normal", "high", "realtime"], &mut issues);
+    check_choice("session.preferred_method", &config.session.preferred_method, &["p2p", "relay", "hybrid"], &mut issues);
+    check_range("session.max_relay_hops", config.session.max_relay_hops as u64, 1, 8, &mut issues);
+    check_range("session.p2p_timeout_secs", config.session.p2p_timeout_secs, 1, 120, &mut issues);
+    check_choice("telemetry.log_level", &config.telemetry.log_level, &["trace", "debug", "info", "warn", "error"], &mut issues);
+    check_range("telemetry.max_log_size_mb", config.telemetry.max_log_size_mb, 1, 1024, &mut issues);
+    check_range("telemetry.log_retention", config.telemetry.log_retention as u64, 1, 100, &mut issues);
+    check_range("netdiag.samples", config.netdiag.samples as u64, 1, 50, &mut issues);
+    check_range("netdiag.timeout_ms", config.netdiag.timeout_ms, 100, 30_000, &mut issues);
+    check_range("netdiag.refresh_interval_secs", config.netdiag.refresh_interval_secs, 30, 86_400, &mut issues);
+    check_range("netdiag.max_concurrent_probes", config.netdiag.max_concurrent_probes as u64, 1, 256, &mut issues);
+    check_range("snapshots.interval_minutes", config.snapshots.interval_minutes, 5, 7 * 24 * 60, &mut issues);
+    check_range("snapshots.keep_last", config.snapshots.keep_last as u64, 1, 100, &mut issues);
+    check_range("snapshots.keep_daily_days", config.snapshots.keep_daily_days as u64, 0, 90, &mut issues);
+    check_range("snapshots.max_total_mb", config.snapshots.max_total_mb, 0, 1024 * 1024, &mut issues);
+    check_range("prefetch.max_concurrent", config.prefetch.max_concurrent as u64, 1, 16, &mut issues);
+    check_range("prefetch.bandwidth_limit_kbps", config.prefetch.bandwidth_limit_kbps, 0, 1024 * 1024, &mut issues);
+    check_range("prefetch.max_attempts", config.prefetch.max_attempts as u64, 1, 10, &mut issues);
+    check_range("launcher.shutdown_timeout_secs", config.launcher.shutdown_timeout_secs, 1, 300, &mut issues);
+    check_range("diagnostics.sample_interval_secs", config.diagnostics.sample_interval_secs, 1, 300, &mut issues);
+    check_range("diagnostics.history_minutes", config.diagnostics.history_minutes, 1, 24 * 60, &mut issues);
+    check_range("ipc.command_timeout_secs", config.ipc.command_timeout_secs, 1, 600, &mut issues);
+    check_range("auth.max_failed_attempts", config.auth.max_failed_attempts as u64, 1, 100, &mut issues);
+    check_range("auth.lockout_window_secs", config.auth.lockout_window_secs, 60, 24 * 60 * 60, &mut issues);
+    check_range("auth.max_lockout_secs", config.auth.max_lockout_secs, 30, 24 * 60 * 60, &mut issues);
+    check_range("friends.request_expiry_days", config.friends.request_expiry_days, 1, 365, &mut issues);
+
+    let thresholds = &config.netdiag.thresholds;
+    check_range("netdiag.thresholds.ok_loss_percent", thresholds.ok_loss_percent as u64, 0, 100, &mut issues);
+    for (good, ok, good_value, ok_value) in [
+        ("good_rtt_ms", "ok_rtt_ms", thresholds.good_rtt_ms, thresholds.ok_rtt_ms),
+        ("good_jitter_ms", "ok_jitter_ms", thresholds.good_jitter_ms, thresholds.ok_jitter_ms),
+        ("good_loss_percent", "ok_loss_percent", thresholds.good_loss_percent, thresholds.ok_loss_percent),
+    ] {
+        if good_value > ok_value {
+            issues.push(ConfigIssue::new(format!("netdiag.thresholds.{}", good), format!("{} is above {} ({})", good_value, ok, ok_value)));
+        }
+    }
+
+    if !config.email.from_address.contains('@') {
+        issues.push(ConfigIssue::new("email.from_address", format!("'{}' is not an email address", config.email.from_address)));
+    }
+
+    let url = &config.sync.server_url;
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        issues.push(ConfigIssue::new("sync.server_url", format!("'{}' is not an http(s) URL", url)));
+    }
+    if let Some(game_path) = &config.default_game_path {
+        if !Path::new(game_path).exists() {
+            issues.push(ConfigIssue::new("default_game_path", format!("{} does not exist", game_path)));
+        }
+    }
+
+    issues
+}
+
+/// Build a config from a migrated table, replacing every invalid value with
+/// its default
+pub fn resolve(user: &Table, report: &mut ConfigReport) -> AppConfig {
+    let defaults = defaults();
+    let mut merged = defaults.clone();
+    merge(&mut merged, &defaults, user, &mut Vec::new(), report);
+
+    let mut config: AppConfig = Value::Table(merged.clone()).try_into().unwrap_or_default();
+    let invalid = check_values(&config);
+    if !invalid.is_empty() {
+        for issue in &invalid {
+            let path: Vec<&str> = issue.field.split('.').collect();
+            assign(&mut merged, &path, lookup(&defaults, &path).cloned());
+        }
+        config = Value::Table(merged).try_into().unwrap_or_default();
+        report.errors.extend(invalid);
+    }
+    config
+}
@@ -0,0 +1,240 @@
+//! Config hot-reload
+//!
+//! Watches `config.toml` and re-reads it whenever it changes on disk:
+//! - Only the fields in [`LIVE_FIELDS`] take effect while running; any other
+//!   change is reported as needing a restart and otherwise ignored
+//! - A file that doesn't parse, or has invalid values, is reported with the
+//!   field, line and column of each problem and changes nothing
+//! - The directory is watched rather than the file, so editors that save by
+//!   replacing the file are still noticed
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+use super::{AppConfig, ConfigError, ConfigReport};
+
+/// Fields applied without a restart
+pub const LIVE_FIELDS: &[&str] = &["cache.max_size_bytes", "telemetry.log_level", "session.relay_servers", "session.stun_servers", "session.encrypt_payloads", "launcher.install_hints", "snapshots.scheduled", "snapshots.interval_minutes", "snapshots.keep_last", "snapshots.keep_daily_days", "snapshots.only_when_game_stopped", "snapshots.max_total_mb", "prefetch.max_concurrent", "prefetch.bandwidth_limit_kbps", "prefetch.max_attempts"];
+
+/// Quiet time after the last file event before the file is read, so a save
+/// written in several steps is read once
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// A valid edit of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigChange {
+    /// Changed fields that take effect now
+    pub applied: Vec<String>,
+    /// Changed fields that wait for the next start
+    pub restart_required: Vec<String>,
+    /// The file as it now reads
+    pub config: AppConfig,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfigEvent {
+    Changed(Box<ConfigChange>),
+    /// The edit was rejected
+    Invalid(ConfigReport),
+}
+
+/// The config as last read, compared against each new version of the file
+#[derive(Debug, Clone)]
+pub struct ConfigState {
+    current: AppConfig,
+}
+
+impl ConfigState {
+    pub fn new(current: AppConfig) -> Self {
+        Self { current }
+    }
+
+    pub fn current(&self) -> &AppConfig {
+        &self.current
+    }
+
+    /// Take `content` as the new file. None when nothing changed.
+    pub fn update(&mut self, content: &str) -> Option<ConfigEvent> {
+        let config = match AppConfig::parse(content) {
+            Ok((config, report)) if report.is_valid() => config,
+            Ok((_, report)) => return Some(ConfigEvent::Invalid(report)),
+            Err(_) => return Some(ConfigEvent::Invalid(AppConfig::validate(content))),
+        };
+
+        let changed = changed_fields(&self.current, &config);
+        if changed.is_empty() {
+            return None;
+        }
+        let (applied, restart_required) = changed.into_iter()
+            .partition(|field| LIVE_FIELDS.contains(&field.as_str()));
+        self.current = config.clone();
+        Some(ConfigEvent::Changed(Box::new(ConfigChange { applied, restart_required, config })))
+    }
+}
+
+/// Dotted paths of the values that differ between `old` and `new`, sorted
+pub fn changed_fields(old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    let old = serde_json::to_value(old).unwrap_or_default();
+    let new = serde_json::to_value(new).unwrap_or_default();
+    let mut changed = Vec::new();
+    diff_values("", &old, &new, &mut changed);
+    changed.sort();
+    changed
+}
+
+fn diff_values(path: &str, old: &serde_json::Value, new: &serde_json::Value, changed: &mut Vec<String>) {
+    match (old, new) {
+        (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+            let keys: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                let missing = serde_json::Value::Null;
+                diff_values(&field, old.get(key).unwrap_or(&missing), new.get(key).unwrap_or(&missing), changed);
+            }
+        }
+        (old, new) if old != new => changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+/// Re-reads the config file in the background while it's alive
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    task: tokio::task::JoinHandle<()>,
+    events: broadcast::Sender<ConfigEvent>,
+}
+
+impl ConfigWatcher {
+    /// Watch `path`, comparing each change against `current`
+    pub fn spawn(path: PathBuf, current: AppConfig) -> Result<Self, ConfigError> {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf();
+        let file_name = path.file_name().map(|name| name.to_os_string());
+
+        let (touched, mut touches) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            if event.kind.is_access() {
+                return;
+            }
+            if event.paths.iter().any(|changed| changed.file_name().map(|name| name.to_os_string()) == file_name) {
+                let _ = touched.send(());
+            }
+        })
+        .map_err(|e| ConfigError::WatchFailed(e.to_string()))?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| ConfigError::WatchFailed(e.to_string()))?;
+
+        let events = broadcast::channel(16).0;
+        let sender = events.clone();
+        let task = tokio::spawn(async move {
+            let mut state = ConfigState::new(current);
+            while touches.recv().await.is_some() {
+                tokio::time::sleep(DEBOUNCE).await;
+                while touches.try_recv().is_ok() {}
+
+                let content = match tokio::fs::read_to_string(&path).await {
+                    Ok(content) => content,
+                    // Mid-replace, or deleted; the next save is picked up
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => {
+                        warn!("Could not re-read {:?}: {}", path, e);
+                        continue;
+                    }
+                };
+                match state.update(&content) {
+                    Some(ConfigEvent::Changed(change)) => {
+                        info!("Config changed: applied {:?}, restart required for {:?}", change.applied, change.restart_required);
+                        let _ = sender.send(ConfigEvent::Changed(change));
+                    }
+                    Some(ConfigEvent::Invalid(report)) => {
+                        for issue in &report.errors {
+                            warn!("Config edit rejected, {}: {}", issue.field, issue.message);
+                        }
+                        let _ = sender.send(ConfigEvent::Invalid(report));
+                    }
+                    None => {}
+                }
+            }
+        });
+
+        Ok(Self { _watcher: watcher, task, events })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ConfigEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::SessionConfig;
+
+    #[test]
+    fn test_live_and_restart_fields_are_told_apart() {
+        let mut state = ConfigState::new(AppConfig::default());
+        let content = "config_version = 1\n\
+                       [cache]\nmax_size_bytes = 2147483648\n\
+                       [telemetry]\nlog_level = \"debug\"\n\
+                       [launcher]\nshutdown_timeout_secs = 30\n";
+        let Some(ConfigEvent::Changed(change)) = state.update(content) else { panic!("expected a change") };
+        assert_eq!(change.applied, ["cache.max_size_bytes", "telemetry.log_level"]);
+        assert_eq!(change.restart_required, ["launcher.shutdown_timeout_secs"]);
+        assert_eq!(state.current().cache.max_size_bytes, 2 * 1024 * 1024 * 1024);
+
+        // Saving the same content again isn't a change
+        assert!(state.update(content).is_none());
+    }
+
+    #[test]
+    fn test_invalid_edits_are_rejected_with_details() {
+        let mut state = ConfigState::new(AppConfig::default());
+
+        let Some(ConfigEvent::Invalid(report)) = state.update("[cache]\nmax_size_bytes = ") else { panic!("expected a rejection") };
+        assert_eq!(report.errors[0].line, Some(2));
+
+        let Some(ConfigEvent::Invalid(report)) = state.update("config_version = 1\n[telemetry]\nlog_level = \"loud\"\n") else {
+            panic!("expected a rejection")
+        };
+        assert_eq!(report.errors[0].field, "telemetry.log_level");
+
+        // Nothing was taken from either
+        assert_eq!(state.current().telemetry.log_level, "info");
+    }
+
+    #[tokio::test]
+    async fn test_file_edits_are_noticed() {
+        let dir = std::env::temp_dir().join(format!("yt-config-watch-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("config.toml");
+        AppConfig::default().save(&path).await.unwrap();
+        let watcher = ConfigWatcher::spawn(path.clone(), AppConfig::default()).unwrap();
+        let mut events = watcher.subscribe();
+
+        let relays = AppConfig {
+            session: SessionConfig { relay_servers: vec!["relay.example:9000".to_string()], ..Default::default() },
+            ..AppConfig::default()
+        };
+        relays.save(&path).await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap().unwrap();
+        let ConfigEvent::Changed(change) = event else { panic!("expected a change") };
+        assert_eq!(change.applied, ["session.relay_servers"]);
+
+        tokio::fs::write(&path, "[session\n").await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap().unwrap();
+        assert!(matches!(event, ConfigEvent::Invalid(_)));
+
+        drop(watcher);
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
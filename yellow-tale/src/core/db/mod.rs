@@ -1,6 +1,10 @@
+pub mod supervisor;
+
+use std::time::Duration;
+
 use sqlx::{postgres::PgPoolOptions, PgPool, Error as SqlxError};
 use thiserror::Error;
-use tracing::{info, error};
+use tracing::info;
 
 #[derive(Error, Debug)]
 pub enum DbError {
@@ -26,11 +30,16 @@ impl Database {
         let database_url = std::env::var("DATABASE_URL")
             .map_err(|_| DbError::MissingDatabaseUrl)?;
         
+        Self::connect_url(&database_url).await
+    }
+    
+    pub async fn connect_url(database_url: &str) -> Result<Self, DbError> {
         info!("Connecting to database...");
         
         let pool = PgPoolOptions::new()
             .max_connections(10)
-            .connect(&database_url)
+            .acquire_timeout(Duration::from_secs(5))
+            .connect(database_url)
             .await
             .map_err(|e| DbError::ConnectionFailed(e.to_string()))?;
         
@@ -39,6 +48,10 @@ impl Database {
         Ok(Self { pool })
     }
     
+    pub fn from_pool(pool: PgPool) -> Self {
+        Self { pool }
+    }
+    
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
@@ -85,6 +98,58 @@ impl Database {
         .await
         .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
         
+        // Refresh tokens; rows from before them can't be refreshed
+        sqlx::query(r#"
+            ALTER TABLE user_sessions
+                ADD COLUMN IF NOT EXISTS refresh_token_hash VARCHAR(255) UNIQUE,
+                ADD COLUMN IF NOT EXISTS refresh_expires_at TIMESTAMPTZ,
+                ADD COLUMN IF NOT EXISTS family_id UUID,
+                ADD COLUMN IF NOT EXISTS replaced_by UUID
+        "#)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
+        
+        sqlx::query(r#"
+            ALTER TABLE user_sessions
+                ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ
+        "#)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
+        
+        sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
+        
+        // The latest email verification code per user
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS email_verifications (
+                user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                code_hash VARCHAR(64) NOT NULL,
+                sent_at TIMESTAMPTZ NOT NULL,
+                expires_at TIMESTAMPTZ NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
+        
+        // Failed password attempts, by lowercased username and device
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS login_attempts (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                username VARCHAR(255) NOT NULL,
+                source VARCHAR(255),
+                attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
+        
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS friendships (
                 id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -100,6 +165,16 @@ impl Database {
         .await
         .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
         
+        // Requests from before these have no message and never expire
+        sqlx::query(r#"
+            ALTER TABLE friendships
+                ADD COLUMN IF NOT EXISTS message VARCHAR(200),
+                ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ
+        "#)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::MigrationFailed(e.to_string()))?;
+        
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS blocks (
                 id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -152,6 +227,9 @@ impl Database {
         let indexes = [
             "CREATE INDEX IF NOT EXISTS idx_users_username ON users(username)",
             "CREATE INDEX IF NOT EXISTS idx_users_email ON users(email)",
+            "CREATE INDEX IF NOT EXISTS idx_user_sessions_family ON user_sessions(family_id)",
+            "CREATE INDEX IF NOT EXISTS idx_login_attempts_username ON login_attempts(username, attempted_at)",
+            "CREATE INDEX IF NOT EXISTS idx_login_attempts_source ON login_attempts(source, attempted_at)",
             "CREATE INDEX IF NOT EXISTS idx_friendships_user ON friendships(user_id)",
             "CREATE INDEX IF NOT EXISTS idx_friendships_friend ON friendships(friend_id)",
             "CREATE INDEX IF NOT EXISTS idx_sessions_host ON game_sessions(host_id)",
@@ -0,0 +1,349 @@
+//! Database connection supervision
+//!
+//! Keeps retrying the connection in the background so accounts and friends
+//! come online as soon as Postgres is reachable, and re-probes the connection
+//! whenever a query fails for connectivity reasons.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::{Error as SqlxError, PgPool};
+use tokio::sync::{watch, Notify, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::{Database, DbError};
+use crate::core::friends::FriendsService;
+use crate::core::health::{CheckResult, HealthCheck};
+use crate::core::mail::Mailer;
+use crate::core::presence::PresenceHub;
+use crate::core::users::{lockout::LockoutPolicy, UserService};
+
+/// Services that need a live database
+pub struct DatabaseServices {
+    pub users: UserService,
+    pub friends: FriendsService,
+    /// Status changes announced by the users service
+    pub presence: PresenceHub,
+    pub pool: PgPool,
+}
+
+/// How the services are set up once connected
+#[derive(Clone, Default)]
+pub struct ServiceOptions {
+    pub lockout: LockoutPolicy,
+    /// Sends email verification codes; without one they can't be requested
+    pub mailer: Option<Arc<dyn Mailer>>,
+    /// Friend requests need a confirmed email address
+    pub require_verified_email: bool,
+    /// How long friend requests wait for an answer; the service default when unset
+    pub friend_request_ttl: Option<chrono::Duration>,
+    pub presence: PresenceHub,
+}
+
+impl DatabaseServices {
+    pub fn new(db: &Database, options: &ServiceOptions) -> Self {
+        let mut users = UserService::new(db.pool().clone())
+            .with_lockout(options.lockout.clone())
+            .with_presence(options.presence.clone());
+        if let Some(mailer) = &options.mailer {
+            users = users.with_mailer(mailer.clone());
+        }
+        let mut friends = FriendsService::new(db.pool().clone()).with_verified_email_required(options.require_verified_email);
+        if let Some(ttl) = options.friend_request_ttl {
+            friends = friends.with_request_ttl(ttl);
+        }
+        Self {
+            users,
+            friends,
+            presence: options.presence.clone(),
+            pool: db.pool().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseStatus {
+    /// Never connected; retrying in the background
+    Offline,
+    Online,
+    /// Connected before, but the last probe or query failed
+    Degraded,
+}
+
+#[derive(Clone)]
+pub struct SupervisorConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub probe_interval: Duration,
+    pub services: ServiceOptions,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            probe_interval: Duration::from_secs(30),
+            services: ServiceOptions::default(),
+        }
+    }
+}
+
+/// How the supervisor reaches the database
+#[async_trait]
+pub trait DatabaseConnector: Send + Sync + 'static {
+    async fn connect(&self) -> Result<Database, DbError>;
+    async fn probe(&self, db: &Database) -> bool;
+}
+
+pub struct PostgresConnector {
+    url: String,
+}
+
+impl PostgresConnector {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    pub fn from_env() -> Result<Self, DbError> {
+        std::env::var("DATABASE_URL")
+            .map(Self::new)
+            .map_err(|_| DbError::MissingDatabaseUrl)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait]
+impl DatabaseConnector for PostgresConnector {
+    async fn connect(&self) -> Result<Database, DbError> {
+        let db = Database::connect_url(&self.url).await?;
+        if let Err(e) = db.run_migrations().await {
+            warn!("Migration warning: {}", e);
+        }
+        Ok(db)
+    }
+
+    async fn probe(&self, db: &Database) -> bool {
+        sqlx::query("SELECT 1").execute(db.pool()).await.is_ok()
+    }
+}
+
+/// Whether a query failed because the database couldn't be reached
+pub fn is_connection_error(error: &SqlxError) -> bool {
+    matches!(
+        error,
+        SqlxError::Io(_) | SqlxError::PoolTimedOut | SqlxError::PoolClosed | SqlxError::WorkerCrashed
+    )
+}
+
+/// Service errors that may wrap a query failure
+pub trait QueryError {
+    fn sqlx_error(&self) -> Option<&SqlxError>;
+}
+
+/// Owns the database-backed services and keeps them connected
+#[derive(Clone)]
+pub struct DatabaseSupervisor {
+    services: Arc<RwLock<Option<DatabaseServices>>>,
+    status: Arc<watch::Sender<DatabaseStatus>>,
+    probe: Arc<Notify>,
+}
+
+impl DatabaseSupervisor {
+    pub fn new() -> Self {
+        let (status, _) = watch::channel(DatabaseStatus::Offline);
+        Self {
+            services: Arc::new(RwLock::new(None)),
+            status: Arc::new(status),
+            probe: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Slot that holds the services once connected
+    pub fn services(&self) -> Arc<RwLock<Option<DatabaseServices>>> {
+        self.services.clone()
+    }
+
+    pub fn status(&self) -> DatabaseStatus {
+        *self.status.borrow()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<DatabaseStatus> {
+        self.status.subscribe()
+    }
+
+    /// Mark the connection degraded and probe it right away
+    pub fn report_failure(&self) {
+        self.status.send_if_modified(|status| {
+            let changed = *status == DatabaseStatus::Online;
+            if changed {
+                *status = DatabaseStatus::Degraded;
+            }
+            changed
+        });
+        self.probe.notify_one();
+    }
+
+    pub fn spawn(&self, connector: impl DatabaseConnector, config: SupervisorConfig) -> JoinHandle<()> {
+        let supervisor = self.clone();
+        tokio::spawn(async move { supervisor.run(connector, config).await })
+    }
+
+    fn set_status(&self, new_status: DatabaseStatus) {
+        let changed = self.status.send_if_modified(|status| {
+            let changed = *status != new_status;
+            *status = new_status;
+            changed
+        });
+        if changed {
+            info!("Database status: {:?}", new_status);
+        }
+    }
+
+    async fn run(self, connector: impl DatabaseConnector, config: SupervisorConfig) {
+        let mut backoff = config.initial_backoff;
+        let db = loop {
+            match connector.connect().await {
+                Ok(db) => break db,
+                Err(e) => {
+                    warn!("Database not available, retrying in {:?}: {}", backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(config.max_backoff);
+                }
+            }
+        };
+
+        *self.services.write().await = Some(DatabaseServices::new(&db, &config.services));
+        self.set_status(DatabaseStatus::Online);
+
+        backoff = config.initial_backoff;
+        loop {
+            let wait = match self.status() {
+                DatabaseStatus::Online => config.probe_interval,
+                _ => backoff,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.probe.notified() => {}
+            }
+
+            if connector.probe(&db).await {
+                backoff = config.initial_backoff;
+                self.set_status(DatabaseStatus::Online);
+            } else {
+                backoff = (backoff * 2).min(config.max_backoff);
+                self.set_status(DatabaseStatus::Degraded);
+            }
+        }
+    }
+}
+
+/// Down until connected; otherwise whether a pooled connection can be had
+#[async_trait]
+impl HealthCheck for DatabaseSupervisor {
+    fn name(&self) -> &str {
+        "database"
+    }
+
+    async fn health_check(&self) -> CheckResult {
+        let pool = match self.services.read().await.as_ref() {
+            Some(services) => services.pool.clone(),
+            None => return CheckResult::down("Not connected"),
+        };
+        match pool.acquire().await {
+            Ok(_) if self.status() == DatabaseStatus::Degraded => CheckResult::degraded("Last probe failed"),
+            Ok(_) => CheckResult::ok(),
+            Err(e) => CheckResult::down(format!("Could not acquire a connection: {}", e)),
+        }
+    }
+}
+
+impl Default for DatabaseSupervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Treats the database as reachable whenever something listens on `addr`
+    struct PortConnector {
+        addr: SocketAddr,
+    }
+
+    #[async_trait]
+    impl DatabaseConnector for PortConnector {
+        async fn connect(&self) -> Result<Database, DbError> {
+            TcpStream::connect(self.addr).await
+                .map_err(|e| DbError::ConnectionFailed(e.to_string()))?;
+            let pool = sqlx::postgres::PgPoolOptions::new()
+                .connect_lazy(&format!("postgres://yellowtale@{}/yellowtale", self.addr))
+                .map_err(|e| DbError::ConnectionFailed(e.to_string()))?;
+            Ok(Database::from_pool(pool))
+        }
+
+        async fn probe(&self, _db: &Database) -> bool {
+            TcpStream::connect(self.addr).await.is_ok()
+        }
+    }
+
+    fn fast_config() -> SupervisorConfig {
+        SupervisorConfig {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            probe_interval: Duration::from_millis(20),
+            ..SupervisorConfig::default()
+        }
+    }
+
+    async fn wait_for(status: &mut watch::Receiver<DatabaseStatus>, expected: DatabaseStatus) {
+        tokio::time::timeout(Duration::from_secs(5), status.wait_for(|s| *s == expected))
+            .await
+            .expect("status change timed out")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_comes_online_when_port_starts_listening() {
+        // Reserve a free port, then release it so nothing listens yet
+        let addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+        let supervisor = DatabaseSupervisor::new();
+        let mut status = supervisor.subscribe();
+        let task = supervisor.spawn(PortConnector { addr }, fast_config());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(supervisor.status(), DatabaseStatus::Offline);
+        assert!(supervisor.services().read().await.is_none());
+
+        let listener = TcpListener::bind(addr).await.unwrap();
+        wait_for(&mut status, DatabaseStatus::Online).await;
+        assert!(supervisor.services().read().await.is_some());
+
+        drop(listener);
+        supervisor.report_failure();
+        wait_for(&mut status, DatabaseStatus::Degraded).await;
+
+        let _listener = TcpListener::bind(addr).await.unwrap();
+        wait_for(&mut status, DatabaseStatus::Online).await;
+
+        task.abort();
+    }
+
+    #[test]
+    fn test_connection_errors_are_recognized() {
+        assert!(is_connection_error(&SqlxError::PoolTimedOut));
+        assert!(!is_connection_error(&SqlxError::RowNotFound));
+    }
+}
@@ -0,0 +1,459 @@
+//! Bottleneck detection over the metrics history
+//!
+//! Each heuristic is a pure function over samples, oldest first, and returns
+//! at most one finding for the stretch where its pattern held longest.
+//! Every finding names the performance setting that addresses it.
+
+use std::ops::Range;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::MetricsSample;
+use crate::core::performance::PriorityLevel;
+
+/// Never suggest a heap smaller than this
+const MIN_HEAP_MB: u64 = 1024;
+
+/// Left free for the OS and the JVM's own overhead when lowering the heap
+const HEAP_HEADROOM_MB: u64 = 512;
+
+/// How the game was launched; needed to tell a bottleneck from a setting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LaunchContext {
+    /// Cores on the machine
+    pub cpu_cores: usize,
+
+    /// Cores the game is bound to (empty = all cores)
+    pub cpu_affinity: Vec<usize>,
+
+    /// The profile's `-Xmx` in MB, if known
+    pub max_heap_mb: Option<u64>,
+}
+
+/// Limits the heuristics compare against
+#[derive(Debug, Clone, Copy)]
+pub struct AnalysisThresholds {
+    /// Consecutive samples a pattern must hold for to count as sustained
+    pub min_sustained_samples: usize,
+
+    /// Core usage at or above this is saturated (percent)
+    pub core_saturated: f32,
+
+    /// Share of RAM in use at or above which the system is out of memory
+    pub ram_pressure: f32,
+
+    /// Overall CPU usage below this rules the CPU out (percent)
+    pub low_cpu: f32,
+
+    /// GPU usage at or above this counts as busy, when observable (percent)
+    pub gpu_busy: f32,
+
+    /// Frame-time coefficient of variation that counts as uneven pacing
+    pub frame_time_cv: f32,
+
+    /// A frame time this many times the median is a spike
+    pub spike_factor: f32,
+
+    /// Spikes needed during asset streaming before blaming the disk
+    pub min_stall_spikes: usize,
+
+    /// Disk reads in one sample that count as heavy
+    pub heavy_read_bytes: u64,
+
+    /// Share of streaming spikes that must coincide with heavy reads
+    pub stall_correlation: f32,
+}
+
+impl Default for AnalysisThresholds {
+    fn default() -> Self {
+        Self {
+            min_sustained_samples: 30,
+            core_saturated: 90.0,
+            ram_pressure: 0.95,
+            low_cpu: 50.0,
+            gpu_busy: 85.0,
+            frame_time_cv: 0.35,
+            spike_factor: 2.0,
+            min_stall_spikes: 5,
+            heavy_read_bytes: 32 * 1024 * 1024,
+            stall_correlation: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    CpuSaturation,
+    MemoryPaging,
+    GpuBound,
+    DiskStall,
+}
+
+/// The samples a finding is based on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvidenceWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub samples: usize,
+}
+
+/// A change to a performance setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "setting", rename_all = "snake_case")]
+pub enum SuggestedAction {
+    /// `OptimizationSettings::cpu_affinity` (empty = all cores)
+    CpuAffinity { cores: Vec<usize> },
+
+    /// `OptimizationSettings::priority`
+    Priority { level: PriorityLevel },
+
+    /// The profile's `-Xmx`
+    MaxHeapMb { mb: u64 },
+
+    /// A feature gate to switch on
+    EnableFeature { feature: String },
+
+    /// `cache.enable_warming`, so assets are read ahead of launch
+    WarmDiskCache,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub category: Category,
+
+    /// 0.0 - 1.0
+    pub confidence: f32,
+
+    pub evidence_window: EvidenceWindow,
+
+    /// What was seen, for display
+    pub summary: String,
+
+    pub suggested_action: SuggestedAction,
+}
+
+/// Runs every heuristic and ranks the findings, most confident first
+pub fn analyze(samples: &[MetricsSample], context: &LaunchContext, thresholds: &AnalysisThresholds) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = [
+        cpu_saturation(samples, context, thresholds),
+        memory_paging(samples, context, thresholds),
+        gpu_bound(samples, thresholds),
+        disk_stall(samples, thresholds),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    findings.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    findings
+}
+
+/// A few cores pinned while the rest idle: the game's main threads can't
+/// spread out. Bound to a subset of cores, it should get them all back;
+/// otherwise a higher priority keeps those threads from being preempted.
+pub fn cpu_saturation(samples: &[MetricsSample], context: &LaunchContext, thresholds: &AnalysisThresholds) -> Option<Finding> {
+    let cores = samples.iter().map(|s| s.cpu_per_core.len()).max().unwrap_or(0).max(context.cpu_cores);
+    if cores < 2 {
+        return None;
+    }
+    let saturated = |s: &MetricsSample| s.cpu_per_core.iter().filter(|&&usage| usage >= thresholds.core_saturated).count();
+    let run = longest_run(samples, |_, s| {
+        let busy = saturated(s);
+        busy > 0 && busy * 2 <= cores
+    })?;
+    if run.len() < thresholds.min_sustained_samples {
+        return None;
+    }
+
+    let busy = samples[run.clone()].iter().map(saturated).sum::<usize>() as f32 / run.len() as f32;
+    let restricted = !context.cpu_affinity.is_empty() && context.cpu_affinity.len() < cores;
+    let (summary, suggested_action, weight) = if restricted {
+        (
+            format!("{:.1} of {} cores saturated while the game is bound to {}", busy, cores, context.cpu_affinity.len()),
+            SuggestedAction::CpuAffinity { cores: Vec::new() },
+            1.0,
+        )
+    } else {
+        (
+            format!("{:.1} of {} cores saturated while the others stay idle", busy, cores),
+            SuggestedAction::Priority { level: PriorityLevel::High },
+            0.7,
+        )
+    };
+
+    Some(Finding {
+        category: Category::CpuSaturation,
+        confidence: sustained_confidence(run.len(), thresholds) * weight,
+        evidence_window: window(samples, run),
+        summary,
+        suggested_action,
+    })
+}
+
+/// RAM full and swap growing: the heap asks for more than the machine has.
+/// Suggests a heap smaller by what was paged out.
+pub fn memory_paging(samples: &[MetricsSample], context: &LaunchContext, thresholds: &AnalysisThresholds) -> Option<Finding> {
+    let heap = context.max_heap_mb?;
+    let run = longest_run(samples, |_, s| {
+        s.ram_total_mb > 0 && s.ram_used_mb as f32 >= s.ram_total_mb as f32 * thresholds.ram_pressure
+    })?;
+    if run.len() < thresholds.min_sustained_samples {
+        return None;
+    }
+
+    let run_samples = &samples[run.clone()];
+    let swap_start = run_samples.first()?.swap_used_mb;
+    let paged_mb = run_samples.iter().map(|s| s.swap_used_mb).max()?.saturating_sub(swap_start);
+    if paged_mb == 0 {
+        return None;
+    }
+    let suggested = heap.saturating_sub(paged_mb + HEAP_HEADROOM_MB).max(MIN_HEAP_MB);
+    if suggested >= heap {
+        return None;
+    }
+
+    Some(Finding {
+        category: Category::MemoryPaging,
+        confidence: sustained_confidence(run.len(), thresholds),
+        evidence_window: window(samples, run),
+        summary: format!("RAM full and {} MB paged to swap with a {} MB heap", paged_mb, heap),
+        suggested_action: SuggestedAction::MaxHeapMb { mb: suggested },
+    })
+}
+
+/// Uneven frame pacing while the CPU has room to spare points at the GPU
+pub fn gpu_bound(samples: &[MetricsSample], thresholds: &AnalysisThresholds) -> Option<Finding> {
+    let run = longest_run(samples, |_, s| {
+        s.frame_time_ms.is_some()
+            && s.cpu_usage < thresholds.low_cpu
+            && s.gpu_usage.is_none_or(|gpu| gpu >= thresholds.gpu_busy)
+    })?;
+    if run.len() < thresholds.min_sustained_samples {
+        return None;
+    }
+
+    let run_samples = &samples[run.clone()];
+    let frame_times: Vec<f32> = run_samples.iter().filter_map(|s| s.frame_time_ms).collect();
+    let mean = frame_times.iter().sum::<f32>() / frame_times.len() as f32;
+    if mean <= 0.0 {
+        return None;
+    }
+    let variance = frame_times.iter().map(|t| (t - mean).powi(2)).sum::<f32>() / frame_times.len() as f32;
+    let cv = variance.sqrt() / mean;
+    if cv < thresholds.frame_time_cv {
+        return None;
+    }
+    // Without GPU usage the low CPU is the only evidence
+    let weight = if run_samples.iter().any(|s| s.gpu_usage.is_some()) { 1.0 } else { 0.75 };
+
+    Some(Finding {
+        category: Category::GpuBound,
+        confidence: sustained_confidence(run.len(), thresholds) * weight,
+        evidence_window: window(samples, run),
+        summary: format!("Frame times vary by {:.0}% around {:.1} ms with the CPU mostly idle", cv * 100.0, mean),
+        suggested_action: SuggestedAction::EnableFeature { feature: "performance.texture_compression".to_string() },
+    })
+}
+
+/// Frame spikes during asset streaming that line up with heavy disk reads
+pub fn disk_stall(samples: &[MetricsSample], thresholds: &AnalysisThresholds) -> Option<Finding> {
+    let mut frame_times: Vec<f32> = samples.iter().filter_map(|s| s.frame_time_ms).collect();
+    if frame_times.is_empty() {
+        return None;
+    }
+    frame_times.sort_by(f32::total_cmp);
+    let median = frame_times[frame_times.len() / 2];
+
+    let spikes: Vec<&MetricsSample> = samples.iter()
+        .filter(|s| s.asset_streaming && s.frame_time_ms.is_some_and(|t| t > median * thresholds.spike_factor))
+        .collect();
+    if spikes.len() < thresholds.min_stall_spikes {
+        return None;
+    }
+    let heavy = spikes.iter().filter(|s| s.disk_read_bytes >= thresholds.heavy_read_bytes).count();
+    let correlation = heavy as f32 / spikes.len() as f32;
+    if correlation < thresholds.stall_correlation {
+        return None;
+    }
+
+    let volume = (spikes.len() as f32 / (thresholds.min_stall_spikes * 2) as f32).min(1.0);
+    Some(Finding {
+        category: Category::DiskStall,
+        confidence: correlation * (0.5 + 0.5 * volume),
+        evidence_window: EvidenceWindow {
+            start: spikes.first()?.timestamp,
+            end: spikes.last()?.timestamp,
+            samples: spikes.len(),
+        },
+        summary: format!("{} of {} frame spikes while streaming assets came with heavy disk reads", heavy, spikes.len()),
+        suggested_action: SuggestedAction::WarmDiskCache,
+    })
+}
+
+/// Longest stretch of consecutive samples matching `matches`
+fn longest_run(samples: &[MetricsSample], matches: impl Fn(usize, &MetricsSample) -> bool) -> Option<Range<usize>> {
+    let mut best: Option<Range<usize>> = None;
+    let mut start = None;
+    for i in 0..=samples.len() {
+        match (i < samples.len() && matches(i, &samples[i]), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                if best.as_ref().is_none_or(|b| i - s > b.len()) {
+                    best = Some(s..i);
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    best
+}
+
+fn window(samples: &[MetricsSample], run: Range<usize>) -> EvidenceWindow {
+    EvidenceWindow {
+        start: samples[run.start].timestamp,
+        end: samples[run.end - 1].timestamp,
+        samples: run.len(),
+    }
+}
+
+/// 0.5 at the minimum sustained length, rising to 1.0 at four times that
+fn sustained_confidence(len: usize, thresholds: &AnalysisThresholds) -> f32 {
+    0.5 + 0.5 * (len as f32 / (thresholds.min_sustained_samples * 4).max(1) as f32).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn idle(n: usize) -> Vec<MetricsSample> {
+        let start = Utc::now();
+        (0..n).map(|i| MetricsSample {
+            timestamp: start + Duration::seconds(i as i64),
+            cpu_usage: 20.0,
+            cpu_per_core: vec![20.0; 8],
+            ram_used_mb: 8_000,
+            ram_total_mb: 16_000,
+            swap_used_mb: 0,
+            disk_read_bytes: 0,
+            disk_write_bytes: 0,
+            gpu_usage: None,
+            frame_time_ms: Some(16.0),
+            asset_streaming: false,
+            game_cpu_usage: None,
+            game_memory_mb: None,
+        }).collect()
+    }
+
+    fn context() -> LaunchContext {
+        LaunchContext { cpu_cores: 8, cpu_affinity: Vec::new(), max_heap_mb: Some(8_192) }
+    }
+
+    #[test]
+    fn test_healthy_history_has_no_findings() {
+        assert!(analyze(&idle(120), &context(), &AnalysisThresholds::default()).is_empty());
+    }
+
+    #[test]
+    fn test_few_saturated_cores_suggest_core_allocation() {
+        let mut samples = idle(120);
+        for s in &mut samples[20..80] {
+            s.cpu_per_core[0] = 99.0;
+            s.cpu_per_core[1] = 97.0;
+            s.cpu_usage = 35.0;
+        }
+        let thresholds = AnalysisThresholds::default();
+
+        let bound = LaunchContext { cpu_affinity: vec![0, 1], ..context() };
+        let finding = cpu_saturation(&samples, &bound, &thresholds).unwrap();
+        assert_eq!(finding.category, Category::CpuSaturation);
+        assert_eq!(finding.evidence_window.samples, 60);
+        assert_eq!(finding.suggested_action, SuggestedAction::CpuAffinity { cores: Vec::new() });
+
+        let unbound = cpu_saturation(&samples, &context(), &thresholds).unwrap();
+        assert_eq!(unbound.suggested_action, SuggestedAction::Priority { level: PriorityLevel::High });
+        assert!(unbound.confidence < finding.confidence);
+
+        // A short burst isn't sustained
+        assert!(cpu_saturation(&samples[70..], &bound, &thresholds).is_none());
+    }
+
+    #[test]
+    fn test_paging_suggests_a_smaller_heap() {
+        let mut samples = idle(90);
+        for (i, s) in samples[30..].iter_mut().enumerate() {
+            s.ram_used_mb = 15_900;
+            s.swap_used_mb = 100 + i as u64 * 25;
+        }
+        let finding = memory_paging(&samples, &context(), &AnalysisThresholds::default()).unwrap();
+        assert_eq!(finding.category, Category::MemoryPaging);
+        // 1475 MB paged plus headroom comes off the 8192 MB heap
+        assert_eq!(finding.suggested_action, SuggestedAction::MaxHeapMb { mb: 8_192 - 1_475 - 512 });
+
+        // Full RAM without swap growth isn't paging
+        for s in &mut samples {
+            s.swap_used_mb = 100;
+        }
+        assert!(memory_paging(&samples, &context(), &AnalysisThresholds::default()).is_none());
+    }
+
+    #[test]
+    fn test_erratic_frames_with_idle_cpu_are_gpu_bound() {
+        let mut samples = idle(60);
+        for (i, s) in samples.iter_mut().enumerate() {
+            s.frame_time_ms = Some(if i % 2 == 0 { 10.0 } else { 30.0 });
+            s.gpu_usage = Some(98.0);
+        }
+        let thresholds = AnalysisThresholds::default();
+        let finding = gpu_bound(&samples, &thresholds).unwrap();
+        assert_eq!(finding.category, Category::GpuBound);
+        assert!(matches!(finding.suggested_action, SuggestedAction::EnableFeature { .. }));
+
+        // The same pacing with a busy CPU isn't the GPU's fault
+        for s in &mut samples {
+            s.cpu_usage = 90.0;
+        }
+        assert!(gpu_bound(&samples, &thresholds).is_none());
+    }
+
+    #[test]
+    fn test_streaming_spikes_with_disk_reads_are_disk_stalls() {
+        let mut samples = idle(60);
+        for s in &mut samples[10..40] {
+            s.asset_streaming = true;
+        }
+        for i in (10..40).step_by(3) {
+            samples[i].frame_time_ms = Some(80.0);
+            samples[i].disk_read_bytes = 200 * 1024 * 1024;
+        }
+        let thresholds = AnalysisThresholds::default();
+        let finding = disk_stall(&samples, &thresholds).unwrap();
+        assert_eq!(finding.category, Category::DiskStall);
+        assert_eq!(finding.evidence_window.samples, 10);
+        assert_eq!(finding.suggested_action, SuggestedAction::WarmDiskCache);
+
+        // Spikes without reads behind them point elsewhere
+        for s in &mut samples {
+            s.disk_read_bytes = 0;
+        }
+        assert!(disk_stall(&samples, &thresholds).is_none());
+    }
+
+    #[test]
+    fn test_findings_are_ranked_by_confidence() {
+        let mut samples = idle(240);
+        for s in &mut samples {
+            s.cpu_per_core[0] = 99.0;
+        }
+        for (i, s) in samples[100..].iter_mut().enumerate() {
+            s.ram_used_mb = 15_900;
+            s.swap_used_mb = i as u64 * 50;
+        }
+        let findings = analyze(&samples, &context(), &AnalysisThresholds::default());
+        let categories: Vec<Category> = findings.iter().map(|f| f.category).collect();
+        assert_eq!(categories, vec![Category::MemoryPaging, Category::CpuSaturation]);
+        assert!(findings[0].confidence >= findings[1].confidence);
+    }
+}
@@ -0,0 +1,253 @@
+//! Frame pacing from an external frametime log
+//!
+//! The launcher can't see inside the game, so pacing is judged from a log
+//! someone else wrote: a PresentMon CSV, or the game's own frametime dump.
+//! - A CSV's frametime column is found by name (`MsBetweenPresents`,
+//!   `FrameTime`, ...); a log without a header has one frametime per line
+//! - 1% and 0.1% lows are the fps at the 99th and 99.9th percentile frame
+//!   times
+//! - A stutter is a frame taking more than `stutter_factor` times the median
+//!   of the frames just before it
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::summary::percentile;
+use super::DiagnosticsError;
+
+/// Column names that hold a frame time in ms, most specific first
+const FRAME_TIME_COLUMNS: &[&str] = &["msbetweenpresents", "frametime", "frame_time_ms", "frametime_ms", "ms"];
+
+/// Stutters listed in a report; the rest are only counted
+const MAX_STUTTER_EVENTS: usize = 100;
+
+/// Frames needed before the rolling median is trusted
+const MIN_MEDIAN_FRAMES: usize = 5;
+
+/// A frame that took much longer than the ones around it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StutterEvent {
+    /// Index of the frame in the log
+    pub frame: usize,
+    pub frame_time_ms: f64,
+    /// Median of the frames before it
+    pub median_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FramePacingReport {
+    pub source: PathBuf,
+    pub analyzed_at: DateTime<Utc>,
+    pub frames: usize,
+    /// Rows without a usable frame time
+    pub skipped_rows: usize,
+    pub duration_ms: f64,
+    pub avg_fps: f64,
+    pub avg_frame_time_ms: f64,
+    pub median_frame_time_ms: f64,
+    pub low_1_percent_fps: f64,
+    pub low_0_1_percent_fps: f64,
+    /// Variance of the frame times in ms², the lower the smoother
+    pub frame_time_variance: f64,
+    pub frame_time_std_dev_ms: f64,
+    pub stutter_count: usize,
+    /// The first stutters, in log order
+    pub stutters: Vec<StutterEvent>,
+}
+
+/// Frame times read from a log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameLog {
+    pub frame_times_ms: Vec<f64>,
+    pub skipped_rows: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct FramePacingAnalyzer {
+    /// How many times the rolling median a frame must take to stutter
+    pub stutter_factor: f64,
+    /// Frames the rolling median looks back over
+    pub median_window: usize,
+}
+
+impl Default for FramePacingAnalyzer {
+    fn default() -> Self {
+        Self { stutter_factor: 2.0, median_window: 30 }
+    }
+}
+
+impl FramePacingAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read and analyse the log at `path` off the async runtime
+    pub async fn analyze_file(&self, path: &Path) -> Result<FramePacingReport, DiagnosticsError> {
+        let source = path.to_path_buf();
+        let log = tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&source)?;
+            parse_frame_log(std::io::BufReader::new(file))
+        })
+        .await
+        .map_err(|e| DiagnosticsError::InvalidFrameLog(e.to_string()))??;
+        self.analyze(path, &log)
+    }
+
+    pub fn analyze(&self, source: &Path, log: &FrameLog) -> Result<FramePacingReport, DiagnosticsError> {
+        let times = &log.frame_times_ms;
+        if times.is_empty() {
+            return Err(DiagnosticsError::InvalidFrameLog("no frame times found".to_string()));
+        }
+
+        let mut sorted = times.clone();
+        sorted.sort_by(f64::total_cmp);
+        let frames = times.len();
+        let duration_ms: f64 = times.iter().sum();
+        let avg = duration_ms / frames as f64;
+        let variance = times.iter().map(|t| (t - avg).powi(2)).sum::<f64>() / frames as f64;
+        let fps_at = |p: f64| percentile(&sorted, p).map_or(0.0, fps);
+
+        let mut stutters = Vec::new();
+        let mut stutter_count = 0;
+        let mut window: VecDeque<f64> = VecDeque::with_capacity(self.median_window);
+        for (frame, &frame_time_ms) in times.iter().enumerate() {
+            if window.len() >= MIN_MEDIAN_FRAMES.min(self.median_window) {
+                let median_ms = median(window.iter().copied());
+                if frame_time_ms > median_ms * self.stutter_factor {
+                    stutter_count += 1;
+                    if stutters.len() < MAX_STUTTER_EVENTS {
+                        stutters.push(StutterEvent { frame, frame_time_ms, median_ms });
+                    }
+                }
+            }
+            if window.len() == self.median_window {
+                window.pop_front();
+            }
+            window.push_back(frame_time_ms);
+        }
+
+        Ok(FramePacingReport {
+            source: source.to_path_buf(),
+            analyzed_at: Utc::now(),
+            frames,
+            skipped_rows: log.skipped_rows,
+            duration_ms,
+            avg_fps: fps(avg),
+            avg_frame_time_ms: avg,
+            median_frame_time_ms: median(sorted.iter().copied()),
+            low_1_percent_fps: fps_at(99.0),
+            low_0_1_percent_fps: fps_at(99.9),
+            frame_time_variance: variance,
+            frame_time_std_dev_ms: variance.sqrt(),
+            stutter_count,
+            stutters,
+        })
+    }
+}
+
+/// Frame times from a CSV with a frametime column, or from one number per
+/// line. Blank lines and `#` comments are ignored; other rows without a
+/// positive frame time are counted as skipped.
+pub fn parse_frame_log(reader: impl BufRead) -> Result<FrameLog, DiagnosticsError> {
+    let mut log = FrameLog::default();
+    let mut column: Option<usize> = None;
+    let mut first = true;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+
+        if std::mem::take(&mut first) && parse_frame_time(fields[0]).is_none() {
+            let names: Vec<String> = fields.iter().map(|f| f.trim_matches('"').to_lowercase()).collect();
+            let found = FRAME_TIME_COLUMNS.iter().find_map(|wanted| names.iter().position(|name| name == wanted));
+            column = Some(found.ok_or_else(|| DiagnosticsError::InvalidFrameLog(format!("no frame time column in header '{}'", line)))?);
+            continue;
+        }
+
+        match fields.get(column.unwrap_or(0)).and_then(|f| parse_frame_time(f)) {
+            Some(frame_time) => log.frame_times_ms.push(frame_time),
+            None => log.skipped_rows += 1,
+        }
+    }
+    Ok(log)
+}
+
+fn parse_frame_time(field: &str) -> Option<f64> {
+    field.trim_matches('"').parse::<f64>().ok().filter(|t| t.is_finite() && *t > 0.0)
+}
+
+fn fps(frame_time_ms: f64) -> f64 {
+    if frame_time_ms > 0.0 { 1000.0 / frame_time_ms } else { 0.0 }
+}
+
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(f64::total_cmp);
+    match values.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => values[n / 2],
+        n => (values[n / 2 - 1] + values[n / 2]) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1000 frames at a steady 60 fps with 50 ms hitches at known frames
+    fn series(stutters_at: &[usize]) -> FrameLog {
+        let frame_times_ms = (0..1000)
+            .map(|i| if stutters_at.contains(&i) { 50.0 } else { 16.0 + (i % 3) as f64 * 0.5 })
+            .collect();
+        FrameLog { frame_times_ms, skipped_rows: 0 }
+    }
+
+    #[test]
+    fn test_known_stutters_and_lows() {
+        let report = FramePacingAnalyzer::new().analyze(Path::new("frames.csv"), &series(&[100, 500, 501, 900])).unwrap();
+
+        assert_eq!(report.frames, 1000);
+        assert_eq!(report.stutter_count, 4);
+        let frames: Vec<usize> = report.stutters.iter().map(|s| s.frame).collect();
+        assert_eq!(frames, [100, 500, 501, 900]);
+        assert_eq!(report.stutters[0].median_ms, 16.5);
+        assert_eq!(report.median_frame_time_ms, 16.5);
+
+        // Four hitches are under 1% of the frames but over 0.1%
+        assert_eq!(report.low_1_percent_fps, 1000.0 / 17.0);
+        assert_eq!(report.low_0_1_percent_fps, 20.0);
+        assert!(report.avg_fps > 59.0 && report.avg_fps < 61.0);
+
+        let smooth = FramePacingAnalyzer::new().analyze(Path::new("frames.csv"), &series(&[])).unwrap();
+        assert_eq!(smooth.stutter_count, 0);
+        assert!(smooth.frame_time_variance < report.frame_time_variance);
+        assert!((smooth.frame_time_std_dev_ms - smooth.frame_time_variance.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_presentmon_csv_and_plain_logs_parse() {
+        let csv = "Application,ProcessID,SwapChainAddress,Runtime,SyncInterval,MsBetweenPresents,MsBetweenDisplayChange\n\
+                   HytaleClient.exe,4242,0x1,DXGI,1,16.6,16.7\n\
+                   HytaleClient.exe,4242,0x1,DXGI,1,NA,16.7\n\
+                   \n\
+                   HytaleClient.exe,4242,0x1,DXGI,1,33.4,33.3\n";
+        let log = parse_frame_log(csv.as_bytes()).unwrap();
+        assert_eq!(log, FrameLog { frame_times_ms: vec![16.6, 33.4], skipped_rows: 1 });
+
+        let plain = "# frametimes from the client\n16.0\n17.5\n-1\n";
+        let log = parse_frame_log(plain.as_bytes()).unwrap();
+        assert_eq!(log, FrameLog { frame_times_ms: vec![16.0, 17.5], skipped_rows: 1 });
+
+        assert!(matches!(parse_frame_log("time,fps\n1,60\n".as_bytes()), Err(DiagnosticsError::InvalidFrameLog(_))));
+        let empty = FramePacingAnalyzer::new().analyze(Path::new("empty.log"), &FrameLog::default());
+        assert!(matches!(empty, Err(DiagnosticsError::InvalidFrameLog(_))));
+    }
+}
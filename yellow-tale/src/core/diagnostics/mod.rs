@@ -7,17 +7,52 @@
 //! - Disk IO
 //! - Frame-time variance (if observable externally)
 //! - Exportable logs
+//! - How recent game runs ended, to line up crashes with the metrics
+//! - Bottleneck findings from the sample history (see [`analysis`])
+//! - min/avg/max/p95 over the rolling sample window (see [`summary`])
+//! - Frame pacing from an external frametime log (see [`frame_pacing`])
 //! 
 //! All metrics are exposed via IPC.
 
+pub mod analysis;
+pub mod frame_pacing;
+pub mod summary;
+
 use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use sysinfo::{System, Disks, Pid};
 use chrono::{DateTime, Utc};
 use tracing::info;
 
+use analysis::{AnalysisThresholds, Finding, LaunchContext};
+use frame_pacing::FramePacingReport;
+use summary::MetricsSummary;
+use crate::core::launcher::{safe_mode::{CrashRates, GameExitReport}, ProcessState};
+use crate::core::performance::{DriveSpace, ProcessLoad, SystemSnapshot};
+
+/// Game exits kept for reports
+const MAX_GAME_EXITS: usize = 20;
+
+/// Other processes above either limit are listed in system snapshots
+const HEAVY_PROCESS_CPU_PERCENT: f32 = 10.0;
+const HEAVY_PROCESS_MEMORY_MB: u64 = 2048;
+const MAX_HEAVY_PROCESSES: usize = 10;
+
+/// How often the background sampler takes a sample by default
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How far back the sample history reaches by default
+pub const DEFAULT_HISTORY_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Most samples kept, whatever the window and interval. A sample with its
+/// per-core readings is well under a kilobyte, so this bounds the history
+/// to a few megabytes.
+pub const MAX_HISTORY_SAMPLES: usize = 3600;
+
 #[derive(Error, Debug)]
 pub enum DiagnosticsError {
     #[error("Process not found: {0}")]
@@ -26,6 +61,9 @@ pub enum DiagnosticsError {
     #[error("Failed to export: {0}")]
     ExportFailed(String),
     
+    #[error("Invalid frame log: {0}")]
+    InvalidFrameLog(String),
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -53,6 +91,38 @@ pub struct MetricsSample {
     
     /// Disk write bytes since last sample
     pub disk_write_bytes: u64,
+    
+    /// Used swap in MB
+    #[serde(default)]
+    pub swap_used_mb: u64,
+    
+    /// GPU usage (0.0 - 100.0), when observable
+    #[serde(default)]
+    pub gpu_usage: Option<f32>,
+    
+    /// Average frame time in ms since last sample, when observable externally
+    #[serde(default)]
+    pub frame_time_ms: Option<f32>,
+    
+    /// Whether the game was streaming assets during this sample
+    #[serde(default)]
+    pub asset_streaming: bool,
+    
+    /// CPU usage by the game process, while it runs
+    #[serde(default)]
+    pub game_cpu_usage: Option<f32>,
+    
+    /// Memory used by the game process in MB, while it runs
+    #[serde(default)]
+    pub game_memory_mb: Option<u64>,
+}
+
+/// Readings the launcher can't take itself, reported by whatever observes
+/// the game and attached to the next sample
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct FrameStats {
+    pub frame_time_ms: f32,
+    pub gpu_usage: Option<f32>,
 }
 
 /// Process-specific metrics
@@ -89,11 +159,35 @@ pub struct DiagnosticsReport {
     /// Recent metrics samples
     pub metrics_history: Vec<MetricsSample>,
     
+    /// Statistics over `metrics_history`, when there are samples
+    #[serde(default)]
+    pub summary: Option<MetricsSummary>,
+    
     /// Game process metrics (if running)
     pub game_metrics: Option<ProcessMetrics>,
     
     /// Recent log entries
     pub recent_logs: Vec<LogEntry>,
+    
+    /// Bottlenecks found in the metrics history, most confident first
+    #[serde(default)]
+    pub findings: Vec<Finding>,
+    
+    /// How recent runs ended, oldest first
+    #[serde(default)]
+    pub game_exits: Vec<GameExitReport>,
+    
+    /// Crashes in `game_exits` with mods against without
+    #[serde(default)]
+    pub crash_rates: CrashRates,
+    
+    /// The last frame log analysed
+    #[serde(default)]
+    pub frame_pacing: Option<FramePacingReport>,
+    
+    /// The launcher's config when the report was exported
+    #[serde(default)]
+    pub config: Option<serde_json::Value>,
 }
 
 /// System information
@@ -161,6 +255,9 @@ pub struct DiagnosticsCollector {
     /// Maximum history length
     max_history: usize,
     
+    /// How often the background sampler takes a sample
+    sample_interval: Duration,
+    
     /// Recent log entries
     recent_logs: VecDeque<LogEntry>,
     
@@ -169,22 +266,66 @@ pub struct DiagnosticsCollector {
     
     /// PID of game process (if tracking)
     tracked_pid: Option<u32>,
+    
+    /// Frame readings waiting for the next sample
+    pending_frame: Option<FrameStats>,
+    
+    /// Whether the game is currently streaming assets
+    asset_streaming: bool,
+    
+    /// Launch settings the analysis judges the history against
+    launch_context: LaunchContext,
+    
+    thresholds: AnalysisThresholds,
+    
+    /// Recent game exits
+    game_exits: VecDeque<GameExitReport>,
+    
+    /// The last frame log analysed
+    frame_pacing: Option<FramePacingReport>,
 }
 
 impl DiagnosticsCollector {
     /// Create a new diagnostics collector
     pub fn new() -> Self {
+        let system = System::new_all();
+        let launch_context = LaunchContext {
+            cpu_cores: system.cpus().len(),
+            ..Default::default()
+        };
         Self {
-            system: System::new_all(),
+            system,
             disks: Disks::new_with_refreshed_list(),
             metrics_history: VecDeque::new(),
-            max_history: 3600, // Keep 1 hour at 1 sample/second
+            max_history: history_len(DEFAULT_SAMPLE_INTERVAL, DEFAULT_HISTORY_WINDOW),
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
             recent_logs: VecDeque::new(),
             max_logs: 1000,
             tracked_pid: None,
+            pending_frame: None,
+            asset_streaming: false,
+            launch_context,
+            thresholds: AnalysisThresholds::default(),
+            game_exits: VecDeque::new(),
+            frame_pacing: None,
         }
     }
     
+    /// Keep `window` worth of samples taken every `sample_interval`, up to
+    /// `MAX_HISTORY_SAMPLES`
+    pub fn with_history(mut self, sample_interval: Duration, window: Duration) -> Self {
+        self.sample_interval = sample_interval.max(Duration::from_secs(1));
+        self.max_history = history_len(self.sample_interval, window);
+        while self.metrics_history.len() > self.max_history {
+            self.metrics_history.pop_front();
+        }
+        self
+    }
+    
+    pub fn sample_interval(&self) -> Duration {
+        self.sample_interval
+    }
+    
     /// Set the game process to track
     pub fn track_process(&mut self, pid: u32) {
         self.tracked_pid = Some(pid);
@@ -196,6 +337,43 @@ impl DiagnosticsCollector {
         self.tracked_pid = None;
     }
     
+    /// Record how a game run ended, alongside the metrics taken during it
+    pub fn record_game_exit(&mut self, report: GameExitReport) {
+        let (level, outcome) = match &report.state {
+            ProcessState::Crashed { reason } => ("error", format!("crashed ({})", reason)),
+            ProcessState::Terminated { .. } => ("info", "was stopped by the user".to_string()),
+            _ => ("info", "exited".to_string()),
+        };
+        self.log(level, format!("Game {} after {}s", outcome, report.uptime_secs), Some("launcher".to_string()));
+        self.untrack_process();
+        
+        self.game_exits.push_back(report);
+        while self.game_exits.len() > MAX_GAME_EXITS {
+            self.game_exits.pop_front();
+        }
+    }
+    
+    /// Keep a frame log analysis for the next reports
+    pub fn record_frame_pacing(&mut self, report: FramePacingReport) {
+        self.frame_pacing = Some(report);
+    }
+    
+    /// Record the core affinity and heap the game was launched with
+    pub fn set_launch_settings(&mut self, cpu_affinity: Vec<usize>, max_heap_mb: Option<u64>) {
+        self.launch_context.cpu_affinity = cpu_affinity;
+        self.launch_context.max_heap_mb = max_heap_mb;
+    }
+    
+    /// Attach frame readings to the next sample
+    pub fn report_frame_stats(&mut self, stats: FrameStats) {
+        self.pending_frame = Some(stats);
+    }
+    
+    /// Mark samples as taken while the game streams assets
+    pub fn set_asset_streaming(&mut self, streaming: bool) {
+        self.asset_streaming = streaming;
+    }
+    
     /// Collect a metrics sample
     pub fn collect_sample(&mut self) -> MetricsSample {
         self.system.refresh_all();
@@ -211,14 +389,25 @@ impl DiagnosticsCollector {
             cpu_per_core.iter().sum::<f32>() / cpu_per_core.len() as f32
         };
         
+        // Per-process IO is already a delta since the previous refresh
+        let game = self.tracked_pid.and_then(|pid| self.system.process(Pid::from_u32(pid)));
+        let disk_usage = game.map(|process| process.disk_usage());
+        let frame = self.pending_frame.take();
+        
         let sample = MetricsSample {
             timestamp: Utc::now(),
             cpu_usage,
             cpu_per_core,
             ram_used_mb: self.system.used_memory() / 1024 / 1024,
             ram_total_mb: self.system.total_memory() / 1024 / 1024,
-            disk_read_bytes: 0, // Would need to track delta
-            disk_write_bytes: 0,
+            disk_read_bytes: disk_usage.map_or(0, |d| d.read_bytes),
+            disk_write_bytes: disk_usage.map_or(0, |d| d.written_bytes),
+            swap_used_mb: self.system.used_swap() / 1024 / 1024,
+            gpu_usage: frame.and_then(|f| f.gpu_usage),
+            frame_time_ms: frame.map(|f| f.frame_time_ms),
+            asset_streaming: self.asset_streaming,
+            game_cpu_usage: game.map(|process| process.cpu_usage()),
+            game_memory_mb: game.map(|process| process.memory() / 1024 / 1024),
         };
         
         // Store in history
@@ -275,6 +464,53 @@ impl DiagnosticsCollector {
         }
     }
     
+    /// The machine right now: load per core, free RAM, space on the drive
+    /// holding `game_path`, and the heaviest other processes. Only reads.
+    pub fn system_snapshot(&mut self, game_path: Option<&Path>) -> SystemSnapshot {
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        self.system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+        self.disks.refresh();
+        
+        // The drive whose mount point is the longest prefix of the game's path
+        let game_drive = game_path.and_then(|path| {
+            self.disks.iter()
+                .filter(|disk| path.starts_with(disk.mount_point()))
+                .max_by_key(|disk| disk.mount_point().as_os_str().len())
+                .map(|disk| DriveSpace {
+                    mount_point: disk.mount_point().to_string_lossy().to_string(),
+                    total_mb: disk.total_space() / 1024 / 1024,
+                    available_mb: disk.available_space() / 1024 / 1024,
+                })
+        });
+        
+        let own_pid = std::process::id();
+        let mut heavy_processes: Vec<ProcessLoad> = self.system.processes().iter()
+            .filter(|(pid, process)| {
+                process.thread_kind().is_none() && pid.as_u32() != own_pid && Some(pid.as_u32()) != self.tracked_pid
+            })
+            .map(|(pid, process)| ProcessLoad {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+                cpu_usage: process.cpu_usage(),
+                memory_mb: process.memory() / 1024 / 1024,
+            })
+            .filter(|load| load.cpu_usage >= HEAVY_PROCESS_CPU_PERCENT || load.memory_mb >= HEAVY_PROCESS_MEMORY_MB)
+            .collect();
+        heavy_processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage).then(b.memory_mb.cmp(&a.memory_mb)));
+        heavy_processes.truncate(MAX_HEAVY_PROCESSES);
+        
+        SystemSnapshot {
+            total_ram_mb: self.system.total_memory() / 1024 / 1024,
+            available_ram_mb: self.system.available_memory() / 1024 / 1024,
+            cpu_cores: self.system.cpus().len(),
+            cpu_usage: self.system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            process_count: self.system.processes().len(),
+            game_drive,
+            heavy_processes,
+        }
+    }
+    
     /// Add a log entry
     pub fn log(&mut self, level: &str, message: String, source: Option<String>) {
         let entry = LogEntry {
@@ -300,31 +536,63 @@ impl DiagnosticsCollector {
             .collect()
     }
     
+    /// Look for bottlenecks in the sample history
+    pub fn analyze(&mut self) -> Vec<Finding> {
+        analysis::analyze(self.metrics_history.make_contiguous(), &self.launch_context, &self.thresholds)
+    }
+    
     /// Generate a full diagnostics report
     pub fn generate_report(&mut self) -> DiagnosticsReport {
+        let findings = self.analyze();
         DiagnosticsReport {
             generated_at: Utc::now(),
             launcher_version: crate::VERSION.to_string(),
             system_info: self.get_system_info(),
             metrics_history: self.metrics_history.iter().cloned().collect(),
+            summary: summary::summarize(self.metrics_history.iter()),
             game_metrics: self.get_process_metrics(),
             recent_logs: self.recent_logs.iter().cloned().collect(),
+            findings,
+            game_exits: self.game_exits.iter().cloned().collect(),
+            crash_rates: CrashRates::from_exits(&self.game_exits),
+            frame_pacing: self.frame_pacing.clone(),
+            config: None,
         }
     }
     
-    /// Export diagnostics report to a file
-    pub async fn export_report(&mut self, path: PathBuf) -> Result<(), DiagnosticsError> {
-        let report = self.generate_report();
-        let content = serde_json::to_string_pretty(&report)
+    /// Export a diagnostics report, with the given config snapshot, to a
+    /// JSON file, gzipped when `compress` is set. Returns the bytes written.
+    pub async fn export_report(
+        &mut self,
+        path: &Path,
+        config: Option<serde_json::Value>,
+        compress: bool,
+    ) -> Result<u64, DiagnosticsError> {
+        let report = DiagnosticsReport { config, ..self.generate_report() };
+        let mut content = serde_json::to_vec_pretty(&report)
             .map_err(|e| DiagnosticsError::ExportFailed(e.to_string()))?;
+        if compress {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&content)?;
+            content = encoder.finish()?;
+        }
         
-        tokio::fs::write(&path, content).await?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, &content).await?;
         info!("Exported diagnostics report to {:?}", path);
         
-        Ok(())
+        Ok(content.len() as u64)
     }
 }
 
+/// Samples needed to cover `window`, within `1..=MAX_HISTORY_SAMPLES`
+fn history_len(sample_interval: Duration, window: Duration) -> usize {
+    let samples = window.as_secs() / sample_interval.as_secs().max(1);
+    (samples as usize).clamp(1, MAX_HISTORY_SAMPLES)
+}
+
 impl Default for DiagnosticsCollector {
     fn default() -> Self {
         Self::new()
@@ -341,4 +609,33 @@ mod tests {
         let sample = collector.collect_sample();
         assert!(sample.ram_total_mb > 0);
     }
+    
+    #[tokio::test]
+    async fn test_history_is_bounded_and_exported() {
+        // Ten minutes at five seconds is 120 samples; a day at one second is capped
+        assert_eq!(history_len(Duration::from_secs(5), Duration::from_secs(600)), 120);
+        assert_eq!(history_len(Duration::from_secs(1), Duration::from_secs(86_400)), MAX_HISTORY_SAMPLES);
+        assert_eq!(history_len(Duration::from_secs(60), Duration::from_secs(1)), 1);
+        
+        let mut collector = DiagnosticsCollector::new().with_history(Duration::from_secs(1), Duration::from_secs(2));
+        for _ in 0..3 {
+            collector.collect_sample();
+        }
+        collector.log("warn", "Cache is nearly full".to_string(), None);
+        
+        let dir = std::env::temp_dir().join(format!("yt-diagnostics-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("report.json.gz");
+        let config = serde_json::json!({ "launcher": { "shutdown_timeout_secs": 10 } });
+        let written = collector.export_report(&path, Some(config.clone()), true).await.unwrap();
+        
+        let compressed = tokio::fs::read(&path).await.unwrap();
+        assert_eq!(written, compressed.len() as u64);
+        let report: DiagnosticsReport = serde_json::from_reader(flate2::read::GzDecoder::new(&compressed[..])).unwrap();
+        assert_eq!(report.metrics_history.len(), 2);
+        assert_eq!(report.summary.unwrap().samples, 2);
+        assert_eq!(report.config, Some(config));
+        assert_eq!(report.recent_logs[0].message, "Cache is nearly full");
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
 }
@@ -0,0 +1,112 @@
+//! Statistics over the sample window
+//!
+//! Reduces the rolling sample history to min/avg/max/p95 per metric, so a
+//! report shows how the machine behaved over the last minutes rather than
+//! just at the moment it was generated. Percentiles use the nearest-rank
+//! method: p95 is a value that was actually sampled, never an interpolation.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::MetricsSample;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MetricStats {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub p95: f64,
+}
+
+impl MetricStats {
+    /// None when there are no values
+    pub fn from_values(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let mut values: Vec<f64> = values.into_iter().filter(|v| v.is_finite()).collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        Some(Self {
+            min: values[0],
+            avg: values.iter().sum::<f64>() / values.len() as f64,
+            max: values[values.len() - 1],
+            p95: percentile(&values, 95.0)?,
+        })
+    }
+}
+
+/// Nearest-rank percentile of already sorted values, `p` in 0..=100
+pub fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Every metric over the window the history covers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSummary {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub samples: usize,
+    pub cpu_usage: MetricStats,
+    pub ram_used_mb: MetricStats,
+    pub disk_read_bytes: MetricStats,
+    pub disk_write_bytes: MetricStats,
+    /// Over the samples taken while the game ran
+    pub game_cpu_usage: Option<MetricStats>,
+    pub game_memory_mb: Option<MetricStats>,
+}
+
+/// None for an empty history
+pub fn summarize<'a>(samples: impl Iterator<Item = &'a MetricsSample> + Clone) -> Option<MetricsSummary> {
+    let mut timestamps = samples.clone().map(|s| s.timestamp);
+    let window_start = timestamps.next()?;
+    Some(MetricsSummary {
+        window_start,
+        window_end: timestamps.last().unwrap_or(window_start),
+        samples: samples.clone().count(),
+        cpu_usage: MetricStats::from_values(samples.clone().map(|s| s.cpu_usage as f64))?,
+        ram_used_mb: MetricStats::from_values(samples.clone().map(|s| s.ram_used_mb as f64))?,
+        disk_read_bytes: MetricStats::from_values(samples.clone().map(|s| s.disk_read_bytes as f64))?,
+        disk_write_bytes: MetricStats::from_values(samples.clone().map(|s| s.disk_write_bytes as f64))?,
+        game_cpu_usage: MetricStats::from_values(samples.clone().filter_map(|s| s.game_cpu_usage).map(f64::from)),
+        game_memory_mb: MetricStats::from_values(samples.filter_map(|s| s.game_memory_mb).map(|mb| mb as f64)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_rank_percentile() {
+        let hundred: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&hundred, 95.0), Some(95.0));
+        assert_eq!(percentile(&hundred, 50.0), Some(50.0));
+        assert_eq!(percentile(&hundred, 100.0), Some(100.0));
+        assert_eq!(percentile(&hundred, 0.0), Some(1.0));
+
+        // 95% of 20 is exactly rank 19; 95% of 10 rounds up to rank 10
+        let twenty: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert_eq!(percentile(&twenty, 95.0), Some(19.0));
+        assert_eq!(percentile(&twenty[..10], 95.0), Some(10.0));
+
+        assert_eq!(percentile(&[7.0], 95.0), Some(7.0));
+        assert_eq!(percentile(&[], 95.0), None);
+    }
+
+    #[test]
+    fn test_stats_ignore_order_and_non_finite_values() {
+        let stats = MetricStats::from_values([40.0, 10.0, f64::NAN, 30.0, 20.0]).unwrap();
+        assert_eq!(stats, MetricStats { min: 10.0, avg: 25.0, max: 40.0, p95: 40.0 });
+        assert_eq!(MetricStats::from_values([f64::INFINITY]), None);
+
+        // One spike among twenty samples lands above p95
+        let mut values = vec![10.0; 19];
+        values.push(100.0);
+        let stats = MetricStats::from_values(values).unwrap();
+        assert_eq!((stats.p95, stats.max, stats.avg), (10.0, 100.0, 14.5));
+    }
+}
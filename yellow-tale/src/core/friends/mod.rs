@@ -1,10 +1,18 @@
-use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use thiserror::Error;
 use tracing::info;
 use uuid::Uuid;
 
+use crate::core::db::supervisor::QueryError;
+use crate::core::presence::{PresenceFeed, PresenceHub};
+
+/// Longest message a friend request may carry, in characters
+pub const MAX_REQUEST_MESSAGE: usize = 200;
+
 #[derive(Error, Debug)]
 pub enum FriendsError {
     #[error("Friend request already exists")]
@@ -31,10 +39,25 @@ pub enum FriendsError {
     #[error("Already blocked")]
     AlreadyBlocked,
     
+    #[error("Verify your email address before sending friend requests")]
+    EmailNotVerified,
+    
+    #[error("Friend request message must be at most {MAX_REQUEST_MESSAGE} characters")]
+    MessageTooLong,
+    
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 }
 
+impl QueryError for FriendsError {
+    fn sqlx_error(&self) -> Option<&sqlx::Error> {
+        match self {
+            FriendsError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FriendshipStatus {
@@ -80,7 +103,50 @@ pub struct FriendRequest {
     pub from_username: String,
     pub from_display_name: String,
     pub from_avatar_url: Option<String>,
+    /// Note from the sender, shown with the request
+    #[serde(default)]
+    pub message: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Requests from before expiry was added have none and stay until answered
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl FriendRequest {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// id, user id, username, display name, avatar, message, created, expires
+type RequestRow = (Uuid, Uuid, String, String, Option<String>, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>);
+
+/// Requests from `rows` that haven't expired by `now`
+fn live_requests(rows: Vec<RequestRow>, now: DateTime<Utc>) -> Vec<FriendRequest> {
+    rows.into_iter()
+        .map(|r| FriendRequest {
+            id: r.0,
+            from_user_id: r.1,
+            from_username: r.2,
+            from_display_name: r.3,
+            from_avatar_url: r.4,
+            message: r.5,
+            created_at: r.6,
+            expires_at: r.7,
+        })
+        .filter(|request| !request.is_expired(now))
+        .collect()
+}
+
+/// Trims the message and checks its length; blank means no message
+pub fn request_message(message: Option<&str>) -> Result<Option<String>, FriendsError> {
+    let Some(message) = message.map(str::trim).filter(|m| !m.is_empty()) else {
+        return Ok(None);
+    };
+    if message.chars().count() > MAX_REQUEST_MESSAGE {
+        return Err(FriendsError::MessageTooLong);
+    }
+    Ok(Some(message.to_string()))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,17 +159,43 @@ pub struct BlockedUser {
 
 pub struct FriendsService {
     pool: PgPool,
+    require_verified_email: bool,
+    /// How long a request waits for an answer
+    request_ttl: Duration,
 }
 
 impl FriendsService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self { pool, require_verified_email: false, request_ttl: Duration::days(30) }
     }
     
-    pub async fn send_friend_request(&self, from_user: Uuid, to_user: Uuid) -> Result<Uuid, FriendsError> {
+    pub fn with_request_ttl(mut self, ttl: Duration) -> Self {
+        self.request_ttl = ttl;
+        self
+    }
+    
+    /// Only accounts with a confirmed email address may send friend requests
+    pub fn with_verified_email_required(mut self, required: bool) -> Self {
+        self.require_verified_email = required;
+        self
+    }
+    
+    pub async fn send_friend_request(&self, from_user: Uuid, to_user: Uuid, message: Option<&str>) -> Result<Uuid, FriendsError> {
         if from_user == to_user {
             return Err(FriendsError::SelfFriend);
         }
+        let message = request_message(message)?;
+        
+        if self.require_verified_email {
+            let verified: bool = sqlx::query_scalar("SELECT email_verified FROM users WHERE id = $1")
+                .bind(from_user)
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or(FriendsError::UserNotFound)?;
+            if !verified {
+                return Err(FriendsError::EmailNotVerified);
+            }
+        }
         
         let blocked = sqlx::query_scalar::<_, i64>(
             "SELECT COUNT(*) FROM blocks WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1)"
@@ -118,7 +210,7 @@ impl FriendsService {
         }
         
         let existing = sqlx::query_as::<_, (String,)>(
-            "SELECT status FROM friendships WHERE user_id = $1 AND friend_id = $2"
+            "SELECT status FROM friendships WHERE user_id = $1 AND friend_id = $2 AND (expires_at IS NULL OR expires_at > NOW())"
         )
         .bind(from_user)
         .bind(to_user)
@@ -134,7 +226,7 @@ impl FriendsService {
         }
         
         let reverse = sqlx::query_as::<_, (Uuid, String)>(
-            "SELECT id, status FROM friendships WHERE user_id = $1 AND friend_id = $2"
+            "SELECT id, status FROM friendships WHERE user_id = $1 AND friend_id = $2 AND (expires_at IS NULL OR expires_at > NOW())"
         )
         .bind(to_user)
         .bind(from_user)
@@ -143,7 +235,7 @@ impl FriendsService {
         
         if let Some((id, status)) = reverse {
             if status == "pending" {
-                sqlx::query("UPDATE friendships SET status = 'accepted', updated_at = NOW() WHERE id = $1")
+                sqlx::query("UPDATE friendships SET status = 'accepted', expires_at = NULL, updated_at = NOW() WHERE id = $1")
                     .bind(id)
                     .execute(&self.pool)
                     .await?;
@@ -161,11 +253,20 @@ impl FriendsService {
             }
         }
         
+        // Replaces an expired or declined request between the two
         let request_id: Uuid = sqlx::query_scalar(
-            "INSERT INTO friendships (user_id, friend_id, status) VALUES ($1, $2, 'pending') RETURNING id"
+            r#"
+            INSERT INTO friendships (user_id, friend_id, status, message, expires_at) VALUES ($1, $2, 'pending', $3, $4)
+            ON CONFLICT (user_id, friend_id) DO UPDATE
+                SET status = 'pending', message = EXCLUDED.message, expires_at = EXCLUDED.expires_at,
+                    created_at = NOW(), updated_at = NOW()
+            RETURNING id
+            "#
         )
         .bind(from_user)
         .bind(to_user)
+        .bind(&message)
+        .bind(Utc::now() + self.request_ttl)
         .fetch_one(&self.pool)
         .await?;
         
@@ -175,7 +276,7 @@ impl FriendsService {
     
     pub async fn accept_friend_request(&self, user_id: Uuid, from_user: Uuid) -> Result<(), FriendsError> {
         let friendship = sqlx::query_as::<_, (Uuid, String)>(
-            "SELECT id, status FROM friendships WHERE user_id = $1 AND friend_id = $2"
+            "SELECT id, status FROM friendships WHERE user_id = $1 AND friend_id = $2 AND (expires_at IS NULL OR expires_at > NOW())"
         )
         .bind(from_user)
         .bind(user_id)
@@ -187,7 +288,7 @@ impl FriendsService {
             return Err(FriendsError::RequestNotFound);
         }
         
-        sqlx::query("UPDATE friendships SET status = 'accepted', updated_at = NOW() WHERE id = $1")
+        sqlx::query("UPDATE friendships SET status = 'accepted', expires_at = NULL, updated_at = NOW() WHERE id = $1")
             .bind(friendship.0)
             .execute(&self.pool)
             .await?;
@@ -206,7 +307,8 @@ impl FriendsService {
     
     pub async fn decline_friend_request(&self, user_id: Uuid, from_user: Uuid) -> Result<(), FriendsError> {
         let result = sqlx::query(
-            "UPDATE friendships SET status = 'declined', updated_at = NOW() WHERE user_id = $1 AND friend_id = $2 AND status = 'pending'"
+            "UPDATE friendships SET status = 'declined', updated_at = NOW()
+             WHERE user_id = $1 AND friend_id = $2 AND status = 'pending' AND (expires_at IS NULL OR expires_at > NOW())"
         )
         .bind(from_user)
         .bind(user_id)
@@ -264,9 +366,9 @@ impl FriendsService {
     }
     
     pub async fn get_pending_requests(&self, user_id: Uuid) -> Result<Vec<FriendRequest>, FriendsError> {
-        let rows = sqlx::query_as::<_, (Uuid, Uuid, String, String, Option<String>, DateTime<Utc>)>(
+        let rows = sqlx::query_as::<_, RequestRow>(
             r#"
-            SELECT f.id, u.id, u.username, u.display_name, u.avatar_url, f.created_at
+            SELECT f.id, u.id, u.username, u.display_name, u.avatar_url, f.message, f.created_at, f.expires_at
             FROM friendships f
             JOIN users u ON u.id = f.user_id
             WHERE f.friend_id = $1 AND f.status = 'pending'
@@ -277,20 +379,13 @@ impl FriendsService {
         .fetch_all(&self.pool)
         .await?;
         
-        Ok(rows.into_iter().map(|r| FriendRequest {
-            id: r.0,
-            from_user_id: r.1,
-            from_username: r.2,
-            from_display_name: r.3,
-            from_avatar_url: r.4,
-            created_at: r.5,
-        }).collect())
+        Ok(live_requests(rows, Utc::now()))
     }
     
     pub async fn get_outgoing_requests(&self, user_id: Uuid) -> Result<Vec<FriendRequest>, FriendsError> {
-        let rows = sqlx::query_as::<_, (Uuid, Uuid, String, String, Option<String>, DateTime<Utc>)>(
+        let rows = sqlx::query_as::<_, RequestRow>(
             r#"
-            SELECT f.id, u.id, u.username, u.display_name, u.avatar_url, f.created_at
+            SELECT f.id, u.id, u.username, u.display_name, u.avatar_url, f.message, f.created_at, f.expires_at
             FROM friendships f
             JOIN users u ON u.id = f.friend_id
             WHERE f.user_id = $1 AND f.status = 'pending'
@@ -301,14 +396,32 @@ impl FriendsService {
         .fetch_all(&self.pool)
         .await?;
         
-        Ok(rows.into_iter().map(|r| FriendRequest {
-            id: r.0,
-            from_user_id: r.1,
-            from_username: r.2,
-            from_display_name: r.3,
-            from_avatar_url: r.4,
-            created_at: r.5,
-        }).collect())
+        Ok(live_requests(rows, Utc::now()))
+    }
+    
+    /// Start keeping status changes of the user's current friends from `hub`.
+    /// Friends added later are picked up by subscribing again.
+    pub async fn subscribe_presence(&self, user_id: Uuid, hub: &PresenceHub) -> Result<PresenceFeed, FriendsError> {
+        let friends: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT friend_id FROM friendships WHERE user_id = $1 AND status = 'accepted'"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(PresenceFeed::spawn(hub, friends.into_iter().collect()))
+    }
+    
+    /// Deletes requests nobody answered in time; returns how many
+    pub async fn purge_expired_requests(&self) -> Result<u64, FriendsError> {
+        let result = sqlx::query("DELETE FROM friendships WHERE status = 'pending' AND expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+        
+        if result.rows_affected() > 0 {
+            info!("Purged {} expired friend requests", result.rows_affected());
+        }
+        Ok(result.rows_affected())
     }
     
     pub async fn block_user(&self, blocker: Uuid, blocked: Uuid, reason: Option<&str>) -> Result<(), FriendsError> {
@@ -392,6 +505,18 @@ impl FriendsService {
         Ok(count > 0)
     }
     
+    /// Everyone with a block in either direction between them and `user_id`
+    pub async fn block_set(&self, user_id: Uuid) -> Result<HashSet<Uuid>, FriendsError> {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            "SELECT CASE WHEN blocker_id = $1 THEN blocked_id ELSE blocker_id END FROM blocks WHERE blocker_id = $1 OR blocked_id = $1"
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(ids.into_iter().collect())
+    }
+    
     pub async fn are_friends(&self, user1: Uuid, user2: Uuid) -> Result<bool, FriendsError> {
         let count = sqlx::query_scalar::<_, i64>(
             "SELECT COUNT(*) FROM friendships WHERE user_id = $1 AND friend_id = $2 AND status = 'accepted'"
@@ -439,4 +564,40 @@ mod tests {
         assert_eq!(FriendshipStatus::Pending.to_string(), "pending");
         assert_eq!(FriendshipStatus::Accepted.to_string(), "accepted");
     }
+    
+    fn row(message: Option<&str>, expires_at: Option<DateTime<Utc>>) -> RequestRow {
+        let created_at = Utc::now() - Duration::days(1);
+        (Uuid::new_v4(), Uuid::new_v4(), "alex".into(), "Alex".into(), None, message.map(String::from), created_at, expires_at)
+    }
+    
+    #[test]
+    fn test_request_message_round_trips() {
+        assert_eq!(request_message(Some("  hey it's Alex from the Orbis server ")).unwrap().as_deref(), Some("hey it's Alex from the Orbis server"));
+        assert_eq!(request_message(Some("   ")).unwrap(), None);
+        assert_eq!(request_message(None).unwrap(), None);
+        assert!(request_message(Some(&"é".repeat(MAX_REQUEST_MESSAGE))).is_ok());
+        assert!(matches!(request_message(Some(&"a".repeat(MAX_REQUEST_MESSAGE + 1))), Err(FriendsError::MessageTooLong)));
+        
+        let now = Utc::now();
+        let request = live_requests(vec![row(Some("hey"), Some(now + Duration::days(29)))], now).remove(0);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["message"], "hey");
+        let parsed: FriendRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.message.as_deref(), Some("hey"));
+        assert_eq!(parsed.expires_at, request.expires_at);
+    }
+    
+    #[test]
+    fn test_expired_requests_are_hidden() {
+        let now = Utc::now();
+        let rows = vec![
+            row(Some("fresh"), Some(now + Duration::hours(1))),
+            row(Some("stale"), Some(now - Duration::hours(1))),
+            row(Some("due"), Some(now)),
+            // From before requests expired
+            row(Some("legacy"), None),
+        ];
+        let visible: Vec<_> = live_requests(rows, now).into_iter().filter_map(|r| r.message).collect();
+        assert_eq!(visible, ["fresh", "legacy"]);
+    }
 }
@@ -0,0 +1,203 @@
+//! Component health
+//!
+//! Cheap checks of the subsystems behind `get_status`:
+//! - Each check runs under a short timeout, so a stuck component reports
+//!   `degraded` instead of holding up the rest
+//! - The overall status is the worst component's
+//! - Status changes between checks are reported as transitions
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How long one component's check may take
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ordered from best to worst
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    #[default]
+    Ok,
+    Degraded,
+    Down,
+}
+
+/// What a single check found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub status: HealthStatus,
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    pub fn ok() -> Self {
+        Self { status: HealthStatus::Ok, detail: None }
+    }
+
+    pub fn degraded(detail: impl Into<String>) -> Self {
+        Self { status: HealthStatus::Degraded, detail: Some(detail.into()) }
+    }
+
+    pub fn down(detail: impl Into<String>) -> Self {
+        Self { status: HealthStatus::Down, detail: Some(detail.into()) }
+    }
+
+    /// Healthy, with a note for the UI
+    pub fn ok_with(detail: impl Into<String>) -> Self {
+        Self { status: HealthStatus::Ok, detail: Some(detail.into()) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    pub detail: Option<String>,
+    pub last_checked: DateTime<Utc>,
+}
+
+/// A component whose status changed since the previous check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthTransition {
+    pub name: String,
+    pub from: HealthStatus,
+    pub to: HealthStatus,
+    pub detail: Option<String>,
+}
+
+/// A subsystem that can report on itself. Checks should be cheap: no more
+/// than touching a file, a socket or a pooled connection.
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn health_check(&self) -> CheckResult;
+}
+
+pub type CheckFuture<'a> = Pin<Box<dyn Future<Output = CheckResult> + Send + 'a>>;
+
+/// Run every check at once, each bounded by `timeout`
+pub async fn check_all(checks: Vec<(String, CheckFuture<'_>)>, timeout: Duration) -> Vec<ComponentHealth> {
+    let checks = checks.into_iter().map(|(name, check)| async move {
+        let result = tokio::time::timeout(timeout, check).await
+            .unwrap_or_else(|_| CheckResult::degraded("timeout"));
+        ComponentHealth { name, status: result.status, detail: result.detail, last_checked: Utc::now() }
+    });
+    futures_util::future::join_all(checks).await
+}
+
+/// The worst status among `components`; `ok` when there are none
+pub fn overall(components: &[ComponentHealth]) -> HealthStatus {
+    components.iter().map(|c| c.status).max().unwrap_or_default()
+}
+
+/// Remembers each component's last status to spot transitions
+#[derive(Debug, Default)]
+pub struct HealthTracker {
+    last: Mutex<HashMap<String, HealthStatus>>,
+}
+
+impl HealthTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a round of checks, returning the components whose status
+    /// differs from the last round. A component's first check is not a
+    /// transition.
+    pub fn record(&self, components: &[ComponentHealth]) -> Vec<HealthTransition> {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        components.iter()
+            .filter_map(|component| {
+                let previous = last.insert(component.name.clone(), component.status)?;
+                (previous != component.status).then(|| HealthTransition {
+                    name: component.name.clone(),
+                    from: previous,
+                    to: component.status,
+                    detail: component.detail.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A directory the launcher needs to write to, such as the cache
+pub struct WritableDir {
+    name: String,
+    dir: PathBuf,
+}
+
+impl WritableDir {
+    pub fn new(name: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for WritableDir {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn health_check(&self) -> CheckResult {
+        let probe = self.dir.join(".health-check");
+        let written = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(&probe, b"ok").await?;
+            tokio::fs::remove_file(&probe).await
+        };
+        match written.await {
+            Ok(()) => CheckResult::ok(),
+            Err(e) => CheckResult::down(format!("{} is not writable: {}", self.dir.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(name: &str, status: HealthStatus) -> ComponentHealth {
+        ComponentHealth { name: name.to_string(), status, detail: None, last_checked: Utc::now() }
+    }
+
+    #[tokio::test]
+    async fn test_stuck_check_times_out_as_degraded() {
+        let checks: Vec<(String, CheckFuture<'_>)> = vec![
+            ("fast".to_string(), Box::pin(async { CheckResult::ok() })),
+            ("stuck".to_string(), Box::pin(std::future::pending())),
+        ];
+        let started = std::time::Instant::now();
+        let components = check_all(checks, Duration::from_millis(50)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(components[0].status, HealthStatus::Ok);
+        assert_eq!(components[1].status, HealthStatus::Degraded);
+        assert_eq!(components[1].detail.as_deref(), Some("timeout"));
+        assert_eq!(overall(&components), HealthStatus::Degraded);
+        assert_eq!(overall(&[]), HealthStatus::Ok);
+    }
+
+    #[test]
+    fn test_tracker_reports_changes_only() {
+        let tracker = HealthTracker::new();
+        assert!(tracker.record(&[component("cache", HealthStatus::Ok)]).is_empty());
+        assert!(tracker.record(&[component("cache", HealthStatus::Ok)]).is_empty());
+
+        let transitions = tracker.record(&[component("cache", HealthStatus::Down), component("relay", HealthStatus::Ok)]);
+        assert_eq!(transitions, vec![HealthTransition {
+            name: "cache".to_string(),
+            from: HealthStatus::Ok,
+            to: HealthStatus::Down,
+            detail: None,
+        }]);
+    }
+}
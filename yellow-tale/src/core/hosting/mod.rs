@@ -0,0 +1,745 @@
+//! Local world hosting
+//!
+//! Runs a dedicated server for a world the player hosts from the launcher:
+//! - Generated server config and a world directory under the data dir
+//! - Port selection that skips ports already in use
+//! - Restart on crash, up to a limit
+//! - Server output forwarded as events, with the player count parsed from it
+//! - Graceful `stop` over stdin before the process is killed
+
+use std::collections::BTreeSet;
+use std::net::{TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::core::health::{CheckResult, HealthCheck};
+
+/// Name of the config file the server reads from its working directory
+pub const SERVER_CONFIG_FILE: &str = "pond.toml";
+
+/// Line the server prints once it accepts players
+const READY_MARKER: &str = "is now running";
+
+#[derive(Error, Debug)]
+pub enum HostingError {
+    #[error("A world is already being hosted")]
+    AlreadyRunning,
+
+    #[error("No world is being hosted")]
+    NotRunning,
+
+    #[error("Invalid world name: {0}")]
+    InvalidWorldName(String),
+
+    #[error("No free port between {start} and {end}")]
+    NoFreePort { start: u16, end: u16 },
+
+    #[error("Server executable not found: {0}")]
+    ExecutableNotFound(PathBuf),
+
+    #[error("Failed to start server: {0}")]
+    SpawnFailed(String),
+
+    #[error("Failed to write server config: {0}")]
+    Config(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// What the player asked to host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldHostConfig {
+    /// Directory name under `worlds/` in the data dir
+    pub world_name: String,
+
+    /// Preferred port; the next free one is used if it's taken
+    pub port: u16,
+
+    pub max_players: u32,
+
+    /// Shown in the server list
+    pub motd: String,
+}
+
+impl Default for WorldHostConfig {
+    fn default() -> Self {
+        Self {
+            world_name: "default".to_string(),
+            port: 25565,
+            max_players: 8,
+            motd: "Yellow Tale Local Server".to_string(),
+        }
+    }
+}
+
+/// How the service runs the server process
+#[derive(Debug, Clone)]
+pub struct HostServiceConfig {
+    /// Server binary, started with the world directory as working directory
+    pub executable: PathBuf,
+    pub args: Vec<String>,
+
+    /// Crashes restarted before giving up
+    pub max_restarts: u32,
+    pub restart_delay: Duration,
+
+    /// How long `stop` waits after the stop command before killing
+    pub stop_timeout: Duration,
+
+    /// Ports tried after the preferred one
+    pub port_attempts: u16,
+}
+
+impl HostServiceConfig {
+    /// The Pond server shipped next to the launcher executable
+    pub fn bundled() -> Self {
+        let dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| PathBuf::from("."));
+        Self {
+            executable: dir.join(format!("pond{}", std::env::consts::EXE_SUFFIX)),
+            args: Vec::new(),
+            max_restarts: 3,
+            restart_delay: Duration::from_secs(2),
+            stop_timeout: Duration::from_secs(15),
+            port_attempts: 20,
+        }
+    }
+}
+
+impl Default for HostServiceConfig {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostState {
+    Stopped,
+    /// Process running, not yet accepting players
+    Starting,
+    Running,
+    Stopping,
+    /// Exited more often than the restart limit allows
+    Crashed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostingStatus {
+    pub state: HostState,
+    pub world_name: Option<String>,
+    pub port: Option<u16>,
+    pub pid: Option<u32>,
+    /// Time since the current server process started
+    pub uptime_seconds: u64,
+    pub players_online: u32,
+    pub players: Vec<String>,
+    pub max_players: u32,
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Pushed to the UI while a world is hosted
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum HostEvent {
+    Log { stream: LogStream, line: String },
+    Restarted { attempt: u32, exit_code: Option<i32> },
+    Crashed { exit_code: Option<i32> },
+    Stopped { world_name: String },
+}
+
+impl HostEvent {
+    /// IPC event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Log { .. } => "hosting_log",
+            Self::Restarted { .. } => "hosting_restarted",
+            Self::Crashed { .. } => "hosting_crashed",
+            Self::Stopped { .. } => "hosting_stopped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerEvent {
+    Joined(String),
+    Left(String),
+}
+
+/// Reads `<name> joined the game` / `<name> left the game`, ignoring any log prefix
+pub fn parse_player_event(line: &str) -> Option<PlayerEvent> {
+    let player = |rest: &str| rest.split_whitespace().last().map(str::to_string);
+    if let Some(rest) = line.strip_suffix(" joined the game") {
+        return player(rest).map(PlayerEvent::Joined);
+    }
+    if let Some(rest) = line.strip_suffix(" left the game") {
+        return player(rest).map(PlayerEvent::Left);
+    }
+    None
+}
+
+/// World names become directory names, so keep them to a safe character set
+fn validate_world_name(name: &str) -> Result<(), HostingError> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(HostingError::InvalidWorldName(name.to_string()))
+    }
+}
+
+/// Whether both TCP and UDP can bind `port` on every interface
+pub fn port_available(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_ok() && UdpSocket::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// First free port from `start`, trying `attempts` ports and skipping `taken`
+pub fn select_port(start: u16, attempts: u16, taken: &[u16]) -> Result<u16, HostingError> {
+    let end = start.saturating_add(attempts.saturating_sub(1));
+    (start..=end)
+        .find(|port| !taken.contains(port) && port_available(*port))
+        .ok_or(HostingError::NoFreePort { start, end })
+}
+
+/// Pond config for a hosted world
+fn server_config(config: &WorldHostConfig, port: u16, launcher_api_port: u16) -> Result<String, HostingError> {
+    let value = serde_json::json!({
+        "server": {
+            "name": config.motd,
+            "port": port,
+            "max_players": config.max_players,
+            "tick_rate": 20,
+            "description": config.motd,
+        },
+        "plugins": {
+            "directory": "plugins",
+            "auto_load": true,
+            "hot_reload": false,
+            "sandbox_enabled": true,
+        },
+        "performance": {
+            "tick_budget_ms": 50.0,
+            "adaptive_throttling": true,
+            "max_entities_per_tick": 100,
+            "max_chunk_updates_per_tick": 50,
+            "memory_pool_size_mb": 256,
+        },
+        "assets": {
+            "max_cosmetic_size_mb": 5,
+            "allowed_types": ["skin", "cape", "hat", "particle", "emote"],
+            "require_approval": false,
+            "cache_directory": "cache/assets",
+        },
+        "integration": {
+            "enabled": true,
+            "launcher_api_port": launcher_api_port,
+            "advertise_capabilities": true,
+            "accept_asset_manifests": true,
+        },
+    });
+    let table = toml::Value::try_from(value).map_err(|e| HostingError::Config(e.to_string()))?;
+    toml::to_string_pretty(&table).map_err(|e| HostingError::Config(e.to_string()))
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: HostState,
+    config: Option<WorldHostConfig>,
+    port: Option<u16>,
+    pid: Option<u32>,
+    started: Option<Instant>,
+    players: BTreeSet<String>,
+    restarts: u32,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            state: HostState::Stopped,
+            config: None,
+            port: None,
+            pid: None,
+            started: None,
+            players: BTreeSet::new(),
+            restarts: 0,
+        }
+    }
+
+    fn process_started(&mut self, pid: Option<u32>) {
+        self.state = HostState::Starting;
+        self.pid = pid;
+        self.started = Some(Instant::now());
+        self.players.clear();
+    }
+
+    fn process_ended(&mut self, state: HostState) {
+        self.state = state;
+        self.pid = None;
+        self.started = None;
+        self.players.clear();
+    }
+}
+
+/// Everything the supervisor task needs
+#[derive(Clone)]
+struct Context {
+    shared: Arc<Mutex<Shared>>,
+    events: broadcast::Sender<HostEvent>,
+    config: HostServiceConfig,
+    world_dir: PathBuf,
+    world_name: String,
+}
+
+impl Context {
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn spawn_server(&self) -> Result<ServerProcess, HostingError> {
+        let mut child = Command::new(&self.config.executable)
+            .args(&self.config.args)
+            .current_dir(&self.world_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| HostingError::SpawnFailed(e.to_string()))?;
+
+        let mut output = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            output.push(tokio::spawn(self.clone().forward_output(stdout, LogStream::Stdout)));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            output.push(tokio::spawn(self.clone().forward_output(stderr, LogStream::Stderr)));
+        }
+        self.lock().process_started(child.id());
+        info!("Hosting '{}' (PID {:?})", self.world_name, child.id());
+        Ok(ServerProcess { child, output })
+    }
+
+    async fn forward_output(self, output: impl AsyncRead + Unpin, stream: LogStream) {
+        let mut lines = BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            {
+                let mut shared = self.lock();
+                if shared.state == HostState::Starting && line.contains(READY_MARKER) {
+                    shared.state = HostState::Running;
+                }
+                match parse_player_event(&line) {
+                    Some(PlayerEvent::Joined(name)) => { shared.players.insert(name); }
+                    Some(PlayerEvent::Left(name)) => { shared.players.remove(&name); }
+                    None => {}
+                }
+            }
+            let _ = self.events.send(HostEvent::Log { stream, line });
+        }
+    }
+
+    /// Keeps the server running until asked to stop or out of restarts
+    async fn supervise(self, mut server: ServerProcess, mut stop: oneshot::Receiver<()>) {
+        loop {
+            let stdin = server.child.stdin.take();
+            let status = tokio::select! {
+                status = server.child.wait() => status,
+                // A dropped sender means the service is gone; stop as well
+                _ = &mut stop => {
+                    self.lock().state = HostState::Stopping;
+                    self.shutdown(server.child, stdin).await;
+                    drain_output(server.output).await;
+                    self.lock().process_ended(HostState::Stopped);
+                    let _ = self.events.send(HostEvent::Stopped { world_name: self.world_name.clone() });
+                    return;
+                }
+            };
+
+            drain_output(std::mem::take(&mut server.output)).await;
+            let exit_code = status.as_ref().ok().and_then(ExitStatus::code);
+            if exit_code == Some(0) {
+                info!("Hosted server for '{}' exited", self.world_name);
+                self.lock().process_ended(HostState::Stopped);
+                let _ = self.events.send(HostEvent::Stopped { world_name: self.world_name.clone() });
+                return;
+            }
+
+            let attempt = {
+                let mut shared = self.lock();
+                if shared.restarts >= self.config.max_restarts {
+                    shared.process_ended(HostState::Crashed);
+                    None
+                } else {
+                    shared.restarts += 1;
+                    shared.process_ended(HostState::Starting);
+                    Some(shared.restarts)
+                }
+            };
+            let Some(attempt) = attempt else {
+                warn!("Hosted server for '{}' crashed (exit code {:?}); giving up", self.world_name, exit_code);
+                let _ = self.events.send(HostEvent::Crashed { exit_code });
+                return;
+            };
+
+            warn!("Hosted server for '{}' crashed (exit code {:?}); restart {} of {}",
+                  self.world_name, exit_code, attempt, self.config.max_restarts);
+            let _ = self.events.send(HostEvent::Restarted { attempt, exit_code });
+            tokio::select! {
+                _ = tokio::time::sleep(self.config.restart_delay) => {}
+                _ = &mut stop => {
+                    self.lock().process_ended(HostState::Stopped);
+                    let _ = self.events.send(HostEvent::Stopped { world_name: self.world_name.clone() });
+                    return;
+                }
+            }
+
+            server = match self.spawn_server() {
+                Ok(server) => server,
+                Err(e) => {
+                    warn!("Could not restart hosted server: {}", e);
+                    self.lock().process_ended(HostState::Crashed);
+                    let _ = self.events.send(HostEvent::Crashed { exit_code });
+                    return;
+                }
+            };
+        }
+    }
+
+    /// Sends `stop` and waits for the server to save and exit, killing it after the timeout
+    async fn shutdown(&self, mut child: Child, stdin: Option<ChildStdin>) {
+        if let Some(mut stdin) = stdin {
+            if stdin.write_all(b"stop\n").await.is_ok() && stdin.flush().await.is_ok() {
+                if let Ok(status) = tokio::time::timeout(self.config.stop_timeout, child.wait()).await {
+                    info!("Hosted server for '{}' stopped ({:?})", self.world_name, status.ok());
+                    return;
+                }
+            }
+        }
+        warn!("Hosted server for '{}' did not stop in time; killing it", self.world_name);
+        if let Err(e) = child.kill().await {
+            warn!("Could not kill hosted server: {}", e);
+        }
+    }
+}
+
+struct ServerProcess {
+    child: Child,
+    /// Tasks forwarding stdout and stderr
+    output: Vec<JoinHandle<()>>,
+}
+
+/// Lets the output tasks forward the last lines the server printed
+async fn drain_output(output: Vec<JoinHandle<()>>) {
+    for task in output {
+        if tokio::time::timeout(Duration::from_secs(1), task).await.is_err() {
+            warn!("Hosted server output still open after exit");
+        }
+    }
+}
+
+struct Supervised {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// Runs and supervises the server for a locally hosted world
+pub struct WorldHostService {
+    worlds_dir: PathBuf,
+    config: HostServiceConfig,
+    shared: Arc<Mutex<Shared>>,
+    events: broadcast::Sender<HostEvent>,
+    running: tokio::sync::Mutex<Option<Supervised>>,
+}
+
+impl WorldHostService {
+    pub fn new(worlds_dir: impl Into<PathBuf>, config: HostServiceConfig) -> Self {
+        Self {
+            worlds_dir: worlds_dir.into(),
+            config,
+            shared: Arc::new(Mutex::new(Shared::new())),
+            events: broadcast::channel(256).0,
+            running: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<HostEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn world_dir(&self, world_name: &str) -> PathBuf {
+        self.worlds_dir.join(world_name)
+    }
+
+    /// Generate the config, pick ports and start supervising the server
+    pub async fn start(&self, config: WorldHostConfig) -> Result<HostingStatus, HostingError> {
+        let mut running = self.running.lock().await;
+        if running.as_ref().is_some_and(|r| !r.task.is_finished()) {
+            return Err(HostingError::AlreadyRunning);
+        }
+
+        validate_world_name(&config.world_name)?;
+        if !self.config.executable.exists() {
+            return Err(HostingError::ExecutableNotFound(self.config.executable.clone()));
+        }
+
+        let world_dir = self.world_dir(&config.world_name);
+        tokio::fs::create_dir_all(&world_dir).await?;
+
+        let port = select_port(config.port, self.config.port_attempts, &[])?;
+        let api_port = select_port(port.saturating_add(1), self.config.port_attempts, &[port])?;
+        if port != config.port {
+            info!("Port {} is in use; hosting on {}", config.port, port);
+        }
+        tokio::fs::write(world_dir.join(SERVER_CONFIG_FILE), server_config(&config, port, api_port)?).await?;
+
+        {
+            let mut shared = self.lock();
+            *shared = Shared::new();
+            shared.config = Some(config.clone());
+            shared.port = Some(port);
+        }
+        let context = Context {
+            shared: self.shared.clone(),
+            events: self.events.clone(),
+            config: self.config.clone(),
+            world_dir,
+            world_name: config.world_name.clone(),
+        };
+        let server = match context.spawn_server() {
+            Ok(server) => server,
+            Err(e) => {
+                *self.lock() = Shared::new();
+                return Err(e);
+            }
+        };
+
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(context.supervise(server, stop_rx));
+        *running = Some(Supervised { stop, task });
+        drop(running);
+
+        Ok(self.status())
+    }
+
+    /// Ask the server to stop and wait until it has
+    pub async fn stop(&self) -> Result<(), HostingError> {
+        let Some(supervised) = self.running.lock().await.take() else {
+            return Err(HostingError::NotRunning);
+        };
+        let _ = supervised.stop.send(());
+        if let Err(e) = supervised.task.await {
+            warn!("Hosting supervisor ended abnormally: {}", e);
+        }
+
+        let mut shared = self.lock();
+        shared.process_ended(HostState::Stopped);
+        shared.port = None;
+        Ok(())
+    }
+
+    pub fn status(&self) -> HostingStatus {
+        let shared = self.lock();
+        let config = shared.config.as_ref();
+        HostingStatus {
+            state: shared.state,
+            world_name: config.map(|c| c.world_name.clone()),
+            port: shared.port,
+            pid: shared.pid,
+            uptime_seconds: shared.started.map(|t| t.elapsed().as_secs()).unwrap_or(0),
+            players_online: shared.players.len() as u32,
+            players: shared.players.iter().cloned().collect(),
+            max_players: config.map(|c| c.max_players).unwrap_or(0),
+            restarts: shared.restarts,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Down once the server crashed past its restart limit, degraded while it
+/// keeps restarting
+#[async_trait::async_trait]
+impl HealthCheck for WorldHostService {
+    fn name(&self) -> &str {
+        "hosting"
+    }
+
+    async fn health_check(&self) -> CheckResult {
+        let status = self.status();
+        match status.state {
+            HostState::Crashed => CheckResult::down(format!("Server crashed after {} restarts", status.restarts)),
+            HostState::Stopped => CheckResult::ok_with("Not hosting"),
+            _ if status.restarts > 0 => CheckResult::degraded(format!("Server restarted {} times", status.restarts)),
+            _ => CheckResult::ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-hosting-{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Stands in for the server: prints what Pond prints, then runs `tail`
+    fn stub_server(tail: &str) -> HostServiceConfig {
+        let script = format!(
+            "echo 'Starting Pond server...'\n\
+             echo 'Pond server is now running'\n\
+             echo '[INFO] Steve joined the game'\n\
+             echo '[INFO] Alex joined the game'\n\
+             echo '[INFO] Steve left the game'\n\
+             {}",
+            tail
+        );
+        HostServiceConfig {
+            executable: PathBuf::from("/bin/sh"),
+            args: vec!["-c".to_string(), script],
+            max_restarts: 2,
+            restart_delay: Duration::from_millis(10),
+            stop_timeout: Duration::from_secs(5),
+            port_attempts: 20,
+        }
+    }
+
+    fn free_port() -> u16 {
+        TcpListener::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port()
+    }
+
+    async fn wait_until(host: &WorldHostService, done: impl Fn(&HostingStatus) -> bool) -> HostingStatus {
+        for _ in 0..500 {
+            let status = host.status();
+            if done(&status) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("hosting status never matched: {:?}", host.status());
+    }
+
+    #[test]
+    fn test_parse_player_events() {
+        assert_eq!(parse_player_event("Steve joined the game"), Some(PlayerEvent::Joined("Steve".to_string())));
+        assert_eq!(parse_player_event("[12:00:01 INFO]: Alex left the game"), Some(PlayerEvent::Left("Alex".to_string())));
+        assert_eq!(parse_player_event("Pond server is now running"), None);
+        assert!(validate_world_name("my_world-2").is_ok());
+        assert!(validate_world_name("../escape").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hosts_world_and_stops_over_stdin() {
+        let dir = temp_dir();
+        let host = WorldHostService::new(&dir, stub_server(
+            "while read line; do [ \"$line\" = stop ] && echo 'Saving world' && exit 0; done"
+        ));
+        let mut events = host.subscribe();
+
+        // Something already listens on the preferred port
+        let preferred = free_port();
+        let _taken = TcpListener::bind(("0.0.0.0", preferred)).unwrap();
+        let config = WorldHostConfig { world_name: "island".to_string(), port: preferred, ..Default::default() };
+        let started = host.start(config.clone()).await.unwrap();
+        let port = started.port.unwrap();
+        assert_ne!(port, preferred);
+        assert!(matches!(host.start(config).await, Err(HostingError::AlreadyRunning)));
+
+        let status = wait_until(&host, |s| s.state == HostState::Running && s.players == ["Alex"]).await;
+        assert_eq!(status.players_online, 1);
+        assert_eq!(status.max_players, 8);
+        assert_eq!(status.world_name.as_deref(), Some("island"));
+
+        let generated = std::fs::read_to_string(dir.join("island").join(SERVER_CONFIG_FILE)).unwrap();
+        let generated: toml::Value = toml::from_str(&generated).unwrap();
+        assert_eq!(generated["server"]["port"].as_integer(), Some(port as i64));
+        assert_eq!(generated["server"]["max_players"].as_integer(), Some(8));
+
+        host.stop().await.unwrap();
+        assert_eq!(host.status().state, HostState::Stopped);
+        assert_eq!(host.status().restarts, 0);
+
+        let mut lines = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let HostEvent::Log { line, .. } = event {
+                lines.push(line);
+            }
+        }
+        assert_eq!(lines.first().map(String::as_str), Some("Starting Pond server..."));
+        assert_eq!(lines.last().map(String::as_str), Some("Saving world"));
+        assert!(matches!(host.stop().await, Err(HostingError::NotRunning)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_crashing_server_is_restarted_up_to_the_limit() {
+        let dir = temp_dir();
+        let host = WorldHostService::new(&dir, stub_server("exit 7"));
+        let mut events = host.subscribe();
+
+        host.start(WorldHostConfig { port: free_port(), ..Default::default() }).await.unwrap();
+        let status = wait_until(&host, |s| s.state == HostState::Crashed).await;
+        assert_eq!(status.restarts, 2);
+        assert_eq!(status.pid, None);
+
+        let mut restarts = Vec::new();
+        let mut crashed = None;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                HostEvent::Restarted { attempt, exit_code } => restarts.push((attempt, exit_code)),
+                HostEvent::Crashed { exit_code } => crashed = Some(exit_code),
+                _ => {}
+            }
+        }
+        assert_eq!(restarts, vec![(1, Some(7)), (2, Some(7))]);
+        assert_eq!(crashed, Some(Some(7)));
+
+        // A crashed world can be stopped and hosted again
+        host.stop().await.unwrap();
+        host.start(WorldHostConfig { port: free_port(), ..Default::default() }).await.unwrap();
+        host.stop().await.unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_executable_is_reported() {
+        let dir = temp_dir();
+        let host = WorldHostService::new(&dir, HostServiceConfig {
+            executable: dir.join("pond"),
+            ..stub_server("")
+        });
+        assert!(matches!(host.start(WorldHostConfig::default()).await, Err(HostingError::ExecutableNotFound(_))));
+        assert!(matches!(
+            host.start(WorldHostConfig { world_name: String::new(), ..Default::default() }).await,
+            Err(HostingError::InvalidWorldName(_))
+        ));
+        assert_eq!(host.status().state, HostState::Stopped);
+    }
+}
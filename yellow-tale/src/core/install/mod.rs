@@ -0,0 +1,450 @@
+//! Hytale Installation Module
+//!
+//! Finds the game on this machine and checks a path holds a playable install:
+//! - Candidates come from config hints, the Windows uninstall registry keys,
+//!   Steam libraries (including ones on other drives) and each OS's standard
+//!   launcher directory
+//! - A candidate may be the launcher's data directory, the game directory
+//!   itself, or the client executable inside it
+//! - A game directory is playable when it has a client executable and
+//!   `Assets.zip`; a readable `version.json` raises confidence
+//! - Candidates that exist but aren't playable are reported with what's missing
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Where the official launcher puts the current game build, relative to its data directory
+pub const GAME_SUBDIR: &str = "install/release/package/game/latest";
+
+/// Client entry points, relative to the game directory
+pub const CLIENT_EXECUTABLES: &[&str] = &[
+    "Client/HytaleClient.jar",
+    "Client/HytaleClient.exe",
+    "Client/HytaleClient",
+];
+
+/// Game assets archive, relative to the game directory
+pub const ASSETS_FILE: &str = "Assets.zip";
+
+/// Build manifest naming the game version, relative to the game directory
+pub const VERSION_MANIFEST: &str = "version.json";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InstallError {
+    #[error("{0} does not exist")]
+    NotFound(PathBuf),
+
+    #[error("{} is not a playable Hytale install, missing: {}", .path.display(), .missing.join(", "))]
+    Incomplete { path: PathBuf, missing: Vec<String> },
+}
+
+/// How a candidate location was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateSource {
+    ConfigHint,
+    Registry,
+    Steam,
+    Standard,
+    /// Passed to `set_game_path`
+    Manual,
+}
+
+/// A place the game might be installed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub source: CandidateSource,
+}
+
+impl Candidate {
+    pub fn new(path: impl Into<PathBuf>, source: CandidateSource) -> Self {
+        Self { path: path.into(), source }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// Playable, but the version couldn't be read
+    Medium,
+    /// Playable with a readable version manifest
+    High,
+}
+
+/// A playable install
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectedInstall {
+    /// Game directory, holding the client and `Assets.zip`
+    pub path: PathBuf,
+    /// Launcher data directory above the game directory, when the install
+    /// uses the official layout
+    pub root: Option<PathBuf>,
+    pub executable: PathBuf,
+    pub version: Option<String>,
+    pub confidence: Confidence,
+    pub source: CandidateSource,
+}
+
+/// A candidate that exists but isn't a playable install
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedCandidate {
+    pub path: PathBuf,
+    pub source: CandidateSource,
+    pub missing: Vec<String>,
+}
+
+/// Result of scanning every candidate
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectionReport {
+    /// Best first: confidence, then the order candidates were listed in
+    pub installs: Vec<DetectedInstall>,
+    pub rejected: Vec<RejectedCandidate>,
+}
+
+#[derive(Deserialize)]
+struct VersionManifest {
+    version: String,
+}
+
+/// Check a path holds a playable install
+pub fn validate(path: &Path, source: CandidateSource) -> Result<DetectedInstall, InstallError> {
+    if !path.exists() {
+        return Err(InstallError::NotFound(path.to_path_buf()));
+    }
+
+    // Check every way the path could point at the game, reporting what the
+    // most complete one lacks
+    let mut best: Option<(PathBuf, Vec<String>)> = None;
+    for (game_dir, root) in layouts(path) {
+        let executable = CLIENT_EXECUTABLES.iter().map(|exe| game_dir.join(exe)).find(|exe| exe.is_file());
+        let assets = game_dir.join(ASSETS_FILE).is_file();
+
+        let mut missing = Vec::new();
+        if executable.is_none() {
+            missing.push(format!("client executable ({})", CLIENT_EXECUTABLES.join(" or ")));
+        }
+        if !assets {
+            missing.push(ASSETS_FILE.to_string());
+        }
+        if let (Some(executable), true) = (executable, assets) {
+            let version = read_version(&game_dir);
+            return Ok(DetectedInstall {
+                confidence: if version.is_some() { Confidence::High } else { Confidence::Medium },
+                path: game_dir,
+                root,
+                executable,
+                version,
+                source,
+            });
+        }
+        if best.as_ref().is_none_or(|(_, fewest)| missing.len() < fewest.len()) {
+            best = Some((game_dir, missing));
+        }
+    }
+
+    let (path, missing) = best.unwrap_or_else(|| (path.to_path_buf(), Vec::new()));
+    Err(InstallError::Incomplete { path, missing })
+}
+
+/// Game directories `path` could mean, with the launcher directory above
+/// each one if it has the official layout
+fn layouts(path: &Path) -> Vec<(PathBuf, Option<PathBuf>)> {
+    let root_of = |game_dir: &Path| {
+        let mut root = game_dir.to_path_buf();
+        for _ in Path::new(GAME_SUBDIR).components() {
+            root = root.parent()?.to_path_buf();
+        }
+        (root.join(GAME_SUBDIR) == game_dir).then_some(root)
+    };
+
+    if path.is_file() {
+        // An executable: Client/<exe> inside the game directory
+        return path.parent()
+            .and_then(Path::parent)
+            .map(|game_dir| vec![(game_dir.to_path_buf(), root_of(game_dir))])
+            .unwrap_or_default();
+    }
+    vec![
+        (path.join(GAME_SUBDIR), Some(path.to_path_buf())),
+        (path.to_path_buf(), root_of(path)),
+    ]
+}
+
+fn read_version(game_dir: &Path) -> Option<String> {
+    let content = std::fs::read(game_dir.join(VERSION_MANIFEST)).ok()?;
+    let manifest: VersionManifest = serde_json::from_slice(&content).ok()?;
+    let version = manifest.version.trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Scans candidate locations for playable installs
+pub struct InstallationDetector {
+    candidates: Vec<Candidate>,
+}
+
+impl InstallationDetector {
+    /// Check exactly these candidates, in this order
+    pub fn with_candidates(candidates: Vec<Candidate>) -> Self {
+        Self { candidates }
+    }
+
+    /// Candidates for this machine, config hints first
+    pub fn for_this_machine(hints: &[String]) -> Self {
+        let mut candidates: Vec<Candidate> = hints.iter()
+            .map(|hint| Candidate::new(hint, CandidateSource::ConfigHint))
+            .collect();
+        candidates.extend(registry_locations().into_iter().map(|p| Candidate::new(p, CandidateSource::Registry)));
+        candidates.extend(steam_locations().into_iter().map(|p| Candidate::new(p, CandidateSource::Steam)));
+        candidates.extend(standard_locations().into_iter().map(|p| Candidate::new(p, CandidateSource::Standard)));
+        Self { candidates }
+    }
+
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// Every playable install, best first, and why the other existing
+    /// candidates were passed over
+    pub fn detect(&self) -> DetectionReport {
+        let mut report = DetectionReport::default();
+        let mut seen = HashSet::new();
+        for candidate in &self.candidates {
+            match validate(&candidate.path, candidate.source) {
+                Ok(install) => {
+                    if seen.insert(install.path.clone()) {
+                        report.installs.push(install);
+                    }
+                }
+                Err(InstallError::Incomplete { missing, .. }) => {
+                    report.rejected.push(RejectedCandidate {
+                        path: candidate.path.clone(),
+                        source: candidate.source,
+                        missing,
+                    });
+                }
+                Err(InstallError::NotFound(_)) => {}
+            }
+        }
+        // Stable, so equally confident installs keep candidate order
+        report.installs.sort_by_key(|install| std::cmp::Reverse(install.confidence));
+        report
+    }
+}
+
+/// `InstallLocation` values under uninstall keys whose name mentions Hytale
+fn parse_uninstall_query(output: &str) -> Vec<PathBuf> {
+    output.lines()
+        .filter_map(|line| {
+            let (name, value) = line.trim().split_once("REG_SZ")?;
+            let value = value.trim();
+            (name.trim() == "InstallLocation" && !value.is_empty()).then(|| PathBuf::from(value))
+        })
+        .collect()
+}
+
+fn registry_locations() -> Vec<PathBuf> {
+    const KEYS: &[&str] = &[
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Uninstall",
+        r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
+        r"HKLM\SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+    ];
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+
+    KEYS.iter()
+        .filter_map(|key| {
+            std::process::Command::new("reg")
+                .args(["query", key, "/s", "/f", "Hytale", "/d"])
+                .output()
+                .ok()
+        })
+        .flat_map(|output| parse_uninstall_query(&String::from_utf8_lossy(&output.stdout)))
+        .collect()
+}
+
+/// Library paths listed in a Steam `libraryfolders.vdf`
+fn parse_library_folders(vdf: &str) -> Vec<PathBuf> {
+    vdf.lines()
+        .filter_map(|line| {
+            let mut quoted = line.split('"').skip(1).step_by(2);
+            (quoted.next()? == "path").then(|| quoted.next()).flatten()
+        })
+        .map(|path| PathBuf::from(path.replace("\\\\", "\\")))
+        .collect()
+}
+
+fn steam_locations() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut roots: Vec<PathBuf> = Vec::new();
+    if cfg!(windows) {
+        roots.extend(["ProgramFiles(x86)", "ProgramFiles"].iter()
+            .filter_map(std::env::var_os)
+            .map(|dir| PathBuf::from(dir).join("Steam")));
+    } else if cfg!(target_os = "macos") {
+        roots.extend(home.iter().map(|home| home.join("Library/Application Support/Steam")));
+    } else {
+        roots.extend(home.iter().flat_map(|home| [home.join(".steam/steam"), home.join(".local/share/Steam")]));
+    }
+
+    let mut libraries = roots.clone();
+    for root in &roots {
+        if let Ok(vdf) = std::fs::read_to_string(root.join("steamapps/libraryfolders.vdf")) {
+            libraries.extend(parse_library_folders(&vdf));
+        }
+    }
+    let mut seen = HashSet::new();
+    libraries.into_iter()
+        .filter(|library| seen.insert(library.clone()))
+        .map(|library| library.join("steamapps/common/Hytale"))
+        .collect()
+}
+
+fn standard_locations() -> Vec<PathBuf> {
+    let env = |name: &str| std::env::var_os(name).map(PathBuf::from);
+    let mut locations = Vec::new();
+    if cfg!(windows) {
+        locations.extend(["APPDATA", "LOCALAPPDATA", "ProgramFiles"].iter().filter_map(|v| env(v)).map(|dir| dir.join("Hytale")));
+        // Games moved to a second drive usually sit near its root
+        for drive in 'C'..='Z' {
+            locations.push(PathBuf::from(format!(r"{}:\Hytale", drive)));
+            locations.push(PathBuf::from(format!(r"{}:\Games\Hytale", drive)));
+        }
+    } else if cfg!(target_os = "macos") {
+        locations.extend(env("HOME").map(|home| home.join("Library/Application Support/Hytale")));
+        locations.push(PathBuf::from("/Applications/Hytale"));
+    } else {
+        let data = env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")));
+        locations.extend(data.map(|data| data.join("Hytale")));
+        locations.extend(env("HOME").map(|home| home.join(".var/app/com.hypixel.HytaleLauncher/data/Hytale")));
+    }
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-install-{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Lay out a game directory with the given files under `dir`
+    fn fabricate(dir: &Path, files: &[&str]) {
+        for file in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let content = if *file == VERSION_MANIFEST { r#"{ "version": "2026.1.4" }"# } else { "" };
+            std::fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_official_layout_is_found_from_any_path_into_it() {
+        let root = temp_dir();
+        let game_dir = root.join(GAME_SUBDIR);
+        fabricate(&game_dir, &["Client/HytaleClient.jar", ASSETS_FILE, VERSION_MANIFEST]);
+
+        for path in [root.clone(), game_dir.clone(), game_dir.join("Client/HytaleClient.jar")] {
+            let install = validate(&path, CandidateSource::Manual).unwrap();
+            assert_eq!(install.path, game_dir);
+            assert_eq!(install.root.as_ref(), Some(&root));
+            assert_eq!(install.version.as_deref(), Some("2026.1.4"));
+            assert_eq!(install.confidence, Confidence::High);
+        }
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_incomplete_installs_list_whats_missing() {
+        let root = temp_dir();
+        fabricate(&root.join(GAME_SUBDIR), &["Client/HytaleClient.exe"]);
+        let err = validate(&root, CandidateSource::Manual).unwrap_err();
+        assert_eq!(err, InstallError::Incomplete { path: root.join(GAME_SUBDIR), missing: vec![ASSETS_FILE.to_string()] });
+        assert!(err.to_string().ends_with("missing: Assets.zip"));
+
+        let empty = temp_dir();
+        std::fs::create_dir_all(&empty).unwrap();
+        let InstallError::Incomplete { missing, .. } = validate(&empty, CandidateSource::Manual).unwrap_err() else {
+            panic!("expected an incomplete install");
+        };
+        assert_eq!(missing.len(), 2);
+        assert!(missing[0].starts_with("client executable"));
+
+        let absent = empty.join("nope");
+        assert_eq!(validate(&absent, CandidateSource::Manual), Err(InstallError::NotFound(absent)));
+
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::remove_dir_all(&empty).ok();
+    }
+
+    #[test]
+    fn test_detect_ranks_installs_and_reports_rejections() {
+        let base = temp_dir();
+        // A Steam copy on a second drive without a version manifest
+        let steam = base.join("D/SteamLibrary/steamapps/common/Hytale");
+        fabricate(&steam, &["Client/HytaleClient", ASSETS_FILE]);
+        // The standalone launcher's copy, complete
+        let standalone = base.join("AppData/Hytale");
+        fabricate(&standalone.join(GAME_SUBDIR), &["Client/HytaleClient.jar", ASSETS_FILE, VERSION_MANIFEST]);
+        // A stale hint at a folder with no game in it
+        let stale = base.join("OldGames/Hytale");
+        fabricate(&stale, &["readme.txt"]);
+
+        let detector = InstallationDetector::with_candidates(vec![
+            Candidate::new(&stale, CandidateSource::ConfigHint),
+            Candidate::new(&steam, CandidateSource::Steam),
+            Candidate::new(base.join("missing"), CandidateSource::Standard),
+            Candidate::new(&standalone, CandidateSource::Standard),
+            // The same install reached a second way only counts once
+            Candidate::new(standalone.join(GAME_SUBDIR), CandidateSource::Standard),
+        ]);
+        let report = detector.detect();
+
+        let found: Vec<(&Path, Confidence)> = report.installs.iter().map(|i| (i.path.as_path(), i.confidence)).collect();
+        assert_eq!(found, vec![
+            (standalone.join(GAME_SUBDIR).as_path(), Confidence::High),
+            (steam.as_path(), Confidence::Medium),
+        ]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].path, stale);
+        assert_eq!(report.rejected[0].source, CandidateSource::ConfigHint);
+
+        std::fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_parses_registry_and_steam_library_listings() {
+        let reg = r"
+HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Hytale
+    DisplayName    REG_SZ    Hytale
+    InstallLocation    REG_SZ    E:\Games\Hytale
+    Publisher    REG_SZ    Hypixel Studios
+";
+        assert_eq!(parse_uninstall_query(reg), vec![PathBuf::from(r"E:\Games\Hytale")]);
+
+        let vdf = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+	}
+}
+"#;
+        assert_eq!(parse_library_folders(vdf), vec![
+            PathBuf::from(r"C:\Program Files (x86)\Steam"),
+            PathBuf::from(r"D:\SteamLibrary"),
+        ]);
+    }
+}
@@ -0,0 +1,290 @@
+//! Integrity Module
+//!
+//! Builds the attestation Rubidium servers ask for when the player connects:
+//! - A manifest with the launcher version, a SHA-256 of the launcher's own
+//!   executable and a SHA-256 of every enabled mod
+//! - An Ed25519 signature under a key generated once per install and kept in
+//!   `install_key.json` in the data dir; only its public key is sent
+//! - Signed heartbeats that renew the token the server hands back
+//!
+//! Only files on disk are hashed. Nothing here looks at the game process or
+//! its memory.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::core::mods::activator::ScannedMod;
+use crate::core::util::sha256_file;
+
+/// File the per-install key is kept in
+pub const INSTALL_KEY_FILE: &str = "install_key.json";
+
+#[derive(Error, Debug)]
+pub enum IntegrityError {
+    #[error("Install key is corrupt: {0}")]
+    InvalidKey(String),
+
+    #[error("Could not hash {path}: {error}")]
+    Hash { path: PathBuf, error: std::io::Error },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Identity of this install. `key` is the hex seed of its Ed25519 signing
+/// key and never leaves the machine; servers pin the public half.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallKey {
+    pub install_id: Uuid,
+    key: String,
+}
+
+impl InstallKey {
+    pub fn new(install_id: Uuid, seed: &[u8; 32]) -> Self {
+        Self { install_id, key: hex::encode(seed) }
+    }
+
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self::new(Uuid::new_v4(), &key)
+    }
+
+    /// Read the key from `path`, creating it on first use
+    pub async fn load_or_create(path: &Path) -> Result<Self, IntegrityError> {
+        if path.exists() {
+            let content = tokio::fs::read_to_string(path).await?;
+            let key: Self = serde_json::from_str(&content)
+                .map_err(|e| IntegrityError::InvalidKey(e.to_string()))?;
+            if key.signing_key().is_none() {
+                return Err(IntegrityError::InvalidKey("key is not a 32-byte hex seed".to_string()));
+            }
+            return Ok(key);
+        }
+
+        let key = Self::generate();
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(&key)?).await?;
+        Ok(key)
+    }
+
+    fn signing_key(&self) -> Option<SigningKey> {
+        let seed: [u8; 32] = hex::decode(&self.key).ok()?.try_into().ok()?;
+        Some(SigningKey::from_bytes(&seed))
+    }
+
+    /// Hex Ed25519 public key the server pins
+    pub fn public_key(&self) -> String {
+        self.signing_key().map(|key| hex::encode(key.verifying_key().to_bytes())).unwrap_or_default()
+    }
+
+    /// Hex Ed25519 signature of `message` under this install's key
+    pub fn sign(&self, message: &[u8]) -> String {
+        self.signing_key().map(|key| hex::encode(key.sign(message).to_bytes())).unwrap_or_default()
+    }
+}
+
+// Field order matters: the manifest is signed as serialized. These mirror
+// `rubidium::anticheat::attestation`.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub name: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModDigest {
+    pub id: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityManifest {
+    pub install_id: Uuid,
+    pub launcher_version: String,
+    pub binaries: Vec<FileDigest>,
+    pub mods: Vec<ModDigest>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What the launcher sends to a Rubidium server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AttestationMessage {
+    Hello {
+        manifest: String,
+        signature: String,
+        public_key: String,
+    },
+    Heartbeat {
+        token: String,
+        sequence: u64,
+        signature: String,
+    },
+}
+
+/// Builds and signs attestation messages for this install
+pub struct Attestor {
+    key: InstallKey,
+    launcher_version: String,
+    binaries: Vec<(String, PathBuf)>,
+    sequence: AtomicU64,
+}
+
+impl Attestor {
+    pub fn new(key: InstallKey, launcher_version: impl Into<String>) -> Self {
+        Self {
+            key,
+            launcher_version: launcher_version.into(),
+            binaries: Vec::new(),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Load the install key from the data dir and attest the running executable
+    pub async fn load(data_dir: &Path, launcher_version: impl Into<String>) -> Result<Self, IntegrityError> {
+        let key = InstallKey::load_or_create(&data_dir.join(INSTALL_KEY_FILE)).await?;
+        let mut attestor = Self::new(key, launcher_version);
+        if let Ok(exe) = std::env::current_exe() {
+            attestor = attestor.with_binary("yellow-tale", exe);
+        }
+        Ok(attestor)
+    }
+
+    /// Include a launcher file in every manifest
+    pub fn with_binary(mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.binaries.push((name.into(), path.into()));
+        self
+    }
+
+    pub fn install_id(&self) -> Uuid {
+        self.key.install_id
+    }
+
+    /// Hash the launcher's binaries and every enabled mod
+    pub async fn manifest<'a>(&self, mods: impl IntoIterator<Item = &'a ScannedMod>) -> Result<IntegrityManifest, IntegrityError> {
+        let mut binaries = Vec::new();
+        for (name, path) in &self.binaries {
+            binaries.push(FileDigest { name: name.clone(), sha256: hash(path).await? });
+        }
+
+        let mut digests = Vec::new();
+        for module in mods.into_iter().filter(|m| m.enabled) {
+            digests.push(ModDigest { id: module.id.clone(), sha256: hash(&module.path).await? });
+        }
+        digests.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(IntegrityManifest {
+            install_id: self.key.install_id,
+            launcher_version: self.launcher_version.clone(),
+            binaries,
+            mods: digests,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// The message sent on connect
+    pub fn hello(&self, manifest: &IntegrityManifest) -> Result<AttestationMessage, IntegrityError> {
+        let manifest = serde_json::to_string(manifest)?;
+        Ok(AttestationMessage::Hello {
+            signature: self.key.sign(manifest.as_bytes()),
+            manifest,
+            public_key: self.key.public_key(),
+        })
+    }
+
+    /// Renew `token`; every heartbeat carries a higher sequence than the last
+    pub fn heartbeat(&self, token: &str) -> AttestationMessage {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        AttestationMessage::Heartbeat {
+            token: token.to_string(),
+            sequence,
+            signature: self.key.sign(format!("{}:{}", token, sequence).as_bytes()),
+        }
+    }
+}
+
+async fn hash(path: &Path) -> Result<String, IntegrityError> {
+    sha256_file(path).await.map_err(|error| IntegrityError::Hash { path: path.to_path_buf(), error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared with Rubidium's attestation tests so the two can't drift.
+    const HELLO_FIXTURE: &str = include_str!("../../../../rubidium/runtime/tests/fixtures/attestation_hello.json");
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-integrity-{}", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_hello_matches_server_fixture() {
+        let fixture: serde_json::Value = serde_json::from_str(HELLO_FIXTURE).unwrap();
+        let seed = hex::decode(fixture["seed"].as_str().unwrap()).unwrap();
+        let signed = fixture["message"]["manifest"].as_str().unwrap();
+        let manifest: IntegrityManifest = serde_json::from_str(signed).unwrap();
+
+        let attestor = Attestor::new(InstallKey::new(manifest.install_id, &seed.try_into().unwrap()), "0.1.0");
+        let hello = attestor.hello(&manifest).unwrap();
+        assert_eq!(serde_json::to_value(&hello).unwrap(), fixture["message"]);
+    }
+
+    #[test]
+    fn test_heartbeat_sequence_increases() {
+        let key = InstallKey::new(Uuid::nil(), &[7; 32]);
+        let attestor = Attestor::new(key.clone(), "0.1.0");
+        let first = attestor.heartbeat("abc");
+        let second = attestor.heartbeat("abc");
+        match (first, second) {
+            (
+                AttestationMessage::Heartbeat { sequence: 1, signature, .. },
+                AttestationMessage::Heartbeat { sequence: 2, .. },
+            ) => assert_eq!(signature, key.sign(b"abc:1")),
+            other => panic!("unexpected heartbeats: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_manifest_hashes_enabled_mods_and_keeps_key() {
+        let dir = temp_dir();
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("minimap.jar"), b"test").await.unwrap();
+        tokio::fs::write(dir.join("old.jar"), b"old").await.unwrap();
+        tokio::fs::write(dir.join("launcher"), b"foo").await.unwrap();
+        let mods = [
+            ScannedMod { id: "minimap".to_string(), path: dir.join("minimap.jar"), enabled: true },
+            ScannedMod { id: "old".to_string(), path: dir.join("old.jar"), enabled: false },
+        ];
+
+        let attestor = Attestor::load(&dir, "0.1.0").await.unwrap()
+            .with_binary("launcher", dir.join("launcher"));
+        let manifest = attestor.manifest(&mods).await.unwrap();
+        assert_eq!(manifest.install_id, attestor.install_id());
+        assert_eq!(manifest.mods, vec![ModDigest {
+            id: "minimap".to_string(),
+            sha256: "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string(),
+        }]);
+        let launcher = manifest.binaries.iter().find(|b| b.name == "launcher").unwrap();
+        assert_eq!(launcher.sha256, "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae");
+
+        let reloaded = Attestor::load(&dir, "0.1.0").await.unwrap();
+        assert_eq!(reloaded.install_id(), attestor.install_id());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -7,26 +7,59 @@
 //! 
 //! The UI communicates ONLY via IPC - no filesystem access from UI.
 
+pub mod registry;
+pub mod transport;
+
+use registry::{IpcVersion, Negotiated};
+
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::core::{
-    launcher::LauncherService,
+    launcher::{safe_mode::{self, GameExitReport, SafeModeReport}, LastExit, LaunchConfig, LauncherService},
     profiles::ProfileManager,
     cache::CacheManager,
-    sessions::SessionOrchestrator,
-    diagnostics::DiagnosticsCollector,
-    users::{UserService, SignupRequest, LoginRequest},
-    friends::FriendsService,
-    relay::RelayServer,
+    sessions::{SessionError, SessionOrchestrator},
+    diagnostics::{frame_pacing::FramePacingAnalyzer, DiagnosticsCollector},
+    users::{AuthResponse, SignupRequest, LoginRequest, search::SearchCursor},
+    db::supervisor::{is_connection_error, DatabaseServices, DatabaseStatus, DatabaseSupervisor, QueryError},
+    relay::{JoinValidator, RelayConfig, RelayIdentity, RelayServer},
+    settings_sync::{SettingsSync, SyncSection},
+    mods::{activator::{self, ModProfileSpec, ProfileActivator, ProfileMod}, analyzer::ModAnalyzer, manager::ModManager, profile_sync::ProfileSync, resolver::{ModManifest, ModResolver}, scanner::ModScanner},
+    java::{self, JavaManager, JavaRequirement},
+    snapshots::{SnapshotManager, SnapshotScheduler, SnapshotTrigger},
+    install::{self, CandidateSource, InstallationDetector},
+    client::{ApiClient, ServerSearch},
+    updates::UpdateManager,
+    hosting::{WorldHostConfig, WorldHostService},
+    integrity::Attestor,
+    preload::{AssetPrefetcher, HttpManifestSource, ManifestSource, PreloadManager},
+    pond::{PondClient, QueueClient},
+    performance::{self, readiness::{self, LaunchRequirements, ReadinessIssue, ReadinessIssueKind, ReadinessThresholds}},
+    netdiag::{PingMonitor, ServerTarget},
+    presence::PresenceFeed,
+    config::{validation, watcher::{ConfigEvent, ConfigWatcher}, AppConfig, SnapshotConfig},
+    health::{self, CheckFuture, ComponentHealth, HealthCheck, HealthTracker, HEALTH_CHECK_TIMEOUT},
+    util::verify::{self, VerifyProgress, VerifyReport},
 };
+use yellow_tale_core::FeatureManager as FeatureGateManager;
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, RwLock};
+
+/// IPC API version. Clients with the same major and an equal or older minor
+/// version are accepted.
+pub const IPC_VERSION: &str = "1.51.0";
+
+/// How long a command may run unless `with_command_timeout` says otherwise
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
 
-/// IPC API version
-pub const IPC_VERSION: &str = "1.0.0";
+/// Most requests one `batch` may carry
+const MAX_BATCH: usize = 64;
 
 #[derive(Error, Debug)]
 pub enum IpcError {
@@ -44,6 +77,9 @@ pub enum IpcError {
     
     #[error("Version mismatch: expected {expected}, got {actual}")]
     VersionMismatch { expected: String, actual: String },
+    
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
 }
 
 /// An IPC request from the UI
@@ -79,6 +115,14 @@ pub struct IpcResponse {
     
     /// Result data (if success is true)
     pub data: Option<serde_json::Value>,
+    
+    /// Set when the command is deprecated
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    
+    /// Command to use instead of a deprecated one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
 }
 
 impl IpcResponse {
@@ -90,6 +134,8 @@ impl IpcResponse {
             success: true,
             error: None,
             data: Some(data),
+            deprecated: false,
+            replacement: None,
         }
     }
     
@@ -101,6 +147,38 @@ impl IpcResponse {
             success: false,
             error: Some(error.into()),
             data: None,
+            deprecated: false,
+            replacement: None,
+        }
+    }
+    
+    /// Mark the response as coming from a deprecated command
+    pub fn with_deprecation(mut self, replacement: impl Into<String>) -> Self {
+        self.deprecated = true;
+        self.replacement = Some(replacement.into());
+        self
+    }
+}
+
+/// An event pushed to the UI without a matching request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpcEvent {
+    /// API version
+    pub version: String,
+    
+    /// Event name
+    pub event: String,
+    
+    /// Event payload
+    pub data: serde_json::Value,
+}
+
+impl IpcEvent {
+    pub fn new(event: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            version: IPC_VERSION.to_string(),
+            event: event.into(),
+            data,
         }
     }
 }
@@ -111,12 +189,20 @@ impl IpcResponse {
 pub enum Command {
     // System commands
     GetVersion,
+    GetCapabilities,
+    NegotiateVersion,
     GetStatus,
+    GetDatabaseStatus,
+    Batch,
     
     // Launcher commands
     LaunchGame,
     GetGameState,
     TerminateGame,
+    GetLaunchRecommendation,
+    GetCrashRecoverySuggestion,
+    GetLastExit,
+    ValidateLaunch,
     
     // Profile commands
     ListProfiles,
@@ -135,6 +221,8 @@ pub enum Command {
     // Cache commands
     GetCacheStats,
     ClearCache,
+    CachePrune,
+    VerifyCache,
     
     // Performance commands
     GetSystemSnapshot,
@@ -144,6 +232,8 @@ pub enum Command {
     CollectMetrics,
     GetDiagnosticsReport,
     ExportDiagnostics,
+    AnalyzePerformance,
+    AnalyzeFrameLog,
     
     // Session commands
     CreateSession,
@@ -151,12 +241,20 @@ pub enum Command {
     LeaveSession,
     GetSessionInfo,
     GetInviteCode,
+    DetectNat,
+    TransferHost,
     
     // User/Auth commands
     Signup,
     Login,
     Logout,
+    RefreshSession,
+    ChangePassword,
+    ListSessions,
+    RevokeSession,
     ValidateSession,
+    RequestEmailVerification,
+    ConfirmEmail,
     GetCurrentUser,
     UpdateUserProfile,
     SearchUsers,
@@ -169,6 +267,8 @@ pub enum Command {
     GetFriends,
     GetPendingRequests,
     GetOnlineFriends,
+    SubscribePresence,
+    PollEvents,
     BlockUser,
     UnblockUser,
     GetBlockedUsers,
@@ -177,65 +277,549 @@ pub enum Command {
     StartRelayServer,
     StopRelayServer,
     GetRelayStatus,
+    ResetRelayMetrics,
     ConnectToRelay,
     DisconnectFromRelay,
+    
+    // Settings sync commands
+    SyncNow,
+    GetSyncStatus,
+    UpdateSyncSection,
+    
+    // Mod profile commands
+    ActivateModProfile,
+    ScanMods,
+    DetectModConflicts,
+    OptimizeLoadOrder,
+    ListModProfiles,
+    SaveModProfile,
+    SyncProfiles,
+    GetProfileSyncStatus,
+    
+    // Game installation commands
+    DetectInstallations,
+    SetGamePath,
+    
+    // Java runtime commands
+    ListJavaRuntimes,
+    ProvisionJavaRuntime,
+    SetProfileJava,
+    SetJavaPath,
+    InstallManagedJava,
+    
+    // Snapshot commands
+    GetSnapshotSchedule,
+    SetSnapshotSchedule,
+    PruneSnapshots,
+    GetSnapshots,
+    CreateSnapshot,
+    RestoreSnapshot,
+    DeleteSnapshot,
+    
+    // Feature gate commands
+    RefreshFeatureGates,
+    GetFeatureState,
+    
+    // Launcher update commands
+    CheckForUpdates,
+    DownloadUpdate,
+    GetUpdateProgress,
+    
+    // World hosting commands
+    StartLocalServer,
+    StopLocalServer,
+    GetHostingStatus,
+    
+    // Notification commands
+    GetNotifications,
+    
+    // Config commands
+    ValidateConfig,
+    
+    // Attestation commands
+    GetAttestation,
+    AttestationHeartbeat,
+    
+    // Asset preload commands
+    PreloadServerAssets,
+    GetPreloadStatus,
+    DiscoverServerCapabilities,
+    PrefetchServerAssets,
+    GetPrefetchStatus,
+    JoinQueue,
+    GetQueueStatus,
+    LeaveQueue,
+    
+    // Ping measurement commands
+    PingServer,
+    GetPingHistory,
+    SetServerFavorite,
+    FavoriteServer,
+    UnfavoriteServer,
+    ListFavorites,
+    
+    // Server directory commands
+    SearchServers,
+}
+
+/// Checks relay join tokens against whichever database connection is
+/// current, rejecting every join while the database is down
+struct AccountJoinValidator(Arc<RwLock<Option<DatabaseServices>>>);
+
+#[async_trait::async_trait]
+impl JoinValidator for AccountJoinValidator {
+    async fn validate(&self, token: &str) -> Option<RelayIdentity> {
+        let services = self.0.read().await;
+        services.as_ref()?.users.validate(token).await
+    }
 }
 
 /// The IPC server handling UI communication
 pub struct IpcServer {
     launcher: LauncherService,
+    game_exits: broadcast::Receiver<GameExitReport>,
     profiles: ProfileManager,
     cache: CacheManager,
+    cache_verify: Arc<VerifyProgress>,
+    cache_verification: Option<tokio::task::JoinHandle<VerifyReport>>,
     sessions: SessionOrchestrator,
-    diagnostics: DiagnosticsCollector,
-    users: Option<UserService>,
-    friends: Option<FriendsService>,
+    diagnostics: Arc<Mutex<DiagnosticsCollector>>,
+    metrics_sampler: Option<tokio::task::JoinHandle<()>>,
+    services: Arc<RwLock<Option<DatabaseServices>>>,
+    database: Option<DatabaseSupervisor>,
+    events: broadcast::Sender<IpcEvent>,
+    /// Friends' status changes waiting for `poll_events`
+    presence_feed: Option<PresenceFeed>,
     relay: Arc<RwLock<RelayServer>>,
+    settings_sync: Option<SettingsSync>,
+    sync_server_url: Option<String>,
+    mod_activator: Option<ProfileActivator>,
+    /// Last mod profile activated, whose mods launches check for
+    active_mod_profile: Option<ModProfileSpec>,
+    /// Mods enabled before a safe-mode launch, put back once it ends
+    safe_mode_restore: Option<ModProfileSpec>,
+    mod_scanner: Option<ModScanner>,
+    profile_sync: Option<Arc<Mutex<ProfileSync>>>,
+    /// Token of the last `sync_profiles`, reused when a failed sync is retried
+    profile_sync_token: Option<String>,
+    profile_sync_retry: Option<tokio::task::JoinHandle<()>>,
+    mod_manager: Option<ModManager>,
+    java: Option<JavaManager>,
+    snapshots: Option<Arc<SnapshotScheduler>>,
+    snapshot_schedule: Option<tokio::task::JoinHandle<()>>,
+    feature_gates: Option<FeatureGateManager>,
+    feature_gates_url: Option<String>,
+    updates: Option<Arc<UpdateManager>>,
+    hosting: Option<WorldHostService>,
+    api_url: Option<String>,
+    config_path: Option<PathBuf>,
+    config_watcher: Option<ConfigWatcher>,
+    /// Reloads still to be applied to the cache and sessions
+    config_changes: Option<broadcast::Receiver<ConfigEvent>>,
+    integrity: Option<Attestor>,
+    preload: Option<Arc<PreloadManager>>,
+    prefetch: Option<Arc<AssetPrefetcher>>,
+    queue: Option<Arc<QueueClient>>,
+    ping_monitor: Option<Arc<PingMonitor>>,
+    health_checks: Vec<Arc<dyn HealthCheck>>,
+    health: HealthTracker,
+    /// How long a command may run before it's answered with a timeout error
+    command_timeout: Duration,
 }
 
 impl IpcServer {
-    /// Create a new IPC server
+    /// Create a new IPC server. Sessions are brokered through the server's
+    /// relay, so invite codes resolve against its session registry.
     pub fn new(
         launcher: LauncherService,
         profiles: ProfileManager,
         cache: CacheManager,
-        sessions: SessionOrchestrator,
+        mut sessions: SessionOrchestrator,
         diagnostics: DiagnosticsCollector,
     ) -> Self {
+        let relay = RelayServer::new();
+        sessions.set_broker(Arc::new(relay.broker()));
         Self {
+            game_exits: launcher.subscribe_exits(),
             launcher,
             profiles,
             cache,
+            cache_verify: Arc::new(VerifyProgress::new()),
+            cache_verification: None,
             sessions,
-            diagnostics,
-            users: None,
-            friends: None,
-            relay: Arc::new(RwLock::new(RelayServer::new())),
+            diagnostics: Arc::new(Mutex::new(diagnostics)),
+            metrics_sampler: None,
+            services: Arc::new(RwLock::new(None)),
+            database: None,
+            events: broadcast::channel(64).0,
+            presence_feed: None,
+            relay: Arc::new(RwLock::new(relay)),
+            settings_sync: None,
+            sync_server_url: None,
+            mod_activator: None,
+            active_mod_profile: None,
+            safe_mode_restore: None,
+            mod_scanner: None,
+            profile_sync: None,
+            profile_sync_token: None,
+            profile_sync_retry: None,
+            mod_manager: None,
+            java: None,
+            snapshots: None,
+            snapshot_schedule: None,
+            feature_gates: None,
+            feature_gates_url: None,
+            updates: None,
+            hosting: None,
+            api_url: None,
+            config_path: None,
+            config_watcher: None,
+            config_changes: None,
+            integrity: None,
+            preload: None,
+            prefetch: None,
+            queue: None,
+            ping_monitor: None,
+            health_checks: Vec::new(),
+            health: HealthTracker::new(),
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
         }
     }
     
-    pub fn with_services(
-        mut self,
-        users: Option<UserService>,
-        friends: Option<FriendsService>,
-    ) -> Self {
-        self.users = users;
-        self.friends = friends;
+    /// Use the supervisor's services and forward its status changes as events
+    pub fn with_database(mut self, database: DatabaseSupervisor) -> Self {
+        self.services = database.services();
+        
+        let mut status = database.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            while status.changed().await.is_ok() {
+                let current = *status.borrow_and_update();
+                let _ = events.send(IpcEvent::new(
+                    "database_status_changed",
+                    serde_json::json!({ "status": current }),
+                ));
+            }
+        });
+        
+        self.database = Some(database);
+        self
+    }
+    
+    /// Receive events pushed to the UI outside of request/response
+    pub fn subscribe_events(&self) -> broadcast::Receiver<IpcEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Turn a service error into a response, hiding query failures from the UI
+    fn service_error<E: QueryError + std::fmt::Display>(&self, id: Uuid, error: E) -> IpcResponse {
+        let Some(sqlx_error) = error.sqlx_error() else {
+            return IpcResponse::error(id, error.to_string());
+        };
+        
+        warn!("Database query failed: {}", sqlx_error);
+        if is_connection_error(sqlx_error) {
+            if let Some(database) = &self.database {
+                database.report_failure();
+            }
+            return IpcResponse::error(id, "Database temporarily unavailable");
+        }
+        IpcResponse::error(id, "Database error")
+    }
+    
+    pub fn with_settings_sync(mut self, sync: SettingsSync, server_url: impl Into<String>) -> Self {
+        self.settings_sync = Some(sync);
+        self.sync_server_url = Some(server_url.into());
+        self
+    }
+    
+    pub fn with_mod_activator(mut self, activator: ProfileActivator) -> Self {
+        self.mod_activator = Some(activator);
+        self
+    }
+    
+    pub fn with_mod_scanner(mut self, scanner: ModScanner) -> Self {
+        self.mod_scanner = Some(scanner);
+        self
+    }
+    
+    /// Mod profiles synced through the server set by `with_api_url`
+    pub fn with_profile_sync(mut self, sync: ProfileSync) -> Self {
+        self.profile_sync = Some(Arc::new(Mutex::new(sync)));
+        self
+    }
+    
+    pub fn with_mod_manager(mut self, manager: ModManager) -> Self {
+        self.mod_manager = Some(manager);
+        self
+    }
+    
+    pub fn with_java(mut self, java: JavaManager) -> Self {
+        self.java = Some(java);
+        self
+    }
+    
+    /// Take snapshots on `schedule`, holding them while the game runs if
+    /// it says so. The schedule stops when the server is dropped.
+    pub fn with_snapshots(mut self, manager: Arc<SnapshotManager>, schedule: &SnapshotConfig) -> Self {
+        let scheduler = Arc::new(SnapshotScheduler::new(manager, schedule).with_quiesce(Arc::new(self.launcher.clone())));
+        self.snapshot_schedule = Some(scheduler.spawn());
+        self.snapshots = Some(scheduler);
+        self
+    }
+    
+    pub fn with_feature_gates(mut self, gates: FeatureGateManager, server_url: impl Into<String>) -> Self {
+        self.feature_gates = Some(gates);
+        self.feature_gates_url = Some(server_url.into());
+        self
+    }
+    
+    pub fn with_updates(mut self, updates: UpdateManager) -> Self {
+        self.updates = Some(Arc::new(updates));
+        self
+    }
+    
+    /// Central server for commands that proxy its API
+    pub fn with_api_url(mut self, server_url: impl Into<String>) -> Self {
+        self.api_url = Some(server_url.into());
+        self
+    }
+    
+    /// Config file checked by `validate_config`
+    pub fn with_config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+    
+    /// Reload the config as it's edited, forwarding `config_changed` and
+    /// `config_invalid` events. The log level changes at once; the cache size
+    /// and relay servers before the next command.
+    pub fn with_config_watcher(mut self, watcher: ConfigWatcher) -> Self {
+        let mut changes = watcher.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match changes.recv().await {
+                    Ok(ConfigEvent::Changed(change)) => {
+                        if change.applied.iter().any(|field| field == "telemetry.log_level") {
+                            if let Err(e) = crate::core::telemetry::set_log_level(&change.config.telemetry.log_level) {
+                                warn!("Could not change the log level: {}", e);
+                            }
+                        }
+                        let data = serde_json::to_value(&change).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new("config_changed", data));
+                    }
+                    Ok(ConfigEvent::Invalid(report)) => {
+                        let data = serde_json::to_value(&report).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new("config_invalid", data));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Dropped {} config events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        self.config_changes = Some(watcher.subscribe());
+        self.config_watcher = Some(watcher);
+        self
+    }
+    
+    /// Sign attestations for Rubidium servers
+    pub fn with_integrity(mut self, attestor: Attestor) -> Self {
+        self.integrity = Some(attestor);
+        self
+    }
+    
+    /// Cache servers' assets before joining, forwarding progress as events
+    pub fn with_preload(mut self, preload: PreloadManager) -> Self {
+        let mut progress = preload.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match progress.recv().await {
+                    Ok(status) => {
+                        let data = serde_json::to_value(&status).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new("preload_progress", data));
+                    }
+                    // Only the latest status matters to the UI
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        self.preload = Some(Arc::new(preload));
+        self
+    }
+    
+    /// Fill the asset cache in the background, holding downloads while the
+    /// game runs
+    pub fn with_prefetch(mut self, prefetcher: AssetPrefetcher) -> Self {
+        self.prefetch = Some(Arc::new(prefetcher.with_quiesce(Arc::new(self.launcher.clone()))));
+        self
+    }
+    
+    /// Wait in full Pond servers' queues, forwarding position changes as
+    /// events
+    pub fn with_queue(mut self, queue: Arc<QueueClient>) -> Self {
+        let mut updates = queue.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match updates.recv().await {
+                    Ok(status) => {
+                        let data = serde_json::to_value(&status).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new("queue_updated", data));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        self.queue = Some(queue);
+        self
+    }
+    
+    /// Measure pings to servers, forwarding each measurement as an event
+    pub fn with_ping_monitor(mut self, monitor: Arc<PingMonitor>) -> Self {
+        let mut results = monitor.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match results.recv().await {
+                    Ok(result) => {
+                        let data = serde_json::to_value(&result).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new("ping_updated", data));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        self.ping_monitor = Some(monitor);
+        self
+    }
+    
+    /// Report on another component in `get_status`, such as a directory
+    /// that must stay writable
+    pub fn with_health_check(mut self, check: Arc<dyn HealthCheck>) -> Self {
+        self.health_checks.push(check);
+        self
+    }
+    
+    /// Host worlds locally, forwarding server output and restarts as events
+    pub fn with_hosting(mut self, hosting: WorldHostService) -> Self {
+        let mut host_events = hosting.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match host_events.recv().await {
+                    Ok(event) => {
+                        let data = serde_json::to_value(&event).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new(event.name(), data));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Dropped {} hosting events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        self.hosting = Some(hosting);
+        self
+    }
+    
+    /// Answer commands still running after `timeout` with an error, except
+    /// those the registry marks as long-running
+    pub fn with_command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+    
+    /// Sample metrics in the background at the collector's interval, so
+    /// reports cover the minutes before they were asked for. Sampling stops
+    /// when the server is dropped.
+    pub fn with_metrics_sampling(mut self) -> Self {
+        let diagnostics = self.diagnostics.clone();
+        self.metrics_sampler = Some(tokio::spawn(async move {
+            let interval = diagnostics.lock().await.sample_interval();
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                diagnostics.lock().await.collect_sample();
+            }
+        }));
         self
     }
     
     /// Handle an incoming IPC request
     pub async fn handle(&mut self, request: IpcRequest) -> IpcResponse {
-        // Version check
-        if request.version != IPC_VERSION {
-            return IpcResponse::error(
-                request.id,
-                format!("Version mismatch: expected {}, got {}", IPC_VERSION, request.version),
-            );
-        }
+        let Negotiated { spec, version } = match registry::negotiate(&request.version, &request.command) {
+            Ok(negotiated) => negotiated,
+            Err(e) => return IpcResponse::error(request.id, e.to_string()),
+        };
         
         info!("Handling IPC command: {}", request.command);
         
+        self.record_game_exits().await;
+        self.finish_cache_verification().await;
+        self.apply_config_changes().await;
+        self.apply_session_changes().await;
+        self.retry_profile_sync();
+        
+        let (id, command) = (request.id, request.command.clone());
+        let response = if command == "batch" {
+            self.handle_batch(request).await
+        } else if spec.long_running {
+            self.dispatch(request).await
+        } else {
+            let timeout = self.command_timeout;
+            match tokio::time::timeout(timeout, self.dispatch(request)).await {
+                Ok(response) => response,
+                Err(_) => {
+                    warn!("IPC command {} timed out after {:?}", command, timeout);
+                    IpcResponse::error(id, format!("{} timed out after {}s", command, timeout.as_secs_f64()))
+                }
+            }
+        };
+        let response = IpcResponse { version: version.to_string(), ..response };
+        match spec.replacement {
+            Some(replacement) => response.with_deprecation(replacement),
+            None => response,
+        }
+    }
+    
+    /// Run each embedded request in order, answering with their responses
+    /// in the same order. A failing request doesn't stop the rest.
+    async fn handle_batch(&mut self, request: IpcRequest) -> IpcResponse {
+        let requests: Vec<IpcRequest> = match request.params.get("requests").cloned().map(serde_json::from_value) {
+            Some(Ok(requests)) => requests,
+            Some(Err(e)) => return IpcResponse::error(request.id, format!("Invalid batch request: {}", e)),
+            None => return IpcResponse::error(request.id, "Missing requests"),
+        };
+        if requests.len() > MAX_BATCH {
+            return IpcResponse::error(request.id, format!("A batch holds at most {} requests", MAX_BATCH));
+        }
+        
+        let mut responses = Vec::with_capacity(requests.len());
+        for embedded in requests {
+            let response = if embedded.command == "batch" {
+                IpcResponse::error(embedded.id, "Batches can't be nested")
+            } else {
+                Box::pin(self.handle(embedded)).await
+            };
+            responses.push(response);
+        }
+        IpcResponse::success(request.id, serde_json::json!({ "responses": responses }))
+    }
+    
+    async fn dispatch(&mut self, request: IpcRequest) -> IpcResponse {
         match request.command.as_str() {
             // System commands
             "get_version" => {
@@ -245,35 +829,98 @@ impl IpcServer {
                 }))
             }
             
+            "get_capabilities" => IpcResponse::success(request.id, registry::capabilities()),
+            
+            "negotiate_version" => {
+                let client = request.version.parse().unwrap_or_else(|_| IpcVersion::current());
+                IpcResponse::success(request.id, registry::version_negotiation(client))
+            }
+            
             "get_status" => {
                 let game_state = self.launcher.get_state().await;
                 let session = self.sessions.current_session();
+                let components = self.component_health().await;
                 
                 IpcResponse::success(request.id, serde_json::json!({
                     "game_state": game_state,
                     "in_session": session.is_some(),
                     "session_id": session.map(|s| s.id.to_string()),
+                    "overall": health::overall(&components),
+                    "components": components,
                 }))
             }
             
+            "get_database_status" => {
+                let status = self.database.as_ref().map_or(DatabaseStatus::Offline, |db| db.status());
+                IpcResponse::success(request.id, serde_json::json!({ "status": status }))
+            }
+            
             // Launcher commands
             "launch_game" => {
-                match serde_json::from_value::<crate::core::launcher::LaunchConfig>(request.params.clone()) {
-                    Ok(config) => {
-                        match self.launcher.launch(config).await {
-                            Ok(pid) => IpcResponse::success(request.id, serde_json::json!({ "pid": pid })),
-                            Err(e) => IpcResponse::error(request.id, e.to_string()),
-                        }
+                let mut config = match self.launch_config(&request.params) {
+                    Ok(config) => config,
+                    Err(e) => return IpcResponse::error(request.id, e),
+                };
+                
+                // Computed before launching so it reflects how the previous run ended
+                let recommendation = self.launcher.launch_recommendation(config.profile_id.as_deref()).await;
+                let safe_mode = if config.safe_mode {
+                    Some(self.prepare_safe_mode(&mut config).await)
+                } else {
+                    // A normal launch wants the mods back even if the
+                    // safe-mode run's exit hasn't been seen yet
+                    self.restore_after_safe_mode().await;
+                    None
+                };
+                
+                match self.launcher.launch(config).await {
+                    Ok(pid) => {
+                        self.diagnostics.lock().await.track_process(pid);
+                        IpcResponse::success(request.id, serde_json::json!({
+                            "pid": pid,
+                            "recommendation": recommendation,
+                            "safe_mode": safe_mode,
+                        }))
+                    }
+                    Err(e) => {
+                        self.restore_after_safe_mode().await;
+                        IpcResponse::error(request.id, e.to_string())
                     }
-                    Err(e) => IpcResponse::error(request.id, format!("Invalid launch config: {}", e)),
                 }
             }
             
+            "validate_launch" => {
+                let config = match self.launch_config(&request.params) {
+                    Ok(config) => config,
+                    Err(e) => return IpcResponse::error(request.id, e),
+                };
+                let problems = match config.validate().await {
+                    Ok(()) => Vec::new(),
+                    Err(e) => e.problems,
+                };
+                IpcResponse::success(request.id, serde_json::json!({
+                    "valid": problems.is_empty(),
+                    "problems": problems,
+                }))
+            }
+            
             "get_game_state" => {
                 let state = self.launcher.get_state().await;
                 IpcResponse::success(request.id, serde_json::to_value(state).unwrap_or_default())
             }
             
+            "get_launch_recommendation" => {
+                let profile_id = request.params.get("profile_id").and_then(|v| v.as_str());
+                let recommendation = self.launcher.launch_recommendation(profile_id).await;
+                IpcResponse::success(request.id, serde_json::to_value(recommendation).unwrap_or_default())
+            }
+            
+            "get_crash_recovery_suggestion" => {
+                let profile_id = request.params.get("profile_id").and_then(|v| v.as_str());
+                let suggestion = self.launcher.crash_recovery_suggestion(profile_id).await;
+                IpcResponse::success(request.id, serde_json::to_value(suggestion).unwrap_or_default())
+            }
+            
             "terminate_game" => {
                 match self.launcher.terminate().await {
                     Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "terminated": true })),
@@ -281,6 +928,16 @@ impl IpcServer {
                 }
             }
             
+            "get_last_exit" => {
+                match self.launcher.last_exit().await {
+                    Some(report) => IpcResponse::success(
+                        request.id,
+                        serde_json::to_value(LastExit::from(&report)).unwrap_or_default()
+                    ),
+                    None => IpcResponse::error(request.id, "No game has exited yet"),
+                }
+            }
+            
             // Profile commands
             "list_profiles" => {
                 let profiles: Vec<_> = self.profiles.list().iter().map(|p| {
@@ -322,146 +979,595 @@ impl IpcServer {
                 }
             }
             
-            // Cache commands
-            "get_cache_stats" => {
-                let stats = self.cache.stats();
-                IpcResponse::success(request.id, serde_json::to_value(stats).unwrap_or_default())
-            }
-            
-            "clear_cache" => {
-                match self.cache.clear().await {
-                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "cleared": true })),
-                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+            "update_profile" => {
+                let Some(id) = request.params.get("id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok()) else {
+                    return IpcResponse::error(request.id, "Missing or invalid 'id' parameter");
+                };
+                if self.profiles.get(&id).is_none() {
+                    return IpcResponse::error(request.id, "Profile not found");
                 }
-            }
-            
-            // Diagnostics commands
-            "collect_metrics" => {
-                let sample = self.diagnostics.collect_sample();
-                IpcResponse::success(request.id, serde_json::to_value(sample).unwrap_or_default())
-            }
-            
-            "get_diagnostics_report" => {
-                let report = self.diagnostics.generate_report();
-                IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
-            }
-            
-            // Session commands
-            "create_session" => {
-                let name = request.params.get("name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Host")
-                    .to_string();
-                let max = request.params.get("max_participants")
-                    .and_then(|v| v.as_u64())
-                    .unwrap_or(8) as usize;
-                
-                match self.sessions.create_session(name, max).await {
-                    Ok(session) => IpcResponse::success(
+                let name = request.params.get("name").and_then(|v| v.as_str());
+                if let Some(name) = name {
+                    let taken = self.profiles.list().iter()
+                        .any(|p| p.id != id && p.name.eq_ignore_ascii_case(name));
+                    if taken {
+                        return IpcResponse::error(request.id, format!("A profile named '{}' already exists", name));
+                    }
+                }
+                let settings = request.params.get("settings").cloned();
+                match self.profiles.update(&id, name, settings).await {
+                    Ok(profile) => IpcResponse::success(
                         request.id,
-                        serde_json::json!({
-                            "session_id": session.id.to_string(),
-                            "invite_code": session.invite_code,
-                        })
+                        serde_json::to_value(&profile).unwrap_or_default()
                     ),
                     Err(e) => IpcResponse::error(request.id, e.to_string()),
                 }
             }
             
-            "get_invite_code" => {
-                match self.sessions.get_invite_code() {
-                    Some(code) => IpcResponse::success(request.id, serde_json::json!({ "invite_code": code })),
-                    None => IpcResponse::error(request.id, "Not in a session"),
+            "delete_profile" => {
+                let Some(id) = request.params.get("id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok()) else {
+                    return IpcResponse::error(request.id, "Missing or invalid 'id' parameter");
+                };
+                if self.profiles.get(&id).is_none() {
+                    return IpcResponse::error(request.id, "Profile not found");
                 }
-            }
-            
-            "leave_session" => {
-                match self.sessions.leave_session().await {
-                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "left": true })),
+                if self.launcher.active_profile().await == Some(id.to_string()) {
+                    return IpcResponse::error(request.id, "Profile is in use by the running game");
+                }
+                match self.profiles.delete(&id).await {
+                    Ok(()) => IpcResponse::success(request.id, serde_json::json!({ "id": id, "deleted": true })),
                     Err(e) => IpcResponse::error(request.id, e.to_string()),
                 }
             }
             
-            // User/Auth commands
-            "signup" => {
-                let Some(ref users) = self.users else {
-                    return IpcResponse::error(request.id, "Database not available");
+            // Mod commands
+            "list_mods" => {
+                let Some(manager) = &self.mod_manager else {
+                    return IpcResponse::error(request.id, "Mod management not available");
                 };
-                match serde_json::from_value::<SignupRequest>(request.params.clone()) {
-                    Ok(req) => match users.signup(req).await {
-                        Ok(auth) => IpcResponse::success(request.id, serde_json::json!({
-                            "user": auth.user,
-                            "session": { "token": auth.session.token, "expires_at": auth.session.expires_at }
-                        })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
-                    },
-                    Err(e) => IpcResponse::error(request.id, format!("Invalid signup request: {}", e)),
+                match manager.list().await {
+                    Ok(mods) => IpcResponse::success(request.id, serde_json::json!({ "mods": mods })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
                 }
             }
             
-            "login" => {
-                let Some(ref users) = self.users else {
-                    return IpcResponse::error(request.id, "Database not available");
+            "install_mod" => {
+                let Some(manager) = &self.mod_manager else {
+                    return IpcResponse::error(request.id, "Mod management not available");
                 };
-                match serde_json::from_value::<LoginRequest>(request.params.clone()) {
-                    Ok(req) => match users.login(req).await {
-                        Ok(auth) => IpcResponse::success(request.id, serde_json::json!({
-                            "user": auth.user,
-                            "session": { "token": auth.session.token, "expires_at": auth.session.expires_at }
-                        })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
-                    },
-                    Err(e) => IpcResponse::error(request.id, format!("Invalid login request: {}", e)),
+                let Some(path) = request.params.get("path").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'path' parameter");
+                };
+                match manager.install(std::path::Path::new(path)).await {
+                    Ok(installed) => IpcResponse::success(request.id, serde_json::json!({ "mod": installed })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
                 }
             }
             
-            "logout" => {
-                let Some(ref users) = self.users else {
-                    return IpcResponse::error(request.id, "Database not available");
+            "enable_mod" | "disable_mod" => {
+                let Some(manager) = &self.mod_manager else {
+                    return IpcResponse::error(request.id, "Mod management not available");
                 };
-                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
-                match users.logout(token).await {
-                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "logged_out": true })),
+                let Some(mod_id) = request.params.get("mod_id").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'mod_id' parameter");
+                };
+                let result = if request.command == "enable_mod" {
+                    manager.enable(mod_id).await
+                } else {
+                    manager.disable(mod_id).await
+                };
+                match result {
+                    Ok(changed) => IpcResponse::success(request.id, serde_json::json!({ "mod": changed })),
                     Err(e) => IpcResponse::error(request.id, e.to_string()),
                 }
             }
             
-            "validate_session" => {
-                let Some(ref users) = self.users else {
+            "remove_mod" => {
+                let Some(manager) = &self.mod_manager else {
+                    return IpcResponse::error(request.id, "Mod management not available");
+                };
+                let Some(mod_id) = request.params.get("mod_id").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'mod_id' parameter");
+                };
+                // Without confirmation, say what would be deleted
+                let result = if request.params.get("confirm").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    manager.remove(mod_id).await.map(|removed| (removed, true))
+                } else {
+                    manager.find(mod_id).await.map(|found| (found, false))
+                };
+                match result {
+                    Ok((found, removed)) => IpcResponse::success(request.id, serde_json::json!({
+                        "mod": found,
+                        "removed": removed,
+                        "confirm_required": !removed,
+                    })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Cache commands
+            "get_cache_stats" => {
+                let mut stats = serde_json::to_value(self.cache.stats()).unwrap_or_default();
+                stats["verification"] = serde_json::to_value(self.cache_verify.status()).unwrap_or_default();
+                IpcResponse::success(request.id, stats)
+            }
+            
+            "clear_cache" => {
+                match self.cache.clear().await {
+                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "cleared": true })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "verify_cache" => {
+                let targets = self.cache.verify_targets();
+                let total = targets.len();
+                if !self.cache_verify.start(total) {
+                    return IpcResponse::error(request.id, "Cache verification already running");
+                }
+                let progress = self.cache_verify.clone();
+                let events = self.events.clone();
+                self.cache_verification = Some(tokio::spawn(async move {
+                    let report = verify::verify_files(targets, &progress).await;
+                    let _ = events.send(IpcEvent::new("cache_verified", serde_json::to_value(&report).unwrap_or_default()));
+                    report
+                }));
+                IpcResponse::success(request.id, serde_json::json!({ "started": true, "total": total }))
+            }
+            
+            "cache_prune" => {
+                match self.cache.prune().await {
+                    Ok(evicted) => IpcResponse::success(request.id, serde_json::to_value(evicted).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Performance commands
+            "get_system_snapshot" => {
+                let game_path = self.game_path(&request.params).await;
+                let snapshot = self.diagnostics.lock().await.system_snapshot(game_path.as_deref());
+                IpcResponse::success(request.id, serde_json::to_value(snapshot).unwrap_or_default())
+            }
+            
+            "prepare_for_launch" => {
+                let mut requirements = LaunchRequirements::default();
+                if let Some(id) = request.params.get("profile_id").and_then(|v| v.as_str()) {
+                    let Some(profile) = Uuid::parse_str(id).ok().and_then(|id| self.profiles.get(&id)) else {
+                        return IpcResponse::error(request.id, "Profile not found");
+                    };
+                    requirements = LaunchRequirements::from_profile(&serde_json::to_value(profile).unwrap_or_default());
+                }
+                if let Some(ram_mb) = request.params.get("ram_allocation_mb").and_then(|v| v.as_u64()) {
+                    requirements.ram_allocation_mb = Some(ram_mb);
+                }
+                
+                // Drop expired assets, then read ahead what's left so the
+                // game's first loads come from memory
+                let mut cache_issue = None;
+                let pruned_bytes = match self.cache.prune().await {
+                    Ok(evicted) => evicted.bytes,
+                    Err(e) => {
+                        cache_issue = Some(ReadinessIssue::new(ReadinessIssueKind::CacheUnavailable, format!("Could not prune the cache: {}", e)));
+                        0
+                    }
+                };
+                let cached: Vec<PathBuf> = self.cache.verify_targets().into_iter().map(|target| target.path).collect();
+                let warmed = performance::warm_files(&cached, readiness::CACHE_WARM_BUDGET_BYTES).await;
+                
+                let game_path = self.game_path(&request.params).await;
+                let snapshot = self.diagnostics.lock().await.system_snapshot(game_path.as_deref());
+                let mut readiness = readiness::assess(snapshot, &requirements, &ReadinessThresholds::default());
+                readiness.cache = readiness::CachePreparation {
+                    pruned_bytes,
+                    warmed_files: warmed.files,
+                    warmed_bytes: warmed.bytes,
+                };
+                if let Some(issue) = cache_issue {
+                    readiness.warn(issue);
+                }
+                IpcResponse::success(request.id, serde_json::to_value(readiness).unwrap_or_default())
+            }
+            
+            // Diagnostics commands
+            "collect_metrics" => {
+                let sample = self.diagnostics.lock().await.collect_sample();
+                IpcResponse::success(request.id, serde_json::to_value(sample).unwrap_or_default())
+            }
+            
+            "get_diagnostics_report" => {
+                let report = self.diagnostics.lock().await.generate_report();
+                IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+            }
+            
+            "export_diagnostics" => {
+                let Some(path) = request.params.get("path").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'path' parameter");
+                };
+                let compress = request.params.get("compress").and_then(|v| v.as_bool()).unwrap_or(false);
+                let config = match &self.config_path {
+                    Some(config_path) => match AppConfig::load(config_path).await {
+                        Ok((config, _)) => serde_json::to_value(config).ok(),
+                        Err(e) => {
+                            warn!("Exporting diagnostics without the config: {}", e);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let path = PathBuf::from(path);
+                match self.diagnostics.lock().await.export_report(&path, config, compress).await {
+                    Ok(bytes) => IpcResponse::success(request.id, serde_json::json!({
+                        "path": path,
+                        "bytes": bytes,
+                        "compressed": compress,
+                    })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "analyze_frame_log" => {
+                let Some(path) = request.params.get("path").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'path' parameter");
+                };
+                match FramePacingAnalyzer::new().analyze_file(std::path::Path::new(path)).await {
+                    Ok(report) => {
+                        self.diagnostics.lock().await.record_frame_pacing(report.clone());
+                        IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+                    }
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "analyze_performance" => {
+                if request.params.get("cpu_affinity").is_some() || request.params.get("max_heap_mb").is_some() {
+                    let cpu_affinity = match request.params.get("cpu_affinity") {
+                        Some(cores) => match serde_json::from_value::<Vec<usize>>(cores.clone()) {
+                            Ok(cores) => cores,
+                            Err(_) => return IpcResponse::error(request.id, "'cpu_affinity' must be a list of core indexes"),
+                        },
+                        None => Vec::new(),
+                    };
+                    let max_heap_mb = request.params.get("max_heap_mb").and_then(|v| v.as_u64());
+                    self.diagnostics.lock().await.set_launch_settings(cpu_affinity, max_heap_mb);
+                }
+                let findings = self.diagnostics.lock().await.analyze();
+                IpcResponse::success(request.id, serde_json::json!({ "findings": findings }))
+            }
+            
+            // Session commands
+            "create_session" => {
+                let name = request.params.get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Host")
+                    .to_string();
+                let max = request.params.get("max_participants")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(8) as usize;
+                let password = request.params.get("password").and_then(|v| v.as_str());
+                
+                match self.sessions.create_session(name, max, password).await {
+                    Ok(session) => IpcResponse::success(
+                        request.id,
+                        serde_json::json!({
+                            "session_id": session.id.to_string(),
+                            "invite_code": session.invite_code,
+                        })
+                    ),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "join_session" => {
+                let Some(invite_code) = request.params.get("invite_code").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'invite_code' parameter");
+                };
+                let name = request.params.get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Player")
+                    .to_string();
+                
+                // Joining a server waits for its required assets; missing
+                // streamable ones only produce warnings
+                let mut warnings = Vec::new();
+                if let Some(server) = request.params.get("server").and_then(|v| v.as_str()) {
+                    let Some(preload) = &self.preload else {
+                        return IpcResponse::error(request.id, "Asset preloading not available");
+                    };
+                    match preload.join_warnings(server) {
+                        Ok(found) => warnings = found,
+                        Err(e) => {
+                            if !preload.is_running() {
+                                let preload = preload.clone();
+                                let server = server.to_string();
+                                tokio::spawn(async move {
+                                    let _ = preload.preload(&server).await;
+                                });
+                            }
+                            return IpcResponse::error(request.id, e.to_string());
+                        }
+                    }
+                }
+                
+                let password = request.params.get("password").and_then(|v| v.as_str());
+                match self.sessions.join_session(invite_code, name, password).await {
+                    Ok(session) => IpcResponse::success(
+                        request.id,
+                        serde_json::json!({
+                            "session_id": session.id.to_string(),
+                            "invite_code": session.invite_code,
+                            "warnings": warnings,
+                        })
+                    ),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "get_session_info" => {
+                if let Some(session_id) = request.params.get("session_id").and_then(|v| v.as_str()) {
+                    return match self.relay.read().await.get_session_info(session_id).await {
+                        Some(info) => IpcResponse::success(request.id, serde_json::to_value(info).unwrap_or_default()),
+                        None => IpcResponse::error(request.id, SessionError::NotFound(session_id.to_string()).to_string()),
+                    };
+                }
+                match self.sessions.current_session() {
+                    Some(session) => IpcResponse::success(request.id, serde_json::to_value(session).unwrap_or_default()),
+                    None => IpcResponse::error(request.id, SessionError::NotInSession.to_string()),
+                }
+            }
+            
+            "get_invite_code" => {
+                match self.sessions.get_invite_code() {
+                    Some(code) => IpcResponse::success(request.id, serde_json::json!({ "invite_code": code })),
+                    None => IpcResponse::error(request.id, "Not in a session"),
+                }
+            }
+            
+            "leave_session" => {
+                match self.sessions.leave_session().await {
+                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "left": true })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "transfer_host" => {
+                let Some(participant_id) = request.params.get("participant_id")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| Uuid::parse_str(s).ok()) else {
+                    return IpcResponse::error(request.id, "Missing or invalid 'participant_id' parameter");
+                };
+                match self.sessions.transfer_host(participant_id) {
+                    Ok(()) => IpcResponse::success(request.id, serde_json::json!({ "requested": true })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "detect_nat" => {
+                let report = self.sessions.detect_nat().await;
+                IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+            }
+            
+            // User/Auth commands
+            "signup" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                match serde_json::from_value::<SignupRequest>(request.params.clone()) {
+                    Ok(req) => match users.signup(req).await {
+                        Ok(auth) => IpcResponse::success(request.id, auth_data(auth)),
+                        Err(e) => self.service_error(request.id, e),
+                    },
+                    Err(e) => IpcResponse::error(request.id, format!("Invalid signup request: {}", e)),
+                }
+            }
+            
+            "login" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                match serde_json::from_value::<LoginRequest>(request.params.clone()) {
+                    Ok(req) => match users.login(req).await {
+                        Ok(auth) => IpcResponse::success(request.id, auth_data(auth)),
+                        Err(e) => self.service_error(request.id, e),
+                    },
+                    Err(e) => IpcResponse::error(request.id, format!("Invalid login request: {}", e)),
+                }
+            }
+            
+            "logout" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
+                match users.logout(token).await {
+                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "logged_out": true })),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "refresh_session" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let Some(refresh_token) = request.params.get("refresh_token").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'refresh_token' parameter");
+                };
+                match users.refresh_session(refresh_token).await {
+                    Ok(auth) => IpcResponse::success(request.id, auth_data(auth)),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "change_password" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
+                let (Some(old_password), Some(new_password)) = (
+                    request.params.get("old_password").and_then(|v| v.as_str()),
+                    request.params.get("new_password").and_then(|v| v.as_str()),
+                ) else {
+                    return IpcResponse::error(request.id, "Missing 'old_password' or 'new_password' parameter");
+                };
+                let (session_id, user) = match users.authenticate(token).await {
+                    Ok(authenticated) => authenticated,
+                    Err(e) => return self.service_error(request.id, e),
+                };
+                match users.change_password(user.id, session_id, old_password, new_password).await {
+                    Ok(revoked) => IpcResponse::success(request.id, serde_json::json!({ "changed": true, "sessions_revoked": revoked })),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "list_sessions" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
+                let (session_id, user) = match users.authenticate(token).await {
+                    Ok(authenticated) => authenticated,
+                    Err(e) => return self.service_error(request.id, e),
+                };
+                match users.list_sessions(user.id).await {
+                    Ok(sessions) => IpcResponse::success(request.id, serde_json::json!({
+                        "sessions": sessions,
+                        "current_session_id": session_id,
+                    })),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            // Without a session_id, signs out everywhere but the caller's session
+            "revoke_session" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
+                let target = match request.params.get("session_id").and_then(|v| v.as_str()) {
+                    Some(id) => match Uuid::parse_str(id) {
+                        Ok(id) => Some(id),
+                        Err(_) => return IpcResponse::error(request.id, "Invalid session ID"),
+                    },
+                    None => None,
+                };
+                let (session_id, user) = match users.authenticate(token).await {
+                    Ok(authenticated) => authenticated,
+                    Err(e) => return self.service_error(request.id, e),
+                };
+                let revoked = match target {
+                    Some(target) => users.revoke_session(user.id, target).await.map(|_| 1),
+                    None => users.revoke_other_sessions(user.id, session_id).await,
+                };
+                match revoked {
+                    Ok(revoked) => IpcResponse::success(request.id, serde_json::json!({ "sessions_revoked": revoked })),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "validate_session" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
                 match users.validate_session(token).await {
                     Ok(user) => IpcResponse::success(request.id, serde_json::to_value(user).unwrap_or_default()),
-                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "request_email_verification" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
+                let user = match users.validate_session(token).await {
+                    Ok(user) => user,
+                    Err(e) => return self.service_error(request.id, e),
+                };
+                match users.request_email_verification(user.id).await {
+                    Ok(expires_at) => IpcResponse::success(request.id, serde_json::json!({ "sent": true, "expires_at": expires_at })),
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "confirm_email" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
+                let Some(code) = request.params.get("code").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'code' parameter");
+                };
+                let user = match users.validate_session(token).await {
+                    Ok(user) => user,
+                    Err(e) => return self.service_error(request.id, e),
+                };
+                match users.confirm_email(user.id, code).await {
+                    Ok(()) => IpcResponse::success(request.id, serde_json::json!({ "email_verified": true })),
+                    Err(e) => self.service_error(request.id, e),
                 }
             }
             
             "search_users" => {
-                let Some(ref users) = self.users else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let query = request.params.get("query").and_then(|v| v.as_str()).unwrap_or("");
-                let limit = request.params.get("limit").and_then(|v| v.as_i64()).unwrap_or(20);
-                match users.search_users(query, limit).await {
-                    Ok(results) => IpcResponse::success(request.id, serde_json::json!({ "users": results })),
-                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                let limit = request.params.get("limit").and_then(|v| v.as_u64()).unwrap_or(20).clamp(1, 50) as usize;
+                let cursor = match request.params.get("cursor").and_then(|v| v.as_str()) {
+                    Some(cursor) => match SearchCursor::decode(cursor) {
+                        Some(cursor) => Some(cursor),
+                        None => return IpcResponse::error(request.id, "Invalid cursor"),
+                    },
+                    None => None,
+                };
+                
+                // Signed-in searches never show anyone with a block either way
+                let blocked = match request.params.get("token").and_then(|v| v.as_str()) {
+                    Some(token) => {
+                        let user = match users.validate_session(token).await {
+                            Ok(user) => user,
+                            Err(e) => return self.service_error(request.id, e),
+                        };
+                        match friends.block_set(user.id).await {
+                            Ok(blocked) => blocked,
+                            Err(e) => return self.service_error(request.id, e),
+                        }
+                    }
+                    None => HashSet::new(),
+                };
+                
+                match users.search_users(query, &blocked, cursor.as_ref(), limit).await {
+                    Ok(page) => IpcResponse::success(request.id, serde_json::to_value(page).unwrap_or_default()),
+                    Err(e) => self.service_error(request.id, e),
                 }
             }
             
             "get_current_user" => {
-                let Some(ref users) = self.users else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let token = request.params.get("token").and_then(|v| v.as_str()).unwrap_or("");
                 match users.validate_session(token).await {
                     Ok(user) => IpcResponse::success(request.id, serde_json::to_value(user).unwrap_or_default()),
-                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                    Err(e) => self.service_error(request.id, e),
                 }
             }
             
             "update_user_profile" => {
-                let Some(ref users) = self.users else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { users, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -471,7 +1577,7 @@ impl IpcServer {
                 match user_id {
                     Some(id) => match users.update_profile(id, display_name, avatar_url).await {
                         Ok(user) => IpcResponse::success(request.id, serde_json::to_value(user).unwrap_or_default()),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     None => IpcResponse::error(request.id, "Invalid user ID"),
                 }
@@ -479,24 +1585,27 @@ impl IpcServer {
             
             // Friends commands
             "send_friend_request" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let from_id = request.params.get("from_user_id").and_then(|v| v.as_str())
                     .and_then(|s| Uuid::parse_str(s).ok());
                 let to_id = request.params.get("to_user_id").and_then(|v| v.as_str())
                     .and_then(|s| Uuid::parse_str(s).ok());
+                let message = request.params.get("message").and_then(|v| v.as_str());
                 match (from_id, to_id) {
-                    (Some(from), Some(to)) => match friends.send_friend_request(from, to).await {
+                    (Some(from), Some(to)) => match friends.send_friend_request(from, to, message).await {
                         Ok(id) => IpcResponse::success(request.id, serde_json::json!({ "request_id": id })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     _ => IpcResponse::error(request.id, "Invalid user IDs"),
                 }
             }
             
             "accept_friend_request" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -506,14 +1615,15 @@ impl IpcServer {
                 match (user_id, from_id) {
                     (Some(user), Some(from)) => match friends.accept_friend_request(user, from).await {
                         Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "accepted": true })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     _ => IpcResponse::error(request.id, "Invalid user IDs"),
                 }
             }
             
             "decline_friend_request" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -523,14 +1633,15 @@ impl IpcServer {
                 match (user_id, from_id) {
                     (Some(user), Some(from)) => match friends.decline_friend_request(user, from).await {
                         Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "declined": true })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     _ => IpcResponse::error(request.id, "Invalid user IDs"),
                 }
             }
             
             "remove_friend" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -540,14 +1651,15 @@ impl IpcServer {
                 match (user_id, friend_id) {
                     (Some(user), Some(friend)) => match friends.remove_friend(user, friend).await {
                         Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "removed": true })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     _ => IpcResponse::error(request.id, "Invalid user IDs"),
                 }
             }
             
             "get_friends" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -555,14 +1667,15 @@ impl IpcServer {
                 match user_id {
                     Some(id) => match friends.get_friends(id).await {
                         Ok(list) => IpcResponse::success(request.id, serde_json::json!({ "friends": list })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     None => IpcResponse::error(request.id, "Invalid user ID"),
                 }
             }
             
             "get_pending_requests" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -570,14 +1683,15 @@ impl IpcServer {
                 match user_id {
                     Some(id) => match friends.get_pending_requests(id).await {
                         Ok(list) => IpcResponse::success(request.id, serde_json::json!({ "requests": list })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     None => IpcResponse::error(request.id, "Invalid user ID"),
                 }
             }
             
             "get_online_friends" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -585,14 +1699,46 @@ impl IpcServer {
                 match user_id {
                     Some(id) => match friends.get_online_friends(id).await {
                         Ok(list) => IpcResponse::success(request.id, serde_json::json!({ "friends": list })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     None => IpcResponse::error(request.id, "Invalid user ID"),
                 }
             }
             
+            "subscribe_presence" => {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, presence, .. }) = services.as_ref() else {
+                    return IpcResponse::error(request.id, "Database not available");
+                };
+                let user_id = request.params.get("user_id").and_then(|v| v.as_str())
+                    .and_then(|s| Uuid::parse_str(s).ok());
+                let Some(user_id) = user_id else {
+                    return IpcResponse::error(request.id, "Invalid user ID");
+                };
+                match friends.subscribe_presence(user_id, presence).await {
+                    Ok(feed) => {
+                        drop(services);
+                        // Replacing the feed stops the previous one
+                        self.presence_feed = Some(feed);
+                        IpcResponse::success(request.id, serde_json::json!({ "subscribed": true }))
+                    }
+                    Err(e) => self.service_error(request.id, e),
+                }
+            }
+            
+            "poll_events" => {
+                let events: Vec<IpcEvent> = self.presence_feed.as_ref()
+                    .map(|feed| feed.drain())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|change| IpcEvent::new("friend_presence_changed", serde_json::to_value(change).unwrap_or_default()))
+                    .collect();
+                IpcResponse::success(request.id, serde_json::json!({ "events": events }))
+            }
+            
             "block_user" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let blocker_id = request.params.get("blocker_id").and_then(|v| v.as_str())
@@ -603,14 +1749,15 @@ impl IpcServer {
                 match (blocker_id, blocked_id) {
                     (Some(blocker), Some(blocked)) => match friends.block_user(blocker, blocked, reason).await {
                         Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "blocked": true })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     _ => IpcResponse::error(request.id, "Invalid user IDs"),
                 }
             }
             
             "unblock_user" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let blocker_id = request.params.get("blocker_id").and_then(|v| v.as_str())
@@ -620,14 +1767,15 @@ impl IpcServer {
                 match (blocker_id, blocked_id) {
                     (Some(blocker), Some(blocked)) => match friends.unblock_user(blocker, blocked).await {
                         Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "unblocked": true })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     _ => IpcResponse::error(request.id, "Invalid user IDs"),
                 }
             }
             
             "get_blocked_users" => {
-                let Some(ref friends) = self.friends else {
+                let services = self.services.read().await;
+                let Some(DatabaseServices { friends, .. }) = services.as_ref() else {
                     return IpcResponse::error(request.id, "Database not available");
                 };
                 let user_id = request.params.get("user_id").and_then(|v| v.as_str())
@@ -635,7 +1783,7 @@ impl IpcServer {
                 match user_id {
                     Some(id) => match friends.get_blocked_users(id).await {
                         Ok(list) => IpcResponse::success(request.id, serde_json::json!({ "blocked": list })),
-                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                        Err(e) => self.service_error(request.id, e),
                     },
                     None => IpcResponse::error(request.id, "Invalid user ID"),
                 }
@@ -644,9 +1792,30 @@ impl IpcServer {
             // Relay commands
             "start_relay_server" => {
                 let addr = request.params.get("address").and_then(|v| v.as_str()).unwrap_or("0.0.0.0:9000");
+                let auth_required = request.params.get("auth_required").and_then(|v| v.as_bool()).unwrap_or(false);
+                if auth_required && self.services.read().await.is_none() {
+                    return IpcResponse::error(request.id, "Database not available");
+                }
+                
+                // Limits left out keep their defaults
+                let limits = match request.params.get("limits") {
+                    Some(limits) => match serde_json::from_value::<RelayConfig>(limits.clone()) {
+                        Ok(limits) => limits,
+                        Err(e) => return IpcResponse::error(request.id, format!("Invalid 'limits' parameter: {}", e)),
+                    },
+                    None => RelayConfig::default(),
+                };
+                
                 let mut relay = self.relay.write().await;
+                relay.set_auth(auth_required.then(|| {
+                    Arc::new(AccountJoinValidator(self.services.clone())) as Arc<dyn JoinValidator>
+                }));
+                relay.set_config(limits);
                 match relay.start(addr).await {
-                    Ok(bound_addr) => IpcResponse::success(request.id, serde_json::json!({ "address": bound_addr.to_string() })),
+                    Ok(bound_addr) => IpcResponse::success(request.id, serde_json::json!({
+                        "address": bound_addr.to_string(),
+                        "auth_required": auth_required,
+                    })),
                     Err(e) => IpcResponse::error(request.id, e.to_string()),
                 }
             }
@@ -662,11 +1831,20 @@ impl IpcServer {
                 IpcResponse::success(request.id, serde_json::json!({
                     "running": relay.is_running(),
                     "address": relay.bind_address().map(|a| a.to_string()),
+                    "auth_required": relay.requires_auth(),
+                    "limits": relay.config(),
+                    "traffic": relay.traffic(),
+                    "sessions": relay.list_sessions().await,
                     "session_count": relay.get_session_count().await,
                     "peer_count": relay.get_total_peers().await,
                 }))
             }
             
+            "reset_relay_metrics" => {
+                self.relay.read().await.reset_traffic().await;
+                IpcResponse::success(request.id, serde_json::json!({ "reset": true }))
+            }
+            
             "connect_to_relay" => {
                 let relay = self.relay.read().await;
                 if !relay.is_running() {
@@ -686,13 +1864,1103 @@ impl IpcServer {
                 }))
             }
             
-            // Unknown command
-            _ => IpcResponse::error(request.id, format!("Unknown command: {}", request.command)),
-        }
-    }
-    
-    /// Print current status (for testing)
-    pub async fn status(&self) {
+            // Settings sync commands
+            "sync_now" => {
+                let (Some(sync), Some(server_url)) = (self.settings_sync.as_mut(), self.sync_server_url.as_ref()) else {
+                    return IpcResponse::error(request.id, "Settings sync not available");
+                };
+                let Some(token) = request.params.get("token").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'token' parameter");
+                };
+                let client = ApiClient::with_token(server_url, token.to_string());
+                match sync.sync_now(&client).await {
+                    Ok(report) => {
+                        // Without a config file they're applied on the next start
+                        if let Some(config_path) = &self.config_path {
+                            if let Err(e) = sync.apply_pulled_to(config_path).await {
+                                warn!("Could not apply synced settings: {}", e);
+                            }
+                        }
+                        IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+                    }
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "get_sync_status" => {
+                match &self.settings_sync {
+                    Some(sync) => IpcResponse::success(request.id, serde_json::to_value(sync.status()).unwrap_or_default()),
+                    None => IpcResponse::error(request.id, "Settings sync not available"),
+                }
+            }
+            
+            "update_sync_section" => {
+                let Some(sync) = self.settings_sync.as_mut() else {
+                    return IpcResponse::error(request.id, "Settings sync not available");
+                };
+                let section = request.params.get("section")
+                    .and_then(|v| serde_json::from_value::<SyncSection>(v.clone()).ok());
+                let Some(section) = section else {
+                    return IpcResponse::error(request.id, "Invalid 'section' parameter");
+                };
+                let data = request.params.get("data").cloned().unwrap_or(serde_json::Value::Null);
+                match sync.update_section(section, data).await {
+                    Ok(_) => IpcResponse::success(request.id, serde_json::json!({ "updated": true })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Mod profile commands
+            "activate_mod_profile" => {
+                let Some(activator) = &self.mod_activator else {
+                    return IpcResponse::error(request.id, "Mod activation not available");
+                };
+                let profile = request.params.get("profile")
+                    .and_then(|v| serde_json::from_value::<ModProfileSpec>(v.clone()).ok());
+                let Some(profile) = profile else {
+                    return IpcResponse::error(request.id, "Invalid 'profile' parameter");
+                };
+                let dry_run = request.params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+                match activator.activate(&profile, dry_run).await {
+                    Ok(report) => {
+                        self.launcher.crash_tracker().write().await.record_activation(&report).await;
+                        if !dry_run && report.success() {
+                            self.active_mod_profile = Some(profile);
+                        }
+                        IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+                    }
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            "scan_mods" => {
+                let Some(scanner) = &self.mod_scanner else {
+                    return IpcResponse::error(request.id, "Mod scanning not available");
+                };
+                if request.params.get("full").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    scanner.clear_cache().await;
+                }
+                match scanner.scan().await {
+                    Ok(result) => IpcResponse::success(request.id, serde_json::to_value(result).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            "detect_mod_conflicts" => {
+                let Some(scanner) = &self.mod_scanner else {
+                    return IpcResponse::error(request.id, "Mod scanning not available");
+                };
+                let mut mods = match scanner.scan().await {
+                    Ok(result) => result.mods,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                // With a profile active only its mods count, even if other
+                // files were enabled by hand since
+                if let Some(profile) = &self.active_mod_profile {
+                    mods.retain(|info| profile.mods.iter().any(|m| m.id == info.id));
+                }
+                let mut report = ModAnalyzer::new(&mods).report();
+                report.profile_id = self.active_mod_profile.as_ref().map(|profile| profile.id.clone());
+                IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+            }
+            "optimize_load_order" => {
+                let Some(scanner) = &self.mod_scanner else {
+                    return IpcResponse::error(request.id, "Mod scanning not available");
+                };
+                let Some(profile) = &self.active_mod_profile else {
+                    return IpcResponse::error(request.id, "No mod profile is active");
+                };
+                let scanned = match scanner.scan().await {
+                    Ok(result) => result.mods,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                // Profiles name mods by file; manifests depend on them by
+                // declared id. Mods the scan doesn't know keep their place.
+                let manifests: Vec<ModManifest> = profile.mods.iter()
+                    .map(|m| scanned.iter()
+                        .filter(|info| info.enabled)
+                        .find(|info| {
+                            info.id == m.id
+                                || activator::classify(&info.file_path).is_some_and(|(id, _)| id == m.id)
+                        })
+                        .map(ModManifest::from)
+                        .unwrap_or_else(|| ModManifest::bare(&m.id)))
+                    .collect();
+                let resolved = match ModResolver::resolve(&manifests) {
+                    Ok(resolved) => resolved,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                
+                let mut reordered = profile.clone();
+                reordered.mods = resolved.order.iter()
+                    .filter_map(|id| manifests.iter().position(|m| m.id == *id))
+                    .map(|i| profile.mods[i].clone())
+                    .collect();
+                let before: Vec<&str> = profile.mods.iter().map(|m| m.id.as_str()).collect();
+                let after: Vec<&str> = reordered.mods.iter().map(|m| m.id.as_str()).collect();
+                let response = serde_json::json!({
+                    "profile_id": profile.id,
+                    "changed": before != after,
+                    "before": before,
+                    "after": after,
+                    "unresolved": resolved.unresolved,
+                });
+                self.active_mod_profile = Some(reordered);
+                IpcResponse::success(request.id, response)
+            }
+            "list_mod_profiles" => {
+                let Some(sync) = &self.profile_sync else {
+                    return IpcResponse::error(request.id, "Mod profile sync not available");
+                };
+                let profiles = sync.lock().await.profiles().to_vec();
+                IpcResponse::success(request.id, serde_json::json!({ "profiles": profiles }))
+            }
+            "save_mod_profile" => {
+                let Some(sync) = &self.profile_sync else {
+                    return IpcResponse::error(request.id, "Mod profile sync not available");
+                };
+                let id = request.params.get("id")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| Uuid::parse_str(s).ok());
+                let Some(name) = request.params.get("name").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'name' parameter");
+                };
+                let description = request.params.get("description").and_then(|v| v.as_str()).map(String::from);
+                let mods = request.params.get("mods")
+                    .and_then(|v| serde_json::from_value::<Vec<ProfileMod>>(v.clone()).ok());
+                let Some(mods) = mods else {
+                    return IpcResponse::error(request.id, "Invalid 'mods' parameter");
+                };
+                match sync.lock().await.save_profile(id, name.to_string(), description, mods).await {
+                    Ok(profile) => IpcResponse::success(request.id, serde_json::to_value(profile).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            "sync_profiles" => {
+                let (Some(sync), Some(server_url)) = (&self.profile_sync, &self.api_url) else {
+                    return IpcResponse::error(request.id, "Mod profile sync not available");
+                };
+                let Some(token) = request.params.get("token").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'token' parameter");
+                };
+                let client = ApiClient::with_token(server_url, token.to_string());
+                let result = sync.lock().await.sync_now(&client).await;
+                self.profile_sync_token = Some(token.to_string());
+                match result {
+                    Ok(report) => IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            "get_profile_sync_status" => {
+                let Some(sync) = &self.profile_sync else {
+                    return IpcResponse::error(request.id, "Mod profile sync not available");
+                };
+                let status = sync.lock().await.status();
+                IpcResponse::success(request.id, serde_json::to_value(status).unwrap_or_default())
+            }
+            
+            // Game installation commands
+            "detect_installations" => {
+                let hints = match &self.config_path {
+                    Some(path) => AppConfig::load(path).await.map(|(config, _)| config.launcher.install_hints).unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                let report = tokio::task::spawn_blocking(move || InstallationDetector::for_this_machine(&hints).detect()).await;
+                match report {
+                    Ok(report) => IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "set_game_path" => {
+                let Some(path) = request.params.get("path").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'path' parameter");
+                };
+                let install = match install::validate(std::path::Path::new(path), CandidateSource::Manual) {
+                    Ok(install) => install,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                if let Some(config_path) = &self.config_path {
+                    let saved = match AppConfig::load(config_path).await {
+                        Ok((mut config, _)) => {
+                            config.default_game_path = Some(install.executable.to_string_lossy().into_owned());
+                            config.save(config_path).await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = saved {
+                        return IpcResponse::error(request.id, format!("Could not save game path: {}", e));
+                    }
+                }
+                IpcResponse::success(request.id, serde_json::to_value(install).unwrap_or_default())
+            }
+            
+            // Java runtime commands
+            "list_java_runtimes" => {
+                let Some(java) = &self.java else {
+                    return IpcResponse::error(request.id, "Java runtime management not available");
+                };
+                let requirement = match java_requirement(&request.params) {
+                    Ok(requirement) => requirement,
+                    Err(e) => return IpcResponse::error(request.id, e),
+                };
+                let runtimes = java.list_runtimes().await;
+                let Some(requirement) = requirement else {
+                    return IpcResponse::success(request.id, serde_json::json!({ "runtimes": runtimes }));
+                };
+                let suitable: Vec<_> = runtimes.iter()
+                    .filter(|runtime| java::check_runtime(runtime, Some(&requirement)).is_ok())
+                    .map(|runtime| &runtime.home)
+                    .collect();
+                IpcResponse::success(request.id, serde_json::json!({ "runtimes": runtimes, "suitable": suitable }))
+            }
+            
+            "provision_java_runtime" => {
+                let Some(java) = &self.java else {
+                    return IpcResponse::error(request.id, "Java runtime management not available");
+                };
+                let major_version = request.params.get("major_version")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|v| u32::try_from(v).ok());
+                let Some(major_version) = major_version else {
+                    return IpcResponse::error(request.id, "Invalid 'major_version' parameter");
+                };
+                match java.provision(major_version).await {
+                    Ok(runtime) => IpcResponse::success(request.id, serde_json::to_value(runtime).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "set_profile_java" => {
+                let Some(java) = &mut self.java else {
+                    return IpcResponse::error(request.id, "Java runtime management not available");
+                };
+                let Some(profile_id) = request.params.get("profile_id").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'profile_id' parameter");
+                };
+                let home = request.params.get("java_home").and_then(|v| v.as_str()).map(std::path::PathBuf::from);
+                match java.set_profile_java(profile_id, home).await {
+                    Ok(runtime) => IpcResponse::success(request.id, serde_json::json!({ "runtime": runtime })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "set_java_path" => {
+                let Some(java) = &mut self.java else {
+                    return IpcResponse::error(request.id, "Java runtime management not available");
+                };
+                let Some(path) = request.params.get("path").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'path' parameter");
+                };
+                let requirement = match java_requirement(&request.params) {
+                    Ok(requirement) => requirement,
+                    Err(e) => return IpcResponse::error(request.id, e),
+                };
+                let profile_id = request.params.get("profile_id").and_then(|v| v.as_str()).unwrap_or("default");
+                match java.set_java_path(profile_id, std::path::Path::new(path), requirement.as_ref()).await {
+                    Ok(runtime) => IpcResponse::success(request.id, serde_json::json!({ "runtime": runtime })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "install_managed_java" => {
+                let Some(java) = &self.java else {
+                    return IpcResponse::error(request.id, "Java runtime management not available");
+                };
+                let requirement = match java_requirement(&request.params) {
+                    Ok(requirement) => requirement,
+                    Err(e) => return IpcResponse::error(request.id, e),
+                };
+                let major_version = match request.params.get("major_version") {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(v) => match v.as_u64().and_then(|v| u32::try_from(v).ok()) {
+                        Some(major) => Some(major),
+                        None => return IpcResponse::error(request.id, "Invalid 'major_version' parameter"),
+                    },
+                };
+                
+                // Report progress while the download runs
+                let progress = java.install_progress();
+                let events = self.events.clone();
+                let ticker = tokio::spawn(async move {
+                    let mut ticks = tokio::time::interval(Duration::from_millis(500));
+                    loop {
+                        ticks.tick().await;
+                        let data = serde_json::to_value(progress.status()).unwrap_or_default();
+                        let _ = events.send(IpcEvent::new("java_install_progress", data));
+                    }
+                });
+                let result = java.install_managed(requirement.as_ref(), major_version).await;
+                ticker.abort();
+                
+                match result {
+                    Ok((runtime, installed)) => {
+                        if installed {
+                            let data = serde_json::to_value(java.install_progress().status()).unwrap_or_default();
+                            let _ = self.events.send(IpcEvent::new("java_install_progress", data));
+                        }
+                        IpcResponse::success(request.id, serde_json::json!({ "runtime": runtime, "installed": installed }))
+                    }
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Snapshot commands
+            "get_snapshot_schedule" => {
+                let Some(snapshots) = &self.snapshots else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                IpcResponse::success(request.id, serde_json::to_value(snapshots.status()).unwrap_or_default())
+            }
+            
+            "set_snapshot_schedule" => {
+                let Some(snapshots) = self.snapshots.clone() else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                let mut schedule = snapshots.status().schedule;
+                let params = &request.params;
+                let flag = |name: &str| params.get(name).and_then(|v| v.as_bool());
+                let number = |name: &str| params.get(name).and_then(|v| v.as_u64());
+                if let Some(scheduled) = flag("scheduled") {
+                    schedule.scheduled = scheduled;
+                }
+                if let Some(minutes) = number("interval_minutes") {
+                    schedule.interval_minutes = minutes;
+                }
+                if let Some(keep_last) = number("keep_last") {
+                    schedule.keep_last = keep_last as usize;
+                }
+                if let Some(days) = number("keep_daily_days") {
+                    schedule.keep_daily_days = u32::try_from(days).unwrap_or(u32::MAX);
+                }
+                if let Some(only_when_game_stopped) = flag("only_when_game_stopped") {
+                    schedule.only_when_game_stopped = only_when_game_stopped;
+                }
+                if let Some(max_total_mb) = number("max_total_mb") {
+                    schedule.max_total_mb = max_total_mb;
+                }
+                
+                let mut config = match &self.config_path {
+                    Some(path) => match AppConfig::load(path).await {
+                        Ok((config, _)) => config,
+                        Err(e) => return IpcResponse::error(request.id, format!("Could not load config: {}", e)),
+                    },
+                    None => AppConfig::default(),
+                };
+                config.snapshots = schedule.clone();
+                let issues: Vec<String> = validation::check_values(&config).into_iter()
+                    .filter(|issue| issue.field.starts_with("snapshots."))
+                    .map(|issue| format!("{}: {}", issue.field, issue.message))
+                    .collect();
+                if !issues.is_empty() {
+                    return IpcResponse::error(request.id, format!("Invalid schedule: {}", issues.join("; ")));
+                }
+                if let Some(path) = &self.config_path {
+                    if let Err(e) = config.save(path).await {
+                        return IpcResponse::error(request.id, format!("Could not save schedule: {}", e));
+                    }
+                }
+                
+                snapshots.set_schedule(&schedule);
+                IpcResponse::success(request.id, serde_json::to_value(snapshots.status()).unwrap_or_default())
+            }
+            
+            "prune_snapshots" => {
+                let Some(snapshots) = &self.snapshots else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                let removed = snapshots.manager().prune().await;
+                IpcResponse::success(request.id, serde_json::json!({ "removed": removed }))
+            }
+            
+            "get_snapshots" => {
+                let Some(snapshots) = &self.snapshots else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                let report = snapshots.manager().report().await;
+                IpcResponse::success(request.id, serde_json::to_value(report).unwrap_or_default())
+            }
+            
+            "create_snapshot" => {
+                let Some(snapshots) = &self.snapshots else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                match snapshots.manager().create(SnapshotTrigger::Manual).await {
+                    Ok(manifest) => IpcResponse::success(request.id, serde_json::json!({
+                        "id": manifest.id,
+                        "created_at": manifest.created_at,
+                        "size_bytes": manifest.size_bytes,
+                    })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "restore_snapshot" => {
+                let Some(snapshots) = &self.snapshots else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                let Some(id) = request.params.get("id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok()) else {
+                    return IpcResponse::error(request.id, "Missing or invalid id");
+                };
+                let force = request.params.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
+                match snapshots.manager().restore(id, force).await {
+                    Ok(safety) => IpcResponse::success(request.id, serde_json::json!({
+                        "safety_snapshot": safety.map(|manifest| manifest.id),
+                    })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "delete_snapshot" => {
+                let Some(snapshots) = &self.snapshots else {
+                    return IpcResponse::error(request.id, "Snapshots not available");
+                };
+                let Some(id) = request.params.get("id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok()) else {
+                    return IpcResponse::error(request.id, "Missing or invalid id");
+                };
+                match snapshots.manager().delete(id).await {
+                    Ok(freed_bytes) => IpcResponse::success(request.id, serde_json::json!({ "freed_bytes": freed_bytes })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Feature gate commands
+            "refresh_feature_gates" => {
+                let (Some(gates), Some(server_url)) = (self.feature_gates.as_mut(), self.feature_gates_url.as_ref()) else {
+                    return IpcResponse::error(request.id, "Feature gates not available");
+                };
+                let client = match request.params.get("token").and_then(|v| v.as_str()) {
+                    Some(token) => ApiClient::with_token(server_url, token.to_string()),
+                    None => ApiClient::new(server_url),
+                };
+                let force = request.params.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
+                let state = gates.refresh(&client, force).await;
+                IpcResponse::success(request.id, serde_json::to_value(state).unwrap_or_default())
+            }
+            
+            "get_feature_state" => {
+                let Some(gates) = &self.feature_gates else {
+                    return IpcResponse::error(request.id, "Feature gates not available");
+                };
+                let mut state = serde_json::to_value(gates.gate_state(chrono::Utc::now())).unwrap_or_default();
+                state["features"] = serde_json::to_value(gates.list_all()).unwrap_or_default();
+                IpcResponse::success(request.id, state)
+            }
+            
+            // Launcher update commands
+            "check_for_updates" => {
+                let Some(updates) = &self.updates else {
+                    return IpcResponse::error(request.id, "Launcher updates not available");
+                };
+                match updates.check().await {
+                    Ok(check) => IpcResponse::success(request.id, serde_json::to_value(check).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "download_update" => {
+                let Some(updates) = &self.updates else {
+                    return IpcResponse::error(request.id, "Launcher updates not available");
+                };
+                let release = match updates.begin_download().await {
+                    Ok(release) => release,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                
+                // Runs in the background; poll get_update_progress or wait for the event
+                let updates = updates.clone();
+                let events = self.events.clone();
+                let response = serde_json::json!({ "version": release.version, "total_bytes": release.size });
+                tokio::spawn(async move {
+                    let event = match updates.download(&release).await {
+                        Ok(staged) => IpcEvent::new("update_ready", serde_json::to_value(staged).unwrap_or_default()),
+                        Err(e) => IpcEvent::new("update_failed", serde_json::json!({
+                            "version": release.version,
+                            "error": e.to_string(),
+                        })),
+                    };
+                    let _ = events.send(event);
+                });
+                IpcResponse::success(request.id, response)
+            }
+            
+            "get_update_progress" => {
+                let Some(updates) = &self.updates else {
+                    return IpcResponse::error(request.id, "Launcher updates not available");
+                };
+                let mut progress = serde_json::to_value(updates.progress()).unwrap_or_default();
+                progress["staged"] = serde_json::to_value(updates.staged().await).unwrap_or_default();
+                progress["rollback"] = serde_json::to_value(updates.rollback().await).unwrap_or_default();
+                IpcResponse::success(request.id, progress)
+            }
+            
+            // World hosting commands
+            "start_local_server" => {
+                let Some(hosting) = &self.hosting else {
+                    return IpcResponse::error(request.id, "World hosting not available");
+                };
+                let config = match serde_json::from_value::<WorldHostConfig>(request.params.clone()) {
+                    Ok(config) => config,
+                    Err(e) => return IpcResponse::error(request.id, format!("Invalid hosting config: {}", e)),
+                };
+                if self.sessions.current_session().is_some() {
+                    return IpcResponse::error(request.id, "Leave the current session before hosting a world");
+                }
+                
+                let status = match hosting.start(config.clone()).await {
+                    Ok(status) => status,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                let host_name = request.params.get("host_name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Host")
+                    .to_string();
+                let port = status.port.unwrap_or(config.port);
+                let session = match self.sessions.host_server(host_name, config.max_players as usize, &config.world_name, port).await {
+                    Ok(session) => session,
+                    Err(e) => {
+                        let _ = hosting.stop().await;
+                        return IpcResponse::error(request.id, e.to_string());
+                    }
+                };
+                
+                let mut data = serde_json::to_value(status).unwrap_or_default();
+                data["session_id"] = serde_json::json!(session.id.to_string());
+                data["invite_code"] = serde_json::json!(session.invite_code);
+                IpcResponse::success(request.id, data)
+            }
+            
+            "stop_local_server" => {
+                let Some(hosting) = &self.hosting else {
+                    return IpcResponse::error(request.id, "World hosting not available");
+                };
+                let result = hosting.stop().await;
+                if self.sessions.hosted_world().is_some() {
+                    let _ = self.sessions.leave_session().await;
+                }
+                match result {
+                    Ok(()) => IpcResponse::success(request.id, serde_json::json!({ "stopped": true })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "get_hosting_status" => {
+                let Some(hosting) = &self.hosting else {
+                    return IpcResponse::error(request.id, "World hosting not available");
+                };
+                let mut data = serde_json::to_value(hosting.status()).unwrap_or_default();
+                let invite_code = self.sessions.hosted_world().and(self.sessions.get_invite_code());
+                data["invite_code"] = serde_json::json!(invite_code);
+                IpcResponse::success(request.id, data)
+            }
+            
+            // Notification commands
+            "get_notifications" => {
+                let Some(server_url) = &self.api_url else {
+                    return IpcResponse::error(request.id, "Server not configured");
+                };
+                let Some(token) = request.params.get("token").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'token' parameter");
+                };
+                let unread_only = request.params.get("unread_only").and_then(|v| v.as_bool()).unwrap_or(false);
+                let limit = request.params.get("limit").and_then(|v| v.as_i64());
+                let client = ApiClient::with_token(server_url, token.to_string());
+                match client.get_notifications(unread_only, limit).await {
+                    Ok(page) => IpcResponse::success(request.id, serde_json::to_value(page).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Server directory commands
+            "search_servers" => {
+                let Some(server_url) = &self.api_url else {
+                    return IpcResponse::error(request.id, "Server not configured");
+                };
+                let search = match serde_json::from_value::<ServerSearch>(request.params.clone()) {
+                    Ok(search) => search,
+                    Err(e) => return IpcResponse::error(request.id, format!("Invalid search: {}", e)),
+                };
+                match ApiClient::new(server_url).search_servers(&search).await {
+                    Ok(page) => IpcResponse::success(request.id, serde_json::to_value(page).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Config commands
+            "validate_config" => {
+                let content = match request.params.get("content").and_then(|v| v.as_str()) {
+                    Some(content) => content.to_string(),
+                    None => {
+                        let Some(path) = &self.config_path else {
+                            return IpcResponse::error(request.id, "Config path not configured");
+                        };
+                        match tokio::fs::read_to_string(path).await {
+                            Ok(content) => content,
+                            Err(e) => return IpcResponse::error(request.id, format!("Could not read config: {}", e)),
+                        }
+                    }
+                };
+                let report = AppConfig::validate(&content);
+                let mut data = serde_json::to_value(&report).unwrap_or_default();
+                data["valid"] = serde_json::json!(report.is_valid());
+                IpcResponse::success(request.id, data)
+            }
+            
+            // Attestation commands
+            "get_attestation" => {
+                let Some(attestor) = &self.integrity else {
+                    return IpcResponse::error(request.id, "Attestation not available");
+                };
+                let mods = match &self.mod_activator {
+                    Some(activator) => match activator.scan().await {
+                        Ok(mods) => mods,
+                        Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                    },
+                    None => Default::default(),
+                };
+                let hello = attestor.manifest(mods.values()).await
+                    .and_then(|manifest| Ok((attestor.hello(&manifest)?, manifest)));
+                match hello {
+                    Ok((message, manifest)) => IpcResponse::success(request.id, serde_json::json!({
+                        "install_id": attestor.install_id(),
+                        "manifest": manifest,
+                        "message": message,
+                    })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "attestation_heartbeat" => {
+                let Some(attestor) = &self.integrity else {
+                    return IpcResponse::error(request.id, "Attestation not available");
+                };
+                let Some(token) = request.params.get("token").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'token' parameter");
+                };
+                IpcResponse::success(request.id, serde_json::json!({ "message": attestor.heartbeat(token) }))
+            }
+            
+            // Asset preload commands
+            "preload_server_assets" => {
+                let Some(preload) = &self.preload else {
+                    return IpcResponse::error(request.id, "Asset preloading not available");
+                };
+                let Some(server) = request.params.get("server").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'server' parameter");
+                };
+                if preload.is_running() {
+                    return IpcResponse::error(request.id, "A preload is already running");
+                }
+                
+                // Runs in the background; progress arrives as preload_progress events
+                let preload = preload.clone();
+                let server = server.to_string();
+                tokio::spawn(async move {
+                    let _ = preload.preload(&server).await;
+                });
+                IpcResponse::success(request.id, serde_json::json!({ "started": true }))
+            }
+            
+            "get_preload_status" => {
+                let Some(preload) = &self.preload else {
+                    return IpcResponse::error(request.id, "Asset preloading not available");
+                };
+                IpcResponse::success(request.id, serde_json::to_value(preload.status()).unwrap_or_default())
+            }
+            
+            "prefetch_server_assets" => {
+                let Some(prefetch) = &self.prefetch else {
+                    return IpcResponse::error(request.id, "Asset prefetching not available");
+                };
+                let Some(server) = request.params.get("server").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'server' parameter");
+                };
+                if prefetch.is_running() {
+                    return IpcResponse::error(request.id, "A prefetch is already running");
+                }
+                let manifest = match HttpManifestSource::new().manifest(server).await {
+                    Ok(manifest) => manifest,
+                    Err(e) => return IpcResponse::error(request.id, e.to_string()),
+                };
+                
+                // Runs in the background; progress is read with get_prefetch_status
+                let prefetch = prefetch.clone();
+                let server = server.to_string();
+                let assets = manifest.assets.len();
+                tokio::spawn(async move {
+                    let _ = prefetch.prefetch(&server, &manifest).await;
+                });
+                IpcResponse::success(request.id, serde_json::json!({ "started": true, "assets": assets }))
+            }
+            
+            "get_prefetch_status" => {
+                let Some(prefetch) = &self.prefetch else {
+                    return IpcResponse::error(request.id, "Asset prefetching not available");
+                };
+                IpcResponse::success(request.id, serde_json::to_value(prefetch.status()).unwrap_or_default())
+            }
+            
+            "join_queue" => {
+                let Some(queue) = &self.queue else {
+                    return IpcResponse::error(request.id, "Server queue not available");
+                };
+                let Some(server) = request.params.get("server").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'server' parameter");
+                };
+                let Some(user_id) = request.params.get("user_id").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'user_id' parameter");
+                };
+                let Ok(user_id) = Uuid::parse_str(user_id) else {
+                    return IpcResponse::error(request.id, "Invalid 'user_id' parameter");
+                };
+                // Only premium accounts claim priority; the server checks the claim
+                let claim_priority = self.feature_gates.as_ref()
+                    .is_none_or(|gates| gates.is_enabled("pond_integration.queue_priority"));
+                let token = request.params.get("token").and_then(|v| v.as_str()).filter(|_| claim_priority);
+                match queue.join_queue(server, user_id, token).await {
+                    Ok(status) => IpcResponse::success(request.id, serde_json::to_value(status).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "get_queue_status" => {
+                let Some(queue) = &self.queue else {
+                    return IpcResponse::error(request.id, "Server queue not available");
+                };
+                IpcResponse::success(request.id, serde_json::to_value(queue.status()).unwrap_or_default())
+            }
+            
+            "leave_queue" => {
+                let Some(queue) = &self.queue else {
+                    return IpcResponse::error(request.id, "Server queue not available");
+                };
+                match queue.leave_queue().await {
+                    Ok(left) => IpcResponse::success(request.id, serde_json::json!({ "left": left })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "discover_server_capabilities" => {
+                let Some(address) = request.params.get("address").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'address' parameter");
+                };
+                if self.feature_gates.as_ref().is_some_and(|gates| !gates.is_enabled("pond_integration.capability_discovery")) {
+                    return IpcResponse::error(request.id, "Capability discovery is not enabled");
+                }
+                let latency_ms = request.params.get("latency_ms").and_then(|v| v.as_u64())
+                    .map(|ms| u32::try_from(ms).unwrap_or(u32::MAX));
+                match PondClient::new(crate::VERSION).discover_with_latency(address, latency_ms).await {
+                    Ok(discovery) => IpcResponse::success(request.id, serde_json::to_value(discovery).unwrap_or_default()),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            // Ping measurement commands
+            "ping_server" | "set_server_favorite" | "favorite_server" => {
+                let Some(monitor) = &self.ping_monitor else {
+                    return IpcResponse::error(request.id, "Ping measurement not available");
+                };
+                let Some(address) = request.params.get("address").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'address' parameter");
+                };
+                let port = match request.params.get("port").and_then(|v| v.as_u64()) {
+                    Some(port) => match u16::try_from(port) {
+                        Ok(port) if port > 0 => Some(port),
+                        _ => return IpcResponse::error(request.id, "'port' must be between 1 and 65535"),
+                    },
+                    None => None,
+                };
+                let server_id = request.params.get("server_id").and_then(|v| v.as_str()).map(String::from);
+                let target = ServerTarget::new(server_id, address, port);
+                
+                if request.command == "ping_server" {
+                    return match monitor.ping(&target).await {
+                        Ok(result) => IpcResponse::success(request.id, serde_json::to_value(result).unwrap_or_default()),
+                        Err(e) => IpcResponse::error(request.id, e.to_string()),
+                    };
+                }
+                let favorite = match request.params.get("favorite").and_then(|v| v.as_bool()) {
+                    Some(favorite) => favorite,
+                    None if request.command == "favorite_server" => true,
+                    None => return IpcResponse::error(request.id, "Missing 'favorite' parameter"),
+                };
+                match monitor.set_favorite(target, favorite).await {
+                    Ok(()) => IpcResponse::success(request.id, serde_json::json!({ "favorite": favorite })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "get_ping_history" => {
+                let Some(monitor) = &self.ping_monitor else {
+                    return IpcResponse::error(request.id, "Ping measurement not available");
+                };
+                let Some(server_id) = request.params.get("server_id").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'server_id' parameter");
+                };
+                IpcResponse::success(request.id, serde_json::to_value(monitor.history(server_id).await).unwrap_or_default())
+            }
+            
+            "unfavorite_server" => {
+                let Some(monitor) = &self.ping_monitor else {
+                    return IpcResponse::error(request.id, "Ping measurement not available");
+                };
+                let Some(server_id) = request.params.get("server_id").and_then(|v| v.as_str()) else {
+                    return IpcResponse::error(request.id, "Missing 'server_id' parameter");
+                };
+                match monitor.unfavorite(server_id).await {
+                    Ok(removed) => IpcResponse::success(request.id, serde_json::json!({ "favorite": false, "removed": removed })),
+                    Err(e) => IpcResponse::error(request.id, e.to_string()),
+                }
+            }
+            
+            "list_favorites" => {
+                let Some(monitor) = &self.ping_monitor else {
+                    return IpcResponse::error(request.id, "Ping measurement not available");
+                };
+                IpcResponse::success(request.id, serde_json::json!({ "favorites": monitor.favorite_servers().await }))
+            }
+            
+            // Unknown command
+            _ => IpcResponse::error(request.id, format!("Unknown command: {}", request.command)),
+        }
+    }
+    
+    /// Parse a launch config from `params`, filling in the profile's Java
+    /// runtime and the active mod profile's mods when they aren't given
+    fn launch_config(&self, params: &serde_json::Value) -> Result<LaunchConfig, String> {
+        let mut config = serde_json::from_value::<LaunchConfig>(params.clone())
+            .map_err(|e| format!("Invalid launch config: {}", e))?;
+        
+        if config.java_runtime.is_none() {
+            if let Some(java) = &self.java {
+                config.java_runtime = java.profile_java(config.profile_key()).map(|home| home.to_path_buf());
+            }
+        }
+        
+        if let (Some(activator), Some(active)) = (&self.mod_activator, &self.active_mod_profile) {
+            let same_profile = config.profile_id.as_ref().is_none_or(|id| *id == active.id);
+            if config.mods.is_empty() && same_profile {
+                config.mods_dir = Some(activator.mods_dir().to_path_buf());
+                config.mods = active.mods.iter().map(|m| m.id.clone()).collect();
+            }
+        }
+        Ok(config)
+    }
+    
+    /// Disable every mod and apply the launcher's safe-mode changes to
+    /// `config`. The mods enabled beforehand are remembered and re-enabled
+    /// when the run ends, so the active profile is left as it was.
+    async fn prepare_safe_mode(&mut self, config: &mut LaunchConfig) -> SafeModeReport {
+        let mut report = safe_mode::apply_safe_mode(config).await;
+        
+        let Some(activator) = &self.mod_activator else {
+            report.warnings.push("Mod activation not available; mods were left as they are".to_string());
+            return report;
+        };
+        if self.safe_mode_restore.is_none() {
+            match activator.scan().await {
+                Ok(scanned) => self.safe_mode_restore = Some(ModProfileSpec {
+                    id: "safe_mode_restore".to_string(),
+                    name: "Mods before safe mode".to_string(),
+                    mods: scanned.into_values()
+                        .filter(|m| m.enabled)
+                        .map(|m| ProfileMod { id: m.id, file_name: None, download_url: None, sha256: None })
+                        .collect(),
+                }),
+                Err(e) => {
+                    report.warnings.push(format!("Could not record enabled mods; mods were left as they are: {}", e));
+                    return report;
+                }
+            }
+        }
+        let no_mods = ModProfileSpec {
+            id: "safe_mode".to_string(),
+            name: "Safe mode".to_string(),
+            mods: Vec::new(),
+        };
+        match activator.activate(&no_mods, false).await {
+            Ok(activation) if activation.success() => report.mods_disabled = activation.disabled,
+            Ok(activation) => report.warnings.extend(
+                activation.failed.into_iter().map(|f| format!("Could not disable {}: {}", f.id, f.error))
+            ),
+            Err(e) => report.warnings.push(format!("Could not disable mods: {}", e)),
+        }
+        report
+    }
+    
+    /// The game executable from `game_path`, or else the config's
+    /// `default_game_path`
+    async fn game_path(&self, params: &serde_json::Value) -> Option<PathBuf> {
+        if let Some(path) = params.get("game_path").and_then(|v| v.as_str()) {
+            return Some(PathBuf::from(path));
+        }
+        let (config, _) = AppConfig::load(self.config_path.as_ref()?).await.ok()?;
+        config.default_game_path.map(PathBuf::from)
+    }
+    
+    /// Hand game exits the launcher noticed to diagnostics, and re-enable
+    /// mods once a safe-mode run is over
+    async fn record_game_exits(&mut self) {
+        let mut safe_mode_ended = false;
+        loop {
+            match self.game_exits.try_recv() {
+                Ok(report) => {
+                    safe_mode_ended |= report.safe_mode;
+                    self.diagnostics.lock().await.record_game_exit(report);
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        if safe_mode_ended {
+            self.restore_after_safe_mode().await;
+        }
+    }
+    
+    /// Re-enable the mods a safe-mode launch disabled
+    async fn restore_after_safe_mode(&mut self) {
+        let (Some(activator), Some(restore)) = (&self.mod_activator, self.safe_mode_restore.take()) else {
+            return;
+        };
+        // Activation rolls back on failure, so keep the list to try again
+        match activator.activate(&restore, false).await {
+            Ok(report) if report.success() => info!("Re-enabled {} mods after safe mode", report.enabled.len()),
+            Ok(report) => {
+                warn!("Could not re-enable mods after safe mode: {:?}", report.failed);
+                self.safe_mode_restore = Some(restore);
+            }
+            Err(e) => {
+                warn!("Could not re-enable mods after safe mode: {}", e);
+                self.safe_mode_restore = Some(restore);
+            }
+        }
+    }
+    
+    /// Drop the entries a finished `verify_cache` walk found invalid
+    async fn finish_cache_verification(&mut self) {
+        if !self.cache_verification.as_ref().is_some_and(|walk| walk.is_finished()) {
+            return;
+        }
+        let Some(walk) = self.cache_verification.take() else { return };
+        match walk.await {
+            Ok(report) => {
+                for key in report.invalid() {
+                    self.cache.forget(key);
+                }
+            }
+            Err(e) => warn!("Cache verification failed: {}", e),
+        }
+    }
+    
+    /// Start the retry of a failed profile sync once its backoff has passed,
+    /// sending the report as a `mod_profiles_synced` event
+    fn retry_profile_sync(&mut self) {
+        if self.profile_sync_retry.as_ref().is_some_and(|retry| !retry.is_finished()) {
+            return;
+        }
+        let (Some(sync), Some(server_url), Some(token)) = (&self.profile_sync, &self.api_url, &self.profile_sync_token) else {
+            return;
+        };
+        // A sync holding the lock is already talking to the server
+        if !sync.try_lock().is_ok_and(|sync| sync.retry_due(chrono::Utc::now())) {
+            return;
+        }
+        
+        let sync = sync.clone();
+        let client = ApiClient::with_token(server_url, token.clone());
+        let events = self.events.clone();
+        self.profile_sync_retry = Some(tokio::spawn(async move {
+            match sync.lock().await.sync_now(&client).await {
+                Ok(report) => {
+                    let _ = events.send(IpcEvent::new("mod_profiles_synced", serde_json::to_value(&report).unwrap_or_default()));
+                }
+                Err(e) => warn!("Mod profile sync retry failed: {}", e),
+            }
+        }));
+    }
+    
+    /// Bring the current session up to date with what its relay reported,
+    /// pushing each change to the UI
+    async fn apply_session_changes(&mut self) {
+        for event in self.sessions.apply_relay_messages().await {
+            let data = serde_json::to_value(&event).unwrap_or_default();
+            let _ = self.events.send(IpcEvent::new(event.name(), data));
+        }
+    }
+    
+    /// Apply the live fields of config reloads to the cache and sessions
+    async fn apply_config_changes(&mut self) {
+        let Some(changes) = self.config_changes.as_mut() else { return };
+        let mut reloads = Vec::new();
+        loop {
+            match changes.try_recv() {
+                Ok(ConfigEvent::Changed(change)) => reloads.push(*change),
+                Ok(ConfigEvent::Invalid(_)) | Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        
+        for change in reloads {
+            let config = &change.config;
+            if change.applied.iter().any(|field| field == "cache.max_size_bytes") {
+                match self.cache.set_max_size(config.cache.max_size_bytes).await {
+                    Ok(evicted) if !evicted.keys.is_empty() => {
+                        info!("Cache limit lowered, evicted {} entries ({} bytes)", evicted.keys.len(), evicted.bytes);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Could not resize the cache: {}", e),
+                }
+            }
+            if change.applied.iter().any(|field| field.starts_with("session.")) {
+                let mut sessions = self.sessions.config().clone();
+                sessions.relay_servers = config.session.relay_servers.clone();
+                sessions.stun_servers = config.session.stun_servers.clone();
+                sessions.encrypt_payloads = config.session.encrypt_payloads;
+                self.sessions.set_config(sessions);
+            }
+            if let Some(snapshots) = &self.snapshots {
+                if change.applied.iter().any(|field| field.starts_with("snapshots.")) {
+                    snapshots.set_schedule(&config.snapshots);
+                }
+            }
+            if let Some(prefetch) = &self.prefetch {
+                if change.applied.iter().any(|field| field.starts_with("prefetch.")) {
+                    prefetch.set_config(&config.prefetch);
+                }
+            }
+        }
+    }
+    
+    /// Check every component at once, emitting `component_health_changed`
+    /// for each whose status moved since the last check
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        let mut checks: Vec<(String, CheckFuture<'_>)> = Vec::new();
+        if let Some(database) = &self.database {
+            checks.push((database.name().to_string(), database.health_check()));
+        }
+        let relay = self.relay.clone();
+        checks.push(("relay".to_string(), Box::pin(async move { relay.read().await.health_check().await })));
+        if let Some(preload) = &self.preload {
+            checks.push((preload.name().to_string(), preload.health_check()));
+        }
+        if let Some(updates) = &self.updates {
+            checks.push((updates.name().to_string(), updates.health_check()));
+        }
+        if let Some(hosting) = &self.hosting {
+            checks.push((hosting.name().to_string(), hosting.health_check()));
+        }
+        for check in &self.health_checks {
+            checks.push((check.name().to_string(), check.health_check()));
+        }
+        
+        let components = health::check_all(checks, HEALTH_CHECK_TIMEOUT).await;
+        for transition in self.health.record(&components) {
+            let data = serde_json::to_value(&transition).unwrap_or_default();
+            let _ = self.events.send(IpcEvent::new("component_health_changed", data));
+        }
+        components
+    }
+    
+    /// Print current status (for testing)
+    pub async fn status(&self) {
         info!("IPC Server ready");
         info!("  Version: {}", IPC_VERSION);
         info!("  Commands available: {}", Self::list_commands().len());
@@ -700,63 +2968,892 @@ impl IpcServer {
     
     /// List all available commands
     pub fn list_commands() -> Vec<&'static str> {
-        vec![
-            "get_version",
-            "get_status",
-            "launch_game",
-            "get_game_state",
-            "terminate_game",
-            "list_profiles",
-            "get_profile",
-            "create_profile",
-            "update_profile",
-            "delete_profile",
-            "list_mods",
-            "install_mod",
-            "remove_mod",
-            "enable_mod",
-            "disable_mod",
-            "get_cache_stats",
-            "clear_cache",
-            "get_system_snapshot",
-            "prepare_for_launch",
-            "collect_metrics",
-            "get_diagnostics_report",
-            "export_diagnostics",
-            "create_session",
-            "join_session",
-            "leave_session",
-            "get_session_info",
-            "get_invite_code",
-            "signup",
-            "login",
-            "logout",
-            "validate_session",
-            "get_current_user",
-            "update_user_profile",
-            "search_users",
-            "send_friend_request",
-            "accept_friend_request",
-            "decline_friend_request",
-            "remove_friend",
-            "get_friends",
-            "get_pending_requests",
-            "get_online_friends",
-            "block_user",
-            "unblock_user",
-            "get_blocked_users",
-            "start_relay_server",
-            "stop_relay_server",
-            "get_relay_status",
-            "connect_to_relay",
-            "disconnect_from_relay",
-        ]
+        registry::COMMANDS.iter().map(|c| c.name).collect()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        if let Some(sampler) = self.metrics_sampler.take() {
+            sampler.abort();
+        }
+        if let Some(schedule) = self.snapshot_schedule.take() {
+            schedule.abort();
+        }
+    }
+}
+
+/// What `signup`, `login` and `refresh_session` answer
+fn auth_data(auth: AuthResponse) -> serde_json::Value {
+    serde_json::json!({
+        "user": auth.user,
+        "session": {
+            "token": auth.session.token,
+            "expires_at": auth.session.expires_at,
+            "refresh_token": auth.session.refresh_token,
+            "refresh_expires_at": auth.session.refresh_expires_at,
+        }
+    })
+}
+
+/// The optional `requirement` param, like `">=17"`
+fn java_requirement(params: &serde_json::Value) -> Result<Option<JavaRequirement>, String> {
+    match params.get("requirement") {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(text)) => text.parse().map(Some).map_err(|e: java::JavaError| e.to_string()),
+        Some(_) => Err("Invalid 'requirement' parameter".to_string()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::health::{CheckResult, HealthStatus};
+    use crate::core::launcher::{safe_mode::RunCounts, ProcessState};
+    use crate::core::mods::activator::HttpModDownloader;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    
+    /// Down whenever `failing` is set
+    struct FakeComponent {
+        failing: AtomicBool,
+    }
+    
+    #[async_trait::async_trait]
+    impl HealthCheck for FakeComponent {
+        fn name(&self) -> &str {
+            "fake"
+        }
+        
+        async fn health_check(&self) -> CheckResult {
+            if self.failing.load(Ordering::SeqCst) {
+                CheckResult::down("Disk gone")
+            } else {
+                CheckResult::ok()
+            }
+        }
+    }
+    
+    /// Never answers within the health check timeout
+    struct HungComponent;
+    
+    #[async_trait::async_trait]
+    impl HealthCheck for HungComponent {
+        fn name(&self) -> &str {
+            "hung"
+        }
+        
+        async fn health_check(&self) -> CheckResult {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            CheckResult::ok()
+        }
+    }
+    
+    fn server() -> IpcServer {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-{}", Uuid::new_v4()));
+        IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(dir.join("profiles")),
+            CacheManager::new(dir.join("cache"), 1024 * 1024 * 1024),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        )
+    }
+    
+    fn request(command: &str, params: serde_json::Value) -> IpcRequest {
+        IpcRequest {
+            id: Uuid::new_v4(),
+            version: IPC_VERSION.to_string(),
+            command: command.to_string(),
+            params,
+        }
+    }
+    
+    fn get_status() -> IpcRequest {
+        request("get_status", serde_json::json!({}))
+    }
+    
+    #[tokio::test]
+    async fn test_get_status_reports_worst_component_and_transitions() {
+        let fake = Arc::new(FakeComponent { failing: AtomicBool::new(false) });
+        let mut server = server().with_health_check(fake.clone());
+        let mut events = server.subscribe_events();
+        
+        let data = server.handle(get_status()).await.data.unwrap();
+        assert_eq!(data["overall"], "ok");
+        assert_eq!(data["in_session"], false);
+        let names: Vec<&str> = data["components"].as_array().unwrap().iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["relay", "fake"]);
+        
+        fake.failing.store(true, Ordering::SeqCst);
+        let data = server.handle(get_status()).await.data.unwrap();
+        assert_eq!(data["overall"], "down");
+        assert_eq!(data["components"][1]["detail"], "Disk gone");
+        
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.event, "component_health_changed");
+        let transition: health::HealthTransition = serde_json::from_value(event.data).unwrap();
+        assert_eq!((transition.name.as_str(), transition.from, transition.to), ("fake", HealthStatus::Ok, HealthStatus::Down));
+        
+        // Still down: no new event
+        server.handle(get_status()).await;
+        assert!(events.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_batch_answers_in_order_when_one_fails() {
+        let mut server = server();
+        let requests = vec![
+            request("get_version", serde_json::json!({})),
+            request("get_invite_code", serde_json::json!({})),
+            request("create_profile", serde_json::json!({ "name": "Survival" })),
+            request("batch", serde_json::json!({ "requests": [] })),
+        ];
+        let ids: Vec<Uuid> = requests.iter().map(|r| r.id).collect();
+        
+        let response = server.handle(request("batch", serde_json::json!({ "requests": requests }))).await;
+        let responses: Vec<IpcResponse> = serde_json::from_value(response.data.unwrap()["responses"].clone()).unwrap();
+        assert_eq!(responses.iter().map(|r| r.id).collect::<Vec<_>>(), ids);
+        assert!(responses[0].success);
+        assert_eq!(responses[1].error.as_deref(), Some("Not in a session"));
+        assert!(responses[2].success);
+        assert_eq!(responses[3].error.as_deref(), Some("Batches can't be nested"));
+        
+        // Later requests see what earlier ones did
+        let listed = server.handle(request("list_profiles", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(listed["profiles"][0]["name"], "Survival");
+        
+        let malformed = server.handle(request("batch", serde_json::json!({ "requests": [{ "command": "get_version" }] }))).await;
+        assert!(malformed.error.unwrap().starts_with("Invalid batch request"));
+    }
+    
+    #[tokio::test]
+    async fn test_session_changes_are_pushed_and_hosting_handed_off() {
+        use crate::core::sessions::SessionConfig;
+        
+        let mut server = server();
+        let addr = server.relay.write().await.start("127.0.0.1:0").await.unwrap();
+        let config = SessionConfig { relay_servers: vec![addr.to_string()], ..Default::default() };
+        server.sessions.set_config(config.clone());
+        let mut events = server.subscribe_events();
+        
+        let alone = server.handle(request("transfer_host", serde_json::json!({ "participant_id": Uuid::new_v4() }))).await;
+        assert_eq!(alone.error.as_deref(), Some("Not in session"));
+        
+        let created = server.handle(request("create_session", serde_json::json!({ "name": "Host" }))).await.data.unwrap();
+        let mut guest = SessionOrchestrator::with_broker(Arc::new(server.relay.read().await.broker()));
+        guest.set_config(config);
+        let joined = guest.join_session(created["invite_code"].as_str().unwrap(), "Guest".to_string(), None).await.unwrap();
+        let guest_id = joined.participants[0].id;
+        
+        // Relay messages are applied as commands come in
+        let joined_event = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                server.handle(request("get_invite_code", serde_json::json!({}))).await;
+                while let Ok(event) = events.try_recv() {
+                    if event.event == "session_participant_joined" {
+                        return event;
+                    }
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.unwrap();
+        assert_eq!(joined_event.data["participant"]["id"], guest_id.to_string());
+        
+        let handed = server.handle(request("transfer_host", serde_json::json!({ "participant_id": guest_id }))).await;
+        assert_eq!(handed.data.unwrap()["requested"], true);
+        server.relay.write().await.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_detect_nat_reports_the_nat_type() {
+        use crate::core::sessions::{nat::tests::FakeNat, NatType, SessionConfig};
+        
+        let mut server = server();
+        let undetected = server.handle(request("detect_nat", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(undetected["nat_type"], "unknown");
+        
+        let servers = ["198.51.100.1:3478", "198.51.100.2:3478"];
+        server.sessions.set_config(SessionConfig { stun_servers: servers.iter().map(|s| s.to_string()).collect(), ..Default::default() });
+        server.sessions.set_stun_transport(Arc::new(FakeNat::new(NatType::Symmetric, &servers)));
+        let data = server.handle(request("detect_nat", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(data["nat_type"], "symmetric");
+        assert_eq!(data["external_ip"], "203.0.113.7");
+        assert_eq!(data["stun_server"], servers[0]);
+    }
+    
+    #[tokio::test]
+    async fn test_poll_events_drains_friend_presence() {
+        let mut server = server();
+        let empty = server.handle(request("poll_events", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(empty["events"], serde_json::json!([]));
+        let offline = server.handle(request("subscribe_presence", serde_json::json!({ "user_id": Uuid::new_v4() }))).await;
+        assert_eq!(offline.error.as_deref(), Some("Database not available"));
+        
+        // What subscribe_presence sets up once the friends list is loaded
+        let hub = crate::core::presence::PresenceHub::new();
+        let friend = Uuid::new_v4();
+        server.presence_feed = Some(PresenceFeed::spawn(&hub, [friend].into()));
+        hub.publish(crate::core::presence::PresenceChange::new(friend, "online"));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        
+        let data = server.handle(request("poll_events", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(data["events"][0]["event"], "friend_presence_changed");
+        assert_eq!(data["events"][0]["data"]["user_id"], friend.to_string());
+        assert_eq!(data["events"][0]["data"]["status"], "online");
+        let again = server.handle(request("poll_events", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(again["events"], serde_json::json!([]));
+    }
+    
+    #[tokio::test]
+    async fn test_responses_carry_the_negotiated_version() {
+        let mut server = server();
+        let old_client = IpcRequest { version: "1.0.2".to_string(), ..request("get_version", serde_json::json!({})) };
+        assert_eq!(server.handle(old_client).await.version, "1.0.2");
+        
+        let newer = format!("1.{}.0", IpcVersion::current().minor + 1);
+        let rejected = server.handle(IpcRequest { version: newer.clone(), ..request("get_version", serde_json::json!({})) }).await;
+        assert!(rejected.error.unwrap().starts_with("Version mismatch"));
+        
+        // Still told what it could use instead
+        let answer = server.handle(IpcRequest { version: newer, ..request("negotiate_version", serde_json::json!({})) }).await;
+        assert_eq!(answer.version, IPC_VERSION);
+        let data = answer.data.unwrap();
+        assert_eq!((data["compatible"].as_bool(), data["max_version"].as_str()), (Some(false), Some(IPC_VERSION)));
+    }
+    
+    #[tokio::test]
+    async fn test_hung_command_times_out_without_stalling_others() {
+        let mut server = server()
+            .with_health_check(Arc::new(HungComponent))
+            .with_command_timeout(std::time::Duration::from_millis(50));
+        
+        let response = server.handle(get_status()).await;
+        assert_eq!(response.error.as_deref(), Some("get_status timed out after 0.05s"));
+        
+        let batch = vec![get_status(), request("get_version", serde_json::json!({}))];
+        let response = server.handle(request("batch", serde_json::json!({ "requests": batch }))).await;
+        let responses = &response.data.unwrap()["responses"];
+        assert_eq!(responses[0]["success"], false);
+        assert_eq!(responses[1]["success"], true);
+    }
+    
+    #[tokio::test]
+    async fn test_mod_commands_manage_the_mods_directory() {
+        let mods_dir = std::env::temp_dir().join(format!("yt-ipc-mods-{}", Uuid::new_v4()));
+        let mut server = server().with_mod_manager(ModManager::new(mods_dir.clone()));
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mods/minimap-2.1.0.jar");
+        
+        let installed = server.handle(request("install_mod", serde_json::json!({ "path": fixture }))).await;
+        assert_eq!(installed.data.unwrap()["mod"]["id"], "Hytale:Minimap");
+        let again = server.handle(request("install_mod", serde_json::json!({ "path": fixture }))).await;
+        assert_eq!(again.error.as_deref(), Some("Mod already installed: minimap-2.1.0.jar"));
+        
+        let disabled = server.handle(request("disable_mod", serde_json::json!({ "mod_id": "Hytale:Minimap" }))).await;
+        assert_eq!(disabled.data.unwrap()["mod"]["enabled"], false);
+        let listed = server.handle(request("list_mods", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(listed["mods"][0]["file_name"], "minimap-2.1.0.jar.disabled");
+        
+        // Removing needs confirmation
+        let preview = server.handle(request("remove_mod", serde_json::json!({ "mod_id": "Hytale:Minimap" }))).await.data.unwrap();
+        assert_eq!((preview["removed"].as_bool(), preview["confirm_required"].as_bool()), (Some(false), Some(true)));
+        assert!(mods_dir.join("minimap-2.1.0.jar.disabled").exists());
+        let removed = server.handle(request("remove_mod", serde_json::json!({ "mod_id": "Hytale:Minimap", "confirm": true }))).await;
+        assert_eq!(removed.data.unwrap()["removed"], true);
+        assert!(!mods_dir.join("minimap-2.1.0.jar.disabled").exists());
+        
+        let missing = server.handle(request("enable_mod", serde_json::json!({ "mod_id": "Hytale:Minimap" }))).await;
+        assert_eq!(missing.error.as_deref(), Some("Mod not found: Hytale:Minimap"));
+        
+        tokio::fs::remove_dir_all(&mods_dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_join_session_and_session_info_errors() {
+        let mut server = server();
+        let broker: Arc<dyn crate::core::sessions::SessionBroker> = Arc::new(server.relay.read().await.broker());
+        let mut host = SessionOrchestrator::with_broker(broker);
+        let info = |session_id: Option<&str>| request("get_session_info", match session_id {
+            Some(id) => serde_json::json!({ "session_id": id }),
+            None => serde_json::json!({}),
+        });
+        let join = |code: &str| request("join_session", serde_json::json!({ "invite_code": code, "name": "Guest" }));
+        
+        assert_eq!(server.handle(info(None)).await.error.as_deref(), Some("Not in session"));
+        assert_eq!(server.handle(info(Some("nope"))).await.error.as_deref(), Some("Session not found: nope"));
+        assert_eq!(server.handle(join("ZZZZZZ")).await.error.as_deref(), Some("Invalid invite code: ZZZZZZ"));
+        
+        let solo = host.create_session("Solo".to_string(), 1, None).await.unwrap();
+        let full = server.handle(join(&solo.invite_code)).await.error.unwrap();
+        assert!(full.starts_with("Session full"), "{}", full);
+        host.leave_session().await.unwrap();
+        
+        let party = host.create_session("Host".to_string(), 4, None).await.unwrap();
+        assert!(server.handle(join(&party.invite_code)).await.success);
+        let session = server.handle(info(None)).await.data.unwrap();
+        assert_eq!(session["id"], party.id.to_string());
+        assert_eq!(session["host"]["name"], "Host");
+        assert_eq!(session["participants"][0]["name"], "Guest");
+        assert_eq!(session["participants"][0]["connection"], "Hybrid");
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_profile_updates_and_deletes_persist() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-profiles-{}", Uuid::new_v4()));
+        let mut server = IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(dir.clone()),
+            CacheManager::new(dir.join("cache"), 1024 * 1024),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        );
+        let reloaded = || async {
+            let mut profiles = ProfileManager::new(dir.clone());
+            profiles.load_all().await.unwrap();
+            profiles.list().iter().map(|p| (p.id, p.name.clone())).collect::<Vec<_>>()
+        };
+        let create = |name: &str| request("create_profile", serde_json::json!({ "name": name }));
+        let vanilla = server.handle(create("Vanilla")).await.data.unwrap()["id"].as_str().unwrap().to_string();
+        server.handle(create("Modded")).await;
+        
+        let taken = server.handle(request("update_profile", serde_json::json!({ "id": vanilla, "name": "modded" }))).await;
+        assert_eq!(taken.error.as_deref(), Some("A profile named 'modded' already exists"));
+        let renamed = server.handle(request("update_profile", serde_json::json!({ "id": vanilla, "name": "Survival" }))).await;
+        assert_eq!(renamed.data.unwrap()["name"], "Survival");
+        let id = Uuid::parse_str(&vanilla).unwrap();
+        assert!(reloaded().await.contains(&(id, "Survival".to_string())));
+        
+        let unknown = server.handle(request("update_profile", serde_json::json!({ "id": Uuid::new_v4(), "name": "X" }))).await;
+        assert_eq!(unknown.error.as_deref(), Some("Profile not found"));
+        
+        // Not while the game is running with it
+        let config = LaunchConfig {
+            executable_path: PathBuf::from("/bin/sh"),
+            working_dir: Some(std::env::temp_dir()),
+            args: vec!["-c".to_string(), "sleep 5".to_string()],
+            profile_id: Some(vanilla.clone()),
+            ..Default::default()
+        };
+        server.launcher.launch(config).await.unwrap();
+        let in_use = server.handle(request("delete_profile", serde_json::json!({ "id": vanilla }))).await;
+        assert_eq!(in_use.error.as_deref(), Some("Profile is in use by the running game"));
+        server.launcher.kill().await.unwrap();
+        
+        let deleted = server.handle(request("delete_profile", serde_json::json!({ "id": vanilla }))).await;
+        assert_eq!(deleted.data.unwrap()["deleted"], true);
+        assert!(server.handle(request("get_profile", serde_json::json!({ "id": vanilla }))).await.error.is_some());
+        let names: Vec<_> = reloaded().await.into_iter().map(|(_, name)| name).collect();
+        assert_eq!(names, ["Modded"]);
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_export_diagnostics_with_background_samples() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-diagnostics-{}", Uuid::new_v4()));
+        let config_path = dir.join("config.toml");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        AppConfig::default().save(&config_path).await.unwrap();
+        let mut server = server().with_config_path(&config_path).with_metrics_sampling();
+        // The sampler's first tick is immediate
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        
+        let missing = server.handle(request("export_diagnostics", serde_json::json!({}))).await;
+        assert_eq!(missing.error.as_deref(), Some("Missing 'path' parameter"));
+        
+        let path = dir.join("reports").join("report.json");
+        let exported = server.handle(request("export_diagnostics", serde_json::json!({ "path": path }))).await.data.unwrap();
+        assert_eq!(exported["compressed"], false);
+        let report: serde_json::Value = serde_json::from_slice(&tokio::fs::read(&path).await.unwrap()).unwrap();
+        assert_eq!(exported["bytes"].as_u64(), Some(tokio::fs::metadata(&path).await.unwrap().len()));
+        assert!(report["summary"]["samples"].as_u64().unwrap() >= 1);
+        assert_eq!(report["config"]["diagnostics"]["sample_interval_secs"], 5);
+        assert!(report["system_info"]["cpu_cores"].as_u64().unwrap() > 0);
+        
+        // Sampling stops with the server
+        let diagnostics = Arc::downgrade(&server.diagnostics);
+        drop(server);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(diagnostics.upgrade().is_none());
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_frame_log_analysis_lands_in_report() {
+        let path = std::env::temp_dir().join(format!("yt-frames-{}.log", Uuid::new_v4()));
+        let frames: Vec<&str> = (0..200).map(|i| if i == 150 { "80.0" } else { "16.7" }).collect();
+        tokio::fs::write(&path, frames.join("\n")).await.unwrap();
+        let mut server = server();
+        
+        let missing = server.handle(request("analyze_frame_log", serde_json::json!({ "path": "/nonexistent/frames.csv" }))).await;
+        assert!(missing.error.unwrap().starts_with("IO error"));
+        
+        let analysis = server.handle(request("analyze_frame_log", serde_json::json!({ "path": path }))).await.data.unwrap();
+        assert_eq!(analysis["stutter_count"], 1);
+        assert_eq!(analysis["stutters"][0]["frame"], 150);
+        let report = server.handle(request("get_diagnostics_report", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(report["frame_pacing"], analysis);
+        
+        tokio::fs::remove_file(&path).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_system_snapshot_and_launch_readiness() {
+        let mut server = server();
+        let game_path = std::env::temp_dir().join("HytaleClient");
+        
+        let snapshot = server.handle(request("get_system_snapshot", serde_json::json!({ "game_path": game_path }))).await.data.unwrap();
+        assert!(snapshot["cpu_cores"].as_u64().unwrap() > 0);
+        assert_eq!(snapshot["cpu_usage"].as_array().unwrap().len() as u64, snapshot["cpu_cores"].as_u64().unwrap());
+        assert!(snapshot["heavy_processes"].is_array());
+        
+        let unknown = server.handle(request("prepare_for_launch", serde_json::json!({ "profile_id": Uuid::new_v4() }))).await;
+        assert_eq!(unknown.error.as_deref(), Some("Profile not found"));
+        
+        // No machine has this much RAM
+        let readiness = server.handle(request("prepare_for_launch", serde_json::json!({ "ram_allocation_mb": 1u64 << 40 }))).await.data.unwrap();
+        assert_eq!(readiness["ready"], false);
+        assert_eq!(readiness["blockers"][0]["kind"], "insufficient_ram");
+        assert_eq!(readiness["cache"]["warmed_files"], 0);
+    }
+    
+    #[tokio::test]
+    async fn test_prepare_for_launch_prunes_and_warms_the_cache() {
+        let mut server = server();
+        server.cache.put(b"block textures", None).await.unwrap();
+        server.cache.put(b"ambient sounds", Some(chrono::Duration::hours(1))).await.unwrap();
+        let expiring = server.cache.put(b"session manifest", Some(chrono::Duration::milliseconds(20))).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        
+        let readiness = server.handle(request("prepare_for_launch", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(readiness["cache"]["pruned_bytes"], 16);
+        assert_eq!(readiness["cache"]["warmed_files"], 2);
+        assert_eq!(readiness["cache"]["warmed_bytes"], 28);
+        assert!(!server.cache.contains(&expiring.key));
+        
+        let stats = server.handle(request("get_cache_stats", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(stats["entry_count"], 2);
+        assert_eq!(stats["evicted_bytes"], 16);
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_config_reload_reaches_events_and_sessions() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-config-{}", Uuid::new_v4()));
+        let config_path = dir.join("config.toml");
+        AppConfig::default().save(&config_path).await.unwrap();
+        let watcher = ConfigWatcher::spawn(config_path.clone(), AppConfig::default()).unwrap();
+        let mut server = server().with_config_watcher(watcher);
+        let mut events = server.subscribe_events();
+        
+        let mut edited = AppConfig::default();
+        edited.session.relay_servers = vec!["relay.example:9000".to_string()];
+        edited.launcher.shutdown_timeout_secs = 30;
+        edited.save(&config_path).await.unwrap();
+        
+        let event = tokio::time::timeout(std::time::Duration::from_secs(10), events.recv()).await.unwrap().unwrap();
+        assert_eq!(event.event, "config_changed");
+        assert_eq!(event.data["applied"], serde_json::json!(["session.relay_servers"]));
+        assert_eq!(event.data["restart_required"], serde_json::json!(["launcher.shutdown_timeout_secs"]));
+        
+        server.handle(request("get_version", serde_json::json!({}))).await;
+        assert_eq!(server.sessions.config().relay_servers, ["relay.example:9000"]);
+        
+        tokio::fs::write(&config_path, "[cache]\nmax_size_bytes = 1\n").await.unwrap();
+        let event = tokio::time::timeout(std::time::Duration::from_secs(10), events.recv()).await.unwrap().unwrap();
+        assert_eq!(event.event, "config_invalid");
+        assert_eq!(event.data["errors"][0]["field"], "cache.max_size_bytes");
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_set_game_path_validates_and_saves() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-game-path-{}", Uuid::new_v4()));
+        let config_path = dir.join("config.toml");
+        AppConfig::default().save(&config_path).await.unwrap();
+        let mut server = server().with_config_path(&config_path);
+        
+        let game_dir = dir.join("Hytale").join(install::GAME_SUBDIR);
+        std::fs::create_dir_all(game_dir.join("Client")).unwrap();
+        std::fs::write(game_dir.join("Client/HytaleClient.jar"), b"").unwrap();
+        
+        let set = |path: &std::path::Path| request("set_game_path", serde_json::json!({ "path": path }));
+        let rejected = server.handle(set(&dir.join("Hytale"))).await;
+        assert!(rejected.error.unwrap().ends_with("missing: Assets.zip"));
+        assert_eq!(AppConfig::load(&config_path).await.unwrap().0.default_game_path, None);
+        
+        std::fs::write(game_dir.join(install::ASSETS_FILE), b"").unwrap();
+        let accepted = server.handle(set(&dir.join("Hytale"))).await;
+        assert!(accepted.success, "{:?}", accepted.error);
+        assert_eq!(accepted.data.unwrap()["confidence"], "medium");
+        let executable = game_dir.join("Client/HytaleClient.jar").to_string_lossy().into_owned();
+        assert_eq!(AppConfig::load(&config_path).await.unwrap().0.default_game_path, Some(executable));
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_set_java_path_validates() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-java-{}", Uuid::new_v4()));
+        let java = JavaManager::load(&dir, Box::new(java::AdoptiumSource::new())).await;
+        let mut server = server().with_java(java);
+        
+        let bad_requirement = server.handle(request("set_java_path", serde_json::json!({
+            "path": dir, "requirement": "newest",
+        }))).await;
+        assert!(bad_requirement.error.unwrap().starts_with("Invalid Java requirement"));
+        
+        let missing = server.handle(request("set_java_path", serde_json::json!({
+            "path": dir.join("jdk-21"), "requirement": ">=17",
+        }))).await;
+        assert!(!missing.success);
+        
+        let listed = server.handle(request("list_java_runtimes", serde_json::json!({ "requirement": ">=999" }))).await;
+        assert_eq!(listed.data.unwrap()["suitable"], serde_json::json!([]));
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_snapshot_schedule_and_prune() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-snapshots-{}", Uuid::new_v4()));
+        let config_path = dir.join("config.toml");
+        AppConfig::default().save(&config_path).await.unwrap();
+        std::fs::create_dir_all(dir.join("worlds")).unwrap();
+        std::fs::write(dir.join("worlds/level.dat"), b"level").unwrap();
+        let manager = Arc::new(
+            SnapshotManager::new(dir.join("snapshots"), &SnapshotConfig::default())
+                .with_target("worlds", dir.join("worlds")).unwrap(),
+        );
+        for _ in 0..3 {
+            manager.create(crate::core::snapshots::SnapshotTrigger::Manual).await.unwrap();
+        }
+        let mut server = server().with_config_path(&config_path).with_snapshots(manager.clone(), &SnapshotConfig::default());
+        
+        let status = server.handle(request("get_snapshot_schedule", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(status["schedule"]["interval_minutes"], 60);
+        assert!(status["next_run_at"].is_string());
+        
+        let rejected = server.handle(request("set_snapshot_schedule", serde_json::json!({ "interval_minutes": 1 }))).await;
+        assert!(rejected.error.unwrap().contains("snapshots.interval_minutes"));
+        
+        let set = server.handle(request("set_snapshot_schedule", serde_json::json!({
+            "keep_last": 1, "keep_daily_days": 0, "max_total_mb": 512,
+        }))).await;
+        assert!(set.success, "{:?}", set.error);
+        assert_eq!(set.data.unwrap()["schedule"]["keep_last"], 1);
+        let saved = AppConfig::load(&config_path).await.unwrap().0.snapshots;
+        assert_eq!((saved.keep_last, saved.max_total_mb), (1, 512));
+        
+        let pruned = server.handle(request("prune_snapshots", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(pruned["removed"].as_array().unwrap().len(), 2);
+        assert_eq!(manager.list().await.len(), 1);
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_snapshots_share_unchanged_files() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-snapshot-store-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("worlds")).unwrap();
+        std::fs::write(dir.join("worlds/level.dat"), vec![1u8; 4096]).unwrap();
+        std::fs::write(dir.join("worlds/region.bin"), vec![2u8; 1024]).unwrap();
+        let manager = Arc::new(
+            SnapshotManager::new(dir.join("snapshots"), &SnapshotConfig::default())
+                .with_target("worlds", dir.join("worlds")).unwrap(),
+        );
+        let mut server = server().with_snapshots(manager, &SnapshotConfig::default());
+        
+        let first = server.handle(request("create_snapshot", serde_json::json!({}))).await.data.unwrap();
+        std::fs::write(dir.join("worlds/region.bin"), vec![3u8; 1024]).unwrap();
+        let second = server.handle(request("create_snapshot", serde_json::json!({}))).await.data.unwrap();
+        
+        let report = server.handle(request("get_snapshots", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(report["logical_bytes"], 2 * (4096 + 1024));
+        assert_eq!(report["stored_bytes"], 4096 + 2 * 1024);
+        assert_eq!(report["snapshots"][0]["id"], second["id"]);
+        assert_eq!(report["snapshots"][0]["unique_bytes"], 1024);
+        
+        let restored = server.handle(request("restore_snapshot", serde_json::json!({ "id": first["id"], "force": true }))).await;
+        assert!(restored.success, "{:?}", restored.error);
+        assert!(restored.data.unwrap()["safety_snapshot"].is_string());
+        assert_eq!(std::fs::read(dir.join("worlds/region.bin")).unwrap(), vec![2u8; 1024]);
+        
+        let deleted = server.handle(request("delete_snapshot", serde_json::json!({ "id": second["id"] }))).await;
+        // The safety snapshot still holds the second snapshot's files
+        assert_eq!(deleted.data.unwrap()["freed_bytes"], 0);
+        let missing = server.handle(request("delete_snapshot", serde_json::json!({ "id": second["id"] }))).await;
+        assert!(missing.error.unwrap().contains("not found"));
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_discover_server_capabilities() {
+        let bridge = pond::LauncherBridge::new(Arc::new(pond::AssetRegistry::new()));
+        let endpoint = pond::DiscoveryEndpoint::bind("127.0.0.1:0".parse().unwrap(), Arc::new(bridge)).await.unwrap();
+        let address = endpoint.local_addr().unwrap().to_string();
+        tokio::spawn(endpoint.serve());
+        let mut server = server();
+        
+        let discovery = server.handle(request("discover_server_capabilities", serde_json::json!({
+            "address": address, "latency_ms": 90,
+        }))).await;
+        assert!(discovery.success, "{:?}", discovery.error);
+        let discovery = discovery.data.unwrap();
+        assert!(discovery["capabilities"]["features"].as_array().unwrap().contains(&"cosmetics".into()));
+        assert_eq!(discovery["network_hints"]["recommended_update_rate"], 15);
+        
+        let missing = server.handle(request("discover_server_capabilities", serde_json::json!({}))).await;
+        assert!(missing.error.unwrap().contains("address"));
+        
+        // Gates that turn discovery off keep the launcher from asking
+        let cache = std::env::temp_dir().join(format!("yt-ipc-discovery-gates-{}.json", Uuid::new_v4()));
+        let mut gates = serde_json::to_value(yellow_tale_core::FeatureGates::free_tier()).unwrap();
+        gates["yellow_tale"]["pond_integration"]["capability_discovery"] = false.into();
+        std::fs::write(&cache, serde_json::json!({ "tier": "free", "fetched_at": chrono::Utc::now(), "gates": gates }).to_string()).unwrap();
+        let gates = FeatureGateManager::new().with_cache(&cache, chrono::Duration::hours(1));
+        let mut server = server.with_feature_gates(gates, "http://127.0.0.1:9");
+        let gated = server.handle(request("discover_server_capabilities", serde_json::json!({ "address": address }))).await;
+        assert!(gated.error.unwrap().contains("not enabled"));
+        std::fs::remove_file(&cache).ok();
+    }
+    
+    #[tokio::test]
+    async fn test_prefetch_commands() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-prefetch-{}", Uuid::new_v4()));
+        let mut server = server();
+        let unavailable = server.handle(request("get_prefetch_status", serde_json::json!({}))).await;
+        assert!(unavailable.error.unwrap().contains("not available"));
+        
+        let mut server = server.with_prefetch(AssetPrefetcher::new(&dir, &crate::core::config::PrefetchConfig::default()));
+        let status = server.handle(request("get_prefetch_status", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(status["phase"], "idle");
+        assert_eq!(status["failed"], serde_json::json!([]));
+        
+        // Nothing listens on port 9, so the manifest can't be fetched
+        let unreachable = server.handle(request("prefetch_server_assets", serde_json::json!({ "server": "127.0.0.1:9" }))).await;
+        assert!(unreachable.error.unwrap().contains("preload manifest"));
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_queue_commands() {
+        let mut server = server();
+        let unavailable = server.handle(request("get_queue_status", serde_json::json!({}))).await;
+        assert!(unavailable.error.unwrap().contains("not available"));
+        
+        let mut server = server.with_queue(Arc::new(QueueClient::new()));
+        let status = server.handle(request("get_queue_status", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(status["phase"], "idle");
+        
+        let missing = server.handle(request("join_queue", serde_json::json!({ "server": "127.0.0.1:9" }))).await;
+        assert!(missing.error.unwrap().contains("user_id"));
+        let invalid = server.handle(request("join_queue", serde_json::json!({ "server": "127.0.0.1:9", "user_id": "nobody" }))).await;
+        assert!(invalid.error.unwrap().contains("Invalid 'user_id'"));
+        // Nothing listens on port 9
+        let unreachable = server.handle(request("join_queue", serde_json::json!({
+            "server": "127.0.0.1:9", "user_id": Uuid::new_v4(), "token": "t",
+        }))).await;
+        assert!(unreachable.error.unwrap().contains("Could not reach"));
+        
+        let left = server.handle(request("leave_queue", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(left["left"], false);
+    }
+    
+    #[tokio::test]
+    async fn test_sync_profiles_queues_edits_while_offline() {
+        let dir = std::env::temp_dir().join(format!("yt-ipc-profile-sync-{}", Uuid::new_v4()));
+        let sync = ProfileSync::load(&dir).await.unwrap();
+        // Nothing listens on port 9, so every sync fails to connect
+        let mut server = server().with_profile_sync(sync).with_api_url("http://127.0.0.1:9");
+        
+        let saved = server.handle(request("save_mod_profile", serde_json::json!({
+            "name": "PvP",
+            "mods": ["minimap", { "id": "hud_plus", "file_name": "hud_plus-1.2.jar" }],
+        }))).await;
+        assert!(saved.success, "{:?}", saved.error);
+        let id = saved.data.as_ref().unwrap()["id"].clone();
+        
+        let synced = server.handle(request("sync_profiles", serde_json::json!({ "token": "t" }))).await;
+        assert!(synced.success, "{:?}", synced.error);
+        let report = synced.data.unwrap();
+        assert_eq!(report["profiles"][0]["id"], id);
+        assert_eq!(report["profiles"][0]["status"], "queued");
+        assert!(report["last_error"].is_string());
+        assert!(report["next_retry_at"].is_string());
+        
+        let status = server.handle(request("get_profile_sync_status", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(status["profiles"][0]["status"], "queued");
+        let listed = server.handle(request("list_mod_profiles", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(listed["profiles"][0]["mods"][1]["file_name"], "hud_plus-1.2.jar");
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_optimize_load_order_reorders_the_active_profile() {
+        let dir = std::env::temp_dir().join(format!("yt-load-order-{}", Uuid::new_v4()));
+        let mods_dir = dir.join("mods");
+        tokio::fs::create_dir_all(&mods_dir).await.unwrap();
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mod_conflicts");
+        for name in ["hud_plus.zip", "chat_api-1.4.0.jar", "minimap-2.1.0.jar"] {
+            tokio::fs::copy(fixtures.join(name), mods_dir.join(name)).await.unwrap();
+        }
+        let activator = ProfileActivator::new(mods_dir.clone(), Box::new(HttpModDownloader::new(dir.join("downloads"))));
+        let mut server = server()
+            .with_mod_activator(activator)
+            .with_mod_scanner(ModScanner::new(mods_dir.clone()));
+        
+        let none = server.handle(request("optimize_load_order", serde_json::json!({}))).await;
+        assert_eq!(none.error.as_deref(), Some("No mod profile is active"));
+        
+        let profile = serde_json::json!({
+            "id": "pvp",
+            "name": "PvP",
+            "mods": ["hud_plus", "minimap-2.1.0", "chat_api-1.4.0"],
+        });
+        let activated = server.handle(request("activate_mod_profile", serde_json::json!({ "profile": profile }))).await;
+        assert!(activated.data.unwrap()["failed"].as_array().unwrap().is_empty());
+        
+        // hud_plus needs chat_api, which the profile names by its file
+        let first = server.handle(request("optimize_load_order", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(first["changed"], true);
+        assert_eq!(first["before"], serde_json::json!(["hud_plus", "minimap-2.1.0", "chat_api-1.4.0"]));
+        assert_eq!(first["after"], serde_json::json!(["minimap-2.1.0", "chat_api-1.4.0", "hud_plus"]));
+        assert_eq!(first["unresolved"], serde_json::json!([{
+            "mod_id": "hud_plus",
+            "requires": "chat_api",
+            "version_requirement": "^2.0",
+            "installed": "1.4.0",
+        }]));
+        
+        // The new order sticks and launches use it
+        let second = server.handle(request("optimize_load_order", serde_json::json!({}))).await.data.unwrap();
+        assert_eq!(second["changed"], false);
+        let config = server.launch_config(&serde_json::json!({
+            "executable_path": "/bin/sh",
+            "working_dir": dir,
+            "args": [],
+            "env_vars": {},
+            "inherit_env": true,
+        })).unwrap();
+        assert_eq!(config.mods, vec!["minimap-2.1.0", "chat_api-1.4.0", "hud_plus"]);
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_safe_mode_launch_leaves_the_profile_enabled() {
+        let dir = std::env::temp_dir().join(format!("yt-safe-mode-{}", Uuid::new_v4()));
+        let mods_dir = dir.join("mods");
+        tokio::fs::create_dir_all(&mods_dir).await.unwrap();
+        tokio::fs::write(mods_dir.join("minimap.jar"), b"jar").await.unwrap();
+        tokio::fs::write(mods_dir.join("shaders_plus.jar"), b"jar").await.unwrap();
+        let activator = ProfileActivator::new(mods_dir.clone(), Box::new(HttpModDownloader::new(dir.join("downloads"))));
+        let mut server = server().with_mod_activator(activator);
+        
+        let profile = serde_json::json!({ "id": "modded", "name": "Modded", "mods": ["minimap", "shaders_plus"] });
+        let activated = server.handle(request("activate_mod_profile", serde_json::json!({ "profile": profile }))).await;
+        assert!(activated.success);
+        
+        let launch = |script: String, safe_mode: bool| serde_json::json!({
+            "executable_path": "/bin/sh",
+            "working_dir": dir,
+            "args": ["-c", script],
+            "env_vars": {},
+            "inherit_env": true,
+            "profile_id": "modded",
+            "safe_mode": safe_mode,
+        });
+        async fn wait_for_exit(server: &IpcServer) {
+            while matches!(server.launcher.poll_status().await, ProcessState::Running { .. }) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }
+        
+        let calm = server.handle(request("get_crash_recovery_suggestion", serde_json::json!({ "profile_id": "modded" }))).await;
+        assert_eq!(calm.data.unwrap()["safe_mode_recommended"], false);
+        
+        // A crash with the profile's mods enabled
+        let crashed = server.handle(request("launch_game", launch("exit 3".to_string(), false))).await;
+        assert!(crashed.success, "{:?}", crashed.error);
+        wait_for_exit(&server).await;
+        let suggestion = server.handle(request("get_crash_recovery_suggestion", serde_json::json!({ "profile_id": "modded" }))).await.data.unwrap();
+        assert_eq!(suggestion["safe_mode_recommended"], true);
+        assert_eq!(suggestion["mods_at_crash"], serde_json::json!(["minimap", "shaders_plus"]));
+        
+        // The safe-mode run sees every mod disabled
+        let seen = dir.join("seen.txt");
+        let script = format!("ls {} > {}", mods_dir.display(), seen.display());
+        let safe = server.handle(request("launch_game", launch(script, true))).await;
+        assert!(safe.success, "{:?}", safe.error);
+        let disabled = &safe.data.unwrap()["safe_mode"]["mods_disabled"];
+        assert_eq!(disabled, &serde_json::json!(["minimap", "shaders_plus"]));
+        wait_for_exit(&server).await;
+        let listing = tokio::fs::read_to_string(&seen).await.unwrap();
+        assert_eq!(listing.lines().collect::<Vec<_>>(), vec!["minimap.jar.disabled", "shaders_plus.jar.disabled"]);
+        
+        // Once it has ended the mods are back and nothing about the profile changed
+        let suggestion = server.handle(request("get_crash_recovery_suggestion", serde_json::json!({ "profile_id": "modded" }))).await.data.unwrap();
+        assert_eq!(suggestion["safe_mode_recommended"], true);
+        assert!(mods_dir.join("minimap.jar").exists());
+        assert!(mods_dir.join("shaders_plus.jar").exists());
+        assert_eq!(server.active_mod_profile.as_ref().map(|p| p.id.as_str()), Some("modded"));
+        assert!(server.safe_mode_restore.is_none());
+        
+        let report = server.diagnostics.lock().await.generate_report();
+        assert_eq!(report.crash_rates.with_mods, RunCounts { runs: 1, crashes: 1 });
+        assert_eq!(report.crash_rates.without_mods, RunCounts { runs: 1, crashes: 0 });
+        
+        // And a normal launch finds them where the profile expects
+        let normal = server.handle(request("launch_game", launch("exit 0".to_string(), false))).await;
+        assert!(normal.success, "{:?}", normal.error);
+        wait_for_exit(&server).await;
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+    
+    #[tokio::test]
+    async fn test_validate_launch_checks_active_mods() {
+        let dir = std::env::temp_dir().join(format!("yt-validate-launch-{}", Uuid::new_v4()));
+        let mods_dir = dir.join("mods");
+        tokio::fs::create_dir_all(&mods_dir).await.unwrap();
+        tokio::fs::write(mods_dir.join("minimap.jar"), b"jar").await.unwrap();
+        let activator = ProfileActivator::new(mods_dir.clone(), Box::new(HttpModDownloader::new(dir.join("downloads"))));
+        let mut server = server().with_mod_activator(activator);
+        
+        let profile = serde_json::json!({ "id": "pvp", "name": "PvP", "mods": ["minimap"] });
+        let activated = server.handle(request("activate_mod_profile", serde_json::json!({ "profile": profile }))).await;
+        assert!(activated.success);
+        
+        let config = serde_json::json!({
+            "executable_path": "/bin/sh",
+            "working_dir": dir,
+            "args": ["-c", "exit 0"],
+            "env_vars": {},
+            "inherit_env": true,
+        });
+        let valid = server.handle(request("validate_launch", config.clone())).await.data.unwrap();
+        assert_eq!(valid, serde_json::json!({ "valid": true, "problems": [] }));
+        
+        // The profile's mod went away after activation
+        tokio::fs::remove_file(mods_dir.join("minimap.jar")).await.unwrap();
+        let invalid = server.handle(request("validate_launch", config.clone())).await.data.unwrap();
+        assert_eq!(invalid["valid"], false);
+        assert_eq!(invalid["problems"][0]["kind"], "mod_missing");
+        
+        // Launching reports the same problem instead of spawning
+        let launch = server.handle(request("launch_game", config)).await;
+        assert!(launch.error.unwrap().starts_with("Cannot launch: Mod minimap"));
+        assert!(matches!(server.launcher.get_state().await, ProcessState::Idle));
+        
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
     
     #[test]
     fn test_ipc_response_success() {
@@ -0,0 +1,639 @@
+//! Command registry and version negotiation
+//!
+//! Every command the core answers is listed here with the IPC version that
+//! introduced it and the shape of its params. `get_capabilities` is generated
+//! from this table, so adding a command means adding a row.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::{IpcError, IPC_VERSION};
+
+/// A parsed `major.minor.patch` IPC version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct IpcVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl IpcVersion {
+    /// The version this core speaks
+    pub fn current() -> Self {
+        IPC_VERSION.parse().expect("IPC_VERSION is a valid version")
+    }
+
+    /// Whether a core at this version serves a client at `client`: same
+    /// major, and a minor no newer than this one. Patch versions never matter.
+    pub fn accepts(&self, client: &IpcVersion) -> bool {
+        self.major == client.major && client.minor <= self.minor
+    }
+
+    /// Versions a core at this version accepts, as `1.0 to 1.27`
+    pub fn accepted_range(&self) -> String {
+        format!("{}.0 to {}.{}", self.major, self.major, self.minor)
+    }
+}
+
+impl FromStr for IpcVersion {
+    type Err = IpcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || IpcError::InvalidVersion(s.to_string());
+
+        // Pre-release and build metadata don't affect compatibility
+        let core = s.trim().split(['-', '+']).next().unwrap_or_default();
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().map_err(|_| invalid()));
+        let version = Self {
+            major: parts.next().ok_or_else(invalid)??,
+            minor: parts.next().ok_or_else(invalid)??,
+            patch: parts.next().ok_or_else(invalid)??,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(version)
+    }
+}
+
+impl fmt::Display for IpcVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    String,
+    Uuid,
+    Integer,
+    Boolean,
+    Array,
+    Object,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub required: bool,
+}
+
+const fn required(name: &'static str, kind: ParamKind) -> ParamSpec {
+    ParamSpec { name, kind, required: true }
+}
+
+const fn optional(name: &'static str, kind: ParamKind) -> ParamSpec {
+    ParamSpec { name, kind, required: false }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// First IPC version that understands the command
+    pub since: &'static str,
+    pub params: &'static [ParamSpec],
+    /// Command to use instead, if this one is deprecated
+    pub replacement: Option<&'static str>,
+    /// Expected to outlast the command timeout, so it isn't cut off
+    pub long_running: bool,
+}
+
+impl CommandSpec {
+    const fn new(name: &'static str, params: &'static [ParamSpec]) -> Self {
+        Self { name, since: "1.0.0", params, replacement: None, long_running: false }
+    }
+
+    const fn since(mut self, version: &'static str) -> Self {
+        self.since = version;
+        self
+    }
+
+    const fn deprecated(mut self, replacement: &'static str) -> Self {
+        self.replacement = Some(replacement);
+        self
+    }
+
+    const fn long_running(mut self) -> Self {
+        self.long_running = true;
+        self
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.replacement.is_some()
+    }
+
+    /// JSON Schema for the command's params object
+    pub fn params_schema(&self) -> serde_json::Value {
+        let properties: serde_json::Map<String, serde_json::Value> = self.params.iter()
+            .map(|p| {
+                let schema = match p.kind {
+                    ParamKind::String => serde_json::json!({ "type": "string" }),
+                    ParamKind::Uuid => serde_json::json!({ "type": "string", "format": "uuid" }),
+                    ParamKind::Integer => serde_json::json!({ "type": "integer" }),
+                    ParamKind::Boolean => serde_json::json!({ "type": "boolean" }),
+                    ParamKind::Array => serde_json::json!({ "type": "array" }),
+                    ParamKind::Object => serde_json::json!({ "type": "object" }),
+                    ParamKind::Any => serde_json::json!({}),
+                };
+                (p.name.to_string(), schema)
+            })
+            .collect();
+        let required: Vec<&str> = self.params.iter()
+            .filter(|p| p.required)
+            .map(|p| p.name)
+            .collect();
+
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+
+    pub fn to_capability(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "min_version": self.since,
+            "params": self.params_schema(),
+            "deprecated": self.is_deprecated(),
+            "replacement": self.replacement,
+        })
+    }
+}
+
+/// A `LaunchConfig`, taken by `launch_game` and `validate_launch`
+const LAUNCH_CONFIG_PARAMS: &[ParamSpec] = {
+    use ParamKind::*;
+    &[
+        required("executable_path", String),
+        optional("working_dir", String),
+        required("args", Array),
+        required("env_vars", Object),
+        required("inherit_env", Boolean),
+        optional("profile_id", String),
+        optional("safe_mode", Boolean),
+        optional("shader_cache_dir", String),
+        optional("java_runtime", String),
+        optional("mods_dir", String),
+        optional("mods", Array),
+    ]
+};
+
+/// Every command `IpcServer::handle` answers
+pub const COMMANDS: &[CommandSpec] = {
+    use ParamKind::*;
+    &[
+        // System commands
+        CommandSpec::new("get_version", &[]),
+        CommandSpec::new("get_capabilities", &[]).since("1.1.0"),
+        CommandSpec::new("negotiate_version", &[]).since("1.28.0"),
+        CommandSpec::new("get_status", &[]),
+        CommandSpec::new("get_database_status", &[]),
+        CommandSpec::new("batch", &[required("requests", Array)]).since("1.27.0").long_running(),
+
+        // Launcher commands
+        CommandSpec::new("launch_game", LAUNCH_CONFIG_PARAMS),
+        CommandSpec::new("get_game_state", &[]).deprecated("get_status"),
+        CommandSpec::new("terminate_game", &[]).long_running(),
+        CommandSpec::new("get_launch_recommendation", &[optional("profile_id", String)]).since("1.2.0"),
+        CommandSpec::new("get_crash_recovery_suggestion", &[optional("profile_id", String)]).since("1.43.0"),
+        CommandSpec::new("get_last_exit", &[]).since("1.20.0"),
+        CommandSpec::new("validate_launch", LAUNCH_CONFIG_PARAMS).since("1.26.0"),
+
+        // Profile commands
+        CommandSpec::new("list_profiles", &[]),
+        CommandSpec::new("get_profile", &[required("id", Uuid)]),
+        CommandSpec::new("create_profile", &[required("name", String)]),
+        CommandSpec::new("update_profile", &[required("id", Uuid), optional("name", String), optional("settings", Object)]).since("1.18.0"),
+        CommandSpec::new("delete_profile", &[required("id", Uuid)]).since("1.18.0"),
+
+        // Mod commands
+        CommandSpec::new("list_mods", &[]).since("1.17.0"),
+        CommandSpec::new("install_mod", &[required("path", String)]).since("1.17.0").long_running(),
+        CommandSpec::new("enable_mod", &[required("mod_id", String)]).since("1.17.0"),
+        CommandSpec::new("disable_mod", &[required("mod_id", String)]).since("1.17.0"),
+        CommandSpec::new("remove_mod", &[required("mod_id", String), optional("confirm", Boolean)]).since("1.17.0"),
+
+        // Cache commands
+        CommandSpec::new("get_cache_stats", &[]),
+        CommandSpec::new("clear_cache", &[]),
+        CommandSpec::new("cache_prune", &[]).since("1.21.0"),
+        CommandSpec::new("verify_cache", &[]).since("1.22.0"),
+
+        // Performance commands
+        CommandSpec::new("get_system_snapshot", &[optional("game_path", String)]).since("1.25.0"),
+        CommandSpec::new("prepare_for_launch", &[
+            optional("profile_id", String),
+            optional("ram_allocation_mb", Integer),
+            optional("game_path", String),
+        ]).since("1.25.0"),
+
+        // Diagnostics commands
+        CommandSpec::new("collect_metrics", &[]),
+        CommandSpec::new("get_diagnostics_report", &[]),
+        CommandSpec::new("export_diagnostics", &[required("path", String), optional("compress", Boolean)]).since("1.23.0").long_running(),
+        CommandSpec::new("analyze_performance", &[optional("cpu_affinity", Array), optional("max_heap_mb", Integer)]).since("1.13.0"),
+        CommandSpec::new("analyze_frame_log", &[required("path", String)]).since("1.24.0"),
+
+        // Session commands
+        CommandSpec::new("create_session", &[
+            optional("name", String),
+            optional("max_participants", Integer),
+            optional("password", String),
+        ]),
+        CommandSpec::new("join_session", &[
+            required("invite_code", String),
+            optional("name", String),
+            optional("server", String),
+            optional("password", String),
+        ]).since("1.10.0"),
+        CommandSpec::new("get_session_info", &[optional("session_id", String)]).since("1.19.0"),
+        CommandSpec::new("get_invite_code", &[]),
+        CommandSpec::new("leave_session", &[]),
+        CommandSpec::new("detect_nat", &[]).since("1.34.0"),
+        CommandSpec::new("transfer_host", &[required("participant_id", Uuid)]).since("1.35.0"),
+
+        // User/Auth commands
+        CommandSpec::new("signup", &[
+            required("username", String),
+            required("display_name", String),
+            required("email", String),
+            required("password", String),
+        ]),
+        CommandSpec::new("login", &[
+            required("username_or_email", String),
+            required("password", String),
+            optional("device_info", String),
+        ]),
+        CommandSpec::new("logout", &[required("token", String)]),
+        CommandSpec::new("refresh_session", &[required("refresh_token", String)]).since("1.29.0"),
+        CommandSpec::new("change_password", &[
+            required("token", String),
+            required("old_password", String),
+            required("new_password", String),
+        ]).since("1.30.0"),
+        CommandSpec::new("list_sessions", &[required("token", String)]).since("1.30.0"),
+        CommandSpec::new("revoke_session", &[
+            required("token", String),
+            optional("session_id", String),
+        ]).since("1.30.0"),
+        CommandSpec::new("validate_session", &[required("token", String)]),
+        CommandSpec::new("request_email_verification", &[required("token", String)]).since("1.31.0"),
+        CommandSpec::new("confirm_email", &[
+            required("token", String),
+            required("code", String),
+        ]).since("1.31.0"),
+        CommandSpec::new("search_users", &[
+            required("query", String),
+            optional("limit", Integer),
+            optional("cursor", String),
+            optional("token", String),
+        ]),
+        CommandSpec::new("get_current_user", &[required("token", String)]),
+        CommandSpec::new("update_user_profile", &[
+            required("user_id", Uuid),
+            optional("display_name", String),
+            optional("avatar_url", String),
+        ]),
+
+        // Friends commands
+        CommandSpec::new("send_friend_request", &[
+            required("from_user_id", Uuid),
+            required("to_user_id", Uuid),
+            optional("message", String),
+        ]),
+        CommandSpec::new("accept_friend_request", &[required("user_id", Uuid), required("from_user_id", Uuid)]),
+        CommandSpec::new("decline_friend_request", &[required("user_id", Uuid), required("from_user_id", Uuid)]),
+        CommandSpec::new("remove_friend", &[required("user_id", Uuid), required("friend_id", Uuid)]),
+        CommandSpec::new("get_friends", &[required("user_id", Uuid)]),
+        CommandSpec::new("get_pending_requests", &[required("user_id", Uuid)]),
+        CommandSpec::new("get_online_friends", &[required("user_id", Uuid)]),
+        CommandSpec::new("subscribe_presence", &[required("user_id", Uuid)]).since("1.33.0"),
+        CommandSpec::new("poll_events", &[]).since("1.33.0"),
+        CommandSpec::new("block_user", &[required("blocker_id", Uuid), required("blocked_id", Uuid), optional("reason", String)]),
+        CommandSpec::new("unblock_user", &[required("blocker_id", Uuid), required("blocked_id", Uuid)]),
+        CommandSpec::new("get_blocked_users", &[required("user_id", Uuid)]),
+
+        // Relay commands
+        CommandSpec::new("start_relay_server", &[
+            optional("address", String),
+            optional("auth_required", Boolean),
+            optional("limits", Object),
+        ]).since("1.15.0"),
+        CommandSpec::new("stop_relay_server", &[]),
+        CommandSpec::new("get_relay_status", &[]),
+        CommandSpec::new("reset_relay_metrics", &[]).since("1.38.0"),
+        CommandSpec::new("connect_to_relay", &[]),
+        CommandSpec::new("disconnect_from_relay", &[]),
+
+        // Settings sync commands
+        CommandSpec::new("sync_now", &[required("token", String)]),
+        CommandSpec::new("get_sync_status", &[]),
+        CommandSpec::new("update_sync_section", &[required("section", String), optional("data", Any)]),
+
+        // Mod profile commands
+        CommandSpec::new("activate_mod_profile", &[required("profile", Object), optional("dry_run", Boolean)]).long_running(),
+        CommandSpec::new("scan_mods", &[optional("full", Boolean)]).since("1.11.0").long_running(),
+        CommandSpec::new("detect_mod_conflicts", &[]).since("1.41.0").long_running(),
+        CommandSpec::new("optimize_load_order", &[]).since("1.42.0").long_running(),
+        CommandSpec::new("list_mod_profiles", &[]).since("1.44.0"),
+        CommandSpec::new("save_mod_profile", &[
+            optional("id", Uuid),
+            required("name", String),
+            optional("description", String),
+            required("mods", Array),
+        ]).since("1.44.0"),
+        CommandSpec::new("sync_profiles", &[required("token", String)]).since("1.44.0").long_running(),
+        CommandSpec::new("get_profile_sync_status", &[]).since("1.44.0"),
+
+        // Game installation commands
+        CommandSpec::new("detect_installations", &[]).since("1.45.0").long_running(),
+        CommandSpec::new("set_game_path", &[required("path", String)]).since("1.45.0"),
+
+        // Java runtime commands
+        CommandSpec::new("list_java_runtimes", &[optional("requirement", String)]).since("1.3.0"),
+        CommandSpec::new("provision_java_runtime", &[required("major_version", Integer)]).since("1.3.0").long_running(),
+        CommandSpec::new("set_profile_java", &[required("profile_id", String), optional("java_home", String)]).since("1.3.0"),
+        CommandSpec::new("set_java_path", &[required("path", String), optional("requirement", String), optional("profile_id", String)]).since("1.46.0"),
+        CommandSpec::new("install_managed_java", &[optional("requirement", String), optional("major_version", Integer)]).since("1.46.0").long_running(),
+
+        // Snapshot commands
+        CommandSpec::new("get_snapshot_schedule", &[]).since("1.47.0"),
+        CommandSpec::new("set_snapshot_schedule", &[
+            optional("scheduled", Boolean),
+            optional("interval_minutes", Integer),
+            optional("keep_last", Integer),
+            optional("keep_daily_days", Integer),
+            optional("only_when_game_stopped", Boolean),
+            optional("max_total_mb", Integer),
+        ]).since("1.47.0"),
+        CommandSpec::new("prune_snapshots", &[]).since("1.47.0"),
+        CommandSpec::new("get_snapshots", &[]).since("1.48.0"),
+        CommandSpec::new("create_snapshot", &[]).since("1.48.0").long_running(),
+        CommandSpec::new("restore_snapshot", &[required("id", Uuid), optional("force", Boolean)]).since("1.48.0").long_running(),
+        CommandSpec::new("delete_snapshot", &[required("id", Uuid)]).since("1.48.0"),
+
+        // Feature gate commands
+        CommandSpec::new("refresh_feature_gates", &[optional("token", String), optional("force", Boolean)]).since("1.4.0"),
+        CommandSpec::new("get_feature_state", &[]).since("1.4.0"),
+
+        // Launcher update commands
+        CommandSpec::new("check_for_updates", &[]).since("1.5.0"),
+        CommandSpec::new("download_update", &[]).since("1.5.0").long_running(),
+        CommandSpec::new("get_update_progress", &[]).since("1.5.0"),
+
+        // World hosting commands
+        CommandSpec::new("start_local_server", &[
+            optional("world_name", String),
+            optional("port", Integer),
+            optional("max_players", Integer),
+            optional("motd", String),
+            optional("host_name", String),
+        ]).since("1.6.0"),
+        CommandSpec::new("stop_local_server", &[]).since("1.6.0"),
+        CommandSpec::new("get_hosting_status", &[]).since("1.6.0"),
+
+        // Notification commands
+        CommandSpec::new("get_notifications", &[
+            required("token", String),
+            optional("unread_only", Boolean),
+            optional("limit", Integer),
+        ]).since("1.7.0"),
+
+        // Config commands
+        CommandSpec::new("validate_config", &[
+            optional("content", String),
+        ]).since("1.8.0"),
+
+        // Attestation commands
+        CommandSpec::new("get_attestation", &[]).since("1.9.0"),
+        CommandSpec::new("attestation_heartbeat", &[required("token", String)]).since("1.9.0"),
+
+        // Asset preload commands
+        CommandSpec::new("preload_server_assets", &[required("server", String)]).since("1.10.0"),
+        CommandSpec::new("get_preload_status", &[]).since("1.10.0"),
+        CommandSpec::new("discover_server_capabilities", &[required("address", String), optional("latency_ms", Integer)]).since("1.49.0"),
+        CommandSpec::new("prefetch_server_assets", &[required("server", String)]).since("1.50.0"),
+        CommandSpec::new("get_prefetch_status", &[]).since("1.50.0"),
+        CommandSpec::new("join_queue", &[required("server", String), required("user_id", String), optional("token", String)]).since("1.51.0"),
+        CommandSpec::new("get_queue_status", &[]).since("1.51.0"),
+        CommandSpec::new("leave_queue", &[]).since("1.51.0"),
+
+        // Ping measurement commands
+        CommandSpec::new("ping_server", &[required("address", String), optional("port", Integer), optional("server_id", String)]).since("1.12.0"),
+        CommandSpec::new("get_ping_history", &[required("server_id", String)]).since("1.12.0"),
+        CommandSpec::new("set_server_favorite", &[
+            required("server_id", String),
+            required("address", String),
+            optional("port", Integer),
+            required("favorite", Boolean),
+        ]).since("1.12.0"),
+        CommandSpec::new("favorite_server", &[
+            required("server_id", String),
+            required("address", String),
+            optional("port", Integer),
+        ]).since("1.40.0"),
+        CommandSpec::new("unfavorite_server", &[required("server_id", String)]).since("1.40.0"),
+        CommandSpec::new("list_favorites", &[]).since("1.40.0"),
+
+        // Server directory commands
+        CommandSpec::new("search_servers", &[
+            optional("game_mode", String),
+            optional("tags", Array),
+            optional("has_slots", Boolean),
+            optional("q", String),
+            optional("page", Integer),
+            optional("per_page", Integer),
+        ]).since("1.39.0"),
+    ]
+};
+
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+/// A request this core will serve
+#[derive(Debug, Clone, Copy)]
+pub struct Negotiated {
+    pub spec: &'static CommandSpec,
+    /// The older of the client's version and this core's, answered in
+    /// responses; this core's own when the client doesn't fit
+    pub version: IpcVersion,
+}
+
+/// Checks a request's version and command against what this core supports
+///
+/// `negotiate_version` is answered for any well-formed version, so a
+/// client that doesn't fit can still learn what would.
+pub fn negotiate(version: &str, command: &str) -> Result<Negotiated, IpcError> {
+    let client: IpcVersion = version.parse()?;
+    let current = IpcVersion::current();
+
+    if command != "negotiate_version" && !current.accepts(&client) {
+        return Err(IpcError::VersionMismatch {
+            expected: current.accepted_range(),
+            actual: client.to_string(),
+        });
+    }
+
+    let spec = find(command).ok_or_else(|| IpcError::UnknownCommand(command.to_string()))?;
+    let version = if current.accepts(&client) { client.min(current) } else { current };
+    Ok(Negotiated { spec, version })
+}
+
+/// What `negotiate_version` answers a client at `client`: whether it's
+/// served, the accepted range and the commands available at each minor
+pub fn version_negotiation(client: IpcVersion) -> serde_json::Value {
+    let current = IpcVersion::current();
+    let minors: Vec<serde_json::Value> = (0..=current.minor)
+        .map(|minor| {
+            let version = IpcVersion { major: current.major, minor, patch: 0 };
+            let commands: Vec<&str> = COMMANDS.iter()
+                .filter(|c| c.since.parse::<IpcVersion>().is_ok_and(|since| since <= version))
+                .map(|c| c.name)
+                .collect();
+            serde_json::json!({ "version": format!("{}.{}", current.major, minor), "commands": commands })
+        })
+        .collect();
+
+    let negotiated = current.accepts(&client).then(|| client.min(current));
+    serde_json::json!({
+        "server_version": IPC_VERSION,
+        "min_version": IpcVersion { major: current.major, minor: 0, patch: 0 }.to_string(),
+        "max_version": IPC_VERSION,
+        "compatible": negotiated.is_some(),
+        "negotiated_version": negotiated.map(|v| v.to_string()),
+        "minors": minors,
+    })
+}
+
+pub fn capabilities() -> serde_json::Value {
+    serde_json::json!({
+        "ipc_version": IPC_VERSION,
+        "commands": COMMANDS.iter().map(CommandSpec::to_capability).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ipc::Command;
+
+    /// A client one minor version ahead of this core
+    fn newer_minor() -> String {
+        let current = IpcVersion::current();
+        format!("{}.{}.0", current.major, current.minor + 1)
+    }
+
+    fn version(s: &str) -> IpcVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(version("1.2.3"), IpcVersion { major: 1, minor: 2, patch: 3 });
+        assert_eq!(version("1.0.0-beta.1"), IpcVersion { major: 1, minor: 0, patch: 0 });
+        for malformed in ["1.0", "1.0.0.0", "one", "", "1..0", "-1.0.0", "1.x.0", "v1.0.0"] {
+            assert!(malformed.parse::<IpcVersion>().is_err(), "{:?} parsed", malformed);
+        }
+    }
+
+    #[test]
+    fn test_older_and_equal_minors_are_accepted() {
+        let server = version("1.0.0");
+        for client in ["1.0.0", "1.0.1", "1.0.9"] {
+            assert!(server.accepts(&version(client)), "1.0.0 rejected {}", client);
+        }
+        assert!(version("1.3.0").accepts(&version("1.1.5")));
+
+        assert_eq!(negotiate("1.0.0", "get_status").unwrap().spec.name, "get_status");
+        let current = IpcVersion::current();
+        let same_minor = format!("{}.{}.{}", current.major, current.minor, current.patch + 1);
+        assert_eq!(negotiate(&same_minor, "get_status").unwrap().version, current);
+        assert_eq!(negotiate("1.0.3", "get_status").unwrap().version, version("1.0.3"));
+    }
+
+    #[test]
+    fn test_newer_minor_is_rejected() {
+        assert!(!version("1.0.0").accepts(&version("1.1.0")));
+        match negotiate(&newer_minor(), "get_status") {
+            Err(IpcError::VersionMismatch { expected, actual }) => {
+                let current = IpcVersion::current();
+                assert_eq!(expected, format!("1.0 to 1.{}", current.minor));
+                assert_eq!(actual, newer_minor());
+            }
+            other => panic!("expected version mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_negotiate_version_answers_any_well_formed_version() {
+        let answer = version_negotiation(version(&newer_minor()));
+        assert_eq!(answer["compatible"], false);
+        assert_eq!(answer["negotiated_version"], serde_json::Value::Null);
+        assert_eq!(negotiate(&newer_minor(), "negotiate_version").unwrap().spec.name, "negotiate_version");
+        assert!(matches!(negotiate("2.0", "negotiate_version"), Err(IpcError::InvalidVersion(_))));
+
+        let answer = version_negotiation(version("1.1.0"));
+        assert_eq!(answer["negotiated_version"], "1.1.0");
+        assert_eq!(answer["min_version"], "1.0.0");
+        let minors = answer["minors"].as_array().unwrap();
+        assert_eq!(minors.len() as u64, IpcVersion::current().minor + 1);
+        assert_eq!(minors[0]["version"], "1.0");
+        let at = |minor: usize| minors[minor]["commands"].as_array().unwrap().iter().map(|c| c.as_str().unwrap()).collect::<Vec<_>>();
+        assert!(at(0).contains(&"get_status"));
+        assert!(!at(0).contains(&"get_capabilities"));
+        assert!(at(1).contains(&"get_capabilities"));
+        assert_eq!(at(minors.len() - 1).len(), COMMANDS.len());
+    }
+
+    #[test]
+    fn test_other_major_is_rejected() {
+        match negotiate("2.0.0", "get_status") {
+            Err(IpcError::VersionMismatch { expected, actual }) => {
+                assert_eq!(expected, format!("1.0 to 1.{}", IpcVersion::current().minor));
+                assert_eq!(actual, "2.0.0");
+            }
+            other => panic!("expected version mismatch, got {:?}", other),
+        }
+        assert!(matches!(negotiate("0.9.0", "get_status"), Err(IpcError::VersionMismatch { .. })));
+        assert!(matches!(negotiate("latest", "get_status"), Err(IpcError::InvalidVersion(_))));
+    }
+
+    #[test]
+    fn test_deprecated_commands_name_a_replacement() {
+        let spec = negotiate("1.0.0", "get_game_state").unwrap().spec;
+        assert_eq!(spec.replacement, Some("get_status"));
+        for spec in COMMANDS.iter().filter(|c| c.is_deprecated()) {
+            assert!(find(spec.replacement.unwrap()).is_some(), "{} points at an unknown command", spec.name);
+        }
+    }
+
+    #[test]
+    fn test_registry_matches_command_enum() {
+        let current = IpcVersion::current();
+        for spec in COMMANDS {
+            assert!(
+                serde_json::from_value::<Command>(serde_json::json!(spec.name)).is_ok(),
+                "{} missing from Command", spec.name
+            );
+            assert!(spec.since.parse::<IpcVersion>().unwrap() <= current);
+        }
+    }
+
+    #[test]
+    fn test_capabilities_include_params_schema() {
+        let caps = capabilities();
+        let commands = caps["commands"].as_array().unwrap();
+        assert_eq!(commands.len(), COMMANDS.len());
+
+        let get_profile = commands.iter().find(|c| c["name"] == "get_profile").unwrap();
+        assert_eq!(get_profile["params"]["properties"]["id"]["format"], "uuid");
+        assert_eq!(get_profile["params"]["required"], serde_json::json!(["id"]));
+        assert_eq!(get_profile["deprecated"], false);
+    }
+}
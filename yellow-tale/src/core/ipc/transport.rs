@@ -0,0 +1,583 @@
+//! Local IPC transport
+//!
+//! Serves `IpcServer::handle` to UIs in other processes:
+//! - A Unix domain socket on Linux/macOS, a named pipe on Windows
+//! - One JSON envelope per line: `IpcRequest` in, `IpcResponse` and
+//!   `IpcEvent` out
+//! - Each connection opens with `{"token": "..."}`, checked against a token
+//!   written to a file only the current user can read
+//! - Requests on one connection run concurrently, so responses can arrive
+//!   out of order; clients match them by `id`
+
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::{IpcRequest, IpcResponse, IpcServer};
+
+/// Longest request line accepted
+const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// Longest handshake line accepted
+const MAX_HANDSHAKE: usize = 1024;
+
+/// Time a new connection has to authenticate
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outgoing lines queued per connection
+const OUTBOX: usize = 64;
+
+/// First line a client sends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handshake {
+    pub token: String,
+}
+
+/// The server's answer to the handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeReply {
+    pub authenticated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Where the core listens unless told otherwise
+#[cfg(unix)]
+pub fn default_endpoint(data_dir: &Path) -> PathBuf {
+    data_dir.join("ipc.sock")
+}
+
+/// Where the core listens unless told otherwise
+#[cfg(windows)]
+pub fn default_endpoint(_data_dir: &Path) -> PathBuf {
+    PathBuf::from(r"\\.\pipe\yellow-tale-ipc")
+}
+
+/// File holding the token clients authenticate with
+pub fn default_token_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("ipc.token")
+}
+
+/// Stream a client connects over
+#[cfg(unix)]
+pub type LocalStream = tokio::net::UnixStream;
+
+/// Stream a client connects over
+#[cfg(windows)]
+pub type LocalStream = tokio::net::windows::named_pipe::NamedPipeClient;
+
+/// Open a connection to `endpoint`, without authenticating
+#[cfg(unix)]
+pub async fn connect_stream(endpoint: &Path) -> io::Result<LocalStream> {
+    tokio::net::UnixStream::connect(endpoint).await
+}
+
+/// Open a connection to `endpoint`, without authenticating
+#[cfg(windows)]
+pub async fn connect_stream(endpoint: &Path) -> io::Result<LocalStream> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+    // Every instance can be busy for a moment while the listener makes the next
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    for _ in 0..50 {
+        match ClientOptions::new().open(endpoint) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            result => return result,
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::TimedOut, "IPC pipe stayed busy"))
+}
+
+/// Authenticate a fresh connection with `token`
+///
+/// Anything the server sends after its reply stays buffered in `stream`.
+pub async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut BufReader<S>, token: &str) -> io::Result<()> {
+    let mut line = serde_json::to_string(&Handshake { token: token.to_string() }).map_err(io::Error::other)?;
+    line.push('\n');
+    stream.get_mut().write_all(line.as_bytes()).await?;
+    stream.get_mut().flush().await?;
+
+    let mut reply = Vec::new();
+    let read = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_frame(stream, &mut reply, MAX_HANDSHAKE)).await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "No handshake reply"))??;
+    if !read {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed during handshake"));
+    }
+    let reply: HandshakeReply = serde_json::from_slice(&reply)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid handshake reply: {}", e)))?;
+    if reply.authenticated {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            reply.error.unwrap_or_else(|| "Not authenticated".to_string()),
+        ))
+    }
+}
+
+/// Read one line into `buf`, without the newline
+///
+/// False at end of stream. Partial lines survive cancellation in `buf`, so
+/// this can be raced in `select!` as long as `buf` is kept.
+async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R, buf: &mut Vec<u8>, limit: usize) -> io::Result<bool> {
+    loop {
+        let remaining = (limit + 1).saturating_sub(buf.len()) as u64;
+        let read = (&mut *reader).take(remaining).read_until(b'\n', buf).await?;
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            return Ok(true);
+        }
+        if buf.len() > limit {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Frame longer than {} bytes", limit)));
+        }
+        if read == 0 {
+            return Ok(false);
+        }
+    }
+}
+
+/// Compare without stopping at the first difference
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected.bytes().zip(given.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Write a fresh token to `path`, readable only by the current user
+async fn write_token(path: &Path) -> io::Result<String> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    // A file left by an earlier run may have looser permissions; start over
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // On Windows the data directory already inherits a per-user ACL
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    file.write_all(token.as_bytes()).await?;
+    file.sync_all().await?;
+    Ok(token)
+}
+
+/// Serves an `IpcServer` on a local socket or named pipe
+pub struct IpcListener {
+    endpoint: PathBuf,
+    token_path: PathBuf,
+    token: Arc<str>,
+    server: Arc<Mutex<IpcServer>>,
+    #[cfg(unix)]
+    listener: tokio::net::UnixListener,
+    #[cfg(windows)]
+    pipe: tokio::net::windows::named_pipe::NamedPipeServer,
+}
+
+impl IpcListener {
+    /// Start listening on `endpoint` and write a new token to `token_path`
+    pub async fn bind(endpoint: PathBuf, token_path: PathBuf, server: Arc<Mutex<IpcServer>>) -> io::Result<Self> {
+        #[cfg(unix)]
+        let listener = {
+            use std::os::unix::fs::PermissionsExt;
+
+            if let Some(dir) = endpoint.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            if tokio::fs::symlink_metadata(&endpoint).await.is_ok() {
+                // A socket left by a crash refuses connections; a live one means another core
+                if tokio::net::UnixStream::connect(&endpoint).await.is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("Another instance is listening on {}", endpoint.display()),
+                    ));
+                }
+                tokio::fs::remove_file(&endpoint).await?;
+            }
+            let listener = tokio::net::UnixListener::bind(&endpoint)?;
+            tokio::fs::set_permissions(&endpoint, std::fs::Permissions::from_mode(0o600)).await?;
+            listener
+        };
+        #[cfg(windows)]
+        let pipe = tokio::net::windows::named_pipe::ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create(&endpoint)?;
+
+        let token = write_token(&token_path).await?.into();
+        Ok(Self {
+            endpoint,
+            token_path,
+            token,
+            server,
+            #[cfg(unix)]
+            listener,
+            #[cfg(windows)]
+            pipe,
+        })
+    }
+
+    pub fn endpoint(&self) -> &Path {
+        &self.endpoint
+    }
+
+    pub fn token_path(&self) -> &Path {
+        &self.token_path
+    }
+
+    /// Accept clients until `shutdown` completes
+    ///
+    /// Once it does, no new clients are accepted, requests already running
+    /// are answered, every connection is closed and the socket and token
+    /// file are removed.
+    pub async fn serve(self, shutdown: impl Future<Output = ()>) -> io::Result<()> {
+        info!("IPC listening on {}", self.endpoint.display());
+        let (stop, stopped) = watch::channel(false);
+        let mut connections = JoinSet::new();
+        tokio::pin!(shutdown);
+
+        #[cfg(unix)]
+        let result = loop {
+            tokio::select! {
+                _ = &mut shutdown => break Ok(()),
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        connections.spawn(serve_connection(stream, self.server.clone(), self.token.clone(), stopped.clone()));
+                    }
+                    Err(e) => warn!("IPC accept failed: {}", e),
+                },
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            }
+        };
+
+        #[cfg(windows)]
+        let result = {
+            let mut pipe = self.pipe;
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown => break Ok(()),
+                    connected = pipe.connect() => {
+                        if let Err(e) = connected {
+                            break Err(e);
+                        }
+                        // The next client needs an instance of its own
+                        let next = match tokio::net::windows::named_pipe::ServerOptions::new()
+                            .reject_remote_clients(true)
+                            .create(&self.endpoint)
+                        {
+                            Ok(next) => next,
+                            Err(e) => break Err(e),
+                        };
+                        let stream = std::mem::replace(&mut pipe, next);
+                        connections.spawn(serve_connection(stream, self.server.clone(), self.token.clone(), stopped.clone()));
+                    }
+                    Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                }
+            }
+        };
+
+        let _ = stop.send(true);
+        while connections.join_next().await.is_some() {}
+
+        #[cfg(unix)]
+        if let Err(e) = tokio::fs::remove_file(&self.endpoint).await {
+            warn!("Could not remove {}: {}", self.endpoint.display(), e);
+        }
+        if let Err(e) = tokio::fs::remove_file(&self.token_path).await {
+            warn!("Could not remove {}: {}", self.token_path.display(), e);
+        }
+        info!("IPC listener stopped");
+        result
+    }
+}
+
+/// Authenticate one client, then answer its requests and forward events
+/// until it disconnects or the listener stops
+async fn serve_connection<S>(stream: S, server: Arc<Mutex<IpcServer>>, token: Arc<str>, mut stopped: watch::Receiver<bool>)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read);
+    let mut buf = Vec::new();
+
+    let authenticated = match tokio::time::timeout(HANDSHAKE_TIMEOUT, read_frame(&mut reader, &mut buf, MAX_HANDSHAKE)).await {
+        Ok(Ok(true)) => serde_json::from_slice::<Handshake>(&buf).is_ok_and(|hello| tokens_match(&token, &hello.token)),
+        _ => false,
+    };
+    let reply = HandshakeReply {
+        authenticated,
+        error: (!authenticated).then(|| "Invalid token".to_string()),
+    };
+    if write_line(&mut write, &reply).await.is_err() || !authenticated {
+        debug!("IPC client failed the handshake");
+        return;
+    }
+    buf.clear();
+
+    let (outbox, mut queued) = mpsc::channel::<String>(OUTBOX);
+    let writer = tokio::spawn(async move {
+        while let Some(line) = queued.recv().await {
+            if write.write_all(line.as_bytes()).await.is_err() || write.flush().await.is_err() {
+                break;
+            }
+        }
+        let _ = write.shutdown().await;
+    });
+
+    let mut events = server.lock().await.subscribe_events();
+    let mut requests = JoinSet::new();
+    loop {
+        tokio::select! {
+            _ = stopped.changed() => break,
+            frame = read_frame(&mut reader, &mut buf, MAX_FRAME) => {
+                match frame {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        debug!("Dropping IPC client: {}", e);
+                        break;
+                    }
+                }
+                let outbox = outbox.clone();
+                match serde_json::from_slice::<IpcRequest>(&buf) {
+                    Ok(request) => {
+                        let server = server.clone();
+                        requests.spawn(async move {
+                            let response = server.lock().await.handle(request).await;
+                            let _ = outbox.send(to_line(&response)).await;
+                        });
+                    }
+                    Err(e) => {
+                        let response = IpcResponse::error(Uuid::nil(), format!("Invalid request: {}", e));
+                        let _ = outbox.send(to_line(&response)).await;
+                    }
+                }
+                buf.clear();
+            }
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let _ = outbox.send(to_line(&event)).await;
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("IPC client missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Some(_) = requests.join_next(), if !requests.is_empty() => {}
+        }
+    }
+
+    // Answer what was already asked before closing
+    while requests.join_next().await.is_some() {}
+    drop(outbox);
+    let _ = writer.await;
+}
+
+fn to_line<T: Serialize>(message: &T) -> String {
+    let mut line = serde_json::to_string(message).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+async fn write_line<W: AsyncWrite + Unpin, T: Serialize>(write: &mut W, message: &T) -> io::Result<()> {
+    write.write_all(to_line(message).as_bytes()).await?;
+    write.flush().await
+}
+
+/// Connects to a running core over its local endpoint
+///
+/// One request at a time; events and responses to other requests are
+/// skipped.
+pub struct IpcClient {
+    stream: BufReader<LocalStream>,
+}
+
+impl IpcClient {
+    /// Connect to `endpoint` and authenticate with the token in `token_path`
+    pub async fn connect(endpoint: &Path, token_path: &Path) -> io::Result<Self> {
+        let token = tokio::fs::read_to_string(token_path).await?;
+        Self::connect_with_token(endpoint, token.trim()).await
+    }
+
+    pub async fn connect_with_token(endpoint: &Path, token: &str) -> io::Result<Self> {
+        let mut stream = BufReader::new(connect_stream(endpoint).await?);
+        handshake(&mut stream, token).await?;
+        Ok(Self { stream })
+    }
+
+    /// Send `command` and wait for its response
+    pub async fn request(&mut self, command: &str, params: serde_json::Value) -> io::Result<IpcResponse> {
+        let request = IpcRequest {
+            id: Uuid::new_v4(),
+            version: super::IPC_VERSION.to_string(),
+            command: command.to_string(),
+            params,
+        };
+        write_line(self.stream.get_mut(), &request).await?;
+
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if !read_frame(&mut self.stream, &mut line, MAX_FRAME).await? {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed"));
+            }
+            match serde_json::from_slice::<IpcResponse>(&line) {
+                Ok(response) if response.id == request.id => return Ok(response),
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::core::{
+        cache::CacheManager, diagnostics::DiagnosticsCollector, ipc::IpcEvent,
+        launcher::LauncherService, profiles::ProfileManager, sessions::SessionOrchestrator,
+    };
+
+    struct Running {
+        dir: PathBuf,
+        endpoint: PathBuf,
+        token_path: PathBuf,
+        stop: tokio::sync::oneshot::Sender<()>,
+        serving: tokio::task::JoinHandle<io::Result<()>>,
+        events: broadcast::Sender<IpcEvent>,
+    }
+
+    async fn listen() -> Running {
+        // Kept short: socket paths are limited to about 100 bytes
+        let dir = PathBuf::from("/tmp").join(format!("yt-ipc-{}", &Uuid::new_v4().simple().to_string()[..8]));
+        let server = IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(dir.join("profiles")),
+            CacheManager::new(dir.join("cache"), 1024 * 1024 * 1024),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        );
+        let events = server.events.clone();
+        let listener = IpcListener::bind(default_endpoint(&dir), default_token_path(&dir), Arc::new(Mutex::new(server)))
+            .await
+            .unwrap();
+        let (endpoint, token_path) = (listener.endpoint().to_path_buf(), listener.token_path().to_path_buf());
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let serving = tokio::spawn(listener.serve(async {
+            let _ = stopped.await;
+        }));
+        Running { dir, endpoint, token_path, stop, serving, events }
+    }
+
+    #[tokio::test]
+    async fn test_requests_round_trip_over_the_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let running = listen().await;
+        let mode = std::fs::metadata(&running.token_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Two clients at once, each getting its own answers
+        let mut first = IpcClient::connect(&running.endpoint, &running.token_path).await.unwrap();
+        let mut second = IpcClient::connect(&running.endpoint, &running.token_path).await.unwrap();
+        let (a, b) = tokio::join!(
+            first.request("get_version", serde_json::json!({})),
+            second.request("fly", serde_json::json!({})),
+        );
+        assert!(a.unwrap().success);
+        assert!(!b.unwrap().success);
+
+        // Events reach connected clients between responses
+        running.events.send(IpcEvent::new("ping_updated", serde_json::json!({}))).unwrap();
+        assert!(first.request("get_version", serde_json::json!({})).await.unwrap().success);
+
+        let _ = running.stop.send(());
+        running.serving.await.unwrap().unwrap();
+        assert!(!running.endpoint.exists());
+        assert!(!running.token_path.exists());
+        assert!(first.request("get_version", serde_json::json!({})).await.is_err());
+        tokio::fs::remove_dir_all(&running.dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests_are_matched_by_id() {
+        let running = listen().await;
+        let token = tokio::fs::read_to_string(&running.token_path).await.unwrap();
+        let mut stream = BufReader::new(connect_stream(&running.endpoint).await.unwrap());
+        handshake(&mut stream, &token).await.unwrap();
+
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for id in &ids {
+            let request = IpcRequest {
+                id: *id,
+                version: super::super::IPC_VERSION.to_string(),
+                command: "get_version".to_string(),
+                params: serde_json::json!({}),
+            };
+            write_line(stream.get_mut(), &request).await.unwrap();
+        }
+        stream.get_mut().write_all(b"not json\n").await.unwrap();
+
+        let mut answered = Vec::new();
+        let mut line = Vec::new();
+        while answered.len() < 4 {
+            line.clear();
+            assert!(read_frame(&mut stream, &mut line, MAX_FRAME).await.unwrap());
+            if let Ok(response) = serde_json::from_slice::<IpcResponse>(&line) {
+                answered.push(response.id);
+            }
+        }
+        for id in &ids {
+            assert!(answered.contains(id));
+        }
+        assert!(answered.contains(&Uuid::nil()));
+
+        let _ = running.stop.send(());
+        running.serving.await.unwrap().unwrap();
+        tokio::fs::remove_dir_all(&running.dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_wrong_token_is_turned_away() {
+        let running = listen().await;
+
+        let error = IpcClient::connect_with_token(&running.endpoint, "guess").await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+        // A second core can't take over the socket
+        let server = IpcServer::new(
+            LauncherService::new(),
+            ProfileManager::new(running.dir.join("profiles")),
+            CacheManager::new(running.dir.join("cache"), 1024 * 1024 * 1024),
+            SessionOrchestrator::new(),
+            DiagnosticsCollector::new(),
+        );
+        let taken = IpcListener::bind(running.endpoint.clone(), running.dir.join("other.token"), Arc::new(Mutex::new(server))).await;
+        assert_eq!(taken.err().unwrap().kind(), io::ErrorKind::AddrInUse);
+
+        let _ = running.stop.send(());
+        running.serving.await.unwrap().unwrap();
+        tokio::fs::remove_dir_all(&running.dir).await.ok();
+    }
+}
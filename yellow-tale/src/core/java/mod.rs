@@ -0,0 +1,1007 @@
+//! Java Runtime Module
+//!
+//! Finds and provisions the Java runtimes profiles launch with:
+//! - Enumerates installed runtimes (JAVA_HOME, PATH, the Windows registry,
+//!   common install locations on macOS and Linux, and runtimes provisioned here)
+//! - Validates each one by parsing `java -version`, including its architecture
+//! - Checks runtimes against version requirements like `>=17`
+//! - Downloads Temurin builds from Adoptium into the data dir, verifying
+//!   their checksum before extracting and reporting progress as it goes
+//! - Remembers which runtime each profile uses
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{info, warn};
+
+#[derive(Error, Debug)]
+pub enum JavaError {
+    #[error("No Java runtime found at {0}")]
+    NotFound(PathBuf),
+
+    #[error("Invalid Java runtime at {path}: {reason}")]
+    InvalidRuntime { path: PathBuf, reason: String },
+
+    #[error("No Java {0} build available for this platform")]
+    NoPackage(u32),
+
+    #[error("Download failed: {0}")]
+    DownloadFailed(String),
+
+    #[error("Checksum mismatch for {name}: expected {expected}, got {actual}")]
+    ChecksumMismatch { name: String, expected: String, actual: String },
+
+    #[error("Failed to extract {0}")]
+    ExtractFailed(String),
+
+    #[error("Provisioned runtime reports Java {actual}, expected {expected}")]
+    WrongVersion { expected: u32, actual: u32 },
+
+    #[error("Invalid Java requirement: {0}")]
+    InvalidRequirement(String),
+
+    #[error("Java {version} does not meet the requirement {requirement}")]
+    Unsuitable { version: String, requirement: String },
+
+    #[error("Java runtime is built for {found}, this machine needs {expected}")]
+    WrongArchitecture { found: String, expected: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A validated Java installation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JavaRuntime {
+    /// Runtime home, the directory containing `bin/java`
+    pub home: PathBuf,
+    pub version: String,
+    pub major_version: u32,
+    pub vendor: String,
+    /// CPU architecture the runtime was built for (`x64`, `aarch64`, ...),
+    /// when `java` reported it
+    #[serde(default)]
+    pub arch: Option<String>,
+    /// Provisioned by the launcher rather than installed by the user
+    pub managed: bool,
+}
+
+/// Temurin releases the launcher provisions, newest first
+pub const MANAGED_MAJOR_VERSIONS: &[u32] = &[25, 21, 17];
+
+/// A Java version requirement, like `>=17` or `21`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JavaRequirement {
+    text: String,
+    req: VersionReq,
+}
+
+impl FromStr for JavaRequirement {
+    type Err = JavaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let req = VersionReq::parse(s.trim()).map_err(|e| JavaError::InvalidRequirement(format!("{}: {}", s, e)))?;
+        Ok(Self { text: s.trim().to_string(), req })
+    }
+}
+
+impl std::fmt::Display for JavaRequirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl JavaRequirement {
+    pub fn matches(&self, runtime: &JavaRuntime) -> bool {
+        java_semver(&runtime.version, runtime.major_version).is_some_and(|v| self.req.matches(&v))
+    }
+
+    /// Newest provisionable release that meets the requirement
+    pub fn best_major(&self) -> Option<u32> {
+        MANAGED_MAJOR_VERSIONS.iter().copied().find(|&major| self.req.matches(&Version::new(major.into(), 0, 0)))
+    }
+}
+
+/// A Java version as semver: `1.8.0_392` is 8.0.392, `25-ea` is 25.0.0
+fn java_semver(version: &str, major: u32) -> Option<Version> {
+    let numbers: Vec<u64> = version.split(|c: char| !c.is_ascii_digit())
+        .take_while(|part| !part.is_empty())
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    let rest = match numbers.first() {
+        Some(1) => numbers.get(2..).unwrap_or_default(),
+        Some(_) => numbers.get(1..).unwrap_or_default(),
+        None => return None,
+    };
+    Some(Version::new(major.into(), rest.first().copied().unwrap_or(0), rest.get(1).copied().unwrap_or(0)))
+}
+
+/// Architecture names as `os.arch` and `uname` spell them, in Adoptium's terms
+pub fn normalize_arch(arch: &str) -> String {
+    match arch.trim().to_ascii_lowercase().as_str() {
+        "amd64" | "x86_64" | "x64" => "x64".to_string(),
+        "aarch64" | "arm64" => "aarch64".to_string(),
+        "x86" | "i386" | "i486" | "i586" | "i686" => "x86".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Architecture this launcher runs on
+pub fn host_arch() -> String {
+    normalize_arch(std::env::consts::ARCH)
+}
+
+/// Check a runtime can run here and meets `requirement`
+pub fn check_runtime(runtime: &JavaRuntime, requirement: Option<&JavaRequirement>) -> Result<(), JavaError> {
+    if let Some(arch) = &runtime.arch {
+        let expected = host_arch();
+        if *arch != expected {
+            return Err(JavaError::WrongArchitecture { found: arch.clone(), expected });
+        }
+    }
+    match requirement {
+        Some(requirement) if !requirement.matches(runtime) => Err(JavaError::Unsuitable {
+            version: runtime.version.clone(),
+            requirement: requirement.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// What a runtime install is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallStage {
+    Idle,
+    Downloading,
+    Verifying,
+    Extracting,
+    Done,
+    Failed,
+}
+
+impl InstallStage {
+    const ALL: [InstallStage; 6] = [
+        InstallStage::Idle,
+        InstallStage::Downloading,
+        InstallStage::Verifying,
+        InstallStage::Extracting,
+        InstallStage::Done,
+        InstallStage::Failed,
+    ];
+}
+
+/// Progress of the current install, shared with whoever reports on it
+#[derive(Debug, Default)]
+pub struct InstallProgress {
+    stage: AtomicU8,
+    downloaded: AtomicU64,
+    total: AtomicU64,
+}
+
+/// Point-in-time copy of `InstallProgress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallStatus {
+    pub stage: InstallStage,
+    pub downloaded_bytes: u64,
+    /// Unknown until the server sends a length
+    pub total_bytes: Option<u64>,
+}
+
+impl InstallProgress {
+    fn start(&self) {
+        self.downloaded.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.set_stage(InstallStage::Downloading);
+    }
+
+    fn set_stage(&self, stage: InstallStage) {
+        self.stage.store(stage as u8, Ordering::Relaxed);
+    }
+
+    pub fn set_total(&self, bytes: u64) {
+        self.total.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_downloaded(&self, bytes: u64) {
+        self.downloaded.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> InstallStatus {
+        let total = self.total.load(Ordering::Relaxed);
+        InstallStatus {
+            stage: InstallStage::ALL[self.stage.load(Ordering::Relaxed) as usize],
+            downloaded_bytes: self.downloaded.load(Ordering::Relaxed),
+            total_bytes: (total > 0).then_some(total),
+        }
+    }
+}
+
+/// A downloadable runtime build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimePackage {
+    pub major_version: u32,
+    /// Release name, e.g. `jdk-21.0.3+9`, used as the install directory
+    pub release_name: String,
+    pub archive_name: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Where runtime builds come from
+#[async_trait]
+pub trait RuntimeSource: Send + Sync {
+    async fn resolve(&self, major_version: u32) -> Result<RuntimePackage, JavaError>;
+
+    /// Write the archive to `dest`, counting bytes into `progress`
+    async fn download(&self, package: &RuntimePackage, dest: &Path, progress: &InstallProgress) -> Result<(), JavaError>;
+}
+
+/// Temurin JREs from the Adoptium API
+pub struct AdoptiumSource {
+    client: reqwest::Client,
+    api_base: String,
+}
+
+impl AdoptiumSource {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: "https://api.adoptium.net/v3".to_string(),
+        }
+    }
+}
+
+impl Default for AdoptiumSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct AdoptiumAsset {
+    release_name: String,
+    binary: AdoptiumBinary,
+}
+
+#[derive(Deserialize)]
+struct AdoptiumBinary {
+    package: AdoptiumPackage,
+}
+
+#[derive(Deserialize)]
+struct AdoptiumPackage {
+    name: String,
+    link: String,
+    checksum: String,
+}
+
+#[async_trait]
+impl RuntimeSource for AdoptiumSource {
+    async fn resolve(&self, major_version: u32) -> Result<RuntimePackage, JavaError> {
+        let (os, arch) = adoptium_platform().ok_or(JavaError::NoPackage(major_version))?;
+        let url = format!(
+            "{}/assets/latest/{}/hotspot?os={}&architecture={}&image_type=jre&vendor=eclipse",
+            self.api_base, major_version, os, arch
+        );
+        let assets: Vec<AdoptiumAsset> = self.client.get(&url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| JavaError::DownloadFailed(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| JavaError::DownloadFailed(e.to_string()))?;
+
+        let asset = assets.into_iter().next().ok_or(JavaError::NoPackage(major_version))?;
+        Ok(RuntimePackage {
+            major_version,
+            release_name: asset.release_name,
+            archive_name: asset.binary.package.name,
+            url: asset.binary.package.link,
+            sha256: asset.binary.package.checksum,
+        })
+    }
+
+    async fn download(&self, package: &RuntimePackage, dest: &Path, progress: &InstallProgress) -> Result<(), JavaError> {
+        let mut response = self.client.get(&package.url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| JavaError::DownloadFailed(e.to_string()))?;
+        if let Some(length) = response.content_length() {
+            progress.set_total(length);
+        }
+
+        let mut file = tokio::fs::File::create(dest).await?;
+        while let Some(chunk) = response.chunk().await.map_err(|e| JavaError::DownloadFailed(e.to_string()))? {
+            file.write_all(&chunk).await?;
+            progress.add_downloaded(chunk.len() as u64);
+        }
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+fn adoptium_platform() -> Option<(&'static str, &'static str)> {
+    let os = match std::env::consts::OS {
+        "windows" => "windows",
+        "macos" => "mac",
+        "linux" => "linux",
+        _ => return None,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "aarch64",
+        _ => return None,
+    };
+    Some((os, arch))
+}
+
+/// Parse the stderr of `java -version` into (version, major, vendor).
+/// Handles both `1.8.0_392` and `21.0.3` style versions.
+pub fn parse_java_version(output: &str) -> Option<(String, u32, String)> {
+    // Skip the `key = value` dump `-XshowSettings` prints first
+    let mut lines = output.lines().filter(|line| !line.contains(" = "));
+    let version_line = lines.find(|line| line.contains("version \""))?;
+    let start = version_line.find('"')? + 1;
+    let end = start + version_line[start..].find('"')?;
+    let version = version_line[start..end].to_string();
+
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    let first: u32 = parts.next()?.parse().ok()?;
+    let major = if first == 1 { parts.next()?.parse().ok()? } else { first };
+
+    // The line after the version names the build, e.g. "OpenJDK Runtime
+    // Environment Temurin-21.0.3+9" or "IBM Semeru Runtime Open Edition"
+    let vendor = lines.next()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| version_line.split_whitespace().next().unwrap_or("Unknown").to_string());
+
+    Some((version, major, vendor))
+}
+
+/// The architecture from `java -XshowSettings:properties -version` output
+pub fn parse_java_arch(output: &str) -> Option<String> {
+    output.lines()
+        .filter_map(|line| line.trim().strip_prefix("os.arch"))
+        .filter_map(|rest| rest.trim_start().strip_prefix('='))
+        .map(normalize_arch)
+        .find(|arch| !arch.is_empty())
+}
+
+/// The `java` binary inside a runtime home
+pub fn java_executable(home: &Path) -> PathBuf {
+    let name = if cfg!(windows) { "java.exe" } else { "java" };
+    home.join("bin").join(name)
+}
+
+/// Run `java -version` in `home` and describe the runtime
+pub async fn probe(home: &Path, managed: bool) -> Result<JavaRuntime, JavaError> {
+    let executable = java_executable(home);
+    if !executable.is_file() {
+        return Err(JavaError::NotFound(home.to_path_buf()));
+    }
+
+    let output = tokio::process::Command::new(&executable)
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .await
+        .map_err(|e| JavaError::InvalidRuntime { path: home.to_path_buf(), reason: e.to_string() })?;
+
+    // Older runtimes print the version on stderr, some builds on stdout
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stderr), String::from_utf8_lossy(&output.stdout));
+    let (version, major_version, vendor) = parse_java_version(&text)
+        .filter(|_| output.status.success())
+        .ok_or_else(|| JavaError::InvalidRuntime {
+            path: home.to_path_buf(),
+            reason: "Could not read `java -version` output".to_string(),
+        })?;
+
+    let arch = parse_java_arch(&text);
+
+    Ok(JavaRuntime { home: home.to_path_buf(), version, major_version, vendor, arch, managed })
+}
+
+/// Java homes listed under the registry keys the Oracle and Adoptium
+/// installers write, as printed by `reg query <key> /s`
+pub fn parse_reg_query(output: &str) -> Vec<PathBuf> {
+    output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            if !matches!(name, "JavaHome" | "Path") || fields.next()? != "REG_SZ" {
+                return None;
+            }
+            let value = line.split("REG_SZ").nth(1)?.trim();
+            (!value.is_empty()).then(|| PathBuf::from(value))
+        })
+        .collect()
+}
+
+fn registry_homes() -> Vec<PathBuf> {
+    const KEYS: &[&str] = &[
+        r"HKLM\SOFTWARE\JavaSoft\JDK",
+        r"HKLM\SOFTWARE\JavaSoft\JRE",
+        r"HKLM\SOFTWARE\JavaSoft\Java Runtime Environment",
+        r"HKLM\SOFTWARE\Eclipse Adoptium\JDK",
+        r"HKLM\SOFTWARE\Eclipse Adoptium\JRE",
+    ];
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+
+    KEYS.iter()
+        .filter_map(|key| std::process::Command::new("reg").args(["query", key, "/s"]).output().ok())
+        .flat_map(|output| parse_reg_query(&String::from_utf8_lossy(&output.stdout)))
+        .collect()
+}
+
+/// Subdirectories of each parent, with `suffix` appended
+fn children(parents: &[PathBuf], suffix: &str) -> Vec<PathBuf> {
+    parents.iter()
+        .filter_map(|parent| std::fs::read_dir(parent).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .map(|path| if suffix.is_empty() { path } else { path.join(suffix) })
+        .collect()
+}
+
+/// Homes of the `java` binaries on PATH
+fn path_homes() -> Vec<PathBuf> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(if cfg!(windows) { "java.exe" } else { "java" }))
+        .filter(|executable| executable.is_file())
+        // `/usr/bin/java` is usually a link into the real runtime
+        .map(|executable| std::fs::canonicalize(&executable).unwrap_or(executable))
+        .filter_map(|executable| Some(executable.parent()?.parent()?.to_path_buf()))
+        .collect()
+}
+
+/// Places a Java runtime might be installed on this machine
+pub fn candidate_homes() -> Vec<PathBuf> {
+    let mut homes = Vec::new();
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(java_home));
+    }
+    homes.extend(path_homes());
+    homes.extend(registry_homes());
+
+    if cfg!(windows) {
+        let roots: Vec<PathBuf> = ["ProgramFiles", "ProgramW6432", "LOCALAPPDATA"].iter()
+            .filter_map(std::env::var_os)
+            .map(PathBuf::from)
+            .flat_map(|root| ["Eclipse Adoptium", "Java", "Temurin", "Programs/Eclipse Adoptium"].map(|d| root.join(d)))
+            .collect();
+        homes.extend(children(&roots, ""));
+    } else if cfg!(target_os = "macos") {
+        let mut roots = vec![PathBuf::from("/Library/Java/JavaVirtualMachines")];
+        if let Some(home) = std::env::var_os("HOME") {
+            roots.push(PathBuf::from(home).join("Library/Java/JavaVirtualMachines"));
+        }
+        homes.extend(children(&roots, "Contents/Home"));
+        homes.extend(children(&[PathBuf::from("/opt/homebrew/opt")], "libexec/openjdk.jdk/Contents/Home"));
+    } else {
+        let roots = ["/usr/lib/jvm", "/usr/java", "/opt/java", "/opt/jdk"].map(PathBuf::from);
+        homes.extend(children(&roots, ""));
+    }
+
+    homes
+}
+
+/// Installed and provisioned runtimes, plus each profile's choice
+pub struct JavaManager {
+    runtimes_dir: PathBuf,
+    settings_path: PathBuf,
+    profiles: BTreeMap<String, PathBuf>,
+    source: Box<dyn RuntimeSource>,
+    progress: Arc<InstallProgress>,
+}
+
+impl JavaManager {
+    /// Provisioned runtimes live in `<data_dir>/runtimes`
+    pub async fn load(data_dir: &Path, source: Box<dyn RuntimeSource>) -> Self {
+        let settings_path = data_dir.join("java_profiles.json");
+        let profiles = match tokio::fs::read_to_string(&settings_path).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable Java profile settings: {}", e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            runtimes_dir: data_dir.join("runtimes"),
+            settings_path,
+            profiles,
+            source,
+            progress: Arc::new(InstallProgress::default()),
+        }
+    }
+
+    /// Progress of the running (or last) `provision`
+    pub fn install_progress(&self) -> Arc<InstallProgress> {
+        self.progress.clone()
+    }
+
+    pub fn runtimes_dir(&self) -> &Path {
+        &self.runtimes_dir
+    }
+
+    /// Every runtime that passes `java -version`, provisioned ones first
+    pub async fn list_runtimes(&self) -> Vec<JavaRuntime> {
+        let managed: Vec<PathBuf> = children(std::slice::from_ref(&self.runtimes_dir), "").into_iter()
+            .filter(|path| !is_hidden(path))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut runtimes = Vec::new();
+        for (home, is_managed) in managed.into_iter().map(|h| (h, true)).chain(candidate_homes().into_iter().map(|h| (h, false))) {
+            let key = std::fs::canonicalize(&home).unwrap_or_else(|_| home.clone());
+            if !seen.insert(key) {
+                continue;
+            }
+            if let Ok(runtime) = probe(&home, is_managed).await {
+                runtimes.push(runtime);
+            }
+        }
+        runtimes
+    }
+
+    /// Download and install the latest build of `major_version`, or return
+    /// the copy already provisioned
+    pub async fn provision(&self, major_version: u32) -> Result<JavaRuntime, JavaError> {
+        let result = self.provision_inner(major_version).await;
+        self.progress.set_stage(if result.is_ok() { InstallStage::Done } else { InstallStage::Failed });
+        result
+    }
+
+    async fn provision_inner(&self, major_version: u32) -> Result<JavaRuntime, JavaError> {
+        let package = self.source.resolve(major_version).await?;
+        let install_dir = self.runtimes_dir.join(sanitize(&package.release_name));
+        if let Ok(runtime) = probe(&install_dir, true).await {
+            return Ok(runtime);
+        }
+
+        let downloads = self.runtimes_dir.join(".downloads");
+        tokio::fs::create_dir_all(&downloads).await?;
+        let archive = downloads.join(sanitize(&package.archive_name));
+        let partial = downloads.join(format!("{}.part", sanitize(&package.archive_name)));
+
+        info!("Downloading Java {} ({})", major_version, package.release_name);
+        self.progress.start();
+        let downloaded = match self.source.download(&package, &partial, &self.progress).await {
+            Ok(()) => {
+                self.progress.set_stage(InstallStage::Verifying);
+                verify_checksum(&partial, &package).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = downloaded {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&partial, &archive).await?;
+
+        self.progress.set_stage(InstallStage::Extracting);
+        let staging = self.runtimes_dir.join(format!(".staging-{}", uuid::Uuid::new_v4()));
+        let installed = self.install(&archive, &staging, &install_dir, major_version).await;
+        let _ = tokio::fs::remove_file(&archive).await;
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        if installed.is_err() {
+            let _ = tokio::fs::remove_dir_all(&install_dir).await;
+        }
+        installed
+    }
+
+    async fn install(&self, archive: &Path, staging: &Path, install_dir: &Path, major_version: u32) -> Result<JavaRuntime, JavaError> {
+        tokio::fs::create_dir_all(staging).await?;
+        extract(archive, staging).await?;
+
+        let home = find_home(staging)
+            .ok_or_else(|| JavaError::ExtractFailed(format!("no bin/java in {}", archive.display())))?;
+        let _ = tokio::fs::remove_dir_all(install_dir).await;
+        tokio::fs::rename(&home, install_dir).await?;
+
+        let runtime = probe(install_dir, true).await?;
+        if runtime.major_version != major_version {
+            return Err(JavaError::WrongVersion { expected: major_version, actual: runtime.major_version });
+        }
+        info!("Installed Java {} at {:?}", runtime.version, install_dir);
+        Ok(runtime)
+    }
+
+    /// A runtime meeting `requirement`, provisioning one when none is
+    /// installed. Returns whether it had to be downloaded.
+    pub async fn install_managed(
+        &self,
+        requirement: Option<&JavaRequirement>,
+        major_version: Option<u32>,
+    ) -> Result<(JavaRuntime, bool), JavaError> {
+        if let Some(requirement) = requirement {
+            let suitable = self.list_runtimes().await.into_iter()
+                .find(|runtime| check_runtime(runtime, Some(requirement)).is_ok());
+            if let Some(runtime) = suitable {
+                return Ok((runtime, false));
+            }
+        }
+
+        let major_version = match (major_version, requirement) {
+            (Some(major), _) => major,
+            (None, Some(requirement)) => requirement.best_major().ok_or_else(|| JavaError::Unsuitable {
+                version: format!("{:?}", MANAGED_MAJOR_VERSIONS),
+                requirement: requirement.to_string(),
+            })?,
+            (None, None) => MANAGED_MAJOR_VERSIONS[0],
+        };
+        let runtime = self.provision(major_version).await?;
+        check_runtime(&runtime, requirement)?;
+        Ok((runtime, true))
+    }
+
+    /// Validate the runtime at `path` (its home or its `java` binary) and
+    /// have the profile launch with it
+    pub async fn set_java_path(
+        &mut self,
+        profile_id: &str,
+        path: &Path,
+        requirement: Option<&JavaRequirement>,
+    ) -> Result<JavaRuntime, JavaError> {
+        let home = match path.is_file() {
+            true => path.parent().and_then(Path::parent).unwrap_or(path),
+            false => path,
+        };
+        let runtime = probe(home, home.starts_with(&self.runtimes_dir)).await?;
+        check_runtime(&runtime, requirement)?;
+        self.profiles.insert(profile_id.to_string(), runtime.home.clone());
+        self.save().await?;
+        Ok(runtime)
+    }
+
+    /// Set (or with `None`, clear) the runtime a profile launches with
+    pub async fn set_profile_java(&mut self, profile_id: &str, home: Option<PathBuf>) -> Result<Option<JavaRuntime>, JavaError> {
+        let runtime = match home {
+            Some(home) => {
+                let managed = home.starts_with(&self.runtimes_dir);
+                let runtime = probe(&home, managed).await?;
+                self.profiles.insert(profile_id.to_string(), runtime.home.clone());
+                Some(runtime)
+            }
+            None => {
+                self.profiles.remove(profile_id);
+                None
+            }
+        };
+        self.save().await?;
+        Ok(runtime)
+    }
+
+    /// The runtime home a profile is set to use
+    pub fn profile_java(&self, profile_id: &str) -> Option<&Path> {
+        self.profiles.get(profile_id).map(PathBuf::as_path)
+    }
+
+    async fn save(&self) -> Result<(), JavaError> {
+        if let Some(parent) = self.settings_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let contents = serde_json::to_string_pretty(&self.profiles)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        tokio::fs::write(&self.settings_path, contents).await?;
+        Ok(())
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Keep release names from escaping the runtimes directory
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+') { c } else { '_' })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+async fn verify_checksum(path: &Path, package: &RuntimePackage) -> Result<(), JavaError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    let actual = hex::encode(hasher.finalize());
+    if !actual.eq_ignore_ascii_case(&package.sha256) {
+        return Err(JavaError::ChecksumMismatch {
+            name: package.archive_name.clone(),
+            expected: package.sha256.clone(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Unpack with the system `tar`, which reads both the `.tar.gz` builds for
+/// macOS/Linux and (as bsdtar on Windows 10+) the `.zip` builds for Windows
+async fn extract(archive: &Path, dest: &Path) -> Result<(), JavaError> {
+    let output = tokio::process::Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dest)
+        .output()
+        .await
+        .map_err(|e| JavaError::ExtractFailed(format!("could not run tar: {}", e)))?;
+    if !output.status.success() {
+        return Err(JavaError::ExtractFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+/// Archives hold a single release directory; on macOS the home is nested
+/// under `Contents/Home`
+fn find_home(staging: &Path) -> Option<PathBuf> {
+    let mut candidates = vec![staging.to_path_buf()];
+    for child in children(&[staging.to_path_buf()], "") {
+        candidates.push(child.join("Contents").join("Home"));
+        candidates.push(child);
+    }
+    candidates.into_iter().find(|home| java_executable(home).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_java_version() {
+        let temurin = "openjdk version \"21.0.3\" 2024-04-16 LTS\n\
+                       OpenJDK Runtime Environment Temurin-21.0.3+9 (build 21.0.3+9-LTS)\n\
+                       OpenJDK 64-Bit Server VM Temurin-21.0.3+9 (build 21.0.3+9-LTS, mixed mode)";
+        let (version, major, vendor) = parse_java_version(temurin).unwrap();
+        assert_eq!(version, "21.0.3");
+        assert_eq!(major, 21);
+        assert!(vendor.contains("Temurin"));
+
+        let legacy = "java version \"1.8.0_392\"\nJava(TM) SE Runtime Environment (build 1.8.0_392-b08)";
+        assert_eq!(parse_java_version(legacy).unwrap().1, 8);
+        assert_eq!(parse_java_version("openjdk version \"25-ea\" 2025-09-16").unwrap().1, 25);
+        assert!(parse_java_version("command not found").is_none());
+    }
+
+    #[test]
+    fn test_parse_java_version_across_vendors() {
+        let cases = [
+            ("openjdk version \"17.0.10\" 2024-01-16 LTS\n\
+              OpenJDK Runtime Environment Zulu17.48+15-CA (build 17.0.10+7-LTS)", "17.0.10", 17, "Zulu"),
+            ("openjdk version \"21.0.2\" 2024-01-16 LTS\n\
+              OpenJDK Runtime Environment Corretto-21.0.2.13.1 (build 21.0.2+13-LTS)", "21.0.2", 21, "Corretto"),
+            ("openjdk version \"21.0.2\" 2024-01-16\n\
+              OpenJDK Runtime Environment GraalVM CE 21.0.2+13.1 (build 21.0.2+13-jvmci-23.1-b30)", "21.0.2", 21, "GraalVM"),
+            ("openjdk version \"17.0.9\" 2023-10-17 LTS\n\
+              OpenJDK Runtime Environment Microsoft-8552221 (build 17.0.9+8-LTS)", "17.0.9", 17, "Microsoft"),
+            ("openjdk version \"17.0.8.1\" 2023-08-24\n\
+              IBM Semeru Runtime Open Edition 17.0.8.1 (build 17.0.8.1+1)\n\
+              Eclipse OpenJ9 VM 17.0.8.1 (build openj9-0.40.0, JRE 17 Linux amd64-64-Bit)", "17.0.8.1", 17, "Semeru"),
+            ("java version \"21.0.1\" 2023-10-17 LTS\n\
+              Java(TM) SE Runtime Environment (build 21.0.1+12-LTS-29)", "21.0.1", 21, "Java(TM)"),
+        ];
+        for (output, version, major, vendor) in cases {
+            let parsed = parse_java_version(output).unwrap();
+            assert_eq!((parsed.0.as_str(), parsed.1), (version, major));
+            assert!(parsed.2.contains(vendor), "{} not in {}", vendor, parsed.2);
+        }
+    }
+
+    #[test]
+    fn test_parse_java_arch() {
+        let output = "Property settings:\n    java.class.version = 65.0\n    os.arch = amd64\n    os.name = Linux\n\n\
+                      openjdk version \"21.0.3\" 2024-04-16 LTS\n\
+                      OpenJDK Runtime Environment Temurin-21.0.3+9 (build 21.0.3+9-LTS)";
+        assert_eq!(parse_java_arch(output).as_deref(), Some("x64"));
+        assert_eq!(parse_java_version(output).unwrap().0, "21.0.3");
+        assert_eq!(parse_java_arch("    os.arch = arm64").as_deref(), Some("aarch64"));
+        assert_eq!(parse_java_arch("    os.arch = i386").as_deref(), Some("x86"));
+        assert_eq!(parse_java_arch("openjdk version \"21\""), None);
+    }
+
+    #[test]
+    fn test_requirements() {
+        let runtime = |version: &str, major_version| JavaRuntime {
+            home: PathBuf::from("/opt/jdk"),
+            version: version.to_string(),
+            major_version,
+            vendor: "Temurin".to_string(),
+            arch: Some(host_arch()),
+            managed: false,
+        };
+        let at_least_17: JavaRequirement = ">=17".parse().unwrap();
+        assert!(at_least_17.matches(&runtime("21.0.3", 21)));
+        assert!(at_least_17.matches(&runtime("17.0.9.9", 17)));
+        assert!(at_least_17.matches(&runtime("25-ea", 25)));
+        assert!(!at_least_17.matches(&runtime("1.8.0_392", 8)));
+        assert_eq!(at_least_17.best_major(), Some(25));
+
+        let exactly_21: JavaRequirement = "21".parse().unwrap();
+        assert!(exactly_21.matches(&runtime("21.0.3", 21)));
+        assert!(!exactly_21.matches(&runtime("25", 25)));
+        assert_eq!(exactly_21.best_major(), Some(21));
+        assert_eq!("<17".parse::<JavaRequirement>().unwrap().best_major(), None);
+        assert!(matches!("newest".parse::<JavaRequirement>(), Err(JavaError::InvalidRequirement(_))));
+
+        assert!(matches!(check_runtime(&runtime("1.8.0_392", 8), Some(&at_least_17)), Err(JavaError::Unsuitable { .. })));
+        let foreign = JavaRuntime { arch: Some("sparc".to_string()), ..runtime("21.0.3", 21) };
+        assert!(matches!(check_runtime(&foreign, None), Err(JavaError::WrongArchitecture { .. })));
+    }
+
+    #[test]
+    fn test_parse_reg_query() {
+        let output = "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\JavaSoft\\JDK\\21\r\n\
+                      \x20   JavaHome    REG_SZ    C:\\Program Files\\Java\\jdk-21\r\n\
+                      \x20   RuntimeLib    REG_SZ    C:\\Program Files\\Java\\jdk-21\\bin\\server\\jvm.dll\r\n\
+                      \r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Eclipse Adoptium\\JRE\\17.0.9.9\\hotspot\\MSI\r\n\
+                      \x20   Path    REG_SZ    C:\\Program Files\\Eclipse Adoptium\\jre-17.0.9.9-hotspot\\\r\n";
+        assert_eq!(parse_reg_query(output), vec![
+            PathBuf::from("C:\\Program Files\\Java\\jdk-21"),
+            PathBuf::from("C:\\Program Files\\Eclipse Adoptium\\jre-17.0.9.9-hotspot\\"),
+        ]);
+    }
+
+    /// Serves a local archive instead of hitting Adoptium
+    struct FixtureSource {
+        archive: PathBuf,
+        sha256: String,
+        fail: bool,
+        downloads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl RuntimeSource for FixtureSource {
+        async fn resolve(&self, major_version: u32) -> Result<RuntimePackage, JavaError> {
+            Ok(RuntimePackage {
+                major_version,
+                release_name: "jdk-21.0.3+9-jre".to_string(),
+                archive_name: "OpenJDK21U-jre.tar.gz".to_string(),
+                url: "file://fixture".to_string(),
+                sha256: self.sha256.clone(),
+            })
+        }
+
+        async fn download(&self, _package: &RuntimePackage, dest: &Path, progress: &InstallProgress) -> Result<(), JavaError> {
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            let bytes = tokio::fs::copy(&self.archive, dest).await?;
+            progress.set_total(bytes);
+            progress.add_downloaded(bytes);
+            if self.fail {
+                return Err(JavaError::DownloadFailed("connection reset".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    fn fixture_archive(dir: &Path) -> (PathBuf, String) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let release = dir.join("src").join("jdk-21.0.3+9-jre");
+        std::fs::create_dir_all(release.join("bin")).unwrap();
+        let java = release.join("bin").join("java");
+        std::fs::write(&java, "#!/bin/sh\n\
+            echo 'openjdk version \"21.0.3\" 2024-04-16 LTS' >&2\n\
+            echo 'OpenJDK Runtime Environment Temurin-21.0.3+9 (build 21.0.3+9-LTS)' >&2\n").unwrap();
+        std::fs::set_permissions(&java, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let archive = dir.join("fixture.tar.gz");
+        let status = std::process::Command::new("tar")
+            .arg("-czf").arg(&archive)
+            .arg("-C").arg(dir.join("src"))
+            .arg("jdk-21.0.3+9-jre")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let sha256 = hex::encode(Sha256::digest(std::fs::read(&archive).unwrap()));
+        (archive, sha256)
+    }
+
+    #[cfg(unix)]
+    async fn manager(dir: &Path, sha256: Option<&str>, fail: bool) -> (JavaManager, Arc<AtomicUsize>) {
+        let (archive, actual) = fixture_archive(dir);
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let source = FixtureSource {
+            archive,
+            sha256: sha256.unwrap_or(&actual).to_string(),
+            fail,
+            downloads: downloads.clone(),
+        };
+        (JavaManager::load(&dir.join("data"), Box::new(source)).await, downloads)
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-java-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn leftovers(dir: &Path) -> Vec<String> {
+        std::fs::read_dir(dir).map(|entries| {
+            entries.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().to_string()).collect()
+        }).unwrap_or_default()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_provision_extracts_and_detects_version() {
+        let dir = temp_dir();
+        let (mut java, downloads) = manager(&dir, None, false).await;
+
+        let runtime = java.provision(21).await.unwrap();
+        assert_eq!(runtime.major_version, 21);
+        assert_eq!(runtime.version, "21.0.3");
+        assert!(runtime.managed);
+        assert_eq!(runtime.home, java.runtimes_dir().join("jdk-21.0.3+9-jre"));
+        assert_eq!(leftovers(&java.runtimes_dir().join(".downloads")), Vec::<String>::new());
+
+        // Already provisioned builds aren't downloaded again
+        java.provision(21).await.unwrap();
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+        assert!(java.list_runtimes().await.iter().any(|r| r.managed && r.major_version == 21));
+
+        java.set_profile_java("modded", Some(runtime.home.clone())).await.unwrap();
+        assert!(java.set_profile_java("broken", Some(dir.join("missing"))).await.is_err());
+        let reloaded = JavaManager::load(&dir.join("data"), Box::new(AdoptiumSource::new())).await;
+        assert_eq!(reloaded.profile_java("modded"), Some(runtime.home.as_path()));
+        assert_eq!(reloaded.profile_java("broken"), None);
+        assert_eq!(java.install_progress().status().stage, InstallStage::Done);
+
+        // A suitable runtime is reused, and pointing at its binary works too
+        let requirement: JavaRequirement = ">=17".parse().unwrap();
+        assert_eq!(java.install_managed(Some(&requirement), None).await.unwrap(), (runtime.clone(), false));
+        let set = java.set_java_path("modded", &runtime.home.join("bin").join("java"), Some(&requirement)).await.unwrap();
+        assert_eq!(set.home, runtime.home);
+        let too_new: JavaRequirement = ">=25".parse().unwrap();
+        assert!(matches!(
+            java.set_java_path("modded", &runtime.home, Some(&too_new)).await,
+            Err(JavaError::Unsuitable { .. })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_downloads_leave_nothing_behind() {
+        let dir = temp_dir();
+        let (java, _) = manager(&dir, Some(&"0".repeat(64)), false).await;
+        assert!(matches!(java.provision(21).await, Err(JavaError::ChecksumMismatch { .. })));
+        let status = java.install_progress().status();
+        assert_eq!(status.stage, InstallStage::Failed);
+        assert_eq!(Some(status.downloaded_bytes), status.total_bytes);
+        assert_eq!(leftovers(&java.runtimes_dir().join(".downloads")), Vec::<String>::new());
+        assert_eq!(leftovers(java.runtimes_dir()), vec![".downloads".to_string()]);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let dir = temp_dir();
+        let (java, _) = manager(&dir, None, true).await;
+        assert!(matches!(java.provision(21).await, Err(JavaError::DownloadFailed(_))));
+        assert_eq!(leftovers(&java.runtimes_dir().join(".downloads")), Vec::<String>::new());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -6,22 +6,40 @@
 //! # Features
 //! - Launch game with custom environment variables, working directory, and arguments
 //! - Track process PID and state
-//! - Detect crashes and clean exits
-//! - Clean shutdown handling
+//! - Detect crashes and clean exits, watching the process in the background
+//! - Clean shutdown: ask the game to close, and kill it only if it hasn't
+//!   within the shutdown timeout
+//! - Safe-mode recommendations after repeated startup crashes
+
+pub mod safe_mode;
+pub mod validation;
 
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{info, warn, error};
 
+use safe_mode::{CrashRecoverySuggestion, CrashTracker, GameExitReport, LaunchRecommendation, DEFAULT_PROFILE};
+use validation::LaunchValidationError;
+
+/// How long the game gets to close after being asked, before it's killed
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running game is checked for having exited
+const MONITOR_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often `terminate` checks whether the game has closed
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 #[derive(Error, Debug)]
 pub enum LauncherError {
-    #[error("Game executable not found: {0}")]
-    ExecutableNotFound(PathBuf),
+    #[error(transparent)]
+    Invalid(#[from] LaunchValidationError),
     
     #[error("Failed to launch game: {0}")]
     LaunchFailed(String),
@@ -50,6 +68,80 @@ pub struct LaunchConfig {
     
     /// Whether to inherit parent environment
     pub inherit_env: bool,
+    
+    /// Profile the launch belongs to, for crash tracking
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    
+    /// Launch with mods disabled, default performance settings and a cleared shader cache
+    #[serde(default)]
+    pub safe_mode: bool,
+    
+    /// Shader cache cleared by a safe-mode launch
+    #[serde(default)]
+    pub shader_cache_dir: Option<PathBuf>,
+    
+    /// Java runtime home exposed to the game as JAVA_HOME and on PATH
+    #[serde(default)]
+    pub java_runtime: Option<PathBuf>,
+    
+    /// Mods directory of the active mod profile
+    #[serde(default)]
+    pub mods_dir: Option<PathBuf>,
+    
+    /// Mods the active profile expects to find enabled in `mods_dir`
+    #[serde(default)]
+    pub mods: Vec<String>,
+}
+
+impl LaunchConfig {
+    /// Crash tracking key for this launch
+    pub fn profile_key(&self) -> &str {
+        self.profile_id.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Launch `executable_path` with no arguments, inheriting the environment
+    pub fn new(executable_path: impl Into<PathBuf>) -> Self {
+        Self {
+            executable_path: executable_path.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Append arguments after any already set
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env_vars.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_profile(mut self, profile_id: impl Into<String>) -> Self {
+        self.profile_id = Some(profile_id.into());
+        self
+    }
+
+    pub fn with_safe_mode(mut self, safe_mode: bool) -> Self {
+        self.safe_mode = safe_mode;
+        self
+    }
+
+    pub fn with_java_runtime(mut self, home: impl Into<PathBuf>) -> Self {
+        self.java_runtime = Some(home.into());
+        self
+    }
 }
 
 impl Default for LaunchConfig {
@@ -60,6 +152,12 @@ impl Default for LaunchConfig {
             args: Vec::new(),
             env_vars: HashMap::new(),
             inherit_env: true,
+            profile_id: None,
+            safe_mode: false,
+            shader_cache_dir: None,
+            java_runtime: None,
+            mods_dir: None,
+            mods: Vec::new(),
         }
     }
 }
@@ -75,21 +173,77 @@ pub enum ProcessState {
     Exited { code: i32 },
     /// Process crashed or was killed
     Crashed { reason: String },
+    /// Process was stopped through the launcher; `forced` when it had to be
+    /// killed
+    Terminated { forced: bool },
+}
+
+/// How the last run ended, as `get_last_exit` reports it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastExit {
+    /// None when the process ended on a signal
+    pub exit_code: Option<i32>,
+    pub crashed: bool,
+    pub runtime_seconds: u64,
+    pub terminated_by_user: bool,
+}
+
+impl From<&GameExitReport> for LastExit {
+    fn from(report: &GameExitReport) -> Self {
+        Self {
+            exit_code: report.exit_code,
+            crashed: report.crashed(),
+            runtime_seconds: report.uptime_secs,
+            terminated_by_user: report.terminated_by_user(),
+        }
+    }
 }
 
 /// Information about a launched process
 #[derive(Debug)]
 struct LaunchedProcess {
     child: Child,
-    #[allow(dead_code)] // Kept for future use (restart, config export)
     config: LaunchConfig,
     state: ProcessState,
+    launched_at: chrono::DateTime<chrono::Utc>,
+    started: Instant,
+    /// Set once the launcher has asked the process to close
+    terminating: bool,
+    exit_code: Option<i32>,
 }
 
-/// Service for managing game process lifecycle
+impl LaunchedProcess {
+    fn exit_report(&self) -> GameExitReport {
+        GameExitReport {
+            profile_id: self.config.profile_key().to_string(),
+            launched_at: self.launched_at,
+            exited_at: chrono::Utc::now(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            state: self.state.clone(),
+            safe_mode: self.config.safe_mode,
+            exit_code: self.exit_code,
+            mods: self.config.mods.clone(),
+        }
+    }
+}
+
+/// Service for managing game process lifecycle. Clones share the same
+/// process.
+#[derive(Clone)]
 pub struct LauncherService {
     /// Currently tracked process (if any)
     process: Arc<RwLock<Option<LaunchedProcess>>>,
+    
+    /// Startup crash streaks per profile
+    crashes: Arc<RwLock<CrashTracker>>,
+    
+    /// How the most recent run ended
+    last_exit: Arc<RwLock<Option<GameExitReport>>>,
+    
+    /// Every run's end, as it's detected
+    exits: broadcast::Sender<GameExitReport>,
+    
+    shutdown_timeout: Duration,
 }
 
 impl LauncherService {
@@ -97,15 +251,51 @@ impl LauncherService {
     pub fn new() -> Self {
         Self {
             process: Arc::new(RwLock::new(None)),
+            crashes: Arc::new(RwLock::new(CrashTracker::in_memory())),
+            last_exit: Arc::new(RwLock::new(None)),
+            exits: broadcast::channel(16).0,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
         }
     }
     
+    /// How long `terminate` waits for the game to close before killing it
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+    
+    /// Use a persisted crash history instead of an in-memory one
+    pub fn with_crash_tracker(mut self, tracker: CrashTracker) -> Self {
+        self.crashes = Arc::new(RwLock::new(tracker));
+        self
+    }
+    
+    /// Crash history shared with whatever records profile changes
+    pub fn crash_tracker(&self) -> Arc<RwLock<CrashTracker>> {
+        self.crashes.clone()
+    }
+    
+    /// What to offer before launching `profile_id` (or the default profile)
+    pub async fn launch_recommendation(&self, profile_id: Option<&str>) -> LaunchRecommendation {
+        // Pick up an exit that hasn't been polled yet
+        self.poll_status().await;
+        self.crashes.read().await.recommendation(profile_id.unwrap_or(DEFAULT_PROFILE))
+    }
+    
+    /// Whether the next launch of `profile_id` should be in safe mode, and
+    /// which mods were enabled when it last crashed
+    pub async fn crash_recovery_suggestion(&self, profile_id: Option<&str>) -> CrashRecoverySuggestion {
+        self.poll_status().await;
+        self.crashes.read().await.recovery_suggestion(profile_id.unwrap_or(DEFAULT_PROFILE))
+    }
+    
     /// Launch a game with the given configuration
     pub async fn launch(&self, config: LaunchConfig) -> Result<u32, LauncherError> {
-        // Verify executable exists
-        if !config.executable_path.exists() {
-            return Err(LauncherError::ExecutableNotFound(config.executable_path.clone()));
-        }
+        // Report everything that would stop the game, before spawning it
+        config.validate().await?;
+        
+        // Record how the previous run ended before it's replaced
+        self.poll_status().await;
         
         info!("Launching game: {:?}", config.executable_path);
         
@@ -126,6 +316,15 @@ impl LauncherService {
         if !config.inherit_env {
             cmd.env_clear();
         }
+        if let Some(ref java_home) = config.java_runtime {
+            let inherited = if config.inherit_env { std::env::var_os("PATH") } else { None };
+            let paths = std::iter::once(java_home.join("bin"))
+                .chain(inherited.iter().flat_map(std::env::split_paths));
+            if let Ok(path) = std::env::join_paths(paths) {
+                cmd.env("PATH", path);
+            }
+            cmd.env("JAVA_HOME", java_home);
+        }
         for (key, value) in &config.env_vars {
             cmd.env(key, value);
         }
@@ -141,7 +340,11 @@ impl LauncherService {
         })?;
         
         let pid = child.id();
-        info!("Game launched with PID: {}", pid);
+        if config.safe_mode {
+            info!("Game launched in safe mode with PID: {}", pid);
+        } else {
+            info!("Game launched with PID: {}", pid);
+        }
         
         // Store the process
         let mut process_guard = self.process.write().await;
@@ -149,6 +352,19 @@ impl LauncherService {
             child,
             config,
             state: ProcessState::Running { pid },
+            launched_at: chrono::Utc::now(),
+            started: Instant::now(),
+            terminating: false,
+            exit_code: None,
+        });
+        drop(process_guard);
+        
+        // Notice the exit even if nobody polls
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            while matches!(monitor.poll_status().await, ProcessState::Running { pid: running } if running == pid) {
+                tokio::time::sleep(MONITOR_INTERVAL).await;
+            }
         });
         
         Ok(pid)
@@ -163,16 +379,56 @@ impl LauncherService {
         }
     }
     
-    /// Check if the game process is still running and update state
+    /// The profile of the launch that is still running, if any
+    pub async fn active_profile(&self) -> Option<String> {
+        if !matches!(self.poll_status().await, ProcessState::Running { .. }) {
+            return None;
+        }
+        let process_guard = self.process.read().await;
+        process_guard.as_ref().map(|proc| proc.config.profile_key().to_string())
+    }
+    
+    /// Check if the game process is still running and update state.
+    /// The first poll that sees the process gone records its exit report.
     pub async fn poll_status(&self) -> ProcessState {
+        let (state, report) = self.poll_process().await;
+        if let Some(report) = report {
+            self.record_exit(report).await;
+        }
+        state
+    }
+    
+    /// How the most recent run ended, if one has
+    pub async fn last_exit(&self) -> Option<GameExitReport> {
+        self.poll_status().await;
+        self.last_exit.read().await.clone()
+    }
+    
+    /// Receive each run's exit report as it's detected
+    pub fn subscribe_exits(&self) -> broadcast::Receiver<GameExitReport> {
+        self.exits.subscribe()
+    }
+    
+    async fn record_exit(&self, report: GameExitReport) {
+        self.crashes.write().await.record_exit(report.clone()).await;
+        *self.last_exit.write().await = Some(report.clone());
+        let _ = self.exits.send(report);
+    }
+    
+    async fn poll_process(&self) -> (ProcessState, Option<GameExitReport>) {
         let mut process_guard = self.process.write().await;
         
         if let Some(ref mut proc) = *process_guard {
+            let was_running = matches!(proc.state, ProcessState::Running { .. });
             if let ProcessState::Running { pid: _ } = proc.state {
                 // Try to check if process has exited
                 match proc.child.try_wait() {
                     Ok(Some(status)) => {
-                        if status.success() {
+                        proc.exit_code = status.code();
+                        if proc.terminating {
+                            info!("Game closed on request");
+                            proc.state = ProcessState::Terminated { forced: false };
+                        } else if status.success() {
                             let code = status.code().unwrap_or(0);
                             info!("Game exited cleanly with code: {}", code);
                             proc.state = ProcessState::Exited { code };
@@ -195,45 +451,65 @@ impl LauncherService {
                     }
                 }
             }
-            proc.state.clone()
+            let exited = was_running && !matches!(proc.state, ProcessState::Running { .. });
+            (proc.state.clone(), exited.then(|| proc.exit_report()))
         } else {
-            ProcessState::Idle
+            (ProcessState::Idle, None)
         }
     }
     
-    /// Request the game process to terminate gracefully
+    /// Ask the game process to close, killing it if it hasn't within the
+    /// shutdown timeout
     pub async fn terminate(&self) -> Result<(), LauncherError> {
-        let mut process_guard = self.process.write().await;
+        let pid = {
+            let mut process_guard = self.process.write().await;
+            match process_guard.as_mut() {
+                Some(proc) if matches!(proc.state, ProcessState::Running { .. }) => {
+                    proc.terminating = true;
+                    proc.child.id()
+                }
+                _ => return Err(LauncherError::ProcessNotRunning),
+            }
+        };
         
-        if let Some(ref mut proc) = *process_guard {
-            info!("Requesting game termination...");
-            
-            // Request graceful termination
-            // Note: On Unix, we could send SIGTERM but that requires the nix crate
-            // For simplicity, we use kill() which works cross-platform
-            let _ = proc.child.kill();
-            
-            proc.state = ProcessState::Exited { code: 0 };
-            Ok(())
-        } else {
-            Err(LauncherError::ProcessNotRunning)
+        info!("Requesting game termination...");
+        match request_close(pid).await {
+            Ok(()) => {
+                let deadline = Instant::now() + self.shutdown_timeout;
+                while Instant::now() < deadline {
+                    if !matches!(self.poll_status().await, ProcessState::Running { .. }) {
+                        return Ok(());
+                    }
+                    tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                }
+                warn!("Game did not close within {:?}", self.shutdown_timeout);
+            }
+            Err(e) => warn!("Could not ask the game to close: {}", e),
+        }
+        
+        match self.kill().await {
+            // Closed while we were giving up on it
+            Err(LauncherError::ProcessNotRunning) => Ok(()),
+            result => result,
         }
     }
     
     /// Force kill the game process
     pub async fn kill(&self) -> Result<(), LauncherError> {
-        let mut process_guard = self.process.write().await;
-        
-        if let Some(ref mut proc) = *process_guard {
+        let report = {
+            let mut process_guard = self.process.write().await;
+            let Some(proc) = process_guard.as_mut().filter(|p| matches!(p.state, ProcessState::Running { .. })) else {
+                return Err(LauncherError::ProcessNotRunning);
+            };
+            
             warn!("Force killing game process...");
             proc.child.kill()?;
-            proc.state = ProcessState::Crashed {
-                reason: "Forcefully terminated".to_string(),
-            };
-            Ok(())
-        } else {
-            Err(LauncherError::ProcessNotRunning)
-        }
+            proc.exit_code = proc.child.wait().ok().and_then(|status| status.code());
+            proc.state = ProcessState::Terminated { forced: true };
+            proc.exit_report()
+        };
+        self.record_exit(report).await;
+        Ok(())
     }
     
     /// Clear the stored process state
@@ -249,6 +525,30 @@ impl Default for LauncherService {
     }
 }
 
+/// Ask a process to close the way a user would: SIGTERM on unix, a close
+/// message to its windows on Windows
+async fn request_close(pid: u32) -> std::io::Result<()> {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = tokio::process::Command::new("taskkill");
+        command.args(["/PID", &pid.to_string()]);
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = tokio::process::Command::new("kill");
+        command.args(["-TERM", &pid.to_string()]);
+        command
+    };
+    
+    let status = command.stdout(Stdio::null()).stderr(Stdio::null()).status().await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("close request failed ({})", status)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,4 +558,76 @@ mod tests {
         let launcher = LauncherService::new();
         assert!(matches!(launcher.get_state().await, ProcessState::Idle));
     }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_startup_crash_is_recorded_for_profile() {
+        let launcher = LauncherService::new();
+        let config = LaunchConfig {
+            executable_path: PathBuf::from("/bin/sh"),
+            working_dir: Some(std::env::temp_dir()),
+            args: vec!["-c".to_string(), "exit 3".to_string()],
+            profile_id: Some("modded".to_string()),
+            ..Default::default()
+        };
+        
+        for expected in [safe_mode::RecommendationLevel::OfferSafeMode, safe_mode::RecommendationLevel::SuggestSafeMode] {
+            launcher.launch(config.clone()).await.unwrap();
+            while matches!(launcher.poll_status().await, ProcessState::Running { .. }) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            
+            let recommendation = launcher.launch_recommendation(Some("modded")).await;
+            assert_eq!(recommendation.level, expected);
+            assert!(recommendation.last_exit.unwrap().crashed());
+        }
+        assert_eq!(launcher.launch_recommendation(None).await.consecutive_crashes, 0);
+    }
+    
+    #[cfg(unix)]
+    fn shell(script: &str) -> LaunchConfig {
+        LaunchConfig::new("/bin/sh").with_working_dir(std::env::temp_dir()).with_args(["-c", script])
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_is_noticed_without_polling() {
+        let launcher = LauncherService::new();
+        let mut exits = launcher.subscribe_exits();
+        launcher.launch(shell("sleep 0.2; exit 3")).await.unwrap();
+        
+        let report = tokio::time::timeout(std::time::Duration::from_secs(5), exits.recv()).await.unwrap().unwrap();
+        assert!(report.crashed());
+        assert_eq!(report.exit_code, Some(3));
+        assert_eq!(LastExit::from(&report), LastExit {
+            exit_code: Some(3),
+            crashed: true,
+            runtime_seconds: 0,
+            terminated_by_user: false,
+        });
+        assert!(launcher.terminate().await.is_err());
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_terminate_asks_before_killing() {
+        let launcher = LauncherService::new().with_shutdown_timeout(Duration::from_millis(300));
+        
+        // Closes when asked
+        launcher.launch(shell("sleep 30")).await.unwrap();
+        launcher.terminate().await.unwrap();
+        assert!(matches!(launcher.get_state().await, ProcessState::Terminated { forced: false }));
+        let exit = LastExit::from(&launcher.last_exit().await.unwrap());
+        assert!(exit.terminated_by_user && !exit.crashed);
+        
+        // Ignores the request and gets killed
+        launcher.launch(shell("trap '' TERM; sleep 5")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let started = Instant::now();
+        launcher.terminate().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(matches!(launcher.get_state().await, ProcessState::Terminated { forced: true }));
+        assert!(launcher.last_exit().await.unwrap().terminated_by_user());
+        assert_eq!(launcher.launch_recommendation(None).await.consecutive_crashes, 0);
+    }
 }
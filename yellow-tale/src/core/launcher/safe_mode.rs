@@ -0,0 +1,578 @@
+//! Crash tracking and safe-mode launches
+//!
+//! Every run the launcher observes ends in a `GameExitReport`. Runs that crash
+//! shortly after launch count toward a per-profile streak, and the streak
+//! decides what the next launch recommends:
+//! - one quick crash: offer safe mode
+//! - `SUGGEST_SAFE_MODE_AFTER` in a row: suggest safe mode
+//! - `SUSPECT_MOD_AFTER` in a row: also name the mod most recently added or
+//!   enabled on the profile as the likely culprit
+//!
+//! A safe-mode launch runs with mods disabled, default performance settings
+//! and a cleared shader cache. Each run records the mods it had, so a crash
+//! can name them and diagnostics can compare crash rates with and without
+//! mods.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{LaunchConfig, ProcessState};
+use crate::core::mods::activator::ActivationReport;
+use crate::core::performance::OptimizationSettings;
+
+/// A crash within this many seconds of launch counts toward the streak
+pub const QUICK_CRASH_WINDOW_SECS: u64 = 60;
+
+/// Consecutive quick crashes before safe mode is suggested
+pub const SUGGEST_SAFE_MODE_AFTER: u32 = 2;
+
+/// Consecutive quick crashes before a suspect mod is named
+pub const SUSPECT_MOD_AFTER: u32 = 4;
+
+/// Changes kept per profile
+const MAX_CHANGES: usize = 50;
+
+/// Key for launches that don't name a profile
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Set on the game process during a safe-mode launch
+pub const SAFE_MODE_ENV: &str = "YELLOW_TALE_SAFE_MODE";
+
+/// How a launched run ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameExitReport {
+    pub profile_id: String,
+    pub launched_at: DateTime<Utc>,
+    pub exited_at: DateTime<Utc>,
+    pub uptime_secs: u64,
+    pub state: ProcessState,
+    pub safe_mode: bool,
+    /// None when the process ended on a signal
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Mods enabled for the run; empty in safe mode
+    #[serde(default)]
+    pub mods: Vec<String>,
+}
+
+impl GameExitReport {
+    pub fn crashed(&self) -> bool {
+        matches!(self.state, ProcessState::Crashed { .. })
+    }
+
+    /// Stopped through the launcher rather than exiting on its own
+    pub fn terminated_by_user(&self) -> bool {
+        matches!(self.state, ProcessState::Terminated { .. })
+    }
+
+    pub fn is_quick_crash(&self) -> bool {
+        self.crashed() && self.uptime_secs <= QUICK_CRASH_WINDOW_SECS
+    }
+
+    pub fn had_mods(&self) -> bool {
+        !self.safe_mode && !self.mods.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileChangeKind {
+    ModAdded,
+    ModEnabled,
+    ModDisabled,
+    ModRemoved,
+}
+
+/// An entry in a profile's mod change history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileChange {
+    pub kind: ProfileChangeKind,
+    pub mod_id: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfileRecord {
+    consecutive_crashes: u32,
+    last_exit: Option<GameExitReport>,
+    /// The last run that had mods, which safe-mode runs don't replace
+    #[serde(default)]
+    last_modded_exit: Option<GameExitReport>,
+    changes: Vec<ProfileChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendationLevel {
+    /// Launch normally
+    Normal,
+    /// The last run crashed on startup; safe mode is available
+    OfferSafeMode,
+    /// Several runs in a row crashed on startup
+    SuggestSafeMode,
+    /// Crashes keep happening; a recently changed mod is the likely cause
+    SuspectMod,
+}
+
+/// What the UI should offer before the next launch of a profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchRecommendation {
+    pub profile_id: String,
+    pub level: RecommendationLevel,
+    pub consecutive_crashes: u32,
+    pub safe_mode_suggested: bool,
+    pub suspected_mod: Option<ProfileChange>,
+    pub last_exit: Option<GameExitReport>,
+    pub reason: Option<String>,
+}
+
+/// Whether to relaunch a profile in safe mode after its last run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashRecoverySuggestion {
+    pub profile_id: String,
+    /// The last run crashed with mods enabled
+    pub safe_mode_recommended: bool,
+    /// Mods enabled when the last run crashed
+    pub mods_at_crash: Vec<String>,
+    pub crashed_at: Option<DateTime<Utc>>,
+    pub consecutive_crashes: u32,
+    pub reason: Option<String>,
+}
+
+/// Per-profile crash streaks and mod change history, optionally persisted
+/// as JSON so a streak survives restarting the launcher
+#[derive(Debug, Default)]
+pub struct CrashTracker {
+    path: Option<PathBuf>,
+    profiles: HashMap<String, ProfileRecord>,
+}
+
+impl CrashTracker {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load the history at `path`; a missing or unreadable file starts empty
+    pub async fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let profiles = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable crash history {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path: Some(path), profiles }
+    }
+
+    async fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let contents = serde_json::to_string_pretty(&self.profiles)?;
+            tokio::fs::write(path, contents).await?;
+            Ok::<_, anyhow::Error>(())
+        }.await;
+        if let Err(e) = result {
+            warn!("Failed to save crash history: {}", e);
+        }
+    }
+
+    /// Update the profile's streak. Quick crashes extend it and any other
+    /// normal run ends it; safe-mode runs leave it alone since they don't
+    /// exercise the profile's own mods and settings.
+    pub async fn record_exit(&mut self, report: GameExitReport) -> LaunchRecommendation {
+        let record = self.profiles.entry(report.profile_id.clone()).or_default();
+        if !report.safe_mode {
+            if report.is_quick_crash() {
+                record.consecutive_crashes += 1;
+                warn!(
+                    "Profile {} crashed {}s after launch ({} in a row)",
+                    report.profile_id, report.uptime_secs, record.consecutive_crashes
+                );
+            } else {
+                record.consecutive_crashes = 0;
+            }
+        }
+        let profile_id = report.profile_id.clone();
+        if report.had_mods() {
+            record.last_modded_exit = Some(report.clone());
+        } else if !report.safe_mode {
+            record.last_modded_exit = None;
+        }
+        record.last_exit = Some(report);
+
+        self.save().await;
+        self.recommendation(&profile_id)
+    }
+
+    pub async fn record_change(&mut self, profile_id: &str, kind: ProfileChangeKind, mod_id: &str) {
+        self.push_change(profile_id, kind, mod_id, Utc::now());
+        self.save().await;
+    }
+
+    /// Record what a mod profile activation changed
+    pub async fn record_activation(&mut self, report: &ActivationReport) {
+        if report.dry_run || !report.success() {
+            return;
+        }
+        let now = Utc::now();
+        let changes = report.downloaded.iter().map(|id| (ProfileChangeKind::ModAdded, id))
+            .chain(report.enabled.iter().map(|id| (ProfileChangeKind::ModEnabled, id)))
+            .chain(report.disabled.iter().map(|id| (ProfileChangeKind::ModDisabled, id)));
+        for (kind, mod_id) in changes {
+            self.push_change(&report.profile_id, kind, mod_id, now);
+        }
+        self.save().await;
+    }
+
+    fn push_change(&mut self, profile_id: &str, kind: ProfileChangeKind, mod_id: &str, at: DateTime<Utc>) {
+        let record = self.profiles.entry(profile_id.to_string()).or_default();
+        record.changes.push(ProfileChange { kind, mod_id: mod_id.to_string(), at });
+        if record.changes.len() > MAX_CHANGES {
+            let excess = record.changes.len() - MAX_CHANGES;
+            record.changes.drain(..excess);
+        }
+    }
+
+    /// Safe mode is recommended when the profile's last normal run crashed
+    /// with mods; a safe-mode run since then doesn't change that
+    pub fn recovery_suggestion(&self, profile_id: &str) -> CrashRecoverySuggestion {
+        let record = self.profiles.get(profile_id);
+        let mut suggestion = CrashRecoverySuggestion {
+            profile_id: profile_id.to_string(),
+            consecutive_crashes: record.map_or(0, |r| r.consecutive_crashes),
+            ..Default::default()
+        };
+        let Some(crash) = record.and_then(|r| r.last_modded_exit.as_ref()).filter(|e| e.crashed()) else {
+            return suggestion;
+        };
+        suggestion.safe_mode_recommended = true;
+        suggestion.mods_at_crash = crash.mods.clone();
+        suggestion.crashed_at = Some(crash.exited_at);
+        suggestion.reason = Some(format!(
+            "The last launch crashed after {}s with {} mods enabled",
+            crash.uptime_secs, crash.mods.len()
+        ));
+        suggestion
+    }
+
+    pub fn recommendation(&self, profile_id: &str) -> LaunchRecommendation {
+        let record = self.profiles.get(profile_id);
+        let consecutive_crashes = record.map_or(0, |r| r.consecutive_crashes);
+        let last_exit = record.and_then(|r| r.last_exit.clone());
+
+        let level = match consecutive_crashes {
+            0 => RecommendationLevel::Normal,
+            n if n >= SUSPECT_MOD_AFTER => RecommendationLevel::SuspectMod,
+            n if n >= SUGGEST_SAFE_MODE_AFTER => RecommendationLevel::SuggestSafeMode,
+            _ => RecommendationLevel::OfferSafeMode,
+        };
+        let suspected_mod = match level {
+            RecommendationLevel::SuspectMod => record.and_then(|r| most_recent_addition(&r.changes)),
+            _ => None,
+        };
+        let reason = match level {
+            RecommendationLevel::Normal => None,
+            RecommendationLevel::OfferSafeMode => Some("The last launch crashed during startup".to_string()),
+            RecommendationLevel::SuggestSafeMode => Some(format!(
+                "The last {} launches crashed during startup", consecutive_crashes
+            )),
+            RecommendationLevel::SuspectMod => Some(match &suspected_mod {
+                Some(change) => format!(
+                    "The last {} launches crashed during startup; {} was the most recently added mod",
+                    consecutive_crashes, change.mod_id
+                ),
+                None => format!("The last {} launches crashed during startup", consecutive_crashes),
+            }),
+        };
+
+        LaunchRecommendation {
+            profile_id: profile_id.to_string(),
+            level,
+            consecutive_crashes,
+            safe_mode_suggested: level >= RecommendationLevel::SuggestSafeMode,
+            suspected_mod,
+            last_exit,
+            reason,
+        }
+    }
+}
+
+/// The latest mod added or enabled that hasn't since been disabled or removed
+fn most_recent_addition(changes: &[ProfileChange]) -> Option<ProfileChange> {
+    let mut dropped = HashSet::new();
+    for change in changes.iter().rev() {
+        match change.kind {
+            ProfileChangeKind::ModDisabled | ProfileChangeKind::ModRemoved => {
+                dropped.insert(change.mod_id.as_str());
+            }
+            ProfileChangeKind::ModAdded | ProfileChangeKind::ModEnabled => {
+                if !dropped.contains(change.mod_id.as_str()) {
+                    return Some(change.clone());
+                }
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunCounts {
+    pub runs: u32,
+    pub crashes: u32,
+}
+
+impl RunCounts {
+    fn add(&mut self, report: &GameExitReport) {
+        self.runs += 1;
+        self.crashes += u32::from(report.crashed());
+    }
+}
+
+/// Crashes among runs with mods against runs without them, safe mode
+/// included. Runs stopped by the user aren't counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashRates {
+    pub with_mods: RunCounts,
+    pub without_mods: RunCounts,
+}
+
+impl CrashRates {
+    pub fn from_exits<'a>(exits: impl IntoIterator<Item = &'a GameExitReport>) -> Self {
+        let mut rates = Self::default();
+        for report in exits.into_iter().filter(|r| !r.terminated_by_user()) {
+            if report.had_mods() {
+                rates.with_mods.add(report);
+            } else {
+                rates.without_mods.add(report);
+            }
+        }
+        rates
+    }
+}
+
+/// What a safe-mode launch changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SafeModeReport {
+    pub mods_disabled: Vec<String>,
+    pub shader_cache_cleared: usize,
+    pub performance: OptimizationSettings,
+    pub warnings: Vec<String>,
+}
+
+/// Prepare `config` for a safe-mode launch: clear its shader cache, drop its
+/// mod list and mark the process so nothing applies tuned performance
+/// settings. Disabling mods is left to the caller, which owns the mods
+/// directory.
+pub async fn apply_safe_mode(config: &mut LaunchConfig) -> SafeModeReport {
+    let mut report = SafeModeReport::default();
+    config.mods.clear();
+
+    if let Some(dir) = &config.shader_cache_dir {
+        match clear_shader_cache(dir).await {
+            Ok(removed) => report.shader_cache_cleared = removed,
+            Err(e) => report.warnings.push(format!("Could not clear shader cache: {}", e)),
+        }
+    }
+    config.env_vars.insert(SAFE_MODE_ENV.to_string(), "1".to_string());
+
+    report
+}
+
+/// Empty the shader cache directory, keeping the directory itself.
+/// Returns the number of entries removed.
+pub async fn clear_shader_cache(dir: &Path) -> std::io::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if entry.file_type().await?.is_dir() {
+            tokio::fs::remove_dir_all(&path).await?;
+        } else {
+            tokio::fs::remove_file(&path).await?;
+        }
+        removed += 1;
+    }
+    info!("Cleared {} shader cache entries in {:?}", removed, dir);
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit(profile: &str, uptime_secs: u64, crashed: bool, safe_mode: bool) -> GameExitReport {
+        let state = if crashed {
+            ProcessState::Crashed { reason: "Exit code: 1".to_string() }
+        } else {
+            ProcessState::Exited { code: 0 }
+        };
+        GameExitReport {
+            profile_id: profile.to_string(),
+            launched_at: Utc::now(),
+            exited_at: Utc::now(),
+            uptime_secs,
+            state,
+            safe_mode,
+            exit_code: Some(if crashed { 1 } else { 0 }),
+            mods: if safe_mode { Vec::new() } else { vec!["minimap".to_string()] },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recommendation_escalates_with_consecutive_crashes() {
+        let mut tracker = CrashTracker::in_memory();
+        tracker.record_change("modded", ProfileChangeKind::ModAdded, "minimap").await;
+        tracker.record_change("modded", ProfileChangeKind::ModEnabled, "shaders_plus").await;
+        tracker.record_change("modded", ProfileChangeKind::ModAdded, "backpacks").await;
+        tracker.record_change("modded", ProfileChangeKind::ModRemoved, "backpacks").await;
+
+        assert_eq!(tracker.recommendation("modded").level, RecommendationLevel::Normal);
+
+        let levels = [
+            RecommendationLevel::OfferSafeMode,
+            RecommendationLevel::SuggestSafeMode,
+            RecommendationLevel::SuggestSafeMode,
+            RecommendationLevel::SuspectMod,
+        ];
+        for (i, expected) in levels.into_iter().enumerate() {
+            let recommendation = tracker.record_exit(exit("modded", 5, true, false)).await;
+            assert_eq!(recommendation.level, expected);
+            assert_eq!(recommendation.consecutive_crashes, i as u32 + 1);
+        }
+
+        let recommendation = tracker.recommendation("modded");
+        assert!(recommendation.safe_mode_suggested);
+        assert_eq!(recommendation.suspected_mod.unwrap().mod_id, "shaders_plus");
+        assert_eq!(tracker.recommendation("vanilla").level, RecommendationLevel::Normal);
+    }
+
+    #[tokio::test]
+    async fn test_clean_or_late_exits_reset_the_streak() {
+        let mut tracker = CrashTracker::in_memory();
+        tracker.record_exit(exit(DEFAULT_PROFILE, 5, true, false)).await;
+        tracker.record_exit(exit(DEFAULT_PROFILE, 5, true, false)).await;
+
+        // Safe mode proves nothing about the profile's own setup
+        let recommendation = tracker.record_exit(exit(DEFAULT_PROFILE, 600, false, true)).await;
+        assert_eq!(recommendation.consecutive_crashes, 2);
+
+        let recommendation = tracker.record_exit(exit(DEFAULT_PROFILE, QUICK_CRASH_WINDOW_SECS + 1, true, false)).await;
+        assert_eq!(recommendation.level, RecommendationLevel::Normal);
+
+        tracker.record_exit(exit(DEFAULT_PROFILE, 5, true, false)).await;
+        let recommendation = tracker.record_exit(exit(DEFAULT_PROFILE, 3600, false, false)).await;
+        assert_eq!(recommendation.consecutive_crashes, 0);
+        assert!(recommendation.last_exit.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_history_persists_and_activations_are_recorded() {
+        let dir = std::env::temp_dir().join(format!("yt-crash-history-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("crash_history.json");
+
+        let mut tracker = CrashTracker::load(&path).await;
+        tracker.record_activation(&ActivationReport {
+            profile_id: "modded".to_string(),
+            downloaded: vec!["minimap".to_string()],
+            enabled: vec!["shaders_plus".to_string()],
+            ..Default::default()
+        }).await;
+        for _ in 0..SUSPECT_MOD_AFTER {
+            tracker.record_exit(exit("modded", 1, true, false)).await;
+        }
+
+        let reloaded = CrashTracker::load(&path).await;
+        let recommendation = reloaded.recommendation("modded");
+        assert_eq!(recommendation.consecutive_crashes, SUSPECT_MOD_AFTER);
+        assert_eq!(recommendation.suspected_mod.unwrap().kind, ProfileChangeKind::ModEnabled);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recovery_suggestion_survives_safe_mode_runs() {
+        let mut tracker = CrashTracker::in_memory();
+        assert!(!tracker.recovery_suggestion("modded").safe_mode_recommended);
+
+        // A late crash still counts; only startup crashes build a streak
+        let mut crash = exit("modded", 900, true, false);
+        crash.mods = vec!["minimap".to_string(), "shaders_plus".to_string()];
+        tracker.record_exit(crash).await;
+        let suggestion = tracker.recovery_suggestion("modded");
+        assert!(suggestion.safe_mode_recommended);
+        assert_eq!(suggestion.mods_at_crash, vec!["minimap", "shaders_plus"]);
+        assert_eq!(suggestion.consecutive_crashes, 0);
+
+        // Running in safe mode doesn't clear the crash it is working around
+        tracker.record_exit(exit("modded", 600, false, true)).await;
+        assert!(tracker.recovery_suggestion("modded").safe_mode_recommended);
+
+        // A clean run without mods does, as does a clean modded one
+        tracker.record_exit(exit("modded", 600, false, false)).await;
+        assert!(!tracker.recovery_suggestion("modded").safe_mode_recommended);
+
+        let mut vanilla_crash = exit("vanilla", 5, true, false);
+        vanilla_crash.mods.clear();
+        tracker.record_exit(vanilla_crash).await;
+        assert!(!tracker.recovery_suggestion("vanilla").safe_mode_recommended);
+    }
+
+    #[test]
+    fn test_crash_rates_split_runs_by_mods() {
+        let mut vanilla = exit(DEFAULT_PROFILE, 600, false, false);
+        vanilla.mods.clear();
+        let mut stopped = exit(DEFAULT_PROFILE, 600, false, false);
+        stopped.state = ProcessState::Terminated { forced: false };
+        let exits = [
+            exit(DEFAULT_PROFILE, 5, true, false),
+            exit(DEFAULT_PROFILE, 5, true, false),
+            exit(DEFAULT_PROFILE, 600, false, false),
+            exit(DEFAULT_PROFILE, 600, false, true),
+            exit(DEFAULT_PROFILE, 5, true, true),
+            vanilla,
+            stopped,
+        ];
+        let rates = CrashRates::from_exits(&exits);
+        assert_eq!(rates.with_mods, RunCounts { runs: 3, crashes: 2 });
+        assert_eq!(rates.without_mods, RunCounts { runs: 3, crashes: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_safe_mode_clears_cache_and_marks_process() {
+        let dir = std::env::temp_dir().join(format!("yt-shader-cache-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("vulkan")).unwrap();
+        std::fs::write(dir.join("vulkan").join("pipeline.bin"), b"cache").unwrap();
+        std::fs::write(dir.join("index.dat"), b"cache").unwrap();
+
+        let mut config = LaunchConfig {
+            shader_cache_dir: Some(dir.clone()),
+            safe_mode: true,
+            mods: vec!["minimap".to_string()],
+            ..Default::default()
+        };
+        let report = apply_safe_mode(&mut config).await;
+        assert!(config.mods.is_empty());
+        assert_eq!(report.shader_cache_cleared, 2);
+        assert!(report.warnings.is_empty());
+        assert_eq!(config.env_vars.get(SAFE_MODE_ENV).map(String::as_str), Some("1"));
+        assert!(dir.exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        assert_eq!(clear_shader_cache(&dir.join("missing")).await.unwrap(), 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
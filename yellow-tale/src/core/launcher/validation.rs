@@ -0,0 +1,220 @@
+//! Launch config validation
+//!
+//! Checks a launch config against the machine before anything is spawned,
+//! collecting every problem instead of stopping at the first:
+//! - The executable exists and, on unix, is executable
+//! - Java is there when the launch needs it: a configured runtime, or a
+//!   `java` on PATH for a `.jar`
+//! - The working directory exists and can be written to
+//! - Every mod the profile expects is enabled in the mods directory
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::LaunchConfig;
+use crate::core::java::java_executable;
+use crate::core::mods::activator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchProblemKind {
+    ExecutableNotFound,
+    ExecutableNotRunnable,
+    JavaNotFound,
+    WorkingDirNotWritable,
+    ModMissing,
+    ModDisabled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchProblem {
+    pub kind: LaunchProblemKind,
+    pub message: String,
+}
+
+impl LaunchProblem {
+    pub fn new(kind: LaunchProblemKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+/// Everything wrong with a launch config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchValidationError {
+    pub problems: Vec<LaunchProblem>,
+}
+
+impl fmt::Display for LaunchValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.problems.iter().map(|p| p.message.as_str()).collect();
+        write!(f, "Cannot launch: {}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for LaunchValidationError {}
+
+impl LaunchConfig {
+    /// Directory the game runs in: `working_dir`, or the executable's own
+    pub fn effective_working_dir(&self) -> Option<PathBuf> {
+        self.working_dir.clone().or_else(|| self.executable_path.parent().map(Path::to_path_buf))
+    }
+
+    /// Check the config against the machine without launching anything
+    pub async fn validate(&self) -> Result<(), LaunchValidationError> {
+        let mut problems = Vec::new();
+
+        let exe = &self.executable_path;
+        if !exe.is_file() {
+            problems.push(LaunchProblem::new(
+                LaunchProblemKind::ExecutableNotFound,
+                format!("Game executable not found: {}", exe.display()),
+            ));
+        } else if !is_runnable(exe) {
+            problems.push(LaunchProblem::new(
+                LaunchProblemKind::ExecutableNotRunnable,
+                format!("Game executable is not executable: {}", exe.display()),
+            ));
+        }
+
+        match &self.java_runtime {
+            Some(home) if !java_executable(home).is_file() => problems.push(LaunchProblem::new(
+                LaunchProblemKind::JavaNotFound,
+                format!("Java runtime {} has no {}", home.display(), java_executable(Path::new("")).display()),
+            )),
+            None if exe.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("jar")) && !java_on_path() => {
+                problems.push(LaunchProblem::new(
+                    LaunchProblemKind::JavaNotFound,
+                    "The game needs Java, but none is configured or on PATH",
+                ));
+            }
+            _ => {}
+        }
+
+        if let Some(dir) = self.effective_working_dir() {
+            if let Err(e) = probe_writable(&dir).await {
+                problems.push(LaunchProblem::new(
+                    LaunchProblemKind::WorkingDirNotWritable,
+                    format!("Working directory {} is not writable: {}", dir.display(), e),
+                ));
+            }
+        }
+
+        if let Some(mods_dir) = self.mods_dir.as_deref().filter(|_| !self.mods.is_empty() && !self.safe_mode) {
+            match activator::scan_dir(mods_dir).await {
+                Ok(scanned) => {
+                    for id in &self.mods {
+                        match scanned.get(id) {
+                            Some(found) if found.enabled => {}
+                            Some(_) => problems.push(LaunchProblem::new(
+                                LaunchProblemKind::ModDisabled,
+                                format!("Mod {} is disabled; activate the profile again", id),
+                            )),
+                            None => problems.push(LaunchProblem::new(
+                                LaunchProblemKind::ModMissing,
+                                format!("Mod {} is no longer in {}", id, mods_dir.display()),
+                            )),
+                        }
+                    }
+                }
+                Err(e) => problems.push(LaunchProblem::new(
+                    LaunchProblemKind::ModMissing,
+                    format!("Could not read {}: {}", mods_dir.display(), e),
+                )),
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(LaunchValidationError { problems })
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_runnable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_runnable(_path: &Path) -> bool {
+    true
+}
+
+fn java_on_path() -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| dir.parent().is_some_and(|home| java_executable(home).is_file()))
+    })
+}
+
+/// Create and remove a file in `dir`
+async fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".yt-launch-check-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&probe, b"ok").await?;
+    tokio::fs::remove_file(&probe).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(error: &LaunchValidationError) -> Vec<LaunchProblemKind> {
+        error.problems.iter().map(|p| p.kind).collect()
+    }
+
+    #[tokio::test]
+    async fn test_every_problem_is_reported() {
+        let dir = std::env::temp_dir().join(format!("yt-launch-check-{}", uuid::Uuid::new_v4()));
+        let mods_dir = dir.join("mods");
+        tokio::fs::create_dir_all(&mods_dir).await.unwrap();
+        tokio::fs::write(mods_dir.join("minimap.jar"), b"jar").await.unwrap();
+        tokio::fs::write(mods_dir.join("shaders.jar.disabled"), b"jar").await.unwrap();
+
+        let config = LaunchConfig {
+            executable_path: dir.join("HytaleClient"),
+            working_dir: Some(dir.join("missing")),
+            java_runtime: Some(dir.join("jre")),
+            mods_dir: Some(mods_dir.clone()),
+            mods: vec!["minimap".to_string(), "shaders".to_string(), "maps_plus".to_string()],
+            ..Default::default()
+        };
+        let error = config.validate().await.unwrap_err();
+        assert_eq!(kinds(&error), [
+            LaunchProblemKind::ExecutableNotFound,
+            LaunchProblemKind::JavaNotFound,
+            LaunchProblemKind::WorkingDirNotWritable,
+            LaunchProblemKind::ModDisabled,
+            LaunchProblemKind::ModMissing,
+        ]);
+        assert!(error.to_string().starts_with("Cannot launch: Game executable not found"));
+
+        // Safe mode runs without mods, so they aren't checked
+        let safe = LaunchConfig { safe_mode: true, ..config };
+        assert!(!kinds(&safe.validate().await.unwrap_err()).contains(&LaunchProblemKind::ModMissing));
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runnable_executable_passes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("yt-launch-check-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let exe = dir.join("HytaleClient");
+        tokio::fs::write(&exe, b"#!/bin/sh\n").await.unwrap();
+
+        let config = LaunchConfig::new(&exe);
+        let error = config.validate().await.unwrap_err();
+        assert_eq!(kinds(&error), [LaunchProblemKind::ExecutableNotRunnable]);
+
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(config.validate().await, Ok(()));
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -0,0 +1,250 @@
+//! Outgoing email
+//!
+//! Services send mail through the `Mailer` trait. `SmtpMailer` hands
+//! messages to an SMTP relay, such as the host's MTA, in plain SMTP without
+//! authentication; encryption and delivery onward are the relay's job.
+//! `MemoryMailer` keeps messages in memory for tests and development.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Longest an SMTP exchange may take from connecting to `QUIT`
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum MailError {
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Mail server rejected {command}: {reply}")]
+    Rejected { command: String, reply: String },
+
+    #[error("Mail server timed out")]
+    Timeout,
+
+    #[error("Mail server unreachable: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+/// Delivers through an SMTP relay
+pub struct SmtpMailer {
+    /// `host:port` of the relay
+    server: String,
+    from: String,
+    /// Name given in `EHLO`
+    hello: String,
+}
+
+impl SmtpMailer {
+    pub fn new(server: impl Into<String>, from: impl Into<String>) -> Self {
+        Self { server: server.into(), from: from.into(), hello: "localhost".to_string() }
+    }
+
+    async fn deliver(&self, email: &Email) -> Result<(), MailError> {
+        let stream = TcpStream::connect(&self.server).await?;
+        let mut stream = BufReader::new(stream);
+
+        expect_reply(&mut stream, "connect", 220).await?;
+        command(&mut stream, &format!("EHLO {}", self.hello), 250).await?;
+        command(&mut stream, &format!("MAIL FROM:<{}>", self.from), 250).await?;
+        command(&mut stream, &format!("RCPT TO:<{}>", email.to), 250).await?;
+        command(&mut stream, "DATA", 354).await?;
+        stream.get_mut().write_all(message(&self.from, email).as_bytes()).await?;
+        expect_reply(&mut stream, "message", 250).await?;
+        // The message is accepted; a failed goodbye doesn't change that
+        let _ = command(&mut stream, "QUIT", 221).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        check_address(&self.from)?;
+        check_address(&email.to)?;
+        tokio::time::timeout(SMTP_TIMEOUT, self.deliver(email))
+            .await
+            .map_err(|_| MailError::Timeout)?
+    }
+}
+
+/// Keeps sent mail instead of delivering it
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        check_address(&email.to)?;
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+/// Addresses go into SMTP commands and headers as they are, so anything
+/// that could end a line or the angle brackets is refused
+fn check_address(address: &str) -> Result<(), MailError> {
+    let valid = address.split_once('@').is_some_and(|(local, domain)| !local.is_empty() && !domain.is_empty())
+        && !address.chars().any(|c| c.is_control() || c.is_whitespace() || c == '<' || c == '>');
+    if valid {
+        Ok(())
+    } else {
+        Err(MailError::InvalidAddress(address.escape_debug().to_string()))
+    }
+}
+
+/// Headers and body with CRLF line endings and dot-stuffing, ending in the
+/// `.` line that closes `DATA`
+fn message(from: &str, email: &Email) -> String {
+    let subject: String = email.subject.chars().filter(|c| !c.is_control()).collect();
+    let mut message = format!(
+        "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        from,
+        email.to,
+        subject,
+        chrono::Utc::now().to_rfc2822(),
+    );
+    for line in email.body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    message
+}
+
+async fn command(stream: &mut BufReader<TcpStream>, line: &str, expected: u16) -> Result<(), MailError> {
+    stream.get_mut().write_all(format!("{}\r\n", line).as_bytes()).await?;
+    let name = line.split([' ', ':']).next().unwrap_or(line);
+    expect_reply(stream, name, expected).await
+}
+
+/// Read a possibly multi-line reply and check its code
+async fn expect_reply(stream: &mut BufReader<TcpStream>, command: &str, expected: u16) -> Result<(), MailError> {
+    let mut reply = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        reply.push_str(&line);
+        // "250-" continues the reply, "250 " ends it
+        if line.as_bytes().get(3) != Some(&b'-') {
+            break;
+        }
+    }
+
+    // RCPT TO may also answer 251, "will forward"
+    let code: u16 = reply.get(..3).and_then(|code| code.parse().ok()).unwrap_or(0);
+    if code == expected || (expected == 250 && code == 251) {
+        Ok(())
+    } else {
+        Err(MailError::Rejected { command: command.to_string(), reply: reply.trim_end().to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Accepts one message and returns everything the client sent
+    async fn fake_relay(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut transcript = String::new();
+        stream.get_mut().write_all(b"220 relay ready\r\n").await.unwrap();
+
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.unwrap() == 0 {
+                break;
+            }
+            transcript.push_str(&line);
+            let reply: &[u8] = if in_data {
+                if line != ".\r\n" {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else if line.starts_with("EHLO") {
+                b"250-relay\r\n250 8BITMIME\r\n"
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line.starts_with("QUIT") {
+                stream.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            stream.get_mut().write_all(reply).await.unwrap();
+        }
+        transcript
+    }
+
+    #[tokio::test]
+    async fn test_smtp_mailer_speaks_to_a_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let relay = tokio::spawn(fake_relay(listener));
+
+        let mailer = SmtpMailer::new(server, "noreply@yellowtale.example");
+        let email = Email {
+            to: "anna@example.com".to_string(),
+            subject: "Your code".to_string(),
+            body: "Code: 123456\n.hidden".to_string(),
+        };
+        mailer.send(&email).await.unwrap();
+
+        let transcript = relay.await.unwrap();
+        assert!(transcript.contains("MAIL FROM:<noreply@yellowtale.example>\r\n"));
+        assert!(transcript.contains("RCPT TO:<anna@example.com>\r\n"));
+        assert!(transcript.contains("Subject: Your code\r\n"));
+        // A body line starting with '.' is stuffed so it can't end the message
+        assert!(transcript.contains("\r\nCode: 123456\r\n..hidden\r\n.\r\n"));
+        assert!(transcript.ends_with("QUIT\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_addresses_that_could_inject_commands_are_refused() {
+        let mailer = MemoryMailer::new();
+        for to in ["anna@example.com>\r\nRCPT TO:<eve@example.com", "no-at-sign", "anna@"] {
+            let email = Email { to: to.to_string(), subject: String::new(), body: String::new() };
+            assert!(matches!(mailer.send(&email).await, Err(MailError::InvalidAddress(_))));
+        }
+        assert!(mailer.sent().is_empty());
+    }
+}
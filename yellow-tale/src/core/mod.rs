@@ -4,11 +4,13 @@
 //! - **game**: Modular game adapter layer (swap implementations for Hytale API)
 //! - **features**: Feature toggle system for premium/API-gated functionality
 //! - **launcher**: Process lifecycle control for game executables
+//! - **java**: Java runtime discovery and provisioning
+//! - **install**: Hytale installation detection and validation
 //! - **profiles**: User profile management and migration
 //! - **mods**: Generic mod orchestration (not a mod loader)
 //! - **cache**: Content-addressed storage with deduplication
 //! - **performance**: Pre-launch optimization (legal & safe)
-//! - **diagnostics**: Read-only system metrics collection
+//! - **diagnostics**: Read-only system metrics collection and bottleneck analysis
 //! - **sessions**: Session orchestration and P2P connection handling
 //! - **ipc**: UI communication layer
 //! - **telemetry**: Logging and metrics
@@ -16,13 +18,26 @@
 //! - **config**: Application configuration
 //! - **db**: PostgreSQL database for persistent storage
 //! - **users**: User authentication and account management
+//! - **mail**: Outgoing email (verification codes)
 //! - **friends**: Social features (friends, blocking)
+//! - **presence**: Friend online/offline changes pushed to the launcher
 //! - **relay**: WebSocket relay server for tunneling
 //! - **client**: HTTP client for central server
+//! - **settings_sync**: Cross-device settings sync
+//! - **updates**: Launcher self-update channel
+//! - **hosting**: Dedicated server process for locally hosted worlds
+//! - **integrity**: Signed file-hash attestation for Rubidium servers
+//! - **preload**: Server asset downloads ahead of joining
+//! - **pond**: Capability discovery handshake and join queue with Pond servers
+//! - **netdiag**: Ping and connection quality to game servers
+//! - **snapshots**: World, profile and mod list backups with retention
+//! - **health**: Component health checks behind `get_status`
 
 pub mod game;
 pub mod features;
 pub mod launcher;
+pub mod java;
+pub mod install;
 pub mod profiles;
 pub mod mods;
 pub mod cache;
@@ -35,9 +50,20 @@ pub mod util;
 pub mod config;
 pub mod db;
 pub mod users;
+pub mod mail;
 pub mod friends;
+pub mod presence;
 pub mod relay;
 pub mod client;
+pub mod settings_sync;
+pub mod updates;
+pub mod hosting;
+pub mod integrity;
+pub mod preload;
+pub mod pond;
+pub mod netdiag;
+pub mod snapshots;
+pub mod health;
 
 // Re-export commonly used types
 pub use game::{GameAdapter, GameProtocol, AssetLoader, EventBus, GameEvent};
@@ -55,3 +81,4 @@ pub use users::UserService;
 pub use friends::FriendsService;
 pub use relay::RelayServer;
 pub use client::ApiClient;
+pub use settings_sync::SettingsSync;
@@ -0,0 +1,481 @@
+//! Mod profile activation
+//!
+//! Materializes a profile's mod set in the mods directory:
+//! - Scans which mod files are currently enabled or disabled
+//! - Disables mods outside the profile by renaming them to `*.disabled`
+//! - Re-enables disabled mods the profile wants
+//! - Downloads mods that aren't on disk at all
+//!
+//! Downloads happen before anything is renamed, and every file operation is
+//! journaled, so a failed activation leaves the directory as it found it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use super::ModError;
+
+/// Extensions recognized as mod packages
+pub const MOD_EXTENSIONS: &[&str] = &["jar", "zip", "ytmod"];
+
+/// Suffix appended to a mod file to disable it
+pub const DISABLED_SUFFIX: &str = "disabled";
+
+/// A mod referenced by a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ProfileModRepr")]
+pub struct ProfileMod {
+    pub id: String,
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub download_url: Option<String>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Profiles stored by the server list mods either as bare ids or as objects
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProfileModRepr {
+    Id(String),
+    Full {
+        id: String,
+        #[serde(default)]
+        file_name: Option<String>,
+        #[serde(default)]
+        download_url: Option<String>,
+        #[serde(default)]
+        sha256: Option<String>,
+    },
+}
+
+impl From<ProfileModRepr> for ProfileMod {
+    fn from(repr: ProfileModRepr) -> Self {
+        match repr {
+            ProfileModRepr::Id(id) => ProfileMod { id, file_name: None, download_url: None, sha256: None },
+            ProfileModRepr::Full { id, file_name, download_url, sha256 } => {
+                ProfileMod { id, file_name, download_url, sha256 }
+            }
+        }
+    }
+}
+
+impl ProfileMod {
+    fn target_file_name(&self) -> String {
+        self.file_name.clone().unwrap_or_else(|| format!("{}.jar", self.id))
+    }
+}
+
+/// The mod set a profile wants active
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModProfileSpec {
+    pub id: String,
+    pub name: String,
+    pub mods: Vec<ProfileMod>,
+}
+
+/// A mod file found in the mods directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScannedMod {
+    pub id: String,
+    pub path: PathBuf,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedMod {
+    pub id: String,
+    pub error: String,
+}
+
+/// What an activation did (or would do, for a dry run)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivationReport {
+    pub profile_id: String,
+    pub dry_run: bool,
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+    pub downloaded: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub failed: Vec<FailedMod>,
+    pub rolled_back: bool,
+}
+
+impl ActivationReport {
+    pub fn success(&self) -> bool {
+        self.failed.is_empty() && !self.rolled_back
+    }
+}
+
+/// Fetches a mod package that isn't on disk yet
+#[async_trait]
+pub trait ModDownloader: Send + Sync {
+    async fn download(&self, module: &ProfileMod, dest: &Path) -> Result<(), String>;
+}
+
+/// Downloads over HTTP, keeping a content-addressed copy in the cache directory
+pub struct HttpModDownloader {
+    client: reqwest::Client,
+    cache_dir: PathBuf,
+}
+
+impl HttpModDownloader {
+    pub fn new(cache_dir: PathBuf) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            cache_dir,
+        }
+    }
+}
+
+#[async_trait]
+impl ModDownloader for HttpModDownloader {
+    async fn download(&self, module: &ProfileMod, dest: &Path) -> Result<(), String> {
+        if let Some(hash) = &module.sha256 {
+            let cached = self.cache_dir.join(hash);
+            if tokio::fs::copy(&cached, dest).await.is_ok() {
+                return Ok(());
+            }
+        }
+
+        let url = module.download_url.as_ref()
+            .ok_or_else(|| format!("No download source for {}", module.id))?;
+        let response = self.client.get(url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+
+        let hash = hex::encode(Sha256::digest(&bytes));
+        if let Some(expected) = &module.sha256 {
+            if !expected.eq_ignore_ascii_case(&hash) {
+                return Err(format!("Checksum mismatch for {}", module.id));
+            }
+        }
+
+        tokio::fs::write(dest, &bytes).await.map_err(|e| e.to_string())?;
+        if tokio::fs::create_dir_all(&self.cache_dir).await.is_ok() {
+            let _ = tokio::fs::write(self.cache_dir.join(&hash), &bytes).await;
+        }
+        Ok(())
+    }
+}
+
+/// A file operation that can be undone
+#[derive(Debug)]
+enum JournalEntry {
+    Renamed { from: PathBuf, to: PathBuf },
+    Created(PathBuf),
+}
+
+/// Applies mod profiles to a mods directory
+pub struct ProfileActivator {
+    mods_dir: PathBuf,
+    downloader: Box<dyn ModDownloader>,
+}
+
+impl ProfileActivator {
+    pub fn new(mods_dir: PathBuf, downloader: Box<dyn ModDownloader>) -> Self {
+        Self { mods_dir, downloader }
+    }
+
+    pub fn mods_dir(&self) -> &Path {
+        &self.mods_dir
+    }
+
+    /// Scan the mods directory for enabled and disabled mod files
+    pub async fn scan(&self) -> Result<BTreeMap<String, ScannedMod>, ModError> {
+        scan_dir(&self.mods_dir).await
+    }
+
+    /// Make the mods directory match the profile
+    pub async fn activate(&self, profile: &ModProfileSpec, dry_run: bool) -> Result<ActivationReport, ModError> {
+        tokio::fs::create_dir_all(&self.mods_dir).await?;
+        let scanned = self.scan().await?;
+        let wanted: BTreeSet<&str> = profile.mods.iter().map(|m| m.id.as_str()).collect();
+
+        let mut report = ActivationReport {
+            profile_id: profile.id.clone(),
+            dry_run,
+            ..Default::default()
+        };
+
+        let mut to_download = Vec::new();
+        let mut to_enable = Vec::new();
+        for module in &profile.mods {
+            match scanned.get(&module.id) {
+                Some(existing) if existing.enabled => report.unchanged.push(module.id.clone()),
+                Some(existing) => to_enable.push(existing),
+                None => to_download.push(module),
+            }
+        }
+        let to_disable: Vec<&ScannedMod> = scanned.values()
+            .filter(|m| m.enabled && !wanted.contains(m.id.as_str()))
+            .collect();
+
+        if dry_run {
+            report.downloaded = to_download.iter().map(|m| m.id.clone()).collect();
+            report.enabled = to_enable.iter().map(|m| m.id.clone()).collect();
+            report.disabled = to_disable.iter().map(|m| m.id.clone()).collect();
+            return Ok(report);
+        }
+
+        let mut journal = Vec::new();
+
+        for module in to_download {
+            let dest = self.mods_dir.join(module.target_file_name());
+            let partial = self.mods_dir.join(format!("{}.part", module.target_file_name()));
+            let result = match self.downloader.download(module, &partial).await {
+                Ok(()) => tokio::fs::rename(&partial, &dest).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    journal.push(JournalEntry::Created(dest));
+                    report.downloaded.push(module.id.clone());
+                }
+                Err(error) => {
+                    let _ = tokio::fs::remove_file(&partial).await;
+                    report.failed.push(FailedMod { id: module.id.clone(), error });
+                }
+            }
+        }
+
+        if report.failed.is_empty() {
+            for module in to_enable {
+                let target = enabled_path(&module.path);
+                match rename_unless_exists(&module.path, &target).await {
+                    Ok(()) => {
+                        journal.push(JournalEntry::Renamed { from: module.path.clone(), to: target });
+                        report.enabled.push(module.id.clone());
+                    }
+                    Err(e) => {
+                        report.failed.push(FailedMod { id: module.id.clone(), error: e.to_string() });
+                        break;
+                    }
+                }
+            }
+        }
+
+        if report.failed.is_empty() {
+            for module in to_disable {
+                let target = disabled_path(&module.path);
+                match rename_unless_exists(&module.path, &target).await {
+                    Ok(()) => {
+                        journal.push(JournalEntry::Renamed { from: module.path.clone(), to: target });
+                        report.disabled.push(module.id.clone());
+                    }
+                    Err(e) => {
+                        report.failed.push(FailedMod { id: module.id.clone(), error: e.to_string() });
+                        break;
+                    }
+                }
+            }
+        }
+
+        if !report.failed.is_empty() {
+            warn!("Activation of profile {} failed, rolling back {} changes", profile.name, journal.len());
+            rollback(journal).await;
+            report.rolled_back = true;
+            report.enabled.clear();
+            report.disabled.clear();
+            report.downloaded.clear();
+            return Ok(report);
+        }
+
+        info!(
+            "Activated profile {}: {} enabled, {} disabled, {} downloaded",
+            profile.name, report.enabled.len(), report.disabled.len(), report.downloaded.len()
+        );
+        Ok(report)
+    }
+}
+
+/// Enabled and disabled mod files in `mods_dir` by id; empty if the
+/// directory doesn't exist
+pub async fn scan_dir(mods_dir: &Path) -> Result<BTreeMap<String, ScannedMod>, ModError> {
+    let mut found = BTreeMap::new();
+    if !mods_dir.exists() {
+        return Ok(found);
+    }
+
+    let mut entries = tokio::fs::read_dir(mods_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some((id, enabled)) = classify(&path) {
+            // An enabled copy wins over a stale disabled one
+            if enabled || !found.contains_key(&id) {
+                found.insert(id.clone(), ScannedMod { id, path, enabled });
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+pub(super) async fn rename_unless_exists(from: &Path, to: &Path) -> std::io::Result<()> {
+    if to.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    tokio::fs::rename(from, to).await
+}
+
+async fn rollback(journal: Vec<JournalEntry>) {
+    for entry in journal.into_iter().rev() {
+        let result = match &entry {
+            JournalEntry::Renamed { from, to } => tokio::fs::rename(to, from).await,
+            JournalEntry::Created(path) => tokio::fs::remove_file(path).await,
+        };
+        if let Err(e) = result {
+            warn!("Rollback step {:?} failed: {}", entry, e);
+        }
+    }
+}
+
+/// Returns the mod id and whether the file is enabled, or None for non-mod files
+pub fn classify(path: &Path) -> Option<(String, bool)> {
+    let name = path.file_name()?.to_str()?;
+    let (name, enabled) = match name.strip_suffix(&format!(".{}", DISABLED_SUFFIX)) {
+        Some(stripped) => (stripped, false),
+        None => (name, true),
+    };
+    let (stem, ext) = name.rsplit_once('.')?;
+    if !MOD_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
+        return None;
+    }
+    Some((stem.to_lowercase().replace(' ', "_"), enabled))
+}
+
+pub(super) fn disabled_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", DISABLED_SUFFIX));
+    path.with_file_name(name)
+}
+
+pub(super) fn enabled_path(path: &Path) -> PathBuf {
+    path.with_extension("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    struct FakeDownloader {
+        failing: HashSet<String>,
+    }
+
+    #[async_trait]
+    impl ModDownloader for FakeDownloader {
+        async fn download(&self, module: &ProfileMod, dest: &Path) -> Result<(), String> {
+            if self.failing.contains(&module.id) {
+                return Err("connection refused".to_string());
+            }
+            tokio::fs::write(dest, b"fake jar").await.map_err(|e| e.to_string())
+        }
+    }
+
+    async fn temp_mods_dir(files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yt-activator-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        for file in files {
+            tokio::fs::write(dir.join(file), b"fake jar").await.unwrap();
+        }
+        dir
+    }
+
+    async fn listing(dir: &Path) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        let mut entries = tokio::fs::read_dir(dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            names.insert(entry.file_name().to_string_lossy().to_string());
+        }
+        names
+    }
+
+    fn activator(dir: &Path, failing: &[&str]) -> ProfileActivator {
+        ProfileActivator::new(dir.to_path_buf(), Box::new(FakeDownloader {
+            failing: failing.iter().map(|s| s.to_string()).collect(),
+        }))
+    }
+
+    fn profile(mods: &[&str]) -> ModProfileSpec {
+        ModProfileSpec {
+            id: "p1".to_string(),
+            name: "Test".to_string(),
+            mods: mods.iter().map(|id| ProfileMod {
+                id: id.to_string(),
+                file_name: None,
+                download_url: None,
+                sha256: None,
+            }).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_activation_enables_disables_and_downloads() {
+        let dir = temp_mods_dir(&["alpha.jar", "beta.jar", "gamma.jar.disabled", "notes.txt"]).await;
+        let report = activator(&dir, &[]).activate(&profile(&["alpha", "gamma", "delta"]), false).await.unwrap();
+
+        assert!(report.success());
+        assert_eq!(report.unchanged, vec!["alpha"]);
+        assert_eq!(report.enabled, vec!["gamma"]);
+        assert_eq!(report.disabled, vec!["beta"]);
+        assert_eq!(report.downloaded, vec!["delta"]);
+        assert_eq!(
+            listing(&dir).await,
+            ["alpha.jar", "beta.jar.disabled", "delta.jar", "gamma.jar", "notes.txt"]
+                .iter().map(|s| s.to_string()).collect()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_directory_untouched() {
+        let dir = temp_mods_dir(&["alpha.jar", "beta.jar"]).await;
+        let before = listing(&dir).await;
+        let report = activator(&dir, &[]).activate(&profile(&["alpha", "delta"]), true).await.unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.disabled, vec!["beta"]);
+        assert_eq!(report.downloaded, vec!["delta"]);
+        assert_eq!(listing(&dir).await, before);
+    }
+
+    #[tokio::test]
+    async fn test_failed_download_rolls_back() {
+        let dir = temp_mods_dir(&["alpha.jar", "beta.jar", "gamma.jar.disabled"]).await;
+        let before = listing(&dir).await;
+        let report = activator(&dir, &["broken"])
+            .activate(&profile(&["gamma", "delta", "broken"]), false)
+            .await
+            .unwrap();
+
+        assert!(!report.success());
+        assert!(report.rolled_back);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].id, "broken");
+        assert_eq!(listing(&dir).await, before);
+    }
+
+    #[test]
+    fn test_profile_mods_accept_bare_ids() {
+        let mods: Vec<ProfileMod> = serde_json::from_value(serde_json::json!([
+            "alpha",
+            { "id": "beta", "download_url": "https://example.com/beta.jar" }
+        ])).unwrap();
+
+        assert_eq!(mods[0].id, "alpha");
+        assert_eq!(mods[0].target_file_name(), "alpha.jar");
+        assert_eq!(mods[1].download_url.as_deref(), Some("https://example.com/beta.jar"));
+    }
+}
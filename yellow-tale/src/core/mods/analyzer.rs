@@ -0,0 +1,408 @@
+//! Mod conflict detection
+//!
+//! Checks the enabled mods from a scan against each other:
+//! - Two files with the same mod id
+//! - Mods that declare each other incompatible
+//! - Two mods providing the same capability
+//! - Two mods overriding the same game file
+//! - Dependencies whose installed version misses the requirement, and
+//!   requirements on one mod that no single version can meet
+//!
+//! Each conflict carries a severity and the fixes that would clear it.
+//! Disabled mods never conflict, so they're left out.
+
+use std::collections::BTreeMap;
+
+use semver::{Comparator, Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use super::scanner::ModInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Works, but one mod silently loses
+    Warning,
+    /// Likely to crash or refuse to load
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    DuplicateId,
+    Incompatible,
+    SharedProvide,
+    OverlappingOverride,
+    VersionMismatch,
+    RequirementClash,
+}
+
+/// A fix the UI can offer for a conflict
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Resolution {
+    /// Disable this file; named by file because duplicates share an id
+    Disable { mod_id: String, file_name: String },
+    /// Load this mod after the others so its version of the file wins
+    LoadLast { mod_id: String },
+    /// Install a version of this mod meeting `requirement`, or a newer one
+    /// when there is none
+    Update { mod_id: String, requirement: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModConflict {
+    pub kind: ConflictKind,
+    pub severity: Severity,
+    /// Mods involved, in scan order
+    pub mods: Vec<String>,
+    /// The capability, file or dependency the mods disagree on
+    pub subject: Option<String>,
+    pub message: String,
+    pub resolutions: Vec<Resolution>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictReport {
+    /// Set when the mods are the active profile's
+    pub profile_id: Option<String>,
+    /// Enabled mods that were checked
+    pub checked: usize,
+    pub conflicts: Vec<ModConflict>,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl ConflictReport {
+    pub fn has_errors(&self) -> bool {
+        self.errors > 0
+    }
+}
+
+/// Checks a set of scanned mods against each other
+pub struct ModAnalyzer<'a> {
+    mods: Vec<&'a ModInfo>,
+}
+
+impl<'a> ModAnalyzer<'a> {
+    pub fn new(mods: &'a [ModInfo]) -> Self {
+        Self { mods: mods.iter().filter(|m| m.enabled).collect() }
+    }
+
+    pub fn report(&self) -> ConflictReport {
+        let mut conflicts = Vec::new();
+        self.duplicate_ids(&mut conflicts);
+        self.incompatibilities(&mut conflicts);
+        self.shared(&mut conflicts, ConflictKind::SharedProvide, |m| &m.provides);
+        self.shared(&mut conflicts, ConflictKind::OverlappingOverride, |m| &m.overrides);
+        self.versions(&mut conflicts);
+
+        let errors = conflicts.iter().filter(|c| c.severity == Severity::Error).count();
+        ConflictReport {
+            profile_id: None,
+            checked: self.mods.len(),
+            warnings: conflicts.len() - errors,
+            errors,
+            conflicts,
+        }
+    }
+
+    fn by_id(&self) -> BTreeMap<&'a str, Vec<&'a ModInfo>> {
+        let mut by_id: BTreeMap<&str, Vec<&ModInfo>> = BTreeMap::new();
+        for info in &self.mods {
+            by_id.entry(info.id.as_str()).or_default().push(info);
+        }
+        by_id
+    }
+
+    fn duplicate_ids(&self, out: &mut Vec<ModConflict>) {
+        for (id, copies) in self.by_id() {
+            if copies.len() < 2 {
+                continue;
+            }
+            // Keep the newest copy; offer to disable the rest
+            let newest = copies.iter()
+                .max_by_key(|m| m.version.as_deref().and_then(parse_version))
+                .map(|m| &m.file_path);
+            out.push(ModConflict {
+                kind: ConflictKind::DuplicateId,
+                severity: Severity::Error,
+                mods: vec![id.to_string()],
+                subject: Some(id.to_string()),
+                message: format!("{} files are enabled for {}: {}", copies.len(), id, file_names(&copies).join(", ")),
+                resolutions: copies.iter()
+                    .filter(|m| Some(&m.file_path) != newest)
+                    .map(|m| disable(m))
+                    .collect(),
+            });
+        }
+    }
+
+    fn incompatibilities(&self, out: &mut Vec<ModConflict>) {
+        let by_id = self.by_id();
+        let mut seen = Vec::new();
+        for info in &self.mods {
+            for other_id in &info.incompatibilities {
+                let Some(others) = by_id.get(other_id.as_str()) else {
+                    continue;
+                };
+                let pair = ordered(&info.id, other_id);
+                if seen.contains(&pair) {
+                    continue;
+                }
+                seen.push(pair);
+                out.push(ModConflict {
+                    kind: ConflictKind::Incompatible,
+                    severity: Severity::Error,
+                    mods: vec![info.id.clone(), other_id.clone()],
+                    subject: None,
+                    message: format!("{} is incompatible with {}", info.id, other_id),
+                    resolutions: others.iter().map(|m| disable(m)).chain([disable(info)]).collect(),
+                });
+            }
+        }
+    }
+
+    /// Values several mods claim, such as a capability or a file
+    fn shared(&self, out: &mut Vec<ModConflict>, kind: ConflictKind, values: fn(&ModInfo) -> &[String]) {
+        let mut claims: BTreeMap<&str, Vec<&ModInfo>> = BTreeMap::new();
+        for info in &self.mods {
+            for value in values(info) {
+                let claimants = claims.entry(value.as_str()).or_default();
+                // A duplicate file of the same mod is already its own conflict
+                if !claimants.iter().any(|m| m.id == info.id) {
+                    claimants.push(info);
+                }
+            }
+        }
+
+        for (value, claimants) in claims {
+            if claimants.len() < 2 {
+                continue;
+            }
+            let ids: Vec<String> = claimants.iter().map(|m| m.id.clone()).collect();
+            let (severity, message, resolutions) = match kind {
+                ConflictKind::OverlappingOverride => (
+                    Severity::Warning,
+                    format!("{} all replace {}; only the last one loaded takes effect", ids.join(", "), value),
+                    ids.iter().map(|id| Resolution::LoadLast { mod_id: id.clone() }).collect(),
+                ),
+                _ => (
+                    Severity::Error,
+                    format!("{} all provide {}", ids.join(", "), value),
+                    claimants.iter().map(|m| disable(m)).collect(),
+                ),
+            };
+            out.push(ModConflict { kind, severity, mods: ids, subject: Some(value.to_string()), message, resolutions });
+        }
+    }
+
+    fn versions(&self, out: &mut Vec<ModConflict>) {
+        let by_id = self.by_id();
+        let mut requirements: BTreeMap<&str, Vec<(&ModInfo, VersionReq)>> = BTreeMap::new();
+
+        for info in &self.mods {
+            for dep in &info.dependencies {
+                let Some(req) = dep.version_requirement.as_deref().and_then(|r| VersionReq::parse(r).ok()) else {
+                    continue;
+                };
+                requirements.entry(dep.mod_id.as_str()).or_default().push((info, req.clone()));
+
+                let installed = by_id.get(dep.mod_id.as_str()).into_iter().flatten()
+                    .filter_map(|m| Some((*m, parse_version(m.version.as_deref()?)?)));
+                for (target, version) in installed {
+                    if req.matches(&version) {
+                        continue;
+                    }
+                    out.push(ModConflict {
+                        kind: ConflictKind::VersionMismatch,
+                        severity: if dep.optional { Severity::Warning } else { Severity::Error },
+                        mods: vec![info.id.clone(), target.id.clone()],
+                        subject: Some(dep.mod_id.clone()),
+                        message: format!("{} needs {} {}, but {} is installed", info.id, dep.mod_id, req, version),
+                        resolutions: vec![
+                            Resolution::Update { mod_id: dep.mod_id.clone(), requirement: Some(req.to_string()) },
+                            disable(info),
+                        ],
+                    });
+                }
+            }
+        }
+
+        for (dep_id, reqs) in requirements {
+            if reqs.len() < 2 || satisfiable(reqs.iter().map(|(_, req)| req)) {
+                continue;
+            }
+            let ids: Vec<String> = reqs.iter().map(|(m, _)| m.id.clone()).collect();
+            let wanted: Vec<String> = reqs.iter().map(|(m, req)| format!("{} needs {}", m.id, req)).collect();
+            out.push(ModConflict {
+                kind: ConflictKind::RequirementClash,
+                severity: Severity::Error,
+                mods: ids,
+                subject: Some(dep_id.to_string()),
+                message: format!("No version of {} satisfies every mod: {}", dep_id, wanted.join("; ")),
+                resolutions: reqs.iter()
+                    .map(|(m, _)| Resolution::Update { mod_id: m.id.clone(), requirement: None })
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// Versions as mods write them: `2.1`, `v1.4.2` and `1.0.0-beta` all parse
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    if let Ok(parsed) = Version::parse(version) {
+        return Some(parsed);
+    }
+    let (core, rest) = match version.find(['-', '+']) {
+        Some(at) => version.split_at(at),
+        None => (version, ""),
+    };
+    let mut parts: Vec<&str> = core.split('.').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    parts.resize(3, "0");
+    Version::parse(&format!("{}{}", parts.join("."), rest)).ok()
+}
+
+/// Whether one version can meet every requirement. Only the versions the
+/// requirements name are tried, which is enough for the usual `^`, `~`,
+/// `>=` and `=` ranges.
+fn satisfiable<'r>(reqs: impl Iterator<Item = &'r VersionReq> + Clone) -> bool {
+    let candidates: Vec<Version> = reqs.clone()
+        .flat_map(|req| req.comparators.iter().map(lower_bound))
+        .collect();
+    candidates.is_empty() || candidates.iter().any(|v| reqs.clone().all(|req| req.matches(v)))
+}
+
+fn lower_bound(comparator: &Comparator) -> Version {
+    let mut version = Version::new(comparator.major, comparator.minor.unwrap_or(0), comparator.patch.unwrap_or(0));
+    if comparator.op == Op::Greater {
+        version.patch += 1;
+    }
+    version
+}
+
+fn disable(info: &ModInfo) -> Resolution {
+    Resolution::Disable { mod_id: info.id.clone(), file_name: file_name(info) }
+}
+
+fn file_name(info: &ModInfo) -> String {
+    info.file_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn file_names(mods: &[&ModInfo]) -> Vec<String> {
+    mods.iter().map(|m| file_name(m)).collect()
+}
+
+fn ordered<'s>(a: &'s str, b: &'s str) -> (&'s str, &'s str) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mods::scanner::ModScanner;
+    use std::path::PathBuf;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mod_conflicts");
+
+    async fn fixture_report() -> ConflictReport {
+        let result = ModScanner::new(PathBuf::from(FIXTURES)).scan().await.unwrap();
+        assert!(result.unreadable.is_empty(), "{:?}", result.unreadable);
+        ModAnalyzer::new(&result.mods).report()
+    }
+
+    fn find(report: &ConflictReport, kind: ConflictKind) -> Vec<&ModConflict> {
+        report.conflicts.iter().filter(|c| c.kind == kind).collect()
+    }
+
+    fn disables(conflict: &ModConflict) -> Vec<&str> {
+        conflict.resolutions.iter()
+            .filter_map(|r| match r {
+                Resolution::Disable { file_name, .. } => Some(file_name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_ids_keep_the_newest_file() {
+        let report = fixture_report().await;
+        let duplicates = find(&report, ConflictKind::DuplicateId);
+        // The disabled world_map-0.9 doesn't count against world_map.zip
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].subject.as_deref(), Some("minimap"));
+        assert_eq!(duplicates[0].severity, Severity::Error);
+        assert_eq!(disables(duplicates[0]), vec!["minimap-2.0.0.jar"]);
+    }
+
+    #[tokio::test]
+    async fn test_declared_incompatibility() {
+        let report = fixture_report().await;
+        let incompatible = find(&report, ConflictKind::Incompatible);
+        assert_eq!(incompatible.len(), 1);
+        assert_eq!(incompatible[0].mods, vec!["hud_plus", "old_hud"]);
+        assert_eq!(disables(incompatible[0]), vec!["old_hud-1.0.jar", "hud_plus.zip"]);
+    }
+
+    #[tokio::test]
+    async fn test_shared_provides_and_overrides() {
+        let report = fixture_report().await;
+
+        // world_map's manifest is TOML
+        let provides = find(&report, ConflictKind::SharedProvide);
+        assert_eq!(provides.len(), 1);
+        assert_eq!(provides[0].subject.as_deref(), Some("minimap"));
+        assert_eq!(provides[0].mods, vec!["minimap", "world_map"]);
+        assert_eq!(provides[0].severity, Severity::Error);
+
+        let overrides = find(&report, ConflictKind::OverlappingOverride);
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].subject.as_deref(), Some("ui/hud/map.png"));
+        assert_eq!(overrides[0].severity, Severity::Warning);
+        assert_eq!(overrides[0].resolutions, vec![
+            Resolution::LoadLast { mod_id: "hud_plus".into() },
+            Resolution::LoadLast { mod_id: "minimap".into() },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_version_mismatch_and_requirement_clash() {
+        let report = fixture_report().await;
+
+        let mismatches = find(&report, ConflictKind::VersionMismatch);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].mods, vec!["hud_plus", "chat_api"]);
+        assert_eq!(mismatches[0].resolutions[0], Resolution::Update {
+            mod_id: "chat_api".into(),
+            requirement: Some("^2.0".into()),
+        });
+
+        let clashes = find(&report, ConflictKind::RequirementClash);
+        assert_eq!(clashes.len(), 1);
+        assert_eq!(clashes[0].subject.as_deref(), Some("lib_core"));
+        assert_eq!(clashes[0].mods, vec!["party_tools", "raid_tools"]);
+
+        assert_eq!(report.checked, 8);
+        assert_eq!((report.errors, report.warnings), (5, 1));
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn test_lenient_versions_and_satisfiable_ranges() {
+        assert_eq!(parse_version("2.1"), Some(Version::new(2, 1, 0)));
+        assert_eq!(parse_version("v1.4.2"), Some(Version::new(1, 4, 2)));
+        assert_eq!(parse_version("3-beta").map(|v| v.pre.to_string()), Some("beta".to_string()));
+        assert_eq!(parse_version("nightly"), None);
+
+        let req = |r: &str| VersionReq::parse(r).unwrap();
+        assert!(satisfiable([req("^1.2"), req(">=1.4.0")].iter()));
+        assert!(satisfiable([req("~1.2.3"), req("^1")].iter()));
+        assert!(!satisfiable([req("^1.2"), req(">=2.0.0")].iter()));
+        assert!(!satisfiable([req("=1.0.0"), req(">1.0.0")].iter()));
+    }
+}
@@ -0,0 +1,109 @@
+//! Just enough of the ZIP format to pull a manifest out of a mod archive.
+//! Entries are found through the central directory and may be stored or
+//! deflated; ZIP64 and encrypted archives are reported as unreadable.
+
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const EOCD_LEN: usize = 22;
+
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    pub name: String,
+    method: u16,
+    flags: u16,
+    compressed_size: u64,
+    pub size: u64,
+    local_offset: u64,
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, String> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| "unexpected end of archive".to_string())
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32, String> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "unexpected end of archive".to_string())
+}
+
+/// Every entry listed in the archive's central directory
+pub fn entries(data: &[u8]) -> Result<Vec<ZipEntry>, String> {
+    if data.len() < EOCD_LEN {
+        return Err("not a zip archive".to_string());
+    }
+    // The end record sits before a comment of up to 64 KiB
+    let earliest = data.len().saturating_sub(EOCD_LEN + u16::MAX as usize);
+    let eocd = (earliest..=data.len() - EOCD_LEN)
+        .rev()
+        .find(|&at| u32_at(data, at) == Ok(EOCD_SIGNATURE))
+        .ok_or("not a zip archive")?;
+
+    let count = u16_at(data, eocd + 10)? as usize;
+    let offset = u32_at(data, eocd + 16)?;
+    if offset == u32::MAX || count == u16::MAX as usize {
+        return Err("ZIP64 archives are not supported".to_string());
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    let mut at = offset as usize;
+    for _ in 0..count {
+        if u32_at(data, at)? != CENTRAL_SIGNATURE {
+            return Err("corrupt central directory".to_string());
+        }
+        let name_len = u16_at(data, at + 28)? as usize;
+        let extra_len = u16_at(data, at + 30)? as usize;
+        let comment_len = u16_at(data, at + 32)? as usize;
+        let name = data.get(at + 46..at + 46 + name_len).ok_or("unexpected end of archive")?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(data, at + 10)?,
+            flags: u16_at(data, at + 8)?,
+            compressed_size: u32_at(data, at + 20)? as u64,
+            size: u32_at(data, at + 24)? as u64,
+            local_offset: u32_at(data, at + 42)? as u64,
+        });
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// Contents of `entry`, refusing anything that inflates past `limit` bytes
+pub fn read(data: &[u8], entry: &ZipEntry, limit: u64) -> Result<Vec<u8>, String> {
+    if entry.size > limit {
+        return Err(format!("{} is larger than {} bytes", entry.name, limit));
+    }
+    if entry.flags & 1 != 0 {
+        return Err(format!("{} is encrypted", entry.name));
+    }
+
+    let at = entry.local_offset as usize;
+    if u32_at(data, at)? != LOCAL_SIGNATURE {
+        return Err(format!("corrupt local header for {}", entry.name));
+    }
+    let start = at + 30 + u16_at(data, at + 26)? as usize + u16_at(data, at + 28)? as usize;
+    let compressed = data.get(start..start + entry.compressed_size as usize)
+        .ok_or_else(|| format!("{} is truncated", entry.name))?;
+
+    match entry.method {
+        0 => Ok(compressed.to_vec()),
+        8 => {
+            let mut out = Vec::with_capacity(entry.size as usize);
+            DeflateDecoder::new(compressed)
+                .take(limit + 1)
+                .read_to_end(&mut out)
+                .map_err(|e| format!("{} is corrupt: {}", entry.name, e))?;
+            if out.len() as u64 > limit {
+                return Err(format!("{} is larger than {} bytes", entry.name, limit));
+            }
+            Ok(out)
+        }
+        method => Err(format!("{} uses unsupported compression method {}", entry.name, method)),
+    }
+}
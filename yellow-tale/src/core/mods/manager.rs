@@ -0,0 +1,251 @@
+//! Individual mod management
+//!
+//! Installs, enables, disables and removes single mod files in the mods
+//! directory:
+//! - A mod is found by the id the scanner reports for it, or by file name
+//!   when two files claim the same id
+//! - Disabling renames the file to `*.disabled`, as profile activation does
+//! - Installing copies the package in under a temporary name first, and
+//!   refuses a file name or mod id that is already taken
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::activator::{classify, disabled_path, enabled_path, rename_unless_exists};
+use super::scanner::{self, ModScanner};
+use super::ModError;
+
+/// A mod file in the mods directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledMod {
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub enabled: bool,
+    pub file_name: String,
+    /// False for archives the scanner couldn't read
+    pub readable: bool,
+}
+
+/// Manages the mod files in one mods directory
+pub struct ModManager {
+    mods_dir: PathBuf,
+    scanner: ModScanner,
+}
+
+impl ModManager {
+    pub fn new(mods_dir: PathBuf) -> Self {
+        Self {
+            scanner: ModScanner::new(mods_dir.clone()),
+            mods_dir,
+        }
+    }
+
+    pub fn mods_dir(&self) -> &Path {
+        &self.mods_dir
+    }
+
+    /// Every mod file, readable or not, sorted by id
+    pub async fn list(&self) -> Result<Vec<InstalledMod>, ModError> {
+        let scan = self.scanner.scan().await?;
+        let mut mods: Vec<InstalledMod> = scan.mods.into_iter()
+            .map(|info| InstalledMod {
+                file_name: file_name(&info.file_path),
+                id: info.id,
+                name: info.name,
+                version: info.version,
+                enabled: info.enabled,
+                readable: true,
+            })
+            .collect();
+        mods.extend(scan.unreadable.iter().filter_map(|unreadable| {
+            let (id, enabled) = classify(&unreadable.file_path)?;
+            Some(InstalledMod {
+                name: id.clone(),
+                id,
+                version: None,
+                enabled,
+                file_name: file_name(&unreadable.file_path),
+                readable: false,
+            })
+        }));
+        mods.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.file_name.cmp(&b.file_name)));
+        Ok(mods)
+    }
+
+    /// Copy the package at `source` into the mods directory
+    pub async fn install(&self, source: &Path) -> Result<InstalledMod, ModError> {
+        let package = source.to_path_buf();
+        let info = tokio::task::spawn_blocking(move || scanner::read_info(&package))
+            .await
+            .map_err(|e| ModError::InvalidPackage(e.to_string()))?
+            .map_err(|e| ModError::InvalidPackage(format!("{}: {}", source.display(), e)))?;
+
+        let dest = self.mods_dir.join(file_name(source));
+        let enabled_dest = enabled_path_of(&dest);
+        if dest.exists() || enabled_dest.exists() || disabled_path(&enabled_dest).exists() {
+            return Err(ModError::AlreadyInstalled(file_name(&dest)));
+        }
+        if self.list().await?.iter().any(|m| m.id == info.id) {
+            return Err(ModError::AlreadyInstalled(info.id));
+        }
+
+        tokio::fs::create_dir_all(&self.mods_dir).await?;
+        let partial = self.mods_dir.join(format!(".{}.part", file_name(&dest)));
+        let copied = async {
+            tokio::fs::copy(source, &partial).await?;
+            rename_unless_exists(&partial, &dest).await
+        };
+        if let Err(e) = copied.await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e.into());
+        }
+
+        info!("Installed mod {} from {:?}", info.id, source);
+        Ok(InstalledMod {
+            id: info.id,
+            name: info.name,
+            version: info.version,
+            enabled: info.enabled,
+            file_name: file_name(&dest),
+            readable: true,
+        })
+    }
+
+    pub async fn enable(&self, mod_ref: &str) -> Result<InstalledMod, ModError> {
+        self.set_enabled(mod_ref, true).await
+    }
+
+    pub async fn disable(&self, mod_ref: &str) -> Result<InstalledMod, ModError> {
+        self.set_enabled(mod_ref, false).await
+    }
+
+    /// Delete a mod's file
+    pub async fn remove(&self, mod_ref: &str) -> Result<InstalledMod, ModError> {
+        let found = self.find(mod_ref).await?;
+        tokio::fs::remove_file(self.mods_dir.join(&found.file_name)).await?;
+        info!("Removed mod {} ({})", found.id, found.file_name);
+        Ok(found)
+    }
+
+    /// The one mod whose id or file name is `mod_ref`
+    pub async fn find(&self, mod_ref: &str) -> Result<InstalledMod, ModError> {
+        let mods = self.list().await?;
+        if let Some(by_file) = mods.iter().find(|m| m.file_name == mod_ref) {
+            return Ok(by_file.clone());
+        }
+        let mut matches = mods.into_iter().filter(|m| m.id.eq_ignore_ascii_case(mod_ref));
+        match (matches.next(), matches.next()) {
+            (Some(found), None) => Ok(found),
+            (Some(_), Some(_)) => Err(ModError::Ambiguous(mod_ref.to_string())),
+            (None, _) => Err(ModError::NotFound(mod_ref.to_string())),
+        }
+    }
+
+    async fn set_enabled(&self, mod_ref: &str, enabled: bool) -> Result<InstalledMod, ModError> {
+        let mut found = self.find(mod_ref).await?;
+        if found.enabled == enabled {
+            return Ok(found);
+        }
+
+        let path = self.mods_dir.join(&found.file_name);
+        let target = if enabled { enabled_path(&path) } else { disabled_path(&path) };
+        rename_unless_exists(&path, &target).await?;
+
+        info!("{} mod {}", if enabled { "Enabled" } else { "Disabled" }, found.id);
+        found.enabled = enabled;
+        found.file_name = file_name(&target);
+        Ok(found)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// `path` without a `.disabled` suffix
+fn enabled_path_of(path: &Path) -> PathBuf {
+    match classify(path) {
+        Some((_, false)) => enabled_path(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mods");
+
+    async fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yt-{}-{}", name, Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        dir
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(FIXTURES).join(name)
+    }
+
+    #[tokio::test]
+    async fn test_install_toggle_and_remove() {
+        let dir = temp_dir("mods").await;
+        let manager = ModManager::new(dir.clone());
+
+        let minimap = manager.install(&fixture("minimap-2.1.0.jar")).await.unwrap();
+        assert_eq!(minimap.id, "Hytale:Minimap");
+        assert_eq!(minimap.version.as_deref(), Some("2.1.0"));
+        manager.install(&fixture("legacy_hud-0.9.jar.disabled")).await.unwrap();
+
+        let listed = manager.list().await.unwrap();
+        let summary: Vec<_> = listed.iter().map(|m| (m.id.as_str(), m.enabled)).collect();
+        assert_eq!(summary, [("Hytale:Minimap", true), ("legacy_hud", false)]);
+
+        let disabled = manager.disable("hytale:minimap").await.unwrap();
+        assert_eq!(disabled.file_name, "minimap-2.1.0.jar.disabled");
+        assert!(dir.join("minimap-2.1.0.jar.disabled").exists());
+        // Already disabled: nothing to do
+        assert_eq!(manager.disable("Hytale:Minimap").await.unwrap(), disabled);
+
+        let enabled = manager.enable("legacy_hud").await.unwrap();
+        assert_eq!(enabled.file_name, "legacy_hud-0.9.jar");
+
+        manager.remove("Hytale:Minimap").await.unwrap();
+        assert!(!dir.join("minimap-2.1.0.jar.disabled").exists());
+        assert!(matches!(manager.remove("Hytale:Minimap").await, Err(ModError::NotFound(_))));
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_install_refuses_collisions_and_bad_packages() {
+        let dir = temp_dir("mods").await;
+        let manager = ModManager::new(dir.clone());
+        manager.install(&fixture("minimap-2.1.0.jar")).await.unwrap();
+
+        // Same file again, even once disabled
+        manager.disable("Hytale:Minimap").await.unwrap();
+        assert!(matches!(manager.install(&fixture("minimap-2.1.0.jar")).await, Err(ModError::AlreadyInstalled(_))));
+
+        // Another file claiming the same id
+        let elsewhere = temp_dir("mods-source").await;
+        let renamed = elsewhere.join("minimap-latest.jar");
+        tokio::fs::copy(fixture("minimap-2.1.0.jar"), &renamed).await.unwrap();
+        match manager.install(&renamed).await {
+            Err(ModError::AlreadyInstalled(id)) => assert_eq!(id, "Hytale:Minimap"),
+            other => panic!("expected a collision, got {:?}", other),
+        }
+
+        assert!(matches!(manager.install(&fixture("broken.jar")).await, Err(ModError::InvalidPackage(_))));
+        assert!(matches!(manager.install(&fixture("README.txt")).await, Err(ModError::InvalidPackage(_))));
+
+        let names: Vec<_> = manager.list().await.unwrap().into_iter().map(|m| m.file_name).collect();
+        assert_eq!(names, ["minimap-2.1.0.jar.disabled"]);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+        tokio::fs::remove_dir_all(&elsewhere).await.ok();
+    }
+}
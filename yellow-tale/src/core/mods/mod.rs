@@ -6,9 +6,22 @@
 //! - Version pinning
 //! - Dependency graph resolution
 //! - Per-profile mod sets
+//! - Reading mod metadata from archive manifests
+//! - Managing individual mod files in the mods directory
+//! - Detecting conflicts between enabled mods
+//! - Computing a profile's load order from mod manifests
+//! - Syncing mod profiles with the user's account
 //! 
 //! This is compatible with official mod systems without replacing them.
 
+pub mod activator;
+pub mod analyzer;
+mod archive;
+pub mod manager;
+pub mod profile_sync;
+pub mod resolver;
+pub mod scanner;
+
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
@@ -34,6 +47,12 @@ pub enum ModError {
     #[error("Circular dependency detected: {0}")]
     CircularDependency(String),
     
+    #[error("More than one mod file matches {0}; use the file name")]
+    Ambiguous(String),
+    
+    #[error("Not a usable mod package: {0}")]
+    InvalidPackage(String),
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -0,0 +1,550 @@
+//! Mod profile cloud sync
+//!
+//! Keeps a user's mod profiles the same on every device they log in from:
+//! - Profiles are stored locally in `<data_dir>/sync/mod_profiles.json`
+//! - Profiles only one side changed since the last sync are pushed or pulled
+//! - Profiles both sides changed are kept twice: the server's copy under the
+//!   original id, and ours renamed as a new profile
+//! - When the server can't be reached, local edits stay pending and the sync
+//!   is retried with backoff
+//!
+//! Deleting a profile isn't synced; the server can't delete profiles yet.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::activator::ProfileMod;
+use crate::core::client::{ApiClient, ClientError, RemoteModProfile};
+
+/// First retry delay after a failed sync; doubled per failure
+const RETRY_BASE_SECS: i64 = 30;
+
+/// Longest wait between retries
+const RETRY_MAX_SECS: i64 = 30 * 60;
+
+#[derive(Error, Debug)]
+pub enum ProfileSyncError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+/// Where profiles are synced to
+#[async_trait]
+pub trait ProfileRemote: Send + Sync {
+    async fn list_profiles(&self) -> Result<Vec<RemoteModProfile>, ClientError>;
+    async fn upsert_profile(&self, profile: &RemoteModProfile) -> Result<RemoteModProfile, ClientError>;
+}
+
+#[async_trait]
+impl ProfileRemote for ApiClient {
+    async fn list_profiles(&self) -> Result<Vec<RemoteModProfile>, ClientError> {
+        self.list_mod_profiles().await
+    }
+
+    async fn upsert_profile(&self, profile: &RemoteModProfile) -> Result<RemoteModProfile, ClientError> {
+        self.upsert_mod_profile(profile).await
+    }
+}
+
+/// A mod profile kept on this device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncedModProfile {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub mods: Vec<ProfileMod>,
+    pub updated_at: DateTime<Utc>,
+    /// `updated_at` of the copy this device and the server last agreed on
+    #[serde(default)]
+    pub base_updated_at: Option<DateTime<Utc>>,
+}
+
+impl SyncedModProfile {
+    fn has_local_changes(&self) -> bool {
+        self.base_updated_at != Some(self.updated_at)
+    }
+
+    fn same_content(&self, remote: &RemoteModProfile) -> bool {
+        self.name == remote.name && self.description == remote.description && self.mods == remote.mods
+    }
+
+    fn to_remote(&self) -> RemoteModProfile {
+        RemoteModProfile {
+            id: self.id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            mods: self.mods.clone(),
+            updated_at: self.updated_at,
+        }
+    }
+
+    fn from_remote(remote: &RemoteModProfile) -> Self {
+        Self {
+            id: remote.id,
+            name: remote.name.clone(),
+            description: remote.description.clone(),
+            mods: remote.mods.clone(),
+            updated_at: remote.updated_at,
+            base_updated_at: Some(remote.updated_at),
+        }
+    }
+}
+
+/// What the last sync did with one profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProfileSyncStatus {
+    InSync,
+    Pushed,
+    Pulled,
+    /// Both sides changed; the server's version kept the id and ours was
+    /// saved as `copy_id`
+    Conflict { copy_id: Uuid, copy_name: String },
+    /// Changed locally and waiting for the server to be reachable
+    Queued,
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSyncEntry {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(flatten)]
+    pub status: ProfileSyncStatus,
+}
+
+/// Per-profile outcome of a sync, also returned as the sync status
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileSyncReport {
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub profiles: Vec<ProfileSyncEntry>,
+    pub last_error: Option<String>,
+    pub next_retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RetryState {
+    attempts: u32,
+    next_retry_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfileSyncState {
+    profiles: Vec<SyncedModProfile>,
+    #[serde(default)]
+    statuses: BTreeMap<Uuid, ProfileSyncStatus>,
+    #[serde(default)]
+    last_synced_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_error: Option<String>,
+    #[serde(default)]
+    retry: Option<RetryState>,
+}
+
+/// Result of merging local profiles with the server's
+#[derive(Debug, Clone)]
+pub struct ProfileMerge {
+    pub profiles: Vec<SyncedModProfile>,
+    /// Ids of merged profiles the server doesn't have yet
+    pub to_push: Vec<Uuid>,
+    pub statuses: BTreeMap<Uuid, ProfileSyncStatus>,
+}
+
+/// Merge by `updated_at` against each profile's last synced version.
+///
+/// A profile only one side changed takes that side's copy. When both changed
+/// to different content, nothing is thrown away: the server's copy is pulled
+/// and ours becomes a new profile named "<name> (local copy)".
+pub fn merge_profiles(local: &[SyncedModProfile], remote: &[RemoteModProfile]) -> ProfileMerge {
+    let mut profiles = Vec::new();
+    let mut to_push = Vec::new();
+    let mut statuses = BTreeMap::new();
+
+    for mine in local {
+        let Some(theirs) = remote.iter().find(|r| r.id == mine.id) else {
+            to_push.push(mine.id);
+            profiles.push(mine.clone());
+            continue;
+        };
+
+        let remote_changed = mine.base_updated_at != Some(theirs.updated_at);
+        if mine.same_content(theirs) {
+            statuses.insert(mine.id, ProfileSyncStatus::InSync);
+            profiles.push(SyncedModProfile::from_remote(theirs));
+        } else if mine.has_local_changes() && remote_changed {
+            let copy = SyncedModProfile {
+                id: Uuid::new_v4(),
+                name: format!("{} (local copy)", mine.name),
+                base_updated_at: None,
+                ..mine.clone()
+            };
+            statuses.insert(mine.id, ProfileSyncStatus::Conflict {
+                copy_id: copy.id,
+                copy_name: copy.name.clone(),
+            });
+            to_push.push(copy.id);
+            profiles.push(SyncedModProfile::from_remote(theirs));
+            profiles.push(copy);
+        } else if mine.has_local_changes() {
+            to_push.push(mine.id);
+            profiles.push(mine.clone());
+        } else {
+            statuses.insert(mine.id, ProfileSyncStatus::Pulled);
+            profiles.push(SyncedModProfile::from_remote(theirs));
+        }
+    }
+
+    for theirs in remote.iter().filter(|r| !local.iter().any(|l| l.id == r.id)) {
+        statuses.insert(theirs.id, ProfileSyncStatus::Pulled);
+        profiles.push(SyncedModProfile::from_remote(theirs));
+    }
+
+    ProfileMerge { profiles, to_push, statuses }
+}
+
+/// Local profile store and sync driver
+pub struct ProfileSync {
+    path: PathBuf,
+    state: ProfileSyncState,
+}
+
+impl ProfileSync {
+    /// Load profiles from `<data_dir>/sync/mod_profiles.json`, starting empty if absent
+    pub async fn load(data_dir: &Path) -> Result<Self, ProfileSyncError> {
+        let path = data_dir.join("sync").join("mod_profiles.json");
+        let state = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProfileSyncState::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self { path, state })
+    }
+
+    async fn save(&self) -> Result<(), ProfileSyncError> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.path, serde_json::to_vec_pretty(&self.state)?).await?;
+        Ok(())
+    }
+
+    pub fn profiles(&self) -> &[SyncedModProfile] {
+        &self.state.profiles
+    }
+
+    /// Create or edit a profile; `id` of `None` creates a new one
+    pub async fn save_profile(
+        &mut self,
+        id: Option<Uuid>,
+        name: String,
+        description: Option<String>,
+        mods: Vec<ProfileMod>,
+    ) -> Result<SyncedModProfile, ProfileSyncError> {
+        let id = id.unwrap_or_else(Uuid::new_v4);
+        let updated_at = Utc::now();
+        let profile = match self.state.profiles.iter_mut().find(|p| p.id == id) {
+            Some(existing) => {
+                existing.name = name;
+                existing.description = description;
+                existing.mods = mods;
+                existing.updated_at = updated_at;
+                existing.clone()
+            }
+            None => {
+                let profile = SyncedModProfile { id, name, description, mods, updated_at, base_updated_at: None };
+                self.state.profiles.push(profile.clone());
+                profile
+            }
+        };
+        self.state.statuses.insert(id, ProfileSyncStatus::Queued);
+        self.save().await?;
+        Ok(profile)
+    }
+
+    /// Whether a failed sync is waiting to be retried and its time has come
+    pub fn retry_due(&self, now: DateTime<Utc>) -> bool {
+        self.state.retry.as_ref().is_some_and(|r| r.next_retry_at <= now)
+    }
+
+    pub fn status(&self) -> ProfileSyncReport {
+        let profiles = self.state.profiles.iter()
+            .map(|p| ProfileSyncEntry {
+                id: p.id,
+                name: p.name.clone(),
+                status: match self.state.statuses.get(&p.id) {
+                    Some(status) => status.clone(),
+                    None if p.has_local_changes() => ProfileSyncStatus::Queued,
+                    None => ProfileSyncStatus::InSync,
+                },
+            })
+            .collect();
+
+        ProfileSyncReport {
+            last_synced_at: self.state.last_synced_at,
+            profiles,
+            last_error: self.state.last_error.clone(),
+            next_retry_at: self.state.retry.as_ref().map(|r| r.next_retry_at),
+        }
+    }
+
+    /// Pull, merge, and push every profile the server is missing.
+    ///
+    /// Server errors don't fail the call: affected profiles stay queued and
+    /// a retry is scheduled. Only failing to save locally is an error.
+    pub async fn sync_now(&mut self, remote: &dyn ProfileRemote) -> Result<ProfileSyncReport, ProfileSyncError> {
+        let listed = match remote.list_profiles().await {
+            Ok(listed) => listed,
+            Err(e) => {
+                warn!("Mod profile sync failed: {}", e);
+                for profile in &self.state.profiles {
+                    if profile.has_local_changes() {
+                        self.state.statuses.insert(profile.id, ProfileSyncStatus::Queued);
+                    }
+                }
+                self.schedule_retry(e.to_string());
+                self.save().await?;
+                return Ok(self.status());
+            }
+        };
+
+        let merged = merge_profiles(&self.state.profiles, &listed);
+        let mut statuses = merged.statuses;
+        let mut profiles = merged.profiles;
+        let mut error = None;
+        for id in &merged.to_push {
+            let Some(profile) = profiles.iter_mut().find(|p| p.id == *id) else { continue };
+            match remote.upsert_profile(&profile.to_remote()).await {
+                Ok(_) => {
+                    profile.base_updated_at = Some(profile.updated_at);
+                    statuses.entry(*id).or_insert(ProfileSyncStatus::Pushed);
+                }
+                Err(e) => {
+                    warn!("Pushing mod profile {} failed: {}", profile.name, e);
+                    statuses.entry(*id).or_insert(ProfileSyncStatus::Failed { error: e.to_string() });
+                    error = Some(e.to_string());
+                }
+            }
+        }
+
+        let pushed = merged.to_push.len();
+        self.state.profiles = profiles;
+        self.state.statuses = statuses;
+        match error {
+            Some(error) => self.schedule_retry(error),
+            None => {
+                self.state.last_synced_at = Some(Utc::now());
+                self.state.last_error = None;
+                self.state.retry = None;
+                info!("Mod profiles synced ({} pushed, {} on the server)", pushed, listed.len());
+            }
+        }
+        self.save().await?;
+        Ok(self.status())
+    }
+
+    fn schedule_retry(&mut self, error: String) {
+        let attempts = self.state.retry.as_ref().map_or(0, |r| r.attempts) + 1;
+        let delay = RETRY_BASE_SECS.saturating_mul(1 << (attempts - 1).min(16)).min(RETRY_MAX_SECS);
+        self.state.retry = Some(RetryState {
+            attempts,
+            next_retry_at: Utc::now() + Duration::seconds(delay),
+        });
+        self.state.last_error = Some(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).unwrap()
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-profile-sync-{}", Uuid::new_v4()))
+    }
+
+    fn mods(ids: &[&str]) -> Vec<ProfileMod> {
+        ids.iter()
+            .map(|id| ProfileMod { id: id.to_string(), file_name: None, download_url: None, sha256: None })
+            .collect()
+    }
+
+    /// Stands in for the server, recording every push
+    #[derive(Default)]
+    struct FakeRemote {
+        profiles: Mutex<Vec<RemoteModProfile>>,
+        offline: Mutex<bool>,
+        pushes: Mutex<Vec<RemoteModProfile>>,
+    }
+
+    impl FakeRemote {
+        fn with(profiles: Vec<RemoteModProfile>) -> Self {
+            Self { profiles: Mutex::new(profiles), ..Default::default() }
+        }
+
+        fn check_online(&self) -> Result<(), ClientError> {
+            match *self.offline.lock().unwrap() {
+                true => Err(ClientError::Api("connection refused".into())),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ProfileRemote for FakeRemote {
+        async fn list_profiles(&self) -> Result<Vec<RemoteModProfile>, ClientError> {
+            self.check_online()?;
+            Ok(self.profiles.lock().unwrap().clone())
+        }
+
+        async fn upsert_profile(&self, profile: &RemoteModProfile) -> Result<RemoteModProfile, ClientError> {
+            self.check_online()?;
+            self.pushes.lock().unwrap().push(profile.clone());
+            let mut profiles = self.profiles.lock().unwrap();
+            profiles.retain(|p| p.id != profile.id);
+            profiles.push(profile.clone());
+            Ok(profile.clone())
+        }
+    }
+
+    fn remote_profile(id: Uuid, name: &str, mod_ids: &[&str], updated: i64) -> RemoteModProfile {
+        RemoteModProfile { id, name: name.into(), description: None, mods: mods(mod_ids), updated_at: at(updated) }
+    }
+
+    fn status_of(report: &ProfileSyncReport, id: Uuid) -> ProfileSyncStatus {
+        report.profiles.iter().find(|p| p.id == id).unwrap().status.clone()
+    }
+
+    #[tokio::test]
+    async fn test_new_local_profiles_are_pushed() {
+        let dir = temp_dir();
+        let mut sync = ProfileSync::load(&dir).await.unwrap();
+        let profile = sync.save_profile(None, "PvP".into(), None, mods(&["minimap"])).await.unwrap();
+        assert_eq!(status_of(&sync.status(), profile.id), ProfileSyncStatus::Queued);
+
+        let remote = FakeRemote::default();
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert_eq!(status_of(&report, profile.id), ProfileSyncStatus::Pushed);
+        assert_eq!(remote.pushes.lock().unwrap()[0], profile.clone().to_remote());
+        assert!(report.last_error.is_none());
+
+        // A second sync has nothing to do
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert_eq!(status_of(&report, profile.id), ProfileSyncStatus::InSync);
+        assert_eq!(remote.pushes.lock().unwrap().len(), 1);
+
+        // And the synced state survives a reload
+        let reloaded = ProfileSync::load(&dir).await.unwrap();
+        assert_eq!(reloaded.profiles()[0].base_updated_at, Some(profile.updated_at));
+    }
+
+    #[tokio::test]
+    async fn test_remote_profiles_and_edits_are_pulled() {
+        let dir = temp_dir();
+        let mut sync = ProfileSync::load(&dir).await.unwrap();
+        let id = Uuid::new_v4();
+        let remote = FakeRemote::with(vec![remote_profile(id, "Builder", &["worldedit"], 100)]);
+
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert_eq!(status_of(&report, id), ProfileSyncStatus::Pulled);
+        assert_eq!(sync.profiles()[0].mods, mods(&["worldedit"]));
+
+        // Another device edits it; we haven't touched our copy
+        *remote.profiles.lock().unwrap() = vec![remote_profile(id, "Builder", &["worldedit", "schematics"], 200)];
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert_eq!(status_of(&report, id), ProfileSyncStatus::Pulled);
+        assert_eq!(sync.profiles()[0].mods, mods(&["worldedit", "schematics"]));
+        assert!(remote.pushes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_edits_on_both_sides_keep_both_copies() {
+        let dir = temp_dir();
+        let mut sync = ProfileSync::load(&dir).await.unwrap();
+        let id = Uuid::new_v4();
+        let remote = FakeRemote::with(vec![remote_profile(id, "Survival", &["minimap"], 100)]);
+        sync.sync_now(&remote).await.unwrap();
+
+        sync.save_profile(Some(id), "Survival".into(), None, mods(&["minimap", "hud_plus"])).await.unwrap();
+        *remote.profiles.lock().unwrap() = vec![remote_profile(id, "Survival", &["minimap", "backpacks"], 150)];
+
+        let report = sync.sync_now(&remote).await.unwrap();
+        let ProfileSyncStatus::Conflict { copy_id, copy_name } = status_of(&report, id) else {
+            panic!("expected a conflict, got {:?}", report.profiles);
+        };
+        assert_eq!(copy_name, "Survival (local copy)");
+        assert_eq!(status_of(&report, copy_id), ProfileSyncStatus::Pushed);
+
+        let original = sync.profiles().iter().find(|p| p.id == id).unwrap();
+        assert_eq!(original.mods, mods(&["minimap", "backpacks"]));
+        let copy = sync.profiles().iter().find(|p| p.id == copy_id).unwrap();
+        assert_eq!(copy.mods, mods(&["minimap", "hud_plus"]));
+        assert_eq!(remote.profiles.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_offline_sync_queues_and_retries() {
+        let dir = temp_dir();
+        let mut sync = ProfileSync::load(&dir).await.unwrap();
+        let profile = sync.save_profile(None, "Offline".into(), None, mods(&["zoom"])).await.unwrap();
+
+        let remote = FakeRemote::default();
+        *remote.offline.lock().unwrap() = true;
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert_eq!(status_of(&report, profile.id), ProfileSyncStatus::Queued);
+        assert_eq!(report.last_error.as_deref(), Some("API error: connection refused"));
+        let first_retry = report.next_retry_at.unwrap();
+        assert!(!sync.retry_due(Utc::now()));
+        assert!(sync.retry_due(first_retry));
+
+        // Each failure waits longer
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert!(report.next_retry_at.unwrap() > first_retry);
+
+        *remote.offline.lock().unwrap() = false;
+        let report = sync.sync_now(&remote).await.unwrap();
+        assert_eq!(status_of(&report, profile.id), ProfileSyncStatus::Pushed);
+        assert!(report.next_retry_at.is_none());
+        assert!(report.last_error.is_none());
+    }
+
+    #[test]
+    fn test_merge_without_history_treats_differences_as_conflicts() {
+        // A profile that reached both sides before this device ever synced
+        let id = Uuid::new_v4();
+        let local = SyncedModProfile {
+            id,
+            name: "Shared".into(),
+            description: None,
+            mods: mods(&["a"]),
+            updated_at: at(300),
+            base_updated_at: None,
+        };
+
+        let same = merge_profiles(std::slice::from_ref(&local), &[remote_profile(id, "Shared", &["a"], 100)]);
+        assert_eq!(same.statuses.get(&id), Some(&ProfileSyncStatus::InSync));
+        assert!(same.to_push.is_empty());
+        assert_eq!(same.profiles[0].base_updated_at, Some(at(100)));
+
+        let differs = merge_profiles(&[local], &[remote_profile(id, "Shared", &["b"], 100)]);
+        assert!(matches!(differs.statuses.get(&id), Some(ProfileSyncStatus::Conflict { .. })));
+        assert_eq!(differs.profiles.len(), 2);
+        assert_eq!(differs.to_push.len(), 1);
+    }
+}
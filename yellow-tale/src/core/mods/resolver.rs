@@ -0,0 +1,325 @@
+//! Mod load order resolution
+//!
+//! Orders a profile's mods from what their manifests declare:
+//! - Required and optional dependencies load before the mods needing them
+//! - `loadAfter` and `loadBefore` order two mods when both are present
+//! - Missing required dependencies, and installed versions that miss the
+//!   requirement, are reported as unresolved instead of failing the sort
+//!
+//! Mods with no constraint between them keep their current order, so
+//! resolving an order that already works changes nothing.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::analyzer::parse_version;
+use super::scanner::{ModDependency, ModInfo};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("Load order cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+
+    #[error("More than one mod has the id {0}")]
+    DuplicateId(String),
+}
+
+/// What the resolver needs to know about one mod
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModManifest {
+    pub id: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<ModDependency>,
+    #[serde(default)]
+    pub load_before: Vec<String>,
+    #[serde(default)]
+    pub load_after: Vec<String>,
+}
+
+impl ModManifest {
+    /// A mod nothing is known about, which keeps its place in the order
+    pub fn bare(id: impl Into<String>) -> Self {
+        Self { id: id.into(), ..Default::default() }
+    }
+}
+
+impl From<&ModInfo> for ModManifest {
+    fn from(info: &ModInfo) -> Self {
+        Self {
+            id: info.id.clone(),
+            version: info.version.clone(),
+            dependencies: info.dependencies.clone(),
+            load_before: info.load_before.clone(),
+            load_after: info.load_after.clone(),
+        }
+    }
+}
+
+/// A required dependency the resolved set doesn't satisfy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unresolved {
+    pub mod_id: String,
+    pub requires: String,
+    pub version_requirement: Option<String>,
+    /// Version present, when the mod is there but too old or new
+    pub installed: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadOrder {
+    pub order: Vec<String>,
+    pub unresolved: Vec<Unresolved>,
+}
+
+pub struct ModResolver;
+
+impl ModResolver {
+    /// Sort `mods` into a load order. Only a cycle or a repeated id fails;
+    /// unmet requirements are listed in the result.
+    pub fn resolve(mods: &[ModManifest]) -> Result<LoadOrder, ResolveError> {
+        let mut index = HashMap::new();
+        for (i, manifest) in mods.iter().enumerate() {
+            if index.insert(manifest.id.as_str(), i).is_some() {
+                return Err(ResolveError::DuplicateId(manifest.id.clone()));
+            }
+        }
+
+        // edges[a] holds every mod that has to load after a
+        let mut edges: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); mods.len()];
+        let mut unresolved = Vec::new();
+        for (i, manifest) in mods.iter().enumerate() {
+            for dep in &manifest.dependencies {
+                match index.get(dep.mod_id.as_str()) {
+                    Some(&d) => {
+                        edges[d].insert(i);
+                        if let Some(installed) = unmet_version(dep, &mods[d]) {
+                            unresolved.push(unresolved_dep(manifest, dep, Some(installed)));
+                        }
+                    }
+                    None if !dep.optional => unresolved.push(unresolved_dep(manifest, dep, None)),
+                    None => {}
+                }
+            }
+            for after in &manifest.load_after {
+                if let Some(&a) = index.get(after.as_str()) {
+                    edges[a].insert(i);
+                }
+            }
+            for before in &manifest.load_before {
+                if let Some(&b) = index.get(before.as_str()) {
+                    edges[i].insert(b);
+                }
+            }
+        }
+        // A mod listing itself can't be a real constraint
+        for (i, targets) in edges.iter_mut().enumerate() {
+            targets.remove(&i);
+        }
+
+        let mut incoming = vec![0usize; mods.len()];
+        for targets in &edges {
+            for &t in targets {
+                incoming[t] += 1;
+            }
+        }
+
+        // Always take the earliest ready mod, which keeps the sort stable
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..mods.len())
+            .filter(|&i| incoming[i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(mods.len());
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(mods[i].id.clone());
+            for &t in &edges[i] {
+                incoming[t] -= 1;
+                if incoming[t] == 0 {
+                    ready.push(Reverse(t));
+                }
+            }
+        }
+
+        if order.len() < mods.len() {
+            let cycle = find_cycle(&edges, &incoming);
+            return Err(ResolveError::Cycle(cycle.into_iter().map(|i| mods[i].id.clone()).collect()));
+        }
+        Ok(LoadOrder { order, unresolved })
+    }
+}
+
+fn unmet_version(dep: &ModDependency, target: &ModManifest) -> Option<String> {
+    let req = VersionReq::parse(dep.version_requirement.as_deref()?).ok()?;
+    let installed = target.version.as_deref()?;
+    let version = parse_version(installed)?;
+    (!req.matches(&version)).then(|| installed.to_string())
+}
+
+fn unresolved_dep(manifest: &ModManifest, dep: &ModDependency, installed: Option<String>) -> Unresolved {
+    Unresolved {
+        mod_id: manifest.id.clone(),
+        requires: dep.mod_id.clone(),
+        version_requirement: dep.version_requirement.clone(),
+        installed,
+    }
+}
+
+/// One cycle among the mods the sort couldn't place, starting and ending
+/// at its earliest mod
+fn find_cycle(edges: &[BTreeSet<usize>], incoming: &[usize]) -> Vec<usize> {
+    let stuck = |i: usize| incoming[i] > 0;
+    let start = (0..edges.len()).find(|&i| stuck(i)).unwrap_or_default();
+
+    // Every stuck mod has a stuck predecessor, so walking predecessors from
+    // any stuck mod must come back around
+    let mut preds: Vec<Vec<usize>> = vec![Vec::new(); edges.len()];
+    for (from, targets) in edges.iter().enumerate() {
+        for &to in targets {
+            if stuck(from) {
+                preds[to].push(from);
+            }
+        }
+    }
+    let mut path = vec![start];
+    let mut seen = vec![None; edges.len()];
+    seen[start] = Some(0);
+    let mut current = start;
+    loop {
+        let Some(&prev) = preds[current].iter().min() else {
+            return path;
+        };
+        if let Some(at) = seen[prev] {
+            // path walks backwards; reverse the loop so it reads in load order
+            let mut cycle: Vec<usize> = path[at..].iter().rev().copied().collect();
+            let first = (0..cycle.len()).min_by_key(|&k| cycle[k]).unwrap_or_default();
+            cycle.rotate_left(first);
+            cycle.push(cycle[0]);
+            return cycle;
+        }
+        seen[prev] = Some(path.len());
+        path.push(prev);
+        current = prev;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(id: &str, requires: &[&str]) -> ModManifest {
+        ModManifest {
+            id: id.to_string(),
+            dependencies: requires.iter()
+                .map(|r| ModDependency { mod_id: r.to_string(), version_requirement: None, optional: false })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn order(mods: &[ModManifest]) -> Vec<String> {
+        ModResolver::resolve(mods).unwrap().order
+    }
+
+    #[test]
+    fn test_dependencies_load_first() {
+        let mods = [
+            manifest("raid_tools", &["party_tools", "lib_core"]),
+            manifest("party_tools", &["lib_core"]),
+            manifest("lib_core", &[]),
+        ];
+        assert_eq!(order(&mods), vec!["lib_core", "party_tools", "raid_tools"]);
+    }
+
+    #[test]
+    fn test_sort_is_stable() {
+        // Unrelated mods keep their places around the one that has to move
+        let mods = [
+            manifest("zoom", &[]),
+            manifest("hud_plus", &["chat_api"]),
+            manifest("alpha", &[]),
+            manifest("chat_api", &[]),
+            manifest("minimap", &[]),
+        ];
+        let first = order(&mods);
+        assert_eq!(first, vec!["zoom", "alpha", "chat_api", "hud_plus", "minimap"]);
+
+        // A resolved order resolves to itself
+        let by_id: HashMap<&str, &ModManifest> = mods.iter().map(|m| (m.id.as_str(), m)).collect();
+        let again: Vec<ModManifest> = first.iter().map(|id| by_id[id.as_str()].clone()).collect();
+        assert_eq!(order(&again), first);
+
+        // And reversing mods with no constraints between them is kept
+        let free: Vec<ModManifest> = ["c", "b", "a"].iter().map(|id| manifest(id, &[])).collect();
+        assert_eq!(order(&free), vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_load_before_and_after_hints() {
+        let mut shaders = manifest("shaders", &[]);
+        shaders.load_after = vec!["textures".into(), "not_installed".into()];
+        let mut fixes = manifest("fixes", &[]);
+        fixes.load_before = vec!["textures".into()];
+        let mods = [shaders, manifest("textures", &[]), fixes];
+        assert_eq!(order(&mods), vec!["fixes", "textures", "shaders"]);
+    }
+
+    #[test]
+    fn test_cycles_are_reported_in_load_order() {
+        let mods = [
+            manifest("standalone", &[]),
+            manifest("a", &["c"]),
+            manifest("b", &["a"]),
+            manifest("c", &["b"]),
+            manifest("d", &["a"]),
+        ];
+        let err = ModResolver::resolve(&mods).unwrap_err();
+        assert_eq!(err, ResolveError::Cycle(vec!["a".into(), "b".into(), "c".into(), "a".into()]));
+        assert_eq!(err.to_string(), "Load order cycle: a -> b -> c -> a");
+
+        // A cycle made of a dependency and a hint counts too
+        let mut lib = manifest("lib", &[]);
+        lib.load_after = vec!["addon".into()];
+        let mods = [lib, manifest("addon", &["lib"])];
+        assert!(matches!(ModResolver::resolve(&mods), Err(ResolveError::Cycle(_))));
+
+        // Naming yourself is ignored rather than a cycle
+        assert_eq!(order(&[manifest("selfish", &["selfish"])]), vec!["selfish"]);
+    }
+
+    #[test]
+    fn test_missing_and_mismatched_requirements() {
+        let mut hud = manifest("hud_plus", &["missing_lib"]);
+        hud.dependencies.push(ModDependency {
+            mod_id: "chat_api".into(),
+            version_requirement: Some("^2.0".into()),
+            optional: false,
+        });
+        hud.dependencies.push(ModDependency { mod_id: "emotes".into(), version_requirement: None, optional: true });
+        let chat = ModManifest { version: Some("1.4.0".into()), ..ModManifest::bare("chat_api") };
+
+        let resolved = ModResolver::resolve(&[hud, chat]).unwrap();
+        assert_eq!(resolved.order, vec!["chat_api", "hud_plus"]);
+        assert_eq!(resolved.unresolved, vec![
+            Unresolved {
+                mod_id: "hud_plus".into(),
+                requires: "missing_lib".into(),
+                version_requirement: None,
+                installed: None,
+            },
+            Unresolved {
+                mod_id: "hud_plus".into(),
+                requires: "chat_api".into(),
+                version_requirement: Some("^2.0".into()),
+                installed: Some("1.4.0".into()),
+            },
+        ]);
+
+        let twice = [ModManifest::bare("a"), ModManifest::bare("a")];
+        assert_eq!(ModResolver::resolve(&twice), Err(ResolveError::DuplicateId("a".into())));
+    }
+}
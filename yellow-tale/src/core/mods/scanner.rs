@@ -0,0 +1,525 @@
+//! Mod metadata scanner
+//!
+//! Reads what each archive in the mods directory says about itself:
+//! - `mod.json`, `manifest.json` or `mod.toml` at the archive root, in
+//!   either the generic lowercase layout or Hytale's capitalized one
+//! - The file name (`minimap-2.1.0.jar`) when there is no manifest
+//!
+//! Results are cached by path, modification time and size, so a rescan only
+//! opens archives that changed since the last one. An archive that can't be
+//! read is reported as unreadable instead of failing the scan.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use super::activator::classify;
+use super::archive;
+use super::ModError;
+
+/// Manifest names looked for at the archive root, in order of preference
+pub const MANIFEST_NAMES: &[&str] = &["mod.json", "manifest.json", "mod.toml"];
+
+/// File in the data dir the scan cache is kept in. The suffix changes when
+/// `ModInfo` gains manifest fields, so older caches are rebuilt.
+pub const SCAN_CACHE_FILE: &str = "mod_scan_cache.v2.json";
+
+const MAX_MANIFEST_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataSource {
+    Manifest,
+    FileName,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModDependency {
+    pub mod_id: String,
+    /// `None` when any version will do
+    pub version_requirement: Option<String>,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModInfo {
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub dependencies: Vec<ModDependency>,
+    pub incompatibilities: Vec<String>,
+    /// Capabilities this mod supplies, such as `minimap`; two enabled mods
+    /// shouldn't supply the same one
+    #[serde(default)]
+    pub provides: Vec<String>,
+    /// Game files this mod replaces, as paths inside the game's assets
+    #[serde(default)]
+    pub overrides: Vec<String>,
+    /// Mods this one should load before or after when both are present
+    #[serde(default)]
+    pub load_before: Vec<String>,
+    #[serde(default)]
+    pub load_after: Vec<String>,
+    pub file_path: PathBuf,
+    /// SHA-256 of the whole archive
+    pub hash: String,
+    pub enabled: bool,
+    pub source: MetadataSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnreadableMod {
+    pub file_path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanStats {
+    /// Mod archives found, readable or not
+    pub files: usize,
+    /// Archives answered from the cache without being opened
+    pub cached: usize,
+    pub unreadable: usize,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub mods: Vec<ModInfo>,
+    pub unreadable: Vec<UnreadableMod>,
+    pub stats: ScanStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Scanned {
+    Mod(Box<ModInfo>),
+    Unreadable(UnreadableMod),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+}
+
+impl FileStamp {
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        let modified = metadata.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: metadata.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    stamp: FileStamp,
+    scanned: Scanned,
+}
+
+/// Scans a mods directory, remembering what it found between scans
+pub struct ModScanner {
+    mods_dir: PathBuf,
+    cache_file: Option<PathBuf>,
+    cache: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl ModScanner {
+    pub fn new(mods_dir: PathBuf) -> Self {
+        Self {
+            mods_dir,
+            cache_file: None,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keep the cache in `path` so the first scan after a restart is fast too
+    pub async fn with_cache_file(mut self, path: PathBuf) -> Self {
+        if let Ok(content) = tokio::fs::read_to_string(&path).await {
+            match serde_json::from_str(&content) {
+                Ok(entries) => self.cache = Mutex::new(entries),
+                Err(e) => warn!("Ignoring unreadable mod scan cache: {}", e),
+            }
+        }
+        self.cache_file = Some(path);
+        self
+    }
+
+    pub fn mods_dir(&self) -> &Path {
+        &self.mods_dir
+    }
+
+    /// Forget what was read from `path`, so the next scan opens it again
+    pub async fn invalidate(&self, path: &Path) {
+        self.cache.lock().await.remove(path);
+    }
+
+    pub async fn clear_cache(&self) {
+        self.cache.lock().await.clear();
+    }
+
+    pub async fn scan(&self) -> Result<ScanResult, ModError> {
+        let start = Instant::now();
+        let mut result = ScanResult::default();
+        if !self.mods_dir.exists() {
+            return Ok(result);
+        }
+
+        let mut cache = self.cache.lock().await;
+        let mut seen = HashMap::new();
+        let mut changed = false;
+
+        let mut entries = tokio::fs::read_dir(&self.mods_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let Some((fallback_id, enabled)) = classify(&path) else {
+                continue;
+            };
+            result.stats.files += 1;
+
+            let stamp = FileStamp::of(&metadata);
+            let scanned = match cache.get(&path) {
+                Some(cached) if cached.stamp == stamp => {
+                    result.stats.cached += 1;
+                    cached.scanned.clone()
+                }
+                _ => {
+                    debug!("Reading mod metadata from {:?}", path);
+                    let file = path.clone();
+                    let scanned = tokio::task::spawn_blocking(move || read_mod(&file, &fallback_id, enabled))
+                        .await
+                        .unwrap_or_else(|e| Scanned::Unreadable(UnreadableMod {
+                            file_path: path.clone(),
+                            error: e.to_string(),
+                        }));
+                    changed = true;
+                    scanned
+                }
+            };
+            seen.insert(path, CacheEntry { stamp, scanned: scanned.clone() });
+
+            match scanned {
+                Scanned::Mod(info) => result.mods.push(*info),
+                Scanned::Unreadable(unreadable) => result.unreadable.push(unreadable),
+            }
+        }
+
+        changed |= seen.len() != cache.len();
+        *cache = seen;
+        if changed {
+            if let Some(cache_file) = &self.cache_file {
+                if let Err(e) = save_cache(cache_file, &cache).await {
+                    warn!("Could not save mod scan cache: {}", e);
+                }
+            }
+        }
+        drop(cache);
+
+        result.mods.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.file_path.cmp(&b.file_path)));
+        result.unreadable.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        result.stats.unreadable = result.unreadable.len();
+        result.stats.duration_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
+    }
+}
+
+async fn save_cache(path: &Path, cache: &HashMap<PathBuf, CacheEntry>) -> Result<(), ModError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let content = serde_json::to_string(cache)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    tokio::fs::write(path, content).await?;
+    Ok(())
+}
+
+/// What one mod file says about itself, without going through the cache
+pub(super) fn read_info(path: &Path) -> Result<ModInfo, String> {
+    let (fallback_id, enabled) = classify(path).ok_or("not a mod package")?;
+    match read_mod(path, &fallback_id, enabled) {
+        Scanned::Mod(info) => Ok(*info),
+        Scanned::Unreadable(unreadable) => Err(unreadable.error),
+    }
+}
+
+fn read_mod(path: &Path, fallback_id: &str, enabled: bool) -> Scanned {
+    let unreadable = |error: String| Scanned::Unreadable(UnreadableMod { file_path: path.to_path_buf(), error });
+
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => return unreadable(e.to_string()),
+    };
+    let entries = match archive::entries(&data) {
+        Ok(entries) => entries,
+        Err(e) => return unreadable(e),
+    };
+
+    let manifest = MANIFEST_NAMES.iter().find_map(|wanted| {
+        entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(wanted))
+    });
+    let mut info = match manifest {
+        Some(entry) => {
+            let parsed = archive::read(&data, entry, MAX_MANIFEST_BYTES)
+                .and_then(|bytes| parse_manifest(&entry.name, &bytes)
+                    .map_err(|e| format!("invalid {}: {}", entry.name, e)))
+                .and_then(|json| from_manifest(&json, fallback_id)
+                    .ok_or_else(|| format!("{} is not a JSON object", entry.name)));
+            match parsed {
+                Ok(info) => info,
+                Err(e) => return unreadable(e),
+            }
+        }
+        None => from_file_name(fallback_id),
+    };
+
+    info.file_path = path.to_path_buf();
+    info.hash = hex::encode(Sha256::digest(&data));
+    info.enabled = enabled;
+    Scanned::Mod(Box::new(info))
+}
+
+/// A JSON or TOML manifest as JSON, so both go through `from_manifest`
+fn parse_manifest(name: &str, bytes: &[u8]) -> Result<Value, String> {
+    if name.to_ascii_lowercase().ends_with(".toml") {
+        let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        let table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        serde_json::to_value(table).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// Field of `object` under any of `keys`, ignoring case
+fn field<'a>(object: &'a Map<String, Value>, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().find_map(|key| {
+        object.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+            .filter(|v| !v.is_null())
+    })
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn requirement(value: Option<&Value>) -> Option<String> {
+    value.and_then(text).filter(|req| req != "*")
+}
+
+/// Names from a string, a list of strings, or a list of `{ "name": ... }`
+fn names(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter()
+            .filter_map(|item| match item {
+                Value::Object(object) => field(object, &["name", "id"]).and_then(text),
+                other => text(other),
+            })
+            .collect(),
+        Value::Object(object) => object.keys().cloned().collect(),
+        other => text(other).into_iter().collect(),
+    }
+}
+
+/// Dependencies as an `{ id: requirement }` map or a list of ids or objects
+fn dependencies(value: &Value, optional: bool) -> Vec<ModDependency> {
+    match value {
+        Value::Object(object) => object.iter()
+            .map(|(id, req)| ModDependency {
+                mod_id: id.clone(),
+                version_requirement: requirement(Some(req)),
+                optional,
+            })
+            .collect(),
+        Value::Array(items) => items.iter()
+            .filter_map(|item| match item {
+                Value::Object(object) => Some(ModDependency {
+                    mod_id: field(object, &["id", "modId"]).and_then(text)?,
+                    version_requirement: requirement(field(object, &["version", "versionRequirement"])),
+                    optional: field(object, &["optional"]).and_then(Value::as_bool).unwrap_or(optional),
+                }),
+                other => Some(ModDependency { mod_id: text(other)?, version_requirement: None, optional }),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn from_manifest(json: &Value, fallback_id: &str) -> Option<ModInfo> {
+    let object = json.as_object()?;
+    let name = field(object, &["name", "displayName"]).and_then(text);
+    // Hytale manifests identify a mod by `Group:Name`
+    let group = field(object, &["group"]).and_then(text);
+    let id = field(object, &["id", "modId"]).and_then(text)
+        .or_else(|| Some(format!("{}:{}", group.as_ref()?, name.as_ref()?)))
+        .or_else(|| name.as_ref().map(|n| n.to_lowercase().replace(' ', "_")))
+        .unwrap_or_else(|| from_file_name(fallback_id).id);
+
+    let mut authors: Vec<String> = field(object, &["authors"]).map(names).unwrap_or_default();
+    authors.extend(field(object, &["author"]).map(names).unwrap_or_default());
+
+    let mut deps = field(object, &["dependencies", "depends"]).map(|v| dependencies(v, false)).unwrap_or_default();
+    deps.extend(field(object, &["optionalDependencies"]).map(|v| dependencies(v, true)).unwrap_or_default());
+
+    Some(ModInfo {
+        name: name.unwrap_or_else(|| id.clone()),
+        id,
+        version: field(object, &["version"]).and_then(text),
+        description: field(object, &["description"]).and_then(text),
+        authors,
+        dependencies: deps,
+        incompatibilities: field(object, &["incompatibilities", "incompatible", "conflicts", "breaks"])
+            .map(names)
+            .unwrap_or_default(),
+        provides: field(object, &["provides"]).map(names).unwrap_or_default(),
+        overrides: field(object, &["overrides", "replaces"]).map(names).unwrap_or_default(),
+        load_before: field(object, &["loadBefore", "load_before", "before"]).map(names).unwrap_or_default(),
+        load_after: field(object, &["loadAfter", "load_after", "after"]).map(names).unwrap_or_default(),
+        file_path: PathBuf::new(),
+        hash: String::new(),
+        enabled: true,
+        source: MetadataSource::Manifest,
+    })
+}
+
+/// `better_chat-1.4.2` → id `better_chat`, name `better chat`, version `1.4.2`
+fn from_file_name(stem: &str) -> ModInfo {
+    let split = stem.char_indices()
+        .find(|&(i, c)| (c == '-' || c == '_') && stem[i + 1..].starts_with(|c: char| c.is_ascii_digit()));
+    let (base, version) = match split {
+        Some((i, _)) => (&stem[..i], Some(stem[i + 1..].to_string())),
+        None => (stem, None),
+    };
+
+    ModInfo {
+        id: base.to_string(),
+        name: base.replace(['-', '_'], " "),
+        version,
+        description: None,
+        authors: Vec::new(),
+        dependencies: Vec::new(),
+        incompatibilities: Vec::new(),
+        provides: Vec::new(),
+        overrides: Vec::new(),
+        load_before: Vec::new(),
+        load_after: Vec::new(),
+        file_path: PathBuf::new(),
+        hash: String::new(),
+        enabled: true,
+        source: MetadataSource::FileName,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    use std::collections::BTreeMap;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mods");
+
+    fn by_id(result: &ScanResult) -> BTreeMap<&str, &ModInfo> {
+        result.mods.iter().map(|m| (m.id.as_str(), m)).collect()
+    }
+
+    async fn fixture_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yt-scanner-{}", Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let mut entries = tokio::fs::read_dir(FIXTURES).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            tokio::fs::copy(entry.path(), dir.join(entry.file_name())).await.unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_scan_reads_manifests_names_and_corrupt_archives() {
+        let dir = fixture_dir().await;
+        let result = ModScanner::new(dir.clone()).scan().await.unwrap();
+        let mods = by_id(&result);
+
+        let minimap = mods["Hytale:Minimap"];
+        assert_eq!(minimap.source, MetadataSource::Manifest);
+        assert_eq!(minimap.version.as_deref(), Some("2.1.0"));
+        assert_eq!(minimap.authors, vec!["Ada", "Lin"]);
+        assert_eq!(minimap.dependencies, vec![
+            ModDependency { mod_id: "Hytale:Waypoints".into(), version_requirement: Some(">=1.0.0".into()), optional: false },
+            ModDependency { mod_id: "Hytale:Compass".into(), version_requirement: None, optional: true },
+        ]);
+        assert_eq!(minimap.incompatibilities, vec!["Hytale:OldMap"]);
+
+        let chat = mods["better_chat"];
+        assert_eq!(chat.name, "Better Chat");
+        assert_eq!(chat.authors, vec!["sam"]);
+        assert_eq!(chat.dependencies[0].mod_id, "chat_api");
+        assert_eq!(chat.incompatibilities, vec!["legacy_chat"]);
+
+        let overlay = mods["coords_overlay"];
+        assert_eq!(overlay.source, MetadataSource::FileName);
+        assert_eq!(overlay.version.as_deref(), Some("1.4.2"));
+        assert!(overlay.enabled);
+        let data = tokio::fs::read(dir.join("coords_overlay-1.4.2.jar")).await.unwrap();
+        assert_eq!(overlay.hash, hex::encode(Sha256::digest(&data)));
+
+        assert!(!mods["legacy_hud"].enabled);
+
+        let unreadable: Vec<_> = result.unreadable.iter()
+            .map(|u| u.file_path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(unreadable, vec!["broken.jar", "truncated.zip"]);
+        assert_eq!(result.stats.files, 6);
+        assert_eq!(result.stats.unreadable, 2);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_rescan_uses_cache_until_a_file_changes() {
+        let dir = fixture_dir().await;
+        let cache_file = dir.join("cache").join(SCAN_CACHE_FILE);
+        let scanner = ModScanner::new(dir.clone()).with_cache_file(cache_file.clone()).await;
+        let first = scanner.scan().await.unwrap();
+        assert_eq!(first.stats.cached, 0);
+
+        // A new scanner picks the cache up from disk
+        let scanner = ModScanner::new(dir.clone()).with_cache_file(cache_file).await;
+        let second = scanner.scan().await.unwrap();
+        assert_eq!(second.stats.cached, second.stats.files);
+        assert_eq!(second.mods, first.mods);
+
+        // Replacing the corrupt archive with a readable one is noticed
+        tokio::fs::copy(dir.join("coords_overlay-1.4.2.jar"), dir.join("broken.jar")).await.unwrap();
+        let third = scanner.scan().await.unwrap();
+        assert_eq!(third.stats.cached, third.stats.files - 1);
+        assert!(by_id(&third).contains_key("broken"));
+
+        scanner.invalidate(&dir.join("better_chat.zip")).await;
+        let fourth = scanner.scan().await.unwrap();
+        assert_eq!(fourth.stats.cached, fourth.stats.files - 1);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -0,0 +1,699 @@
+//! Network Diagnostics Module
+//!
+//! Measures how well game servers can be reached from this machine:
+//! - Round-trip time from TCP connect timing, plus a UDP probe when the
+//!   server's port is known
+//! - Min/avg/jitter/loss over several samples, classified good/ok/poor
+//! - A background monitor that re-measures favorited servers and keeps up
+//!   to 50 measurements from the last 24 hours per server in the data dir
+//!
+//! A refused connection still proves the host answered, so it counts as a
+//! sample; only timeouts and unreachable hosts count as loss.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::{broadcast, Mutex, RwLock, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::core::config::NetDiagConfig;
+
+/// Port probed when a server doesn't name one
+pub const DEFAULT_PORT: u16 = 5520;
+
+/// How much history is kept per server
+pub const HISTORY_WINDOW_HOURS: i64 = 24;
+
+/// Most measurements kept per server, however recent
+pub const MAX_HISTORY_POINTS: usize = 50;
+
+/// Pause between samples, so one slow answer doesn't delay the next probe
+const SAMPLE_SPACING: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug)]
+pub enum NetDiagError {
+    #[error("Could not resolve {0}")]
+    Resolve(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Upper bounds for each quality class; a server must meet all three
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QualityThresholds {
+    pub good_rtt_ms: u32,
+    pub ok_rtt_ms: u32,
+    pub good_jitter_ms: u32,
+    pub ok_jitter_ms: u32,
+    pub good_loss_percent: u32,
+    pub ok_loss_percent: u32,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            good_rtt_ms: 60,
+            ok_rtt_ms: 150,
+            good_jitter_ms: 15,
+            ok_jitter_ms: 40,
+            good_loss_percent: 0,
+            ok_loss_percent: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quality {
+    Good,
+    Ok,
+    Poor,
+    /// No sample got an answer
+    Unreachable,
+}
+
+impl QualityThresholds {
+    pub fn classify(&self, stats: &PingStats) -> Quality {
+        let Some(avg) = stats.avg_ms else {
+            return Quality::Unreachable;
+        };
+        let jitter = stats.jitter_ms.unwrap_or(0.0);
+        let within = |rtt: u32, jitter_max: u32, loss: u32| {
+            avg <= rtt as f64 && jitter <= jitter_max as f64 && stats.loss_percent <= loss as f64
+        };
+        if within(self.good_rtt_ms, self.good_jitter_ms, self.good_loss_percent) {
+            Quality::Good
+        } else if within(self.ok_rtt_ms, self.ok_jitter_ms, self.ok_loss_percent) {
+            Quality::Ok
+        } else {
+            Quality::Poor
+        }
+    }
+}
+
+/// Summary of one run of samples, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PingStats {
+    pub sent: u32,
+    pub received: u32,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    /// Mean difference between consecutive answered round trips
+    pub jitter_ms: Option<f64>,
+    pub loss_percent: f64,
+}
+
+fn round_ms(ms: f64) -> f64 {
+    (ms * 100.0).round() / 100.0
+}
+
+/// Stats for samples in the order they were taken; `None` is a lost sample
+pub fn summarize(samples: &[Option<Duration>]) -> PingStats {
+    let rtts: Vec<f64> = samples.iter()
+        .flatten()
+        .map(|rtt| rtt.as_secs_f64() * 1000.0)
+        .collect();
+    let sent = samples.len() as u32;
+    let received = rtts.len() as u32;
+    let loss_percent = if sent == 0 { 0.0 } else { round_ms(f64::from(sent - received) / f64::from(sent) * 100.0) };
+    if rtts.is_empty() {
+        return PingStats { sent, received, loss_percent, ..Default::default() };
+    }
+
+    let jitter = (rtts.len() > 1).then(|| {
+        let total: f64 = rtts.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum();
+        round_ms(total / (rtts.len() - 1) as f64)
+    });
+    PingStats {
+        sent,
+        received,
+        min_ms: Some(round_ms(rtts.iter().copied().fold(f64::INFINITY, f64::min))),
+        avg_ms: Some(round_ms(rtts.iter().sum::<f64>() / rtts.len() as f64)),
+        max_ms: Some(round_ms(rtts.iter().copied().fold(0.0, f64::max))),
+        jitter_ms: jitter,
+        loss_percent,
+    }
+}
+
+/// A server to measure, named by the id the UI knows it by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerTarget {
+    pub server_id: String,
+    pub address: String,
+    /// Known game port; enables the UDP probe
+    #[serde(default)]
+    pub port: Option<u16>,
+}
+
+impl ServerTarget {
+    /// An ad-hoc target without a server id is keyed by its address
+    pub fn new(server_id: Option<String>, address: impl Into<String>, port: Option<u16>) -> Self {
+        let address = address.into();
+        let server_id = server_id.unwrap_or_else(|| format!("{}:{}", address, port.unwrap_or(DEFAULT_PORT)));
+        Self { server_id, address, port }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingResult {
+    pub server_id: String,
+    pub address: String,
+    pub port: u16,
+    pub measured_at: DateTime<Utc>,
+    pub tcp: PingStats,
+    /// Round trip of the UDP probe, if anything answered it
+    pub udp_rtt_ms: Option<f64>,
+    pub quality: Quality,
+}
+
+/// Sends the actual packets; the pinger does the timing
+#[async_trait]
+pub trait Probe: Send + Sync {
+    async fn tcp(&self, addr: SocketAddr) -> std::io::Result<()>;
+
+    async fn udp(&self, addr: SocketAddr) -> std::io::Result<()>;
+}
+
+/// Opens real sockets
+pub struct SocketProbe;
+
+#[async_trait]
+impl Probe for SocketProbe {
+    async fn tcp(&self, addr: SocketAddr) -> std::io::Result<()> {
+        tokio::net::TcpStream::connect(addr).await.map(drop)
+    }
+
+    async fn udp(&self, addr: SocketAddr) -> std::io::Result<()> {
+        let local: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = tokio::net::UdpSocket::bind(local).await?;
+        socket.connect(addr).await?;
+        socket.send(&[0]).await?;
+        socket.recv(&mut [0u8; 512]).await.map(drop)
+    }
+}
+
+/// Whether the host answered: a refusal comes back from the host itself
+fn answered(result: &std::io::Result<()>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(e) => e.kind() == std::io::ErrorKind::ConnectionRefused,
+    }
+}
+
+/// Takes timed samples against one server
+pub struct Pinger {
+    probe: Arc<dyn Probe>,
+    samples: u32,
+    timeout: Duration,
+    thresholds: QualityThresholds,
+}
+
+impl Pinger {
+    pub fn new(config: &NetDiagConfig) -> Self {
+        Self {
+            probe: Arc::new(SocketProbe),
+            samples: config.samples.max(1),
+            timeout: Duration::from_millis(config.timeout_ms),
+            thresholds: config.thresholds,
+        }
+    }
+
+    pub fn with_probe(mut self, probe: Arc<dyn Probe>) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    async fn timed<F>(&self, probe: F) -> Option<Duration>
+    where
+        F: std::future::Future<Output = std::io::Result<()>>,
+    {
+        let started = Instant::now();
+        match tokio::time::timeout(self.timeout, probe).await {
+            Ok(result) if answered(&result) => Some(started.elapsed()),
+            _ => None,
+        }
+    }
+
+    pub async fn measure(&self, target: &ServerTarget) -> Result<PingResult, NetDiagError> {
+        let port = target.port.unwrap_or(DEFAULT_PORT);
+        let addr = tokio::net::lookup_host((target.address.as_str(), port)).await
+            .map_err(|_| NetDiagError::Resolve(target.address.clone()))?
+            .next()
+            .ok_or_else(|| NetDiagError::Resolve(target.address.clone()))?;
+
+        let mut samples = Vec::with_capacity(self.samples as usize);
+        for i in 0..self.samples {
+            if i > 0 {
+                tokio::time::sleep(SAMPLE_SPACING).await;
+            }
+            samples.push(self.timed(self.probe.tcp(addr)).await);
+        }
+        let udp_rtt = match target.port {
+            Some(_) => self.timed(self.probe.udp(addr)).await,
+            None => None,
+        };
+
+        let tcp = summarize(&samples);
+        Ok(PingResult {
+            server_id: target.server_id.clone(),
+            address: target.address.clone(),
+            port,
+            measured_at: Utc::now(),
+            quality: self.thresholds.classify(&tcp),
+            tcp,
+            udp_rtt_ms: udp_rtt.map(|rtt| round_ms(rtt.as_secs_f64() * 1000.0)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    pub at: DateTime<Utc>,
+    pub avg_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+    pub loss_percent: f64,
+    pub quality: Quality,
+}
+
+impl From<&PingResult> for HistoryPoint {
+    fn from(result: &PingResult) -> Self {
+        Self {
+            at: result.measured_at,
+            avg_ms: result.tcp.avg_ms,
+            jitter_ms: result.tcp.jitter_ms,
+            loss_percent: result.tcp.loss_percent,
+            quality: result.quality,
+        }
+    }
+}
+
+/// The latest measurements of one server over the last day, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PingHistory {
+    pub server_id: String,
+    pub points: Vec<HistoryPoint>,
+}
+
+impl PingHistory {
+    /// Add a point and drop the ones that fell out of the window
+    pub fn push(&mut self, point: HistoryPoint, now: DateTime<Utc>) {
+        self.points.push(point);
+        self.prune(now);
+    }
+
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - chrono::Duration::hours(HISTORY_WINDOW_HOURS);
+        self.points.retain(|point| point.at > cutoff);
+        let excess = self.points.len().saturating_sub(MAX_HISTORY_POINTS);
+        self.points.drain(..excess);
+    }
+}
+
+/// A favorited server with its most recent measurement, if any
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FavoriteStatus {
+    #[serde(flatten)]
+    pub target: ServerTarget,
+    pub last_ping: Option<HistoryPoint>,
+}
+
+/// Favorites and per-server history under `<data_dir>/netdiag`, measured at
+/// most `max_concurrent_probes` servers at a time
+pub struct PingMonitor {
+    dir: PathBuf,
+    pinger: Pinger,
+    refresh_interval: Duration,
+    permits: Arc<Semaphore>,
+    favorites: RwLock<Vec<ServerTarget>>,
+    histories: Mutex<HashMap<String, PingHistory>>,
+    events: broadcast::Sender<PingResult>,
+}
+
+impl PingMonitor {
+    pub async fn load(data_dir: &Path, config: &NetDiagConfig) -> Self {
+        let dir = data_dir.join("netdiag");
+        let favorites = match tokio::fs::read_to_string(dir.join("favorites.json")).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable ping favorites: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            dir,
+            pinger: Pinger::new(config),
+            refresh_interval: Duration::from_secs(config.refresh_interval_secs),
+            permits: Arc::new(Semaphore::new(config.max_concurrent_probes.max(1))),
+            favorites: RwLock::new(favorites),
+            histories: Mutex::new(HashMap::new()),
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    pub fn with_probe(mut self, probe: Arc<dyn Probe>) -> Self {
+        self.pinger = self.pinger.with_probe(probe);
+        self
+    }
+
+    /// Every finished measurement, favorites and ad-hoc pings alike
+    pub fn subscribe(&self) -> broadcast::Receiver<PingResult> {
+        self.events.subscribe()
+    }
+
+    pub async fn favorites(&self) -> Vec<ServerTarget> {
+        self.favorites.read().await.clone()
+    }
+
+    /// Favorites with their latest measurement, in the order they were added
+    pub async fn favorite_servers(&self) -> Vec<FavoriteStatus> {
+        let mut servers = Vec::new();
+        for target in self.favorites().await {
+            let last_ping = self.history(&target.server_id).await.points.pop();
+            servers.push(FavoriteStatus { target, last_ping });
+        }
+        servers
+    }
+
+    /// Add `target` to, or remove it from, the servers refreshed in the background
+    pub async fn set_favorite(&self, target: ServerTarget, favorite: bool) -> Result<(), NetDiagError> {
+        let mut favorites = self.favorites.write().await;
+        favorites.retain(|existing| existing.server_id != target.server_id);
+        if favorite {
+            favorites.push(target);
+        }
+        self.save_favorites(&favorites).await
+    }
+
+    /// Stop refreshing `server_id`; false if it wasn't a favorite
+    pub async fn unfavorite(&self, server_id: &str) -> Result<bool, NetDiagError> {
+        let mut favorites = self.favorites.write().await;
+        let before = favorites.len();
+        favorites.retain(|existing| existing.server_id != server_id);
+        if favorites.len() == before {
+            return Ok(false);
+        }
+        self.save_favorites(&favorites).await?;
+        Ok(true)
+    }
+
+    async fn save_favorites(&self, favorites: &[ServerTarget]) -> Result<(), NetDiagError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let contents = serde_json::to_string_pretty(favorites).unwrap_or_default();
+        tokio::fs::write(self.dir.join("favorites.json"), contents).await?;
+        Ok(())
+    }
+
+    fn history_path(&self, server_id: &str) -> PathBuf {
+        let name = hex::encode(&Sha256::digest(server_id.as_bytes())[..8]);
+        self.dir.join("history").join(format!("{}.json", name))
+    }
+
+    /// The cached history for `server_id`, read from disk on first use
+    async fn cached<'a>(&self, histories: &'a mut HashMap<String, PingHistory>, server_id: &str) -> &'a mut PingHistory {
+        if !histories.contains_key(server_id) {
+            let empty = || PingHistory { server_id: server_id.to_string(), points: Vec::new() };
+            let history = match tokio::fs::read_to_string(self.history_path(server_id)).await {
+                Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                    warn!("Ignoring unreadable ping history for {}: {}", server_id, e);
+                    empty()
+                }),
+                Err(_) => empty(),
+            };
+            histories.insert(server_id.to_string(), history);
+        }
+        histories.get_mut(server_id).expect("history was just inserted")
+    }
+
+    /// The last day of measurements for `server_id`
+    pub async fn history(&self, server_id: &str) -> PingHistory {
+        let mut histories = self.histories.lock().await;
+        let history = self.cached(&mut histories, server_id).await;
+        history.prune(Utc::now());
+        history.clone()
+    }
+
+    async fn record(&self, result: &PingResult) -> Result<(), NetDiagError> {
+        let mut histories = self.histories.lock().await;
+        let history = self.cached(&mut histories, &result.server_id).await;
+        history.push(HistoryPoint::from(result), Utc::now());
+
+        let path = self.history_path(&result.server_id);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, serde_json::to_string(&*history).unwrap_or_default()).await?;
+        Ok(())
+    }
+
+    /// Measure `target` once a probe slot is free and add it to its history
+    pub async fn ping(&self, target: &ServerTarget) -> Result<PingResult, NetDiagError> {
+        let permit = self.permits.acquire().await.expect("ping semaphore is never closed");
+        let result = self.pinger.measure(target).await?;
+        drop(permit);
+
+        if let Err(e) = self.record(&result).await {
+            warn!("Failed to save ping history for {}: {}", target.server_id, e);
+        }
+        let _ = self.events.send(result.clone());
+        Ok(result)
+    }
+
+    /// Measure every favorite; the semaphore keeps the socket count bounded
+    pub async fn refresh_favorites(self: &Arc<Self>) -> Vec<PingResult> {
+        let mut tasks = JoinSet::new();
+        for target in self.favorites().await {
+            let monitor = self.clone();
+            tasks.spawn(async move {
+                let result = monitor.ping(&target).await;
+                if let Err(e) = &result {
+                    debug!("Ping to {} failed: {}", target.server_id, e);
+                }
+                result.ok()
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(Some(result)) = result {
+                results.push(result);
+            }
+        }
+        results
+    }
+
+    /// Refresh favorites every `refresh_interval` until the monitor is dropped
+    pub fn spawn_refresh(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let monitor = Arc::downgrade(&self);
+        let interval = self.refresh_interval;
+        drop(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(monitor) = monitor.upgrade() else {
+                    break;
+                };
+                monitor.refresh_favorites().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn ms(ms: u64) -> Option<Duration> {
+        Some(Duration::from_millis(ms))
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-netdiag-{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Connects to a real listener after an injected delay per sample
+    struct DelayedProbe {
+        delays: std::sync::Mutex<VecDeque<Duration>>,
+    }
+
+    #[async_trait]
+    impl Probe for DelayedProbe {
+        async fn tcp(&self, addr: SocketAddr) -> std::io::Result<()> {
+            let delay = self.delays.lock().unwrap().pop_front().unwrap_or_default();
+            tokio::time::sleep(delay).await;
+            SocketProbe.tcp(addr).await
+        }
+
+        async fn udp(&self, _addr: SocketAddr) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::TimedOut.into())
+        }
+    }
+
+    /// Answers after a fixed delay and records the most probes in flight
+    #[derive(Default)]
+    struct CountingProbe {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Probe for CountingProbe {
+        async fn tcp(&self, _addr: SocketAddr) -> std::io::Result<()> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn udp(&self, _addr: SocketAddr) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_summary_math_and_classification() {
+        let stats = summarize(&[ms(20), ms(40), None, ms(20), ms(60)]);
+        assert_eq!((stats.sent, stats.received), (5, 4));
+        assert_eq!(stats.min_ms, Some(20.0));
+        assert_eq!(stats.max_ms, Some(60.0));
+        assert_eq!(stats.avg_ms, Some(35.0));
+        // |40-20| + |20-40| + |60-20|, over three gaps; the lost sample is skipped
+        assert_eq!(stats.jitter_ms, Some(26.67));
+        assert_eq!(stats.loss_percent, 20.0);
+
+        let thresholds = QualityThresholds::default();
+        assert_eq!(thresholds.classify(&stats), Quality::Ok);
+        assert_eq!(thresholds.classify(&summarize(&[ms(30), ms(32), ms(31)])), Quality::Good);
+        assert_eq!(thresholds.classify(&summarize(&[ms(300), ms(310)])), Quality::Poor);
+        assert_eq!(thresholds.classify(&summarize(&[None, None])), Quality::Unreachable);
+        assert_eq!(summarize(&[ms(12)]).jitter_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_local_listener_timing_with_injected_delays() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+
+        let delays = [20, 40, 20, 60, 1000].map(Duration::from_millis);
+        let config = NetDiagConfig { samples: 5, timeout_ms: 300, ..Default::default() };
+        let pinger = Pinger::new(&config).with_probe(Arc::new(DelayedProbe {
+            delays: std::sync::Mutex::new(delays.into_iter().collect()),
+        }));
+        let result = pinger.measure(&ServerTarget::new(None, "127.0.0.1", Some(port))).await.unwrap();
+
+        let stats = &result.tcp;
+        assert_eq!(result.server_id, format!("127.0.0.1:{}", port));
+        assert_eq!((stats.sent, stats.received, stats.loss_percent), (5, 4, 20.0));
+        // Loopback adds well under a millisecond; leave room for a busy machine
+        let near = |value: Option<f64>, expected: f64| (value.unwrap() - expected).abs() < 10.0;
+        assert!(near(stats.min_ms, 20.0), "{:?}", stats);
+        assert!(near(stats.max_ms, 60.0), "{:?}", stats);
+        assert!(near(stats.avg_ms, 35.0), "{:?}", stats);
+        assert!(near(stats.jitter_ms, 26.67), "{:?}", stats);
+        assert_eq!(result.udp_rtt_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_favorites_refresh_with_bounded_concurrency() {
+        let dir = temp_dir();
+        let config = NetDiagConfig { samples: 2, max_concurrent_probes: 3, ..Default::default() };
+        let probe = Arc::new(CountingProbe::default());
+        let monitor = Arc::new(PingMonitor::load(&dir, &config).await.with_probe(probe.clone()));
+        for i in 0..12 {
+            let target = ServerTarget::new(Some(format!("server-{}", i)), "127.0.0.1", Some(5520));
+            monitor.set_favorite(target, true).await.unwrap();
+        }
+        monitor.set_favorite(ServerTarget::new(Some("server-0".into()), "127.0.0.1", None), false).await.unwrap();
+
+        let results = monitor.refresh_favorites().await;
+        assert_eq!(results.len(), 11);
+        assert!(probe.peak.load(Ordering::SeqCst) <= 3, "peak {}", probe.peak.load(Ordering::SeqCst));
+        assert!(results.iter().all(|result| result.udp_rtt_ms.is_some()));
+
+        // Favorites and history survive a restart
+        let reloaded = PingMonitor::load(&dir, &config).await;
+        assert_eq!(reloaded.favorites().await.len(), 11);
+        assert_eq!(reloaded.history("server-5").await.points.len(), 1);
+        assert!(reloaded.history("server-0").await.points.is_empty());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[test]
+    fn test_history_keeps_one_day() {
+        let now = Utc::now();
+        let point = |hours_ago: i64| HistoryPoint {
+            at: now - chrono::Duration::hours(hours_ago),
+            avg_ms: Some(40.0),
+            jitter_ms: Some(2.0),
+            loss_percent: 0.0,
+            quality: Quality::Good,
+        };
+        let mut history = PingHistory::default();
+        history.push(point(30), now);
+        history.push(point(23), now);
+        history.push(point(0), now);
+        assert_eq!(history.points, vec![point(23), point(0)]);
+
+        history.prune(now + chrono::Duration::hours(2));
+        assert_eq!(history.points, vec![point(0)]);
+    }
+
+    #[test]
+    fn test_history_keeps_the_latest_points() {
+        let now = Utc::now();
+        let mut history = PingHistory::default();
+        for minutes_ago in (0..80).rev() {
+            history.push(HistoryPoint {
+                at: now - chrono::Duration::minutes(minutes_ago),
+                avg_ms: Some(minutes_ago as f64),
+                jitter_ms: None,
+                loss_percent: 0.0,
+                quality: Quality::Good,
+            }, now);
+        }
+        assert_eq!(history.points.len(), MAX_HISTORY_POINTS);
+        assert_eq!(history.points.first().unwrap().avg_ms, Some(49.0));
+        assert_eq!(history.points.last().unwrap().avg_ms, Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_favorites_round_trip_through_the_data_dir() {
+        let dir = temp_dir();
+        let config = NetDiagConfig::default();
+        let monitor = PingMonitor::load(&dir, &config).await.with_probe(Arc::new(CountingProbe::default()));
+        monitor.set_favorite(ServerTarget::new(Some("castle".into()), "castle.example.com", Some(5520)), true).await.unwrap();
+        monitor.set_favorite(ServerTarget::new(Some("arena".into()), "127.0.0.1", None), true).await.unwrap();
+        monitor.ping(&ServerTarget::new(Some("arena".into()), "127.0.0.1", None)).await.unwrap();
+        assert!(monitor.unfavorite("castle").await.unwrap());
+        assert!(!monitor.unfavorite("castle").await.unwrap());
+
+        let reloaded = PingMonitor::load(&dir, &config).await;
+        let favorites = reloaded.favorite_servers().await;
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].target, ServerTarget::new(Some("arena".into()), "127.0.0.1", None));
+        assert_eq!(favorites[0].last_ping.as_ref().map(|point| point.quality), Some(Quality::Good));
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
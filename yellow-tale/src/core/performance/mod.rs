@@ -6,9 +6,14 @@
 //! - RAM cleanup before launch
 //! - Disk IO warm-up
 //! - Optional background task suppression
+//! - Readiness checks before launch (see [`readiness`])
 //! 
 //! NO runtime injection, hooking, or cheating behavior.
 
+pub mod readiness;
+
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use sysinfo::{System, Pid};
@@ -27,20 +32,15 @@ pub enum PerformanceError {
 }
 
 /// Process priority levels
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 pub enum PriorityLevel {
     Low,
+    #[default]
     Normal,
     High,
     Realtime,
 }
 
-impl Default for PriorityLevel {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 /// Pre-launch optimization settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationSettings {
@@ -92,7 +92,7 @@ pub struct PreparationResult {
 }
 
 /// System information snapshot
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SystemSnapshot {
     /// Total RAM in MB
     pub total_ram_mb: u64,
@@ -108,6 +108,39 @@ pub struct SystemSnapshot {
     
     /// Running process count
     pub process_count: usize,
+    
+    /// Space on the drive the game is installed on, when known
+    #[serde(default)]
+    pub game_drive: Option<DriveSpace>,
+    
+    /// Other processes using a lot of CPU or memory, busiest first
+    #[serde(default)]
+    pub heavy_processes: Vec<ProcessLoad>,
+}
+
+/// Free space on one drive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriveSpace {
+    pub mount_point: String,
+    pub total_mb: u64,
+    pub available_mb: u64,
+}
+
+/// Another process competing with the game for CPU or memory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessLoad {
+    pub pid: u32,
+    pub name: String,
+    /// Can exceed 100 for a process busy on several cores
+    pub cpu_usage: f32,
+    pub memory_mb: u64,
+}
+
+/// What warming files into the disk cache read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarmResult {
+    pub files: usize,
+    pub bytes: u64,
 }
 
 /// Performance preparation service
@@ -143,6 +176,8 @@ impl PerformanceOptimizer {
             cpu_cores: self.system.cpus().len(),
             cpu_usage,
             process_count: self.system.processes().len(),
+            game_drive: None,
+            heavy_processes: Vec::new(),
         }
     }
     
@@ -174,7 +209,7 @@ impl PerformanceOptimizer {
         
         // Disk cache warming
         if !settings.warm_files.is_empty() {
-            result.files_warmed = self.warm_disk_cache(&settings.warm_files).await;
+            result.files_warmed = warm_files(&settings.warm_files, u64::MAX).await.files;
             info!("Warmed {} files into disk cache", result.files_warmed);
         }
         
@@ -218,22 +253,6 @@ impl PerformanceOptimizer {
         Ok(freed)
     }
     
-    /// Pre-read files to warm the disk cache
-    async fn warm_disk_cache(&self, files: &[std::path::PathBuf]) -> usize {
-        let mut warmed = 0;
-        
-        for file in files {
-            if file.exists() {
-                // Just read the file to bring it into OS page cache
-                if let Ok(_) = tokio::fs::read(file).await {
-                    warmed += 1;
-                }
-            }
-        }
-        
-        warmed
-    }
-    
     /// Set process priority (to be called after spawn)
     #[cfg(target_os = "windows")]
     pub fn set_process_priority(&self, pid: u32, level: PriorityLevel) -> Result<(), PerformanceError> {
@@ -296,6 +315,32 @@ impl PerformanceOptimizer {
     }
 }
 
+/// Pre-read files to bring them into the OS page cache, stopping before
+/// `max_bytes` have been read. Missing or unreadable files are skipped.
+pub async fn warm_files(files: &[PathBuf], max_bytes: u64) -> WarmResult {
+    let mut warmed = WarmResult::default();
+    
+    for file in files {
+        let Ok(metadata) = tokio::fs::metadata(file).await else {
+            continue;
+        };
+        if warmed.bytes.saturating_add(metadata.len()) > max_bytes {
+            break;
+        }
+        // Read in chunks so a large file doesn't have to fit in memory
+        let file = file.clone();
+        let read = tokio::task::spawn_blocking(move || {
+            std::io::copy(&mut std::fs::File::open(file)?, &mut std::io::sink())
+        }).await;
+        if let Ok(Ok(bytes)) = read {
+            warmed.files += 1;
+            warmed.bytes += bytes;
+        }
+    }
+    
+    warmed
+}
+
 impl Default for PerformanceOptimizer {
     fn default() -> Self {
         Self::new()
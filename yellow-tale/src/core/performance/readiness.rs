@@ -0,0 +1,245 @@
+//! Launch readiness
+//!
+//! Judges a system snapshot against what a profile needs, before the game
+//! starts, so the UI can warn about problems the launcher can't fix:
+//! - Blockers are problems the launch would fail or thrash on, such as a
+//!   heap larger than the machine's RAM
+//! - Warnings are worth showing but don't stop the launch, such as another
+//!   program keeping a few cores busy
+//!
+//! Only observes: nothing here changes another process.
+
+use serde::{Deserialize, Serialize};
+
+use super::SystemSnapshot;
+
+/// Most bytes of cached assets read ahead before a launch
+pub const CACHE_WARM_BUDGET_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Where profiles keep the game's heap size: under `performance` (the shared
+/// profile layout) or in their free-form `settings`
+const RAM_ALLOCATION_POINTERS: &[&str] = &["/performance/ram_allocation_mb", "/settings/ram_allocation_mb"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessIssueKind {
+    /// The heap asked for is more than the machine has
+    InsufficientRam,
+    /// The heap fits, but not with what's free right now
+    LowRam,
+    LowDiskSpace,
+    CompetingProcess,
+    HighCpuLoad,
+    CacheUnavailable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadinessIssue {
+    pub kind: ReadinessIssueKind,
+    pub message: String,
+}
+
+impl ReadinessIssue {
+    pub fn new(kind: ReadinessIssueKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+/// What preparing the cache did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachePreparation {
+    /// Bytes of expired entries dropped
+    pub pruned_bytes: u64,
+    pub warmed_files: usize,
+    pub warmed_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LaunchReadiness {
+    /// True when there are no blockers
+    pub ready: bool,
+    pub blockers: Vec<ReadinessIssue>,
+    pub warnings: Vec<ReadinessIssue>,
+    pub snapshot: SystemSnapshot,
+    #[serde(default)]
+    pub cache: CachePreparation,
+}
+
+impl LaunchReadiness {
+    pub fn warn(&mut self, issue: ReadinessIssue) {
+        self.warnings.push(issue);
+    }
+}
+
+/// What the launch needs from the machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchRequirements {
+    /// Heap the game is given
+    pub ram_allocation_mb: Option<u64>,
+}
+
+impl LaunchRequirements {
+    /// Read the requirements from a serialized profile
+    pub fn from_profile(profile: &serde_json::Value) -> Self {
+        let ram_allocation_mb = RAM_ALLOCATION_POINTERS.iter()
+            .find_map(|pointer| profile.pointer(pointer).and_then(|v| v.as_u64()));
+        Self { ram_allocation_mb }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReadinessThresholds {
+    /// RAM the OS and launcher need on top of the game's heap
+    pub ram_headroom_mb: u64,
+    /// Free space on the game drive below which the launch is blocked
+    pub min_disk_free_mb: u64,
+    /// Free space on the game drive below which a warning is shown
+    pub low_disk_free_mb: u64,
+    /// CPU use at which another process counts as competing
+    pub competing_cpu_percent: f32,
+    /// Average CPU use across cores considered high
+    pub high_cpu_percent: f32,
+}
+
+impl Default for ReadinessThresholds {
+    fn default() -> Self {
+        Self {
+            ram_headroom_mb: 1024,
+            min_disk_free_mb: 512,
+            low_disk_free_mb: 4096,
+            competing_cpu_percent: 25.0,
+            high_cpu_percent: 80.0,
+        }
+    }
+}
+
+/// Judge `snapshot` against `requirements`
+pub fn assess(snapshot: SystemSnapshot, requirements: &LaunchRequirements, thresholds: &ReadinessThresholds) -> LaunchReadiness {
+    let mut blockers = Vec::new();
+    let mut warnings = Vec::new();
+
+    if let Some(heap_mb) = requirements.ram_allocation_mb {
+        if heap_mb + thresholds.ram_headroom_mb > snapshot.total_ram_mb {
+            blockers.push(ReadinessIssue::new(
+                ReadinessIssueKind::InsufficientRam,
+                format!("The profile gives the game {} MB, but this machine has {} MB in total", heap_mb, snapshot.total_ram_mb),
+            ));
+        } else if heap_mb + thresholds.ram_headroom_mb > snapshot.available_ram_mb {
+            warnings.push(ReadinessIssue::new(
+                ReadinessIssueKind::LowRam,
+                format!("The profile gives the game {} MB, but only {} MB is free; close other programs to avoid paging", heap_mb, snapshot.available_ram_mb),
+            ));
+        }
+    }
+
+    if let Some(drive) = &snapshot.game_drive {
+        let message = format!("Only {} MB is free on {}", drive.available_mb, drive.mount_point);
+        if drive.available_mb < thresholds.min_disk_free_mb {
+            blockers.push(ReadinessIssue::new(ReadinessIssueKind::LowDiskSpace, message));
+        } else if drive.available_mb < thresholds.low_disk_free_mb {
+            warnings.push(ReadinessIssue::new(ReadinessIssueKind::LowDiskSpace, message));
+        }
+    }
+
+    for process in snapshot.heavy_processes.iter().filter(|p| p.cpu_usage >= thresholds.competing_cpu_percent) {
+        warnings.push(ReadinessIssue::new(
+            ReadinessIssueKind::CompetingProcess,
+            format!("{} (pid {}) is using {:.0}% CPU", process.name, process.pid, process.cpu_usage),
+        ));
+    }
+
+    if !snapshot.cpu_usage.is_empty() {
+        let average = snapshot.cpu_usage.iter().sum::<f32>() / snapshot.cpu_usage.len() as f32;
+        if average >= thresholds.high_cpu_percent {
+            warnings.push(ReadinessIssue::new(
+                ReadinessIssueKind::HighCpuLoad,
+                format!("The CPU is already {:.0}% busy", average),
+            ));
+        }
+    }
+
+    LaunchReadiness {
+        ready: blockers.is_empty(),
+        blockers,
+        warnings,
+        snapshot,
+        cache: CachePreparation::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::performance::{DriveSpace, ProcessLoad};
+
+    fn snapshot() -> SystemSnapshot {
+        SystemSnapshot {
+            total_ram_mb: 16_384,
+            available_ram_mb: 10_000,
+            cpu_cores: 4,
+            cpu_usage: vec![10.0, 20.0, 5.0, 5.0],
+            process_count: 300,
+            game_drive: Some(DriveSpace { mount_point: "/games".to_string(), total_mb: 500_000, available_mb: 50_000 }),
+            heavy_processes: vec![
+                ProcessLoad { pid: 4321, name: "encoder".to_string(), cpu_usage: 180.0, memory_mb: 900 },
+                ProcessLoad { pid: 99, name: "browser".to_string(), cpu_usage: 12.0, memory_mb: 3000 },
+            ],
+        }
+    }
+
+    fn kinds(issues: &[ReadinessIssue]) -> Vec<ReadinessIssueKind> {
+        issues.iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn test_heap_is_checked_against_total_and_free_ram() {
+        let thresholds = ReadinessThresholds::default();
+        let needs = |mb| LaunchRequirements { ram_allocation_mb: Some(mb) };
+
+        let fits = assess(snapshot(), &needs(8_192), &thresholds);
+        assert!(fits.ready);
+        assert_eq!(kinds(&fits.warnings), [ReadinessIssueKind::CompetingProcess]);
+        assert_eq!(fits.warnings[0].message, "encoder (pid 4321) is using 180% CPU");
+
+        // Fits in the machine, not in what's free
+        let tight = assess(snapshot(), &needs(9_500), &thresholds);
+        assert!(tight.ready);
+        assert!(kinds(&tight.warnings).contains(&ReadinessIssueKind::LowRam));
+
+        let too_big = assess(snapshot(), &needs(16_000), &thresholds);
+        assert!(!too_big.ready);
+        assert_eq!(kinds(&too_big.blockers), [ReadinessIssueKind::InsufficientRam]);
+
+        // Nothing asked for, nothing to check
+        assert!(assess(snapshot(), &LaunchRequirements::default(), &thresholds).blockers.is_empty());
+    }
+
+    #[test]
+    fn test_disk_space_and_cpu_load() {
+        let thresholds = ReadinessThresholds::default();
+        let mut busy = snapshot();
+        busy.cpu_usage = vec![95.0, 90.0, 85.0, 70.0];
+        busy.game_drive.as_mut().unwrap().available_mb = 2_000;
+        let readiness = assess(busy.clone(), &LaunchRequirements::default(), &thresholds);
+        assert!(readiness.ready);
+        assert_eq!(kinds(&readiness.warnings), [
+            ReadinessIssueKind::LowDiskSpace,
+            ReadinessIssueKind::CompetingProcess,
+            ReadinessIssueKind::HighCpuLoad,
+        ]);
+
+        busy.game_drive.as_mut().unwrap().available_mb = 100;
+        let readiness = assess(busy, &LaunchRequirements::default(), &thresholds);
+        assert_eq!(kinds(&readiness.blockers), [ReadinessIssueKind::LowDiskSpace]);
+        assert_eq!(readiness.blockers[0].message, "Only 100 MB is free on /games");
+    }
+
+    #[test]
+    fn test_requirements_from_profile() {
+        let shared = serde_json::json!({ "performance": { "ram_allocation_mb": 6144 } });
+        assert_eq!(LaunchRequirements::from_profile(&shared).ram_allocation_mb, Some(6144));
+        let settings = serde_json::json!({ "settings": { "ram_allocation_mb": 4096 } });
+        assert_eq!(LaunchRequirements::from_profile(&settings).ram_allocation_mb, Some(4096));
+        assert_eq!(LaunchRequirements::from_profile(&serde_json::json!({ "settings": {} })), LaunchRequirements::default());
+    }
+}
@@ -0,0 +1,310 @@
+//! Pond Capability Discovery
+//!
+//! Asks a Pond server what it offers before the player joins:
+//! - Sends the launcher's `ClientCapabilities` to the server's discovery
+//!   endpoint on its launcher API port
+//! - Gets back the server's capabilities, asset preload manifest and
+//!   network hints in one answer
+//!
+//! Both sides ignore fields they don't know, so servers can add fields
+//! without breaking older launchers; the protocol version only changes when
+//! a field changes meaning. The join queue, on the same port, is in
+//! [`queue`].
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::core::preload::AssetPreloadManifest;
+
+pub mod queue;
+
+pub use queue::{QueueClient, QueueStatus};
+
+/// Where Pond servers answer discovery requests
+pub const DISCOVERY_PATH: &str = "/pond/discover";
+
+/// Pond's default `launcher_api_port`
+pub const DEFAULT_PORT: u16 = 25566;
+
+/// Highest discovery protocol this launcher speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum PondError {
+    #[error("Could not reach {0}: {1}")]
+    Unreachable(String, String),
+
+    #[error("{0} refused the request: {1}")]
+    Rejected(String, String),
+
+    #[error("Unreadable answer from Pond: {0}")]
+    InvalidResponse(String),
+
+    #[error("Server answered with unsupported protocol version {0}")]
+    UnsupportedProtocol(u32),
+}
+
+/// What the launcher tells the server about itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientCapabilities {
+    pub protocol_version: u32,
+    pub launcher_version: String,
+    pub features: Vec<String>,
+    /// Measured round trip, so the network hints fit this connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u32>,
+}
+
+// Mirrors `pond::core::integration`; every field defaults so older or
+// newer servers that leave some out still parse, and fields the launcher
+// doesn't use are ignored.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmeticCapabilities {
+    pub supported_types: Vec<String>,
+    pub max_file_size_mb: u32,
+    pub allows_animated: bool,
+    pub allows_custom: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformanceHints {
+    pub recommended_render_distance: u32,
+    pub recommended_entity_distance: u32,
+    pub server_tick_rate: u32,
+    pub adaptive_throttling: bool,
+    pub texture_streaming: bool,
+    pub chunk_preload_radius: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectivityFeatures {
+    pub connection_pooling: bool,
+    pub keep_alive_interval_ms: u32,
+    pub ping_optimization: bool,
+    pub route_selection: bool,
+    pub session_handoff: bool,
+    pub reconnect_grace_period_secs: u32,
+    pub queue_position_tracking: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncCapabilities {
+    pub friend_activity: bool,
+    pub player_presence: bool,
+    pub session_transfer: bool,
+    pub profile_sync: bool,
+    pub settings_sync: bool,
+    pub achievement_sync: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerCapabilities {
+    /// Feature badges such as `cosmetics` or `queue_priority`
+    pub features: Vec<String>,
+    pub api_version: String,
+    pub cosmetics: CosmeticCapabilities,
+    pub performance: PerformanceHints,
+    pub connectivity: ConnectivityFeatures,
+    pub sync: SyncCapabilities,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkOptimizationHints {
+    pub recommended_update_rate: u32,
+    pub interpolation_delay_ms: u32,
+    pub prediction_enabled: bool,
+    pub compression_level: u32,
+    pub batch_updates: bool,
+}
+
+/// A server's answer to discovery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerDiscovery {
+    /// The version both sides speak: the lower of the two
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub capabilities: ServerCapabilities,
+    #[serde(default)]
+    pub preload: AssetPreloadManifest,
+    #[serde(default)]
+    pub network_hints: NetworkOptimizationHints,
+}
+
+/// Talks to Pond servers' launcher API
+pub struct PondClient {
+    client: reqwest::Client,
+    launcher_version: String,
+    features: Vec<String>,
+}
+
+impl PondClient {
+    pub fn new(launcher_version: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            launcher_version: launcher_version.into(),
+            features: vec!["asset_preload".to_string(), "ping_history".to_string()],
+        }
+    }
+
+    /// `addr` is a host, `host:port` or URL; without a port Pond's default
+    /// launcher API port is used
+    pub fn discovery_url(addr: &str) -> String {
+        api_url(addr, DISCOVERY_PATH)
+    }
+
+    pub async fn discover(&self, addr: &str) -> Result<ServerDiscovery, PondError> {
+        self.discover_with_latency(addr, None).await
+    }
+
+    /// Discover, asking for network hints that suit `latency_ms`
+    pub async fn discover_with_latency(&self, addr: &str, latency_ms: Option<u32>) -> Result<ServerDiscovery, PondError> {
+        let request = ClientCapabilities {
+            protocol_version: PROTOCOL_VERSION,
+            launcher_version: self.launcher_version.clone(),
+            features: self.features.clone(),
+            latency_ms,
+        };
+        let discovery: ServerDiscovery = post_json(&self.client, addr, &Self::discovery_url(addr), &request).await?;
+        if discovery.protocol_version == 0 || discovery.protocol_version > PROTOCOL_VERSION {
+            return Err(PondError::UnsupportedProtocol(discovery.protocol_version));
+        }
+        Ok(discovery)
+    }
+}
+
+/// `path` on the launcher API of `addr`, a host, `host:port` or URL
+pub fn api_url(addr: &str, path: &str) -> String {
+    let addr = addr.trim_end_matches('/');
+    if addr.starts_with("http://") || addr.starts_with("https://") {
+        return format!("{}{}", addr, path);
+    }
+    let has_port = addr.rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && !host.ends_with(':') && port.parse::<u16>().is_ok());
+    if has_port {
+        format!("http://{}{}", addr, path)
+    } else {
+        format!("http://{}:{}{}", addr, DEFAULT_PORT, path)
+    }
+}
+
+/// POST `body` as JSON to `addr`'s `url` and read the answer. Error
+/// answers become `Rejected` with the server's message.
+async fn post_json<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    addr: &str,
+    url: &str,
+    body: &impl Serialize,
+) -> Result<T, PondError> {
+    let response = client.post(url).json(body).send().await
+        .map_err(|e| PondError::Unreachable(addr.to_string(), e.to_string()))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        let message = body.get("error").and_then(|v| v.as_str()).map(String::from)
+            .unwrap_or_else(|| status.to_string());
+        return Err(PondError::Rejected(addr.to_string(), message));
+    }
+    response.json().await.map_err(|e| PondError::InvalidResponse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A real Pond discovery endpoint on a free port
+    async fn pond_server() -> SocketAddr {
+        let bridge = pond::LauncherBridge::new(Arc::new(pond::AssetRegistry::new()));
+        bridge.register_preload_asset(pond::PreloadAsset {
+            path: "models/boat.bin".to_string(),
+            sha256: "cd".repeat(32),
+            size_bytes: 4096,
+            priority: pond::PreloadPriority::Streamable,
+            url: None,
+        });
+        let endpoint = pond::DiscoveryEndpoint::bind("127.0.0.1:0".parse().unwrap(), Arc::new(bridge)).await.unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(endpoint.serve());
+        addr
+    }
+
+    /// Answers every connection with `body` as JSON
+    async fn canned_server(status: &'static str, body: &'static str) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body,
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_discovery_url() {
+        assert_eq!(PondClient::discovery_url("play.example.com"), "http://play.example.com:25566/pond/discover");
+        assert_eq!(PondClient::discovery_url("10.0.0.5:4000"), "http://10.0.0.5:4000/pond/discover");
+        assert_eq!(PondClient::discovery_url("https://pond.example.com/"), "https://pond.example.com/pond/discover");
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_with_pond() {
+        let addr = pond_server().await;
+        let client = PondClient::new("0.1.0");
+
+        let discovery = client.discover_with_latency(&addr.to_string(), Some(250)).await.unwrap();
+        assert_eq!(discovery.protocol_version, PROTOCOL_VERSION);
+        assert!(discovery.capabilities.features.contains(&"world_manifest".to_string()));
+        assert_eq!(discovery.capabilities.performance.server_tick_rate, 20);
+        assert!(discovery.capabilities.sync.friend_activity);
+        assert_eq!(discovery.preload.assets[0].path, "models/boat.bin");
+        assert_eq!(discovery.network_hints.recommended_update_rate, 5);
+        assert!(discovery.network_hints.prediction_enabled);
+
+        let nearby = client.discover(&format!("http://{}", addr)).await.unwrap();
+        assert_eq!(nearby.network_hints.recommended_update_rate, 20);
+    }
+
+    #[tokio::test]
+    async fn test_tolerates_servers_from_other_versions() {
+        // A newer server: extra fields and sections the launcher doesn't know
+        let addr = canned_server("200 OK", r#"{
+            "protocol_version": 1,
+            "capabilities": { "features": ["cosmetics", "holograms"], "api_version": "2.0.0", "holograms": { "max": 4 } },
+            "network_hints": { "recommended_update_rate": 15, "jitter_buffer_ms": 30 },
+            "voice_chat": { "codec": "opus" }
+        }"#).await;
+        let discovery = PondClient::new("0.1.0").discover(&addr.to_string()).await.unwrap();
+        assert_eq!(discovery.capabilities.features, ["cosmetics", "holograms"]);
+        assert_eq!(discovery.capabilities.cosmetics, CosmeticCapabilities::default());
+        assert!(discovery.preload.assets.is_empty());
+        assert_eq!(discovery.network_hints.recommended_update_rate, 15);
+
+        let addr = canned_server("200 OK", r#"{ "protocol_version": 2 }"#).await;
+        let result = PondClient::new("0.1.0").discover(&addr.to_string()).await;
+        assert!(matches!(result, Err(PondError::UnsupportedProtocol(2))));
+
+        let addr = canned_server("400 Bad Request", r#"{ "error": "protocol_version must be at least 1" }"#).await;
+        let result = PondClient::new("0.1.0").discover(&addr.to_string()).await;
+        assert!(matches!(result, Err(PondError::Rejected(_, message)) if message.contains("protocol_version")));
+    }
+}
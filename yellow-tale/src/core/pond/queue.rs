@@ -0,0 +1,360 @@
+//! Server Join Queue
+//!
+//! Waits in line for a full Pond server:
+//! - Takes a ticket from the server's queue on its launcher API port,
+//!   sending the player's session token to claim priority; the server
+//!   checks the claim against the player's subscription
+//! - Polls the ticket for the position and estimated wait until the
+//!   server holds a slot for the player
+//! - Gives the place back on `leave_queue`
+//!
+//! Pond drops tickets that stop polling, so a launcher that quits doesn't
+//! hold up the line.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::{api_url, post_json, PondError};
+
+pub const QUEUE_JOIN_PATH: &str = "/pond/queue/join";
+pub const QUEUE_STATUS_PATH: &str = "/pond/queue/status";
+pub const QUEUE_LEAVE_PATH: &str = "/pond/queue/leave";
+
+/// How often a waiting ticket is polled; well inside the minute Pond
+/// keeps a silent ticket
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePhase {
+    #[default]
+    Idle,
+    Waiting,
+    /// The server holds a slot; join now
+    Admitted,
+    /// The server no longer knows the ticket
+    Lost,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub server: Option<String>,
+    pub phase: QueuePhase,
+    /// Players ahead
+    pub position: u32,
+    pub queue_length: u32,
+    pub estimated_wait_secs: u32,
+    /// Whether the server placed the player in its priority tier
+    pub priority: bool,
+    /// Why the last poll failed; polling carries on unless the ticket is lost
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TicketState {
+    Waiting,
+    Admitted,
+}
+
+/// Mirrors `pond::core::integration::queue::QueueStatus`
+#[derive(Debug, Clone, Deserialize)]
+struct TicketStatus {
+    ticket: Uuid,
+    state: TicketState,
+    #[serde(default)]
+    position: u32,
+    #[serde(default)]
+    queue_length: u32,
+    #[serde(default)]
+    estimated_wait_secs: u32,
+    #[serde(default)]
+    priority: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct JoinRequest<'a> {
+    user_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority_token: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct TicketRequest {
+    ticket: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaveResponse {
+    left: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Ticket {
+    server: String,
+    ticket: Uuid,
+}
+
+/// Holds the launcher's place in one server's queue at a time
+pub struct QueueClient {
+    client: reqwest::Client,
+    poll_interval: Duration,
+    ticket: Mutex<Option<Ticket>>,
+    status: Mutex<QueueStatus>,
+    events: broadcast::Sender<QueueStatus>,
+    poller: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl QueueClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            poll_interval: POLL_INTERVAL,
+            ticket: Mutex::new(None),
+            status: Mutex::new(QueueStatus::default()),
+            events: broadcast::channel(64).0,
+            poller: Mutex::new(None),
+        }
+    }
+
+    /// Override [`POLL_INTERVAL`]
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Status after every change, for the UI
+    pub fn subscribe(&self) -> broadcast::Receiver<QueueStatus> {
+        self.events.subscribe()
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Replace the status, announcing it if anything changed
+    fn set_status(&self, status: QueueStatus) {
+        let changed = {
+            let mut current = self.status.lock().unwrap();
+            std::mem::replace(&mut *current, status.clone()) != status
+        };
+        if changed {
+            let _ = self.events.send(status);
+        }
+    }
+
+    fn apply(&self, server: &str, answer: &TicketStatus) -> QueueStatus {
+        let status = QueueStatus {
+            server: Some(server.to_string()),
+            phase: match answer.state {
+                TicketState::Waiting => QueuePhase::Waiting,
+                TicketState::Admitted => QueuePhase::Admitted,
+            },
+            position: answer.position,
+            queue_length: answer.queue_length,
+            estimated_wait_secs: answer.estimated_wait_secs,
+            priority: answer.priority,
+            error: None,
+        };
+        self.set_status(status.clone());
+        status
+    }
+
+    /// Join `addr`'s queue as `user_id`, leaving any other queue first.
+    /// With a `priority_token` (the player's session token) the server
+    /// checks the account for priority. Polls in the background until
+    /// admitted.
+    pub async fn join_queue(self: &Arc<Self>, addr: &str, user_id: Uuid, priority_token: Option<&str>) -> Result<QueueStatus, PondError> {
+        let previous = self.ticket.lock().unwrap().clone();
+        if previous.as_ref().is_some_and(|t| t.server != addr) {
+            if let Err(e) = self.leave_queue().await {
+                warn!("Could not leave the previous queue: {}", e);
+            }
+        }
+
+        let request = JoinRequest { user_id, priority_token };
+        let answer: TicketStatus = post_json(&self.client, addr, &api_url(addr, QUEUE_JOIN_PATH), &request).await?;
+        *self.ticket.lock().unwrap() = Some(Ticket { server: addr.to_string(), ticket: answer.ticket });
+        let status = self.apply(addr, &answer);
+        info!("Queued for {} at position {} (priority: {})", addr, status.position, status.priority);
+
+        let poller = (status.phase == QueuePhase::Waiting).then(|| {
+            let client = self.clone();
+            tokio::spawn(async move { client.poll().await })
+        });
+        if let Some(old) = std::mem::replace(&mut *self.poller.lock().unwrap(), poller) {
+            old.abort();
+        }
+        Ok(status)
+    }
+
+    /// Poll the ticket until the server admits the player or forgets it
+    async fn poll(&self) {
+        loop {
+            tokio::time::sleep(self.poll_interval).await;
+            let Some(Ticket { server, ticket }) = self.ticket.lock().unwrap().clone() else {
+                return;
+            };
+            let url = api_url(&server, QUEUE_STATUS_PATH);
+            match post_json::<TicketStatus>(&self.client, &server, &url, &TicketRequest { ticket }).await {
+                Ok(answer) => {
+                    if self.apply(&server, &answer).phase == QueuePhase::Admitted {
+                        info!("Admitted to {}", server);
+                        return;
+                    }
+                }
+                Err(PondError::Rejected(_, message)) => {
+                    warn!("Lost place in {}'s queue: {}", server, message);
+                    *self.ticket.lock().unwrap() = None;
+                    let mut status = self.status();
+                    status.phase = QueuePhase::Lost;
+                    status.error = Some(message);
+                    self.set_status(status);
+                    return;
+                }
+                Err(e) => {
+                    let mut status = self.status();
+                    status.error = Some(e.to_string());
+                    self.set_status(status);
+                }
+            }
+        }
+    }
+
+    /// Give up the current place, or the slot held after admission.
+    /// Returns whether there was one.
+    pub async fn leave_queue(&self) -> Result<bool, PondError> {
+        if let Some(poller) = self.poller.lock().unwrap().take() {
+            poller.abort();
+        }
+        let Some(Ticket { server, ticket }) = self.ticket.lock().unwrap().take() else {
+            self.set_status(QueueStatus::default());
+            return Ok(false);
+        };
+        self.set_status(QueueStatus::default());
+        let url = api_url(&server, QUEUE_LEAVE_PATH);
+        let answer: LeaveResponse = post_json(&self.client, &server, &url, &TicketRequest { ticket }).await?;
+        Ok(answer.left)
+    }
+}
+
+impl Default for QueueClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+
+    /// Answers the subscription lookup as the central server would
+    struct Subscriptions(HashMap<String, Uuid>);
+
+    #[async_trait]
+    impl pond::CentralApi for Subscriptions {
+        async fn post(&self, _path: &str, body: serde_json::Value) -> Result<serde_json::Value, pond::CentralApiError> {
+            Ok(match self.0.get(body["token"].as_str().unwrap_or_default()) {
+                Some(user_id) => serde_json::json!({
+                    "success": true,
+                    "data": { "user_id": user_id, "tier": "premium", "status": "active" },
+                }),
+                None => serde_json::json!({ "success": false, "error": "Invalid token" }),
+            })
+        }
+    }
+
+    /// A full Pond server with one slot, and its launcher bridge
+    async fn pond_server(premium: Uuid) -> (SocketAddr, Arc<pond::LauncherBridge>) {
+        let verifier = pond::PriorityVerifier::new(Arc::new(Subscriptions(HashMap::from([
+            ("premium-session".to_string(), premium),
+        ]))));
+        let bridge = Arc::new(
+            pond::LauncherBridge::new(Arc::new(pond::AssetRegistry::new()))
+                .with_priority_verifier(Arc::new(verifier)),
+        );
+        bridge.set_max_players(1);
+        bridge.set_player_count(1);
+        let endpoint = pond::DiscoveryEndpoint::bind("127.0.0.1:0".parse().unwrap(), bridge.clone()).await.unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(endpoint.serve());
+        (addr, bridge)
+    }
+
+    fn client() -> Arc<QueueClient> {
+        Arc::new(QueueClient::new().with_poll_interval(Duration::from_millis(20)))
+    }
+
+    #[tokio::test]
+    async fn test_priority_client_jumps_the_queue() {
+        let premium = Uuid::new_v4();
+        let (addr, bridge) = pond_server(premium).await;
+        let addr = addr.to_string();
+
+        let regulars: Vec<Arc<QueueClient>> = (0..4).map(|_| client()).collect();
+        for (i, queue) in regulars.iter().enumerate() {
+            // A token without a premium subscription behind it changes nothing
+            let token = (i == 3).then_some("free-session");
+            let status = queue.join_queue(&addr, Uuid::new_v4(), token).await.unwrap();
+            assert_eq!((status.phase, status.position, status.priority), (QueuePhase::Waiting, i as u32, false));
+        }
+
+        let jumper = client();
+        let status = jumper.join_queue(&addr, premium, Some("premium-session")).await.unwrap();
+        assert!(status.priority);
+        assert_eq!((status.position, status.queue_length), (0, 5));
+
+        // Everyone else learns they moved back on their next poll
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        for (i, queue) in regulars.iter().enumerate() {
+            let status = queue.status();
+            assert_eq!(status.position, i as u32 + 1);
+            assert_eq!(status.estimated_wait_secs, (i as u32 + 1) * 30);
+        }
+
+        // A slot frees up and the priority player is told to join
+        let mut events = jumper.subscribe();
+        bridge.set_player_count(0);
+        let admitted = tokio::time::timeout(Duration::from_secs(2), events.recv()).await.unwrap().unwrap();
+        assert_eq!(admitted.phase, QueuePhase::Admitted);
+        assert_eq!(admitted.server.as_deref(), Some(addr.as_str()));
+
+        // Leaving the queue moves the rest up
+        assert!(regulars[0].leave_queue().await.unwrap());
+        assert_eq!(regulars[0].status(), QueueStatus::default());
+        assert!(!regulars[0].leave_queue().await.unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(regulars[1].status().position, 0);
+        assert_eq!(bridge.export_server_info().queue_length, 3);
+    }
+
+    #[tokio::test]
+    async fn test_lost_tickets_and_unreachable_servers() {
+        let (addr, bridge) = pond_server(Uuid::new_v4()).await;
+        let user = Uuid::new_v4();
+        let queue = client();
+        queue.join_queue(&addr.to_string(), user, None).await.unwrap();
+
+        // The server forgets the ticket, as it would after a restart
+        bridge.leave_queue(user);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = queue.status();
+        assert_eq!(status.phase, QueuePhase::Lost);
+        assert_eq!(status.error.as_deref(), Some("Unknown ticket"));
+
+        // Nothing listens on port 9
+        let result = client().join_queue("127.0.0.1:9", user, None).await;
+        assert!(matches!(result, Err(PondError::Unreachable(..))));
+    }
+}
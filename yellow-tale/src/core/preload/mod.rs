@@ -0,0 +1,563 @@
+//! Asset Preload Module
+//!
+//! Gets a server's assets into the cache before the player joins:
+//! - Fetches the server's `AssetPreloadManifest` over HTTP
+//! - Skips every asset whose SHA-256 is already in `cache/assets`
+//! - Downloads required assets first, then streamable ones
+//! - Publishes progress so the UI can hold the Join button until every
+//!   required asset is cached
+//!
+//! A streamable asset that fails to download never blocks joining; it is
+//! reported as a warning and the game streams it in later.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::core::health::{CheckResult, HealthCheck};
+
+pub mod prefetch;
+
+pub use prefetch::{AssetPrefetcher, PrefetchStatus};
+
+/// Where Pond servers publish their preload manifest
+pub const MANIFEST_PATH: &str = "/pond/preload/manifest";
+
+/// Assets without their own URL are served from here by hash
+pub const ASSET_PATH: &str = "/pond/preload/assets";
+
+#[derive(Error, Debug)]
+pub enum PreloadError {
+    #[error("Could not fetch the preload manifest: {0}")]
+    Manifest(String),
+
+    #[error("Download failed for {path}: {error}")]
+    Download { path: String, error: String },
+
+    #[error("Checksum mismatch for {path}: expected {expected}, got {actual}")]
+    ChecksumMismatch { path: String, expected: String, actual: String },
+
+    #[error("Required assets could not be downloaded: {}", .0.join(", "))]
+    RequiredMissing(Vec<String>),
+
+    #[error("Assets for {0} are not ready yet")]
+    NotReady(String),
+
+    #[error("A preload is already running")]
+    AlreadyRunning,
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// Mirrors `pond::core::integration`; fields the launcher doesn't use are
+// left out and ignored when deserializing.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreloadPriority {
+    Required,
+    Streamable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreloadAsset {
+    pub path: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub priority: PreloadPriority,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetPreloadManifest {
+    #[serde(default)]
+    pub assets: Vec<PreloadAsset>,
+}
+
+/// Where manifests and asset bytes come from
+#[async_trait]
+pub trait ManifestSource: Send + Sync {
+    async fn manifest(&self, server: &str) -> Result<AssetPreloadManifest, PreloadError>;
+
+    async fn asset(&self, server: &str, asset: &PreloadAsset) -> Result<Vec<u8>, PreloadError>;
+}
+
+/// Fetches from the server's own HTTP endpoint
+pub struct HttpManifestSource {
+    client: reqwest::Client,
+}
+
+impl HttpManifestSource {
+    pub fn new() -> Self {
+        Self { client: reqwest::Client::new() }
+    }
+
+    fn base_url(server: &str) -> String {
+        let server = server.trim_end_matches('/');
+        if server.starts_with("http://") || server.starts_with("https://") {
+            server.to_string()
+        } else {
+            format!("http://{}", server)
+        }
+    }
+
+    /// Where `server` serves `asset`: its own URL, or by hash from the
+    /// server's asset endpoint
+    pub fn asset_url(server: &str, asset: &PreloadAsset) -> String {
+        let base = Self::base_url(server);
+        match &asset.url {
+            Some(url) if url.starts_with('/') => format!("{}{}", base, url),
+            Some(url) => url.clone(),
+            None => format!("{}{}/{}", base, ASSET_PATH, asset.sha256),
+        }
+    }
+}
+
+impl Default for HttpManifestSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ManifestSource for HttpManifestSource {
+    async fn manifest(&self, server: &str) -> Result<AssetPreloadManifest, PreloadError> {
+        let url = format!("{}{}", Self::base_url(server), MANIFEST_PATH);
+        let response = self.client.get(&url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PreloadError::Manifest(e.to_string()))?;
+        response.json().await.map_err(|e| PreloadError::Manifest(e.to_string()))
+    }
+
+    async fn asset(&self, server: &str, asset: &PreloadAsset) -> Result<Vec<u8>, PreloadError> {
+        let url = Self::asset_url(server, asset);
+        let download_error = |e: reqwest::Error| PreloadError::Download { path: asset.path.clone(), error: e.to_string() };
+        let response = self.client.get(&url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(download_error)?;
+        Ok(response.bytes().await.map_err(download_error)?.to_vec())
+    }
+}
+
+/// Asset files named by their SHA-256, like the mod download cache
+pub struct AssetCache {
+    dir: PathBuf,
+}
+
+impl AssetCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path_of(&self, sha256: &str) -> PathBuf {
+        self.dir.join(sha256.to_lowercase())
+    }
+
+    pub fn contains(&self, sha256: &str) -> bool {
+        self.path_of(sha256).is_file()
+    }
+
+    /// Where a download of `sha256` is kept until it is complete
+    pub fn partial_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("partial").join(format!("{}.part", sha256.to_lowercase()))
+    }
+
+    /// Move a complete, verified download into the cache
+    pub async fn adopt(&self, sha256: &str, file: &Path) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::rename(file, self.path_of(sha256)).await
+    }
+
+    /// Store `bytes` under `sha256`; a half-written file is never visible
+    pub async fn put(&self, sha256: &str, bytes: &[u8]) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path_of(sha256);
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &path).await
+    }
+}
+
+/// Which assets are already cached and which still need fetching, in
+/// download order
+#[derive(Debug, Clone, Default)]
+pub struct PreloadPlan {
+    pub cached: Vec<PreloadAsset>,
+    pub missing: Vec<PreloadAsset>,
+}
+
+pub fn plan(manifest: &AssetPreloadManifest, cache: &AssetCache) -> PreloadPlan {
+    let (cached, mut missing): (Vec<_>, Vec<_>) = manifest.assets.iter()
+        .cloned()
+        .partition(|asset| cache.contains(&asset.sha256));
+    // Stable, so the server's order holds within a priority
+    missing.sort_by_key(|asset| asset.priority);
+    PreloadPlan { cached, missing }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreloadPhase {
+    #[default]
+    Idle,
+    FetchingManifest,
+    /// Required assets are downloading; joining must wait
+    Downloading,
+    /// Required assets are cached; streamable ones are still downloading
+    Streaming,
+    Complete,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreloadStatus {
+    pub server: Option<String>,
+    pub phase: PreloadPhase,
+    /// Every required asset is cached
+    pub ready: bool,
+    /// Share of all asset bytes that are cached
+    pub percent: f64,
+    /// Share of required asset bytes that are cached
+    pub required_percent: f64,
+    pub total_bytes: u64,
+    pub cached_bytes: u64,
+    /// Paths downloaded during this preload
+    pub downloaded: Vec<String>,
+    /// Streamable assets that could not be downloaded
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    required_total: u64,
+    #[serde(skip)]
+    required_cached: u64,
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        100.0
+    } else {
+        (part as f64 / whole as f64 * 1000.0).round() / 10.0
+    }
+}
+
+impl PreloadStatus {
+    fn add_cached(&mut self, asset: &PreloadAsset) {
+        self.cached_bytes += asset.size_bytes;
+        if asset.priority == PreloadPriority::Required {
+            self.required_cached += asset.size_bytes;
+        }
+        self.percent = percent(self.cached_bytes, self.total_bytes);
+        self.required_percent = percent(self.required_cached, self.required_total);
+    }
+}
+
+/// Downloads the assets a server asks for and tracks readiness to join
+pub struct PreloadManager {
+    source: Box<dyn ManifestSource>,
+    cache: AssetCache,
+    status: Mutex<PreloadStatus>,
+    running: AtomicBool,
+    events: broadcast::Sender<PreloadStatus>,
+}
+
+impl PreloadManager {
+    /// Assets live in `<cache_dir>/assets`
+    pub fn new(cache_dir: &Path, source: Box<dyn ManifestSource>) -> Self {
+        Self {
+            source,
+            cache: AssetCache::new(cache_dir.join("assets")),
+            status: Mutex::new(PreloadStatus::default()),
+            running: AtomicBool::new(false),
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    /// Status after every change, for the UI
+    pub fn subscribe(&self) -> broadcast::Receiver<PreloadStatus> {
+        self.events.subscribe()
+    }
+
+    pub fn status(&self) -> PreloadStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn update(&self, change: impl FnOnce(&mut PreloadStatus)) {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            change(&mut status);
+            status.clone()
+        };
+        let _ = self.events.send(status);
+    }
+
+    /// Whether the player may join `server`, with any warnings to show
+    pub fn join_warnings(&self, server: &str) -> Result<Vec<String>, PreloadError> {
+        let status = self.status();
+        if status.server.as_deref() == Some(server) && status.ready {
+            Ok(status.warnings)
+        } else {
+            Err(PreloadError::NotReady(server.to_string()))
+        }
+    }
+
+    /// Fetch `server`'s manifest and download what the cache lacks, required
+    /// assets first. Returns once streamable assets are done too; `ready`
+    /// flips as soon as the required ones are cached.
+    pub async fn preload(&self, server: &str) -> Result<PreloadStatus, PreloadError> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err(PreloadError::AlreadyRunning);
+        }
+        let result = self.run(server).await;
+        self.running.store(false, Ordering::SeqCst);
+
+        if let Err(e) = &result {
+            warn!("Preload for {} failed: {}", server, e);
+            let error = e.to_string();
+            self.update(|status| {
+                status.phase = PreloadPhase::Failed;
+                status.error = Some(error);
+            });
+        }
+        result
+    }
+
+    async fn run(&self, server: &str) -> Result<PreloadStatus, PreloadError> {
+        self.update(|status| {
+            *status = PreloadStatus {
+                server: Some(server.to_string()),
+                phase: PreloadPhase::FetchingManifest,
+                ..Default::default()
+            };
+        });
+
+        let manifest = self.source.manifest(server).await?;
+        let plan = plan(&manifest, &self.cache);
+        self.update(|status| {
+            status.phase = PreloadPhase::Downloading;
+            for asset in &manifest.assets {
+                status.total_bytes += asset.size_bytes;
+                if asset.priority == PreloadPriority::Required {
+                    status.required_total += asset.size_bytes;
+                }
+            }
+            for asset in &plan.cached {
+                status.add_cached(asset);
+            }
+        });
+        info!(
+            "Preloading {}: {} of {} assets already cached",
+            server, plan.cached.len(), manifest.assets.len()
+        );
+
+        let (required, streamable): (Vec<_>, Vec<_>) = plan.missing.iter()
+            .partition(|asset| asset.priority == PreloadPriority::Required);
+        let mut fetched = HashSet::new();
+
+        let mut failed = Vec::new();
+        for asset in required {
+            if let Err(e) = self.fetch(server, asset, &mut fetched).await {
+                warn!("{}", e);
+                failed.push(asset.path.clone());
+            }
+        }
+        if !failed.is_empty() {
+            return Err(PreloadError::RequiredMissing(failed));
+        }
+        self.update(|status| {
+            status.ready = true;
+            status.phase = PreloadPhase::Streaming;
+        });
+
+        for asset in streamable {
+            if let Err(e) = self.fetch(server, asset, &mut fetched).await {
+                warn!("{}", e);
+                self.update(|status| status.warnings.push(e.to_string()));
+            }
+        }
+        self.update(|status| status.phase = PreloadPhase::Complete);
+        Ok(self.status())
+    }
+
+    /// Download and verify one asset; paths sharing a hash download once
+    async fn fetch(&self, server: &str, asset: &PreloadAsset, fetched: &mut HashSet<String>) -> Result<(), PreloadError> {
+        let hash = asset.sha256.to_lowercase();
+        if !fetched.contains(&hash) && !self.cache.contains(&hash) {
+            let bytes = self.source.asset(server, asset).await?;
+            let actual = hex::encode(Sha256::digest(&bytes));
+            if actual != hash {
+                return Err(PreloadError::ChecksumMismatch {
+                    path: asset.path.clone(),
+                    expected: asset.sha256.clone(),
+                    actual,
+                });
+            }
+            self.cache.put(&hash, &bytes).await?;
+            fetched.insert(hash);
+        }
+
+        self.update(|status| {
+            status.add_cached(asset);
+            status.downloaded.push(asset.path.clone());
+        });
+        Ok(())
+    }
+}
+
+/// Degraded when the last preload failed or left assets behind
+#[async_trait]
+impl HealthCheck for PreloadManager {
+    fn name(&self) -> &str {
+        "downloader"
+    }
+
+    async fn health_check(&self) -> CheckResult {
+        let status = self.status();
+        match status.phase {
+            PreloadPhase::Failed => CheckResult::degraded(status.error.unwrap_or_else(|| "Preload failed".to_string())),
+            _ if !status.warnings.is_empty() => {
+                CheckResult::degraded(format!("{} asset(s) failed to download", status.warnings.len()))
+            }
+            _ => CheckResult::ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    /// In-memory Pond server that records every asset request
+    struct FakeServer {
+        manifest: AssetPreloadManifest,
+        files: HashMap<String, Vec<u8>>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl ManifestSource for FakeServer {
+        async fn manifest(&self, _server: &str) -> Result<AssetPreloadManifest, PreloadError> {
+            Ok(self.manifest.clone())
+        }
+
+        async fn asset(&self, _server: &str, asset: &PreloadAsset) -> Result<Vec<u8>, PreloadError> {
+            self.requests.lock().unwrap().push(asset.path.clone());
+            self.files.get(&asset.path).cloned().ok_or_else(|| PreloadError::Download {
+                path: asset.path.clone(),
+                error: "404 Not Found".to_string(),
+            })
+        }
+    }
+
+    fn asset(path: &str, content: &[u8], priority: PreloadPriority) -> (PreloadAsset, Vec<u8>) {
+        let asset = PreloadAsset {
+            path: path.to_string(),
+            sha256: hex::encode(Sha256::digest(content)),
+            size_bytes: content.len() as u64,
+            priority,
+            url: None,
+        };
+        (asset, content.to_vec())
+    }
+
+    /// A manager whose server offers `assets` but only serves `served`
+    fn fake_manager(dir: &Path, assets: &[(PreloadAsset, Vec<u8>)], served: &[&str]) -> (PreloadManager, Arc<Mutex<Vec<String>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server = FakeServer {
+            manifest: AssetPreloadManifest { assets: assets.iter().map(|(a, _)| a.clone()).collect() },
+            files: assets.iter()
+                .filter(|(a, _)| served.contains(&a.path.as_str()))
+                .map(|(a, bytes)| (a.path.clone(), bytes.clone()))
+                .collect(),
+            requests: requests.clone(),
+        };
+        (PreloadManager::new(dir, Box::new(server)), requests)
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-preload-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_only_the_delta_is_fetched_required_first() {
+        use PreloadPriority::*;
+        let dir = temp_dir();
+        let assets = [
+            asset("sounds/ambient.ogg", b"ambient", Streamable),
+            asset("textures/grass.png", b"grass", Required),
+            asset("models/npc.glb", b"npc model", Required),
+            asset("textures/grass_copy.png", b"grass", Required),
+        ];
+        AssetCache::new(dir.join("assets")).put(&assets[2].0.sha256, &assets[2].1).await.unwrap();
+
+        let all: Vec<&str> = assets.iter().map(|(a, _)| a.path.as_str()).collect();
+        let (manager, requests) = fake_manager(&dir, &assets, &all);
+        let mut events = manager.subscribe();
+        let status = manager.preload("play.example.com:25565").await.unwrap();
+
+        // The cached model and the duplicate texture are never requested
+        assert_eq!(*requests.lock().unwrap(), vec!["textures/grass.png", "sounds/ambient.ogg"]);
+        assert_eq!(status.phase, PreloadPhase::Complete);
+        assert!(status.ready);
+        assert_eq!(status.percent, 100.0);
+        assert!(manager.join_warnings("play.example.com:25565").unwrap().is_empty());
+
+        let mut became_ready_at = None;
+        while let Ok(event) = events.try_recv() {
+            if event.ready && became_ready_at.is_none() {
+                became_ready_at = Some(event);
+            }
+        }
+        let became_ready_at = became_ready_at.unwrap();
+        assert_eq!(became_ready_at.required_percent, 100.0);
+        assert!(became_ready_at.percent < 100.0);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_missing_optional_assets_warn_but_required_block() {
+        use PreloadPriority::*;
+        let dir = temp_dir();
+        let assets = [
+            asset("textures/stone.png", b"stone", Required),
+            asset("music/theme.ogg", b"theme", Streamable),
+        ];
+
+        let (manager, _) = fake_manager(&dir, &assets, &["textures/stone.png"]);
+        let status = manager.preload("pond.local").await.unwrap();
+        assert!(status.ready);
+        assert_eq!(status.warnings.len(), 1);
+        assert_eq!(manager.join_warnings("pond.local").unwrap().len(), 1);
+        assert!(manager.join_warnings("other.server").is_err());
+
+        let (manager, _) = fake_manager(&temp_dir(), &assets, &["music/theme.ogg"]);
+        match manager.preload("pond.local").await {
+            Err(PreloadError::RequiredMissing(paths)) => assert_eq!(paths, vec!["textures/stone.png"]),
+            other => panic!("expected a missing required asset, got {:?}", other),
+        }
+        assert_eq!(manager.status().phase, PreloadPhase::Failed);
+        assert!(matches!(manager.join_warnings("pond.local"), Err(PreloadError::NotReady(_))));
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -0,0 +1,592 @@
+//! Background asset prefetching
+//!
+//! Downloads what a server's preload manifest lists but the asset cache
+//! lacks, while the player is still in the launcher:
+//! - Several assets at once, sharing one bandwidth cap
+//! - Interrupted downloads are kept and resumed with a range request
+//! - Held while the game runs, carrying on where they stopped
+//! - Failed assets are retried with backoff, then listed in the status
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use super::{AssetCache, AssetPreloadManifest, HttpManifestSource, PreloadAsset, PreloadError};
+use crate::core::config::PrefetchConfig;
+use crate::core::snapshots::Quiesce;
+
+/// How often a held prefetch asks whether the game has stopped
+pub const PAUSE_POLL: Duration = Duration::from_secs(5);
+
+/// Wait before the first retry of an asset; doubles with each one after
+pub const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+
+/// How often a running download asks whether the game has started
+const BUSY_CHECK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchPhase {
+    #[default]
+    Idle,
+    Running,
+    /// Held while the game runs
+    Paused,
+    Complete,
+}
+
+/// An asset that failed every attempt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefetchFailure {
+    pub path: String,
+    pub sha256: String,
+    pub attempts: u32,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchStatus {
+    pub server: Option<String>,
+    pub phase: PrefetchPhase,
+    pub paused_reason: Option<String>,
+    /// Distinct assets in the manifest
+    pub items_total: usize,
+    /// Assets cached, before or during this prefetch
+    pub items_done: usize,
+    pub bytes_total: u64,
+    pub bytes_done: u64,
+    /// Download speed over the last second or so
+    pub speed_bytes_per_sec: u64,
+    pub failed: Vec<PrefetchFailure>,
+}
+
+/// Why a download attempt stopped early
+enum Interrupted {
+    /// The game started; the partial file is kept and not held against
+    /// the asset's attempts
+    Paused,
+    Failed(PreloadError),
+}
+
+impl From<std::io::Error> for Interrupted {
+    fn from(e: std::io::Error) -> Self {
+        Self::Failed(e.into())
+    }
+}
+
+/// Spaces out reads so all downloads together stay under a byte rate
+struct RateLimiter {
+    next_free: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    async fn acquire(&self, bytes: usize, bytes_per_sec: u64) {
+        if bytes_per_sec == 0 {
+            return;
+        }
+        let until = {
+            let mut next_free = self.next_free.lock().unwrap();
+            let start = (*next_free).max(Instant::now());
+            *next_free = start + Duration::from_secs_f64(bytes as f64 / bytes_per_sec as f64);
+            *next_free
+        };
+        tokio::time::sleep_until(until).await;
+    }
+}
+
+struct SpeedMeter {
+    since: Instant,
+    bytes: u64,
+}
+
+/// Fills the asset cache from preload manifests in the background
+pub struct AssetPrefetcher {
+    client: reqwest::Client,
+    cache: AssetCache,
+    config: Mutex<PrefetchConfig>,
+    quiesce: Option<Arc<dyn Quiesce>>,
+    status: Mutex<PrefetchStatus>,
+    running: AtomicBool,
+    limiter: RateLimiter,
+    meter: Mutex<SpeedMeter>,
+    pause_poll: Duration,
+    retry_backoff: Duration,
+}
+
+impl AssetPrefetcher {
+    /// Assets live in `<cache_dir>/assets`, next to the preloader's
+    pub fn new(cache_dir: &Path, config: &PrefetchConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            cache: AssetCache::new(cache_dir.join("assets")),
+            config: Mutex::new(config.clone()),
+            quiesce: None,
+            status: Mutex::new(PrefetchStatus::default()),
+            running: AtomicBool::new(false),
+            limiter: RateLimiter { next_free: Mutex::new(Instant::now()) },
+            meter: Mutex::new(SpeedMeter { since: Instant::now(), bytes: 0 }),
+            pause_poll: PAUSE_POLL,
+            retry_backoff: RETRY_BACKOFF,
+        }
+    }
+
+    /// Hold downloads while the hook says the game is running
+    pub fn with_quiesce(mut self, quiesce: Arc<dyn Quiesce>) -> Self {
+        self.quiesce = Some(quiesce);
+        self
+    }
+
+    /// Override [`PAUSE_POLL`] and [`RETRY_BACKOFF`]
+    pub fn with_timing(mut self, pause_poll: Duration, retry_backoff: Duration) -> Self {
+        self.pause_poll = pause_poll;
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// The bandwidth cap applies at once; concurrency and attempts from
+    /// the next asset on
+    pub fn set_config(&self, config: &PrefetchConfig) {
+        *self.config.lock().unwrap() = config.clone();
+    }
+
+    fn config(&self) -> PrefetchConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn status(&self) -> PrefetchStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn update(&self, change: impl FnOnce(&mut PrefetchStatus)) {
+        change(&mut self.status.lock().unwrap());
+    }
+
+    /// Download every asset in `manifest` the cache lacks from `server`,
+    /// returning once each is cached or has failed every attempt
+    pub async fn prefetch(&self, server: &str, manifest: &AssetPreloadManifest) -> Result<PrefetchStatus, PreloadError> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err(PreloadError::AlreadyRunning);
+        }
+
+        let mut seen = HashSet::new();
+        let assets: Vec<&PreloadAsset> = manifest.assets.iter()
+            .filter(|asset| seen.insert(asset.sha256.to_lowercase()))
+            .collect();
+        let (cached, mut missing): (Vec<&PreloadAsset>, Vec<&PreloadAsset>) = assets.iter()
+            .partition(|asset| self.cache.contains(&asset.sha256));
+        missing.sort_by_key(|asset| asset.priority);
+        self.update(|status| {
+            *status = PrefetchStatus {
+                server: Some(server.to_string()),
+                phase: PrefetchPhase::Running,
+                items_total: assets.len(),
+                items_done: cached.len(),
+                bytes_total: assets.iter().map(|asset| asset.size_bytes).sum(),
+                bytes_done: cached.iter().map(|asset| asset.size_bytes).sum(),
+                ..Default::default()
+            };
+        });
+        info!("Prefetching {}: {} of {} assets already cached", server, cached.len(), assets.len());
+
+        let concurrency = self.config().max_concurrent.max(1);
+        let missing: Vec<PreloadAsset> = missing.into_iter().cloned().collect();
+        stream::iter(missing)
+            .map(|asset| async move { self.fetch(server, &asset).await })
+            .buffer_unordered(concurrency)
+            .collect::<Vec<()>>()
+            .await;
+
+        self.update(|status| {
+            status.phase = PrefetchPhase::Complete;
+            status.paused_reason = None;
+            status.speed_bytes_per_sec = 0;
+        });
+        self.running.store(false, Ordering::SeqCst);
+        Ok(self.status())
+    }
+
+    /// Download one asset, retrying with backoff
+    async fn fetch(&self, server: &str, asset: &PreloadAsset) {
+        let url = HttpManifestSource::asset_url(server, asset);
+        let mut counted = 0;
+        let mut attempts = 0;
+        loop {
+            self.wait_while_busy().await;
+            let error = match self.download(&url, asset, &mut counted).await {
+                Ok(()) => {
+                    self.update(|status| status.items_done += 1);
+                    return;
+                }
+                Err(Interrupted::Paused) => continue,
+                Err(Interrupted::Failed(e)) => e,
+            };
+
+            attempts += 1;
+            if attempts >= self.config().max_attempts.max(1) {
+                warn!("Giving up on {} after {} attempts: {}", asset.path, attempts, error);
+                self.update(|status| status.failed.push(PrefetchFailure {
+                    path: asset.path.clone(),
+                    sha256: asset.sha256.clone(),
+                    attempts,
+                    error: error.to_string(),
+                }));
+                return;
+            }
+            let backoff = self.retry_backoff * 2u32.saturating_pow(attempts - 1);
+            warn!("{}; retrying in {:?}", error, backoff);
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    /// One attempt, resuming from whatever an earlier one left behind.
+    /// `counted` is how much of the asset is in `bytes_done`.
+    async fn download(&self, url: &str, asset: &PreloadAsset, counted: &mut u64) -> Result<(), Interrupted> {
+        let hash = asset.sha256.to_lowercase();
+        let partial = self.cache.partial_path(&hash);
+        if let Some(parent) = partial.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut offset = tokio::fs::metadata(&partial).await.map(|m| m.len()).unwrap_or(0);
+        if offset > asset.size_bytes {
+            offset = 0;
+        }
+        self.recount(counted, offset);
+
+        if offset < asset.size_bytes || asset.size_bytes == 0 {
+            let download_error = |e: reqwest::Error| {
+                Interrupted::Failed(PreloadError::Download { path: asset.path.clone(), error: e.to_string() })
+            };
+            let mut request = self.client.get(url);
+            if offset > 0 {
+                request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+            }
+            let mut response = request.send().await
+                .and_then(|r| r.error_for_status())
+                .map_err(download_error)?;
+
+            let mut file = if offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+                tokio::fs::OpenOptions::new().append(true).open(&partial).await?
+            } else {
+                // The server sent the whole file
+                self.recount(counted, 0);
+                tokio::fs::File::create(&partial).await?
+            };
+
+            let mut last_check = Instant::now();
+            while let Some(chunk) = response.chunk().await.map_err(download_error)? {
+                self.limiter.acquire(chunk.len(), self.config().bandwidth_limit_kbps * 1024).await;
+                file.write_all(&chunk).await?;
+                self.recount(counted, *counted + chunk.len() as u64);
+                self.measure(chunk.len() as u64);
+
+                if last_check.elapsed() >= BUSY_CHECK {
+                    last_check = Instant::now();
+                    if self.busy().await.is_some() {
+                        file.flush().await?;
+                        return Err(Interrupted::Paused);
+                    }
+                }
+            }
+            file.sync_all().await?;
+        }
+
+        let actual = hash_file(&partial).await?;
+        if actual != hash {
+            tokio::fs::remove_file(&partial).await.ok();
+            self.recount(counted, 0);
+            return Err(Interrupted::Failed(PreloadError::ChecksumMismatch {
+                path: asset.path.clone(),
+                expected: asset.sha256.clone(),
+                actual,
+            }));
+        }
+        self.cache.adopt(&hash, &partial).await?;
+        self.recount(counted, asset.size_bytes);
+        Ok(())
+    }
+
+    /// Move an asset's share of `bytes_done` from `counted` to `now`
+    fn recount(&self, counted: &mut u64, now: u64) {
+        let before = std::mem::replace(counted, now);
+        self.update(|status| status.bytes_done = (status.bytes_done + now).saturating_sub(before));
+    }
+
+    fn measure(&self, bytes: u64) {
+        let mut meter = self.meter.lock().unwrap();
+        meter.bytes += bytes;
+        let elapsed = meter.since.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let speed = (meter.bytes as f64 / elapsed.as_secs_f64()) as u64;
+            *meter = SpeedMeter { since: Instant::now(), bytes: 0 };
+            self.update(|status| status.speed_bytes_per_sec = speed);
+        }
+    }
+
+    async fn busy(&self) -> Option<String> {
+        self.quiesce.as_ref()?.busy().await
+    }
+
+    async fn wait_while_busy(&self) {
+        while let Some(reason) = self.busy().await {
+            self.update(|status| {
+                status.phase = PrefetchPhase::Paused;
+                status.paused_reason = Some(reason);
+                status.speed_bytes_per_sec = 0;
+            });
+            tokio::time::sleep(self.pause_poll).await;
+        }
+        self.update(|status| {
+            if status.phase == PrefetchPhase::Paused {
+                status.phase = PrefetchPhase::Running;
+                status.paused_reason = None;
+            }
+        });
+    }
+}
+
+async fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::preload::{PreloadPriority, ASSET_PATH};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    #[derive(Default)]
+    struct Fixtures {
+        files: HashMap<String, Vec<u8>>,
+        /// Answer 503 this many times first
+        fail_first: Mutex<HashMap<String, usize>>,
+        /// Close the connection halfway through the first response
+        cut_once: Mutex<HashSet<String>>,
+        /// Hash and Range header of every request
+        requests: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    /// Local HTTP server serving `fixtures` by hash, with range support
+    async fn asset_server(fixtures: Arc<Fixtures>) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let fixtures = fixtures.clone();
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await.unwrap();
+                    let hash = line.split_whitespace().nth(1).unwrap_or("").rsplit('/').next().unwrap_or("").to_string();
+                    let mut range = None;
+                    loop {
+                        let mut header = String::new();
+                        if reader.read_line(&mut header).await.unwrap() == 0 || header.trim().is_empty() {
+                            break;
+                        }
+                        if let Some(value) = header.strip_prefix("range: ").or_else(|| header.strip_prefix("Range: ")) {
+                            range = Some(value.trim().to_string());
+                        }
+                    }
+                    fixtures.requests.lock().unwrap().push((hash.clone(), range.clone()));
+                    let mut stream = reader.into_inner();
+
+                    let failing = match fixtures.fail_first.lock().unwrap().get_mut(&hash) {
+                        Some(left) if *left > 0 => {
+                            *left -= 1;
+                            true
+                        }
+                        _ => false,
+                    };
+                    let Some(body) = fixtures.files.get(&hash).filter(|_| !failing) else {
+                        let status = if failing { "503 Service Unavailable" } else { "404 Not Found" };
+                        let _ = stream.write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes()).await;
+                        return;
+                    };
+                    let start = range.as_deref()
+                        .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    let status = if start > 0 { "206 Partial Content" } else { "200 OK" };
+                    let rest = &body[start..];
+                    let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, rest.len());
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    if fixtures.cut_once.lock().unwrap().remove(&hash) {
+                        let _ = stream.write_all(&rest[..rest.len() / 2]).await;
+                        let _ = stream.flush().await;
+                        return;
+                    }
+                    let _ = stream.write_all(rest).await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn asset(path: &str, content: &[u8], priority: PreloadPriority) -> (PreloadAsset, Vec<u8>) {
+        let asset = PreloadAsset {
+            path: path.to_string(),
+            sha256: hex::encode(Sha256::digest(content)),
+            size_bytes: content.len() as u64,
+            priority,
+            url: None,
+        };
+        (asset, content.to_vec())
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-prefetch-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn prefetcher(dir: &Path, config: PrefetchConfig) -> AssetPrefetcher {
+        AssetPrefetcher::new(dir, &config).with_timing(Duration::from_millis(20), Duration::from_millis(10))
+    }
+
+    fn served(assets: &[&(PreloadAsset, Vec<u8>)]) -> Fixtures {
+        Fixtures {
+            files: assets.iter().map(|(a, bytes)| (a.sha256.clone(), bytes.clone())).collect(),
+            ..Default::default()
+        }
+    }
+
+    struct FakeGame {
+        running: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Quiesce for FakeGame {
+        async fn busy(&self) -> Option<String> {
+            self.running.load(Ordering::SeqCst).then(|| "The game is running".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skips_cached_assets_and_resumes_cut_downloads() {
+        let dir = temp_dir();
+        let sky = asset("textures/sky.png", &[1; 40_000], PreloadPriority::Required);
+        let boat = asset("models/boat.bin", &[2; 90_000], PreloadPriority::Streamable);
+        let cached = asset("sounds/wave.ogg", &[3; 5_000], PreloadPriority::Streamable);
+        let fixtures = Arc::new(served(&[&sky, &boat, &cached]));
+        fixtures.cut_once.lock().unwrap().insert(boat.0.sha256.clone());
+        let server = asset_server(fixtures.clone()).await.to_string();
+
+        let prefetcher = prefetcher(&dir, PrefetchConfig { max_concurrent: 2, ..Default::default() });
+        prefetcher.cache.put(&cached.0.sha256, &cached.1).await.unwrap();
+        let manifest = AssetPreloadManifest { assets: vec![sky.0.clone(), boat.0.clone(), cached.0.clone(), sky.0.clone()] };
+        let status = prefetcher.prefetch(&server, &manifest).await.unwrap();
+
+        assert_eq!(status.phase, PrefetchPhase::Complete);
+        assert_eq!((status.items_done, status.items_total), (3, 3));
+        assert_eq!(status.bytes_done, status.bytes_total);
+        assert!(status.failed.is_empty());
+        for (asset, bytes) in [&sky, &boat, &cached] {
+            assert_eq!(&std::fs::read(prefetcher.cache.dir().join(&asset.sha256)).unwrap(), bytes);
+        }
+        assert_eq!(std::fs::read_dir(prefetcher.cache.dir().join("partial")).unwrap().count(), 0);
+
+        // The cut download picked up where it stopped
+        let requests = fixtures.requests.lock().unwrap().clone();
+        let boat_requests: Vec<_> = requests.iter().filter(|(hash, _)| *hash == boat.0.sha256).collect();
+        assert_eq!(boat_requests.len(), 2);
+        assert_eq!(boat_requests[1].1.as_deref(), Some("bytes=45000-"));
+        assert_eq!(requests.iter().filter(|(hash, _)| *hash == sky.0.sha256).count(), 1);
+        assert!(!requests.iter().any(|(hash, _)| *hash == cached.0.sha256));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_reports_failures() {
+        let dir = temp_dir();
+        let flaky = asset("textures/flaky.png", b"flaky", PreloadPriority::Required);
+        let missing = asset("textures/missing.png", b"missing", PreloadPriority::Required);
+        let fixtures = Arc::new(served(&[&flaky]));
+        fixtures.fail_first.lock().unwrap().insert(flaky.0.sha256.clone(), 1);
+        let server = asset_server(fixtures.clone()).await.to_string();
+
+        let prefetcher = prefetcher(&dir, PrefetchConfig::default());
+        let manifest = AssetPreloadManifest { assets: vec![flaky.0.clone(), missing.0.clone()] };
+        let status = prefetcher.prefetch(&server, &manifest).await.unwrap();
+
+        assert_eq!(status.items_done, 1);
+        assert!(prefetcher.cache.contains(&flaky.0.sha256));
+        assert_eq!(status.failed.len(), 1);
+        assert_eq!(status.failed[0].path, "textures/missing.png");
+        assert_eq!(status.failed[0].attempts, 3);
+        assert!(status.failed[0].error.contains("404"));
+        let requests = fixtures.requests.lock().unwrap().clone();
+        assert_eq!(requests.iter().filter(|(hash, _)| *hash == missing.0.sha256).count(), 3);
+        assert_eq!(requests.iter().filter(|(hash, _)| *hash == flaky.0.sha256).count(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_the_game_to_stop() {
+        let dir = temp_dir();
+        let sky = asset("textures/sky.png", b"sky", PreloadPriority::Required);
+        let fixtures = Arc::new(served(&[&sky]));
+        let server = asset_server(fixtures.clone()).await.to_string();
+        let game = Arc::new(FakeGame { running: AtomicBool::new(true) });
+        let prefetcher = Arc::new(prefetcher(&dir, PrefetchConfig::default()).with_quiesce(game.clone()));
+
+        let manifest = AssetPreloadManifest { assets: vec![sky.0.clone()] };
+        let task = {
+            let prefetcher = prefetcher.clone();
+            tokio::spawn(async move { prefetcher.prefetch(&server, &manifest).await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = prefetcher.status();
+        assert_eq!(status.phase, PrefetchPhase::Paused);
+        assert_eq!(status.paused_reason.as_deref(), Some("The game is running"));
+        assert!(fixtures.requests.lock().unwrap().is_empty());
+
+        game.running.store(false, Ordering::SeqCst);
+        let status = task.await.unwrap().unwrap();
+        assert_eq!(status.items_done, 1);
+        assert!(matches!(
+            prefetcher.prefetch("unused", &AssetPreloadManifest::default()).await,
+            Ok(PrefetchStatus { items_total: 0, .. })
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_cap() {
+        let dir = temp_dir();
+        let big = asset("models/big.bin", &[7; 64 * 1024], PreloadPriority::Streamable);
+        let server = asset_server(Arc::new(served(&[&big]))).await.to_string();
+        let prefetcher = prefetcher(&dir, PrefetchConfig { bandwidth_limit_kbps: 128, ..Default::default() });
+
+        let started = Instant::now();
+        let status = prefetcher.prefetch(&server, &AssetPreloadManifest { assets: vec![big.0.clone()] }).await.unwrap();
+        assert_eq!(status.items_done, 1);
+        // 64 KiB at 128 KiB/s
+        assert!(started.elapsed() >= Duration::from_millis(450), "{:?}", started.elapsed());
+        assert_eq!(HttpManifestSource::asset_url(&server, &big.0), format!("http://{}{}/{}", server, ASSET_PATH, big.0.sha256));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
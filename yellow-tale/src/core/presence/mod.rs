@@ -0,0 +1,238 @@
+//! Friend presence
+//!
+//! `UserService` announces status changes on a `PresenceHub`. The hub
+//! broadcasts them in-process and through Postgres `NOTIFY`, so launchers
+//! sharing the database hear about each other's users; a listener feeds
+//! changes from other processes back into the hub. A `PresenceFeed` keeps
+//! the changes of one user's friends until the UI collects them.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Postgres channel presence changes are sent on
+const CHANNEL: &str = "user_presence";
+
+/// Changes a feed keeps before dropping the oldest
+const FEED_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresenceChange {
+    pub user_id: Uuid,
+    pub status: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl PresenceChange {
+    pub fn new(user_id: Uuid, status: impl Into<String>) -> Self {
+        Self { user_id, status: status.into(), changed_at: Utc::now() }
+    }
+}
+
+/// What goes over `NOTIFY`, tagged so a hub can skip its own changes
+#[derive(Debug, Serialize, Deserialize)]
+struct Notification {
+    origin: Uuid,
+    change: PresenceChange,
+}
+
+/// Fans status changes out to subscribers
+#[derive(Clone)]
+pub struct PresenceHub {
+    /// Tells this hub's notifications apart from other processes'
+    origin: Uuid,
+    changes: broadcast::Sender<PresenceChange>,
+}
+
+impl Default for PresenceHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PresenceHub {
+    pub fn new() -> Self {
+        Self { origin: Uuid::new_v4(), changes: broadcast::channel(256).0 }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceChange> {
+        self.changes.subscribe()
+    }
+
+    /// Tell subscribers in this process only
+    pub fn publish(&self, change: PresenceChange) {
+        let _ = self.changes.send(change);
+    }
+
+    /// Tell subscribers here and, through the database, in other processes.
+    /// A failed `NOTIFY` is logged; the status itself is already saved.
+    pub async fn announce(&self, pool: &PgPool, change: PresenceChange) {
+        let payload = serde_json::to_string(&Notification { origin: self.origin, change: change.clone() })
+            .unwrap_or_default();
+        self.publish(change);
+        if let Err(e) = sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(CHANNEL)
+            .bind(payload)
+            .execute(pool)
+            .await
+        {
+            warn!("Could not send presence change to other launchers: {}", e);
+        }
+    }
+
+    /// Feed changes announced by other processes into this hub. Reconnects
+    /// whenever the connection drops; changes made meanwhile are missed.
+    pub fn spawn_listener(&self, database_url: String) -> JoinHandle<()> {
+        let hub = self.clone();
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                match hub.listen(&database_url).await {
+                    Ok(()) => backoff = Duration::from_secs(1),
+                    Err(e) => warn!("Presence listener disconnected, retrying in {:?}: {}", backoff, e),
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_secs(60));
+            }
+        })
+    }
+
+    async fn listen(&self, database_url: &str) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect(database_url).await?;
+        listener.listen(CHANNEL).await?;
+        info!("Listening for presence changes");
+        loop {
+            let notification = listener.recv().await?;
+            self.receive(notification.payload());
+        }
+    }
+
+    fn receive(&self, payload: &str) {
+        match serde_json::from_str::<Notification>(payload) {
+            Ok(notification) if notification.origin != self.origin => self.publish(notification.change),
+            Ok(_) => {}
+            Err(e) => debug!("Ignoring malformed presence notification: {}", e),
+        }
+    }
+}
+
+/// Status changes of one user's friends, kept until collected
+pub struct PresenceFeed {
+    changes: Arc<Mutex<VecDeque<PresenceChange>>>,
+    task: JoinHandle<()>,
+}
+
+impl PresenceFeed {
+    /// Start keeping changes from `hub` made by anyone in `friends`
+    pub fn spawn(hub: &PresenceHub, friends: HashSet<Uuid>) -> Self {
+        let changes = Arc::new(Mutex::new(VecDeque::new()));
+        let mut incoming = hub.subscribe();
+        let buffer = changes.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                match incoming.recv().await {
+                    Ok(change) if friends.contains(&change.user_id) => {
+                        let mut buffer = buffer.lock().unwrap();
+                        if buffer.len() == FEED_CAPACITY {
+                            buffer.pop_front();
+                        }
+                        buffer.push_back(change);
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Presence feed missed {} changes", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Self { changes, task }
+    }
+
+    /// Changes since the last call, oldest first
+    pub fn drain(&self) -> Vec<PresenceChange> {
+        self.changes.lock().unwrap().drain(..).collect()
+    }
+}
+
+impl Drop for PresenceFeed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_friend_logging_in_reaches_the_feed() {
+        let hub = PresenceHub::new();
+        let (anna, bo, stranger) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let anna_feed = PresenceFeed::spawn(&hub, HashSet::from([bo]));
+        let bo_feed = PresenceFeed::spawn(&hub, HashSet::from([anna]));
+
+        // What UserService::login publishes for Bo
+        hub.publish(PresenceChange::new(bo, "online"));
+        hub.publish(PresenceChange::new(stranger, "online"));
+        settle().await;
+
+        let changes = anna_feed.drain();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].user_id, changes[0].status.as_str()), (bo, "online"));
+        assert!(anna_feed.drain().is_empty());
+        assert!(bo_feed.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_notifications_from_other_launchers_are_republished() {
+        let hub = PresenceHub::new();
+        let other = PresenceHub::new();
+        let bo = Uuid::new_v4();
+        let feed = PresenceFeed::spawn(&hub, HashSet::from([bo]));
+
+        let change = PresenceChange::new(bo, "away");
+        let from = |origin| serde_json::to_string(&Notification { origin, change: change.clone() }).unwrap();
+        hub.receive(&from(other.origin));
+        // Already published locally when it was announced
+        hub.receive(&from(hub.origin));
+        hub.receive("not json");
+        settle().await;
+
+        assert_eq!(feed.drain(), vec![change]);
+    }
+
+    #[tokio::test]
+    async fn test_feed_keeps_only_the_latest_changes() {
+        let hub = PresenceHub::new();
+        let bo = Uuid::new_v4();
+        let feed = PresenceFeed::spawn(&hub, HashSet::from([bo]));
+
+        for i in 0..FEED_CAPACITY + 5 {
+            hub.publish(PresenceChange::new(bo, format!("status {}", i)));
+            if i % 32 == 0 {
+                settle().await;
+            }
+        }
+        settle().await;
+
+        let changes = feed.drain();
+        assert_eq!(changes.len(), FEED_CAPACITY);
+        assert_eq!(changes.last().unwrap().status, format!("status {}", FEED_CAPACITY + 4));
+    }
+}
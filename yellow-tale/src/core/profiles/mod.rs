@@ -0,0 +1,227 @@
+//! Launcher profiles
+//!
+//! Each profile is a JSON file named after its id in the profiles
+//! directory. Every change is written through before it's applied in
+//! memory, so what `list` shows is what a restart loads. Saves replace the
+//! file atomically and keep the previous one as `<id>.json.bak`, which is
+//! loaded instead if the file is damaged.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+use yellow_tale_core::filesystem::backup_path;
+
+use crate::core::util::{read_with_backup, write_atomic};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// Missing in profiles saved before it was tracked
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Free-form launch settings, e.g. `performance.ram_allocation_mb`
+    #[serde(default = "empty_settings")]
+    pub settings: serde_json::Value,
+}
+
+fn empty_settings() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+pub struct ProfileManager {
+    dir: PathBuf,
+    profiles: HashMap<Uuid, Profile>,
+}
+
+impl ProfileManager {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, profiles: HashMap::new() }
+    }
+
+    /// Load every `*.json` file in the profiles directory, replacing what's
+    /// in memory. A file that doesn't parse is skipped with a warning rather
+    /// than hiding the others.
+    pub async fn load_all(&mut self) -> anyhow::Result<()> {
+        self.profiles.clear();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match read_with_backup(&path, |content| Ok(serde_json::from_slice::<Profile>(content)?)).await {
+                Ok(profile) => {
+                    self.profiles.insert(profile.id, profile);
+                }
+                Err(e) => warn!("Skipping unreadable profile {:?}: {}", path, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Every profile, oldest first
+    pub fn list(&self) -> Vec<&Profile> {
+        let mut profiles: Vec<&Profile> = self.profiles.values().collect();
+        profiles.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        profiles
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&Profile> {
+        self.profiles.get(id)
+    }
+
+    pub async fn create(&mut self, name: &str) -> anyhow::Result<Profile> {
+        let profile = Profile {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            updated_at: None,
+            settings: empty_settings(),
+        };
+        self.save(&profile).await?;
+        self.profiles.insert(profile.id, profile.clone());
+        Ok(profile)
+    }
+
+    /// Rename a profile and/or replace its settings
+    pub async fn update(&mut self, id: &Uuid, name: Option<&str>, settings: Option<serde_json::Value>) -> anyhow::Result<Profile> {
+        let mut profile = self.get(id).cloned().ok_or_else(|| anyhow!("Profile not found"))?;
+        if let Some(name) = name {
+            profile.name = name.to_string();
+        }
+        if let Some(settings) = settings {
+            profile.settings = settings;
+        }
+        profile.updated_at = Some(Utc::now());
+        self.save(&profile).await?;
+        self.profiles.insert(profile.id, profile.clone());
+        Ok(profile)
+    }
+
+    pub async fn delete(&mut self, id: &Uuid) -> anyhow::Result<()> {
+        if !self.profiles.contains_key(id) {
+            return Err(anyhow!("Profile not found"));
+        }
+        let path = self.path(id);
+        for file in [backup_path(&path), path] {
+            match tokio::fs::remove_file(&file).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.profiles.remove(id);
+        Ok(())
+    }
+
+    fn path(&self, id: &Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    async fn save(&self, profile: &Profile) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        write_atomic(&self.path(&profile.id), serde_json::to_vec_pretty(profile)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-profiles-{}", Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_changes_survive_a_reload() {
+        let dir = temp_dir();
+        let mut profiles = ProfileManager::new(dir.clone());
+        let vanilla = profiles.create("Vanilla").await.unwrap();
+        let modded = profiles.create("Modded").await.unwrap();
+        let doomed = profiles.create("Doomed").await.unwrap();
+
+        let settings = serde_json::json!({ "performance": { "ram_allocation_mb": 4096 } });
+        let updated = profiles.update(&modded.id, Some("Survival"), Some(settings.clone())).await.unwrap();
+        assert_eq!(updated.name, "Survival");
+        assert!(updated.updated_at.is_some());
+        let renamed = profiles.update(&vanilla.id, Some("Creative"), None).await.unwrap();
+        assert_eq!(renamed.settings, serde_json::json!({}));
+        profiles.delete(&doomed.id).await.unwrap();
+        assert!(!dir.join(format!("{}.json", doomed.id)).exists());
+
+        let mut reloaded = ProfileManager::new(dir.clone());
+        reloaded.load_all().await.unwrap();
+        let names: Vec<&str> = reloaded.list().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Creative", "Survival"]);
+        assert_eq!(reloaded.get(&modded.id).unwrap().settings, settings);
+        assert!(reloaded.get(&doomed.id).is_none());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_profiles_are_errors() {
+        let dir = temp_dir();
+        let mut profiles = ProfileManager::new(dir.clone());
+        let missing = Uuid::new_v4();
+        let err = profiles.update(&missing, Some("Nope"), None).await.unwrap_err();
+        assert_eq!(err.to_string(), "Profile not found");
+        assert!(profiles.delete(&missing).await.is_err());
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_load_skips_other_files() {
+        let dir = temp_dir();
+        let mut profiles = ProfileManager::new(dir.clone());
+        let kept = profiles.create("Kept").await.unwrap();
+        tokio::fs::create_dir_all(dir.join("cache")).await.unwrap();
+        tokio::fs::write(dir.join("notes.txt"), b"not a profile").await.unwrap();
+        tokio::fs::write(dir.join("broken.json"), b"{").await.unwrap();
+        // Written before profiles tracked updates or had settings
+        let legacy = Uuid::new_v4();
+        let old = serde_json::json!({ "id": legacy, "name": "Legacy", "created_at": "2024-01-01T00:00:00Z" });
+        tokio::fs::write(dir.join(format!("{}.json", legacy)), old.to_string()).await.unwrap();
+
+        profiles.load_all().await.unwrap();
+        let names: Vec<&str> = profiles.list().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Legacy", "Kept"]);
+        assert_eq!(profiles.get(&legacy).unwrap().settings, serde_json::json!({}));
+        assert!(profiles.get(&kept.id).is_some());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_a_damaged_profile_falls_back_to_its_backup() {
+        let dir = temp_dir();
+        let mut profiles = ProfileManager::new(dir.clone());
+        let profile = profiles.create("Vanilla").await.unwrap();
+        profiles.update(&profile.id, Some("Survival"), None).await.unwrap();
+        let path = dir.join(format!("{}.json", profile.id));
+        assert!(backup_path(&path).exists());
+
+        // Torn by something other than the launcher
+        tokio::fs::write(&path, b"{\"id\": ").await.unwrap();
+        let mut reloaded = ProfileManager::new(dir.clone());
+        reloaded.load_all().await.unwrap();
+        assert_eq!(reloaded.get(&profile.id).unwrap().name, "Vanilla");
+
+        reloaded.delete(&profile.id).await.unwrap();
+        assert!(!path.exists() && !backup_path(&path).exists());
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
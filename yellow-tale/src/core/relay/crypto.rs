@@ -0,0 +1,200 @@
+//! End-to-end sealing of relay payloads
+//!
+//! The session host picks a random session key and seals it to each
+//! encrypting peer's X25519 public key: a fresh ephemeral key agrees a
+//! secret with the peer's key, SHA-256 turns that secret into a one-off key,
+//! and XChaCha20-Poly1305 encrypts the session key under it. `Data` payloads
+//! are then encrypted under the session key with a random nonce per message,
+//! so the relay only ever forwards ciphertext.
+//!
+//! Peers are not authenticated beyond what the relay reports, so this keeps
+//! traffic from a relay that reads it, not from one that hands out its own
+//! keys.
+
+use std::fmt;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use super::RelayError;
+
+const NONCE_LEN: usize = 24;
+const SEAL_CONTEXT: &[u8] = b"yellow-tale relay session key v1";
+
+/// A peer's X25519 key pair, generated once per client
+pub struct PeerKeys {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl PeerKeys {
+    pub fn generate() -> Self {
+        let secret = StaticSecret::random_from_rng(rand::rngs::OsRng);
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// Hex encoded, as sent in `Join` and `PeerInfo`
+    pub fn public_key(&self) -> String {
+        hex::encode(self.public.as_bytes())
+    }
+
+    /// Recover a session key the host sealed to this peer
+    pub fn open(&self, key_id: u32, ephemeral_key: &str, sealed_key: &[u8]) -> Result<SessionKey, RelayError> {
+        let ephemeral = parse_public_key(ephemeral_key)?;
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        if !shared.was_contributory() {
+            return Err(RelayError::Encryption("Weak ephemeral key".to_string()));
+        }
+        let kek = seal_key(shared.as_bytes(), &ephemeral, &self.public);
+        let key = open(&kek, sealed_key, &key_id.to_be_bytes())
+            .map_err(|_| RelayError::Encryption("Session key could not be opened".to_string()))?;
+        if key.len() != 32 {
+            return Err(RelayError::Encryption("Session key has the wrong length".to_string()));
+        }
+        Ok(SessionKey { id: key_id, key: *Key::from_slice(&key) })
+    }
+}
+
+/// A session key as sealed to one peer
+#[derive(Debug, Clone)]
+pub struct SealedKey {
+    /// Hex encoded ephemeral X25519 public key
+    pub ephemeral_key: String,
+    /// Nonce followed by the encrypted session key
+    pub sealed_key: Vec<u8>,
+}
+
+/// The key `Data` payloads are encrypted under. `id` changes on every
+/// rotation so peers can tell which key a payload needs.
+#[derive(Clone)]
+pub struct SessionKey {
+    id: u32,
+    key: Key,
+}
+
+impl fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+impl SessionKey {
+    pub fn generate(id: u32) -> Self {
+        let mut key = Key::default();
+        rand::rngs::OsRng.fill_bytes(&mut key);
+        Self { id, key }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Seal this key so only the holder of `public_key` can open it
+    pub fn seal_to(&self, public_key: &str) -> Result<SealedKey, RelayError> {
+        let recipient = parse_public_key(public_key)?;
+        let ephemeral = EphemeralSecret::random_from_rng(rand::rngs::OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&recipient);
+        if !shared.was_contributory() {
+            return Err(RelayError::Encryption("Weak peer public key".to_string()));
+        }
+        let kek = seal_key(shared.as_bytes(), &ephemeral_public, &recipient);
+        Ok(SealedKey {
+            ephemeral_key: hex::encode(ephemeral_public.as_bytes()),
+            sealed_key: seal(&kek, &self.key, &self.id.to_be_bytes())?,
+        })
+    }
+
+    /// Encrypt a payload sent by `from`; the sender is bound to the
+    /// ciphertext so the relay can't reattribute it
+    pub fn encrypt(&self, from: Uuid, payload: &[u8]) -> Result<Vec<u8>, RelayError> {
+        seal(&self.key, payload, &self.associated_data(from))
+    }
+
+    pub fn decrypt(&self, from: Uuid, payload: &[u8]) -> Result<Vec<u8>, RelayError> {
+        open(&self.key, payload, &self.associated_data(from))
+            .map_err(|_| RelayError::Encryption("Payload could not be decrypted".to_string()))
+    }
+
+    fn associated_data(&self, from: Uuid) -> Vec<u8> {
+        let mut aad = from.as_bytes().to_vec();
+        aad.extend_from_slice(&self.id.to_be_bytes());
+        aad
+    }
+}
+
+fn parse_public_key(hex_key: &str) -> Result<PublicKey, RelayError> {
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| RelayError::Encryption("Invalid public key".to_string()))?;
+    Ok(PublicKey::from(bytes))
+}
+
+fn seal_key(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(SEAL_CONTEXT);
+    hasher.update(shared);
+    hasher.update(ephemeral.as_bytes());
+    hasher.update(recipient.as_bytes());
+    hasher.finalize()
+}
+
+/// Random nonce followed by the ciphertext
+fn seal(key: &Key, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, RelayError> {
+    let mut nonce = XNonce::default();
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    let ciphertext = XChaCha20Poly1305::new(key)
+        .encrypt(&nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| RelayError::Encryption("Payload could not be encrypted".to_string()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(key: &Key, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, chacha20poly1305::aead::Error> {
+    if sealed.len() < NONCE_LEN {
+        return Err(chacha20poly1305::aead::Error);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key).decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_key_opens_only_for_its_recipient() {
+        let alice = PeerKeys::generate();
+        let mallory = PeerKeys::generate();
+        let key = SessionKey::generate(3);
+
+        let sealed = key.seal_to(&alice.public_key()).unwrap();
+        let opened = alice.open(3, &sealed.ephemeral_key, &sealed.sealed_key).unwrap();
+        assert_eq!(opened.key, key.key);
+        assert!(mallory.open(3, &sealed.ephemeral_key, &sealed.sealed_key).is_err());
+        // The key id is bound to the seal
+        assert!(alice.open(4, &sealed.ephemeral_key, &sealed.sealed_key).is_err());
+    }
+
+    #[test]
+    fn test_payload_is_bound_to_sender_and_key() {
+        let key = SessionKey::generate(1);
+        let from = Uuid::new_v4();
+
+        let first = key.encrypt(from, b"hello").unwrap();
+        let second = key.encrypt(from, b"hello").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(key.decrypt(from, &first).unwrap(), b"hello");
+        assert!(key.decrypt(Uuid::new_v4(), &first).is_err());
+        assert!(SessionKey::generate(1).decrypt(from, &first).is_err());
+        assert!(key.decrypt(from, &first[..10]).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+//! Flood protection for relay connections
+//!
+//! Every text or binary frame a peer sends costs one token from a message
+//! bucket and its length from a byte bucket, both refilling at a steady rate
+//! up to a burst. Frames over the size limit, or that find a bucket empty,
+//! are dropped instead of forwarded and count as a violation; enough
+//! violations over a connection's life and the relay hangs up on it.
+//!
+//! Wrong session passwords are also counted per address, across
+//! connections, so reconnecting doesn't buy more guesses.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Limits a relay applies to each connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    /// Largest text or binary frame forwarded, in bytes
+    pub max_frame_bytes: usize,
+    /// Frames per second a peer may send once its burst is spent
+    pub messages_per_sec: u32,
+    /// Frames a peer may send at once
+    pub message_burst: u32,
+    /// Bytes per second a peer may send once its burst is spent
+    pub bytes_per_sec: u64,
+    /// Bytes a peer may send at once; at least `max_frame_bytes`
+    pub byte_burst: u64,
+    /// Dropped frames before the peer is disconnected
+    pub max_violations: u32,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            max_frame_bytes: 256 * 1024,
+            messages_per_sec: 100,
+            message_burst: 200,
+            bytes_per_sec: 1024 * 1024,
+            byte_burst: 2 * 1024 * 1024,
+            max_violations: 20,
+        }
+    }
+}
+
+/// Why a frame was dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    TooLarge,
+    RateLimited,
+}
+
+impl Violation {
+    /// What the offending peer is told
+    pub fn message(self) -> &'static str {
+        match self {
+            Violation::TooLarge => "Message too large",
+            Violation::RateLimited => "Rate limit exceeded",
+        }
+    }
+}
+
+/// Tokens refilling at a fixed rate up to a capacity
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket
+    pub fn new(capacity: u64, refill_per_sec: u64, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_sec: refill_per_sec as f64,
+            refilled_at: now,
+        }
+    }
+
+    /// Take `amount` tokens if there are that many by `now`
+    pub fn try_take(&mut self, amount: u64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.refilled_at = now;
+
+        if self.tokens < amount as f64 {
+            return false;
+        }
+        self.tokens -= amount as f64;
+        true
+    }
+}
+
+/// One connection's buckets and violation count
+pub struct PeerLimiter {
+    max_frame_bytes: usize,
+    max_violations: u32,
+    messages: TokenBucket,
+    bytes: TokenBucket,
+    violations: u32,
+}
+
+impl PeerLimiter {
+    pub fn new(config: &RelayConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            max_frame_bytes: config.max_frame_bytes,
+            max_violations: config.max_violations.max(1),
+            messages: TokenBucket::new(config.message_burst as u64, config.messages_per_sec as u64, now),
+            bytes: TokenBucket::new(config.byte_burst.max(config.max_frame_bytes as u64), config.bytes_per_sec, now),
+            violations: 0,
+        }
+    }
+
+    /// Check a frame of `len` bytes arriving now, counting it against the
+    /// peer if it has to be dropped
+    pub fn check(&mut self, len: usize) -> Result<(), Violation> {
+        self.check_at(len, Instant::now())
+    }
+
+    fn check_at(&mut self, len: usize, now: Instant) -> Result<(), Violation> {
+        let verdict = if len > self.max_frame_bytes {
+            Err(Violation::TooLarge)
+        } else if !self.messages.try_take(1, now) || !self.bytes.try_take(len as u64, now) {
+            Err(Violation::RateLimited)
+        } else {
+            Ok(())
+        };
+        if verdict.is_err() {
+            self.violations += 1;
+        }
+        verdict
+    }
+
+    /// The peer has been dropping frames for too long to keep
+    pub fn exhausted(&self) -> bool {
+        self.violations >= self.max_violations
+    }
+}
+
+/// Wrong session passwords per address. An address that reaches the limit
+/// is refused until `lockout` has passed since its last wrong guess.
+pub struct PasswordFailures {
+    max_failures: u32,
+    lockout: Duration,
+    by_addr: Mutex<HashMap<IpAddr, (u32, Instant)>>,
+}
+
+impl PasswordFailures {
+    pub fn new(max_failures: u32, lockout: Duration) -> Self {
+        Self {
+            max_failures: max_failures.max(1),
+            lockout,
+            by_addr: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `addr` has guessed wrong too often to be let try again
+    pub fn locked_out(&self, addr: IpAddr) -> bool {
+        self.locked_out_at(addr, Instant::now())
+    }
+
+    /// Count a wrong guess from `addr`, returning whether it's now locked out
+    pub fn record(&self, addr: IpAddr) -> bool {
+        self.record_at(addr, Instant::now())
+    }
+
+    /// Forget `addr`'s wrong guesses once it gets a password right
+    pub fn clear(&self, addr: IpAddr) {
+        self.by_addr.lock().unwrap().remove(&addr);
+    }
+
+    fn locked_out_at(&self, addr: IpAddr, now: Instant) -> bool {
+        let mut by_addr = self.by_addr.lock().unwrap();
+        by_addr.retain(|_, (_, last)| now.saturating_duration_since(*last) < self.lockout);
+        by_addr.get(&addr).is_some_and(|(failures, _)| *failures >= self.max_failures)
+    }
+
+    fn record_at(&self, addr: IpAddr, now: Instant) -> bool {
+        let mut by_addr = self.by_addr.lock().unwrap();
+        let (failures, last) = by_addr.entry(addr).or_insert((0, now));
+        if now.saturating_duration_since(*last) >= self.lockout {
+            *failures = 0;
+        }
+        *failures += 1;
+        *last = now;
+        *failures >= self.max_failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_a_burst_then_refills_at_its_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3, 2, start);
+
+        assert!((0..3).all(|_| bucket.try_take(1, start)));
+        assert!(!bucket.try_take(1, start));
+
+        // Two tokens a second: one after half a second, not two
+        let later = start + Duration::from_millis(500);
+        assert!(bucket.try_take(1, later));
+        assert!(!bucket.try_take(1, later));
+
+        // Refilling stops at the capacity
+        let much_later = later + Duration::from_secs(60);
+        assert!(bucket.try_take(3, much_later));
+        assert!(!bucket.try_take(1, much_later));
+    }
+
+    #[test]
+    fn test_limiter_drops_oversized_and_excess_frames() {
+        let config = RelayConfig {
+            max_frame_bytes: 100,
+            messages_per_sec: 1,
+            message_burst: 2,
+            bytes_per_sec: 100,
+            byte_burst: 150,
+            max_violations: 3,
+        };
+        let mut limiter = PeerLimiter::new(&config);
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at(101, start), Err(Violation::TooLarge));
+        assert_eq!(limiter.check_at(100, start), Ok(()));
+        // A message token is left, but not enough bytes
+        assert_eq!(limiter.check_at(100, start), Err(Violation::RateLimited));
+        assert!(!limiter.exhausted());
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(limiter.check_at(10, later), Ok(()));
+        assert_eq!(limiter.check_at(10, later), Err(Violation::RateLimited));
+        assert!(limiter.exhausted());
+    }
+
+    #[test]
+    fn test_password_failures_lock_out_an_address_for_a_while() {
+        let failures = PasswordFailures::new(3, Duration::from_secs(60));
+        let guesser: IpAddr = "203.0.113.7".parse().unwrap();
+        let bystander: IpAddr = "203.0.113.8".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(!failures.record_at(guesser, start));
+        assert!(!failures.record_at(guesser, start));
+        assert!(!failures.locked_out_at(guesser, start));
+        assert!(failures.record_at(guesser, start + Duration::from_secs(30)));
+        assert!(failures.locked_out_at(guesser, start + Duration::from_secs(60)));
+        assert!(!failures.locked_out_at(bystander, start));
+
+        // The lockout runs from the last wrong guess
+        assert!(!failures.locked_out_at(guesser, start + Duration::from_secs(90)));
+        assert!(!failures.record_at(guesser, start + Duration::from_secs(91)));
+
+        failures.record_at(bystander, start);
+        failures.clear(bystander);
+        assert!(!failures.record_at(bystander, start));
+        assert!(!failures.record_at(bystander, start));
+    }
+}
@@ -0,0 +1,166 @@
+//! Traffic counters for the relay
+//!
+//! Counters are atomics so the fan-out paths only ever hold the sessions'
+//! read lock. The per-second rates come from a ring of one-second slots
+//! covering the last `WINDOW_SECS`; a slot is cleared by the first frame to
+//! land in it after it went stale, so a frame racing that clear can go
+//! uncounted in the rate. The totals are exact.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// Seconds the moving averages cover
+pub const WINDOW_SECS: u64 = 10;
+
+/// Traffic relayed since the counters started or were last reset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrafficStats {
+    /// Frames forwarded, each counted once however many peers got it
+    pub messages_relayed: u64,
+    /// Bytes delivered to peers, counted per recipient
+    pub bytes_relayed: u64,
+    /// Frames sent to the whole session
+    pub broadcast_messages: u64,
+    /// Frames sent to one peer
+    pub directed_messages: u64,
+    /// Frames forwarded per second over the last `WINDOW_SECS`
+    pub messages_per_sec: f64,
+    /// Bytes delivered per second over the last `WINDOW_SECS`
+    pub bytes_per_sec: f64,
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    second: AtomicU64,
+    messages: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Counters for one session or the whole relay
+#[derive(Debug)]
+pub struct RelayMetrics {
+    started: Instant,
+    messages: AtomicU64,
+    bytes: AtomicU64,
+    broadcast: AtomicU64,
+    directed: AtomicU64,
+    window: [Slot; WINDOW_SECS as usize],
+}
+
+impl RelayMetrics {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            messages: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            broadcast: AtomicU64::new(0),
+            directed: AtomicU64::new(0),
+            window: Default::default(),
+        }
+    }
+
+    /// Count a frame of `len` bytes delivered to `recipients` peers
+    pub fn record(&self, len: usize, recipients: usize, broadcast: bool) {
+        self.record_at(len, recipients, broadcast, Instant::now());
+    }
+
+    fn record_at(&self, len: usize, recipients: usize, broadcast: bool, now: Instant) {
+        let bytes = (len * recipients) as u64;
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        let kind = if broadcast { &self.broadcast } else { &self.directed };
+        kind.fetch_add(1, Ordering::Relaxed);
+
+        // Seconds are numbered from 1 so a fresh slot's 0 is never current
+        let second = now.saturating_duration_since(self.started).as_secs() + 1;
+        let slot = &self.window[(second % WINDOW_SECS) as usize];
+        if slot.second.swap(second, Ordering::Relaxed) != second {
+            slot.messages.store(0, Ordering::Relaxed);
+            slot.bytes.store(0, Ordering::Relaxed);
+        }
+        slot.messages.fetch_add(1, Ordering::Relaxed);
+        slot.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TrafficStats {
+        self.snapshot_at(Instant::now())
+    }
+
+    fn snapshot_at(&self, now: Instant) -> TrafficStats {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        let current = elapsed as u64 + 1;
+        let (mut messages, mut bytes) = (0, 0);
+        for slot in &self.window {
+            let second = slot.second.load(Ordering::Relaxed);
+            if second != 0 && second + WINDOW_SECS > current {
+                messages += slot.messages.load(Ordering::Relaxed);
+                bytes += slot.bytes.load(Ordering::Relaxed);
+            }
+        }
+        // A relay up for less than the window averages over its uptime
+        let span = elapsed.clamp(1.0, WINDOW_SECS as f64);
+
+        TrafficStats {
+            messages_relayed: self.messages.load(Ordering::Relaxed),
+            bytes_relayed: self.bytes.load(Ordering::Relaxed),
+            broadcast_messages: self.broadcast.load(Ordering::Relaxed),
+            directed_messages: self.directed.load(Ordering::Relaxed),
+            messages_per_sec: messages as f64 / span,
+            bytes_per_sec: bytes as f64 / span,
+        }
+    }
+
+    /// Zero every counter
+    pub fn reset(&self) {
+        for counter in [&self.messages, &self.bytes, &self.broadcast, &self.directed] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        for slot in &self.window {
+            slot.second.store(0, Ordering::Relaxed);
+            slot.messages.store(0, Ordering::Relaxed);
+            slot.bytes.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for RelayMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rates_cover_only_the_window() {
+        let metrics = RelayMetrics::new();
+        let start = metrics.started;
+
+        for second in 0..20 {
+            let at = start + Duration::from_secs(second) + Duration::from_millis(500);
+            for _ in 0..3 {
+                metrics.record_at(100, 2, true, at);
+            }
+        }
+
+        let stats = metrics.snapshot_at(start + Duration::from_millis(19_900));
+        assert_eq!(stats.messages_relayed, 60);
+        assert_eq!(stats.bytes_relayed, 60 * 200);
+        // The last ten seconds, including the current one
+        assert_eq!(stats.messages_per_sec, 3.0);
+        assert_eq!(stats.bytes_per_sec, 600.0);
+
+        // Nothing recent, nothing per second
+        let idle = metrics.snapshot_at(start + Duration::from_secs(60));
+        assert_eq!(idle.messages_relayed, 60);
+        assert_eq!(idle.messages_per_sec, 0.0);
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot_at(start + Duration::from_millis(19_900)), TrafficStats::default());
+    }
+}
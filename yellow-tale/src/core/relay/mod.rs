@@ -1,16 +1,68 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
 use chrono::{DateTime, Utc};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock};
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{accept_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use tracing::{info, warn, error};
 use uuid::Uuid;
 
+use crate::core::health::{CheckResult, HealthCheck};
+use crate::core::sessions::{Participant, Session, SessionBroker, SessionError};
+
+pub mod crypto;
+pub mod limits;
+pub mod metrics;
+
+use crypto::{PeerKeys, SessionKey};
+use limits::{PasswordFailures, PeerLimiter};
+use metrics::RelayMetrics;
+
+pub use limits::RelayConfig;
+pub use metrics::TrafficStats;
+
+/// Peers allowed in a session whose host didn't set a limit
+pub const DEFAULT_MAX_PEERS: usize = 8;
+
+/// How often the relay pings each peer to measure latency
+pub const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Unanswered pings in a row before a peer is dropped
+pub const MAX_MISSED_PINGS: u32 = 3;
+
+/// How long a rejected peer gets to acknowledge the close
+const CLOSE_GRACE: Duration = Duration::from_secs(2);
+
+/// Wrong session passwords a connection may send before it's closed
+pub const MAX_PASSWORD_ATTEMPTS: u32 = 2;
+
+/// What a join with a wrong or missing session password is told
+pub const INVALID_PASSWORD: &str = "invalid password";
+
+/// Wrong session passwords one address may send, over all its connections,
+/// before its joins to password-protected sessions are refused
+pub const MAX_PASSWORD_FAILURES_PER_ADDR: u32 = 10;
+
+/// How long a refused address waits, counted from its last wrong password
+pub const PASSWORD_LOCKOUT: Duration = Duration::from_secs(10 * 60);
+
+/// What a join from a refused address is told
+pub const TOO_MANY_PASSWORD_ATTEMPTS: &str = "too many wrong passwords, try again later";
+
+/// Why a peer that kept breaking the relay's limits was disconnected
+pub const FLOODING: &str = "Disconnected for flooding the relay";
+
 #[derive(Error, Debug)]
 pub enum RelayError {
     #[error("Relay server not running")]
@@ -34,6 +86,12 @@ pub enum RelayError {
     #[error("Invalid message")]
     InvalidMessage,
     
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    
+    #[error("Password hashing failed: {0}")]
+    PasswordHash(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -45,6 +103,27 @@ pub enum RelayMessage {
         session_id: String,
         user_id: Uuid,
         username: String,
+        /// Account session token, required by the central server's relay
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        /// Hex X25519 public key of a peer that wants end-to-end encryption
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
+        /// Session size limit; only honored from the host
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_peers: Option<usize>,
+        /// Argon2 hash of the passphrase later joins must give; only
+        /// honored from the host
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password_hash: Option<String>,
+        /// Passphrase of a password-protected session
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+    },
+    /// Change the session's size limit. Host only; peers already connected
+    /// stay if it drops below their count.
+    ConfigureSession {
+        max_peers: usize,
     },
     Leave {
         session_id: String,
@@ -54,9 +133,15 @@ pub enum RelayMessage {
         from: Uuid,
         to: Option<Uuid>,
         payload: Vec<u8>,
+        /// Session key the payload is encrypted under; plaintext without one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_id: Option<u32>,
     },
     PeerList {
         peers: Vec<PeerInfo>,
+        /// The session host, which may not be connected yet
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        host: Option<Uuid>,
     },
     PeerJoined {
         peer: PeerInfo,
@@ -64,8 +149,15 @@ pub enum RelayMessage {
     PeerLeft {
         user_id: Uuid,
     },
-    Ping,
-    Pong,
+    /// Answered with a `Pong` carrying the same nonce
+    Ping {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nonce: Option<u64>,
+    },
+    Pong {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nonce: Option<u64>,
+    },
     Error {
         message: String,
     },
@@ -75,9 +167,30 @@ pub enum RelayMessage {
     HostMigration {
         new_host: Uuid,
     },
+    /// Hand hosting to another peer. Host only; answered with a
+    /// `HostMigration` to everyone.
+    TransferHost {
+        new_host: Uuid,
+    },
     SessionClosed {
         reason: String,
     },
+    /// Never sent by a relay: `RelayClient` lost its connection and is
+    /// trying to get it back
+    Disconnected {
+        reason: String,
+    },
+    /// Never sent by a relay: `RelayClient` is connected again and has
+    /// rejoined the session
+    Reconnected,
+    /// A session key sealed by the host to one peer's public key
+    SessionKey {
+        from: Uuid,
+        to: Uuid,
+        key_id: u32,
+        ephemeral_key: String,
+        sealed_key: Vec<u8>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,17 +200,42 @@ pub struct PeerInfo {
     pub is_host: bool,
     pub joined_at: DateTime<Utc>,
     pub latency_ms: Option<u32>,
+    /// Whether the peer encrypts `Data` payloads end to end
+    #[serde(default)]
+    pub encryption: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 struct ConnectedPeer {
     user_id: Uuid,
     username: String,
+    public_key: Option<String>,
     #[allow(dead_code)]
     session_id: String,
     sender: mpsc::UnboundedSender<Message>,
     joined_at: DateTime<Utc>,
     is_host: bool,
+    /// Round trip of the last answered ping
+    latency_ms: Option<u32>,
+    /// The ping awaiting a pong, and when it went out
+    pending_ping: Option<(u64, Instant)>,
+    missed_pings: u32,
+}
+
+impl ConnectedPeer {
+    fn info(&self) -> PeerInfo {
+        PeerInfo {
+            user_id: self.user_id,
+            username: self.username.clone(),
+            is_host: self.is_host,
+            joined_at: self.joined_at,
+            latency_ms: self.latency_ms,
+            encryption: self.public_key.is_some(),
+            public_key: self.public_key.clone(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -107,25 +245,133 @@ struct RelaySession {
     peers: HashMap<Uuid, ConnectedPeer>,
     max_peers: usize,
     created_at: DateTime<Utc>,
+    /// Argon2 hash of the passphrase joins must give, set by the host
+    password_hash: Option<String>,
+    traffic: RelayMetrics,
+}
+
+impl RelaySession {
+    fn info(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id.clone(),
+            host_id: self.host_id,
+            peer_count: self.peers.len(),
+            max_peers: self.max_peers,
+            password_required: self.password_hash.is_some(),
+            created_at: self.created_at,
+            peers: self.peers.values().map(ConnectedPeer::info).collect(),
+            traffic: self.traffic.snapshot(),
+        }
+    }
+    
+    /// Send `message` to every peer but `from`, or only to `to`, counting
+    /// what was delivered in the session's and the relay's traffic
+    fn relay(&self, message: Message, from: Uuid, to: Option<Uuid>, totals: &RelayMetrics) {
+        let len = message.len();
+        let recipients = match to {
+            Some(target) => usize::from(self.peers.get(&target).is_some_and(|peer| peer.sender.send(message).is_ok())),
+            None => self.peers.iter()
+                .filter(|(peer_id, _)| **peer_id != from)
+                .filter(|(_, peer)| peer.sender.send(message.clone()).is_ok())
+                .count(),
+        };
+        if recipients > 0 {
+            self.traffic.record(len, recipients, to.is_none());
+            totals.record(len, recipients, to.is_none());
+        }
+    }
+}
+
+/// The account a `Join` token belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayIdentity {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+/// Checks the account token sent with `Join`, so the relay doesn't need a
+/// database of its own
+#[async_trait::async_trait]
+pub trait JoinValidator: Send + Sync {
+    /// The account behind `token`, or `None` if it isn't a live session
+    async fn validate(&self, token: &str) -> Option<RelayIdentity>;
 }
 
 pub struct RelayServer {
     sessions: Arc<RwLock<HashMap<String, RelaySession>>>,
+    /// Limits set before the session's first peer connects, by session id
+    limits: Arc<RwLock<HashMap<String, usize>>>,
+    /// Orchestrator sessions open for joining by invite code, by session id
+    listings: Arc<RwLock<HashMap<String, Session>>>,
     peers_by_id: Arc<RwLock<HashMap<Uuid, String>>>,
     shutdown_tx: Option<broadcast::Sender<()>>,
     bind_addr: Option<SocketAddr>,
+    listener: Option<JoinHandle<()>>,
+    /// When set, joins need a token for the user they claim to be
+    auth: Option<Arc<dyn JoinValidator>>,
+    ping_interval: Duration,
+    /// Size and rate limits for every connection
+    config: RelayConfig,
+    /// Traffic across every session, outliving them
+    traffic: Arc<RelayMetrics>,
+    /// Wrong session passwords per address
+    password_failures: Arc<PasswordFailures>,
 }
 
 impl RelayServer {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            limits: Arc::new(RwLock::new(HashMap::new())),
+            listings: Arc::new(RwLock::new(HashMap::new())),
             peers_by_id: Arc::new(RwLock::new(HashMap::new())),
             shutdown_tx: None,
             bind_addr: None,
+            listener: None,
+            auth: None,
+            ping_interval: PING_INTERVAL,
+            config: RelayConfig::default(),
+            traffic: Arc::new(RelayMetrics::new()),
+            password_failures: Arc::new(PasswordFailures::new(MAX_PASSWORD_FAILURES_PER_ADDR, PASSWORD_LOCKOUT)),
         }
     }
     
+    /// Apply `config`'s limits instead of the defaults
+    pub fn with_config(mut self, config: RelayConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// Change the limits; takes effect on the next `start`
+    pub fn set_config(&mut self, config: RelayConfig) {
+        self.config = config;
+    }
+    
+    pub fn config(&self) -> &RelayConfig {
+        &self.config
+    }
+    
+    /// Ping peers more or less often than `PING_INTERVAL`
+    pub fn with_ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+    
+    /// Require every `Join` to carry a token `validator` accepts
+    pub fn with_auth(mut self, validator: Arc<dyn JoinValidator>) -> Self {
+        self.auth = Some(validator);
+        self
+    }
+    
+    /// Change the join validator; takes effect on the next `start`
+    pub fn set_auth(&mut self, validator: Option<Arc<dyn JoinValidator>>) {
+        self.auth = validator;
+    }
+    
+    pub fn requires_auth(&self) -> bool {
+        self.auth.is_some()
+    }
+    
     pub async fn start(&mut self, addr: &str) -> Result<SocketAddr, RelayError> {
         let listener = TcpListener::bind(addr).await
             .map_err(|e| RelayError::BindFailed(e.to_string()))?;
@@ -138,20 +384,33 @@ impl RelayServer {
         
         let sessions = Arc::clone(&self.sessions);
         let peers_by_id = Arc::clone(&self.peers_by_id);
+        let limits = Arc::clone(&self.limits);
+        let auth = self.auth.clone();
+        let config = Arc::new(self.config.clone());
+        let traffic = Arc::clone(&self.traffic);
+        let password_failures = Arc::clone(&self.password_failures);
+        let mut ping_timer = tokio::time::interval(self.ping_interval);
+        ping_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
         
         info!("Relay server starting on {}", local_addr);
         
-        tokio::spawn(async move {
+        self.listener = Some(tokio::spawn(async move {
             let mut shutdown_rx = shutdown_tx.subscribe();
+            let mut nonce = 0u64;
             
             loop {
                 tokio::select! {
+                    _ = ping_timer.tick() => {
+                        nonce += 1;
+                        Self::ping_peers(&sessions, &peers_by_id, nonce).await;
+                    }
                     result = listener.accept() => {
                         match result {
                             Ok((stream, addr)) => {
                                 let sessions = Arc::clone(&sessions);
                                 let peers_by_id = Arc::clone(&peers_by_id);
-                                tokio::spawn(Self::handle_connection(stream, addr, sessions, peers_by_id));
+                                let limits = Arc::clone(&limits);
+                                tokio::spawn(Self::handle_connection(stream, addr, sessions, peers_by_id, limits, auth.clone(), config.clone(), traffic.clone(), password_failures.clone()));
                             }
                             Err(e) => {
                                 error!("Failed to accept connection: {}", e);
@@ -164,16 +423,52 @@ impl RelayServer {
                     }
                 }
             }
-        });
+        }));
         
         Ok(local_addr)
     }
     
+    /// Ping every peer, dropping those that have missed too many in a row
+    async fn ping_peers(
+        sessions: &Arc<RwLock<HashMap<String, RelaySession>>>,
+        peers_by_id: &Arc<RwLock<HashMap<Uuid, String>>>,
+        nonce: u64,
+    ) {
+        let ping = serde_json::to_string(&RelayMessage::Ping { nonce: Some(nonce) }).unwrap();
+        let mut stale = Vec::new();
+        
+        for (session_id, session) in sessions.write().await.iter_mut() {
+            for peer in session.peers.values_mut() {
+                if peer.pending_ping.is_some() {
+                    peer.missed_pings += 1;
+                }
+                if peer.missed_pings >= MAX_MISSED_PINGS {
+                    let _ = peer.sender.send(Message::Close(None));
+                    stale.push((session_id.clone(), peer.user_id));
+                    continue;
+                }
+                peer.pending_ping = Some((nonce, Instant::now()));
+                let _ = peer.sender.send(Message::Text(ping.clone()));
+            }
+        }
+        
+        for (session_id, user_id) in stale {
+            warn!("Dropping {} from session {} after {} missed pings", user_id, session_id, MAX_MISSED_PINGS);
+            Self::remove_peer(sessions, peers_by_id, &session_id, user_id).await;
+        }
+    }
+    
+    #[allow(clippy::too_many_arguments)]
     async fn handle_connection(
         stream: TcpStream,
         addr: SocketAddr,
         sessions: Arc<RwLock<HashMap<String, RelaySession>>>,
         peers_by_id: Arc<RwLock<HashMap<Uuid, String>>>,
+        limits: Arc<RwLock<HashMap<String, usize>>>,
+        auth: Option<Arc<dyn JoinValidator>>,
+        config: Arc<RelayConfig>,
+        traffic: Arc<RelayMetrics>,
+        password_failures: Arc<PasswordFailures>,
     ) {
         info!("New connection from {}", addr);
         
@@ -198,15 +493,101 @@ impl RelayServer {
         
         let mut current_user_id: Option<Uuid> = None;
         let mut current_session_id: Option<String> = None;
+        let mut rejected = false;
+        let mut wrong_passwords = 0;
+        let mut limiter = PeerLimiter::new(&config);
         
         while let Some(result) = ws_receiver.next().await {
+            // Dropped frames are never fanned out to the other peers
+            let len = match &result {
+                Ok(Message::Text(text)) => Some(text.len()),
+                Ok(Message::Binary(data)) => Some(data.len()),
+                _ => None,
+            };
+            if let Some(Err(violation)) = len.map(|len| limiter.check(len)) {
+                if limiter.exhausted() {
+                    warn!("Disconnecting {}: {}", addr, FLOODING);
+                    let closed_msg = RelayMessage::SessionClosed { reason: FLOODING.to_string() };
+                    let _ = tx.send(Message::Text(serde_json::to_string(&closed_msg).unwrap()));
+                    let _ = tx.send(Message::Close(None));
+                    rejected = true;
+                    break;
+                }
+                warn!("Dropped a frame from {}: {}", addr, violation.message());
+                let error_msg = RelayMessage::Error { message: violation.message().to_string() };
+                let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
+                continue;
+            }
+            
             match result {
                 Ok(Message::Text(text)) => {
                     match serde_json::from_str::<RelayMessage>(&text) {
                         Ok(msg) => {
                             match msg {
-                                RelayMessage::Join { session_id, user_id, username } => {
+                                RelayMessage::Join { session_id, user_id, username, token, public_key, max_peers, password_hash, password } => {
+                                    let username = match &auth {
+                                        None => username,
+                                        Some(auth) => {
+                                            let identity = match token.as_deref() {
+                                                Some(token) => auth.validate(token).await,
+                                                None => None,
+                                            };
+                                            match identity {
+                                                Some(identity) if identity.user_id == user_id => identity.username,
+                                                _ => {
+                                                    warn!("Rejected unauthenticated join from {}", addr);
+                                                    let error_msg = RelayMessage::Error {
+                                                        message: "unauthorized".to_string(),
+                                                    };
+                                                    let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
+                                                    let _ = tx.send(Message::Close(None));
+                                                    rejected = true;
+                                                    break;
+                                                }
+                                            }
+                                        }
+                                    };
+                                    
+                                    // Without auth the user id is only a claim, so
+                                    // claiming the host's doesn't skip the password
+                                    let host_verified = auth.is_some();
+                                    
+                                    // Checked before locking for the join; hashing is slow
+                                    let required = sessions.read().await.get(&session_id)
+                                        .filter(|session| !host_verified || session.host_id != user_id)
+                                        .and_then(|session| session.password_hash.clone());
+                                    if let Some(hash) = required {
+                                        if password_failures.locked_out(addr.ip()) {
+                                            warn!("Refused a join from {} after too many wrong passwords", addr);
+                                            let error_msg = RelayMessage::Error { message: TOO_MANY_PASSWORD_ATTEMPTS.to_string() };
+                                            let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
+                                            let _ = tx.send(Message::Close(None));
+                                            rejected = true;
+                                            break;
+                                        }
+                                        let attempt = password.clone();
+                                        let matches = tokio::task::spawn_blocking(move || password_matches(attempt.as_deref(), &hash))
+                                            .await
+                                            .unwrap_or(false);
+                                        if !matches {
+                                            wrong_passwords += 1;
+                                            warn!("Wrong session password from {} ({} of {})", addr, wrong_passwords, MAX_PASSWORD_ATTEMPTS);
+                                            let locked_out = password_failures.record(addr.ip());
+                                            let error_msg = RelayMessage::Error { message: INVALID_PASSWORD.to_string() };
+                                            let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
+                                            if wrong_passwords >= MAX_PASSWORD_ATTEMPTS || locked_out {
+                                                let _ = tx.send(Message::Close(None));
+                                                rejected = true;
+                                                break;
+                                            }
+                                            continue;
+                                        }
+                                        password_failures.clear(addr.ip());
+                                    }
+                                    
+                                    let preset = limits.read().await.get(&session_id).copied();
                                     let mut sessions_guard = sessions.write().await;
+                                    let created = !sessions_guard.contains_key(&session_id);
                                     
                                     let session = sessions_guard
                                         .entry(session_id.clone())
@@ -214,15 +595,27 @@ impl RelayServer {
                                             id: session_id.clone(),
                                             host_id: user_id,
                                             peers: HashMap::new(),
-                                            max_peers: 8,
+                                            max_peers: preset.unwrap_or(DEFAULT_MAX_PEERS),
                                             created_at: Utc::now(),
+                                            password_hash: None,
+                                            traffic: RelayMetrics::new(),
                                         });
                                     
+                                    // Only a verified host, or whoever started the session
+                                    // on this connection, may change its settings
+                                    let acts_as_host = session.host_id == user_id && (host_verified || created);
+                                    if let Some(max_peers) = max_peers.filter(|_| acts_as_host) {
+                                        session.max_peers = max_peers.max(1);
+                                    }
+                                    if let Some(password_hash) = password_hash.filter(|_| acts_as_host) {
+                                        session.password_hash = Some(password_hash);
+                                    }
+                                    
                                     if session.peers.len() >= session.max_peers {
                                         let error_msg = RelayMessage::Error {
                                             message: "Session full".to_string(),
                                         };
-                                        let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap().into()));
+                                        let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
                                         continue;
                                     }
                                     
@@ -231,33 +624,25 @@ impl RelayServer {
                                     let peer = ConnectedPeer {
                                         user_id,
                                         username: username.clone(),
+                                        public_key,
                                         session_id: session_id.clone(),
                                         sender: tx.clone(),
                                         joined_at: Utc::now(),
                                         is_host,
-                                    };
-                                    
-                                    let peer_info = PeerInfo {
-                                        user_id,
-                                        username: username.clone(),
-                                        is_host,
-                                        joined_at: peer.joined_at,
                                         latency_ms: None,
+                                        pending_ping: None,
+                                        missed_pings: 0,
                                     };
                                     
+                                    let peer_info = peer.info();
                                     let existing_peers: Vec<PeerInfo> = session.peers.values()
-                                        .map(|p| PeerInfo {
-                                            user_id: p.user_id,
-                                            username: p.username.clone(),
-                                            is_host: p.is_host,
-                                            joined_at: p.joined_at,
-                                            latency_ms: None,
-                                        })
+                                        .map(ConnectedPeer::info)
                                         .collect();
+                                    let host = Some(session.host_id);
                                     
                                     for existing in session.peers.values() {
                                         let join_msg = RelayMessage::PeerJoined { peer: peer_info.clone() };
-                                        let _ = existing.sender.send(Message::Text(serde_json::to_string(&join_msg).unwrap().into()));
+                                        let _ = existing.sender.send(Message::Text(serde_json::to_string(&join_msg).unwrap()));
                                     }
                                     
                                     session.peers.insert(user_id, peer);
@@ -268,36 +653,96 @@ impl RelayServer {
                                     current_user_id = Some(user_id);
                                     current_session_id = Some(session_id);
                                     
-                                    let peer_list = RelayMessage::PeerList { peers: existing_peers };
-                                    let _ = tx.send(Message::Text(serde_json::to_string(&peer_list).unwrap().into()));
+                                    let peer_list = RelayMessage::PeerList { peers: existing_peers, host };
+                                    let _ = tx.send(Message::Text(serde_json::to_string(&peer_list).unwrap()));
                                     
                                     info!("User {} ({}) joined session", username, user_id);
                                 }
                                 
-                                RelayMessage::Data { from, to, payload } => {
+                                RelayMessage::Data { from, to, payload, key_id } => {
                                     if let Some(ref session_id) = current_session_id {
                                         let sessions_guard = sessions.read().await;
                                         if let Some(session) = sessions_guard.get(session_id) {
-                                            let data_msg = RelayMessage::Data { from, to, payload };
+                                            let data_msg = RelayMessage::Data { from, to, payload, key_id };
                                             let msg_text = serde_json::to_string(&data_msg).unwrap();
-                                            
-                                            if let Some(target_id) = to {
-                                                if let Some(target) = session.peers.get(&target_id) {
-                                                    let _ = target.sender.send(Message::Text(msg_text.into()));
-                                                }
-                                            } else {
-                                                for (peer_id, peer) in &session.peers {
-                                                    if *peer_id != from {
-                                                        let _ = peer.sender.send(Message::Text(msg_text.clone().into()));
-                                                    }
-                                                }
-                                            }
+                                            session.relay(Message::Text(msg_text), from, to, &traffic);
+                                        }
+                                    }
+                                }
+                                
+                                RelayMessage::SessionKey { to, .. } => {
+                                    if let Some(ref session_id) = current_session_id {
+                                        let sessions_guard = sessions.read().await;
+                                        if let Some(target) = sessions_guard.get(session_id).and_then(|s| s.peers.get(&to)) {
+                                            let _ = target.sender.send(Message::Text(text.clone()));
                                         }
                                     }
                                 }
                                 
-                                RelayMessage::Ping => {
-                                    let _ = tx.send(Message::Text(serde_json::to_string(&RelayMessage::Pong).unwrap().into()));
+                                RelayMessage::ConfigureSession { max_peers } => {
+                                    let (Some(ref session_id), Some(user_id)) = (&current_session_id, current_user_id) else {
+                                        continue;
+                                    };
+                                    let mut sessions_guard = sessions.write().await;
+                                    match sessions_guard.get_mut(session_id) {
+                                        Some(session) if session.host_id == user_id => {
+                                            session.max_peers = max_peers.max(1);
+                                            info!("Session {} limited to {} peers", session_id, session.max_peers);
+                                        }
+                                        Some(_) => {
+                                            let error_msg = RelayMessage::Error {
+                                                message: "Only the host can change the session limit".to_string(),
+                                            };
+                                            let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
+                                        }
+                                        None => {}
+                                    }
+                                }
+                                
+                                RelayMessage::TransferHost { new_host } => {
+                                    let (Some(ref session_id), Some(user_id)) = (&current_session_id, current_user_id) else {
+                                        continue;
+                                    };
+                                    let mut sessions_guard = sessions.write().await;
+                                    let Some(session) = sessions_guard.get_mut(session_id) else {
+                                        continue;
+                                    };
+                                    let refused = if session.host_id != user_id {
+                                        Some("Only the host can hand off hosting")
+                                    } else if !session.peers.contains_key(&new_host) {
+                                        Some("Peer not found")
+                                    } else {
+                                        None
+                                    };
+                                    match refused {
+                                        Some(message) => {
+                                            let error_msg = RelayMessage::Error { message: message.to_string() };
+                                            let _ = tx.send(Message::Text(serde_json::to_string(&error_msg).unwrap()));
+                                        }
+                                        None => {
+                                            Self::migrate_host(session, new_host);
+                                            info!("Host handed off to {} in session {}", new_host, session_id);
+                                        }
+                                    }
+                                }
+                                
+                                RelayMessage::Ping { nonce } => {
+                                    let _ = tx.send(Message::Text(serde_json::to_string(&RelayMessage::Pong { nonce }).unwrap()));
+                                }
+                                
+                                RelayMessage::Pong { nonce } => {
+                                    if let (Some(ref session_id), Some(user_id)) = (&current_session_id, current_user_id) {
+                                        let mut sessions_guard = sessions.write().await;
+                                        if let Some(peer) = sessions_guard.get_mut(session_id).and_then(|s| s.peers.get_mut(&user_id)) {
+                                            // Any pong shows the peer is alive; only the
+                                            // latest ping's measures latency
+                                            peer.missed_pings = 0;
+                                            if let Some((_, at)) = peer.pending_ping.filter(|(sent, _)| Some(*sent) == nonce) {
+                                                peer.latency_ms = Some(at.elapsed().as_millis().min(u32::MAX as u128) as u32);
+                                                peer.pending_ping = None;
+                                            }
+                                        }
+                                    }
                                 }
                                 
                                 RelayMessage::Leave { session_id, user_id } => {
@@ -317,11 +762,7 @@ impl RelayServer {
                     if let (Some(ref session_id), Some(user_id)) = (&current_session_id, current_user_id) {
                         let sessions_guard = sessions.read().await;
                         if let Some(session) = sessions_guard.get(session_id) {
-                            for (peer_id, peer) in &session.peers {
-                                if *peer_id != user_id {
-                                    let _ = peer.sender.send(Message::Binary(data.clone()));
-                                }
-                            }
+                            session.relay(Message::Binary(data), user_id, None, &traffic);
                         }
                     }
                 }
@@ -337,10 +778,28 @@ impl RelayServer {
         }
         
         if let (Some(session_id), Some(user_id)) = (current_session_id, current_user_id) {
-            Self::remove_peer(&sessions, &peers_by_id, &session_id, user_id).await;
+            // A client that reconnected before this connection was noticed
+            // dead has already taken its place
+            let replaced = sessions.read().await.get(&session_id)
+                .and_then(|session| session.peers.get(&user_id))
+                .is_some_and(|peer| !peer.sender.same_channel(&tx));
+            if !replaced {
+                Self::remove_peer(&sessions, &peers_by_id, &session_id, user_id).await;
+            }
         }
         
-        send_task.abort();
+        if rejected {
+            // Let the error and close frame go out first, then read until
+            // the peer closes too: hanging up on unread frames resets the
+            // connection, which can cost the peer the error
+            drop(tx);
+            let _ = send_task.await;
+            let _ = tokio::time::timeout(CLOSE_GRACE, async {
+                while let Some(Ok(_)) = ws_receiver.next().await {}
+            }).await;
+        } else {
+            send_task.abort();
+        }
         info!("Connection closed for {}", addr);
     }
     
@@ -358,20 +817,12 @@ impl RelayServer {
             
             let leave_msg = RelayMessage::PeerLeft { user_id };
             for peer in session.peers.values() {
-                let _ = peer.sender.send(Message::Text(serde_json::to_string(&leave_msg).unwrap().into()));
+                let _ = peer.sender.send(Message::Text(serde_json::to_string(&leave_msg).unwrap()));
             }
             
             if was_host && !session.peers.is_empty() {
                 let new_host_id = *session.peers.keys().next().unwrap();
-                if let Some(new_host) = session.peers.get_mut(&new_host_id) {
-                    new_host.is_host = true;
-                }
-                session.host_id = new_host_id;
-                
-                let migration_msg = RelayMessage::HostMigration { new_host: new_host_id };
-                for peer in session.peers.values() {
-                    let _ = peer.sender.send(Message::Text(serde_json::to_string(&migration_msg).unwrap().into()));
-                }
+                Self::migrate_host(session, new_host_id);
                 info!("Host migrated to {} in session {}", new_host_id, session_id);
             }
             
@@ -384,11 +835,25 @@ impl RelayServer {
         peers_by_id.write().await.remove(&user_id);
     }
     
+    /// Make `new_host` the session's host and tell every peer
+    fn migrate_host(session: &mut RelaySession, new_host: Uuid) {
+        for peer in session.peers.values_mut() {
+            peer.is_host = peer.user_id == new_host;
+        }
+        session.host_id = new_host;
+        
+        let migration_msg = RelayMessage::HostMigration { new_host };
+        for peer in session.peers.values() {
+            let _ = peer.sender.send(Message::Text(serde_json::to_string(&migration_msg).unwrap()));
+        }
+    }
+    
     pub async fn stop(&mut self) {
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
         }
         self.bind_addr = None;
+        self.listener = None;
         info!("Relay server stopped");
     }
     
@@ -409,14 +874,40 @@ impl RelayServer {
     }
     
     pub async fn get_session_info(&self, session_id: &str) -> Option<SessionInfo> {
-        let sessions = self.sessions.read().await;
-        sessions.get(session_id).map(|s| SessionInfo {
-            id: s.id.clone(),
-            host_id: s.host_id,
-            peer_count: s.peers.len(),
-            max_peers: s.max_peers,
-            created_at: s.created_at,
-        })
+        self.sessions.read().await.get(session_id).map(RelaySession::info)
+    }
+    
+    /// Every live session with its peers and their latency
+    pub async fn list_sessions(&self) -> Vec<SessionInfo> {
+        self.sessions.read().await.values().map(RelaySession::info).collect()
+    }
+    
+    /// Traffic relayed across all sessions, including closed ones
+    pub fn traffic(&self) -> TrafficStats {
+        self.traffic.snapshot()
+    }
+    
+    /// Zero the relay's and every live session's traffic counters
+    pub async fn reset_traffic(&self) {
+        self.traffic.reset();
+        for session in self.sessions.read().await.values() {
+            session.traffic.reset();
+        }
+    }
+    
+    /// Limit a session to `max_peers`. Applies straight away if the
+    /// session is live, otherwise when its first peer connects.
+    pub async fn set_session_limit(&self, session_id: &str, max_peers: usize) {
+        set_session_limit(&self.limits, &self.sessions, session_id, max_peers).await;
+    }
+    
+    /// A session broker backed by this relay's session registry
+    pub fn broker(&self) -> RelayBroker {
+        RelayBroker {
+            listings: self.listings.clone(),
+            sessions: self.sessions.clone(),
+            limits: self.limits.clone(),
+        }
     }
 }
 
@@ -426,20 +917,490 @@ impl Default for RelayServer {
     }
 }
 
+/// A stopped relay is fine; a started one whose accept loop has exited is not
+#[async_trait::async_trait]
+impl HealthCheck for RelayServer {
+    fn name(&self) -> &str {
+        "relay"
+    }
+
+    async fn health_check(&self) -> CheckResult {
+        match (&self.bind_addr, &self.listener) {
+            (Some(_), Some(listener)) if listener.is_finished() => CheckResult::down("Listener has stopped"),
+            (Some(addr), _) => CheckResult::ok_with(format!("Listening on {}", addr)),
+            (None, _) => CheckResult::ok_with("Not started"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
     pub id: String,
     pub host_id: Uuid,
     pub peer_count: usize,
     pub max_peers: usize,
+    /// Joins need the passphrase the host set
+    #[serde(default)]
+    pub password_required: bool,
     pub created_at: DateTime<Utc>,
+    pub peers: Vec<PeerInfo>,
+    #[serde(default)]
+    pub traffic: TrafficStats,
+}
+
+/// Argon2 hash of a session passphrase, for the host's `Join`
+pub fn hash_session_password(password: &str) -> Result<String, RelayError> {
+    Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map(|hash| hash.to_string())
+        .map_err(|e| RelayError::PasswordHash(e.to_string()))
+}
+
+fn password_matches(password: Option<&str>, hash: &str) -> bool {
+    let (Some(password), Ok(hash)) = (password, PasswordHash::new(hash)) else {
+        return false;
+    };
+    Argon2::default().verify_password(password.as_bytes(), &hash).is_ok()
+}
+
+async fn set_session_limit(
+    limits: &RwLock<HashMap<String, usize>>,
+    sessions: &RwLock<HashMap<String, RelaySession>>,
+    session_id: &str,
+    max_peers: usize,
+) {
+    let max_peers = max_peers.max(1);
+    limits.write().await.insert(session_id.to_string(), max_peers);
+    if let Some(session) = sessions.write().await.get_mut(session_id) {
+        session.max_peers = max_peers;
+    }
+}
+
+/// Resolves invite codes against a relay's session registry. Peers already
+/// connected to the relay session count towards a session being full, and
+/// registered sessions are limited to their `max_participants` on the relay.
+#[derive(Clone, Default)]
+pub struct RelayBroker {
+    listings: Arc<RwLock<HashMap<String, Session>>>,
+    sessions: Arc<RwLock<HashMap<String, RelaySession>>>,
+    limits: Arc<RwLock<HashMap<String, usize>>>,
+}
+
+#[async_trait::async_trait]
+impl SessionBroker for RelayBroker {
+    async fn register(&self, session: &Session) -> Result<(), SessionError> {
+        let session_id = session.id.to_string();
+        set_session_limit(&self.limits, &self.sessions, &session_id, session.max_participants).await;
+        self.listings.write().await.insert(session_id, session.clone());
+        Ok(())
+    }
+    
+    async fn join(&self, invite_code: &str, participant: Participant) -> Result<Session, SessionError> {
+        let code = invite_code.trim().to_ascii_uppercase();
+        let mut listings = self.listings.write().await;
+        let Some(session) = listings.values_mut().find(|s| s.invite_code == code) else {
+            return Err(SessionError::InvalidInviteCode(invite_code.to_string()));
+        };
+        
+        let connected = self.sessions.read().await
+            .get(&session.id.to_string())
+            .map_or(0, |s| s.peers.len());
+        if connected.max(session.participants.len() + 1) >= session.max_participants {
+            return Err(SessionError::SessionFull(session.id.to_string()));
+        }
+        
+        session.participants.push(participant);
+        Ok(session.clone())
+    }
+    
+    async fn leave(&self, session_id: Uuid, participant_id: Uuid) -> Result<(), SessionError> {
+        let mut listings = self.listings.write().await;
+        let key = session_id.to_string();
+        let Some(session) = listings.get_mut(&key) else {
+            return Err(SessionError::NotFound(key));
+        };
+        
+        if session.host.id == participant_id {
+            listings.remove(&key);
+            self.limits.write().await.remove(&key);
+        } else {
+            session.participants.retain(|p| p.id != participant_id);
+        }
+        Ok(())
+    }
+}
+
+/// A client's end-to-end encryption state, shared with its receive task
+struct E2eState {
+    keys: PeerKeys,
+    user_id: Uuid,
+    host: Option<Uuid>,
+    current: Option<SessionKey>,
+    /// The key before the last rotation, for payloads already in flight
+    previous: Option<SessionKey>,
+    /// Public keys of the other peers that encrypt
+    peer_keys: HashMap<Uuid, String>,
+}
+
+impl E2eState {
+    fn new(user_id: Uuid) -> Self {
+        Self {
+            keys: PeerKeys::generate(),
+            user_id,
+            host: None,
+            current: None,
+            previous: None,
+            peer_keys: HashMap::new(),
+        }
+    }
+    
+    /// Track keys and the host from an incoming message. Returns the message
+    /// to pass on, with `Data` decrypted, and any replies to send.
+    fn receive(&mut self, msg: RelayMessage) -> (Option<RelayMessage>, Vec<RelayMessage>) {
+        let mut replies = Vec::new();
+        
+        match msg {
+            RelayMessage::PeerList { ref peers, host } => {
+                self.peer_keys = peers.iter()
+                    .filter(|p| p.encryption)
+                    .filter_map(|p| p.public_key.clone().map(|key| (p.user_id, key)))
+                    .collect();
+                // Older relays leave out `host`; nobody being host then means we are
+                self.host = host
+                    .or_else(|| peers.iter().find(|p| p.is_host).map(|p| p.user_id))
+                    .or(Some(self.user_id));
+                if self.is_host() {
+                    replies = self.rotate();
+                }
+            }
+            RelayMessage::PeerJoined { ref peer } => {
+                if peer.is_host {
+                    self.host = Some(peer.user_id);
+                }
+                if let Some(public_key) = peer.public_key.clone().filter(|_| peer.encryption) {
+                    if self.is_host() {
+                        replies.extend(self.seal_to(peer.user_id, &public_key));
+                    }
+                    self.peer_keys.insert(peer.user_id, public_key);
+                }
+            }
+            RelayMessage::PeerLeft { user_id } => {
+                self.peer_keys.remove(&user_id);
+            }
+            RelayMessage::HostMigration { new_host } => {
+                self.host = Some(new_host);
+                if self.is_host() {
+                    replies = self.rotate();
+                } else if let Some(current) = self.current.take() {
+                    // Nothing more goes out under a key the old host holds;
+                    // the new host sends a fresh one
+                    self.previous = Some(current);
+                }
+            }
+            RelayMessage::SessionKey { from, to, key_id, ref ephemeral_key, ref sealed_key } => {
+                if to != self.user_id || self.host.is_some_and(|host| host != from) {
+                    warn!("Ignoring session key from {}, who is not the host", from);
+                    return (None, replies);
+                }
+                match self.keys.open(key_id, ephemeral_key, sealed_key) {
+                    Ok(key) => self.install(key),
+                    Err(e) => {
+                        warn!("Session key from {} rejected: {}", from, e);
+                        return (None, replies);
+                    }
+                }
+            }
+            RelayMessage::Data { from, to, payload, key_id: Some(key_id) } => {
+                let key = self.current.iter().chain(&self.previous).find(|k| k.id() == key_id);
+                return match key.map(|k| k.decrypt(from, &payload)) {
+                    Some(Ok(payload)) => (Some(RelayMessage::Data { from, to, payload, key_id: Some(key_id) }), replies),
+                    _ => {
+                        warn!("Dropping payload from {} that does not decrypt under key {}", from, key_id);
+                        (None, replies)
+                    }
+                };
+            }
+            _ => {}
+        }
+        
+        (Some(msg), replies)
+    }
+    
+    fn is_host(&self) -> bool {
+        self.host == Some(self.user_id)
+    }
+    
+    /// Start a new session key and seal it to every encrypting peer
+    fn rotate(&mut self) -> Vec<RelayMessage> {
+        let last_id = self.current.iter().chain(&self.previous).map(SessionKey::id).max().unwrap_or(0);
+        self.install(SessionKey::generate(last_id.wrapping_add(1)));
+        
+        self.peer_keys.iter()
+            .filter_map(|(peer, public_key)| self.seal_to(*peer, public_key))
+            .collect()
+    }
+    
+    fn install(&mut self, key: SessionKey) {
+        if self.current.as_ref().is_some_and(|current| current.id() == key.id()) {
+            return;
+        }
+        if let Some(current) = self.current.replace(key) {
+            self.previous = Some(current);
+        }
+    }
+    
+    fn seal_to(&self, peer: Uuid, public_key: &str) -> Option<RelayMessage> {
+        let key = self.current.as_ref()?;
+        match key.seal_to(public_key) {
+            Ok(sealed) => Some(RelayMessage::SessionKey {
+                from: self.user_id,
+                to: peer,
+                key_id: key.id(),
+                ephemeral_key: sealed.ephemeral_key,
+                sealed_key: sealed.sealed_key,
+            }),
+            Err(e) => {
+                warn!("Could not seal the session key to {}: {}", peer, e);
+                None
+            }
+        }
+    }
+}
+
+/// How `RelayClient` gets a dropped connection back
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Attempts before giving up; 0 never reconnects
+    pub max_attempts: u32,
+    /// Wait before the first attempt, doubling for each one after
+    pub initial_delay: Duration,
+    /// Longest wait between attempts
+    pub max_delay: Duration,
+    /// `send_data` and `send_binary` frames held while offline and sent
+    /// after rejoining; the oldest are dropped first
+    pub buffer_size: usize,
+    /// Silence from the relay that counts as a dropped connection. The
+    /// client pings when it's been a third of this.
+    pub idle_timeout: Duration,
+}
+
+impl ReconnectPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            buffer_size: 256,
+            idle_timeout: PING_INTERVAL * MAX_MISSED_PINGS,
+        }
+    }
+}
+
+/// A frame on its way to the relay
+enum Outbound {
+    /// Session traffic, held across a reconnect
+    Payload(Message),
+    /// Only meaningful on the connection it was sent on
+    Control(Message),
+}
+
+/// How a connection to the relay ended
+enum LinkEnd {
+    /// The client disconnected or the relay hung up on purpose
+    Closed,
+    /// The connection broke
+    Broken(String),
+}
+
+/// The task behind a connected `RelayClient`: carries frames both ways and
+/// reconnects when the connection breaks
+struct RelayLink {
+    server_url: String,
+    join: Message,
+    events: mpsc::UnboundedSender<RelayMessage>,
+    e2e: Option<Arc<Mutex<E2eState>>>,
+    policy: ReconnectPolicy,
+    connected: Arc<AtomicBool>,
+    buffered: VecDeque<Message>,
+}
+
+impl RelayLink {
+    async fn run(mut self, socket: WebSocketStream<MaybeTlsStream<TcpStream>>, mut outbound: mpsc::UnboundedReceiver<Outbound>) {
+        let mut socket = socket;
+        loop {
+            let reason = match self.serve(socket, &mut outbound).await {
+                LinkEnd::Closed => break,
+                LinkEnd::Broken(reason) => reason,
+            };
+            self.connected.store(false, Ordering::Relaxed);
+            warn!("Lost the relay connection: {}", reason);
+            let _ = self.events.send(RelayMessage::Disconnected { reason });
+            
+            let Some(next) = self.reconnect(&mut outbound).await else {
+                break;
+            };
+            socket = next;
+            self.connected.store(true, Ordering::Relaxed);
+            let _ = self.events.send(RelayMessage::Reconnected);
+        }
+        self.connected.store(false, Ordering::Relaxed);
+    }
+    
+    /// Join over `socket`, send what was held while offline, then relay
+    /// until the connection ends
+    async fn serve(&mut self, socket: WebSocketStream<MaybeTlsStream<TcpStream>>, outbound: &mut mpsc::UnboundedReceiver<Outbound>) -> LinkEnd {
+        let (mut sink, mut stream) = socket.split();
+        
+        if sink.send(self.join.clone()).await.is_err() {
+            return LinkEnd::Broken("Rejoining failed".to_string());
+        }
+        while let Some(frame) = self.buffered.pop_front() {
+            if sink.send(frame.clone()).await.is_err() {
+                self.buffered.push_front(frame);
+                return LinkEnd::Broken("Sending held frames failed".to_string());
+            }
+        }
+        
+        let idle_timeout = self.policy.idle_timeout;
+        let keepalive = idle_timeout / 3;
+        let mut keepalive_timer = tokio::time::interval_at(tokio::time::Instant::now() + keepalive, keepalive);
+        let mut last_heard = Instant::now();
+        
+        loop {
+            tokio::select! {
+                out = outbound.recv() => {
+                    let Some(out) = out else {
+                        let _ = sink.close().await;
+                        return LinkEnd::Closed;
+                    };
+                    let (frame, payload) = match out {
+                        Outbound::Payload(frame) => (frame, true),
+                        Outbound::Control(frame) => (frame, false),
+                    };
+                    if sink.send(frame.clone()).await.is_err() {
+                        if payload {
+                            self.buffer(frame);
+                        }
+                        return LinkEnd::Broken("Sending to the relay failed".to_string());
+                    }
+                }
+                frame = stream.next() => {
+                    last_heard = Instant::now();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => {
+                            for reply in self.receive(&text) {
+                                let _ = sink.send(Message::Text(serde_json::to_string(&reply).unwrap())).await;
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) => return LinkEnd::Closed,
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return LinkEnd::Broken(e.to_string()),
+                        None => return LinkEnd::Broken("Connection closed".to_string()),
+                    }
+                }
+                _ = keepalive_timer.tick() => {
+                    if last_heard.elapsed() >= idle_timeout {
+                        return LinkEnd::Broken(format!("No word from the relay in {}s", idle_timeout.as_secs()));
+                    }
+                    // Both relays answer these, so a quiet session still
+                    // hears from its relay
+                    if last_heard.elapsed() >= keepalive {
+                        let ping = serde_json::to_string(&RelayMessage::Ping { nonce: None }).unwrap();
+                        if sink.send(Message::Text(ping)).await.is_err() {
+                            return LinkEnd::Broken("Sending to the relay failed".to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Pass a frame from the relay on to the consumer, returning the
+    /// replies it calls for
+    fn receive(&self, text: &str) -> Vec<RelayMessage> {
+        let Ok(msg) = serde_json::from_str::<RelayMessage>(text) else {
+            return Vec::new();
+        };
+        let (msg, replies) = match msg {
+            // The relay measures latency with these
+            RelayMessage::Ping { nonce } => (None, vec![RelayMessage::Pong { nonce }]),
+            // Answers to our keepalives
+            RelayMessage::Pong { .. } => (None, Vec::new()),
+            msg => match self.e2e {
+                Some(ref e2e) => e2e.lock().unwrap_or_else(|e| e.into_inner()).receive(msg),
+                None => (Some(msg), Vec::new()),
+            },
+        };
+        if let Some(msg) = msg {
+            // Nobody reading doesn't stop the client sending
+            let _ = self.events.send(msg);
+        }
+        replies
+    }
+    
+    /// Connect again with backoff, holding payloads sent meanwhile. `None`
+    /// when the client disconnected or the attempts ran out.
+    async fn reconnect(&mut self, outbound: &mut mpsc::UnboundedReceiver<Outbound>) -> Option<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        for attempt in 0..self.policy.max_attempts {
+            let wait = tokio::time::sleep(self.policy.delay(attempt));
+            tokio::pin!(wait);
+            loop {
+                tokio::select! {
+                    _ = &mut wait => break,
+                    out = outbound.recv() => match out {
+                        Some(Outbound::Payload(frame)) => self.buffer(frame),
+                        Some(Outbound::Control(_)) => {}
+                        None => return None,
+                    },
+                }
+            }
+            
+            match tokio::time::timeout(self.policy.idle_timeout, tokio_tungstenite::connect_async(&self.server_url)).await {
+                Ok(Ok((socket, _))) => {
+                    info!("Reconnected to relay {}", self.server_url);
+                    return Some(socket);
+                }
+                Ok(Err(e)) => warn!("Reconnecting to the relay failed (attempt {} of {}): {}", attempt + 1, self.policy.max_attempts, e),
+                Err(_) => warn!("Reconnecting to the relay timed out (attempt {} of {})", attempt + 1, self.policy.max_attempts),
+            }
+        }
+        
+        let reason = format!("Lost the connection to the relay; gave up after {} attempts", self.policy.max_attempts);
+        let _ = self.events.send(RelayMessage::SessionClosed { reason });
+        None
+    }
+    
+    fn buffer(&mut self, frame: Message) {
+        if self.policy.buffer_size == 0 {
+            return;
+        }
+        if self.buffered.len() >= self.policy.buffer_size {
+            self.buffered.pop_front();
+        }
+        self.buffered.push_back(frame);
+    }
 }
 
 pub struct RelayClient {
     server_url: String,
-    sender: Option<mpsc::UnboundedSender<Message>>,
+    sender: Option<mpsc::UnboundedSender<Outbound>>,
     user_id: Uuid,
     session_id: Option<String>,
+    token: Option<String>,
+    max_peers: Option<usize>,
+    password: Option<String>,
+    password_hash: Option<String>,
+    encrypt: bool,
+    e2e: Option<Arc<Mutex<E2eState>>>,
+    reconnect: ReconnectPolicy,
+    /// Whether the socket is up, kept by the connection's task
+    connected: Arc<AtomicBool>,
 }
 
 impl RelayClient {
@@ -449,52 +1410,109 @@ impl RelayClient {
             sender: None,
             user_id,
             session_id: None,
+            token: None,
+            max_peers: None,
+            password: None,
+            password_hash: None,
+            encrypt: false,
+            e2e: None,
+            reconnect: ReconnectPolicy::default(),
+            connected: Arc::new(AtomicBool::new(false)),
         }
     }
     
+    /// Recover dropped connections per `policy` instead of the default
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+    
+    /// Authenticate joins, as the central server's hosted relay requires
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+    
+    /// Ask for a session size limit when joining; the relay only honors it
+    /// from the host
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = Some(max_peers);
+        self
+    }
+    
+    /// Passphrase for joining a password-protected session
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+    
+    /// Protect the session with the passphrase behind `password_hash`, from
+    /// `hash_session_password`; the relay only honors it from the host
+    pub fn with_password_hash(mut self, password_hash: impl Into<String>) -> Self {
+        self.password_hash = Some(password_hash.into());
+        self
+    }
+    
+    /// Encrypt `Data` payloads end to end from the next `connect`
+    ///
+    /// The host hands each encrypting peer the session key and a new host
+    /// rotates it. Peers that don't enable this are never given the key, and
+    /// `send_data` fails until a key has arrived. Binary frames are not
+    /// encrypted.
+    pub fn enable_encryption(&mut self) {
+        self.encrypt = true;
+    }
+    
+    /// Id of the session key payloads are currently encrypted under
+    pub fn session_key_id(&self) -> Option<u32> {
+        let e2e = self.e2e.as_ref()?.lock().unwrap_or_else(|e| e.into_inner());
+        e2e.current.as_ref().map(SessionKey::id)
+    }
+    
+    /// Join `session_id` on the relay, returning what it sends. A dropped
+    /// connection is retried per the `ReconnectPolicy`, reported to the
+    /// receiver as `Disconnected` and `Reconnected`; once the client gives
+    /// up the receiver gets a `SessionClosed` and then closes.
     pub async fn connect(&mut self, session_id: &str, username: &str) -> Result<mpsc::UnboundedReceiver<RelayMessage>, RelayError> {
         let (ws_stream, _) = tokio_tungstenite::connect_async(&self.server_url)
             .await
             .map_err(|e| RelayError::ConnectionFailed(e.to_string()))?;
         
-        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
-        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        let (tx, rx) = mpsc::unbounded_channel::<Outbound>();
         let (msg_tx, msg_rx) = mpsc::unbounded_channel::<RelayMessage>();
         
-        self.sender = Some(tx.clone());
+        self.sender = Some(tx);
         self.session_id = Some(session_id.to_string());
         
+        // A fresh key pair for every session
+        self.e2e = self.encrypt.then(|| Arc::new(Mutex::new(E2eState::new(self.user_id))));
+        let public_key = self.e2e.as_ref()
+            .map(|e2e| e2e.lock().unwrap_or_else(|e| e.into_inner()).keys.public_key());
+        
         let join_msg = RelayMessage::Join {
             session_id: session_id.to_string(),
             user_id: self.user_id,
             username: username.to_string(),
+            token: self.token.clone(),
+            public_key,
+            max_peers: self.max_peers,
+            password_hash: self.password_hash.clone(),
+            password: self.password.clone(),
         };
         
-        let _ = tx.send(Message::Text(serde_json::to_string(&join_msg).unwrap().into()));
-        
-        tokio::spawn(async move {
-            while let Some(msg) = rx.recv().await {
-                if ws_sender.send(msg).await.is_err() {
-                    break;
-                }
-            }
-        });
-        
-        tokio::spawn(async move {
-            while let Some(result) = ws_receiver.next().await {
-                match result {
-                    Ok(Message::Text(text)) => {
-                        if let Ok(msg) = serde_json::from_str::<RelayMessage>(&text) {
-                            if msg_tx.send(msg).is_err() {
-                                break;
-                            }
-                        }
-                    }
-                    Ok(Message::Close(_)) | Err(_) => break,
-                    _ => {}
-                }
-            }
-        });
+        // Fresh for every connect, so an old connection winding down
+        // can't mark this one dead
+        self.connected = Arc::new(AtomicBool::new(true));
+        let link = RelayLink {
+            server_url: self.server_url.clone(),
+            join: Message::Text(serde_json::to_string(&join_msg).unwrap()),
+            events: msg_tx,
+            e2e: self.e2e.clone(),
+            policy: self.reconnect.clone(),
+            connected: self.connected.clone(),
+            buffered: VecDeque::new(),
+        };
+        tokio::spawn(link.run(ws_stream, rx));
         
         info!("Connected to relay session {}", session_id);
         Ok(msg_rx)
@@ -503,19 +1521,46 @@ impl RelayClient {
     pub fn send_data(&self, payload: Vec<u8>, to: Option<Uuid>) -> Result<(), RelayError> {
         let sender = self.sender.as_ref().ok_or(RelayError::NotRunning)?;
         
+        let (payload, key_id) = match self.e2e {
+            Some(ref e2e) => {
+                let e2e = e2e.lock().unwrap_or_else(|e| e.into_inner());
+                let key = e2e.current.as_ref()
+                    .ok_or_else(|| RelayError::Encryption("No session key yet".to_string()))?;
+                (key.encrypt(self.user_id, &payload)?, Some(key.id()))
+            }
+            None => (payload, None),
+        };
+        
         let msg = RelayMessage::Data {
             from: self.user_id,
             to,
             payload,
+            key_id,
         };
         
-        sender.send(Message::Text(serde_json::to_string(&msg).unwrap().into()))
+        sender.send(Outbound::Payload(Message::Text(serde_json::to_string(&msg).unwrap())))
+            .map_err(|_| RelayError::ConnectionFailed("Channel closed".to_string()))
+    }
+    
+    /// Change the session's size limit; the relay answers non-hosts with an error
+    pub fn set_session_limit(&self, max_peers: usize) -> Result<(), RelayError> {
+        let sender = self.sender.as_ref().ok_or(RelayError::NotRunning)?;
+        let msg = RelayMessage::ConfigureSession { max_peers };
+        sender.send(Outbound::Control(Message::Text(serde_json::to_string(&msg).unwrap())))
+            .map_err(|_| RelayError::ConnectionFailed("Channel closed".to_string()))
+    }
+    
+    /// Hand hosting to `new_host`; the relay answers non-hosts with an error
+    pub fn transfer_host(&self, new_host: Uuid) -> Result<(), RelayError> {
+        let sender = self.sender.as_ref().ok_or(RelayError::NotRunning)?;
+        let msg = RelayMessage::TransferHost { new_host };
+        sender.send(Outbound::Control(Message::Text(serde_json::to_string(&msg).unwrap())))
             .map_err(|_| RelayError::ConnectionFailed("Channel closed".to_string()))
     }
     
     pub fn send_binary(&self, data: Vec<u8>) -> Result<(), RelayError> {
         let sender = self.sender.as_ref().ok_or(RelayError::NotRunning)?;
-        sender.send(Message::Binary(data.into()))
+        sender.send(Outbound::Payload(Message::Binary(data)))
             .map_err(|_| RelayError::ConnectionFailed("Channel closed".to_string()))
     }
     
@@ -525,12 +1570,13 @@ impl RelayClient {
                 session_id,
                 user_id: self.user_id,
             };
-            let _ = sender.send(Message::Text(serde_json::to_string(&leave_msg).unwrap().into()));
+            let _ = sender.send(Outbound::Control(Message::Text(serde_json::to_string(&leave_msg).unwrap())));
         }
     }
     
+    /// Connected and the socket is up; false while reconnecting
     pub fn is_connected(&self) -> bool {
-        self.sender.is_some()
+        self.sender.is_some() && self.connected.load(Ordering::Relaxed)
     }
 }
 
@@ -544,10 +1590,22 @@ mod tests {
             session_id: "test-123".to_string(),
             user_id: Uuid::new_v4(),
             username: "player1".to_string(),
+            token: None,
+            public_key: None,
+            max_peers: None,
+            password_hash: None,
+            password: None,
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("join"));
         assert!(json.contains("test-123"));
+        assert!(!json.contains("token"));
+        assert!(!json.contains("public_key"));
+        
+        // Pings from clients that predate nonces
+        let ping: RelayMessage = serde_json::from_str(r#"{"type":"ping"}"#).unwrap();
+        assert!(matches!(ping, RelayMessage::Ping { nonce: None }));
+        assert_eq!(serde_json::to_string(&RelayMessage::Pong { nonce: None }).unwrap(), r#"{"type":"pong"}"#);
     }
     
     #[test]
@@ -558,7 +1616,606 @@ mod tests {
             is_host: true,
             joined_at: Utc::now(),
             latency_ms: Some(50),
+            encryption: false,
+            public_key: None,
         };
         assert!(peer.is_host);
+        
+        // Peers from relays without encryption support
+        let legacy: PeerInfo = serde_json::from_value(serde_json::json!({
+            "user_id": Uuid::new_v4(),
+            "username": "old_client",
+            "is_host": false,
+            "joined_at": Utc::now(),
+            "latency_ms": null,
+        })).unwrap();
+        assert!(!legacy.encryption);
+    }
+    
+    async fn local_relay() -> (RelayServer, String) {
+        let mut server = RelayServer::new();
+        let addr = server.start("127.0.0.1:0").await.unwrap();
+        (server, format!("ws://{}", addr))
+    }
+    
+    async fn next_matching(
+        rx: &mut mpsc::UnboundedReceiver<RelayMessage>,
+        matches: impl Fn(&RelayMessage) -> bool,
+    ) -> RelayMessage {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let msg = rx.recv().await.expect("relay connection closed");
+                if matches(&msg) {
+                    return msg;
+                }
+            }
+        }).await.expect("timed out waiting for relay message")
+    }
+    
+    async fn encrypted_client(url: &str, session_id: &str, username: &str) -> (RelayClient, mpsc::UnboundedReceiver<RelayMessage>) {
+        let mut client = RelayClient::new(url, Uuid::new_v4());
+        client.enable_encryption();
+        let rx = client.connect(session_id, username).await.unwrap();
+        (client, rx)
+    }
+    
+    #[tokio::test]
+    async fn test_relay_only_sees_ciphertext() {
+        let (mut server, url) = local_relay().await;
+        
+        let (host, mut host_rx) = encrypted_client(&url, "sealed", "host").await;
+        next_matching(&mut host_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        assert_eq!(host.session_key_id(), Some(1));
+        
+        let (guest, mut guest_rx) = encrypted_client(&url, "sealed", "guest").await;
+        next_matching(&mut guest_rx, |m| matches!(m, RelayMessage::SessionKey { .. })).await;
+        assert_eq!(guest.session_key_id(), Some(1));
+        
+        // A peer without encryption receives exactly what the relay forwards
+        let (observer, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut observer_tx, mut observer_rx) = observer.split();
+        let join = RelayMessage::Join {
+            session_id: "sealed".to_string(),
+            user_id: Uuid::new_v4(),
+            username: "observer".to_string(),
+            token: None,
+            public_key: None,
+            max_peers: None,
+            password_hash: None,
+            password: None,
+        };
+        observer_tx.send(Message::Text(serde_json::to_string(&join).unwrap())).await.unwrap();
+        next_matching(&mut host_rx, |m| matches!(m, RelayMessage::PeerJoined { .. })).await;
+        next_matching(&mut guest_rx, |m| matches!(m, RelayMessage::PeerJoined { .. })).await;
+        
+        let plaintext = b"player moved to 10,64,-3".to_vec();
+        host.send_data(plaintext.clone(), None).unwrap();
+        
+        let forwarded = loop {
+            let Some(Ok(Message::Text(text))) = observer_rx.next().await else {
+                panic!("observer disconnected");
+            };
+            if let Ok(RelayMessage::Data { payload, key_id, .. }) = serde_json::from_str(&text) {
+                assert_eq!(key_id, Some(1));
+                break payload;
+            }
+        };
+        assert_ne!(forwarded, plaintext);
+        assert!(!forwarded.windows(plaintext.len()).any(|w| w == plaintext.as_slice()));
+        
+        match next_matching(&mut guest_rx, |m| matches!(m, RelayMessage::Data { .. })).await {
+            RelayMessage::Data { from, payload, .. } => {
+                assert_eq!(from, host.user_id);
+                assert_eq!(payload, plaintext);
+            }
+            _ => unreachable!(),
+        }
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_host_migration_rotates_session_key() {
+        let (mut server, url) = local_relay().await;
+        
+        let (mut host, mut host_rx) = encrypted_client(&url, "rekey", "host").await;
+        next_matching(&mut host_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        let (first, mut first_rx) = encrypted_client(&url, "rekey", "first").await;
+        next_matching(&mut first_rx, |m| matches!(m, RelayMessage::SessionKey { .. })).await;
+        let (second, mut second_rx) = encrypted_client(&url, "rekey", "second").await;
+        next_matching(&mut second_rx, |m| matches!(m, RelayMessage::SessionKey { .. })).await;
+        
+        host.disconnect();
+        
+        let is_migration = |m: &RelayMessage| matches!(m, RelayMessage::HostMigration { .. });
+        let RelayMessage::HostMigration { new_host } = next_matching(&mut first_rx, is_migration).await else {
+            unreachable!()
+        };
+        next_matching(&mut second_rx, is_migration).await;
+        
+        let (new_host, (guest, guest_rx)) = if new_host == first.user_id {
+            (&first, (&second, &mut second_rx))
+        } else {
+            (&second, (&first, &mut first_rx))
+        };
+        assert_eq!(new_host.session_key_id(), Some(2));
+        
+        next_matching(guest_rx, |m| matches!(m, RelayMessage::SessionKey { key_id: 2, .. })).await;
+        assert_eq!(guest.session_key_id(), Some(2));
+        
+        new_host.send_data(b"after migration".to_vec(), Some(guest.user_id)).unwrap();
+        match next_matching(guest_rx, |m| matches!(m, RelayMessage::Data { .. })).await {
+            RelayMessage::Data { payload, key_id, .. } => {
+                assert_eq!(key_id, Some(2));
+                assert_eq!(payload, b"after migration");
+            }
+            _ => unreachable!(),
+        }
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_only_the_host_hands_off_hosting() {
+        let (mut server, url) = local_relay().await;
+        
+        let mut host = RelayClient::new(&url, Uuid::new_v4());
+        let mut host_rx = host.connect("handoff", "host").await.unwrap();
+        next_matching(&mut host_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        let mut guest = RelayClient::new(&url, Uuid::new_v4());
+        let mut guest_rx = guest.connect("handoff", "guest").await.unwrap();
+        next_matching(&mut guest_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        
+        guest.transfer_host(guest.user_id).unwrap();
+        next_matching(&mut guest_rx, |m| matches!(m, RelayMessage::Error { message } if message == "Only the host can hand off hosting")).await;
+        
+        host.transfer_host(guest.user_id).unwrap();
+        for rx in [&mut host_rx, &mut guest_rx] {
+            next_matching(rx, |m| matches!(m, RelayMessage::HostMigration { new_host } if *new_host == guest.user_id)).await;
+        }
+        let info = server.get_session_info("handoff").await.unwrap();
+        assert_eq!(info.host_id, guest.user_id);
+        assert!(info.peers.iter().all(|p| p.is_host == (p.user_id == guest.user_id)));
+        
+        server.stop().await;
+    }
+    
+    /// Accepts exactly one token, for one user
+    struct OneToken(RelayIdentity);
+    
+    #[async_trait::async_trait]
+    impl JoinValidator for OneToken {
+        async fn validate(&self, token: &str) -> Option<RelayIdentity> {
+            (token == "valid-token").then(|| self.0.clone())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_join_requires_a_valid_token() {
+        let identity = RelayIdentity { user_id: Uuid::new_v4(), username: "account_name".to_string() };
+        let mut server = RelayServer::new().with_auth(Arc::new(OneToken(identity.clone())));
+        let url = format!("ws://{}", server.start("127.0.0.1:0").await.unwrap());
+        
+        let rejected = [
+            RelayClient::new(&url, identity.user_id),
+            RelayClient::new(&url, identity.user_id).with_token("bogus"),
+            // Someone else's token doesn't let you claim their id
+            RelayClient::new(&url, Uuid::new_v4()).with_token("valid-token"),
+        ];
+        for mut client in rejected {
+            let mut rx = client.connect("guarded", "spoofed").await.unwrap();
+            let RelayMessage::Error { message } = next_matching(&mut rx, |m| matches!(m, RelayMessage::Error { .. })).await else {
+                unreachable!()
+            };
+            assert_eq!(message, "unauthorized");
+            // ...and the relay hangs up
+            let closed = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.unwrap();
+            assert!(closed.is_none());
+        }
+        assert_eq!(server.get_session_count().await, 0);
+        
+        let mut client = RelayClient::new(&url, identity.user_id).with_token("valid-token");
+        let mut rx = client.connect("guarded", "spoofed").await.unwrap();
+        next_matching(&mut rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        
+        let mut observer = RelayClient::new(&url, identity.user_id).with_token("valid-token");
+        let mut observer_rx = observer.connect("guarded", "observer").await.unwrap();
+        let RelayMessage::PeerList { peers, .. } = next_matching(&mut observer_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await else {
+            unreachable!()
+        };
+        // The account's name is used, not the one the client sent
+        assert_eq!(peers[0].username, "account_name");
+        
+        server.stop().await;
+    }
+    
+    async fn joined(session_id: &str, mut client: RelayClient) -> (RelayClient, mpsc::UnboundedReceiver<RelayMessage>) {
+        let mut rx = client.connect(session_id, "player").await.unwrap();
+        next_matching(&mut rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        (client, rx)
+    }
+    
+    async fn expect_error(rx: &mut mpsc::UnboundedReceiver<RelayMessage>) -> String {
+        match next_matching(rx, |m| matches!(m, RelayMessage::Error { .. })).await {
+            RelayMessage::Error { message } => message,
+            _ => unreachable!(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_host_limits_session_size() {
+        let (mut server, url) = local_relay().await;
+        
+        let (host, _host_rx) = joined("duo", RelayClient::new(&url, Uuid::new_v4()).with_max_peers(2)).await;
+        // Only the host's limit counts
+        let (guest, mut guest_rx) = joined("duo", RelayClient::new(&url, Uuid::new_v4()).with_max_peers(16)).await;
+        assert_eq!(server.get_session_info("duo").await.unwrap().max_peers, 2);
+        
+        let mut third = RelayClient::new(&url, Uuid::new_v4());
+        let mut third_rx = third.connect("duo", "third").await.unwrap();
+        assert_eq!(expect_error(&mut third_rx).await, "Session full");
+        
+        guest.set_session_limit(8).unwrap();
+        assert_eq!(expect_error(&mut guest_rx).await, "Only the host can change the session limit");
+        assert_eq!(server.get_session_info("duo").await.unwrap().max_peers, 2);
+        
+        host.set_session_limit(3).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while server.get_session_info("duo").await.unwrap().max_peers != 3 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }).await.unwrap();
+        let _third = joined("duo", RelayClient::new(&url, Uuid::new_v4())).await;
+        assert_eq!(server.get_session_info("duo").await.unwrap().peer_count, 3);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_preset_limit_applies_when_session_starts() {
+        let (mut server, url) = local_relay().await;
+        server.set_session_limit("preset", 2).await;
+        
+        let _first = joined("preset", RelayClient::new(&url, Uuid::new_v4())).await;
+        let _second = joined("preset", RelayClient::new(&url, Uuid::new_v4())).await;
+        let mut third = RelayClient::new(&url, Uuid::new_v4());
+        let mut third_rx = third.connect("preset", "third").await.unwrap();
+        assert_eq!(expect_error(&mut third_rx).await, "Session full");
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_private_session_needs_the_password() {
+        let (mut server, url) = local_relay().await;
+        
+        let hash = hash_session_password("hunter2").unwrap();
+        let host = RelayClient::new(&url, Uuid::new_v4()).with_password_hash(hash);
+        let (host, _host_rx) = joined("private", host).await;
+        assert!(server.get_session_info("private").await.unwrap().password_required);
+        
+        let mut missing = RelayClient::new(&url, Uuid::new_v4());
+        let mut missing_rx = missing.connect("private", "guest").await.unwrap();
+        assert_eq!(expect_error(&mut missing_rx).await, INVALID_PASSWORD);
+        
+        // Without auth, claiming to be the host isn't enough either
+        let mut impostor = RelayClient::new(&url, host.user_id).with_password_hash(hash_session_password("mine").unwrap());
+        let mut impostor_rx = impostor.connect("private", "host").await.unwrap();
+        assert_eq!(expect_error(&mut impostor_rx).await, INVALID_PASSWORD);
+        
+        // One wrong guess leaves the connection open for a retry
+        let (socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut socket_tx, mut socket_rx) = socket.split();
+        let join = |password: &str| RelayMessage::Join {
+            session_id: "private".to_string(),
+            user_id: Uuid::new_v4(),
+            username: "guesser".to_string(),
+            token: None,
+            public_key: None,
+            max_peers: None,
+            password_hash: None,
+            password: Some(password.to_string()),
+        };
+        for attempt in ["hunter3", "hunter4"] {
+            socket_tx.send(Message::Text(serde_json::to_string(&join(attempt)).unwrap())).await.unwrap();
+            let Some(Ok(Message::Text(text))) = socket_rx.next().await else {
+                panic!("relay hung up early");
+            };
+            assert!(matches!(serde_json::from_str(&text).unwrap(), RelayMessage::Error { message } if message == INVALID_PASSWORD));
+        }
+        // ...but not for a second one
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(5), socket_rx.next()).await.unwrap();
+        assert!(matches!(closed, None | Some(Ok(Message::Close(_)))));
+        
+        let guest = RelayClient::new(&url, Uuid::new_v4()).with_password("hunter2");
+        let _guest = joined("private", guest).await;
+        assert_eq!(server.get_session_info("private").await.unwrap().peer_count, 2);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_flooding_peer_is_dropped() {
+        let config = RelayConfig {
+            max_frame_bytes: 1024,
+            messages_per_sec: 5,
+            message_burst: 10,
+            max_violations: 5,
+            ..Default::default()
+        };
+        let mut server = RelayServer::new().with_config(config);
+        let url = format!("ws://{}", server.start("127.0.0.1:0").await.unwrap());
+        
+        let (host, mut host_rx) = joined("flooded", RelayClient::new(&url, Uuid::new_v4())).await;
+        let (bystander, mut bystander_rx) = joined("flooded", RelayClient::new(&url, Uuid::new_v4())).await;
+        
+        let (flooder, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut flooder_tx, mut flooder_rx) = flooder.split();
+        let flooder_id = Uuid::new_v4();
+        let join = RelayMessage::Join {
+            session_id: "flooded".to_string(),
+            user_id: flooder_id,
+            username: "flooder".to_string(),
+            token: None,
+            public_key: None,
+            max_peers: None,
+            password_hash: None,
+            password: None,
+        };
+        flooder_tx.send(Message::Text(serde_json::to_string(&join).unwrap())).await.unwrap();
+        next_matching(&mut host_rx, |m| matches!(m, RelayMessage::PeerJoined { .. })).await;
+        
+        flooder_tx.send(Message::Binary(vec![0; 2048])).await.unwrap();
+        for _ in 0..100 {
+            let data = RelayMessage::Data { from: flooder_id, to: None, payload: b"spam".to_vec(), key_id: None };
+            if flooder_tx.send(Message::Text(serde_json::to_string(&data).unwrap())).await.is_err() {
+                break;
+            }
+        }
+        
+        let mut errors = Vec::new();
+        let reason = loop {
+            let next = tokio::time::timeout(std::time::Duration::from_secs(5), flooder_rx.next()).await.unwrap();
+            let Some(Ok(Message::Text(text))) = next else {
+                panic!("relay hung up without saying why");
+            };
+            match serde_json::from_str(&text).unwrap() {
+                RelayMessage::Error { message } => errors.push(message),
+                RelayMessage::SessionClosed { reason } => break reason,
+                _ => {}
+            }
+        };
+        assert_eq!(reason, FLOODING);
+        assert_eq!(errors[0], "Message too large");
+        assert!(errors[1..].iter().all(|e| e == "Rate limit exceeded"));
+        
+        let left = next_matching(&mut host_rx, |m| matches!(m, RelayMessage::PeerLeft { .. })).await;
+        assert!(matches!(left, RelayMessage::PeerLeft { user_id } if user_id == flooder_id));
+        
+        // Only the burst got through, and everyone else is still connected
+        host.send_data(b"still here".to_vec(), Some(bystander.user_id)).unwrap();
+        let mut spam = 0;
+        loop {
+            match next_matching(&mut bystander_rx, |m| matches!(m, RelayMessage::Data { .. })).await {
+                RelayMessage::Data { payload, .. } if payload == b"spam" => spam += 1,
+                RelayMessage::Data { payload, .. } => {
+                    assert_eq!(payload, b"still here");
+                    break;
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert!(spam < 100);
+        assert_eq!(server.get_session_info("flooded").await.unwrap().peer_count, 2);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_traffic_counters_track_relayed_messages() {
+        const BROADCASTS: usize = 500;
+        const DIRECTED: usize = 300;
+        const BINARY: usize = 200;
+        
+        let config = RelayConfig { messages_per_sec: 10_000, message_burst: 10_000, ..Default::default() };
+        let mut server = RelayServer::new().with_config(config);
+        let url = format!("ws://{}", server.start("127.0.0.1:0").await.unwrap());
+        
+        let (host, _host_rx) = joined("busy", RelayClient::new(&url, Uuid::new_v4())).await;
+        let (first, _first_rx) = joined("busy", RelayClient::new(&url, Uuid::new_v4())).await;
+        let (second, _second_rx) = joined("busy", RelayClient::new(&url, Uuid::new_v4())).await;
+        
+        let payload = vec![7u8; 64];
+        for _ in 0..BROADCASTS {
+            host.send_data(payload.clone(), None).unwrap();
+        }
+        for _ in 0..DIRECTED {
+            host.send_data(payload.clone(), Some(first.user_id)).unwrap();
+        }
+        for _ in 0..BINARY {
+            second.send_binary(payload.clone()).unwrap();
+        }
+        
+        let sent = BROADCASTS + DIRECTED + BINARY;
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            while server.traffic().messages_relayed < sent as u64 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }).await.expect("timed out waiting for the relay to forward everything");
+        
+        let framed = |to| serde_json::to_string(&RelayMessage::Data { from: host.user_id, to, payload: payload.clone(), key_id: None }).unwrap().len();
+        // Broadcasts reach the two other peers, directed frames one
+        let bytes = BROADCASTS * framed(None) * 2 + DIRECTED * framed(Some(first.user_id)) + BINARY * payload.len() * 2;
+        
+        let totals = server.traffic();
+        assert_eq!(totals.messages_relayed, sent as u64);
+        assert_eq!(totals.broadcast_messages, (BROADCASTS + BINARY) as u64);
+        assert_eq!(totals.directed_messages, DIRECTED as u64);
+        assert_eq!(totals.bytes_relayed, bytes as u64);
+        assert!(totals.messages_per_sec > 0.0 && totals.bytes_per_sec > 0.0);
+        
+        let session = server.get_session_info("busy").await.unwrap().traffic;
+        assert_eq!(session.messages_relayed, totals.messages_relayed);
+        assert_eq!(session.bytes_relayed, totals.bytes_relayed);
+        
+        server.reset_traffic().await;
+        assert_eq!(server.traffic(), TrafficStats::default());
+        assert_eq!(server.get_session_info("busy").await.unwrap().traffic, TrafficStats::default());
+        
+        server.stop().await;
+    }
+    
+    /// Forwards connections to a relay, like a network that can go down
+    struct Tunnel {
+        url: String,
+        down: Arc<AtomicBool>,
+        links: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    }
+    
+    impl Tunnel {
+        async fn to(target: SocketAddr) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("ws://{}", listener.local_addr().unwrap());
+            let down = Arc::new(AtomicBool::new(false));
+            let links = Arc::new(Mutex::new(Vec::new()));
+            
+            let (refusing, accepted) = (down.clone(), links.clone());
+            tokio::spawn(async move {
+                while let Ok((mut inbound, _)) = listener.accept().await {
+                    if refusing.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let link = tokio::spawn(async move {
+                        if let Ok(mut outbound) = TcpStream::connect(target).await {
+                            let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                        }
+                    });
+                    accepted.lock().unwrap().push(link);
+                }
+            });
+            Self { url, down, links }
+        }
+        
+        /// Cut every connection and refuse new ones
+        fn go_down(&self) {
+            self.down.store(true, Ordering::Relaxed);
+            for link in self.links.lock().unwrap().drain(..) {
+                link.abort();
+            }
+        }
+        
+        fn come_back(&self) {
+            self.down.store(false, Ordering::Relaxed);
+        }
+    }
+    
+    fn quick_reconnect(max_attempts: u32) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts,
+            initial_delay: std::time::Duration::from_millis(50),
+            max_delay: std::time::Duration::from_millis(200),
+            ..Default::default()
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_client_resumes_after_the_connection_drops() {
+        let mut server = RelayServer::new();
+        let addr = server.start("127.0.0.1:0").await.unwrap();
+        let tunnel = Tunnel::to(addr).await;
+        
+        let (guest, mut guest_rx) = joined("resume", RelayClient::new(&format!("ws://{}", addr), Uuid::new_v4())).await;
+        let roamer = RelayClient::new(&tunnel.url, Uuid::new_v4()).with_reconnect(quick_reconnect(20));
+        let (roamer, mut roamer_rx) = joined("resume", roamer).await;
+        assert!(roamer.is_connected());
+        
+        tunnel.go_down();
+        next_matching(&mut roamer_rx, |m| matches!(m, RelayMessage::Disconnected { .. })).await;
+        assert!(!roamer.is_connected());
+        
+        // Held while offline, sent once back
+        roamer.send_data(b"sent while offline".to_vec(), Some(guest.user_id)).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        tunnel.come_back();
+        
+        next_matching(&mut roamer_rx, |m| matches!(m, RelayMessage::Reconnected)).await;
+        assert!(roamer.is_connected());
+        next_matching(&mut roamer_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await;
+        
+        match next_matching(&mut guest_rx, |m| matches!(m, RelayMessage::Data { .. })).await {
+            RelayMessage::Data { from, payload, .. } => {
+                assert_eq!(from, roamer.user_id);
+                assert_eq!(payload, b"sent while offline");
+            }
+            _ => unreachable!(),
+        }
+        guest.send_data(b"welcome back".to_vec(), Some(roamer.user_id)).unwrap();
+        let back = next_matching(&mut roamer_rx, |m| matches!(m, RelayMessage::Data { .. })).await;
+        assert!(matches!(back, RelayMessage::Data { payload, .. } if payload == b"welcome back"));
+        assert_eq!(server.get_session_info("resume").await.unwrap().peer_count, 2);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_client_gives_up_after_its_attempts() {
+        let mut server = RelayServer::new();
+        let tunnel = Tunnel::to(server.start("127.0.0.1:0").await.unwrap()).await;
+        
+        let client = RelayClient::new(&tunnel.url, Uuid::new_v4()).with_reconnect(quick_reconnect(2));
+        let (client, mut rx) = joined("doomed", client).await;
+        
+        tunnel.go_down();
+        next_matching(&mut rx, |m| matches!(m, RelayMessage::Disconnected { .. })).await;
+        let closed = next_matching(&mut rx, |m| matches!(m, RelayMessage::SessionClosed { .. })).await;
+        assert!(matches!(closed, RelayMessage::SessionClosed { reason } if reason.contains("2 attempts")));
+        let ended = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert!(ended.is_none());
+        assert!(!client.is_connected());
+        assert!(client.send_data(b"too late".to_vec(), None).is_err());
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_pings_measure_latency_and_drop_silent_peers() {
+        let mut server = RelayServer::new().with_ping_interval(std::time::Duration::from_millis(50));
+        let url = format!("ws://{}", server.start("127.0.0.1:0").await.unwrap());
+        
+        let (answering, mut answering_rx) = joined("pinged", RelayClient::new(&url, Uuid::new_v4())).await;
+        
+        // A peer that joins and then never answers
+        let (silent, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut silent_tx, _silent_rx) = silent.split();
+        let silent_id = Uuid::new_v4();
+        let join = RelayMessage::Join {
+            session_id: "pinged".to_string(),
+            user_id: silent_id,
+            username: "silent".to_string(),
+            token: None,
+            public_key: None,
+            max_peers: None,
+            password_hash: None,
+            password: None,
+        };
+        silent_tx.send(Message::Text(serde_json::to_string(&join).unwrap())).await.unwrap();
+        
+        let left = next_matching(&mut answering_rx, |m| matches!(m, RelayMessage::PeerLeft { .. })).await;
+        assert!(matches!(left, RelayMessage::PeerLeft { user_id } if user_id == silent_id));
+        
+        let info = server.get_session_info("pinged").await.unwrap();
+        assert_eq!(info.peer_count, 1);
+        assert!(info.peers[0].latency_ms.is_some());
+        
+        let mut late = RelayClient::new(&url, Uuid::new_v4());
+        let mut late_rx = late.connect("pinged", "late").await.unwrap();
+        let RelayMessage::PeerList { peers, .. } = next_matching(&mut late_rx, |m| matches!(m, RelayMessage::PeerList { .. })).await else {
+            unreachable!()
+        };
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].user_id, answering.user_id);
+        assert!(peers[0].latency_ms.is_some());
+        
+        server.stop().await;
     }
 }
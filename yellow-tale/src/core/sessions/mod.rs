@@ -1,21 +1,46 @@
 //! Session Orchestration Module
 //! 
 //! Provides connection orchestration (NOT a VPN):
-//! - Session broker client
+//! - Session broker (the relay's session registry by default)
 //! - Invite code generation
 //! - Session lifecycle tracking
+//! - NAT type detection deciding between P2P and relay
 //! - Abstracted P2P attempt layer
-//! - Relay interface (stub for future infrastructure)
+//! - Relay control connection keeping participants and the host current
 //! 
 //! This is connection orchestration, NOT tunneling.
 
+pub mod nat;
+
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use tracing::info;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::core::relay::{hash_session_password, RelayBroker, RelayClient, RelayMessage, INVALID_PASSWORD};
+
+pub use nat::{NatDetector, NatReport, NatType, StunTransport, UdpStunTransport};
+
+/// Session metadata key naming the world hosted on this machine
+pub const HOSTED_WORLD_KEY: &str = "hosted_world";
+
+/// Session metadata key with the hosted server's port
+pub const SERVER_PORT_KEY: &str = "server_port";
+
+/// Session metadata key with the host's NAT type
+pub const NAT_TYPE_KEY: &str = "nat_type";
+
+/// Session metadata key set to "true" when joins need a password
+pub const PASSWORD_REQUIRED_KEY: &str = "password_required";
+
+/// How long the relay gets to accept a join
+const RELAY_JOIN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Error, Debug)]
 pub enum SessionError {
@@ -42,32 +67,33 @@ pub enum SessionError {
     
     #[error("Relay not available")]
     RelayUnavailable,
+    
+    #[error("Only the session host can do that")]
+    NotHost,
+    
+    #[error("Invalid session password")]
+    InvalidPassword,
 }
 
 /// Connection method for session
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 pub enum ConnectionMethod {
     /// Direct peer-to-peer connection
     P2P,
     /// Connection through relay server
     Relay,
     /// Hybrid - P2P with relay fallback
+    #[default]
     Hybrid,
 }
 
-impl Default for ConnectionMethod {
-    fn default() -> Self {
-        Self::Hybrid
-    }
-}
-
 /// State of a P2P connection attempt
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum P2PState {
     /// Not attempted
     Idle,
-    /// Gathering network information
-    Gathering,
+    /// Gathering network information; `nat` is set once detection finished
+    Gathering { nat: Option<NatType> },
     /// Attempting direct connection
     Connecting,
     /// Successfully connected
@@ -152,6 +178,28 @@ pub enum SessionState {
     Closed,
 }
 
+/// A change to the current session reported by the relay, pushed to the UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum SessionEvent {
+    HostChanged { session_id: Uuid, host: Participant, previous_host: Uuid },
+    ParticipantJoined { session_id: Uuid, participant: Participant },
+    ParticipantLeft { session_id: Uuid, participant_id: Uuid },
+    Closed { session_id: Uuid, reason: String },
+}
+
+impl SessionEvent {
+    /// IPC event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::HostChanged { .. } => "session_host_changed",
+            Self::ParticipantJoined { .. } => "session_participant_joined",
+            Self::ParticipantLeft { .. } => "session_participant_left",
+            Self::Closed { .. } => "session_closed",
+        }
+    }
+}
+
 /// Configuration for the session orchestrator
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -167,6 +215,14 @@ pub struct SessionConfig {
     /// Relay server addresses (stubs)
     pub relay_servers: Vec<String>,
     
+    /// STUN servers used to detect the NAT type; none skips detection
+    pub stun_servers: Vec<String>,
+    
+    /// Encrypt relayed payloads end to end so the relay only forwards
+    /// ciphertext
+    #[serde(default)]
+    pub encrypt_payloads: bool,
+    
     /// Whether to auto-accept invites
     pub auto_accept: bool,
 }
@@ -178,11 +234,28 @@ impl Default for SessionConfig {
             max_relay_hops: 3,
             p2p_timeout: Duration::from_secs(10),
             relay_servers: Vec::new(),
+            stun_servers: Vec::new(),
+            encrypt_payloads: false,
             auto_accept: false,
         }
     }
 }
 
+/// Lists sessions so others can join them by invite code
+#[async_trait]
+pub trait SessionBroker: Send + Sync {
+    /// Make a newly created session joinable
+    async fn register(&self, session: &Session) -> Result<(), SessionError>;
+    
+    /// Add `participant` to the session behind `invite_code`, returning the
+    /// session as joined. Unknown codes fail with `InvalidInviteCode` and
+    /// sessions at `max_participants` with `SessionFull`.
+    async fn join(&self, invite_code: &str, participant: Participant) -> Result<Session, SessionError>;
+    
+    /// Remove a participant; the host leaving unlists the session
+    async fn leave(&self, session_id: Uuid, participant_id: Uuid) -> Result<(), SessionError>;
+}
+
 /// Orchestrates session creation, joining, and connection management
 pub struct SessionOrchestrator {
     /// Configuration
@@ -200,9 +273,26 @@ pub struct SessionOrchestrator {
     /// Relay connection state
     relay_state: RelayState,
     
-    /// Known sessions (from broker) - will be populated by broker queries
-    #[allow(dead_code)] // Reserved for future broker integration
-    known_sessions: HashMap<String, Session>,
+    /// Where sessions are listed and invite codes resolved
+    broker: Arc<dyn SessionBroker>,
+    
+    /// Carries STUN requests for NAT detection
+    stun: Arc<dyn StunTransport>,
+    
+    /// Last NAT detection, reused until the config changes
+    nat: Option<NatReport>,
+    
+    /// Control connection to the session's room on the relay
+    relay: Option<RelayClient>,
+    
+    /// What the relay reported, waiting for `apply_relay_messages`
+    relay_messages: Option<mpsc::UnboundedReceiver<RelayMessage>>,
+    
+    /// Host who left before the relay named a successor
+    departed_host: Option<Uuid>,
+    
+    /// Relay state to go back to once a dropped connection is back
+    interrupted_relay: Option<RelayState>,
 }
 
 impl SessionOrchestrator {
@@ -214,7 +304,13 @@ impl SessionOrchestrator {
             local_participant: None,
             p2p_state: P2PState::Idle,
             relay_state: RelayState::Disconnected,
-            known_sessions: HashMap::new(),
+            broker: Arc::new(RelayBroker::default()),
+            stun: Arc::new(UdpStunTransport::new()),
+            nat: None,
+            relay: None,
+            relay_messages: None,
+            departed_host: None,
+            interrupted_relay: None,
         }
     }
     
@@ -226,6 +322,14 @@ impl SessionOrchestrator {
         }
     }
     
+    /// Create a new session orchestrator using `broker`
+    pub fn with_broker(broker: Arc<dyn SessionBroker>) -> Self {
+        Self {
+            broker,
+            ..Self::new()
+        }
+    }
+    
     /// Generate a unique invite code
     fn generate_invite_code() -> String {
         // Generate a human-readable invite code
@@ -244,11 +348,38 @@ impl SessionOrchestrator {
         code
     }
     
-    /// Create a new session as host
-    pub async fn create_session(&mut self, name: String, max_participants: usize) -> Result<Session, SessionError> {
+    /// Create a new session as host. With a `password`, joins go through
+    /// the relay, which turns away anyone who doesn't give it.
+    pub async fn create_session(&mut self, name: String, max_participants: usize, password: Option<&str>) -> Result<Session, SessionError> {
+        self.open_session(name, max_participants, HashMap::new(), password).await
+    }
+    
+    /// Create a session for a server hosted on this machine, so friends can join it by invite code
+    pub async fn host_server(&mut self, name: String, max_participants: usize, world_name: &str, port: u16) -> Result<Session, SessionError> {
+        let metadata = HashMap::from([
+            (HOSTED_WORLD_KEY.to_string(), world_name.to_string()),
+            (SERVER_PORT_KEY.to_string(), port.to_string()),
+        ]);
+        self.open_session(name, max_participants, metadata, None).await
+    }
+    
+    async fn open_session(&mut self, name: String, max_participants: usize, metadata: HashMap<String, String>, password: Option<&str>) -> Result<Session, SessionError> {
         if self.current_session.is_some() {
             return Err(SessionError::AlreadyInSession);
         }
+        // Only the relay can check passwords
+        if password.is_some() && self.config.relay_servers.is_empty() {
+            return Err(SessionError::RelayUnavailable);
+        }
+        
+        let mut metadata = metadata;
+        if password.is_some() {
+            metadata.insert(PASSWORD_REQUIRED_KEY.to_string(), "true".to_string());
+        }
+        let nat = self.nat_type().await;
+        if nat != NatType::Unknown {
+            metadata.insert(NAT_TYPE_KEY.to_string(), nat.to_string());
+        }
         
         let host = Participant {
             id: Uuid::new_v4(),
@@ -267,37 +398,50 @@ impl SessionOrchestrator {
             max_participants,
             state: SessionState::Open,
             created_at: Utc::now(),
-            metadata: HashMap::new(),
+            metadata,
         };
         
+        self.broker.register(&session).await?;
         info!("Created session {} with invite code {}", session.id, session.invite_code);
         
+        if password.is_some() {
+            // An unprotected room would let anyone in
+            if let Err(e) = self.connect_relay(&session, &host, password).await {
+                if let Err(leave) = self.broker.leave(session.id, host.id).await {
+                    warn!("Failed to unlist session {} after connecting failed: {}", session.id, leave);
+                }
+                return Err(e);
+            }
+        } else if !self.config.relay_servers.is_empty() {
+            // Without the relay nobody is told about joins; the session still works
+            if let Err(e) = self.connect_relay(&session, &host, None).await {
+                warn!("Session {} has no relay connection: {}", session.id, e);
+                self.relay_state = RelayState::Disconnected;
+            }
+        }
+        
         self.local_participant = Some(host);
         self.current_session = Some(session.clone());
         
         Ok(session)
     }
     
-    /// Join a session using an invite code
-    pub async fn join_session(&mut self, invite_code: &str, name: String) -> Result<Session, SessionError> {
+    /// World hosted by the current session, if it is one
+    pub fn hosted_world(&self) -> Option<&str> {
+        self.current_session.as_ref()
+            .and_then(|s| s.metadata.get(HOSTED_WORLD_KEY))
+            .map(String::as_str)
+    }
+    
+    /// Join a session using an invite code, giving `password` if the
+    /// session has one
+    pub async fn join_session(&mut self, invite_code: &str, name: String, password: Option<&str>) -> Result<Session, SessionError> {
         if self.current_session.is_some() {
             return Err(SessionError::AlreadyInSession);
         }
         
-        // In a real implementation, this would:
-        // 1. Contact the session broker with the invite code
-        // 2. Get session details and connection info
-        // 3. Attempt P2P connection
-        // 4. Fall back to relay if needed
-        
-        // For now, we'll create a stub session
         info!("Attempting to join session with code: {}", invite_code);
         
-        // Simulate broker lookup
-        if invite_code.len() != 14 {
-            return Err(SessionError::InvalidInviteCode(invite_code.to_string()));
-        }
-        
         let participant = Participant {
             id: Uuid::new_v4(),
             name,
@@ -307,31 +451,80 @@ impl SessionOrchestrator {
             latency_ms: None,
         };
         
+        let session = self.broker.join(invite_code, participant.clone()).await?;
+        
+        if let Err(e) = self.connect(&session, &participant, password).await {
+            self.disconnect_relay();
+            self.p2p_state = P2PState::Idle;
+            self.relay_state = RelayState::Disconnected;
+            if let Err(leave) = self.broker.leave(session.id, participant.id).await {
+                warn!("Failed to leave session {} after connecting failed: {}", session.id, leave);
+            }
+            return Err(e);
+        }
+        
+        info!("Joined session {} as {}", session.id, participant.name);
         self.local_participant = Some(participant);
+        self.current_session = Some(session.clone());
         
-        // Attempt connection
-        self.attempt_p2p_connection().await?;
+        Ok(session)
+    }
+    
+    /// Connect to a joined session: directly unless only relay is allowed
+    /// or the two NATs can't be punched through, falling back to relay if
+    /// that fails and the config permits it. Password-protected sessions
+    /// always need the relay to let the participant in.
+    async fn connect(&mut self, session: &Session, participant: &Participant, password: Option<&str>) -> Result<(), SessionError> {
+        let password_required = session.metadata.get(PASSWORD_REQUIRED_KEY).is_some_and(|v| v == "true");
+        if password_required && self.config.relay_servers.is_empty() {
+            return Err(SessionError::RelayUnavailable);
+        }
         
-        // If P2P fails and we allow relay, try that
-        if matches!(self.p2p_state, P2PState::Failed { .. }) {
-            if self.config.preferred_method != ConnectionMethod::P2P {
-                self.connect_relay().await?;
+        if self.config.preferred_method != ConnectionMethod::Relay {
+            let host_nat = session.metadata.get(NAT_TYPE_KEY)
+                .and_then(|nat| NatType::parse(nat))
+                .unwrap_or(NatType::Unknown);
+            self.attempt_p2p_connection(host_nat).await?;
+            let P2PState::Failed { reason } = &self.p2p_state else {
+                // Session changes still come through the relay
+                if password_required {
+                    self.connect_relay(session, participant, password).await?;
+                } else if !self.config.relay_servers.is_empty() {
+                    if let Err(e) = self.connect_relay(session, participant, None).await {
+                        warn!("Session {} has no relay connection: {}", session.id, e);
+                        self.relay_state = RelayState::Disconnected;
+                    }
+                }
+                return Ok(());
+            };
+            if self.config.preferred_method == ConnectionMethod::P2P {
+                return Err(SessionError::P2PFailed(reason.clone()));
             }
         }
         
-        // This is a stub - real implementation would get session from broker
-        Err(SessionError::ConnectionFailed("Session broker not implemented".to_string()))
+        self.connect_relay(session, participant, password).await?;
+        self.relay_state = RelayState::Relaying { session_id: session.id.to_string() };
+        Ok(())
     }
     
-    /// Attempt a P2P connection
-    async fn attempt_p2p_connection(&mut self) -> Result<(), SessionError> {
+    /// Attempt a P2P connection to a host behind `host_nat`
+    async fn attempt_p2p_connection(&mut self, host_nat: NatType) -> Result<(), SessionError> {
         info!("Attempting P2P connection...");
         
-        self.p2p_state = P2PState::Gathering;
+        self.p2p_state = P2PState::Gathering { nat: None };
+        let local_nat = self.nat_type().await;
+        self.p2p_state = P2PState::Gathering { nat: Some(local_nat) };
+        
+        if !local_nat.compatible_with(host_nat) {
+            info!("Skipping P2P: {} NAT can't reach a host behind {} NAT", local_nat, host_nat);
+            self.p2p_state = P2PState::Failed {
+                reason: format!("{} NAT can't connect directly to {} NAT", local_nat, host_nat),
+            };
+            return Ok(());
+        }
         
         // In a real implementation:
-        // 1. Gather local network info (NAT type, local/public IPs)
-        // 2. Exchange connection info via signaling server
+        // 1. Exchange connection info via signaling server
         // 3. Attempt hole punching
         // 4. Establish direct connection
         
@@ -347,37 +540,225 @@ impl SessionOrchestrator {
         Ok(())
     }
     
-    /// Connect to a relay server
-    async fn connect_relay(&mut self) -> Result<(), SessionError> {
-        if self.config.relay_servers.is_empty() {
+    /// Join the session's room on the first relay server as `participant`,
+    /// waiting for the relay to accept. The host's `password` protects the
+    /// room; anyone else's is checked against it.
+    async fn connect_relay(&mut self, session: &Session, participant: &Participant, password: Option<&str>) -> Result<(), SessionError> {
+        let Some(relay_addr) = self.config.relay_servers.first().cloned() else {
             return Err(SessionError::RelayUnavailable);
-        }
+        };
         
-        info!("Connecting to relay server...");
+        info!("Connecting to relay server {}...", relay_addr);
         
         self.relay_state = RelayState::Connecting;
         
-        // In a real implementation:
-        // 1. Connect to relay server via WebSocket or QUIC
-        // 2. Authenticate and register
-        // 3. Join session room on relay
+        let url = if relay_addr.contains("://") { relay_addr.clone() } else { format!("ws://{}", relay_addr) };
+        let mut client = RelayClient::new(&url, participant.id);
+        if self.config.encrypt_payloads {
+            client.enable_encryption();
+        }
+        if participant.id == session.host.id {
+            client = client.with_max_peers(session.max_participants);
+            if let Some(password) = password {
+                let hash = hash_session_password(password)
+                    .map_err(|e| SessionError::ConnectionFailed(e.to_string()))?;
+                // Without relay auth the host's id is only a claim, so a
+                // host that reconnects has to give the password too
+                client = client.with_password_hash(hash).with_password(password);
+            }
+        } else if let Some(password) = password {
+            client = client.with_password(password);
+        }
+        let mut messages = client.connect(&session.id.to_string(), &participant.name).await
+            .map_err(|e| SessionError::ConnectionFailed(e.to_string()))?;
         
-        let relay_addr = self.config.relay_servers.first().cloned().unwrap();
+        // The peer list is the relay's answer to a join it accepted
+        let joined = tokio::time::timeout(RELAY_JOIN_TIMEOUT, async {
+            loop {
+                match messages.recv().await {
+                    Some(RelayMessage::PeerList { .. }) => return Ok(()),
+                    Some(RelayMessage::Error { message }) if message == INVALID_PASSWORD => {
+                        return Err(SessionError::InvalidPassword);
+                    }
+                    Some(RelayMessage::Error { message }) => return Err(SessionError::ConnectionFailed(message)),
+                    Some(_) => continue,
+                    None => return Err(SessionError::ConnectionFailed("Relay closed the connection".to_string())),
+                }
+            }
+        }).await.unwrap_or_else(|_| Err(SessionError::ConnectionFailed("Relay did not answer the join".to_string())));
+        if let Err(e) = joined {
+            client.disconnect();
+            self.relay_state = RelayState::Disconnected;
+            return Err(e);
+        }
+        
+        self.relay = Some(client);
+        self.relay_messages = Some(messages);
         self.relay_state = RelayState::Connected { relay_addr };
         
-        info!("Connected to relay (stub)");
+        info!("Connected to relay");
         Ok(())
     }
     
+    fn disconnect_relay(&mut self) {
+        if let Some(mut relay) = self.relay.take() {
+            relay.disconnect();
+        }
+        self.relay_messages = None;
+        self.departed_host = None;
+        self.interrupted_relay = None;
+    }
+    
+    /// Apply what the relay reported since the last call to the current
+    /// session, returning the changes
+    pub async fn apply_relay_messages(&mut self) -> Vec<SessionEvent> {
+        let mut messages = Vec::new();
+        if let Some(receiver) = self.relay_messages.as_mut() {
+            while let Ok(message) = receiver.try_recv() {
+                messages.push(message);
+            }
+        }
+        
+        let mut events = Vec::new();
+        for message in messages {
+            events.extend(self.apply_relay_message(message).await);
+        }
+        events
+    }
+    
+    async fn apply_relay_message(&mut self, message: RelayMessage) -> Option<SessionEvent> {
+        let session = self.current_session.as_mut()?;
+        let session_id = session.id;
+        
+        match message {
+            RelayMessage::PeerJoined { peer } => {
+                if peer.user_id == session.host.id || session.participants.iter().any(|p| p.id == peer.user_id) {
+                    return None;
+                }
+                let participant = Participant {
+                    id: peer.user_id,
+                    name: peer.username,
+                    connection: ConnectionMethod::Relay,
+                    p2p_state: P2PState::Idle,
+                    joined_at: peer.joined_at,
+                    latency_ms: peer.latency_ms,
+                };
+                session.participants.push(participant.clone());
+                Some(SessionEvent::ParticipantJoined { session_id, participant })
+            }
+            
+            RelayMessage::PeerLeft { user_id } => {
+                if user_id == session.host.id {
+                    // Stays host until the relay names the next one
+                    self.departed_host = Some(user_id);
+                } else {
+                    let before = session.participants.len();
+                    session.participants.retain(|p| p.id != user_id);
+                    if session.participants.len() == before {
+                        return None;
+                    }
+                }
+                Some(SessionEvent::ParticipantLeft { session_id, participant_id: user_id })
+            }
+            
+            RelayMessage::HostMigration { new_host } => {
+                let index = session.participants.iter().position(|p| p.id == new_host)?;
+                let host = session.participants.remove(index);
+                let previous = std::mem::replace(&mut session.host, host);
+                if self.departed_host.take() != Some(previous.id) {
+                    session.participants.push(previous.clone());
+                }
+                info!("{} is now hosting session {}", session.host.name, session_id);
+                
+                let event = SessionEvent::HostChanged { session_id, host: session.host.clone(), previous_host: previous.id };
+                // The old host's leaving unlisted the session; relisting it
+                // keeps the invite code working
+                if self.local_participant.as_ref().is_some_and(|p| p.id == new_host) {
+                    let session = session.clone();
+                    if let Err(e) = self.broker.register(&session).await {
+                        warn!("Could not relist session {} after becoming host: {}", session_id, e);
+                    }
+                }
+                Some(event)
+            }
+            
+            RelayMessage::Disconnected { reason } => {
+                warn!("Relay connection for session {} dropped, reconnecting: {}", session_id, reason);
+                if self.interrupted_relay.is_none() {
+                    self.interrupted_relay = Some(std::mem::replace(&mut self.relay_state, RelayState::Connecting));
+                }
+                None
+            }
+            
+            RelayMessage::Reconnected => {
+                info!("Relay connection for session {} is back", session_id);
+                if let Some(state) = self.interrupted_relay.take() {
+                    self.relay_state = state;
+                }
+                None
+            }
+            
+            RelayMessage::SessionClosed { reason } => {
+                info!("Session {} closed by the relay: {}", session_id, reason);
+                self.disconnect_relay();
+                self.p2p_state = P2PState::Idle;
+                self.relay_state = RelayState::Disconnected;
+                self.current_session = None;
+                self.local_participant = None;
+                Some(SessionEvent::Closed { session_id, reason })
+            }
+            
+            _ => None,
+        }
+    }
+    
+    /// Hand hosting to another participant. The session changes once the
+    /// relay confirms with a host migration.
+    pub fn transfer_host(&self, participant_id: Uuid) -> Result<(), SessionError> {
+        let session = self.current_session.as_ref().ok_or(SessionError::NotInSession)?;
+        if self.local_participant.as_ref().map(|p| p.id) != Some(session.host.id) {
+            return Err(SessionError::NotHost);
+        }
+        if !session.participants.iter().any(|p| p.id == participant_id) {
+            return Err(SessionError::NotFound(participant_id.to_string()));
+        }
+        let relay = self.relay.as_ref().ok_or(SessionError::RelayUnavailable)?;
+        relay.transfer_host(participant_id)
+            .map_err(|e| SessionError::ConnectionFailed(e.to_string()))
+    }
+    
+    /// Detect the NAT type against the configured STUN servers, replacing
+    /// the remembered result
+    pub async fn detect_nat(&mut self) -> NatReport {
+        let report = NatDetector::new(&self.config.stun_servers, self.stun.as_ref()).detect().await;
+        self.nat = Some(report.clone());
+        report
+    }
+    
+    /// NAT type from the last detection, detecting first if there was none
+    async fn nat_type(&mut self) -> NatType {
+        match &self.nat {
+            Some(report) => report.nat_type,
+            None => self.detect_nat().await.nat_type,
+        }
+    }
+    
     /// Leave the current session
     pub async fn leave_session(&mut self) -> Result<(), SessionError> {
-        if self.current_session.is_none() {
+        let Some(session) = &self.current_session else {
             return Err(SessionError::NotInSession);
-        }
+        };
         
         info!("Leaving session...");
         
+        if let Some(local) = &self.local_participant {
+            if let Err(e) = self.broker.leave(session.id, local.id).await {
+                warn!("Failed to leave session {} on the broker: {}", session.id, e);
+            }
+        }
+        
         // Clean up connections
+        self.disconnect_relay();
         self.p2p_state = P2PState::Idle;
         self.relay_state = RelayState::Disconnected;
         self.current_session = None;
@@ -401,8 +782,22 @@ impl SessionOrchestrator {
         (self.p2p_state.clone(), self.relay_state.clone())
     }
     
+    /// Replace the session broker
+    pub fn set_broker(&mut self, broker: Arc<dyn SessionBroker>) {
+        self.broker = broker;
+    }
+    
+    /// Replace how STUN requests are sent
+    pub fn set_stun_transport(&mut self, stun: Arc<dyn StunTransport>) {
+        self.stun = stun;
+        self.nat = None;
+    }
+    
     /// Update configuration
     pub fn set_config(&mut self, config: SessionConfig) {
+        if config.stun_servers != self.config.stun_servers {
+            self.nat = None;
+        }
         self.config = config;
     }
     
@@ -425,13 +820,274 @@ mod tests {
     #[tokio::test]
     async fn test_create_session() {
         let mut orchestrator = SessionOrchestrator::new();
-        let session = orchestrator.create_session("TestHost".to_string(), 8).await.unwrap();
+        let session = orchestrator.create_session("TestHost".to_string(), 8, None).await.unwrap();
         
         assert_eq!(session.host.name, "TestHost");
         assert_eq!(session.max_participants, 8);
         assert_eq!(session.invite_code.len(), 14);
     }
     
+    #[tokio::test]
+    async fn test_hosted_server_session() {
+        let mut orchestrator = SessionOrchestrator::new();
+        let session = orchestrator.host_server("TestHost".to_string(), 8, "island", 25570).await.unwrap();
+        
+        assert_eq!(session.metadata.get(SERVER_PORT_KEY).map(String::as_str), Some("25570"));
+        assert_eq!(orchestrator.hosted_world(), Some("island"));
+        assert_eq!(orchestrator.get_invite_code(), Some(session.invite_code.as_str()));
+        
+        orchestrator.leave_session().await.unwrap();
+        assert_eq!(orchestrator.hosted_world(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_join_session_through_broker() {
+        let broker: Arc<dyn SessionBroker> = Arc::new(crate::core::relay::RelayServer::new().broker());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        let session = host.create_session("TestHost".to_string(), 2, None).await.unwrap();
+        
+        let mut guest = SessionOrchestrator::with_broker(broker.clone());
+        let joined = guest.join_session(&session.invite_code.to_lowercase(), "Guest".to_string(), None).await.unwrap();
+        assert_eq!(joined.id, session.id);
+        assert_eq!(joined.participants.len(), 1);
+        assert_eq!(joined.participants[0].name, "Guest");
+        assert_eq!(guest.get_invite_code(), Some(session.invite_code.as_str()));
+        assert!(matches!(guest.connection_state().0, P2PState::Connected { .. }));
+        
+        // Host plus one guest fills a session of two
+        let mut third = SessionOrchestrator::with_broker(broker.clone());
+        let full = third.join_session(&session.invite_code, "Third".to_string(), None).await;
+        assert!(matches!(full, Err(SessionError::SessionFull(_))));
+        assert!(third.current_session().is_none());
+        
+        guest.leave_session().await.unwrap();
+        third.join_session(&session.invite_code, "Third".to_string(), None).await.unwrap();
+        
+        // The host leaving unlists the session
+        host.leave_session().await.unwrap();
+        let mut late = SessionOrchestrator::with_broker(broker);
+        let unknown = late.join_session(&session.invite_code, "Late".to_string(), None).await;
+        assert!(matches!(unknown, Err(SessionError::InvalidInviteCode(_))));
+    }
+    
+    async fn local_relay() -> (crate::core::relay::RelayServer, SessionConfig) {
+        let mut relay = crate::core::relay::RelayServer::new();
+        let addr = relay.start("127.0.0.1:0").await.unwrap();
+        (relay, SessionConfig { relay_servers: vec![addr.to_string()], ..Default::default() })
+    }
+    
+    /// Apply relay messages until `count` session changes came of them
+    async fn next_events(orchestrator: &mut SessionOrchestrator, count: usize) -> Vec<SessionEvent> {
+        let mut events = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while events.len() < count {
+                events.extend(orchestrator.apply_relay_messages().await);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("timed out waiting for session events");
+        events
+    }
+    
+    #[tokio::test]
+    async fn test_relay_only_join_needs_a_relay() {
+        let (_relay, relay_config) = local_relay().await;
+        let broker: Arc<dyn SessionBroker> = Arc::new(RelayBroker::default());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        let session = host.create_session("TestHost".to_string(), 8, None).await.unwrap();
+        
+        let mut guest = SessionOrchestrator::with_broker(broker.clone());
+        guest.set_config(SessionConfig { preferred_method: ConnectionMethod::Relay, ..Default::default() });
+        let result = guest.join_session(&session.invite_code, "Guest".to_string(), None).await;
+        assert!(matches!(result, Err(SessionError::RelayUnavailable)));
+        
+        guest.set_config(SessionConfig { preferred_method: ConnectionMethod::Relay, ..relay_config });
+        let joined = guest.join_session(&session.invite_code, "Guest".to_string(), None).await.unwrap();
+        // The failed attempt didn't leave a stale participant behind
+        assert_eq!(joined.participants.len(), 1);
+        assert!(matches!(guest.connection_state().1, RelayState::Relaying { session_id } if session_id == session.id.to_string()));
+    }
+    
+    #[tokio::test]
+    async fn test_incompatible_nats_go_straight_to_relay() {
+        use nat::tests::FakeNat;
+        
+        let (_relay, relay_config) = local_relay().await;
+        let servers = ["198.51.100.1:3478", "198.51.100.2:3478"];
+        let config = SessionConfig {
+            stun_servers: servers.iter().map(|s| s.to_string()).collect(),
+            ..relay_config
+        };
+        let broker: Arc<dyn SessionBroker> = Arc::new(RelayBroker::default());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        host.set_config(config.clone());
+        host.set_stun_transport(Arc::new(FakeNat::new(NatType::Symmetric, &servers)));
+        let session = host.create_session("TestHost".to_string(), 8, None).await.unwrap();
+        assert_eq!(session.metadata.get(NAT_TYPE_KEY).map(String::as_str), Some("symmetric"));
+        
+        let mut guest = SessionOrchestrator::with_broker(broker.clone());
+        guest.set_config(config.clone());
+        guest.set_stun_transport(Arc::new(FakeNat::new(NatType::Restricted, &servers)));
+        guest.join_session(&session.invite_code, "Guest".to_string(), None).await.unwrap();
+        let (p2p, relay) = guest.connection_state();
+        assert!(matches!(p2p, P2PState::Failed { .. }));
+        assert!(matches!(relay, RelayState::Relaying { .. }));
+        
+        // Behind a full cone NAT the guest can reach even a symmetric host
+        let mut open = SessionOrchestrator::with_broker(broker.clone());
+        open.set_config(config.clone());
+        open.set_stun_transport(Arc::new(FakeNat::new(NatType::FullCone, &servers)));
+        assert_eq!(open.detect_nat().await.nat_type, NatType::FullCone);
+        open.join_session(&session.invite_code, "Open".to_string(), None).await.unwrap();
+        assert!(matches!(open.connection_state(), (P2PState::Connected { .. }, RelayState::Connected { .. })));
+        
+        // Without a relay to fall back on, P2P-only joins report why
+        let mut strict = SessionOrchestrator::with_broker(broker);
+        strict.set_config(SessionConfig { preferred_method: ConnectionMethod::P2P, ..config });
+        strict.set_stun_transport(Arc::new(FakeNat::new(NatType::Symmetric, &servers)));
+        let result = strict.join_session(&session.invite_code, "Strict".to_string(), None).await;
+        assert!(matches!(result, Err(SessionError::P2PFailed(reason)) if reason.contains("symmetric")));
+    }
+    
+    #[tokio::test]
+    async fn test_guest_takes_over_when_the_host_leaves() {
+        let (relay, config) = local_relay().await;
+        let broker: Arc<dyn SessionBroker> = Arc::new(relay.broker());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        host.set_config(config.clone());
+        let session = host.create_session("TestHost".to_string(), 4, None).await.unwrap();
+        
+        let mut guest = SessionOrchestrator::with_broker(broker.clone());
+        guest.set_config(config.clone());
+        let guest_id = guest.join_session(&session.invite_code, "Guest".to_string(), None).await.unwrap().participants[0].id;
+        
+        let events = next_events(&mut host, 1).await;
+        assert!(matches!(&events[0], SessionEvent::ParticipantJoined { participant, .. } if participant.id == guest_id));
+        assert_eq!(host.current_session().unwrap().participants.len(), 1);
+        
+        host.leave_session().await.unwrap();
+        let events = next_events(&mut guest, 2).await;
+        assert_eq!(events.iter().map(SessionEvent::name).collect::<Vec<_>>(), ["session_participant_left", "session_host_changed"]);
+        let current = guest.current_session().unwrap();
+        assert_eq!(current.host.id, guest_id);
+        assert!(current.participants.is_empty());
+        assert_eq!(guest.get_invite_code(), Some(session.invite_code.as_str()));
+        
+        // The new host relisted the session, so its invite code still works
+        let mut late = SessionOrchestrator::with_broker(broker);
+        late.set_config(config);
+        let rejoined = late.join_session(&session.invite_code, "Late".to_string(), None).await.unwrap();
+        assert_eq!(rejoined.host.id, guest_id);
+    }
+    
+    #[tokio::test]
+    async fn test_host_hands_off_hosting() {
+        let (relay, config) = local_relay().await;
+        let broker: Arc<dyn SessionBroker> = Arc::new(relay.broker());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        host.set_config(config.clone());
+        let session = host.create_session("TestHost".to_string(), 4, None).await.unwrap();
+        let host_id = session.host.id;
+        
+        let mut guest = SessionOrchestrator::with_broker(broker);
+        guest.set_config(config);
+        let guest_id = guest.join_session(&session.invite_code, "Guest".to_string(), None).await.unwrap().participants[0].id;
+        next_events(&mut host, 1).await;
+        
+        assert!(matches!(guest.transfer_host(host_id), Err(SessionError::NotHost)));
+        assert!(matches!(host.transfer_host(Uuid::new_v4()), Err(SessionError::NotFound(_))));
+        host.transfer_host(guest_id).unwrap();
+        
+        for side in [&mut host, &mut guest] {
+            let events = next_events(side, 1).await;
+            assert!(matches!(&events[0], SessionEvent::HostChanged { host, previous_host, .. } if host.id == guest_id && *previous_host == host_id));
+            let current = side.current_session().unwrap();
+            assert_eq!(current.host.id, guest_id);
+            assert_eq!(current.participants.iter().map(|p| p.id).collect::<Vec<_>>(), [host_id]);
+        }
+        
+        // Only the new host can hand it back
+        assert!(matches!(host.transfer_host(guest_id), Err(SessionError::NotHost)));
+        guest.transfer_host(host_id).unwrap();
+        assert!(matches!(&next_events(&mut host, 1).await[0], SessionEvent::HostChanged { host, .. } if host.id == host_id));
+    }
+    
+    #[tokio::test]
+    async fn test_relay_state_follows_reconnects() {
+        let (_relay, config) = local_relay().await;
+        let mut host = SessionOrchestrator::new();
+        host.set_config(config);
+        host.create_session("TestHost".to_string(), 4, None).await.unwrap();
+        assert!(matches!(host.connection_state().1, RelayState::Connected { .. }));
+        
+        host.apply_relay_message(RelayMessage::Disconnected { reason: "Connection reset".to_string() }).await;
+        assert!(matches!(host.connection_state().1, RelayState::Connecting));
+        host.apply_relay_message(RelayMessage::Reconnected).await;
+        assert!(matches!(host.connection_state().1, RelayState::Connected { .. }));
+        
+        // Giving up ends the session
+        host.apply_relay_message(RelayMessage::Disconnected { reason: "Connection reset".to_string() }).await;
+        let closed = host.apply_relay_message(RelayMessage::SessionClosed { reason: "Lost the connection".to_string() }).await;
+        assert!(matches!(closed, Some(SessionEvent::Closed { .. })));
+        assert!(host.current_session().is_none());
+        assert!(matches!(host.connection_state().1, RelayState::Disconnected));
+    }
+    
+    #[tokio::test]
+    async fn test_private_session_needs_the_password() {
+        let broker: Arc<dyn SessionBroker> = Arc::new(RelayBroker::default());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        let result = host.create_session("TestHost".to_string(), 4, Some("hunter2")).await;
+        assert!(matches!(result, Err(SessionError::RelayUnavailable)));
+        
+        let (relay, config) = local_relay().await;
+        host.set_config(config.clone());
+        let session = host.create_session("TestHost".to_string(), 4, Some("hunter2")).await.unwrap();
+        // Invitees learn a password is needed, never what it is
+        assert_eq!(session.metadata.get(PASSWORD_REQUIRED_KEY).map(String::as_str), Some("true"));
+        assert!(!session.metadata.values().any(|v| v.contains("hunter2")));
+        assert!(relay.get_session_info(&session.id.to_string()).await.unwrap().password_required);
+        
+        let mut guest = SessionOrchestrator::with_broker(broker.clone());
+        guest.set_config(config.clone());
+        for password in [None, Some("hunter3")] {
+            let result = guest.join_session(&session.invite_code, "Guest".to_string(), password).await;
+            assert!(matches!(result, Err(SessionError::InvalidPassword)));
+            assert!(guest.current_session().is_none());
+        }
+        
+        let joined = guest.join_session(&session.invite_code, "Guest".to_string(), Some("hunter2")).await.unwrap();
+        // The turned away attempts didn't take up places
+        assert_eq!(joined.participants.len(), 1);
+        assert!(matches!(&next_events(&mut host, 1).await[0], SessionEvent::ParticipantJoined { participant, .. } if participant.name == "Guest"));
+    }
+    
+    #[tokio::test]
+    async fn test_encrypted_sessions_share_a_key() {
+        let (relay, config) = local_relay().await;
+        let config = SessionConfig { encrypt_payloads: true, ..config };
+        let broker: Arc<dyn SessionBroker> = Arc::new(RelayBroker::default());
+        let mut host = SessionOrchestrator::with_broker(broker.clone());
+        host.set_config(config.clone());
+        let session = host.create_session("TestHost".to_string(), 4, None).await.unwrap();
+        
+        let mut guest = SessionOrchestrator::with_broker(broker.clone());
+        guest.set_config(config);
+        guest.join_session(&session.invite_code, "Guest".to_string(), None).await.unwrap();
+        next_events(&mut host, 1).await;
+        
+        let info = relay.get_session_info(&session.id.to_string()).await.unwrap();
+        assert_eq!(info.peers.len(), 2);
+        assert!(info.peers.iter().all(|p| p.encryption));
+        
+        // The guest is handed the host's key
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while guest.relay.as_ref().and_then(RelayClient::session_key_id).is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("the session key never arrived");
+        assert_eq!(host.relay.as_ref().unwrap().session_key_id(), Some(1));
+    }
+    
     #[test]
     fn test_invite_code_format() {
         let code = SessionOrchestrator::generate_invite_code();
@@ -0,0 +1,502 @@
+//! NAT type detection
+//!
+//! Sends STUN binding requests (RFC 5389) to the configured servers from one
+//! UDP socket and compares the public addresses they report:
+//! - The mapped address equals the local one: no NAT in the way (open)
+//! - Two servers see different mappings: symmetric NAT, hole punching fails
+//! - A server can answer from another address (RFC 3489 `CHANGE-REQUEST`):
+//!   full cone, anyone can reach the mapping
+//! - Otherwise the NAT only lets in replies from addresses already contacted
+//!   (restricted)
+//!
+//! Most public STUN servers ignore `CHANGE-REQUEST`, so a full cone NAT
+//! behind them is reported as restricted; that only makes P2P less likely to
+//! be tried, never more.
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+/// Public STUN servers used when the config names none
+pub const DEFAULT_STUN_SERVERS: &[&str] = &["stun.l.google.com:19302", "stun.cloudflare.com:3478"];
+
+/// How long one binding request may take, retransmissions included
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Sends of one request before giving up on it
+const TRANSMISSIONS: u32 = 3;
+
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const HEADER_LEN: usize = 20;
+
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_CHANGE_REQUEST: u16 = 0x0003;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+const CHANGE_IP: u32 = 0x04;
+const CHANGE_PORT: u32 = 0x02;
+
+/// How reachable this machine is from the internet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NatType {
+    /// Public address, nothing to punch through
+    Open,
+    /// Anyone can send to the mapped address
+    FullCone,
+    /// Only addresses this machine sent to can reply
+    Restricted,
+    /// A new mapping per destination
+    Symmetric,
+    /// No STUN server answered, UDP is probably filtered
+    Blocked,
+    /// Not detected, no STUN servers are configured
+    Unknown,
+}
+
+impl NatType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::FullCone => "full_cone",
+            Self::Restricted => "restricted",
+            Self::Symmetric => "symmetric",
+            Self::Blocked => "blocked",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [Self::Open, Self::FullCone, Self::Restricted, Self::Symmetric, Self::Blocked, Self::Unknown]
+            .into_iter()
+            .find(|nat| nat.as_str() == s)
+    }
+
+    /// Whether a direct connection between the two sides is worth trying.
+    /// An undetected side is given the benefit of the doubt.
+    pub fn compatible_with(self, other: NatType) -> bool {
+        use NatType::*;
+        match (self, other) {
+            (Blocked, _) | (_, Blocked) => false,
+            (Unknown, _) | (_, Unknown) => true,
+            (Open | FullCone, _) | (_, Open | FullCone) => true,
+            (Restricted, Restricted) => true,
+            (Symmetric, _) | (_, Symmetric) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for NatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Outcome of a detection, as returned by `detect_nat`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NatReport {
+    pub nat_type: NatType,
+    /// Public address as seen by the first server that answered
+    pub external_ip: Option<String>,
+    pub external_port: Option<u16>,
+    /// Address of the interface the requests left from
+    pub internal_ip: Option<String>,
+    /// Server the public address came from
+    pub stun_server: Option<String>,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl NatReport {
+    fn undetected(nat_type: NatType) -> Self {
+        Self {
+            nat_type,
+            external_ip: None,
+            external_port: None,
+            internal_ip: None,
+            stun_server: None,
+            detected_at: Utc::now(),
+        }
+    }
+}
+
+/// Carries STUN messages; the detector builds and reads them
+#[async_trait]
+pub trait StunTransport: Send + Sync {
+    /// Send `request` to `server` and wait for the reply with the same
+    /// transaction id, returning it with the address it came from. Every
+    /// request of one detection must leave from the same local port.
+    async fn exchange(&self, server: SocketAddr, request: &[u8]) -> io::Result<Option<(Vec<u8>, SocketAddr)>>;
+
+    /// Local address requests to `server` leave from
+    async fn local_addr(&self, server: SocketAddr) -> io::Result<SocketAddr>;
+}
+
+/// Sends requests from one IPv4 UDP socket, bound on first use
+#[derive(Default)]
+pub struct UdpStunTransport {
+    socket: Mutex<Option<UdpSocket>>,
+}
+
+impl UdpStunTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StunTransport for UdpStunTransport {
+    async fn exchange(&self, server: SocketAddr, request: &[u8]) -> io::Result<Option<(Vec<u8>, SocketAddr)>> {
+        let mut socket = self.socket.lock().await;
+        if socket.is_none() {
+            *socket = Some(UdpSocket::bind(("0.0.0.0", 0)).await?);
+        }
+        let socket = socket.as_ref().unwrap();
+
+        let mut buf = [0u8; 1024];
+        for _ in 0..TRANSMISSIONS {
+            socket.send_to(request, server).await?;
+            let wait = tokio::time::sleep(REQUEST_TIMEOUT / TRANSMISSIONS);
+            tokio::pin!(wait);
+            loop {
+                tokio::select! {
+                    _ = &mut wait => break,
+                    received = socket.recv_from(&mut buf) => {
+                        let (len, from) = received?;
+                        // Late replies to earlier requests are dropped
+                        if len >= HEADER_LEN && buf[8..HEADER_LEN] == request[8..HEADER_LEN] {
+                            return Ok(Some((buf[..len].to_vec(), from)));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn local_addr(&self, server: SocketAddr) -> io::Result<SocketAddr> {
+        let port = match self.socket.lock().await.as_ref() {
+            Some(socket) => socket.local_addr()?.port(),
+            None => 0,
+        };
+        // Connecting a UDP socket sends nothing but picks the outgoing interface
+        let probe = UdpSocket::bind(("0.0.0.0", 0)).await?;
+        probe.connect(server).await?;
+        Ok(SocketAddr::new(probe.local_addr()?.ip(), port))
+    }
+}
+
+/// What a binding request asks the server to change when replying
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Change {
+    ip: bool,
+    port: bool,
+}
+
+/// Binding request with a fresh transaction id
+fn binding_request(change: Change) -> Vec<u8> {
+    let flags = if change.ip { CHANGE_IP } else { 0 } | if change.port { CHANGE_PORT } else { 0 };
+    let attributes = if flags != 0 { 8 } else { 0 };
+
+    let mut message = Vec::with_capacity(HEADER_LEN + attributes);
+    message.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    message.extend_from_slice(&(attributes as u16).to_be_bytes());
+    message.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    message.extend_from_slice(&rand::random::<[u8; 12]>());
+    if flags != 0 {
+        message.extend_from_slice(&ATTR_CHANGE_REQUEST.to_be_bytes());
+        message.extend_from_slice(&4u16.to_be_bytes());
+        message.extend_from_slice(&flags.to_be_bytes());
+    }
+    message
+}
+
+/// Mapped address from a binding success answering `request`
+fn mapped_address(request: &[u8], response: &[u8]) -> Option<SocketAddr> {
+    if response.len() < HEADER_LEN
+        || u16::from_be_bytes([response[0], response[1]]) != BINDING_SUCCESS
+        || response[4..HEADER_LEN] != request[4..HEADER_LEN]
+    {
+        return None;
+    }
+
+    let mut mapped = None;
+    let mut rest = &response[HEADER_LEN..];
+    while rest.len() >= 4 {
+        let kind = u16::from_be_bytes([rest[0], rest[1]]);
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let value = rest.get(4..4 + len)?;
+        match kind {
+            ATTR_XOR_MAPPED_ADDRESS => return decode_address(value, Some(&response[4..HEADER_LEN])),
+            ATTR_MAPPED_ADDRESS => mapped = decode_address(value, None),
+            _ => {}
+        }
+        // Attributes are padded to four bytes
+        rest = rest.get(4 + len.div_ceil(4) * 4..).unwrap_or_default();
+    }
+    mapped
+}
+
+/// Address attribute value, unmasked with the cookie and transaction id when XORed
+fn decode_address(value: &[u8], xor: Option<&[u8]>) -> Option<SocketAddr> {
+    let mask = |bytes: &[u8]| -> Vec<u8> {
+        match xor {
+            Some(key) => bytes.iter().zip(key.iter().cycle()).map(|(b, k)| b ^ k).collect(),
+            None => bytes.to_vec(),
+        }
+    };
+    let port = mask(value.get(2..4)?);
+    let port = u16::from_be_bytes([port[0], port[1]]);
+    let ip = match value.get(1)? {
+        0x01 => IpAddr::from(<[u8; 4]>::try_from(mask(value.get(4..8)?)).ok()?),
+        0x02 => IpAddr::from(<[u8; 16]>::try_from(mask(value.get(4..20)?)).ok()?),
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Classifies the local NAT against a list of STUN servers
+pub struct NatDetector<'a> {
+    servers: &'a [String],
+    transport: &'a dyn StunTransport,
+}
+
+impl<'a> NatDetector<'a> {
+    pub fn new(servers: &'a [String], transport: &'a dyn StunTransport) -> Self {
+        Self { servers, transport }
+    }
+
+    pub async fn detect(&self) -> NatReport {
+        if self.servers.is_empty() {
+            return NatReport::undetected(NatType::Unknown);
+        }
+
+        let mut answered = Vec::new();
+        for server in self.servers {
+            let Some(addr) = resolve(server).await else {
+                debug!("Could not resolve STUN server {}", server);
+                continue;
+            };
+            if let Some(mapped) = self.binding(addr, Change::default()).await {
+                answered.push((server, addr, mapped));
+                if answered.len() == 2 {
+                    break;
+                }
+            }
+        }
+        let Some(&(server, addr, mapped)) = answered.first() else {
+            info!("No STUN server answered, treating UDP as blocked");
+            return NatReport::undetected(NatType::Blocked);
+        };
+
+        let local = self.transport.local_addr(addr).await.ok();
+        let nat_type = if local == Some(mapped) {
+            NatType::Open
+        } else if answered.iter().any(|(_, _, other)| *other != mapped) {
+            NatType::Symmetric
+        } else {
+            // A reply from an address never contacted only gets through a full cone
+            match self.exchange(addr, Change { ip: true, port: true }).await {
+                Some((_, from)) if from != addr => NatType::FullCone,
+                _ => NatType::Restricted,
+            }
+        };
+        info!("Detected {} NAT, public address {}", nat_type, mapped);
+
+        NatReport {
+            nat_type,
+            external_ip: Some(mapped.ip().to_string()),
+            external_port: Some(mapped.port()),
+            internal_ip: local.map(|local| local.ip().to_string()),
+            stun_server: Some(server.clone()),
+            detected_at: Utc::now(),
+        }
+    }
+
+    async fn binding(&self, server: SocketAddr, change: Change) -> Option<SocketAddr> {
+        self.exchange(server, change).await.map(|(mapped, _)| mapped)
+    }
+
+    /// Mapped address and the address the reply came from
+    async fn exchange(&self, server: SocketAddr, change: Change) -> Option<(SocketAddr, SocketAddr)> {
+        let request = binding_request(change);
+        match self.transport.exchange(server, &request).await {
+            Ok(Some((response, from))) => mapped_address(&request, &response).map(|mapped| (mapped, from)),
+            Ok(None) => None,
+            Err(e) => {
+                debug!("STUN request to {} failed: {}", server, e);
+                None
+            }
+        }
+    }
+}
+
+/// First IPv4 address of a `host:port`
+async fn resolve(server: &str) -> Option<SocketAddr> {
+    tokio::net::lookup_host(server).await.ok()?.find(SocketAddr::is_ipv4)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Binding success carrying `mapped`, as a server would send it
+    fn binding_response(request: &[u8], mapped: SocketAddr) -> Vec<u8> {
+        let xored: Vec<u8> = match mapped.ip() {
+            IpAddr::V4(ip) => ip.octets().to_vec(),
+            IpAddr::V6(ip) => ip.octets().to_vec(),
+        }
+        .iter()
+        .zip(request[4..HEADER_LEN].iter().cycle())
+        .map(|(b, k)| b ^ k)
+        .collect();
+        let family = if mapped.is_ipv4() { 0x01 } else { 0x02 };
+        let port = mapped.port() ^ (MAGIC_COOKIE >> 16) as u16;
+
+        let mut response = BINDING_SUCCESS.to_be_bytes().to_vec();
+        response.extend_from_slice(&(4 + 4 + xored.len() as u16).to_be_bytes());
+        response.extend_from_slice(&request[4..HEADER_LEN]);
+        response.extend_from_slice(&ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+        response.extend_from_slice(&(4 + xored.len() as u16).to_be_bytes());
+        response.extend_from_slice(&[0, family]);
+        response.extend_from_slice(&port.to_be_bytes());
+        response.extend_from_slice(&xored);
+        response
+    }
+
+    /// A NAT in front of this machine, answering for the STUN servers
+    pub(crate) struct FakeNat {
+        pub local: SocketAddr,
+        /// Mapping each server sees; servers missing here don't answer
+        pub mappings: HashMap<SocketAddr, SocketAddr>,
+        /// Lets in replies from addresses never sent to
+        pub full_cone: bool,
+    }
+
+    impl FakeNat {
+        pub fn new(nat_type: NatType, servers: &[&str]) -> Self {
+            let local: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+            let mappings = servers
+                .iter()
+                .enumerate()
+                .map(|(i, server)| {
+                    let mapped = match nat_type {
+                        NatType::Open => local,
+                        NatType::Symmetric => SocketAddr::from(([203, 0, 113, 7], 40000 + i as u16)),
+                        NatType::Blocked => return None,
+                        _ => "203.0.113.7:40000".parse().unwrap(),
+                    };
+                    Some((server.parse().unwrap(), mapped))
+                })
+                .collect::<Option<_>>()
+                .unwrap_or_default();
+            Self { local, mappings, full_cone: nat_type == NatType::FullCone }
+        }
+    }
+
+    #[async_trait]
+    impl StunTransport for FakeNat {
+        async fn exchange(&self, server: SocketAddr, request: &[u8]) -> io::Result<Option<(Vec<u8>, SocketAddr)>> {
+            let Some(&mapped) = self.mappings.get(&server) else {
+                return Ok(None);
+            };
+            // The server answers a change request from its alternate address
+            let from = if request.len() > HEADER_LEN {
+                if !self.full_cone {
+                    return Ok(None);
+                }
+                SocketAddr::new([198, 51, 100, 99].into(), server.port() + 1)
+            } else {
+                server
+            };
+            Ok(Some((binding_response(request, mapped), from)))
+        }
+
+        async fn local_addr(&self, _server: SocketAddr) -> io::Result<SocketAddr> {
+            Ok(self.local)
+        }
+    }
+
+    const SERVERS: [&str; 2] = ["198.51.100.1:3478", "198.51.100.2:3478"];
+
+    async fn detect(nat_type: NatType) -> NatReport {
+        let servers: Vec<String> = SERVERS.iter().map(|s| s.to_string()).collect();
+        NatDetector::new(&servers, &FakeNat::new(nat_type, &SERVERS)).detect().await
+    }
+
+    #[tokio::test]
+    async fn test_classifies_nat_types() {
+        for nat_type in [NatType::Open, NatType::FullCone, NatType::Restricted, NatType::Symmetric, NatType::Blocked] {
+            assert_eq!(detect(nat_type).await.nat_type, nat_type);
+        }
+
+        let report = detect(NatType::Restricted).await;
+        assert_eq!(report.external_ip.as_deref(), Some("203.0.113.7"));
+        assert_eq!(report.external_port, Some(40000));
+        assert_eq!(report.internal_ip.as_deref(), Some("192.168.1.20"));
+        assert_eq!(report.stun_server.as_deref(), Some(SERVERS[0]));
+
+        let none = NatDetector::new(&[], &FakeNat::new(NatType::Open, &SERVERS)).detect().await;
+        assert_eq!(none.nat_type, NatType::Unknown);
+    }
+
+    #[test]
+    fn test_binding_messages() {
+        let request = binding_request(Change::default());
+        assert_eq!(request.len(), HEADER_LEN);
+        assert_eq!(binding_request(Change { ip: true, port: true })[HEADER_LEN..], [0, 3, 0, 4, 0, 0, 0, 6]);
+
+        for mapped in ["203.0.113.7:40000", "[2001:db8::7]:40000"] {
+            let mapped: SocketAddr = mapped.parse().unwrap();
+            assert_eq!(mapped_address(&request, &binding_response(&request, mapped)), Some(mapped));
+        }
+
+        // A reply to another transaction, or a truncated one, is ignored
+        let other = binding_request(Change::default());
+        let response = binding_response(&other, "203.0.113.7:40000".parse().unwrap());
+        assert_eq!(mapped_address(&request, &response), None);
+        assert_eq!(mapped_address(&other, &response[..response.len() - 2]), None);
+    }
+
+    #[test]
+    fn test_nat_compatibility() {
+        use NatType::*;
+        assert!(Restricted.compatible_with(Restricted));
+        assert!(FullCone.compatible_with(Symmetric));
+        assert!(Unknown.compatible_with(Symmetric));
+        assert!(!Restricted.compatible_with(Symmetric));
+        assert!(!Symmetric.compatible_with(Symmetric));
+        assert!(!Open.compatible_with(Blocked));
+        assert_eq!(NatType::parse("full_cone"), Some(FullCone));
+    }
+
+    #[tokio::test]
+    async fn test_udp_transport_against_local_server() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let (len, from) = server.recv_from(&mut buf).await.unwrap();
+                // No alternate address to answer change requests from
+                if len == HEADER_LEN {
+                    server.send_to(&binding_response(&buf[..len], from), from).await.unwrap();
+                }
+            }
+        });
+
+        let servers = vec![server_addr.to_string()];
+        let report = NatDetector::new(&servers, &UdpStunTransport::new()).detect().await;
+        assert_eq!(report.nat_type, NatType::Open);
+        assert_eq!(report.external_ip.as_deref(), Some("127.0.0.1"));
+    }
+}
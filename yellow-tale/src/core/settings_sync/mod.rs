@@ -0,0 +1,733 @@
+//! Settings Sync Module
+//!
+//! Keeps launcher settings consistent across a user's devices:
+//! - Versioned sync document with one timestamped entry per section
+//! - Push/pull against the central server with etag conflict detection
+//! - Last-writer-wins merge per section, with a conflict report
+//! - Subscription-aware quota (free accounts don't sync mod profile archives)
+//!
+//! Pulled performance settings are written back to `config.toml`; the
+//! other sections belong to the UI, which gets their data in the report.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::core::client::{ApiClient, ClientError, RemoteSyncDocument, SubscriptionFeatures};
+use crate::core::config::{AppConfig, ConfigError, PerformanceConfig};
+
+/// Current sync document schema version
+pub const SYNC_SCHEMA_VERSION: u32 = 1;
+
+/// Storage allowance for accounts without cloud storage
+pub const FREE_SYNC_LIMIT_BYTES: u64 = 256 * 1024;
+
+/// How many times a push is retried after another device wins the race
+const MAX_PUSH_ATTEMPTS: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("Server error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("Sync document is {size} bytes, quota is {quota} bytes")]
+    QuotaExceeded { size: u64, quota: u64 },
+
+    #[error("Sync kept conflicting with another device, try again")]
+    TooManyConflicts,
+
+    #[error("Unsupported sync schema version: {0}")]
+    UnsupportedSchema(u32),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Config error: {0}")]
+    Config(#[from] ConfigError),
+}
+
+/// A group of settings that is synced and merged as a unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncSection {
+    PerformanceSettings,
+    ModProfiles,
+    Waypoints,
+    Markers,
+    FeatureToggles,
+}
+
+impl SyncSection {
+    pub const ALL: [SyncSection; 5] = [
+        SyncSection::PerformanceSettings,
+        SyncSection::ModProfiles,
+        SyncSection::Waypoints,
+        SyncSection::Markers,
+        SyncSection::FeatureToggles,
+    ];
+
+    /// Archive sections are large and only synced with cloud storage
+    pub fn is_archive(&self) -> bool {
+        matches!(self, SyncSection::ModProfiles)
+    }
+
+    /// Sections kept in `config.toml`, which the launcher applies itself
+    pub fn is_config(&self) -> bool {
+        matches!(self, SyncSection::PerformanceSettings)
+    }
+}
+
+/// One section's data plus who last wrote it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionEntry {
+    pub updated_at: DateTime<Utc>,
+    pub device_id: String,
+    pub data: serde_json::Value,
+}
+
+/// The document exchanged with the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncDocument {
+    pub schema_version: u32,
+    pub sections: BTreeMap<SyncSection, SectionEntry>,
+}
+
+impl SyncDocument {
+    pub fn new() -> Self {
+        Self {
+            schema_version: SYNC_SCHEMA_VERSION,
+            sections: BTreeMap::new(),
+        }
+    }
+
+    /// Copy of the document without the sections the policy excludes
+    pub fn filtered(&self, policy: &SyncPolicy) -> SyncDocument {
+        SyncDocument {
+            schema_version: self.schema_version,
+            sections: self.sections.iter()
+                .filter(|(section, _)| policy.include_archives || !section.is_archive())
+                .map(|(section, entry)| (*section, entry.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        serde_json::to_vec(self).map(|b| b.len() as u64).unwrap_or(0)
+    }
+}
+
+impl Default for SyncDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a subscription allows to be synced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncPolicy {
+    pub include_archives: bool,
+    pub quota_bytes: u64,
+}
+
+impl SyncPolicy {
+    pub fn from_features(features: &SubscriptionFeatures) -> Self {
+        if features.cloud_storage_mb > 0 {
+            Self {
+                include_archives: true,
+                quota_bytes: features.cloud_storage_mb as u64 * 1024 * 1024,
+            }
+        } else {
+            Self::free()
+        }
+    }
+
+    pub fn free() -> Self {
+        Self {
+            include_archives: false,
+            quota_bytes: FREE_SYNC_LIMIT_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    KeptLocal,
+    TookRemote,
+}
+
+/// A section both devices edited since the last sync
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub section: SyncSection,
+    pub local_updated_at: DateTime<Utc>,
+    pub remote_updated_at: DateTime<Utc>,
+    pub local_device: String,
+    pub remote_device: String,
+    pub resolution: ConflictResolution,
+}
+
+/// Outcome of merging a local and a remote document
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    pub document: SyncDocument,
+    pub conflicts: Vec<SyncConflict>,
+    /// Sections where the remote copy replaced ours
+    pub pulled: Vec<SyncSection>,
+}
+
+/// Merge two documents section by section, last writer wins.
+///
+/// A section only counts as a conflict when both sides changed it after
+/// `last_synced_at` and ended up with different data; the newer write is
+/// still kept, but the loser is reported so the UI can tell the user.
+pub fn merge(
+    local: &SyncDocument,
+    remote: &SyncDocument,
+    last_synced_at: Option<DateTime<Utc>>,
+) -> MergeResult {
+    let mut document = SyncDocument::new();
+    let mut conflicts = Vec::new();
+    let mut pulled = Vec::new();
+
+    for section in SyncSection::ALL {
+        let entry = match (local.sections.get(&section), remote.sections.get(&section)) {
+            (Some(l), None) => l.clone(),
+            (None, Some(r)) => {
+                pulled.push(section);
+                r.clone()
+            }
+            (Some(l), Some(r)) => {
+                let remote_wins = (r.updated_at, &r.device_id) > (l.updated_at, &l.device_id);
+                let changed_since = |e: &SectionEntry| last_synced_at.is_none_or(|t| e.updated_at > t);
+
+                if l.data != r.data && changed_since(l) && changed_since(r) {
+                    conflicts.push(SyncConflict {
+                        section,
+                        local_updated_at: l.updated_at,
+                        remote_updated_at: r.updated_at,
+                        local_device: l.device_id.clone(),
+                        remote_device: r.device_id.clone(),
+                        resolution: if remote_wins {
+                            ConflictResolution::TookRemote
+                        } else {
+                            ConflictResolution::KeptLocal
+                        },
+                    });
+                }
+
+                if remote_wins {
+                    if l.data != r.data {
+                        pulled.push(section);
+                    }
+                    r.clone()
+                } else {
+                    l.clone()
+                }
+            }
+            (None, None) => continue,
+        };
+        document.sections.insert(section, entry);
+    }
+
+    MergeResult { document, conflicts, pulled }
+}
+
+/// Result of a completed `sync_now`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub etag: String,
+    pub synced_at: DateTime<Utc>,
+    pub pulled: Vec<SyncSection>,
+    /// What each pulled section now holds, for the UI to apply
+    #[serde(default)]
+    pub pulled_data: BTreeMap<SyncSection, serde_json::Value>,
+    pub conflicts: Vec<SyncConflict>,
+    pub skipped_sections: Vec<SyncSection>,
+    pub size_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+/// Sync state reported over IPC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub device_id: String,
+    pub etag: Option<String>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub sections: Vec<SyncSection>,
+    pub pending_sections: Vec<SyncSection>,
+    pub last_conflicts: Vec<SyncConflict>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncState {
+    device_id: String,
+    document: SyncDocument,
+    etag: Option<String>,
+    last_synced_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_conflicts: Vec<SyncConflict>,
+    #[serde(default)]
+    last_error: Option<String>,
+    /// Config sections pulled from another device but not yet written to
+    /// `config.toml`
+    #[serde(default)]
+    unapplied: BTreeSet<SyncSection>,
+}
+
+/// Local sync store and push/pull driver
+pub struct SettingsSync {
+    path: PathBuf,
+    state: SyncState,
+}
+
+impl SettingsSync {
+    /// Load sync state from `<data_dir>/sync/state.json`, creating a fresh device id if absent
+    pub async fn load(data_dir: &Path) -> Result<Self, SyncError> {
+        let path = data_dir.join("sync").join("state.json");
+        let state = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SyncState {
+                device_id: Uuid::new_v4().to_string(),
+                document: SyncDocument::new(),
+                etag: None,
+                last_synced_at: None,
+                last_conflicts: Vec::new(),
+                last_error: None,
+                unapplied: BTreeSet::new(),
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self { path, state })
+    }
+
+    async fn save(&self) -> Result<(), SyncError> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.path, serde_json::to_vec_pretty(&self.state)?).await?;
+        Ok(())
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.state.device_id
+    }
+
+    pub fn section(&self, section: SyncSection) -> Option<&serde_json::Value> {
+        self.state.document.sections.get(&section).map(|e| &e.data)
+    }
+
+    /// Record a local change to a section; unchanged data keeps its old timestamp
+    pub async fn update_section(&mut self, section: SyncSection, data: serde_json::Value) -> Result<(), SyncError> {
+        if self.section(section) == Some(&data) {
+            return Ok(());
+        }
+        self.state.document.sections.insert(section, SectionEntry {
+            updated_at: Utc::now(),
+            device_id: self.state.device_id.clone(),
+            data,
+        });
+        self.save().await
+    }
+
+    /// Write pulled config sections into `config`, returning those applied.
+    /// Run this before recording the local config, or a restart overwrites
+    /// what another device changed with this device's older settings.
+    pub async fn apply_pulled(&mut self, config: &mut AppConfig) -> Result<Vec<SyncSection>, SyncError> {
+        if self.state.unapplied.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut applied = Vec::new();
+        for section in std::mem::take(&mut self.state.unapplied) {
+            let Some(data) = self.section(section).cloned() else {
+                continue;
+            };
+            if section != SyncSection::PerformanceSettings {
+                continue;
+            }
+            match serde_json::from_value::<PerformanceConfig>(data) {
+                Ok(performance) => {
+                    config.performance = performance;
+                    applied.push(section);
+                }
+                Err(e) => warn!("Ignoring pulled performance settings: {}", e),
+            }
+        }
+        self.save().await?;
+        Ok(applied)
+    }
+
+    /// `apply_pulled` against the config file at `path`, saving it if
+    /// anything changed
+    pub async fn apply_pulled_to(&mut self, path: &Path) -> Result<Vec<SyncSection>, SyncError> {
+        if self.state.unapplied.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (mut config, _) = AppConfig::load(path).await?;
+        let applied = self.apply_pulled(&mut config).await?;
+        if !applied.is_empty() {
+            config.save(path).await?;
+        }
+        Ok(applied)
+    }
+
+    /// Record the local config's synced sections; unchanged ones keep their
+    /// timestamps. Until this device first syncs, what it has counts as
+    /// older than anything on the server, so a new install adopts the
+    /// account's settings instead of pushing its defaults over them.
+    pub async fn record_config(&mut self, config: &AppConfig) -> Result<(), SyncError> {
+        let data = serde_json::to_value(&config.performance)?;
+        if self.state.last_synced_at.is_some() {
+            return self.update_section(SyncSection::PerformanceSettings, data).await;
+        }
+        if self.section(SyncSection::PerformanceSettings) == Some(&data) {
+            return Ok(());
+        }
+        self.state.document.sections.insert(SyncSection::PerformanceSettings, SectionEntry {
+            updated_at: DateTime::<Utc>::UNIX_EPOCH,
+            device_id: self.state.device_id.clone(),
+            data,
+        });
+        self.save().await
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        let pending_sections = self.state.document.sections.iter()
+            .filter(|(_, e)| self.state.last_synced_at.is_none_or(|t| e.updated_at > t))
+            .map(|(s, _)| *s)
+            .collect();
+
+        SyncStatus {
+            device_id: self.state.device_id.clone(),
+            etag: self.state.etag.clone(),
+            last_synced_at: self.state.last_synced_at,
+            sections: self.state.document.sections.keys().copied().collect(),
+            pending_sections,
+            last_conflicts: self.state.last_conflicts.clone(),
+            last_error: self.state.last_error.clone(),
+        }
+    }
+
+    /// Pull, merge, and push until the server accepts our document
+    pub async fn sync_now(&mut self, client: &ApiClient) -> Result<SyncReport, SyncError> {
+        let result = self.sync_inner(client).await;
+        if let Err(e) = &result {
+            warn!("Settings sync failed: {}", e);
+            self.state.last_error = Some(e.to_string());
+            self.save().await?;
+        }
+        result
+    }
+
+    async fn sync_inner(&mut self, client: &ApiClient) -> Result<SyncReport, SyncError> {
+        let features = client.get_subscription_features().await?;
+        let policy = SyncPolicy::from_features(&features);
+
+        let mut remote = client.sync_pull().await?;
+        for _ in 0..MAX_PUSH_ATTEMPTS {
+            let (remote_document, remote_etag) = match remote {
+                Some(RemoteSyncDocument { document, etag }) => {
+                    let document: SyncDocument = serde_json::from_value(document)?;
+                    if document.schema_version > SYNC_SCHEMA_VERSION {
+                        return Err(SyncError::UnsupportedSchema(document.schema_version));
+                    }
+                    (document, Some(etag))
+                }
+                None => (SyncDocument::new(), None),
+            };
+
+            let mut merged = merge(&self.state.document, &remote_document, self.state.last_synced_at);
+            // Settings recorded before the first sync survive only when the
+            // account had nothing newer; once uploaded they're a normal edit
+            let now = Utc::now();
+            for entry in merged.document.sections.values_mut() {
+                if entry.updated_at == DateTime::<Utc>::UNIX_EPOCH {
+                    entry.updated_at = now;
+                }
+            }
+            let upload = merged.document.filtered(&policy);
+            let size_bytes = upload.size_bytes();
+            if size_bytes > policy.quota_bytes {
+                return Err(SyncError::QuotaExceeded { size: size_bytes, quota: policy.quota_bytes });
+            }
+
+            match client.sync_push(serde_json::to_value(&upload)?, remote_etag).await {
+                Ok(etag) => {
+                    let synced_at = Utc::now();
+                    let skipped_sections = merged.document.sections.keys()
+                        .filter(|s| !upload.sections.contains_key(s))
+                        .copied()
+                        .collect();
+
+                    let pulled_data = merged.pulled.iter()
+                        .filter_map(|s| merged.document.sections.get(s).map(|e| (*s, e.data.clone())))
+                        .collect();
+                    self.state.unapplied.extend(merged.pulled.iter().filter(|s| s.is_config()));
+                    self.state.document = merged.document;
+                    self.state.etag = Some(etag.clone());
+                    self.state.last_synced_at = Some(synced_at);
+                    self.state.last_conflicts = merged.conflicts.clone();
+                    self.state.last_error = None;
+                    self.save().await?;
+
+                    info!("Settings synced ({} pulled, {} conflicts)", merged.pulled.len(), merged.conflicts.len());
+                    return Ok(SyncReport {
+                        etag,
+                        synced_at,
+                        pulled: merged.pulled,
+                        pulled_data,
+                        conflicts: merged.conflicts,
+                        skipped_sections,
+                        size_bytes,
+                        quota_bytes: policy.quota_bytes,
+                    });
+                }
+                Err(ClientError::Conflict(_)) => {
+                    remote = client.sync_pull().await?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(SyncError::TooManyConflicts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).unwrap()
+    }
+
+    fn entry(secs: i64, device: &str, data: serde_json::Value) -> SectionEntry {
+        SectionEntry { updated_at: at(secs), device_id: device.to_string(), data }
+    }
+
+    fn doc(entries: Vec<(SyncSection, SectionEntry)>) -> SyncDocument {
+        SyncDocument { schema_version: SYNC_SCHEMA_VERSION, sections: entries.into_iter().collect() }
+    }
+
+    #[test]
+    fn test_concurrent_edits_to_different_sections_merge_cleanly() {
+        let base = serde_json::json!({ "v": 0 });
+        let local = doc(vec![
+            (SyncSection::PerformanceSettings, entry(200, "desktop", serde_json::json!({ "priority": "high" }))),
+            (SyncSection::Waypoints, entry(50, "desktop", base.clone())),
+        ]);
+        let remote = doc(vec![
+            (SyncSection::PerformanceSettings, entry(50, "desktop", base.clone())),
+            (SyncSection::Waypoints, entry(210, "laptop", serde_json::json!({ "home": [1, 2, 3] }))),
+        ]);
+
+        let result = merge(&local, &remote, Some(at(100)));
+
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.pulled, vec![SyncSection::Waypoints]);
+        assert_eq!(result.document.sections[&SyncSection::PerformanceSettings].data["priority"], "high");
+        assert_eq!(result.document.sections[&SyncSection::Waypoints].device_id, "laptop");
+    }
+
+    #[test]
+    fn test_concurrent_edits_to_same_section_report_conflict() {
+        let local = doc(vec![
+            (SyncSection::FeatureToggles, entry(300, "desktop", serde_json::json!({ "minimap": false }))),
+        ]);
+        let remote = doc(vec![
+            (SyncSection::FeatureToggles, entry(250, "laptop", serde_json::json!({ "minimap": true }))),
+        ]);
+
+        let result = merge(&local, &remote, Some(at(100)));
+
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].section, SyncSection::FeatureToggles);
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::KeptLocal);
+        assert!(result.pulled.is_empty());
+        assert_eq!(result.document.sections[&SyncSection::FeatureToggles].data["minimap"], false);
+
+        let result = merge(&remote, &local, Some(at(100)));
+        assert_eq!(result.conflicts[0].resolution, ConflictResolution::TookRemote);
+        assert_eq!(result.pulled, vec![SyncSection::FeatureToggles]);
+    }
+
+    #[test]
+    fn test_same_section_identical_data_is_not_a_conflict() {
+        let data = serde_json::json!({ "max_fps": 144 });
+        let local = doc(vec![(SyncSection::PerformanceSettings, entry(300, "desktop", data.clone()))]);
+        let remote = doc(vec![(SyncSection::PerformanceSettings, entry(250, "laptop", data))]);
+
+        let result = merge(&local, &remote, Some(at(100)));
+        assert!(result.conflicts.is_empty());
+        assert!(result.pulled.is_empty());
+    }
+
+    #[test]
+    fn test_equal_timestamps_resolve_deterministically() {
+        let a = doc(vec![(SyncSection::Markers, entry(300, "a-device", serde_json::json!(1)))]);
+        let b = doc(vec![(SyncSection::Markers, entry(300, "b-device", serde_json::json!(2)))]);
+
+        let from_a = merge(&a, &b, None);
+        let from_b = merge(&b, &a, None);
+        assert_eq!(from_a.document, from_b.document);
+        assert_eq!(from_a.document.sections[&SyncSection::Markers].device_id, "b-device");
+    }
+
+    #[test]
+    fn test_free_policy_skips_archives() {
+        let document = doc(vec![
+            (SyncSection::ModProfiles, entry(1, "desktop", serde_json::json!({ "archive": "..." }))),
+            (SyncSection::PerformanceSettings, entry(1, "desktop", serde_json::json!({}))),
+        ]);
+
+        let free = document.filtered(&SyncPolicy::free());
+        assert!(!free.sections.contains_key(&SyncSection::ModProfiles));
+        assert!(free.sections.contains_key(&SyncSection::PerformanceSettings));
+
+        let premium = SyncPolicy::from_features(&SubscriptionFeatures { cloud_storage_mb: 5120, ..Default::default() });
+        assert!(document.filtered(&premium).sections.contains_key(&SyncSection::ModProfiles));
+    }
+
+    /// The current document and etag of a fake sync server
+    type Stored = Arc<Mutex<(Option<serde_json::Value>, u32)>>;
+
+    /// Local HTTP server speaking just enough of the sync API
+    async fn sync_server(stored: Stored) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let stored = stored.clone();
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await.unwrap();
+                    let path = line.split_whitespace().nth(1).unwrap_or("").to_string();
+                    let mut length = 0;
+                    loop {
+                        let mut header = String::new();
+                        if reader.read_line(&mut header).await.unwrap() == 0 || header.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).await.unwrap();
+                    let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+                    let (status, data) = {
+                        let mut stored = stored.lock().unwrap();
+                        match path.as_str() {
+                            "/api/v1/subscription" => ("200 OK", serde_json::json!({ "features": SubscriptionFeatures::default() })),
+                            "/api/v1/sync/pull" => {
+                                let etag = stored.0.as_ref().map(|_| stored.1.to_string());
+                                ("200 OK", serde_json::json!({ "document": stored.0, "etag": etag }))
+                            }
+                            _ if request["base_etag"].as_str() != stored.0.as_ref().map(|_| stored.1.to_string()).as_deref() => {
+                                ("409 Conflict", serde_json::Value::Null)
+                            }
+                            _ => {
+                                stored.0 = Some(request["document"].clone());
+                                stored.1 += 1;
+                                ("200 OK", serde_json::json!({ "etag": stored.1.to_string() }))
+                            }
+                        }
+                    };
+                    let response = serde_json::json!({ "success": !data.is_null(), "data": data, "error": null }).to_string();
+                    let mut stream = reader.into_inner();
+                    let head = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, response.len());
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// A device's data dir with its own config.toml
+    struct Device {
+        dir: PathBuf,
+    }
+
+    impl Device {
+        async fn new(priority: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("yt-sync-{}", Uuid::new_v4()));
+            let mut config = AppConfig::default();
+            config.performance.default_priority = priority.to_string();
+            config.save(&dir.join("config.toml")).await.unwrap();
+            Self { dir }
+        }
+
+        async fn priority(&self) -> String {
+            AppConfig::load(&self.dir.join("config.toml")).await.unwrap().0.performance.default_priority
+        }
+
+        async fn set_priority(&self, priority: &str) {
+            let path = self.dir.join("config.toml");
+            let (mut config, _) = AppConfig::load(&path).await.unwrap();
+            config.performance.default_priority = priority.to_string();
+            config.save(&path).await.unwrap();
+        }
+
+        /// What the launcher does on start, then a sync as the IPC does it
+        async fn start_and_sync(&self, client: &ApiClient) -> SyncReport {
+            let path = self.dir.join("config.toml");
+            let mut sync = SettingsSync::load(&self.dir).await.unwrap();
+            sync.apply_pulled_to(&path).await.unwrap();
+            sync.record_config(&AppConfig::load(&path).await.unwrap().0).await.unwrap();
+            let report = sync.sync_now(client).await.unwrap();
+            sync.apply_pulled_to(&path).await.unwrap();
+            report
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pulled_settings_reach_the_other_device_and_survive_a_restart() {
+        let stored: Stored = Arc::default();
+        let client = ApiClient::with_token(&sync_server(stored.clone()).await, "t0k3n".to_string());
+        let desktop = Device::new("high").await;
+        let laptop = Device::new("normal").await;
+
+        desktop.start_and_sync(&client).await;
+        // The laptop's defaults don't beat settings the account already has
+        let report = laptop.start_and_sync(&client).await;
+        assert_eq!(report.pulled, vec![SyncSection::PerformanceSettings]);
+        assert_eq!(report.pulled_data[&SyncSection::PerformanceSettings]["default_priority"], "high");
+        assert_eq!(laptop.priority().await, "high");
+
+        // Restarting doesn't put the old local value back
+        let report = laptop.start_and_sync(&client).await;
+        assert!(report.pulled.is_empty());
+        assert_eq!(laptop.priority().await, "high");
+
+        // An edit on the laptop flows back the other way
+        laptop.set_priority("low").await;
+        laptop.start_and_sync(&client).await;
+        let report = desktop.start_and_sync(&client).await;
+        assert_eq!(report.pulled, vec![SyncSection::PerformanceSettings]);
+        assert_eq!(desktop.priority().await, "low");
+        let document: SyncDocument = serde_json::from_value(stored.lock().unwrap().0.clone().unwrap()).unwrap();
+        assert_eq!(document.sections[&SyncSection::PerformanceSettings].data["default_priority"], "low");
+
+        for device in [desktop, laptop] {
+            tokio::fs::remove_dir_all(&device.dir).await.ok();
+        }
+    }
+}
@@ -0,0 +1,1154 @@
+//! Snapshots Module
+//!
+//! Backs up the data a player would hate to lose:
+//! - Hosted worlds, profile configs and the mod list, snapshotted together
+//! - File contents are stored once under `objects/<sha256>`, so a file that
+//!   hasn't changed since an earlier snapshot costs nothing; each snapshot
+//!   is a manifest naming the blobs it needs
+//! - Blobs are hashed as they're copied in and again as they're copied out,
+//!   so a restore never writes a file that doesn't match its manifest
+//! - Restores are staged next to the live data and checked before anything
+//!   live is touched, and take a safety snapshot of the current data first
+//! - Deleting a snapshot removes only the blobs no other snapshot uses
+//! - Pruned to the newest few within a size budget, plus one a day for a week
+//! - Scheduled snapshots that wait for the game to close (see [`scheduler`])
+//! - Snapshots taken as single `.tar.gz` archives, before the object store,
+//!   are moved into it by [`SnapshotManager::migrate_archives`]
+
+pub mod scheduler;
+
+pub use scheduler::{Quiesce, ScheduleStatus, ScheduledRun, SnapshotScheduler};
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::core::config::SnapshotConfig;
+
+/// Directory under the snapshot dir holding every blob
+pub const OBJECTS_DIR: &str = "objects";
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Snapshot {0} not found")]
+    NotFound(Uuid),
+
+    #[error("Nothing to snapshot: none of the target paths exist")]
+    NothingToSnapshot,
+
+    #[error("Invalid snapshot target '{0}'")]
+    InvalidTarget(String),
+
+    #[error("{count} file(s) in {target} changed after the snapshot was taken; restore with force to replace them")]
+    NewerFiles { target: String, count: usize },
+
+    #[error("Snapshot {id} is corrupt: {reason}")]
+    Corrupt { id: Uuid, reason: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Why a snapshot was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotTrigger {
+    Manual,
+    Scheduled,
+    PreUpdate,
+    /// Taken automatically before a restore replaced the live data
+    PreRestore,
+}
+
+/// A directory or file included in every snapshot, under a stable name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotTarget {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// One snapshotted file and the blob holding its contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub target: String,
+    /// `/`-separated path under the target; empty when the target is a file
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    /// Modification time in seconds since the epoch, given back on restore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+}
+
+impl SnapshotFile {
+    fn entry_name(&self) -> String {
+        if self.path.is_empty() {
+            self.target.clone()
+        } else {
+            format!("{}/{}", self.target, self.path)
+        }
+    }
+}
+
+/// Stored as `<id>.json`; the files' contents live in `objects/`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub trigger: SnapshotTrigger,
+    /// Total size of the files, as if the snapshot were a full copy
+    pub size_bytes: u64,
+    /// Hash of the `<id>.tar.gz` a snapshot from before the object store
+    /// was written to; gone once it has been migrated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_sha256: Option<String>,
+    /// Targets that existed when the snapshot was taken
+    pub targets: Vec<SnapshotTarget>,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// A snapshot as `get_snapshots` lists it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSummary {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub trigger: SnapshotTrigger,
+    pub targets: Vec<SnapshotTarget>,
+    pub file_count: usize,
+    /// Total size of the files, as if the snapshot were a full copy
+    pub size_bytes: u64,
+    /// Bytes of blobs no other snapshot uses: what deleting it frees
+    pub unique_bytes: u64,
+}
+
+/// Every snapshot, newest first, with what they take on disk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotReport {
+    pub snapshots: Vec<SnapshotSummary>,
+    /// What the snapshots would take as full copies
+    pub logical_bytes: u64,
+    /// What the object store takes, each blob counted once
+    pub stored_bytes: u64,
+}
+
+impl SnapshotReport {
+    pub fn new(mut manifests: Vec<SnapshotManifest>) -> Self {
+        manifests.sort_by_key(|s| Reverse(s.created_at));
+
+        let mut blobs: HashMap<&str, (u64, usize)> = HashMap::new();
+        for manifest in &manifests {
+            for sha256 in distinct_blobs(manifest) {
+                let size = manifest.files.iter().find(|f| f.sha256 == sha256).map_or(0, |f| f.size);
+                blobs.entry(sha256).or_insert((size, 0)).1 += 1;
+            }
+        }
+
+        let snapshots = manifests.iter().map(|manifest| SnapshotSummary {
+            id: manifest.id,
+            created_at: manifest.created_at,
+            trigger: manifest.trigger,
+            targets: manifest.targets.clone(),
+            file_count: manifest.files.len(),
+            size_bytes: manifest.size_bytes,
+            unique_bytes: distinct_blobs(manifest).into_iter()
+                .filter_map(|sha256| blobs.get(sha256))
+                .filter(|(_, users)| *users == 1)
+                .map(|(size, _)| size)
+                .sum(),
+        }).collect();
+
+        Self {
+            snapshots,
+            logical_bytes: manifests.iter().map(|m| m.size_bytes).sum(),
+            stored_bytes: blobs.values().map(|(size, _)| size).sum(),
+        }
+    }
+}
+
+/// Each blob a snapshot uses, once
+fn distinct_blobs(manifest: &SnapshotManifest) -> HashSet<&str> {
+    manifest.files.iter().map(|f| f.sha256.as_str()).collect()
+}
+
+/// Which snapshots pruning keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Newest snapshots kept, as far as `max_total_bytes` allows
+    pub keep_last: usize,
+    /// Days, counting today, that keep their newest snapshot as well
+    pub keep_daily_days: u32,
+    /// Size of the blobs the kept snapshots use, each counted once. The
+    /// newest snapshot and each recent day's count towards it but are
+    /// never pruned for it.
+    pub max_total_bytes: Option<u64>,
+}
+
+impl From<&SnapshotConfig> for RetentionPolicy {
+    fn from(config: &SnapshotConfig) -> Self {
+        Self {
+            keep_last: config.keep_last,
+            keep_daily_days: config.keep_daily_days,
+            max_total_bytes: (config.max_total_mb > 0).then(|| config.max_total_mb * 1024 * 1024),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Snapshots the policy no longer keeps, oldest last
+    pub fn expired(&self, snapshots: &[SnapshotManifest], now: DateTime<Utc>) -> Vec<Uuid> {
+        let mut newest_first: Vec<&SnapshotManifest> = snapshots.iter().collect();
+        newest_first.sort_by_key(|s| Reverse(s.created_at));
+
+        // The newest snapshot, and the newest of each recent day, are kept
+        // whatever the budget says
+        let today = now.date_naive();
+        let mut days_kept = HashSet::new();
+        let pinned: Vec<bool> = newest_first.iter()
+            .enumerate()
+            .map(|(index, snapshot)| {
+                let day = snapshot.created_at.date_naive();
+                let recent_day = (today - day).num_days() < i64::from(self.keep_daily_days);
+                let first_of_day = recent_day && days_kept.insert(day);
+                index == 0 || first_of_day
+            })
+            .collect();
+
+        // A snapshot costs the blobs no kept snapshot already uses
+        let mut kept_blobs = HashSet::new();
+        let cost = |snapshot: &SnapshotManifest, kept_blobs: &HashSet<&str>| -> u64 {
+            let mut counted = HashSet::new();
+            snapshot.files.iter()
+                .filter(|f| !kept_blobs.contains(f.sha256.as_str()) && counted.insert(f.sha256.as_str()))
+                .map(|f| f.size)
+                .sum()
+        };
+        let mut used = 0;
+        for snapshot in newest_first.iter().zip(&pinned).filter(|(_, pinned)| **pinned).map(|(s, _)| s) {
+            used += cost(snapshot, &kept_blobs);
+            kept_blobs.extend(distinct_blobs(snapshot));
+        }
+
+        let mut over_budget = false;
+        let mut expired = Vec::new();
+        for (index, snapshot) in newest_first.into_iter().enumerate() {
+            if pinned[index] {
+                continue;
+            }
+            // Once one doesn't fit, everything older goes too
+            let cost = cost(snapshot, &kept_blobs);
+            over_budget |= self.max_total_bytes.is_some_and(|max| used + cost > max);
+            if index < self.keep_last && !over_budget {
+                used += cost;
+                kept_blobs.extend(distinct_blobs(snapshot));
+            } else {
+                expired.push(snapshot.id);
+            }
+        }
+        expired
+    }
+}
+
+/// Creates, lists, restores and prunes snapshots kept in one directory
+pub struct SnapshotManager {
+    dir: PathBuf,
+    targets: Vec<SnapshotTarget>,
+    retention: std::sync::Mutex<RetentionPolicy>,
+    /// Held for the whole of a create, restore or delete
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl SnapshotManager {
+    pub fn new(dir: impl Into<PathBuf>, config: &SnapshotConfig) -> Self {
+        Self {
+            dir: dir.into(),
+            targets: Vec::new(),
+            retention: std::sync::Mutex::new(RetentionPolicy::from(config)),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Include `path` in every snapshot as `name`. A path that doesn't exist
+    /// yet is skipped until it does.
+    pub fn with_target(mut self, name: &str, path: impl Into<PathBuf>) -> Result<Self, SnapshotError> {
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            && !self.targets.iter().any(|t| t.name == name);
+        if !valid {
+            return Err(SnapshotError::InvalidTarget(name.to_string()));
+        }
+        self.targets.push(SnapshotTarget { name: name.to_string(), path: path.into() });
+        Ok(self)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn retention(&self) -> RetentionPolicy {
+        *self.retention.lock().unwrap()
+    }
+
+    /// Applies from the next create or prune
+    pub fn set_retention(&self, retention: RetentionPolicy) {
+        *self.retention.lock().unwrap() = retention;
+    }
+
+    /// Snapshot every target, then prune by the retention policy. Nothing is
+    /// pruned when the snapshot fails; blobs it had already stored are
+    /// swept up.
+    pub async fn create(&self, trigger: SnapshotTrigger) -> Result<SnapshotManifest, SnapshotError> {
+        let _guard = self.lock.lock().await;
+        let manifest = match self.write(self.targets.clone(), trigger).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                if !matches!(e, SnapshotError::NothingToSnapshot) {
+                    self.sweep().await;
+                }
+                return Err(e);
+            }
+        };
+        self.prune_locked().await;
+        Ok(manifest)
+    }
+
+    /// Every snapshot with a readable manifest, newest first
+    pub async fn list(&self) -> Vec<SnapshotManifest> {
+        let dir = self.dir.clone();
+        let (mut snapshots, _) = tokio::task::spawn_blocking(move || read_manifests(&dir))
+            .await
+            .unwrap_or_default();
+        snapshots.sort_by_key(|s| Reverse(s.created_at));
+        snapshots
+    }
+
+    /// Every snapshot with its full and on-disk size
+    pub async fn report(&self) -> SnapshotReport {
+        SnapshotReport::new(self.list().await)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<SnapshotManifest, SnapshotError> {
+        let contents = match tokio::fs::read_to_string(self.manifest_path(id)).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(SnapshotError::NotFound(id)),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&contents)
+            .map_err(|e| SnapshotError::Corrupt { id, reason: format!("Unreadable manifest: {}", e) })
+    }
+
+    /// Put the snapshot's targets back where they were taken from.
+    ///
+    /// Each target is rebuilt from its blobs next to the live data and every
+    /// blob's hash checked first, so a corrupt snapshot fails without
+    /// touching live data. Files modified after the snapshot was taken are
+    /// only replaced with `force`. The current data is snapshotted before it
+    /// is replaced; that snapshot is returned, or `None` if none of the
+    /// targets existed.
+    pub async fn restore(&self, id: Uuid, force: bool) -> Result<Option<SnapshotManifest>, SnapshotError> {
+        let _guard = self.lock.lock().await;
+        let manifest = self.get(id).await?;
+        if manifest.archive_sha256.is_some() {
+            return Err(SnapshotError::Corrupt { id, reason: "Archive could not be moved into the object store".to_string() });
+        }
+        for target in &manifest.targets {
+            validate_manifest_target(&manifest, target)?;
+        }
+
+        if !force {
+            for target in &manifest.targets {
+                let path = target.path.clone();
+                let created_at = SystemTime::from(manifest.created_at);
+                let count = tokio::task::spawn_blocking(move || count_modified_since(&path, created_at))
+                    .await
+                    .map_err(io::Error::other)??;
+                if count > 0 {
+                    return Err(SnapshotError::NewerFiles { target: target.name.clone(), count });
+                }
+            }
+        }
+
+        let objects = self.objects_dir();
+        let staged = {
+            let manifest = manifest.clone();
+            tokio::task::spawn_blocking(move || stage_restore(&objects, &manifest))
+                .await
+                .map_err(io::Error::other)??
+        };
+
+        let safety = match self.write(manifest.targets.clone(), SnapshotTrigger::PreRestore).await {
+            Ok(safety) => Some(safety),
+            Err(SnapshotError::NothingToSnapshot) => None,
+            Err(e) => {
+                discard_staged(&staged);
+                self.sweep().await;
+                return Err(e);
+            }
+        };
+
+        tokio::task::spawn_blocking(move || swap_in(&staged))
+            .await
+            .map_err(io::Error::other)??;
+        info!("Restored snapshot {} from {}", id, manifest.created_at);
+        self.prune_locked().await;
+        Ok(safety)
+    }
+
+    /// Delete a snapshot and the blobs only it used; returns the bytes freed
+    pub async fn delete(&self, id: Uuid) -> Result<u64, SnapshotError> {
+        let _guard = self.lock.lock().await;
+        self.remove(id).await?;
+        Ok(self.sweep().await)
+    }
+
+    /// Delete the snapshots the retention policy no longer keeps
+    pub async fn prune(&self) -> Vec<Uuid> {
+        let _guard = self.lock.lock().await;
+        self.prune_locked().await
+    }
+
+    /// Move snapshots taken as `.tar.gz` archives into the object store.
+    /// An archive that fails its checks is left as it was. Returns how many
+    /// were moved.
+    pub async fn migrate_archives(&self) -> usize {
+        let _guard = self.lock.lock().await;
+        let mut migrated = 0;
+        for manifest in self.list().await.into_iter().filter(|m| m.archive_sha256.is_some()) {
+            let dir = self.dir.clone();
+            let id = manifest.id;
+            match tokio::task::spawn_blocking(move || migrate_archive(&dir, manifest)).await.map_err(io::Error::other) {
+                Ok(Ok(())) => migrated += 1,
+                Ok(Err(e)) => warn!("Could not move snapshot {} into the object store: {}", id, e),
+                Err(e) => warn!("Could not move snapshot {} into the object store: {}", id, e),
+            }
+        }
+        if migrated > 0 {
+            info!("Moved {} snapshot archive(s) into the object store", migrated);
+            self.sweep().await;
+        }
+        migrated
+    }
+
+    async fn write(&self, targets: Vec<SnapshotTarget>, trigger: SnapshotTrigger) -> Result<SnapshotManifest, SnapshotError> {
+        let dir = self.dir.clone();
+        let (manifest, stored) = tokio::task::spawn_blocking(move || write_snapshot(&dir, &targets, trigger))
+            .await
+            .map_err(io::Error::other)??;
+        info!(
+            "Created {:?} snapshot {} ({} files, {} bytes, {} new)",
+            trigger, manifest.id, manifest.files.len(), manifest.size_bytes, stored,
+        );
+        Ok(manifest)
+    }
+
+    async fn remove(&self, id: Uuid) -> Result<(), SnapshotError> {
+        match tokio::fs::remove_file(self.manifest_path(id)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(SnapshotError::NotFound(id)),
+            Err(e) => return Err(e.into()),
+        }
+        match tokio::fs::remove_file(self.dir.join(format!("{}.tar.gz", id))).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove blobs no manifest uses; returns the bytes freed
+    async fn sweep(&self) -> u64 {
+        let dir = self.dir.clone();
+        match tokio::task::spawn_blocking(move || sweep_objects(&dir)).await.map_err(io::Error::other) {
+            Ok(Ok(freed)) => freed,
+            Ok(Err(e)) | Err(e) => {
+                warn!("Could not sweep snapshot objects: {}", e);
+                0
+            }
+        }
+    }
+
+    async fn prune_locked(&self) -> Vec<Uuid> {
+        let expired = self.retention().expired(&self.list().await, Utc::now());
+        let mut removed = Vec::new();
+        for id in expired {
+            match self.remove(id).await {
+                Ok(()) => removed.push(id),
+                Err(e) => warn!("Could not prune snapshot {}: {}", id, e),
+            }
+        }
+        if !removed.is_empty() {
+            self.sweep().await;
+        }
+        removed
+    }
+
+    fn manifest_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.dir.join(OBJECTS_DIR)
+    }
+}
+
+/// Hashes and counts everything written through it
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+    written: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, hasher: Sha256::new(), written: 0 }
+    }
+
+    fn finish(self) -> (W, String, u64) {
+        (self.inner, hex::encode(self.hasher.finalize()), self.written)
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Every manifest in `dir`, and whether all of them could be read
+fn read_manifests(dir: &Path) -> (Vec<SnapshotManifest>, bool) {
+    let mut manifests = Vec::new();
+    let mut complete = true;
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (manifests, complete);
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        match std::fs::read_to_string(&path).map(|c| serde_json::from_str::<SnapshotManifest>(&c)) {
+            Ok(Ok(manifest)) => manifests.push(manifest),
+            Ok(Err(e)) => {
+                warn!("Ignoring unreadable snapshot manifest {:?}: {}", path, e);
+                complete = false;
+            }
+            Err(e) => {
+                warn!("Could not read snapshot manifest {:?}: {}", path, e);
+                complete = false;
+            }
+        }
+    }
+    (manifests, complete)
+}
+
+/// Files under `path` as `/`-separated relative paths, sorted; a file
+/// target is a single entry with an empty path
+fn collect_files(path: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    if path.is_file() {
+        return Ok(vec![(String::new(), path.to_path_buf())]);
+    }
+    let mut files = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                let full = entry.path();
+                let relative = full.strip_prefix(path).unwrap_or(&full)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((relative, full));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the manifest and how many bytes of new blobs it stored
+fn write_snapshot(dir: &Path, targets: &[SnapshotTarget], trigger: SnapshotTrigger) -> Result<(SnapshotManifest, u64), SnapshotError> {
+    let targets: Vec<SnapshotTarget> = targets.iter().filter(|t| t.path.exists()).cloned().collect();
+    if targets.is_empty() {
+        return Err(SnapshotError::NothingToSnapshot);
+    }
+    let objects = dir.join(OBJECTS_DIR);
+    std::fs::create_dir_all(&objects)?;
+
+    let id = Uuid::new_v4();
+    let created_at = Utc::now();
+    let mut files = Vec::new();
+    let mut stored = 0;
+    for target in &targets {
+        for (relative, full) in collect_files(&target.path)? {
+            let modified = std::fs::metadata(&full)?.modified()?
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs());
+            let mut hasher = HashingWriter::new(io::sink());
+            io::copy(&mut File::open(&full)?, &mut hasher)?;
+            let (_, sha256, size) = hasher.finish();
+
+            let blob = objects.join(&sha256);
+            if !blob.exists() {
+                let copied = store_blob(&objects, &mut File::open(&full)?, &sha256)?;
+                if copied != size {
+                    return Err(io::Error::other(format!("{:?} changed while it was being snapshotted", full)).into());
+                }
+                stored += size;
+            }
+            files.push(SnapshotFile { target: target.name.clone(), path: relative, size, sha256, modified });
+        }
+    }
+
+    let size_bytes = files.iter().map(|f| f.size).sum();
+    let manifest = SnapshotManifest { id, created_at, trigger, size_bytes, archive_sha256: None, targets, files };
+    write_manifest(dir, &manifest)?;
+    Ok((manifest, stored))
+}
+
+/// Copy `source` into the store as `sha256`, failing if its contents don't
+/// hash to that. Returns the bytes copied.
+fn store_blob(objects: &Path, source: &mut dyn Read, sha256: &str) -> Result<u64, SnapshotError> {
+    let temp = objects.join(format!(".{}.tmp", Uuid::new_v4()));
+    let result = (|| {
+        let mut out = HashingWriter::new(BufWriter::new(File::create(&temp)?));
+        io::copy(source, &mut out)?;
+        let (buffered, actual, size) = out.finish();
+        if actual != sha256 {
+            return Err(io::Error::other(format!("Contents changed while being stored (expected {}, got {})", sha256, actual)));
+        }
+        buffered.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&temp, objects.join(sha256))?;
+        Ok(size)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    Ok(result?)
+}
+
+/// Write `<id>.json` through a temporary file
+fn write_manifest(dir: &Path, manifest: &SnapshotManifest) -> io::Result<()> {
+    let contents = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let temp = dir.join(format!(".{}.json.tmp", manifest.id));
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, dir.join(format!("{}.json", manifest.id)))
+}
+
+/// Delete blobs and leftover temporary files no manifest needs. Does
+/// nothing if any manifest is unreadable, since its blobs can't be told
+/// apart.
+fn sweep_objects(dir: &Path) -> io::Result<u64> {
+    let (manifests, complete) = read_manifests(dir);
+    if !complete {
+        warn!("Skipping the snapshot object sweep: a manifest is unreadable");
+        return Ok(0);
+    }
+    let used: HashSet<&str> = manifests.iter().flat_map(distinct_blobs).collect();
+
+    let mut freed = 0;
+    let entries = match std::fs::read_dir(dir.join(OBJECTS_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if used.contains(name.as_str()) {
+            continue;
+        }
+        freed += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        std::fs::remove_file(entry.path())?;
+    }
+    Ok(freed)
+}
+
+/// Move a `.tar.gz` snapshot into the object store: check the archive
+/// against its manifest, store each entry as a blob, rewrite the manifest
+/// and drop the archive
+fn migrate_archive(dir: &Path, mut manifest: SnapshotManifest) -> Result<(), SnapshotError> {
+    let corrupt = |reason: String| SnapshotError::Corrupt { id: manifest.id, reason };
+    let archive = dir.join(format!("{}.tar.gz", manifest.id));
+    let objects = dir.join(OBJECTS_DIR);
+    std::fs::create_dir_all(&objects)?;
+
+    let mut hasher = HashingWriter::new(io::sink());
+    io::copy(&mut File::open(&archive).map_err(|e| corrupt(e.to_string()))?, &mut hasher)?;
+    let (_, archive_sha256, _) = hasher.finish();
+    if Some(&archive_sha256) != manifest.archive_sha256.as_ref() {
+        return Err(corrupt("Archive checksum does not match the manifest".to_string()));
+    }
+
+    let index: HashMap<String, usize> = manifest.files.iter().enumerate().map(|(i, f)| (f.entry_name(), i)).collect();
+    let mut seen = HashSet::new();
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(&archive)?));
+    for entry in tar.entries().map_err(|e| corrupt(e.to_string()))? {
+        let mut entry = entry.map_err(|e| corrupt(e.to_string()))?;
+        let name = entry.path().map_err(|e| corrupt(e.to_string()))?.to_string_lossy().into_owned();
+        let Some(&position) = index.get(&name) else {
+            return Err(corrupt(format!("Unexpected entry {}", name)));
+        };
+        if !seen.insert(position) {
+            return Err(corrupt(format!("Duplicate entry {}", name)));
+        }
+        let modified = entry.header().mtime().ok();
+        let file = &mut manifest.files[position];
+        if !objects.join(&file.sha256).exists() {
+            store_blob(&objects, &mut entry, &file.sha256)
+                .map_err(|_| corrupt(format!("{} does not match its recorded hash", name)))?;
+        }
+        file.modified = modified;
+    }
+    if seen.len() != manifest.files.len() {
+        return Err(corrupt(format!("{} file(s) missing from the archive", manifest.files.len() - seen.len())));
+    }
+
+    manifest.archive_sha256 = None;
+    manifest.size_bytes = manifest.files.iter().map(|f| f.size).sum();
+    write_manifest(dir, &manifest)?;
+    std::fs::remove_file(&archive)?;
+    Ok(())
+}
+
+/// Reject manifest targets whose files would land outside the target
+fn validate_manifest_target(manifest: &SnapshotManifest, target: &SnapshotTarget) -> Result<(), SnapshotError> {
+    let escapes = manifest.files.iter()
+        .filter(|f| f.target == target.name)
+        .any(|f| Path::new(&f.path).components().any(|c| !matches!(c, Component::Normal(_))));
+    if escapes || target.path.file_name().is_none() {
+        return Err(SnapshotError::Corrupt { id: manifest.id, reason: format!("Invalid paths for target {}", target.name) });
+    }
+    Ok(())
+}
+
+/// Files under `path` modified after `since`
+fn count_modified_since(path: &Path, since: SystemTime) -> io::Result<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let mut count = 0;
+    for (_, full) in collect_files(path)? {
+        if std::fs::metadata(&full)?.modified()? > since {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// A target rebuilt next to its live path, waiting to be swapped in
+struct StagedTarget {
+    live: PathBuf,
+    staging: PathBuf,
+    replaced: PathBuf,
+}
+
+/// `<parent>/.<name>.<suffix>-<id>`, on the same filesystem as the target
+fn sibling(path: &Path, suffix: &str, id: Uuid) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}-{}", name, suffix, id))
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn discard_staged(staged: &[StagedTarget]) {
+    for target in staged {
+        let _ = remove_path(&target.staging);
+    }
+}
+
+fn stage_restore(objects: &Path, manifest: &SnapshotManifest) -> Result<Vec<StagedTarget>, SnapshotError> {
+    let mut staged = Vec::new();
+    let mut roots = HashMap::new();
+    for target in &manifest.targets {
+        let staging = sibling(&target.path, "restore", manifest.id);
+        remove_path(&staging)?;
+        let is_file = manifest.files.iter().any(|f| f.target == target.name && f.path.is_empty());
+        if let Some(parent) = staging.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if !is_file {
+            std::fs::create_dir_all(&staging)?;
+        }
+        roots.insert(target.name.clone(), staging.clone());
+        staged.push(StagedTarget {
+            live: target.path.clone(),
+            replaced: sibling(&target.path, "replaced", manifest.id),
+            staging,
+        });
+    }
+
+    if let Err(e) = rebuild_files(objects, manifest, &roots) {
+        discard_staged(&staged);
+        return Err(e);
+    }
+    Ok(staged)
+}
+
+/// Copy each file's blob under its target's staging path, checking its
+/// hash and giving back its modification time
+fn rebuild_files(objects: &Path, manifest: &SnapshotManifest, roots: &HashMap<String, PathBuf>) -> Result<(), SnapshotError> {
+    let corrupt = |reason: String| SnapshotError::Corrupt { id: manifest.id, reason };
+    for file in &manifest.files {
+        let Some(root) = roots.get(&file.target) else {
+            return Err(corrupt(format!("{} belongs to no target", file.entry_name())));
+        };
+        let destination = if file.path.is_empty() { root.clone() } else { root.join(&file.path) };
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut blob = File::open(objects.join(&file.sha256))
+            .map_err(|_| corrupt(format!("Blob for {} is missing", file.entry_name())))?;
+        let mut out = HashingWriter::new(BufWriter::new(File::create(&destination)?));
+        io::copy(&mut blob, &mut out)?;
+        out.flush()?;
+        let (_, sha256, size) = out.finish();
+        if size != file.size || sha256 != file.sha256 {
+            return Err(corrupt(format!("{} does not match its recorded hash", file.entry_name())));
+        }
+
+        if let Some(modified) = file.modified {
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(modified);
+            File::options().write(true).open(&destination)?.set_modified(modified)?;
+        }
+    }
+    Ok(())
+}
+
+/// Move each live target aside, rename its staged copy into place and drop
+/// the old data
+fn swap_in(staged: &[StagedTarget]) -> io::Result<()> {
+    for target in staged {
+        remove_path(&target.replaced)?;
+        if target.live.exists() {
+            std::fs::rename(&target.live, &target.replaced)?;
+        }
+        std::fs::rename(&target.staging, &target.live)?;
+        remove_path(&target.replaced)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-snapshots-{}", Uuid::new_v4()))
+    }
+
+    fn snapshot_at(created_at: DateTime<Utc>) -> SnapshotManifest {
+        SnapshotManifest {
+            id: Uuid::new_v4(),
+            created_at,
+            trigger: SnapshotTrigger::Scheduled,
+            size_bytes: 0,
+            archive_sha256: None,
+            targets: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// The names of the blobs in the store
+    fn blobs(manager: &SnapshotManager) -> HashSet<String> {
+        std::fs::read_dir(manager.dir().join(OBJECTS_DIR)).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Every file under `path` with its contents
+    fn tree(path: &Path) -> Vec<(String, Vec<u8>)> {
+        collect_files(path).unwrap().into_iter()
+            .map(|(relative, full)| (relative, std::fs::read(full).unwrap()))
+            .collect()
+    }
+
+    fn manager(dir: &Path) -> SnapshotManager {
+        SnapshotManager::new(dir.join("snapshots"), &SnapshotConfig::default())
+            .with_target("world", dir.join("worlds/default")).unwrap()
+            .with_target("mod_list", dir.join("mods/index.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_retention_keeps_last_and_one_per_day() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 18, 0, 0).unwrap();
+        let hours_ago = |hours: i64| snapshot_at(now - chrono::Duration::hours(hours));
+        // Hourly today, then two a day going back ten days
+        let mut snapshots: Vec<SnapshotManifest> = (0..6).map(hours_ago).collect();
+        for day in 1..=10 {
+            snapshots.push(hours_ago(day * 24));
+            snapshots.push(hours_ago(day * 24 + 3));
+        }
+        let policy = RetentionPolicy { keep_last: 3, keep_daily_days: 7, max_total_bytes: None };
+
+        let expired: HashSet<Uuid> = policy.expired(&snapshots, now).into_iter().collect();
+        let kept: Vec<&SnapshotManifest> = snapshots.iter().filter(|s| !expired.contains(&s.id)).collect();
+
+        // Three newest, then the newest of each of the six days before today
+        assert_eq!(kept.len(), 3 + 6);
+        assert!(snapshots[..3].iter().all(|s| !expired.contains(&s.id)));
+        for day in 1..=6 {
+            assert!(!expired.contains(&snapshots[6 + (day - 1) * 2].id), "day {}", day);
+            assert!(expired.contains(&snapshots[6 + (day - 1) * 2 + 1].id), "day {}", day);
+        }
+        assert!(snapshots[6 + 6 * 2..].iter().all(|s| expired.contains(&s.id)));
+        assert_eq!(policy.expired(&snapshots[..2], now), Vec::<Uuid>::new());
+    }
+
+    #[test]
+    fn test_retention_size_budget_keeps_daily_snapshots() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 18, 0, 0).unwrap();
+        // Each snapshot holds one file no other has
+        let sized = |hours: i64, size: u64| {
+            let snapshot = snapshot_at(now - chrono::Duration::hours(hours));
+            let sha256 = snapshot.id.to_string();
+            let file = SnapshotFile { target: "world".to_string(), path: "level.dat".to_string(), size, sha256, modified: None };
+            SnapshotManifest { size_bytes: size, files: vec![file], ..snapshot }
+        };
+        // Four today, then one a day for three days
+        let snapshots = vec![
+            sized(0, 40), sized(1, 30), sized(2, 30), sized(3, 10),
+            sized(24, 50), sized(48, 50), sized(72, 50),
+        ];
+        let policy = RetentionPolicy { keep_last: 10, keep_daily_days: 7, max_total_bytes: Some(200) };
+
+        // The four daily snapshots use 190 bytes; the 30-byte one after the
+        // newest doesn't fit, so it and everything older but the dailies go
+        let expired = policy.expired(&snapshots, now);
+        assert_eq!(expired, vec![snapshots[1].id, snapshots[2].id, snapshots[3].id]);
+
+        // Without daily keeps the budget is taken newest first: 160 bytes
+        // fit, the sixth snapshot would make it 210
+        let policy = RetentionPolicy { keep_daily_days: 0, ..policy };
+        let expired = policy.expired(&snapshots, now);
+        assert_eq!(expired, vec![snapshots[5].id, snapshots[6].id]);
+
+        // The newest snapshot is never pruned, even alone over budget
+        let policy = RetentionPolicy { max_total_bytes: Some(1), ..policy };
+        assert_eq!(policy.expired(&snapshots[..1], now), Vec::<Uuid>::new());
+
+        // Blobs shared with a kept snapshot cost nothing
+        let mut shared = snapshots.clone();
+        for snapshot in &mut shared[1..] {
+            snapshot.files[0].sha256 = shared_sha();
+        }
+        shared[0].files[0].sha256 = shared_sha();
+        let policy = RetentionPolicy { max_total_bytes: Some(40), ..policy };
+        assert_eq!(policy.expired(&shared, now), Vec::<Uuid>::new());
+    }
+
+    fn shared_sha() -> String {
+        "ab".repeat(32)
+    }
+
+    #[tokio::test]
+    async fn test_prune_removes_expired_archives() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("worlds/default")).unwrap();
+        std::fs::write(dir.join("worlds/default/level.dat"), b"level").unwrap();
+        let config = SnapshotConfig { keep_last: 2, keep_daily_days: 0, ..SnapshotConfig::default() };
+        let manager = SnapshotManager::new(dir.join("snapshots"), &config)
+            .with_target("world", dir.join("worlds/default")).unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            ids.push(manager.create(SnapshotTrigger::Manual).await.unwrap().id);
+        }
+
+        let listed: Vec<Uuid> = manager.list().await.into_iter().map(|s| s.id).collect();
+        assert_eq!(listed, vec![ids[3], ids[2]]);
+        assert!(!dir.join(format!("snapshots/{}.tar.gz", ids[0])).exists());
+        assert!(matches!(manager.delete(ids[0]).await, Err(SnapshotError::NotFound(_))));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_restore_round_trip_with_safety_snapshot() {
+        let dir = temp_dir();
+        let world = dir.join("worlds/default");
+        std::fs::create_dir_all(world.join("region")).unwrap();
+        std::fs::write(world.join("level.dat"), b"spawn at 0,0").unwrap();
+        std::fs::write(world.join("region/r.0.0.bin"), vec![7u8; 100_000]).unwrap();
+        std::fs::create_dir_all(dir.join("mods")).unwrap();
+        std::fs::write(dir.join("mods/index.toml"), b"[mods]").unwrap();
+        let manager = manager(&dir);
+
+        let snapshot = manager.create(SnapshotTrigger::Manual).await.unwrap();
+        assert_eq!(snapshot.files.len(), 3);
+        assert!(snapshot.size_bytes > 0);
+
+        // Give the edits an mtime after the snapshot, whatever the clock resolution
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        std::fs::write(world.join("level.dat"), b"spawn moved").unwrap();
+        std::fs::write(world.join("griefed.bin"), b"x").unwrap();
+        std::fs::write(dir.join("mods/index.toml"), b"[mods]\nbroken = true").unwrap();
+
+        let err = manager.restore(snapshot.id, false).await.unwrap_err();
+        assert!(matches!(err, SnapshotError::NewerFiles { count: 2, .. }), "{}", err);
+
+        let safety = manager.restore(snapshot.id, true).await.unwrap().unwrap();
+        assert_eq!(safety.trigger, SnapshotTrigger::PreRestore);
+        assert_eq!(std::fs::read(world.join("level.dat")).unwrap(), b"spawn at 0,0");
+        assert_eq!(std::fs::read(world.join("region/r.0.0.bin")).unwrap(), vec![7u8; 100_000]);
+        assert!(!world.join("griefed.bin").exists());
+        assert_eq!(std::fs::read(dir.join("mods/index.toml")).unwrap(), b"[mods]");
+
+        // Restored files keep their snapshot mtimes, so restoring again needs no force
+        manager.restore(snapshot.id, false).await.unwrap();
+        let leftovers: Vec<_> = std::fs::read_dir(dir.join("worlds")).unwrap().collect();
+        assert_eq!(leftovers.len(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_files_are_stored_once() {
+        let dir = temp_dir();
+        let world = dir.join("worlds/default");
+        std::fs::create_dir_all(world.join("region")).unwrap();
+        std::fs::write(world.join("level.dat"), b"spawn at 0,0").unwrap();
+        std::fs::write(world.join("region/r.0.0.bin"), vec![7u8; 200_000]).unwrap();
+        std::fs::write(world.join("region/r.0.1.bin"), vec![9u8; 150_000]).unwrap();
+        let manager = manager(&dir);
+
+        let first = manager.create(SnapshotTrigger::Manual).await.unwrap();
+        let first_tree = tree(&world);
+        let first_blobs = blobs(&manager);
+        assert_eq!(first_blobs.len(), 3);
+
+        std::fs::write(world.join("region/r.0.1.bin"), vec![4u8; 150_000]).unwrap();
+        let second = manager.create(SnapshotTrigger::Manual).await.unwrap();
+        let second_tree = tree(&world);
+
+        // Only the changed file's blob was added
+        let second_blobs = blobs(&manager);
+        let added: Vec<&String> = second_blobs.difference(&first_blobs).collect();
+        let changed = second.files.iter().find(|f| f.path == "region/r.0.1.bin").unwrap();
+        assert_eq!(added, vec![&changed.sha256]);
+
+        let report = manager.report().await;
+        assert_eq!(report.logical_bytes, 2 * (12 + 200_000 + 150_000));
+        assert_eq!(report.stored_bytes, 12 + 200_000 + 2 * 150_000);
+        let unique: Vec<u64> = report.snapshots.iter().map(|s| s.unique_bytes).collect();
+        assert_eq!(unique, vec![150_000, 150_000]);
+        assert_eq!(report.snapshots[0].id, second.id);
+
+        // Both restore byte for byte
+        manager.restore(first.id, true).await.unwrap();
+        assert_eq!(tree(&world), first_tree);
+        manager.restore(second.id, true).await.unwrap();
+        assert_eq!(tree(&world), second_tree);
+
+        // The restores' safety snapshots share blobs with the two above.
+        // Deleting all but the second frees the one blob only they used.
+        let others: Vec<Uuid> = manager.list().await.iter().map(|s| s.id).filter(|id| *id != second.id).collect();
+        let mut freed = 0;
+        for id in others {
+            freed += manager.delete(id).await.unwrap();
+        }
+        assert_eq!(freed, 150_000);
+        assert_eq!(blobs(&manager), second.files.iter().map(|f| f.sha256.clone()).collect());
+        manager.restore(second.id, true).await.unwrap();
+        assert_eq!(tree(&world), second_tree);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_archives_move_into_the_object_store() {
+        let dir = temp_dir();
+        let world = dir.join("worlds/default");
+        std::fs::create_dir_all(&world).unwrap();
+        let manager = manager(&dir);
+        std::fs::create_dir_all(manager.dir()).unwrap();
+
+        // A snapshot as they were written before the object store
+        let id = Uuid::new_v4();
+        let contents = b"old world".to_vec();
+        let archive = manager.dir().join(format!("{}.tar.gz", id));
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(File::create(&archive).unwrap(), flate2::Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(1_700_000_000);
+        builder.append_data(&mut header, "world/level.dat", contents.as_slice()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        let sha256 = hex::encode(Sha256::digest(&contents));
+        let legacy = SnapshotManifest {
+            id,
+            created_at: Utc::now() - chrono::Duration::hours(1),
+            trigger: SnapshotTrigger::Scheduled,
+            size_bytes: std::fs::metadata(&archive).unwrap().len(),
+            archive_sha256: Some(hex::encode(Sha256::digest(std::fs::read(&archive).unwrap()))),
+            targets: vec![SnapshotTarget { name: "world".to_string(), path: world.clone() }],
+            files: vec![SnapshotFile { target: "world".to_string(), path: "level.dat".to_string(), size: 9, sha256: sha256.clone(), modified: None }],
+        };
+        write_manifest(manager.dir(), &legacy).unwrap();
+
+        assert_eq!(manager.migrate_archives().await, 1);
+        assert!(!archive.exists());
+        assert_eq!(blobs(&manager), HashSet::from([sha256]));
+        let migrated = manager.get(id).await.unwrap();
+        assert_eq!((migrated.archive_sha256, migrated.size_bytes), (None, 9));
+
+        manager.restore(id, true).await.unwrap();
+        assert_eq!(std::fs::read(world.join("level.dat")).unwrap(), contents);
+        let modified = std::fs::metadata(world.join("level.dat")).unwrap().modified().unwrap();
+        assert_eq!(modified, SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        assert_eq!(manager.migrate_archives().await, 0);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_blob_restore_leaves_live_data() {
+        let dir = temp_dir();
+        let world = dir.join("worlds/default");
+        std::fs::create_dir_all(&world).unwrap();
+        std::fs::write(world.join("level.dat"), b"original").unwrap();
+        let manager = manager(&dir);
+        let snapshot = manager.create(SnapshotTrigger::Manual).await.unwrap();
+
+        std::fs::write(world.join("level.dat"), b"current").unwrap();
+        let blob = manager.dir().join(OBJECTS_DIR).join(&snapshot.files[0].sha256);
+        let mut bytes = std::fs::read(&blob).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        std::fs::write(&blob, bytes).unwrap();
+
+        let err = manager.restore(snapshot.id, true).await.unwrap_err();
+        assert!(matches!(err, SnapshotError::Corrupt { .. }), "{}", err);
+        assert_eq!(std::fs::read(world.join("level.dat")).unwrap(), b"current");
+        // No staging directory or safety snapshot was left behind
+        assert_eq!(std::fs::read_dir(dir.join("worlds")).unwrap().count(), 1);
+        assert_eq!(manager.list().await.len(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
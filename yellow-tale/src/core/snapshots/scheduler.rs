@@ -0,0 +1,279 @@
+//! Scheduled snapshots
+//!
+//! Takes a snapshot every `interval_minutes`, holding off while a
+//! [`Quiesce`] hook says the data is in use (by default, while the game
+//! runs). Schedule and retention changes apply without a restart.
+
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::{RetentionPolicy, SnapshotError, SnapshotManager, SnapshotTrigger};
+use crate::core::config::SnapshotConfig;
+use crate::core::launcher::{LauncherService, ProcessState};
+
+/// How soon a snapshot held off by the quiesce hook is tried again
+pub const BUSY_RETRY: Duration = Duration::from_secs(60);
+
+/// Says when the snapshotted data is in use
+#[async_trait]
+pub trait Quiesce: Send + Sync {
+    /// Why a snapshot shouldn't be taken right now, or `None` if it can be
+    async fn busy(&self) -> Option<String>;
+}
+
+#[async_trait]
+impl Quiesce for LauncherService {
+    async fn busy(&self) -> Option<String> {
+        matches!(self.poll_status().await, ProcessState::Running { .. })
+            .then(|| "The game is running".to_string())
+    }
+}
+
+/// How a scheduled run went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ScheduledRun {
+    Created { id: Uuid },
+    /// Held off by the quiesce hook; tried again after [`BUSY_RETRY`]
+    Deferred { reason: String },
+    /// None of the targets exist yet
+    NothingToSnapshot,
+    Failed { error: String },
+}
+
+/// What `get_snapshot_schedule` answers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    pub schedule: SnapshotConfig,
+    /// None while scheduling is off
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_run: Option<ScheduledRun>,
+}
+
+struct SchedulerState {
+    schedule: SnapshotConfig,
+    due: Instant,
+    last_run_at: Option<DateTime<Utc>>,
+    last_run: Option<ScheduledRun>,
+}
+
+/// Runs a `SnapshotManager` on a schedule
+pub struct SnapshotScheduler {
+    manager: Arc<SnapshotManager>,
+    quiesce: Option<Arc<dyn Quiesce>>,
+    state: Mutex<SchedulerState>,
+    /// Wakes the background task when the schedule changes
+    changed: Arc<Notify>,
+}
+
+impl SnapshotScheduler {
+    pub fn new(manager: Arc<SnapshotManager>, schedule: &SnapshotConfig) -> Self {
+        manager.set_retention(RetentionPolicy::from(schedule));
+        Self {
+            manager,
+            quiesce: None,
+            state: Mutex::new(SchedulerState {
+                schedule: schedule.clone(),
+                due: Instant::now() + interval(schedule),
+                last_run_at: None,
+                last_run: None,
+            }),
+            changed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Consulted before each scheduled snapshot while
+    /// `only_when_game_stopped` is set
+    pub fn with_quiesce(mut self, quiesce: Arc<dyn Quiesce>) -> Self {
+        self.quiesce = Some(quiesce);
+        self
+    }
+
+    pub fn manager(&self) -> &Arc<SnapshotManager> {
+        &self.manager
+    }
+
+    pub fn status(&self) -> ScheduleStatus {
+        let state = self.state.lock().unwrap();
+        let next_run_at = state.schedule.scheduled.then(|| {
+            let wait = state.due.saturating_duration_since(Instant::now());
+            Utc::now() + chrono::Duration::from_std(wait).unwrap_or_default()
+        });
+        ScheduleStatus {
+            schedule: state.schedule.clone(),
+            next_run_at,
+            last_run_at: state.last_run_at,
+            last_run: state.last_run.clone(),
+        }
+    }
+
+    /// Replace the schedule and retention; the next run is a full interval
+    /// from now
+    pub fn set_schedule(&self, schedule: &SnapshotConfig) {
+        self.manager.set_retention(RetentionPolicy::from(schedule));
+        {
+            let mut state = self.state.lock().unwrap();
+            state.schedule = schedule.clone();
+            state.due = Instant::now() + interval(schedule);
+        }
+        self.changed.notify_one();
+    }
+
+    /// Take a scheduled snapshot now, unless the quiesce hook holds it off
+    pub async fn run_now(&self) -> ScheduledRun {
+        let only_when_game_stopped = self.state.lock().unwrap().schedule.only_when_game_stopped;
+        let busy = match (&self.quiesce, only_when_game_stopped) {
+            (Some(quiesce), true) => quiesce.busy().await,
+            _ => None,
+        };
+
+        let run = match busy {
+            Some(reason) => ScheduledRun::Deferred { reason },
+            None => match self.manager.create(SnapshotTrigger::Scheduled).await {
+                Ok(manifest) => ScheduledRun::Created { id: manifest.id },
+                Err(SnapshotError::NothingToSnapshot) => ScheduledRun::NothingToSnapshot,
+                Err(e) => {
+                    warn!("Scheduled snapshot failed: {}", e);
+                    ScheduledRun::Failed { error: e.to_string() }
+                }
+            },
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let wait = match run {
+            ScheduledRun::Deferred { .. } => BUSY_RETRY.min(interval(&state.schedule)),
+            _ => interval(&state.schedule),
+        };
+        state.due = Instant::now() + wait;
+        state.last_run_at = Some(Utc::now());
+        state.last_run = Some(run.clone());
+        run
+    }
+
+    /// Run the schedule until the scheduler is dropped
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let scheduler: Weak<Self> = Arc::downgrade(self);
+        let changed = self.changed.clone();
+        tokio::spawn(async move {
+            loop {
+                let due = match scheduler.upgrade() {
+                    Some(scheduler) => {
+                        let state = scheduler.state.lock().unwrap();
+                        state.schedule.scheduled.then_some(state.due)
+                    }
+                    None => break,
+                };
+                let Some(due) = due else {
+                    changed.notified().await;
+                    continue;
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep_until(due) => {}
+                    _ = changed.notified() => continue,
+                }
+
+                let Some(scheduler) = scheduler.upgrade() else {
+                    break;
+                };
+                if let ScheduledRun::Deferred { reason } = scheduler.run_now().await {
+                    info!("Scheduled snapshot deferred: {}", reason);
+                }
+            }
+        })
+    }
+}
+
+fn interval(schedule: &SnapshotConfig) -> Duration {
+    Duration::from_secs(schedule.interval_minutes.max(1) * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct FakeGame {
+        running: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Quiesce for FakeGame {
+        async fn busy(&self) -> Option<String> {
+            self.running.load(Ordering::SeqCst).then(|| "The game is running".to_string())
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yt-snapshot-schedule-{}", Uuid::new_v4()))
+    }
+
+    fn scheduler(dir: &Path, game: Arc<FakeGame>) -> SnapshotScheduler {
+        let manager = SnapshotManager::new(dir.join("snapshots"), &SnapshotConfig::default())
+            .with_target("world", dir.join("worlds/default")).unwrap();
+        SnapshotScheduler::new(Arc::new(manager), &SnapshotConfig::default()).with_quiesce(game)
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_the_game_to_stop() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("worlds/default")).unwrap();
+        std::fs::write(dir.join("worlds/default/level.dat"), b"level").unwrap();
+        let game = Arc::new(FakeGame { running: AtomicBool::new(true) });
+        let scheduler = scheduler(&dir, game.clone());
+
+        assert!(matches!(scheduler.run_now().await, ScheduledRun::Deferred { .. }));
+        assert!(scheduler.manager().list().await.is_empty());
+        let retry_at = scheduler.status().next_run_at.unwrap();
+        assert!(retry_at <= Utc::now() + chrono::Duration::from_std(BUSY_RETRY).unwrap());
+
+        scheduler.set_schedule(&SnapshotConfig { only_when_game_stopped: false, ..SnapshotConfig::default() });
+        assert!(matches!(scheduler.run_now().await, ScheduledRun::Created { .. }));
+
+        game.running.store(false, Ordering::SeqCst);
+        scheduler.set_schedule(&SnapshotConfig::default());
+        let run = scheduler.run_now().await;
+        let ScheduledRun::Created { id } = run else { panic!("{:?}", run) };
+        assert_eq!(scheduler.manager().list().await[0].trigger, SnapshotTrigger::Scheduled);
+        assert_eq!(scheduler.status().last_run, Some(ScheduledRun::Created { id }));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_snapshot_keeps_earlier_ones() {
+        let dir = temp_dir();
+        let world = dir.join("worlds/default");
+        std::fs::create_dir_all(&world).unwrap();
+        std::fs::write(world.join("level.dat"), b"level").unwrap();
+        let game = Arc::new(FakeGame { running: AtomicBool::new(false) });
+        let scheduler = scheduler(&dir, game);
+        let mut good = Vec::new();
+        for _ in 0..2 {
+            let ScheduledRun::Created { id } = scheduler.run_now().await else { panic!("snapshot failed") };
+            good.insert(0, id);
+        }
+
+        // A FIFO where the world directory was can't be archived
+        std::fs::remove_dir_all(&world).unwrap();
+        let status = std::process::Command::new("mkfifo").arg(&world).status().unwrap();
+        assert!(status.success());
+
+        assert!(matches!(scheduler.run_now().await, ScheduledRun::Failed { .. }));
+        let kept: Vec<Uuid> = scheduler.manager().list().await.into_iter().map(|s| s.id).collect();
+        assert_eq!(kept, good);
+        // Both snapshots share one blob, and nothing half-written was left
+        let objects = std::fs::read_dir(scheduler.manager().dir().join(crate::core::snapshots::OBJECTS_DIR)).unwrap().count();
+        assert_eq!(objects, 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -4,16 +4,23 @@
 //! - Structured logging with tracing
 //! - Log file rotation
 //! - Metric aggregation
+//! - Changing the log level while running
 
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use thiserror::Error;
 use tracing_subscriber::{
     fmt,
     layer::SubscriberExt,
+    reload,
     util::SubscriberInitExt,
     EnvFilter,
+    Registry,
 };
 
+/// Swaps the filter of the installed subscriber
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
 #[derive(Error, Debug)]
 pub enum TelemetryError {
     #[error("Failed to initialize logging: {0}")]
@@ -21,17 +28,32 @@ pub enum TelemetryError {
     
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+    
+    #[error("Invalid log level '{0}'")]
+    InvalidLevel(String),
 }
 
-/// Initialize the logging system
-pub fn init_logging() -> Result<(), TelemetryError> {
-    // Create a filter from RUST_LOG env var or use default
+/// The default filter, or RUST_LOG's, behind a handle `set_log_level` can swap
+fn reloadable_filter() -> reload::Layer<EnvFilter, Registry> {
     let filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info,yellow_tale=debug"));
-    
-    // Set up the subscriber with formatting
+    let (layer, handle) = reload::Layer::new(filter);
+    let _ = FILTER.set(handle);
+    layer
+}
+
+/// Replace the log filter of the running launcher, e.g. with `debug`
+pub fn set_log_level(level: &str) -> Result<(), TelemetryError> {
+    let filter = EnvFilter::try_new(level).map_err(|_| TelemetryError::InvalidLevel(level.to_string()))?;
+    let handle = FILTER.get().ok_or_else(|| TelemetryError::InitFailed("logging is not initialized".to_string()))?;
+    handle.reload(filter).map_err(|e| TelemetryError::InitFailed(e.to_string()))
+}
+
+/// Initialize the logging system
+pub fn init_logging() -> Result<(), TelemetryError> {
+    // Set up the subscriber with formatting, filtered by RUST_LOG or the default
     tracing_subscriber::registry()
-        .with(filter)
+        .with(reloadable_filter())
         .with(fmt::layer()
             .with_target(true)
             .with_thread_ids(false)
@@ -54,11 +76,8 @@ pub fn init_logging_with_file(log_dir: PathBuf) -> Result<(), TelemetryError> {
         .append(true)
         .open(&log_file)?;
     
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info,yellow_tale=debug"));
-    
     tracing_subscriber::registry()
-        .with(filter)
+        .with(reloadable_filter())
         .with(fmt::layer()
             .with_target(true)
             .with_ansi(false)
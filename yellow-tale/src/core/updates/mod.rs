@@ -0,0 +1,544 @@
+//! Updates Module
+//!
+//! Keeps the launcher itself current:
+//! - Asks the central server for the newest release on the configured channel
+//! - Compares it against `VERSION` with semver precedence
+//! - Downloads the artifact into `updates/` in the data dir, checking its
+//!   size and SHA-256 before staging it
+//! - Remembers the version being replaced so an update can be rolled back
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use tracing::{info, warn};
+
+use crate::core::health::{CheckResult, HealthCheck};
+use crate::core::util::safe_filename;
+
+#[derive(Error, Debug)]
+pub enum UpdateError {
+    #[error("Could not reach the update server: {0}")]
+    Fetch(String),
+
+    #[error("Invalid version '{0}'")]
+    InvalidVersion(String),
+
+    #[error("Already up to date ({0})")]
+    UpToDate(String),
+
+    #[error("Updates are not published for this platform")]
+    UnsupportedPlatform,
+
+    #[error("An update is already downloading")]
+    AlreadyDownloading,
+
+    #[error("Download failed: {0}")]
+    DownloadFailed(String),
+
+    #[error("Size mismatch for {version}: expected {expected} bytes, got {actual}")]
+    SizeMismatch { version: String, expected: u64, actual: u64 },
+
+    #[error("Checksum mismatch for {version}: expected {expected}, got {actual}")]
+    ChecksumMismatch { version: String, expected: String, actual: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+}
+
+/// A published launcher build for one platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseArtifact {
+    pub version: String,
+    pub channel: String,
+    pub platform: String,
+    /// Absolute, or relative to the update server
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+    pub changelog: String,
+    pub published_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    pub version: String,
+    pub channel: String,
+    pub changelog: String,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Where release information and artifacts come from
+#[async_trait]
+pub trait UpdateSource: Send + Sync {
+    async fn latest(&self, channel: UpdateChannel, platform: &str) -> Result<Option<ReleaseArtifact>, UpdateError>;
+
+    /// Releases newer than `since`, newest first
+    async fn changelog_since(&self, channel: UpdateChannel, platform: &str, since: &str) -> Result<Vec<ChangelogEntry>, UpdateError>;
+
+    /// Write the artifact to `dest`, reporting bytes written so far
+    async fn download(&self, artifact: &ReleaseArtifact, dest: &Path, progress: &(dyn Fn(u64) + Send + Sync)) -> Result<(), UpdateError>;
+}
+
+/// Whether `candidate` has higher semver precedence than `current`, so
+/// `1.0.0` beats `1.0.0-rc.1` and `1.0.0-beta.10` beats `1.0.0-beta.2`
+pub fn is_newer(candidate: &str, current: &str) -> Result<bool, UpdateError> {
+    let parse = |v: &str| semver::Version::parse(v.trim().trim_start_matches('v'))
+        .map_err(|_| UpdateError::InvalidVersion(v.to_string()));
+    Ok(parse(candidate)?.cmp_precedence(&parse(current)?).is_gt())
+}
+
+/// Platform name releases are published under
+pub fn current_platform() -> Option<&'static str> {
+    match std::env::consts::OS {
+        "windows" => Some("windows"),
+        "macos" => Some("macos"),
+        "linux" => Some("linux"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdatePhase {
+    #[default]
+    Idle,
+    Downloading,
+    Verifying,
+    Ready,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateProgress {
+    pub phase: UpdatePhase,
+    pub version: Option<String>,
+    pub downloaded_bytes: u64,
+    pub total_bytes: u64,
+    pub error: Option<String>,
+}
+
+/// Result of `check_for_updates`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub update_available: bool,
+    pub latest: Option<ReleaseArtifact>,
+    /// Everything released since the running version
+    pub changelog: Vec<ChangelogEntry>,
+}
+
+/// A verified artifact waiting to be installed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StagedUpdate {
+    pub version: String,
+    pub path: PathBuf,
+    pub sha256: String,
+    pub staged_at: DateTime<Utc>,
+}
+
+/// The install an update replaces, kept so it can be restored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollbackInfo {
+    pub version: String,
+    pub path: PathBuf,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UpdateState {
+    staged: Option<StagedUpdate>,
+    rollback: Option<RollbackInfo>,
+}
+
+/// Checks for, downloads and stages launcher updates
+pub struct UpdateManager {
+    updates_dir: PathBuf,
+    state_path: PathBuf,
+    current_version: String,
+    install_path: PathBuf,
+    channel: UpdateChannel,
+    source: Box<dyn UpdateSource>,
+    progress: Arc<Mutex<UpdateProgress>>,
+    state: tokio::sync::Mutex<UpdateState>,
+}
+
+impl UpdateManager {
+    /// Downloads live in `<data_dir>/updates`
+    pub async fn load(data_dir: &Path, current_version: &str, channel: UpdateChannel, source: Box<dyn UpdateSource>) -> Self {
+        let updates_dir = data_dir.join("updates");
+        let state_path = updates_dir.join("update_state.json");
+        let state = match tokio::fs::read_to_string(&state_path).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable update state: {}", e);
+                UpdateState::default()
+            }),
+            Err(_) => UpdateState::default(),
+        };
+
+        Self {
+            updates_dir,
+            state_path,
+            current_version: current_version.to_string(),
+            install_path: std::env::current_exe().unwrap_or_default(),
+            channel,
+            source,
+            progress: Arc::new(Mutex::new(UpdateProgress::default())),
+            state: tokio::sync::Mutex::new(state),
+        }
+    }
+
+    /// The executable recorded as the rollback target; the running one by default
+    pub fn with_install_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.install_path = path.into();
+        self
+    }
+
+    pub fn channel(&self) -> UpdateChannel {
+        self.channel
+    }
+
+    pub fn updates_dir(&self) -> &Path {
+        &self.updates_dir
+    }
+
+    pub async fn check(&self) -> Result<UpdateCheck, UpdateError> {
+        let platform = current_platform().ok_or(UpdateError::UnsupportedPlatform)?;
+        let latest = self.source.latest(self.channel, platform).await?;
+        let update_available = match &latest {
+            Some(release) => is_newer(&release.version, &self.current_version)?,
+            None => false,
+        };
+        let changelog = if update_available {
+            self.source.changelog_since(self.channel, platform, &self.current_version).await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(UpdateCheck {
+            current_version: self.current_version.clone(),
+            channel: self.channel,
+            update_available,
+            latest,
+            changelog,
+        })
+    }
+
+    /// Claims the download slot for the newest release and returns it; call
+    /// `download` with it afterwards, possibly on another task
+    pub async fn begin_download(&self) -> Result<ReleaseArtifact, UpdateError> {
+        let check = self.check().await?;
+        let release = match check.latest {
+            Some(release) if check.update_available => release,
+            _ => return Err(UpdateError::UpToDate(self.current_version.clone())),
+        };
+
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        if matches!(progress.phase, UpdatePhase::Downloading | UpdatePhase::Verifying) {
+            return Err(UpdateError::AlreadyDownloading);
+        }
+        *progress = UpdateProgress {
+            phase: UpdatePhase::Downloading,
+            version: Some(release.version.clone()),
+            downloaded_bytes: 0,
+            total_bytes: release.size,
+            error: None,
+        };
+        Ok(release)
+    }
+
+    /// Download, verify and stage `release`, recording the running install
+    /// as the rollback target
+    pub async fn download(&self, release: &ReleaseArtifact) -> Result<StagedUpdate, UpdateError> {
+        let result = self.fetch_and_stage(release).await;
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        match &result {
+            Ok(_) => progress.phase = UpdatePhase::Ready,
+            Err(e) => {
+                progress.phase = UpdatePhase::Failed;
+                progress.error = Some(e.to_string());
+            }
+        }
+        result
+    }
+
+    async fn fetch_and_stage(&self, release: &ReleaseArtifact) -> Result<StagedUpdate, UpdateError> {
+        let downloads = self.updates_dir.join(".downloads");
+        tokio::fs::create_dir_all(&downloads).await?;
+        let file_name = artifact_file_name(release);
+        let partial = downloads.join(format!("{}.part", file_name));
+
+        info!("Downloading launcher {} ({} bytes)", release.version, release.size);
+        let progress = self.progress.clone();
+        let report = move |bytes: u64| {
+            progress.lock().unwrap_or_else(|e| e.into_inner()).downloaded_bytes = bytes;
+        };
+        let downloaded = match self.source.download(release, &partial, &report).await {
+            Ok(()) => {
+                self.progress.lock().unwrap_or_else(|e| e.into_inner()).phase = UpdatePhase::Verifying;
+                verify(&partial, release).await
+            }
+            Err(e) => Err(e),
+        };
+        let sha256 = match downloaded {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(e);
+            }
+        };
+
+        let version_dir = self.updates_dir.join(safe_filename(&release.version));
+        tokio::fs::create_dir_all(&version_dir).await?;
+        let path = version_dir.join(&file_name);
+        tokio::fs::rename(&partial, &path).await?;
+
+        let staged = StagedUpdate { version: release.version.clone(), path, sha256, staged_at: Utc::now() };
+        let mut state = self.state.lock().await;
+        if let Some(previous) = state.staged.take() {
+            if previous.version != staged.version {
+                if let Some(dir) = previous.path.parent() {
+                    let _ = tokio::fs::remove_dir_all(dir).await;
+                }
+            }
+        }
+        state.rollback = Some(RollbackInfo {
+            version: self.current_version.clone(),
+            path: self.install_path.clone(),
+            recorded_at: Utc::now(),
+        });
+        state.staged = Some(staged.clone());
+        self.save(&state).await?;
+        info!("Launcher {} staged at {:?}", staged.version, staged.path);
+        Ok(staged)
+    }
+
+    pub fn progress(&self) -> UpdateProgress {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub async fn staged(&self) -> Option<StagedUpdate> {
+        self.state.lock().await.staged.clone()
+    }
+
+    pub async fn rollback(&self) -> Option<RollbackInfo> {
+        self.state.lock().await.rollback.clone()
+    }
+
+    async fn save(&self, state: &UpdateState) -> Result<(), UpdateError> {
+        tokio::fs::create_dir_all(&self.updates_dir).await?;
+        let contents = serde_json::to_string_pretty(state)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        tokio::fs::write(&self.state_path, contents).await?;
+        Ok(())
+    }
+}
+
+/// Degraded after a failed download, until the next one starts
+#[async_trait]
+impl HealthCheck for UpdateManager {
+    fn name(&self) -> &str {
+        "updates"
+    }
+
+    async fn health_check(&self) -> CheckResult {
+        let progress = self.progress();
+        match progress.phase {
+            UpdatePhase::Failed => CheckResult::degraded(progress.error.unwrap_or_else(|| "Download failed".to_string())),
+            _ => CheckResult::ok(),
+        }
+    }
+}
+
+/// Last segment of the artifact URL, kept inside the updates directory
+fn artifact_file_name(release: &ReleaseArtifact) -> String {
+    let name = release.url.split(['?', '#']).next().unwrap_or_default()
+        .rsplit('/').next().unwrap_or_default();
+    let name = safe_filename(name).trim_start_matches('.').to_string();
+    if name.is_empty() {
+        format!("yellow-tale-{}-{}", safe_filename(&release.version), release.platform)
+    } else {
+        name
+    }
+}
+
+/// Check the size and SHA-256 of a downloaded artifact, returning its hash
+async fn verify(path: &Path, release: &ReleaseArtifact) -> Result<String, UpdateError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        size += read as u64;
+        hasher.update(&buffer[..read]);
+    }
+
+    if size != release.size {
+        return Err(UpdateError::SizeMismatch { version: release.version.clone(), expected: release.size, actual: size });
+    }
+    let actual = hex::encode(hasher.finalize());
+    if !actual.eq_ignore_ascii_case(&release.sha256) {
+        return Err(UpdateError::ChecksumMismatch {
+            version: release.version.clone(),
+            expected: release.sha256.clone(),
+            actual,
+        });
+    }
+    Ok(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTIFACT: &[u8] = b"yellow tale launcher build";
+
+    struct FixtureSource {
+        release: ReleaseArtifact,
+    }
+
+    #[async_trait]
+    impl UpdateSource for FixtureSource {
+        async fn latest(&self, _channel: UpdateChannel, _platform: &str) -> Result<Option<ReleaseArtifact>, UpdateError> {
+            Ok(Some(self.release.clone()))
+        }
+
+        async fn changelog_since(&self, _channel: UpdateChannel, _platform: &str, _since: &str) -> Result<Vec<ChangelogEntry>, UpdateError> {
+            Ok(vec![ChangelogEntry {
+                version: self.release.version.clone(),
+                channel: self.release.channel.clone(),
+                changelog: self.release.changelog.clone(),
+                published_at: self.release.published_at,
+            }])
+        }
+
+        async fn download(&self, _artifact: &ReleaseArtifact, dest: &Path, progress: &(dyn Fn(u64) + Send + Sync)) -> Result<(), UpdateError> {
+            tokio::fs::write(dest, ARTIFACT).await?;
+            progress(ARTIFACT.len() as u64);
+            Ok(())
+        }
+    }
+
+    fn release(sha256: &str) -> ReleaseArtifact {
+        ReleaseArtifact {
+            version: "0.2.0".to_string(),
+            channel: "stable".to_string(),
+            platform: current_platform().unwrap_or("linux").to_string(),
+            url: "/releases/yellow-tale-0.2.0.tar.gz".to_string(),
+            sha256: sha256.to_string(),
+            size: ARTIFACT.len() as u64,
+            changelog: "Faster launches".to_string(),
+            published_at: Utc::now(),
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("yellow-tale-updates-{}", uuid::Uuid::new_v4()))
+    }
+
+    async fn update_manager(dir: &Path, sha256: &str) -> UpdateManager {
+        UpdateManager::load(dir, "0.1.0", UpdateChannel::Stable, Box::new(FixtureSource { release: release(sha256) }))
+            .await
+            .with_install_path(dir.join("yellow-tale"))
+    }
+
+    #[test]
+    fn test_version_comparison() {
+        assert!(is_newer("0.2.0", "0.1.0").unwrap());
+        assert!(is_newer("0.10.0", "0.9.1").unwrap());
+        assert!(!is_newer("0.1.0", "0.1.0").unwrap());
+        assert!(!is_newer("0.1.0", "0.2.0").unwrap());
+        assert!(is_newer("1.0.0", "1.0.0-rc.1").unwrap());
+        assert!(!is_newer("1.0.0-rc.1", "1.0.0").unwrap());
+        assert!(is_newer("1.0.0-beta.10", "1.0.0-beta.2").unwrap());
+        assert!(is_newer("1.0.0-beta", "1.0.0-alpha.3").unwrap());
+        assert!(!is_newer("1.0.0+build.7", "1.0.0").unwrap());
+        assert!(is_newer("v1.2.0", "1.1.9").unwrap());
+        assert!(matches!(is_newer("1.2", "1.0.0"), Err(UpdateError::InvalidVersion(_))));
+    }
+
+    #[tokio::test]
+    async fn test_download_stages_update_and_records_rollback() {
+        if current_platform().is_none() {
+            return;
+        }
+        let dir = temp_dir();
+        let manager = update_manager(&dir, &hex::encode(Sha256::digest(ARTIFACT))).await;
+
+        let check = manager.check().await.unwrap();
+        assert!(check.update_available);
+        assert_eq!(check.changelog.len(), 1);
+
+        let release = manager.begin_download().await.unwrap();
+        let staged = manager.download(&release).await.unwrap();
+        assert_eq!(tokio::fs::read(&staged.path).await.unwrap(), ARTIFACT);
+        assert_eq!(staged.path, dir.join("updates/0.2.0/yellow-tale-0.2.0.tar.gz"));
+
+        let progress = manager.progress();
+        assert_eq!(progress.phase, UpdatePhase::Ready);
+        assert_eq!(progress.downloaded_bytes, progress.total_bytes);
+
+        let rollback = manager.rollback().await.unwrap();
+        assert_eq!(rollback.version, "0.1.0");
+        assert_eq!(rollback.path, dir.join("yellow-tale"));
+
+        // Staging and rollback metadata survive a restart.
+        let reloaded = update_manager(&dir, "").await;
+        assert_eq!(reloaded.staged().await, Some(staged));
+        assert_eq!(reloaded.rollback().await, Some(rollback));
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_hash_mismatch_is_rejected() {
+        if current_platform().is_none() {
+            return;
+        }
+        let dir = temp_dir();
+        let manager = update_manager(&dir, &"0".repeat(64)).await;
+
+        let release = manager.begin_download().await.unwrap();
+        let err = manager.download(&release).await.unwrap_err();
+        assert!(matches!(err, UpdateError::ChecksumMismatch { .. }), "{}", err);
+
+        let progress = manager.progress();
+        assert_eq!(progress.phase, UpdatePhase::Failed);
+        assert!(progress.error.unwrap().contains("Checksum mismatch"));
+        assert!(manager.staged().await.is_none());
+        assert!(manager.rollback().await.is_none());
+
+        let mut leftovers = tokio::fs::read_dir(dir.join("updates/.downloads")).await.unwrap();
+        assert!(leftovers.next_entry().await.unwrap().is_none());
+        assert!(!dir.join("updates/0.2.0").exists());
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
@@ -0,0 +1,142 @@
+//! Email verification codes
+//!
+//! `request_email_verification` mails a six-digit code, kept hashed in
+//! `email_verifications` with its expiry, one row per user. A new code
+//! replaces the last one, but not within `resend_cooldown` of it. Wrong
+//! guesses count against the code; once `max_attempts` are used up it stops
+//! working and a new one has to be requested.
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationPolicy {
+    pub code_ttl: Duration,
+    /// Wrong codes allowed before the code stops working
+    pub max_attempts: i32,
+    pub resend_cooldown: Duration,
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        Self {
+            code_ttl: Duration::minutes(15),
+            max_attempts: 5,
+            resend_cooldown: Duration::seconds(60),
+        }
+    }
+}
+
+/// A code as stored, without the code itself
+#[derive(Debug, Clone)]
+pub struct StoredCode {
+    pub code_hash: String,
+    pub expires_at: DateTime<Utc>,
+    /// Wrong codes entered so far
+    pub attempts: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeCheck {
+    Confirmed,
+    Wrong,
+    Expired,
+    /// Too many wrong codes; this one can't be confirmed any more
+    Exhausted,
+}
+
+impl VerificationPolicy {
+    /// How long until another code may be sent after one sent at `last_sent_at`
+    pub fn resend_wait(&self, last_sent_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<Duration> {
+        let wait = last_sent_at? + self.resend_cooldown - now;
+        (wait > Duration::zero()).then_some(wait)
+    }
+
+    pub fn check_code(&self, user_id: Uuid, stored: &StoredCode, code: &str, now: DateTime<Utc>) -> CodeCheck {
+        if stored.attempts >= self.max_attempts {
+            return CodeCheck::Exhausted;
+        }
+        if stored.expires_at <= now {
+            return CodeCheck::Expired;
+        }
+        if hash_code(user_id, code.trim()) == stored.code_hash {
+            CodeCheck::Confirmed
+        } else {
+            CodeCheck::Wrong
+        }
+    }
+}
+
+/// Six digits, leading zeros included
+pub fn generate_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+/// Salted with the user so equal codes don't hash alike
+pub fn hash_code(user_id: Uuid, code: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(user_id.as_bytes());
+    hasher.update(code.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(user_id: Uuid, code: &str, now: DateTime<Utc>) -> StoredCode {
+        StoredCode {
+            code_hash: hash_code(user_id, code),
+            expires_at: now + VerificationPolicy::default().code_ttl,
+            attempts: 0,
+        }
+    }
+
+    #[test]
+    fn test_codes_are_six_digits() {
+        for _ in 0..100 {
+            let code = generate_code();
+            assert_eq!(code.len(), 6);
+            assert!(code.chars().all(|c| c.is_ascii_digit()));
+        }
+    }
+
+    #[test]
+    fn test_code_confirms_until_it_expires() {
+        let policy = VerificationPolicy::default();
+        let user = Uuid::new_v4();
+        let now = Utc::now();
+        let code = stored(user, "042917", now);
+
+        assert_eq!(policy.check_code(user, &code, " 042917 ", now), CodeCheck::Confirmed);
+        // Another user's code with the same digits doesn't match
+        assert_eq!(policy.check_code(Uuid::new_v4(), &code, "042917", now), CodeCheck::Wrong);
+        assert_eq!(policy.check_code(user, &code, "042917", code.expires_at), CodeCheck::Expired);
+    }
+
+    #[test]
+    fn test_wrong_codes_use_up_the_code() {
+        let policy = VerificationPolicy::default();
+        let user = Uuid::new_v4();
+        let now = Utc::now();
+        let mut code = stored(user, "042917", now);
+
+        for _ in 0..policy.max_attempts {
+            assert_eq!(policy.check_code(user, &code, "000000", now), CodeCheck::Wrong);
+            code.attempts += 1;
+        }
+        assert_eq!(policy.check_code(user, &code, "042917", now), CodeCheck::Exhausted);
+    }
+
+    #[test]
+    fn test_resends_wait_for_the_cooldown() {
+        let policy = VerificationPolicy::default();
+        let now = Utc::now();
+
+        assert_eq!(policy.resend_wait(None, now), None);
+        assert_eq!(policy.resend_wait(Some(now - Duration::seconds(20)), now), Some(Duration::seconds(40)));
+        assert_eq!(policy.resend_wait(Some(now - policy.resend_cooldown), now), None);
+    }
+}
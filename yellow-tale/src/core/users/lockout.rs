@@ -0,0 +1,105 @@
+//! Lockout after failed password attempts
+//!
+//! Failures are kept in `login_attempts`, so restarting doesn't reset them,
+//! and are counted both per account and per source (the device that sent
+//! them). Once `threshold` failures fall within `window`, each attempt has to
+//! wait twice as long after the last failure as the one before, up to
+//! `max_backoff`. Proving the password clears the account's failures.
+
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    /// Failures within `window` allowed before attempts have to wait
+    pub threshold: u32,
+    /// How long a failure counts
+    pub window: Duration,
+    /// Wait after the failure that reached `threshold`
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            window: Duration::minutes(15),
+            base_backoff: Duration::seconds(30),
+            max_backoff: Duration::minutes(15),
+        }
+    }
+}
+
+impl LockoutPolicy {
+    /// How long until another attempt is allowed, given past failures in
+    /// time order
+    pub fn retry_after(&self, failures: &[DateTime<Utc>], now: DateTime<Utc>) -> Option<Duration> {
+        let since = now - self.window;
+        let recent: Vec<_> = failures.iter().filter(|at| **at > since).collect();
+        let excess = recent.len().checked_sub(self.threshold as usize)?;
+        let last = **recent.last()?;
+
+        // Doubling past 2^16 can't stay under any sensible cap anyway
+        let backoff = self.base_backoff
+            .checked_mul(1 << excess.min(16) as i32)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff));
+        let wait = last + backoff - now;
+        (wait > Duration::zero()).then_some(wait)
+    }
+}
+
+/// `retry_after` in whole seconds, rounded up
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    let millis = wait.num_milliseconds().max(0) as u64;
+    millis.div_ceil(1000).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waits_double_after_the_threshold() {
+        let policy = LockoutPolicy::default();
+        let start = Utc::now();
+        let mut failures = Vec::new();
+
+        for i in 0..4 {
+            failures.push(start + Duration::seconds(i));
+            assert_eq!(policy.retry_after(&failures, start + Duration::seconds(i)), None);
+        }
+
+        let mut now = start + Duration::seconds(4);
+        failures.push(now);
+        assert_eq!(policy.retry_after(&failures, now), Some(Duration::seconds(30)));
+        assert_eq!(policy.retry_after(&failures, now + Duration::seconds(10)), Some(Duration::seconds(20)));
+
+        now += Duration::seconds(30);
+        assert_eq!(policy.retry_after(&failures, now), None);
+        failures.push(now);
+        assert_eq!(policy.retry_after(&failures, now), Some(Duration::seconds(60)));
+
+        now += Duration::seconds(60);
+        failures.push(now);
+        assert_eq!(policy.retry_after(&failures, now), Some(Duration::seconds(120)));
+    }
+
+    #[test]
+    fn test_backoff_is_capped_and_old_failures_age_out() {
+        let policy = LockoutPolicy::default();
+        let now = Utc::now();
+
+        let burst = vec![now; 40];
+        assert_eq!(policy.retry_after(&burst, now), Some(policy.max_backoff));
+
+        let stale: Vec<_> = (0..10).map(|i| now - policy.window - Duration::seconds(i)).collect();
+        assert_eq!(policy.retry_after(&stale, now), None);
+    }
+
+    #[test]
+    fn test_retry_after_rounds_up() {
+        assert_eq!(retry_after_secs(Duration::milliseconds(1)), 1);
+        assert_eq!(retry_after_secs(Duration::milliseconds(29_001)), 30);
+        assert_eq!(retry_after_secs(Duration::seconds(30)), 30);
+    }
+}
@@ -2,7 +2,10 @@ use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
-use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sqlx::PgPool;
@@ -10,6 +13,21 @@ use thiserror::Error;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::core::db::supervisor::QueryError;
+use crate::core::mail::{Email, MailError, Mailer};
+use crate::core::presence::{PresenceChange, PresenceHub};
+use crate::core::relay::{JoinValidator, RelayIdentity};
+
+pub mod email;
+pub mod lockout;
+pub mod refresh;
+pub mod search;
+
+use email::{CodeCheck, StoredCode, VerificationPolicy};
+use lockout::LockoutPolicy;
+use refresh::{RefreshCheck, StoredRefresh};
+use search::{SearchCursor, UserSearchPage};
+
 #[derive(Error, Debug)]
 pub enum AuthError {
     #[error("Username already exists")]
@@ -27,12 +45,45 @@ pub enum AuthError {
     #[error("Session expired")]
     SessionExpired,
     
+    /// The access token ran out but the refresh token can still renew it
+    #[error("Access token expired; refresh the session")]
+    AccessTokenExpired,
+    
+    #[error("Refresh token already used; every session from this login was signed out")]
+    RefreshTokenReused,
+    
     #[error("Session revoked")]
     SessionRevoked,
     
     #[error("Invalid session")]
     InvalidSession,
     
+    /// Too many failed password attempts; `retry_after` is in seconds
+    #[error("Too many failed attempts; try again in {retry_after}s")]
+    TooManyAttempts { retry_after: u64 },
+    
+    #[error("Email already verified")]
+    EmailAlreadyVerified,
+    
+    /// A code was sent recently; `retry_after` is in seconds
+    #[error("A code was sent recently; try again in {retry_after}s")]
+    VerificationCooldown { retry_after: u64 },
+    
+    #[error("Invalid verification code")]
+    InvalidVerificationCode,
+    
+    #[error("Verification code expired; request a new one")]
+    VerificationCodeExpired,
+    
+    #[error("Too many wrong codes; request a new one")]
+    VerificationCodeExhausted,
+    
+    #[error("Email delivery not configured")]
+    MailerNotConfigured,
+    
+    #[error("Could not send email: {0}")]
+    Mail(#[from] MailError),
+    
     #[error("Password too weak: {0}")]
     WeakPassword(String),
     
@@ -49,6 +100,15 @@ pub enum AuthError {
     HashingFailed(String),
 }
 
+impl QueryError for AuthError {
+    fn sqlx_error(&self) -> Option<&sqlx::Error> {
+        match self {
+            AuthError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
@@ -59,14 +119,33 @@ pub struct User {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub last_seen_at: Option<DateTime<Utc>>,
+    /// Confirmed through `confirm_email`
+    #[serde(default)]
+    pub email_verified: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSession {
     pub id: Uuid,
     pub user_id: Uuid,
+    /// Access token, sent with every request
     pub token: String,
     pub expires_at: DateTime<Utc>,
+    /// Exchanged once for a new pair through `refresh_session`
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+/// A signed-in device, as listed to the account owner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When the session can no longer be refreshed
+    pub expires_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -92,11 +171,38 @@ pub struct AuthResponse {
 
 pub struct UserService {
     pool: PgPool,
+    lockout: LockoutPolicy,
+    mailer: Option<Arc<dyn Mailer>>,
+    verification: VerificationPolicy,
+    presence: PresenceHub,
 }
 
 impl UserService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            lockout: LockoutPolicy::default(),
+            mailer: None,
+            verification: VerificationPolicy::default(),
+            presence: PresenceHub::new(),
+        }
+    }
+    
+    /// Where status changes are announced
+    pub fn with_presence(mut self, presence: PresenceHub) -> Self {
+        self.presence = presence;
+        self
+    }
+    
+    pub fn with_lockout(mut self, lockout: LockoutPolicy) -> Self {
+        self.lockout = lockout;
+        self
+    }
+    
+    /// Sends email verification codes
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = Some(mailer);
+        self
     }
     
     fn validate_username(username: &str) -> Result<(), AuthError> {
@@ -219,6 +325,7 @@ impl UserService {
             status: "online".to_string(),
             created_at: Utc::now(),
             last_seen_at: Some(Utc::now()),
+            email_verified: false,
         };
         
         let session = self.create_session(user_id, None, None).await?;
@@ -226,10 +333,62 @@ impl UserService {
         Ok(AuthResponse { user, session })
     }
     
+    /// Fail with `TooManyAttempts` while `account` or `source` has to wait
+    async fn check_lockout(&self, account: &str, source: Option<&str>) -> Result<(), AuthError> {
+        let now = Utc::now();
+        let since = now - self.lockout.window;
+        
+        let failures: Vec<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT attempted_at FROM login_attempts WHERE username = $1 AND attempted_at > $2 ORDER BY attempted_at"
+        )
+        .bind(account)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut wait = self.lockout.retry_after(&failures, now);
+        
+        if let Some(source) = source {
+            let failures: Vec<DateTime<Utc>> = sqlx::query_scalar(
+                "SELECT attempted_at FROM login_attempts WHERE source = $1 AND attempted_at > $2 ORDER BY attempted_at"
+            )
+            .bind(source)
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+            wait = wait.max(self.lockout.retry_after(&failures, now));
+        }
+        
+        match wait {
+            Some(wait) => Err(AuthError::TooManyAttempts { retry_after: lockout::retry_after_secs(wait) }),
+            None => Ok(()),
+        }
+    }
+    
+    async fn record_failed_attempt(&self, account: &str, source: Option<&str>) -> Result<(), AuthError> {
+        sqlx::query("INSERT INTO login_attempts (username, source) VALUES ($1, $2)")
+            .bind(account)
+            .bind(source)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM login_attempts WHERE attempted_at < $1")
+            .bind(Utc::now() - self.lockout.window)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+    
+    async fn clear_failed_attempts(&self, account: &str) -> Result<(), AuthError> {
+        sqlx::query("DELETE FROM login_attempts WHERE username = $1")
+            .bind(account)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+    
     pub async fn login(&self, req: LoginRequest) -> Result<AuthResponse, AuthError> {
-        let row = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>, String)>(
+        let row = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>, bool, String)>(
             r#"
-            SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at, password_hash
+            SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at, email_verified, password_hash
             FROM users
             WHERE username = $1 OR email = $1
             "#
@@ -238,18 +397,33 @@ impl UserService {
         .fetch_optional(&self.pool)
         .await?;
         
-        let (id, username, display_name, email, avatar_url, _status, created_at, _last_seen_at, password_hash) = 
-            row.ok_or(AuthError::InvalidCredentials)?;
+        // Failures count against the account whichever name was typed
+        let account = match &row {
+            Some(row) => row.1.to_lowercase(),
+            None => req.username_or_email.to_lowercase(),
+        };
+        let source = req.device_info.as_deref();
+        self.check_lockout(&account, source).await?;
+        
+        let Some((id, username, display_name, email, avatar_url, status, created_at, _last_seen_at, email_verified, password_hash)) = row else {
+            self.record_failed_attempt(&account, source).await?;
+            return Err(AuthError::InvalidCredentials);
+        };
         
         if !Self::verify_password(&req.password, &password_hash) {
             warn!("Failed login attempt for: {}", req.username_or_email);
+            self.record_failed_attempt(&account, source).await?;
             return Err(AuthError::InvalidCredentials);
         }
+        self.clear_failed_attempts(&account).await?;
         
         sqlx::query("UPDATE users SET status = 'online', last_seen_at = NOW() WHERE id = $1")
             .bind(id)
             .execute(&self.pool)
             .await?;
+        if status != "online" {
+            self.presence.announce(&self.pool, PresenceChange::new(id, "online")).await;
+        }
         
         let user = User {
             id,
@@ -260,6 +434,7 @@ impl UserService {
             status: "online".to_string(),
             created_at,
             last_seen_at: Some(Utc::now()),
+            email_verified,
         };
         
         let session = self.create_session(id, req.device_info.as_deref(), None).await?;
@@ -270,23 +445,41 @@ impl UserService {
     }
     
     async fn create_session(&self, user_id: Uuid, device_info: Option<&str>, ip: Option<&str>) -> Result<UserSession, AuthError> {
+        Self::insert_session(&self.pool, user_id, Uuid::new_v4(), device_info, ip).await
+    }
+    
+    /// Store a new access and refresh token pair in session `family_id`
+    async fn insert_session<'e>(
+        executor: impl sqlx::PgExecutor<'e>,
+        user_id: Uuid,
+        family_id: Uuid,
+        device_info: Option<&str>,
+        ip: Option<&str>,
+    ) -> Result<UserSession, AuthError> {
         let token = Self::generate_session_token();
-        let token_hash = Self::hash_token(&token);
-        let expires_at = Utc::now() + Duration::days(30);
+        let refresh_token = Self::generate_session_token();
+        let now = Utc::now();
+        let expires_at = now + refresh::access_token_ttl();
+        let refresh_expires_at = now + refresh::refresh_token_ttl();
         
         let session_id: Uuid = sqlx::query_scalar(
             r#"
-            INSERT INTO user_sessions (user_id, token_hash, device_info, ip_address, expires_at)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO user_sessions
+                (user_id, token_hash, refresh_token_hash, family_id, device_info, ip_address, expires_at, refresh_expires_at, last_used_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING id
             "#
         )
         .bind(user_id)
-        .bind(&token_hash)
+        .bind(Self::hash_token(&token))
+        .bind(Self::hash_token(&refresh_token))
+        .bind(family_id)
         .bind(device_info)
         .bind(ip)
         .bind(expires_at)
-        .fetch_one(&self.pool)
+        .bind(refresh_expires_at)
+        .bind(now)
+        .fetch_one(executor)
         .await?;
         
         Ok(UserSession {
@@ -294,29 +487,96 @@ impl UserService {
             user_id,
             token,
             expires_at,
+            refresh_token,
+            refresh_expires_at,
         })
     }
     
+    /// The user behind an access token. An expired one whose session can
+    /// still be refreshed fails with `AccessTokenExpired`.
     pub async fn validate_session(&self, token: &str) -> Result<User, AuthError> {
+        self.authenticate(token).await.map(|(_, user)| user)
+    }
+    
+    /// Like `validate_session`, also returning which session the token belongs to
+    pub async fn authenticate(&self, token: &str) -> Result<(Uuid, User), AuthError> {
         let token_hash = Self::hash_token(token);
         
-        let session = sqlx::query_as::<_, (Uuid, DateTime<Utc>, Option<DateTime<Utc>>)>(
-            "SELECT user_id, expires_at, revoked_at FROM user_sessions WHERE token_hash = $1"
+        let (session_id, user_id, expires_at, refresh_expires_at, revoked_at) = sqlx::query_as::<_, (Uuid, Uuid, DateTime<Utc>, Option<DateTime<Utc>>, Option<DateTime<Utc>>)>(
+            "SELECT id, user_id, expires_at, refresh_expires_at, revoked_at FROM user_sessions WHERE token_hash = $1"
         )
         .bind(&token_hash)
         .fetch_optional(&self.pool)
         .await?
         .ok_or(AuthError::InvalidSession)?;
         
-        if session.2.is_some() {
-            return Err(AuthError::SessionRevoked);
-        }
+        refresh::check_access(expires_at, refresh_expires_at, revoked_at, Utc::now())?;
+        
+        // Minute resolution is plenty for the session list
+        sqlx::query(
+            "UPDATE user_sessions SET last_used_at = NOW() WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')"
+        )
+        .bind(session_id)
+        .execute(&self.pool)
+        .await?;
         
-        if session.1 < Utc::now() {
-            return Err(AuthError::SessionExpired);
+        Ok((session_id, self.get_user(user_id).await?))
+    }
+    
+    /// Exchange a refresh token for a new access and refresh token pair
+    ///
+    /// The old pair stops working. Presenting a refresh token a second time
+    /// signs out every session descended from the same login.
+    pub async fn refresh_session(&self, refresh_token: &str) -> Result<AuthResponse, AuthError> {
+        let mut tx = self.pool.begin().await?;
+        
+        let row = sqlx::query_as::<_, (Uuid, Uuid, Option<Uuid>, Option<DateTime<Utc>>, Option<DateTime<Utc>>, Option<Uuid>, Option<String>, Option<String>)>(
+            r#"
+            SELECT id, user_id, family_id, refresh_expires_at, revoked_at, replaced_by, device_info, ip_address
+            FROM user_sessions
+            WHERE refresh_token_hash = $1
+            FOR UPDATE
+            "#
+        )
+        .bind(Self::hash_token(refresh_token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AuthError::InvalidSession)?;
+        
+        let (session_id, user_id, family_id, refresh_expires_at, revoked_at, replaced_by, device_info, ip_address) = row;
+        let stored = StoredRefresh {
+            session_id,
+            user_id,
+            family_id: family_id.unwrap_or(session_id),
+            refresh_expires_at,
+            revoked_at,
+            replaced_by,
+        };
+        
+        match refresh::check_refresh(&stored, Utc::now()) {
+            RefreshCheck::Rotate => {}
+            RefreshCheck::Replayed { family_id } => {
+                warn!("Refresh token replayed for user {}; revoking session family {}", user_id, family_id);
+                sqlx::query("UPDATE user_sessions SET revoked_at = COALESCE(revoked_at, NOW()) WHERE family_id = $1")
+                    .bind(family_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                return Err(AuthError::RefreshTokenReused);
+            }
+            RefreshCheck::Rejected(rejection) => return Err(rejection.into()),
         }
         
-        self.get_user(session.0).await
+        let session = Self::insert_session(&mut *tx, user_id, stored.family_id, device_info.as_deref(), ip_address.as_deref()).await?;
+        sqlx::query("UPDATE user_sessions SET revoked_at = NOW(), replaced_by = $1 WHERE id = $2")
+            .bind(session.id)
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        
+        let user = self.get_user(user_id).await?;
+        Ok(AuthResponse { user, session })
     }
     
     pub async fn logout(&self, token: &str) -> Result<(), AuthError> {
@@ -330,9 +590,236 @@ impl UserService {
         Ok(())
     }
     
+    /// Replace the password after checking the current one, signing out
+    /// every session except `keep_session`. Returns how many were signed out.
+    ///
+    /// Wrong current passwords count toward the same lockout as failed
+    /// logins. The hash is checked the same way whether or not it matches,
+    /// so only the answer differs.
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        keep_session: Uuid,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<u64, AuthError> {
+        Self::validate_password(new_password)?;
+        
+        let (username, password_hash): (String, String) = sqlx::query_as("SELECT username, password_hash FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        let account = username.to_lowercase();
+        self.check_lockout(&account, None).await?;
+        
+        if !Self::verify_password(old_password, &password_hash) {
+            warn!("Wrong current password in password change for user {}", user_id);
+            self.record_failed_attempt(&account, None).await?;
+            return Err(AuthError::InvalidCredentials);
+        }
+        self.clear_failed_attempts(&account).await?;
+        
+        let new_hash = Self::hash_password(new_password)?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+            .bind(&new_hash)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        let revoked = Self::revoke_sessions_except(&mut *tx, user_id, Some(keep_session)).await?;
+        tx.commit().await?;
+        
+        info!("Password changed for user {}; {} other sessions signed out", user_id, revoked);
+        Ok(revoked)
+    }
+    
+    /// The user's sessions that can still be used or refreshed, most recently used first
+    pub async fn list_sessions(&self, user_id: Uuid) -> Result<Vec<SessionInfo>, AuthError> {
+        let rows = sqlx::query_as::<_, (Uuid, Option<String>, Option<String>, DateTime<Utc>, Option<DateTime<Utc>>, DateTime<Utc>)>(
+            r#"
+            SELECT id, device_info, ip_address, created_at, last_used_at,
+                   GREATEST(expires_at, COALESCE(refresh_expires_at, expires_at))
+            FROM user_sessions
+            WHERE user_id = $1
+              AND revoked_at IS NULL
+              AND GREATEST(expires_at, COALESCE(refresh_expires_at, expires_at)) > NOW()
+            ORDER BY last_used_at DESC NULLS LAST, created_at DESC
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().map(|r| SessionInfo {
+            id: r.0,
+            device_info: r.1,
+            ip_address: r.2,
+            created_at: r.3,
+            last_used_at: r.4,
+            expires_at: r.5,
+        }).collect())
+    }
+    
+    /// Sign out one of the user's sessions
+    pub async fn revoke_session(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AuthError> {
+        let revoked = sqlx::query(
+            "UPDATE user_sessions SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        
+        if revoked == 0 {
+            return Err(AuthError::InvalidSession);
+        }
+        Ok(())
+    }
+    
+    /// Sign out every session of the user, returning how many there were
+    pub async fn revoke_all_sessions(&self, user_id: Uuid) -> Result<u64, AuthError> {
+        Self::revoke_sessions_except(&self.pool, user_id, None).await
+    }
+    
+    /// Sign out every session of the user but `keep_session`
+    pub async fn revoke_other_sessions(&self, user_id: Uuid, keep_session: Uuid) -> Result<u64, AuthError> {
+        Self::revoke_sessions_except(&self.pool, user_id, Some(keep_session)).await
+    }
+    
+    async fn revoke_sessions_except<'e>(
+        executor: impl sqlx::PgExecutor<'e>,
+        user_id: Uuid,
+        keep_session: Option<Uuid>,
+    ) -> Result<u64, AuthError> {
+        let revoked = sqlx::query(
+            "UPDATE user_sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL AND id IS DISTINCT FROM $2"
+        )
+        .bind(user_id)
+        .bind(keep_session)
+        .execute(executor)
+        .await?
+        .rows_affected();
+        Ok(revoked)
+    }
+    
+    /// Mail a code for confirming the account's email address, returning
+    /// when it expires. Replaces any earlier code.
+    pub async fn request_email_verification(&self, user_id: Uuid) -> Result<DateTime<Utc>, AuthError> {
+        let (email, verified): (String, bool) = sqlx::query_as("SELECT email, email_verified FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        if verified {
+            return Err(AuthError::EmailAlreadyVerified);
+        }
+        let mailer = self.mailer.as_ref().ok_or(AuthError::MailerNotConfigured)?;
+        
+        let now = Utc::now();
+        let last_sent_at: Option<DateTime<Utc>> = sqlx::query_scalar("SELECT sent_at FROM email_verifications WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(wait) = self.verification.resend_wait(last_sent_at, now) {
+            return Err(AuthError::VerificationCooldown { retry_after: lockout::retry_after_secs(wait) });
+        }
+        
+        let code = email::generate_code();
+        let expires_at = now + self.verification.code_ttl;
+        // The WHERE repeats the cooldown so two requests can't both send
+        let stored = sqlx::query(
+            r#"
+            INSERT INTO email_verifications (user_id, code_hash, sent_at, expires_at, attempts)
+            VALUES ($1, $2, $3, $4, 0)
+            ON CONFLICT (user_id) DO UPDATE
+                SET code_hash = EXCLUDED.code_hash, sent_at = EXCLUDED.sent_at,
+                    expires_at = EXCLUDED.expires_at, attempts = 0
+                WHERE email_verifications.sent_at <= $5
+            "#
+        )
+        .bind(user_id)
+        .bind(email::hash_code(user_id, &code))
+        .bind(now)
+        .bind(expires_at)
+        .bind(now - self.verification.resend_cooldown)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if stored == 0 {
+            let retry_after = self.verification.resend_cooldown.num_seconds().max(1) as u64;
+            return Err(AuthError::VerificationCooldown { retry_after });
+        }
+        
+        let message = Email {
+            to: email,
+            subject: "Your Yellow Tale verification code".to_string(),
+            body: format!(
+                "Your verification code is {}.
+
+It expires in {} minutes. If you didn't ask for it, you can ignore this email.",
+                code,
+                self.verification.code_ttl.num_minutes(),
+            ),
+        };
+        if let Err(e) = mailer.send(&message).await {
+            // Nothing arrived, so don't hold the user to the cooldown
+            sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?;
+            warn!("Verification email to user {} failed: {}", user_id, e);
+            return Err(e.into());
+        }
+        
+        info!("Sent email verification code to user {}", user_id);
+        Ok(expires_at)
+    }
+    
+    /// Mark the account's email verified if `code` is the one last sent
+    pub async fn confirm_email(&self, user_id: Uuid, code: &str) -> Result<(), AuthError> {
+        let mut tx = self.pool.begin().await?;
+        
+        let (code_hash, expires_at, attempts) = sqlx::query_as::<_, (String, DateTime<Utc>, i32)>(
+            "SELECT code_hash, expires_at, attempts FROM email_verifications WHERE user_id = $1 FOR UPDATE"
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AuthError::InvalidVerificationCode)?;
+        let stored = StoredCode { code_hash, expires_at, attempts };
+        
+        match self.verification.check_code(user_id, &stored, code, Utc::now()) {
+            CodeCheck::Confirmed => {
+                sqlx::query("UPDATE users SET email_verified = TRUE, updated_at = NOW() WHERE id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                info!("Email verified for user {}", user_id);
+                Ok(())
+            }
+            CodeCheck::Wrong => {
+                sqlx::query("UPDATE email_verifications SET attempts = attempts + 1 WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                Err(AuthError::InvalidVerificationCode)
+            }
+            CodeCheck::Expired => Err(AuthError::VerificationCodeExpired),
+            CodeCheck::Exhausted => Err(AuthError::VerificationCodeExhausted),
+        }
+    }
+    
     pub async fn get_user(&self, user_id: Uuid) -> Result<User, AuthError> {
-        let row = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>)>(
-            "SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at FROM users WHERE id = $1"
+        let row = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>, bool)>(
+            "SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at, email_verified FROM users WHERE id = $1"
         )
         .bind(user_id)
         .fetch_optional(&self.pool)
@@ -348,12 +835,13 @@ impl UserService {
             status: row.5,
             created_at: row.6,
             last_seen_at: row.7,
+            email_verified: row.8,
         })
     }
     
     pub async fn get_user_by_username(&self, username: &str) -> Result<User, AuthError> {
-        let row = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>)>(
-            "SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at FROM users WHERE username = $1"
+        let row = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>, bool)>(
+            "SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at, email_verified FROM users WHERE username = $1"
         )
         .bind(username)
         .fetch_optional(&self.pool)
@@ -369,15 +857,29 @@ impl UserService {
             status: row.5,
             created_at: row.6,
             last_seen_at: row.7,
+            email_verified: row.8,
         })
     }
     
+    /// Set the user's status, announcing it to their friends if it changed
     pub async fn update_status(&self, user_id: Uuid, status: &str) -> Result<(), AuthError> {
-        sqlx::query("UPDATE users SET status = $1, last_seen_at = NOW(), updated_at = NOW() WHERE id = $2")
-            .bind(status)
-            .bind(user_id)
-            .execute(&self.pool)
-            .await?;
+        let previous: Option<String> = sqlx::query_scalar(
+            r#"
+            WITH previous AS (SELECT id, status FROM users WHERE id = $2 FOR UPDATE)
+            UPDATE users u SET status = $1, last_seen_at = NOW(), updated_at = NOW()
+            FROM previous
+            WHERE u.id = previous.id
+            RETURNING previous.status
+            "#
+        )
+        .bind(status)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        if previous.is_some_and(|previous| previous != status) {
+            self.presence.announce(&self.pool, PresenceChange::new(user_id, status)).await;
+        }
         Ok(())
     }
     
@@ -401,23 +903,38 @@ impl UserService {
         self.get_user(user_id).await
     }
     
-    pub async fn search_users(&self, query: &str, limit: i64) -> Result<Vec<User>, AuthError> {
-        let pattern = format!("%{}%", query);
-        
-        let rows = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>)>(
+    /// Search by username or display name, leaving out `blocked` users and
+    /// continuing after `cursor`
+    pub async fn search_users(
+        &self,
+        query: &str,
+        blocked: &HashSet<Uuid>,
+        cursor: Option<&SearchCursor>,
+        limit: usize,
+    ) -> Result<UserSearchPage, AuthError> {
+        // Candidates come back in rank order so the cap never drops a better
+        // match; `rank_page` applies the exact ordering and the cursor.
+        let rows = sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, String, DateTime<Utc>, Option<DateTime<Utc>>, bool)>(
             r#"
-            SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at 
+            SELECT id, username, display_name, email, avatar_url, status, created_at, last_seen_at, email_verified
             FROM users 
             WHERE username ILIKE $1 OR display_name ILIKE $1
-            LIMIT $2
+            ORDER BY CASE
+                    WHEN LOWER(username) = LOWER($2) OR LOWER(display_name) = LOWER($2) THEN 0
+                    WHEN username ILIKE $3 OR display_name ILIKE $3 THEN 1
+                    ELSE 2
+                END, last_seen_at DESC NULLS LAST, id
+            LIMIT $4
             "#
         )
-        .bind(&pattern)
-        .bind(limit)
+        .bind(format!("%{}%", query))
+        .bind(query)
+        .bind(format!("{}%", query))
+        .bind(search::MAX_CANDIDATES)
         .fetch_all(&self.pool)
         .await?;
         
-        Ok(rows.into_iter().map(|r| User {
+        let candidates = rows.into_iter().map(|r| User {
             id: r.0,
             username: r.1,
             display_name: r.2,
@@ -426,7 +943,23 @@ impl UserService {
             status: r.5,
             created_at: r.6,
             last_seen_at: r.7,
-        }).collect())
+            email_verified: r.8,
+        }).collect();
+        Ok(search::rank_page(query, candidates, blocked, cursor, limit))
+    }
+}
+
+/// Relay joins are admitted for live account sessions, under the account's username
+#[async_trait::async_trait]
+impl JoinValidator for UserService {
+    async fn validate(&self, token: &str) -> Option<RelayIdentity> {
+        match self.validate_session(token).await {
+            Ok(user) => Some(RelayIdentity { user_id: user.id, username: user.username }),
+            Err(e) => {
+                warn!("Relay join token rejected: {}", e);
+                None
+            }
+        }
     }
 }
 
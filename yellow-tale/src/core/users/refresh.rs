@@ -0,0 +1,150 @@
+//! Access and refresh token lifetimes
+//!
+//! A login issues a short-lived access token and a long-lived refresh token,
+//! both stored hashed on one `user_sessions` row. Refreshing replaces the row
+//! with a new one in the same family, so every refresh moves the refresh
+//! expiry forward. A refresh token is good for one use: presenting one that
+//! was already rotated means it leaked, and the whole family is revoked.
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use super::AuthError;
+
+/// How long an access token is accepted
+pub fn access_token_ttl() -> Duration {
+    Duration::hours(1)
+}
+
+/// How long a refresh token can renew the session, counted from its issue
+pub fn refresh_token_ttl() -> Duration {
+    Duration::days(30)
+}
+
+/// The parts of a `user_sessions` row a refresh is decided on
+#[derive(Debug, Clone)]
+pub struct StoredRefresh {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    /// Sessions descended from the same login
+    pub family_id: Uuid,
+    pub refresh_expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Set once the refresh token was used
+    pub replaced_by: Option<Uuid>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RefreshCheck {
+    /// Issue a new pair and retire this row
+    Rotate,
+    /// The token was already used; revoke `family_id`
+    Replayed { family_id: Uuid },
+    Rejected(RefreshRejection),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshRejection {
+    Revoked,
+    Expired,
+}
+
+impl From<RefreshRejection> for AuthError {
+    fn from(rejection: RefreshRejection) -> Self {
+        match rejection {
+            RefreshRejection::Revoked => AuthError::SessionRevoked,
+            RefreshRejection::Expired => AuthError::SessionExpired,
+        }
+    }
+}
+
+/// Decide what presenting `stored`'s refresh token at `now` does
+pub fn check_refresh(stored: &StoredRefresh, now: DateTime<Utc>) -> RefreshCheck {
+    // Checked before revocation: a rotated row is revoked too
+    if stored.replaced_by.is_some() {
+        return RefreshCheck::Replayed { family_id: stored.family_id };
+    }
+    if stored.revoked_at.is_some() {
+        return RefreshCheck::Rejected(RefreshRejection::Revoked);
+    }
+    match stored.refresh_expires_at {
+        Some(expires_at) if expires_at > now => RefreshCheck::Rotate,
+        _ => RefreshCheck::Rejected(RefreshRejection::Expired),
+    }
+}
+
+/// Whether an access token on a row with these times is still accepted,
+/// telling an expired but refreshable session apart from a finished one
+pub fn check_access(
+    expires_at: DateTime<Utc>,
+    refresh_expires_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(), AuthError> {
+    if revoked_at.is_some() {
+        return Err(AuthError::SessionRevoked);
+    }
+    if expires_at >= now {
+        return Ok(());
+    }
+    match refresh_expires_at {
+        Some(refresh_expires_at) if refresh_expires_at > now => Err(AuthError::AccessTokenExpired),
+        _ => Err(AuthError::SessionExpired),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(now: DateTime<Utc>) -> StoredRefresh {
+        StoredRefresh {
+            session_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            family_id: Uuid::new_v4(),
+            refresh_expires_at: Some(now + refresh_token_ttl()),
+            revoked_at: None,
+            replaced_by: None,
+        }
+    }
+
+    #[test]
+    fn test_rotation_then_replay_revokes_the_family() {
+        let now = Utc::now();
+        let mut row = stored(now);
+        assert_eq!(check_refresh(&row, now), RefreshCheck::Rotate);
+
+        // What rotation leaves behind: the row revoked and pointing at its successor
+        row.revoked_at = Some(now);
+        row.replaced_by = Some(Uuid::new_v4());
+        assert_eq!(check_refresh(&row, now), RefreshCheck::Replayed { family_id: row.family_id });
+    }
+
+    #[test]
+    fn test_logged_out_and_expired_sessions_cannot_refresh() {
+        let now = Utc::now();
+
+        let logged_out = StoredRefresh { revoked_at: Some(now), ..stored(now) };
+        assert_eq!(check_refresh(&logged_out, now), RefreshCheck::Rejected(RefreshRejection::Revoked));
+        assert!(matches!(AuthError::from(RefreshRejection::Revoked), AuthError::SessionRevoked));
+
+        let expired = StoredRefresh { refresh_expires_at: Some(now - Duration::seconds(1)), ..stored(now) };
+        assert_eq!(check_refresh(&expired, now), RefreshCheck::Rejected(RefreshRejection::Expired));
+
+        // Sessions from before refresh tokens existed
+        let legacy = StoredRefresh { refresh_expires_at: None, ..stored(now) };
+        assert_eq!(check_refresh(&legacy, now), RefreshCheck::Rejected(RefreshRejection::Expired));
+    }
+
+    #[test]
+    fn test_expired_access_reports_whether_it_can_be_refreshed() {
+        let now = Utc::now();
+        let refresh = Some(now + refresh_token_ttl());
+
+        assert!(check_access(now + access_token_ttl(), refresh, None, now).is_ok());
+        assert!(matches!(check_access(now - Duration::minutes(1), refresh, None, now), Err(AuthError::AccessTokenExpired)));
+        assert!(matches!(check_access(now - Duration::minutes(1), Some(now - Duration::minutes(1)), None, now), Err(AuthError::SessionExpired)));
+        assert!(matches!(check_access(now - Duration::minutes(1), None, None, now), Err(AuthError::SessionExpired)));
+        assert!(matches!(check_access(now + access_token_ttl(), refresh, Some(now), now), Err(AuthError::SessionRevoked)));
+    }
+}
@@ -0,0 +1,166 @@
+//! User search ranking and pagination
+//!
+//! Matches are ranked exact first, then prefix, then substring, comparing
+//! both the username and the display name without regard to case. Within a
+//! rank the most recently seen user comes first and ties fall back to the
+//! id, so the order is total and a cursor never skips or repeats a user.
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::User;
+
+/// Matches considered per search; pages past this many results are not
+/// reachable
+pub const MAX_CANDIDATES: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchRank {
+    Exact,
+    Prefix,
+    Substring,
+}
+
+impl MatchRank {
+    /// Best match of `query` against any of `names`
+    pub fn of(query: &str, names: &[&str]) -> Option<Self> {
+        let query = query.to_lowercase();
+        names.iter()
+            .filter_map(|name| {
+                let name = name.to_lowercase();
+                if name == query {
+                    Some(Self::Exact)
+                } else if name.starts_with(&query) {
+                    Some(Self::Prefix)
+                } else if name.contains(&query) {
+                    Some(Self::Substring)
+                } else {
+                    None
+                }
+            })
+            .min()
+    }
+}
+
+/// Sort key of the last user on a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SearchCursor {
+    rank: MatchRank,
+    last_seen: Reverse<Option<i64>>,
+    id: Uuid,
+}
+
+impl SearchCursor {
+    fn of(rank: MatchRank, user: &User) -> Self {
+        Self {
+            rank,
+            last_seen: Reverse(user.last_seen_at.map(|t| t.timestamp_micros())),
+            id: user.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let last_seen = self.last_seen.0.map(|t| t.to_string()).unwrap_or_default();
+        format!("{}.{}.{}", self.rank as u8, last_seen, self.id)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.splitn(3, '.');
+        let rank = match parts.next()? {
+            "0" => MatchRank::Exact,
+            "1" => MatchRank::Prefix,
+            "2" => MatchRank::Substring,
+            _ => return None,
+        };
+        let last_seen = match parts.next()? {
+            "" => None,
+            micros => Some(micros.parse().ok()?),
+        };
+        let id = parts.next()?.parse().ok()?;
+        Some(Self { rank, last_seen: Reverse(last_seen), id })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSearchPage {
+    pub users: Vec<User>,
+    pub next_cursor: Option<String>,
+}
+
+/// Rank `candidates` for `query`, drop anyone in `blocked` and return the
+/// page that follows `after`
+pub fn rank_page(query: &str, candidates: Vec<User>, blocked: &HashSet<Uuid>, after: Option<&SearchCursor>, limit: usize) -> UserSearchPage {
+    let mut ranked: Vec<(SearchCursor, User)> = candidates.into_iter()
+        .filter(|user| !blocked.contains(&user.id))
+        .filter_map(|user| {
+            let rank = MatchRank::of(query, &[&user.username, &user.display_name])?;
+            Some((SearchCursor::of(rank, &user), user))
+        })
+        .filter(|(key, _)| after.is_none_or(|after| key > after))
+        .collect();
+    ranked.sort_by_key(|(key, _)| *key);
+
+    let next_cursor = (ranked.len() > limit).then(|| ranked[limit - 1].0.encode());
+    ranked.truncate(limit);
+    UserSearchPage {
+        users: ranked.into_iter().map(|(_, user)| user).collect(),
+        next_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn user(username: &str, display_name: &str, seen_hours_ago: Option<i64>) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            display_name: display_name.to_string(),
+            email: format!("{}@example.com", username),
+            avatar_url: None,
+            status: "offline".to_string(),
+            created_at: Utc::now(),
+            last_seen_at: seen_hours_ago.map(|h| Utc::now() - Duration::hours(h)),
+            email_verified: false,
+        }
+    }
+
+    fn names(page: &UserSearchPage) -> Vec<&str> {
+        page.users.iter().map(|u| u.username.as_str()).collect()
+    }
+
+    #[test]
+    fn test_ranking_order() {
+        let candidates = vec![
+            user("xXanna", "xXanna", Some(1)),
+            user("annabel", "Annabel", Some(48)),
+            user("a_k", "Anna", None),
+            user("anna_b", "Anna B", Some(2)),
+            user("joanna", "Jo", None),
+        ];
+        let page = rank_page("ANNA", candidates, &HashSet::new(), None, 3);
+        assert_eq!(names(&page), vec!["a_k", "anna_b", "annabel"]);
+
+        let cursor = SearchCursor::decode(page.next_cursor.as_deref().unwrap()).unwrap();
+        let candidates = vec![
+            user("xXanna", "xXanna", Some(1)),
+            user("joanna", "Jo", None),
+        ];
+        let rest = rank_page("ANNA", candidates, &HashSet::new(), Some(&cursor), 3);
+        assert_eq!(names(&rest), vec!["xXanna", "joanna"]);
+        assert!(rest.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_blocked_users_are_excluded() {
+        let candidates = vec![user("anna", "Anna", Some(1)), user("annabel", "Annabel", None)];
+        let blocked: HashSet<Uuid> = [candidates[0].id].into();
+        let page = rank_page("anna", candidates, &blocked, None, 20);
+        assert_eq!(names(&page), vec!["annabel"]);
+    }
+}
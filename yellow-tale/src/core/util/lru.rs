@@ -0,0 +1,228 @@
+//! Least-recently-used bookkeeping for size-bounded stores
+//!
+//! Tracks the size, last access and optional expiry of each entry in a
+//! store such as the content cache, and decides what to drop:
+//! - Inserting evicts expired entries first, then the least recently used,
+//!   until the store fits its limit again
+//! - `prune` drops only expired entries
+//!
+//! The index never touches disk; the store deletes whatever it's told was
+//! evicted.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LruEntry {
+    pub size: u64,
+    pub last_access: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl LruEntry {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// What an eviction or prune dropped
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evicted {
+    pub keys: Vec<String>,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LruIndex {
+    max_bytes: u64,
+    entries: HashMap<String, LruEntry>,
+    total_bytes: u64,
+    /// Bytes dropped by eviction or pruning since the index was created or
+    /// loaded
+    #[serde(skip)]
+    evicted_bytes: u64,
+}
+
+impl LruIndex {
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes, ..Self::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    pub fn evicted_bytes(&self) -> u64 {
+        self.evicted_bytes
+    }
+
+    pub fn get(&self, key: &str) -> Option<&LruEntry> {
+        self.entries.get(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    /// Add or replace `key`, evicting other entries until the index fits
+    /// `max_bytes`. An entry larger than the limit on its own still goes in,
+    /// alone.
+    pub fn insert(&mut self, key: &str, size: u64, ttl: Option<Duration>, now: DateTime<Utc>) -> Evicted {
+        let entry = LruEntry { size, last_access: now, expires_at: ttl.map(|ttl| now + ttl) };
+        if let Some(previous) = self.entries.insert(key.to_string(), entry) {
+            self.total_bytes -= previous.size;
+        }
+        self.total_bytes += size;
+
+        self.evict_to_fit(Some(key), now)
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Change the limit, evicting entries until the index fits the new one
+    pub fn set_max_bytes(&mut self, max_bytes: u64, now: DateTime<Utc>) -> Evicted {
+        self.max_bytes = max_bytes;
+        self.evict_to_fit(None, now)
+    }
+
+    /// Mark `key` as used. False when it's missing or has expired.
+    pub fn touch(&mut self, key: &str, now: DateTime<Utc>) -> bool {
+        match self.entries.get_mut(key) {
+            Some(entry) if !entry.is_expired(now) => {
+                entry.last_access = now;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget `key` without counting it as evicted
+    pub fn remove(&mut self, key: &str) -> Option<LruEntry> {
+        let entry = self.entries.remove(key)?;
+        self.total_bytes -= entry.size;
+        Some(entry)
+    }
+
+    /// Drop every expired entry
+    pub fn prune(&mut self, now: DateTime<Utc>) -> Evicted {
+        let mut expired: Vec<String> = self.entries.iter()
+            .filter(|(_, entry)| entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        expired.sort();
+
+        let bytes = expired.iter().map(|key| self.drop_entry(key)).sum();
+        Evicted { keys: expired, bytes }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.total_bytes = 0;
+    }
+
+    /// Drop expired entries, then the least recently used other than `keep`,
+    /// until the index fits `max_bytes`
+    fn evict_to_fit(&mut self, keep: Option<&str>, now: DateTime<Utc>) -> Evicted {
+        let mut evicted = self.prune(now);
+        if self.total_bytes > self.max_bytes {
+            let mut candidates: Vec<(DateTime<Utc>, String)> = self.entries.iter()
+                .filter(|(other, _)| Some(other.as_str()) != keep)
+                .map(|(other, entry)| (entry.last_access, other.clone()))
+                .collect();
+            candidates.sort();
+            for (_, other) in candidates {
+                if self.total_bytes <= self.max_bytes {
+                    break;
+                }
+                evicted.bytes += self.drop_entry(&other);
+                evicted.keys.push(other);
+            }
+        }
+        evicted
+    }
+
+    fn drop_entry(&mut self, key: &str) -> u64 {
+        let size = self.remove(key).map_or(0, |entry| entry.size);
+        self.evicted_bytes += size;
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_least_recently_used_go_first() {
+        let mut index = LruIndex::new(100);
+        for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+            assert!(index.insert(key, 30, None, at(i as i64)).keys.is_empty());
+        }
+        // "a" is now the most recently used
+        assert!(index.touch("a", at(10)));
+
+        let evicted = index.insert("d", 30, None, at(11));
+        assert_eq!(evicted, Evicted { keys: vec!["b".to_string()], bytes: 30 });
+
+        // Room for 80 takes everything else, oldest first
+        let evicted = index.insert("e", 80, None, at(12));
+        assert_eq!(evicted.keys, ["c", "a", "d"]);
+        assert_eq!((index.len(), index.total_bytes(), index.evicted_bytes()), (1, 80, 120));
+
+        // Too big on its own: it stays, alone
+        index.insert("huge", 500, None, at(13));
+        assert_eq!(index.total_bytes(), 500);
+        assert!(index.get("e").is_none());
+    }
+
+    #[test]
+    fn test_expired_entries_are_pruned_and_evicted_first() {
+        let mut index = LruIndex::new(100);
+        index.insert("short", 20, Some(Duration::seconds(60)), at(0));
+        index.insert("long", 20, Some(Duration::seconds(600)), at(1));
+        index.insert("forever", 20, None, at(2));
+
+        assert!(index.touch("short", at(59)));
+        assert!(!index.touch("short", at(60)));
+        let pruned = index.prune(at(60));
+        assert_eq!(pruned, Evicted { keys: vec!["short".to_string()], bytes: 20 });
+        assert!(index.prune(at(61)).keys.is_empty());
+
+        // "long" has expired by now, so it goes before the older "forever"
+        index.insert("new", 70, None, at(700));
+        assert_eq!(index.get("long"), None);
+        assert!(index.get("forever").is_some());
+
+        // Replacing an entry doesn't count it twice
+        index.insert("new", 10, None, at(701));
+        assert_eq!(index.total_bytes(), 30);
+    }
+
+    #[test]
+    fn test_shrinking_the_limit_evicts() {
+        let mut index = LruIndex::new(100);
+        for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+            index.insert(key, 30, None, at(i as i64));
+        }
+        assert!(index.set_max_bytes(200, at(3)).keys.is_empty());
+        let evicted = index.set_max_bytes(40, at(4));
+        assert_eq!(evicted, Evicted { keys: vec!["a".to_string(), "b".to_string()], bytes: 60 });
+        assert_eq!((index.max_bytes(), index.total_bytes()), (40, 30));
+    }
+}
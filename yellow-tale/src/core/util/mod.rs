@@ -3,10 +3,17 @@
 //! Shared utilities used across the application:
 //! - Path helpers
 //! - Hash utilities
+//! - Crash-safe file writes
 //! - Common types
+//! - LRU bookkeeping for size-bounded stores ([`lru`])
+//! - Digest checks of content-addressed files ([`verify`])
+
+pub mod lru;
+pub mod verify;
 
 use std::path::{Path, PathBuf};
 use sha2::{Sha256, Digest};
+use yellow_tale_core::filesystem::{atomic_write_with_backup, load_with_backup};
 
 /// Compute SHA-256 hash of data
 pub fn sha256_hash(data: &[u8]) -> String {
@@ -21,6 +28,27 @@ pub async fn sha256_file(path: &Path) -> std::io::Result<String> {
     Ok(sha256_hash(&data))
 }
 
+/// Write through a temporary file renamed over `path`, so a crash mid-write
+/// never leaves a truncated file behind, keeping the previous save as
+/// `<name>.bak` for [`load_with_backup`]
+pub async fn write_atomic(path: &Path, content: Vec<u8>) -> std::io::Result<()> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || atomic_write_with_backup(&path, &content))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Read and parse `path`, falling back to its `<name>.bak` when it doesn't
+/// parse
+pub async fn read_with_backup<T, F>(path: &Path, parse: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnMut(&[u8]) -> anyhow::Result<T> + Send + 'static,
+{
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || load_with_backup(&path, parse)).await?
+}
+
 /// Ensure a directory exists, creating it if necessary
 pub async fn ensure_dir(path: &Path) -> std::io::Result<()> {
     if !path.exists() {
@@ -0,0 +1,163 @@
+//! SHA-256 verification of content-addressed files
+//!
+//! Walks a store's files in small chunks, hashing each off the async
+//! runtime and yielding between chunks, so a long walk doesn't hold up
+//! whoever else is waiting. Files whose digest doesn't match are deleted
+//! so they get fetched again. Progress is readable while the walk runs.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Files hashed between yields
+pub const VERIFY_CHUNK: usize = 16;
+
+/// A stored file and the digest it was stored under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyTarget {
+    pub key: String,
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+/// Counters for a verification walk, shared with whoever reports on it
+#[derive(Debug, Default)]
+pub struct VerifyProgress {
+    running: AtomicBool,
+    total: AtomicUsize,
+    checked: AtomicUsize,
+    corrupted: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyStatus {
+    pub running: bool,
+    pub total: usize,
+    pub checked: usize,
+    pub corrupted: usize,
+}
+
+impl VerifyProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> VerifyStatus {
+        VerifyStatus {
+            running: self.is_running(),
+            total: self.total.load(Ordering::SeqCst),
+            checked: self.checked.load(Ordering::SeqCst),
+            corrupted: self.corrupted.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Claim the counters for a new walk. False if one is already running.
+    pub fn start(&self, total: usize) -> bool {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.total.store(total, Ordering::SeqCst);
+        self.checked.store(0, Ordering::SeqCst);
+        self.corrupted.store(0, Ordering::SeqCst);
+        true
+    }
+}
+
+/// What a walk found
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    /// Keys whose file didn't match its digest and was deleted
+    pub corrupted: Vec<String>,
+    /// Keys whose file was already gone
+    pub missing: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Every key the store should forget
+    pub fn invalid(&self) -> impl Iterator<Item = &String> {
+        self.corrupted.iter().chain(&self.missing)
+    }
+}
+
+/// Check every target against its digest, deleting the files that don't
+/// match. `progress` must have been claimed with `start`; it's released when
+/// the walk ends.
+pub async fn verify_files(targets: Vec<VerifyTarget>, progress: &VerifyProgress) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for chunk in targets.chunks(VERIFY_CHUNK) {
+        for target in chunk {
+            let path = target.path.clone();
+            let digest = tokio::task::spawn_blocking(move || std::fs::read(&path).map(|data| super::sha256_hash(&data)))
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+            match digest {
+                Ok(digest) if digest.eq_ignore_ascii_case(&target.sha256) => {}
+                Ok(digest) => {
+                    warn!("Cache entry {} is corrupted (sha256 {}), deleting it", target.key, digest);
+                    if let Err(e) = tokio::fs::remove_file(&target.path).await {
+                        warn!("Could not delete {:?}: {}", target.path, e);
+                    }
+                    progress.corrupted.fetch_add(1, Ordering::SeqCst);
+                    report.corrupted.push(target.key.clone());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => report.missing.push(target.key.clone()),
+                Err(e) => warn!("Could not verify cache entry {}: {}", target.key, e),
+            }
+            report.checked += 1;
+            progress.checked.fetch_add(1, Ordering::SeqCst);
+        }
+        tokio::task::yield_now().await;
+    }
+    progress.running.store(false, Ordering::SeqCst);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::util::sha256_hash;
+
+    #[tokio::test]
+    async fn test_corrupted_files_are_found_and_deleted() {
+        let dir = std::env::temp_dir().join(format!("yt-verify-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let mut targets = Vec::new();
+        for i in 0..(VERIFY_CHUNK + 3) {
+            let data = format!("asset {}", i);
+            let sha256 = sha256_hash(data.as_bytes());
+            let path = dir.join(&sha256);
+            tokio::fs::write(&path, &data).await.unwrap();
+            targets.push(VerifyTarget { key: sha256.clone(), path, sha256 });
+        }
+        // A partial write and a file that vanished
+        tokio::fs::write(&targets[3].path, b"asse").await.unwrap();
+        tokio::fs::remove_file(&targets[VERIFY_CHUNK + 1].path).await.unwrap();
+
+        let progress = VerifyProgress::new();
+        assert!(progress.start(targets.len()));
+        assert!(!progress.start(targets.len()));
+        let report = verify_files(targets.clone(), &progress).await;
+
+        assert_eq!(report.checked, targets.len());
+        assert_eq!(report.corrupted, [targets[3].key.clone()]);
+        assert_eq!(report.missing, [targets[VERIFY_CHUNK + 1].key.clone()]);
+        assert!(!targets[3].path.exists());
+        assert!(targets[4].path.exists());
+        assert_eq!(progress.status(), VerifyStatus {
+            running: false,
+            total: targets.len(),
+            checked: targets.len(),
+            corrupted: 1,
+        });
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -24,4 +24,4 @@ pub use core::*;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// IPC API version for schema compatibility
-pub const IPC_API_VERSION: &str = "1.0.0";
+pub const IPC_API_VERSION: &str = core::ipc::IPC_VERSION;
@@ -4,11 +4,12 @@
 //! It initializes the core systems and provides a CLI interface.
 
 use yellow_tale::core::{
-    config::AppConfig,
+    config::{AppConfig, ConfigError},
     telemetry,
-    db::Database,
-    users::UserService,
-    friends::FriendsService,
+    db::supervisor::{DatabaseSupervisor, PostgresConnector, ServiceOptions, SupervisorConfig},
+    mail::{Mailer, SmtpMailer},
+    presence::PresenceHub,
+    users::lockout::LockoutPolicy,
 };
 use tracing::{info, warn};
 use std::path::PathBuf;
@@ -25,47 +26,84 @@ async fn main() -> anyhow::Result<()> {
     tokio::fs::create_dir_all(&data_dir).await.ok();
     
     let config_path = get_config_path();
-    let config = match AppConfig::load(&config_path).await {
-        Ok(cfg) => {
+    let mut config = match AppConfig::load(&config_path).await {
+        Ok((cfg, report)) => {
             info!("Configuration loaded from {:?}", config_path);
+            for issue in &report.errors {
+                warn!("Invalid config value {}: {} (using the default)", issue.field, issue.message);
+            }
+            for issue in &report.warnings {
+                warn!("Config {}: {}", issue.field, issue.message);
+            }
             cfg
         }
-        Err(e) => {
-            info!("Using default configuration: {}", e);
+        Err(ConfigError::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            info!("No configuration found, writing defaults");
             let default_config = AppConfig::default();
             if let Err(save_err) = default_config.save(&config_path).await {
                 info!("Could not save default config: {}", save_err);
             }
             default_config
         }
+        Err(e) => {
+            // Leave the file alone so it can be fixed by hand
+            warn!("Could not load {:?}, using defaults for this run: {}", config_path, e);
+            AppConfig::default()
+        }
     };
     
     info!("Initializing core systems...");
     
-    let db = match Database::connect().await {
-        Ok(db) => {
-            info!("Database connected");
-            if let Err(e) = db.run_migrations().await {
-                warn!("Migration warning: {}", e);
-            }
-            Some(Arc::new(db))
+    // Connects in the background; accounts and friends come online once it succeeds
+    let database = match PostgresConnector::from_env() {
+        Ok(connector) => {
+            let supervisor = DatabaseSupervisor::new();
+            let lockout = LockoutPolicy {
+                threshold: config.auth.max_failed_attempts,
+                window: chrono::Duration::seconds(config.auth.lockout_window_secs as i64),
+                max_backoff: chrono::Duration::seconds(config.auth.max_lockout_secs as i64),
+                ..LockoutPolicy::default()
+            };
+            let mailer = config.email.smtp_server.as_ref().map(|server| {
+                Arc::new(SmtpMailer::new(server.clone(), config.email.from_address.clone())) as Arc<dyn Mailer>
+            });
+            // Hears status changes made by other launchers on the same database
+            let presence = PresenceHub::new();
+            presence.spawn_listener(connector.url().to_string());
+            let services = ServiceOptions {
+                lockout,
+                mailer,
+                require_verified_email: config.auth.require_verified_email,
+                friend_request_ttl: Some(chrono::Duration::days(config.friends.request_expiry_days as i64)),
+                presence,
+            };
+            supervisor.spawn(connector, SupervisorConfig { services, ..SupervisorConfig::default() });
+            
+            // Expired friend requests are already hidden; this clears them out
+            let services = supervisor.services();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+                loop {
+                    interval.tick().await;
+                    if let Some(services) = services.read().await.as_ref() {
+                        if let Err(e) = services.friends.purge_expired_requests().await {
+                            warn!("Could not purge expired friend requests: {}", e);
+                        }
+                    }
+                }
+            });
+            Some(supervisor)
         }
         Err(e) => {
-            warn!("Database not available (offline mode): {}", e);
+            warn!("Database not configured (offline mode): {}", e);
             None
         }
     };
     
-    let (user_service, friends_service) = if let Some(ref db) = db {
-        let user_svc = UserService::new(db.pool().clone());
-        let friends_svc = FriendsService::new(db.pool().clone());
-        info!("User and Friends services initialized");
-        (Some(user_svc), Some(friends_svc))
-    } else {
-        (None, None)
-    };
-    
-    let launcher = yellow_tale::core::launcher::LauncherService::new();
+    let crash_history = yellow_tale::core::launcher::safe_mode::CrashTracker::load(data_dir.join("crash_history.json")).await;
+    let launcher = yellow_tale::core::launcher::LauncherService::new()
+        .with_crash_tracker(crash_history)
+        .with_shutdown_timeout(std::time::Duration::from_secs(config.launcher.shutdown_timeout_secs));
     info!("Launcher service initialized");
     
     let profiles_dir = data_dir.join("profiles");
@@ -77,9 +115,9 @@ async fn main() -> anyhow::Result<()> {
     
     let cache_dir = data_dir.join("cache");
     let mut cache_manager = yellow_tale::core::cache::CacheManager::new(
-        cache_dir,
+        cache_dir.clone(),
         config.cache.max_size_bytes,
-    );
+    ).with_verify_integrity(config.cache.verify_integrity);
     if let Err(e) = cache_manager.init().await {
         info!("Could not initialize cache: {}", e);
     }
@@ -87,10 +125,20 @@ async fn main() -> anyhow::Result<()> {
     info!("Cache manager initialized ({} entries, {} bytes)", 
           cache_stats.entry_count, cache_stats.total_size);
     
-    let session_orchestrator = yellow_tale::core::sessions::SessionOrchestrator::new();
+    let session_orchestrator = yellow_tale::core::sessions::SessionOrchestrator::with_config(
+        yellow_tale::core::sessions::SessionConfig {
+            relay_servers: config.session.relay_servers.clone(),
+            stun_servers: config.session.stun_servers.clone(),
+            encrypt_payloads: config.session.encrypt_payloads,
+            ..Default::default()
+        },
+    );
     info!("Session orchestrator initialized");
     
-    let mut diagnostics = yellow_tale::core::diagnostics::DiagnosticsCollector::new();
+    let mut diagnostics = yellow_tale::core::diagnostics::DiagnosticsCollector::new().with_history(
+        std::time::Duration::from_secs(config.diagnostics.sample_interval_secs),
+        std::time::Duration::from_secs(config.diagnostics.history_minutes * 60),
+    );
     let system_info = diagnostics.get_system_info();
     info!("Diagnostics collector initialized");
     info!("System: {} {} | {} cores | {} MB RAM", 
@@ -103,7 +151,132 @@ async fn main() -> anyhow::Result<()> {
         cache_manager,
         session_orchestrator,
         diagnostics,
-    ).with_services(user_service, friends_service);
+    );
+    ipc_server = ipc_server.with_metrics_sampling();
+    ipc_server = ipc_server.with_command_timeout(std::time::Duration::from_secs(config.ipc.command_timeout_secs));
+    ipc_server = ipc_server.with_health_check(std::sync::Arc::new(
+        yellow_tale::core::health::WritableDir::new("cache", cache_dir.clone()),
+    ));
+    let database_configured = database.is_some();
+    if let Some(database) = database {
+        ipc_server = ipc_server.with_database(database);
+    }
+    
+    if config.sync.enabled {
+        match yellow_tale::core::settings_sync::SettingsSync::load(&data_dir).await {
+            Ok(mut sync) => {
+                // What another device changed goes into config.toml before
+                // this device's settings are recorded over it
+                match sync.apply_pulled_to(&config_path).await {
+                    Ok(applied) if !applied.is_empty() => {
+                        info!("Applied synced settings: {:?}", applied);
+                        if let Ok((reloaded, _)) = AppConfig::load(&config_path).await {
+                            config.performance = reloaded.performance;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Could not apply synced settings: {}", e),
+                }
+                if let Err(e) = sync.record_config(&config).await {
+                    warn!("Could not record performance settings for sync: {}", e);
+                }
+                info!("Settings sync initialized (device {})", sync.device_id());
+                ipc_server = ipc_server.with_settings_sync(sync, config.sync.server_url.clone());
+            }
+            Err(e) => warn!("Settings sync unavailable: {}", e),
+        }
+    }
+    
+    let mod_activator = yellow_tale::core::mods::activator::ProfileActivator::new(
+        data_dir.join("mods"),
+        Box::new(yellow_tale::core::mods::activator::HttpModDownloader::new(cache_dir.join("mods"))),
+    );
+    ipc_server = ipc_server.with_mod_activator(mod_activator);
+    
+    let mod_scanner = yellow_tale::core::mods::scanner::ModScanner::new(data_dir.join("mods"))
+        .with_cache_file(cache_dir.join(yellow_tale::core::mods::scanner::SCAN_CACHE_FILE))
+        .await;
+    ipc_server = ipc_server.with_mod_scanner(mod_scanner);
+    match yellow_tale::core::mods::profile_sync::ProfileSync::load(&data_dir).await {
+        Ok(sync) => ipc_server = ipc_server.with_profile_sync(sync),
+        Err(e) => warn!("Mod profile sync unavailable: {}", e),
+    }
+    ipc_server = ipc_server.with_mod_manager(yellow_tale::core::mods::manager::ModManager::new(data_dir.join("mods")));
+    
+    let java = yellow_tale::core::java::JavaManager::load(
+        &data_dir,
+        Box::new(yellow_tale::core::java::AdoptiumSource::new()),
+    ).await;
+    ipc_server = ipc_server.with_java(java);
+    
+    // Cached gates until the UI refreshes them with the user's token
+    let feature_gates = yellow_tale_core::FeatureManager::new().with_cache(
+        data_dir.join("feature_gates.json"),
+        chrono::Duration::seconds(yellow_tale_core::features::DEFAULT_GATES_TTL_SECS),
+    );
+    ipc_server = ipc_server.with_feature_gates(feature_gates, config.sync.server_url.clone());
+    
+    let updates = yellow_tale::core::updates::UpdateManager::load(
+        &data_dir,
+        yellow_tale::VERSION,
+        config.updates.channel,
+        Box::new(yellow_tale::core::client::ApiClient::new(&config.sync.server_url)),
+    ).await;
+    ipc_server = ipc_server.with_updates(updates);
+    ipc_server = ipc_server.with_api_url(config.sync.server_url.clone());
+    
+    let hosting = yellow_tale::core::hosting::WorldHostService::new(
+        data_dir.join("worlds"),
+        yellow_tale::core::hosting::HostServiceConfig::bundled(),
+    );
+    ipc_server = ipc_server.with_hosting(hosting);
+    ipc_server = ipc_server.with_config_path(config_path.clone());
+    match yellow_tale::core::config::watcher::ConfigWatcher::spawn(config_path.clone(), config.clone()) {
+        Ok(watcher) => ipc_server = ipc_server.with_config_watcher(watcher),
+        Err(e) => warn!("Config changes will need a restart: {}", e),
+    }
+    ipc_server = ipc_server.with_preload(yellow_tale::core::preload::PreloadManager::new(
+        &cache_dir,
+        Box::new(yellow_tale::core::preload::HttpManifestSource::new()),
+    ));
+    ipc_server = ipc_server.with_prefetch(yellow_tale::core::preload::AssetPrefetcher::new(&cache_dir, &config.prefetch));
+    ipc_server = ipc_server.with_queue(std::sync::Arc::new(yellow_tale::core::pond::QueueClient::new()));
+    
+    let ping_monitor = std::sync::Arc::new(
+        yellow_tale::core::netdiag::PingMonitor::load(&data_dir, &config.netdiag).await,
+    );
+    ping_monitor.clone().spawn_refresh();
+    ipc_server = ipc_server.with_ping_monitor(ping_monitor);
+    
+    let snapshots = yellow_tale::core::snapshots::SnapshotManager::new(data_dir.join("snapshots"), &config.snapshots)
+        .with_target("worlds", data_dir.join("worlds"))
+        .and_then(|m| m.with_target("profiles", data_dir.join("profiles")))
+        .and_then(|m| m.with_target("mod_list", data_dir.join("mods").join("index.toml")));
+    match snapshots {
+        Ok(snapshots) => {
+            if config.snapshots.scheduled {
+                info!("Scheduled snapshots every {} minutes", config.snapshots.interval_minutes);
+            }
+            let snapshots = std::sync::Arc::new(snapshots);
+            let migrating = snapshots.clone();
+            tokio::spawn(async move {
+                let migrated = migrating.migrate_archives().await;
+                if migrated > 0 {
+                    info!("Moved {} snapshot archive(s) into the object store", migrated);
+                }
+            });
+            ipc_server = ipc_server.with_snapshots(snapshots, &config.snapshots);
+        }
+        Err(e) => warn!("Snapshots unavailable: {}", e),
+    }
+    
+    match yellow_tale::core::integrity::Attestor::load(&data_dir, yellow_tale::VERSION).await {
+        Ok(attestor) => {
+            info!("Attestation ready (install {})", attestor.install_id());
+            ipc_server = ipc_server.with_integrity(attestor);
+        }
+        Err(e) => warn!("Attestation unavailable: {}", e),
+    }
     
     info!("Yellow Tale initialized successfully!");
     
@@ -138,14 +311,24 @@ async fn main() -> anyhow::Result<()> {
         }
     }
     
-    if db.is_some() {
-        info!("Database: Connected | Users & Friends: Ready | Relay: Standby");
+    if database_configured {
+        info!("Database: Connecting in background | Users & Friends: Pending | Relay: Standby");
     } else {
         info!("Database: Offline | Users & Friends: Unavailable | Relay: Standby");
     }
     
-    info!("Yellow Tale ready. Awaiting commands...");
-    info!("Note: In production, this would start the IPC listener for Tauri UI");
+    let listener = yellow_tale::core::ipc::transport::IpcListener::bind(
+        yellow_tale::core::ipc::transport::default_endpoint(&data_dir),
+        yellow_tale::core::ipc::transport::default_token_path(&data_dir),
+        std::sync::Arc::new(tokio::sync::Mutex::new(ipc_server)),
+    ).await?;
+    
+    info!("Yellow Tale ready. Awaiting commands on {}", listener.endpoint().display());
+    
+    listener.serve(async {
+        let _ = tokio::signal::ctrl_c().await;
+        info!("Shutting down...");
+    }).await?;
     
     Ok(())
 }